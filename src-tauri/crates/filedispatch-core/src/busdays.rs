@@ -0,0 +1,91 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+
+/// Upper bound on days walked back, so a pathological holiday list can't loop forever.
+const MAX_LOOKBACK_DAYS: u32 = 20_000;
+
+/// Monday–Friday, excluding any listed holidays.
+pub fn is_business_day(date: NaiveDate, holidays: &[NaiveDate]) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !holidays.contains(&date)
+}
+
+/// Earliest local date still "within the last `amount` business days" of `today`.
+///
+/// Today counts as the first business day when it is one, even though it is
+/// only partially elapsed; a file dated on or after the returned day is inside
+/// the window. With `amount == 0` nothing qualifies, so tomorrow is returned.
+pub fn business_days_threshold(today: NaiveDate, amount: u32, holidays: &[NaiveDate]) -> NaiveDate {
+    if amount == 0 {
+        return today.succ_opt().unwrap_or(today);
+    }
+
+    let mut day = today;
+    let mut counted = 0;
+    for _ in 0..MAX_LOOKBACK_DAYS {
+        if is_business_day(day, holidays) {
+            counted += 1;
+            if counted == amount {
+                return day;
+            }
+        }
+        match day.pred_opt() {
+            Some(prev) => day = prev,
+            None => break,
+        }
+    }
+    day
+}
+
+/// Parses ISO (`YYYY-MM-DD`) holiday strings, skipping entries that don't parse.
+pub fn parse_holidays(values: &[String]) -> Vec<NaiveDate> {
+    values
+        .iter()
+        .filter_map(|value| NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn weekends_are_not_business_days() {
+        // 2024-05-11 is a Saturday
+        assert!(!is_business_day(date(2024, 5, 11), &[]));
+        assert!(!is_business_day(date(2024, 5, 12), &[]));
+        assert!(is_business_day(date(2024, 5, 13), &[]));
+    }
+
+    #[test]
+    fn threshold_skips_weekend_span() {
+        // Monday 2024-05-13: Mon, Fri, Thu, Wed, Tue -> Tuesday 2024-05-07
+        assert_eq!(business_days_threshold(date(2024, 5, 13), 5, &[]), date(2024, 5, 7));
+        // Saturday counts nothing for itself: Fri, Thu, Wed, Tue, Mon
+        assert_eq!(business_days_threshold(date(2024, 5, 11), 5, &[]), date(2024, 5, 6));
+        assert_eq!(business_days_threshold(date(2024, 5, 13), 1, &[]), date(2024, 5, 13));
+    }
+
+    #[test]
+    fn threshold_skips_holidays_inside_window() {
+        let holidays = [date(2024, 5, 9)];
+        // Mon 13, Fri 10, (Thu 9 holiday), Wed 8, Tue 7, Mon 6
+        assert_eq!(
+            business_days_threshold(date(2024, 5, 13), 5, &holidays),
+            date(2024, 5, 6)
+        );
+    }
+
+    #[test]
+    fn zero_amount_excludes_today() {
+        assert_eq!(business_days_threshold(date(2024, 5, 13), 0, &[]), date(2024, 5, 14));
+    }
+
+    #[test]
+    fn parses_iso_holidays() {
+        let parsed = parse_holidays(&["2024-12-25".to_string(), "bad".to_string(), " 2025-01-01 ".to_string()]);
+        assert_eq!(parsed, vec![date(2024, 12, 25), date(2025, 1, 1)]);
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::Metadata;
+use std::io::Read;
+use std::path::Path;
+
+/// Suffixes sync clients on Linux give to files that haven't been downloaded
+/// yet (Nextcloud/ownCloud virtual files, iCloud stubs copied from a Mac).
+const PLACEHOLDER_EXTENSIONS: &[&str] = &["nextcloud", "owncloud", "icloud"];
+
+/// Returns true when `path` is an online-only stub whose data still lives in
+/// the cloud, so reading it would make the sync client download it.
+///
+/// Windows checks the Cloud Files recall attributes, macOS the `SF_DATALESS`
+/// flag, and Linux falls back to the placeholder suffixes above.
+pub fn is_placeholder(path: &Path, metadata: &Metadata) -> bool {
+    if metadata.is_dir() {
+        return false;
+    }
+    has_placeholder_attributes(metadata) || has_placeholder_extension(path)
+}
+
+fn has_placeholder_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            PLACEHOLDER_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn has_placeholder_attributes(metadata: &Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x0000_0400;
+    const FILE_ATTRIBUTE_OFFLINE: u32 = 0x0000_1000;
+    const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x0004_0000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x0040_0000;
+
+    let attributes = metadata.file_attributes();
+    if attributes & (FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | FILE_ATTRIBUTE_RECALL_ON_OPEN) != 0 {
+        return true;
+    }
+    // Older OneDrive builds mark dehydrated files as offline reparse points.
+    attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0 && attributes & FILE_ATTRIBUTE_OFFLINE != 0
+}
+
+#[cfg(target_os = "macos")]
+fn has_placeholder_attributes(metadata: &Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt;
+    // SF_DATALESS from <sys/stat.h>; the same bit getattrlist reports for
+    // iCloud Drive and File Provider items that haven't been materialized.
+    const SF_DATALESS: u32 = 0x4000_0000;
+    metadata.st_flags() & SF_DATALESS != 0
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn has_placeholder_attributes(_metadata: &Metadata) -> bool {
+    false
+}
+
+/// Reads the first byte of `path`, which makes the sync client start
+/// downloading it. May block until the download completes.
+pub fn request_hydration(path: &Path) -> std::io::Result<()> {
+    let mut file = std::fs::File::open(path)?;
+    let mut buf = [0u8; 1];
+    file.read(&mut buf).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn regular_files_are_not_placeholders() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        fs::write(&path, "data").unwrap();
+
+        assert!(!is_placeholder(&path, &fs::metadata(&path).unwrap()));
+        assert!(!is_placeholder(
+            dir.path(),
+            &fs::metadata(dir.path()).unwrap()
+        ));
+    }
+
+    #[test]
+    fn known_placeholder_suffixes_are_detected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.pdf.nextcloud");
+        fs::write(&path, "").unwrap();
+
+        assert!(is_placeholder(&path, &fs::metadata(&path).unwrap()));
+    }
+}
@@ -0,0 +1,2293 @@
+//! Condition evaluation. Everything that only needs `FileInfo` and the rule
+//! is decided here; what needs the file's contents or the system (text and
+//! OCR, archive listings, faces, CSV cells, locks, shell commands, files at
+//! a destination) is asked of a `ContentProvider`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration as StdDuration, Instant};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use lru::LruCache;
+use regex::{Regex, RegexBuilder};
+
+use crate::busdays::{business_days_threshold, parse_holidays};
+use crate::file_info::FileInfo;
+use crate::media::MediaInfo;
+use crate::models::{
+    ActiveApp, ArchiveContainsCondition, ArchiveMatchScope, CollisionCompare, ComparisonOperator,
+    Condition, ConditionGroup, ContentSource, CsvColumnCondition, DateCondition, DateOperator,
+    DuplicateScope, ExistsAtDestinationCondition, FaceCountCondition, FileKind, GroupSizeCondition,
+    ImageDimensionsCondition, ImageShape, IsDuplicateCondition, MatchType, MediaDurationCondition,
+    PageCountCondition, PixelBound, Rule, Settings, SizeCondition, SizeUnit, StringCondition,
+    StringOperator, TimeOperator, TimeUnit,
+};
+use crate::patterns::PatternEngine;
+use crate::screenshot::{self, ScreenshotEvidence, ScreenshotVerdict};
+use crate::timezone::{local_date_in, local_time_in, resolve_timezone};
+
+thread_local! {
+    /// Thread-local cache for compiled regexes to avoid recompilation
+    static REGEX_CACHE: RefCell<LruCache<(String, bool), Regex>> =
+        RefCell::new(LruCache::new(NonZeroUsize::new(100).unwrap()));
+    static REGEX_USAGE: RefCell<RegexUsage> = RefCell::new(RegexUsage::default());
+}
+
+#[derive(Debug)]
+pub struct EvaluationResult {
+    pub matched: bool,
+    pub captures: HashMap<String, String>,
+}
+
+impl EvaluationResult {
+    fn no_match() -> Self {
+        Self {
+            matched: false,
+            captures: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EvaluationOptions {
+    /// Conditions that need the file's contents don't match, and the
+    /// provider isn't asked for them.
+    pub skip_content: bool,
+    /// Failures reading the contents are returned instead of not matching.
+    pub surface_errors: bool,
+    /// The time date and time conditions take as now; the clock's when unset.
+    /// Only previews set it, to see how a rule behaves at another time.
+    pub now: Option<DateTime<Utc>>,
+}
+
+impl EvaluationOptions {
+    pub fn now(&self) -> DateTime<Utc> {
+        self.now.unwrap_or_else(Utc::now)
+    }
+}
+
+/// Extracted contents; `ocr_confidence` is set only when the text came from OCR.
+#[derive(Debug, Clone)]
+pub struct ResolvedContents {
+    pub text: String,
+    pub ocr_confidence: Option<f32>,
+}
+
+impl ResolvedContents {
+    pub fn plain(text: String) -> Self {
+        Self {
+            text,
+            ocr_confidence: None,
+        }
+    }
+
+    pub fn ocr(text: String, confidence: f32) -> Self {
+        Self {
+            text,
+            ocr_confidence: Some(confidence),
+        }
+    }
+}
+
+/// File entry paths of an archive (directories excluded), in archive order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveListing {
+    pub entries: Vec<String>,
+    /// The walk stopped at the entry or byte cap before reaching the end.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prediction {
+    pub category: String,
+    pub score: f32,
+}
+
+/// A file with this file's name in the folder an Exists at destination
+/// condition looked in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub path: PathBuf,
+    /// Same size and the same hash.
+    pub same_content: bool,
+}
+
+/// What conditions need beyond `FileInfo`. The app reads files, runs OCR
+/// and its classifier; other tools answer from whatever they have. Each
+/// method's default is what a file with nothing to offer gets, so a
+/// provider only needs the ones its rules use.
+pub trait ContentProvider {
+    /// The file's text from `source`, or `None` when it has none.
+    fn contents(
+        &mut self,
+        info: &FileInfo,
+        source: &ContentSource,
+    ) -> Result<Option<ResolvedContents>>;
+
+    /// Searches the text without reading all of it, for files too big to
+    /// hold. `None` leaves the condition to `contents`.
+    fn search(
+        &mut self,
+        _info: &FileInfo,
+        _source: &ContentSource,
+        _cond: &StringCondition,
+    ) -> Option<Result<EvaluationResult>> {
+        None
+    }
+
+    /// The classifier's best category for the file, if classifying is on.
+    fn classify(&mut self, _info: &FileInfo) -> Result<Option<Prediction>> {
+        Ok(None)
+    }
+
+    /// Up to `max_entries` entries, or `None` when the file isn't a
+    /// readable archive.
+    fn archive_listing(&mut self, _info: &FileInfo, _max_entries: u32) -> Option<ArchiveListing> {
+        None
+    }
+
+    fn face_count(&mut self, _info: &FileInfo) -> Option<u32> {
+        None
+    }
+
+    /// Number of pages. Only asked for PDFs.
+    fn page_count(&mut self, _info: &FileInfo) -> Option<u32> {
+        None
+    }
+
+    /// Length and codecs from the container's headers. Only asked for video
+    /// and audio files.
+    fn media_info(&mut self, _info: &FileInfo) -> Result<Option<MediaInfo>> {
+        Ok(None)
+    }
+
+    /// The names of the file's Finder tags, or `None` where files can't
+    /// carry them.
+    fn tags(&mut self, _info: &FileInfo) -> Result<Option<Vec<String>>> {
+        Ok(None)
+    }
+
+    /// Another file in `scope` with the same contents as this one.
+    fn duplicate_of(
+        &mut self,
+        _info: &FileInfo,
+        _scope: DuplicateScope,
+    ) -> Result<Option<PathBuf>> {
+        Ok(None)
+    }
+
+    /// The MIME type sniffed from the file's first bytes.
+    fn mime_type(&mut self, _info: &FileInfo) -> Option<String> {
+        None
+    }
+
+    /// When the image was taken, from its EXIF. Only asked for images.
+    fn exif_date(&mut self, _info: &FileInfo) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    /// Width and height in pixels. Only asked for images.
+    fn image_dimensions(&mut self, _info: &FileInfo) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// What the system and the image say about whether it's a screenshot,
+    /// beyond its name. Only asked for images.
+    fn screenshot_evidence(&mut self, _info: &FileInfo) -> Option<ScreenshotEvidence> {
+        None
+    }
+
+    /// The cells of the condition's column, in the rows it looks at.
+    fn csv_column(&mut self, _info: &FileInfo, _cond: &CsvColumnCondition) -> Option<Vec<String>> {
+        None
+    }
+
+    /// The file named like `info` in `folder`, a resolved destination
+    /// pattern. `info` itself never collides.
+    fn collision(&mut self, _folder: &str, _info: &FileInfo) -> Option<Collision> {
+        None
+    }
+
+    fn is_locked(&mut self, _path: &Path) -> bool {
+        false
+    }
+
+    /// Runs a Shell script condition; true when it exits successfully.
+    fn run_shell(&mut self, _command: &str, _path: &Path) -> bool {
+        false
+    }
+}
+
+/// Regex cache lookups on the current thread since the last `take_regex_usage`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RegexUsage {
+    pub hits: u64,
+    pub misses: u64,
+    pub compile: StdDuration,
+}
+
+pub fn take_regex_usage() -> RegexUsage {
+    REGEX_USAGE.with(|usage| std::mem::take(&mut *usage.borrow_mut()))
+}
+
+/// Counts every lookup; `compile` is the time spent building the regex on a miss.
+fn note_regex_lookup(hit: bool, compile: StdDuration) {
+    REGEX_USAGE.with(|usage| {
+        let mut usage = usage.borrow_mut();
+        if hit {
+            usage.hits += 1;
+        } else {
+            usage.misses += 1;
+            usage.compile += compile;
+        }
+    });
+}
+
+pub fn evaluate_conditions(
+    rule: &Rule,
+    info: &FileInfo,
+    settings: &Settings,
+    content: &mut dyn ContentProvider,
+    options: &EvaluationOptions,
+) -> Result<EvaluationResult> {
+    let mut result = evaluate_group(&rule.conditions, info, settings, content, options)?;
+    add_rule_captures(rule, &mut result);
+    Ok(result)
+}
+
+/// Adds what a matched rule tells its actions about itself, like
+/// `{classification}`, to what its conditions captured.
+pub fn add_rule_captures(rule: &Rule, result: &mut EvaluationResult) {
+    if !result.matched {
+        return;
+    }
+    if let Some(code) = &rule.classification {
+        result
+            .captures
+            .insert("classification".to_string(), code.clone());
+    }
+}
+
+pub fn evaluate_group(
+    group: &ConditionGroup,
+    info: &FileInfo,
+    settings: &Settings,
+    content: &mut dyn ContentProvider,
+    options: &EvaluationOptions,
+) -> Result<EvaluationResult> {
+    if options.skip_content
+        && matches!(group.match_type, MatchType::None)
+        && group_has_content_condition(group)
+    {
+        return Ok(EvaluationResult::no_match());
+    }
+
+    match group.match_type {
+        MatchType::All => {
+            let mut captures = HashMap::new();
+            for condition in &group.conditions {
+                // The destination may use what earlier conditions captured.
+                let result = match condition {
+                    Condition::ExistsAtDestination(cond) => {
+                        evaluate_exists_at_destination(cond, info, settings, &captures, content)
+                    }
+                    _ => evaluate_condition(condition, info, settings, content, options)?,
+                };
+                if !result.matched {
+                    return Ok(EvaluationResult::no_match());
+                }
+                captures.extend(result.captures);
+            }
+            Ok(EvaluationResult {
+                matched: true,
+                captures,
+            })
+        }
+        MatchType::Any => {
+            for condition in &group.conditions {
+                let result = evaluate_condition(condition, info, settings, content, options)?;
+                if result.matched {
+                    return Ok(result);
+                }
+            }
+            Ok(EvaluationResult::no_match())
+        }
+        MatchType::None => {
+            for condition in &group.conditions {
+                let result = evaluate_condition(condition, info, settings, content, options)?;
+                if result.matched {
+                    return Ok(EvaluationResult::no_match());
+                }
+            }
+            Ok(EvaluationResult {
+                matched: true,
+                captures: HashMap::new(),
+            })
+        }
+    }
+}
+
+pub fn evaluate_condition(
+    condition: &Condition,
+    info: &FileInfo,
+    settings: &Settings,
+    content: &mut dyn ContentProvider,
+    options: &EvaluationOptions,
+) -> Result<EvaluationResult> {
+    match condition {
+        Condition::Name(cond) => evaluate_string(&info.name, cond),
+        Condition::Extension(cond) => evaluate_string(&info.extension, cond),
+        Condition::FullName(cond) => evaluate_string(&info.full_name, cond),
+        Condition::Path(cond) => evaluate_string(&slashed_path(&info.path), cond),
+        Condition::ParentFolder(cond) => {
+            evaluate_string(info.parent.as_deref().unwrap_or_default(), cond)
+        }
+        Condition::RelativePath(cond) => evaluate_string(&relative_path(info), cond),
+        Condition::Contents(cond) => {
+            if options.skip_content {
+                return Ok(EvaluationResult::no_match());
+            }
+            let string_cond = StringCondition {
+                operator: cond.operator.clone(),
+                value: cond.value.clone(),
+                case_sensitive: cond.case_sensitive,
+            };
+            if let Some(found) = content.search(info, &cond.source, &string_cond) {
+                return match found {
+                    Ok(result) => Ok(result),
+                    Err(err) if options.surface_errors => Err(err),
+                    Err(_) => Ok(EvaluationResult::no_match()),
+                };
+            }
+            let resolved = content.contents(info, &cond.source);
+            let contents = if options.surface_errors {
+                resolved?
+            } else {
+                resolved.unwrap_or(None)
+            };
+            let Some(contents) = contents.filter(|c| !c.text.is_empty()) else {
+                return Ok(EvaluationResult::no_match());
+            };
+            if !ocr_confidence_passes(contents.ocr_confidence, cond.min_ocr_confidence) {
+                return Ok(EvaluationResult::no_match());
+            }
+            let mut result = evaluate_string(&contents.text, &string_cond)?;
+            if let (true, Some(confidence)) = (result.matched, contents.ocr_confidence) {
+                result
+                    .captures
+                    .insert("ocr_confidence".to_string(), format!("{:.2}", confidence));
+            }
+            Ok(result)
+        }
+        Condition::Size(cond) => Ok(EvaluationResult {
+            matched: evaluate_size(info.size, cond),
+            captures: HashMap::new(),
+        }),
+        Condition::DateCreated(cond) => Ok(EvaluationResult {
+            matched: evaluate_date_condition(info.created, cond, settings, options.now())?,
+            captures: HashMap::new(),
+        }),
+        Condition::DateModified(cond) => Ok(EvaluationResult {
+            matched: evaluate_date_condition(info.modified, cond, settings, options.now())?,
+            captures: HashMap::new(),
+        }),
+        Condition::DateAdded(cond) => Ok(EvaluationResult {
+            matched: evaluate_date_condition(info.added, cond, settings, options.now())?,
+            captures: HashMap::new(),
+        }),
+        Condition::ExifDate(cond) => {
+            if options.skip_content || info.kind != FileKind::Image {
+                return Ok(EvaluationResult::no_match());
+            }
+            let matched = match content.exif_date(info) {
+                Some(taken) => evaluate_date_condition(taken, cond, settings, options.now())?,
+                None => false,
+            };
+            Ok(EvaluationResult {
+                matched,
+                captures: HashMap::new(),
+            })
+        }
+        Condition::DateLastMatched(cond) => Ok(EvaluationResult {
+            // Use the last_matched field from FileInfo if available
+            // Files that have never been matched will return None, and we'll treat them
+            // as "matched a very long time ago" (so they match "not in the last X")
+            matched: match info.last_matched {
+                Some(dt) => evaluate_date_condition(dt, cond, settings, options.now())?,
+                // If never matched, only match conditions looking for old/never-matched files
+                None => matches!(
+                    &cond.operator,
+                    DateOperator::NotInTheLast { .. }
+                        | DateOperator::NotInTheLastBusinessDays { .. }
+                ),
+            },
+            captures: HashMap::new(),
+        }),
+        Condition::CurrentTime(cond) => Ok(EvaluationResult {
+            matched: evaluate_time_at(
+                &cond.operator,
+                condition_zone(cond.timezone.as_deref(), settings)?,
+                options.now(),
+            ),
+            captures: HashMap::new(),
+        }),
+        Condition::Kind(cond) => Ok(EvaluationResult {
+            matched: evaluate_kind(info.kind.clone(), cond.kind.clone(), cond.negate),
+            captures: HashMap::new(),
+        }),
+        Condition::ShellScript(cond) => Ok(EvaluationResult {
+            matched: content.run_shell(&cond.command, &info.path),
+            captures: HashMap::new(),
+        }),
+        Condition::IsLocked(cond) => Ok(EvaluationResult {
+            matched: content.is_locked(&info.path) != cond.negate,
+            captures: HashMap::new(),
+        }),
+        Condition::ClassifiedAs(cond) => {
+            if options.skip_content {
+                return Ok(EvaluationResult::no_match());
+            }
+            let prediction = match content.classify(info) {
+                Ok(prediction) => prediction,
+                Err(err) if options.surface_errors => return Err(err),
+                Err(_) => None,
+            };
+            Ok(evaluate_classification(
+                prediction,
+                &cond.category,
+                cond.min_score,
+            ))
+        }
+        Condition::ArchiveContains(cond) => {
+            if options.skip_content {
+                return Ok(EvaluationResult::no_match());
+            }
+            match content.archive_listing(info, cond.max_entries) {
+                Some(listing) => evaluate_archive_contains(&listing, cond),
+                None => Ok(EvaluationResult::no_match()),
+            }
+        }
+        Condition::FaceCount(cond) => {
+            if options.skip_content {
+                return Ok(EvaluationResult::no_match());
+            }
+            match content.face_count(info) {
+                Some(count) => Ok(evaluate_face_count(count, cond)),
+                None => Ok(EvaluationResult::no_match()),
+            }
+        }
+        Condition::ImageDimensions(cond) => {
+            if options.skip_content || info.kind != FileKind::Image {
+                return Ok(EvaluationResult::no_match());
+            }
+            match content.image_dimensions(info) {
+                Some((width, height)) => Ok(evaluate_image_dimensions(width, height, cond)),
+                None => Ok(EvaluationResult::no_match()),
+            }
+        }
+        Condition::PageCount(cond) => {
+            if options.skip_content || !info.extension.eq_ignore_ascii_case("pdf") {
+                return Ok(EvaluationResult::no_match());
+            }
+            match content.page_count(info) {
+                Some(count) => Ok(evaluate_page_count(count, cond)),
+                None => Ok(EvaluationResult::no_match()),
+            }
+        }
+        Condition::MediaDuration(cond) => Ok(match media_info(info, content, options)? {
+            Some(media) => evaluate_media_duration(&media, cond),
+            None => EvaluationResult::no_match(),
+        }),
+        Condition::MediaCodec(cond) => match media_info(info, content, options)? {
+            Some(media) if !media.codecs.is_empty() => {
+                evaluate_each(&media.codecs, cond, "media_codec")
+            }
+            _ => Ok(EvaluationResult::no_match()),
+        },
+        Condition::IsScreenshot(cond) => {
+            if options.skip_content {
+                return Ok(EvaluationResult::no_match());
+            }
+            let is_screenshot = info.kind == FileKind::Image
+                && screenshot_verdict(info, settings, content).is_screenshot();
+            Ok(EvaluationResult {
+                matched: is_screenshot != cond.negate,
+                captures: HashMap::new(),
+            })
+        }
+        Condition::IsDuplicate(cond) => {
+            if options.skip_content || info.is_dir || info.is_placeholder {
+                return Ok(EvaluationResult::no_match());
+            }
+            let original = match content.duplicate_of(info, cond.scope) {
+                Ok(original) => original,
+                Err(err) if options.surface_errors => return Err(err),
+                Err(_) => return Ok(EvaluationResult::no_match()),
+            };
+            Ok(evaluate_is_duplicate(original.as_deref(), cond))
+        }
+        Condition::GroupSize(cond) => Ok(evaluate_group_size(info, cond)),
+        Condition::ActiveApplication(cond) => evaluate_active_application(info, cond),
+        Condition::MimeType(cond) => {
+            if options.skip_content || info.is_dir {
+                return Ok(EvaluationResult::no_match());
+            }
+            match content.mime_type(info) {
+                Some(mime) => evaluate_string(&mime, cond),
+                None => Ok(EvaluationResult::no_match()),
+            }
+        }
+        Condition::Tag(cond) => match content.tags(info) {
+            Ok(Some(tags)) => evaluate_each(&tags, cond, "tag"),
+            Ok(None) => Ok(EvaluationResult::no_match()),
+            Err(err) if options.surface_errors => Err(err),
+            Err(_) => Ok(EvaluationResult::no_match()),
+        },
+        Condition::EventSource(cond) => Ok(EvaluationResult {
+            matched: cond.sources.contains(&info.event_source),
+            captures: HashMap::new(),
+        }),
+        Condition::CsvColumn(cond) => {
+            if options.skip_content {
+                return Ok(EvaluationResult::no_match());
+            }
+            match content.csv_column(info, cond) {
+                Some(cells) => evaluate_csv_column(&cells, cond),
+                None => Ok(EvaluationResult::no_match()),
+            }
+        }
+        Condition::ExistsAtDestination(cond) => Ok(evaluate_exists_at_destination(
+            cond,
+            info,
+            settings,
+            &HashMap::new(),
+            content,
+        )),
+        Condition::Nested(group) => evaluate_group(group, info, settings, content, options),
+    }
+}
+
+/// The media conditions' view of the file: `None` for files that aren't
+/// video or audio, and for ones that can't be read unless errors are
+/// surfaced.
+fn media_info(
+    info: &FileInfo,
+    content: &mut dyn ContentProvider,
+    options: &EvaluationOptions,
+) -> Result<Option<MediaInfo>> {
+    if options.skip_content || !matches!(info.kind, FileKind::Video | FileKind::Audio) {
+        return Ok(None);
+    }
+    match content.media_info(info) {
+        Ok(media) => Ok(media),
+        Err(err) if options.surface_errors => Err(err),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Looks for a file named like this one in the resolved folder. A match
+/// captures `collision_path` and `collision_same_content`; a negated one has
+/// nothing to capture.
+pub fn evaluate_exists_at_destination(
+    cond: &ExistsAtDestinationCondition,
+    info: &FileInfo,
+    settings: &Settings,
+    captures: &HashMap<String, String>,
+    content: &mut dyn ContentProvider,
+) -> EvaluationResult {
+    let patterns = PatternEngine::new();
+    patterns.set_default_timezone(settings.default_timezone.as_deref());
+    let folder = patterns.resolve(&cond.destination_pattern, info, captures);
+    let collision = content.collision(&folder, info);
+
+    let collides = match (&collision, cond.compare) {
+        (None, _) => false,
+        (Some(_), CollisionCompare::NameOnly) => true,
+        (Some(collision), CollisionCompare::NameAndHash) => collision.same_content,
+    };
+    let mut captures = HashMap::new();
+    if let Some(collision) = collision.filter(|_| collides && !cond.negate) {
+        captures.insert(
+            "collision_path".to_string(),
+            collision.path.to_string_lossy().to_string(),
+        );
+        captures.insert(
+            "collision_same_content".to_string(),
+            collision.same_content.to_string(),
+        );
+    }
+    EvaluationResult {
+        matched: collides != cond.negate,
+        captures,
+    }
+}
+
+/// The first cell that satisfies the condition wins; its value is captured
+/// under the column's key alongside any regex captures.
+pub fn evaluate_csv_column(
+    cells: &[String],
+    cond: &CsvColumnCondition,
+) -> Result<EvaluationResult> {
+    for cell in cells {
+        let mut result = evaluate_string(cell.trim(), &cond.condition)?;
+        if result.matched {
+            result
+                .captures
+                .insert(cond.column.capture_key(), cell.trim().to_string());
+            return Ok(result);
+        }
+    }
+    Ok(EvaluationResult::no_match())
+}
+
+pub fn evaluate_archive_contains(
+    listing: &ArchiveListing,
+    cond: &ArchiveContainsCondition,
+) -> Result<EvaluationResult> {
+    let mut first_match: Option<(&str, EvaluationResult)> = None;
+    let mut all_matched = !listing.entries.is_empty();
+    for entry in &listing.entries {
+        let result = evaluate_string(entry, &cond.name_condition)?;
+        if result.matched {
+            if first_match.is_none() {
+                first_match = Some((entry.as_str(), result));
+            }
+            if cond.match_scope == ArchiveMatchScope::AnyEntry {
+                break;
+            }
+        } else {
+            all_matched = false;
+            if cond.match_scope == ArchiveMatchScope::AllEntries {
+                break;
+            }
+        }
+    }
+
+    let matched = match cond.match_scope {
+        ArchiveMatchScope::AnyEntry => first_match.is_some(),
+        // Entries past the cap were never checked.
+        ArchiveMatchScope::AllEntries => all_matched && !listing.truncated,
+    };
+    let Some((entry, result)) = first_match.filter(|_| matched) else {
+        return Ok(EvaluationResult::no_match());
+    };
+    let mut captures = result.captures;
+    captures.insert(
+        "archive_entry_count".to_string(),
+        listing.entries.len().to_string(),
+    );
+    captures.insert("archive_match".to_string(), entry.to_string());
+    Ok(EvaluationResult {
+        matched: true,
+        captures,
+    })
+}
+
+pub fn evaluate_face_count(count: u32, cond: &FaceCountCondition) -> EvaluationResult {
+    let count = u64::from(count);
+    let matched = compare_count(count, &cond.operator, cond.value);
+    let mut captures = HashMap::new();
+    if matched {
+        captures.insert("face_count".to_string(), count.to_string());
+    }
+    EvaluationResult { matched, captures }
+}
+
+pub fn evaluate_page_count(count: u32, cond: &PageCountCondition) -> EvaluationResult {
+    let count = u64::from(count);
+    let matched = compare_count(count, &cond.operator, cond.value);
+    let mut captures = HashMap::new();
+    if matched {
+        captures.insert("page_count".to_string(), count.to_string());
+    }
+    EvaluationResult { matched, captures }
+}
+
+/// Captures `media_duration`, in whole seconds, when it matches.
+pub fn evaluate_media_duration(
+    media: &MediaInfo,
+    cond: &MediaDurationCondition,
+) -> EvaluationResult {
+    let Some(duration) = media.duration else {
+        return EvaluationResult::no_match();
+    };
+    let unit = to_duration(1, &cond.unit).num_seconds() as u64;
+    let operator = match cond.operator {
+        ComparisonOperator::Between { min, max } => ComparisonOperator::Between {
+            min: min.saturating_mul(unit),
+            max: max.saturating_mul(unit),
+        },
+        ref operator => operator.clone(),
+    };
+    let seconds = duration.as_secs();
+    let matched = compare_count(seconds, &operator, cond.value.saturating_mul(unit));
+    let mut captures = HashMap::new();
+    if matched {
+        captures.insert("media_duration".to_string(), seconds.to_string());
+    }
+    EvaluationResult { matched, captures }
+}
+
+/// Captures `duplicate_of` when the file matches as a copy of `original`.
+pub fn evaluate_is_duplicate(
+    original: Option<&Path>,
+    cond: &IsDuplicateCondition,
+) -> EvaluationResult {
+    let mut captures = HashMap::new();
+    if let (Some(original), false) = (original, cond.negate) {
+        captures.insert(
+            "duplicate_of".to_string(),
+            original.to_string_lossy().to_string(),
+        );
+    }
+    EvaluationResult {
+        matched: original.is_some() != cond.negate,
+        captures,
+    }
+}
+
+/// Matches when any of `values` does, or for a negative operator when every
+/// one does, which an empty list always does. The value that matched is
+/// captured under `key`.
+pub fn evaluate_each(
+    values: &[String],
+    cond: &StringCondition,
+    key: &str,
+) -> Result<EvaluationResult> {
+    let negative = is_negative(&cond.operator);
+    for value in values {
+        let mut result = evaluate_string(value, cond)?;
+        if result.matched != negative {
+            if result.matched {
+                result.captures.insert(key.to_string(), value.clone());
+            }
+            return Ok(result);
+        }
+    }
+    Ok(EvaluationResult {
+        matched: negative,
+        captures: HashMap::new(),
+    })
+}
+
+pub fn evaluate_image_dimensions(
+    width: u32,
+    height: u32,
+    cond: &ImageDimensionsCondition,
+) -> EvaluationResult {
+    let within = |size: u32, bound: &Option<PixelBound>| {
+        bound
+            .as_ref()
+            .is_none_or(|bound| compare_count(u64::from(size), &bound.operator, bound.value))
+    };
+    let shaped = cond.shape.is_none_or(|shape| match shape {
+        ImageShape::Portrait => height > width,
+        ImageShape::Landscape => width > height,
+        ImageShape::Square => width == height,
+    });
+    let matched = within(width, &cond.width) && within(height, &cond.height) && shaped;
+    let mut captures = HashMap::new();
+    if matched {
+        captures.insert("image_width".to_string(), width.to_string());
+        captures.insert("image_height".to_string(), height.to_string());
+    }
+    EvaluationResult { matched, captures }
+}
+
+/// Weighs the image's name with what `content` finds out about it.
+pub fn screenshot_verdict(
+    info: &FileInfo,
+    settings: &Settings,
+    content: &mut dyn ContentProvider,
+) -> ScreenshotVerdict {
+    let evidence = content.screenshot_evidence(info).unwrap_or_default();
+    screenshot::judge(
+        &info.full_name,
+        &settings.screenshot_name_prefixes,
+        &evidence,
+    )
+}
+
+pub fn evaluate_group_size(info: &FileInfo, cond: &GroupSizeCondition) -> EvaluationResult {
+    let size = info
+        .arrival
+        .as_ref()
+        .map_or(1, |arrival| arrival.size as u64);
+    EvaluationResult {
+        matched: compare_count(size, &cond.operator, cond.value),
+        captures: HashMap::new(),
+    }
+}
+
+fn compare_count(count: u64, operator: &ComparisonOperator, value: u64) -> bool {
+    match operator {
+        ComparisonOperator::Equals => count == value,
+        ComparisonOperator::NotEquals => count != value,
+        ComparisonOperator::GreaterThan => count > value,
+        ComparisonOperator::LessThan => count < value,
+        ComparisonOperator::GreaterOrEqual => count >= value,
+        ComparisonOperator::LessOrEqual => count <= value,
+        ComparisonOperator::Between { min, max } => count >= *min && count <= *max,
+    }
+}
+
+/// Matches the app's name, or its identifier when the name doesn't. A
+/// negative operator has to hold for both. An app that isn't known never
+/// matches, whichever the operator.
+pub fn evaluate_active_application(
+    info: &FileInfo,
+    cond: &StringCondition,
+) -> Result<EvaluationResult> {
+    let Some(ActiveApp::Known { name, identifier }) = &info.active_app else {
+        return Ok(EvaluationResult::no_match());
+    };
+    let negative = is_negative(&cond.operator);
+    let by_name = evaluate_string(name, cond)?;
+    let Some(identifier) = identifier else {
+        return Ok(by_name);
+    };
+    if by_name.matched != negative {
+        return Ok(by_name);
+    }
+    evaluate_string(identifier, cond)
+}
+
+/// Operators that match when the string doesn't have what they look for.
+fn is_negative(operator: &StringOperator) -> bool {
+    matches!(
+        operator,
+        StringOperator::IsNot
+            | StringOperator::DoesNotContain
+            | StringOperator::DoesNotMatch
+            | StringOperator::DoesNotMatchGlob
+            | StringOperator::IsNotOneOf
+    )
+}
+
+/// Plain-text extraction carries no confidence, so the gate only applies to
+/// OCR output.
+fn ocr_confidence_passes(confidence: Option<f32>, minimum: Option<f32>) -> bool {
+    match (confidence, minimum) {
+        (Some(confidence), Some(minimum)) => confidence >= minimum,
+        _ => true,
+    }
+}
+
+fn group_has_content_condition(group: &ConditionGroup) -> bool {
+    group.conditions.iter().any(|condition| match condition {
+        Condition::Contents(_)
+        | Condition::ClassifiedAs(_)
+        | Condition::ArchiveContains(_)
+        | Condition::CsvColumn(_)
+        | Condition::FaceCount(_)
+        | Condition::ImageDimensions(_)
+        | Condition::ExifDate(_)
+        | Condition::PageCount(_)
+        | Condition::MediaDuration(_)
+        | Condition::MediaCodec(_)
+        | Condition::MimeType(_)
+        | Condition::IsScreenshot(_)
+        | Condition::IsDuplicate(_) => true,
+        Condition::Nested(nested) => group_has_content_condition(nested),
+        _ => false,
+    })
+}
+
+/// Applies the `ClassifiedAs` gate to a prediction. An empty category accepts
+/// whichever category scored highest.
+pub fn prediction_matches(prediction: &Prediction, category: &str, min_score: f32) -> bool {
+    let category = category.trim();
+    (category.is_empty() || prediction.category.eq_ignore_ascii_case(category))
+        && prediction.score >= min_score
+}
+
+fn evaluate_classification(
+    prediction: Option<Prediction>,
+    category: &str,
+    min_score: f32,
+) -> EvaluationResult {
+    match prediction {
+        Some(prediction) if prediction_matches(&prediction, category, min_score) => {
+            let mut captures = HashMap::new();
+            captures.insert(
+                "prediction_score".to_string(),
+                format!("{:.2}", prediction.score),
+            );
+            captures.insert("predicted_category".to_string(), prediction.category);
+            EvaluationResult {
+                matched: true,
+                captures,
+            }
+        }
+        _ => EvaluationResult::no_match(),
+    }
+}
+
+/// The path with `/` between its parts, so one Path condition works on
+/// every platform. Backslashes are only separators on Windows.
+fn slashed_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.into_owned()
+    }
+}
+
+/// The file's path under its watched folder, from the subfolder it was
+/// picked up in.
+fn relative_path(info: &FileInfo) -> String {
+    if info.original_subdir.is_empty() {
+        info.full_name.clone()
+    } else {
+        format!("{}/{}", info.original_subdir, info.full_name)
+    }
+}
+
+pub fn evaluate_string(target: &str, cond: &StringCondition) -> Result<EvaluationResult> {
+    let mut captures = HashMap::new();
+
+    let matched = match cond.operator {
+        // Use eq_ignore_ascii_case to avoid allocations for equality checks
+        StringOperator::Is => {
+            if cond.case_sensitive {
+                target == cond.value
+            } else {
+                target.eq_ignore_ascii_case(&cond.value)
+            }
+        }
+        StringOperator::IsNot => {
+            if cond.case_sensitive {
+                target != cond.value
+            } else {
+                !target.eq_ignore_ascii_case(&cond.value)
+            }
+        }
+        // For contains/starts_with/ends_with, only allocate when case-insensitive
+        StringOperator::Contains => {
+            if cond.case_sensitive {
+                target.contains(&cond.value)
+            } else {
+                target.to_lowercase().contains(&cond.value.to_lowercase())
+            }
+        }
+        StringOperator::DoesNotContain => {
+            if cond.case_sensitive {
+                !target.contains(&cond.value)
+            } else {
+                !target.to_lowercase().contains(&cond.value.to_lowercase())
+            }
+        }
+        StringOperator::StartsWith => {
+            if cond.case_sensitive {
+                target.starts_with(&cond.value)
+            } else {
+                target
+                    .to_lowercase()
+                    .starts_with(&cond.value.to_lowercase())
+            }
+        }
+        StringOperator::EndsWith => {
+            if cond.case_sensitive {
+                target.ends_with(&cond.value)
+            } else {
+                target.to_lowercase().ends_with(&cond.value.to_lowercase())
+            }
+        }
+        // Regex handles case-insensitivity internally, no pre-allocation needed
+        // Use cached compiled regex to avoid recompilation per file
+        StringOperator::Matches | StringOperator::DoesNotMatch => {
+            let regex = get_or_compile_regex(&cond.value, !cond.case_sensitive)?;
+            let matches = regex.captures(target);
+            if let Some(caps) = matches {
+                for (i, cap) in caps.iter().enumerate().skip(1) {
+                    if let Some(value) = cap {
+                        captures.insert(i.to_string(), value.as_str().to_string());
+                    }
+                }
+                cond.operator == StringOperator::Matches
+            } else {
+                cond.operator == StringOperator::DoesNotMatch
+            }
+        }
+        // Globs go through the same cache, keyed by their regex form
+        StringOperator::MatchesGlob | StringOperator::DoesNotMatchGlob => {
+            let pattern = glob_to_regex(&cond.value).map_err(anyhow::Error::msg)?;
+            let regex = get_or_compile_regex(&pattern, !cond.case_sensitive)?;
+            regex.is_match(target) == (cond.operator == StringOperator::MatchesGlob)
+        }
+        StringOperator::IsOneOf | StringOperator::IsNotOneOf => {
+            let found = list_entries(&cond.value).find(|entry| {
+                if cond.case_sensitive {
+                    target == *entry
+                } else {
+                    target.eq_ignore_ascii_case(entry)
+                }
+            });
+            if let (Some(entry), StringOperator::IsOneOf) = (found, &cond.operator) {
+                captures.insert("one_of".to_string(), entry.to_string());
+            }
+            found.is_some() == (cond.operator == StringOperator::IsOneOf)
+        }
+    };
+
+    Ok(EvaluationResult { matched, captures })
+}
+
+/// The entries of an `IsOneOf` list: split at commas and newlines, trimmed,
+/// empty ones left out.
+pub fn list_entries(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+}
+
+/// Translates a glob (see `StringOperator::MatchesGlob`) into a regex
+/// anchored at both ends, with `.` matching newlines too.
+pub fn glob_to_regex(glob: &str) -> Result<String, String> {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::from(r"(?s)\A(?:");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                i += 1;
+                regex.push('[');
+                if matches!(chars.get(i), Some('!' | '^')) {
+                    regex.push('^');
+                    i += 1;
+                }
+                // A `]` right after the opening bracket is a member.
+                if chars.get(i) == Some(&']') {
+                    regex.push_str(r"\]");
+                    i += 1;
+                }
+                loop {
+                    match chars.get(i) {
+                        None => return Err("unclosed [ in glob".to_string()),
+                        Some(']') => break,
+                        Some(&member) => {
+                            if matches!(member, '\\' | '[' | '&' | '~' | '^') {
+                                regex.push('\\');
+                            }
+                            regex.push(member);
+                        }
+                    }
+                    i += 1;
+                }
+                regex.push(']');
+            }
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+        i += 1;
+    }
+    regex.push_str(r")\z");
+    Ok(regex)
+}
+
+/// Get a compiled regex from cache or compile and cache it
+fn get_or_compile_regex(pattern: &str, case_insensitive: bool) -> Result<Regex> {
+    let key = (pattern.to_string(), case_insensitive);
+
+    // Try to get from cache first
+    let cached = REGEX_CACHE.with(|cache| cache.borrow_mut().get(&key).cloned());
+
+    if let Some(regex) = cached {
+        note_regex_lookup(true, StdDuration::ZERO);
+        return Ok(regex);
+    }
+
+    // Compile and cache
+    let started = Instant::now();
+    let mut builder = RegexBuilder::new(pattern);
+    builder.case_insensitive(case_insensitive);
+    let regex = builder.build()?;
+    note_regex_lookup(false, started.elapsed());
+
+    REGEX_CACHE.with(|cache| {
+        cache.borrow_mut().put(key, regex.clone());
+    });
+
+    Ok(regex)
+}
+
+pub fn evaluate_size(size: u64, cond: &SizeCondition) -> bool {
+    let value = match cond.value {
+        Some(v) => to_bytes(v, &cond.unit),
+        None => 0,
+    };
+    match &cond.operator {
+        ComparisonOperator::Equals => size == value,
+        ComparisonOperator::NotEquals => size != value,
+        ComparisonOperator::GreaterThan => size > value,
+        ComparisonOperator::LessThan => size < value,
+        ComparisonOperator::GreaterOrEqual => size >= value,
+        ComparisonOperator::LessOrEqual => size <= value,
+        ComparisonOperator::Between { min, max } => {
+            let min = to_bytes(*min, &cond.unit);
+            let max = to_bytes(*max, &cond.unit);
+            size >= min && size <= max
+        }
+    }
+}
+
+pub fn to_bytes(value: u64, unit: &SizeUnit) -> u64 {
+    match unit {
+        SizeUnit::Bytes => value,
+        SizeUnit::Kilobytes => value * 1024,
+        SizeUnit::Megabytes => value * 1024 * 1024,
+        SizeUnit::Gigabytes => value * 1024 * 1024 * 1024,
+    }
+}
+
+fn evaluate_date_condition(
+    date: DateTime<Utc>,
+    cond: &DateCondition,
+    settings: &Settings,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    let zone = condition_zone(cond.timezone.as_deref(), settings)?;
+    Ok(evaluate_date_at(
+        date,
+        &cond.operator,
+        &parse_holidays(&settings.holidays),
+        zone,
+        now,
+    ))
+}
+
+/// The zone a date or time condition opted into; `None` keeps the original behavior.
+fn condition_zone(name: Option<&str>, settings: &Settings) -> Result<Option<Tz>> {
+    resolve_timezone(name, settings.default_timezone.as_deref()).map_err(anyhow::Error::msg)
+}
+
+/// Without a zone, fixed dates compare the UTC day and business days the local one.
+pub fn evaluate_date(
+    date: DateTime<Utc>,
+    operator: &DateOperator,
+    holidays: &[NaiveDate],
+    zone: Option<Tz>,
+) -> bool {
+    evaluate_date_at(date, operator, holidays, zone, Utc::now())
+}
+
+/// `evaluate_date` with relative operators counting back from `now`.
+pub fn evaluate_date_at(
+    date: DateTime<Utc>,
+    operator: &DateOperator,
+    holidays: &[NaiveDate],
+    zone: Option<Tz>,
+    now: DateTime<Utc>,
+) -> bool {
+    let date_only = match zone {
+        Some(zone) => date.with_timezone(&zone).date_naive(),
+        None => date.date_naive(),
+    };
+
+    match operator {
+        DateOperator::Is { date } => date_only == *date,
+        DateOperator::IsBefore { date } => date_only < *date,
+        DateOperator::IsAfter { date } => date_only > *date,
+        DateOperator::Between { start, end } => date_only >= *start && date_only <= *end,
+        DateOperator::InTheLast { amount, unit } => {
+            let delta = to_duration(*amount, unit);
+            date >= now - delta
+        }
+        DateOperator::NotInTheLast { amount, unit } => {
+            let delta = to_duration(*amount, unit);
+            date < now - delta
+        }
+        DateOperator::InTheLastBusinessDays { amount } => {
+            let threshold = business_days_threshold(local_date_in(now, zone), *amount, holidays);
+            local_date_in(date, zone) >= threshold
+        }
+        DateOperator::NotInTheLastBusinessDays { amount } => {
+            let threshold = business_days_threshold(local_date_in(now, zone), *amount, holidays);
+            local_date_in(date, zone) < threshold
+        }
+    }
+}
+
+/// Human-readable cutoff for relative date operators, shown in previews.
+pub fn describe_date_threshold(
+    operator: &DateOperator,
+    holidays: &[NaiveDate],
+    zone: Option<Tz>,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let today = local_date_in(now, zone);
+    match operator {
+        DateOperator::InTheLastBusinessDays { amount } => Some(format!(
+            "in the last {} business day(s): on or after {}",
+            amount,
+            business_days_threshold(today, *amount, holidays)
+        )),
+        DateOperator::NotInTheLastBusinessDays { amount } => Some(format!(
+            "not in the last {} business day(s): before {}",
+            amount,
+            business_days_threshold(today, *amount, holidays)
+        )),
+        _ => None,
+    }
+}
+
+pub fn evaluate_time(operator: &TimeOperator, zone: Option<Tz>) -> bool {
+    evaluate_time_at(operator, zone, Utc::now())
+}
+
+pub fn evaluate_time_at(operator: &TimeOperator, zone: Option<Tz>, now: DateTime<Utc>) -> bool {
+    evaluate_time_with(local_time_in(now, zone), operator)
+}
+
+fn evaluate_time_with(now: NaiveTime, operator: &TimeOperator) -> bool {
+    match operator {
+        TimeOperator::Is { time } => now == *time,
+        TimeOperator::IsBefore { time } => now < *time,
+        TimeOperator::IsAfter { time } => now > *time,
+        TimeOperator::Between { start, end } => {
+            if start <= end {
+                now >= *start && now <= *end
+            } else {
+                now >= *start || now <= *end
+            }
+        }
+    }
+}
+
+fn to_duration(amount: u32, unit: &TimeUnit) -> Duration {
+    match unit {
+        TimeUnit::Minutes => Duration::minutes(amount as i64),
+        TimeUnit::Hours => Duration::hours(amount as i64),
+        TimeUnit::Days => Duration::days(amount as i64),
+        TimeUnit::Weeks => Duration::weeks(amount as i64),
+        TimeUnit::Months => Duration::days(30 * amount as i64),
+        TimeUnit::Years => Duration::days(365 * amount as i64),
+    }
+}
+
+pub fn evaluate_kind(actual: FileKind, expected: FileKind, negate: bool) -> bool {
+    let matches = actual == expected;
+    if negate {
+        !matches
+    } else {
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timezone::local_time_in;
+    use chrono::TimeZone;
+
+    // ==================== STRING CONDITION TESTS ====================
+
+    #[test]
+    fn string_is_matches_exact() {
+        let cond = StringCondition {
+            operator: StringOperator::Is,
+            value: "report".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("report", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_is_case_insensitive() {
+        let cond = StringCondition {
+            operator: StringOperator::Is,
+            value: "REPORT".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("report", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_is_case_sensitive_fails() {
+        let cond = StringCondition {
+            operator: StringOperator::Is,
+            value: "REPORT".to_string(),
+            case_sensitive: true,
+        };
+        let result = evaluate_string("report", &cond).unwrap();
+        assert!(!result.matched);
+    }
+
+    #[test]
+    fn string_is_not() {
+        let cond = StringCondition {
+            operator: StringOperator::IsNot,
+            value: "other".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("report", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_contains() {
+        let cond = StringCondition {
+            operator: StringOperator::Contains,
+            value: "port".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("report", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_does_not_contain() {
+        let cond = StringCondition {
+            operator: StringOperator::DoesNotContain,
+            value: "xyz".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("report", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_starts_with() {
+        let cond = StringCondition {
+            operator: StringOperator::StartsWith,
+            value: "rep".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("report", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_ends_with() {
+        let cond = StringCondition {
+            operator: StringOperator::EndsWith,
+            value: "ort".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("report", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_matches_regex() {
+        let cond = StringCondition {
+            operator: StringOperator::Matches,
+            value: r"report_\d{4}".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("report_2024", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_matches_regex_captures() {
+        let cond = StringCondition {
+            operator: StringOperator::Matches,
+            value: r"invoice_(\d{4})_(\w+)".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("invoice_2024_sales", &cond).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.captures.get("1"), Some(&"2024".to_string()));
+        assert_eq!(result.captures.get("2"), Some(&"sales".to_string()));
+    }
+
+    #[test]
+    fn string_does_not_match_regex() {
+        let cond = StringCondition {
+            operator: StringOperator::DoesNotMatch,
+            value: r"^\d+$".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("report", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_matches_glob_whole_string() {
+        let glob = |operator: StringOperator, value: &str, case_sensitive: bool| StringCondition {
+            operator,
+            value: value.to_string(),
+            case_sensitive,
+        };
+        let matched =
+            |target: &str, cond: &StringCondition| evaluate_string(target, cond).unwrap().matched;
+        let txt = glob(StringOperator::MatchesGlob, "*.txt", false);
+        assert!(matched("notes.TXT", &txt));
+        assert!(!matched("notes.txt.bak", &txt));
+        let exact = glob(StringOperator::MatchesGlob, "*.txt", true);
+        assert!(!matched("notes.TXT", &exact));
+
+        let report = glob(
+            StringOperator::MatchesGlob,
+            "report-202?-[!a-c]*.csv",
+            false,
+        );
+        assert!(matched("report-2024-q1.csv", &report));
+        assert!(!matched("report-2024-a1.csv", &report));
+        assert!(!matched("report-20245-q1.csv", &report));
+
+        let brackets = glob(StringOperator::MatchesGlob, "[]*?]x(1).+", true);
+        assert!(matched("?x(1).+", &brackets));
+        assert!(!matched("ax(1).+", &brackets));
+
+        let not_backup = glob(StringOperator::DoesNotMatchGlob, "*.backup.*", false);
+        assert!(matched("db.sqlite", &not_backup));
+        assert!(!matched("db.backup.sqlite", &not_backup));
+
+        assert!(glob_to_regex("[abc").is_err());
+    }
+
+    #[test]
+    fn string_is_one_of_list() {
+        let cond = StringCondition {
+            operator: StringOperator::IsOneOf,
+            value: "jpg, jpeg,\n png,,gif ,webp\n".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("PNG", &cond).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.captures["one_of"], "png");
+        assert!(!evaluate_string("pn", &cond).unwrap().matched);
+        assert!(!evaluate_string("", &cond).unwrap().matched);
+
+        let cond = StringCondition {
+            operator: StringOperator::IsNotOneOf,
+            case_sensitive: true,
+            ..cond
+        };
+        assert!(!evaluate_string("gif", &cond).unwrap().matched);
+        let result = evaluate_string("GIF", &cond).unwrap();
+        assert!(result.matched);
+        assert!(result.captures.is_empty());
+    }
+
+    // ==================== SIZE CONDITION TESTS ====================
+
+    #[test]
+    fn size_equals() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::Equals,
+            value: Some(100),
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(100, &cond));
+        assert!(!evaluate_size(99, &cond));
+    }
+
+    #[test]
+    fn size_not_equals() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::NotEquals,
+            value: Some(100),
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(99, &cond));
+        assert!(!evaluate_size(100, &cond));
+    }
+
+    #[test]
+    fn size_greater_than() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::GreaterThan,
+            value: Some(100),
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(101, &cond));
+        assert!(!evaluate_size(100, &cond));
+    }
+
+    #[test]
+    fn size_less_than() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::LessThan,
+            value: Some(100),
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(99, &cond));
+        assert!(!evaluate_size(100, &cond));
+    }
+
+    #[test]
+    fn size_greater_or_equal() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::GreaterOrEqual,
+            value: Some(100),
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(100, &cond));
+        assert!(evaluate_size(101, &cond));
+        assert!(!evaluate_size(99, &cond));
+    }
+
+    #[test]
+    fn size_less_or_equal() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::LessOrEqual,
+            value: Some(100),
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(100, &cond));
+        assert!(evaluate_size(99, &cond));
+        assert!(!evaluate_size(101, &cond));
+    }
+
+    #[test]
+    fn size_between() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::Between { min: 50, max: 150 },
+            value: None,
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(50, &cond));
+        assert!(evaluate_size(100, &cond));
+        assert!(evaluate_size(150, &cond));
+        assert!(!evaluate_size(49, &cond));
+        assert!(!evaluate_size(151, &cond));
+    }
+
+    #[test]
+    fn size_kilobytes_conversion() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::Equals,
+            value: Some(1),
+            unit: SizeUnit::Kilobytes,
+        };
+        assert!(evaluate_size(1024, &cond));
+    }
+
+    #[test]
+    fn size_megabytes_conversion() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::GreaterThan,
+            value: Some(1),
+            unit: SizeUnit::Megabytes,
+        };
+        assert!(evaluate_size(1024 * 1024 + 1, &cond));
+        assert!(!evaluate_size(1024 * 1024, &cond));
+    }
+
+    #[test]
+    fn size_gigabytes_conversion() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::LessThan,
+            value: Some(1),
+            unit: SizeUnit::Gigabytes,
+        };
+        assert!(evaluate_size(1024 * 1024 * 1024 - 1, &cond));
+    }
+
+    // ==================== DATE CONDITION TESTS ====================
+
+    #[test]
+    fn date_is() {
+        let today = Utc::now();
+        let operator = DateOperator::Is {
+            date: today.date_naive(),
+        };
+        assert!(evaluate_date(today, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_is_before() {
+        let past = Utc::now() - Duration::days(10);
+        let operator = DateOperator::IsBefore {
+            date: Utc::now().date_naive(),
+        };
+        assert!(evaluate_date(past, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_is_after() {
+        let future = Utc::now();
+        let operator = DateOperator::IsAfter {
+            date: (Utc::now() - Duration::days(10)).date_naive(),
+        };
+        assert!(evaluate_date(future, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_between() {
+        let now = Utc::now();
+        let operator = DateOperator::Between {
+            start: (now - Duration::days(5)).date_naive(),
+            end: (now + Duration::days(5)).date_naive(),
+        };
+        assert!(evaluate_date(now, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_in_the_last_days() {
+        let recent = Utc::now() - Duration::hours(12);
+        let operator = DateOperator::InTheLast {
+            amount: 1,
+            unit: TimeUnit::Days,
+        };
+        assert!(evaluate_date(recent, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_in_the_last_hours() {
+        let recent = Utc::now() - Duration::minutes(30);
+        let operator = DateOperator::InTheLast {
+            amount: 1,
+            unit: TimeUnit::Hours,
+        };
+        assert!(evaluate_date(recent, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_not_in_the_last() {
+        let old = Utc::now() - Duration::days(10);
+        let operator = DateOperator::NotInTheLast {
+            amount: 5,
+            unit: TimeUnit::Days,
+        };
+        assert!(evaluate_date(old, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_in_the_last_weeks() {
+        let recent = Utc::now() - Duration::days(3);
+        let operator = DateOperator::InTheLast {
+            amount: 1,
+            unit: TimeUnit::Weeks,
+        };
+        assert!(evaluate_date(recent, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_in_the_last_months() {
+        let recent = Utc::now() - Duration::days(15);
+        let operator = DateOperator::InTheLast {
+            amount: 1,
+            unit: TimeUnit::Months,
+        };
+        assert!(evaluate_date(recent, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_in_the_last_years() {
+        let recent = Utc::now() - Duration::days(180);
+        let operator = DateOperator::InTheLast {
+            amount: 1,
+            unit: TimeUnit::Years,
+        };
+        assert!(evaluate_date(recent, &operator, &[], None));
+    }
+
+    fn local_noon(date: chrono::NaiveDate) -> chrono::DateTime<Utc> {
+        date.and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(chrono::Local)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn business_days_boundary_day_is_inside_window() {
+        let today = chrono::Local::now().date_naive();
+        let threshold = crate::busdays::business_days_threshold(today, 5, &[]);
+        let inside = DateOperator::InTheLastBusinessDays { amount: 5 };
+        let outside = DateOperator::NotInTheLastBusinessDays { amount: 5 };
+
+        assert!(evaluate_date(local_noon(threshold), &inside, &[], None));
+        assert!(!evaluate_date(local_noon(threshold), &outside, &[], None));
+
+        let before = threshold.pred_opt().unwrap();
+        assert!(!evaluate_date(local_noon(before), &inside, &[], None));
+        assert!(evaluate_date(local_noon(before), &outside, &[], None));
+    }
+
+    #[test]
+    fn business_days_holiday_extends_window() {
+        let today = chrono::Local::now().date_naive();
+        let threshold = crate::busdays::business_days_threshold(today, 3, &[]);
+        let before = threshold.pred_opt().unwrap();
+        let operator = DateOperator::InTheLastBusinessDays { amount: 3 };
+        assert!(!evaluate_date(local_noon(before), &operator, &[], None));
+
+        // Declaring every day of the window a holiday pushes the cutoff further back.
+        let holidays: Vec<chrono::NaiveDate> =
+            threshold.iter_days().take_while(|d| *d <= today).collect();
+        assert!(evaluate_date(
+            local_noon(before),
+            &operator,
+            &holidays,
+            None
+        ));
+    }
+
+    #[test]
+    fn date_operator_without_business_variants_still_deserializes() {
+        let legacy = r#"{"type":"inTheLast","amount":5,"unit":"days"}"#;
+        let parsed: DateOperator = serde_json::from_str(legacy).unwrap();
+        assert!(matches!(parsed, DateOperator::InTheLast { amount: 5, .. }));
+        let business = r#"{"type":"notInTheLastBusinessDays","amount":5}"#;
+        let parsed: DateOperator = serde_json::from_str(business).unwrap();
+        assert!(matches!(
+            parsed,
+            DateOperator::NotInTheLastBusinessDays { amount: 5 }
+        ));
+    }
+
+    // ==================== TIME CONDITION TESTS ====================
+
+    #[test]
+    fn time_is() {
+        let now = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+        let operator = TimeOperator::Is {
+            time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+        };
+        assert!(evaluate_time_with(now, &operator));
+    }
+
+    #[test]
+    fn time_between_normal_range() {
+        let now = NaiveTime::from_hms_opt(14, 0, 0).unwrap();
+        let operator = TimeOperator::Between {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        };
+        assert!(evaluate_time_with(now, &operator));
+    }
+
+    #[test]
+    fn time_between_outside_range() {
+        let now = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+        let operator = TimeOperator::Between {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        };
+        assert!(!evaluate_time_with(now, &operator));
+    }
+
+    // ==================== TIME ZONE TESTS ====================
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn date_is_compares_the_calendar_day_of_the_zone() {
+        // Uploaded at 02:00 UTC on June 2nd, which is still June 1st in New York.
+        let upload = utc(2024, 6, 2, 2, 0);
+        let june_first = DateOperator::Is {
+            date: chrono::NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+        };
+        assert!(!evaluate_date(upload, &june_first, &[], None));
+        assert!(evaluate_date(
+            upload,
+            &june_first,
+            &[],
+            Some(chrono_tz::America::New_York)
+        ));
+    }
+
+    #[test]
+    fn time_between_spanning_spring_forward_follows_the_zone_clock() {
+        // On 2026-03-08 New York jumps from 02:00 EST straight to 03:00 EDT.
+        let zone = Some(chrono_tz::America::New_York);
+        let operator = TimeOperator::Between {
+            start: NaiveTime::from_hms_opt(1, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(3, 0, 0).unwrap(),
+        };
+        let at = |h, mi| local_time_in(utc(2026, 3, 8, h, mi), zone);
+
+        assert_eq!(at(6, 59), NaiveTime::from_hms_opt(1, 59, 0).unwrap());
+        assert!(evaluate_time_with(at(6, 59), &operator));
+        // One real minute later the clock reads 03:00, the end of the window.
+        assert_eq!(at(7, 0), NaiveTime::from_hms_opt(3, 0, 0).unwrap());
+        assert!(evaluate_time_with(at(7, 0), &operator));
+        assert!(!evaluate_time_with(at(7, 30), &operator));
+    }
+
+    #[test]
+    fn time_between_spanning_fall_back_matches_both_passes() {
+        // On 2026-11-01 New York runs 01:00–02:00 twice, first in EDT then in EST.
+        let zone = Some(chrono_tz::America::New_York);
+        let operator = TimeOperator::Between {
+            start: NaiveTime::from_hms_opt(1, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(1, 59, 0).unwrap(),
+        };
+        let at = |h, mi| local_time_in(utc(2026, 11, 1, h, mi), zone);
+
+        assert!(evaluate_time_with(at(5, 30), &operator));
+        assert!(evaluate_time_with(at(6, 30), &operator));
+        assert!(!evaluate_time_with(at(7, 30), &operator));
+    }
+
+    // ==================== KIND CONDITION TESTS ====================
+
+    #[test]
+    fn kind_matches_file() {
+        assert!(evaluate_kind(FileKind::File, FileKind::File, false));
+    }
+
+    #[test]
+    fn kind_matches_folder() {
+        assert!(evaluate_kind(FileKind::Folder, FileKind::Folder, false));
+    }
+
+    #[test]
+    fn kind_matches_image() {
+        assert!(evaluate_kind(FileKind::Image, FileKind::Image, false));
+    }
+
+    #[test]
+    fn kind_matches_video() {
+        assert!(evaluate_kind(FileKind::Video, FileKind::Video, false));
+    }
+
+    #[test]
+    fn kind_matches_audio() {
+        assert!(evaluate_kind(FileKind::Audio, FileKind::Audio, false));
+    }
+
+    #[test]
+    fn kind_matches_document() {
+        assert!(evaluate_kind(FileKind::Document, FileKind::Document, false));
+    }
+
+    #[test]
+    fn kind_matches_archive() {
+        assert!(evaluate_kind(FileKind::Archive, FileKind::Archive, false));
+    }
+
+    #[test]
+    fn kind_matches_code() {
+        assert!(evaluate_kind(FileKind::Code, FileKind::Code, false));
+    }
+
+    #[test]
+    fn kind_negate_works() {
+        assert!(evaluate_kind(FileKind::File, FileKind::Folder, true));
+        assert!(!evaluate_kind(FileKind::File, FileKind::File, true));
+    }
+
+    #[test]
+    fn kind_mismatch() {
+        assert!(!evaluate_kind(FileKind::File, FileKind::Image, false));
+    }
+
+    // ==================== CLASSIFIER CONDITION TESTS ====================
+
+    #[test]
+    fn classification_exposes_prediction_tokens() {
+        let prediction = Prediction {
+            category: "Invoices".to_string(),
+            score: 0.734,
+        };
+        let result = evaluate_classification(Some(prediction.clone()), "", 0.5);
+        assert!(result.matched);
+        assert_eq!(
+            result.captures.get("predicted_category").unwrap(),
+            "Invoices"
+        );
+        assert_eq!(result.captures.get("prediction_score").unwrap(), "0.73");
+
+        assert!(!evaluate_classification(Some(prediction), "Invoices", 0.8).matched);
+        assert!(!evaluate_classification(None, "", 0.0).matched);
+    }
+
+    #[test]
+    fn current_time_between_handles_wraparound() {
+        let now = NaiveTime::from_hms_opt(1, 30, 0).unwrap();
+        let operator = TimeOperator::Between {
+            start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+        };
+        assert!(evaluate_time_with(now, &operator));
+    }
+
+    #[test]
+    fn current_time_before_after() {
+        let now = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        let before = TimeOperator::IsBefore {
+            time: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+        };
+        let after = TimeOperator::IsAfter {
+            time: NaiveTime::from_hms_opt(8, 30, 0).unwrap(),
+        };
+        assert!(evaluate_time_with(now, &before));
+        assert!(evaluate_time_with(now, &after));
+    }
+
+    // ==================== EDGE CASE TESTS ====================
+
+    // --- Date/Time Boundary Conditions ---
+
+    #[test]
+    fn date_at_exact_midnight() {
+        // Test date condition at exactly midnight (00:00:00)
+        let midnight = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
+        let operator = DateOperator::Is {
+            date: midnight.date_naive(),
+        };
+        assert!(evaluate_date(midnight, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_one_second_before_midnight() {
+        let almost_midnight = Utc::now()
+            .date_naive()
+            .and_hms_opt(23, 59, 59)
+            .unwrap()
+            .and_utc();
+        let operator = DateOperator::Is {
+            date: almost_midnight.date_naive(),
+        };
+        assert!(evaluate_date(almost_midnight, &operator, &[], None));
+    }
+
+    #[test]
+    fn time_at_exact_midnight() {
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+        let operator = TimeOperator::Is {
+            time: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+        };
+        assert!(evaluate_time_with(midnight, &operator));
+    }
+
+    #[test]
+    fn time_between_crossing_midnight_at_boundary() {
+        // Test wraparound at exactly the end boundary
+        let end_time = NaiveTime::from_hms_opt(2, 0, 0).unwrap();
+        let operator = TimeOperator::Between {
+            start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+        };
+        assert!(evaluate_time_with(end_time, &operator));
+    }
+
+    #[test]
+    fn time_between_not_crossing_midnight_at_exact_boundaries() {
+        let start = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        let end = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+        let operator = TimeOperator::Between { start, end };
+        // At start boundary
+        assert!(evaluate_time_with(start, &operator));
+        // At end boundary
+        assert!(evaluate_time_with(end, &operator));
+    }
+
+    #[test]
+    fn date_in_the_last_just_within_boundary() {
+        // Test InTheLast just within the duration
+        let just_within = Utc::now() - Duration::hours(23);
+        let operator = DateOperator::InTheLast {
+            amount: 1,
+            unit: TimeUnit::Days,
+        };
+        // Should match since it's within 1 day
+        assert!(evaluate_date(just_within, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_not_in_the_last_clearly_outside() {
+        // Test NotInTheLast clearly outside the duration
+        let clearly_outside = Utc::now() - Duration::days(2);
+        let operator = DateOperator::NotInTheLast {
+            amount: 1,
+            unit: TimeUnit::Days,
+        };
+        // Should match since it's more than 1 day ago
+        assert!(evaluate_date(clearly_outside, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_in_the_last_just_outside_boundary() {
+        // Test InTheLast just outside the duration
+        let just_outside = Utc::now() - Duration::hours(25);
+        let operator = DateOperator::InTheLast {
+            amount: 1,
+            unit: TimeUnit::Days,
+        };
+        // Should NOT match since it's more than 1 day ago
+        assert!(!evaluate_date(just_outside, &operator, &[], None));
+    }
+
+    // --- Size Boundary Conditions ---
+
+    #[test]
+    fn size_zero_bytes_equals() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::Equals,
+            value: Some(0),
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(0, &cond));
+        assert!(!evaluate_size(1, &cond));
+    }
+
+    #[test]
+    fn size_zero_bytes_greater_than() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::GreaterThan,
+            value: Some(0),
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(1, &cond));
+        assert!(!evaluate_size(0, &cond));
+    }
+
+    #[test]
+    fn size_zero_bytes_less_than() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::LessThan,
+            value: Some(0),
+            unit: SizeUnit::Bytes,
+        };
+        // Nothing is less than 0 for u64
+        assert!(!evaluate_size(0, &cond));
+    }
+
+    #[test]
+    fn size_between_with_equal_min_max() {
+        // Edge case: min equals max
+        let cond = SizeCondition {
+            operator: ComparisonOperator::Between { min: 100, max: 100 },
+            value: None,
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(100, &cond));
+        assert!(!evaluate_size(99, &cond));
+        assert!(!evaluate_size(101, &cond));
+    }
+
+    #[test]
+    fn size_between_with_min_greater_than_max() {
+        // Edge case: min > max (should never match)
+        let cond = SizeCondition {
+            operator: ComparisonOperator::Between { min: 150, max: 50 },
+            value: None,
+            unit: SizeUnit::Bytes,
+        };
+        // This condition can never be satisfied
+        assert!(!evaluate_size(100, &cond));
+        assert!(!evaluate_size(50, &cond));
+        assert!(!evaluate_size(150, &cond));
+    }
+
+    #[test]
+    fn size_max_u64_value() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::Equals,
+            value: Some(u64::MAX),
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(u64::MAX, &cond));
+        assert!(!evaluate_size(u64::MAX - 1, &cond));
+    }
+
+    #[test]
+    fn size_none_value_treated_as_zero() {
+        let cond = SizeCondition {
+            operator: ComparisonOperator::Equals,
+            value: None,
+            unit: SizeUnit::Bytes,
+        };
+        assert!(evaluate_size(0, &cond));
+    }
+
+    // --- Regex Edge Cases ---
+
+    #[test]
+    fn regex_empty_pattern() {
+        let cond = StringCondition {
+            operator: StringOperator::Matches,
+            value: "".to_string(),
+            case_sensitive: false,
+        };
+        // Empty regex matches everything
+        let result = evaluate_string("anything", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn regex_invalid_pattern_returns_error() {
+        let cond = StringCondition {
+            operator: StringOperator::Matches,
+            value: r"[invalid".to_string(), // Unclosed bracket
+            case_sensitive: false,
+        };
+        let result = evaluate_string("test", &cond);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn regex_special_characters_in_input() {
+        let cond = StringCondition {
+            operator: StringOperator::Matches,
+            value: r"file\.txt".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("file.txt", &cond).unwrap();
+        assert!(result.matched);
+        // Should not match without the dot
+        let result2 = evaluate_string("filetxt", &cond).unwrap();
+        assert!(!result2.matched);
+    }
+
+    #[test]
+    fn regex_unicode_characters() {
+        let cond = StringCondition {
+            operator: StringOperator::Matches,
+            value: r"文档_\d+".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("文档_2024", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn regex_cache_hit() {
+        // Test that regex caching works by using the same pattern twice
+        let cond = StringCondition {
+            operator: StringOperator::Matches,
+            value: r"test_\d+".to_string(),
+            case_sensitive: false,
+        };
+        // First call - compiles and caches
+        let result1 = evaluate_string("test_123", &cond).unwrap();
+        assert!(result1.matched);
+        // Second call - should use cached regex
+        let result2 = evaluate_string("test_456", &cond).unwrap();
+        assert!(result2.matched);
+    }
+
+    // --- String Operator Edge Cases ---
+
+    #[test]
+    fn string_empty_value_contains() {
+        let cond = StringCondition {
+            operator: StringOperator::Contains,
+            value: "".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("anything", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_empty_target_contains() {
+        let cond = StringCondition {
+            operator: StringOperator::Contains,
+            value: "x".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("", &cond).unwrap();
+        assert!(!result.matched);
+    }
+
+    #[test]
+    fn string_both_empty() {
+        let cond = StringCondition {
+            operator: StringOperator::Is,
+            value: "".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_unicode_case_sensitivity_ascii_folded() {
+        // Test case insensitivity with ASCII only (eq_ignore_ascii_case)
+        let cond = StringCondition {
+            operator: StringOperator::Is,
+            value: "ÑOÑO".to_string(), // Uppercase ASCII O, uppercase Ñ
+            case_sensitive: false,
+        };
+        // eq_ignore_ascii_case folds ASCII O to o, but Ñ stays unchanged
+        // "ÑOÑO" vs "ÑoÑo" - ASCII O/o matches, Ñ matches Ñ
+        let result = evaluate_string("ÑoÑo", &cond).unwrap();
+        assert!(result.matched);
+    }
+
+    #[test]
+    fn string_unicode_case_sensitivity_non_ascii_not_folded() {
+        // Test that non-ASCII case differences are NOT handled
+        let cond = StringCondition {
+            operator: StringOperator::Is,
+            value: "MÜNCHEN".to_string(), // Uppercase Ü
+            case_sensitive: false,
+        };
+        // eq_ignore_ascii_case does NOT fold ü to Ü
+        let result = evaluate_string("münchen", &cond).unwrap();
+        // This won't match because ü ≠ Ü in ASCII comparison
+        assert!(!result.matched);
+    }
+
+    // --- Date Between Edge Cases ---
+
+    #[test]
+    fn date_between_same_start_and_end() {
+        let today = Utc::now();
+        let operator = DateOperator::Between {
+            start: today.date_naive(),
+            end: today.date_naive(),
+        };
+        assert!(evaluate_date(today, &operator, &[], None));
+    }
+
+    #[test]
+    fn date_between_start_after_end() {
+        let now = Utc::now();
+        let operator = DateOperator::Between {
+            start: (now + Duration::days(5)).date_naive(),
+            end: (now - Duration::days(5)).date_naive(),
+        };
+        // Start > end, should not match anything
+        assert!(!evaluate_date(now, &operator, &[], None));
+    }
+
+    // --- Captures with Multiple Groups ---
+
+    #[test]
+    fn regex_multiple_captures() {
+        let cond = StringCondition {
+            operator: StringOperator::Matches,
+            value: r"(\d{4})-(\d{2})-(\d{2})_([a-z]+)".to_string(),
+            case_sensitive: false,
+        };
+        let result = evaluate_string("2024-01-15_report", &cond).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.captures.get("1"), Some(&"2024".to_string()));
+        assert_eq!(result.captures.get("2"), Some(&"01".to_string()));
+        assert_eq!(result.captures.get("3"), Some(&"15".to_string()));
+        assert_eq!(result.captures.get("4"), Some(&"report".to_string()));
+    }
+
+    #[test]
+    fn regex_optional_capture_group() {
+        let cond = StringCondition {
+            operator: StringOperator::Matches,
+            value: r"file_(\d+)?\.txt".to_string(),
+            case_sensitive: false,
+        };
+        // With number
+        let result1 = evaluate_string("file_123.txt", &cond).unwrap();
+        assert!(result1.matched);
+        assert_eq!(result1.captures.get("1"), Some(&"123".to_string()));
+
+        // Without number - capture group is None
+        let result2 = evaluate_string("file_.txt", &cond).unwrap();
+        assert!(result2.matched);
+        // Optional group that didn't match won't be in captures
+        assert!(!result2.captures.contains_key("1"));
+    }
+}
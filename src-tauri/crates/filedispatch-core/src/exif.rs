@@ -0,0 +1,217 @@
+//! Reads the few EXIF tags rules look at from an EXIF chunk, starting at its
+//! TIFF header as image decoders hand it over. Only the directories holding
+//! those tags are walked; malformed chunks read as having none of them.
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+
+/// IFD0 tags naming the camera that took the image.
+const MAKE: u16 = 0x010f;
+const MODEL: u16 = 0x0110;
+/// IFD0's last-changed time, used when the Exif IFD has no capture time.
+const DATE_TIME: u16 = 0x0132;
+/// Points from IFD0 to the Exif IFD.
+const EXIF_IFD: u16 = 0x8769;
+/// Exif IFD capture times, and the UTC offsets newer cameras write for them.
+const DATE_TIME_ORIGINAL: u16 = 0x9003;
+const DATE_TIME_DIGITIZED: u16 = 0x9004;
+const OFFSET_TIME: u16 = 0x9010;
+const OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+const OFFSET_TIME_DIGITIZED: u16 = 0x9012;
+/// Entry type of NUL-terminated text.
+const ASCII: u16 = 2;
+
+/// An EXIF chunk with a valid TIFF header.
+pub struct Exif<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Exif<'a> {
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.get(..4)? {
+            [0x49, 0x49, 42, 0] => false,
+            [0x4d, 0x4d, 0, 42] => true,
+            _ => return None,
+        };
+        Some(Self { data, big_endian })
+    }
+
+    /// Whether IFD0 names a camera maker or model. Screenshot tools don't
+    /// write either.
+    pub fn has_camera_tags(&self) -> bool {
+        self.first_ifd().is_some_and(|ifd| {
+            self.entries(ifd)
+                .any(|(tag, _)| tag == MAKE || tag == MODEL)
+        })
+    }
+
+    /// When the image was taken: the original capture time, else when it was
+    /// digitized, else when the file was last written. Times without an
+    /// offset are taken as the local time they were written in.
+    pub fn date_taken(&self) -> Option<DateTime<Utc>> {
+        let ifd0 = self.first_ifd()?;
+        let exif_ifd = self
+            .find(ifd0, EXIF_IFD)
+            .and_then(|entry| self.u32_at(entry + 8))
+            .map(|offset| offset as usize);
+        let in_exif = |tag: u16| exif_ifd.and_then(|ifd| self.ascii(ifd, tag));
+        let (time, offset) = in_exif(DATE_TIME_ORIGINAL)
+            .map(|time| (time, in_exif(OFFSET_TIME_ORIGINAL)))
+            .or_else(|| {
+                in_exif(DATE_TIME_DIGITIZED).map(|time| (time, in_exif(OFFSET_TIME_DIGITIZED)))
+            })
+            .or_else(|| {
+                self.ascii(ifd0, DATE_TIME)
+                    .map(|time| (time, in_exif(OFFSET_TIME)))
+            })?;
+        let naive = NaiveDateTime::parse_from_str(time.trim(), "%Y:%m:%d %H:%M:%S").ok()?;
+        let taken = match offset.and_then(parse_offset) {
+            Some(offset) => offset.from_local_datetime(&naive).single()?.to_utc(),
+            None => Local.from_local_datetime(&naive).earliest()?.to_utc(),
+        };
+        Some(taken)
+    }
+
+    fn first_ifd(&self) -> Option<usize> {
+        self.u32_at(4).map(|offset| offset as usize)
+    }
+
+    /// The tags in the IFD at `ifd`, each with the offset of its entry.
+    fn entries(&self, ifd: usize) -> impl Iterator<Item = (u16, usize)> + '_ {
+        let count = self.u16_at(ifd).unwrap_or(0);
+        (0..usize::from(count)).map_while(move |index| {
+            let entry = ifd + 2 + index * 12;
+            self.u16_at(entry).map(|tag| (tag, entry))
+        })
+    }
+
+    fn find(&self, ifd: usize, tag: u16) -> Option<usize> {
+        self.entries(ifd)
+            .find(|(found, _)| *found == tag)
+            .map(|(_, entry)| entry)
+    }
+
+    /// The text of an ASCII entry, up to its NUL.
+    fn ascii(&self, ifd: usize, tag: u16) -> Option<&'a str> {
+        let entry = self.find(ifd, tag)?;
+        if self.u16_at(entry + 2)? != ASCII {
+            return None;
+        }
+        let count = self.u32_at(entry + 4)? as usize;
+        // Values of four bytes or fewer sit in the entry itself.
+        let start = if count <= 4 {
+            entry + 8
+        } else {
+            self.u32_at(entry + 8)? as usize
+        };
+        let bytes = self.data.get(start..start.checked_add(count)?)?;
+        let text = bytes.split(|byte| *byte == 0).next().unwrap_or_default();
+        std::str::from_utf8(text).ok()
+    }
+
+    fn u16_at(&self, at: usize) -> Option<u16> {
+        let bytes = [*self.data.get(at)?, *self.data.get(at + 1)?];
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+}
+
+/// An offset as EXIF writes it, `+HH:MM` or `-HH:MM`.
+fn parse_offset(text: &str) -> Option<FixedOffset> {
+    let text = text.trim();
+    let sign = match text.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let (hours, minutes) = text.get(1..)?.split_once(':')?;
+    let seconds = hours.parse::<i32>().ok()? * 3600 + minutes.parse::<i32>().ok()? * 60;
+    FixedOffset::east_opt(sign * seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A little-endian EXIF chunk: IFD0 holds `ifd0` and, when `exif` isn't
+    /// empty, a pointer to an Exif IFD holding it. Entries are ASCII text.
+    fn exif_chunk(ifd0: &[(u16, &str)], exif: &[(u16, &str)]) -> Vec<u8> {
+        fn ifd(start: usize, entries: &[(u16, &str)], pointer: Option<u32>) -> Vec<u8> {
+            let count = entries.len() + usize::from(pointer.is_some());
+            let mut table = (count as u16).to_le_bytes().to_vec();
+            let mut values = Vec::new();
+            let values_at = start + 2 + count * 12 + 4;
+            for (tag, text) in entries {
+                let mut value = text.as_bytes().to_vec();
+                value.push(0);
+                table.extend_from_slice(&tag.to_le_bytes());
+                table.extend_from_slice(&ASCII.to_le_bytes());
+                table.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                if value.len() <= 4 {
+                    value.resize(4, 0);
+                    table.extend_from_slice(&value);
+                } else {
+                    let at = (values_at + values.len()) as u32;
+                    table.extend_from_slice(&at.to_le_bytes());
+                    values.extend_from_slice(&value);
+                }
+            }
+            if let Some(pointer) = pointer {
+                table.extend_from_slice(&EXIF_IFD.to_le_bytes());
+                table.extend_from_slice(&[4, 0, 1, 0, 0, 0]);
+                table.extend_from_slice(&pointer.to_le_bytes());
+            }
+            table.extend_from_slice(&[0, 0, 0, 0]);
+            table.extend_from_slice(&values);
+            table
+        }
+
+        let mut chunk = vec![0x49, 0x49, 42, 0, 8, 0, 0, 0];
+        if exif.is_empty() {
+            chunk.extend(ifd(8, ifd0, None));
+            return chunk;
+        }
+        // The pointer entry makes IFD0 one entry longer than without it.
+        let pointer = 8 + ifd(8, ifd0, None).len() + 12;
+        chunk.extend(ifd(8, ifd0, Some(pointer as u32)));
+        chunk.extend(ifd(pointer, exif, None));
+        chunk
+    }
+
+    #[test]
+    fn prefers_the_original_capture_time_and_its_offset() {
+        let chunk = exif_chunk(
+            &[(DATE_TIME, "2024:02:01 09:00:00")],
+            &[
+                (DATE_TIME_ORIGINAL, "2019:07:14 18:30:05"),
+                (OFFSET_TIME_ORIGINAL, "+02:00"),
+            ],
+        );
+        let taken = Exif::parse(&chunk).unwrap().date_taken().unwrap();
+        assert_eq!(taken.to_rfc3339(), "2019-07-14T16:30:05+00:00");
+    }
+
+    #[test]
+    fn falls_back_to_ifd0_and_local_time() {
+        let chunk = exif_chunk(&[(DATE_TIME, "2024:02:01 09:00:00")], &[]);
+        let taken = Exif::parse(&chunk).unwrap().date_taken().unwrap();
+        let local = taken.with_timezone(&Local).naive_local();
+        assert_eq!(local.to_string(), "2024-02-01 09:00:00");
+
+        let blank = exif_chunk(&[(DATE_TIME, "0000:00:00 00:00:00")], &[]);
+        assert_eq!(Exif::parse(&blank).unwrap().date_taken(), None);
+        assert!(Exif::parse(b"not exif").is_none());
+    }
+}
@@ -0,0 +1,473 @@
+//! What conditions and patterns know about a file. `FileInfo::from_path`
+//! reads it from disk; tools without the `fs` feature fill one in themselves.
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+#[cfg(feature = "fs")]
+use std::fs;
+use std::io;
+#[cfg(feature = "fs")]
+use std::io::Read;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "fs")]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+#[cfg(feature = "fs")]
+use filetime::FileTime;
+
+#[cfg(feature = "fs")]
+use crate::cloud_placeholder::is_placeholder;
+#[cfg(feature = "fs")]
+use crate::kinds::{kind_for_extension, override_for};
+use crate::models::{ActiveApp, Arrival, EventSource, FileKind};
+
+/// Bytes read for sniffing the kind, as many as `infer` looks at.
+#[cfg(feature = "fs")]
+const SNIFF_BYTES: u64 = 8192;
+
+#[derive(Debug, Clone)]
+pub struct FileInfo {
+    pub path: PathBuf,
+    /// Lossy forms of the name, which conditions and patterns work with.
+    pub name: String,
+    pub extension: String,
+    pub full_name: String,
+    /// The name as the OS has it, which may not be valid Unicode.
+    pub os_name: OsString,
+    pub size: u64,
+    pub created: DateTime<Utc>,
+    pub modified: DateTime<Utc>,
+    pub added: DateTime<Utc>,
+    pub kind: FileKind,
+    pub parent: Option<String>,
+    #[allow(dead_code)]
+    pub is_dir: bool,
+    pub hash: String,
+    /// Online-only cloud stub whose contents haven't been downloaded yet
+    pub is_placeholder: bool,
+    /// When this file was last matched by any rule (populated from match_repo when available)
+    pub last_matched: Option<DateTime<Utc>>,
+    /// The group this file arrived with, in folders that group arrivals.
+    pub arrival: Option<Arrival>,
+    /// The application in front when the file's event came in. Files looked
+    /// at without an event, as by a scan or a preview, have none.
+    pub active_app: Option<ActiveApp>,
+    /// What brought the file to the engine.
+    pub event_source: EventSource,
+    /// The file's directory relative to its watched folder when it was
+    /// picked up, with `/` between parts; empty at the top of the folder.
+    pub original_subdir: String,
+}
+
+/// Why a path couldn't be looked at as a file to process.
+#[derive(Debug)]
+pub enum FileInfoError {
+    /// Gone by the time it was looked at, or a symlink to nothing.
+    NotFound,
+    PermissionDenied,
+    /// FIFOs, sockets and devices are never processed.
+    NotARegularFile { kind: SpecialFileKind },
+    /// The path has no file name, e.g. it ends in `..`.
+    InvalidName,
+    Io(io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialFileKind {
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+}
+
+impl SpecialFileKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SpecialFileKind::Fifo => "FIFO",
+            SpecialFileKind::Socket => "socket",
+            SpecialFileKind::BlockDevice => "block device",
+            SpecialFileKind::CharDevice => "character device",
+        }
+    }
+}
+
+impl FileInfoError {
+    /// Skipped on purpose rather than failed.
+    pub fn is_skip(&self) -> bool {
+        matches!(self, FileInfoError::NotARegularFile { .. })
+    }
+
+    #[cfg(feature = "fs")]
+    fn from_io(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => FileInfoError::NotFound,
+            io::ErrorKind::PermissionDenied => FileInfoError::PermissionDenied,
+            _ => FileInfoError::Io(err),
+        }
+    }
+}
+
+impl fmt::Display for FileInfoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileInfoError::NotFound => write!(f, "File no longer exists"),
+            FileInfoError::PermissionDenied => write!(f, "Permission denied"),
+            FileInfoError::NotARegularFile { kind } => {
+                write!(f, "Not a regular file ({})", kind.label())
+            }
+            FileInfoError::InvalidName => write!(f, "Path has no file name"),
+            FileInfoError::Io(err) => write!(f, "Couldn't read file info: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for FileInfoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileInfoError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl FileInfo {
+    pub fn from_path(path: &Path) -> Result<Self, FileInfoError> {
+        let os_name = path
+            .file_name()
+            .ok_or(FileInfoError::InvalidName)?
+            .to_os_string();
+        let metadata = fs::metadata(path).map_err(FileInfoError::from_io)?;
+        if let Some(kind) = special_kind(&metadata.file_type()) {
+            return Err(FileInfoError::NotARegularFile { kind });
+        }
+        let is_dir = metadata.is_dir();
+        let full_name = os_name.to_string_lossy().to_string();
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let size = if is_dir { 0 } else { metadata.len() };
+
+        let created = metadata
+            .created()
+            .ok()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|| {
+                DateTime::<Utc>::from(filetime_to_system_time(FileTime::from_last_modification_time(
+                    &metadata,
+                )))
+            });
+        let modified = metadata
+            .modified()
+            .ok()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|| {
+                DateTime::<Utc>::from(filetime_to_system_time(FileTime::from_last_modification_time(
+                    &metadata,
+                )))
+            });
+
+        let added = created;
+
+        let is_placeholder = is_placeholder(path, &metadata);
+        // Sniffing magic bytes would force the sync client to download a placeholder.
+        let head = if is_dir || is_placeholder {
+            None
+        } else {
+            read_head(path)?
+        };
+        // The user's mapping for the extension beats what the contents say.
+        let kind = (!is_dir)
+            .then(|| override_for(&extension))
+            .flatten()
+            .or_else(|| head.as_deref().and_then(sniff_kind))
+            .unwrap_or_else(|| kind_for_extension(is_dir, &extension));
+        let parent = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|s| s.to_string_lossy().to_string());
+
+        let hash = format!("{}:{}", modified.timestamp(), size);
+
+        Ok(FileInfo {
+            path: path.to_path_buf(),
+            name,
+            extension,
+            full_name,
+            os_name,
+            size,
+            created,
+            modified,
+            added,
+            kind,
+            parent,
+            is_dir,
+            hash,
+            is_placeholder,
+            last_matched: None,
+            arrival: None,
+            active_app: None,
+            event_source: EventSource::Live,
+            original_subdir: String::new(),
+        })
+    }
+}
+
+impl FileInfo {
+    /// A name rendered from a pattern, with the lossy form of this file's
+    /// stem turned back into the original bytes where the OS allows, so
+    /// `{date}_{name}.{ext}` keeps a name that isn't valid Unicode intact.
+    pub fn restore_name(&self, rendered: &str) -> OsString {
+        if self.os_name.to_str().is_some() {
+            return OsString::from(rendered);
+        }
+        let stem = Path::new(&self.os_name).file_stem().unwrap_or_default();
+        restore_lossy(rendered, &self.name, stem)
+    }
+}
+
+#[cfg(all(feature = "fs", unix))]
+fn special_kind(file_type: &fs::FileType) -> Option<SpecialFileKind> {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_fifo() {
+        Some(SpecialFileKind::Fifo)
+    } else if file_type.is_socket() {
+        Some(SpecialFileKind::Socket)
+    } else if file_type.is_block_device() {
+        Some(SpecialFileKind::BlockDevice)
+    } else if file_type.is_char_device() {
+        Some(SpecialFileKind::CharDevice)
+    } else {
+        None
+    }
+}
+
+#[cfg(all(feature = "fs", not(unix)))]
+fn special_kind(_file_type: &fs::FileType) -> Option<SpecialFileKind> {
+    None
+}
+
+#[cfg(unix)]
+fn restore_lossy(rendered: &str, lossy: &str, original: &OsStr) -> OsString {
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    if lossy.is_empty() {
+        return OsString::from(rendered);
+    }
+    let mut bytes = Vec::with_capacity(rendered.len());
+    let mut rest = rendered;
+    while let Some(at) = rest.find(lossy) {
+        bytes.extend_from_slice(&rest.as_bytes()[..at]);
+        bytes.extend_from_slice(original.as_bytes());
+        rest = &rest[at + lossy.len()..];
+    }
+    bytes.extend_from_slice(rest.as_bytes());
+    OsString::from_vec(bytes)
+}
+
+/// Windows names that aren't valid UTF-16 keep their lossy form.
+#[cfg(not(unix))]
+fn restore_lossy(rendered: &str, _lossy: &str, _original: &OsStr) -> OsString {
+    OsString::from(rendered)
+}
+
+#[cfg(feature = "fs")]
+fn filetime_to_system_time(filetime: FileTime) -> SystemTime {
+
+    let seconds = filetime.seconds();
+    let nanos = filetime.nanoseconds();
+    if seconds >= 0 {
+        UNIX_EPOCH + Duration::new(seconds as u64, nanos)
+    } else {
+        UNIX_EPOCH - Duration::new((-seconds) as u64, nanos)
+    }
+}
+
+/// The first bytes of a file, for magic-number sniffing. Opening the file
+/// also finds the ones that can be listed but not read; other read errors
+/// just leave the kind to the extension.
+#[cfg(feature = "fs")]
+fn read_head(path: &Path) -> Result<Option<Vec<u8>>, FileInfoError> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            return match err.kind() {
+                io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => {
+                    Err(FileInfoError::from_io(err))
+                }
+                _ => Ok(None),
+            }
+        }
+    };
+    let mut head = Vec::new();
+    Ok(file
+        .take(SNIFF_BYTES)
+        .read_to_end(&mut head)
+        .ok()
+        .map(|_| head))
+}
+
+/// The kind the file's magic bytes say it is, if they say.
+#[cfg(feature = "fs")]
+fn sniff_kind(head: &[u8]) -> Option<FileKind> {
+    let mime = infer::get(head)?.mime_type();
+    if mime.starts_with("image/") {
+        return Some(FileKind::Image);
+    }
+    if mime.starts_with("video/") {
+        return Some(FileKind::Video);
+    }
+    if mime.starts_with("audio/") {
+        return Some(FileKind::Audio);
+    }
+    if mime.starts_with("text/") || mime == "application/pdf" {
+        return Some(FileKind::Document);
+    }
+    if mime.contains("zip") || mime.contains("archive") || mime.contains("tar") {
+        return Some(FileKind::Archive);
+    }
+    None
+}
+
+#[cfg(all(test, feature = "fs"))]
+mod tests {
+    use super::{FileInfo, FileInfoError};
+    use crate::models::FileKind;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn file_info_from_png() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("sample.png");
+        let png_header = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        fs::write(&file_path, png_header).unwrap();
+
+        let info = FileInfo::from_path(&file_path).unwrap();
+        assert_eq!(info.name, "sample");
+        assert_eq!(info.extension, "png");
+        assert_eq!(info.full_name, "sample.png");
+        assert_eq!(info.kind, FileKind::Image);
+        assert!(!info.is_dir);
+        assert!(info.size >= 8);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn names_that_are_not_utf8_keep_their_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(OsStr::from_bytes(b"caf\xe9 menu.PDF"));
+        fs::write(&path, b"%PDF-1.4").unwrap();
+
+        let info = FileInfo::from_path(&path).unwrap();
+        assert_eq!(info.full_name, "caf\u{FFFD} menu.PDF");
+        assert_eq!(info.name, "caf\u{FFFD} menu");
+        assert_eq!(info.extension, "pdf");
+        assert_eq!(info.kind, FileKind::Document);
+        assert_eq!(info.os_name.as_bytes(), b"caf\xe9 menu.PDF");
+
+        let rendered = format!("2025-03-01 {}.{}", info.name, info.extension);
+        assert_eq!(
+            info.restore_name(&rendered).into_vec(),
+            b"2025-03-01 caf\xe9 menu.pdf".to_vec()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn special_files_are_skipped_and_files_beside_them_are_not() {
+        use super::SpecialFileKind;
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let fifo = dir.path().join("pipe");
+        let fifo_path = CString::new(fifo.as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(fifo_path.as_ptr(), 0o644) }, 0);
+        let file = dir.path().join("notes.txt");
+        fs::write(&file, b"notes").unwrap();
+
+        // Opening the FIFO to sniff it would block, so this returning at all
+        // is part of the test.
+        let err = FileInfo::from_path(&fifo).unwrap_err();
+        assert!(matches!(
+            err,
+            FileInfoError::NotARegularFile {
+                kind: SpecialFileKind::Fifo
+            }
+        ));
+        assert!(err.is_skip());
+        assert_eq!(err.to_string(), "Not a regular file (FIFO)");
+
+        assert_eq!(FileInfo::from_path(&file).unwrap().full_name, "notes.txt");
+        let gone = FileInfo::from_path(&dir.path().join("gone.txt")).unwrap_err();
+        assert!(matches!(gone, FileInfoError::NotFound));
+        let nameless = FileInfo::from_path(&dir.path().join("..")).unwrap_err();
+        assert!(matches!(nameless, FileInfoError::InvalidName));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_files_report_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Root reads everything, so there's nothing to test.
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let dir = tempdir().unwrap();
+        let secret = dir.path().join("secret.pdf");
+        fs::write(&secret, b"%PDF-1.4").unwrap();
+        fs::set_permissions(&secret, fs::Permissions::from_mode(0o000)).unwrap();
+
+        let err = FileInfo::from_path(&secret).unwrap_err();
+        assert!(matches!(err, FileInfoError::PermissionDenied));
+        assert!(!err.is_skip());
+
+        fs::set_permissions(&secret, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(FileInfo::from_path(&secret).unwrap().kind, FileKind::Document);
+    }
+
+    #[test]
+    fn kind_overrides_beat_the_contents_and_change_kind_conditions() {
+        use crate::kinds::set_overrides;
+        use crate::{simulate, SyntheticFile};
+        use std::collections::HashMap;
+
+        let rule = crate::rule_schema::import_rules(
+            r#"{"schemaVersion": "2.0", "rules": [{
+                "name": "Documents", "enabled": true, "stopProcessing": false,
+                "conditions": {"matchType": "all", "conditions": [
+                    {"type": "kind", "kind": "document", "negate": false}
+                ]},
+                "actions": [{"type": "move", "destination": "~/Documents",
+                             "onConflict": "rename", "skipDuplicates": false}]
+            }]}"#,
+        )
+        .unwrap()
+        .remove(0);
+        // Pages-like: a zip file under an extension nothing knows.
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.fdpages");
+        fs::write(&path, b"PK\x03\x04rest of the zip").unwrap();
+        let synthetic = SyntheticFile::new("/in/notes.fdpages");
+
+        assert_eq!(FileInfo::from_path(&path).unwrap().kind, FileKind::Archive);
+        assert!(!simulate(&rule, &synthetic).unwrap().matched);
+
+        set_overrides(&HashMap::from([(".FDPAGES".to_string(), FileKind::Document)]));
+        assert_eq!(FileInfo::from_path(&path).unwrap().kind, FileKind::Document);
+        assert!(simulate(&rule, &synthetic).unwrap().matched);
+
+        set_overrides(&HashMap::new());
+        assert_eq!(FileInfo::from_path(&path).unwrap().kind, FileKind::Archive);
+    }
+}
@@ -0,0 +1,236 @@
+//! Finder tags as macOS stores them: a binary property list holding an array
+//! of strings, in the `com.apple.metadata:_kMDItemUserTags` extended
+//! attribute. Each entry is the tag's name, followed by a newline and the
+//! index of its color when it has one, e.g. `Red\n6`.
+
+/// The extended attribute Finder keeps a file's tags in.
+pub const ATTRIBUTE: &str = "com.apple.metadata:_kMDItemUserTags";
+
+const MAGIC: &[u8] = b"bplist00";
+const TRAILER_LEN: usize = 32;
+/// High nibbles of the object markers tags are written with.
+const ASCII_STRING: u8 = 0x5;
+const UTF16_STRING: u8 = 0x6;
+const ARRAY: u8 = 0xa;
+/// Marker of the integer that follows a length too long for its nibble.
+const INT: u8 = 0x1;
+
+/// The entries of a tag list, colors included. `None` when the value isn't
+/// a binary property list holding an array of strings.
+pub fn decode(data: &[u8]) -> Option<Vec<String>> {
+    if !data.starts_with(MAGIC) || data.len() < MAGIC.len() + TRAILER_LEN {
+        return None;
+    }
+    let trailer = &data[data.len() - TRAILER_LEN..];
+    let plist = Plist {
+        data,
+        offset_size: usize::from(trailer[6]),
+        ref_size: usize::from(trailer[7]),
+        objects: be_uint(&trailer[8..16])?,
+        table: be_uint(&trailer[24..32])?,
+    };
+    let top = be_uint(&trailer[16..24])?;
+    let (marker, count, start) = plist.object(top)?;
+    if marker != ARRAY {
+        return None;
+    }
+    (0..count)
+        .map(|index| {
+            let at = start.checked_add(index.checked_mul(plist.ref_size)?)?;
+            plist.string(plist.uint(at, plist.ref_size)?)
+        })
+        .collect()
+}
+
+/// The tag list holding `entries`, as Finder writes it.
+pub fn encode(entries: &[String]) -> Vec<u8> {
+    let mut data = MAGIC.to_vec();
+    let ref_size = bytes_for(entries.len() as u64);
+    let mut offsets = vec![data.len()];
+    push_marker(&mut data, ARRAY, entries.len());
+    for index in 1..=entries.len() {
+        push_uint(&mut data, index as u64, ref_size);
+    }
+    for entry in entries {
+        offsets.push(data.len());
+        if entry.is_ascii() {
+            push_marker(&mut data, ASCII_STRING, entry.len());
+            data.extend_from_slice(entry.as_bytes());
+        } else {
+            let units: Vec<u16> = entry.encode_utf16().collect();
+            push_marker(&mut data, UTF16_STRING, units.len());
+            for unit in units {
+                data.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+    }
+    let table = data.len() as u64;
+    let offset_size = bytes_for(table);
+    for offset in &offsets {
+        push_uint(&mut data, *offset as u64, offset_size);
+    }
+    data.extend_from_slice(&[0; 6]);
+    data.push(offset_size as u8);
+    data.push(ref_size as u8);
+    data.extend_from_slice(&(offsets.len() as u64).to_be_bytes());
+    data.extend_from_slice(&0u64.to_be_bytes());
+    data.extend_from_slice(&table.to_be_bytes());
+    data
+}
+
+/// An entry's tag name, without the color Finder appends to it.
+pub fn tag_name(entry: &str) -> &str {
+    match entry.rsplit_once('\n') {
+        Some((name, color)) if color.bytes().all(|byte| byte.is_ascii_digit()) => name,
+        _ => entry,
+    }
+}
+
+/// `entries` with each of `names` added unless a tag of that name, in any
+/// case, is already there.
+pub fn add_tags(mut entries: Vec<String>, names: &[String]) -> Vec<String> {
+    for name in names {
+        let name = name.trim();
+        let present = entries
+            .iter()
+            .any(|entry| tag_name(entry).to_lowercase() == name.to_lowercase());
+        if !name.is_empty() && !present {
+            entries.push(name.to_string());
+        }
+    }
+    entries
+}
+
+struct Plist<'a> {
+    data: &'a [u8],
+    offset_size: usize,
+    ref_size: usize,
+    objects: usize,
+    table: usize,
+}
+
+impl Plist<'_> {
+    /// The kind, length and start of the contents of object `index`.
+    fn object(&self, index: usize) -> Option<(u8, usize, usize)> {
+        if index >= self.objects {
+            return None;
+        }
+        let at = self.uint(
+            self.table
+                .checked_add(index.checked_mul(self.offset_size)?)?,
+            self.offset_size,
+        )?;
+        let marker = *self.data.get(at)?;
+        let (kind, length) = (marker >> 4, usize::from(marker & 0xf));
+        if length < 0xf {
+            return Some((kind, length, at + 1));
+        }
+        let size_marker = *self.data.get(at + 1)?;
+        if size_marker >> 4 != INT {
+            return None;
+        }
+        let size = 1usize.checked_shl(u32::from(size_marker & 0xf))?;
+        Some((kind, self.uint(at + 2, size)?, at + 2 + size))
+    }
+
+    fn string(&self, index: usize) -> Option<String> {
+        let (kind, length, start) = self.object(index)?;
+        match kind {
+            ASCII_STRING => {
+                let bytes = self.data.get(start..start.checked_add(length)?)?;
+                String::from_utf8(bytes.to_vec()).ok()
+            }
+            UTF16_STRING => {
+                let end = start.checked_add(length.checked_mul(2)?)?;
+                let units: Vec<u16> = self
+                    .data
+                    .get(start..end)?
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect();
+                String::from_utf16(&units).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn uint(&self, at: usize, size: usize) -> Option<usize> {
+        be_uint(self.data.get(at..at.checked_add(size)?)?)
+    }
+}
+
+fn be_uint(bytes: &[u8]) -> Option<usize> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        return None;
+    }
+    let value = bytes
+        .iter()
+        .fold(0u64, |value, byte| value << 8 | u64::from(*byte));
+    usize::try_from(value).ok()
+}
+
+fn bytes_for(value: u64) -> usize {
+    match value {
+        0..=0xff => 1,
+        0x100..=0xffff => 2,
+        0x1_0000..=0xffff_ffff => 4,
+        _ => 8,
+    }
+}
+
+fn push_uint(data: &mut Vec<u8>, value: u64, size: usize) {
+    data.extend_from_slice(&value.to_be_bytes()[8 - size..]);
+}
+
+fn push_marker(data: &mut Vec<u8>, kind: u8, length: usize) {
+    if length < 0xf {
+        data.push(kind << 4 | length as u8);
+        return;
+    }
+    data.push(kind << 4 | 0xf);
+    let size = bytes_for(length as u64);
+    data.push(INT << 4 | size.trailing_zeros() as u8);
+    push_uint(data, length as u64, size);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What Finder writes for a file tagged Red.
+    const RED: &[u8] = b"bplist00\xa1\x01\x55Red\n6\x08\x0a\
+        \x00\x00\x00\x00\x00\x00\x01\x01\
+        \x00\x00\x00\x00\x00\x00\x00\x02\
+        \x00\x00\x00\x00\x00\x00\x00\x00\
+        \x00\x00\x00\x00\x00\x00\x00\x10";
+
+    #[test]
+    fn reads_and_writes_finders_encoding() {
+        let entries = decode(RED).unwrap();
+        assert_eq!(entries, ["Red\n6"]);
+        assert_eq!(tag_name(&entries[0]), "Red");
+        assert_eq!(encode(&entries), RED);
+    }
+
+    #[test]
+    fn long_and_non_ascii_names_round_trip() {
+        let entries = vec![
+            "Rechnungen 2024 – bezahlt".to_string(),
+            "a tag name longer than fifteen bytes\n4".to_string(),
+            "Work".to_string(),
+        ];
+        assert_eq!(decode(&encode(&entries)).unwrap(), entries);
+        assert_eq!(decode(&encode(&[])).unwrap(), Vec::<String>::new());
+        assert_eq!(decode(b"bplist00"), None);
+        assert_eq!(decode(&RED[..RED.len() - 1]), None);
+    }
+
+    #[test]
+    fn adding_keeps_colors_and_skips_tags_already_there() {
+        let entries = add_tags(
+            vec!["Red\n6".to_string()],
+            &["red".to_string(), " Invoices ".to_string(), String::new()],
+        );
+        assert_eq!(entries, ["Red\n6", "Invoices"]);
+    }
+}
@@ -0,0 +1,291 @@
+//! Which kind a file is by its extension. A built-in table covers common
+//! extensions; users map further ones, or remap built-in ones, through the
+//! `kind_overrides` setting. An override beats what the file's magic bytes
+//! say, so e.g. Pages documents, which are zip files, can count as documents.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+use serde::Serialize;
+
+use crate::models::FileKind;
+
+/// The built-in extension table. Extensions not listed here are `Other`, or
+/// `File` when there is none, unless the contents say otherwise.
+pub const DEFAULT_KINDS: &[(&str, FileKind)] = &[
+    ("jpg", FileKind::Image),
+    ("jpeg", FileKind::Image),
+    ("png", FileKind::Image),
+    ("gif", FileKind::Image),
+    ("bmp", FileKind::Image),
+    ("tif", FileKind::Image),
+    ("tiff", FileKind::Image),
+    ("webp", FileKind::Image),
+    ("heic", FileKind::Image),
+    ("heif", FileKind::Image),
+    ("avif", FileKind::Image),
+    ("svg", FileKind::Image),
+    ("ico", FileKind::Image),
+    ("psd", FileKind::Image),
+    ("dng", FileKind::Image),
+    ("cr2", FileKind::Image),
+    ("nef", FileKind::Image),
+    ("arw", FileKind::Image),
+    ("mp4", FileKind::Video),
+    ("m4v", FileKind::Video),
+    ("mov", FileKind::Video),
+    ("avi", FileKind::Video),
+    ("mkv", FileKind::Video),
+    ("webm", FileKind::Video),
+    ("wmv", FileKind::Video),
+    ("flv", FileKind::Video),
+    ("mpg", FileKind::Video),
+    ("mpeg", FileKind::Video),
+    ("mp3", FileKind::Audio),
+    ("m4a", FileKind::Audio),
+    ("wav", FileKind::Audio),
+    ("flac", FileKind::Audio),
+    ("aac", FileKind::Audio),
+    ("ogg", FileKind::Audio),
+    ("opus", FileKind::Audio),
+    ("aiff", FileKind::Audio),
+    ("wma", FileKind::Audio),
+    ("pdf", FileKind::Document),
+    ("doc", FileKind::Document),
+    ("docx", FileKind::Document),
+    ("odt", FileKind::Document),
+    ("rtf", FileKind::Document),
+    ("txt", FileKind::Document),
+    ("md", FileKind::Document),
+    ("pages", FileKind::Document),
+    ("xls", FileKind::Document),
+    ("xlsx", FileKind::Document),
+    ("ods", FileKind::Document),
+    ("csv", FileKind::Document),
+    ("numbers", FileKind::Document),
+    ("ppt", FileKind::Document),
+    ("pptx", FileKind::Document),
+    ("odp", FileKind::Document),
+    ("key", FileKind::Document),
+    ("epub", FileKind::Document),
+    ("zip", FileKind::Archive),
+    ("tar", FileKind::Archive),
+    ("gz", FileKind::Archive),
+    ("tgz", FileKind::Archive),
+    ("bz2", FileKind::Archive),
+    ("xz", FileKind::Archive),
+    ("7z", FileKind::Archive),
+    ("rar", FileKind::Archive),
+    ("dmg", FileKind::Archive),
+    ("iso", FileKind::Archive),
+    ("rs", FileKind::Code),
+    ("js", FileKind::Code),
+    ("ts", FileKind::Code),
+    ("tsx", FileKind::Code),
+    ("jsx", FileKind::Code),
+    ("py", FileKind::Code),
+    ("ipynb", FileKind::Code),
+    ("go", FileKind::Code),
+    ("java", FileKind::Code),
+    ("kt", FileKind::Code),
+    ("swift", FileKind::Code),
+    ("cpp", FileKind::Code),
+    ("c", FileKind::Code),
+    ("h", FileKind::Code),
+    ("hpp", FileKind::Code),
+    ("cs", FileKind::Code),
+    ("rb", FileKind::Code),
+    ("php", FileKind::Code),
+    ("sh", FileKind::Code),
+    ("html", FileKind::Code),
+    ("css", FileKind::Code),
+    ("scss", FileKind::Code),
+    ("json", FileKind::Code),
+    ("yaml", FileKind::Code),
+    ("yml", FileKind::Code),
+    ("toml", FileKind::Code),
+];
+
+/// The user's mappings, normalized, as last set from the settings.
+static OVERRIDES: RwLock<BTreeMap<String, FileKind>> = RwLock::new(BTreeMap::new());
+
+/// Where an entry of the effective table comes from.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum KindSource {
+    Default,
+    Override,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct KindMapping {
+    pub extension: String,
+    pub kind: FileKind,
+    pub source: KindSource,
+}
+
+/// `extension` as the table keys it: lowercase, without a leading dot.
+pub fn normalize_extension(extension: &str) -> Result<String, String> {
+    let trimmed = extension.trim();
+    let normalized = trimmed.strip_prefix('.').unwrap_or(trimmed).to_lowercase();
+    if normalized.is_empty() {
+        return Err("Extension is empty".to_string());
+    }
+    if normalized
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, '.' | '/' | '\\'))
+    {
+        return Err(format!("\"{extension}\" is not a file extension"));
+    }
+    Ok(normalized)
+}
+
+/// Checks and normalizes the `kind_overrides` setting.
+pub fn normalize_overrides(
+    overrides: &HashMap<String, FileKind>,
+) -> Result<HashMap<String, FileKind>, String> {
+    let mut normalized = HashMap::with_capacity(overrides.len());
+    for (extension, kind) in overrides {
+        let key = normalize_extension(extension)?;
+        if *kind == FileKind::Folder {
+            return Err(format!("\"{extension}\" can't map to Folder"));
+        }
+        if let Some(previous) = normalized.insert(key.clone(), kind.clone()) {
+            if previous != *kind {
+                return Err(format!("\"{key}\" is mapped to two kinds"));
+            }
+        }
+    }
+    Ok(normalized)
+}
+
+/// Replaces the user's mappings. Files processed from now on use them;
+/// entries that don't validate are left out.
+pub fn set_overrides(overrides: &HashMap<String, FileKind>) {
+    let normalized = overrides
+        .iter()
+        .filter(|(_, kind)| **kind != FileKind::Folder)
+        .filter_map(|(extension, kind)| Some((normalize_extension(extension).ok()?, kind.clone())))
+        .collect();
+    if let Ok(mut current) = OVERRIDES.write() {
+        *current = normalized;
+    }
+}
+
+/// The kind the user mapped `extension` to, if they did.
+pub fn override_for(extension: &str) -> Option<FileKind> {
+    let overrides = OVERRIDES.read().ok()?;
+    if overrides.is_empty() {
+        return None;
+    }
+    overrides.get(&extension.to_lowercase()).cloned()
+}
+
+fn default_for(extension: &str) -> Option<FileKind> {
+    let extension = extension.to_lowercase();
+    DEFAULT_KINDS
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, kind)| kind.clone())
+}
+
+/// The kind of a file whose contents weren't sniffed, or didn't say.
+pub fn kind_for_extension(is_dir: bool, extension: &str) -> FileKind {
+    if is_dir {
+        return FileKind::Folder;
+    }
+    if extension.is_empty() {
+        return FileKind::File;
+    }
+    override_for(extension)
+        .or_else(|| default_for(extension))
+        .unwrap_or(FileKind::Other)
+}
+
+/// The built-in table with the user's mappings on top, sorted by extension.
+pub fn effective_mappings() -> Vec<KindMapping> {
+    let overrides = OVERRIDES.read().map(|o| o.clone()).unwrap_or_default();
+    merge(&overrides)
+}
+
+fn merge(overrides: &BTreeMap<String, FileKind>) -> Vec<KindMapping> {
+    let mut table: BTreeMap<String, KindMapping> = DEFAULT_KINDS
+        .iter()
+        .map(|(extension, kind)| {
+            let mapping = KindMapping {
+                extension: extension.to_string(),
+                kind: kind.clone(),
+                source: KindSource::Default,
+            };
+            (extension.to_string(), mapping)
+        })
+        .collect();
+    for (extension, kind) in overrides {
+        let mapping = KindMapping {
+            extension: extension.clone(),
+            kind: kind.clone(),
+            source: KindSource::Override,
+        };
+        table.insert(extension.clone(), mapping);
+    }
+    table.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions_are_normalized_to_lowercase_without_the_dot() {
+        assert_eq!(normalize_extension(".HEIF").unwrap(), "heif");
+        assert_eq!(normalize_extension(" Pages ").unwrap(), "pages");
+        assert!(normalize_extension(".").is_err());
+        assert!(normalize_extension("tar.gz").is_err());
+        assert!(normalize_extension("a/b").is_err());
+
+        let overrides = HashMap::from([(".HEIF".to_string(), FileKind::Document)]);
+        let normalized = normalize_overrides(&overrides).unwrap();
+        assert_eq!(normalized.get("heif"), Some(&FileKind::Document));
+        assert_eq!(default_for("HEIF"), Some(FileKind::Image));
+    }
+
+    #[test]
+    fn overrides_that_contradict_themselves_or_name_folders_are_rejected() {
+        let twice = HashMap::from([
+            ("KEY".to_string(), FileKind::Document),
+            (".key".to_string(), FileKind::Code),
+        ]);
+        assert!(normalize_overrides(&twice).is_err());
+        let folder = HashMap::from([("bundle".to_string(), FileKind::Folder)]);
+        assert!(normalize_overrides(&folder).is_err());
+    }
+
+    #[test]
+    fn the_effective_table_says_where_each_mapping_comes_from() {
+        let overrides = BTreeMap::from([
+            ("pages".to_string(), FileKind::Archive),
+            ("sketch".to_string(), FileKind::Image),
+        ]);
+        let table = merge(&overrides);
+        let find = |extension: &str| table.iter().find(|m| m.extension == extension).cloned();
+
+        assert_eq!(
+            find("pages"),
+            Some(KindMapping {
+                extension: "pages".to_string(),
+                kind: FileKind::Archive,
+                source: KindSource::Override,
+            })
+        );
+        assert_eq!(find("sketch").map(|m| m.source), Some(KindSource::Override));
+        assert_eq!(
+            find("heic").map(|m| (m.kind, m.source)),
+            Some((FileKind::Image, KindSource::Default))
+        );
+        assert_eq!(table.iter().filter(|m| m.extension == "pages").count(), 1);
+        assert!(table
+            .windows(2)
+            .all(|pair| pair[0].extension < pair[1].extension));
+    }
+}
@@ -0,0 +1,52 @@
+//! The parts of File Dispatch that don't need the app: the rule model and its
+//! export format, the pattern engine, condition evaluation, rule validation,
+//! simulation and drafting rules from example files. The app builds on this
+//! crate; tools that lint or test rule exports can use it on their own.
+//!
+//! ```
+//! use filedispatch_core::{simulate, validate_rules, SyntheticFile};
+//!
+//! let json = r#"{"schemaVersion": "2.0", "rules": [{
+//!     "name": "Screenshots", "enabled": true, "stopProcessing": false,
+//!     "conditions": {"matchType": "all", "conditions": [
+//!         {"type": "name", "operator": "startsWith", "value": "Screenshot",
+//!          "caseSensitive": false}
+//!     ]},
+//!     "actions": [{"type": "move", "destination": "~/Pictures/Screenshots",
+//!                  "onConflict": "rename", "skipDuplicates": false}]
+//! }]}"#;
+//! assert!(validate_rules(json).is_valid());
+//!
+//! let rules = filedispatch_core::rule_schema::import_rules(json).unwrap();
+//! let result = simulate(&rules[0], &SyntheticFile::new("/tmp/Screenshot 1.png")).unwrap();
+//! assert_eq!(result.actions, ["Move → ~/Pictures/Screenshots"]);
+//! ```
+//!
+//! With the default `fs` feature, `FileInfo::from_path` reads files from
+//! disk. Without it the crate does no file I/O and pulls in no file-type
+//! sniffing.
+
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+pub mod busdays;
+#[cfg(feature = "fs")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fs")))]
+pub mod cloud_placeholder;
+pub mod evaluate;
+pub mod exif;
+pub mod file_info;
+pub mod finder_tags;
+pub mod kinds;
+pub mod media;
+pub mod models;
+pub mod patterns;
+pub mod rule_schema;
+pub mod screenshot;
+pub mod simulate;
+pub mod suggest;
+pub mod timezone;
+pub mod unknown_fields;
+pub mod validate;
+
+pub use simulate::{simulate, SimulationResult, SyntheticFile};
+pub use validate::{validate_rules, Report};
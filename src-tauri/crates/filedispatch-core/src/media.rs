@@ -0,0 +1,672 @@
+//! Reads how long a media file plays and which codecs its streams use from
+//! its container's headers, without decoding any stream. MP4/MOV, Matroska
+//! (and so WebM) and MP3 are understood; anything else is an error.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+
+/// Values longer than this are taken for a malformed file rather than read.
+const MAX_VALUE_BYTES: u64 = 4096;
+/// How far into an MP3 the first frame is looked for, past any ID3 tag.
+const MP3_SYNC_WINDOW: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaInfo {
+    /// `None` when the container doesn't say, e.g. a live recording.
+    pub duration: Option<Duration>,
+    /// One per distinct codec, in stream order, with common names such as
+    /// `h264`, `hevc`, `aac` or `mp3` where the container's id is known.
+    pub codecs: Vec<String>,
+}
+
+pub fn read_media_info<R: Read + Seek>(reader: &mut R) -> Result<MediaInfo> {
+    let mut head = [0u8; 12];
+    let read = read_up_to(reader, &mut head)?;
+    let head = &head[..read];
+    reader.seek(SeekFrom::Start(0))?;
+    if head.starts_with(&[0x1a, 0x45, 0xdf, 0xa3]) {
+        read_matroska(reader)
+    } else if head.get(4..8).is_some_and(|kind| {
+        matches!(
+            kind,
+            b"ftyp" | b"moov" | b"mdat" | b"free" | b"skip" | b"wide"
+        )
+    }) {
+        read_mp4(reader)
+    } else if head.starts_with(b"ID3") || Mp3Frame::parse(head).is_some() {
+        read_mp3(reader)
+    } else {
+        bail!("not an MP4, Matroska or MP3 file")
+    }
+}
+
+fn push_codec(info: &mut MediaInfo, codec: String) {
+    if !codec.is_empty() && !info.codecs.contains(&codec) {
+        info.codecs.push(codec);
+    }
+}
+
+// ---------------------------------------------------------------- MP4/MOV
+
+/// A box: its type and where its payload starts and ends.
+struct Mp4Box {
+    kind: [u8; 4],
+    start: u64,
+    end: u64,
+}
+
+fn read_mp4<R: Read + Seek>(reader: &mut R) -> Result<MediaInfo> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let moov = mp4_boxes(reader, 0, len)?
+        .into_iter()
+        .find(|b| &b.kind == b"moov")
+        .ok_or_else(|| anyhow!("MP4 without a moov box"))?;
+    let mut info = MediaInfo::default();
+    for child in mp4_boxes(reader, moov.start, moov.end)? {
+        match &child.kind {
+            b"mvhd" => info.duration = mp4_duration(reader, &child)?,
+            b"trak" => {
+                if let Some(codec) = mp4_track_codec(reader, &child)? {
+                    push_codec(&mut info, codec);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+/// The boxes laid end to end between `start` and `end`.
+fn mp4_boxes<R: Read + Seek>(reader: &mut R, start: u64, end: u64) -> Result<Vec<Mp4Box>> {
+    let mut boxes = Vec::new();
+    let mut at = start;
+    while at + 8 <= end {
+        reader.seek(SeekFrom::Start(at))?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let size = u64::from(u32::from_be_bytes(header[..4].try_into()?));
+        let kind: [u8; 4] = header[4..].try_into()?;
+        let (header_len, size) = match size {
+            // The size runs to the end of whatever holds the box.
+            0 => (8, end - at),
+            1 => {
+                let mut large = [0u8; 8];
+                reader.read_exact(&mut large)?;
+                (16, u64::from_be_bytes(large))
+            }
+            size => (8, size),
+        };
+        if size < header_len || at.saturating_add(size) > end {
+            bail!(
+                "MP4 box {} overruns its container",
+                String::from_utf8_lossy(&kind)
+            );
+        }
+        boxes.push(Mp4Box {
+            kind,
+            start: at + header_len,
+            end: at + size,
+        });
+        at += size;
+    }
+    Ok(boxes)
+}
+
+fn mp4_duration<R: Read + Seek>(reader: &mut R, mvhd: &Mp4Box) -> Result<Option<Duration>> {
+    let payload = read_payload(reader, mvhd.start, mvhd.end.min(mvhd.start + 32))?;
+    let field = |at: usize, len: usize| {
+        payload
+            .get(at..at + len)
+            .map(|bytes| bytes.iter().fold(0u64, |n, b| n << 8 | u64::from(*b)))
+            .ok_or_else(|| anyhow!("MP4 mvhd box is cut short"))
+    };
+    // Version 1 widens the times and the duration to 64 bits.
+    let (timescale, duration, unknown) = match payload.first() {
+        Some(1) => (field(20, 4)?, field(24, 8)?, u64::MAX),
+        _ => (field(12, 4)?, field(16, 4)?, u64::from(u32::MAX)),
+    };
+    // Fragmented files leave the duration at 0 and add it up per fragment.
+    if timescale == 0 || duration == 0 || duration == unknown {
+        return Ok(None);
+    }
+    // A crafted version 1 box can claim more seconds than a Duration holds.
+    Ok(Duration::try_from_secs_f64(duration as f64 / timescale as f64).ok())
+}
+
+/// The format of the track's first sample description, under
+/// `mdia/minf/stbl/stsd`.
+fn mp4_track_codec<R: Read + Seek>(reader: &mut R, trak: &Mp4Box) -> Result<Option<String>> {
+    let mut parent = (trak.start, trak.end);
+    for kind in [b"mdia", b"minf", b"stbl", b"stsd"] {
+        let Some(child) = mp4_boxes(reader, parent.0, parent.1)?
+            .into_iter()
+            .find(|b| &b.kind == kind)
+        else {
+            return Ok(None);
+        };
+        parent = (child.start, child.end);
+    }
+    // Version and flags, the entry count, then the first entry's size and
+    // format.
+    let stsd = read_payload(reader, parent.0, parent.1.min(parent.0 + 16))?;
+    Ok(stsd.get(12..16).map(|format| {
+        let format = String::from_utf8_lossy(format);
+        mp4_codec_name(format.trim_end()).to_string()
+    }))
+}
+
+fn mp4_codec_name(format: &str) -> String {
+    match format {
+        "avc1" | "avc3" => "h264",
+        "hvc1" | "hev1" => "hevc",
+        "av01" => "av1",
+        "vp08" => "vp8",
+        "vp09" => "vp9",
+        "mp4v" => "mpeg4",
+        "apcn" | "apch" | "apcs" | "apco" | "ap4h" | "ap4x" => "prores",
+        "mp4a" => "aac",
+        ".mp3" => "mp3",
+        "Opus" => "opus",
+        "fLaC" => "flac",
+        "alac" => "alac",
+        "ac-3" => "ac3",
+        "ec-3" => "eac3",
+        "lpcm" | "sowt" | "twos" | "in24" | "in32" | "fl32" | "fl64" => "pcm",
+        other => return other.to_lowercase(),
+    }
+    .to_string()
+}
+
+// ---------------------------------------------------------------- Matroska
+
+const SEGMENT: u32 = 0x1853_8067;
+const INFO: u32 = 0x1549_a966;
+const TIMESTAMP_SCALE: u32 = 0x2a_d7b1;
+const DURATION: u32 = 0x4489;
+const TRACKS: u32 = 0x1654_ae6b;
+const TRACK_ENTRY: u32 = 0xae;
+const CODEC_ID: u32 = 0x86;
+const CLUSTER: u32 = 0x1f43_b675;
+
+/// An element: its id and where its payload starts and ends.
+struct Element {
+    id: u32,
+    start: u64,
+    end: u64,
+}
+
+fn read_matroska<R: Read + Seek>(reader: &mut R) -> Result<MediaInfo> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let header = read_element(reader, len)?;
+    reader.seek(SeekFrom::Start(header.end))?;
+    let segment = read_element(reader, len)?;
+    if segment.id != SEGMENT {
+        bail!("Matroska file without a segment");
+    }
+
+    let mut info = MediaInfo::default();
+    let (mut seen_info, mut seen_tracks) = (false, false);
+    let mut at = segment.start;
+    // Info and Tracks come before the first cluster in practice, so the
+    // walk stops there once it has both.
+    while at < segment.end && !(seen_info && seen_tracks) {
+        reader.seek(SeekFrom::Start(at))?;
+        let element = read_element(reader, segment.end)?;
+        match element.id {
+            INFO => {
+                info.duration = matroska_duration(reader, &element)?;
+                seen_info = true;
+            }
+            TRACKS => {
+                for entry in children(reader, &element)? {
+                    if entry.id != TRACK_ENTRY {
+                        continue;
+                    }
+                    for field in children(reader, &entry)? {
+                        if field.id == CODEC_ID {
+                            let id = read_payload(reader, field.start, field.end)?;
+                            let id = String::from_utf8_lossy(&id);
+                            push_codec(&mut info, matroska_codec_name(id.trim_end_matches('\0')));
+                        }
+                    }
+                }
+                seen_tracks = true;
+            }
+            CLUSTER if element.end == segment.end => break,
+            _ => {}
+        }
+        at = element.end;
+    }
+    Ok(info)
+}
+
+fn matroska_duration<R: Read + Seek>(reader: &mut R, info: &Element) -> Result<Option<Duration>> {
+    // Nanoseconds per tick, unless the file says otherwise.
+    let mut scale = 1_000_000u64;
+    let mut ticks = None;
+    for field in children(reader, info)? {
+        let value = read_payload(reader, field.start, field.end)?;
+        match field.id {
+            TIMESTAMP_SCALE => scale = value.iter().fold(0u64, |n, b| n << 8 | u64::from(*b)),
+            DURATION => {
+                ticks = match value.len() {
+                    4 => Some(f64::from(f32::from_be_bytes(value[..].try_into()?))),
+                    8 => Some(f64::from_be_bytes(value[..].try_into()?)),
+                    _ => None,
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(ticks
+        .map(|ticks| ticks * scale as f64 / 1e9)
+        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()))
+}
+
+fn matroska_codec_name(id: &str) -> String {
+    match id {
+        "V_MPEG4/ISO/AVC" => "h264",
+        "V_MPEGH/ISO/HEVC" => "hevc",
+        "V_AV1" => "av1",
+        "V_VP8" => "vp8",
+        "V_VP9" => "vp9",
+        "V_PRORES" => "prores",
+        "A_OPUS" => "opus",
+        "A_VORBIS" => "vorbis",
+        "A_FLAC" => "flac",
+        "A_MPEG/L3" => "mp3",
+        "A_AC3" => "ac3",
+        "A_EAC3" => "eac3",
+        id if id.starts_with("A_AAC") => "aac",
+        id if id.starts_with("A_PCM") => "pcm",
+        id => return id.to_lowercase(),
+    }
+    .to_string()
+}
+
+/// The elements inside `parent`.
+fn children<R: Read + Seek>(reader: &mut R, parent: &Element) -> Result<Vec<Element>> {
+    let mut elements = Vec::new();
+    let mut at = parent.start;
+    while at < parent.end {
+        reader.seek(SeekFrom::Start(at))?;
+        let element = read_element(reader, parent.end)?;
+        at = element.end;
+        elements.push(element);
+    }
+    Ok(elements)
+}
+
+/// Reads the element header at the reader's position. An element of
+/// unknown size runs to `container_end`, the end of whatever holds it.
+fn read_element<R: Read + Seek>(reader: &mut R, container_end: u64) -> Result<Element> {
+    let (id, _) = read_vint(reader, 4)?;
+    let (size, size_len) = read_vint(reader, 8)?;
+    let start = reader.stream_position()?;
+    let id = id as u32;
+    let end = if size == (1u64 << (7 * size_len)) - 1 {
+        container_end
+    } else {
+        start.saturating_add(size)
+    };
+    if end > container_end {
+        bail!("Matroska element {id:#x} overruns its container");
+    }
+    Ok(Element { id, start, end })
+}
+
+/// A variable-length integer of at most `max_len` bytes, and its length.
+/// Ids keep their length marker; sizes don't.
+fn read_vint<R: Read>(reader: &mut R, max_len: u32) -> Result<(u64, u32)> {
+    let mut first = [0u8; 1];
+    reader.read_exact(&mut first)?;
+    let len = first[0].leading_zeros() + 1;
+    if len > max_len {
+        bail!("malformed Matroska header");
+    }
+    let mut rest = [0u8; 7];
+    reader.read_exact(&mut rest[..len as usize - 1])?;
+    let tail = rest[..len as usize - 1]
+        .iter()
+        .fold(0u64, |n, b| n << 8 | u64::from(*b));
+    let raw = u64::from(first[0]) << (8 * (len - 1)) | tail;
+    // Ids are compared with their marker bit, sizes without it.
+    let value = if max_len == 4 {
+        raw
+    } else {
+        raw & ((1u64 << (7 * len)) - 1)
+    };
+    Ok((value, len))
+}
+
+// ---------------------------------------------------------------- MP3
+
+/// The header of an MPEG audio frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Mp3Frame {
+    mpeg1: bool,
+    layer: u8,
+    bitrate_kbps: u32,
+    sample_rate: u32,
+    padding: bool,
+    mono: bool,
+}
+
+impl Mp3Frame {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let [sync, b1, b2, b3] = *bytes.get(..4)? else {
+            return None;
+        };
+        if sync != 0xff || b1 & 0xe0 != 0xe0 {
+            return None;
+        }
+        // 3 is MPEG-1, 2 MPEG-2 and 0 MPEG-2.5; 1 is reserved.
+        let version = (b1 >> 3) & 3;
+        let layer = match (b1 >> 1) & 3 {
+            3 => 1,
+            2 => 2,
+            1 => 3,
+            _ => return None,
+        };
+        let mpeg1 = version == 3;
+        let rates: [u32; 3] = match version {
+            3 => [44100, 48000, 32000],
+            2 => [22050, 24000, 16000],
+            0 => [11025, 12000, 8000],
+            _ => return None,
+        };
+        let sample_rate = *rates.get(usize::from((b2 >> 2) & 3))?;
+        let index = usize::from(b2 >> 4);
+        if index == 0 || index == 15 {
+            return None;
+        }
+        let bitrates: [u32; 14] = match (mpeg1, layer) {
+            (true, 1) => [
+                32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+            ],
+            (true, 2) => [
+                32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+            ],
+            (true, _) => [
+                32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+            ],
+            (false, 1) => [
+                32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+            ],
+            (false, _) => [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+        };
+        Some(Self {
+            mpeg1,
+            layer,
+            bitrate_kbps: bitrates[index - 1],
+            sample_rate,
+            padding: b2 & 2 != 0,
+            mono: b3 >> 6 == 3,
+        })
+    }
+
+    fn samples(&self) -> u32 {
+        match (self.layer, self.mpeg1) {
+            (1, _) => 384,
+            (3, false) => 576,
+            _ => 1152,
+        }
+    }
+
+    fn len(&self) -> usize {
+        let padding = u32::from(self.padding);
+        let len = if self.layer == 1 {
+            (12 * self.bitrate_kbps * 1000 / self.sample_rate + padding) * 4
+        } else {
+            self.samples() / 8 * self.bitrate_kbps * 1000 / self.sample_rate + padding
+        };
+        len as usize
+    }
+
+    /// Where a Xing or Info header sits, after the side information.
+    fn xing_offset(&self) -> usize {
+        4 + match (self.mpeg1, self.mono) {
+            (true, false) => 32,
+            (true, true) | (false, false) => 17,
+            (false, true) => 9,
+        }
+    }
+}
+
+fn read_mp3<R: Read + Seek>(reader: &mut R) -> Result<MediaInfo> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let mut tag = [0u8; 10];
+    let mut audio_start = 0u64;
+    if read_up_to(reader, &mut tag)? == 10 && tag.starts_with(b"ID3") {
+        // The tag's size is stored seven bits to the byte, and leaves out
+        // the header and the optional footer.
+        let size = tag[6..]
+            .iter()
+            .fold(0u64, |n, b| n << 7 | u64::from(b & 0x7f));
+        audio_start = 10 + size + if tag[5] & 0x10 != 0 { 10 } else { 0 };
+    }
+
+    reader.seek(SeekFrom::Start(audio_start))?;
+    let mut window = vec![0u8; MP3_SYNC_WINDOW];
+    let read = read_up_to(reader, &mut window)?;
+    window.truncate(read);
+    // A frame counts once the next one follows where its length says, or
+    // it's the last thing in the window.
+    let (offset, frame) = (0..window.len())
+        .filter_map(|at| Some((at, Mp3Frame::parse(&window[at..])?)))
+        .find(|(at, frame)| match window.get(at + frame.len()..) {
+            Some(next) if next.len() >= 4 => {
+                Mp3Frame::parse(next).is_some_and(|next| next.layer == frame.layer)
+            }
+            _ => true,
+        })
+        .ok_or_else(|| anyhow!("no MPEG audio frame found"))?;
+
+    let seconds_per_frame = f64::from(frame.samples()) / f64::from(frame.sample_rate);
+    let header = &window[offset..];
+    let at = |from: usize| -> Option<u32> {
+        Some(u32::from_be_bytes(
+            header.get(from..from + 4)?.try_into().ok()?,
+        ))
+    };
+    let xing = frame.xing_offset();
+    // VBR files count their frames in a Xing/Info or a VBRI header;
+    // constant bitrate ones are worked out from their size.
+    let frames = match (header.get(xing..xing + 4), header.get(36..40)) {
+        (Some(b"Xing" | b"Info"), _) => at(xing + 4)
+            .filter(|flags| flags & 1 != 0)
+            .and_then(|_| at(xing + 8)),
+        (_, Some(b"VBRI")) => at(36 + 14),
+        _ => None,
+    };
+    let seconds = match frames {
+        Some(frames) => f64::from(frames) * seconds_per_frame,
+        None => {
+            let audio = len.saturating_sub(audio_start + offset as u64);
+            audio as f64 * 8.0 / (f64::from(frame.bitrate_kbps) * 1000.0)
+        }
+    };
+    Ok(MediaInfo {
+        duration: Duration::try_from_secs_f64(seconds).ok(),
+        codecs: vec![format!("mp{}", frame.layer)],
+    })
+}
+
+// ---------------------------------------------------------------- Reading
+
+fn read_payload<R: Read + Seek>(reader: &mut R, start: u64, end: u64) -> Result<Vec<u8>> {
+    let len = end.saturating_sub(start);
+    if len > MAX_VALUE_BYTES {
+        bail!("media header value of {len} bytes is too long");
+    }
+    reader.seek(SeekFrom::Start(start))?;
+    let mut value = vec![0u8; len as usize];
+    reader.read_exact(&mut value)?;
+    Ok(value)
+}
+
+/// Fills `buf` as far as the reader goes; returns the byte count.
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn read(bytes: Vec<u8>) -> Result<MediaInfo> {
+        read_media_info(&mut Cursor::new(bytes))
+    }
+
+    fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut bytes = ((8 + payload.len()) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    fn mp4_track(format: &[u8; 4]) -> Vec<u8> {
+        let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 16];
+        stsd.extend_from_slice(format);
+        stsd.extend_from_slice(&[0; 8]);
+        let mut inner = mp4_box(b"stsd", &stsd);
+        for kind in [b"stbl", b"minf", b"mdia", b"trak"] {
+            inner = mp4_box(kind, &inner);
+        }
+        inner
+    }
+
+    /// An element with an eight-byte size.
+    fn element(id: &[u8], payload: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.push(0x01);
+        bytes.extend_from_slice(&(payload.len() as u64).to_be_bytes()[1..]);
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    #[test]
+    fn reads_mp4_duration_and_track_codecs() {
+        let mut mvhd = vec![0; 12];
+        mvhd.extend_from_slice(&1000u32.to_be_bytes());
+        mvhd.extend_from_slice(&1_830_500u32.to_be_bytes());
+        mvhd.extend_from_slice(&[0; 80]);
+        let mut moov = mp4_box(b"mvhd", &mvhd);
+        moov.extend(mp4_track(b"avc1"));
+        moov.extend(mp4_track(b"mp4a"));
+        let mut file = mp4_box(b"ftyp", b"isom\0\0\0\0");
+        file.extend(mp4_box(b"mdat", &[0; 64]));
+        file.extend(mp4_box(b"moov", &moov));
+
+        let info = read(file.clone()).unwrap();
+        assert_eq!(info.duration, Some(Duration::from_millis(1_830_500)));
+        assert_eq!(info.codecs, ["h264", "aac"]);
+
+        file.truncate(file.len() - 10);
+        assert!(read(file).is_err());
+    }
+
+    #[test]
+    fn an_mp4_duration_too_long_to_hold_is_unknown() {
+        let mut mvhd = vec![1];
+        mvhd.extend_from_slice(&[0; 19]);
+        mvhd.extend_from_slice(&1u32.to_be_bytes());
+        mvhd.extend_from_slice(&(u64::MAX - 1).to_be_bytes());
+        mvhd.extend_from_slice(&[0; 80]);
+        let mut moov = mp4_box(b"mvhd", &mvhd);
+        moov.extend(mp4_track(b"avc1"));
+        let mut file = mp4_box(b"ftyp", b"isom\0\0\0\0");
+        file.extend(mp4_box(b"moov", &moov));
+
+        let info = read(file).unwrap();
+        assert_eq!(info.duration, None);
+        assert_eq!(info.codecs, ["h264"]);
+    }
+
+    #[test]
+    fn reads_matroska_info_and_tracks() {
+        let mut info = element(&[0x2a, 0xd7, 0xb1], &[0x0f, 0x42, 0x40]);
+        info.extend(element(&[0x44, 0x89], &120_000f64.to_be_bytes()));
+        let mut tracks = element(&[0xae], &element(&[0x86], b"V_VP9"));
+        tracks.extend(element(&[0xae], &element(&[0x86], b"A_OPUS")));
+        tracks.extend(element(&[0xae], &element(&[0x86], b"A_OPUS")));
+        let mut segment = element(&[0x15, 0x49, 0xa9, 0x66], &info);
+        segment.extend(element(&[0x16, 0x54, 0xae, 0x6b], &tracks));
+        // A cluster of unknown size, as live recordings write them.
+        segment.extend_from_slice(&[0x1f, 0x43, 0xb6, 0x75, 0xff, 0x81, 0x00]);
+
+        let mut file = element(&[0x1a, 0x45, 0xdf, 0xa3], &element(&[0x42, 0x82], b"webm"));
+        file.extend_from_slice(&[0x18, 0x53, 0x80, 0x67, 0x01, 0xff, 0xff, 0xff, 0xff]);
+        file.extend_from_slice(&[0xff, 0xff, 0xff]);
+        file.extend(segment);
+
+        let info = read(file).unwrap();
+        assert_eq!(info.duration, Some(Duration::from_secs(120)));
+        assert_eq!(info.codecs, ["vp9", "opus"]);
+    }
+
+    #[test]
+    fn a_matroska_duration_too_long_to_hold_is_unknown() {
+        let mut info = element(&[0x2a, 0xd7, 0xb1], &[0x0f, 0x42, 0x40]);
+        info.extend(element(&[0x44, 0x89], &1e300f64.to_be_bytes()));
+        let tracks = element(&[0xae], &element(&[0x86], b"V_VP9"));
+        let mut segment = element(&[0x15, 0x49, 0xa9, 0x66], &info);
+        segment.extend(element(&[0x16, 0x54, 0xae, 0x6b], &tracks));
+        let mut file = element(&[0x1a, 0x45, 0xdf, 0xa3], &element(&[0x42, 0x82], b"webm"));
+        file.extend(element(&[0x18, 0x53, 0x80, 0x67], &segment));
+
+        let info = read(file).unwrap();
+        assert_eq!(info.duration, None);
+        assert_eq!(info.codecs, ["vp9"]);
+    }
+
+    #[test]
+    fn reads_mp3_length_from_bitrate_or_xing_header() {
+        // MPEG-1 layer III, 128 kbit/s at 44.1 kHz: 417-byte frames.
+        let frame = |extra: &[u8]| {
+            let mut frame = vec![0xff, 0xfb, 0x90, 0x00];
+            frame.extend_from_slice(extra);
+            frame.resize(417, 0);
+            frame
+        };
+        let mut file = b"ID3\x04\x00\x00\x00\x00\x00\x14".to_vec();
+        file.extend_from_slice(&[0; 20]);
+        for _ in 0..100 {
+            file.extend(frame(&[]));
+        }
+        let info = read(file).unwrap();
+        assert_eq!(info.codecs, ["mp3"]);
+        let seconds = info.duration.unwrap().as_secs_f64();
+        assert!((seconds - 100.0 * 417.0 * 8.0 / 128_000.0).abs() < 1e-9);
+
+        let mut xing = vec![0; 32];
+        xing.extend_from_slice(b"Xing");
+        xing.extend_from_slice(&1u32.to_be_bytes());
+        xing.extend_from_slice(&1000u32.to_be_bytes());
+        let mut file = frame(&xing);
+        file.extend(frame(&[]));
+        let seconds = read(file).unwrap().duration.unwrap().as_secs_f64();
+        assert!((seconds - 1000.0 * 1152.0 / 44_100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn other_files_are_errors() {
+        assert!(read(b"just some text, not media".to_vec()).is_err());
+        assert!(read(Vec::new()).is_err());
+    }
+}
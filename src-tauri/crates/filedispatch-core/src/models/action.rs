@@ -0,0 +1,397 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::settings::ReflinkMode;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Action {
+    Move(MoveAction),
+    Copy(CopyAction),
+    Rename(RenameAction),
+    SortIntoSubfolder(SortAction),
+    Archive(ArchiveAction),
+    Unarchive(UnarchiveAction),
+    Delete(DeleteAction),
+    DeletePermanently(DeleteAction),
+    RunScript(ScriptAction),
+    Notify(NotifyAction),
+    Open(OpenAction),
+    ShowInFileManager(ShowInFileManagerAction),
+    OpenWith(OpenWithAction),
+    MakePdfSearchable(MakePdfSearchableAction),
+    CreateFolderStructure(CreateFolderStructureAction),
+    DispatchToFolder(DispatchToFolderAction),
+    SetTags(SetTagsAction),
+    Pause(PauseAction),
+    Continue,
+    Ignore,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveAction {
+    pub destination: String,
+    pub on_conflict: ConflictResolution,
+    pub skip_duplicates: bool,
+    #[serde(default)]
+    pub destination_permissions: DestinationPermissions,
+    #[serde(default)]
+    pub quarantine: QuarantineHandling,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bring_companions: Option<BringCompanions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_partition: Option<AutoPartition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyAction {
+    pub destination: String,
+    pub on_conflict: ConflictResolution,
+    pub skip_duplicates: bool,
+    /// Overrides the global reflink setting for this action.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_reflink: Option<ReflinkMode>,
+    #[serde(default)]
+    pub destination_permissions: DestinationPermissions,
+    #[serde(default)]
+    pub quarantine: QuarantineHandling,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bring_companions: Option<BringCompanions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_partition: Option<AutoPartition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameAction {
+    pub pattern: String,
+    pub on_conflict: ConflictResolution,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SortAction {
+    pub destination: String,
+    pub on_conflict: ConflictResolution,
+    #[serde(default)]
+    pub destination_permissions: DestinationPermissions,
+    #[serde(default)]
+    pub quarantine: QuarantineHandling,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bring_companions: Option<BringCompanions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_partition: Option<AutoPartition>,
+}
+
+/// Files that go along with a moved, copied or sorted file, such as the XMP
+/// sidecar and JPEG preview next to a RAW photo. Each gets the same operation
+/// and conflict handling as the file itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BringCompanions {
+    /// Extensions of files sharing the file's stem, such as `xmp`.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// A glob matched against the names of files next to it, after tokens are
+    /// resolved, such as `{name}_*.txt`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// A listed extension with no file is fine rather than an error.
+    #[serde(default)]
+    pub missing_ok: bool,
+    /// A companion that couldn't be brought along fails the whole action.
+    #[serde(default)]
+    pub fail_on_error: bool,
+    /// Records companions as handled by every rule in the folder, so a later
+    /// rule doesn't process them again.
+    #[serde(default = "default_true")]
+    pub record_matches: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Mode and group a moved or copied file gets once it has landed. Unix only;
+/// elsewhere the file is left as it is.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DestinationPermissions {
+    /// Whatever the move or copy left.
+    #[default]
+    Keep,
+    /// The folder's read and write bits, and its group when the folder is
+    /// setgid, as a file created there would get.
+    Inherit,
+    Explicit {
+        /// Octal, such as `0600`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mode: Option<String>,
+        /// A group name or numeric id.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+    },
+}
+
+/// What happens to macOS's `com.apple.quarantine` attribute, which
+/// Gatekeeper checks before opening downloaded apps and disk images, when a
+/// file is moved or copied. Elsewhere the file is left as it is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QuarantineHandling {
+    /// Whatever the move or copy left: a rename keeps the attribute, while a
+    /// copy between volumes may drop it.
+    #[default]
+    Keep,
+    /// The file lands with the attribute it had, even when it had to be
+    /// copied.
+    Preserve,
+    /// The attribute is removed once the file lands. Needs
+    /// `allow_quarantine_strip` in settings.
+    Strip,
+}
+
+/// Spreads files over subfolders of the destination so no one folder grows
+/// too big. Applied to the resolved destination folder, before conflicts
+/// there are handled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AutoPartition {
+    /// Fills numbered subfolders in turn, starting a new one once the last
+    /// holds `max_per_dir` entries.
+    #[serde(rename_all = "camelCase")]
+    ByCount {
+        max_per_dir: usize,
+        /// The subfolder name, with `{n}` for its number or `{n:4}` for a
+        /// number padded to four digits, such as `batch-{n:4}`.
+        subdir_pattern: String,
+    },
+    /// One subfolder per year, month or day.
+    #[serde(rename_all = "camelCase")]
+    ByDate {
+        granularity: DateGranularity,
+        date_source: PartitionDateSource,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DateGranularity {
+    /// `2024`
+    Year,
+    /// `2024-05`
+    Month,
+    /// `2024-05-13`
+    Day,
+}
+
+/// Which date of the file picks its subfolder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PartitionDateSource {
+    Created,
+    Modified,
+    /// When the action runs.
+    Now,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveAction {
+    pub destination: String,
+    pub format: ArchiveFormat,
+    pub delete_after: bool,
+    /// Password-protects the archive. Zip only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<ArchiveEncryption>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveEncryption {
+    pub method: ArchiveEncryptionMethod,
+    pub password_source: PasswordSource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveEncryptionMethod {
+    /// WinZip AES-256, which 7-Zip, WinZip and macOS's Archive Utility open.
+    ZipAes256,
+}
+
+/// Where an archive password comes from. It's looked up each time the
+/// action runs and never written to the logs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum PasswordSource {
+    /// Stored in the rule itself, and so in exports and backups of it.
+    Literal(String),
+    /// The name of an environment variable.
+    EnvVar(String),
+    /// The service name of an entry in the system keychain.
+    Keyring(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnarchiveAction {
+    pub destination: Option<String>,
+    pub delete_after: bool,
+    /// Only entries matching one of these are extracted; empty extracts all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_globs: Vec<String>,
+    /// Entries matching any of these are left out, even when included.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_globs: Vec<String>,
+    /// Leaves out `__MACOSX`, `.DS_Store` and `Thumbs.db` entries.
+    #[serde(default)]
+    pub skip_junk: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteAction {
+    pub permanent: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptAction {
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotifyAction {
+    pub message: String,
+    /// A bundled sound file or a system sound name; `None` is silent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound: Option<String>,
+    /// Attach a thumbnail of a matched image or PDF where notifications can
+    /// show one.
+    #[serde(default)]
+    pub generate_thumbnail: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenAction {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShowInFileManagerAction {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenWithAction {
+    pub app_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MakePdfSearchableAction {
+    pub skip_if_text: bool,
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateFolderStructureAction {
+    pub base_destination: String,
+    /// Relative path patterns created under `base_destination`.
+    pub structure: Vec<String>,
+}
+
+/// Hands the file to another folder's rules once the preceding actions have
+/// run. Actions listed after it are not executed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DispatchToFolderAction {
+    pub folder_id: String,
+}
+
+/// Gives the file tags where it is at that point in the rule: Finder tags on
+/// macOS, the `user.xdg.tags` attribute on Linux. Tags are resolved as
+/// patterns. Skipped elsewhere and on volumes that can't store tags.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetTagsAction {
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub mode: TagMode,
+}
+
+/// What becomes of the tags a file already has.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TagMode {
+    /// They're kept, and the new ones added after them.
+    #[default]
+    Append,
+    /// They're dropped.
+    Replace,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseAction {
+    pub duration_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    #[serde(rename = "tarGz")]
+    TarGz,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictResolution {
+    Rename,
+    Replace,
+    Skip,
+    /// Suspends the action and queues the conflict for the user to decide.
+    Ask,
+    /// Always adds a suffix unique to this file and machine, so the name is
+    /// free without looking at the destination first.
+    UniqueSuffix,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionDetails {
+    pub source_path: String,
+    pub destination_path: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ActionType {
+    Move,
+    Copy,
+    Rename,
+    SortIntoSubfolder,
+    Archive,
+    Unarchive,
+    Delete,
+    DeletePermanently,
+    RunScript,
+    Notify,
+    Open,
+    ShowInFileManager,
+    OpenWith,
+    MakePdfSearchable,
+    CreateFolderStructure,
+    DispatchToFolder,
+    SetTags,
+    Pause,
+    Continue,
+    Ignore,
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use super::action::ActionType;
+use super::health::HealthSeverity;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleAnalysis {
+    pub folder_id: String,
+    pub findings: Vec<RuleFinding>,
+    /// Number of recent files used for the empirical pass (0 when it was not run).
+    pub sampled_files: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleFinding {
+    pub kind: FindingKind,
+    pub rule_id: String,
+    pub rule_name: String,
+    /// The earlier or overlapping rule involved, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_rule_id: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FindingKind {
+    Shadowed,
+    LikelyShadowed,
+    Overlap,
+    Unsatisfiable,
+}
+
+/// Watched folders and the rules whose destinations put files into another
+/// watched folder, so that folder's rules run on them too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderFlowGraph {
+    pub folders: Vec<FlowFolder>,
+    pub edges: Vec<FlowEdge>,
+    pub findings: Vec<FlowFinding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowFolder {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub scan_depth: i32,
+}
+
+/// One rule action that can hand a file from one watched folder to another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowEdge {
+    pub from_folder_id: String,
+    pub to_folder_id: String,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub action_type: ActionType,
+    /// The destination pattern as written, or the target folder's name for a
+    /// dispatch.
+    pub destination: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowFinding {
+    pub kind: FlowKind,
+    pub severity: HealthSeverity,
+    /// The folders in the order files pass through them; a cycle ends where
+    /// it started.
+    pub folder_ids: Vec<String>,
+    pub rule_ids: Vec<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FlowKind {
+    /// Files go from one folder into another and stop there.
+    Handoff,
+    /// Files pass through three or more folders.
+    Chain,
+    /// Files can come back to a folder they already left.
+    Cycle,
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::action::ActionType;
+
+pub type ApprovalId = String;
+
+/// A match of an approval-required rule, waiting for the user to run or
+/// dismiss it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingApproval {
+    pub id: ApprovalId,
+    pub rule_id: String,
+    /// Filled in when listing; empty on freshly proposed entries.
+    #[serde(default)]
+    pub rule_name: String,
+    pub file_path: String,
+    /// SHA-256 of the file when it matched; execution refuses a changed file.
+    pub file_hash: String,
+    /// Pattern captures from the match, reused when the plan is executed.
+    pub captures: HashMap<String, String>,
+    pub planned: Vec<PlannedAction>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One step of the plan, as resolved by a dry run at match time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedAction {
+    pub action_type: ActionType,
+    pub source_path: String,
+    pub destination_path: Option<String>,
+}
+
+/// Why an approval couldn't be executed. Serialized to the frontend as `{ kind, message }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalError {
+    pub kind: ApprovalErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ApprovalErrorKind {
+    NotFound,
+    Expired,
+    /// The file is gone from the path it matched at.
+    Missing,
+    /// The file changed and still matches; the stored plan was refreshed and
+    /// needs approving again.
+    Replanned,
+    /// The file changed and the rule no longer matches it.
+    NoLongerMatches,
+    Failed,
+}
+
+impl ApprovalError {
+    pub fn new(kind: ApprovalErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self::new(ApprovalErrorKind::Failed, message)
+    }
+}
+
+impl std::fmt::Display for ApprovalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// One backup folder, as listed on the settings page.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    /// The folder name, e.g. `file-dispatch-20250301-180000`.
+    pub id: String,
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+    /// Database and settings together.
+    pub size_bytes: u64,
+    pub schema_version: Option<i64>,
+    pub status: BackupStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackupStatus {
+    Valid,
+    /// Made by a newer File Dispatch; this version can't restore it.
+    NewerSchema,
+    /// Missing, unreadable or not a File Dispatch database.
+    Invalid,
+    /// Made with a password; it's checked when restored.
+    Encrypted,
+}
+
+/// Why a backup or restore failed. Serialized to the frontend as
+/// `{ kind, message }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupError {
+    pub kind: BackupErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackupErrorKind {
+    NotFound,
+    Invalid,
+    /// The backup's schema is newer than this version understands.
+    NewerSchema,
+    /// The backup is encrypted and no password was given.
+    PasswordRequired,
+    WrongPassword,
+    Failed,
+}
+
+impl BackupError {
+    pub fn new(kind: BackupErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self::new(BackupErrorKind::Failed, message)
+    }
+}
+
+impl std::fmt::Display for BackupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for BackupError {}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// What the tray badge counts: items waiting on a decision and errors the
+/// user hasn't acknowledged yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BadgeCounts {
+    pub approvals: usize,
+    pub conflicts: usize,
+    pub errors: usize,
+}
+
+impl BadgeCounts {
+    pub fn total(&self) -> usize {
+        self.approvals + self.conflicts + self.errors
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/// Bytes one kind of action transferred for a rule in a month.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ByteUsage {
+    /// As in the activity log, e.g. `copy`.
+    pub action_type: String,
+    pub bytes: u64,
+    /// Files transferred.
+    pub count: u64,
+}
+
+/// What `rule_budget_status` reports: a rule's usage this month against its
+/// budget.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub rule_id: String,
+    /// `YYYY-MM` in the default time zone.
+    pub month: String,
+    pub budget_bytes: Option<u64>,
+    pub used_bytes: u64,
+    pub usage: Vec<ByteUsage>,
+}
+
+/// Payload of `rule://budget-exhausted`, sent the first time in a month a
+/// rule skips a file for want of budget.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetExhausted {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub month: String,
+    pub budget_bytes: u64,
+    pub used_bytes: u64,
+}
@@ -0,0 +1,81 @@
+use serde::Serialize;
+
+/// The lanes commands that touch the engine and database queue in, each
+/// with its own limit on how many run at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Lane {
+    /// Quick queries: log pages, insights, journal pages.
+    ReadLight,
+    /// Queries that walk folders or whole tables: scans, previews without
+    /// contents, exports, analysis.
+    ReadHeavy,
+    /// Anything that changes rules, folders, settings or history.
+    Write,
+    /// Work that reads contents and may hold the OCR engine.
+    Ocr,
+}
+
+/// How busy a lane is right now.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaneOccupancy {
+    pub lane: Lane,
+    pub running: usize,
+    pub waiting: usize,
+    pub limit: usize,
+}
+
+/// Why a command failed. Serialized to the frontend as `{ kind, message }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandError {
+    pub kind: CommandErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CommandErrorKind {
+    /// The lane stayed full for as long as the command was willing to wait;
+    /// nothing ran, so it's safe to retry.
+    Busy,
+    /// The command ran past its time limit and stopped.
+    Timeout,
+    /// The window that asked for it cancelled it.
+    Cancelled,
+    Failed,
+}
+
+impl CommandError {
+    pub fn new(kind: CommandErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self::new(CommandErrorKind::Failed, message)
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::failed(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        Self::failed(message)
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
@@ -0,0 +1,474 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::models::EventSource;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionGroup {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub match_type: MatchType,
+    pub conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchType {
+    All,
+    Any,
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Condition {
+    Name(StringCondition),
+    Extension(StringCondition),
+    FullName(StringCondition),
+    /// Matches the file's whole path, with `/` between its parts on every
+    /// platform. Regex groups are captured as a name's are.
+    Path(StringCondition),
+    /// Matches the name of the folder the file is in.
+    ParentFolder(StringCondition),
+    /// Matches the file's path under its watched folder as it was when the
+    /// file was picked up, with `/` between parts, e.g. `2024/scan.pdf`.
+    RelativePath(StringCondition),
+    Contents(ContentsCondition),
+    Size(SizeCondition),
+    DateCreated(DateCondition),
+    DateModified(DateCondition),
+    DateAdded(DateCondition),
+    DateLastMatched(DateCondition),
+    /// Matches images by when their EXIF says they were taken. Never matches
+    /// a file that isn't an image, or one with no date in its EXIF.
+    ExifDate(DateCondition),
+    CurrentTime(TimeCondition),
+    Kind(KindCondition),
+    ShellScript(ShellCondition),
+    IsLocked(LockCondition),
+    ClassifiedAs(ClassifiedAsCondition),
+    ArchiveContains(ArchiveContainsCondition),
+    FaceCount(FaceCountCondition),
+    ImageDimensions(ImageDimensionsCondition),
+    PageCount(PageCountCondition),
+    MediaDuration(MediaDurationCondition),
+    /// Matches the codecs of a video or audio file's streams, e.g. `h264` or
+    /// `aac`, as read from its container. A negative operator has to hold
+    /// for every stream. Never matches other files, or media whose container
+    /// can't be read.
+    MediaCodec(StringCondition),
+    IsScreenshot(ScreenshotCondition),
+    IsDuplicate(IsDuplicateCondition),
+    GroupSize(GroupSizeCondition),
+    /// Matches the name or identifier of the application that was in front
+    /// when the file's event came in. Never matches when that isn't known.
+    ActiveApplication(StringCondition),
+    /// Matches the MIME type the file's first bytes say it is, whatever its
+    /// extension, e.g. `image/jpeg`. Never matches a directory or a file
+    /// whose type can't be told from its bytes.
+    MimeType(StringCondition),
+    /// Matches the names of the file's Finder tags, without their colors. A
+    /// negative operator has to hold for every tag, so an untagged file is
+    /// not tagged `Red`. Never matches off macOS, or on a volume that can't
+    /// store tags.
+    Tag(StringCondition),
+    EventSource(EventSourceCondition),
+    CsvColumn(CsvColumnCondition),
+    ExistsAtDestination(ExistsAtDestinationCondition),
+    Nested(ConditionGroup),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StringCondition {
+    pub operator: StringOperator,
+    pub value: String,
+    pub case_sensitive: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StringOperator {
+    Is,
+    IsNot,
+    Contains,
+    DoesNotContain,
+    StartsWith,
+    EndsWith,
+    Matches,
+    DoesNotMatch,
+    /// Shell-style glob that has to match the whole string, so `*.txt`
+    /// doesn't match `notes.txt.bak`. `[...]` is read first: `*` and `?`
+    /// inside brackets are literal, and `[!...]` or `[^...]` negates the set.
+    /// Outside brackets `*` matches any run of characters, `/` and newlines
+    /// included, `?` exactly one, and everything else itself.
+    MatchesGlob,
+    DoesNotMatchGlob,
+    /// Whether the string is one of the entries of a comma- or
+    /// newline-separated list, each compared as `Is` would. Whitespace around
+    /// entries is trimmed and empty entries are skipped. The entry that
+    /// matched is captured as `one_of`.
+    IsOneOf,
+    IsNotOneOf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeCondition {
+    pub operator: ComparisonOperator,
+    pub value: Option<u64>,
+    pub unit: SizeUnit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ComparisonOperator {
+    Equals,
+    NotEquals,
+    GreaterThan,
+    LessThan,
+    GreaterOrEqual,
+    LessOrEqual,
+    Between { min: u64, max: u64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SizeUnit {
+    Bytes,
+    Kilobytes,
+    Megabytes,
+    Gigabytes,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateCondition {
+    pub operator: DateOperator,
+    /// IANA zone (or "default") the file's timestamp is converted to before
+    /// comparing calendar days. Unset keeps the original behavior.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeCondition {
+    pub operator: TimeOperator,
+    /// IANA zone (or "default") whose wall clock is compared; unset uses local time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TimeOperator {
+    Is { time: NaiveTime },
+    IsBefore { time: NaiveTime },
+    IsAfter { time: NaiveTime },
+    Between { start: NaiveTime, end: NaiveTime },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DateOperator {
+    Is { date: NaiveDate },
+    IsBefore { date: NaiveDate },
+    IsAfter { date: NaiveDate },
+    InTheLast { amount: u32, unit: TimeUnit },
+    NotInTheLast { amount: u32, unit: TimeUnit },
+    Between { start: NaiveDate, end: NaiveDate },
+    /// Weekends and `Settings::holidays` don't count towards `amount`.
+    InTheLastBusinessDays { amount: u32 },
+    NotInTheLastBusinessDays { amount: u32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimeUnit {
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KindCondition {
+    pub kind: FileKind,
+    pub negate: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FileKind {
+    File,
+    Folder,
+    Image,
+    Video,
+    Audio,
+    Document,
+    Archive,
+    Code,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellCondition {
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockCondition {
+    #[serde(default)]
+    pub negate: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassifiedAsCondition {
+    /// Empty matches whichever category the classifier predicts.
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub min_score: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveContainsCondition {
+    /// Evaluated against each file entry's path inside the archive.
+    pub name_condition: StringCondition,
+    #[serde(default)]
+    pub match_scope: ArchiveMatchScope,
+    /// Entries to read before giving up; a truncated listing never satisfies
+    /// `AllEntries`.
+    #[serde(default = "default_archive_max_entries")]
+    pub max_entries: u32,
+}
+
+fn default_archive_max_entries() -> u32 {
+    10_000
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveMatchScope {
+    #[default]
+    AnyEntry,
+    AllEntries,
+}
+
+/// Matches images by how many faces a local detector finds in them. Never
+/// matches when the detector is unavailable (model not installed, or a build
+/// without the `faces` feature).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceCountCondition {
+    pub operator: ComparisonOperator,
+    #[serde(default)]
+    pub value: u64,
+}
+
+/// Matches images by their size in pixels, read from the header without
+/// decoding the image. Every part that's given has to hold. Never matches a
+/// file that isn't an image, or one whose header can't be read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageDimensionsCondition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<PixelBound>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<PixelBound>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shape: Option<ImageShape>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PixelBound {
+    pub operator: ComparisonOperator,
+    #[serde(default)]
+    pub value: u64,
+}
+
+/// How an image's width compares with its height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageShape {
+    Portrait,
+    Landscape,
+    Square,
+}
+
+/// Matches PDFs by their number of pages, read from the page tree without
+/// loading the whole document. Never matches a file that isn't a `.pdf`, or
+/// one whose pages can't be counted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageCountCondition {
+    pub operator: ComparisonOperator,
+    #[serde(default)]
+    pub value: u64,
+}
+
+/// Matches video and audio files by how long they play, read from the
+/// container's headers without decoding. Compared in whole seconds, with
+/// `value` (and a range's ends) in `unit`. Never matches other files, or
+/// media whose container doesn't give its length.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDurationCondition {
+    pub operator: ComparisonOperator,
+    #[serde(default)]
+    pub value: u64,
+    pub unit: TimeUnit,
+}
+
+/// Matches images that look like screenshots, from the system's own mark,
+/// the name and the image itself. See `screenshot::judge`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenshotCondition {
+    #[serde(default)]
+    pub negate: bool,
+}
+
+/// Matches a file when another with the same contents is already in `scope`.
+/// The copy found is captured as `duplicate_of`; a negated match has nothing
+/// to capture. Never matches a directory or an online-only placeholder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IsDuplicateCondition {
+    #[serde(default)]
+    pub scope: DuplicateScope,
+    #[serde(default)]
+    pub negate: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateScope {
+    /// The watched folder whose rules are running, as deep as it's scanned.
+    #[default]
+    SameFolder,
+    /// Every enabled watched folder.
+    AnyWatchedFolder,
+}
+
+/// Matches by how many files arrived together with this one, counting itself.
+/// A file in a folder that doesn't group arrivals is a group of one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupSizeCondition {
+    pub operator: ComparisonOperator,
+    #[serde(default)]
+    pub value: u64,
+}
+
+/// Matches files brought to the engine in one of `sources`, e.g. to leave
+/// out files caught up at launch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSourceCondition {
+    pub sources: Vec<EventSource>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentsCondition {
+    pub operator: StringOperator,
+    pub value: String,
+    pub case_sensitive: bool,
+    pub source: ContentSource,
+    /// When the text came from OCR, don't match unless the average recognition
+    /// confidence (0–1) is at least this high.
+    #[serde(default)]
+    pub min_ocr_confidence: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentSource {
+    Text,
+    Ocr,
+    Auto,
+}
+
+/// Matches when a file with the same full name is already in the folder the
+/// pattern resolves to. A folder that doesn't exist holds no collision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExistsAtDestinationCondition {
+    pub destination_pattern: String,
+    #[serde(default)]
+    pub compare: CollisionCompare,
+    #[serde(default)]
+    pub negate: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CollisionCompare {
+    /// Any file with the same name collides.
+    #[default]
+    NameOnly,
+    /// Only a file with the same name and identical contents.
+    NameAndHash,
+}
+
+/// Matches CSV files by the value of one column. The first line is always
+/// the header; rows are counted from the line after it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvColumnCondition {
+    pub column: CsvColumnRef,
+    #[serde(default)]
+    pub row: CsvRowScope,
+    pub condition: StringCondition,
+    #[serde(default)]
+    pub delimiter: CsvDelimiter,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CsvColumnRef {
+    /// 1-based column number.
+    Index { index: usize },
+    /// Header name, compared trimmed and ignoring case.
+    Header { name: String },
+}
+
+impl CsvColumnRef {
+    /// Capture the matched cell is stored under: `csv:3` or `csv:account`.
+    pub fn capture_key(&self) -> String {
+        match self {
+            CsvColumnRef::Index { index } => format!("csv:{index}"),
+            CsvColumnRef::Header { name } => format!("csv:{}", name.trim().to_lowercase()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CsvRowScope {
+    /// Only the first data row.
+    #[default]
+    First,
+    /// Any of the first `max_rows` data rows; the first that matches wins.
+    Any { max_rows: u32 },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CsvDelimiter {
+    /// Comma, semicolon or tab, whichever the header line has most of.
+    #[default]
+    Auto,
+    Char { value: char },
+}
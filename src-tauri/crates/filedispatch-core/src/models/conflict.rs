@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::action::{Action, ActionType, ConflictResolution};
+
+pub type ConflictId = String;
+
+/// A transfer suspended because its destination exists and its action's
+/// conflict policy is Ask. Resolving it runs the suspended action, and the
+/// ones after it in the rule, as they were planned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingConflict {
+    pub id: ConflictId,
+    pub rule_id: String,
+    /// Filled in when listing; empty on freshly suspended entries.
+    #[serde(default)]
+    pub rule_name: String,
+    /// Dispatch trace of the run that was suspended, so the log entries of
+    /// its completion join the same trace.
+    pub trace_id: Option<String>,
+    pub action_type: ActionType,
+    /// Position of the suspended action in the rule.
+    pub action_index: usize,
+    /// The suspended action and the ones after it, as they were when the
+    /// conflict was raised.
+    pub actions: Vec<Action>,
+    /// Pattern captures from the match.
+    pub captures: HashMap<String, String>,
+    pub source: ConflictFile,
+    /// The file already at the destination.
+    pub destination: ConflictFile,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One side of a conflict as it was when the action was suspended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictFile {
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    /// SHA-256 of the contents; resolving refuses to act on a changed file.
+    pub hash: String,
+}
+
+/// What to do about a pending conflict.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum ConflictDecision {
+    Skip,
+    Replace,
+    Rename,
+    /// Put the file next to the existing one under this name instead.
+    UseOtherName(String),
+}
+
+impl ConflictDecision {
+    /// The conflict policy the suspended action runs with. A file under
+    /// another name gets a unique one should that name be taken by then.
+    pub fn policy(&self) -> ConflictResolution {
+        match self {
+            ConflictDecision::Skip => ConflictResolution::Skip,
+            ConflictDecision::Replace => ConflictResolution::Replace,
+            ConflictDecision::Rename | ConflictDecision::UseOtherName(_) => {
+                ConflictResolution::Rename
+            }
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConflictDecision::Skip => "skip",
+            ConflictDecision::Replace => "replace",
+            ConflictDecision::Rename => "rename",
+            ConflictDecision::UseOtherName(_) => "use another name",
+        }
+    }
+}
+
+/// Why a conflict couldn't be resolved. Serialized to the frontend as `{ kind, message }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictError {
+    pub kind: ConflictErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictErrorKind {
+    NotFound,
+    /// The file is gone from where it was suspended.
+    Missing,
+    /// The other name is empty, a path, or already taken; the conflict
+    /// stays pending.
+    InvalidName,
+    /// One of the files changed and the rule was run again, which raised
+    /// the conflict anew.
+    Replanned,
+    /// The file changed and the rule no longer matches it.
+    NoLongerMatches,
+    Failed,
+}
+
+impl ConflictError {
+    pub fn new(kind: ConflictErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self::new(ConflictErrorKind::Failed, message)
+    }
+}
+
+impl std::fmt::Display for ConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
@@ -0,0 +1,219 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{HealthIssue, SlowRule, UnavailableFolder};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineEvent {
+    pub path: String,
+    pub folder_id: String,
+    pub kind: String,
+    pub received_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineError {
+    pub message: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineStatus {
+    pub paused: bool,
+    pub queue_depth: usize,
+    pub processed_count: u64,
+    pub last_event: Option<EngineEvent>,
+    pub last_error: Option<EngineError>,
+    /// Problems the startup health check found, until a repair clears them.
+    #[serde(default)]
+    pub health_issues: Vec<HealthIssue>,
+    /// The slowest rule run of the last hour.
+    #[serde(default)]
+    pub slowest_rule: Option<SlowRule>,
+    /// Watched folders that were removed or moved somewhere they couldn't be
+    /// followed; they're skipped until relinked.
+    #[serde(default)]
+    pub unavailable_folders: Vec<UnavailableFolder>,
+    /// Enabled rules the latest reference check found pointing at something
+    /// missing.
+    #[serde(default)]
+    pub broken_reference_rules: usize,
+    /// Folders processed one file at a time, with their backlogs.
+    #[serde(default)]
+    pub serialized_folders: Vec<SerializedFolder>,
+    /// Destination volumes that are offline, with the files waiting on them.
+    #[serde(default)]
+    pub offline_destinations: Vec<OfflineDestination>,
+    /// Where the work deferred from app launch has got to; `ready` once done.
+    #[serde(default)]
+    pub startup: Option<StartupProgress>,
+    /// How often the records of processed events are committed.
+    #[serde(default)]
+    pub db_writes: DbWriteStats,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for EngineStatus {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            queue_depth: 0,
+            processed_count: 0,
+            last_event: None,
+            last_error: None,
+            health_issues: Vec::new(),
+            slowest_rule: None,
+            unavailable_folders: Vec::new(),
+            broken_reference_rules: 0,
+            serialized_folders: Vec::new(),
+            offline_destinations: Vec::new(),
+            startup: None,
+            db_writes: DbWriteStats::default(),
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// Commits of processed events' records over the last minute.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbWriteStats {
+    pub commits_per_second: f64,
+    /// Events per commit; above 1 only while commits are batched.
+    pub average_batch_size: f64,
+}
+
+/// A strict sequential folder and how many of its files are waiting or
+/// being processed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedFolder {
+    pub folder_id: String,
+    pub backlog: usize,
+}
+
+/// A removable drive or network share a rule transfers to that isn't
+/// mounted. Files for it wait in the retry ledger until it's back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineDestination {
+    /// Where the volume is mounted when it's there.
+    pub path: PathBuf,
+    pub queued_files: usize,
+}
+
+/// The group of files a file arrived with in a folder that groups arrivals.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Arrival {
+    /// Short hash shared by every file of the group.
+    pub id: String,
+    pub size: usize,
+}
+
+/// The application in front when a file's event came in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ActiveApp {
+    Known {
+        /// What the user knows it by, such as `FreeCAD`.
+        name: String,
+        /// The bundle id on macOS and the executable's path on Windows and
+        /// Linux.
+        identifier: Option<String>,
+    },
+    /// The platform couldn't say which, for this reason.
+    Unavailable { reason: String },
+}
+
+impl ActiveApp {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            ActiveApp::Known { name, .. } => Some(name),
+            ActiveApp::Unavailable { .. } => None,
+        }
+    }
+}
+
+/// What brought a file to the engine: the watcher seeing it, or one of the
+/// ways files already sitting in a folder get processed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EventSource {
+    /// The watcher saw it arrive or change.
+    #[default]
+    Live,
+    /// A scheduled rule's run over the folder, or a periodic rescan of it.
+    Scan,
+    /// Changed while the app wasn't running, found at launch.
+    Catchup,
+    /// "Run now" on the folder.
+    Manual,
+    /// A rule tried again after the file was locked or its destination failed.
+    Retry,
+    /// Sent out when the offline volume it was waiting for came back.
+    Sweep,
+}
+
+impl EventSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventSource::Live => "live",
+            EventSource::Scan => "scan",
+            EventSource::Catchup => "catchup",
+            EventSource::Manual => "manual",
+            EventSource::Retry => "retry",
+            EventSource::Sweep => "sweep",
+        }
+    }
+}
+
+/// A step of the work done in the background after launch, in the order they
+/// run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StartupPhase {
+    /// Expired logs and approvals.
+    Cleanup,
+    /// Temp files left by a crash, and folders that went missing.
+    Sweep,
+    Health,
+    Watch,
+    /// Files that changed while the app wasn't running.
+    CatchUp,
+    Ready,
+}
+
+/// Sent as `startup://progress` when a phase starts, as it goes and when it
+/// finishes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupProgress {
+    pub phase: StartupPhase,
+    pub processed: usize,
+    pub finished: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchedFolder {
+    pub folder_id: String,
+    pub path: String,
+    pub scan_depth: i32,
+    /// Events the folder's passthrough patterns dropped since launch.
+    #[serde(default)]
+    pub passthrough_drops: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineStatusSnapshot {
+    pub status: EngineStatus,
+    pub watched_folders: Vec<WatchedFolder>,
+    pub dry_run: bool,
+}
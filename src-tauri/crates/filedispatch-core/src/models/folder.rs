@@ -0,0 +1,236 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub type FolderId = String;
+
+fn default_scan_depth() -> i32 {
+    0 // Current folder only
+}
+
+fn default_incomplete_timeout_minutes() -> u32 {
+    60
+}
+
+/// How the engine treats online-only cloud placeholders found in a folder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlaceholderMode {
+    /// Never process placeholders; each one is logged as skipped.
+    #[default]
+    Skip,
+    /// Evaluate name/size/date rules, but content conditions fail and files stay put.
+    ProcessMetadataOnly,
+    /// Read the file to make the sync client download it, then retry once it's local.
+    Hydrate,
+}
+
+impl PlaceholderMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlaceholderMode::Skip => "skip",
+            PlaceholderMode::ProcessMetadataOnly => "processMetadataOnly",
+            PlaceholderMode::Hydrate => "hydrate",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "processMetadataOnly" => PlaceholderMode::ProcessMetadataOnly,
+            "hydrate" => PlaceholderMode::Hydrate,
+            _ => PlaceholderMode::Skip,
+        }
+    }
+}
+
+/// What a folder stands for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FolderKind {
+    /// A directory on disk, watched live.
+    #[default]
+    Directory,
+    /// The system trash or recycle bin. It has no path of its own, isn't
+    /// watched, and its rules only run in the periodic trash sweep.
+    SystemTrash,
+}
+
+impl FolderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FolderKind::Directory => "directory",
+            FolderKind::SystemTrash => "systemTrash",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "systemTrash" => FolderKind::SystemTrash,
+            _ => FolderKind::Directory,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Folder {
+    pub id: FolderId,
+    pub path: String,
+    pub name: String,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub rule_count: i64,
+    #[serde(default = "default_scan_depth")]
+    pub scan_depth: i32,
+    #[serde(default)]
+    pub remove_duplicates: bool,
+    #[serde(default)]
+    pub trash_incomplete_downloads: bool,
+    #[serde(default = "default_incomplete_timeout_minutes")]
+    pub incomplete_timeout_minutes: u32,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub is_group: bool,
+    #[serde(default)]
+    pub placeholder_mode: PlaceholderMode,
+    /// Process this folder's files one at a time, in the order they arrive.
+    #[serde(default)]
+    pub strict_sequential: bool,
+    /// Files arriving within this many milliseconds of each other are
+    /// processed together as one arrival group; 0 turns grouping off.
+    #[serde(default)]
+    pub group_window_ms: u64,
+    /// Globs for files the watcher drops before anything else sees them: no
+    /// rules, logs, journal entries or counters beyond a count of drops.
+    /// Matched against the path inside the folder and each of its parts.
+    #[serde(default)]
+    pub passthrough_patterns: Vec<String>,
+    /// The folder is a git working tree: `.git` is passed through, and events
+    /// wait while git is mid-checkout, merge or rebase.
+    #[serde(default)]
+    pub respect_git: bool,
+    /// With `respect_git`, files git tracks are left alone too.
+    #[serde(default)]
+    pub skip_git_tracked: bool,
+    /// Images and PDFs matched here get a thumbnail in the activity log.
+    #[serde(default)]
+    pub activity_thumbnails: bool,
+    #[serde(default)]
+    pub kind: FolderKind,
+    /// For the system trash: once it holds more than this many megabytes,
+    /// the items deleted longest ago are purged until it doesn't. 0 is no
+    /// limit.
+    #[serde(default)]
+    pub trash_max_mb: u64,
+}
+
+/// The options set in a folder's options dialog.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSettings {
+    pub scan_depth: i32,
+    pub remove_duplicates: bool,
+    pub trash_incomplete_downloads: bool,
+    pub incomplete_timeout_minutes: u32,
+    #[serde(default)]
+    pub placeholder_mode: PlaceholderMode,
+    #[serde(default)]
+    pub strict_sequential: bool,
+    #[serde(default)]
+    pub group_window_ms: u64,
+    #[serde(default)]
+    pub passthrough_patterns: Vec<String>,
+    #[serde(default)]
+    pub respect_git: bool,
+    #[serde(default)]
+    pub skip_git_tracked: bool,
+    #[serde(default)]
+    pub activity_thumbnails: bool,
+    #[serde(default)]
+    pub trash_max_mb: u64,
+}
+
+/// Sent as `folder://path-changed` when a watched folder was renamed or
+/// relinked to a new path.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderPathChanged {
+    pub folder_id: FolderId,
+    pub old_path: String,
+    pub new_path: String,
+    /// Rules whose destinations pointed into the old path and were updated.
+    pub rules_updated: usize,
+}
+
+/// A watched folder that's gone from disk, until it's found or relinked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnavailableFolder {
+    pub folder_id: FolderId,
+    pub path: String,
+    pub since: DateTime<Utc>,
+}
+
+impl Folder {
+    /// Convert scan_depth to Option<usize> for walkdir max_depth
+    /// -1 means unlimited (None), others map to usize
+    /// Returns the max_depth value to pass to walkdir (adds 1 because walkdir counts from root)
+    pub fn max_depth(&self) -> Option<usize> {
+        if self.scan_depth < 0 {
+            None // Unlimited
+        } else {
+            Some((self.scan_depth + 1) as usize) // +1 because walkdir counts from root
+        }
+    }
+
+    /// Whether the folder is a directory on disk to watch and scan, rather
+    /// than a group or the system trash.
+    pub fn is_directory(&self) -> bool {
+        !self.is_group && self.kind == FolderKind::Directory
+    }
+
+    /// The options set in the folder's options dialog.
+    pub fn settings(&self) -> FolderSettings {
+        FolderSettings {
+            scan_depth: self.scan_depth,
+            remove_duplicates: self.remove_duplicates,
+            trash_incomplete_downloads: self.trash_incomplete_downloads,
+            incomplete_timeout_minutes: self.incomplete_timeout_minutes,
+            placeholder_mode: self.placeholder_mode,
+            strict_sequential: self.strict_sequential,
+            group_window_ms: self.group_window_ms,
+            passthrough_patterns: self.passthrough_patterns.clone(),
+            respect_git: self.respect_git,
+            skip_git_tracked: self.skip_git_tracked,
+            activity_thumbnails: self.activity_thumbnails,
+            trash_max_mb: self.trash_max_mb,
+        }
+    }
+
+    /// The passthrough patterns the watcher and scans go by: the folder's
+    /// own, and `.git` when it respects git.
+    pub fn effective_passthrough_patterns(&self) -> Vec<String> {
+        let mut patterns = self.passthrough_patterns.clone();
+        if self.respect_git {
+            patterns.push(".git".to_string());
+        }
+        patterns
+    }
+
+    pub fn apply_settings(&mut self, settings: &FolderSettings) {
+        self.scan_depth = settings.scan_depth;
+        self.remove_duplicates = settings.remove_duplicates;
+        self.trash_incomplete_downloads = settings.trash_incomplete_downloads;
+        self.incomplete_timeout_minutes = settings.incomplete_timeout_minutes;
+        self.placeholder_mode = settings.placeholder_mode;
+        self.strict_sequential = settings.strict_sequential;
+        self.group_window_ms = settings.group_window_ms;
+        self.passthrough_patterns = settings.passthrough_patterns.clone();
+        self.respect_git = settings.respect_git;
+        self.skip_git_tracked = settings.skip_git_tracked;
+        self.activity_thumbnails = settings.activity_thumbnails;
+        self.trash_max_mb = settings.trash_max_mb;
+    }
+}
@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Folder, FolderSettings, PresetVariable, Rule};
+
+/// A saved folder template, as listed in the add folder dialog.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderTemplateInfo {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub settings: FolderSettings,
+    /// Values asked for when the template is applied, besides `folder`.
+    pub variables: Vec<PresetVariable>,
+    pub rule_count: usize,
+    /// The template file.
+    pub path: String,
+}
+
+/// What applying a template did.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum FolderTemplateApplied {
+    Created {
+        folder: Folder,
+        rules: Vec<Rule>,
+    },
+    /// Nothing was created: these variables have no value and no default.
+    NeedsVariables {
+        variables: Vec<PresetVariable>,
+    },
+}
@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthIssueKind {
+    /// A rule's conditions, actions or schedule no longer deserialize.
+    UnreadableRule,
+    /// A rule whose folder is gone.
+    OrphanedRule,
+    /// A match, approval or subfolder pointing at a rule or folder that's gone.
+    DanglingReference,
+    /// A regex or action pattern the rule validation rejects.
+    InvalidPattern,
+    /// A watched folder whose path is missing or isn't a directory.
+    MissingFolderPath,
+    /// Two rules in one folder share a position, so their order is undefined.
+    DuplicatePosition,
+    /// A column the app can't read back is NULL.
+    MissingValue,
+    /// SQLite's own integrity check failed.
+    Corruption,
+    /// Rules move files on through other watched folders, or in a loop.
+    FolderFlow,
+    /// An Archive action keeps its password in the rule, so plain exports
+    /// refuse the rule and unencrypted backups leave the password out.
+    LiteralPassword,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthIssue {
+    pub severity: HealthSeverity,
+    pub kind: HealthIssueKind,
+    /// The rule or folder the issue is about, when there is one.
+    pub rule_id: Option<String>,
+    pub folder_id: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    pub issues: Vec<HealthIssue>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Which safe fixes `health_repair` may apply; each is off unless asked for.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HealthRepairOptions {
+    /// Disable rules that no longer deserialize. They're kept, never deleted.
+    pub disable_unreadable_rules: bool,
+    /// Re-number each folder's rules 0..n where positions collide.
+    pub renumber_positions: bool,
+    /// Disable folders whose path is missing or isn't a directory.
+    pub disable_missing_folders: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthRepairResult {
+    /// One line per fix applied.
+    pub repaired: Vec<String>,
+    /// The check run again after the fixes.
+    pub report: HealthReport,
+}
+
+/// An enabled rule whose actions point at an app, script, model file or
+/// volume that isn't there.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleReferenceWarnings {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub folder_id: String,
+    pub warnings: Vec<String>,
+}
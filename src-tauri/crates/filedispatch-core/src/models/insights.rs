@@ -0,0 +1,83 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// What File Dispatch has done for the user since insights were last reset.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InsightsSummary {
+    /// Distinct files moved, copied, renamed, sorted, archived or deleted, per day.
+    pub files_sorted: u64,
+    /// Size of the files moved, copied or sorted.
+    pub bytes_moved: u64,
+    /// `files_sorted` times the configured seconds-per-file estimate.
+    pub time_saved_seconds: u64,
+    /// Consecutive days, ending today, without an undo or a failed action.
+    pub streak_days: u32,
+    pub folders: Vec<FolderInsight>,
+}
+
+/// How much of a folder's recent traffic was handled by its rules.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderInsight {
+    pub folder_id: String,
+    pub folder_name: String,
+    /// Files matched by one of the folder's rules in the last 30 days.
+    pub matched_files: u64,
+    /// Matched files plus the files sitting in the folder that weren't matched.
+    pub total_files: u64,
+    /// `matched_files / total_files` as a percentage; `None` for an empty folder.
+    pub match_percent: Option<f64>,
+}
+
+/// What the rules tagged with one classification did over a report's days.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassificationTotal {
+    /// The code as it was logged, which may since have been renamed or removed.
+    pub code: String,
+    /// The code's label in the current settings; `None` once it's no longer listed.
+    pub label: Option<String>,
+    /// Distinct files moved, copied, renamed, sorted, archived or deleted, per day.
+    pub files: u64,
+    /// Size of the files moved, copied or sorted.
+    pub bytes: u64,
+}
+
+/// One day of activity, either rolled up or aggregated from live logs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DailyInsight {
+    pub files_sorted: u64,
+    pub bytes_moved: u64,
+    pub interventions: u64,
+}
+
+/// A day's activity as sent out by the daily digest and carried by the
+/// `digest://ready` event.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyDigest {
+    /// The local day summed up.
+    pub day: NaiveDate,
+    /// Successful actions per action type, most frequent first.
+    pub actions: Vec<DigestActionTotal>,
+    /// Failed actions.
+    pub errors: u64,
+    /// The digest's entry in the activity log.
+    pub log_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestActionTotal {
+    pub action_type: String,
+    pub count: u64,
+    /// Size of the files moved, copied or sorted; 0 for other actions.
+    pub bytes: u64,
+}
+
+impl DailyDigest {
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty() && self.errors == 0
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::engine::ActiveApp;
+
+/// What the engine did with a journaled event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EventDisposition {
+    /// Ran through the folder's rules.
+    Processed,
+    /// Dropped because the same path had an event moments before.
+    Debounced,
+    /// Dropped while processing was paused.
+    Paused,
+    /// Dropped before any rule ran: the file was gone or unreadable, its
+    /// folder removed, a placeholder skipped or a duplicate deleted.
+    Ignored,
+    /// Held with the files that arrived alongside it; it's journaled again
+    /// when the group is processed.
+    Grouped,
+    Error,
+}
+
+impl EventDisposition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventDisposition::Processed => "processed",
+            EventDisposition::Debounced => "debounced",
+            EventDisposition::Paused => "paused",
+            EventDisposition::Ignored => "ignored",
+            EventDisposition::Grouped => "grouped",
+            EventDisposition::Error => "error",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "processed" => Some(EventDisposition::Processed),
+            "debounced" => Some(EventDisposition::Debounced),
+            "paused" => Some(EventDisposition::Paused),
+            "ignored" => Some(EventDisposition::Ignored),
+            "grouped" => Some(EventDisposition::Grouped),
+            "error" => Some(EventDisposition::Error),
+            _ => None,
+        }
+    }
+}
+
+/// One file event as the engine received it, before debouncing or pausing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub id: i64,
+    pub received_at: DateTime<Utc>,
+    pub path: String,
+    /// `created`, `modified`, `renamed` or `deleted`.
+    pub kind: String,
+    pub folder_id: String,
+    /// Unset while the event is still being handled.
+    pub disposition: Option<EventDisposition>,
+    /// The error message for events that failed, or why the file of an
+    /// ignored event couldn't be looked at.
+    pub detail: Option<String>,
+    /// The application in front when the event came in, for events from the
+    /// watcher.
+    #[serde(default)]
+    pub active_app: Option<ActiveApp>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalQuery {
+    pub path_contains: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+}
+
+/// Matching entries, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalPage {
+    pub entries: Vec<JournalEntry>,
+    /// Events not journaled since startup because the writer fell behind.
+    pub dropped: u64,
+}
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::action::ActionDetails;
+
+pub type LogId = String;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub id: LogId,
+    pub rule_id: Option<String>,
+    pub rule_name: Option<String>,
+    pub file_path: String,
+    pub action_type: String,
+    pub action_detail: Option<ActionDetails>,
+    pub status: LogStatus,
+    pub error_message: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogStatus {
+    Success,
+    Error,
+    Skipped,
+    /// Nothing failed, but something needs a look (e.g. a slow rule).
+    Warning,
+}
+
+/// Log entries per status.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusCounts {
+    pub success: u64,
+    pub error: u64,
+    pub skipped: u64,
+    pub warning: u64,
+}
+
+impl StatusCounts {
+    pub fn add(&mut self, status: &LogStatus, count: u64) {
+        match status {
+            LogStatus::Success => self.success += count,
+            LogStatus::Error => self.error += count,
+            LogStatus::Skipped => self.skipped += count,
+            LogStatus::Warning => self.warning += count,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.success + self.error + self.skipped + self.warning
+    }
+}
+
+/// One rule's unreviewed log entries. Entries not written by a rule (backups,
+/// digests, unreadable files) are grouped under no rule.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleActivity {
+    pub rule_id: Option<String>,
+    pub rule_name: Option<String>,
+    pub counts: StatusCounts,
+}
+
+/// What the activity log gained since it was last reviewed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreviewedSummary {
+    /// The last review; `None` until the first, when everything is new.
+    pub since: Option<DateTime<Utc>>,
+    pub counts: StatusCounts,
+    /// Busiest rule first.
+    pub by_rule: Vec<RuleActivity>,
+    /// The rules in `by_rule` with new errors.
+    pub rules_with_errors: Vec<RuleActivity>,
+    /// The latest new errors, newest first.
+    pub recent_errors: Vec<LogEntry>,
+    /// New entries log retention deleted before they were reviewed.
+    pub pruned_before_review: u64,
+}
@@ -0,0 +1,52 @@
+pub mod action;
+pub mod analysis;
+pub mod approval;
+pub mod badge;
+pub mod backup;
+pub mod budget;
+pub mod command;
+pub mod condition;
+pub mod conflict;
+pub mod engine;
+pub mod folder;
+pub mod folder_template;
+pub mod health;
+pub mod insights;
+pub mod journal;
+pub mod log_entry;
+pub mod preview;
+pub mod retry;
+pub mod rule;
+pub mod sandbox;
+pub mod settings;
+pub mod timing;
+pub mod preset;
+pub mod profile;
+pub mod undo;
+pub mod ocr_job;
+
+pub use action::*;
+pub use analysis::*;
+pub use approval::*;
+pub use badge::*;
+pub use backup::*;
+pub use budget::*;
+pub use command::*;
+pub use condition::*;
+pub use conflict::*;
+pub use engine::*;
+pub use folder::*;
+pub use folder_template::*;
+pub use health::*;
+pub use insights::*;
+pub use journal::*;
+pub use log_entry::*;
+pub use preview::*;
+pub use retry::*;
+pub use rule::*;
+pub use sandbox::*;
+pub use settings::*;
+pub use timing::*;
+pub use preset::*;
+pub use profile::*;
+pub use undo::*;
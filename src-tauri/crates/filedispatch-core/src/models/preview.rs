@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use super::action::ActionType;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewItem {
+    pub file_path: String,
+    pub matched: bool,
+    /// The file is outside the rule's path scope, so its conditions weren't
+    /// evaluated.
+    #[serde(default)]
+    pub out_of_scope: bool,
+    pub condition_results: Vec<bool>,
+    pub actions: Vec<String>,
+    /// Computed values worth surfacing, e.g. business-day cutoff dates.
+    #[serde(default)]
+    pub notes: Vec<String>,
+    /// Fields of the file's info replaced for this preview, and `now` when
+    /// the clock was, so the result isn't mistaken for how the file fares.
+    #[serde(default)]
+    pub overridden: Vec<String>,
+    /// The revision of the rule the preview was made against; 0 for a draft
+    /// of a rule that was never saved.
+    #[serde(default)]
+    pub rule_revision: u32,
+}
+
+/// What a rule would do to one file in its folder, worked out without
+/// touching anything.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunItem {
+    pub file_path: String,
+    pub matched: bool,
+    /// One per action, and per companion that would come along, in order.
+    pub outcomes: Vec<DryRunOutcome>,
+    /// Why the file wasn't evaluated, when it wasn't.
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunOutcome {
+    pub action_type: ActionType,
+    /// False when the action would be skipped, e.g. because its destination
+    /// is taken and it skips conflicts.
+    pub would_run: bool,
+    pub source_path: String,
+    /// Where the file would go, conflict handling included; the folders on
+    /// the way needn't exist yet.
+    pub destination_path: Option<String>,
+    /// How a taken destination would be resolved.
+    pub conflict: Option<DryRunConflict>,
+    /// What the action would remove: a deleted file, one archived and
+    /// deleted, or the file a replace overwrites.
+    pub removes: Option<String>,
+    /// Why the action would be skipped.
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DryRunConflict {
+    Rename,
+    Replace,
+    UniqueSuffix,
+    Ask,
+}
@@ -0,0 +1,24 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::Settings;
+
+pub type ProfileId = String;
+
+/// A named snapshot of settings plus which folders and rules are enabled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub id: ProfileId,
+    pub name: String,
+    pub settings: Settings,
+    /// Folder id -> enabled. Folders created after the snapshot keep their current state.
+    pub folder_states: HashMap<String, bool>,
+    /// Rule id -> enabled. Rules created after the snapshot keep their current state.
+    pub rule_states: HashMap<String, bool>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub type RetryId = String;
+
+/// A rule run that failed with a transient error and will be tried again on
+/// that file alone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryEntry {
+    pub id: RetryId,
+    pub rule_id: String,
+    /// Filled in when listing.
+    #[serde(default)]
+    pub rule_name: String,
+    pub folder_id: String,
+    pub file_path: String,
+    /// Failed attempts so far, including the original run.
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: String,
+    /// The offline destination volume the file is waiting for, if that's why
+    /// it failed. Waiting on a volume doesn't use up attempts.
+    #[serde(default)]
+    pub volume: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Payload of `engine://retry-exhausted`, sent when a file is given up on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryExhausted {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub file_path: String,
+    pub attempts: u32,
+    pub last_error: String,
+}
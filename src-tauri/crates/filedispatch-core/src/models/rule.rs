@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::action::{Action, ConflictResolution};
+use super::condition::ConditionGroup;
+
+pub type RuleId = String;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub id: RuleId,
+    pub folder_id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub stop_processing: bool,
+    pub conditions: ConditionGroup,
+    pub actions: Vec<Action>,
+    pub position: i32,
+    /// When set, the rule is a maintenance rule: it ignores watcher events and
+    /// instead runs against the folder's current contents on this schedule.
+    #[serde(default)]
+    pub schedule: Option<RuleSchedule>,
+    /// Matches wait in the review queue instead of running. `stop_processing`
+    /// takes effect only once an approval has been executed, so later rules
+    /// still see the file while it waits.
+    #[serde(default)]
+    pub approval_required: bool,
+    /// Seconds the file must have gone unmodified (and uncreated) before the
+    /// rule is evaluated. Fresher files are rechecked once they're old enough.
+    #[serde(default)]
+    pub min_age_seconds: Option<u64>,
+    /// Limits the rule to files under this path relative to the folder root,
+    /// either a plain subfolder (`Telegram Desktop`) or a glob (`Telegram*/**`).
+    #[serde(default)]
+    pub path_scope: Option<String>,
+    /// Leaves the rule out of the periodic check for missing apps, scripts
+    /// and volumes (see `core::references`).
+    #[serde(default)]
+    pub skip_reference_check: bool,
+    /// Bytes the rule may move, copy or archive in a calendar month in the
+    /// default time zone. A file that would take it over is skipped.
+    #[serde(default)]
+    pub monthly_byte_budget: Option<u64>,
+    /// Retention class from `Settings::classification_codes`, e.g. `FIN-7Y`.
+    /// Logged and kept in undo history with everything the rule does, as
+    /// written at the time, and available to its actions as `{classification}`.
+    #[serde(default)]
+    pub classification: Option<String>,
+    /// What to do with a file this rule moved away that comes back while the
+    /// moved copy is still there. `None` follows `Settings::on_reappear`.
+    #[serde(default)]
+    pub on_reappear: Option<OnReappear>,
+    /// Key/value pairs written into the record of each file the rule matches,
+    /// e.g. `vendor` → `{vendor}`. Values are patterns, resolved when the
+    /// rule runs with its captures and tokens, and can be queried later.
+    #[serde(default)]
+    pub record_metadata: HashMap<String, String>,
+    /// Counts the rule's saves from 1. Log entries record the revision that
+    /// ran, and previews the one they were made against.
+    #[serde(default)]
+    pub revision: u32,
+    /// Fields from a newer version that this one doesn't know, kept so they
+    /// survive being saved again. Shaped like the rule itself, holding only the
+    /// unknown parts (see `crate::unknown_fields`).
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub extra: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A rule as saved at one revision, kept with
+/// `Settings::keep_rule_revisions`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleRevision {
+    pub rule_id: RuleId,
+    pub revision: u32,
+    pub content_hash: String,
+    /// The rule as JSON; fields this version doesn't know are kept as saved.
+    pub rule: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A rule as `rule_list` returns it, with what the latest reference check
+/// found missing.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleListEntry {
+    #[serde(flatten)]
+    pub rule: Rule,
+    pub reference_warnings: Vec<String>,
+}
+
+/// What happens to a file that turns up again with the same hash after a
+/// rule moved it away, such as one a sync client downloads again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum OnReappear {
+    /// The rule runs on it as on any new file.
+    ProcessAgain,
+    /// It's left where it is, with a log entry saying where the earlier copy
+    /// went.
+    #[default]
+    SkipSilently,
+    /// It's moved to the trash; the earlier copy stays.
+    DeleteIncoming,
+    /// It's moved to `destination` to be looked at.
+    MoveToReview { destination: String },
+}
+
+impl OnReappear {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OnReappear::ProcessAgain => "processAgain",
+            OnReappear::SkipSilently => "skipSilently",
+            OnReappear::DeleteIncoming => "deleteIncoming",
+            OnReappear::MoveToReview { .. } => "moveToReview",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RuleSchedule {
+    /// Every `minutes` minutes, counted from when the scheduler first sees the rule.
+    Interval { minutes: u32 },
+    /// Once a day at a local `HH:MM` time.
+    Daily { time: String },
+}
+
+/// A change `rules_bulk_edit` applies to every selected rule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BulkRuleEdit {
+    Enable,
+    Disable,
+    SetStopProcessing { value: bool },
+    /// Rewrites the destination of Move, Copy, SortIntoSubfolder and Archive
+    /// actions and the pattern of Rename actions. With `regex`, `replace` may
+    /// refer to capture groups as `$1`.
+    ReplaceInDestinations {
+        find: String,
+        replace: String,
+        #[serde(default)]
+        regex: bool,
+    },
+    /// Sets the conflict policy of every action that has one.
+    SetConflictResolution { policy: ConflictResolution },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleFieldChange {
+    /// Path of the field in the rule, e.g. `actions[1].destination`.
+    pub field: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// What a bulk edit did to one rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleEditSummary {
+    pub rule_id: RuleId,
+    pub rule_name: String,
+    pub changes: Vec<RuleFieldChange>,
+    pub warnings: Vec<String>,
+}
+
+/// Why a rule file couldn't be imported. Serialized to the frontend as
+/// `{ kind, message, path }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleImportError {
+    pub kind: RuleImportErrorKind,
+    pub message: String,
+    /// Where in the file the problem is, e.g. `rules[0].conditions.conditions[1].operator`.
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RuleImportErrorKind {
+    /// The file doesn't parse or doesn't describe valid rules.
+    Invalid,
+    /// Written by a newer major version of the rule format.
+    UnsupportedVersion,
+    /// The file is encrypted and no password was given.
+    PasswordRequired,
+    WrongPassword,
+    Failed,
+}
+
+impl RuleImportError {
+    pub fn new(kind: RuleImportErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            path: None,
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::new(RuleImportErrorKind::Invalid, message)
+    }
+
+    pub fn at(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+}
+
+impl std::fmt::Display for RuleImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::log_entry::{LogEntry, LogStatus};
+
+/// Files to run a folder's rules against in a sandbox. Saved as JSON so a
+/// bug report can carry the exact files that misbehaved.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxScenario {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub files: Vec<ScenarioFile>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioFile {
+    /// Relative to the watched folder, where the file arrives. A path under
+    /// `~` or an absolute one is a file already there when the run starts,
+    /// such as one a rule's destination will collide with.
+    pub path: String,
+    /// Bytes the file has; `content` is padded or cut to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Text at the start of the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Utc>>,
+    /// Only set where the platform lets creation times be set (macOS and
+    /// Windows).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+}
+
+/// What a sandbox run did. Paths are relative to the sandbox: the watched
+/// folder is under `folder`, `~` under `home` and other absolute paths
+/// under `root`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxResult {
+    /// Every file in the sandbox once the run settled, sorted.
+    pub tree: Vec<SandboxEntry>,
+    /// Log entries the run wrote, oldest first.
+    pub logs: Vec<LogEntry>,
+    /// One per scenario file that arrived in the watched folder.
+    pub files: Vec<SandboxFileOutcome>,
+    /// Events the engine couldn't process.
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxEntry {
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxFileOutcome {
+    pub path: String,
+    /// Where the file ended up; `None` once it's gone, e.g. deleted or
+    /// archived.
+    pub final_path: Option<String>,
+    /// Names of the rules that acted on it, in order.
+    pub rules: Vec<String>,
+    /// The worst status among its log entries; `None` when no rule matched.
+    pub status: Option<LogStatus>,
+}
@@ -0,0 +1,648 @@
+use std::collections::HashMap;
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+use super::condition::FileKind;
+use super::conflict::ConflictDecision;
+use super::rule::OnReappear;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    pub start_at_login: bool,
+    pub show_notifications: bool,
+    /// Hold back Notify actions entirely while do-not-disturb or a focus
+    /// mode is on; their sounds are muted then either way
+    #[serde(default)]
+    pub respect_dnd: bool,
+    /// Which failed actions raise a system notification
+    #[serde(default = "default_notify_on_error")]
+    pub notify_on_error: ErrorNotifyMode,
+    pub minimize_to_tray: bool,
+    pub debounce_ms: u64,
+    pub max_concurrent_rules: u32,
+    pub polling_fallback: bool,
+    pub ignore_patterns: Vec<String>,
+    pub log_retention_days: u32,
+    /// Undo entries older than this can no longer be undone (0 = never expire)
+    #[serde(default = "default_undo_retention_days")]
+    pub undo_retention_days: u32,
+    /// Undo entries kept at most, oldest dropped first (0 = no limit). The
+    /// latest run is always kept whole.
+    #[serde(default = "default_undo_max_entries")]
+    pub undo_max_entries: u32,
+    /// Pending approvals older than this are discarded (0 = never expire)
+    #[serde(default = "default_approval_expiry_hours")]
+    pub approval_expiry_hours: u32,
+    /// Conflicts nobody decided within this many hours get `conflict_fallback`
+    /// (0 = wait forever)
+    #[serde(default = "default_conflict_expiry_hours")]
+    pub conflict_expiry_hours: u32,
+    /// What an expired conflict gets
+    #[serde(default = "default_conflict_fallback")]
+    pub conflict_fallback: ConflictDecision,
+    /// How long sorting one file by hand is assumed to take, for insights
+    #[serde(default = "default_insights_seconds_per_file")]
+    pub insights_seconds_per_file: u32,
+    pub theme: ThemeMode,
+    /// Date format for {date} pattern (e.g., "%Y-%m-%d", "%d/%m/%Y", "%m-%d-%Y")
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    /// Time format for {time} pattern (e.g., "%H-%M-%S", "%I-%M-%S %p")
+    #[serde(default = "default_time_format")]
+    pub time_format: String,
+    /// Use short names for weekday/monthname (Mon vs Monday, Sep vs September)
+    #[serde(default = "default_true")]
+    pub use_short_date_names: bool,
+    #[serde(default = "default_true")]
+    pub show_tooltips: bool,
+    #[serde(default)]
+    pub compact_mode: bool,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub allow_permanent_delete: bool,
+    /// Acknowledges that removing the quarantine attribute lets files open
+    /// without Gatekeeper's check; Strip actions are skipped without it.
+    #[serde(default)]
+    pub allow_quarantine_strip: bool,
+    /// Whether copies try a copy-on-write clone first; Copy actions can override it
+    #[serde(default = "default_use_reflink")]
+    pub use_reflink: ReflinkMode,
+    #[serde(default = "default_true")]
+    pub content_enable_ocr: bool,
+    #[serde(default = "default_content_max_text_bytes")]
+    pub content_max_text_bytes: u64,
+    #[serde(default = "default_content_max_ocr_image_bytes")]
+    pub content_max_ocr_image_bytes: u64,
+    /// Images bigger than this aren't probed for their dimensions or EXIF
+    /// date; 0 probes every one.
+    #[serde(default = "default_content_max_image_probe_bytes")]
+    pub content_max_image_probe_bytes: u64,
+    /// PDFs bigger than this don't have their pages counted; 0 counts every
+    /// one.
+    #[serde(default = "default_content_max_pdf_probe_bytes")]
+    pub content_max_pdf_probe_bytes: u64,
+    #[serde(default = "default_content_max_ocr_pdf_bytes")]
+    pub content_max_ocr_pdf_bytes: u64,
+    #[serde(default = "default_content_max_ocr_pdf_pages")]
+    pub content_max_ocr_pdf_pages: u32,
+    #[serde(default = "default_content_ocr_timeout_image_ms")]
+    pub content_ocr_timeout_image_ms: u64,
+    #[serde(default = "default_content_ocr_timeout_pdf_ms")]
+    pub content_ocr_timeout_pdf_ms: u64,
+    #[serde(default)]
+    pub content_enable_pdf_ocr_text_layer_dev: bool,
+    #[serde(default)]
+    pub content_use_cidfont_ocr: bool,
+    #[serde(default)]
+    pub content_ocr_diagnostic_mode: bool,
+    #[serde(default = "default_ocr_model_source")]
+    pub ocr_model_source: OcrModelSource,
+    #[serde(default)]
+    pub ocr_model_det_path: String,
+    #[serde(default)]
+    pub ocr_model_rec_path: String,
+    #[serde(default)]
+    pub ocr_model_dict_path: String,
+    #[serde(default)]
+    pub ocr_primary_language: String,
+    #[serde(default)]
+    pub ocr_secondary_language: Option<String>,
+    #[serde(default = "default_ocr_confidence_threshold")]
+    pub ocr_confidence_threshold: f32,
+    #[serde(default)]
+    pub ocr_enable_deskew: bool,
+    #[serde(default)]
+    pub ocr_enable_binarization: bool,
+    #[serde(default = "default_preview_max_files")]
+    pub preview_max_files: u32,
+    /// ISO dates (YYYY-MM-DD) excluded from business-day date conditions
+    #[serde(default)]
+    pub holidays: Vec<String>,
+    /// IANA zone used by date conditions and tokens whose zone is "default"
+    #[serde(default)]
+    pub default_timezone: Option<String>,
+    #[serde(default = "default_true")]
+    pub retry_locked_files: bool,
+    #[serde(default = "default_locked_retry_delay_seconds")]
+    pub locked_retry_delay_seconds: u64,
+    #[serde(default = "default_placeholder_hydrate_max_wait_seconds")]
+    pub placeholder_hydrate_max_wait_seconds: u64,
+    /// Tag files with a marker after a rule processes them, so they aren't
+    /// processed again once the database is gone (new machine, wiped data)
+    #[serde(default)]
+    pub write_processed_markers: bool,
+    /// How markers found on a file count toward "already processed"
+    #[serde(default = "default_processed_marker_mode")]
+    pub processed_marker_mode: ProcessedMarkerMode,
+    #[serde(default)]
+    pub classifier_enabled: bool,
+    #[serde(default)]
+    pub classifier_root: String,
+    #[serde(default = "default_classifier_max_files_per_category")]
+    pub classifier_max_files_per_category: u32,
+    /// Log a warning when checking one rule's conditions takes longer than
+    /// this; 0 turns the warning off
+    #[serde(default = "default_slow_rule_warning_ms")]
+    pub slow_rule_warning_ms: u64,
+    /// Stop a folder scan ("Run now" or a scheduled rule) after this many
+    /// files and carry on from there next run; 0 scans the whole folder
+    #[serde(default)]
+    pub scan_max_files_per_run: u32,
+    /// Pace folder scans to this many files a second so live events aren't
+    /// held up behind them; 0 doesn't limit
+    #[serde(default)]
+    pub scan_files_per_second: u32,
+    /// Minutes between queueing every watched folder's files for the engine,
+    /// so files already there when a rule was added get processed; 0 only
+    /// reacts to changes
+    #[serde(default)]
+    pub scan_interval_minutes: u32,
+    /// Update a watched folder's path when it's renamed instead of marking
+    /// it unavailable
+    #[serde(default = "default_true")]
+    pub follow_folder_renames: bool,
+    /// Keep a journal of every file event the engine receives and what
+    /// became of it
+    #[serde(default)]
+    pub event_journal_enabled: bool,
+    /// Journal entries kept before the oldest are dropped
+    #[serde(default = "default_event_journal_max_entries")]
+    pub event_journal_max_entries: u32,
+    /// One summary notification a day instead of watching every file
+    #[serde(default)]
+    pub daily_digest: DailyDigestSettings,
+    /// Scheduled copies of the database and settings
+    #[serde(default)]
+    pub auto_backup: AutoBackupSettings,
+    /// Retention classes rules may be tagged with
+    #[serde(default)]
+    pub classification_codes: Vec<ClassificationCode>,
+    /// Refuse to save a rule without a classification
+    #[serde(default)]
+    pub require_classification: bool,
+    /// Name prefixes of screenshot tools to recognize beyond the built-in ones
+    #[serde(default)]
+    pub screenshot_name_prefixes: Vec<String>,
+    /// Extensions mapped to a kind, on top of the built-in table in `kinds`
+    #[serde(default)]
+    pub kind_overrides: HashMap<String, FileKind>,
+    /// How duplicate checks compare huge files
+    #[serde(default)]
+    pub sampled_hashing: SampledHashSettings,
+    /// What rules without their own setting do with a file they moved away
+    /// that comes back
+    #[serde(default)]
+    pub on_reappear: OnReappear,
+    /// Which actions a later rule skips when an earlier rule already did
+    /// the same thing for the same event
+    #[serde(default)]
+    pub dedupe_actions: DedupeActionsSettings,
+    /// Suffix the unique-suffix conflict policy puts between a file's stem
+    /// and extension, from `{millis}`, `{random}` and `{host}`
+    #[serde(default = "default_unique_suffix_format")]
+    pub unique_suffix_format: String,
+    /// Keys of rules' recorded metadata that match queries look up through
+    /// an index rather than by reading every record
+    #[serde(default)]
+    pub match_metadata_indexed_keys: Vec<String>,
+    /// Size the thumbnail cache is trimmed to, least recently used first
+    #[serde(default = "default_thumbnail_cache_max_mb")]
+    pub thumbnail_cache_max_mb: u64,
+    /// Keep every saved version of each rule in full, so the one a log entry
+    /// ran at can be looked up; kept as long as the logs are
+    #[serde(default)]
+    pub keep_rule_revisions: bool,
+    /// Milliseconds the engine gathers finished events' records before
+    /// committing them together, to spare slow disks a sync per event;
+    /// 0 commits each event's records as soon as it's done
+    #[serde(default)]
+    pub db_batch_commit_ms: u64,
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+/// What `Settings::unique_suffix_format` starts out as, e.g.
+/// `scan.1717423001123.a3f9.officepc.pdf`.
+pub const DEFAULT_UNIQUE_SUFFIX_FORMAT: &str = "{millis}.{random}.{host}";
+
+fn default_unique_suffix_format() -> String {
+    DEFAULT_UNIQUE_SUFFIX_FORMAT.to_string()
+}
+
+fn default_time_format() -> String {
+    "%H-%M-%S".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_content_max_text_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_content_max_ocr_image_bytes() -> u64 {
+    15 * 1024 * 1024
+}
+
+fn default_content_max_image_probe_bytes() -> u64 {
+    512 * 1024 * 1024
+}
+
+fn default_content_max_pdf_probe_bytes() -> u64 {
+    512 * 1024 * 1024
+}
+
+fn default_content_max_ocr_pdf_bytes() -> u64 {
+    30 * 1024 * 1024
+}
+
+fn default_content_max_ocr_pdf_pages() -> u32 {
+    25
+}
+
+fn default_content_ocr_timeout_image_ms() -> u64 {
+    15_000
+}
+
+fn default_content_ocr_timeout_pdf_ms() -> u64 {
+    120_000
+}
+
+fn default_ocr_model_source() -> OcrModelSource {
+    OcrModelSource::Bundled
+}
+
+fn default_ocr_confidence_threshold() -> f32 {
+    0.6
+}
+
+fn default_preview_max_files() -> u32 {
+    100
+}
+
+fn default_notify_on_error() -> ErrorNotifyMode {
+    ErrorNotifyMode::FirstPerRule
+}
+
+fn default_use_reflink() -> ReflinkMode {
+    ReflinkMode::Auto
+}
+
+fn default_undo_retention_days() -> u32 {
+    7
+}
+
+fn default_undo_max_entries() -> u32 {
+    1000
+}
+
+fn default_approval_expiry_hours() -> u32 {
+    72
+}
+
+fn default_conflict_expiry_hours() -> u32 {
+    24
+}
+
+fn default_conflict_fallback() -> ConflictDecision {
+    ConflictDecision::Skip
+}
+
+fn default_insights_seconds_per_file() -> u32 {
+    10
+}
+
+fn default_locked_retry_delay_seconds() -> u64 {
+    30
+}
+
+fn default_placeholder_hydrate_max_wait_seconds() -> u64 {
+    600
+}
+
+fn default_processed_marker_mode() -> ProcessedMarkerMode {
+    ProcessedMarkerMode::Verify
+}
+
+fn default_classifier_max_files_per_category() -> u32 {
+    200
+}
+
+fn default_slow_rule_warning_ms() -> u64 {
+    250
+}
+
+fn default_event_journal_max_entries() -> u32 {
+    10_000
+}
+
+fn default_thumbnail_cache_max_mb() -> u64 {
+    64
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            start_at_login: true,
+            show_notifications: true,
+            respect_dnd: false,
+            notify_on_error: default_notify_on_error(),
+            minimize_to_tray: true,
+            debounce_ms: 500,
+            max_concurrent_rules: 4,
+            polling_fallback: false,
+            ignore_patterns: vec![
+                ".DS_Store".into(),
+                "Thumbs.db".into(),
+                ".git".into(),
+                "node_modules".into(),
+                "*.tmp".into(),
+                "*.part".into(),
+            ],
+            log_retention_days: 30,
+            undo_retention_days: default_undo_retention_days(),
+            undo_max_entries: default_undo_max_entries(),
+            approval_expiry_hours: default_approval_expiry_hours(),
+            conflict_expiry_hours: default_conflict_expiry_hours(),
+            conflict_fallback: default_conflict_fallback(),
+            insights_seconds_per_file: default_insights_seconds_per_file(),
+            theme: ThemeMode::System,
+            date_format: default_date_format(),
+            time_format: default_time_format(),
+            use_short_date_names: true,
+            show_tooltips: true,
+            compact_mode: false,
+            dry_run: false,
+            allow_permanent_delete: false,
+            allow_quarantine_strip: false,
+            use_reflink: default_use_reflink(),
+            content_enable_ocr: true,
+            content_max_text_bytes: default_content_max_text_bytes(),
+            content_max_ocr_image_bytes: default_content_max_ocr_image_bytes(),
+            content_max_image_probe_bytes: default_content_max_image_probe_bytes(),
+            content_max_pdf_probe_bytes: default_content_max_pdf_probe_bytes(),
+            content_max_ocr_pdf_bytes: default_content_max_ocr_pdf_bytes(),
+            content_max_ocr_pdf_pages: default_content_max_ocr_pdf_pages(),
+            content_ocr_timeout_image_ms: default_content_ocr_timeout_image_ms(),
+            content_ocr_timeout_pdf_ms: default_content_ocr_timeout_pdf_ms(),
+            content_enable_pdf_ocr_text_layer_dev: true,
+            content_use_cidfont_ocr: true,
+            content_ocr_diagnostic_mode: false,
+            ocr_model_source: OcrModelSource::Bundled,
+            ocr_model_det_path: String::new(),
+            ocr_model_rec_path: String::new(),
+            ocr_model_dict_path: String::new(),
+            ocr_primary_language: String::new(),
+            ocr_secondary_language: None,
+            ocr_confidence_threshold: default_ocr_confidence_threshold(),
+            ocr_enable_deskew: false,
+            ocr_enable_binarization: false,
+            preview_max_files: default_preview_max_files(),
+            holidays: Vec::new(),
+            default_timezone: None,
+            retry_locked_files: true,
+            locked_retry_delay_seconds: default_locked_retry_delay_seconds(),
+            placeholder_hydrate_max_wait_seconds: default_placeholder_hydrate_max_wait_seconds(),
+            write_processed_markers: false,
+            processed_marker_mode: default_processed_marker_mode(),
+            classifier_enabled: false,
+            classifier_root: String::new(),
+            classifier_max_files_per_category: default_classifier_max_files_per_category(),
+            slow_rule_warning_ms: default_slow_rule_warning_ms(),
+            scan_max_files_per_run: 0,
+            scan_files_per_second: 0,
+            scan_interval_minutes: 0,
+            follow_folder_renames: true,
+            event_journal_enabled: false,
+            event_journal_max_entries: default_event_journal_max_entries(),
+            daily_digest: DailyDigestSettings::default(),
+            auto_backup: AutoBackupSettings::default(),
+            classification_codes: Vec::new(),
+            require_classification: false,
+            screenshot_name_prefixes: Vec::new(),
+            kind_overrides: HashMap::new(),
+            sampled_hashing: SampledHashSettings::default(),
+            on_reappear: OnReappear::default(),
+            dedupe_actions: DedupeActionsSettings::default(),
+            unique_suffix_format: default_unique_suffix_format(),
+            match_metadata_indexed_keys: Vec::new(),
+            thumbnail_cache_max_mb: default_thumbnail_cache_max_mb(),
+            keep_rule_revisions: false,
+            db_batch_commit_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyDigestSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Local time the digest goes out
+    #[serde(default = "default_digest_time")]
+    pub time: NaiveTime,
+    /// Mention failed actions in the notification
+    #[serde(default = "default_true")]
+    pub include_errors: bool,
+    /// Send nothing on days File Dispatch did nothing
+    #[serde(default = "default_true")]
+    pub skip_empty_days: bool,
+}
+
+fn default_digest_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(18, 0, 0).unwrap_or_default()
+}
+
+impl Default for DailyDigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: default_digest_time(),
+            include_errors: true,
+            skip_empty_days: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoBackupSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Days between backups
+    #[serde(default = "default_backup_interval_days")]
+    pub interval_days: u32,
+    /// Backups kept before the oldest are deleted (0 = keep all)
+    #[serde(default = "default_backup_keep_count")]
+    pub keep_count: u32,
+    /// Where backups go; `None` is a `backups` folder next to the database
+    #[serde(default)]
+    pub destination_dir: Option<String>,
+}
+
+fn default_backup_interval_days() -> u32 {
+    1
+}
+
+fn default_backup_keep_count() -> u32 {
+    7
+}
+
+impl Default for AutoBackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_days: default_backup_interval_days(),
+            keep_count: default_backup_keep_count(),
+            destination_dir: None,
+        }
+    }
+}
+
+/// Skipping an action when an earlier rule matching the same event already
+/// did it: a notification with the same message, or a move, copy or sort to
+/// the same destination.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupeActionsSettings {
+    #[serde(default = "default_true")]
+    pub notify: bool,
+    #[serde(default)]
+    pub file_operations: bool,
+}
+
+impl Default for DedupeActionsSettings {
+    fn default() -> Self {
+        Self {
+            notify: true,
+            file_operations: false,
+        }
+    }
+}
+
+/// Duplicate checks on files of at least `min_size_bytes` hash a few windows
+/// of the file and its size instead of all of it: the start, the end and
+/// `window_count` windows spaced evenly between.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampledHashSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_sampled_min_size_bytes")]
+    pub min_size_bytes: u64,
+    #[serde(default = "default_sampled_edge_bytes")]
+    pub head_bytes: u64,
+    #[serde(default = "default_sampled_edge_bytes")]
+    pub tail_bytes: u64,
+    #[serde(default = "default_sampled_window_count")]
+    pub window_count: u32,
+    #[serde(default = "default_sampled_window_bytes")]
+    pub window_bytes: u64,
+    /// What two files with the same samples are taken for
+    #[serde(default)]
+    pub on_match: SampledMatch,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SampledMatch {
+    /// Duplicates, without reading the rest.
+    Trust,
+    /// Duplicates only once hashing both in full agrees.
+    #[default]
+    Confirm,
+}
+
+fn default_sampled_min_size_bytes() -> u64 {
+    1024 * 1024 * 1024
+}
+
+fn default_sampled_edge_bytes() -> u64 {
+    4 * 1024 * 1024
+}
+
+fn default_sampled_window_count() -> u32 {
+    8
+}
+
+fn default_sampled_window_bytes() -> u64 {
+    1024 * 1024
+}
+
+impl Default for SampledHashSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size_bytes: default_sampled_min_size_bytes(),
+            head_bytes: default_sampled_edge_bytes(),
+            tail_bytes: default_sampled_edge_bytes(),
+            window_count: default_sampled_window_count(),
+            window_bytes: default_sampled_window_bytes(),
+            on_match: SampledMatch::default(),
+        }
+    }
+}
+
+/// A retention class, e.g. `FIN-7Y` for finance records kept seven years.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassificationCode {
+    pub code: String,
+    pub label: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ThemeMode {
+    #[serde(alias = "standard")]
+    Light,
+    Dark,
+    System,
+    #[serde(alias = "classic")]
+    Magi,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorNotifyMode {
+    Off,
+    /// The first failure of each rule per hour; later ones are counted into
+    /// the next notification.
+    FirstPerRule,
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReflinkMode {
+    /// Clone where the filesystem supports it, otherwise copy.
+    Auto,
+    /// Fail rather than make a full copy.
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProcessedMarkerMode {
+    /// A marker from the rule is enough, even if the file changed since.
+    Trust,
+    /// Only markers written for the file's current contents count.
+    Verify,
+    Ignore,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OcrModelSource {
+    Bundled,
+    Custom,
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Rolling statistics for one phase of a rule's runs, in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DurationStats {
+    pub count: u64,
+    pub mean_ms: f64,
+    /// Over the most recent runs only.
+    pub p95_ms: f64,
+    pub max_ms: f64,
+    pub last_ms: f64,
+}
+
+/// Regex lookups made while evaluating a rule's conditions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegexCompileStats {
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    /// Time spent compiling on cache misses.
+    pub compile_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleTiming {
+    pub rule_id: String,
+    pub rule_name: String,
+    /// Checking the rule's conditions against a file.
+    pub evaluation: DurationStats,
+    /// Running its actions on a match.
+    pub execution: DurationStats,
+    pub regex: RegexCompileStats,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimingPhase {
+    Evaluation,
+    Execution,
+}
+
+/// The single slowest rule run in a recent window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowRule {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub phase: TimingPhase,
+    pub duration_ms: f64,
+    pub at: DateTime<Utc>,
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub type UndoId = String;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoEntry {
+    pub id: UndoId,
+    pub log_id: String,
+    pub action_type: String,
+    pub original_path: String,
+    pub current_path: String,
+    pub created_at: DateTime<Utc>,
+    /// Hash of the file at `current_path` when the action ran. `None` for
+    /// entries recorded before hashes were tracked, or for directories.
+    #[serde(default)]
+    pub hash: Option<String>,
+    /// How `hash` was taken when it isn't a SHA-256 of the whole file, such
+    /// as `sampled:…` for large files.
+    #[serde(default)]
+    pub hash_scheme: Option<String>,
+    /// The rule's classification when the action ran.
+    #[serde(default)]
+    pub classification: Option<String>,
+    /// Whether the entry can still be undone; filled in by `undo_list`.
+    #[serde(default)]
+    pub status: UndoStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UndoStatus {
+    #[default]
+    Available,
+    /// Older than the undo retention window.
+    Expired,
+    /// The file was modified after the action ran.
+    Changed,
+    /// Nothing exists at `current_path` anymore.
+    Missing,
+}
+
+/// How much undo history is stored, for the settings page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoStats {
+    pub entries: u64,
+    pub oldest: Option<DateTime<Utc>>,
+    /// Entries the next prune deletes under the current limits.
+    pub reclaimable: u64,
+    pub max_entries: u32,
+    pub retention_days: u32,
+}
+
+/// Why an undo was refused. Serialized to the frontend as `{ kind, message }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoError {
+    pub kind: UndoErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UndoErrorKind {
+    NotFound,
+    Expired,
+    /// The file's hash no longer matches; retry with `force` to undo anyway.
+    FileChanged,
+    Missing,
+    OriginalExists,
+    Failed,
+}
+
+impl UndoError {
+    pub fn new(kind: UndoErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self::new(UndoErrorKind::Failed, message)
+    }
+}
+
+impl std::fmt::Display for UndoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
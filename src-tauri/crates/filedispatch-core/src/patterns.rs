@@ -0,0 +1,1335 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use uuid::Uuid;
+
+use crate::evaluate::to_bytes;
+use crate::file_info::FileInfo;
+use crate::models::{ActiveApp, FileKind, SizeUnit};
+use crate::timezone::{parse_timezone, DEFAULT_ZONE};
+
+/// Tokens that render a date and accept an `@Zone` suffix, e.g. `{created:%H@UTC}`.
+const DATE_TOKENS: &[&str] = &[
+    "created", "modified", "added", "now", "date", "time", "year", "month", "day", "hour",
+    "minute", "second", "week", "weekday", "monthname",
+];
+
+/// Date tokens whose format is a `strftime` string, e.g. `{created:%Y/%m}`.
+/// `weekday` and `monthname` also take `short` and `long`.
+const STRFTIME_TOKENS: &[&str] = &[
+    "created",
+    "modified",
+    "added",
+    "now",
+    "weekday",
+    "monthname",
+];
+
+/// Starts a mapping token, e.g. `{map:kind:Image=Pictures,*=Other}`.
+const MAP_PREFIX: &str = "map:";
+
+/// The capture a rule's own `{counter}` number is handed in as. Without it
+/// the token counts the engine's resolves instead.
+pub const COUNTER_CAPTURE: &str = "counter";
+
+const KIND_NAMES: &[(&str, FileKind)] = &[
+    ("File", FileKind::File),
+    ("Folder", FileKind::Folder),
+    ("Image", FileKind::Image),
+    ("Video", FileKind::Video),
+    ("Audio", FileKind::Audio),
+    ("Document", FileKind::Document),
+    ("Archive", FileKind::Archive),
+    ("Code", FileKind::Code),
+    ("Other", FileKind::Other),
+];
+
+thread_local! {
+    /// Branches mapping tokens took, while a `trace_map_branches` call is running.
+    static MAP_TRACE: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f`, returning the branch each mapping token it resolved took, e.g.
+/// `{map:kind} Image → Pictures`.
+pub fn trace_map_branches<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    MAP_TRACE.with(|trace| *trace.borrow_mut() = Some(Vec::new()));
+    let result = f();
+    let branches = MAP_TRACE.with(|trace| trace.borrow_mut().take()).unwrap_or_default();
+    (result, branches)
+}
+
+pub struct PatternEngine {
+    counter: AtomicU32,
+    /// Zone used by `@default` tokens; unset renders them in UTC like unsuffixed ones.
+    default_timezone: Mutex<Option<Tz>>,
+}
+
+impl Default for PatternEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PatternEngine {
+    pub fn new() -> Self {
+        Self {
+            counter: AtomicU32::new(1),
+            default_timezone: Mutex::new(None),
+        }
+    }
+
+    /// Follows `Settings::default_timezone`; an unknown name is treated as unset.
+    pub fn set_default_timezone(&self, name: Option<&str>) {
+        let zone = name.and_then(|name| parse_timezone(name).ok());
+        if let Ok(mut current) = self.default_timezone.lock() {
+            *current = zone;
+        }
+    }
+
+    /// The zone dates are taken in when a token names none.
+    pub fn default_timezone(&self) -> Option<Tz> {
+        self.default_timezone.lock().map(|zone| *zone).unwrap_or(None)
+    }
+
+    pub fn resolve(
+        &self,
+        pattern: &str,
+        info: &FileInfo,
+        captures: &HashMap<String, String>,
+    ) -> String {
+        let mut output = String::new();
+        let now = Utc::now();
+        let counter = self.counter.fetch_add(1, Ordering::SeqCst);
+        let default_zone = self.default_timezone.lock().map(|zone| *zone).unwrap_or(None);
+
+        for piece in split_pattern(pattern) {
+            match piece {
+                Piece::Text(text) => output.push_str(&text),
+                Piece::Token(token) => output.push_str(&resolve_token(
+                    &token,
+                    info,
+                    captures,
+                    now,
+                    counter,
+                    default_zone,
+                )),
+            }
+        }
+
+        output
+    }
+}
+
+enum Piece {
+    Text(String),
+    /// What's between the braces of a `{token}`.
+    Token(String),
+}
+
+/// Splits a pattern into text and tokens. A token runs to the next `}`, or to
+/// the end when unclosed; inside a mapping token `\}` doesn't close it.
+fn split_pattern(pattern: &str) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '{' {
+            text.push(ch);
+            continue;
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(std::mem::take(&mut text)));
+        }
+        let mut token = String::new();
+        while let Some(next) = chars.next() {
+            if next == '}' {
+                break;
+            }
+            token.push(next);
+            if next == '\\' && token.starts_with(MAP_PREFIX) {
+                token.extend(chars.next());
+            }
+        }
+        pieces.push(Piece::Token(token));
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    pieces
+}
+
+fn resolve_token(
+    token: &str,
+    info: &FileInfo,
+    captures: &HashMap<String, String>,
+    now: DateTime<Utc>,
+    counter: u32,
+    default_zone: Option<Tz>,
+) -> String {
+    if let Ok(index) = token.parse::<usize>() {
+        return captures
+            .get(&index.to_string())
+            .cloned()
+            .unwrap_or_default();
+    }
+    if let Some(spec) = token.strip_prefix(MAP_PREFIX) {
+        return resolve_map(spec, info);
+    }
+
+    let (token, zone) = split_timezone(token, default_zone);
+    let (key, format) = token.split_once(':').unwrap_or((token, ""));
+    let zone = zone.unwrap_or(Tz::UTC);
+    let modified = info.modified.with_timezone(&zone);
+
+    match key {
+        // File info
+        "name" => info.name.clone(),
+        "ext" => info.extension.clone(),
+        "fullname" => info.full_name.clone(),
+        "parent" => info.parent.clone().unwrap_or_default(),
+        "size" => format_size(info.size, format),
+        
+        // Custom date formatting
+        "created" => format_date(info.created.with_timezone(&zone), format),
+        "modified" => format_date(modified, format),
+        "added" => format_date(info.added.with_timezone(&zone), format),
+        "now" => format_date(now.with_timezone(&zone), format),
+        
+        // Shorthand date/time using file's modification date
+        "date" => modified.format("%Y-%m-%d").to_string(),
+        "time" => modified.format("%H-%M-%S").to_string(),
+        
+        // Individual date components (from file's modification date)
+        "year" => modified.format("%Y").to_string(),
+        "month" => modified.format("%m").to_string(),
+        "day" => modified.format("%d").to_string(),
+        "hour" => modified.format("%H").to_string(),
+        "minute" => modified.format("%M").to_string(),
+        "second" => modified.format("%S").to_string(),
+        "week" => modified.format("%V").to_string(),
+        
+        // Named date components with short/long support
+        "weekday" => format_weekday(modified, format),
+        "monthname" => format_monthname(modified, format),
+        
+        // Classifier prediction (set by the ClassifiedAs condition)
+        "predicted_category" | "prediction_score" => captures.get(key).cloned().unwrap_or_default(),
+
+        // Average OCR confidence (set by a Contents condition that matched OCR text)
+        "ocr_confidence" => captures.get(key).cloned().unwrap_or_default(),
+
+        // Archive listing (set by the ArchiveContains condition)
+        "archive_entry_count" | "archive_match" => captures.get(key).cloned().unwrap_or_default(),
+
+        // The rule's retention class
+        "classification" => captures.get(key).cloned().unwrap_or_default(),
+
+        // Detected faces (set by the FaceCount condition)
+        "face_count" => captures.get(key).cloned().unwrap_or_default(),
+
+        // Pixel size (set by the ImageDimensions condition)
+        "image_width" | "image_height" => captures.get(key).cloned().unwrap_or_default(),
+
+        // PDF pages (set by the PageCount condition)
+        "page_count" => captures.get(key).cloned().unwrap_or_default(),
+
+        // Length in seconds and codec (set by the MediaDuration and MediaCodec conditions)
+        "media_duration" | "media_codec" => captures.get(key).cloned().unwrap_or_default(),
+
+        // The copy an Is Duplicate condition found
+        "duplicate_of" => captures.get(key).cloned().unwrap_or_default(),
+
+        // Finder tag name (set by the Tag condition)
+        "tag" => captures.get(key).cloned().unwrap_or_default(),
+
+        // The list entry a string condition's "is one of" matched
+        "one_of" => captures.get(key).cloned().unwrap_or_default(),
+
+        // Arrival group, in folders that group files arriving together
+        "group_id" => info.arrival.as_ref().map(|a| a.id.clone()).unwrap_or_default(),
+        "group_size" => info.arrival.as_ref().map_or(1, |a| a.size).to_string(),
+
+        // Application in front when the file's event came in
+        "active_app" => info
+            .active_app
+            .as_ref()
+            .and_then(ActiveApp::name)
+            .unwrap_or_default()
+            .to_string(),
+
+        // How the file reached the engine, and where under its folder it was
+        "event_source" => info.event_source.as_str().to_string(),
+        "original_subdir" => info.original_subdir.clone(),
+
+        // Existing file (set by the ExistsAtDestination condition)
+        "collision_path" | "collision_same_content" => {
+            captures.get(key).cloned().unwrap_or_default()
+        }
+
+        // CSV cell (set by the CsvColumn condition): {csv:account} or {csv:3}
+        "csv" => captures
+            .get(&format!("csv:{}", format.trim().to_lowercase()))
+            .cloned()
+            .unwrap_or_default(),
+
+        // Utilities
+        "counter" => {
+            let number = captures
+                .get(COUNTER_CAPTURE)
+                .and_then(|number| number.parse().ok())
+                .unwrap_or(u64::from(counter));
+            format_counter(number, format)
+        }
+        "random" => format_random(format),
+        
+        // Unknown token - return empty
+        _ => String::new(),
+    }
+}
+
+/// Peels an `@Zone` (or `@default`) suffix off a date token. Unknown zones are
+/// dropped, so the token renders in UTC as it would without a suffix.
+fn split_timezone(token: &str, default_zone: Option<Tz>) -> (&str, Option<Tz>) {
+    let Some((rest, name)) = token.rsplit_once('@') else {
+        return (token, None);
+    };
+    if !DATE_TOKENS.contains(&token_key(rest)) {
+        return (token, None);
+    }
+    let zone = if name == DEFAULT_ZONE {
+        default_zone
+    } else {
+        parse_timezone(name).ok()
+    };
+    (rest, zone)
+}
+
+fn token_key(token: &str) -> &str {
+    token.split_once(':').map_or(token, |(key, _)| key)
+}
+
+/// Zone names given as `@Zone` on date tokens in `pattern`, for validation.
+pub(crate) fn token_timezones(pattern: &str) -> Vec<&str> {
+    pattern
+        .split('{')
+        .skip(1)
+        .filter_map(|part| {
+            let token = part.split('}').next().unwrap_or(part);
+            let (rest, name) = token.rsplit_once('@')?;
+            DATE_TOKENS.contains(&token_key(rest)).then_some(name)
+        })
+        .collect()
+}
+
+/// Checks every mapping token in `pattern`, for validation.
+pub(crate) fn validate_map_tokens(pattern: &str) -> Result<(), String> {
+    for piece in split_pattern(pattern) {
+        if let Piece::Token(token) = piece {
+            if let Some(spec) = token.strip_prefix(MAP_PREFIX) {
+                parse_map(spec).map_err(|err| format!("{{{token}}}: {err}"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the `strftime` format of every date token in `pattern`, for
+/// validation.
+pub(crate) fn validate_date_formats(pattern: &str) -> Result<(), String> {
+    for piece in split_pattern(pattern) {
+        let Piece::Token(token) = piece else {
+            continue;
+        };
+        let (rest, _) = split_timezone(&token, None);
+        let Some((key, format)) = rest.split_once(':') else {
+            continue;
+        };
+        if STRFTIME_TOKENS.contains(&key)
+            && !matches!(format, "short" | "long")
+            && !is_valid_date_format(format)
+        {
+            return Err(format!(
+                "{{{token}}}: \"{format}\" isn't a date format; use specifiers like %Y, %m and %d"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn is_valid_date_format(format: &str) -> bool {
+    StrftimeItems::new(format).all(|item| !matches!(item, Item::Error))
+}
+
+/// A parsed `{map:property:case=value,...,*=fallback}` token.
+#[derive(Debug, Clone, PartialEq)]
+struct ValueMap {
+    property: MapProperty,
+    cases: Vec<(MapCase, String)>,
+    fallback: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MapProperty {
+    Kind,
+    Extension,
+    SizeClass,
+}
+
+impl MapProperty {
+    fn name(self) -> &'static str {
+        match self {
+            MapProperty::Kind => "kind",
+            MapProperty::Extension => "extension",
+            MapProperty::SizeClass => "sizeclass",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum MapCase {
+    Kinds(Vec<FileKind>),
+    /// Lowercase, without the dot.
+    Extensions(Vec<String>),
+    Size(SizeBound, u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SizeBound {
+    Below,
+    AtMost,
+    AtLeast,
+    Above,
+}
+
+impl MapCase {
+    fn matches(&self, info: &FileInfo) -> bool {
+        match self {
+            MapCase::Kinds(kinds) => kinds.contains(&info.kind),
+            MapCase::Extensions(extensions) => {
+                extensions.iter().any(|ext| ext.eq_ignore_ascii_case(&info.extension))
+            }
+            MapCase::Size(bound, bytes) => match bound {
+                SizeBound::Below => info.size < *bytes,
+                SizeBound::AtMost => info.size <= *bytes,
+                SizeBound::AtLeast => info.size >= *bytes,
+                SizeBound::Above => info.size > *bytes,
+            },
+        }
+    }
+}
+
+fn resolve_map(spec: &str, info: &FileInfo) -> String {
+    let Ok(map) = parse_map(spec) else {
+        return String::new();
+    };
+    let (value, fallback) = match map.cases.iter().find(|(case, _)| case.matches(info)) {
+        Some((_, value)) => (value, false),
+        None => (&map.fallback, true),
+    };
+    MAP_TRACE.with(|trace| {
+        if let Some(trace) = trace.borrow_mut().as_mut() {
+            let actual = match map.property {
+                MapProperty::Kind => kind_name(&info.kind).to_string(),
+                MapProperty::Extension => info.extension.clone(),
+                MapProperty::SizeClass => format_size(info.size, ""),
+            };
+            let fallback = if fallback { " (fallback)" } else { "" };
+            trace.push(format!("{{map:{}}} {actual} → {value}{fallback}", map.property.name()));
+        }
+    });
+    value.clone()
+}
+
+fn parse_map(spec: &str) -> Result<ValueMap, String> {
+    let (property, cases) = spec
+        .split_once(':')
+        .ok_or("needs a property and cases, e.g. {map:kind:Image=Pictures,*=Other}")?;
+    let property = match property.trim().to_lowercase().as_str() {
+        "kind" => MapProperty::Kind,
+        "extension" | "ext" => MapProperty::Extension,
+        "sizeclass" => MapProperty::SizeClass,
+        other => {
+            return Err(format!("unknown property \"{other}\"; use kind, extension or sizeclass"))
+        }
+    };
+
+    let mut parsed = Vec::new();
+    let mut fallback = None;
+    for case in split_unescaped(cases, ',') {
+        if case.trim().is_empty() {
+            continue;
+        }
+        let Some((when, value)) = split_case(case) else {
+            return Err(format!("case \"{}\" needs a value after =", unescape(case).trim()));
+        };
+        let when = unescape(when).trim().to_string();
+        let value = unescape(value).trim().to_string();
+        if fallback.is_some() {
+            return Err("the * fallback must be the last case".to_string());
+        }
+        if when == "*" {
+            fallback = Some(value);
+        } else {
+            parsed.push((parse_case(property, &when)?, value));
+        }
+    }
+    let fallback = fallback.ok_or("needs a * fallback as its last case, e.g. *=Other")?;
+    Ok(ValueMap {
+        property,
+        cases: parsed,
+        fallback,
+    })
+}
+
+fn parse_case(property: MapProperty, when: &str) -> Result<MapCase, String> {
+    let names = || when.split('|').map(str::trim).filter(|name| !name.is_empty());
+    match property {
+        MapProperty::Kind => {
+            let kinds = names()
+                .map(|name| {
+                    KIND_NAMES
+                        .iter()
+                        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+                        .map(|(_, kind)| kind.clone())
+                        .ok_or_else(|| format!("unknown kind \"{name}\"; use {}", kind_list()))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if kinds.is_empty() {
+                return Err("a kind case needs a kind before =".to_string());
+            }
+            Ok(MapCase::Kinds(kinds))
+        }
+        MapProperty::Extension => {
+            let extensions: Vec<_> = names()
+                .map(|name| name.trim_start_matches('.').to_lowercase())
+                .collect();
+            if extensions.is_empty() {
+                return Err("an extension case needs an extension before =".to_string());
+            }
+            Ok(MapCase::Extensions(extensions))
+        }
+        MapProperty::SizeClass => parse_size_case(when),
+    }
+}
+
+/// `small<10MB`: an optional label, a comparison and a size with an optional
+/// B, KB, MB or GB unit.
+fn parse_size_case(when: &str) -> Result<MapCase, String> {
+    let invalid = || format!("size case \"{when}\" should look like small<10MB or large>=1GB");
+    let start = when.find(['<', '>']).ok_or_else(invalid)?;
+    let rest = &when[start..];
+    let (bound, rest) = if let Some(rest) = rest.strip_prefix("<=") {
+        (SizeBound::AtMost, rest)
+    } else if let Some(rest) = rest.strip_prefix(">=") {
+        (SizeBound::AtLeast, rest)
+    } else if let Some(rest) = rest.strip_prefix('<') {
+        (SizeBound::Below, rest)
+    } else {
+        (SizeBound::Above, &rest[1..])
+    };
+    let rest = rest.trim();
+    let digits = rest.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(rest.len());
+    let amount: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+    let unit = match rest[digits..].trim().to_lowercase().as_str() {
+        "" | "b" => SizeUnit::Bytes,
+        "k" | "kb" => SizeUnit::Kilobytes,
+        "m" | "mb" => SizeUnit::Megabytes,
+        "g" | "gb" => SizeUnit::Gigabytes,
+        _ => return Err(invalid()),
+    };
+    Ok(MapCase::Size(bound, to_bytes(amount, &unit)))
+}
+
+fn kind_list() -> String {
+    let names: Vec<_> = KIND_NAMES.iter().map(|(name, _)| *name).collect();
+    names.join(", ")
+}
+
+fn kind_name(kind: &FileKind) -> &'static str {
+    KIND_NAMES
+        .iter()
+        .find(|(_, known)| known == kind)
+        .map_or("Other", |(name, _)| *name)
+}
+
+/// Splits on `separator` where it isn't escaped with a backslash.
+fn split_unescaped(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (index, ch) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else if ch == separator {
+            parts.push(&text[start..index]);
+            start = index + ch.len_utf8();
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Splits a case at the `=` before its value, which for sizes is the one
+/// after the comparison: `large>=10MB=Large`.
+fn split_case(case: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    let mut previous = None;
+    for (index, ch) in case.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else if ch == '=' && !matches!(previous, Some('<' | '>')) {
+            return Some((&case[..index], &case[index + 1..]));
+        }
+        previous = Some(ch);
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            out.extend(chars.next());
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+fn format_weekday(date: DateTime<Tz>, format: &str) -> String {
+    match format {
+        "long" => date.format("%A").to_string(),   // Monday
+        "short" | "" => date.format("%a").to_string(), // Mon (default)
+        _ => strftime(date, format),
+    }
+}
+
+fn format_monthname(date: DateTime<Tz>, format: &str) -> String {
+    match format {
+        "long" => date.format("%B").to_string(),   // September
+        "short" | "" => date.format("%b").to_string(), // Sep (default)
+        _ => strftime(date, format),
+    }
+}
+
+fn format_date(date: DateTime<Tz>, format: &str) -> String {
+    if format.is_empty() {
+        date.format("%Y-%m-%d").to_string()
+    } else {
+        strftime(date, format)
+    }
+}
+
+/// `date` in a user's `strftime` format. One chrono can't parse renders as
+/// an empty segment rather than panicking; saving the rule rejects it first.
+fn strftime(date: DateTime<Tz>, format: &str) -> String {
+    if is_valid_date_format(format) {
+        date.format(format).to_string()
+    } else {
+        String::new()
+    }
+}
+
+pub fn format_size(size: u64, format: &str) -> String {
+    if format == "bytes" {
+        return size.to_string();
+    }
+
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    const GB: f64 = MB * 1024.0;
+
+    let size_f = size as f64;
+    if size_f >= GB {
+        format!("{:.1} GB", size_f / GB)
+    } else if size_f >= MB {
+        format!("{:.1} MB", size_f / MB)
+    } else if size_f >= KB {
+        format!("{:.1} KB", size_f / KB)
+    } else {
+        format!("{} B", size)
+    }
+}
+
+/// Whether `pattern` has a `{key}` token, with or without a format.
+pub fn uses_token(pattern: &str, key: &str) -> bool {
+    split_pattern(pattern).iter().any(|piece| match piece {
+        Piece::Token(token) => token.split([':', '@']).next() == Some(key),
+        Piece::Text(_) => false,
+    })
+}
+
+/// `{counter:3}` pads to three digits, as does `{counter:000}`.
+fn format_counter(counter: u64, format: &str) -> String {
+    if format.is_empty() {
+        counter.to_string()
+    } else if format.bytes().all(|byte| byte == b'0') {
+        format!("{:0width$}", counter, width = format.len())
+    } else if let Ok(width) = format.parse::<usize>() {
+        format!("{:0width$}", counter, width = width)
+    } else {
+        counter.to_string()
+    }
+}
+
+fn format_random(format: &str) -> String {
+    let random = Uuid::new_v4().to_string().replace('-', "");
+    if format.is_empty() {
+        random
+    } else if let Ok(len) = format.parse::<usize>() {
+        random.chars().take(len).collect()
+    } else {
+        random
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        trace_map_branches, uses_token, validate_date_formats, validate_map_tokens, PatternEngine,
+        COUNTER_CAPTURE,
+    };
+    use crate::models::FileKind;
+    use crate::file_info::FileInfo;
+    use chrono::{TimeZone, Utc};
+    use std::collections::HashMap;
+    fn sample_info() -> FileInfo {
+        let temp_path = std::env::temp_dir().join("example.txt");
+        let parent_name = temp_path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|s| s.to_str())
+            .unwrap_or("temp")
+            .to_string();
+        FileInfo {
+            path: temp_path.clone(),
+            name: "example".to_string(),
+            extension: "txt".to_string(),
+            full_name: "example.txt".to_string(),
+            os_name: "example.txt".into(),
+            size: 2048,
+            created: Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap(),
+            modified: Utc.with_ymd_and_hms(2024, 1, 3, 4, 5, 6).unwrap(),
+            added: Utc.with_ymd_and_hms(2024, 1, 4, 5, 6, 7).unwrap(),
+            kind: FileKind::File,
+            parent: Some(parent_name),
+            is_dir: false,
+            hash: "hash".to_string(),
+            is_placeholder: false,
+            last_matched: None,
+            arrival: None,
+            active_app: None,
+            event_source: crate::models::EventSource::Live,
+            original_subdir: String::new(),
+        }
+    }
+
+    #[test]
+    fn resolves_basic_tokens() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{name}.{ext}-{parent}", &info, &captures);
+        let expected_parent = info.parent.clone().unwrap_or_default();
+        assert_eq!(result, format!("example.txt-{}", expected_parent));
+    }
+
+    #[test]
+    fn resolves_counter_and_size_formats() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let first = engine.resolve("{counter:3}-{size:bytes}", &info, &captures);
+        let second = engine.resolve("{counter:3}-{size}", &info, &captures);
+
+        assert_eq!(first, "001-2048");
+        assert_eq!(second, "002-2.0 KB");
+    }
+
+    #[test]
+    fn resolves_captures_and_random_length() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let mut captures = HashMap::new();
+        captures.insert("0".to_string(), "alpha".to_string());
+        captures.insert("1".to_string(), "beta".to_string());
+
+        let result = engine.resolve("{0}-{1}-{random:8}", &info, &captures);
+        let parts: Vec<&str> = result.split('-').collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], "alpha");
+        assert_eq!(parts[1], "beta");
+        assert_eq!(parts[2].len(), 8);
+    }
+
+    #[test]
+    fn resolves_prediction_tokens() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let mut captures = HashMap::new();
+        captures.insert("predicted_category".to_string(), "Invoices".to_string());
+        captures.insert("prediction_score".to_string(), "0.82".to_string());
+
+        let result = engine.resolve("Archive/{predicted_category}/{prediction_score}", &info, &captures);
+        assert_eq!(result, "Archive/Invoices/0.82");
+        assert_eq!(engine.resolve("{predicted_category}", &info, &HashMap::new()), "");
+    }
+
+    #[test]
+    fn resolves_ocr_confidence_token() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let mut captures = HashMap::new();
+        captures.insert("ocr_confidence".to_string(), "0.91".to_string());
+
+        assert_eq!(engine.resolve("OCR {ocr_confidence}", &info, &captures), "OCR 0.91");
+        assert_eq!(engine.resolve("{ocr_confidence}", &info, &HashMap::new()), "");
+    }
+
+    #[test]
+    fn resolves_archive_tokens() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let mut captures = HashMap::new();
+        captures.insert("archive_entry_count".to_string(), "12".to_string());
+        captures.insert("archive_match".to_string(), "cad/plan.dwg".to_string());
+
+        let result = engine.resolve("{archive_entry_count} files, first {archive_match}", &info, &captures);
+        assert_eq!(result, "12 files, first cad/plan.dwg");
+    }
+
+    #[test]
+    fn resolves_csv_tokens_by_header_or_index() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let mut captures = HashMap::new();
+        captures.insert("csv:account".to_string(), "ACC-7".to_string());
+        captures.insert("csv:3".to_string(), "EUR".to_string());
+
+        let result = engine.resolve("{csv:Account}-{csv:3}-{csv:memo}", &info, &captures);
+        assert_eq!(result, "ACC-7-EUR-");
+    }
+
+    #[test]
+    fn resolves_arrival_group_tokens() {
+        let engine = PatternEngine::new();
+        let mut info = sample_info();
+        let pattern = "Inbox/{group_id}-{group_size}";
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Inbox/-1");
+
+        info.arrival = Some(crate::models::Arrival {
+            id: "3f9a0c1e".to_string(),
+            size: 3,
+        });
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Inbox/3f9a0c1e-3");
+    }
+
+    #[test]
+    fn resolves_active_app_token() {
+        let engine = PatternEngine::new();
+        let mut info = sample_info();
+        assert_eq!(engine.resolve("Exports/{active_app}", &info, &HashMap::new()), "Exports/");
+
+        info.active_app = Some(crate::models::ActiveApp::Known {
+            name: "FreeCAD".to_string(),
+            identifier: Some("org.freecad.FreeCAD".to_string()),
+        });
+        assert_eq!(
+            engine.resolve("Exports/{active_app}", &info, &HashMap::new()),
+            "Exports/FreeCAD"
+        );
+    }
+
+    #[test]
+    fn resolves_event_source_and_original_subdir_tokens() {
+        let engine = PatternEngine::new();
+        let pattern = "~/Archive/{event_source}/{original_subdir}";
+        let mut info = sample_info();
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "~/Archive/live/");
+
+        info.event_source = crate::models::EventSource::Catchup;
+        info.original_subdir = "2023/taxes".to_string();
+        assert_eq!(
+            engine.resolve(pattern, &info, &HashMap::new()),
+            "~/Archive/catchup/2023/taxes"
+        );
+    }
+
+    #[test]
+    fn resolves_face_count_token() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let mut captures = HashMap::new();
+        captures.insert("face_count".to_string(), "2".to_string());
+
+        assert_eq!(engine.resolve("People/{face_count}", &info, &captures), "People/2");
+        assert_eq!(engine.resolve("People/{face_count}", &info, &HashMap::new()), "People/");
+    }
+
+    // ==================== DATE/TIME TOKENS ====================
+
+    #[test]
+    fn resolves_date_components() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{year}-{month}-{day}", &info, &captures);
+        assert_eq!(result, "2024-01-03");
+    }
+
+    #[test]
+    fn resolves_time_components() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{hour}-{minute}-{second}", &info, &captures);
+        assert_eq!(result, "04-05-06");
+    }
+
+    #[test]
+    fn resolves_date_shorthand() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{date}_{time}", &info, &captures);
+        assert_eq!(result, "2024-01-03_04-05-06");
+    }
+
+    #[test]
+    fn resolves_week_number() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("week-{week}", &info, &captures);
+        assert_eq!(result, "week-01");
+    }
+
+    #[test]
+    fn resolves_weekday_short() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{weekday}", &info, &captures);
+        assert_eq!(result, "Wed");
+    }
+
+    #[test]
+    fn resolves_weekday_long() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{weekday:long}", &info, &captures);
+        assert_eq!(result, "Wednesday");
+    }
+
+    #[test]
+    fn resolves_monthname_short() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{monthname}", &info, &captures);
+        assert_eq!(result, "Jan");
+    }
+
+    #[test]
+    fn resolves_monthname_long() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{monthname:long}", &info, &captures);
+        assert_eq!(result, "January");
+    }
+
+    #[test]
+    fn resolves_created_date() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{created}", &info, &captures);
+        assert_eq!(result, "2024-01-02");
+    }
+
+    #[test]
+    fn resolves_created_custom_format() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{created:%Y%m%d}", &info, &captures);
+        assert_eq!(result, "20240102");
+    }
+
+    #[test]
+    fn resolves_dated_folders() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("/Photos/{created:%Y}/{modified:%Y/%m/%d}", &info, &captures);
+        assert_eq!(result, "/Photos/2024/2024/01/03");
+    }
+
+    #[test]
+    fn invalid_date_formats_render_empty_and_are_rejected() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("/Photos/{created:%Q}/{monthname:%}", &info, &captures);
+        assert_eq!(result, "/Photos//");
+        assert!(validate_date_formats("{created:%Y-%m@UTC}/{weekday:long}/{name:%Q}").is_ok());
+        let err = validate_date_formats("/Photos/{modified:%Y/%Q}").unwrap_err();
+        assert!(err.starts_with("{modified:%Y/%Q}: \"%Y/%Q\" isn't a date format"));
+    }
+
+    #[test]
+    fn resolves_modified_date() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{modified}", &info, &captures);
+        assert_eq!(result, "2024-01-03");
+    }
+
+    #[test]
+    fn resolves_added_date() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{added}", &info, &captures);
+        assert_eq!(result, "2024-01-04");
+    }
+
+    // ==================== TIME ZONE SUFFIX ====================
+
+    #[test]
+    fn date_tokens_render_in_the_named_zone() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        // Modified 2024-01-03 04:05 UTC is still the evening before in Los Angeles.
+        let result = engine.resolve("{date@America/Los_Angeles}_{hour@America/Los_Angeles}", &info, &captures);
+        assert_eq!(result, "2024-01-02_20");
+        let result = engine.resolve("{created:%Y-%m-%d %H:%M@Asia/Tokyo}", &info, &captures);
+        assert_eq!(result, "2024-01-02 12:04");
+        // Unsuffixed tokens keep rendering in UTC.
+        assert_eq!(engine.resolve("{date}", &info, &captures), "2024-01-03");
+    }
+
+    #[test]
+    fn nonexistent_local_time_renders_after_the_spring_forward_gap() {
+        let engine = PatternEngine::new();
+        let mut info = sample_info();
+        let captures = HashMap::new();
+
+        // London skips 01:00–02:00 on 2026-03-29; 01:30 UTC is 02:30 BST there.
+        info.created = Utc.with_ymd_and_hms(2026, 3, 29, 1, 30, 0).unwrap();
+        let result = engine.resolve("{created:%Y-%m-%d %H:%M %Z@Europe/London}", &info, &captures);
+        assert_eq!(result, "2026-03-29 02:30 BST");
+        info.created = Utc.with_ymd_and_hms(2026, 3, 29, 0, 30, 0).unwrap();
+        let result = engine.resolve("{created:%H:%M %Z@Europe/London}", &info, &captures);
+        assert_eq!(result, "00:30 GMT");
+    }
+
+    #[test]
+    fn default_suffix_follows_the_configured_zone() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        assert_eq!(engine.resolve("{hour@default}", &info, &captures), "04");
+        engine.set_default_timezone(Some("Asia/Kolkata"));
+        assert_eq!(engine.resolve("{hour@default}:{minute@default}", &info, &captures), "09:35");
+        assert_eq!(engine.resolve("{hour}", &info, &captures), "04");
+    }
+
+    #[test]
+    fn token_timezones_lists_only_date_token_zones() {
+        assert_eq!(
+            super::token_timezones("{name}@home/{created:%Y@UTC}/{date@default}/{name@x}"),
+            vec!["UTC", "default"]
+        );
+    }
+
+    // ==================== SIZE TOKENS ====================
+
+    #[test]
+    fn resolves_size_human_readable() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{size}", &info, &captures);
+        assert_eq!(result, "2.0 KB");
+    }
+
+    #[test]
+    fn resolves_size_bytes() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{size:bytes}", &info, &captures);
+        assert_eq!(result, "2048");
+    }
+
+    // ==================== FULLNAME TOKEN ====================
+
+    #[test]
+    fn resolves_fullname() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{fullname}", &info, &captures);
+        assert_eq!(result, "example.txt");
+    }
+
+    // ==================== COUNTER TOKENS ====================
+
+    #[test]
+    fn counter_increments() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let first = engine.resolve("{counter}", &info, &captures);
+        let second = engine.resolve("{counter}", &info, &captures);
+        let third = engine.resolve("{counter}", &info, &captures);
+
+        assert_eq!(first, "1");
+        assert_eq!(second, "2");
+        assert_eq!(third, "3");
+    }
+
+    #[test]
+    fn counter_with_padding() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{counter:5}", &info, &captures);
+        assert_eq!(result, "00001");
+        let result = engine.resolve("invoice_{counter:0000}", &info, &captures);
+        assert_eq!(result, "invoice_0002");
+    }
+
+    #[test]
+    fn a_rules_own_counter_comes_from_its_captures() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::from([(COUNTER_CAPTURE.to_string(), "42".to_string())]);
+
+        assert_eq!(engine.resolve("{counter:0000}", &info, &captures), "0042");
+        assert_eq!(engine.resolve("{counter}", &info, &captures), "42");
+        assert!(uses_token("~/Invoices/invoice_{counter:0000}", "counter"));
+        assert!(!uses_token("~/Invoices/{counter_x}/counter", "counter"));
+    }
+
+    // ==================== RANDOM TOKENS ====================
+
+    #[test]
+    fn random_default_length() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{random}", &info, &captures);
+        assert_eq!(result.len(), 32); // UUID without dashes
+    }
+
+    #[test]
+    fn random_custom_length() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("{random:4}", &info, &captures);
+        assert_eq!(result.len(), 4);
+    }
+
+    // ==================== UNKNOWN TOKENS ====================
+
+    #[test]
+    fn unknown_token_returns_empty() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("prefix-{unknown}-suffix", &info, &captures);
+        assert_eq!(result, "prefix--suffix");
+    }
+
+    // ==================== PLAIN TEXT ====================
+
+    #[test]
+    fn plain_text_passes_through() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("just_plain_text", &info, &captures);
+        assert_eq!(result, "just_plain_text");
+    }
+
+    // ==================== REGEX CAPTURES ====================
+
+    #[test]
+    fn numeric_capture_resolution() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let mut captures = HashMap::new();
+        captures.insert("1".to_string(), "2024".to_string());
+        captures.insert("2".to_string(), "report".to_string());
+
+        let result = engine.resolve("{name}_{1}_{2}.{ext}", &info, &captures);
+        assert_eq!(result, "example_2024_report.txt");
+    }
+
+    #[test]
+    fn missing_capture_returns_empty() {
+        let engine = PatternEngine::new();
+        let info = sample_info();
+        let captures = HashMap::new();
+
+        let result = engine.resolve("prefix-{1}-suffix", &info, &captures);
+        assert_eq!(result, "prefix--suffix");
+    }
+
+    const KIND_MAP: &str = "{map:kind:Image=Pictures,Video=Videos,Document=Documents,*=Other}";
+
+    #[test]
+    fn map_by_kind() {
+        let engine = PatternEngine::new();
+        let mut info = sample_info();
+        let captures = HashMap::new();
+
+        info.kind = FileKind::Image;
+        assert_eq!(engine.resolve(KIND_MAP, &info, &captures), "Pictures");
+        info.kind = FileKind::Document;
+        assert_eq!(engine.resolve(KIND_MAP, &info, &captures), "Documents");
+    }
+
+    #[test]
+    fn map_falls_back_when_no_case_matches() {
+        let engine = PatternEngine::new();
+        let mut info = sample_info();
+        info.kind = FileKind::Audio;
+
+        assert_eq!(engine.resolve(KIND_MAP, &info, &HashMap::new()), "Other");
+    }
+
+    #[test]
+    fn map_by_extension() {
+        let engine = PatternEngine::new();
+        let mut info = sample_info();
+        let pattern = "{map:extension:pdf|.DOCX=Docs,txt=Notes,*=Other}";
+
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Notes");
+        info.extension = "docx".to_string();
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Docs");
+        info.extension = "zip".to_string();
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Other");
+    }
+
+    #[test]
+    fn map_by_size_class() {
+        let engine = PatternEngine::new();
+        let mut info = sample_info();
+        let pattern = "{map:sizeclass:small<10MB=Small,large>=10MB=Large,*=Other}";
+
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Small");
+        info.size = 10 * 1024 * 1024;
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Large");
+
+        let pattern = "{map:sizeclass:<=2KB=Tiny,>1gb=Huge,*=Medium}";
+        info.size = 2048;
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Tiny");
+        info.size = 2049;
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Medium");
+    }
+
+    #[test]
+    fn map_values_keep_slashes_and_escaped_separators() {
+        let engine = PatternEngine::new();
+        let mut info = sample_info();
+        info.kind = FileKind::Image;
+        let pattern = r"{map:kind:Image=Media/Pictures\, raw,*=Misc\}}/x";
+
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Media/Pictures, raw/x");
+        info.kind = FileKind::Code;
+        assert_eq!(engine.resolve(pattern, &info, &HashMap::new()), "Misc}/x");
+    }
+
+    #[test]
+    fn map_combines_with_captures_and_date_tokens() {
+        let engine = PatternEngine::new();
+        let mut info = sample_info();
+        info.kind = FileKind::Video;
+        let captures = HashMap::from([("1".to_string(), "trip".to_string())]);
+        let pattern = format!("~/Sorted/{KIND_MAP}/{{year}}-{{month}}/{{1}}-{{name}}.{{ext}}");
+
+        assert_eq!(
+            engine.resolve(&pattern, &info, &captures),
+            "~/Sorted/Videos/2024-01/trip-example.txt"
+        );
+    }
+
+    #[test]
+    fn map_branches_are_traced() {
+        let engine = PatternEngine::new();
+        let mut info = sample_info();
+        info.kind = FileKind::Audio;
+
+        let (resolved, branches) =
+            trace_map_branches(|| engine.resolve(KIND_MAP, &info, &HashMap::new()));
+        assert_eq!(resolved, "Other");
+        assert_eq!(branches, vec!["{map:kind} Audio → Other (fallback)"]);
+        // Nothing is collected outside a trace.
+        engine.resolve(KIND_MAP, &info, &HashMap::new());
+        assert!(trace_map_branches(|| ()).1.is_empty());
+    }
+
+    #[test]
+    fn invalid_maps_are_rejected() {
+        assert!(validate_map_tokens(&format!("~/Sorted/{KIND_MAP}/{{year}}")).is_ok());
+        for (pattern, expected) in [
+            ("{map:kind:Image=Pictures}", "* fallback"),
+            ("{map:kind:Imgae=Pictures,*=Other}", "unknown kind \"Imgae\""),
+            ("{map:colour:red=Red,*=Other}", "unknown property"),
+            ("{map:kind:*=Other,Image=Pictures}", "must be the last case"),
+            ("{map:kind:Image,*=Other}", "needs a value"),
+            ("{map:sizeclass:big=Big,*=Other}", "should look like"),
+            ("{map:sizeclass:big>10TB=Big,*=Other}", "should look like"),
+            ("{map:kind}", "needs a property and cases"),
+        ] {
+            let err = validate_map_tokens(pattern).unwrap_err();
+            assert!(err.contains(expected), "{pattern}: {err}");
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! The rule export format. Exports carry a `schemaVersion`; imports are
+//! checked strictly against it so a typo is reported instead of quietly
+//! becoming a default, files from older versions are upgraded one major
+//! version at a time, and files from a newer major version are refused.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::models::{
+    Action, Condition, ConditionGroup, OnReappear, Rule, RuleImportError, RuleImportErrorKind,
+    RuleSchedule,
+};
+use crate::unknown_fields::{first_unknown_path, merge_unknown, unknown_fields};
+
+/// Version `export_rules` writes. A new minor version may only add fields an
+/// older reader can carry along untouched; anything else needs a new major
+/// version and an entry in `UPGRADES`.
+pub const RULE_SCHEMA_VERSION: SchemaVersion = SchemaVersion { major: 2, minor: 0 };
+
+/// Bare rule lists, as exported before files were versioned.
+const LEGACY_SCHEMA_VERSION: SchemaVersion = SchemaVersion { major: 1, minor: 0 };
+
+/// `UPGRADES[n]` turns a rule from major version `n + 1` into `n + 2`.
+const UPGRADES: [fn(&mut Map<String, Value>); 1] = [upgrade_v1];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SchemaVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl SchemaVersion {
+    /// Accepts `"2.1"`, `"2"` or a bare number.
+    fn parse(value: &Value) -> Option<Self> {
+        let text = match value {
+            Value::String(text) => text.trim().to_string(),
+            Value::Number(number) => number.to_string(),
+            _ => return None,
+        };
+        let (major, minor) = text.split_once('.').unwrap_or((&text, "0"));
+        Some(Self {
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+        })
+    }
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RuleFile {
+    schema_version: Value,
+    rules: Vec<Value>,
+}
+
+/// A rule as exported: what carries over to another folder or machine. Ids,
+/// folder, position and timestamps are assigned on import.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedRule {
+    name: String,
+    enabled: bool,
+    stop_processing: bool,
+    conditions: ConditionGroup,
+    actions: Vec<Action>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schedule: Option<RuleSchedule>,
+    #[serde(default)]
+    approval_required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_age_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path_scope: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    skip_reference_check: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    monthly_byte_budget: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    classification: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_reappear: Option<OnReappear>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    record_metadata: HashMap<String, String>,
+}
+
+impl From<&Rule> for ExportedRule {
+    fn from(rule: &Rule) -> Self {
+        Self {
+            name: rule.name.clone(),
+            enabled: rule.enabled,
+            stop_processing: rule.stop_processing,
+            conditions: rule.conditions.clone(),
+            actions: rule.actions.clone(),
+            schedule: rule.schedule.clone(),
+            approval_required: rule.approval_required,
+            min_age_seconds: rule.min_age_seconds,
+            path_scope: rule.path_scope.clone(),
+            skip_reference_check: rule.skip_reference_check,
+            monthly_byte_budget: rule.monthly_byte_budget,
+            classification: rule.classification.clone(),
+            on_reappear: rule.on_reappear.clone(),
+            record_metadata: rule.record_metadata.clone(),
+        }
+    }
+}
+
+impl ExportedRule {
+    fn into_rule(self, extra: Value) -> Rule {
+        let now = chrono::Utc::now();
+        Rule {
+            id: String::new(),
+            folder_id: String::new(),
+            name: self.name,
+            enabled: self.enabled,
+            stop_processing: self.stop_processing,
+            conditions: self.conditions,
+            actions: self.actions,
+            position: 0,
+            schedule: self.schedule,
+            approval_required: self.approval_required,
+            min_age_seconds: self.min_age_seconds,
+            path_scope: self.path_scope,
+            skip_reference_check: self.skip_reference_check,
+            monthly_byte_budget: self.monthly_byte_budget,
+            classification: self.classification,
+            on_reappear: self.on_reappear,
+            record_metadata: self.record_metadata,
+            revision: 0,
+            extra,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Serializes `rules` as a current-version rule file, fields from newer
+/// versions included.
+pub fn export_rules(rules: &[Rule]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&RuleFile {
+        schema_version: Value::String(RULE_SCHEMA_VERSION.to_string()),
+        rules: export_rule_values(rules)?,
+    })
+}
+
+/// The exported form of each rule, for files that embed rules alongside
+/// other data under a `schemaVersion` of `RULE_SCHEMA_VERSION`.
+pub fn export_rule_values(rules: &[Rule]) -> serde_json::Result<Vec<Value>> {
+    rules
+        .iter()
+        .map(|rule| {
+            let mut value = serde_json::to_value(ExportedRule::from(rule))?;
+            merge_unknown(&mut value, &rule.extra);
+            Ok(value)
+        })
+        .collect()
+}
+
+/// Reads a rule file (JSON or YAML, versioned or legacy) into rules without
+/// ids or a folder. Fields this version doesn't know are errors, except in a
+/// file from a newer minor version, where they're kept in `Rule::extra`.
+pub fn import_rules(payload: &str) -> Result<Vec<Rule>, RuleImportError> {
+    let trimmed = payload.trim();
+    if trimmed.is_empty() {
+        return Err(RuleImportError::invalid("Rule import file is empty."));
+    }
+    let document: Value = if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        serde_json::from_str(trimmed).map_err(|e| RuleImportError::invalid(e.to_string()))?
+    } else {
+        serde_yaml::from_str(trimmed).map_err(|e| RuleImportError::invalid(e.to_string()))?
+    };
+
+    let (version, rules) = match document {
+        Value::Object(ref object) if object.contains_key("schemaVersion") => {
+            let file = RuleFile::deserialize(&document)
+                .map_err(|e| RuleImportError::invalid(e.to_string()))?;
+            return import_rule_values(&file.schema_version, file.rules);
+        }
+        Value::Array(rules) => (LEGACY_SCHEMA_VERSION, rules),
+        rule @ Value::Object(_) => (LEGACY_SCHEMA_VERSION, vec![rule]),
+        _ => {
+            return Err(RuleImportError::invalid(
+                "Rule import file must contain a rule or a list of rules.",
+            ))
+        }
+    };
+    import_versioned(version, rules)
+}
+
+/// Reads rules embedded in another file under `schema_version`, with the
+/// same upgrades and checks as `import_rules`.
+pub fn import_rule_values(
+    schema_version: &Value,
+    rules: Vec<Value>,
+) -> Result<Vec<Rule>, RuleImportError> {
+    let version = SchemaVersion::parse(schema_version).ok_or_else(|| {
+        RuleImportError::invalid(format!("\"{schema_version}\" isn't a schema version"))
+            .at("schemaVersion")
+    })?;
+    import_versioned(version, rules)
+}
+
+fn import_versioned(
+    version: SchemaVersion,
+    rules: Vec<Value>,
+) -> Result<Vec<Rule>, RuleImportError> {
+    if version.major > RULE_SCHEMA_VERSION.major {
+        return Err(RuleImportError::new(
+            RuleImportErrorKind::UnsupportedVersion,
+            format!(
+                "These rules use schema version {version}, from a newer version of File \
+                 Dispatch. This version reads up to {}.x.",
+                RULE_SCHEMA_VERSION.major
+            ),
+        ));
+    }
+    let keep_unknown =
+        version.major == RULE_SCHEMA_VERSION.major && version.minor > RULE_SCHEMA_VERSION.minor;
+
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            import_rule(rule, version, keep_unknown)
+                .map_err(|err| prefix_path(err, &format!("rules[{index}]")))
+        })
+        .collect()
+}
+
+fn import_rule(
+    rule: Value,
+    version: SchemaVersion,
+    keep_unknown: bool,
+) -> Result<Rule, RuleImportError> {
+    let Value::Object(mut object) = rule else {
+        return Err(RuleImportError::invalid("expected a rule object"));
+    };
+    for upgrade in &UPGRADES[(version.major.max(1) - 1) as usize..] {
+        upgrade(&mut object);
+    }
+    let value = Value::Object(object);
+
+    let exported = ExportedRule::deserialize(&value).map_err(|err| locate_error(&value, err))?;
+    let known = serde_json::to_value(&exported)
+        .map_err(|e| RuleImportError::new(RuleImportErrorKind::Failed, e.to_string()))?;
+    let unknown = unknown_fields(&value, &known);
+    if unknown.is_null() || keep_unknown {
+        return Ok(exported.into_rule(unknown));
+    }
+    let path = first_unknown_path(&unknown).unwrap_or_default();
+    Err(RuleImportError::invalid("unknown field").at(path))
+}
+
+/// Version 1 files were plain dumps of stored rules; the fields tied to the
+/// exporting machine are dropped.
+fn upgrade_v1(rule: &mut Map<String, Value>) {
+    for key in ["id", "folderId", "position", "createdAt", "updatedAt"] {
+        rule.remove(key);
+    }
+}
+
+fn prefix_path(mut err: RuleImportError, prefix: &str) -> RuleImportError {
+    err.path = Some(match err.path.take() {
+        Some(path) if !path.is_empty() => format!("{prefix}.{path}"),
+        _ => prefix.to_string(),
+    });
+    err
+}
+
+/// Narrows a failure to parse `rule` down to the condition or action (and,
+/// where serde names it, the field) it comes from.
+fn locate_error(rule: &Value, error: serde_json::Error) -> RuleImportError {
+    if let Some(conditions) = rule.get("conditions") {
+        if let Some(err) = locate_in_group(conditions, "conditions") {
+            return err;
+        }
+    }
+    if let Some(Value::Array(actions)) = rule.get("actions") {
+        for (index, action) in actions.iter().enumerate() {
+            if let Err(err) = Action::deserialize(action) {
+                return element_error(&format!("actions[{index}]"), action, err);
+            }
+        }
+    }
+    RuleImportError::invalid(error.to_string())
+}
+
+fn locate_in_group(group: &Value, path: &str) -> Option<RuleImportError> {
+    let error = ConditionGroup::deserialize(group).err()?;
+    if let Some(Value::Array(conditions)) = group.get("conditions") {
+        for (index, condition) in conditions.iter().enumerate() {
+            let path = format!("{path}.conditions[{index}]");
+            if condition.get("type").and_then(Value::as_str) == Some("nested") {
+                if let Some(err) = locate_in_group(condition, &path) {
+                    return Some(err);
+                }
+            } else if let Err(err) = Condition::deserialize(condition) {
+                return Some(element_error(&path, condition, err));
+            }
+        }
+    }
+    Some(RuleImportError::invalid(error.to_string()).at(path))
+}
+
+/// serde names the offending field for missing fields, and the offending
+/// value for unknown variants; either is enough to point at the field.
+fn element_error(path: &str, element: &Value, error: serde_json::Error) -> RuleImportError {
+    let message = error.to_string();
+    let quoted = message.split('`').nth(1);
+    let field = if message.starts_with("missing field") {
+        quoted.map(str::to_string)
+    } else if message.starts_with("unknown variant") {
+        quoted.and_then(|variant| {
+            element
+                .as_object()?
+                .iter()
+                .find(|(_, value)| value.as_str() == Some(variant))
+                .map(|(key, _)| key.clone())
+        })
+    } else {
+        None
+    };
+    let path = match field {
+        Some(field) => format!("{path}.{field}"),
+        None => path.to_string(),
+    };
+    RuleImportError::invalid(message).at(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/rules")
+            .join(name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn misspelled_operator_is_reported_with_its_path() {
+        let payload = fixture("v2_misspelled_operator.json");
+        let err = import_rules(&payload).unwrap_err();
+        assert_eq!(err.kind, RuleImportErrorKind::Invalid);
+        assert_eq!(
+            err.path.as_deref(),
+            Some("rules[0].conditions.conditions[1].operator")
+        );
+        assert!(err.message.contains("startWith"), "{}", err.message);
+    }
+
+    #[test]
+    fn misspelled_field_is_not_defaulted() {
+        let payload = r#"{"schemaVersion": "2.0", "rules": [{
+            "name": "Invoices", "enabled": true, "stopProcessing": true,
+            "conditions": {"matchType": "all", "conditions": [
+                {"type": "name", "operator": "contains", "value": "invoice",
+                 "caseSensitive": false, "caseSensitve": true}
+            ]},
+            "actions": []
+        }]}"#;
+        let err = import_rules(payload).unwrap_err();
+        assert_eq!(
+            err.path.as_deref(),
+            Some("rules[0].conditions.conditions[0].caseSensitve")
+        );
+    }
+
+    #[test]
+    fn legacy_export_is_upgraded() {
+        let rules = import_rules(&fixture("v1_legacy.yaml")).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].name, "Screenshots");
+        assert!(rules[0].id.is_empty() && rules[0].folder_id.is_empty());
+        assert!(rules[0].extra.is_null());
+        assert!(matches!(rules[1].actions[0], Action::Delete(_)));
+        assert_eq!(rules[1].min_age_seconds, Some(3600));
+    }
+
+    #[test]
+    fn newer_major_version_is_refused() {
+        let err = import_rules(&fixture("v3_future.json")).unwrap_err();
+        assert_eq!(err.kind, RuleImportErrorKind::UnsupportedVersion);
+        assert!(err.message.contains("3.0"), "{}", err.message);
+    }
+
+    #[test]
+    fn unknown_fields_from_a_newer_minor_version_round_trip() {
+        let rules = import_rules(&fixture("v2_1_unknown_fields.json")).unwrap();
+        let rule = &rules[0];
+        assert_eq!(rule.extra["color"], "teal");
+
+        let exported: Value = serde_json::from_str(&export_rules(&rules).unwrap()).unwrap();
+        assert_eq!(exported["schemaVersion"], "2.0");
+        let exported = &exported["rules"][0];
+        assert_eq!(exported["color"], "teal");
+        assert_eq!(exported["conditions"]["conditions"][0]["fuzzy"], true);
+        assert_eq!(exported["actions"][0]["preserveTimestamps"], true);
+    }
+
+    #[test]
+    fn folder_conditions_round_trip() {
+        let conditions = serde_json::json!([
+            {"type": "parentFolder", "operator": "matches", "value": "^(\\d{4})$",
+             "caseSensitive": false},
+            {"type": "relativePath", "operator": "startsWith", "value": "clients/",
+             "caseSensitive": true}
+        ]);
+        let payload = serde_json::json!({"schemaVersion": "2.0", "rules": [{
+            "name": "Yearly", "enabled": true, "stopProcessing": true,
+            "conditions": {"matchType": "all", "conditions": conditions},
+            "actions": []
+        }]});
+        let rules = import_rules(&payload.to_string()).unwrap();
+        assert!(matches!(
+            rules[0].conditions.conditions[..],
+            [Condition::ParentFolder(_), Condition::RelativePath(_)]
+        ));
+
+        let exported: Value = serde_json::from_str(&export_rules(&rules).unwrap()).unwrap();
+        assert_eq!(exported["rules"][0]["conditions"]["conditions"], conditions);
+    }
+
+    #[test]
+    fn unknown_top_level_fields_are_rejected() {
+        let err = import_rules(r#"{"schemaVersion": "2.0", "rulez": []}"#).unwrap_err();
+        assert_eq!(err.kind, RuleImportErrorKind::Invalid);
+        assert!(err.message.contains("rulez"), "{}", err.message);
+    }
+}
@@ -0,0 +1,288 @@
+//! Telling screenshots from other images, whichever tool took them and
+//! whatever they're called. Each signal adds to a score, a camera's EXIF
+//! takes from it, and an image scoring `THRESHOLD` or more is a screenshot.
+//! The name is judged here; the app reads the rest from the file and the
+//! system into a `ScreenshotEvidence`.
+
+use std::fmt;
+
+use crate::exif::Exif;
+
+/// Score at which an image counts as a screenshot.
+pub const THRESHOLD: i32 = 60;
+
+const NAME_SCORE: i32 = 60;
+const PNG_SCORE: i32 = 20;
+const DISPLAY_SIZE_SCORE: i32 = 50;
+const DEVICE_SIZE_SCORE: i32 = 40;
+const CAMERA_SCORE: i32 = -100;
+
+/// How screenshot tools name their files, lowercase: macOS, Windows, GNOME,
+/// KDE, Android and iOS in the languages they ship in most, and CleanShot.
+const NAME_PREFIXES: &[&str] = &[
+    "screenshot",
+    "screen shot",
+    "bildschirmfoto",
+    "capture d'écran",
+    "capture d’écran",
+    "captura de pantalla",
+    "schermafbeelding",
+    "istantanea schermo",
+    "schermata",
+    "skärmavbild",
+    "skjermbilde",
+    "skærmbillede",
+    "zrzut ekranu",
+    "snímek obrazovky",
+    "снимок экрана",
+    "スクリーンショット",
+    "截屏",
+    "屏幕截图",
+    "cleanshot",
+];
+
+/// Screen sizes of common monitors, laptops, phones and tablets, landscape.
+const DEVICE_SIZES: &[(u32, u32)] = &[
+    (1280, 720),
+    (1280, 800),
+    (1366, 768),
+    (1440, 900),
+    (1536, 864),
+    (1600, 900),
+    (1680, 1050),
+    (1920, 1080),
+    (1920, 1200),
+    (2560, 1080),
+    (2560, 1440),
+    (2560, 1600),
+    (2880, 1800),
+    (3024, 1964),
+    (3440, 1440),
+    (3456, 2234),
+    (3840, 2160),
+    (5120, 2880),
+    (1334, 750),
+    (1792, 828),
+    (2340, 1080),
+    (2400, 1080),
+    (2436, 1125),
+    (2532, 1170),
+    (2556, 1179),
+    (2688, 1242),
+    (2778, 1284),
+    (2796, 1290),
+    (3200, 1440),
+    (2160, 1620),
+    (2388, 1668),
+    (2732, 2048),
+];
+
+/// What the app found out about an image beyond its name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScreenshotEvidence {
+    /// The system marked the file as a screen capture when it saved it. Only
+    /// macOS does (`kMDItemIsScreenCapture`); it settles the question.
+    pub system_mark: bool,
+    /// The file is a PNG, whatever its extension says.
+    pub png: bool,
+    /// Its EXIF names the camera that took it.
+    pub camera_exif: bool,
+    pub dimensions: Option<(u32, u32)>,
+    /// Sizes of the displays connected now, in pixels, where the system
+    /// reports them.
+    pub display_sizes: Vec<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenshotVerdict {
+    pub score: i32,
+    /// What counted for and against, in the order it was weighed.
+    pub signals: Vec<String>,
+}
+
+impl ScreenshotVerdict {
+    pub fn is_screenshot(&self) -> bool {
+        self.score >= THRESHOLD
+    }
+}
+
+impl fmt::Display for ScreenshotVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let answer = if self.is_screenshot() { "yes" } else { "no" };
+        write!(
+            f,
+            "Screenshot: {answer}, score {} of {THRESHOLD}",
+            self.score
+        )?;
+        if !self.signals.is_empty() {
+            write!(f, " ({})", self.signals.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Weighs the system's mark first, then the name against the built-in
+/// prefixes and `extra_prefixes`, then what the file itself looks like.
+pub fn judge(
+    file_name: &str,
+    extra_prefixes: &[String],
+    evidence: &ScreenshotEvidence,
+) -> ScreenshotVerdict {
+    if evidence.system_mark {
+        return ScreenshotVerdict {
+            score: 100,
+            signals: vec!["marked as a screen capture by the system".to_string()],
+        };
+    }
+    let mut verdict = ScreenshotVerdict {
+        score: 0,
+        signals: Vec::new(),
+    };
+    let mut add = |score: i32, signal: String| {
+        verdict.score += score;
+        verdict.signals.push(signal);
+    };
+    if let Some(prefix) = name_prefix(file_name, extra_prefixes) {
+        add(
+            NAME_SCORE,
+            format!("named like a screenshot (\"{prefix}\")"),
+        );
+    }
+    if evidence.png {
+        add(PNG_SCORE, "PNG".to_string());
+    }
+    if let Some((width, height)) = evidence.dimensions {
+        if evidence
+            .display_sizes
+            .iter()
+            .any(|&size| same_size(size, (width, height)))
+        {
+            add(
+                DISPLAY_SIZE_SCORE,
+                format!("{width}×{height} matches a connected display"),
+            );
+        } else if DEVICE_SIZES
+            .iter()
+            .any(|&size| same_size(size, (width, height)))
+        {
+            add(
+                DEVICE_SIZE_SCORE,
+                format!("{width}×{height} is a common screen size"),
+            );
+        }
+    }
+    if evidence.camera_exif {
+        add(CAMERA_SCORE, "camera EXIF".to_string());
+    }
+    verdict
+}
+
+/// The prefix `file_name` starts with, ignoring case, if it's named the way a
+/// screenshot tool names its files.
+pub fn name_prefix<'a>(file_name: &str, extra_prefixes: &'a [String]) -> Option<&'a str> {
+    let name = file_name.to_lowercase();
+    NAME_PREFIXES
+        .iter()
+        .copied()
+        .chain(extra_prefixes.iter().map(String::as_str))
+        .filter(|prefix| !prefix.trim().is_empty())
+        .find(|prefix| name.starts_with(&prefix.to_lowercase()))
+}
+
+/// Whether an EXIF chunk, starting at its TIFF header, names a camera maker
+/// or model. Screenshot tools don't write either.
+pub fn has_camera_tags(exif: &[u8]) -> bool {
+    Exif::parse(exif).is_some_and(|exif| exif.has_camera_tags())
+}
+
+/// Sizes match in either orientation.
+fn same_size(a: (u32, u32), b: (u32, u32)) -> bool {
+    a == b || a == (b.1, b.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A little-endian EXIF chunk whose first IFD holds `tags`.
+    fn exif_with(tags: &[u16]) -> Vec<u8> {
+        let mut chunk = vec![0x49, 0x49, 42, 0, 8, 0, 0, 0];
+        chunk.extend_from_slice(&(tags.len() as u16).to_le_bytes());
+        for tag in tags {
+            chunk.extend_from_slice(&tag.to_le_bytes());
+            chunk.extend_from_slice(&[2, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        }
+        chunk.extend_from_slice(&[0, 0, 0, 0]);
+        chunk
+    }
+
+    #[test]
+    fn names_in_several_languages_are_screenshots() {
+        let none = ScreenshotEvidence::default();
+        for name in [
+            "Screenshot 2025-03-01 at 10.15.02.png",
+            "Bildschirmfoto 2025-03-01 um 10.15.02.png",
+            "Capture d’écran 2025-03-01 à 10.15.02.png",
+            "screenshot_20250301-101502.jpg",
+        ] {
+            let verdict = judge(name, &[], &none);
+            assert!(verdict.is_screenshot(), "{name}: {verdict}");
+        }
+        assert!(!judge("image.png", &[], &none).is_screenshot());
+
+        let extra = vec!["Shottr".to_string()];
+        assert_eq!(name_prefix("Shottr 2025-03-01.png", &extra), Some("Shottr"));
+        assert!(judge("SHOTTR 1.png", &extra, &none).is_screenshot());
+    }
+
+    #[test]
+    fn a_pasted_png_the_size_of_a_screen_is_a_screenshot() {
+        let mut evidence = ScreenshotEvidence {
+            png: true,
+            dimensions: Some((1800, 2880)),
+            display_sizes: vec![(2880, 1800)],
+            ..ScreenshotEvidence::default()
+        };
+        let verdict = judge("image.png", &[], &evidence);
+        assert_eq!(verdict.score, 70);
+        assert_eq!(
+            verdict.to_string(),
+            "Screenshot: yes, score 70 of 60 (PNG, 1800×2880 matches a connected display)"
+        );
+
+        evidence.display_sizes.clear();
+        evidence.dimensions = Some((1170, 2532));
+        assert!(judge("image.png", &[], &evidence).is_screenshot());
+
+        evidence.dimensions = Some((1000, 700));
+        assert!(!judge("image.png", &[], &evidence).is_screenshot());
+    }
+
+    #[test]
+    fn camera_exif_outweighs_everything_but_the_system_mark() {
+        let mut evidence = ScreenshotEvidence {
+            camera_exif: true,
+            dimensions: Some((1920, 1080)),
+            ..ScreenshotEvidence::default()
+        };
+        assert!(!judge("Screenshot 1.jpg", &[], &evidence).is_screenshot());
+
+        evidence.system_mark = true;
+        let verdict = judge("IMG_2041.jpg", &[], &evidence);
+        assert!(verdict.is_screenshot());
+        assert_eq!(
+            verdict.signals,
+            ["marked as a screen capture by the system"]
+        );
+    }
+
+    #[test]
+    fn reads_camera_tags_from_exif() {
+        assert!(has_camera_tags(&exif_with(&[0x0112, 0x0110])));
+        assert!(has_camera_tags(&exif_with(&[0x010f])));
+        // Orientation and software only, as an editor might write.
+        assert!(!has_camera_tags(&exif_with(&[0x0112, 0x0131])));
+        assert!(!has_camera_tags(b"not exif"));
+        assert!(!has_camera_tags(&[0x4d, 0x4d, 0, 42, 0, 0, 0xff, 0xff]));
+    }
+}
@@ -0,0 +1,892 @@
+//! Runs a rule against a file that only exists as a description, the way the
+//! app's rule preview runs it against one on disk, and says what the rule
+//! would do with it.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::busdays::parse_holidays;
+use crate::evaluate::{
+    describe_date_threshold, evaluate_condition, evaluate_conditions, ArchiveListing,
+    ContentProvider, EvaluationOptions, ResolvedContents,
+};
+use crate::file_info::FileInfo;
+use crate::kinds::kind_for_extension;
+use crate::media::MediaInfo;
+use crate::models::{
+    Action, ActiveApp, Condition, ConditionGroup, ContentSource, DuplicateScope, EventSource,
+    FileKind, Rule, Settings, TagMode,
+};
+use crate::patterns::{trace_map_branches, PatternEngine};
+use crate::timezone::resolve_timezone;
+
+/// A file described rather than read. Whatever isn't given is what a fresh,
+/// empty file would have: dates default to now, `kind` to what the extension
+/// says, and conditions on contents, archives, faces, image dimensions, EXIF
+/// dates, page counts, media length or codecs or MIME type don't match. The
+/// file has no Finder tags or duplicate, and Is Screenshot goes by the name
+/// alone.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyntheticFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub created: Option<DateTime<Utc>>,
+    /// Defaults to `created`.
+    pub modified: Option<DateTime<Utc>>,
+    /// Defaults to `created`.
+    pub added: Option<DateTime<Utc>>,
+    pub last_matched: Option<DateTime<Utc>>,
+    pub kind: Option<FileKind>,
+    /// The text Contents conditions see, whatever source they ask for.
+    pub contents: Option<String>,
+    pub archive_entries: Option<Vec<String>>,
+    pub face_count: Option<u32>,
+    /// Width and height in pixels.
+    pub image_dimensions: Option<(u32, u32)>,
+    /// When an image was taken, as its EXIF says.
+    pub exif_date: Option<DateTime<Utc>>,
+    /// Pages in a PDF.
+    pub page_count: Option<u32>,
+    /// How long a video or audio file plays, in seconds.
+    pub media_duration: Option<u64>,
+    /// The codecs of a video or audio file's streams, e.g. `h264`.
+    pub media_codecs: Vec<String>,
+    /// What the file's bytes say it is, e.g. `application/pdf`.
+    pub mime_type: Option<String>,
+    /// Finder tag names.
+    pub tags: Vec<String>,
+    /// A file with the same contents, in whatever scope Is Duplicate asks
+    /// about.
+    pub duplicate_of: Option<PathBuf>,
+    pub locked: bool,
+    /// The application in front when the file arrived; Active Application
+    /// doesn't match without one.
+    pub active_app: Option<ActiveApp>,
+    /// How the file reached the engine; live unless given.
+    pub event_source: EventSource,
+    /// The directory under the watched folder it was found in.
+    pub original_subdir: String,
+}
+
+impl SyntheticFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The `FileInfo` conditions and patterns see for this file.
+    pub fn info(&self) -> FileInfo {
+        let path = self.path.as_path();
+        let os_name = path.file_name().map(OsString::from).unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let created = self.created.unwrap_or_else(Utc::now);
+        let modified = self.modified.unwrap_or(created);
+        FileInfo {
+            path: self.path.clone(),
+            name: path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            full_name: os_name.to_string_lossy().to_string(),
+            os_name,
+            size: self.size,
+            created,
+            modified,
+            added: self.added.unwrap_or(created),
+            kind: self
+                .kind
+                .clone()
+                .unwrap_or_else(|| kind_for_extension(false, &extension)),
+            extension,
+            parent: path
+                .parent()
+                .and_then(Path::file_name)
+                .map(|s| s.to_string_lossy().to_string()),
+            is_dir: false,
+            hash: format!("{}:{}", modified.timestamp(), self.size),
+            is_placeholder: false,
+            last_matched: self.last_matched,
+            arrival: None,
+            active_app: self.active_app.clone(),
+            event_source: self.event_source,
+            original_subdir: self.original_subdir.clone(),
+        }
+    }
+}
+
+/// Parts of a real file's info replaced for a preview, to ask what a rule
+/// would do with the file if it were older, bigger or of another kind. Only
+/// the field itself changes: overriding `extension` leaves the name and the
+/// kind as they were. The engine never takes these; previews do.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FileInfoOverrides {
+    pub created: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    pub added: Option<DateTime<Utc>>,
+    pub size: Option<u64>,
+    pub kind: Option<FileKind>,
+    pub extension: Option<String>,
+    pub last_matched: Option<DateTime<Utc>>,
+}
+
+impl FileInfoOverrides {
+    /// Puts the overrides in `info` and names the fields they replaced, in
+    /// the camelCase the app shows them in.
+    pub fn apply(&self, info: &mut FileInfo) -> Vec<&'static str> {
+        let mut overridden = Vec::new();
+        if let Some(created) = self.created {
+            info.created = created;
+            overridden.push("created");
+        }
+        if let Some(modified) = self.modified {
+            info.modified = modified;
+            overridden.push("modified");
+        }
+        if let Some(added) = self.added {
+            info.added = added;
+            overridden.push("added");
+        }
+        if let Some(size) = self.size {
+            info.size = size;
+            overridden.push("size");
+        }
+        if let Some(kind) = &self.kind {
+            info.kind = kind.clone();
+            overridden.push("kind");
+        }
+        if let Some(extension) = &self.extension {
+            info.extension = extension.trim_start_matches('.').to_lowercase();
+            overridden.push("extension");
+        }
+        if let Some(last_matched) = self.last_matched {
+            info.last_matched = Some(last_matched);
+            overridden.push("lastMatched");
+        }
+        overridden
+    }
+}
+
+/// What `simulate` found, shaped like a row of the app's rule preview.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationResult {
+    pub matched: bool,
+    /// Whether each top-level condition matched on its own, in order.
+    pub condition_results: Vec<bool>,
+    pub captures: HashMap<String, String>,
+    /// The rule's actions with their patterns resolved; empty when it didn't match.
+    pub actions: Vec<String>,
+    /// Mapping branches taken and the dates relative date conditions compare against.
+    pub notes: Vec<String>,
+}
+
+/// Runs `rule` against `file` with default settings.
+pub fn simulate(rule: &Rule, file: &SyntheticFile) -> Result<SimulationResult> {
+    simulate_with(rule, file, &Settings::default())
+}
+
+/// Like `simulate`, with the settings that decide the default time zone and
+/// holidays.
+pub fn simulate_with(
+    rule: &Rule,
+    file: &SyntheticFile,
+    settings: &Settings,
+) -> Result<SimulationResult> {
+    let info = file.info();
+    let mut content = SyntheticContent(file);
+    let options = EvaluationOptions {
+        skip_content: false,
+        surface_errors: true,
+        now: None,
+    };
+    let evaluation = evaluate_conditions(rule, &info, settings, &mut content, &options)?;
+    let condition_results = rule
+        .conditions
+        .conditions
+        .iter()
+        .map(|condition| {
+            evaluate_condition(condition, &info, settings, &mut content, &options)
+                .map(|result| result.matched)
+        })
+        .collect::<Result<_>>()?;
+
+    let engine = PatternEngine::new();
+    engine.set_default_timezone(settings.default_timezone.as_deref());
+    let (actions, mut notes) = trace_map_branches(|| {
+        if evaluation.matched {
+            rule.actions
+                .iter()
+                .map(|action| describe_action(action, &info, &evaluation.captures, &engine))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    });
+    notes.extend(date_threshold_notes(&rule.conditions, settings, Utc::now()));
+
+    Ok(SimulationResult {
+        matched: evaluation.matched,
+        condition_results,
+        captures: evaluation.captures,
+        actions,
+        notes,
+    })
+}
+
+/// One line per action, e.g. `Move → ~/Invoices/2024`.
+pub fn describe_action(
+    action: &Action,
+    info: &FileInfo,
+    captures: &HashMap<String, String>,
+    engine: &PatternEngine,
+) -> String {
+    match action {
+        Action::Move(action) => {
+            let dest = engine.resolve(&action.destination, info, captures);
+            format!("Move → {}", dest)
+        }
+        Action::Copy(action) => {
+            let dest = engine.resolve(&action.destination, info, captures);
+            format!("Copy → {}", dest)
+        }
+        Action::Rename(action) => {
+            let name = engine.resolve(&action.pattern, info, captures);
+            format!("Rename → {}", name)
+        }
+        Action::SortIntoSubfolder(action) => {
+            let dest = engine.resolve(&action.destination, info, captures);
+            format!("Sort → {}", dest)
+        }
+        Action::Archive(action) => {
+            let dest = engine.resolve(&action.destination, info, captures);
+            format!("Archive → {}", dest)
+        }
+        Action::Unarchive(action) => {
+            let dest = action
+                .destination
+                .as_ref()
+                .map(|d| engine.resolve(d, info, captures))
+                .unwrap_or_else(|| "Current folder".to_string());
+            format!("Unarchive → {}", dest)
+        }
+        Action::Delete(_) => "Delete (Trash)".to_string(),
+        Action::DeletePermanently(_) => "Delete Permanently".to_string(),
+        Action::RunScript(action) => format!("Run: {}", action.command),
+        Action::Notify(action) => {
+            let message = engine.resolve(&action.message, info, captures);
+            format!("Notify: {}", message)
+        }
+        Action::Open(_) => "Open with default app".to_string(),
+        Action::ShowInFileManager(_) => "Show in file manager".to_string(),
+        Action::OpenWith(action) => format!("Open with {}", action.app_path),
+        Action::MakePdfSearchable(_) => "Make PDF searchable (OCR)".to_string(),
+        Action::CreateFolderStructure(action) => {
+            let base = engine.resolve(&action.base_destination, info, captures);
+            let entries = action
+                .structure
+                .iter()
+                .map(|entry| engine.resolve(entry, info, captures))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("Create folders in {}: {}", base, entries)
+        }
+        Action::DispatchToFolder(action) => format!("Dispatch to folder {}", action.folder_id),
+        Action::SetTags(action) => {
+            let tags = action
+                .tags
+                .iter()
+                .map(|tag| engine.resolve(tag, info, captures))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let verb = match action.mode {
+                TagMode::Append => "Add",
+                TagMode::Replace => "Set",
+            };
+            format!("{} tags: {}", verb, tags)
+        }
+        Action::Pause(action) => format!("Pause {}s", action.duration_seconds),
+        Action::Continue => "Continue matching rules".to_string(),
+        Action::Ignore => "Ignore".to_string(),
+    }
+}
+
+/// `Date created before 2024-03-01`-style notes for the relative date
+/// conditions in `group`, nested ones included, counting back from `now`.
+pub fn date_threshold_notes(
+    group: &ConditionGroup,
+    settings: &Settings,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let holidays = parse_holidays(&settings.holidays);
+    let mut notes = Vec::new();
+    collect_date_thresholds(group, &holidays, settings, now, &mut notes);
+    notes
+}
+
+fn collect_date_thresholds(
+    group: &ConditionGroup,
+    holidays: &[chrono::NaiveDate],
+    settings: &Settings,
+    now: DateTime<Utc>,
+    notes: &mut Vec<String>,
+) {
+    for condition in &group.conditions {
+        let (label, cond) = match condition {
+            Condition::DateCreated(cond) => ("Date created", cond),
+            Condition::DateModified(cond) => ("Date modified", cond),
+            Condition::DateAdded(cond) => ("Date added", cond),
+            Condition::DateLastMatched(cond) => ("Date last matched", cond),
+            Condition::ExifDate(cond) => ("Taken", cond),
+            Condition::Nested(nested) => {
+                collect_date_thresholds(nested, holidays, settings, now, notes);
+                continue;
+            }
+            _ => continue,
+        };
+        let zone = resolve_timezone(
+            cond.timezone.as_deref(),
+            settings.default_timezone.as_deref(),
+        )
+        .unwrap_or(None);
+        if let Some(threshold) = describe_date_threshold(&cond.operator, holidays, zone, now) {
+            notes.push(format!("{} {}", label, threshold));
+        }
+    }
+}
+
+/// Answers conditions from what the `SyntheticFile` says.
+struct SyntheticContent<'a>(&'a SyntheticFile);
+
+impl ContentProvider for SyntheticContent<'_> {
+    fn contents(
+        &mut self,
+        _info: &FileInfo,
+        _source: &ContentSource,
+    ) -> Result<Option<ResolvedContents>> {
+        Ok(self.0.contents.clone().map(ResolvedContents::plain))
+    }
+
+    fn archive_listing(&mut self, _info: &FileInfo, max_entries: u32) -> Option<ArchiveListing> {
+        let entries = self.0.archive_entries.as_ref()?;
+        Some(ArchiveListing {
+            entries: entries.iter().take(max_entries as usize).cloned().collect(),
+            truncated: entries.len() > max_entries as usize,
+        })
+    }
+
+    fn face_count(&mut self, _info: &FileInfo) -> Option<u32> {
+        self.0.face_count
+    }
+
+    fn image_dimensions(&mut self, _info: &FileInfo) -> Option<(u32, u32)> {
+        self.0.image_dimensions
+    }
+
+    fn exif_date(&mut self, _info: &FileInfo) -> Option<DateTime<Utc>> {
+        self.0.exif_date
+    }
+
+    fn page_count(&mut self, _info: &FileInfo) -> Option<u32> {
+        self.0.page_count
+    }
+
+    fn media_info(&mut self, _info: &FileInfo) -> Result<Option<MediaInfo>> {
+        if self.0.media_duration.is_none() && self.0.media_codecs.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(MediaInfo {
+            duration: self.0.media_duration.map(Duration::from_secs),
+            codecs: self.0.media_codecs.clone(),
+        }))
+    }
+
+    fn mime_type(&mut self, _info: &FileInfo) -> Option<String> {
+        self.0.mime_type.clone()
+    }
+
+    fn tags(&mut self, _info: &FileInfo) -> Result<Option<Vec<String>>> {
+        Ok(Some(self.0.tags.clone()))
+    }
+
+    fn duplicate_of(
+        &mut self,
+        _info: &FileInfo,
+        _scope: DuplicateScope,
+    ) -> Result<Option<PathBuf>> {
+        Ok(self.0.duplicate_of.clone())
+    }
+
+    fn is_locked(&mut self, _path: &Path) -> bool {
+        self.0.locked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn rule(conditions: &str, actions: &str) -> Rule {
+        serde_json::from_str(&format!(
+            r#"{{
+                "id": "r1",
+                "folderId": "f1",
+                "name": "Test",
+                "enabled": true,
+                "stopProcessing": false,
+                "conditions": {{"matchType": "all", "conditions": [{conditions}]}},
+                "actions": [{actions}],
+                "position": 0,
+                "createdAt": "2024-01-01T00:00:00Z",
+                "updatedAt": "2024-01-01T00:00:00Z"
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn matching_file_resolves_its_actions() {
+        let rule = rule(
+            r#"{"type": "name", "operator": "matches", "value": "invoice-(\\d+)",
+                "caseSensitive": false},
+               {"type": "extension", "operator": "is", "value": "pdf", "caseSensitive": false}"#,
+            r#"{"type": "move", "destination": "~/Invoices/{created:%Y}/{1}",
+                "onConflict": "rename", "skipDuplicates": false}"#,
+        );
+        let mut file = SyntheticFile::new("/in/Invoice-42.pdf");
+        file.created = Some(Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap());
+
+        let result = simulate(&rule, &file).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.condition_results, [true, true]);
+        assert_eq!(result.captures.get("1").map(String::as_str), Some("42"));
+        assert_eq!(result.actions, ["Move → ~/Invoices/2023/42"]);
+    }
+
+    #[test]
+    fn path_captures_reach_the_destination() {
+        let rule = rule(
+            r#"{"type": "path", "operator": "matches", "value": "^/downloads/work/([^/]+)/",
+                "caseSensitive": false}"#,
+            r#"{"type": "move", "destination": "~/Clients/{1}",
+                "onConflict": "rename", "skipDuplicates": false}"#,
+        );
+        let work = SyntheticFile::new("/downloads/work/acme/brief.pdf");
+        let result = simulate(&rule, &work).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.actions, ["Move → ~/Clients/acme"]);
+        let elsewhere = SyntheticFile::new("/downloads/personal/acme/brief.pdf");
+        assert!(!simulate(&rule, &elsewhere).unwrap().matched);
+    }
+
+    #[test]
+    fn parent_folder_and_relative_path_capture_folder_names() {
+        let rule = rule(
+            r#"{"type": "parentFolder", "operator": "matches", "value": "^(\\d{4})$",
+                "caseSensitive": false},
+               {"type": "relativePath", "operator": "startsWith", "value": "clients/",
+                "caseSensitive": false}"#,
+            r#"{"type": "rename", "pattern": "{1}-{name}", "onConflict": "rename"}"#,
+        );
+        let mut file = SyntheticFile::new("/inbox/clients/2024/brief.pdf");
+        file.original_subdir = "clients/2024".to_string();
+        let result = simulate(&rule, &file).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.captures.get("1").map(String::as_str), Some("2024"));
+
+        // Picked up at the top of the folder, so it isn't under clients/.
+        file.original_subdir = String::new();
+        assert!(!simulate(&rule, &file).unwrap().matched);
+    }
+
+    #[test]
+    fn the_rule_classification_is_a_token() {
+        let mut rule = rule(
+            r#"{"type": "extension", "operator": "is", "value": "pdf", "caseSensitive": false}"#,
+            r#"{"type": "move", "destination": "~/Records/{classification}",
+                "onConflict": "rename", "skipDuplicates": false}"#,
+        );
+        rule.classification = Some("FIN-7Y".to_string());
+
+        let result = simulate(&rule, &SyntheticFile::new("/in/ledger.pdf")).unwrap();
+        assert_eq!(result.actions, ["Move → ~/Records/FIN-7Y"]);
+    }
+
+    #[test]
+    fn non_matching_file_reports_each_condition() {
+        let rule = rule(
+            r#"{"type": "extension", "operator": "is", "value": "pdf", "caseSensitive": false},
+               {"type": "size", "operator": {"type": "greaterThan"}, "value": 1,
+                "unit": "kilobytes"}"#,
+            r#"{"type": "delete", "permanent": false}"#,
+        );
+        let mut file = SyntheticFile::new("/in/scan.pdf");
+        file.size = 512;
+
+        let result = simulate(&rule, &file).unwrap();
+        assert!(!result.matched);
+        assert_eq!(result.condition_results, [true, false]);
+        assert!(result.actions.is_empty());
+    }
+
+    #[test]
+    fn contents_and_archives_come_from_the_description() {
+        let rule = rule(
+            r#"{"type": "contents", "operator": "contains", "value": "total due",
+                "caseSensitive": false, "source": "text"},
+               {"type": "archiveContains", "nameCondition":
+                   {"operator": "endsWith", "value": ".csv", "caseSensitive": false}}"#,
+            r#"{"type": "ignore"}"#,
+        );
+        let mut file = SyntheticFile::new("/in/export.zip");
+        assert!(!simulate(&rule, &file).unwrap().matched);
+
+        file.contents = Some("Total due: 12.00".to_string());
+        file.archive_entries = Some(vec!["data/rows.csv".to_string()]);
+        let result = simulate(&rule, &file).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.actions, ["Ignore"]);
+    }
+
+    #[test]
+    fn screenshots_are_told_apart_by_name_without_reading_the_file() {
+        let screenshot = |negate: bool| {
+            rule(
+                &format!(r#"{{"type": "isScreenshot", "negate": {negate}}}"#),
+                r#"{"type": "ignore"}"#,
+            )
+        };
+        let matched = |rule: &Rule, path: &str, kind: FileKind| {
+            let mut file = SyntheticFile::new(path);
+            file.kind = Some(kind);
+            simulate(rule, &file).unwrap().matched
+        };
+        let image = FileKind::Image;
+
+        let german = "/in/Bildschirmfoto 2025-03-01 um 10.15.02.png";
+        let french = "/in/Capture d'écran 2025-03-01 à 10.15.02.png";
+        assert!(matched(&screenshot(false), german, image.clone()));
+        assert!(matched(&screenshot(false), french, image.clone()));
+        assert!(!matched(
+            &screenshot(false),
+            "/in/IMG_2041.jpg",
+            image.clone()
+        ));
+        // Only images are screenshots.
+        let notes = "/in/Screenshot notes.txt";
+        assert!(!matched(&screenshot(false), notes, FileKind::Document));
+
+        assert!(matched(
+            &screenshot(true),
+            "/in/IMG_2041.jpg",
+            image.clone()
+        ));
+        assert!(!matched(&screenshot(true), german, image));
+    }
+
+    #[test]
+    fn backlog_files_keep_their_folder_structure() {
+        let migrate = rule(
+            r#"{"type": "eventSource", "sources": ["scan", "catchup"]}"#,
+            r#"{"type": "move", "destination": "~/Archive/migrated/{original_subdir}",
+                "onConflict": "rename", "skipDuplicates": false}"#,
+        );
+        let mut file = SyntheticFile::new("/in/2019/trips/lisbon.jpg");
+        file.original_subdir = "2019/trips".to_string();
+        assert!(!simulate(&migrate, &file).unwrap().matched);
+
+        file.event_source = EventSource::Scan;
+        let result = simulate(&migrate, &file).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.actions, ["Move → ~/Archive/migrated/2019/trips"]);
+    }
+
+    #[test]
+    fn exports_are_routed_by_the_app_in_front() {
+        let routed = rule(
+            r#"{"type": "activeApplication", "operator": "contains", "value": "freecad",
+                "caseSensitive": false}"#,
+            r#"{"type": "move", "destination": "~/Projects/{active_app}",
+                "onConflict": "rename", "skipDuplicates": false}"#,
+        );
+        let mut file = SyntheticFile::new("/out/bracket.step");
+        assert!(!simulate(&routed, &file).unwrap().matched);
+
+        file.active_app = Some(ActiveApp::Unavailable {
+            reason: "Wayland doesn't say".to_string(),
+        });
+        assert!(!simulate(&routed, &file).unwrap().matched);
+
+        // The identifier stands in when the name doesn't match.
+        file.active_app = Some(ActiveApp::Known {
+            name: "CAD".to_string(),
+            identifier: Some("org.freecad.FreeCAD".to_string()),
+        });
+        let result = simulate(&routed, &file).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.actions, ["Move → ~/Projects/CAD"]);
+
+        // A negative operator holds for neither.
+        let elsewhere = rule(
+            r#"{"type": "activeApplication", "operator": "isNot", "value": "org.freecad.FreeCAD",
+                "caseSensitive": false}"#,
+            r#"{"type": "ignore"}"#,
+        );
+        assert!(!simulate(&elsewhere, &file).unwrap().matched);
+        file.active_app = Some(ActiveApp::Known {
+            name: "Blender".to_string(),
+            identifier: None,
+        });
+        assert!(simulate(&elsewhere, &file).unwrap().matched);
+    }
+
+    #[test]
+    fn image_dimensions_compare_width_height_and_shape() {
+        let large = rule(
+            r#"{"type": "imageDimensions", "width": {"operator": {"type": "greaterThan"},
+                "value": 3000}, "height": {"operator": {"type": "greaterThan"}, "value": 2000}}"#,
+            r#"{"type": "move", "destination": "/out/{image_width}x{image_height}",
+                "onConflict": "rename", "skipDuplicates": false}"#,
+        );
+        let mut file = SyntheticFile::new("/in/wallpaper.png");
+        file.image_dimensions = Some((6000, 4000));
+        let result = simulate(&large, &file).unwrap();
+        assert!(result.matched);
+        assert!(
+            result.actions[0].contains("/out/6000x4000"),
+            "{:?}",
+            result.actions
+        );
+        file.image_dimensions = Some((6000, 1800));
+        assert!(!simulate(&large, &file).unwrap().matched);
+
+        let portrait = rule(
+            r#"{"type": "imageDimensions", "shape": "portrait"}"#,
+            r#"{"type": "ignore"}"#,
+        );
+        file.image_dimensions = Some((1170, 2532));
+        assert!(simulate(&portrait, &file).unwrap().matched);
+        file.image_dimensions = Some((2000, 2000));
+        assert!(!simulate(&portrait, &file).unwrap().matched);
+
+        // Only images are asked, whatever the provider would say.
+        let mut document = SyntheticFile::new("/in/scan.pdf");
+        document.image_dimensions = Some((1170, 2532));
+        assert!(!simulate(&portrait, &document).unwrap().matched);
+    }
+
+    #[test]
+    fn exif_date_goes_by_when_the_photo_was_taken() {
+        let old = rule(
+            r#"{"type": "exifDate", "operator": {"type": "isBefore", "date": "2020-01-01"}}"#,
+            r#"{"type": "ignore"}"#,
+        );
+        let mut photo = SyntheticFile::new("/in/IMG_0001.jpg");
+        photo.created = Some(Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap());
+        photo.exif_date = Some(Utc.with_ymd_and_hms(2018, 8, 4, 12, 0, 0).unwrap());
+        assert!(simulate(&old, &photo).unwrap().matched);
+
+        // No EXIF date doesn't fall back to the file's own dates.
+        photo.exif_date = None;
+        assert!(!simulate(&old, &photo).unwrap().matched);
+    }
+
+    #[test]
+    fn long_recordings_go_by_media_duration_and_codec() {
+        let long = rule(
+            r#"{"type": "mediaDuration", "operator": {"type": "greaterThan"}, "value": 30,
+                "unit": "minutes"},
+               {"type": "mediaCodec", "operator": "isOneOf", "value": "h264, hevc",
+                "caseSensitive": false}"#,
+            r#"{"type": "move", "destination": "/archive/{media_codec}/{media_duration}s",
+                "onConflict": "rename", "skipDuplicates": false}"#,
+        );
+        let mut recording = SyntheticFile::new("/in/Screen Recording.mov");
+        recording.media_duration = Some(45 * 60);
+        recording.media_codecs = vec!["hevc".to_string(), "aac".to_string()];
+        let result = simulate(&long, &recording).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.actions, ["Move → /archive/hevc/2700s"]);
+
+        recording.media_duration = Some(30 * 60);
+        assert!(!simulate(&long, &recording).unwrap().matched);
+
+        let no_video = rule(
+            r#"{"type": "mediaCodec", "operator": "isNot", "value": "aac",
+                "caseSensitive": false}"#,
+            r#"{"type": "ignore"}"#,
+        );
+        assert!(!simulate(&no_video, &recording).unwrap().matched);
+        recording.media_codecs = vec!["opus".to_string()];
+        assert!(simulate(&no_video, &recording).unwrap().matched);
+
+        // Only video and audio files have a length.
+        let mut notes = SyntheticFile::new("/in/notes.txt");
+        notes.media_duration = Some(45 * 60);
+        assert!(!simulate(&long, &notes).unwrap().matched);
+    }
+
+    #[test]
+    fn tags_match_by_name_and_untagged_files_are_not_tagged() {
+        let invoices = rule(
+            r#"{"type": "tag", "operator": "is", "value": "invoice", "caseSensitive": false}"#,
+            r#"{"type": "setTags", "tags": ["Filed {tag}"], "mode": "replace"}"#,
+        );
+        let mut file = SyntheticFile::new("/in/scan.pdf");
+        file.tags = vec!["Red".to_string(), "Invoice".to_string()];
+        let result = simulate(&invoices, &file).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.actions, ["Set tags: Filed Invoice"]);
+
+        let untagged = rule(
+            r#"{"type": "tag", "operator": "isNot", "value": "Red", "caseSensitive": false}"#,
+            r#"{"type": "setTags", "tags": ["Red", "To Do"]}"#,
+        );
+        assert!(!simulate(&untagged, &file).unwrap().matched);
+        file.tags.clear();
+        let result = simulate(&untagged, &file).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.actions, ["Add tags: Red, To Do"]);
+    }
+
+    #[test]
+    fn duplicates_name_the_copy_they_duplicate() {
+        let duplicates = rule(
+            r#"{"type": "isDuplicate", "scope": "anyWatchedFolder"}"#,
+            r#"{"type": "notify", "message": "Same as {duplicate_of}"}"#,
+        );
+        let mut file = SyntheticFile::new("/in/scan (1).pdf");
+        assert!(!simulate(&duplicates, &file).unwrap().matched);
+        file.duplicate_of = Some(PathBuf::from("/archive/scan.pdf"));
+        let result = simulate(&duplicates, &file).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.actions, ["Notify: Same as /archive/scan.pdf"]);
+
+        let unique = rule(
+            r#"{"type": "isDuplicate", "negate": true}"#,
+            r#"{"type": "ignore"}"#,
+        );
+        assert!(!simulate(&unique, &file).unwrap().matched);
+    }
+
+    #[test]
+    fn page_count_only_applies_to_pdfs() {
+        let long = rule(
+            r#"{"type": "pageCount", "operator": {"type": "greaterThan"}, "value": 50}"#,
+            r#"{"type": "move", "destination": "/archive/large/{page_count}p",
+                "onConflict": "rename", "skipDuplicates": false}"#,
+        );
+        let mut report = SyntheticFile::new("/in/report.PDF");
+        report.page_count = Some(120);
+        let result = simulate(&long, &report).unwrap();
+        assert!(result.matched);
+        assert_eq!(result.actions, ["Move → /archive/large/120p"]);
+        report.page_count = Some(12);
+        assert!(!simulate(&long, &report).unwrap().matched);
+
+        let mut slides = SyntheticFile::new("/in/deck.pptx");
+        slides.page_count = Some(120);
+        assert!(!simulate(&long, &slides).unwrap().matched);
+    }
+
+    #[test]
+    fn mime_type_goes_by_the_sniffed_type_not_the_extension() {
+        let jpeg = rule(
+            r#"{"type": "mimeType", "operator": "startsWith", "value": "image/",
+                "caseSensitive": false}"#,
+            r#"{"type": "ignore"}"#,
+        );
+        let mut file = SyntheticFile::new("/in/photo.txt");
+        file.mime_type = Some("image/jpeg".to_string());
+        assert!(simulate(&jpeg, &file).unwrap().matched);
+
+        // Nothing sniffed never matches, even a negated condition.
+        let not_pdf = rule(
+            r#"{"type": "mimeType", "operator": "isNot", "value": "application/pdf",
+                "caseSensitive": false}"#,
+            r#"{"type": "ignore"}"#,
+        );
+        assert!(simulate(&not_pdf, &file).unwrap().matched);
+        file.mime_type = None;
+        assert!(!simulate(&not_pdf, &file).unwrap().matched);
+    }
+
+    #[test]
+    fn synthetic_file_deserializes_from_camel_case() {
+        let file: SyntheticFile = serde_json::from_str(
+            r#"{"path": "/in/photo.jpg", "size": 2048, "kind": "image", "faceCount": 2,
+                "activeApp": {"status": "known", "name": "Photos", "identifier": null}}"#,
+        )
+        .unwrap();
+        let info = file.info();
+        assert_eq!(info.name, "photo");
+        assert_eq!(info.extension, "jpg");
+        assert_eq!(info.kind, FileKind::Image);
+        assert_eq!(info.modified, info.created);
+        assert_eq!(file.face_count, Some(2));
+        assert_eq!(info.active_app.as_ref().and_then(ActiveApp::name), Some("Photos"));
+    }
+
+    #[test]
+    fn an_older_created_date_flips_in_the_last() {
+        let condition = Condition::DateCreated(crate::models::DateCondition {
+            operator: crate::models::DateOperator::InTheLast {
+                amount: 90,
+                unit: crate::models::TimeUnit::Days,
+            },
+            timezone: None,
+        });
+        let file = SyntheticFile::new("/in/report.pdf");
+        let settings = Settings::default();
+        let options = EvaluationOptions::default();
+        let mut info = file.info();
+        let mut content = SyntheticContent(&file);
+        let fresh = evaluate_condition(&condition, &info, &settings, &mut content, &options);
+        assert!(fresh.unwrap().matched);
+
+        let overrides = FileInfoOverrides {
+            created: Some(Utc::now() - chrono::Duration::days(120)),
+            ..FileInfoOverrides::default()
+        };
+        assert_eq!(overrides.apply(&mut info), ["created"]);
+        let aged = evaluate_condition(&condition, &info, &settings, &mut content, &options);
+        assert!(!aged.unwrap().matched);
+    }
+
+    #[test]
+    fn the_clock_override_decides_current_time() {
+        let condition = Condition::CurrentTime(crate::models::TimeCondition {
+            operator: crate::models::TimeOperator::Between {
+                start: chrono::NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                end: chrono::NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            },
+            timezone: Some("UTC".to_string()),
+        });
+        let file = SyntheticFile::new("/in/report.pdf");
+        let info = file.info();
+        let settings = Settings::default();
+        let mut content = SyntheticContent(&file);
+        let at = |hour| EvaluationOptions {
+            now: Some(Utc.with_ymd_and_hms(2024, 3, 4, hour, 30, 0).unwrap()),
+            ..EvaluationOptions::default()
+        };
+
+        let office = evaluate_condition(&condition, &info, &settings, &mut content, &at(10));
+        assert!(office.unwrap().matched);
+        let night = evaluate_condition(&condition, &info, &settings, &mut content, &at(22));
+        assert!(!night.unwrap().matched);
+    }
+}
@@ -0,0 +1,781 @@
+//! Drafts a rule from example files, for people who would rather point at a
+//! few invoices than write a regex. Counter-examples, files the rule must
+//! leave alone, make the draft tighter. Nothing is saved: the draft goes
+//! through the rule editor like any other new rule.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+
+use crate::models::{
+    Action, ComparisonOperator, Condition, ConditionGroup, ConflictResolution, ContentSource,
+    ContentsCondition, MatchType, MoveAction, Rule, Settings, SizeCondition, SizeUnit,
+    StringCondition, StringOperator,
+};
+use crate::simulate::{simulate_with, SyntheticFile};
+
+/// Above this many extensions the examples are taken to have none in common.
+const MAX_EXTENSIONS: usize = 6;
+/// Content words shorter than this say too little to match on.
+const MIN_WORD_CHARS: usize = 4;
+/// Contents conditions a draft may end up with.
+const MAX_CONTENT_WORDS: usize = 4;
+
+/// A draft rule and how it does on the files it was drafted from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleSuggestion {
+    /// Unsaved, without a folder, and with a Move whose destination is left
+    /// blank for the user.
+    pub rule: Rule,
+    /// Why each condition of `rule` is there, in order.
+    pub explanations: Vec<String>,
+    pub examples: Vec<ExampleCheck>,
+    pub counter_examples: Vec<ExampleCheck>,
+    /// Conditions left out or narrowed to get the examples right.
+    pub notes: Vec<String>,
+    /// What the draft gets wrong, such as a counter-example nothing tells
+    /// apart from the examples. Empty when it's right about every file.
+    pub unsatisfied: Vec<String>,
+}
+
+/// Whether the draft matches a file, with what its conditions captured.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExampleCheck {
+    pub path: PathBuf,
+    pub matched: bool,
+    pub captures: HashMap<String, String>,
+}
+
+/// Drafts a rule that matches every example and none of the
+/// counter-examples, as far as their names, extensions and sizes allow.
+/// Contents are only looked at when every example comes with them.
+pub fn suggest_rule(
+    examples: &[SyntheticFile],
+    counter_examples: &[SyntheticFile],
+    settings: &Settings,
+) -> Result<RuleSuggestion> {
+    let Some(first) = examples.first() else {
+        bail!("Pick at least one example file");
+    };
+    let name = format!("Files like {}", first.info().full_name);
+    let stems: Vec<String> = examples.iter().map(|file| file.info().name).collect();
+    let shape = NameShape::of(&stems);
+    let words = common_words(examples, counter_examples);
+
+    let mut proposals = Vec::new();
+    proposals.extend(extension_proposal(examples));
+    match &shape {
+        Some(shape) => proposals.push(shape.proposal(false)),
+        None => proposals.extend(common_name_proposal(&stems)),
+    }
+    proposals.extend(size_proposal(examples, false));
+    proposals.extend(words.first().map(|word| contents_proposal(word)));
+
+    // Relax: whatever misses an example goes, so the draft starts out
+    // matching all of them.
+    let mut notes = Vec::new();
+    let mut draft_proposals = Vec::new();
+    for proposal in proposals {
+        let rule = draft(&name, std::slice::from_ref(&proposal));
+        if matched_count(&rule, examples, settings)? == examples.len() {
+            draft_proposals.push(proposal);
+        } else {
+            notes.push(format!(
+                "Left out \"{}\": not every example meets it",
+                proposal.explanation
+            ));
+        }
+    }
+
+    // Tighten, one step at a time, while counter-examples still match. A
+    // step is kept only if it leaves out more of them and none of the
+    // examples.
+    let mut steps = Vec::new();
+    if let Some(shape) = shape.as_ref().filter(|shape| shape.varies()) {
+        steps.push((
+            shape.proposal(true),
+            "Narrowed the name to the words the examples use".to_string(),
+        ));
+    }
+    steps.extend(size_proposal(examples, true).map(|proposal| {
+        let note = "Narrowed the size to that of the examples".to_string();
+        (proposal, note)
+    }));
+    for word in words.iter().skip(1) {
+        let note = format!("Added that the contents mention \"{word}\"");
+        steps.push((contents_proposal(word), note));
+    }
+
+    let mut rule = draft(&name, &draft_proposals);
+    let mut counter_matches = matched_count(&rule, counter_examples, settings)?;
+    for (proposal, note) in steps {
+        if counter_matches == 0 {
+            break;
+        }
+        let mut candidate = draft_proposals.clone();
+        match candidate
+            .iter_mut()
+            .find(|existing| existing.slot == proposal.slot && proposal.slot != Slot::Contents)
+        {
+            Some(existing) => *existing = proposal,
+            None => candidate.push(proposal),
+        }
+        let tighter = draft(&name, &candidate);
+        if matched_count(&tighter, examples, settings)? < examples.len() {
+            continue;
+        }
+        let remaining = matched_count(&tighter, counter_examples, settings)?;
+        if remaining < counter_matches {
+            draft_proposals = candidate;
+            rule = tighter;
+            counter_matches = remaining;
+            notes.push(note);
+        }
+    }
+
+    let example_checks = check(&rule, examples, settings)?;
+    let counter_checks = check(&rule, counter_examples, settings)?;
+    let mut unsatisfied = Vec::new();
+    if draft_proposals.is_empty() {
+        unsatisfied.push(
+            "The examples have nothing in common to base a condition on, so the draft \
+             matches every file"
+                .to_string(),
+        );
+    }
+    for example in example_checks.iter().filter(|check| !check.matched) {
+        unsatisfied.push(format!("Doesn't match {}", example.path.display()));
+    }
+    for counter in counter_checks.iter().filter(|check| check.matched) {
+        unsatisfied.push(format!(
+            "Couldn't tell {} apart from the examples",
+            counter.path.display()
+        ));
+    }
+
+    Ok(RuleSuggestion {
+        rule,
+        explanations: draft_proposals
+            .into_iter()
+            .map(|proposal| proposal.explanation)
+            .collect(),
+        examples: example_checks,
+        counter_examples: counter_checks,
+        notes,
+        unsatisfied,
+    })
+}
+
+/// A condition the draft may use and why.
+#[derive(Debug, Clone)]
+struct Proposal {
+    slot: Slot,
+    condition: Condition,
+    explanation: String,
+}
+
+/// What a proposal is about; a tighter one replaces the one in its slot,
+/// except for contents, which add up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Extension,
+    Name,
+    Size,
+    Contents,
+}
+
+fn draft(name: &str, proposals: &[Proposal]) -> Rule {
+    let now = Utc::now();
+    Rule {
+        id: String::new(),
+        folder_id: String::new(),
+        name: name.to_string(),
+        enabled: true,
+        stop_processing: true,
+        conditions: ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: proposals
+                .iter()
+                .map(|proposal| proposal.condition.clone())
+                .collect(),
+        },
+        actions: vec![Action::Move(MoveAction {
+            destination: String::new(),
+            on_conflict: ConflictResolution::Rename,
+            skip_duplicates: false,
+            destination_permissions: Default::default(),
+            quarantine: Default::default(),
+            bring_companions: None,
+            auto_partition: None,
+        })],
+        position: 0,
+        schedule: None,
+        approval_required: false,
+        min_age_seconds: None,
+        path_scope: None,
+        skip_reference_check: false,
+        monthly_byte_budget: None,
+        classification: None,
+        on_reappear: None,
+        record_metadata: HashMap::new(),
+        revision: 0,
+        extra: serde_json::Value::Null,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+fn check(rule: &Rule, files: &[SyntheticFile], settings: &Settings) -> Result<Vec<ExampleCheck>> {
+    files
+        .iter()
+        .map(|file| {
+            let result = simulate_with(rule, file, settings)?;
+            Ok(ExampleCheck {
+                path: file.path.clone(),
+                matched: result.matched,
+                captures: result.captures,
+            })
+        })
+        .collect()
+}
+
+fn matched_count(rule: &Rule, files: &[SyntheticFile], settings: &Settings) -> Result<usize> {
+    Ok(check(rule, files, settings)?
+        .iter()
+        .filter(|check| check.matched)
+        .count())
+}
+
+fn string_condition(operator: StringOperator, value: String) -> StringCondition {
+    StringCondition {
+        operator,
+        value,
+        case_sensitive: false,
+    }
+}
+
+fn extension_proposal(examples: &[SyntheticFile]) -> Option<Proposal> {
+    let extensions: BTreeSet<String> = examples.iter().map(|file| file.info().extension).collect();
+    if extensions.contains("") || extensions.len() > MAX_EXTENSIONS {
+        return None;
+    }
+    let (condition, explanation) = if extensions.len() == 1 {
+        let extension = extensions.into_iter().next()?;
+        let explanation = format!("Every example is a .{extension} file");
+        (string_condition(StringOperator::Is, extension), explanation)
+    } else {
+        let listed: Vec<String> = extensions.iter().map(|ext| format!(".{ext}")).collect();
+        let alternatives: Vec<String> = extensions.iter().map(|ext| escape(ext)).collect();
+        let explanation = format!("The examples are {} files", listed.join(", "));
+        let regex = format!("^(?:{})$", alternatives.join("|"));
+        (
+            string_condition(StringOperator::Matches, regex),
+            explanation,
+        )
+    };
+    Some(Proposal {
+        slot: Slot::Extension,
+        condition: Condition::Extension(condition),
+        explanation,
+    })
+}
+
+/// The longest run of at least three letters or digits every name contains,
+/// for names too different to line up.
+fn common_name_proposal(names: &[String]) -> Option<Proposal> {
+    let lowered: Vec<String> = names.iter().map(|name| name.to_lowercase()).collect();
+    let shortest: Vec<char> = lowered
+        .iter()
+        .min_by_key(|name| name.len())?
+        .chars()
+        .collect();
+    for len in (3..=shortest.len()).rev() {
+        for start in 0..=shortest.len() - len {
+            let run: String = shortest[start..start + len].iter().collect();
+            let run = run.trim_matches(|c: char| !c.is_alphanumeric());
+            if run.chars().count() >= 3 && lowered.iter().all(|name| name.contains(run)) {
+                return Some(Proposal {
+                    slot: Slot::Name,
+                    condition: Condition::Name(string_condition(
+                        StringOperator::Contains,
+                        run.to_string(),
+                    )),
+                    explanation: format!("Every example's name contains \"{run}\""),
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Sizes within a factor of two of each other as a range in KB; `exact`
+/// takes the smallest and largest example to the byte, however spread out.
+fn size_proposal(examples: &[SyntheticFile], exact: bool) -> Option<Proposal> {
+    let min = examples.iter().map(|file| file.size).min()?;
+    let max = examples.iter().map(|file| file.size).max()?;
+    let (unit, low, high, explanation) = if exact {
+        let explanation = format!("Size is between {min} and {max} bytes, as the examples are");
+        (SizeUnit::Bytes, min, max, explanation)
+    } else if min > 0 && max <= min * 2 {
+        let (low, high) = (min / 1024, max.div_ceil(1024));
+        let explanation = format!("Size is between {low} KB and {high} KB, where the examples are");
+        (SizeUnit::Kilobytes, low, high, explanation)
+    } else {
+        return None;
+    };
+    Some(Proposal {
+        slot: Slot::Size,
+        condition: Condition::Size(SizeCondition {
+            operator: ComparisonOperator::Between {
+                min: low,
+                max: high,
+            },
+            value: None,
+            unit,
+        }),
+        explanation,
+    })
+}
+
+fn contents_proposal(word: &str) -> Proposal {
+    Proposal {
+        slot: Slot::Contents,
+        condition: Condition::Contents(ContentsCondition {
+            operator: StringOperator::Contains,
+            value: word.to_string(),
+            case_sensitive: false,
+            source: ContentSource::Auto,
+            min_ocr_confidence: None,
+        }),
+        explanation: format!("Every example's contents mention \"{word}\""),
+    }
+}
+
+/// Words in every example's contents, those fewest counter-examples share
+/// first, then longest first. Empty unless every example has contents.
+fn common_words(examples: &[SyntheticFile], counter_examples: &[SyntheticFile]) -> Vec<String> {
+    let mut common: Option<BTreeSet<String>> = None;
+    for example in examples {
+        let Some(contents) = &example.contents else {
+            return Vec::new();
+        };
+        let words = words(contents);
+        common = Some(match common {
+            Some(common) => common.intersection(&words).cloned().collect(),
+            None => words,
+        });
+    }
+    let counter_words: Vec<BTreeSet<String>> = counter_examples
+        .iter()
+        .map(|file| file.contents.as_deref().map(words).unwrap_or_default())
+        .collect();
+    let mut common: Vec<String> = common.unwrap_or_default().into_iter().collect();
+    common.sort_by_key(|word| {
+        let shared = counter_words
+            .iter()
+            .filter(|words| words.contains(word))
+            .count();
+        (shared, Reverse(word.chars().count()))
+    });
+    common.truncate(MAX_CONTENT_WORDS);
+    common
+}
+
+fn words(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| {
+            word.chars().count() >= MIN_WORD_CHARS && !word.chars().all(|c| c.is_ascii_digit())
+        })
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Letters,
+    Digits,
+    Other,
+}
+
+impl Class {
+    fn of(c: char) -> Self {
+        if c.is_ascii_digit() {
+            Class::Digits
+        } else if c.is_alphabetic() {
+            Class::Letters
+        } else {
+            Class::Other
+        }
+    }
+}
+
+/// Splits a name into runs of letters, digits and everything else.
+fn tokenize(name: &str) -> Vec<(Class, String)> {
+    let mut tokens: Vec<(Class, String)> = Vec::new();
+    for c in name.chars() {
+        let class = Class::of(c);
+        match tokens.last_mut() {
+            Some((last, run)) if *last == class => run.push(c),
+            _ => tokens.push((class, c.to_string())),
+        }
+    }
+    tokens
+}
+
+/// One run of every example's name, at the same place in each.
+struct Column {
+    class: Class,
+    values: Vec<String>,
+}
+
+impl Column {
+    fn same(&self) -> bool {
+        let first = self.values[0].to_lowercase();
+        self.values
+            .iter()
+            .all(|value| value.to_lowercase() == first)
+    }
+}
+
+enum Piece {
+    Literal(String),
+    /// A date, as the regex for it.
+    Date(String),
+    /// Digits that differ, or might; with their width when it doesn't.
+    Number(Option<usize>),
+    /// Letters or separators that differ, with what the examples have.
+    Varying {
+        class: Class,
+        seen: BTreeSet<String>,
+    },
+}
+
+/// Names that line up run for run, such as `Invoice_2024-01-15` and
+/// `Invoice_2024-02-03`.
+struct NameShape {
+    pieces: Vec<Piece>,
+}
+
+impl NameShape {
+    /// `None` unless every name has the same runs in the same order.
+    fn of(names: &[String]) -> Option<Self> {
+        let tokens: Vec<Vec<(Class, String)>> = names.iter().map(|name| tokenize(name)).collect();
+        let first = tokens.first()?;
+        let aligned = tokens.iter().all(|other| {
+            other.len() == first.len() && other.iter().zip(first).all(|(a, b)| a.0 == b.0)
+        });
+        if !aligned {
+            return None;
+        }
+        let columns: Vec<Column> = (0..first.len())
+            .map(|i| Column {
+                class: first[i].0,
+                values: tokens.iter().map(|name| name[i].1.clone()).collect(),
+            })
+            .collect();
+
+        // A single example can't show which digits change, so all of them might.
+        let single = names.len() == 1;
+        let mut pieces = Vec::new();
+        let mut i = 0;
+        while i < columns.len() {
+            if let Some((width, regex)) = date_at(&columns[i..], single) {
+                pieces.push(Piece::Date(regex));
+                i += width;
+                continue;
+            }
+            let column = &columns[i];
+            pieces.push(match column.class {
+                Class::Digits if single || !column.same() => {
+                    let width = column.values[0].len();
+                    let fixed = column.values.iter().all(|value| value.len() == width);
+                    Piece::Number(fixed.then_some(width))
+                }
+                _ if column.same() => Piece::Literal(column.values[0].clone()),
+                class => Piece::Varying {
+                    class,
+                    seen: column.values.iter().cloned().collect(),
+                },
+            });
+            i += 1;
+        }
+        Some(Self { pieces })
+    }
+
+    /// Whether letters or separators differ between examples, so that
+    /// `regex(true)` is tighter than `regex(false)`.
+    fn varies(&self) -> bool {
+        self.pieces
+            .iter()
+            .any(|piece| matches!(piece, Piece::Varying { .. }))
+    }
+
+    /// Dates and numbers are captured. Where letters or separators differ,
+    /// any will do, or with `strict` only those the examples have.
+    fn regex(&self, strict: bool) -> String {
+        let mut regex = String::from("^");
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(text) => regex.push_str(&escape(text)),
+                Piece::Date(date) => {
+                    let _ = write!(regex, "({date})");
+                }
+                Piece::Number(Some(width)) => {
+                    let _ = write!(regex, r"(\d{{{width}}})");
+                }
+                Piece::Number(None) => regex.push_str(r"(\d+)"),
+                Piece::Varying { seen, .. } if strict => {
+                    let alternatives: Vec<String> = seen.iter().map(|text| escape(text)).collect();
+                    let _ = write!(regex, "(?:{})", alternatives.join("|"));
+                }
+                Piece::Varying {
+                    class: Class::Letters,
+                    ..
+                } => regex.push_str(r"\p{Alphabetic}+"),
+                Piece::Varying { .. } => regex.push_str(r"[^\p{Alphabetic}\d]+"),
+            }
+        }
+        regex.push('$');
+        regex
+    }
+
+    fn proposal(&self, strict: bool) -> Proposal {
+        let mut explanation = String::from("Names are shaped like the examples'");
+        if strict && self.varies() {
+            explanation.push_str(", with only the words they use where they differ");
+        }
+        let captured: Vec<String> = self
+            .pieces
+            .iter()
+            .filter_map(|piece| match piece {
+                Piece::Date(_) => Some("the date"),
+                Piece::Number(_) => Some("the number"),
+                _ => None,
+            })
+            .enumerate()
+            .map(|(i, what)| format!("{{{}}} is {what}", i + 1))
+            .collect();
+        if !captured.is_empty() {
+            let _ = write!(explanation, "; {}", captured.join(", "));
+        }
+        Proposal {
+            slot: Slot::Name,
+            condition: Condition::Name(string_condition(
+                StringOperator::Matches,
+                self.regex(strict),
+            )),
+            explanation,
+        }
+    }
+}
+
+/// A date starting at the first column, as `20240115` or as `2024-01-15`
+/// with the same separators in every example: how many columns it takes and
+/// its regex.
+fn date_at(columns: &[Column], single: bool) -> Option<(usize, String)> {
+    let varies = |columns: &[Column]| single || columns.iter().any(|column| !column.same());
+    let digits = |column: &Column, width: usize| {
+        column.class == Class::Digits && column.values.iter().all(|value| value.len() == width)
+    };
+
+    let first = columns.first()?;
+    if digits(first, 8)
+        && varies(&columns[..1])
+        && first
+            .values
+            .iter()
+            .all(|value| valid_date(&value[..4], &value[4..6], &value[6..]))
+    {
+        return Some((1, r"\d{8}".to_string()));
+    }
+
+    let [year, sep1, month, sep2, day, ..] = columns else {
+        return None;
+    };
+    let separator = |column: &Column| {
+        column.class == Class::Other && column.same() && column.values[0].chars().count() == 1
+    };
+    let dated = (0..year.values.len())
+        .all(|i| valid_date(&year.values[i], &month.values[i], &day.values[i]));
+    (digits(year, 4)
+        && digits(month, 2)
+        && digits(day, 2)
+        && separator(sep1)
+        && separator(sep2)
+        && varies(&columns[..5])
+        && dated)
+        .then(|| {
+            let (sep1, sep2) = (escape(&sep1.values[0]), escape(&sep2.values[0]));
+            (5, format!(r"\d{{4}}{sep1}\d{{2}}{sep2}\d{{2}}"))
+        })
+}
+
+fn valid_date(year: &str, month: &str, day: &str) -> bool {
+    match (year.parse(), month.parse(), day.parse()) {
+        (Ok(year), Ok(month), Ok(day)) => {
+            (1900..=2100).contains(&year) && NaiveDate::from_ymd_opt(year, month, day).is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Like `regex::escape`, but leaves `-` alone so dates read naturally; it's
+/// only special inside a class.
+fn escape(text: &str) -> String {
+    text.split('-')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<SyntheticFile> {
+        names
+            .iter()
+            .map(|name| SyntheticFile {
+                size: 120 * 1024,
+                ..SyntheticFile::new(format!("/inbox/{name}"))
+            })
+            .collect()
+    }
+
+    fn name_regex(rule: &Rule) -> &str {
+        rule.conditions
+            .conditions
+            .iter()
+            .find_map(|condition| match condition {
+                Condition::Name(cond) => Some(cond.value.as_str()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn shared_prefix_and_varying_dates_capture_the_date() {
+        let examples = files(&[
+            "Invoice_2024-01-15.pdf",
+            "Invoice_2024-02-03.pdf",
+            "Invoice_2023-12-30.pdf",
+        ]);
+        let suggestion = suggest_rule(&examples, &[], &Settings::default()).unwrap();
+
+        assert_eq!(
+            name_regex(&suggestion.rule),
+            r"^Invoice_(\d{4}-\d{2}-\d{2})$"
+        );
+        assert!(
+            suggestion.unsatisfied.is_empty(),
+            "{:?}",
+            suggestion.unsatisfied
+        );
+        assert!(suggestion.examples.iter().all(|check| check.matched));
+        assert_eq!(suggestion.examples[1].captures["1"], "2024-02-03");
+        assert_eq!(
+            suggestion.explanations.len(),
+            suggestion.rule.conditions.conditions.len()
+        );
+        assert!(suggestion
+            .explanations
+            .iter()
+            .any(|line| line.contains("{1} is the date")));
+        assert!(suggestion
+            .explanations
+            .contains(&"Every example is a .pdf file".to_string()));
+    }
+
+    #[test]
+    fn counter_examples_tighten_the_draft() {
+        let examples = files(&["ACME invoice 0001.pdf", "Globex invoice 0002.pdf"]);
+        let counter_examples = files(&["Spam invoice 0003.pdf"]);
+
+        let loose = suggest_rule(&examples, &[], &Settings::default()).unwrap();
+        assert_eq!(
+            name_regex(&loose.rule),
+            r"^\p{Alphabetic}+ invoice (\d{4})$"
+        );
+
+        let suggestion = suggest_rule(&examples, &counter_examples, &Settings::default()).unwrap();
+        assert_eq!(
+            name_regex(&suggestion.rule),
+            r"^(?:ACME|Globex) invoice (\d{4})$"
+        );
+        assert!(
+            suggestion.unsatisfied.is_empty(),
+            "{:?}",
+            suggestion.unsatisfied
+        );
+        assert!(suggestion.examples.iter().all(|check| check.matched));
+        assert!(!suggestion.counter_examples[0].matched);
+        assert_eq!(
+            suggestion.notes,
+            ["Narrowed the name to the words the examples use"]
+        );
+    }
+
+    #[test]
+    fn contents_tell_apart_what_names_cannot() {
+        let with = |name: &str, contents: &str| SyntheticFile {
+            contents: Some(contents.to_string()),
+            ..files(&[name]).remove(0)
+        };
+        let examples = [
+            with("scan 1.pdf", "Invoice from ACME, total due"),
+            with("scan 2.pdf", "Invoice from Globex, amount due"),
+        ];
+        let counter_examples = [with("scan 3.pdf", "Receipt from ACME")];
+
+        let suggestion = suggest_rule(&examples, &counter_examples, &Settings::default()).unwrap();
+        assert!(
+            suggestion.unsatisfied.is_empty(),
+            "{:?}",
+            suggestion.unsatisfied
+        );
+        assert!(!suggestion.counter_examples[0].matched);
+        assert!(suggestion
+            .explanations
+            .contains(&"Every example's contents mention \"invoice\"".to_string()));
+    }
+
+    #[test]
+    fn impossible_counter_examples_give_a_partial_result() {
+        let examples = files(&["report.pdf"]);
+        let mut counter_examples = files(&["report.pdf"]);
+        counter_examples[0].path = PathBuf::from("/archive/report.pdf");
+
+        let suggestion = suggest_rule(&examples, &counter_examples, &Settings::default()).unwrap();
+        assert!(suggestion.examples[0].matched);
+        assert!(suggestion.counter_examples[0].matched);
+        assert_eq!(
+            suggestion.unsatisfied,
+            ["Couldn't tell /archive/report.pdf apart from the examples"]
+        );
+    }
+
+    #[test]
+    fn names_that_dont_line_up_share_their_longest_common_run() {
+        let examples = files(&["Quarterly report Q1", "report-final", "My report (copy)"]);
+        let suggestion = suggest_rule(&examples, &[], &Settings::default()).unwrap();
+        let Condition::Name(cond) = &suggestion.rule.conditions.conditions[0] else {
+            panic!("expected a name condition");
+        };
+        assert_eq!(cond.operator, StringOperator::Contains);
+        assert_eq!(cond.value, "report");
+        assert!(suggestion.examples.iter().all(|check| check.matched));
+    }
+
+    #[test]
+    fn no_examples_is_an_error() {
+        assert!(suggest_rule(&[], &[], &Settings::default()).is_err());
+    }
+}
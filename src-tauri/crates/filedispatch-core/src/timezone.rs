@@ -0,0 +1,95 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+
+/// Zone name that defers to `Settings::default_timezone`.
+pub const DEFAULT_ZONE: &str = "default";
+
+/// Parses an IANA zone name such as `Europe/Berlin` or `UTC`.
+pub fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.trim()
+        .parse::<Tz>()
+        .map_err(|_| format!("unknown time zone \"{}\"", name.trim()))
+}
+
+/// The zone a condition or token opted into, if any.
+///
+/// `None` keeps the caller's original behavior, as does `"default"` when no
+/// default zone is configured.
+pub fn resolve_timezone(name: Option<&str>, default: Option<&str>) -> Result<Option<Tz>, String> {
+    match name.map(str::trim).filter(|name| !name.is_empty()) {
+        None => Ok(None),
+        Some(DEFAULT_ZONE) => default
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(parse_timezone)
+            .transpose(),
+        Some(name) => parse_timezone(name).map(Some),
+    }
+}
+
+/// Checks a name given on a rule; `"default"` is always accepted.
+pub fn validate_timezone(name: &str) -> Result<(), String> {
+    if name.trim() == DEFAULT_ZONE {
+        return Ok(());
+    }
+    parse_timezone(name).map(|_| ())
+}
+
+/// Calendar day of `instant` in `zone`, or in the system zone when unset.
+pub fn local_date_in(instant: DateTime<Utc>, zone: Option<Tz>) -> NaiveDate {
+    match zone {
+        Some(zone) => instant.with_timezone(&zone).date_naive(),
+        None => instant.with_timezone(&Local).date_naive(),
+    }
+}
+
+/// Wall-clock time of `instant` in `zone`, or in the system zone when unset.
+pub fn local_time_in(instant: DateTime<Utc>, zone: Option<Tz>) -> NaiveTime {
+    match zone {
+        Some(zone) => instant.with_timezone(&zone).time(),
+        None => instant.with_timezone(&Local).time(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn default_defers_to_the_configured_zone() {
+        assert_eq!(resolve_timezone(None, Some("UTC")).unwrap(), None);
+        assert_eq!(resolve_timezone(Some("default"), None).unwrap(), None);
+        assert_eq!(
+            resolve_timezone(Some("default"), Some("Asia/Tokyo")).unwrap(),
+            Some(chrono_tz::Asia::Tokyo)
+        );
+        assert_eq!(
+            resolve_timezone(Some("Europe/Berlin"), Some("Asia/Tokyo")).unwrap(),
+            Some(chrono_tz::Europe::Berlin)
+        );
+    }
+
+    #[test]
+    fn unknown_zones_are_rejected() {
+        assert!(validate_timezone("default").is_ok());
+        assert!(validate_timezone("America/New_York").is_ok());
+        let err = validate_timezone("Mars/Olympus_Mons").unwrap_err();
+        assert!(err.contains("Mars/Olympus_Mons"), "{err}");
+        assert!(resolve_timezone(Some("default"), Some("Nowhere")).is_err());
+    }
+
+    #[test]
+    fn day_rolls_over_at_the_zone_midnight() {
+        // 23:30 UTC is already the next morning in Tokyo.
+        let instant = Utc.with_ymd_and_hms(2024, 6, 1, 23, 30, 0).unwrap();
+        assert_eq!(
+            local_date_in(instant, Some(chrono_tz::UTC)),
+            NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+        );
+        assert_eq!(
+            local_date_in(instant, Some(chrono_tz::Asia::Tokyo)),
+            NaiveDate::from_ymd_opt(2024, 6, 2).unwrap()
+        );
+    }
+}
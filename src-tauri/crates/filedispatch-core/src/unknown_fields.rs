@@ -0,0 +1,169 @@
+//! Keeps JSON fields this version doesn't know about, so rules written by a
+//! newer version survive being loaded and saved again by this one.
+//!
+//! The unknown parts of a document are kept as a sparse copy of it: objects
+//! hold only the unknown keys (and the branches leading to them), arrays keep
+//! their positions with `null` for untouched elements. Tagged objects also
+//! keep their `type`, so a field is never merged into a different kind of
+//! condition or action after the list was edited.
+
+use serde_json::{Map, Value};
+
+/// What `raw` has that `known` (the same document after a round trip through
+/// this version's types) doesn't. `Value::Null` when nothing is missing.
+pub fn unknown_fields(raw: &Value, known: &Value) -> Value {
+    match (raw, known) {
+        (Value::Object(raw), Value::Object(known)) => {
+            let mut unknown = Map::new();
+            for (key, value) in raw {
+                match known.get(key) {
+                    None if !value.is_null() => {
+                        unknown.insert(key.clone(), value.clone());
+                    }
+                    None => {}
+                    Some(known) => {
+                        let nested = unknown_fields(value, known);
+                        if !nested.is_null() {
+                            unknown.insert(key.clone(), nested);
+                        }
+                    }
+                }
+            }
+            if unknown.is_empty() {
+                return Value::Null;
+            }
+            if let Some(tag @ Value::String(_)) = raw.get("type") {
+                unknown.insert("type".to_string(), tag.clone());
+            }
+            Value::Object(unknown)
+        }
+        (Value::Array(raw), Value::Array(known)) => {
+            let mut unknown: Vec<Value> = raw
+                .iter()
+                .zip(known)
+                .map(|(raw, known)| unknown_fields(raw, known))
+                .collect();
+            while unknown.last().is_some_and(Value::is_null) {
+                unknown.pop();
+            }
+            if unknown.is_empty() {
+                Value::Null
+            } else {
+                Value::Array(unknown)
+            }
+        }
+        _ => Value::Null,
+    }
+}
+
+/// Puts fields collected by `unknown_fields` back into `target`. Known fields
+/// always win, and a branch tagged with another `type` is dropped.
+pub fn merge_unknown(target: &mut Value, unknown: &Value) {
+    match (target, unknown) {
+        (Value::Object(target), Value::Object(unknown)) => {
+            if let Some(tag) = unknown.get("type") {
+                if target.get("type").is_some_and(|current| current != tag) {
+                    return;
+                }
+            }
+            for (key, value) in unknown {
+                if key == "type" {
+                    continue;
+                }
+                match target.get_mut(key) {
+                    Some(existing) => merge_unknown(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(unknown)) => {
+            for (target, unknown) in target.iter_mut().zip(unknown) {
+                merge_unknown(target, unknown);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Dotted path of the first unknown field, e.g. `conditions.conditions[0].fuzzy`.
+pub fn first_unknown_path(unknown: &Value) -> Option<String> {
+    match unknown {
+        Value::Object(map) => map.iter().find_map(|(key, value)| {
+            if key == "type" && map.len() > 1 {
+                return None;
+            }
+            match value {
+                Value::Object(_) | Value::Array(_) => match first_unknown_path(value) {
+                    Some(rest) if rest.starts_with('[') => Some(format!("{key}{rest}")),
+                    Some(rest) => Some(format!("{key}.{rest}")),
+                    None => Some(key.clone()),
+                },
+                _ => Some(key.clone()),
+            }
+        }),
+        Value::Array(items) => items.iter().enumerate().find_map(|(index, item)| {
+            first_unknown_path(item).map(|rest| {
+                if rest.starts_with('[') {
+                    format!("[{index}]{rest}")
+                } else {
+                    format!("[{index}].{rest}")
+                }
+            })
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn collects_and_restores_unknown_fields() {
+        let raw = json!({
+            "matchType": "all",
+            "conditions": [
+                {"type": "name", "operator": "is", "value": "a", "caseSensitive": false},
+                {"type": "size", "operator": "greaterThan", "value": 5, "unit": "mb", "fuzzy": true}
+            ],
+            "color": "red"
+        });
+        let mut known = json!({
+            "matchType": "all",
+            "conditions": [
+                {"type": "name", "operator": "is", "value": "a", "caseSensitive": false},
+                {"type": "size", "operator": "greaterThan", "value": 5, "unit": "mb"}
+            ]
+        });
+        let unknown = unknown_fields(&raw, &known);
+        assert_eq!(
+            unknown,
+            json!({"conditions": [null, {"type": "size", "fuzzy": true}], "color": "red"})
+        );
+        assert_eq!(
+            first_unknown_path(&unknown).as_deref(),
+            Some("color")
+        );
+        assert_eq!(
+            first_unknown_path(&json!({"conditions": [null, {"type": "size", "fuzzy": true}]}))
+                .as_deref(),
+            Some("conditions[1].fuzzy")
+        );
+
+        merge_unknown(&mut known, &unknown);
+        assert_eq!(known, raw);
+    }
+
+    #[test]
+    fn unknown_fields_stay_with_their_kind_of_element() {
+        let unknown = json!({"conditions": [{"type": "size", "fuzzy": true}]});
+        // The size condition was removed and a name condition now comes first.
+        let mut edited = json!({"conditions": [{"type": "name", "value": "a"}]});
+        merge_unknown(&mut edited, &unknown);
+        assert_eq!(edited, json!({"conditions": [{"type": "name", "value": "a"}]}));
+        assert!(unknown_fields(&edited, &edited).is_null());
+    }
+}
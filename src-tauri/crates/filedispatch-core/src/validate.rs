@@ -0,0 +1,489 @@
+//! The checks a rule has to pass before it's saved or imported that need
+//! nothing but the rule: regexes compile, time zones exist, pattern tokens
+//! parse, CSV columns make sense. The app adds the ones that look at the
+//! system, like notification sounds and extraction filters.
+
+use anyhow::{bail, Result};
+use regex::RegexBuilder;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::evaluate::{glob_to_regex, list_entries};
+use crate::models::{
+    Action, ArchiveAction, ArchiveFormat, Condition, ConditionGroup, CsvColumnCondition,
+    CsvColumnRef, CsvDelimiter, PasswordSource, Rule, Settings, StringOperator,
+};
+use crate::patterns::{token_timezones, validate_date_formats, validate_map_tokens};
+use crate::rule_schema::import_rules;
+use crate::timezone::validate_timezone;
+
+/// What `validate_rules` found in a rule file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    /// Rules in the file. 0 when the file itself didn't parse.
+    pub rule_count: usize,
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Problem {
+    /// Where in the file, e.g. `rules[2]` or `rules[0].conditions.conditions[1].operator`.
+    pub path: Option<String>,
+    pub message: String,
+}
+
+/// Checks a rule file as exported by the app, or a bare list of rules. A
+/// file that doesn't parse has one problem; otherwise every rule that fails
+/// `validate_rule` has one.
+pub fn validate_rules(json: &str) -> Report {
+    let rules = match import_rules(json) {
+        Ok(rules) => rules,
+        Err(err) => {
+            return Report {
+                rule_count: 0,
+                problems: vec![Problem {
+                    path: err.path,
+                    message: err.message,
+                }],
+            }
+        }
+    };
+    let problems = rules
+        .iter()
+        .enumerate()
+        .filter_map(|(index, rule)| {
+            validate_rule(rule).err().map(|message| Problem {
+                path: Some(format!("rules[{index}]")),
+                message,
+            })
+        })
+        .collect();
+    Report {
+        rule_count: rules.len(),
+        problems,
+    }
+}
+
+pub fn validate_rule(rule: &Rule) -> Result<(), String> {
+    validate_group(&rule.conditions).map_err(|err| format!("Rule \"{}\": {}", rule.name, err))?;
+    validate_patterns(rule)?;
+    for (index, action) in rule.actions.iter().enumerate() {
+        if let Action::Archive(action) = action {
+            validate_archive(action)
+                .map_err(|err| format!("Rule \"{}\": actions[{index}]: {err}", rule.name))?;
+        }
+        if let Action::DispatchToFolder(action) = action {
+            if action.folder_id == rule.folder_id {
+                return Err(format!(
+                    "Rule \"{}\": can't dispatch a file to the folder it's already in",
+                    rule.name
+                ));
+            }
+        }
+    }
+    let mut keys: Vec<_> = rule.record_metadata.keys().collect();
+    keys.sort();
+    for key in keys {
+        validate_metadata_key(key)
+            .map_err(|err| format!("Rule \"{}\": recordMetadata: {err}", rule.name))?;
+    }
+    Ok(())
+}
+
+/// Checks the patterns in the rule's actions and recorded metadata, which
+/// previewing a rule that hasn't been saved needs as much as saving does.
+pub fn validate_patterns(rule: &Rule) -> Result<(), String> {
+    for (index, action) in rule.actions.iter().enumerate() {
+        let value = serde_json::to_value(action).map_err(|err| err.to_string())?;
+        validate_pattern_tokens(&value)
+            .map_err(|err| format!("Rule \"{}\": actions[{index}]: {err}", rule.name))?;
+    }
+    let mut patterns: Vec<_> = rule.record_metadata.iter().collect();
+    patterns.sort();
+    for (key, pattern) in patterns {
+        validate_pattern_tokens(&Value::String(pattern.clone()))
+            .map_err(|err| format!("Rule \"{}\": recordMetadata.{key}: {err}", rule.name))?;
+    }
+    Ok(())
+}
+
+/// Longest key a rule may record metadata under, in bytes.
+pub const MAX_METADATA_KEY_BYTES: usize = 64;
+
+/// Keys are looked up as they're written, so they can't be blank, padded,
+/// overly long or hold the quotes that delimit them in queries.
+fn validate_metadata_key(key: &str) -> Result<(), String> {
+    if key.trim().is_empty() {
+        return Err("a key is blank".to_string());
+    }
+    if key.trim() != key {
+        return Err(format!("key \"{key}\" starts or ends with spaces"));
+    }
+    if key.len() > MAX_METADATA_KEY_BYTES {
+        return Err(format!(
+            "key \"{key}\" is longer than {MAX_METADATA_KEY_BYTES} bytes"
+        ));
+    }
+    if key.contains(['"', '\\']) || key.chars().any(char::is_control) {
+        return Err(format!(
+            "key \"{key}\" has quotes, backslashes or control characters"
+        ));
+    }
+    Ok(())
+}
+
+/// Checks the rule's classification against the codes in `settings`, and
+/// that it has one when they require it. Rules saved before a code was
+/// renamed or removed keep running with it; they only fail here when edited.
+pub fn validate_classification(rule: &Rule, settings: &Settings) -> Result<(), String> {
+    let Some(code) = rule
+        .classification
+        .as_deref()
+        .filter(|code| !code.is_empty())
+    else {
+        if settings.require_classification {
+            return Err(format!("Rule \"{}\": needs a classification", rule.name));
+        }
+        return Ok(());
+    };
+    if settings
+        .classification_codes
+        .iter()
+        .any(|known| known.code == code)
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Rule \"{}\": unknown classification \"{code}\"",
+            rule.name
+        ))
+    }
+}
+
+/// Whether an Archive action keeps its password in the rule itself, where a
+/// plain export, template or backup of the rule would carry it.
+pub fn keeps_literal_password(rule: &Rule) -> bool {
+    rule.actions.iter().any(|action| {
+        matches!(
+            action,
+            Action::Archive(ArchiveAction {
+                encryption: Some(encryption),
+                ..
+            }) if matches!(encryption.password_source, PasswordSource::Literal(_))
+        )
+    })
+}
+
+/// Only zip archives can be encrypted, and the password has to come from
+/// somewhere. Whether it's there is only known when the action runs.
+fn validate_archive(action: &ArchiveAction) -> Result<(), String> {
+    let Some(encryption) = &action.encryption else {
+        return Ok(());
+    };
+    if action.format != ArchiveFormat::Zip {
+        return Err("encryption is not supported for tar archives, only zip".to_string());
+    }
+    let empty = match &encryption.password_source {
+        PasswordSource::Literal(password) => password.is_empty(),
+        PasswordSource::EnvVar(name) | PasswordSource::Keyring(name) => name.trim().is_empty(),
+    };
+    if empty {
+        return Err("archive password source is empty".to_string());
+    }
+    Ok(())
+}
+
+/// The byte a CSV Column condition splits on.
+pub fn ascii_delimiter(value: char) -> Result<u8> {
+    if !value.is_ascii() || value == '"' || value == '\n' || value == '\r' {
+        bail!("Delimiter must be a single ASCII character other than a quote or newline");
+    }
+    Ok(value as u8)
+}
+
+fn validate_group(group: &ConditionGroup) -> Result<(), String> {
+    for condition in &group.conditions {
+        let (operator, pattern, case_sensitive) = match condition {
+            Condition::Name(c)
+            | Condition::Extension(c)
+            | Condition::FullName(c)
+            | Condition::Path(c)
+            | Condition::ParentFolder(c)
+            | Condition::RelativePath(c)
+            | Condition::MediaCodec(c)
+            | Condition::MimeType(c)
+            | Condition::Tag(c) => {
+                (&c.operator, &c.value, c.case_sensitive)
+            }
+            Condition::ArchiveContains(c) => (
+                &c.name_condition.operator,
+                &c.name_condition.value,
+                c.name_condition.case_sensitive,
+            ),
+            Condition::Contents(c) => (&c.operator, &c.value, c.case_sensitive),
+            Condition::CsvColumn(c) => {
+                validate_csv_column(c)?;
+                (
+                    &c.condition.operator,
+                    &c.condition.value,
+                    c.condition.case_sensitive,
+                )
+            }
+            Condition::DateCreated(c)
+            | Condition::DateModified(c)
+            | Condition::DateAdded(c)
+            | Condition::DateLastMatched(c)
+            | Condition::ExifDate(c) => {
+                c.timezone.as_deref().map_or(Ok(()), validate_timezone)?;
+                continue;
+            }
+            Condition::ExistsAtDestination(c) => {
+                if c.destination_pattern.trim().is_empty() {
+                    return Err("Exists at destination needs a folder".to_string());
+                }
+                validate_pattern_tokens(&Value::String(c.destination_pattern.clone()))?;
+                continue;
+            }
+            Condition::ImageDimensions(c) => {
+                if c.width.is_none() && c.height.is_none() && c.shape.is_none() {
+                    return Err("Image dimensions needs a width, height or shape".to_string());
+                }
+                continue;
+            }
+            Condition::CurrentTime(c) => {
+                c.timezone.as_deref().map_or(Ok(()), validate_timezone)?;
+                continue;
+            }
+            Condition::Nested(group) => {
+                validate_group(group)?;
+                continue;
+            }
+            _ => continue,
+        };
+        if matches!(
+            operator,
+            StringOperator::Matches | StringOperator::DoesNotMatch
+        ) {
+            RegexBuilder::new(pattern)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map_err(|err| format!("invalid regex \"{pattern}\": {err}"))?;
+        }
+        if matches!(
+            operator,
+            StringOperator::MatchesGlob | StringOperator::DoesNotMatchGlob
+        ) {
+            glob_to_regex(pattern).map_err(|err| format!("invalid glob \"{pattern}\": {err}"))?;
+        }
+        if matches!(
+            operator,
+            StringOperator::IsOneOf | StringOperator::IsNotOneOf
+        ) && list_entries(pattern).next().is_none()
+        {
+            return Err("\"is one of\" needs at least one entry".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn validate_csv_column(condition: &CsvColumnCondition) -> Result<(), String> {
+    match &condition.column {
+        CsvColumnRef::Index { index: 0 } => {
+            return Err("CSV column numbers start at 1".to_string());
+        }
+        CsvColumnRef::Header { name } if name.trim().is_empty() => {
+            return Err("CSV column needs a header name".to_string());
+        }
+        _ => {}
+    }
+    match condition.delimiter {
+        CsvDelimiter::Char { value } => ascii_delimiter(value)
+            .map(|_| ())
+            .map_err(|err| err.to_string()),
+        CsvDelimiter::Auto => Ok(()),
+    }
+}
+
+/// Any string an action carries may be a pattern, so `@Zone` date tokens,
+/// date formats and mapping tokens are checked wherever they appear.
+fn validate_pattern_tokens(value: &Value) -> Result<(), String> {
+    match value {
+        Value::String(text) => {
+            token_timezones(text)
+                .into_iter()
+                .try_for_each(validate_timezone)?;
+            validate_date_formats(text)?;
+            validate_map_tokens(text)
+        }
+        Value::Array(items) => items.iter().try_for_each(validate_pattern_tokens),
+        Value::Object(fields) => fields.values().try_for_each(validate_pattern_tokens),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ClassificationCode;
+
+    const RULE: &str = r#"{
+        "name": "Invoices",
+        "enabled": true,
+        "stopProcessing": false,
+        "conditions": {
+            "matchType": "all",
+            "conditions": [
+                {"type": "name", "operator": "matches", "value": "INVOICE", "caseSensitive": false}
+            ]
+        },
+        "actions": [{
+            "type": "move",
+            "destination": "~/Invoices/{year}",
+            "onConflict": "rename",
+            "skipDuplicates": false
+        }]
+    }"#;
+
+    fn file(rules: &[&str]) -> String {
+        format!(
+            r#"{{"schemaVersion": "2.0", "rules": [{}]}}"#,
+            rules.join(",")
+        )
+    }
+
+    #[test]
+    fn valid_rules_have_no_problems() {
+        let report = validate_rules(&file(&[RULE, RULE]));
+        assert_eq!(report.rule_count, 2);
+        assert!(report.is_valid(), "{report:?}");
+    }
+
+    #[test]
+    fn every_invalid_rule_is_reported() {
+        let bad_regex = RULE.replace("INVOICE", "[unclosed");
+        let bad_zone = RULE.replace("{year}", "{created:%Y@Nowhere/City}");
+        let bad_format = RULE.replace("{year}", "{created:%Y/%Q}");
+        let report = validate_rules(&file(&[&bad_regex, RULE, &bad_zone, &bad_format]));
+
+        assert_eq!(report.rule_count, 4);
+        let paths: Vec<_> = report.problems.iter().map(|p| p.path.as_deref()).collect();
+        assert_eq!(
+            paths,
+            [Some("rules[0]"), Some("rules[2]"), Some("rules[3]")]
+        );
+        assert!(report.problems[0].message.contains("invalid regex"));
+        assert!(report.problems[1].message.contains("actions[0]"));
+        assert!(report.problems[2].message.contains("isn't a date format"));
+    }
+
+    #[test]
+    fn recorded_metadata_keys_must_be_usable_in_queries() {
+        let with_metadata = |metadata: &str| {
+            RULE.replacen(
+                "\"actions\"",
+                &format!("\"recordMetadata\": {metadata}, \"actions\""),
+                1,
+            )
+        };
+        let good = with_metadata(r#"{"vendor": "{1}", "period": "{created:%Y-%m}"}"#);
+        let quoted = with_metadata(r#"{"ven\"dor": "{1}"}"#);
+        let padded = with_metadata(r#"{" vendor": "{1}"}"#);
+        let bad_zone = with_metadata(r#"{"period": "{created:%Y@Nowhere/City}"}"#);
+        let report = validate_rules(&file(&[&good, &quoted, &padded, &bad_zone]));
+
+        let paths: Vec<_> = report.problems.iter().map(|p| p.path.as_deref()).collect();
+        assert_eq!(
+            paths,
+            [Some("rules[1]"), Some("rules[2]"), Some("rules[3]")]
+        );
+        assert!(report.problems[2].message.contains("recordMetadata.period"));
+    }
+
+    #[test]
+    fn a_file_that_does_not_parse_is_one_problem() {
+        let report = validate_rules(&file(&[&RULE.replace("\"move\"", "\"teleport\"")]));
+        assert_eq!(report.rule_count, 0);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0]
+            .path
+            .as_deref()
+            .unwrap()
+            .starts_with("rules[0]"));
+
+        assert!(!validate_rules("not json").is_valid());
+    }
+
+    #[test]
+    fn only_zip_archives_can_be_encrypted() {
+        let archive = |format: &str| {
+            RULE.replace(
+                r#""type": "move",
+            "destination": "~/Invoices/{year}",
+            "onConflict": "rename",
+            "skipDuplicates": false"#,
+                &format!(
+                    r#""type": "archive",
+            "destination": "~/Archive/{{name}}",
+            "format": "{format}",
+            "deleteAfter": false,
+            "encryption": {{
+                "method": "zipAes256",
+                "passwordSource": {{"type": "envVar", "value": "HR_ARCHIVE_PASSWORD"}}
+            }}"#
+                ),
+            )
+        };
+        let report = validate_rules(&file(&[
+            &archive("zip"),
+            &archive("tar"),
+            &archive("tarGz"),
+        ]));
+
+        assert_eq!(report.rule_count, 3);
+        let paths: Vec<_> = report.problems.iter().map(|p| p.path.as_deref()).collect();
+        assert_eq!(paths, [Some("rules[1]"), Some("rules[2]")]);
+        assert!(report.problems[0].message.contains("not supported for tar"));
+
+        let blank = archive("zip").replace("HR_ARCHIVE_PASSWORD", " ");
+        assert!(!validate_rules(&file(&[&blank])).is_valid());
+
+        let literal = archive("zip").replace("envVar", "literal");
+        let rules = import_rules(&file(&[&archive("zip"), &literal, RULE])).unwrap();
+        let literal: Vec<_> = rules.iter().map(keeps_literal_password).collect();
+        assert_eq!(literal, [false, true, false]);
+    }
+
+    #[test]
+    fn classifications_come_from_the_settings_and_can_be_required() {
+        let mut rule = crate::rule_schema::import_rules(&file(&[RULE]))
+            .unwrap()
+            .remove(0);
+        let mut settings = Settings {
+            classification_codes: vec![ClassificationCode {
+                code: "FIN-7Y".to_string(),
+                label: "Finance, 7 years".to_string(),
+                description: String::new(),
+            }],
+            ..Settings::default()
+        };
+        assert!(validate_classification(&rule, &settings).is_ok());
+
+        settings.require_classification = true;
+        let err = validate_classification(&rule, &settings).unwrap_err();
+        assert!(err.contains("needs a classification"), "{err}");
+
+        rule.classification = Some("FIN-7Y".to_string());
+        assert!(validate_classification(&rule, &settings).is_ok());
+        rule.classification = Some("HR-3Y".to_string());
+        let err = validate_classification(&rule, &settings).unwrap_err();
+        assert!(err.contains("unknown classification \"HR-3Y\""), "{err}");
+    }
+}
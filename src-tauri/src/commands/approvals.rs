@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use filedispatch_core::file_info::FileInfo;
+use tauri::{AppHandle, State};
+
+use crate::core::approvals::{dismiss, ApprovalRunner};
+use crate::core::executor::{ActionExecutor, ActionScope};
+use crate::core::state::AppState;
+use crate::models::{Action, ApprovalError, PendingApproval};
+use crate::storage::approval_repo::ApprovalRepository;
+
+#[tauri::command]
+pub fn approvals_list(state: State<'_, AppState>) -> Result<Vec<PendingApproval>, String> {
+    let repo = ApprovalRepository::new(state.db.clone());
+    let expiry_hours = state
+        .settings
+        .lock()
+        .map(|s| s.approval_expiry_hours)
+        .unwrap_or(0);
+    repo.cleanup_expired(expiry_hours)
+        .map_err(|e| e.to_string())?;
+    repo.list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn approval_execute(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    approval_id: String,
+) -> Result<(), ApprovalError> {
+    let settings = state.settings.lock().map(|s| s.clone()).unwrap_or_default();
+    let executor = ActionExecutor::new(app, state.settings.clone(), state.ocr.clone());
+    let plan = |actions: &[Action],
+                info: &FileInfo,
+                captures: &HashMap<String, String>,
+                _: &ActionScope| executor.simulate_actions(actions, info, captures);
+    let execute = |actions: &[Action],
+                   info: &FileInfo,
+                   captures: &HashMap<String, String>,
+                   scope: &ActionScope| {
+        executor.execute_actions(actions, info, captures, scope)
+    };
+    ApprovalRunner {
+        db: &state.db,
+        settings: &settings,
+        ocr: &state.ocr,
+        duplicates: &state.duplicate_detector,
+        plan: &plan,
+        execute: &execute,
+    }
+    .execute(&approval_id, Utc::now())
+}
+
+#[tauri::command]
+pub fn approval_dismiss(
+    state: State<'_, AppState>,
+    approval_id: String,
+) -> Result<(), ApprovalError> {
+    dismiss(&state.db, &approval_id)
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+use chrono::Utc;
+use tauri::{AppHandle, State};
+
+use crate::commands::settings::apply_settings;
+use crate::core::backup::{
+    backup_dir, backup_settings, list_backups, restore_backup, run_backup, write_log,
+};
+use crate::core::state::AppState;
+use crate::models::{BackupError, BackupInfo, LogStatus, Settings};
+use crate::storage::folder_repo::FolderRepository;
+use crate::utils::platform::normalize_user_path;
+
+/// Backs up now, encrypted with `password` when one is given.
+#[tauri::command]
+pub fn backup_now(
+    state: State<'_, AppState>,
+    password: Option<String>,
+) -> Result<BackupInfo, String> {
+    run_backup(
+        &state.db,
+        &current_settings(&state),
+        Utc::now(),
+        password.as_deref(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn backup_list(state: State<'_, AppState>) -> Result<Vec<BackupInfo>, String> {
+    let dir = backup_dir(&state.db, &current_settings(&state).auto_backup);
+    let version = state.db.schema_version().map_err(|e| e.to_string())?;
+    list_backups(&dir, version).map_err(|e| e.to_string())
+}
+
+/// Swaps the database for a backup and applies the settings saved with it.
+/// Processing is paused and folders unwatched meanwhile. The backup settings
+/// themselves stay as they are, so backups keep going to the same place. An
+/// encrypted backup needs its `password`.
+#[tauri::command]
+pub fn backup_restore(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    backup_id: String,
+    password: Option<String>,
+) -> Result<(), BackupError> {
+    let settings = current_settings(&state);
+    let dir = backup_dir(&state.db, &settings.auto_backup);
+    let saved = backup_settings(&dir, &backup_id, password.as_deref())?;
+
+    let was_paused = state.paused.swap(true, Ordering::SeqCst);
+    if let Ok(mut watcher) = state.watcher.lock() {
+        for (path, _, _) in watcher.list_watched_folders() {
+            let _ = watcher.unwatch_folder(&path);
+        }
+    }
+
+    let mut result = restore_backup(&state.db, &dir, &backup_id, password.as_deref());
+    if result.is_ok() {
+        state.rule_timings.reload(&state.db);
+        if let Some(mut saved) = saved {
+            saved.auto_backup = settings.auto_backup.clone();
+            result = apply_settings(&app, &state, saved).map_err(BackupError::failed);
+        }
+    }
+
+    // Watch whichever database is in place now, restored or not.
+    let folders = FolderRepository::new(state.db.clone())
+        .list()
+        .unwrap_or_default();
+    state.folder_lanes.sync(&folders);
+    if let Ok(mut watcher) = state.watcher.lock() {
+        for folder in folders.into_iter().filter(|f| f.enabled) {
+            let normalized = normalize_user_path(&folder.path);
+            let _ = watcher.watch_folder(
+                normalized,
+                folder.id.clone(),
+                folder.scan_depth,
+                &folder.effective_passthrough_patterns(),
+            );
+        }
+    }
+    state.paused.store(was_paused, Ordering::SeqCst);
+
+    let mut metadata = HashMap::new();
+    metadata.insert("backup_id".to_string(), backup_id.clone());
+    let (status, error_message) = match &result {
+        Ok(()) => (LogStatus::Success, None),
+        Err(err) => (LogStatus::Error, Some(err.message.clone())),
+    };
+    write_log(
+        &state.db,
+        "restore",
+        dir.join(&backup_id).to_string_lossy().to_string(),
+        status,
+        error_message,
+        metadata,
+    );
+    result
+}
+
+fn current_settings(state: &AppState) -> Settings {
+    state.settings.lock().map(|s| s.clone()).unwrap_or_default()
+}
@@ -0,0 +1,45 @@
+use tauri::{AppHandle, Emitter, State};
+
+use crate::core::classify::{self, ClassifierSummary};
+use crate::core::state::AppState;
+use crate::utils::platform::normalize_user_path;
+
+#[tauri::command]
+pub async fn classifier_train(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ClassifierSummary, String> {
+    let settings_snapshot = state
+        .settings
+        .lock()
+        .map(|s| s.clone())
+        .map_err(|e| e.to_string())?;
+    if !settings_snapshot.classifier_enabled {
+        return Err("The classifier is disabled in settings".to_string());
+    }
+    if settings_snapshot.classifier_root.trim().is_empty() {
+        return Err("No category folder configured for the classifier".to_string());
+    }
+
+    let root = normalize_user_path(&settings_snapshot.classifier_root);
+    let max_files = settings_snapshot.classifier_max_files_per_category.max(1) as usize;
+
+    let model = {
+        let mut ocr_guard = state.ocr.lock().map_err(|e| e.to_string())?;
+        classify::train_from_root(&root, &settings_snapshot, &mut ocr_guard, max_files, |progress| {
+            let _ = app.emit("classifier_progress", progress.clone());
+        })
+        .map_err(|e| e.to_string())?
+    };
+
+    let path = classify::default_model_path().map_err(|e| e.to_string())?;
+    classify::save_model(&model, &path).map_err(|e| e.to_string())?;
+    let summary = model.summary();
+    classify::set_active_model(model);
+    Ok(summary)
+}
+
+#[tauri::command]
+pub fn classifier_status() -> Result<Option<ClassifierSummary>, String> {
+    Ok(classify::active_model().map(|model| model.summary()))
+}
@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use filedispatch_core::file_info::FileInfo;
+use tauri::{AppHandle, State};
+
+use crate::core::conflicts::ConflictRunner;
+use crate::core::executor::{ActionExecutor, ActionScope};
+use crate::core::state::AppState;
+use crate::models::{Action, ConflictDecision, ConflictError, PendingConflict};
+use crate::storage::conflict_repo::ConflictRepository;
+
+#[tauri::command]
+pub fn conflicts_list(state: State<'_, AppState>) -> Result<Vec<PendingConflict>, String> {
+    ConflictRepository::new(state.db.clone())
+        .list()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn conflict_resolve(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    decision: ConflictDecision,
+) -> Result<(), ConflictError> {
+    let settings = state.settings.lock().map(|s| s.clone()).unwrap_or_default();
+    let executor = ActionExecutor::new(app, state.settings.clone(), state.ocr.clone());
+    let execute =
+        |actions: &[Action],
+         info: &FileInfo,
+         captures: &HashMap<String, String>,
+         scope: &ActionScope| { executor.execute_actions(actions, info, captures, scope) };
+    let on_conflict = |conflict: &PendingConflict| executor.notify_pending_conflict(conflict);
+    ConflictRunner {
+        db: &state.db,
+        settings: &settings,
+        ocr: &state.ocr,
+        duplicates: &state.duplicate_detector,
+        execute: &execute,
+        on_conflict: &on_conflict,
+    }
+    .resolve(&id, &decision)
+}
@@ -2,8 +2,15 @@ use std::sync::atomic::Ordering;
 
 use tauri::State;
 
+use crate::core::destinations::offline_destinations;
+use crate::core::ocr::OcrManager;
 use crate::core::state::AppState;
-use crate::models::{EngineStatusSnapshot, WatchedFolder};
+use crate::core::event_journal::to_jsonl;
+use crate::models::{
+    CommandError, EngineStatusSnapshot, JournalPage, JournalQuery, Lane, LaneOccupancy,
+    OfflineDestination, RuleTiming, WatchedFolder,
+};
+use crate::utils::sealed;
 
 #[tauri::command]
 pub fn engine_status_get(state: State<'_, AppState>) -> Result<EngineStatusSnapshot, String> {
@@ -13,6 +20,7 @@ pub fn engine_status_get(state: State<'_, AppState>) -> Result<EngineStatusSnaps
         .map_err(|e| e.to_string())?
         .clone();
     status.paused = state.paused.load(Ordering::SeqCst);
+    status.serialized_folders = state.folder_lanes.snapshot();
 
     let dry_run = state
         .settings
@@ -20,13 +28,13 @@ pub fn engine_status_get(state: State<'_, AppState>) -> Result<EngineStatusSnaps
         .map(|s| s.dry_run)
         .unwrap_or(false);
 
-    let watched_folders = state
-        .watcher
-        .lock()
-        .map_err(|e| e.to_string())?
+    let watcher = state.watcher.lock().map_err(|e| e.to_string())?;
+    let drops = watcher.passthrough_drops();
+    let watched_folders = watcher
         .list_watched_folders()
         .into_iter()
         .map(|(path, folder_id, scan_depth)| WatchedFolder {
+            passthrough_drops: drops.get(&folder_id).copied().unwrap_or(0),
             folder_id,
             path: path.to_string_lossy().to_string(),
             scan_depth,
@@ -61,3 +69,80 @@ pub fn engine_pause_toggle(state: State<'_, AppState>) -> Result<bool, String> {
     }
     Ok(next)
 }
+
+/// Destination volumes that are offline, with how many transfers wait for each.
+#[tauri::command]
+pub fn destinations_status(state: State<'_, AppState>) -> Result<Vec<OfflineDestination>, String> {
+    offline_destinations(&state.db).map_err(|e| e.to_string())
+}
+
+/// Per-rule evaluation and execution statistics, slowest first.
+#[tauri::command]
+pub fn rule_timings(state: State<'_, AppState>) -> Result<Vec<RuleTiming>, String> {
+    Ok(state.rule_timings.snapshot())
+}
+
+/// How many commands run and wait in each lane, for debugging stalls.
+#[tauri::command]
+pub fn command_stats(state: State<'_, AppState>) -> Vec<LaneOccupancy> {
+    state.command_lanes.occupancy()
+}
+
+/// Cancels the command started with `request_id`, whether it's waiting or
+/// running, along with OCR it's doing. Returns whether there was one.
+#[tauri::command]
+pub fn command_cancel(state: State<'_, AppState>, request_id: String) -> bool {
+    let found = state.command_lanes.cancel(&request_id);
+    if found {
+        OcrManager::cancel_request(&request_id);
+    }
+    found
+}
+
+#[tauri::command(async)]
+pub fn event_journal_query(
+    state: State<'_, AppState>,
+    query: JournalQuery,
+) -> Result<JournalPage, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadLight, None)?;
+    let entries = state
+        .event_journal
+        .query(&state.db, &query)
+        .map_err(|e| e.to_string())?;
+    Ok(JournalPage {
+        entries,
+        dropped: state.event_journal.dropped(),
+    })
+}
+
+/// The journal between `since` and `until` as JSON lines, oldest first,
+/// encrypted with `password` when one is given.
+#[tauri::command(async)]
+pub fn event_journal_export(
+    state: State<'_, AppState>,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    until: Option<chrono::DateTime<chrono::Utc>>,
+    password: Option<String>,
+) -> Result<String, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadHeavy, None)?;
+    let limit = state
+        .settings
+        .lock()
+        .map(|s| s.event_journal_max_entries)
+        .unwrap_or_default();
+    let query = JournalQuery {
+        path_contains: None,
+        since,
+        until,
+        limit: Some(limit),
+    };
+    let entries = state
+        .event_journal
+        .query(&state.db, &query)
+        .map_err(|e| e.to_string())?;
+    let lines = to_jsonl(&entries).map_err(|e| e.to_string())?;
+    Ok(match password {
+        Some(password) => sealed::seal_text(&lines, &password),
+        None => lines,
+    })
+}
@@ -0,0 +1,31 @@
+use tauri::AppHandle;
+
+use crate::core::faces::{self, FaceModelStatus};
+use crate::core::model_manager::ModelManager;
+
+#[tauri::command]
+pub async fn faces_model_status() -> Result<FaceModelStatus, String> {
+    Ok(faces::model_status())
+}
+
+#[tauri::command]
+pub async fn faces_download_model(app: AppHandle) -> Result<(), String> {
+    if !faces::model_status().supported {
+        return Err("This build of File Dispatch has no face detection".to_string());
+    }
+    let manifest = ModelManager::fetch_manifest()
+        .await
+        .map_err(|e| e.to_string())?;
+    let manager = ModelManager::new().map_err(|e| e.to_string())?;
+    manager
+        .ensure_face_model(&app, &manifest)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn faces_delete_model() -> Result<(), String> {
+    let manager = ModelManager::new().map_err(|e| e.to_string())?;
+    manager.delete_face_model().map_err(|e| e.to_string())
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use tauri::State;
+
+use crate::commands::presets::apply_variables_to_stored_rule;
+use crate::core::arrivals::MAX_GROUP_WINDOW_MS;
+use crate::core::folder_templates::{
+    delete_template, export_template, import_template, list_templates, load_template,
+    save_template, template_dir, FOLDER_VARIABLE,
+};
+use crate::core::health::validate_rule;
+use crate::core::quick_setup::same_path;
+use crate::core::state::AppState;
+use crate::models::{FolderSettings, FolderTemplateApplied, FolderTemplateInfo, PresetVariable};
+use crate::storage::database::Database;
+use crate::storage::folder_repo::{FolderRepository, FolderSetup};
+use crate::storage::rule_repo::RuleRepository;
+use crate::utils::platform::normalize_user_path;
+
+/// Saves a folder's options and rules as a template named `name`.
+#[tauri::command]
+pub fn folder_template_save(
+    state: State<'_, AppState>,
+    folder_id: String,
+    name: String,
+    variables: Option<Vec<PresetVariable>>,
+) -> Result<FolderTemplateInfo, String> {
+    let folder = FolderRepository::new(state.db.clone())
+        .get(&folder_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Folder not found".to_string())?;
+    if folder.is_group {
+        return Err("Groups can't be saved as templates".to_string());
+    }
+    let rules = RuleRepository::new(state.db.clone())
+        .list_by_folder(&folder.id)
+        .map_err(|e| e.to_string())?;
+    save_template(
+        &template_dir(&state.db),
+        &folder,
+        &rules,
+        &name,
+        variables.unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn folder_template_list(state: State<'_, AppState>) -> Result<Vec<FolderTemplateInfo>, String> {
+    list_templates(&template_dir(&state.db)).map_err(|e| e.to_string())
+}
+
+/// Watches `path` set up from `template`. When the template has variables
+/// without a value, nothing is created and they're returned to be asked for.
+#[tauri::command]
+pub fn folder_template_apply(
+    state: State<'_, AppState>,
+    template: String,
+    path: String,
+    name: Option<String>,
+    variables: Option<HashMap<String, String>>,
+) -> Result<FolderTemplateApplied, String> {
+    let applied = apply_folder_template(
+        &state.db,
+        &template_dir(&state.db),
+        &template,
+        &path,
+        name,
+        &variables.unwrap_or_default(),
+    )?;
+    if let FolderTemplateApplied::Created { folder, .. } = &applied {
+        state
+            .folder_lanes
+            .set_strict(&folder.id, folder.strict_sequential);
+        if let Ok(mut watcher) = state.watcher.lock() {
+            let normalized = normalize_user_path(&folder.path);
+            let _ = watcher.watch_folder(
+                normalized,
+                folder.id.clone(),
+                folder.scan_depth,
+                &folder.effective_passthrough_patterns(),
+            );
+        }
+    }
+    Ok(applied)
+}
+
+#[tauri::command]
+pub fn folder_template_delete(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    delete_template(&template_dir(&state.db), &id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn folder_template_export(
+    state: State<'_, AppState>,
+    id: String,
+    path: String,
+) -> Result<(), String> {
+    export_template(&template_dir(&state.db), &id, &normalize_user_path(&path))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn folder_template_import(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<FolderTemplateInfo, String> {
+    import_template(&template_dir(&state.db), &normalize_user_path(&path))
+        .map_err(|e| e.to_string())
+}
+
+/// Creates the folder and its rules from the template. Variables come from
+/// `provided`, then their defaults, and `${folder}` is the new folder. Every
+/// rule is validated first, and the folder and rules are created in one
+/// transaction, so a template that fails leaves nothing behind.
+fn apply_folder_template(
+    db: &Database,
+    dir: &Path,
+    id: &str,
+    path: &str,
+    name: Option<String>,
+    provided: &HashMap<String, String>,
+) -> Result<FolderTemplateApplied, String> {
+    let (info, mut rules) = load_template(dir, id).map_err(|e| e.to_string())?;
+    let target = normalize_user_path(path);
+    if !target.is_dir() {
+        return Err(format!("{} isn't a folder", target.display()));
+    }
+    let repo = FolderRepository::new(db.clone());
+    let watched = repo.list().map_err(|e| e.to_string())?;
+    if watched
+        .iter()
+        .any(|folder| !folder.is_group && same_path(&normalize_user_path(&folder.path), &target))
+    {
+        return Err(format!("{} is already being watched", target.display()));
+    }
+
+    let target_str = target.to_string_lossy().to_string();
+    let mut vars = HashMap::from([(FOLDER_VARIABLE.to_string(), target_str.clone())]);
+    let mut missing = Vec::new();
+    for var in info.variables {
+        let value = provided
+            .get(&var.id)
+            .or(var.default.as_ref())
+            .filter(|value| !value.trim().is_empty());
+        match value {
+            Some(value) => {
+                vars.insert(var.id.clone(), value.clone());
+            }
+            None => missing.push(var),
+        }
+    }
+    if !missing.is_empty() {
+        return Ok(FolderTemplateApplied::NeedsVariables { variables: missing });
+    }
+
+    for rule in rules.iter_mut() {
+        apply_variables_to_stored_rule(rule, &vars);
+        validate_rule(rule)?;
+    }
+    let settings = FolderSettings {
+        incomplete_timeout_minutes: info.settings.incomplete_timeout_minutes.max(1),
+        group_window_ms: info.settings.group_window_ms.min(MAX_GROUP_WINDOW_MS),
+        ..info.settings
+    };
+    let name = name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| Some(target.file_name()?.to_string_lossy().to_string()))
+        .unwrap_or_else(|| target_str.clone());
+
+    let (folder, rules) = repo
+        .create_with_rules(vec![FolderSetup {
+            path: target_str,
+            name,
+            settings: Some(settings),
+            rules,
+        }])
+        .map_err(|e| e.to_string())?
+        .remove(0);
+    Ok(FolderTemplateApplied::Created { folder, rules })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_folder_template;
+    use crate::core::folder_templates::{save_template, template_dir};
+    use crate::models::{
+        Action, Condition, ConditionGroup, ConflictResolution, Folder, FolderSettings,
+        FolderTemplateApplied, MatchType, MoveAction, PlaceholderMode, Rule, StringCondition,
+        StringOperator,
+    };
+    use crate::storage::database::Database;
+    use crate::storage::folder_repo::{FolderRepository, FolderSetup};
+    use crate::storage::rule_repo::RuleRepository;
+    use crate::utils::platform::normalize_user_path;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use tempfile::{tempdir, TempDir};
+
+    fn rule(pattern: &str, destination: &str) -> Rule {
+        let now = chrono::Utc::now();
+        Rule {
+            id: String::new(),
+            folder_id: String::new(),
+            name: format!("Sort {pattern}"),
+            enabled: true,
+            stop_processing: true,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![Condition::Name(StringCondition {
+                    operator: StringOperator::Matches,
+                    value: pattern.to_string(),
+                    case_sensitive: false,
+                })],
+            },
+            actions: vec![Action::Move(MoveAction {
+                destination: destination.to_string(),
+                on_conflict: ConflictResolution::Rename,
+                skip_duplicates: false,
+                destination_permissions: Default::default(),
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
+            })],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// A watched `source` folder with the rules `rules` gives for its path,
+    /// and an unwatched `target` next to it.
+    fn setup(rules: impl FnOnce(&Path) -> Vec<Rule>) -> (TempDir, Database, Folder, PathBuf) {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let target = temp.path().join("target");
+        std::fs::create_dir(&source).unwrap();
+        std::fs::create_dir(&target).unwrap();
+        let db = Database::new_with_path(temp.path().join("test.db")).unwrap();
+        let settings = FolderSettings {
+            scan_depth: -1,
+            remove_duplicates: true,
+            trash_incomplete_downloads: false,
+            incomplete_timeout_minutes: 30,
+            placeholder_mode: PlaceholderMode::Hydrate,
+            strict_sequential: true,
+            group_window_ms: 2_000,
+            passthrough_patterns: vec!["desktop.ini".to_string()],
+            respect_git: true,
+            skip_git_tracked: false,
+            activity_thumbnails: false,
+            trash_max_mb: 0,
+        };
+        let (folder, _) = FolderRepository::new(db.clone())
+            .create_with_rules(vec![FolderSetup {
+                path: source.to_string_lossy().to_string(),
+                name: "Source".to_string(),
+                settings: Some(settings),
+                rules: rules(&source),
+            }])
+            .unwrap()
+            .remove(0);
+        (temp, db, folder, target)
+    }
+
+    fn stored_rules(db: &Database, folder: &Folder) -> Vec<Rule> {
+        RuleRepository::new(db.clone())
+            .list_by_folder(&folder.id)
+            .unwrap()
+    }
+
+    fn rule_count(db: &Database) -> i64 {
+        db.with_conn(|conn| Ok(conn.query_row("SELECT COUNT(*) FROM rules", [], |row| row.get(0))?))
+            .unwrap()
+    }
+
+    #[test]
+    fn save_and_apply_round_trip_onto_a_new_folder() {
+        let (_temp, db, source, target) =
+            setup(|source| vec![rule("\\.pdf$", &source.join("PDFs").to_string_lossy())]);
+        let dir = template_dir(&db);
+        let rules = stored_rules(&db, &source);
+        let info = save_template(&dir, &source, &rules, "Inbox", vec![]).unwrap();
+
+        let applied = apply_folder_template(
+            &db,
+            &dir,
+            &info.id,
+            &target.to_string_lossy(),
+            None,
+            &HashMap::new(),
+        )
+        .unwrap();
+        let FolderTemplateApplied::Created { folder, rules } = applied else {
+            panic!("expected the folder to be created");
+        };
+        assert_eq!(folder.name, "target");
+        assert_eq!(folder.settings(), source.settings());
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].folder_id, folder.id);
+        let Action::Move(action) = &rules[0].actions[0] else {
+            panic!("expected a move");
+        };
+        assert_eq!(
+            normalize_user_path(&action.destination),
+            target.join("PDFs")
+        );
+        assert_eq!(rule_count(&db), 2);
+    }
+
+    #[test]
+    fn undefined_variables_are_asked_for() {
+        let (_temp, db, source, target) = setup(|_| vec![rule("\\.pdf$", "${archive}/PDFs")]);
+        let dir = template_dir(&db);
+        let rules = stored_rules(&db, &source);
+        let info = save_template(&dir, &source, &rules, "Archive", vec![]).unwrap();
+        let target = target.to_string_lossy().to_string();
+
+        let applied =
+            apply_folder_template(&db, &dir, &info.id, &target, None, &HashMap::new()).unwrap();
+        let FolderTemplateApplied::NeedsVariables { variables } = applied else {
+            panic!("expected a prompt for the variable");
+        };
+        assert_eq!(variables.len(), 1);
+        assert_eq!(variables[0].id, "archive");
+        assert_eq!(FolderRepository::new(db.clone()).list().unwrap().len(), 1);
+
+        let provided = HashMap::from([("archive".to_string(), "/srv/archive".to_string())]);
+        let applied = apply_folder_template(&db, &dir, &info.id, &target, None, &provided).unwrap();
+        let FolderTemplateApplied::Created { rules, .. } = applied else {
+            panic!("expected the folder to be created");
+        };
+        let Action::Move(action) = &rules[0].actions[0] else {
+            panic!("expected a move");
+        };
+        assert_eq!(action.destination, "/srv/archive/PDFs");
+    }
+
+    #[test]
+    fn a_template_with_an_invalid_rule_creates_nothing() {
+        let (_temp, db, source, target) = setup(|_| vec![rule("\\.pdf$", "/tmp/PDFs")]);
+        let dir = template_dir(&db);
+        let mut rules = stored_rules(&db, &source);
+        rules.push(rule("([unclosed", "/tmp/broken"));
+        let info = save_template(&dir, &source, &rules, "Broken", vec![]).unwrap();
+
+        let err = apply_folder_template(
+            &db,
+            &dir,
+            &info.id,
+            &target.to_string_lossy(),
+            None,
+            &HashMap::new(),
+        )
+        .unwrap_err();
+        assert!(err.contains("Sort ([unclosed"), "{err}");
+        assert_eq!(FolderRepository::new(db.clone()).list().unwrap().len(), 1);
+        assert_eq!(rule_count(&db), 1);
+    }
+}
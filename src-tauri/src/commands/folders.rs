@@ -1,7 +1,10 @@
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 
+use crate::core::arrivals::MAX_GROUP_WINDOW_MS;
+use crate::core::relink::{relink_folder, rewatch, PATH_CHANGED_EVENT};
 use crate::core::state::AppState;
-use crate::models::Folder;
+use crate::core::watcher::check_passthrough_patterns;
+use crate::models::{CommandError, Folder, FolderKind, FolderSettings, Lane};
 use crate::storage::folder_repo::FolderRepository;
 use crate::utils::platform::normalize_user_path;
 
@@ -11,13 +14,19 @@ pub fn folder_list(state: State<'_, AppState>) -> Result<Vec<Folder>, String> {
     repo.list().map_err(|e| e.to_string())
 }
 
+/// Adds a watched folder, or with `kind` of `SystemTrash`, a folder whose
+/// rules run over the system trash; that one has no path and isn't watched.
 #[tauri::command]
 pub fn folder_add(
     state: State<'_, AppState>,
     path: String,
     name: String,
+    kind: Option<FolderKind>,
 ) -> Result<Folder, String> {
     let repo = FolderRepository::new(state.db.clone());
+    if kind == Some(FolderKind::SystemTrash) {
+        return repo.create_system_trash(&name).map_err(|e| e.to_string());
+    }
     let normalized = normalize_user_path(&path);
     let normalized_str = normalized.to_string_lossy().to_string();
     let folder = repo
@@ -25,7 +34,12 @@ pub fn folder_add(
         .map_err(|e| e.to_string())?;
     if folder.enabled {
         if let Ok(mut watcher) = state.watcher.lock() {
-            let _ = watcher.watch_folder(normalized, folder.id.clone(), folder.scan_depth);
+            let _ = watcher.watch_folder(
+                normalized,
+                folder.id.clone(),
+                folder.scan_depth,
+                &folder.effective_passthrough_patterns(),
+            );
         }
     }
     Ok(folder)
@@ -40,6 +54,7 @@ pub fn folder_remove(state: State<'_, AppState>, id: String) -> Result<(), Strin
             let _ = watcher.unwatch_folder(normalized.as_ref());
         }
     }
+    state.folder_lanes.set_strict(&id, false);
     repo.delete(&id).map_err(|e| e.to_string())
 }
 
@@ -51,7 +66,12 @@ pub fn folder_toggle(state: State<'_, AppState>, id: String, enabled: bool) -> R
         if let Ok(mut watcher) = state.watcher.lock() {
             let normalized = normalize_user_path(&folder.path);
             if enabled {
-                let _ = watcher.watch_folder(normalized, folder.id.clone(), folder.scan_depth);
+                let _ = watcher.watch_folder(
+                    normalized,
+                    folder.id.clone(),
+                    folder.scan_depth,
+                    &folder.effective_passthrough_patterns(),
+                );
             } else {
                 let _ = watcher.unwatch_folder(normalized.as_ref());
             }
@@ -60,31 +80,40 @@ pub fn folder_toggle(state: State<'_, AppState>, id: String, enabled: bool) -> R
     repo.set_enabled(&id, enabled).map_err(|e| e.to_string())
 }
 
-#[tauri::command]
+#[tauri::command(async)]
 pub fn folder_update_settings(
     state: State<'_, AppState>,
     id: String,
-    scan_depth: i32,
-    remove_duplicates: bool,
-    trash_incomplete_downloads: bool,
-    incomplete_timeout_minutes: u32,
-) -> Result<(), String> {
+    settings: FolderSettings,
+    confirm_broad_passthrough: Option<bool>,
+) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
+    check_passthrough_patterns(
+        &settings.passthrough_patterns,
+        confirm_broad_passthrough.unwrap_or(false),
+    )?;
     let repo = FolderRepository::new(state.db.clone());
-    let folder = repo
+    let mut folder = repo
         .get(&id)
         .map_err(|e| e.to_string())?
         .ok_or_else(|| "Folder not found".to_string())?;
 
     // Update database
-    let normalized_timeout = incomplete_timeout_minutes.max(1);
-    repo.update_settings(
-        &id,
-        scan_depth,
-        remove_duplicates,
-        trash_incomplete_downloads,
-        normalized_timeout,
-    )
-    .map_err(|e| e.to_string())?;
+    let settings = FolderSettings {
+        incomplete_timeout_minutes: settings.incomplete_timeout_minutes.max(1),
+        group_window_ms: settings.group_window_ms.min(MAX_GROUP_WINDOW_MS),
+        passthrough_patterns: settings
+            .passthrough_patterns
+            .iter()
+            .map(|pattern| pattern.trim().to_string())
+            .filter(|pattern| !pattern.is_empty())
+            .collect(),
+        skip_git_tracked: settings.respect_git && settings.skip_git_tracked,
+        ..settings
+    };
+    repo.update_settings(&id, &settings).map_err(|e| e.to_string())?;
+    state.folder_lanes.set_strict(&id, settings.strict_sequential);
+    folder.apply_settings(&settings);
 
     // Update watcher if folder is enabled
     if folder.enabled {
@@ -92,7 +121,12 @@ pub fn folder_update_settings(
             let normalized = normalize_user_path(&folder.path);
             // Unwatch and re-watch to update depth settings
             let _ = watcher.unwatch_folder(normalized.as_ref());
-            let _ = watcher.watch_folder(normalized, id, scan_depth);
+            let _ = watcher.watch_folder(
+                normalized,
+                id,
+                settings.scan_depth,
+                &folder.effective_passthrough_patterns(),
+            );
         }
     }
 
@@ -129,3 +163,26 @@ pub fn folder_rename(
     let repo = FolderRepository::new(state.db.clone());
     repo.rename(&id, &name).map_err(|e| e.to_string())
 }
+
+/// Points a folder that was moved or went missing at `path`, keeping its
+/// rules. Destinations that pointed into the old path follow it.
+#[tauri::command]
+pub fn folder_relink(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    path: String,
+) -> Result<Folder, String> {
+    let repo = FolderRepository::new(state.db.clone());
+    let folder = repo
+        .get(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Folder not found".to_string())?;
+    let new_path = normalize_user_path(&path);
+    let changed = relink_folder(&state.db, &id, &new_path).map_err(|e| e.to_string())?;
+    rewatch(&state.watcher, &state.engine_status, &folder, &new_path);
+    let _ = app.emit(PATH_CHANGED_EVENT, changed);
+    repo.get(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Folder not found".to_string())
+}
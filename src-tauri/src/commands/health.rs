@@ -0,0 +1,42 @@
+use tauri::State;
+
+use crate::core::flow;
+use crate::core::health::{self, CheckDepth};
+use crate::core::state::AppState;
+use crate::models::{
+    CommandError, FolderFlowGraph, HealthRepairOptions, HealthRepairResult, HealthReport, Lane,
+};
+
+#[tauri::command(async)]
+pub fn health_check(state: State<'_, AppState>) -> Result<HealthReport, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadHeavy, None)?;
+    let report = health::check(&state.db, CheckDepth::Full).map_err(|e| e.to_string())?;
+    record_issues(&state, &report);
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn health_repair(
+    state: State<'_, AppState>,
+    options: HealthRepairOptions,
+) -> Result<HealthRepairResult, String> {
+    let result = health::repair(&state.db, &options).map_err(|e| e.to_string())?;
+    record_issues(&state, &result.report);
+    Ok(result)
+}
+
+/// How rules hand files between watched folders, with the loops and chains
+/// that form, for the UI to draw.
+#[tauri::command(async)]
+pub fn folder_flow_graph(state: State<'_, AppState>) -> Result<FolderFlowGraph, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadHeavy, None)?;
+    let graph = flow::folder_flow(&state.db).map_err(|e| e.to_string())?;
+    Ok(graph)
+}
+
+/// Keeps the issues in the engine status in step with the latest check.
+fn record_issues(state: &AppState, report: &HealthReport) {
+    if let Ok(mut status) = state.engine_status.lock() {
+        status.health_issues = report.issues.clone();
+    }
+}
@@ -0,0 +1,41 @@
+use chrono::{NaiveDate, Utc};
+use tauri::State;
+
+use crate::core::insights;
+use crate::core::state::AppState;
+use crate::models::{ClassificationTotal, CommandError, InsightsSummary, Lane};
+
+#[tauri::command(async)]
+pub fn insights_summary(state: State<'_, AppState>) -> Result<InsightsSummary, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadLight, None)?;
+    let seconds_per_file = state
+        .settings
+        .lock()
+        .map(|s| s.insights_seconds_per_file)
+        .unwrap_or_default();
+    insights::summary(&state.db, seconds_per_file, Utc::now())
+        .map_err(|e| CommandError::failed(e.to_string()))
+}
+
+#[tauri::command(async)]
+pub fn insights_reset(state: State<'_, AppState>) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
+    insights::reset(&state.db, Utc::now()).map_err(|e| CommandError::failed(e.to_string()))
+}
+
+/// Files and bytes per classification code on the UTC days `from` through `to`.
+#[tauri::command(async)]
+pub fn classification_report(
+    state: State<'_, AppState>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<ClassificationTotal>, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadLight, None)?;
+    let codes = state
+        .settings
+        .lock()
+        .map(|s| s.classification_codes.clone())
+        .unwrap_or_default();
+    insights::classification_report(&state.db, &codes, from, to)
+        .map_err(|e| CommandError::failed(e.to_string()))
+}
@@ -1,23 +1,171 @@
-use tauri::State;
+use std::collections::BTreeMap;
+use std::path::Path;
 
+use chrono::{DateTime, Utc};
+use tauri::{AppHandle, Manager, State};
+
+use crate::core::badge::BadgeManager;
+use crate::core::manifest::{self, ManifestExport, ManifestFormat, ManifestQuery};
 use crate::core::state::AppState;
-use crate::models::LogEntry;
+use crate::core::thumbnails::ThumbnailCache;
+use crate::models::{CommandError, Lane, LogEntry, UnreviewedSummary};
+use crate::storage::badge_repo::BadgeRepository;
 use crate::storage::log_repo::LogRepository;
+use crate::storage::match_repo::{MatchQuery, MatchRecord, MatchRepository};
+use crate::utils::platform::normalize_user_path;
 
-#[tauri::command]
+#[tauri::command(async)]
 pub fn log_list(
     state: State<'_, AppState>,
     limit: Option<usize>,
     offset: Option<usize>,
-) -> Result<Vec<LogEntry>, String> {
+) -> Result<Vec<LogEntry>, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadLight, None)?;
     let repo = LogRepository::new(state.db.clone());
     let limit = limit.unwrap_or(100);
     let offset = offset.unwrap_or(0);
-    repo.list(limit, offset).map_err(|e| e.to_string())
+    repo.list(limit, offset)
+        .map_err(|e| CommandError::failed(e.to_string()))
+}
+
+#[tauri::command(async)]
+pub fn log_clear(state: State<'_, AppState>) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
+    let repo = LogRepository::new(state.db.clone());
+    repo.clear()
+        .map_err(|e| CommandError::failed(e.to_string()))
+}
+
+/// A log entry's thumbnail as a `data:` URL, or `None` once it's gone.
+#[tauri::command(async)]
+pub fn thumbnail_read(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<Option<String>, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadLight, None)?;
+    let settings = state
+        .settings
+        .lock()
+        .map(|settings| settings.clone())
+        .unwrap_or_default();
+    let cache = ThumbnailCache::from_settings(&settings)
+        .map_err(|e| CommandError::failed(e.to_string()))?;
+    Ok(cache.data_url(Path::new(&path)))
 }
 
-#[tauri::command]
-pub fn log_clear(state: State<'_, AppState>) -> Result<(), String> {
+/// Removes every cached thumbnail; log entries that pointed at one show none.
+#[tauri::command(async)]
+pub fn thumbnail_cache_clear(state: State<'_, AppState>) -> Result<usize, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
+    let settings = state
+        .settings
+        .lock()
+        .map(|settings| settings.clone())
+        .unwrap_or_default();
+    ThumbnailCache::from_settings(&settings)
+        .and_then(|cache| Ok(cache.clear()?))
+        .map_err(|e| CommandError::failed(e.to_string()))
+}
+
+/// Marks the errors logged so far as seen, taking them off the tray badge.
+#[tauri::command(async)]
+pub fn errors_acknowledge(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
+    let repo = BadgeRepository::new(state.db.clone());
+    repo.acknowledge_errors(Utc::now())
+        .map_err(|e| CommandError::failed(e.to_string()))?;
+    if let Some(badge) = app.try_state::<BadgeManager>() {
+        badge.refresh();
+    }
+    Ok(())
+}
+
+/// Marks the log as reviewed up to now; called when the activity view
+/// loses focus. Its errors come off the tray badge too.
+#[tauri::command(async)]
+pub fn logs_mark_reviewed(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
     let repo = LogRepository::new(state.db.clone());
-    repo.clear().map_err(|e| e.to_string())
+    repo.mark_reviewed(Utc::now())
+        .map_err(|e| CommandError::failed(e.to_string()))?;
+    if let Some(badge) = app.try_state::<BadgeManager>() {
+        badge.refresh();
+    }
+    Ok(())
+}
+
+/// What was logged since the last review, with up to `error_limit` of the
+/// newest errors. Cheap enough to call whenever the window gets focus.
+#[tauri::command(async)]
+pub fn logs_unreviewed_summary(
+    state: State<'_, AppState>,
+    error_limit: Option<usize>,
+) -> Result<UnreviewedSummary, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadLight, None)?;
+    let repo = LogRepository::new(state.db.clone());
+    repo.unreviewed_summary(error_limit.unwrap_or(5))
+        .map_err(|e| CommandError::failed(e.to_string()))
+}
+
+/// The files `rule_id` matched between `since` and `until`, with where each
+/// is now, saved to `path` or returned when it's small enough.
+#[tauri::command(async)]
+pub fn rule_matches_export(
+    state: State<'_, AppState>,
+    rule_id: String,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    format: ManifestFormat,
+    path: Option<String>,
+) -> Result<ManifestExport, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadHeavy, None)?;
+    let query = ManifestQuery {
+        rule_id: &rule_id,
+        since,
+        until,
+    };
+    let path = path.map(|path| normalize_user_path(&path));
+    manifest::export(&state.db, &query, format, path.as_deref())
+        .map_err(|e| CommandError::failed(e.to_string()))
+}
+
+/// Most matches one query returns.
+const MATCHES_QUERY_MAX: usize = 10_000;
+
+/// Matches recorded by `rule_id`, or by any rule, between `since` and
+/// `until` that have every pair in `metadata`, newest first. Keys in
+/// `Settings::match_metadata_indexed_keys` are looked up through an index.
+#[tauri::command(async)]
+pub fn matches_query(
+    state: State<'_, AppState>,
+    rule_id: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    metadata: Option<BTreeMap<String, String>>,
+    limit: Option<usize>,
+) -> Result<Vec<MatchRecord>, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadHeavy, None)?;
+    let indexed_keys = state
+        .settings
+        .lock()
+        .map(|settings| settings.match_metadata_indexed_keys.clone())
+        .unwrap_or_default();
+    let metadata = metadata.unwrap_or_default();
+    let query = MatchQuery {
+        rule_id: rule_id.as_deref(),
+        since,
+        until,
+        metadata: &metadata,
+        indexed_keys: &indexed_keys,
+        limit: limit.unwrap_or(500).min(MATCHES_QUERY_MAX),
+    };
+    MatchRepository::new(state.db.clone())
+        .query(&query)
+        .map_err(|e| CommandError::failed(e.to_string()))
 }
@@ -1,10 +1,24 @@
+pub mod approvals;
+pub mod backup;
+pub mod classify;
+pub mod conflicts;
+pub mod folder_templates;
 pub mod folders;
 pub mod engine;
+pub mod faces;
+pub mod health;
+pub mod insights;
 pub mod logs;
+pub mod notifications;
 pub mod ocr;
+pub mod permissions;
 pub mod preview;
 pub mod presets;
+pub mod profiles;
+pub mod quick_setup;
+pub mod retries;
 pub mod rules;
 pub mod run;
+pub mod sandbox;
 pub mod settings;
 pub mod undo;
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use filedispatch_core::file_info::FileInfo;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::core::error_notify::{ErrorNotificationAction, ErrorNotifier};
+use crate::core::executor::{ActionExecutor, ActionResultStatus, ActionScope};
+use crate::core::state::AppState;
+use crate::models::Action;
+use crate::storage::rule_repo::RuleRepository;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogNavigation {
+    pub rule_id: String,
+}
+
+/// Handles a button pressed on an error notification.
+#[tauri::command]
+pub fn error_notification_action(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    notifier: State<'_, Arc<ErrorNotifier>>,
+    failure_id: String,
+    rule_id: String,
+    action: ErrorNotificationAction,
+) -> Result<(), String> {
+    match action {
+        ErrorNotificationAction::Retry => {
+            let executor = ActionExecutor::new(app, state.settings.clone(), state.ocr.clone());
+            let execute = |actions: &[Action],
+                           info: &FileInfo,
+                           captures: &HashMap<String, String>,
+                           scope: &ActionScope| {
+                executor.execute_actions(actions, info, captures, scope)
+            };
+            let outcome = notifier.retry(&state.db, &failure_id, &execute)?;
+            if outcome.status == ActionResultStatus::Error {
+                return Err(outcome
+                    .error
+                    .unwrap_or_else(|| "Retry failed".to_string()));
+            }
+            Ok(())
+        }
+        ErrorNotificationAction::OpenLog => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            app.emit("navigate://log", LogNavigation { rule_id })
+                .map_err(|e| e.to_string())
+        }
+        ErrorNotificationAction::DisableRule => RuleRepository::new(state.db.clone())
+            .set_enabled(&rule_id, false)
+            .map_err(|e| e.to_string()),
+    }
+}
@@ -0,0 +1,8 @@
+use crate::utils::permissions::{self, PermissionStatus};
+
+/// Access state per protected location (Desktop, Documents, Downloads,
+/// removable and network volumes). Everything reports granted off macOS.
+#[tauri::command]
+pub fn permissions_status() -> Vec<PermissionStatus> {
+    permissions::permissions_status()
+}
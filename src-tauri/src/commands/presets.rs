@@ -23,31 +23,58 @@ pub fn preset_install(
     variables: HashMap<String, String>,
 ) -> Result<Vec<Rule>, String> {
     let preset_file = read_preset_file(&path).map_err(|e| e.to_string())?;
-    let resolved_vars = resolve_variables(&preset_file.preset, &variables)?;
+    let rules = preset_rules(preset_file.preset, &folder_id, &variables)?;
     let repo = RuleRepository::new(state.db.clone());
 
     let mut created = Vec::new();
-    for mut rule in preset_file.preset.rules.into_iter() {
-        apply_variables_to_rule(&mut rule, &resolved_vars);
-        let new_rule = Rule {
-            id: String::new(),
-            folder_id: folder_id.clone(),
-            name: rule.name,
-            enabled: rule.enabled.unwrap_or(true),
-            stop_processing: rule.stop_processing.unwrap_or(true),
-            conditions: rule.conditions,
-            actions: rule.actions,
-            position: 0,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-        };
-        let created_rule = repo.create(new_rule).map_err(|e| e.to_string())?;
+    for rule in rules {
+        let created_rule = repo.create(rule).map_err(|e| e.to_string())?;
         created.push(created_rule);
     }
 
     Ok(created)
 }
 
+/// The preset's rules for `folder_id`, with its variables filled in from
+/// `variables` or their defaults.
+pub(crate) fn preset_rules(
+    preset: Preset,
+    folder_id: &str,
+    variables: &HashMap<String, String>,
+) -> Result<Vec<Rule>, String> {
+    let resolved_vars = resolve_variables(&preset, variables)?;
+    Ok(preset
+        .rules
+        .into_iter()
+        .map(|mut rule| {
+            apply_variables_to_rule(&mut rule, &resolved_vars);
+            Rule {
+                id: String::new(),
+                folder_id: folder_id.to_string(),
+                name: rule.name,
+                enabled: rule.enabled.unwrap_or(true),
+                stop_processing: rule.stop_processing.unwrap_or(true),
+                conditions: rule.conditions,
+                actions: rule.actions,
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            }
+        })
+        .collect())
+}
+
 fn read_preset_file(path: &str) -> anyhow::Result<PresetFile> {
     let content = fs::read_to_string(path)?;
     let preset: PresetFile = serde_json::from_str(&content)?;
@@ -82,6 +109,16 @@ fn apply_variables_to_rule(rule: &mut PresetRule, vars: &HashMap<String, String>
     }
 }
 
+/// Fills in `${var}` placeholders in a stored rule, in the same places as in
+/// a preset rule.
+pub(crate) fn apply_variables_to_stored_rule(rule: &mut Rule, vars: &HashMap<String, String>) {
+    rule.name = substitute(&rule.name, vars);
+    apply_variables_to_group(&mut rule.conditions, vars);
+    for action in rule.actions.iter_mut() {
+        apply_variables_to_action(action, vars);
+    }
+}
+
 fn apply_variables_to_group(group: &mut ConditionGroup, vars: &HashMap<String, String>) {
     for condition in group.conditions.iter_mut() {
         apply_variables_to_condition(condition, vars);
@@ -93,12 +130,31 @@ fn apply_variables_to_condition(condition: &mut Condition, vars: &HashMap<String
         Condition::Name(StringCondition { value, .. })
         | Condition::Extension(StringCondition { value, .. })
         | Condition::FullName(StringCondition { value, .. })
+        | Condition::Path(StringCondition { value, .. })
+        | Condition::ParentFolder(StringCondition { value, .. })
+        | Condition::RelativePath(StringCondition { value, .. })
+        | Condition::MediaCodec(StringCondition { value, .. })
+        | Condition::ActiveApplication(StringCondition { value, .. })
+        | Condition::MimeType(StringCondition { value, .. })
+        | Condition::Tag(StringCondition { value, .. })
         | Condition::Contents(crate::models::ContentsCondition { value, .. }) => {
             *value = substitute(value, vars);
         }
         Condition::ShellScript(script) => {
             script.command = substitute(&script.command, vars);
         }
+        Condition::ClassifiedAs(classified) => {
+            classified.category = substitute(&classified.category, vars);
+        }
+        Condition::ArchiveContains(archive) => {
+            archive.name_condition.value = substitute(&archive.name_condition.value, vars);
+        }
+        Condition::CsvColumn(csv) => {
+            csv.condition.value = substitute(&csv.condition.value, vars);
+        }
+        Condition::ExistsAtDestination(exists) => {
+            exists.destination_pattern = substitute(&exists.destination_pattern, vars);
+        }
         Condition::Nested(group) => apply_variables_to_group(group, vars),
         _ => {}
     }
@@ -137,6 +193,17 @@ fn apply_variables_to_action(action: &mut Action, vars: &HashMap<String, String>
         Action::Notify(action) => {
             action.message = substitute(&action.message, vars);
         }
+        Action::SetTags(action) => {
+            for tag in action.tags.iter_mut() {
+                *tag = substitute(tag, vars);
+            }
+        }
+        Action::CreateFolderStructure(action) => {
+            action.base_destination = substitute(&action.base_destination, vars);
+            for entry in action.structure.iter_mut() {
+                *entry = substitute(entry, vars);
+            }
+        }
         _ => {}
     }
 }
@@ -189,16 +256,57 @@ mod tests {
             conditions: ConditionGroup {
                 label: None,
                 match_type: MatchType::All,
-                conditions: vec![Condition::Name(StringCondition {
-                    operator: StringOperator::Contains,
-                    value: "${keyword}".to_string(),
-                    case_sensitive: false,
-                })],
+                conditions: vec![
+                    Condition::Name(StringCondition {
+                        operator: StringOperator::Contains,
+                        value: "${keyword}".to_string(),
+                        case_sensitive: false,
+                    }),
+                    Condition::Path(StringCondition {
+                        operator: StringOperator::StartsWith,
+                        value: "${folder}/".to_string(),
+                        case_sensitive: false,
+                    }),
+                    Condition::ParentFolder(StringCondition {
+                        operator: StringOperator::Is,
+                        value: "${keyword}s".to_string(),
+                        case_sensitive: false,
+                    }),
+                    Condition::RelativePath(StringCondition {
+                        operator: StringOperator::StartsWith,
+                        value: "${keyword}s/".to_string(),
+                        case_sensitive: false,
+                    }),
+                    Condition::Tag(StringCondition {
+                        operator: StringOperator::Is,
+                        value: "${keyword}".to_string(),
+                        case_sensitive: false,
+                    }),
+                    Condition::MimeType(StringCondition {
+                        operator: StringOperator::StartsWith,
+                        value: "${kind}/".to_string(),
+                        case_sensitive: false,
+                    }),
+                    Condition::MediaCodec(StringCondition {
+                        operator: StringOperator::Is,
+                        value: "${codec}".to_string(),
+                        case_sensitive: false,
+                    }),
+                    Condition::ActiveApplication(StringCondition {
+                        operator: StringOperator::Is,
+                        value: "${app}".to_string(),
+                        case_sensitive: false,
+                    }),
+                ],
             },
             actions: vec![Action::Move(crate::models::MoveAction {
                 destination: "${folder}/dest".to_string(),
                 on_conflict: crate::models::ConflictResolution::Rename,
                 skip_duplicates: false,
+                destination_permissions: Default::default(),
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
             })],
         };
 
@@ -208,6 +316,9 @@ mod tests {
         let mut vars = HashMap::new();
         vars.insert("folder".to_string(), folder_str.clone());
         vars.insert("keyword".to_string(), "invoice".to_string());
+        vars.insert("kind".to_string(), "image".to_string());
+        vars.insert("codec".to_string(), "hevc".to_string());
+        vars.insert("app".to_string(), "Photoshop".to_string());
 
         apply_variables_to_rule(&mut rule, &vars);
         assert_eq!(rule.name, format!("Move to {}", folder_str));
@@ -216,6 +327,41 @@ mod tests {
         } else {
             panic!("expected name condition");
         }
+        if let Condition::Path(cond) = &rule.conditions.conditions[1] {
+            assert_eq!(cond.value, format!("{}/", folder_str));
+        } else {
+            panic!("expected path condition");
+        }
+        if let Condition::ParentFolder(cond) = &rule.conditions.conditions[2] {
+            assert_eq!(cond.value, "invoices");
+        } else {
+            panic!("expected parent folder condition");
+        }
+        if let Condition::RelativePath(cond) = &rule.conditions.conditions[3] {
+            assert_eq!(cond.value, "invoices/");
+        } else {
+            panic!("expected relative path condition");
+        }
+        if let Condition::Tag(cond) = &rule.conditions.conditions[4] {
+            assert_eq!(cond.value, "invoice");
+        } else {
+            panic!("expected tag condition");
+        }
+        if let Condition::MimeType(cond) = &rule.conditions.conditions[5] {
+            assert_eq!(cond.value, "image/");
+        } else {
+            panic!("expected mime type condition");
+        }
+        if let Condition::MediaCodec(cond) = &rule.conditions.conditions[6] {
+            assert_eq!(cond.value, "hevc");
+        } else {
+            panic!("expected media codec condition");
+        }
+        if let Condition::ActiveApplication(cond) = &rule.conditions.conditions[7] {
+            assert_eq!(cond.value, "Photoshop");
+        } else {
+            panic!("expected active application condition");
+        }
         if let Action::Move(action) = &rule.actions[0] {
             let normalized_dest = action.destination.replace('\\', "/");
             let normalized_folder = folder_str.replace('\\', "/");
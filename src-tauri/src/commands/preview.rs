@@ -1,33 +1,68 @@
-use std::path::PathBuf;
-
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use filedispatch_core::file_info::FileInfo;
+use filedispatch_core::patterns::{trace_map_branches, PatternEngine};
+use filedispatch_core::screenshot;
+use filedispatch_core::simulate::{
+    date_threshold_notes, describe_action, FileInfoOverrides, SyntheticFile,
+};
+use filedispatch_core::suggest::{suggest_rule, RuleSuggestion};
+use filedispatch_core::validate::validate_patterns;
 use tauri::State;
 
-use crate::core::content::ContentCache;
-use crate::core::engine::{evaluate_condition, evaluate_conditions, EvaluationOptions};
-use crate::core::patterns::PatternEngine;
+use crate::core::command_lanes::CommandScope;
+use crate::core::content::{resolve_contents, ContentCache};
+use crate::core::duplicates::DuplicateDetector;
+use crate::core::executor::{
+    ActionExecutor, ActionOutcome, ActionResultStatus, DRY_RUN_CONFLICT_KEY, DRY_RUN_REMOVES_KEY,
+};
+use crate::core::engine::{
+    evaluate_condition, evaluate_conditions, in_path_scope, min_age_eligible_at, picked_up,
+    DuplicateLookup, EvaluationOptions,
+};
 use crate::core::state::AppState;
-use crate::models::{Action, PreviewItem};
+use crate::core::unique_name::{self, NameSuffix};
+use crate::models::{
+    Action, ActiveApp, CommandError, CommandErrorKind, Condition, ConditionGroup,
+    ConflictResolution, ContentSource, DryRunConflict, DryRunItem, DryRunOutcome, EventSource,
+    Lane, PreviewItem,
+};
+use crate::storage::database::Database;
 use crate::storage::folder_repo::FolderRepository;
 use crate::storage::rule_repo::RuleRepository;
-use crate::utils::file_info::FileInfo;
+use crate::utils::foreground;
+use crate::utils::platform::normalize_user_path;
+
+/// How long previewing a whole folder may take before it's stopped.
+const PREVIEW_LIMIT: Duration = Duration::from_secs(120);
 
-#[tauri::command]
+#[tauri::command(async)]
 pub fn preview_rule(
     state: State<'_, AppState>,
     rule_id: String,
     request_id: Option<String>,
-) -> Result<Vec<PreviewItem>, String> {
+) -> Result<Vec<PreviewItem>, CommandError> {
+    let scope = state
+        .command_lanes
+        .enter(Lane::Ocr, request_id.as_deref())?
+        .with_deadline(PREVIEW_LIMIT);
     let rule_repo = RuleRepository::new(state.db.clone());
     let folder_repo = FolderRepository::new(state.db.clone());
     let rule = rule_repo.get(&rule_id).map_err(|e| e.to_string())?;
     let Some(rule) = rule else {
-        return Err("Rule not found".to_string());
+        return Err("Rule not found".into());
     };
+    validate_patterns(&rule)?;
     let folder = folder_repo
         .get(&rule.folder_id)
         .map_err(|e| e.to_string())?;
     let Some(folder) = folder else {
-        return Err("Folder not found".to_string());
+        return Err("Folder not found".into());
     };
 
     let mut results = Vec::new();
@@ -37,7 +72,7 @@ pub fn preview_rule(
         .lock()
         .map(|s| s.clone())
         .unwrap_or_default();
-    let mut ocr = state.ocr.lock().unwrap();
+    pattern_engine.set_default_timezone(settings.default_timezone.as_deref());
 
     let max_depth = folder.max_depth().unwrap_or(usize::MAX);
     let request_id = request_id.as_deref();
@@ -45,30 +80,38 @@ pub fn preview_rule(
         skip_content: false,
         surface_errors: true,
         ocr_request_id: request_id.map(str::to_string),
+        now: None,
+        duplicates: Some(duplicate_lookup(&state.db, &rule.folder_id)),
     };
+    let root = normalize_user_path(&folder.path);
 
     for entry in walkdir::WalkDir::new(&folder.path)
         .max_depth(max_depth)
         .into_iter()
         .filter_map(Result::ok)
     {
-        check_cancel(request_id)?;
+        check_cancel(&scope, request_id)?;
         if !entry.file_type().is_file() {
             continue;
         }
         let path = entry.path().to_path_buf();
+        // Taken a file at a time, so settings saves and other previews
+        // aren't held up for the whole folder.
+        let mut ocr = state.ocr.lock().map_err(|_| "OCR lock poisoned")?;
         match preview_single(
             &rule,
+            &root,
             &path,
             &pattern_engine,
             &settings,
             &mut ocr,
             &options,
+            None,
         ) {
             Ok(item) => results.push(item),
             Err(err) => {
                 if options.surface_errors {
-                    return Err(err.to_string());
+                    return Err(err.to_string().into());
                 }
             }
         }
@@ -77,6 +120,137 @@ pub fn preview_rule(
     Ok(results)
 }
 
+/// Works out what a rule would do to every file in its folder: where each
+/// would go, conflict handling included, and what would be removed. Files
+/// are taken in turn, so one whose destination an earlier file would take
+/// shows up renamed or skipped as the real run would leave it.
+#[tauri::command(async)]
+pub fn rule_dry_run(
+    state: State<'_, AppState>,
+    rule_id: String,
+    request_id: Option<String>,
+) -> Result<Vec<DryRunItem>, CommandError> {
+    let scope = state
+        .command_lanes
+        .enter(Lane::Ocr, request_id.as_deref())?
+        .with_deadline(PREVIEW_LIMIT);
+    let rule_repo = RuleRepository::new(state.db.clone());
+    let folder_repo = FolderRepository::new(state.db.clone());
+    let rule = rule_repo.get(&rule_id).map_err(|e| e.to_string())?;
+    let Some(rule) = rule else {
+        return Err("Rule not found".into());
+    };
+    validate_patterns(&rule)?;
+    let folder = folder_repo
+        .get(&rule.folder_id)
+        .map_err(|e| e.to_string())?;
+    let Some(folder) = folder else {
+        return Err("Folder not found".into());
+    };
+
+    let settings = state
+        .settings
+        .lock()
+        .map(|s| s.clone())
+        .unwrap_or_default();
+    let executor = ActionExecutor::detached(state.settings.clone(), state.ocr.clone());
+    let max_depth = folder.max_depth().unwrap_or(usize::MAX);
+    let request_id = request_id.as_deref();
+    let options = EvaluationOptions {
+        skip_content: false,
+        surface_errors: true,
+        ocr_request_id: request_id.map(str::to_string),
+        now: None,
+        duplicates: Some(duplicate_lookup(&state.db, &rule.folder_id)),
+    };
+    let root = normalize_user_path(&folder.path);
+    let mut claimed = HashSet::new();
+    let mut results = Vec::new();
+
+    for entry in walkdir::WalkDir::new(&folder.path)
+        .max_depth(max_depth)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        check_cancel(&scope, request_id)?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path().to_path_buf();
+        let mut item = DryRunItem {
+            file_path: path.to_string_lossy().to_string(),
+            matched: false,
+            outcomes: Vec::new(),
+            notes: Vec::new(),
+        };
+        let mut info = match FileInfo::from_path(&path) {
+            Ok(info) => info,
+            Err(err) => {
+                item.notes.push(format!(
+                    "{}: {err}",
+                    if err.is_skip() { "Skipped" } else { "Not processed" }
+                ));
+                results.push(item);
+                continue;
+            }
+        };
+        picked_up(&mut info, EventSource::Manual, &root);
+        if !in_path_scope(&rule, &root, &info.path) {
+            item.notes.push(format!(
+                "Out of scope: outside {}",
+                rule.path_scope.as_deref().unwrap_or_default()
+            ));
+            results.push(item);
+            continue;
+        }
+        let evaluation = {
+            let mut ocr = state.ocr.lock().map_err(|_| "OCR lock poisoned")?;
+            evaluate_conditions(&rule, &info, &settings, &mut ocr, &options)
+                .map_err(|e| e.to_string())?
+        };
+        item.matched = evaluation.matched;
+        if evaluation.matched {
+            let captures = &evaluation.captures;
+            item.outcomes = executor
+                .simulate_actions_claiming(&rule.actions, &info, captures, &mut claimed)
+                .into_iter()
+                .map(dry_run_outcome)
+                .collect();
+        }
+        results.push(item);
+    }
+
+    Ok(results)
+}
+
+/// The shape the UI gets a simulated outcome in.
+fn dry_run_outcome(outcome: ActionOutcome) -> DryRunOutcome {
+    let (source_path, destination_path, metadata) = match outcome.details {
+        Some(details) => (details.source_path, details.destination_path, details.metadata),
+        None => (String::new(), None, Default::default()),
+    };
+    let conflict = metadata
+        .get(DRY_RUN_CONFLICT_KEY)
+        .and_then(|label| match label.as_str() {
+            "rename" => Some(DryRunConflict::Rename),
+            "replace" => Some(DryRunConflict::Replace),
+            "unique_suffix" => Some(DryRunConflict::UniqueSuffix),
+            "ask" => Some(DryRunConflict::Ask),
+            _ => None,
+        });
+    let would_run = outcome.status == ActionResultStatus::Simulated;
+    DryRunOutcome {
+        action_type: outcome.action_type,
+        would_run,
+        source_path,
+        destination_path,
+        conflict,
+        removes: metadata.get(DRY_RUN_REMOVES_KEY).cloned(),
+        note: outcome.error.filter(|_| !would_run),
+    }
+}
+
 
 #[derive(serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -89,6 +263,27 @@ pub struct DraftRule {
     pub conditions: crate::models::ConditionGroup,
     pub actions: Vec<crate::models::Action>,
     pub position: i32,
+    #[serde(default)]
+    pub schedule: Option<crate::models::RuleSchedule>,
+    #[serde(default)]
+    pub approval_required: bool,
+    #[serde(default)]
+    pub min_age_seconds: Option<u64>,
+    #[serde(default)]
+    pub path_scope: Option<String>,
+    #[serde(default)]
+    pub skip_reference_check: bool,
+    #[serde(default)]
+    pub monthly_byte_budget: Option<u64>,
+    #[serde(default)]
+    pub classification: Option<String>,
+    #[serde(default)]
+    pub on_reappear: Option<crate::models::OnReappear>,
+    #[serde(default)]
+    pub record_metadata: std::collections::HashMap<String, String>,
+    /// The saved revision the draft was opened from.
+    #[serde(default)]
+    pub revision: u32,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
 }
@@ -105,6 +300,17 @@ impl DraftRule {
             conditions: self.conditions,
             actions: self.actions,
             position: self.position,
+            schedule: self.schedule,
+            approval_required: self.approval_required,
+            min_age_seconds: self.min_age_seconds,
+            path_scope: self.path_scope,
+            skip_reference_check: self.skip_reference_check,
+            monthly_byte_budget: self.monthly_byte_budget,
+            classification: self.classification,
+            on_reappear: self.on_reappear,
+            record_metadata: self.record_metadata,
+            revision: self.revision,
+            extra: serde_json::Value::Null,
             created_at: self
                 .created_at
                 .and_then(|s| s.parse().ok())
@@ -117,14 +323,24 @@ impl DraftRule {
     }
 }
 
-#[tauri::command]
+#[tauri::command(async)]
 pub fn preview_rule_draft(
     state: State<'_, AppState>,
     rule: DraftRule,
     max_files: Option<usize>,
     skip_content: Option<bool>,
     request_id: Option<String>,
-) -> Result<Vec<PreviewItem>, String> {
+) -> Result<Vec<PreviewItem>, CommandError> {
+    let skip_content = skip_content.unwrap_or(false);
+    let lane = if skip_content {
+        Lane::ReadHeavy
+    } else {
+        Lane::Ocr
+    };
+    let scope = state
+        .command_lanes
+        .enter(lane, request_id.as_deref())?
+        .with_deadline(PREVIEW_LIMIT);
     eprintln!("==== preview_rule_draft called ====");
     eprintln!("Rule folder_id: {}", rule.folder_id);
     eprintln!("Rule name: {}", rule.name);
@@ -132,6 +348,7 @@ pub fn preview_rule_draft(
     eprintln!("Rule actions count: {}", rule.actions.len());
 
     let rule = rule.to_rule();
+    validate_patterns(&rule)?;
     let folder_repo = FolderRepository::new(state.db.clone());
     let folder = folder_repo
         .get(&rule.folder_id)
@@ -141,7 +358,7 @@ pub fn preview_rule_draft(
         })?;
     let Some(folder) = folder else {
         eprintln!("Folder not found for id: {}", rule.folder_id);
-        return Err(format!("Folder not found: {}", rule.folder_id));
+        return Err(format!("Folder not found: {}", rule.folder_id).into());
     };
 
     eprintln!("Folder path: {}", folder.path);
@@ -149,7 +366,7 @@ pub fn preview_rule_draft(
     // Check if folder path exists
     if !std::path::Path::new(&folder.path).exists() {
         eprintln!("Folder path does not exist: {}", folder.path);
-        return Err(format!("Folder path does not exist: {}", folder.path));
+        return Err(format!("Folder path does not exist: {}", folder.path).into());
     }
 
     let mut results = Vec::new();
@@ -159,23 +376,25 @@ pub fn preview_rule_draft(
         .lock()
         .map(|s| s.clone())
         .unwrap_or_default();
-    let mut ocr = state.ocr.lock().unwrap();
+    pattern_engine.set_default_timezone(settings.default_timezone.as_deref());
     let request_id = request_id.as_deref();
 
     eprintln!("Starting directory walk...");
     let max_depth = folder.max_depth().unwrap_or(usize::MAX);
+    let root = normalize_user_path(&folder.path);
     let walker = walkdir::WalkDir::new(&folder.path)
         .max_depth(max_depth)
         .into_iter();
 
     let mut file_count = 0;
     let max_files = max_files.unwrap_or(100);
-    let skip_content = skip_content.unwrap_or(false);
 
     let options = EvaluationOptions {
         skip_content,
         surface_errors: !skip_content,
         ocr_request_id: request_id.map(str::to_string),
+        now: None,
+        duplicates: Some(duplicate_lookup(&state.db, &rule.folder_id)),
     };
 
     for entry in walker {
@@ -204,21 +423,24 @@ pub fn preview_rule_draft(
             eprintln!("Processing file #{}: {:?}", file_count, path);
         }
 
-        check_cancel(request_id)?;
+        check_cancel(&scope, request_id)?;
+        let mut ocr = state.ocr.lock().map_err(|_| "OCR lock poisoned")?;
         match preview_single(
             &rule,
+            &root,
             &path,
             &pattern_engine,
             &settings,
             &mut ocr,
             &options,
+            None,
         ) {
             Ok(item) => {
                 results.push(item);
             }
             Err(e) => {
                 if options.surface_errors {
-                    return Err(e.to_string());
+                    return Err(e.to_string().into());
                 }
                 eprintln!("Failed to preview file {:?}: {}", path, e);
             }
@@ -234,53 +456,209 @@ pub fn preview_rule_draft(
     Ok(results)
 }
 
-#[tauri::command]
+/// Previews one file. `overrides` stand in for parts of its info and `now`
+/// for the clock, to see what the rule would do with the file if it were
+/// older, or at another time of day; the result lists what was overridden.
+#[tauri::command(async)]
 pub fn preview_file(
     state: State<'_, AppState>,
     rule_id: String,
     file_path: String,
+    overrides: Option<FileInfoOverrides>,
+    now: Option<DateTime<Utc>>,
     request_id: Option<String>,
-) -> Result<PreviewItem, String> {
+) -> Result<PreviewItem, CommandError> {
+    let _scope = state
+        .command_lanes
+        .enter(Lane::Ocr, request_id.as_deref())?;
     let rule_repo = RuleRepository::new(state.db.clone());
     let rule = rule_repo.get(&rule_id).map_err(|e| e.to_string())?;
     let Some(rule) = rule else {
-        return Err("Rule not found".to_string());
+        return Err("Rule not found".into());
     };
+    let folder = FolderRepository::new(state.db.clone())
+        .get(&rule.folder_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Folder not found".to_string())?;
     let settings = state
         .settings
         .lock()
         .map(|s| s.clone())
         .unwrap_or_default();
-    let mut ocr = state.ocr.lock().unwrap();
+    let mut ocr = state.ocr.lock().map_err(|_| "OCR lock poisoned")?;
     let path = PathBuf::from(file_path);
     let pattern_engine = PatternEngine::new();
+    pattern_engine.set_default_timezone(settings.default_timezone.as_deref());
     let options = EvaluationOptions {
         skip_content: false,
         surface_errors: true,
         ocr_request_id: request_id,
+        now,
+        duplicates: Some(duplicate_lookup(&state.db, &rule.folder_id)),
     };
-    preview_single(&rule, &path, &pattern_engine, &settings, &mut ocr, &options)
-        .map_err(|e| e.to_string())
+    let root = normalize_user_path(&folder.path);
+    preview_single(
+        &rule,
+        &root,
+        &path,
+        &pattern_engine,
+        &settings,
+        &mut ocr,
+        &options,
+        overrides.as_ref(),
+    )
+    .map_err(|e| e.to_string().into())
 }
 
-fn check_cancel(request_id: Option<&str>) -> Result<(), String> {
+/// Drafts a rule from example files and files it mustn't match; see
+/// `filedispatch_core::suggest`. With `use_contents` their text is read too,
+/// falling back to OCR, so that can take a while. Nothing is saved.
+#[tauri::command(async)]
+pub fn rule_suggest(
+    state: State<'_, AppState>,
+    examples: Vec<String>,
+    counter_examples: Vec<String>,
+    use_contents: Option<bool>,
+    request_id: Option<String>,
+) -> Result<RuleSuggestion, CommandError> {
+    let use_contents = use_contents.unwrap_or(false);
+    let lane = if use_contents {
+        Lane::Ocr
+    } else {
+        Lane::ReadHeavy
+    };
+    let scope = state
+        .command_lanes
+        .enter(lane, request_id.as_deref())?
+        .with_deadline(PREVIEW_LIMIT);
+    let settings = state
+        .settings
+        .lock()
+        .map(|s| s.clone())
+        .unwrap_or_default();
+    let request_id = request_id.as_deref();
+
+    let describe = |paths: Vec<String>| -> Result<Vec<SyntheticFile>, CommandError> {
+        let mut files = Vec::new();
+        for path in paths {
+            check_cancel(&scope, request_id)?;
+            let path = normalize_user_path(&path);
+            let info =
+                FileInfo::from_path(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+            let contents = if use_contents {
+                let mut ocr = state.ocr.lock().map_err(|_| "OCR lock poisoned")?;
+                let mut cache = ContentCache::default();
+                resolve_contents(
+                    &info,
+                    &settings,
+                    &mut ocr,
+                    &ContentSource::Auto,
+                    &mut cache,
+                    request_id,
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("Couldn't read contents of {}: {err}", path.display());
+                    None
+                })
+            } else {
+                None
+            };
+            files.push(SyntheticFile {
+                path: info.path,
+                size: info.size,
+                created: Some(info.created),
+                modified: Some(info.modified),
+                added: Some(info.added),
+                kind: Some(info.kind),
+                contents,
+                ..SyntheticFile::default()
+            });
+        }
+        Ok(files)
+    };
+    let examples = describe(examples)?;
+    let counter_examples = describe(counter_examples)?;
+    suggest_rule(&examples, &counter_examples, &settings).map_err(|e| e.to_string().into())
+}
+
+/// Previews find duplicates with a detector of their own, so the copies it
+/// remembers are forgotten with the preview.
+fn duplicate_lookup(db: &Database, folder_id: &str) -> DuplicateLookup {
+    DuplicateLookup {
+        detector: Arc::new(DuplicateDetector::new(db.clone())),
+        folder_id: folder_id.to_string(),
+    }
+}
+
+fn check_cancel(scope: &CommandScope, request_id: Option<&str>) -> Result<(), CommandError> {
+    scope.check()?;
     if let Some(id) = request_id {
         if crate::core::ocr::OcrManager::take_cancelled(id) {
-            return Err("OCR cancelled".to_string());
+            return Err(CommandError::new(
+                CommandErrorKind::Cancelled,
+                "OCR cancelled",
+            ));
         }
     }
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn preview_single(
     rule: &crate::models::Rule,
+    root: &Path,
     path: &PathBuf,
     pattern_engine: &PatternEngine,
     settings: &crate::models::Settings,
     ocr: &mut crate::core::ocr::OcrManager,
     options: &EvaluationOptions,
+    overrides: Option<&FileInfoOverrides>,
 ) -> anyhow::Result<PreviewItem> {
-    let info = FileInfo::from_path(path)?;
+    let mut info = match FileInfo::from_path(path) {
+        Ok(info) => info,
+        // Shown like the engine would treat it instead of failing the preview.
+        Err(err) => {
+            return Ok(PreviewItem {
+                file_path: path.to_string_lossy().to_string(),
+                matched: false,
+                out_of_scope: false,
+                condition_results: Vec::new(),
+                actions: Vec::new(),
+                notes: vec![format!(
+                    "{}: {err}",
+                    if err.is_skip() { "Skipped" } else { "Not processed" }
+                )],
+                overridden: Vec::new(),
+                rule_revision: rule.revision,
+            })
+        }
+    };
+    // Files sitting in the folder are previewed as "Run now" would find them.
+    picked_up(&mut info, EventSource::Manual, root);
+    let mut overridden: Vec<String> = overrides
+        .map(|overrides| overrides.apply(&mut info))
+        .unwrap_or_default()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    if options.now.is_some() {
+        overridden.push("now".to_string());
+    }
+    if !in_path_scope(rule, root, &info.path) {
+        return Ok(PreviewItem {
+            file_path: info.path.to_string_lossy().to_string(),
+            matched: false,
+            out_of_scope: true,
+            condition_results: Vec::new(),
+            actions: Vec::new(),
+            notes: vec![format!(
+                "Out of scope: outside {}",
+                rule.path_scope.as_deref().unwrap_or_default()
+            )],
+            overridden,
+            rule_revision: rule.revision,
+        });
+    }
     let evaluation = evaluate_conditions(rule, &info, settings, ocr, options)?;
 
     let mut condition_results = Vec::new();
@@ -292,71 +670,179 @@ fn preview_single(
         );
     }
 
-    let actions = if evaluation.matched {
-        rule.actions
-            .iter()
-            .map(|action| describe_action(action, &info, &evaluation.captures, pattern_engine))
-            .collect()
-    } else {
-        Vec::new()
-    };
+    let (actions, map_branches) = trace_map_branches(|| {
+        if evaluation.matched {
+            rule.actions
+                .iter()
+                .map(|action| describe_action(action, &info, &evaluation.captures, pattern_engine))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    });
+
+    let now = options.now.unwrap_or_else(Utc::now);
+    let mut notes = map_branches;
+    if !overridden.is_empty() {
+        notes.push(overrides_note(&info, &overridden, now));
+    }
+    if evaluation.matched {
+        notes.extend(
+            rule.actions
+                .iter()
+                .filter_map(|action| unique_suffix_note(action, &info)),
+        );
+    }
+    if let Some(eligible_at) = min_age_eligible_at(rule, &info, now) {
+        notes.push(format!(
+            "Gated by min_age, eligible at {}",
+            eligible_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+        ));
+    }
+    notes.extend(date_threshold_notes(&rule.conditions, settings, now));
+    if let Some(entry) = evaluation.captures.get("one_of") {
+        notes.push(format!("Matched list entry \"{}\"", entry));
+    }
+    if cache.reduced_fidelity() {
+        notes.push("PDF text read without PDFium (reduced fidelity)".to_string());
+    }
+    if cache.streamed() {
+        notes.push("Contents streamed in chunks (large file)".to_string());
+    }
+    if let Some(reason) = cache.archive_error() {
+        notes.push(format!("Archive not readable: {}", reason));
+    }
+    if let Some(reason) = cache.csv_error() {
+        notes.push(format!("CSV not readable: {}", reason));
+    }
+    if let Some(reason) = cache.image_metadata_error() {
+        notes.push(format!("Image metadata not read: {}", reason));
+    }
+    if let Some(reason) = cache.page_count_error() {
+        notes.push(format!("Pages not counted: {}", reason));
+    }
+    if let Some(reason) = cache.mime_error() {
+        notes.push(format!("MIME type not sniffed: {}", reason));
+    }
+    if let Some(reason) = cache.media_error() {
+        notes.push(format!("Media not read: {}", reason));
+    }
+    if let Some(reason) = cache.face_error() {
+        notes.push(format!("Faces not counted: {}", reason));
+    }
+    if let Some(evidence) = cache.screenshot_evidence() {
+        let prefixes = &settings.screenshot_name_prefixes;
+        notes.push(screenshot::judge(&info.full_name, prefixes, evidence).to_string());
+    }
+    if let Some(confidence) = cache.ocr_confidence() {
+        notes.push(match min_ocr_confidence(&rule.conditions) {
+            Some(minimum) => format!("OCR confidence {:.2} (minimum {:.2})", confidence, minimum),
+            None => format!("OCR confidence {:.2}", confidence),
+        });
+    }
+    if uses_active_application(&rule.conditions) {
+        notes.push(active_app_note(
+            info.active_app
+                .clone()
+                .unwrap_or_else(|| foreground::system().active_app(Instant::now())),
+            info.active_app.is_some(),
+        ));
+    }
 
     Ok(PreviewItem {
         file_path: info.path.to_string_lossy().to_string(),
         matched: evaluation.matched,
+        out_of_scope: false,
         condition_results,
         actions,
+        notes,
+        overridden,
+        rule_revision: rule.revision,
     })
 }
 
-fn describe_action(
-    action: &Action,
-    info: &FileInfo,
-    captures: &std::collections::HashMap<String, String>,
-    engine: &PatternEngine,
-) -> String {
-    match action {
-        Action::Move(action) => {
-            let dest = engine.resolve(&action.destination, info, captures);
-            format!("Move → {}", dest)
-        }
-        Action::Copy(action) => {
-            let dest = engine.resolve(&action.destination, info, captures);
-            format!("Copy → {}", dest)
-        }
-        Action::Rename(action) => {
-            let name = engine.resolve(&action.pattern, info, captures);
-            format!("Rename → {}", name)
-        }
-        Action::SortIntoSubfolder(action) => {
-            let dest = engine.resolve(&action.destination, info, captures);
-            format!("Sort → {}", dest)
-        }
-        Action::Archive(action) => {
-            let dest = engine.resolve(&action.destination, info, captures);
-            format!("Archive → {}", dest)
-        }
-        Action::Unarchive(action) => {
-            let dest = action
-                .destination
-                .as_ref()
-                .map(|d| engine.resolve(d, info, captures))
-                .unwrap_or_else(|| "Current folder".to_string());
-            format!("Unarchive → {}", dest)
+/// Spells out what a preview pretended, so it isn't taken for what will
+/// happen to the file as it is.
+fn overrides_note(info: &FileInfo, overridden: &[String], now: DateTime<Utc>) -> String {
+    let local = |date: DateTime<Utc>| {
+        date.with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    };
+    let parts: Vec<String> = overridden
+        .iter()
+        .map(|field| match field.as_str() {
+            "created" => format!("created {}", local(info.created)),
+            "modified" => format!("modified {}", local(info.modified)),
+            "added" => format!("added {}", local(info.added)),
+            "size" => format!("size {} bytes", info.size),
+            "kind" => format!("kind {:?}", info.kind),
+            "extension" => format!("extension {}", info.extension),
+            "lastMatched" => match info.last_matched {
+                Some(date) => format!("last matched {}", local(date)),
+                None => "never matched".to_string(),
+            },
+            "now" => format!("now {}", local(now)),
+            other => other.to_string(),
+        })
+        .collect();
+    format!("Overridden for this preview, not the file's own: {}", parts.join(", "))
+}
+
+/// How a name under the unique-suffix policy comes about, part by part. The
+/// parts are this moment's; the file gets its own when it's filed.
+fn unique_suffix_note(action: &Action, info: &FileInfo) -> Option<String> {
+    let on_conflict = match action {
+        Action::Move(action) => &action.on_conflict,
+        Action::Copy(action) => &action.on_conflict,
+        Action::SortIntoSubfolder(action) => &action.on_conflict,
+        Action::Rename(action) => &action.on_conflict,
+        _ => return None,
+    };
+    if *on_conflict != ConflictResolution::UniqueSuffix {
+        return None;
+    }
+    let format = unique_name::format();
+    let suffix = NameSuffix::generate(chrono::Utc::now());
+    Some(format!(
+        "Unique suffix {format}: millis {}, random {}, host {} → {}",
+        suffix.millis,
+        suffix.random,
+        suffix.host.as_deref().unwrap_or("(none)"),
+        suffix.apply(Path::new(&info.full_name), &format).display()
+    ))
+}
+
+fn uses_active_application(group: &ConditionGroup) -> bool {
+    group.conditions.iter().any(|condition| match condition {
+        Condition::ActiveApplication(_) => true,
+        Condition::Nested(nested) => uses_active_application(nested),
+        _ => false,
+    })
+}
+
+/// Why an Active Application condition did or didn't match. Files are
+/// previewed without an event, so there's only an app when one was captured;
+/// otherwise the system is asked whether it could have told at all.
+fn active_app_note(app: ActiveApp, captured: bool) -> String {
+    match app {
+        ActiveApp::Known { name, .. } if captured => format!("Saved while {} was in front", name),
+        ActiveApp::Known { .. } => {
+            "Active application is captured as files arrive, so it doesn't match in preview"
+                .to_string()
         }
-        Action::Delete(_) => "Delete (Trash)".to_string(),
-        Action::DeletePermanently(_) => "Delete Permanently".to_string(),
-        Action::RunScript(action) => format!("Run: {}", action.command),
-        Action::Notify(action) => {
-            let message = engine.resolve(&action.message, info, captures);
-            format!("Notify: {}", message)
+        ActiveApp::Unavailable { reason } => {
+            format!("Active application can't be told here: {}", reason)
         }
-        Action::Open(_) => "Open with default app".to_string(),
-        Action::ShowInFileManager(_) => "Show in file manager".to_string(),
-        Action::OpenWith(action) => format!("Open with {}", action.app_path),
-        Action::MakePdfSearchable(_) => "Make PDF searchable (OCR)".to_string(),
-        Action::Pause(action) => format!("Pause {}s", action.duration_seconds),
-        Action::Continue => "Continue matching rules".to_string(),
-        Action::Ignore => "Ignore".to_string(),
     }
 }
+
+fn min_ocr_confidence(group: &ConditionGroup) -> Option<f32> {
+    group.conditions.iter().find_map(|condition| match condition {
+        Condition::Contents(cond) => cond.min_ocr_confidence,
+        Condition::Nested(nested) => min_ocr_confidence(nested),
+        _ => None,
+    })
+}
@@ -0,0 +1,70 @@
+use tauri::{AppHandle, State};
+
+use crate::commands::settings::apply_settings;
+use crate::core::state::AppState;
+use crate::models::Profile;
+use crate::storage::folder_repo::FolderRepository;
+use crate::storage::profile_repo::ProfileRepository;
+use crate::utils::platform::normalize_user_path;
+
+#[tauri::command]
+pub fn profile_list(state: State<'_, AppState>) -> Result<Vec<Profile>, String> {
+    let repo = ProfileRepository::new(state.db.clone());
+    repo.list().map_err(|e| e.to_string())
+}
+
+/// Saves the current settings and folder/rule enabled states under `name`.
+#[tauri::command]
+pub fn profile_create(state: State<'_, AppState>, name: String) -> Result<Profile, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map(|s| s.clone())
+        .map_err(|e| e.to_string())?;
+    let repo = ProfileRepository::new(state.db.clone());
+    repo.create(&name, &settings).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn profile_delete(state: State<'_, AppState>, name: String) -> Result<(), String> {
+    let repo = ProfileRepository::new(state.db.clone());
+    repo.delete_by_name(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn profile_switch(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<Profile, String> {
+    let repo = ProfileRepository::new(state.db.clone());
+    let mut profile = repo
+        .get_by_name(&name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Profile '{}' not found", name))?;
+
+    repo.activate(&profile).map_err(|e| e.to_string())?;
+    apply_settings(&app, &state, profile.settings.clone())?;
+
+    // Re-sync watched folders with the enabled states the profile just applied.
+    let folders = FolderRepository::new(state.db.clone())
+        .list()
+        .map_err(|e| e.to_string())?;
+    if let Ok(mut watcher) = state.watcher.lock() {
+        for (path, _, _) in watcher.list_watched_folders() {
+            let _ = watcher.unwatch_folder(&path);
+        }
+        for folder in folders.into_iter().filter(|f| f.enabled) {
+            let normalized = normalize_user_path(&folder.path);
+            let _ = watcher.watch_folder(
+                normalized,
+                folder.id.clone(),
+                folder.scan_depth,
+                &folder.effective_passthrough_patterns(),
+            );
+        }
+    }
+
+    profile.is_active = true;
+    Ok(profile)
+}
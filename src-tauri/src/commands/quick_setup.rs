@@ -0,0 +1,205 @@
+use tauri::State;
+
+use crate::commands::presets::preset_rules;
+use crate::core::health::validate_rule;
+use crate::core::quick_setup::{
+    same_path, suggestions, QuickSetupSelection, QuickSetupSuggestion, SystemDirectories,
+};
+use crate::core::state::AppState;
+use crate::models::Folder;
+use crate::storage::database::Database;
+use crate::storage::folder_repo::{FolderRepository, FolderSetup};
+use crate::utils::platform::normalize_user_path;
+
+#[tauri::command]
+pub fn quick_setup_suggestions(
+    state: State<'_, AppState>,
+) -> Result<Vec<QuickSetupSuggestion>, String> {
+    let watched = FolderRepository::new(state.db.clone())
+        .list()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|folder| !folder.is_group)
+        .map(|folder| normalize_user_path(&folder.path))
+        .collect::<Vec<_>>();
+    Ok(suggestions(&SystemDirectories, &watched))
+}
+
+/// Adds the chosen folders with their presets' rules. Nothing is created
+/// unless every folder and rule can be.
+#[tauri::command]
+pub fn quick_setup_apply(
+    state: State<'_, AppState>,
+    selections: Vec<QuickSetupSelection>,
+) -> Result<Vec<Folder>, String> {
+    let folders = apply_quick_setup(&state.db, selections)?;
+    if let Ok(mut watcher) = state.watcher.lock() {
+        for folder in &folders {
+            let path = normalize_user_path(&folder.path);
+            let _ = watcher.watch_folder(
+                path,
+                folder.id.clone(),
+                folder.scan_depth,
+                &folder.effective_passthrough_patterns(),
+            );
+        }
+    }
+    Ok(folders)
+}
+
+fn apply_quick_setup(
+    db: &Database,
+    selections: Vec<QuickSetupSelection>,
+) -> Result<Vec<Folder>, String> {
+    let repo = FolderRepository::new(db.clone());
+    let watched = repo.list().map_err(|e| e.to_string())?;
+
+    let mut setups = Vec::new();
+    for selection in selections {
+        let path = normalize_user_path(&selection.path);
+        if !path.is_dir() {
+            return Err(format!("{} isn't a folder", path.display()));
+        }
+        if watched
+            .iter()
+            .any(|folder| same_path(&normalize_user_path(&folder.path), &path))
+        {
+            return Err(format!("{} is already being watched", path.display()));
+        }
+        let mut rules = Vec::new();
+        for choice in selection.presets {
+            let name = choice.preset.name.clone();
+            let preset_rules = preset_rules(choice.preset, "", &choice.variables)
+                .map_err(|err| format!("{name}: {err}"))?;
+            for rule in &preset_rules {
+                validate_rule(rule).map_err(|err| format!("{name}: {err}"))?;
+            }
+            rules.extend(preset_rules);
+        }
+        setups.push(FolderSetup {
+            path: path.to_string_lossy().to_string(),
+            name: selection.name,
+            settings: None,
+            rules,
+        });
+    }
+
+    let created = repo.create_with_rules(setups).map_err(|e| e.to_string())?;
+    Ok(created.into_iter().map(|(folder, _)| folder).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_quick_setup;
+    use crate::core::quick_setup::{QuickSetupPreset, QuickSetupSelection};
+    use crate::models::{
+        Action, Condition, ConditionGroup, DeleteAction, MatchType, Preset, PresetRule,
+        StringCondition, StringOperator,
+    };
+    use crate::storage::database::Database;
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::rule_repo::RuleRepository;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    fn preset(id: &str, pattern: &str) -> QuickSetupPreset {
+        QuickSetupPreset {
+            preset: Preset {
+                id: id.to_string(),
+                name: id.to_string(),
+                description: None,
+                author: None,
+                version: None,
+                variables: vec![],
+                rules: vec![PresetRule {
+                    name: format!("{id} rule"),
+                    enabled: None,
+                    stop_processing: None,
+                    conditions: ConditionGroup {
+                        label: None,
+                        match_type: MatchType::All,
+                        conditions: vec![Condition::Name(StringCondition {
+                            operator: StringOperator::Matches,
+                            value: pattern.to_string(),
+                            case_sensitive: false,
+                        })],
+                    },
+                    actions: vec![Action::Delete(DeleteAction { permanent: false })],
+                }],
+            },
+            variables: HashMap::new(),
+        }
+    }
+
+    fn selection(path: &std::path::Path, presets: Vec<QuickSetupPreset>) -> QuickSetupSelection {
+        QuickSetupSelection {
+            path: path.to_string_lossy().to_string(),
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            presets,
+        }
+    }
+
+    #[test]
+    fn creates_folders_with_their_preset_rules() {
+        let dir = tempdir().unwrap();
+        let downloads = dir.path().join("Downloads");
+        std::fs::create_dir(&downloads).unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+
+        let folders = apply_quick_setup(
+            &db,
+            vec![selection(&downloads, vec![preset("a", "^tmp"), preset("b", "\\.part$")])],
+        )
+        .unwrap();
+        assert_eq!(folders.len(), 1);
+        let rules = RuleRepository::new(db).list_by_folder(&folders[0].id).unwrap();
+        let names: Vec<_> = rules.iter().map(|rule| rule.name.as_str()).collect();
+        assert_eq!(names, ["a rule", "b rule"]);
+    }
+
+    #[test]
+    fn one_invalid_preset_creates_nothing() {
+        let dir = tempdir().unwrap();
+        let downloads = dir.path().join("Downloads");
+        let desktop = dir.path().join("Desktop");
+        std::fs::create_dir(&downloads).unwrap();
+        std::fs::create_dir(&desktop).unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+
+        let err = apply_quick_setup(
+            &db,
+            vec![
+                selection(&downloads, vec![preset("fine", "^tmp")]),
+                selection(&desktop, vec![preset("broken", "([unclosed")]),
+            ],
+        )
+        .unwrap_err();
+        assert!(err.starts_with("broken:"), "{err}");
+        assert!(FolderRepository::new(db).list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_failed_insert_rolls_back_earlier_folders() {
+        let dir = tempdir().unwrap();
+        let downloads = dir.path().join("Downloads");
+        std::fs::create_dir(&downloads).unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+
+        // The same folder twice passes the checks but trips the unique path.
+        let err = apply_quick_setup(
+            &db,
+            vec![
+                selection(&downloads, vec![preset("a", "^tmp")]),
+                selection(&downloads, vec![]),
+            ],
+        );
+        assert!(err.is_err());
+        assert!(FolderRepository::new(db.clone()).list().unwrap().is_empty());
+        let orphans: i64 = db
+            .with_conn(|conn| {
+                Ok(conn.query_row("SELECT COUNT(*) FROM rules", [], |row| row.get(0))?)
+            })
+            .unwrap();
+        assert_eq!(orphans, 0);
+    }
+}
@@ -0,0 +1,33 @@
+use chrono::Utc;
+use tauri::State;
+
+use crate::core::state::AppState;
+use crate::models::RetryEntry;
+use crate::storage::retry_repo::RetryRepository;
+
+#[tauri::command]
+pub fn retries_list(state: State<'_, AppState>) -> Result<Vec<RetryEntry>, String> {
+    RetryRepository::new(state.db.clone())
+        .list()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn retry_cancel(state: State<'_, AppState>, retry_id: String) -> Result<(), String> {
+    RetryRepository::new(state.db.clone())
+        .delete(&retry_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Makes a retry due now; the scheduler sends it on its next pass.
+#[tauri::command]
+pub fn retry_force(state: State<'_, AppState>, retry_id: String) -> Result<(), String> {
+    let found = RetryRepository::new(state.db.clone())
+        .force(&retry_id, Utc::now())
+        .map_err(|e| e.to_string())?;
+    if found {
+        Ok(())
+    } else {
+        Err("Retry not found".to_string())
+    }
+}
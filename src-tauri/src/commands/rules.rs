@@ -1,13 +1,42 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use filedispatch_core::file_info::FileInfo;
+use filedispatch_core::rule_schema;
+use filedispatch_core::validate::{keeps_literal_password, validate_classification};
 use tauri::State;
 
+use crate::core::analyze::{analyze_rules, empirical_shadowing, inconclusive_pairs};
+use crate::core::budget::budget_status;
+use crate::core::bulk_edit::bulk_edit;
+use crate::core::engine::{evaluate_conditions, EvaluationOptions};
+use crate::core::error_notify::ErrorNotifier;
+use crate::core::health::validate_rule;
+use crate::core::references::{self, record_count};
+use crate::core::revisions;
 use crate::core::state::AppState;
-use crate::models::Rule;
+use crate::core::trash::validate_folder_rule;
+use crate::models::{
+    BudgetStatus, BulkRuleEdit, CommandError, Lane, Rule, RuleAnalysis, RuleEditSummary,
+    RuleImportError, RuleImportErrorKind, RuleListEntry, RuleReferenceWarnings, RuleRevision,
+    Settings,
+};
+use crate::storage::counter_repo::CounterRepository;
+use crate::storage::match_repo::MatchRepository;
 use crate::storage::rule_repo::RuleRepository;
+use crate::storage::rule_revision_repo::RuleRevisionRepository;
+use crate::utils::sealed::{self, SealError};
+
+const DEFAULT_ANALYSIS_SAMPLE: usize = 200;
 
 #[tauri::command]
-pub fn rule_list(state: State<'_, AppState>, folder_id: String) -> Result<Vec<Rule>, String> {
+pub fn rule_list(
+    state: State<'_, AppState>,
+    folder_id: String,
+) -> Result<Vec<RuleListEntry>, String> {
     let repo = RuleRepository::new(state.db.clone());
-    repo.list_by_folder(&folder_id).map_err(|e| e.to_string())
+    let rules = repo.list_by_folder(&folder_id).map_err(|e| e.to_string())?;
+    Ok(state.reference_checks.annotate(rules))
 }
 
 #[tauri::command]
@@ -16,42 +45,106 @@ pub fn rule_get(state: State<'_, AppState>, id: String) -> Result<Option<Rule>,
     repo.get(&id).map_err(|e| e.to_string())
 }
 
+/// This month's bytes moved, copied and archived by the rule, against its
+/// budget.
 #[tauri::command]
-pub fn rule_create(state: State<'_, AppState>, rule: Rule) -> Result<Rule, String> {
+pub fn rule_budget_status(state: State<'_, AppState>, id: String) -> Result<BudgetStatus, String> {
+    let rule = RuleRepository::new(state.db.clone())
+        .get(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Rule not found".to_string())?;
+    let settings = state.settings.lock().map(|s| s.clone()).unwrap_or_default();
+    budget_status(&state.db, &rule, &settings, Utc::now()).map_err(|e| e.to_string())
+}
+
+#[tauri::command(async)]
+pub fn rule_create(state: State<'_, AppState>, rule: Rule) -> Result<Rule, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
+    validate_rule(&rule)?;
+    validate_folder_rule(&state.db, &rule)?;
+    let settings = state.settings.lock().map(|s| s.clone()).unwrap_or_default();
+    validate_classification(&rule, &settings)?;
     let repo = RuleRepository::new(state.db.clone());
-    repo.create(rule).map_err(|e| e.to_string())
+    let rule = repo.create(rule).map_err(|e| e.to_string())?;
+    revisions::record(&state.db, &rule.id, &settings).map_err(|e| e.to_string())?;
+    recheck_references(&state, &rule);
+    Ok(rule)
 }
 
-#[tauri::command]
-pub fn rule_update(state: State<'_, AppState>, rule: Rule) -> Result<(), String> {
+#[tauri::command(async)]
+pub fn rule_update(state: State<'_, AppState>, rule: Rule) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
+    validate_rule(&rule)?;
+    validate_folder_rule(&state.db, &rule)?;
+    let settings = state.settings.lock().map(|s| s.clone()).unwrap_or_default();
+    validate_classification(&rule, &settings)?;
     let repo = RuleRepository::new(state.db.clone());
     repo.update(&rule).map_err(|e| e.to_string())?;
+    revisions::record(&state.db, &rule.id, &settings).map_err(|e| e.to_string())?;
     let matches = crate::storage::match_repo::MatchRepository::new(state.db.clone());
     let _ = matches.clear_rule(&rule.id);
+    recheck_references(&state, &rule);
     Ok(())
 }
 
+/// The rule as saved at `revision`, while `keep_rule_revisions` is on and log
+/// retention hasn't pruned it.
 #[tauri::command]
-pub fn rule_delete(state: State<'_, AppState>, id: String) -> Result<(), String> {
+pub fn rule_revision_get(
+    state: State<'_, AppState>,
+    rule_id: String,
+    revision: u32,
+) -> Result<Option<RuleRevision>, String> {
+    RuleRevisionRepository::new(state.db.clone())
+        .get(&rule_id, revision)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command(async)]
+pub fn rule_delete(state: State<'_, AppState>, id: String) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
     let repo = RuleRepository::new(state.db.clone());
-    repo.delete(&id).map_err(|e| e.to_string())
+    repo.delete(&id).map_err(|e| e.to_string())?;
+    state.reference_checks.forget(&id);
+    record_count(&state.reference_checks, &state.engine_status);
+    Ok(())
 }
 
-#[tauri::command]
-pub fn rule_toggle(state: State<'_, AppState>, id: String, enabled: bool) -> Result<(), String> {
+/// Starts the rule's `{counter}` over at 1.
+#[tauri::command(async)]
+pub fn rule_counter_reset(state: State<'_, AppState>, id: String) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
+    CounterRepository::new(state.db.clone())
+        .reset(&id)
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub fn rule_toggle(
+    state: State<'_, AppState>,
+    id: String,
+    enabled: bool,
+) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
     let repo = RuleRepository::new(state.db.clone());
-    repo.set_enabled(&id, enabled).map_err(|e| e.to_string())
+    repo.set_enabled(&id, enabled).map_err(|e| e.to_string())?;
+    if let Ok(Some(rule)) = repo.get(&id) {
+        recheck_references(&state, &rule);
+    }
+    Ok(())
 }
 
-#[tauri::command]
+#[tauri::command(async)]
 pub fn rule_reorder(
     state: State<'_, AppState>,
     folder_id: String,
     ordered_ids: Vec<String>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
     let repo = RuleRepository::new(state.db.clone());
     repo.reorder(&folder_id, &ordered_ids)
-        .map_err(|e| e.to_string())
+        .map_err(|e| CommandError::failed(e.to_string()))
 }
 
 #[tauri::command]
@@ -65,10 +158,16 @@ pub fn rule_duplicate(state: State<'_, AppState>, id: String) -> Result<Rule, St
     repo.create(rule).map_err(|e| e.to_string())
 }
 
+/// The folder's rules as a rule file, encrypted with `password` when one is
+/// given.
 #[tauri::command]
-pub fn rule_export(state: State<'_, AppState>, folder_id: String) -> Result<String, String> {
+pub fn rule_export(
+    state: State<'_, AppState>,
+    folder_id: String,
+    password: Option<String>,
+) -> Result<String, String> {
     let repo = RuleRepository::new(state.db.clone());
-    export_rules(&repo, &folder_id)
+    export_rules(&repo, &folder_id, password.as_deref())
 }
 
 #[tauri::command]
@@ -76,67 +175,192 @@ pub fn rule_import(
     state: State<'_, AppState>,
     folder_id: String,
     payload: String,
-) -> Result<Vec<Rule>, String> {
+    password: Option<String>,
+) -> Result<Vec<Rule>, RuleImportError> {
     let repo = RuleRepository::new(state.db.clone());
-    import_rules(&repo, &folder_id, &payload)
+    let settings = state.settings.lock().map(|s| s.clone()).unwrap_or_default();
+    import_rules(&repo, &folder_id, &payload, password.as_deref(), &settings)
 }
 
-fn export_rules(repo: &RuleRepository, folder_id: &str) -> Result<String, String> {
-    let rules = repo.list_by_folder(folder_id).map_err(|e| e.to_string())?;
-    serde_yaml::to_string(&rules).map_err(|e| e.to_string())
+/// Checks every enabled rule for apps, scripts, OCR models and volumes that
+/// are missing, like the periodic check does, and returns the broken ones.
+#[tauri::command]
+pub fn rules_check_references(
+    state: State<'_, AppState>,
+    notifier: State<'_, Arc<ErrorNotifier>>,
+) -> Result<Vec<RuleReferenceWarnings>, String> {
+    let settings = state.settings.lock().map(|s| s.clone()).unwrap_or_default();
+    references::check_all(
+        &state.db,
+        &settings,
+        &state.reference_checks,
+        Some(notifier.inner().as_ref()),
+        &state.engine_status,
+    )
+    .map_err(|e| e.to_string())
 }
 
-fn import_rules(
-    repo: &RuleRepository,
-    folder_id: &str,
-    payload: &str,
-) -> Result<Vec<Rule>, String> {
-    let parsed = parse_rule_payload(payload)?;
-    let mut rules = parsed;
-    let mut created = Vec::new();
-    for mut rule in rules.drain(..) {
-        rule.folder_id = folder_id.to_string();
-        created.push(repo.create(rule).map_err(|e| e.to_string())?);
+/// Keeps a saved rule's reference warnings current without waiting for the
+/// next periodic check.
+fn recheck_references(state: &AppState, rule: &Rule) {
+    let settings = state.settings.lock().map(|s| s.clone()).unwrap_or_default();
+    state.reference_checks.recheck(rule, &settings);
+    record_count(&state.reference_checks, &state.engine_status);
+}
+
+/// Applies one edit to several rules at once. Nothing is saved unless every
+/// edited rule still validates.
+#[tauri::command(async)]
+pub fn rules_bulk_edit(
+    state: State<'_, AppState>,
+    rule_ids: Vec<String>,
+    edit: BulkRuleEdit,
+) -> Result<Vec<RuleEditSummary>, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
+    let summaries = bulk_edit(&state.db, &rule_ids, &edit).map_err(|e| e.to_string())?;
+    let settings = state.settings.lock().map(|s| s.clone()).unwrap_or_default();
+    for summary in summaries.iter().filter(|s| !s.changes.is_empty()) {
+        revisions::record(&state.db, &summary.rule_id, &settings).map_err(|e| e.to_string())?;
     }
-    Ok(created)
+    // Like rule_update, edited rules get another look at files they've already
+    // handled; enabling or disabling is just a toggle.
+    if !matches!(edit, BulkRuleEdit::Enable | BulkRuleEdit::Disable) {
+        let matches = MatchRepository::new(state.db.clone());
+        for summary in summaries.iter().filter(|s| !s.changes.is_empty()) {
+            let _ = matches.clear_rule(&summary.rule_id);
+        }
+    }
+    Ok(summaries)
 }
 
-fn parse_rule_payload(payload: &str) -> Result<Vec<Rule>, String> {
-    #[derive(serde::Deserialize)]
-    #[serde(untagged)]
-    enum RulePayload {
-        One(Rule),
-        Many(Vec<Rule>),
+/// Reports shadowed, overlapping and unsatisfiable rules in a folder. With
+/// `empirical`, rule pairs static reasoning can't decide are replayed against
+/// recently matched files that still exist (content conditions are skipped).
+#[tauri::command(async)]
+pub fn rules_analyze(
+    state: State<'_, AppState>,
+    folder_id: String,
+    empirical: Option<bool>,
+    sample_size: Option<usize>,
+) -> Result<RuleAnalysis, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadHeavy, None)?;
+    let repo = RuleRepository::new(state.db.clone());
+    let rules = repo.list_by_folder(&folder_id).map_err(|e| e.to_string())?;
+    let mut analysis = analyze_rules(&folder_id, &rules);
+    if !empirical.unwrap_or(false) {
+        return Ok(analysis);
     }
 
-    let trimmed = payload.trim();
-    if trimmed.is_empty() {
-        return Err("Rule import file is empty.".to_string());
+    let pairs = inconclusive_pairs(&rules);
+    if pairs.is_empty() {
+        return Ok(analysis);
     }
 
-    if trimmed.starts_with('{') || trimmed.starts_with('[') {
-        if let Ok(parsed) = serde_json::from_str::<RulePayload>(trimmed) {
-            return Ok(match parsed {
-                RulePayload::One(rule) => vec![rule],
-                RulePayload::Many(rules) => rules,
-            });
+    let match_repo = MatchRepository::new(state.db.clone());
+    let samples: Vec<FileInfo> = match_repo
+        .recent_paths_for_folder(&folder_id, sample_size.unwrap_or(DEFAULT_ANALYSIS_SAMPLE))
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter_map(|path| FileInfo::from_path(std::path::Path::new(&path)).ok())
+        .collect();
+
+    let settings = state
+        .settings
+        .lock()
+        .map(|s| s.clone())
+        .unwrap_or_default();
+    let mut ocr = state.ocr.lock().map_err(|e| e.to_string())?;
+    let options = EvaluationOptions {
+        skip_content: true,
+        ..EvaluationOptions::default()
+    };
+    let findings = empirical_shadowing(&rules, &pairs, &samples, |rule, info| {
+        evaluate_conditions(rule, info, &settings, &mut ocr, &options)
+            .map(|result| result.matched)
+            .unwrap_or(false)
+    });
+    analysis.findings.extend(findings);
+    analysis.sampled_files = samples.len();
+    Ok(analysis)
+}
+
+fn export_rules(
+    repo: &RuleRepository,
+    folder_id: &str,
+    password: Option<&str>,
+) -> Result<String, String> {
+    let rules = repo.list_by_folder(folder_id).map_err(|e| e.to_string())?;
+    if password.is_none() {
+        if let Some(rule) = rules.iter().find(|rule| keeps_literal_password(rule)) {
+            return Err(format!(
+                "Rule \"{}\" keeps its archive password in the rule. Encrypt the export \
+                 with a password, or keep the archive password in the keychain or an \
+                 environment variable.",
+                rule.name
+            ));
         }
     }
-
-    let parsed: RulePayload = serde_yaml::from_str(trimmed).map_err(|e| e.to_string())?;
-    Ok(match parsed {
-        RulePayload::One(rule) => vec![rule],
-        RulePayload::Many(rules) => rules,
+    let payload = rule_schema::export_rules(&rules).map_err(|e| e.to_string())?;
+    Ok(match password {
+        Some(password) => sealed::seal_text(&payload, password),
+        None => payload,
     })
 }
 
+fn import_rules(
+    repo: &RuleRepository,
+    folder_id: &str,
+    payload: &str,
+    password: Option<&str>,
+    settings: &Settings,
+) -> Result<Vec<Rule>, RuleImportError> {
+    let opened;
+    let payload = if sealed::is_sealed_text(payload) {
+        let Some(password) = password else {
+            return Err(RuleImportError::new(
+                RuleImportErrorKind::PasswordRequired,
+                "This rule file is encrypted; enter its password to import it.",
+            ));
+        };
+        opened = sealed::open_text(payload, password).map_err(|err| match err {
+            SealError::WrongPassword => {
+                RuleImportError::new(RuleImportErrorKind::WrongPassword, err.to_string())
+            }
+            SealError::Corrupt(_) => RuleImportError::invalid(err.to_string()),
+        })?;
+        opened.as_str()
+    } else {
+        payload
+    };
+    let mut rules = rule_schema::import_rules(payload)?;
+    for (index, rule) in rules.iter().enumerate() {
+        validate_rule(rule)
+            .and_then(|()| validate_classification(rule, settings))
+            .map_err(|message| RuleImportError::invalid(message).at(format!("rules[{index}]")))?;
+    }
+    let mut created = Vec::new();
+    for mut rule in rules.drain(..) {
+        rule.folder_id = folder_id.to_string();
+        created.push(
+            repo.create(rule)
+                .map_err(|e| RuleImportError::new(RuleImportErrorKind::Failed, e.to_string()))?,
+        );
+    }
+    Ok(created)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{export_rules, import_rules};
-    use crate::models::{ConditionGroup, MatchType, Rule};
+    use super::{export_rules, import_rules, validate_rule};
+    use crate::models::{
+        Action, ArchiveAction, ArchiveEncryption, ArchiveEncryptionMethod, ArchiveFormat,
+        ConditionGroup, ConflictResolution, CreateFolderStructureAction, MatchType, MoveAction,
+        PasswordSource, Rule, RuleImportErrorKind, Settings, UnarchiveAction,
+    };
     use crate::storage::database::Database;
     use crate::storage::folder_repo::FolderRepository;
     use crate::storage::rule_repo::RuleRepository;
+    use std::collections::HashMap;
     use tempfile::tempdir;
 
     fn sample_rule(folder_id: String, name: &str) -> Rule {
@@ -153,6 +377,17 @@ mod tests {
             },
             actions: vec![],
             position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         }
@@ -172,10 +407,12 @@ mod tests {
         let rule = sample_rule(folder.id.clone(), "Export Rule");
         let created = rule_repo.create(rule).unwrap();
 
-        let payload = export_rules(&rule_repo, &folder.id).unwrap();
-        let parsed: Vec<Rule> = serde_yaml::from_str(&payload).unwrap();
-        assert_eq!(parsed.len(), 1);
-        assert_eq!(parsed[0].name, created.name);
+        let payload = export_rules(&rule_repo, &folder.id, None).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(parsed["schemaVersion"], "2.0");
+        assert_eq!(parsed["rules"].as_array().unwrap().len(), 1);
+        assert_eq!(parsed["rules"][0]["name"], created.name.as_str());
+        assert!(parsed["rules"][0].get("id").is_none());
     }
 
     #[test]
@@ -192,7 +429,14 @@ mod tests {
 
         let original = sample_rule("source-folder".to_string(), "Import Rule");
         let payload = serde_yaml::to_string(&vec![original]).unwrap();
-        let created = import_rules(&rule_repo, &target_folder.id, &payload).unwrap();
+        let created = import_rules(
+            &rule_repo,
+            &target_folder.id,
+            &payload,
+            None,
+            &Settings::default(),
+        )
+        .unwrap();
 
         assert_eq!(created.len(), 1);
         assert_eq!(created[0].folder_id, target_folder.id);
@@ -216,9 +460,247 @@ mod tests {
 
         let original = sample_rule("source-folder".to_string(), "Import JSON");
         let payload = serde_json::to_string(&original).unwrap();
-        let created = import_rules(&rule_repo, &target_folder.id, &payload).unwrap();
+        let created = import_rules(
+            &rule_repo,
+            &target_folder.id,
+            &payload,
+            None,
+            &Settings::default(),
+        )
+        .unwrap();
 
         assert_eq!(created.len(), 1);
         assert_eq!(created[0].folder_id, target_folder.id);
     }
+
+    #[test]
+    fn validate_rule_rejects_folder_structure_traversal() {
+        let mut rule = sample_rule("folder".to_string(), "Projects");
+        rule.actions = vec![Action::CreateFolderStructure(CreateFolderStructureAction {
+            base_destination: "~/Projects".to_string(),
+            structure: vec!["{1}/incoming".to_string(), "{1}/final".to_string()],
+        })];
+        assert!(validate_rule(&rule).is_ok());
+
+        rule.actions = vec![Action::CreateFolderStructure(CreateFolderStructureAction {
+            base_destination: "~/Projects".to_string(),
+            structure: vec!["{1}/../../etc".to_string()],
+        })];
+        assert!(validate_rule(&rule).is_err());
+
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Reject")
+            .unwrap();
+        let rule_repo = RuleRepository::new(db);
+        let payload = serde_json::to_string(&rule).unwrap();
+        assert!(
+            import_rules(&rule_repo, &folder.id, &payload, None, &Settings::default()).is_err()
+        );
+        assert!(rule_repo.list_by_folder(&folder.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn path_scope_survives_export_and_import() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder_repo = FolderRepository::new(db.clone());
+        let rule_repo = RuleRepository::new(db);
+        let source = folder_repo
+            .create(&dir.path().join("a").to_string_lossy(), "Source")
+            .unwrap();
+        let target = folder_repo
+            .create(&dir.path().join("b").to_string_lossy(), "Target")
+            .unwrap();
+        let mut rule = sample_rule(source.id.clone(), "Scoped");
+        rule.path_scope = Some("Telegram*/**".to_string());
+        rule_repo.create(rule).unwrap();
+
+        let payload = export_rules(&rule_repo, &source.id, None).unwrap();
+        let created =
+            import_rules(&rule_repo, &target.id, &payload, None, &Settings::default()).unwrap();
+        assert_eq!(created[0].path_scope.as_deref(), Some("Telegram*/**"));
+        let stored = rule_repo.get(&created[0].id).unwrap().unwrap();
+        assert_eq!(stored.path_scope.as_deref(), Some("Telegram*/**"));
+    }
+
+    #[test]
+    fn fields_from_a_newer_minor_version_survive_storage() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Newer")
+            .unwrap();
+        let rule_repo = RuleRepository::new(db);
+        let payload = std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("crates/filedispatch-core/tests/fixtures/rules/v2_1_unknown_fields.json"),
+        )
+        .unwrap();
+
+        let created =
+            import_rules(&rule_repo, &folder.id, &payload, None, &Settings::default()).unwrap();
+        let mut stored = rule_repo.get(&created[0].id).unwrap().unwrap();
+        stored.name = "Renamed".to_string();
+        rule_repo.update(&stored).unwrap();
+
+        let exported: serde_json::Value =
+            serde_json::from_str(&export_rules(&rule_repo, &folder.id, None).unwrap()).unwrap();
+        let rule = &exported["rules"][0];
+        assert_eq!(rule["name"], "Renamed");
+        assert_eq!(rule["color"], "teal");
+        assert_eq!(rule["conditions"]["conditions"][0]["fuzzy"], true);
+        assert_eq!(rule["actions"][0]["preserveTimestamps"], true);
+    }
+
+    #[test]
+    fn validate_rule_rejects_scopes_outside_the_folder() {
+        let mut rule = sample_rule("folder".to_string(), "Scoped");
+        for scope in ["Telegram Desktop", "Telegram*/**", "sub/./dir/"] {
+            rule.path_scope = Some(scope.to_string());
+            assert!(validate_rule(&rule).is_ok(), "{scope}");
+        }
+        for scope in ["/home/me/Downloads", "C:\\Downloads", "../Desktop", "a/../../b", "[oops"] {
+            rule.path_scope = Some(scope.to_string());
+            assert!(validate_rule(&rule).is_err(), "{scope}");
+        }
+    }
+
+    #[test]
+    fn validate_rule_rejects_invalid_mapping_tokens() {
+        let mut rule = sample_rule("folder".to_string(), "Sort by kind");
+        let move_to = |destination: &str| {
+            Action::Move(MoveAction {
+                destination: destination.to_string(),
+                on_conflict: ConflictResolution::Rename,
+                skip_duplicates: false,
+                destination_permissions: Default::default(),
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
+            })
+        };
+        rule.actions = vec![move_to("~/{map:kind:Image=Pictures,*=Other}/{year}")];
+        assert!(validate_rule(&rule).is_ok());
+        rule.actions = vec![move_to("~/{map:kind:Image=Pictures,Video=Videos}/{year}")];
+        let err = validate_rule(&rule).unwrap_err();
+        assert!(err.contains("Sort by kind") && err.contains("* fallback"), "{err}");
+    }
+
+    #[test]
+    fn validate_rule_rejects_invalid_unarchive_globs() {
+        let mut rule = sample_rule("folder".to_string(), "Unpack");
+        let unarchive = |exclude: &str| {
+            Action::Unarchive(UnarchiveAction {
+                destination: None,
+                delete_after: false,
+                include_globs: vec!["*.pdf".to_string()],
+                exclude_globs: vec![exclude.to_string()],
+                skip_junk: true,
+            })
+        };
+        rule.actions = vec![unarchive("*/samples")];
+        assert!(validate_rule(&rule).is_ok());
+        rule.actions = vec![unarchive("samples[")];
+        assert!(validate_rule(&rule).unwrap_err().contains("samples["));
+    }
+
+    /// SHA-256 of the unencrypted export of "Client invoices" as written
+    /// before exports could be encrypted.
+    const PLAIN_EXPORT_SHA256: &str =
+        "2b4bcd7507691f4276be5564c58dda0f152138df29719ebce6d34cad7d66a312";
+
+    #[test]
+    fn encrypted_exports_round_trip_and_plain_ones_are_unchanged() {
+        use sha2::{Digest, Sha256};
+
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder_repo = FolderRepository::new(db.clone());
+        let rule_repo = RuleRepository::new(db);
+        let source = folder_repo
+            .create(&dir.path().join("a").to_string_lossy(), "Source")
+            .unwrap();
+        let target = folder_repo
+            .create(&dir.path().join("b").to_string_lossy(), "Target")
+            .unwrap();
+        let mut rule = sample_rule(source.id.clone(), "Client invoices");
+        rule.path_scope = Some("Clients/**".to_string());
+        rule_repo.create(rule).unwrap();
+
+        let plain = export_rules(&rule_repo, &source.id, None).unwrap();
+        let digest: String = Sha256::digest(plain.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        assert_eq!(digest, PLAIN_EXPORT_SHA256);
+
+        let sealed = export_rules(&rule_repo, &source.id, Some("hunter2")).unwrap();
+        assert!(!sealed.contains("Clients"));
+        let err =
+            import_rules(&rule_repo, &target.id, &sealed, None, &Settings::default()).unwrap_err();
+        assert_eq!(err.kind, RuleImportErrorKind::PasswordRequired);
+        let err = import_rules(
+            &rule_repo,
+            &target.id,
+            &sealed,
+            Some("wrong"),
+            &Settings::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, RuleImportErrorKind::WrongPassword);
+
+        let mut tampered = sealed.clone().into_bytes();
+        let last = tampered.len() - 3;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        let err = import_rules(
+            &rule_repo,
+            &target.id,
+            &tampered,
+            Some("hunter2"),
+            &Settings::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, RuleImportErrorKind::Invalid);
+        assert!(rule_repo.list_by_folder(&target.id).unwrap().is_empty());
+
+        let created = import_rules(
+            &rule_repo,
+            &target.id,
+            &sealed,
+            Some("hunter2"),
+            &Settings::default(),
+        )
+        .unwrap();
+        assert_eq!(created[0].name, "Client invoices");
+        assert_eq!(created[0].path_scope.as_deref(), Some("Clients/**"));
+    }
+
+    #[test]
+    fn archive_passwords_kept_in_rules_only_leave_encrypted() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "HR")
+            .unwrap();
+        let rule_repo = RuleRepository::new(db);
+        let mut rule = sample_rule(folder.id.clone(), "Payslips");
+        rule.actions = vec![Action::Archive(ArchiveAction {
+            destination: "~/Archive/{name}".to_string(),
+            format: ArchiveFormat::Zip,
+            delete_after: false,
+            encryption: Some(ArchiveEncryption {
+                method: ArchiveEncryptionMethod::ZipAes256,
+                password_source: PasswordSource::Literal("hunter2".to_string()),
+            }),
+        })];
+        rule_repo.create(rule).unwrap();
+
+        let err = export_rules(&rule_repo, &folder.id, None).unwrap_err();
+        assert!(err.contains("keeps its archive password"), "{err}");
+        let sealed = export_rules(&rule_repo, &folder.id, Some("pw")).unwrap();
+        assert!(!sealed.contains("hunter2"));
+    }
 }
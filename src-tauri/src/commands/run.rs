@@ -1,16 +1,15 @@
+use std::ops::ControlFlow;
+
 use serde::Serialize;
 use tauri::{AppHandle, Emitter, State};
 
-use crate::core::engine::{evaluate_conditions, log_outcomes, EvaluationOptions};
 use crate::core::executor::ActionExecutor;
+use crate::core::scan::{scan_folder, RunResult};
 use crate::core::state::AppState;
+use crate::core::trash::{SystemTrash, TrashSweeper};
+use crate::models::{CommandError, EventSource, FolderKind, Lane};
 use crate::storage::folder_repo::FolderRepository;
-use crate::storage::log_repo::LogRepository;
-use crate::storage::match_repo::MatchRepository;
 use crate::storage::rule_repo::RuleRepository;
-use crate::storage::undo_repo::UndoRepository;
-use crate::utils::file_info::FileInfo;
-use crate::utils::platform::normalize_user_path;
 
 #[derive(Clone, Serialize)]
 pub struct RunProgress {
@@ -19,20 +18,18 @@ pub struct RunProgress {
     pub current_file: String,
 }
 
-#[derive(Clone, Serialize)]
-pub struct RunResult {
-    pub total_files: usize,
-    pub processed: usize,
-    pub matched: usize,
-    pub errors: Vec<String>,
-}
-
+/// Scans have no time limit, as big folders take a while; the window that
+/// started one cancels it by `request_id` when it goes away.
 #[tauri::command]
 pub async fn folder_run_now(
     app: AppHandle,
     state: State<'_, AppState>,
     folder_id: String,
-) -> Result<RunResult, String> {
+    request_id: Option<String>,
+) -> Result<RunResult, CommandError> {
+    let scope = state
+        .command_lanes
+        .enter(Lane::ReadHeavy, request_id.as_deref())?;
     let db = state.db.clone();
     let settings = state.settings.clone();
     let ocr = state.ocr.clone();
@@ -42,149 +39,90 @@ pub async fn folder_run_now(
     let folder = folder_repo
         .get(&folder_id)
         .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Folder not found".to_string())?;
-
-    let folder_path = normalize_user_path(&folder.path);
-    if !folder_path.exists() {
-        return Err(format!("Folder does not exist: {}", folder_path.display()));
-    }
-
-    // Collect all files in the folder respecting scan_depth
-    let max_depth = folder.max_depth().unwrap_or(usize::MAX);
-    let entries: Vec<_> = walkdir::WalkDir::new(&folder_path)
-        .max_depth(max_depth)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().is_file())
-        .collect();
-
-    let total = entries.len();
-    let mut processed = 0;
-    let mut matched = 0;
-    let mut errors = Vec::new();
+        .ok_or("Folder not found")?;
 
     // Create executor
     let executor = ActionExecutor::new(app.clone(), settings.clone(), ocr.clone());
 
-    // Get repositories
-    let rule_repo = RuleRepository::new(db.clone());
-    let match_repo = MatchRepository::new(db.clone());
-    let log_repo = LogRepository::new(db.clone());
-    let undo_repo = UndoRepository::new(db.clone());
-
     // Get rules for this folder
+    let rule_repo = RuleRepository::new(db.clone());
     let rules = rule_repo
         .list_by_folder(&folder_id)
         .map_err(|e| e.to_string())?;
 
-    // Process each file
     let settings_snapshot = settings.lock().map(|s| s.clone()).unwrap_or_default();
-    let mut ocr_guard = ocr.lock().unwrap();
-
-    for entry in entries {
-        let file_path = entry.path();
-        let file_name = file_path
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_default();
 
-        // Emit progress
-        let _ = app.emit(
-            "run_progress",
-            RunProgress {
-                total,
-                processed,
-                current_file: file_name.clone(),
-            },
-        );
-
-        // Get file info
-        let info = match FileInfo::from_path(&file_path) {
-            Ok(info) => info,
-            Err(e) => {
-                errors.push(format!("{}: {}", file_name, e));
-                processed += 1;
-                continue;
-            }
+    if folder.kind == FolderKind::SystemTrash {
+        let trash = SystemTrash::locate().ok_or("Couldn't find the system trash")?;
+        let sweeper = TrashSweeper {
+            db: &db,
+            executor: &executor,
+            settings: &settings_snapshot,
+            ocr: &ocr,
+            duplicates: &state.duplicate_detector,
         };
+        let result = sweeper
+            .sweep(&trash, &folder, &rules)
+            .map_err(|e| e.to_string())?;
+        return Ok(result);
+    }
 
-        // Process against each rule
-        let mut file_matched = false;
-        for rule in &rules {
-            if !rule.enabled {
-                continue;
-            }
-
-            // Skip if already matched (optional - remove this to re-run on all files)
-            // if match_repo
-            //     .has_match(&rule.id, info.path.to_string_lossy().as_ref(), Some(&info.hash))
-            //     .unwrap_or(false)
-            // {
-            //     continue;
-            // }
-
-            // Evaluate conditions
-            let evaluation = match evaluate_conditions(
-                rule,
-                &info,
-                &settings_snapshot,
-                &mut ocr_guard,
-                &EvaluationOptions::default(),
-            ) {
-                    Ok(eval) => eval,
-                    Err(e) => {
-                        errors.push(format!("{}: {}", file_name, e));
-                        continue;
-                    }
-                };
-
-            if !evaluation.matched {
-                continue;
-            }
-
-            file_matched = true;
-
-            // Execute actions
-            let outcomes = executor.execute_actions(&rule.actions, &info, &evaluation.captures);
-
-            // Log outcomes
-            if let Err(e) = log_outcomes(&log_repo, &undo_repo, rule, &info, &outcomes) {
-                errors.push(format!("{}: {}", file_name, e));
+    let result = scan_folder(
+        &db,
+        &executor,
+        &folder,
+        &rules,
+        &settings_snapshot,
+        &ocr,
+        &state.folder_lanes,
+        &state.duplicate_detector,
+        EventSource::Manual,
+        |total, processed, current_file| {
+            if scope.check().is_err() {
+                return ControlFlow::Break(());
             }
-
-            // Record match
-            let _ = match_repo.record_match(
-                &rule.id,
-                info.path.to_string_lossy().as_ref(),
-                Some(&info.hash),
+            let _ = app.emit(
+                "run_progress",
+                RunProgress {
+                    total,
+                    processed,
+                    current_file: current_file.to_string(),
+                },
             );
-
-            // Stop processing if rule says so
-            if rule.stop_processing {
-                break;
-            }
-        }
-
-        if file_matched {
-            matched += 1;
-        }
-        processed += 1;
-    }
-
-    // Emit completion
-    let _ = app.emit(
-        "run_progress",
-        RunProgress {
-            total,
-            processed,
-            current_file: String::new(),
+            ControlFlow::Continue(())
         },
-    );
+    )
+    .map_err(|e| e.to_string())?;
+    // Stopped part way; the next run carries on where this one left off.
+    if !result.complete {
+        scope.check()?;
+    }
+    Ok(result)
+}
 
-    Ok(RunResult {
-        total_files: total,
-        processed,
-        matched,
-        errors,
-    })
+/// Queues the folder's files for the engine now, rather than waiting for the
+/// next periodic rescan. Returns how many files were queued; the rules run
+/// on them as the engine gets to them.
+#[tauri::command]
+pub async fn folder_scan_now(
+    state: State<'_, AppState>,
+    folder_id: String,
+    request_id: Option<String>,
+) -> Result<usize, CommandError> {
+    let scope = state
+        .command_lanes
+        .enter(Lane::ReadHeavy, request_id.as_deref())?;
+    let folder = FolderRepository::new(state.db.clone())
+        .get(&folder_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Folder not found")?;
+    if !folder.is_directory() {
+        return Err("Only watched folders can be scanned".into());
+    }
+    let queued = state
+        .folder_scans
+        .scan(&folder, || scope.check().is_err())
+        .map_err(|e| e.to_string())?;
+    scope.check()?;
+    Ok(queued)
 }
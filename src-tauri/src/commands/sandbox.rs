@@ -0,0 +1,41 @@
+use tauri::State;
+
+use crate::core::sandbox::{load_scenario, run_scenario, save_scenario};
+use crate::core::state::AppState;
+use crate::models::{CommandError, Lane, SandboxResult, SandboxScenario};
+use crate::utils::platform::normalize_user_path;
+
+/// Runs `folder_id`'s rules against `scenario` in a throwaway sandbox, with
+/// the current settings, and returns what they did there.
+#[tauri::command(async)]
+pub fn sandbox_run(
+    state: State<'_, AppState>,
+    folder_id: String,
+    scenario: SandboxScenario,
+) -> Result<SandboxResult, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadHeavy, None)?;
+    let settings = state
+        .settings
+        .lock()
+        .map(|settings| settings.clone())
+        .unwrap_or_default();
+    run_scenario(
+        &state.db,
+        &folder_id,
+        &scenario,
+        &settings,
+        state.ocr.clone(),
+    )
+    .map_err(|e| CommandError::failed(e.to_string()))
+}
+
+#[tauri::command(async)]
+pub fn sandbox_scenario_save(path: String, scenario: SandboxScenario) -> Result<(), CommandError> {
+    save_scenario(&normalize_user_path(&path), &scenario)
+        .map_err(|e| CommandError::failed(e.to_string()))
+}
+
+#[tauri::command(async)]
+pub fn sandbox_scenario_load(path: String) -> Result<SandboxScenario, CommandError> {
+    load_scenario(&normalize_user_path(&path)).map_err(|e| CommandError::failed(e.to_string()))
+}
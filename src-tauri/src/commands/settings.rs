@@ -1,8 +1,11 @@
+use filedispatch_core::kinds::{self, KindMapping};
 use tauri::{AppHandle, State};
 use tauri_plugin_store::StoreBuilder;
 
+use crate::core::diagnostics::validate_settings;
 use crate::core::state::AppState;
-use crate::models::Settings;
+use crate::core::unique_name;
+use crate::models::{CommandError, Lane, Settings};
 
 const SETTINGS_STORE: &str = "settings.json";
 
@@ -18,13 +21,44 @@ pub fn settings_get(app: AppHandle) -> Result<Settings, String> {
     }
 }
 
-#[tauri::command]
+#[tauri::command(async)]
 pub fn settings_update(
     app: AppHandle,
     state: State<'_, AppState>,
     settings: Settings,
+) -> Result<(), CommandError> {
+    let _scope = state.command_lanes.enter(Lane::Write, None)?;
+    Ok(apply_settings(&app, &state, settings)?)
+}
+
+/// The extension table kinds are told by, with where each entry comes from.
+#[tauri::command]
+pub fn kind_mappings_list() -> Vec<KindMapping> {
+    kinds::effective_mappings()
+}
+
+/// The indexed metadata keys trimmed, without blanks or repeats.
+fn normalize_indexed_keys(settings: &Settings) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for key in &settings.match_metadata_indexed_keys {
+        let key = key.trim();
+        if !key.is_empty() && !keys.iter().any(|known| known == key) {
+            keys.push(key.to_string());
+        }
+    }
+    keys
+}
+
+/// Persists `settings` and pushes them into the watcher, engine and OCR manager.
+pub(crate) fn apply_settings(
+    app: &AppHandle,
+    state: &AppState,
+    mut settings: Settings,
 ) -> Result<(), String> {
-    let store = StoreBuilder::new(&app, SETTINGS_STORE)
+    validate_settings(&settings)?;
+    settings.kind_overrides = kinds::normalize_overrides(&settings.kind_overrides)
+        .map_err(|err| format!("Kind mappings: {err}"))?;
+    let store = StoreBuilder::new(app, SETTINGS_STORE)
         .build()
         .map_err(|e| e.to_string())?;
     store.set(
@@ -34,13 +68,33 @@ pub fn settings_update(
     if let Ok(mut watcher) = state.watcher.lock() {
         watcher.set_ignore_patterns(settings.ignore_patterns.clone());
     }
+    kinds::set_overrides(&settings.kind_overrides);
+    unique_name::set_format(&settings.unique_suffix_format);
+    settings.match_metadata_indexed_keys = normalize_indexed_keys(&settings);
+    let reindex = state.settings.lock().map_or(true, |stored| {
+        stored.match_metadata_indexed_keys != settings.match_metadata_indexed_keys
+    });
     if let Ok(mut stored) = state.settings.lock() {
         *stored = settings.clone();
     }
+    if reindex {
+        let match_repo = crate::storage::match_repo::MatchRepository::new(state.db.clone());
+        match_repo
+            .reindex_metadata(&settings.match_metadata_indexed_keys)
+            .map_err(|e| format!("Indexed metadata keys: {e}"))?;
+    }
     if let Ok(mut ocr) = state.ocr.lock() {
         ocr.update_settings(settings.clone());
     }
     let log_repo = crate::storage::log_repo::LogRepository::new(state.db.clone());
     let _ = log_repo.cleanup(settings.log_retention_days);
+    let undo_repo = crate::storage::undo_repo::UndoRepository::new(state.db.clone());
+    let _ = undo_repo.prune(
+        settings.undo_max_entries,
+        settings.undo_retention_days,
+        chrono::Utc::now(),
+    );
+    let approval_repo = crate::storage::approval_repo::ApprovalRepository::new(state.db.clone());
+    let _ = approval_repo.cleanup_expired(settings.approval_expiry_hours);
     store.save().map_err(|e| e.to_string())
 }
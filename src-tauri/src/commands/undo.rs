@@ -1,32 +1,70 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use chrono::{DateTime, Duration, Utc};
 use tauri::State;
 
+use crate::core::duplicates::matches_recorded;
+use crate::core::executor::unique_path;
 use crate::core::state::AppState;
-use crate::models::{ActionDetails, LogEntry, LogStatus, UndoEntry};
+use crate::core::unique_name;
+use crate::models::{
+    ActionDetails, CommandError, ConflictResolution, Lane, LogEntry, LogStatus, UndoEntry,
+    UndoError, UndoErrorKind, UndoStats, UndoStatus,
+};
 use crate::storage::log_repo::LogRepository;
 use crate::storage::undo_repo::UndoRepository;
 
-#[tauri::command]
+#[tauri::command(async)]
 pub fn undo_list(
     state: State<'_, AppState>,
     limit: Option<usize>,
-) -> Result<Vec<UndoEntry>, String> {
+) -> Result<Vec<UndoEntry>, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadLight, None)?;
     let repo = UndoRepository::new(state.db.clone());
     let limit = limit.unwrap_or(50);
-    repo.list(limit).map_err(|e| e.to_string())
+    let retention_days = undo_retention_days(&state);
+    let now = Utc::now();
+    let entries = repo.list(limit).map_err(|e| e.to_string())?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| annotate_status(entry, now, retention_days))
+        .collect())
+}
+
+#[tauri::command(async)]
+pub fn undo_stats(state: State<'_, AppState>) -> Result<UndoStats, CommandError> {
+    let _scope = state.command_lanes.enter(Lane::ReadLight, None)?;
+    let (max_entries, retention_days) = state
+        .settings
+        .lock()
+        .map(|s| (s.undo_max_entries, s.undo_retention_days))
+        .map_err(|e| e.to_string())?;
+    UndoRepository::new(state.db.clone())
+        .stats(max_entries, retention_days, Utc::now())
+        .map_err(|e| CommandError::failed(e.to_string()))
 }
 
 #[tauri::command]
-pub fn undo_execute(state: State<'_, AppState>, undo_id: String) -> Result<(), String> {
+pub fn undo_execute(
+    state: State<'_, AppState>,
+    undo_id: String,
+    force: Option<bool>,
+    on_conflict: Option<ConflictResolution>,
+) -> Result<(), UndoError> {
     let undo_repo = UndoRepository::new(state.db.clone());
     let log_repo = LogRepository::new(state.db.clone());
     let entry = undo_repo
         .get(&undo_id)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Undo entry not found".to_string())?;
+        .map_err(|e| UndoError::failed(e.to_string()))?
+        .ok_or_else(|| UndoError::new(UndoErrorKind::NotFound, "Undo entry not found"))?;
 
-    let result = apply_undo(&entry);
+    let result = verify_undo(
+        &entry,
+        Utc::now(),
+        undo_retention_days(&state),
+        force.unwrap_or(false),
+    )
+    .and_then(|()| apply_undo(&entry, on_conflict));
     let status = if result.is_ok() {
         LogStatus::Success
     } else {
@@ -54,53 +92,194 @@ pub fn undo_execute(state: State<'_, AppState>, undo_id: String) -> Result<(), S
     };
     let _ = log_repo.insert(log_entry);
 
-    if let Err(err) = result {
-        return Err(err.to_string());
-    }
+    result?;
 
-    undo_repo.delete(&undo_id).map_err(|e| e.to_string())?;
+    undo_repo
+        .delete(&undo_id)
+        .map_err(|e| UndoError::failed(e.to_string()))?;
     Ok(())
 }
 
-fn apply_undo(entry: &UndoEntry) -> Result<(), String> {
+fn undo_retention_days(state: &AppState) -> u32 {
+    state
+        .settings
+        .lock()
+        .map(|s| s.undo_retention_days)
+        .unwrap_or(0)
+}
+
+fn is_expired(entry: &UndoEntry, now: DateTime<Utc>, retention_days: u32) -> bool {
+    retention_days > 0 && now - entry.created_at > Duration::days(retention_days as i64)
+}
+
+/// Fills in `status`/`status_reason` so the UI can show why an entry can't be undone.
+fn annotate_status(mut entry: UndoEntry, now: DateTime<Utc>, retention_days: u32) -> UndoEntry {
+    let (status, reason) = if is_expired(&entry, now, retention_days) {
+        (
+            UndoStatus::Expired,
+            Some(format!("Older than the {retention_days}-day undo window")),
+        )
+    } else if !Path::new(&entry.current_path).exists() {
+        (
+            UndoStatus::Missing,
+            Some("File no longer exists at current path".to_string()),
+        )
+    } else {
+        match file_changed(&entry) {
+            Ok(true) => (
+                UndoStatus::Changed,
+                Some("File was modified after this action".to_string()),
+            ),
+            Err(err) => (
+                UndoStatus::Changed,
+                Some(format!("Couldn't check the file for changes: {err}")),
+            ),
+            Ok(false) if entry.hash.is_none() => {
+                (UndoStatus::Available, Some(UNCHECKED.to_string()))
+            }
+            Ok(false) => (UndoStatus::Available, None),
+        }
+    };
+    entry.status = status;
+    entry.status_reason = reason;
+    entry
+}
+
+/// Why an entry recorded before undo hashed files is undone unchecked.
+const UNCHECKED: &str =
+    "Recorded before undo checked files for changes, so edits since then won't be noticed";
+
+/// True when the file at `current_path` no longer matches the recorded hash,
+/// and an error when it couldn't be hashed. Legacy entries without a hash
+/// (and directories) are never reported as changed.
+fn file_changed(entry: &UndoEntry) -> anyhow::Result<bool> {
+    let Some(expected) = entry.hash.as_deref() else {
+        return Ok(false);
+    };
+    let (current, scheme) = (Path::new(&entry.current_path), entry.hash_scheme.as_deref());
+    Ok(!matches_recorded(current, expected, scheme)?)
+}
+
+/// Checks that undoing `entry` won't clobber newer work. `force` overrides the
+/// hash check only, including a file that couldn't be hashed; expired or
+/// missing entries are always refused.
+fn verify_undo(
+    entry: &UndoEntry,
+    now: DateTime<Utc>,
+    retention_days: u32,
+    force: bool,
+) -> Result<(), UndoError> {
+    if is_expired(entry, now, retention_days) {
+        return Err(UndoError::new(
+            UndoErrorKind::Expired,
+            format!("Undo window of {retention_days} days has passed"),
+        ));
+    }
+    if !Path::new(&entry.current_path).exists() {
+        return Err(UndoError::new(
+            UndoErrorKind::Missing,
+            "File no longer exists at current path",
+        ));
+    }
+    if force {
+        return Ok(());
+    }
+    match file_changed(entry) {
+        Ok(false) => Ok(()),
+        Ok(true) => Err(UndoError::new(
+            UndoErrorKind::FileChanged,
+            "File changed since the action ran",
+        )),
+        Err(err) => Err(UndoError::new(
+            UndoErrorKind::FileChanged,
+            format!("Couldn't check whether the file changed: {err}"),
+        )),
+    }
+}
+
+fn apply_undo(
+    entry: &UndoEntry,
+    on_conflict: Option<ConflictResolution>,
+) -> Result<(), UndoError> {
     let current = PathBuf::from(&entry.current_path);
-    let original = PathBuf::from(&entry.original_path);
+    let mut original = PathBuf::from(&entry.original_path);
 
     if !current.exists() {
-        return Err("File no longer exists at current path".to_string());
+        return Err(UndoError::new(
+            UndoErrorKind::Missing,
+            "File no longer exists at current path",
+        ));
     }
 
     match entry.action_type.as_str() {
         "move" | "rename" => {
             if original.exists() {
-                return Err("Original path already exists".to_string());
+                match on_conflict {
+                    Some(ConflictResolution::Rename) => original = unique_path(&original),
+                    Some(ConflictResolution::UniqueSuffix) => {
+                        original = unique_name::next(&original).0
+                    }
+                    Some(ConflictResolution::Replace) => remove_path(&original)?,
+                    Some(ConflictResolution::Skip | ConflictResolution::Ask) | None => {
+                        return Err(UndoError::new(
+                            UndoErrorKind::OriginalExists,
+                            "Original path already exists",
+                        ));
+                    }
+                }
             }
             if let Some(parent) = original.parent() {
-                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-            }
-            std::fs::rename(&current, &original).map_err(|e| e.to_string())?;
-        }
-        "copy" => {
-            if current.is_dir() {
-                std::fs::remove_dir_all(&current).map_err(|e| e.to_string())?;
-            } else {
-                std::fs::remove_file(&current).map_err(|e| e.to_string())?;
+                std::fs::create_dir_all(parent).map_err(|e| UndoError::failed(e.to_string()))?;
             }
+            std::fs::rename(&current, &original).map_err(|e| UndoError::failed(e.to_string()))?;
         }
+        "copy" => remove_path(&current)?,
         _ => {
-            return Err("Action is not undoable".to_string());
+            return Err(UndoError::failed("Action is not undoable"));
         }
     }
 
     Ok(())
 }
 
+fn remove_path(path: &Path) -> Result<(), UndoError> {
+    let result = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    result.map_err(|e| UndoError::failed(e.to_string()))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::apply_undo;
-    use crate::models::UndoEntry;
+    use super::{annotate_status, apply_undo, verify_undo};
+    use crate::core::duplicates::hash_file;
+    use crate::core::executor::{transfer_companions, transfer_file, CompanionPlan, Transfer};
+    use crate::models::{
+        ActionType, BringCompanions, ConflictResolution, DestinationPermissions, QuarantineHandling,
+        UndoEntry, UndoErrorKind, UndoStatus,
+    };
+    use chrono::{Duration, Utc};
+    use std::path::Path;
     use tempfile::tempdir;
 
+    fn undo_entry(action_type: &str, original: &Path, current: &Path) -> UndoEntry {
+        UndoEntry {
+            id: "undo-1".to_string(),
+            log_id: "log-1".to_string(),
+            action_type: action_type.to_string(),
+            original_path: original.to_string_lossy().to_string(),
+            current_path: current.to_string_lossy().to_string(),
+            created_at: Utc::now(),
+            hash: hash_file(current).ok(),
+            hash_scheme: None,
+            classification: None,
+            status: UndoStatus::Available,
+            status_reason: None,
+        }
+    }
+
     #[test]
     fn undo_move_restores_original() {
         let dir = tempdir().unwrap();
@@ -109,20 +288,62 @@ mod tests {
         std::fs::write(&original, b"test").unwrap();
         std::fs::rename(&original, &moved).unwrap();
 
-        let entry = UndoEntry {
-            id: "undo-1".to_string(),
-            log_id: "log-1".to_string(),
-            action_type: "move".to_string(),
-            original_path: original.to_string_lossy().to_string(),
-            current_path: moved.to_string_lossy().to_string(),
-            created_at: chrono::Utc::now(),
-        };
+        let entry = undo_entry("move", &original, &moved);
 
-        apply_undo(&entry).unwrap();
+        apply_undo(&entry, None).unwrap();
         assert!(original.exists());
         assert!(!moved.exists());
     }
 
+    #[test]
+    fn undo_restores_a_file_and_its_companions() {
+        let dir = tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        let archive = dir.path().join("archive");
+        std::fs::create_dir_all(&inbox).unwrap();
+        for name in ["IMG_1.CR2", "IMG_1.xmp", "IMG_1.jpg"] {
+            std::fs::write(inbox.join(name), name).unwrap();
+        }
+        let spec = BringCompanions {
+            extensions: vec!["xmp".to_string(), "jpg".to_string()],
+            pattern: None,
+            missing_ok: false,
+            fail_on_error: false,
+            record_matches: true,
+        };
+        let mut primary = transfer_file(
+            ActionType::Move,
+            Transfer::Move,
+            &inbox.join("IMG_1.CR2"),
+            archive.join("IMG_1.CR2"),
+            ConflictResolution::Rename,
+            false,
+        );
+        let plan = CompanionPlan {
+            spec: &spec,
+            pattern: None,
+            transfer: Transfer::Move,
+            conflict: &ConflictResolution::Rename,
+            skip_duplicates: false,
+            perms: &DestinationPermissions::Keep,
+            quarantine: QuarantineHandling::Keep,
+        };
+        let companions = transfer_companions(&mut primary, &plan);
+        assert_eq!(companions.len(), 2);
+
+        // Each outcome gets its own undo entry, as the log would make.
+        for outcome in std::iter::once(&primary).chain(&companions) {
+            let details = outcome.details.as_ref().unwrap();
+            let current = details.destination_path.as_deref().unwrap();
+            let entry = undo_entry("move", Path::new(&details.source_path), Path::new(current));
+            apply_undo(&entry, None).unwrap();
+        }
+        for name in ["IMG_1.CR2", "IMG_1.xmp", "IMG_1.jpg"] {
+            assert_eq!(std::fs::read_to_string(inbox.join(name)).unwrap(), name);
+            assert!(!archive.join(name).exists());
+        }
+    }
+
     #[test]
     fn undo_copy_removes_copy() {
         let dir = tempdir().unwrap();
@@ -131,17 +352,120 @@ mod tests {
         std::fs::write(&original, b"test").unwrap();
         std::fs::copy(&original, &copy).unwrap();
 
-        let entry = UndoEntry {
-            id: "undo-2".to_string(),
-            log_id: "log-2".to_string(),
-            action_type: "copy".to_string(),
-            original_path: original.to_string_lossy().to_string(),
-            current_path: copy.to_string_lossy().to_string(),
-            created_at: chrono::Utc::now(),
-        };
+        let entry = undo_entry("copy", &original, &copy);
 
-        apply_undo(&entry).unwrap();
+        apply_undo(&entry, None).unwrap();
         assert!(original.exists());
         assert!(!copy.exists());
     }
+
+    #[test]
+    fn undo_refused_after_file_modified() {
+        let dir = tempdir().unwrap();
+        let original = dir.path().join("notes.txt");
+        let moved = dir.path().join("archive-notes.txt");
+        std::fs::write(&moved, b"first draft").unwrap();
+        let entry = undo_entry("move", &original, &moved);
+
+        std::fs::write(&moved, b"newer work").unwrap();
+
+        let err = verify_undo(&entry, Utc::now(), 7, false).unwrap_err();
+        assert_eq!(err.kind, UndoErrorKind::FileChanged);
+        let listed = annotate_status(entry, Utc::now(), 7);
+        assert_eq!(listed.status, UndoStatus::Changed);
+        assert!(listed.status_reason.is_some());
+        assert!(moved.exists());
+    }
+
+    #[test]
+    fn forced_undo_proceeds_after_file_modified() {
+        let dir = tempdir().unwrap();
+        let original = dir.path().join("notes.txt");
+        let moved = dir.path().join("archive-notes.txt");
+        std::fs::write(&moved, b"first draft").unwrap();
+        let entry = undo_entry("move", &original, &moved);
+
+        std::fs::write(&moved, b"newer work").unwrap();
+
+        verify_undo(&entry, Utc::now(), 7, true).unwrap();
+        apply_undo(&entry, None).unwrap();
+        assert_eq!(std::fs::read(&original).unwrap(), b"newer work");
+    }
+
+    #[test]
+    fn expired_entries_are_marked_and_refused() {
+        let dir = tempdir().unwrap();
+        let original = dir.path().join("old.txt");
+        let moved = dir.path().join("moved-old.txt");
+        std::fs::write(&moved, b"data").unwrap();
+        let mut entry = undo_entry("move", &original, &moved);
+        entry.created_at = Utc::now() - Duration::days(10);
+
+        let err = verify_undo(&entry, Utc::now(), 7, true).unwrap_err();
+        assert_eq!(err.kind, UndoErrorKind::Expired);
+        assert!(verify_undo(&entry, Utc::now(), 0, false).is_ok());
+
+        let listed = annotate_status(entry.clone(), Utc::now(), 7);
+        assert_eq!(listed.status, UndoStatus::Expired);
+        let listed = annotate_status(entry, Utc::now(), 30);
+        assert_eq!(listed.status, UndoStatus::Available);
+    }
+
+    #[test]
+    fn legacy_entry_without_hash_is_allowed() {
+        let dir = tempdir().unwrap();
+        let original = dir.path().join("legacy.txt");
+        let moved = dir.path().join("moved-legacy.txt");
+        std::fs::write(&moved, b"data").unwrap();
+        let mut entry = undo_entry("move", &original, &moved);
+        entry.hash = None;
+        std::fs::write(&moved, b"edited").unwrap();
+
+        verify_undo(&entry, Utc::now(), 7, false).unwrap();
+        let listed = annotate_status(entry, Utc::now(), 7);
+        assert_eq!(listed.status, UndoStatus::Available);
+        assert!(listed.status_reason.unwrap().contains("won't be noticed"));
+    }
+
+    #[test]
+    fn files_that_cant_be_checked_need_force() {
+        let dir = tempdir().unwrap();
+        let original = dir.path().join("notes.txt");
+        let moved = dir.path().join("archive-notes.txt");
+        std::fs::write(&moved, b"first draft").unwrap();
+        let mut entry = undo_entry("move", &original, &moved);
+        entry.hash_scheme = Some("from a newer version".to_string());
+
+        let err = verify_undo(&entry, Utc::now(), 7, false).unwrap_err();
+        assert_eq!(err.kind, UndoErrorKind::FileChanged);
+        let listed = annotate_status(entry.clone(), Utc::now(), 7);
+        assert_eq!(listed.status, UndoStatus::Changed);
+        verify_undo(&entry, Utc::now(), 7, true).unwrap();
+    }
+
+    #[test]
+    fn occupied_original_follows_conflict_resolution() {
+        let dir = tempdir().unwrap();
+        let original = dir.path().join("report.txt");
+        let moved = dir.path().join("sorted-report.txt");
+        std::fs::write(&moved, b"moved").unwrap();
+        std::fs::write(&original, b"newcomer").unwrap();
+        let entry = undo_entry("move", &original, &moved);
+
+        let err = apply_undo(&entry, None).unwrap_err();
+        assert_eq!(err.kind, UndoErrorKind::OriginalExists);
+        let err = apply_undo(&entry, Some(ConflictResolution::Skip)).unwrap_err();
+        assert_eq!(err.kind, UndoErrorKind::OriginalExists);
+
+        apply_undo(&entry, Some(ConflictResolution::Rename)).unwrap();
+        assert_eq!(std::fs::read(&original).unwrap(), b"newcomer");
+        assert_eq!(
+            std::fs::read(dir.path().join("report (1).txt")).unwrap(),
+            b"moved"
+        );
+
+        std::fs::write(&moved, b"again").unwrap();
+        apply_undo(&entry, Some(ConflictResolution::Replace)).unwrap();
+        assert_eq!(std::fs::read(&original).unwrap(), b"again");
+    }
 }
@@ -0,0 +1,619 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use crate::models::{
+    Action, ComparisonOperator, Condition, ConditionGroup, DateOperator, FindingKind, MatchType,
+    Rule, RuleAnalysis, RuleFinding, StringCondition, StringOperator,
+};
+
+/// Statically reasons over a folder's rules (in execution order) and reports
+/// shadowed, overlapping and unsatisfiable rules. Disabled rules are ignored.
+pub fn analyze_rules(folder_id: &str, rules: &[Rule]) -> RuleAnalysis {
+    let active: Vec<&Rule> = rules.iter().filter(|rule| rule.enabled).collect();
+    let mut findings = Vec::new();
+
+    let unsatisfiable: Vec<Option<String>> = active
+        .iter()
+        .map(|rule| unsatisfiable_reason(&rule.conditions))
+        .collect();
+    for (rule, reason) in active.iter().zip(&unsatisfiable) {
+        if let Some(reason) = reason {
+            findings.push(finding(FindingKind::Unsatisfiable, rule, None, reason.clone()));
+        }
+    }
+
+    for (j, later) in active.iter().enumerate() {
+        if unsatisfiable[j].is_some() {
+            continue;
+        }
+        let shadowed_by = active[..j].iter().enumerate().find(|(i, earlier)| {
+            unsatisfiable[*i].is_none()
+                && always_stops(earlier)
+                && scope_covers(earlier, later)
+                && covers(&earlier.conditions, &later.conditions)
+        });
+        if let Some((_, earlier)) = shadowed_by {
+            findings.push(finding(
+                FindingKind::Shadowed,
+                later,
+                Some(earlier),
+                format!(
+                    "Never runs: earlier rule '{}' stops processing and matches every file this rule would match",
+                    earlier.name
+                ),
+            ));
+            continue;
+        }
+
+        for (i, earlier) in active[..j].iter().enumerate() {
+            if unsatisfiable[i].is_some() {
+                continue;
+            }
+            if let Some(message) = overlap_message(earlier, later) {
+                findings.push(finding(FindingKind::Overlap, later, Some(earlier), message));
+            }
+        }
+    }
+
+    RuleAnalysis {
+        folder_id: folder_id.to_string(),
+        findings,
+        sampled_files: 0,
+    }
+}
+
+/// Pairs of (earlier, later) rule indices where the earlier rule stops processing
+/// but static reasoning could not decide whether it shadows the later one.
+pub fn inconclusive_pairs(rules: &[Rule]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for (j, later) in rules.iter().enumerate() {
+        if !later.enabled || unsatisfiable_reason(&later.conditions).is_some() {
+            continue;
+        }
+        let candidates: Vec<usize> = (0..j)
+            .filter(|&i| {
+                let earlier = &rules[i];
+                earlier.enabled
+                    && always_stops(earlier)
+                    && scope_covers(earlier, later)
+                    && unsatisfiable_reason(&earlier.conditions).is_none()
+            })
+            .collect();
+        if candidates
+            .iter()
+            .any(|&i| covers(&rules[i].conditions, &later.conditions))
+        {
+            continue;
+        }
+        pairs.extend(candidates.into_iter().map(|i| (i, j)));
+    }
+    pairs
+}
+
+/// Replays inconclusive pairs against sample files. A later rule is reported when
+/// every sample it matched was also matched by the earlier, stopping rule.
+pub fn empirical_shadowing<T>(
+    rules: &[Rule],
+    pairs: &[(usize, usize)],
+    samples: &[T],
+    mut evaluate: impl FnMut(&Rule, &T) -> bool,
+) -> Vec<RuleFinding> {
+    let mut findings = Vec::new();
+    let mut reported = BTreeSet::new();
+    for &(i, j) in pairs {
+        if reported.contains(&j) {
+            continue;
+        }
+        let (earlier, later) = (&rules[i], &rules[j]);
+        let mut later_matches = 0;
+        let mut both = 0;
+        for sample in samples {
+            if evaluate(later, sample) {
+                later_matches += 1;
+                if evaluate(earlier, sample) {
+                    both += 1;
+                }
+            }
+        }
+        if later_matches > 0 && both == later_matches {
+            reported.insert(j);
+            findings.push(finding(
+                FindingKind::LikelyShadowed,
+                later,
+                Some(earlier),
+                format!(
+                    "All {} recent file(s) this rule matched were already captured by earlier rule '{}', which stops processing",
+                    later_matches, earlier.name
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+fn finding(kind: FindingKind, rule: &Rule, related: Option<&Rule>, message: String) -> RuleFinding {
+    RuleFinding {
+        kind,
+        rule_id: rule.id.clone(),
+        rule_name: rule.name.clone(),
+        related_rule_id: related.map(|rule| rule.id.clone()),
+        message,
+    }
+}
+
+fn always_stops(rule: &Rule) -> bool {
+    rule.stop_processing
+        && !rule
+            .actions
+            .iter()
+            .any(|action| matches!(action, Action::Continue))
+}
+
+/// A rule limited to part of the folder only holds back rules limited to the
+/// same part.
+fn scope_covers(earlier: &Rule, later: &Rule) -> bool {
+    earlier.path_scope.is_none() || earlier.path_scope == later.path_scope
+}
+
+/// True when every file matched by `later` is guaranteed to match `earlier`.
+fn covers(earlier: &ConditionGroup, later: &ConditionGroup) -> bool {
+    let earlier_keys: Vec<Value> = earlier.conditions.iter().map(condition_key).collect();
+    let alternatives = match later.match_type {
+        MatchType::All => vec![flatten_all(later)],
+        MatchType::Any => later
+            .conditions
+            .iter()
+            .map(|condition| match condition {
+                Condition::Nested(group) if matches!(group.match_type, MatchType::All) => {
+                    flatten_all(group)
+                }
+                other => vec![condition_key(other)],
+            })
+            .collect(),
+        MatchType::None => return false,
+    };
+
+    match earlier.match_type {
+        MatchType::All => alternatives
+            .iter()
+            .all(|alt| earlier_keys.iter().all(|key| alt.contains(key))),
+        MatchType::Any => alternatives
+            .iter()
+            .all(|alt| earlier_keys.iter().any(|key| alt.contains(key))),
+        MatchType::None => earlier.conditions.is_empty(),
+    }
+}
+
+/// Conjunctive conditions of an All-group, with nested All-groups inlined.
+fn flatten_all(group: &ConditionGroup) -> Vec<Value> {
+    let mut keys = Vec::new();
+    for condition in &group.conditions {
+        match condition {
+            Condition::Nested(nested) if matches!(nested.match_type, MatchType::All) => {
+                keys.extend(flatten_all(nested));
+            }
+            other => keys.push(condition_key(other)),
+        }
+    }
+    keys
+}
+
+/// Structural identity for a condition; case-insensitive values compare lowercased.
+fn condition_key(condition: &Condition) -> Value {
+    let mut value = serde_json::to_value(condition).unwrap_or(Value::Null);
+    if let Value::Object(map) = &mut value {
+        if map.get("caseSensitive") == Some(&Value::Bool(false)) {
+            if let Some(Value::String(text)) = map.get_mut("value") {
+                *text = text.to_lowercase();
+            }
+        }
+    }
+    value
+}
+
+fn unsatisfiable_reason(group: &ConditionGroup) -> Option<String> {
+    match group.match_type {
+        MatchType::Any if group.conditions.is_empty() => {
+            return Some("An empty \"any\" group never matches".to_string());
+        }
+        MatchType::Any => {
+            let all_impossible = group.conditions.iter().all(|condition| match condition {
+                Condition::Nested(nested) => unsatisfiable_reason(nested).is_some(),
+                other => condition_unsatisfiable(other).is_some(),
+            });
+            if all_impossible {
+                return Some("None of the \"any\" conditions can ever match".to_string());
+            }
+            return None;
+        }
+        MatchType::None => return None,
+        MatchType::All => {}
+    }
+
+    for condition in &group.conditions {
+        let reason = match condition {
+            Condition::Nested(nested) => unsatisfiable_reason(nested),
+            other => condition_unsatisfiable(other),
+        };
+        if reason.is_some() {
+            return reason;
+        }
+    }
+
+    let mut literals: Vec<(&'static str, &StringCondition)> = Vec::new();
+    let mut kinds = Vec::new();
+    collect_all_conjuncts(group, &mut literals, &mut kinds);
+
+    for (idx, (field, a)) in literals.iter().enumerate() {
+        for (other_field, b) in &literals[idx + 1..] {
+            if field != other_field || a.case_sensitive != b.case_sensitive {
+                continue;
+            }
+            let same = normalized(a) == normalized(b);
+            match (&a.operator, &b.operator) {
+                (StringOperator::Is, StringOperator::IsNot)
+                | (StringOperator::IsNot, StringOperator::Is)
+                    if same =>
+                {
+                    return Some(format!(
+                        "{} is required to be and not be \"{}\"",
+                        field, a.value
+                    ));
+                }
+                (StringOperator::Is, StringOperator::Is) if !same => {
+                    return Some(format!(
+                        "{} cannot be both \"{}\" and \"{}\"",
+                        field, a.value, b.value
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for (idx, (kind_a, negate_a)) in kinds.iter().enumerate() {
+        for (kind_b, negate_b) in &kinds[idx + 1..] {
+            let contradictory = match (negate_a, negate_b) {
+                (false, false) => kind_a != kind_b,
+                (false, true) | (true, false) => kind_a == kind_b,
+                (true, true) => false,
+            };
+            if contradictory {
+                return Some(format!(
+                    "Kind conditions contradict each other ({:?} / {:?})",
+                    kind_a, kind_b
+                ));
+            }
+        }
+    }
+
+    None
+}
+
+fn collect_all_conjuncts<'a>(
+    group: &'a ConditionGroup,
+    literals: &mut Vec<(&'static str, &'a StringCondition)>,
+    kinds: &mut Vec<(crate::models::FileKind, bool)>,
+) {
+    for condition in &group.conditions {
+        match condition {
+            Condition::Name(cond) => literals.push(("Name", cond)),
+            Condition::Extension(cond) => literals.push(("Extension", cond)),
+            Condition::FullName(cond) => literals.push(("Full name", cond)),
+            Condition::Kind(cond) => kinds.push((cond.kind.clone(), cond.negate)),
+            Condition::Nested(nested) if matches!(nested.match_type, MatchType::All) => {
+                collect_all_conjuncts(nested, literals, kinds)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn condition_unsatisfiable(condition: &Condition) -> Option<String> {
+    match condition {
+        Condition::Size(cond) => match cond.operator {
+            ComparisonOperator::Between { min, max } if min > max => Some(format!(
+                "Size range is empty (minimum {} is greater than maximum {})",
+                min, max
+            )),
+            _ => None,
+        },
+        Condition::DateCreated(cond)
+        | Condition::DateModified(cond)
+        | Condition::DateAdded(cond)
+        | Condition::DateLastMatched(cond) => match &cond.operator {
+            DateOperator::Between { start, end } if start > end => Some(format!(
+                "Date range is empty ({} is after {})",
+                start, end
+            )),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn normalized(cond: &StringCondition) -> String {
+    if cond.case_sensitive {
+        cond.value.clone()
+    } else {
+        cond.value.to_lowercase()
+    }
+}
+
+fn overlap_message(earlier: &Rule, later: &Rule) -> Option<String> {
+    let mut shared = Vec::new();
+    for (field, earlier_values) in literal_sets(&earlier.conditions) {
+        for (other_field, later_values) in literal_sets(&later.conditions) {
+            if field == other_field {
+                shared.extend(
+                    earlier_values
+                        .intersection(&later_values)
+                        .map(|value| format!("{} \"{}\"", field, value)),
+                );
+            }
+        }
+    }
+    if shared.is_empty() {
+        return None;
+    }
+    shared.sort();
+    shared.dedup();
+    let suffix = if always_stops(earlier) {
+        ", which stops processing, so those files never reach this rule"
+    } else {
+        ""
+    };
+    Some(format!(
+        "Overlaps with earlier rule '{}' on {}{}",
+        earlier.name,
+        shared.join(", "),
+        suffix
+    ))
+}
+
+/// Exact name/extension literals (`Is`) a rule's top-level group accepts.
+fn literal_sets(group: &ConditionGroup) -> Vec<(&'static str, BTreeSet<String>)> {
+    if matches!(group.match_type, MatchType::None) {
+        return Vec::new();
+    }
+    let mut names = BTreeSet::new();
+    let mut extensions = BTreeSet::new();
+    for condition in &group.conditions {
+        match condition {
+            Condition::Name(cond) if cond.operator == StringOperator::Is => {
+                names.insert(normalized(cond));
+            }
+            Condition::Extension(cond) if cond.operator == StringOperator::Is => {
+                extensions.insert(normalized(cond).trim_start_matches('.').to_string());
+            }
+            _ => {}
+        }
+    }
+    vec![("name", names), ("extension", extensions)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        ConditionGroup, DateCondition, FileKind, KindCondition, SizeCondition, SizeUnit,
+    };
+    use chrono::{NaiveDate, Utc};
+    use std::collections::HashMap;
+
+    fn ext(value: &str) -> Condition {
+        Condition::Extension(StringCondition {
+            operator: StringOperator::Is,
+            value: value.to_string(),
+            case_sensitive: false,
+        })
+    }
+
+    fn name(operator: StringOperator, value: &str) -> Condition {
+        Condition::Name(StringCondition {
+            operator,
+            value: value.to_string(),
+            case_sensitive: false,
+        })
+    }
+
+    fn rule(id: &str, match_type: MatchType, conditions: Vec<Condition>, stop: bool) -> Rule {
+        Rule {
+            id: id.to_string(),
+            folder_id: "folder".to_string(),
+            name: id.to_string(),
+            enabled: true,
+            stop_processing: stop,
+            conditions: ConditionGroup {
+                label: None,
+                match_type,
+                conditions,
+            },
+            actions: vec![],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn kinds(analysis: &RuleAnalysis, rule_id: &str) -> Vec<FindingKind> {
+        analysis
+            .findings
+            .iter()
+            .filter(|f| f.rule_id == rule_id)
+            .map(|f| f.kind.clone())
+            .collect()
+    }
+
+    #[test]
+    fn detects_shadowed_rule_with_superset_conditions() {
+        let rules = vec![
+            rule("pdfs", MatchType::All, vec![ext("pdf")], true),
+            rule(
+                "invoices",
+                MatchType::All,
+                vec![ext("PDF"), name(StringOperator::Contains, "invoice")],
+                true,
+            ),
+        ];
+        let analysis = analyze_rules("folder", &rules);
+        assert_eq!(kinds(&analysis, "invoices"), vec![FindingKind::Shadowed]);
+        let finding = &analysis.findings[0];
+        assert_eq!(finding.related_rule_id.as_deref(), Some("pdfs"));
+        assert!(finding.message.contains("pdfs"));
+    }
+
+    #[test]
+    fn any_group_and_catch_all_shadow_later_rules() {
+        let rules = vec![
+            rule("docs", MatchType::Any, vec![ext("pdf"), ext("docx")], true),
+            rule("word", MatchType::Any, vec![ext("docx")], true),
+        ];
+        assert_eq!(kinds(&analyze_rules("folder", &rules), "word"), vec![FindingKind::Shadowed]);
+
+        let rules = vec![
+            rule("everything", MatchType::All, vec![], true),
+            rule("images", MatchType::All, vec![ext("png")], false),
+        ];
+        assert_eq!(kinds(&analyze_rules("folder", &rules), "images"), vec![FindingKind::Shadowed]);
+    }
+
+    #[test]
+    fn does_not_flag_more_general_later_rule() {
+        // Guard: the later rule is broader than the earlier one, so it still fires.
+        let rules = vec![
+            rule(
+                "invoices",
+                MatchType::All,
+                vec![ext("pdf"), name(StringOperator::Contains, "invoice")],
+                true,
+            ),
+            rule("pdfs", MatchType::All, vec![ext("pdf")], true),
+        ];
+        let analysis = analyze_rules("folder", &rules);
+        assert!(!kinds(&analysis, "pdfs").contains(&FindingKind::Shadowed));
+        assert_eq!(kinds(&analysis, "pdfs"), vec![FindingKind::Overlap]);
+    }
+
+    #[test]
+    fn continue_action_or_no_stop_prevents_shadowing() {
+        let mut first = rule("pdfs", MatchType::All, vec![ext("pdf")], true);
+        first.actions = vec![Action::Continue];
+        let rules = vec![first, rule("pdfs-again", MatchType::All, vec![ext("pdf")], true)];
+        let analysis = analyze_rules("folder", &rules);
+        assert!(!kinds(&analysis, "pdfs-again").contains(&FindingKind::Shadowed));
+
+        let rules = vec![
+            rule("pdfs", MatchType::All, vec![ext("pdf")], false),
+            rule("pdfs-again", MatchType::All, vec![ext("pdf")], true),
+        ];
+        let analysis = analyze_rules("folder", &rules);
+        assert!(!kinds(&analysis, "pdfs-again").contains(&FindingKind::Shadowed));
+    }
+
+    #[test]
+    fn reports_overlapping_extension_literals() {
+        let rules = vec![
+            rule("images", MatchType::Any, vec![ext("png"), ext("jpg")], false),
+            rule("photos", MatchType::Any, vec![ext(".JPG"), ext("heic")], false),
+            rule("text", MatchType::All, vec![ext("txt")], false),
+        ];
+        let analysis = analyze_rules("folder", &rules);
+        assert_eq!(kinds(&analysis, "photos"), vec![FindingKind::Overlap]);
+        assert!(analysis.findings[0].message.contains("extension \"jpg\""));
+        assert!(kinds(&analysis, "text").is_empty());
+    }
+
+    #[test]
+    fn detects_unsatisfiable_conditions() {
+        let size = Condition::Size(SizeCondition {
+            operator: ComparisonOperator::Between { min: 10, max: 1 },
+            value: None,
+            unit: SizeUnit::Megabytes,
+        });
+        let dates = Condition::DateModified(DateCondition {
+            operator: DateOperator::Between {
+                start: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+                end: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            },
+            timezone: None,
+        });
+        let kind = |kind: FileKind, negate: bool| Condition::Kind(KindCondition { kind, negate });
+        let rules = vec![
+            rule("size", MatchType::All, vec![size], false),
+            rule("dates", MatchType::All, vec![dates], false),
+            rule(
+                "names",
+                MatchType::All,
+                vec![name(StringOperator::Is, "report"), name(StringOperator::IsNot, "Report")],
+                false,
+            ),
+            rule(
+                "two-names",
+                MatchType::All,
+                vec![name(StringOperator::Is, "a"), name(StringOperator::Is, "b")],
+                false,
+            ),
+            rule(
+                "kinds",
+                MatchType::All,
+                vec![kind(FileKind::Image, false), kind(FileKind::Image, true)],
+                false,
+            ),
+            rule("empty-any", MatchType::Any, vec![], true),
+        ];
+        let analysis = analyze_rules("folder", &rules);
+        for id in ["size", "dates", "names", "two-names", "kinds", "empty-any"] {
+            assert_eq!(kinds(&analysis, id), vec![FindingKind::Unsatisfiable], "{id}");
+        }
+    }
+
+    #[test]
+    fn is_and_is_not_in_any_group_is_satisfiable() {
+        // Guard: Is/IsNot only contradict inside an All-group.
+        let rules = vec![rule(
+            "either",
+            MatchType::Any,
+            vec![name(StringOperator::Is, "a"), name(StringOperator::IsNot, "a")],
+            false,
+        )];
+        assert!(analyze_rules("folder", &rules).findings.is_empty());
+    }
+
+    #[test]
+    fn empirical_pass_flags_rules_only_matched_after_earlier_rule() {
+        let rules = vec![
+            rule("big", MatchType::All, vec![name(StringOperator::Contains, "big")], true),
+            rule("raw", MatchType::All, vec![ext("raw")], true),
+            rule("txt", MatchType::All, vec![ext("txt")], true),
+        ];
+        let pairs = inconclusive_pairs(&rules);
+        assert!(pairs.contains(&(0, 1)));
+
+        let samples = ["big-1.raw", "big-2.raw", "notes.txt"];
+        let findings = empirical_shadowing(&rules, &pairs, &samples, |rule, sample| match rule
+            .id
+            .as_str()
+        {
+            "big" => sample.contains("big"),
+            "raw" => sample.ends_with(".raw"),
+            "txt" => sample.ends_with(".txt"),
+            _ => false,
+        });
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule_id, "raw");
+        assert_eq!(findings[0].kind, FindingKind::LikelyShadowed);
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use filedispatch_core::file_info::FileInfo;
+
+use crate::core::budget::record_transfers;
+use crate::core::counters::{number_run, settle_counter};
+use crate::core::dispatch::ExecuteFn;
+use crate::core::duplicates::DuplicateDetector;
+use crate::core::engine::{
+    evaluate_conditions, log_outcomes, record_companion_matches, write_processed_markers,
+    EvaluationOptions,
+};
+use crate::core::executor::{ActionOutcome, ActionResultStatus, ActionScope};
+use crate::core::match_metadata;
+use crate::core::ocr::OcrManager;
+use crate::models::{
+    ApprovalError, ApprovalErrorKind, PendingApproval, PlannedAction, Rule, Settings,
+};
+use crate::storage::approval_repo::ApprovalRepository;
+use crate::storage::database::Database;
+use crate::storage::rule_repo::RuleRepository;
+use crate::storage::write_set::WriteSet;
+
+/// Queues a match of an approval-required rule along with the dry-run plan
+/// `plan` resolves for it.
+pub fn propose(
+    db: &Database,
+    rule: &Rule,
+    info: &FileInfo,
+    captures: &HashMap<String, String>,
+    plan: &ExecuteFn,
+) -> Result<PendingApproval> {
+    let scope = ActionScope::new(&rule.id, info);
+    let planned = planned_actions(&plan(&rule.actions, info, captures, &scope));
+    let mut approval = ApprovalRepository::new(db.clone()).upsert(PendingApproval {
+        id: String::new(),
+        rule_id: rule.id.clone(),
+        rule_name: String::new(),
+        file_path: info.path.to_string_lossy().to_string(),
+        file_hash: info.hash.clone(),
+        captures: captures.clone(),
+        planned,
+        created_at: Utc::now(),
+    })?;
+    approval.rule_name = rule.name.clone();
+    Ok(approval)
+}
+
+fn planned_actions(outcomes: &[ActionOutcome]) -> Vec<PlannedAction> {
+    outcomes
+        .iter()
+        .map(|outcome| PlannedAction {
+            action_type: outcome.action_type.clone(),
+            source_path: outcome
+                .details
+                .as_ref()
+                .map(|details| details.source_path.clone())
+                .unwrap_or_default(),
+            destination_path: outcome
+                .details
+                .as_ref()
+                .and_then(|details| details.destination_path.clone()),
+        })
+        .collect()
+}
+
+pub fn is_expired(approval: &PendingApproval, now: DateTime<Utc>, expiry_hours: u32) -> bool {
+    expiry_hours > 0 && now - approval.created_at > Duration::hours(expiry_hours as i64)
+}
+
+/// What `execute_approval` needs to re-check and run a stored plan.
+pub struct ApprovalRunner<'a> {
+    pub db: &'a Database,
+    pub settings: &'a Settings,
+    pub ocr: &'a Mutex<OcrManager>,
+    pub duplicates: &'a Arc<DuplicateDetector>,
+    pub plan: &'a ExecuteFn<'a>,
+    pub execute: &'a ExecuteFn<'a>,
+}
+
+impl ApprovalRunner<'_> {
+    /// Runs the approved plan if the file is still where it matched and
+    /// unchanged. A changed file is re-evaluated: if the rule still matches,
+    /// the plan is refreshed and must be approved again; otherwise the
+    /// approval is dropped.
+    pub fn execute(&self, id: &str, now: DateTime<Utc>) -> Result<(), ApprovalError> {
+        let approvals = ApprovalRepository::new(self.db.clone());
+        let approval = approvals
+            .get(id)
+            .map_err(|e| ApprovalError::failed(e.to_string()))?
+            .ok_or_else(|| ApprovalError::new(ApprovalErrorKind::NotFound, "Approval not found"))?;
+        let drop_with = |kind: ApprovalErrorKind, message: String| {
+            let _ = approvals.delete(&approval.id);
+            ApprovalError::new(kind, message)
+        };
+
+        if is_expired(&approval, now, self.settings.approval_expiry_hours) {
+            return Err(drop_with(
+                ApprovalErrorKind::Expired,
+                format!(
+                    "Approvals expire after {} hours",
+                    self.settings.approval_expiry_hours
+                ),
+            ));
+        }
+        let rule = RuleRepository::new(self.db.clone())
+            .get(&approval.rule_id)
+            .map_err(|e| ApprovalError::failed(e.to_string()))?
+            .ok_or_else(|| {
+                drop_with(ApprovalErrorKind::NotFound, "Rule no longer exists".to_string())
+            })?;
+        let info = FileInfo::from_path(std::path::Path::new(&approval.file_path)).map_err(|_| {
+            drop_with(
+                ApprovalErrorKind::Missing,
+                "File no longer exists where it matched".to_string(),
+            )
+        })?;
+
+        if info.hash != approval.file_hash {
+            let evaluation = {
+                let mut ocr = self.ocr.lock().unwrap();
+                evaluate_conditions(
+                    &rule,
+                    &info,
+                    self.settings,
+                    &mut ocr,
+                    &EvaluationOptions::finding_duplicates(self.duplicates, &rule.folder_id),
+                )
+                .map_err(|e| ApprovalError::failed(e.to_string()))?
+            };
+            if !evaluation.matched {
+                return Err(drop_with(
+                    ApprovalErrorKind::NoLongerMatches,
+                    "File changed and no longer matches the rule".to_string(),
+                ));
+            }
+            propose(self.db, &rule, &info, &evaluation.captures, self.plan)
+                .map_err(|e| ApprovalError::failed(e.to_string()))?;
+            return Err(ApprovalError::new(
+                ApprovalErrorKind::Replanned,
+                "File changed since it matched; review the updated plan",
+            ));
+        }
+
+        // The number is taken when the approval runs, not when it was queued.
+        let mut captures = approval.captures.clone();
+        number_run(self.db, &rule, &mut captures)
+            .map_err(|e| ApprovalError::failed(e.to_string()))?;
+        let scope = ActionScope::new(&rule.id, &info);
+        let outcomes = (self.execute)(&rule.actions, &info, &captures, &scope);
+        let recorded = match_metadata::resolve(&rule, &info, &captures, self.settings);
+        let mut writes = WriteSet::default();
+        log_outcomes(&mut writes, &rule, &info, &outcomes, &recorded, None);
+        // Later events for this content skip the rule, and its
+        // stop_processing now applies.
+        writes.record_match(
+            &rule.id,
+            &approval.file_path,
+            Some(&approval.file_hash),
+            &recorded,
+            &self.settings.match_metadata_indexed_keys,
+        );
+        record_companion_matches(&RuleRepository::new(self.db.clone()), &mut writes, &outcomes);
+        writes
+            .commit(self.db)
+            .map_err(|e| ApprovalError::failed(e.to_string()))?;
+        record_transfers(self.db, &rule, info.size, &outcomes, self.settings, now)
+            .map_err(|e| ApprovalError::failed(e.to_string()))?;
+        settle_counter(self.db, &rule, &captures, &outcomes)
+            .map_err(|e| ApprovalError::failed(e.to_string()))?;
+        write_processed_markers(self.settings, &rule.id, &info.path, &outcomes);
+        let _ = approvals.delete(&approval.id);
+
+        match outcomes
+            .iter()
+            .find(|outcome| outcome.status == ActionResultStatus::Error)
+        {
+            Some(failed) => Err(ApprovalError::failed(
+                failed.error.clone().unwrap_or_else(|| "Action failed".to_string()),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Drops a pending approval without running it. The file is proposed again
+/// the next time it triggers the rule.
+pub fn dismiss(db: &Database, id: &str) -> Result<(), ApprovalError> {
+    let approvals = ApprovalRepository::new(db.clone());
+    approvals
+        .get(id)
+        .map_err(|e| ApprovalError::failed(e.to_string()))?
+        .ok_or_else(|| ApprovalError::new(ApprovalErrorKind::NotFound, "Approval not found"))?;
+    approvals
+        .delete(id)
+        .map_err(|e| ApprovalError::failed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        Action, ActionDetails, ActionType, Condition, ConditionGroup, ConflictResolution,
+        ContentSource, ContentsCondition, MatchType, MoveAction, StringOperator,
+    };
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::match_repo::MatchRepository;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::{tempdir, TempDir};
+
+    /// Stand-in for the executor that understands a single Move.
+    fn run_move(actions: &[Action], info: &FileInfo, dry_run: bool) -> Vec<ActionOutcome> {
+        let Some(Action::Move(action)) = actions.first() else {
+            return Vec::new();
+        };
+        let dest = Path::new(&action.destination).join(&info.full_name);
+        if !dry_run {
+            fs::rename(&info.path, &dest).unwrap();
+        }
+        vec![ActionOutcome {
+            action_type: ActionType::Move,
+            status: if dry_run {
+                ActionResultStatus::Simulated
+            } else {
+                ActionResultStatus::Success
+            },
+            details: Some(ActionDetails {
+                source_path: info.path.to_string_lossy().to_string(),
+                destination_path: Some(dest.to_string_lossy().to_string()),
+                metadata: HashMap::new(),
+            }),
+            error: None,
+            error_kind: None,
+        }]
+    }
+
+    fn plan(
+        actions: &[Action],
+        info: &FileInfo,
+        _: &HashMap<String, String>,
+        _: &ActionScope,
+    ) -> Vec<ActionOutcome> {
+        run_move(actions, info, true)
+    }
+
+    fn execute(
+        actions: &[Action],
+        info: &FileInfo,
+        _: &HashMap<String, String>,
+        _: &ActionScope,
+    ) -> Vec<ActionOutcome> {
+        run_move(actions, info, false)
+    }
+
+    struct Fixture {
+        dir: TempDir,
+        db: Database,
+        rule: Rule,
+        file: std::path::PathBuf,
+        duplicates: Arc<DuplicateDetector>,
+    }
+
+    fn fixture() -> Fixture {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Inbox")
+            .unwrap();
+        let approved = dir.path().join("approved");
+        fs::create_dir(&approved).unwrap();
+        let rule = RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id,
+                name: "Contracts".to_string(),
+                enabled: true,
+                stop_processing: true,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![Condition::Contents(ContentsCondition {
+                        operator: StringOperator::Contains,
+                        value: "CONTRACT".to_string(),
+                        case_sensitive: true,
+                        source: ContentSource::Text,
+                        min_ocr_confidence: None,
+                    })],
+                },
+                actions: vec![Action::Move(MoveAction {
+                    destination: approved.to_string_lossy().to_string(),
+                    on_conflict: ConflictResolution::Rename,
+                    skip_duplicates: false,
+                    destination_permissions: Default::default(),
+                    quarantine: Default::default(),
+                    bring_companions: None,
+                    auto_partition: None,
+                })],
+                position: 0,
+                schedule: None,
+                approval_required: true,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap();
+        let file = dir.path().join("lease.txt");
+        fs::write(&file, "CONTRACT v1").unwrap();
+        let duplicates = Arc::new(DuplicateDetector::new(db.clone()));
+        Fixture {
+            dir,
+            db,
+            rule,
+            file,
+            duplicates,
+        }
+    }
+
+    fn propose_file(fx: &Fixture) -> PendingApproval {
+        let info = FileInfo::from_path(&fx.file).unwrap();
+        propose(&fx.db, &fx.rule, &info, &HashMap::new(), &plan).unwrap()
+    }
+
+    fn runner<'a>(fx: &'a Fixture, settings: &'a Settings, ocr: &'a Mutex<OcrManager>) -> ApprovalRunner<'a> {
+        ApprovalRunner {
+            db: &fx.db,
+            settings,
+            ocr,
+            duplicates: &fx.duplicates,
+            plan: &plan,
+            execute: &execute,
+        }
+    }
+
+    #[test]
+    fn approve_and_execute_runs_stored_plan() {
+        let fx = fixture();
+        let approval = propose_file(&fx);
+        let moved = fx.dir.path().join("approved").join("lease.txt");
+        assert_eq!(
+            approval.planned[0].destination_path.as_deref(),
+            Some(moved.to_string_lossy().as_ref())
+        );
+        // Proposing the same file again doesn't queue a duplicate.
+        assert_eq!(propose_file(&fx).id, approval.id);
+        assert!(fx.file.exists());
+
+        let settings = Settings::default();
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        runner(&fx, &settings, &ocr).execute(&approval.id, Utc::now()).unwrap();
+
+        assert!(moved.exists());
+        let approvals = ApprovalRepository::new(fx.db.clone());
+        assert!(approvals.list().unwrap().is_empty());
+        let matched = MatchRepository::new(fx.db.clone())
+            .get_hash_matched_rules(&[fx.rule.id.as_str()], &approval.file_hash)
+            .unwrap();
+        assert!(matched.contains(&fx.rule.id));
+    }
+
+    #[test]
+    fn modified_file_is_replanned_or_dropped() {
+        let fx = fixture();
+        let approval = propose_file(&fx);
+        let settings = Settings::default();
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        let runner = runner(&fx, &settings, &ocr);
+
+        fs::write(&fx.file, "CONTRACT v2").unwrap();
+        let err = runner.execute(&approval.id, Utc::now()).unwrap_err();
+        assert_eq!(err.kind, ApprovalErrorKind::Replanned);
+        let refreshed = ApprovalRepository::new(fx.db.clone())
+            .get(&approval.id)
+            .unwrap()
+            .unwrap();
+        assert_ne!(refreshed.file_hash, approval.file_hash);
+        assert!(fx.file.exists());
+
+        fs::write(&fx.file, "shopping list").unwrap();
+        let err = runner.execute(&approval.id, Utc::now()).unwrap_err();
+        assert_eq!(err.kind, ApprovalErrorKind::NoLongerMatches);
+        assert!(ApprovalRepository::new(fx.db.clone()).list().unwrap().is_empty());
+        assert!(fx.file.exists());
+    }
+
+    #[test]
+    fn expired_approvals_are_refused_and_collected() {
+        let fx = fixture();
+        let approval = propose_file(&fx);
+        let settings = Settings {
+            approval_expiry_hours: 24,
+            ..Settings::default()
+        };
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+
+        assert!(!is_expired(&approval, Utc::now(), 24));
+        assert!(!is_expired(&approval, Utc::now() + Duration::days(30), 0));
+        let err = runner(&fx, &settings, &ocr)
+            .execute(&approval.id, Utc::now() + Duration::hours(25))
+            .unwrap_err();
+        assert_eq!(err.kind, ApprovalErrorKind::Expired);
+        assert!(fx.file.exists());
+
+        let approvals = ApprovalRepository::new(fx.db.clone());
+        assert!(approvals.list().unwrap().is_empty());
+        let fresh = propose_file(&fx);
+        approvals.cleanup_expired(24).unwrap();
+        assert_eq!(approvals.list().unwrap().len(), 1);
+        fx.db
+            .with_conn(|conn| {
+                conn.execute(
+                    "UPDATE pending_approvals SET created_at = ?1 WHERE id = ?2",
+                    rusqlite::params![(Utc::now() - Duration::hours(48)).to_rfc3339(), fresh.id],
+                )?;
+                Ok(())
+            })
+            .unwrap();
+        approvals.cleanup_expired(0).unwrap();
+        assert_eq!(approvals.list().unwrap().len(), 1);
+        approvals.cleanup_expired(24).unwrap();
+        assert!(approvals.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn dismissal_drops_without_running() {
+        let fx = fixture();
+        let approval = propose_file(&fx);
+
+        dismiss(&fx.db, &approval.id).unwrap();
+        assert!(fx.file.exists());
+        assert!(ApprovalRepository::new(fx.db.clone()).list().unwrap().is_empty());
+        assert_eq!(
+            dismiss(&fx.db, &approval.id).unwrap_err().kind,
+            ApprovalErrorKind::NotFound
+        );
+        // Dismissing doesn't count as a match, so the file can be proposed again.
+        let matched = MatchRepository::new(fx.db.clone())
+            .get_hash_matched_rules(&[fx.rule.id.as_str()], &approval.file_hash)
+            .unwrap();
+        assert!(matched.is_empty());
+    }
+}
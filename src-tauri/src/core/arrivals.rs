@@ -0,0 +1,272 @@
+//! Arrival groups for folders with a group window. Files that land within the
+//! window of each other, like the attachments a mail client saves from one
+//! email, are held until the window has passed without another arrival and
+//! then processed one by one, each knowing the group's id and size. Every
+//! folder collects its own groups.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::core::watcher::FileEvent;
+use crate::models::{Arrival, FolderId};
+
+/// The longest group window a folder can be given.
+pub const MAX_GROUP_WINDOW_MS: u64 = 60_000;
+
+/// Hex digits of the group id.
+const GROUP_ID_LEN: usize = 8;
+
+#[derive(Default)]
+pub struct ArrivalGroups {
+    state: std::sync::Mutex<GroupState>,
+}
+
+#[derive(Default)]
+struct GroupState {
+    /// The group each folder is still collecting.
+    open: HashMap<FolderId, OpenGroup>,
+    /// Groups whose window passed that haven't been taken yet.
+    closed: Vec<OpenGroup>,
+}
+
+struct OpenGroup {
+    id: String,
+    members: Vec<FileEvent>,
+    last: Instant,
+    window: Duration,
+}
+
+impl OpenGroup {
+    fn closed_by(&self, now: Instant) -> bool {
+        now.duration_since(self.last) >= self.window
+    }
+
+    fn into_events(self) -> Vec<FileEvent> {
+        let arrival = Arrival {
+            id: self.id,
+            size: self.members.len(),
+        };
+        self.members
+            .into_iter()
+            .map(|event| FileEvent {
+                arrival: Some(arrival.clone()),
+                ..event
+            })
+            .collect()
+    }
+}
+
+impl ArrivalGroups {
+    /// Adds `event` to its folder's open group, or opens a new group when the
+    /// last file arrived `window` or more before `now`. Returns the group id.
+    pub fn add(&self, event: FileEvent, now: Instant, window: Duration) -> String {
+        let Ok(mut state) = self.state.lock() else {
+            return String::new();
+        };
+        let state = &mut *state;
+        if let Some(group) = state.open.remove(&event.folder_id) {
+            if group.closed_by(now) {
+                state.closed.push(group);
+            } else {
+                state.open.insert(event.folder_id.clone(), group);
+            }
+        }
+        let group = state
+            .open
+            .entry(event.folder_id.clone())
+            .or_insert_with(|| OpenGroup {
+                id: group_id(&event.folder_id, &event.path),
+                members: Vec::new(),
+                last: now,
+                window,
+            });
+        group.last = now;
+        group.window = window;
+        if !group.members.iter().any(|member| member.path == event.path) {
+            group.members.push(event);
+        }
+        group.id.clone()
+    }
+
+    /// Whether `path` is waiting in a group that hasn't been taken yet.
+    pub fn is_held(&self, folder_id: &str, path: &Path) -> bool {
+        let Ok(state) = self.state.lock() else {
+            return false;
+        };
+        state
+            .open
+            .get(folder_id)
+            .into_iter()
+            .chain(state.closed.iter())
+            .any(|group| group.members.iter().any(|member| member.path == path))
+    }
+
+    /// The members of every group whose window has passed by `now`, each
+    /// tagged with its group, in arrival order.
+    pub fn take_closed(&self, now: Instant) -> Vec<FileEvent> {
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let state = &mut *state;
+        let due: Vec<FolderId> = state
+            .open
+            .iter()
+            .filter(|(_, group)| group.closed_by(now))
+            .map(|(folder_id, _)| folder_id.clone())
+            .collect();
+        for folder_id in due {
+            if let Some(group) = state.open.remove(&folder_id) {
+                state.closed.push(group);
+            }
+        }
+        state
+            .closed
+            .drain(..)
+            .flat_map(OpenGroup::into_events)
+            .collect()
+    }
+}
+
+fn group_id(folder_id: &str, first: &Path) -> String {
+    let opened = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(folder_id.as_bytes());
+    hasher.update(first.to_string_lossy().as_bytes());
+    hasher.update(opened.to_le_bytes());
+    let mut id = format!("{:x}", hasher.finalize());
+    id.truncate(GROUP_ID_LEN);
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::watcher::FileEventKind;
+    use crate::models::EventSource;
+    use std::path::PathBuf;
+
+    const WINDOW: Duration = Duration::from_millis(1_000);
+
+    fn event(folder_id: &str, name: &str) -> FileEvent {
+        FileEvent {
+            path: PathBuf::from(format!("/{folder_id}/{name}")),
+            folder_id: folder_id.to_string(),
+            kind: FileEventKind::Created,
+            rule_id: None,
+            arrival: None,
+            active_app: None,
+            source: EventSource::Live,
+        }
+    }
+
+    fn arrivals(events: &[FileEvent]) -> Vec<(String, Arrival)> {
+        events
+            .iter()
+            .map(|event| {
+                let name = event
+                    .path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                (name, event.arrival.clone().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn files_within_the_window_share_a_group() {
+        let groups = ArrivalGroups::default();
+        let start = Instant::now();
+        for (offset, name) in [(0, "a.pdf"), (300, "b.pdf"), (900, "c.pdf")] {
+            groups.add(
+                event("mail", name),
+                start + Duration::from_millis(offset),
+                WINDOW,
+            );
+        }
+
+        // Each arrival pushes the close back.
+        assert!(groups
+            .take_closed(start + Duration::from_millis(1_500))
+            .is_empty());
+        assert!(groups.is_held("mail", Path::new("/mail/b.pdf")));
+
+        let taken = arrivals(&groups.take_closed(start + Duration::from_millis(1_900)));
+        let names: Vec<&str> = taken.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["a.pdf", "b.pdf", "c.pdf"]);
+        assert!(taken.iter().all(|(_, arrival)| arrival == &taken[0].1));
+        assert_eq!(taken[0].1.size, 3);
+        assert_eq!(taken[0].1.id.len(), GROUP_ID_LEN);
+        assert!(!groups.is_held("mail", Path::new("/mail/b.pdf")));
+    }
+
+    #[test]
+    fn a_straggler_gets_its_own_group() {
+        let groups = ArrivalGroups::default();
+        let start = Instant::now();
+        let first = groups.add(event("mail", "a.pdf"), start, WINDOW);
+        groups.add(
+            event("mail", "b.pdf"),
+            start + Duration::from_millis(200),
+            WINDOW,
+        );
+        // Arrives after the window passed but before anything took the group.
+        let late = groups.add(
+            event("mail", "c.pdf"),
+            start + Duration::from_millis(1_300),
+            WINDOW,
+        );
+        assert_ne!(first, late);
+
+        let taken = arrivals(&groups.take_closed(start + Duration::from_millis(2_400)));
+        assert_eq!(taken.len(), 3);
+        assert_eq!(
+            taken[0].1,
+            Arrival {
+                id: first.clone(),
+                size: 2
+            }
+        );
+        assert_eq!(taken[1].1, Arrival { id: first, size: 2 });
+        assert_eq!(taken[2].1, Arrival { id: late, size: 1 });
+    }
+
+    #[test]
+    fn groups_never_span_folders() {
+        let groups = ArrivalGroups::default();
+        let start = Instant::now();
+        let mail = groups.add(event("mail", "a.pdf"), start, WINDOW);
+        let scans = groups.add(event("scans", "b.pdf"), start, WINDOW);
+        assert_ne!(mail, scans);
+        assert!(!groups.is_held("scans", Path::new("/mail/a.pdf")));
+
+        let taken = groups.take_closed(start + WINDOW);
+        assert_eq!(taken.len(), 2);
+        assert!(taken
+            .iter()
+            .all(|event| event.arrival.as_ref().unwrap().size == 1));
+    }
+
+    #[test]
+    fn a_repeated_event_counts_once() {
+        let groups = ArrivalGroups::default();
+        let start = Instant::now();
+        groups.add(event("mail", "a.pdf"), start, WINDOW);
+        groups.add(
+            event("mail", "a.pdf"),
+            start + Duration::from_millis(10),
+            WINDOW,
+        );
+
+        let taken = groups.take_closed(start + Duration::from_millis(1_010));
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].arrival.as_ref().unwrap().size, 1);
+    }
+}
@@ -0,0 +1,794 @@
+//! Backups of the database and settings. Each backup is a timestamped folder
+//! with a copy of the database made by `VACUUM INTO`, so it's consistent
+//! even while rules are writing, and the settings as they were. A backup
+//! made with a password holds both encrypted, under a `.sealed` suffix.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use rusqlite::{params, Connection, OpenFlags};
+use serde_json::{json, Value};
+
+use crate::models::{
+    ActionDetails, AutoBackupSettings, BackupError, BackupErrorKind, BackupInfo, BackupStatus,
+    LogEntry, LogStatus, Settings,
+};
+use crate::storage::database::Database;
+use crate::storage::log_repo::LogRepository;
+use crate::utils::platform::normalize_user_path;
+use crate::utils::sealed::{self, SealError};
+
+pub const BACKUP_DB_FILE: &str = "file-dispatch.db";
+pub const BACKUP_SETTINGS_FILE: &str = "settings.json";
+pub const SEALED_DB_FILE: &str = "file-dispatch.db.sealed";
+pub const SEALED_SETTINGS_FILE: &str = "settings.json.sealed";
+
+const BACKUP_PREFIX: &str = "file-dispatch-";
+const STAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// How often the scheduler checks whether a backup is due.
+const TICK: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Backs up on the interval in the settings.
+pub struct BackupScheduler {
+    db: Database,
+    settings: Arc<Mutex<Settings>>,
+}
+
+impl BackupScheduler {
+    pub fn new(db: Database, settings: Arc<Mutex<Settings>>) -> Self {
+        Self { db, settings }
+    }
+
+    pub fn start(self) {
+        thread::spawn(move || loop {
+            if let Err(err) = self.tick(Utc::now()) {
+                eprintln!("Backup error: {err}");
+            }
+            thread::sleep(TICK);
+        });
+    }
+
+    /// Backs up if the newest backup is at least the interval old.
+    fn tick(&self, now: DateTime<Utc>) -> Result<Option<BackupInfo>> {
+        let settings = self.settings.lock().map(|s| s.clone()).unwrap_or_default();
+        let options = &settings.auto_backup;
+        if !options.enabled {
+            return Ok(None);
+        }
+        let interval = Duration::days(options.interval_days.max(1) as i64);
+        let newest = backup_folders(&backup_dir(&self.db, options))?
+            .first()
+            .map(|(_, _, created_at)| *created_at);
+        if newest.is_some_and(|newest| now - newest < interval) {
+            return Ok(None);
+        }
+        run_backup(&self.db, &settings, now, None).map(Some)
+    }
+}
+
+/// The configured destination, or a `backups` folder next to the database.
+pub fn backup_dir(db: &Database, options: &AutoBackupSettings) -> PathBuf {
+    match options
+        .destination_dir
+        .as_deref()
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+    {
+        Some(dir) => normalize_user_path(dir),
+        None => db
+            .path()
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("backups"),
+    }
+}
+
+/// Makes a backup, prunes old ones and records the outcome in the log. With
+/// a password, the backup is encrypted with it.
+pub fn run_backup(
+    db: &Database,
+    settings: &Settings,
+    now: DateTime<Utc>,
+    password: Option<&str>,
+) -> Result<BackupInfo> {
+    let options = &settings.auto_backup;
+    let dir = backup_dir(db, options);
+    let stripped = match password {
+        Some(_) => Vec::new(),
+        None => db
+            .with_conn(|conn| blank_literal_passwords(conn))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, name, _)| format!("\"{name}\""))
+            .collect(),
+    };
+    let result = create_backup(db, settings, &dir, now, password).and_then(|backup| {
+        let pruned = prune_backups(&dir, options.keep_count)?;
+        Ok((backup, pruned))
+    });
+
+    let mut metadata = HashMap::new();
+    let (file_path, status, error_message) = match &result {
+        Ok((backup, pruned)) => {
+            metadata.insert("backup_id".to_string(), backup.id.clone());
+            metadata.insert("backup_bytes".to_string(), backup.size_bytes.to_string());
+            metadata.insert("pruned".to_string(), pruned.to_string());
+            if !stripped.is_empty() {
+                metadata.insert(
+                    "warning".to_string(),
+                    format!(
+                        "Left out the archive passwords kept in {}; encrypt the backup to keep them",
+                        stripped.join(", ")
+                    ),
+                );
+            }
+            (backup.path.clone(), LogStatus::Success, None)
+        }
+        Err(err) => (
+            dir.to_string_lossy().to_string(),
+            LogStatus::Error,
+            Some(err.to_string()),
+        ),
+    };
+    write_log(db, "backup", file_path, status, error_message, metadata);
+    result.map(|(backup, _)| backup)
+}
+
+/// Writes a new backup folder into `dir`. It's assembled under a `.partial`
+/// name first, so a backup cut short never shows up as one. An encrypted
+/// backup's database is copied next to the live one to be sealed, so the
+/// plain copy never lands in the backup folder.
+pub fn create_backup(
+    db: &Database,
+    settings: &Settings,
+    dir: &Path,
+    now: DateTime<Utc>,
+    password: Option<&str>,
+) -> Result<BackupInfo> {
+    fs::create_dir_all(dir)?;
+    let id = format!("{BACKUP_PREFIX}{}", now.format(STAMP_FORMAT));
+    let target = dir.join(&id);
+    if target.exists() {
+        bail!("Backup {id} already exists");
+    }
+    let partial = dir.join(format!("{id}.partial"));
+    if partial.exists() {
+        fs::remove_dir_all(&partial)?;
+    }
+    fs::create_dir(&partial)?;
+    let db_file = match password {
+        Some(_) => with_suffix(db.path(), "sealing"),
+        None => partial.join(BACKUP_DB_FILE),
+    };
+    let written = db.backup_into(&db_file).and_then(|()| {
+        // A standalone file, without -wal and -shm companions.
+        let copy = Connection::open(&db_file)?;
+        copy.pragma_update(None, "journal_mode", "DELETE")?;
+        if password.is_none() {
+            strip_literal_passwords(&copy)?;
+        }
+        drop(copy);
+        let json = serde_json::to_vec_pretty(&json!({ "settings": settings }))?;
+        match password {
+            Some(password) => {
+                let sealed_db = sealed::seal(&fs::read(&db_file)?, password);
+                fs::write(partial.join(SEALED_DB_FILE), sealed_db)?;
+                fs::write(
+                    partial.join(SEALED_SETTINGS_FILE),
+                    sealed::seal(&json, password),
+                )?;
+            }
+            None => fs::write(partial.join(BACKUP_SETTINGS_FILE), json)?,
+        }
+        fs::rename(&partial, &target)?;
+        Ok(())
+    });
+    if password.is_some() {
+        let _ = fs::remove_file(&db_file);
+    }
+    if let Err(err) = written {
+        let _ = fs::remove_dir_all(&partial);
+        return Err(err);
+    }
+    let created_at = parse_id(&id).unwrap_or(now);
+    Ok(describe(id, target, created_at, db.schema_version()?))
+}
+
+/// The rules whose Archive actions keep their password in the rule, by id
+/// and name, with their actions as JSON with those passwords blanked.
+fn blank_literal_passwords(conn: &Connection) -> Result<Vec<(String, String, String)>> {
+    let rows: Vec<(String, String, Option<String>)> = conn
+        .prepare("SELECT id, name, actions FROM rules")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    let mut blanked = Vec::new();
+    for (id, name, actions) in rows {
+        let Some(Ok(mut actions)) = actions.map(|json| serde_json::from_str::<Value>(&json)) else {
+            continue;
+        };
+        let mut found = false;
+        for action in actions.as_array_mut().into_iter().flatten() {
+            if let Some(source) = action.pointer_mut("/encryption/passwordSource") {
+                if source["type"] == "literal" {
+                    source["value"] = Value::String(String::new());
+                    found = true;
+                }
+            }
+        }
+        if found {
+            blanked.push((id, name, actions.to_string()));
+        }
+    }
+    Ok(blanked)
+}
+
+/// Blanks archive passwords kept in rules in an unencrypted backup's
+/// database, where anyone with the backup could read them. Restored rules
+/// need them set again before they can encrypt.
+fn strip_literal_passwords(conn: &Connection) -> Result<()> {
+    for (id, _, actions) in blank_literal_passwords(conn)? {
+        conn.execute(
+            "UPDATE rules SET actions = ?1 WHERE id = ?2",
+            params![actions, id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Backups in `dir`, newest first, each opened read-only to check it.
+pub fn list_backups(dir: &Path, current_version: i64) -> Result<Vec<BackupInfo>> {
+    Ok(backup_folders(dir)?
+        .into_iter()
+        .map(|(id, path, created_at)| describe(id, path, created_at, current_version))
+        .collect())
+}
+
+/// Deletes all but the newest `keep_count` backups; zero keeps them all.
+/// Returns how many were deleted.
+pub fn prune_backups(dir: &Path, keep_count: u32) -> Result<usize> {
+    if keep_count == 0 {
+        return Ok(0);
+    }
+    let mut pruned = 0;
+    for (_, path, _) in backup_folders(dir)?.into_iter().skip(keep_count as usize) {
+        fs::remove_dir_all(path)?;
+        pruned += 1;
+    }
+    Ok(pruned)
+}
+
+/// Replaces the database with backup `id`. The current database is kept
+/// next to it with a `.pre-restore` suffix. An encrypted backup needs the
+/// password it was made with.
+pub fn restore_backup(
+    db: &Database,
+    dir: &Path,
+    id: &str,
+    password: Option<&str>,
+) -> Result<(), BackupError> {
+    let folder = find_backup(dir, id)?;
+    let current = db
+        .schema_version()
+        .map_err(|err| BackupError::failed(err.to_string()))?;
+    let staged = with_suffix(db.path(), "restore");
+    let sealed_db = folder.join(SEALED_DB_FILE);
+    let source = if sealed_db.is_file() {
+        let plain = open_sealed(&sealed_db, id, password)?;
+        fs::write(&staged, plain).map_err(|err| BackupError::failed(err.to_string()))?;
+        staged.clone()
+    } else {
+        folder.join(BACKUP_DB_FILE)
+    };
+    let checked = inspect(&source)
+        .map_err(|err| {
+            BackupError::new(
+                BackupErrorKind::Invalid,
+                format!("Backup {id} can't be restored: {err}"),
+            )
+        })
+        .and_then(|version| {
+            if version > current {
+                return Err(BackupError::new(
+                    BackupErrorKind::NewerSchema,
+                    format!(
+                        "Backup {id} was made by a newer version of File Dispatch (schema \
+                         {version}, this version knows up to {current})"
+                    ),
+                ));
+            }
+            Ok(())
+        });
+    if let Err(err) = checked {
+        if source == staged {
+            let _ = fs::remove_file(&staged);
+        }
+        return Err(err);
+    }
+
+    if source != staged {
+        fs::copy(&source, &staged).map_err(|err| BackupError::failed(err.to_string()))?;
+    }
+    let previous = with_suffix(db.path(), "pre-restore");
+    db.replace_file(&staged, &previous).map_err(|err| {
+        let _ = fs::remove_file(&staged);
+        BackupError::failed(format!("Couldn't swap in backup {id}: {err}"))
+    })
+}
+
+/// The settings saved with backup `id`, if it has them.
+pub fn backup_settings(
+    dir: &Path,
+    id: &str,
+    password: Option<&str>,
+) -> Result<Option<Settings>, BackupError> {
+    let folder = find_backup(dir, id)?;
+    let sealed_settings = folder.join(SEALED_SETTINGS_FILE);
+    let raw = if sealed_settings.is_file() {
+        open_sealed(&sealed_settings, id, password)?
+    } else {
+        let Ok(raw) = fs::read(folder.join(BACKUP_SETTINGS_FILE)) else {
+            return Ok(None);
+        };
+        raw
+    };
+    let mut value: serde_json::Value = serde_json::from_slice(&raw).map_err(|err| {
+        BackupError::new(
+            BackupErrorKind::Invalid,
+            format!("Backup {id} has unreadable settings: {err}"),
+        )
+    })?;
+    let settings = value.get_mut("settings").map(serde_json::Value::take);
+    serde_json::from_value(settings.unwrap_or_default())
+        .map(Some)
+        .map_err(|err| {
+            BackupError::new(
+                BackupErrorKind::Invalid,
+                format!("Backup {id} has unreadable settings: {err}"),
+            )
+        })
+}
+
+/// Records a backup or restore in the activity log.
+pub fn write_log(
+    db: &Database,
+    action_type: &str,
+    file_path: String,
+    status: LogStatus,
+    error_message: Option<String>,
+    metadata: HashMap<String, String>,
+) {
+    let _ = LogRepository::new(db.clone()).insert(LogEntry {
+        id: String::new(),
+        rule_id: None,
+        rule_name: None,
+        file_path: file_path.clone(),
+        action_type: action_type.to_string(),
+        action_detail: Some(ActionDetails {
+            source_path: file_path,
+            destination_path: None,
+            metadata,
+        }),
+        status,
+        error_message,
+        created_at: Utc::now(),
+    });
+}
+
+fn open_sealed(path: &Path, id: &str, password: Option<&str>) -> Result<Vec<u8>, BackupError> {
+    let Some(password) = password else {
+        return Err(BackupError::new(
+            BackupErrorKind::PasswordRequired,
+            format!("Backup {id} is encrypted; enter its password to restore it"),
+        ));
+    };
+    let data = fs::read(path).map_err(|err| BackupError::failed(err.to_string()))?;
+    sealed::open(&data, password).map_err(|err| match err {
+        SealError::WrongPassword => BackupError::new(
+            BackupErrorKind::WrongPassword,
+            format!("Wrong password for backup {id}"),
+        ),
+        SealError::Corrupt(_) => BackupError::new(
+            BackupErrorKind::Invalid,
+            format!("Backup {id} can't be restored: {err}"),
+        ),
+    })
+}
+
+fn find_backup(dir: &Path, id: &str) -> Result<PathBuf, BackupError> {
+    backup_folders(dir)
+        .map_err(|err| BackupError::failed(err.to_string()))?
+        .into_iter()
+        .find(|(folder_id, _, _)| folder_id == id)
+        .map(|(_, path, _)| path)
+        .ok_or_else(|| BackupError::new(BackupErrorKind::NotFound, format!("No backup {id}")))
+}
+
+/// Backup folders in `dir` with the time in their name, newest first.
+fn backup_folders(dir: &Path) -> Result<Vec<(String, PathBuf, DateTime<Utc>)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut folders = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let id = entry.file_name().to_string_lossy().to_string();
+        if let Some(created_at) = parse_id(&id) {
+            folders.push((id, entry.path(), created_at));
+        }
+    }
+    folders.sort_by_key(|folder| std::cmp::Reverse(folder.2));
+    Ok(folders)
+}
+
+fn parse_id(id: &str) -> Option<DateTime<Utc>> {
+    let stamp = id.strip_prefix(BACKUP_PREFIX)?;
+    NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT)
+        .ok()
+        .map(|stamp| stamp.and_utc())
+}
+
+fn describe(id: String, path: PathBuf, created_at: DateTime<Utc>, current: i64) -> BackupInfo {
+    let size_bytes = fs::read_dir(&path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0);
+    let (schema_version, status, problem) = if path.join(SEALED_DB_FILE).is_file() {
+        (None, BackupStatus::Encrypted, None)
+    } else {
+        match inspect(&path.join(BACKUP_DB_FILE)) {
+            Ok(version) if version > current => (Some(version), BackupStatus::NewerSchema, None),
+            Ok(version) => (Some(version), BackupStatus::Valid, None),
+            Err(err) => (None, BackupStatus::Invalid, Some(err.to_string())),
+        }
+    };
+    BackupInfo {
+        id,
+        path: path.to_string_lossy().to_string(),
+        created_at,
+        size_bytes,
+        schema_version,
+        status,
+        problem,
+    }
+}
+
+/// Opens a backed-up database read-only and returns its schema version.
+fn inspect(path: &Path) -> Result<i64> {
+    if !path.is_file() {
+        bail!("Database file is missing");
+    }
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    let version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|_| anyhow!("Not a database file"))?;
+    let has_rules: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'rules')",
+        [],
+        |row| row.get(0),
+    )?;
+    if version == 0 || !has_rules {
+        bail!("Not a File Dispatch database");
+    }
+    Ok(version)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        Action, ArchiveAction, ArchiveEncryption, ArchiveEncryptionMethod, ArchiveFormat,
+        ConditionGroup, MatchType, PasswordSource, Rule,
+    };
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::rule_repo::RuleRepository;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::tempdir;
+
+    fn rule(folder_id: &str, name: &str) -> Rule {
+        Rule {
+            id: String::new(),
+            folder_id: folder_id.to_string(),
+            name: name.to_string(),
+            enabled: true,
+            stop_processing: true,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![],
+            },
+            actions: vec![],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn log_entry(n: usize) -> LogEntry {
+        LogEntry {
+            id: String::new(),
+            rule_id: None,
+            rule_name: None,
+            file_path: format!("/in/{n}.pdf"),
+            action_type: "move".to_string(),
+            action_detail: None,
+            status: LogStatus::Success,
+            error_message: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn backups_of_a_live_database_open_cleanly() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("live.db")).unwrap();
+        let backups = dir.path().join("backups");
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let (db, stop) = (db.clone(), stop.clone());
+            thread::spawn(move || {
+                let logs = LogRepository::new(db);
+                let mut n = 0;
+                while !stop.load(Ordering::SeqCst) {
+                    logs.insert(log_entry(n)).unwrap();
+                    n += 1;
+                }
+                n
+            })
+        };
+        let start = Utc::now();
+        for i in 0..5 {
+            let now = start + Duration::seconds(i);
+            create_backup(&db, &Settings::default(), &backups, now, None).unwrap();
+        }
+        stop.store(true, Ordering::SeqCst);
+        let written = writer.join().unwrap();
+
+        let version = db.schema_version().unwrap();
+        let listed = list_backups(&backups, version).unwrap();
+        assert_eq!(listed.len(), 5);
+        for backup in &listed {
+            assert_eq!(backup.status, BackupStatus::Valid, "{:?}", backup.problem);
+            let conn = Connection::open_with_flags(
+                Path::new(&backup.path).join(BACKUP_DB_FILE),
+                OpenFlags::SQLITE_OPEN_READ_ONLY,
+            )
+            .unwrap();
+            let check: String = conn
+                .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(check, "ok");
+            let logs: usize = conn
+                .query_row("SELECT COUNT(*) FROM logs", [], |row| row.get(0))
+                .unwrap();
+            assert!(logs <= written);
+        }
+        assert!(!fs::read_dir(&backups).unwrap().any(|entry| entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .ends_with(".partial")));
+    }
+
+    #[test]
+    fn prune_keeps_the_newest_keep_count() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("live.db")).unwrap();
+        let backups = dir.path().join("backups");
+        let start = Utc::now() - Duration::days(1);
+        for hour in 0..5 {
+            let now = start + Duration::hours(hour);
+            create_backup(&db, &Settings::default(), &backups, now, None).unwrap();
+        }
+        fs::create_dir(backups.join("photos")).unwrap();
+
+        assert_eq!(prune_backups(&backups, 0).unwrap(), 0);
+        assert_eq!(prune_backups(&backups, 3).unwrap(), 2);
+        let kept: Vec<DateTime<Utc>> = list_backups(&backups, 99)
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.created_at)
+            .collect();
+        assert_eq!(kept.len(), 3);
+        assert_eq!(
+            kept[0].timestamp(),
+            (start + Duration::hours(4)).timestamp()
+        );
+        assert_eq!(
+            kept[2].timestamp(),
+            (start + Duration::hours(2)).timestamp()
+        );
+        assert!(backups.join("photos").exists());
+    }
+
+    #[test]
+    fn restore_round_trips_rules_and_folders() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("live.db");
+        let db = Database::new_with_path(db_path.clone()).unwrap();
+        let backups = dir.path().join("backups");
+        let folders = FolderRepository::new(db.clone());
+        let rules = RuleRepository::new(db.clone());
+        let inbox = folders.create("/in", "Inbox").unwrap();
+        let invoices = rules.create(rule(&inbox.id, "Invoices")).unwrap();
+
+        let mut settings = Settings::default();
+        settings.log_retention_days = 90;
+        let backup = create_backup(&db, &settings, &backups, Utc::now(), None).unwrap();
+
+        rules.delete(&invoices.id).unwrap();
+        folders.create("/scans", "Scans").unwrap();
+        restore_backup(&db, &backups, &backup.id, None).unwrap();
+
+        // The repositories made before the restore see the restored file.
+        let restored = folders.list().unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, inbox.id);
+        let restored_rules = rules.list_by_folder(&inbox.id).unwrap();
+        assert_eq!(restored_rules.len(), 1);
+        assert_eq!(restored_rules[0].name, "Invoices");
+        let saved = backup_settings(&backups, &backup.id, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(saved.log_retention_days, 90);
+
+        let previous = Database::new_with_path(with_suffix(&db_path, "pre-restore")).unwrap();
+        assert_eq!(FolderRepository::new(previous).list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn plain_backups_leave_out_archive_passwords_kept_in_rules() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("live.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create("/in", "Inbox")
+            .unwrap();
+        let mut payslips = rule(&inbox.id, "Payslips");
+        payslips.actions = vec![Action::Archive(ArchiveAction {
+            destination: "~/Archive/{name}".to_string(),
+            format: ArchiveFormat::Zip,
+            delete_after: false,
+            encryption: Some(ArchiveEncryption {
+                method: ArchiveEncryptionMethod::ZipAes256,
+                password_source: PasswordSource::Literal("hunter2".to_string()),
+            }),
+        })];
+        let rules = RuleRepository::new(db.clone());
+        let payslips = rules.create(payslips).unwrap();
+
+        let backup = run_backup(&db, &Settings::default(), Utc::now(), None).unwrap();
+        let conn = Connection::open(Path::new(&backup.path).join(BACKUP_DB_FILE)).unwrap();
+        let actions: String = conn
+            .query_row(
+                "SELECT actions FROM rules WHERE id = ?1",
+                params![payslips.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!actions.contains("hunter2") && actions.contains("literal"));
+        let live = rules.get(&payslips.id).unwrap().unwrap();
+        let Action::Archive(ArchiveAction {
+            encryption: Some(kept),
+            ..
+        }) = &live.actions[0]
+        else {
+            panic!("expected an encrypted archive");
+        };
+        assert_eq!(
+            kept.password_source,
+            PasswordSource::Literal("hunter2".to_string())
+        );
+        let logged = LogRepository::new(db).list(1, 0).unwrap();
+        let warning = &logged[0].action_detail.as_ref().unwrap().metadata["warning"];
+        assert!(warning.contains("\"Payslips\""), "{warning}");
+    }
+
+    #[test]
+    fn restore_refuses_newer_and_unknown_backups() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("live.db")).unwrap();
+        let backups = dir.path().join("backups");
+        let backup = create_backup(&db, &Settings::default(), &backups, Utc::now(), None).unwrap();
+        let current = db.schema_version().unwrap();
+        let conn = Connection::open(Path::new(&backup.path).join(BACKUP_DB_FILE)).unwrap();
+        conn.pragma_update(None, "user_version", current + 1)
+            .unwrap();
+        drop(conn);
+
+        let listed = list_backups(&backups, current).unwrap();
+        assert_eq!(listed[0].status, BackupStatus::NewerSchema);
+        let err = restore_backup(&db, &backups, &backup.id, None).unwrap_err();
+        assert_eq!(err.kind, BackupErrorKind::NewerSchema);
+        assert!(!with_suffix(db.path(), "pre-restore").exists());
+
+        let err = restore_backup(&db, &backups, "../live.db", None).unwrap_err();
+        assert_eq!(err.kind, BackupErrorKind::NotFound);
+    }
+
+    #[test]
+    fn encrypted_backups_need_their_password() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("live.db")).unwrap();
+        let backups = dir.path().join("backups");
+        let folders = FolderRepository::new(db.clone());
+        let inbox = folders.create("/in/Clients/Acme", "Acme").unwrap();
+        let backup =
+            create_backup(&db, &Settings::default(), &backups, Utc::now(), Some("pw")).unwrap();
+
+        let folder = Path::new(&backup.path);
+        assert!(!folder.join(BACKUP_DB_FILE).exists());
+        assert!(!folder.join(BACKUP_SETTINGS_FILE).exists());
+        assert!(!with_suffix(db.path(), "sealing").exists());
+        let raw = fs::read(folder.join(SEALED_DB_FILE)).unwrap();
+        assert!(!raw.windows(4).any(|window| window == b"Acme"));
+        assert_eq!(backup.status, BackupStatus::Encrypted);
+
+        folders.delete(&inbox.id).unwrap();
+        let missing = restore_backup(&db, &backups, &backup.id, None).unwrap_err();
+        assert_eq!(missing.kind, BackupErrorKind::PasswordRequired);
+        let wrong = backup_settings(&backups, &backup.id, Some("nope")).unwrap_err();
+        assert_eq!(wrong.kind, BackupErrorKind::WrongPassword);
+        let wrong = restore_backup(&db, &backups, &backup.id, Some("nope")).unwrap_err();
+        assert_eq!(wrong.kind, BackupErrorKind::WrongPassword);
+        assert!(folders.list().unwrap().is_empty());
+
+        assert!(backup_settings(&backups, &backup.id, Some("pw"))
+            .unwrap()
+            .is_some());
+        restore_backup(&db, &backups, &backup.id, Some("pw")).unwrap();
+        assert_eq!(folders.list().unwrap()[0].id, inbox.id);
+        assert!(!with_suffix(db.path(), "restore").exists());
+    }
+
+    #[test]
+    fn tampered_encrypted_backups_are_invalid() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("live.db")).unwrap();
+        let backups = dir.path().join("backups");
+        let backup =
+            create_backup(&db, &Settings::default(), &backups, Utc::now(), Some("pw")).unwrap();
+        let sealed_db = Path::new(&backup.path).join(SEALED_DB_FILE);
+        let mut raw = fs::read(&sealed_db).unwrap();
+        let middle = raw.len() / 2;
+        raw[middle] ^= 0xff;
+        fs::write(&sealed_db, raw).unwrap();
+
+        let err = restore_backup(&db, &backups, &backup.id, Some("pw")).unwrap_err();
+        assert_eq!(err.kind, BackupErrorKind::Invalid);
+        assert!(!with_suffix(db.path(), "pre-restore").exists());
+    }
+}
@@ -0,0 +1,459 @@
+//! The tray icon's badge: how many approvals and conflicts wait for a
+//! decision, plus the errors logged since the user last acknowledged them.
+//! A background thread recounts when asked to and every few seconds, waits
+//! for a burst of changes to settle, and only redraws when what the badge
+//! shows has changed. Drawing happens on that thread, so a platform where
+//! swapping the tray icon is slow or fails never holds up the engine.
+
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use tauri::image::Image;
+use tauri::{AppHandle, Manager};
+
+use crate::models::BadgeCounts;
+use crate::storage::badge_repo::BadgeRepository;
+use crate::storage::database::Database;
+
+/// The id the tray icon is built with, so the badge can find it again.
+pub const TRAY_ID: &str = "main";
+
+/// How long counts have to settle before the badge is redrawn.
+pub const DEBOUNCE: Duration = Duration::from_millis(750);
+
+/// How often counts are checked without being asked, for changes nothing
+/// announces, such as an error being logged.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Icon swaps slower than this are logged.
+const SLOW_SWAP: Duration = Duration::from_millis(250);
+
+/// Which tray icon variant a count gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BadgeIcon {
+    Plain,
+    /// One to nine.
+    Count(u8),
+    /// Ten or more, shown as 9+.
+    Overflow,
+}
+
+impl BadgeIcon {
+    pub fn for_count(count: usize) -> Self {
+        match count {
+            0 => BadgeIcon::Plain,
+            1..=9 => BadgeIcon::Count(count as u8),
+            _ => BadgeIcon::Overflow,
+        }
+    }
+
+    /// The bundled badge drawn over the base icon, under `resources/badges`.
+    pub fn overlay(self) -> Option<String> {
+        match self {
+            BadgeIcon::Plain => None,
+            BadgeIcon::Count(count) => Some(format!("badge-{count}.png")),
+            BadgeIcon::Overflow => Some("badge-9plus.png".to_string()),
+        }
+    }
+}
+
+/// What the badge shows. The dock badge, where there is one, gets the exact
+/// total rather than the capped icon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadgeState {
+    pub icon: BadgeIcon,
+    pub counts: BadgeCounts,
+}
+
+impl BadgeState {
+    fn new(counts: BadgeCounts) -> Self {
+        Self {
+            icon: BadgeIcon::for_count(counts.total()),
+            counts,
+        }
+    }
+
+    pub fn tooltip(&self) -> String {
+        let BadgeCounts {
+            approvals,
+            conflicts,
+            errors,
+        } = self.counts;
+        let parts: Vec<String> = [
+            (approvals, "to approve"),
+            (conflicts, "conflicts"),
+            (errors, "new errors"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{count} {label}"))
+        .collect();
+        if parts.is_empty() {
+            "File Dispatch".to_string()
+        } else {
+            format!("File Dispatch: {}", parts.join(", "))
+        }
+    }
+}
+
+/// Puts a badge on screen. Swapped out in tests.
+pub trait BadgeRenderer: Send {
+    fn render(&self, state: &BadgeState) -> Result<()>;
+}
+
+/// Decides when the badge is redrawn: once a change has had `window` to
+/// settle, and only when what it shows differs from what's on screen.
+#[derive(Debug)]
+pub struct BadgeDebounce {
+    window: Duration,
+    changed_at: Option<Instant>,
+    shown: Option<BadgeState>,
+}
+
+impl BadgeDebounce {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            changed_at: None,
+            shown: None,
+        }
+    }
+
+    /// Notes that counts may have changed. Later changes join the first one's
+    /// window, so a steady stream still gets drawn.
+    pub fn poke(&mut self, now: Instant) {
+        self.changed_at.get_or_insert(now);
+    }
+
+    /// How long until a pending change is due, or `None` with none pending.
+    pub fn wait(&self, now: Instant) -> Option<Duration> {
+        let changed_at = self.changed_at?;
+        Some((changed_at + self.window).saturating_duration_since(now))
+    }
+
+    /// The state to draw for `counts` when it differs from what's shown.
+    /// Clears the pending change either way.
+    pub fn settle(&mut self, counts: BadgeCounts) -> Option<BadgeState> {
+        self.changed_at = None;
+        let state = BadgeState::new(counts);
+        if self.shown.as_ref() == Some(&state) {
+            return None;
+        }
+        Some(state)
+    }
+
+    /// Records `state` as on screen, once drawing it worked.
+    pub fn shown(&mut self, state: BadgeState) {
+        self.shown = Some(state);
+    }
+}
+
+/// Counts and draws the badge; driven by the thread `BadgeManager` starts.
+pub struct Badge {
+    repo: BadgeRepository,
+    renderer: Box<dyn BadgeRenderer>,
+    debounce: BadgeDebounce,
+    /// Whether the last draw failed, so a platform that can't draw badges
+    /// is logged once rather than every few seconds.
+    failing: bool,
+}
+
+impl Badge {
+    pub fn new(db: Database, renderer: Box<dyn BadgeRenderer>, window: Duration) -> Self {
+        Self {
+            repo: BadgeRepository::new(db),
+            renderer,
+            debounce: BadgeDebounce::new(window),
+            failing: false,
+        }
+    }
+
+    pub fn poke(&mut self, now: Instant) {
+        self.debounce.poke(now);
+    }
+
+    /// Redraws the badge if a change is due and shows something new.
+    pub fn tick(&mut self, now: Instant) {
+        if self.debounce.wait(now) != Some(Duration::ZERO) {
+            return;
+        }
+        let counts = match self.repo.counts() {
+            Ok(counts) => counts,
+            Err(err) => {
+                eprintln!("Badge count error: {err}");
+                self.debounce.settle(BadgeCounts::default());
+                return;
+            }
+        };
+        let Some(state) = self.debounce.settle(counts) else {
+            return;
+        };
+        let started = Instant::now();
+        match self.renderer.render(&state) {
+            Ok(()) => {
+                self.failing = false;
+                self.debounce.shown(state);
+            }
+            Err(err) => {
+                if !self.failing {
+                    eprintln!("Tray badge not updated: {err}");
+                }
+                self.failing = true;
+            }
+        }
+        let took = started.elapsed();
+        if took > SLOW_SWAP {
+            eprintln!("Tray badge update took {} ms", took.as_millis());
+        }
+    }
+}
+
+/// Keeps the tray badge up to date in the background.
+pub struct BadgeManager {
+    refresh_tx: Sender<()>,
+}
+
+impl BadgeManager {
+    pub fn start(badge: Badge) -> Self {
+        // One pending request is as good as many.
+        let (refresh_tx, refresh_rx) = bounded(1);
+        thread::spawn(move || run(badge, refresh_rx));
+        Self { refresh_tx }
+    }
+
+    /// Asks for the badge to be brought up to date. Never blocks.
+    pub fn refresh(&self) {
+        let _ = self.refresh_tx.try_send(());
+    }
+}
+
+fn run(mut badge: Badge, refresh_rx: Receiver<()>) {
+    badge.poke(Instant::now());
+    loop {
+        let now = Instant::now();
+        let timeout = badge.debounce.wait(now).unwrap_or(POLL_INTERVAL);
+        match refresh_rx.recv_timeout(timeout) {
+            Ok(()) => badge.poke(Instant::now()),
+            // Nothing was pending, so this was the poll.
+            Err(RecvTimeoutError::Timeout) if badge.debounce.wait(now).is_none() => {
+                badge.poke(Instant::now())
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        badge.tick(Instant::now());
+    }
+}
+
+/// Draws the badge onto the app's tray icon and, on macOS, the dock icon.
+pub struct TauriBadgeRenderer {
+    app: AppHandle,
+    base: RgbaImage,
+    overlays: PathBuf,
+}
+
+impl TauriBadgeRenderer {
+    pub fn new(app: AppHandle) -> Option<Self> {
+        let icon = app.default_window_icon()?;
+        let base = RgbaImage::from_raw(icon.width(), icon.height(), icon.rgba().to_vec())?;
+        let overlays = app
+            .path()
+            .resource_dir()
+            .map(|dir| dir.join("badges"))
+            .ok()
+            .filter(|dir| dir.exists())
+            .unwrap_or_else(|| PathBuf::from("src-tauri/resources/badges"));
+        Some(Self {
+            app,
+            base,
+            overlays,
+        })
+    }
+
+    /// The base icon with `overlay` in its top right corner, a little over
+    /// half its width.
+    fn compose(&self, overlay: &str) -> Result<RgbaImage> {
+        let badge = image::open(self.overlays.join(overlay))?.to_rgba8();
+        let size = (self.base.width() * 5 / 9).max(1);
+        let badge = imageops::resize(&badge, size, size, FilterType::Triangle);
+        let mut icon = self.base.clone();
+        imageops::overlay(&mut icon, &badge, (self.base.width() - size) as i64, 0);
+        Ok(icon)
+    }
+}
+
+impl BadgeRenderer for TauriBadgeRenderer {
+    fn render(&self, state: &BadgeState) -> Result<()> {
+        let tray = self
+            .app
+            .tray_by_id(TRAY_ID)
+            .ok_or_else(|| anyhow!("no tray icon"))?;
+        let icon = match state.icon.overlay() {
+            Some(overlay) => self.compose(&overlay)?,
+            None => self.base.clone(),
+        };
+        let (width, height) = icon.dimensions();
+        tray.set_icon(Some(Image::new(icon.as_raw(), width, height)))?;
+        tray.set_tooltip(Some(state.tooltip()))?;
+        #[cfg(target_os = "macos")]
+        if let Some(window) = self.app.get_webview_window("main") {
+            let total = state.counts.total();
+            window.set_badge_count((total > 0).then_some(total as i64))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    fn counts(approvals: usize, conflicts: usize, errors: usize) -> BadgeCounts {
+        BadgeCounts {
+            approvals,
+            conflicts,
+            errors,
+        }
+    }
+
+    /// Remembers what it was asked to draw, failing while `fail` is set.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        drawn: Arc<Mutex<Vec<BadgeState>>>,
+        fail: Arc<Mutex<bool>>,
+    }
+
+    impl BadgeRenderer for Recorder {
+        fn render(&self, state: &BadgeState) -> Result<()> {
+            if *self.fail.lock().unwrap() {
+                return Err(anyhow!("tray icons can't be swapped here"));
+            }
+            self.drawn.lock().unwrap().push(state.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn icons_cover_one_to_nine_and_cap_after() {
+        assert_eq!(BadgeIcon::for_count(0), BadgeIcon::Plain);
+        assert_eq!(BadgeIcon::for_count(1), BadgeIcon::Count(1));
+        assert_eq!(BadgeIcon::for_count(9), BadgeIcon::Count(9));
+        assert_eq!(BadgeIcon::for_count(10), BadgeIcon::Overflow);
+        assert_eq!(BadgeIcon::for_count(4_000), BadgeIcon::Overflow);
+
+        assert_eq!(BadgeIcon::Plain.overlay(), None);
+        assert_eq!(
+            BadgeIcon::Count(3).overlay().as_deref(),
+            Some("badge-3.png")
+        );
+        assert_eq!(
+            BadgeIcon::Overflow.overlay().as_deref(),
+            Some("badge-9plus.png")
+        );
+        let bundled = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/badges");
+        for count in 1..=10 {
+            let overlay = BadgeIcon::for_count(count).overlay().unwrap();
+            assert!(bundled.join(&overlay).exists(), "{overlay}");
+        }
+    }
+
+    #[test]
+    fn the_icon_counts_approvals_conflicts_and_errors_together() {
+        let state = BadgeState::new(counts(2, 1, 4));
+        assert_eq!(state.icon, BadgeIcon::Count(7));
+        assert_eq!(
+            state.tooltip(),
+            "File Dispatch: 2 to approve, 1 conflicts, 4 new errors"
+        );
+        assert_eq!(BadgeState::new(counts(6, 0, 6)).icon, BadgeIcon::Overflow);
+        assert_eq!(BadgeState::new(counts(0, 0, 0)).tooltip(), "File Dispatch");
+    }
+
+    #[test]
+    fn redraws_wait_for_changes_to_settle_and_skip_repeats() {
+        let start = Instant::now();
+        let mut debounce = BadgeDebounce::new(Duration::from_millis(500));
+        assert_eq!(debounce.wait(start), None);
+
+        debounce.poke(start);
+        debounce.poke(start + Duration::from_millis(300));
+        // The second change joins the first one's window.
+        assert_eq!(
+            debounce.wait(start + Duration::from_millis(300)),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            debounce.wait(start + Duration::from_millis(600)),
+            Some(Duration::ZERO)
+        );
+
+        let state = debounce.settle(counts(1, 0, 0)).unwrap();
+        assert_eq!(state.icon, BadgeIcon::Count(1));
+        debounce.shown(state);
+        assert_eq!(debounce.wait(start), None);
+
+        // Same counts, nothing to draw; a different mix of the same total
+        // changes the tooltip and the dock, so it's drawn.
+        debounce.poke(start);
+        assert_eq!(debounce.settle(counts(1, 0, 0)), None);
+        assert!(debounce.settle(counts(0, 1, 0)).is_some());
+    }
+
+    #[test]
+    fn the_badge_is_drawn_once_counts_settle_and_failures_are_retried() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let recorder = Recorder::default();
+        let window = Duration::from_millis(500);
+        let mut badge = Badge::new(db.clone(), Box::new(recorder.clone()), window);
+        let start = Instant::now();
+
+        badge.poke(start);
+        badge.tick(start + Duration::from_millis(100));
+        assert!(recorder.drawn.lock().unwrap().is_empty());
+        badge.tick(start + window);
+        assert_eq!(recorder.drawn.lock().unwrap().len(), 1);
+        assert_eq!(recorder.drawn.lock().unwrap()[0].icon, BadgeIcon::Plain);
+
+        // Nothing changed: the next poll draws nothing.
+        badge.poke(start + window);
+        badge.tick(start + window * 2);
+        assert_eq!(recorder.drawn.lock().unwrap().len(), 1);
+
+        // A failed swap isn't remembered as shown, so it's tried again.
+        *recorder.fail.lock().unwrap() = true;
+        BadgeRepository::new(db.clone())
+            .acknowledge_errors(chrono::Utc::now() - chrono::Duration::minutes(1))
+            .unwrap();
+        let log = crate::storage::log_repo::LogRepository::new(db);
+        log.insert(crate::models::LogEntry {
+            id: String::new(),
+            rule_id: None,
+            rule_name: None,
+            file_path: "/inbox/a.pdf".to_string(),
+            action_type: "move".to_string(),
+            action_detail: None,
+            status: crate::models::LogStatus::Error,
+            error_message: Some("Permission denied".to_string()),
+            created_at: chrono::Utc::now(),
+        })
+        .unwrap();
+        badge.poke(start + window * 2);
+        badge.tick(start + window * 3);
+        assert!(badge.failing);
+        *recorder.fail.lock().unwrap() = false;
+        badge.poke(start + window * 3);
+        badge.tick(start + window * 4);
+        let drawn = recorder.drawn.lock().unwrap();
+        assert_eq!(drawn.len(), 2);
+        assert_eq!(drawn[1].icon, BadgeIcon::Count(1));
+        assert!(!badge.failing);
+    }
+}
@@ -0,0 +1,121 @@
+//! Files that come back after a rule moved them away, such as a document a
+//! sync client downloads again days after it was sorted out of the folder.
+//! They're only looked for when the hash-match check says the rule has seen
+//! the file before, and only believed when the copy it moved is still where
+//! the logs last saw it, unchanged.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use filedispatch_core::file_info::FileInfo;
+
+use crate::core::dispatch::DispatchTrace;
+use crate::core::duplicates::matches_recorded;
+use crate::core::manifest::{follow, Trail};
+use crate::models::{ActionDetails, LogEntry, LogStatus, OnReappear, Rule};
+use crate::storage::database::Database;
+use crate::storage::log_repo::LogRepository;
+use crate::storage::match_repo::MatchRepository;
+use crate::storage::undo_repo::UndoRepository;
+
+/// What the rule's history says about a file it has already matched.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reappearance {
+    /// The rule left the file where it is, or it was moved back.
+    NotMoved,
+    /// The rule moved it away, but the moved copy can't be vouched for: it's
+    /// gone, changed or was never hashed. The file is processed again.
+    Unverified,
+    Returned(Boomerang),
+}
+
+/// A file back where a rule first found it while the copy it moved remains.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Boomerang {
+    /// Where the earlier copy is now.
+    pub existing: PathBuf,
+    pub original_path: String,
+    pub processed_at: DateTime<Utc>,
+}
+
+/// Checks whether `info`, which `rule_id` already matched by hash, is a copy
+/// of a file the rule moved away. Costs a stat of the moved copy and, when
+/// that's the right size, one hash of it.
+pub fn find_reappearance(db: &Database, rule_id: &str, info: &FileInfo) -> Result<Reappearance> {
+    let Some(matched) = MatchRepository::new(db.clone()).latest_for_hash(rule_id, &info.hash)?
+    else {
+        return Ok(Reappearance::NotMoved);
+    };
+    let Trail {
+        current, last_move, ..
+    } = follow(&LogRepository::new(db.clone()), rule_id, &matched)?;
+    let existing = PathBuf::from(&current);
+    let Some(last_move) = last_move.filter(|_| existing != info.path) else {
+        return Ok(Reappearance::NotMoved);
+    };
+
+    match std::fs::metadata(&existing) {
+        Ok(meta) if meta.is_file() && meta.len() == info.size => {}
+        _ => return Ok(Reappearance::Unverified),
+    }
+    let recorded = UndoRepository::new(db.clone()).get_by_log(&last_move)?;
+    let returned = recorded.is_some_and(|entry| {
+        entry.hash.is_some_and(|hash| {
+            matches_recorded(&existing, &hash, entry.hash_scheme.as_deref()).unwrap_or(false)
+        })
+    });
+    if !returned {
+        return Ok(Reappearance::Unverified);
+    }
+    Ok(Reappearance::Returned(Boomerang {
+        existing,
+        original_path: matched.file_path,
+        processed_at: matched.matched_at,
+    }))
+}
+
+/// Logs that the rule didn't run on a returned file, naming the earlier copy
+/// and what `on_reappear` did instead.
+pub fn log_reappearance(
+    repo: &LogRepository,
+    rule: &Rule,
+    info: &FileInfo,
+    boomerang: &Boomerang,
+    on_reappear: &OnReappear,
+    trace: &DispatchTrace,
+) -> Result<()> {
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "existing_path".to_string(),
+        boomerang.existing.to_string_lossy().to_string(),
+    );
+    metadata.insert(
+        "first_processed_at".to_string(),
+        boomerang.processed_at.to_rfc3339(),
+    );
+    metadata.insert("first_path".to_string(), boomerang.original_path.clone());
+    metadata.insert("on_reappear".to_string(), on_reappear.as_str().to_string());
+    trace.apply(&mut metadata);
+    repo.insert(LogEntry {
+        id: String::new(),
+        rule_id: Some(rule.id.clone()),
+        rule_name: Some(rule.name.clone()),
+        file_path: info.path.to_string_lossy().to_string(),
+        action_type: "reappeared".to_string(),
+        action_detail: Some(ActionDetails {
+            source_path: info.path.to_string_lossy().to_string(),
+            destination_path: None,
+            metadata,
+        }),
+        status: LogStatus::Skipped,
+        error_message: Some(format!(
+            "Already sorted to {} on {}",
+            boomerang.existing.display(),
+            boomerang.processed_at.format("%Y-%m-%d")
+        )),
+        created_at: Utc::now(),
+    })?;
+    Ok(())
+}
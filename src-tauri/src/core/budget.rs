@@ -0,0 +1,301 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use filedispatch_core::file_info::FileInfo;
+use filedispatch_core::patterns::format_size;
+use filedispatch_core::timezone::{local_date_in, parse_timezone};
+
+use crate::core::engine::action_type_to_string;
+use crate::core::executor::{ActionOutcome, ActionResultStatus};
+use crate::models::{
+    ActionType, BudgetExhausted, BudgetStatus, LogEntry, LogStatus, Rule, Settings,
+};
+use crate::storage::budget_repo::BudgetRepository;
+use crate::storage::database::Database;
+use crate::storage::log_repo::LogRepository;
+
+pub const BUDGET_EXHAUSTED_EVENT: &str = "rule://budget-exhausted";
+
+/// What a rule's monthly byte budget says about a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetDecision {
+    Allowed,
+    /// The file would take the rule over its budget and was skipped. Holds
+    /// the notice to send when this is the first such file of the month.
+    Exhausted(Option<BudgetExhausted>),
+}
+
+/// The calendar month of `now` in the default time zone, as `YYYY-MM`.
+pub fn month_of(now: DateTime<Utc>, settings: &Settings) -> String {
+    let zone = settings
+        .default_timezone
+        .as_deref()
+        .filter(|zone| !zone.trim().is_empty())
+        .and_then(|zone| parse_timezone(zone).ok());
+    local_date_in(now, zone).format("%Y-%m").to_string()
+}
+
+/// Checks `info` against the rule's budget before any action runs, so a file
+/// that doesn't fit is skipped whole rather than half transferred. A skip is
+/// logged.
+pub fn check_budget(
+    db: &Database,
+    rule: &Rule,
+    info: &FileInfo,
+    settings: &Settings,
+    now: DateTime<Utc>,
+) -> Result<BudgetDecision> {
+    let Some(budget) = rule.monthly_byte_budget else {
+        return Ok(BudgetDecision::Allowed);
+    };
+    let repo = BudgetRepository::new(db.clone());
+    let month = month_of(now, settings);
+    let used = repo.used_bytes(&rule.id, &month)?;
+    if used.saturating_add(info.size) <= budget {
+        return Ok(BudgetDecision::Allowed);
+    }
+
+    LogRepository::new(db.clone()).insert(LogEntry {
+        id: String::new(),
+        rule_id: Some(rule.id.clone()),
+        rule_name: Some(rule.name.clone()),
+        file_path: info.path.to_string_lossy().to_string(),
+        action_type: "budget".to_string(),
+        action_detail: None,
+        status: LogStatus::Skipped,
+        error_message: Some(format!(
+            "Monthly budget of {} used up: {} transferred, file is {}",
+            format_size(budget, ""),
+            format_size(used, ""),
+            format_size(info.size, "")
+        )),
+        created_at: now,
+    })?;
+    let notice = repo
+        .note_exhausted(&rule.id, &month)?
+        .then(|| BudgetExhausted {
+            rule_id: rule.id.clone(),
+            rule_name: rule.name.clone(),
+            month,
+            budget_bytes: budget,
+            used_bytes: used,
+        });
+    Ok(BudgetDecision::Exhausted(notice))
+}
+
+/// Adds the file to the rule's monthly totals once for each move, copy or
+/// archive that succeeded. Kept for every rule, budget or not.
+pub fn record_transfers(
+    db: &Database,
+    rule: &Rule,
+    size: u64,
+    outcomes: &[ActionOutcome],
+    settings: &Settings,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let repo = BudgetRepository::new(db.clone());
+    let month = month_of(now, settings);
+    for outcome in outcomes {
+        let transfer = matches!(
+            outcome.action_type,
+            ActionType::Move
+                | ActionType::Copy
+                | ActionType::SortIntoSubfolder
+                | ActionType::Archive
+        );
+        if transfer && outcome.status == ActionResultStatus::Success {
+            let action_type = action_type_to_string(&outcome.action_type);
+            repo.record(&rule.id, &month, &action_type, size)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn budget_status(
+    db: &Database,
+    rule: &Rule,
+    settings: &Settings,
+    now: DateTime<Utc>,
+) -> Result<BudgetStatus> {
+    let month = month_of(now, settings);
+    let usage = BudgetRepository::new(db.clone()).usage(&rule.id, &month)?;
+    Ok(BudgetStatus {
+        rule_id: rule.id.clone(),
+        used_bytes: usage.iter().map(|entry| entry.bytes).sum(),
+        budget_bytes: rule.monthly_byte_budget,
+        month,
+        usage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ByteUsage, ConditionGroup, MatchType};
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::rule_repo::RuleRepository;
+    use chrono::TimeZone;
+    use filedispatch_core::SyntheticFile;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    fn fixture(dir: &std::path::Path, budget: u64) -> (Database, Rule) {
+        let db = Database::new_with_path(dir.join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.join("archive").to_string_lossy(), "Archive")
+            .unwrap();
+        let rule = RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id.clone(),
+                name: "To cloud".to_string(),
+                enabled: true,
+                stop_processing: false,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![],
+                },
+                actions: vec![],
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: Some(budget),
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap();
+        (db, rule)
+    }
+
+    fn file(name: &str, size: u64) -> FileInfo {
+        SyntheticFile {
+            size,
+            ..SyntheticFile::new(PathBuf::from("/archive").join(name))
+        }
+        .info()
+    }
+
+    fn succeeded(action_type: ActionType) -> ActionOutcome {
+        ActionOutcome {
+            action_type,
+            status: ActionResultStatus::Success,
+            details: None,
+            error: None,
+            error_kind: None,
+        }
+    }
+
+    #[test]
+    fn transfers_add_up_per_action_type() {
+        let dir = tempdir().unwrap();
+        let (db, rule) = fixture(dir.path(), 1000);
+        let settings = Settings::default();
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap();
+
+        let copy_and_move = [succeeded(ActionType::Copy), succeeded(ActionType::Move)];
+        record_transfers(&db, &rule, 100, &copy_and_move, &settings, now).unwrap();
+        let failed = ActionOutcome {
+            status: ActionResultStatus::Error,
+            ..succeeded(ActionType::Copy)
+        };
+        let not_a_transfer = succeeded(ActionType::Rename);
+        record_transfers(&db, &rule, 50, &[failed, not_a_transfer], &settings, now).unwrap();
+        let copy = [succeeded(ActionType::Copy)];
+        record_transfers(&db, &rule, 30, &copy, &settings, now).unwrap();
+
+        let status = budget_status(&db, &rule, &settings, now).unwrap();
+        assert_eq!(status.month, "2024-03");
+        assert_eq!(status.budget_bytes, Some(1000));
+        assert_eq!(status.used_bytes, 230);
+        assert_eq!(
+            status.usage,
+            [
+                ByteUsage {
+                    action_type: "copy".to_string(),
+                    bytes: 130,
+                    count: 2,
+                },
+                ByteUsage {
+                    action_type: "move".to_string(),
+                    bytes: 100,
+                    count: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_file_that_would_go_over_is_skipped_whole() {
+        let dir = tempdir().unwrap();
+        let (db, rule) = fixture(dir.path(), 100);
+        let settings = Settings::default();
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap();
+        let copy = [succeeded(ActionType::Copy)];
+        record_transfers(&db, &rule, 60, &copy, &settings, now).unwrap();
+
+        let fits = check_budget(&db, &rule, &file("exact.bin", 40), &settings, now).unwrap();
+        assert_eq!(fits, BudgetDecision::Allowed);
+        let over = check_budget(&db, &rule, &file("big.bin", 41), &settings, now).unwrap();
+        assert!(matches!(over, BudgetDecision::Exhausted(_)));
+
+        let logs = LogRepository::new(db.clone()).list(10, 0).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].action_type, "budget");
+        assert_eq!(logs[0].status, LogStatus::Skipped);
+        assert!(logs[0].file_path.ends_with("big.bin"));
+    }
+
+    #[test]
+    fn the_notice_is_sent_once_a_month() {
+        let dir = tempdir().unwrap();
+        let (db, rule) = fixture(dir.path(), 10);
+        let settings = Settings::default();
+        let march = Utc.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap();
+        let april = Utc.with_ymd_and_hms(2024, 4, 10, 12, 0, 0).unwrap();
+        let big = file("big.bin", 11);
+
+        let first = check_budget(&db, &rule, &big, &settings, march).unwrap();
+        let BudgetDecision::Exhausted(Some(notice)) = first else {
+            panic!("expected a notice, got {first:?}");
+        };
+        assert_eq!(notice.month, "2024-03");
+        assert_eq!(notice.budget_bytes, 10);
+        let again = check_budget(&db, &rule, &big, &settings, march).unwrap();
+        assert_eq!(again, BudgetDecision::Exhausted(None));
+
+        let next_month = check_budget(&db, &rule, &big, &settings, april).unwrap();
+        assert!(matches!(next_month, BudgetDecision::Exhausted(Some(_))));
+    }
+
+    #[test]
+    fn usage_starts_over_in_a_new_month_of_the_default_zone() {
+        let dir = tempdir().unwrap();
+        let (db, rule) = fixture(dir.path(), 100);
+        let settings = Settings {
+            default_timezone: Some("America/New_York".to_string()),
+            ..Settings::default()
+        };
+        // 22:00 on March 31st in New York.
+        let march = Utc.with_ymd_and_hms(2024, 4, 1, 2, 0, 0).unwrap();
+        let april = Utc.with_ymd_and_hms(2024, 4, 1, 5, 0, 0).unwrap();
+        let copy = [succeeded(ActionType::Copy)];
+        record_transfers(&db, &rule, 100, &copy, &settings, march).unwrap();
+
+        let small = file("small.bin", 1);
+        let still_march = check_budget(&db, &rule, &small, &settings, march).unwrap();
+        assert!(matches!(still_march, BudgetDecision::Exhausted(_)));
+        let status = budget_status(&db, &rule, &settings, april).unwrap();
+        assert_eq!((status.month.as_str(), status.used_bytes), ("2024-04", 0));
+        let fits = check_budget(&db, &rule, &small, &settings, april).unwrap();
+        assert_eq!(fits, BudgetDecision::Allowed);
+    }
+}
@@ -0,0 +1,443 @@
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+
+use crate::core::health::validate_rule;
+use crate::models::{Action, BulkRuleEdit, ConflictResolution, RuleEditSummary, RuleFieldChange};
+use crate::storage::database::Database;
+use crate::storage::rule_repo::RuleRepository;
+use crate::utils::platform::normalize_user_path;
+
+/// Applies `edit` to each rule in `rule_ids`. Every edited rule is validated
+/// before anything is written and they're saved in one transaction, so an edit
+/// that would break one rule leaves all of them untouched.
+pub fn bulk_edit(
+    db: &Database,
+    rule_ids: &[String],
+    edit: &BulkRuleEdit,
+) -> Result<Vec<RuleEditSummary>> {
+    let replacement = match edit {
+        BulkRuleEdit::ReplaceInDestinations {
+            find,
+            replace,
+            regex,
+        } => Some(Replacement::new(find, replace, *regex)?),
+        _ => None,
+    };
+
+    let repo = RuleRepository::new(db.clone());
+    let mut edited = Vec::new();
+    let mut summaries = Vec::new();
+    for id in rule_ids {
+        let mut rule = repo.get(id)?.ok_or_else(|| anyhow!("Rule {id} not found"))?;
+        let mut changes = Vec::new();
+        match edit {
+            BulkRuleEdit::Enable => set_flag(&mut changes, "enabled", &mut rule.enabled, true),
+            BulkRuleEdit::Disable => set_flag(&mut changes, "enabled", &mut rule.enabled, false),
+            BulkRuleEdit::SetStopProcessing { value } => {
+                set_flag(&mut changes, "stopProcessing", &mut rule.stop_processing, *value)
+            }
+            BulkRuleEdit::ReplaceInDestinations { .. } => {
+                let replacement = replacement.as_ref().expect("built above");
+                for (index, action) in rule.actions.iter_mut().enumerate() {
+                    let Some((field, value)) = destination_mut(action) else {
+                        continue;
+                    };
+                    let after = replacement.apply(value);
+                    if after == *value {
+                        continue;
+                    }
+                    if after.trim().is_empty() {
+                        bail!(
+                            "Rule \"{}\": the replacement leaves actions[{index}].{field} empty",
+                            rule.name
+                        );
+                    }
+                    changes.push(RuleFieldChange {
+                        field: format!("actions[{index}].{field}"),
+                        before: std::mem::replace(value, after.clone()),
+                        after,
+                    });
+                }
+            }
+            BulkRuleEdit::SetConflictResolution { policy } => {
+                for (index, action) in rule.actions.iter_mut().enumerate() {
+                    let Some(current) = conflict_policy_mut(action) else {
+                        continue;
+                    };
+                    if *current == *policy {
+                        continue;
+                    }
+                    changes.push(RuleFieldChange {
+                        field: format!("actions[{index}].onConflict"),
+                        before: policy_name(current).to_string(),
+                        after: policy_name(policy).to_string(),
+                    });
+                    *current = policy.clone();
+                }
+            }
+        }
+        validate_rule(&rule).map_err(|err| anyhow!(err))?;
+
+        let warnings = if changes.is_empty() {
+            vec!["Nothing to change".to_string()]
+        } else {
+            missing_destinations(&changes)
+        };
+        let changed = !changes.is_empty();
+        summaries.push(RuleEditSummary {
+            rule_id: rule.id.clone(),
+            rule_name: rule.name.clone(),
+            changes,
+            warnings,
+        });
+        if changed {
+            edited.push(rule);
+        }
+    }
+
+    repo.update_many(&edited)?;
+    Ok(summaries)
+}
+
+struct Replacement<'a> {
+    find: &'a str,
+    replace: &'a str,
+    regex: Option<Regex>,
+}
+
+impl<'a> Replacement<'a> {
+    fn new(find: &'a str, replace: &'a str, regex: bool) -> Result<Self> {
+        if find.is_empty() {
+            bail!("Enter the text to find");
+        }
+        let regex = if regex {
+            Some(Regex::new(find).map_err(|err| anyhow!("Invalid regex: {err}"))?)
+        } else {
+            None
+        };
+        Ok(Self {
+            find,
+            replace,
+            regex,
+        })
+    }
+
+    fn apply(&self, value: &str) -> String {
+        match &self.regex {
+            Some(regex) => regex.replace_all(value, self.replace).into_owned(),
+            None => value.replace(self.find, self.replace),
+        }
+    }
+}
+
+fn set_flag(changes: &mut Vec<RuleFieldChange>, field: &str, flag: &mut bool, value: bool) {
+    if *flag != value {
+        changes.push(RuleFieldChange {
+            field: field.to_string(),
+            before: flag.to_string(),
+            after: value.to_string(),
+        });
+        *flag = value;
+    }
+}
+
+/// The field naming where the action puts the file, for the actions a
+/// destination replacement touches.
+fn destination_mut(action: &mut Action) -> Option<(&'static str, &mut String)> {
+    match action {
+        Action::Move(action) => Some(("destination", &mut action.destination)),
+        Action::Copy(action) => Some(("destination", &mut action.destination)),
+        Action::SortIntoSubfolder(action) => Some(("destination", &mut action.destination)),
+        Action::Archive(action) => Some(("destination", &mut action.destination)),
+        Action::Rename(action) => Some(("pattern", &mut action.pattern)),
+        _ => None,
+    }
+}
+
+fn conflict_policy_mut(action: &mut Action) -> Option<&mut ConflictResolution> {
+    match action {
+        Action::Move(action) => Some(&mut action.on_conflict),
+        Action::Copy(action) => Some(&mut action.on_conflict),
+        Action::SortIntoSubfolder(action) => Some(&mut action.on_conflict),
+        Action::Rename(action) => Some(&mut action.on_conflict),
+        _ => None,
+    }
+}
+
+fn policy_name(policy: &ConflictResolution) -> &'static str {
+    match policy {
+        ConflictResolution::Rename => "rename",
+        ConflictResolution::Replace => "replace",
+        ConflictResolution::Skip => "skip",
+        ConflictResolution::Ask => "ask",
+        ConflictResolution::UniqueSuffix => "uniqueSuffix",
+    }
+}
+
+/// New destinations without pattern tokens that don't exist on disk, which
+/// usually means a typo in the replacement.
+fn missing_destinations(changes: &[RuleFieldChange]) -> Vec<String> {
+    changes
+        .iter()
+        .filter(|change| change.field.ends_with(".destination") && !change.after.contains('{'))
+        .filter(|change| !normalize_user_path(&change.after).exists())
+        .map(|change| format!("{}: {} doesn't exist yet", change.field, change.after))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        ArchiveAction, ArchiveFormat, ConditionGroup, CopyAction, MatchType, MoveAction,
+        NotifyAction, RenameAction, Rule, SortAction,
+    };
+    use crate::storage::folder_repo::FolderRepository;
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    fn add_rule(db: &Database, name: &str, actions: Vec<Action>) -> Rule {
+        let folder = FolderRepository::new(db.clone())
+            .create(&format!("/watched/{name}"), name)
+            .unwrap();
+        RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id,
+                name: name.to_string(),
+                enabled: true,
+                stop_processing: true,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![],
+                },
+                actions,
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap()
+    }
+
+    fn move_to(destination: &str) -> Action {
+        Action::Move(MoveAction {
+            destination: destination.to_string(),
+            on_conflict: ConflictResolution::Rename,
+            skip_duplicates: false,
+            destination_permissions: Default::default(),
+            quarantine: Default::default(),
+            bring_companions: None,
+            auto_partition: None,
+        })
+    }
+
+    fn rename(pattern: &str, on_conflict: ConflictResolution) -> Action {
+        Action::Rename(RenameAction {
+            pattern: pattern.to_string(),
+            on_conflict,
+        })
+    }
+
+    fn notify() -> Action {
+        Action::Notify(NotifyAction {
+            message: "~/Archive/{name}".to_string(),
+            sound: None,
+            generate_thumbnail: false,
+        })
+    }
+
+    fn destinations(db: &Database, id: &str) -> Vec<String> {
+        let rule = RuleRepository::new(db.clone()).get(id).unwrap().unwrap();
+        rule.actions
+            .iter()
+            .map(|action| match action {
+                Action::Move(a) => a.destination.clone(),
+                Action::Copy(a) => a.destination.clone(),
+                Action::SortIntoSubfolder(a) => a.destination.clone(),
+                Action::Archive(a) => a.destination.clone(),
+                Action::Rename(a) => a.pattern.clone(),
+                Action::Notify(a) => a.message.clone(),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    fn replace(find: &str, replace: &str, regex: bool) -> BulkRuleEdit {
+        BulkRuleEdit::ReplaceInDestinations {
+            find: find.to_string(),
+            replace: replace.to_string(),
+            regex,
+        }
+    }
+
+    #[test]
+    fn prefix_replacement_covers_every_destination_action() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let invoices = add_rule(
+            &db,
+            "Invoices",
+            vec![
+                rename("~/Archive-{name}", ConflictResolution::Skip),
+                move_to("~/Archive/Invoices/{year}"),
+                notify(),
+            ],
+        );
+        let photos = add_rule(
+            &db,
+            "Photos",
+            vec![
+                Action::Copy(CopyAction {
+                    destination: "~/Archive/Photos".to_string(),
+                    on_conflict: ConflictResolution::Rename,
+                    skip_duplicates: false,
+                    use_reflink: None,
+                    destination_permissions: Default::default(),
+                    quarantine: Default::default(),
+                    bring_companions: None,
+                    auto_partition: None,
+                }),
+                Action::SortIntoSubfolder(SortAction {
+                    destination: "~/Archive/Sorted/{kind}".to_string(),
+                    on_conflict: ConflictResolution::Rename,
+                    destination_permissions: Default::default(),
+                    quarantine: Default::default(),
+                    bring_companions: None,
+                    auto_partition: None,
+                }),
+                Action::Archive(ArchiveAction {
+                    destination: "~/Archive/Zips/{name}.zip".to_string(),
+                    format: ArchiveFormat::Zip,
+                    delete_after: false,
+                    encryption: None,
+                }),
+            ],
+        );
+        let ids = vec![invoices.id.clone(), photos.id.clone()];
+
+        bulk_edit(&db, &ids, &replace("~/Archive/", "/Volumes/NAS/Archive/", false)).unwrap();
+        assert_eq!(
+            destinations(&db, &invoices.id),
+            vec![
+                "~/Archive-{name}",
+                "/Volumes/NAS/Archive/Invoices/{year}",
+                "~/Archive/{name}",
+            ]
+        );
+        assert_eq!(
+            destinations(&db, &photos.id),
+            vec![
+                "/Volumes/NAS/Archive/Photos",
+                "/Volumes/NAS/Archive/Sorted/{kind}",
+                "/Volumes/NAS/Archive/Zips/{name}.zip",
+            ]
+        );
+
+        // Capture groups carry over with a regex.
+        bulk_edit(&db, &ids, &replace(r"^/Volumes/NAS/Archive/(\w+)", "/mnt/nas/$1", true))
+            .unwrap();
+        assert_eq!(destinations(&db, &invoices.id)[1], "/mnt/nas/Invoices/{year}");
+        assert_eq!(destinations(&db, &photos.id)[2], "/mnt/nas/Zips/{name}.zip");
+    }
+
+    #[test]
+    fn replacement_breaking_one_rule_saves_none() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let photos = add_rule(&db, "Photos", vec![move_to("~/Archive/Photos")]);
+        let archive = add_rule(&db, "Archive", vec![move_to("~/Archive")]);
+        let ids = vec![photos.id.clone(), archive.id.clone()];
+
+        let err = bulk_edit(&db, &ids, &replace("~/Archive", "", false)).unwrap_err();
+        assert!(err.to_string().contains("Rule \"Archive\""));
+        assert_eq!(destinations(&db, &photos.id), vec!["~/Archive/Photos"]);
+        assert_eq!(destinations(&db, &archive.id), vec!["~/Archive"]);
+
+        let err = bulk_edit(&db, &ids, &replace("^.*$", "", true)).unwrap_err();
+        assert!(err.to_string().contains("empty"));
+        assert_eq!(destinations(&db, &photos.id), vec!["~/Archive/Photos"]);
+    }
+
+    #[test]
+    fn summary_lists_exactly_what_changed() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let existing = dir.path().join("existing");
+        std::fs::create_dir_all(&existing).unwrap();
+        let old_b = dir.path().join("old").join("b");
+        let rule = add_rule(
+            &db,
+            "Mixed",
+            vec![
+                move_to("/old/a"),
+                notify(),
+                rename("{name}", ConflictResolution::Replace),
+                move_to(&old_b.to_string_lossy()),
+            ],
+        );
+        let ids = vec![rule.id.clone()];
+
+        let summaries = bulk_edit(
+            &db,
+            &ids,
+            &BulkRuleEdit::SetConflictResolution {
+                policy: ConflictResolution::Replace,
+            },
+        )
+        .unwrap();
+        let change = |field: &str, before: &str, after: &str| RuleFieldChange {
+            field: field.to_string(),
+            before: before.to_string(),
+            after: after.to_string(),
+        };
+        assert_eq!(
+            summaries,
+            vec![RuleEditSummary {
+                rule_id: rule.id.clone(),
+                rule_name: "Mixed".to_string(),
+                changes: vec![
+                    change("actions[0].onConflict", "rename", "replace"),
+                    change("actions[3].onConflict", "rename", "replace"),
+                ],
+                warnings: vec![],
+            }]
+        );
+
+        let summaries = bulk_edit(&db, &ids, &BulkRuleEdit::Enable).unwrap();
+        assert!(summaries[0].changes.is_empty());
+        assert_eq!(summaries[0].warnings, vec!["Nothing to change"]);
+        let summaries = bulk_edit(&db, &ids, &BulkRuleEdit::Disable).unwrap();
+        assert_eq!(summaries[0].changes, vec![change("enabled", "true", "false")]);
+        assert!(!RuleRepository::new(db.clone()).get(&rule.id).unwrap().unwrap().enabled);
+
+        let old_root = dir.path().join("old").to_string_lossy().to_string();
+        let new_root = existing.to_string_lossy().to_string();
+        let summaries = bulk_edit(&db, &ids, &replace(&old_root, &new_root, false)).unwrap();
+        assert_eq!(
+            summaries[0].changes,
+            vec![change(
+                "actions[3].destination",
+                &format!("{old_root}/b"),
+                &format!("{new_root}/b"),
+            )]
+        );
+        // The new destination's parent exists but the folder itself doesn't.
+        assert_eq!(
+            summaries[0].warnings,
+            vec![format!("actions[3].destination: {new_root}/b doesn't exist yet")]
+        );
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use directories::ProjectDirs;
+use filedispatch_core::file_info::FileInfo;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::core::content::{resolve_contents, ContentCache};
+use crate::core::ocr::OcrManager;
+use crate::models::{ContentSource, Settings};
+
+pub use filedispatch_core::evaluate::Prediction;
+
+/// Bump whenever the on-disk model layout changes; older models must be retrained.
+pub const CLASSIFIER_MODEL_VERSION: u32 = 1;
+
+/// Only the first chunk of each document contributes to the model.
+const MAX_DOCUMENT_CHARS: usize = 100_000;
+/// Terms kept per category centroid after training.
+const MAX_CENTROID_TERMS: usize = 2_000;
+/// Category folders are scanned this deep at most.
+const MAX_CATEGORY_DEPTH: usize = 4;
+
+static ACTIVE_MODEL: Lazy<Mutex<ModelSlot>> = Lazy::new(|| Mutex::new(ModelSlot::default()));
+
+#[derive(Default)]
+struct ModelSlot {
+    loaded: bool,
+    model: Option<Arc<ClassifierModel>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassifierModel {
+    pub version: u32,
+    pub trained_at: DateTime<Utc>,
+    pub document_count: usize,
+    pub idf: HashMap<String, f32>,
+    pub categories: Vec<CategoryCentroid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryCentroid {
+    pub name: String,
+    pub document_count: usize,
+    pub weights: HashMap<String, f32>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainProgress {
+    pub category: String,
+    pub processed: usize,
+    pub skipped: usize,
+    pub current_file: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassifierSummary {
+    pub version: u32,
+    pub trained_at: DateTime<Utc>,
+    pub document_count: usize,
+    pub categories: Vec<CategorySummary>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategorySummary {
+    pub name: String,
+    pub document_count: usize,
+}
+
+impl ClassifierModel {
+    pub fn summary(&self) -> ClassifierSummary {
+        ClassifierSummary {
+            version: self.version,
+            trained_at: self.trained_at,
+            document_count: self.document_count,
+            categories: self
+                .categories
+                .iter()
+                .map(|category| CategorySummary {
+                    name: category.name.clone(),
+                    document_count: category.document_count,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the closest category by cosine similarity between the document's
+    /// TF-IDF vector and each category centroid.
+    pub fn predict(&self, text: &str) -> Option<Prediction> {
+        let counts = term_counts(text);
+        let vector = normalize(weigh(&counts, &self.idf));
+        if vector.is_empty() {
+            return None;
+        }
+
+        self.categories
+            .iter()
+            .map(|category| Prediction {
+                category: category.name.clone(),
+                score: dot(&vector, &category.weights),
+            })
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+    }
+}
+
+/// Accumulates per-document term counts; IDF needs the full corpus, so
+/// weighting happens in `build`.
+#[derive(Default)]
+pub struct ClassifierTrainer {
+    documents: Vec<(String, HashMap<String, u32>)>,
+}
+
+impl ClassifierTrainer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_document(&mut self, category: &str, text: &str) -> bool {
+        let counts = term_counts(text);
+        if counts.is_empty() {
+            return false;
+        }
+        self.documents.push((category.to_string(), counts));
+        true
+    }
+
+    pub fn build(self) -> Result<ClassifierModel> {
+        let mut category_names: Vec<String> =
+            self.documents.iter().map(|(name, _)| name.clone()).collect();
+        category_names.sort();
+        category_names.dedup();
+        if category_names.len() < 2 {
+            return Err(anyhow!(
+                "At least two categories with readable documents are required"
+            ));
+        }
+
+        let total = self.documents.len() as f32;
+        let mut document_frequency: HashMap<String, u32> = HashMap::new();
+        for (_, counts) in &self.documents {
+            for term in counts.keys() {
+                *document_frequency.entry(term.clone()).or_insert(0) += 1;
+            }
+        }
+        let idf: HashMap<String, f32> = document_frequency
+            .into_iter()
+            .map(|(term, df)| (term, ((1.0 + total) / (1.0 + df as f32)).ln() + 1.0))
+            .collect();
+
+        let mut sums: HashMap<String, (usize, HashMap<String, f32>)> = HashMap::new();
+        for (category, counts) in &self.documents {
+            let vector = normalize(weigh(counts, &idf));
+            let entry = sums.entry(category.clone()).or_default();
+            entry.0 += 1;
+            for (term, weight) in vector {
+                *entry.1.entry(term).or_insert(0.0) += weight;
+            }
+        }
+
+        let categories = category_names
+            .into_iter()
+            .map(|name| {
+                let (document_count, weights) = sums.remove(&name).unwrap_or_default();
+                CategoryCentroid {
+                    name,
+                    document_count,
+                    weights: normalize(truncate_terms(weights, MAX_CENTROID_TERMS)),
+                }
+            })
+            .collect();
+
+        // Drop IDF entries that no centroid references to keep the model small.
+        let mut model = ClassifierModel {
+            version: CLASSIFIER_MODEL_VERSION,
+            trained_at: Utc::now(),
+            document_count: self.documents.len(),
+            idf,
+            categories,
+        };
+        let referenced: std::collections::HashSet<&String> = model
+            .categories
+            .iter()
+            .flat_map(|category| category.weights.keys())
+            .collect();
+        let idf = model
+            .idf
+            .iter()
+            .filter(|(term, _)| referenced.contains(term))
+            .map(|(term, weight)| (term.clone(), *weight))
+            .collect();
+        model.idf = idf;
+        Ok(model)
+    }
+}
+
+/// Trains a model from `root`, treating every immediate subfolder as a category.
+/// Files are streamed one at a time and capped per category.
+pub fn train_from_root(
+    root: &Path,
+    settings: &Settings,
+    ocr: &mut OcrManager,
+    max_files_per_category: usize,
+    mut progress: impl FnMut(&TrainProgress),
+) -> Result<ClassifierModel> {
+    if !root.is_dir() {
+        return Err(anyhow!("Category folder does not exist: {}", root.display()));
+    }
+
+    let mut category_dirs: Vec<PathBuf> = fs::read_dir(root)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && !is_hidden(path))
+        .collect();
+    category_dirs.sort();
+
+    let mut trainer = ClassifierTrainer::new();
+    let mut processed = 0;
+    let mut skipped = 0;
+
+    for dir in category_dirs {
+        let category = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut accepted = 0;
+
+        let files = walkdir::WalkDir::new(&dir)
+            .max_depth(MAX_CATEGORY_DEPTH)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file() && !is_hidden(entry.path()));
+
+        for entry in files {
+            if accepted >= max_files_per_category {
+                break;
+            }
+            let path = entry.path();
+            progress(&TrainProgress {
+                category: category.clone(),
+                processed,
+                skipped,
+                current_file: path.to_string_lossy().to_string(),
+            });
+
+            let text = FileInfo::from_path(path).ok().and_then(|info| {
+                let mut cache = ContentCache::default();
+                resolve_contents(&info, settings, ocr, &ContentSource::Auto, &mut cache, None)
+                    .ok()
+                    .flatten()
+            });
+            match text {
+                Some(text) if trainer.add_document(&category, &text) => {
+                    accepted += 1;
+                    processed += 1;
+                }
+                _ => skipped += 1,
+            }
+        }
+    }
+
+    progress(&TrainProgress {
+        category: String::new(),
+        processed,
+        skipped,
+        current_file: String::new(),
+    });
+
+    trainer.build()
+}
+
+pub fn default_model_path() -> Result<PathBuf> {
+    let proj = ProjectDirs::from("", "", "file-dispatch")
+        .ok_or_else(|| anyhow!("Unable to resolve data directory"))?;
+    let data_dir = proj.data_dir();
+    fs::create_dir_all(data_dir)?;
+    Ok(data_dir.join("classifier.json"))
+}
+
+pub fn save_model(model: &ClassifierModel, path: &Path) -> Result<()> {
+    let json = serde_json::to_vec(model)?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+pub fn load_model(path: &Path) -> Result<ClassifierModel> {
+    let bytes = fs::read(path)?;
+    let model: ClassifierModel = serde_json::from_slice(&bytes)?;
+    if model.version != CLASSIFIER_MODEL_VERSION {
+        return Err(anyhow!(
+            "Classifier model version {} is not supported (expected {}); retrain the classifier",
+            model.version,
+            CLASSIFIER_MODEL_VERSION
+        ));
+    }
+    Ok(model)
+}
+
+/// Returns the trained model, loading it from disk on first use.
+pub fn active_model() -> Option<Arc<ClassifierModel>> {
+    let mut slot = ACTIVE_MODEL.lock().ok()?;
+    if !slot.loaded {
+        slot.loaded = true;
+        slot.model = default_model_path()
+            .ok()
+            .filter(|path| path.exists())
+            .and_then(|path| match load_model(&path) {
+                Ok(model) => Some(Arc::new(model)),
+                Err(err) => {
+                    tracing::warn!("Failed to load classifier model: {}", err);
+                    None
+                }
+            });
+    }
+    slot.model.clone()
+}
+
+pub fn set_active_model(model: ClassifierModel) {
+    if let Ok(mut slot) = ACTIVE_MODEL.lock() {
+        slot.loaded = true;
+        slot.model = Some(Arc::new(model));
+    }
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    let end = text
+        .char_indices()
+        .nth(MAX_DOCUMENT_CHARS)
+        .map(|(idx, _)| idx)
+        .unwrap_or(text.len());
+    text[..end]
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| {
+            let len = word.chars().count();
+            (2..=32).contains(&len) && !word.chars().all(|c| c.is_ascii_digit())
+        })
+        .map(|word| word.to_lowercase())
+}
+
+fn term_counts(text: &str) -> HashMap<String, u32> {
+    let mut counts = HashMap::new();
+    for token in tokenize(text) {
+        *counts.entry(token).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn weigh(counts: &HashMap<String, u32>, idf: &HashMap<String, f32>) -> HashMap<String, f32> {
+    counts
+        .iter()
+        .filter_map(|(term, count)| {
+            idf.get(term)
+                .map(|weight| (term.clone(), (1.0 + (*count as f32).ln()) * weight))
+        })
+        .collect()
+}
+
+fn normalize(mut vector: HashMap<String, f32>) -> HashMap<String, f32> {
+    let norm = vector.values().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for value in vector.values_mut() {
+            *value /= norm;
+        }
+    }
+    vector
+}
+
+fn truncate_terms(weights: HashMap<String, f32>, limit: usize) -> HashMap<String, f32> {
+    if weights.len() <= limit {
+        return weights;
+    }
+    let mut entries: Vec<(String, f32)> = weights.into_iter().collect();
+    entries.sort_by(|a, b| b.1.total_cmp(&a.1));
+    entries.truncate(limit);
+    entries.into_iter().collect()
+}
+
+fn dot(a: &HashMap<String, f32>, b: &HashMap<String, f32>) -> f32 {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    small
+        .iter()
+        .filter_map(|(term, weight)| large.get(term).map(|other| weight * other))
+        .sum()
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy().starts_with('.'))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filedispatch_core::evaluate::prediction_matches;
+    use tempfile::tempdir;
+
+    const INVOICES: &[&str] = &[
+        "Invoice number 1042. Amount due 250 USD. Payment terms net 30. Bill to Acme Corp.",
+        "Tax invoice for consulting services. Total amount due including VAT. Please remit payment.",
+        "Invoice date and due date listed below. Balance due on receipt. Thank you for your payment.",
+        "Billing statement: invoice total, subtotal, tax, amount paid and outstanding balance.",
+    ];
+
+    const RECIPES: &[&str] = &[
+        "Preheat the oven to 180 degrees. Mix flour, sugar and butter, then bake for 25 minutes.",
+        "Ingredients: two eggs, a cup of milk, flour. Whisk and fry the pancakes in butter.",
+        "Simmer the tomato sauce with garlic and basil. Boil the pasta and stir in the sauce.",
+        "Chop the onions, saute in olive oil, add rice and stock, stir until creamy.",
+    ];
+
+    fn trained_model() -> ClassifierModel {
+        let mut trainer = ClassifierTrainer::new();
+        for doc in INVOICES {
+            assert!(trainer.add_document("Invoices", doc));
+        }
+        for doc in RECIPES {
+            assert!(trainer.add_document("Recipes", doc));
+        }
+        trainer.build().unwrap()
+    }
+
+    #[test]
+    fn classifies_held_out_documents() {
+        let model = trained_model();
+
+        let invoice = model
+            .predict("Final invoice: total amount due 99 EUR, payment within 14 days.")
+            .unwrap();
+        assert_eq!(invoice.category, "Invoices");
+
+        let recipe = model
+            .predict("Bake the bread: knead flour and water, then bake in a hot oven.")
+            .unwrap();
+        assert_eq!(recipe.category, "Recipes");
+    }
+
+    #[test]
+    fn min_score_gates_prediction() {
+        let model = trained_model();
+        let prediction = model
+            .predict("Invoice amount due. Payment terms apply.")
+            .unwrap();
+
+        assert!(prediction_matches(&prediction, "invoices", 0.1));
+        assert!(prediction_matches(&prediction, "", 0.1));
+        assert!(!prediction_matches(&prediction, "Recipes", 0.0));
+        assert!(!prediction_matches(&prediction, "Invoices", prediction.score + 0.01));
+    }
+
+    #[test]
+    fn unrelated_text_scores_low() {
+        let model = trained_model();
+        assert!(model.predict("zebra quantum").is_none());
+
+        let weak = model
+            .predict("The invoice mentions a quarterly telescope astronomy meeting schedule.")
+            .unwrap();
+        let strong = model
+            .predict("Invoice total amount due, payment balance, tax.")
+            .unwrap();
+        assert!(weak.score < strong.score);
+    }
+
+    #[test]
+    fn build_requires_two_categories() {
+        let mut trainer = ClassifierTrainer::new();
+        trainer.add_document("Invoices", INVOICES[0]);
+        assert!(trainer.build().is_err());
+        assert!(!ClassifierTrainer::new().add_document("Empty", "  1 2 3  "));
+    }
+
+    #[test]
+    fn model_round_trip_checks_version() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("classifier.json");
+        let model = trained_model();
+        save_model(&model, &path).unwrap();
+
+        let loaded = load_model(&path).unwrap();
+        assert_eq!(loaded.categories.len(), 2);
+        assert_eq!(
+            loaded.predict(RECIPES[0]).unwrap().category,
+            model.predict(RECIPES[0]).unwrap().category
+        );
+
+        let mut stale = model.clone();
+        stale.version = CLASSIFIER_MODEL_VERSION + 1;
+        save_model(&stale, &path).unwrap();
+        assert!(load_model(&path).is_err());
+    }
+
+    #[test]
+    fn trains_from_category_folders_with_cap() {
+        let dir = tempdir().unwrap();
+        for (category, docs) in [("Invoices", INVOICES), ("Recipes", RECIPES)] {
+            let folder = dir.path().join(category);
+            fs::create_dir_all(&folder).unwrap();
+            for (idx, doc) in docs.iter().enumerate() {
+                fs::write(folder.join(format!("doc{}.txt", idx)), doc).unwrap();
+            }
+        }
+
+        let settings = Settings::default();
+        let mut ocr = OcrManager::new_placeholder();
+        let mut events = 0;
+        let model = train_from_root(dir.path(), &settings, &mut ocr, 3, |_| events += 1).unwrap();
+
+        assert_eq!(model.document_count, 6);
+        assert!(events > 0);
+        assert_eq!(
+            model.predict("amount due on this invoice").unwrap().category,
+            "Invoices"
+        );
+    }
+}
@@ -0,0 +1,404 @@
+//! Bounded concurrency for commands the UI fires at the engine and database.
+//! Every such command takes a place in one lane before it starts; each lane
+//! runs at most its limit of commands at once and lets the others in in the
+//! order they arrived. A command that can't get in within the lane's wait
+//! fails as busy without having done anything, so the UI can retry it.
+//!
+//! Commands that take a while also get a `CommandScope` to poll: it says
+//! when the window that asked cancelled the request or the command ran past
+//! its time limit. The scope gives the place back when it's dropped.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::models::{CommandError, CommandErrorKind, Lane, LaneOccupancy};
+
+/// The lanes with how many commands each runs at once and how long a
+/// command waits for a place before giving up. Writes go one at a time, as
+/// the database has a single connection; OCR one at a time, as there's one
+/// engine.
+const LANES: [(Lane, usize, Duration); 4] = [
+    (Lane::ReadLight, 4, Duration::from_secs(10)),
+    (Lane::ReadHeavy, 2, Duration::from_secs(20)),
+    (Lane::Write, 1, Duration::from_secs(30)),
+    (Lane::Ocr, 1, Duration::from_secs(20)),
+];
+
+/// How often a waiting command looks whether it was cancelled.
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+type Requests = Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>;
+
+pub struct CommandLanes {
+    gates: Vec<Arc<Gate>>,
+    /// Cancellation flags of the commands running or waiting, by request id.
+    requests: Requests,
+}
+
+struct Gate {
+    lane: Lane,
+    limit: usize,
+    max_wait: Duration,
+    state: Mutex<GateState>,
+    freed: Condvar,
+}
+
+#[derive(Default)]
+struct GateState {
+    running: usize,
+    issued: u64,
+    /// The ticket let in next.
+    next: u64,
+    /// Tickets whose commands stopped waiting before their turn.
+    abandoned: BTreeSet<u64>,
+}
+
+impl GateState {
+    fn waiting(&self) -> usize {
+        (self.issued - self.next) as usize - self.abandoned.len()
+    }
+
+    fn advance(&mut self) {
+        self.next += 1;
+        while self.abandoned.remove(&self.next) {
+            self.next += 1;
+        }
+    }
+
+    fn abandon(&mut self, ticket: u64) {
+        if ticket == self.next {
+            self.advance();
+        } else {
+            self.abandoned.insert(ticket);
+        }
+    }
+}
+
+/// A command's place in its lane, held while it runs.
+pub struct CommandScope {
+    gate: Arc<Gate>,
+    cancelled: Arc<AtomicBool>,
+    request: Option<(Requests, String)>,
+    deadline: Option<Instant>,
+    /// False until the command gets in; only then is there a place to give back.
+    admitted: bool,
+}
+
+impl Default for CommandLanes {
+    fn default() -> Self {
+        Self::with_lanes(&LANES)
+    }
+}
+
+impl CommandLanes {
+    fn with_lanes(lanes: &[(Lane, usize, Duration)]) -> Self {
+        let gates = lanes
+            .iter()
+            .map(|&(lane, limit, max_wait)| {
+                Arc::new(Gate {
+                    lane,
+                    limit,
+                    max_wait,
+                    state: Mutex::new(GateState::default()),
+                    freed: Condvar::new(),
+                })
+            })
+            .collect();
+        Self {
+            gates,
+            requests: Arc::default(),
+        }
+    }
+
+    /// Waits for a place in `lane`. `request_id` is what the frontend
+    /// cancels the command by, if it can.
+    pub fn enter(
+        &self,
+        lane: Lane,
+        request_id: Option<&str>,
+    ) -> Result<CommandScope, CommandError> {
+        let gate = self
+            .gates
+            .iter()
+            .find(|gate| gate.lane == lane)
+            .cloned()
+            .ok_or_else(|| CommandError::failed(format!("No {lane:?} lane")))?;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let request = request_id.map(|id| {
+            if let Ok(mut requests) = self.requests.lock() {
+                requests.insert(id.to_string(), cancelled.clone());
+            }
+            (self.requests.clone(), id.to_string())
+        });
+        // The scope unregisters the request when dropped, even if the
+        // command never gets in.
+        let mut scope = CommandScope {
+            gate: gate.clone(),
+            cancelled,
+            request,
+            deadline: None,
+            admitted: false,
+        };
+
+        let give_up = Instant::now() + gate.max_wait;
+        let mut state = gate
+            .state
+            .lock()
+            .map_err(|_| CommandError::failed("Command lane poisoned"))?;
+        let ticket = state.issued;
+        state.issued += 1;
+        loop {
+            if state.next == ticket && state.running < gate.limit {
+                state.running += 1;
+                state.advance();
+                gate.freed.notify_all();
+                scope.admitted = true;
+                return Ok(scope);
+            }
+            let now = Instant::now();
+            let stopped = if scope.cancelled.load(Ordering::SeqCst) {
+                Some(cancelled_error())
+            } else if now >= give_up {
+                Some(CommandError::new(
+                    CommandErrorKind::Busy,
+                    format!("Too many {} commands running; try again", lane_name(lane)),
+                ))
+            } else {
+                None
+            };
+            if let Some(err) = stopped {
+                state.abandon(ticket);
+                gate.freed.notify_all();
+                return Err(err);
+            }
+            let wait = (give_up - now).min(CANCEL_POLL);
+            state = gate
+                .freed
+                .wait_timeout(state, wait)
+                .map_err(|_| CommandError::failed("Command lane poisoned"))?
+                .0;
+        }
+    }
+
+    /// Cancels the command with `request_id`, waiting or running. Returns
+    /// whether there was one.
+    pub fn cancel(&self, request_id: &str) -> bool {
+        let Ok(requests) = self.requests.lock() else {
+            return false;
+        };
+        match requests.get(request_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn occupancy(&self) -> Vec<LaneOccupancy> {
+        self.gates
+            .iter()
+            .map(|gate| {
+                let (running, waiting) = gate
+                    .state
+                    .lock()
+                    .map(|state| (state.running, state.waiting()))
+                    .unwrap_or_default();
+                LaneOccupancy {
+                    lane: gate.lane,
+                    running,
+                    waiting,
+                    limit: gate.limit,
+                }
+            })
+            .collect()
+    }
+}
+
+impl CommandScope {
+    /// Gives the command `limit` to finish, from now.
+    pub fn with_deadline(mut self, limit: Duration) -> Self {
+        self.deadline = Some(Instant::now() + limit);
+        self
+    }
+
+    /// Fails once the command was cancelled or ran out of time; long
+    /// commands call it between steps.
+    pub fn check(&self) -> Result<(), CommandError> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            return Err(cancelled_error());
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(CommandError::new(
+                CommandErrorKind::Timeout,
+                "Took too long and was stopped",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CommandScope {
+    fn drop(&mut self) {
+        if let Some((requests, id)) = self.request.take() {
+            if let Ok(mut requests) = requests.lock() {
+                // A later command may have reused the id.
+                if requests
+                    .get(&id)
+                    .is_some_and(|flag| Arc::ptr_eq(flag, &self.cancelled))
+                {
+                    requests.remove(&id);
+                }
+            }
+        }
+        if !self.admitted {
+            return;
+        }
+        if let Ok(mut state) = self.gate.state.lock() {
+            state.running -= 1;
+        }
+        self.gate.freed.notify_all();
+    }
+}
+
+fn cancelled_error() -> CommandError {
+    CommandError::new(CommandErrorKind::Cancelled, "Cancelled")
+}
+
+fn lane_name(lane: Lane) -> &'static str {
+    match lane {
+        Lane::ReadLight | Lane::ReadHeavy => "read",
+        Lane::Write => "write",
+        Lane::Ocr => "OCR",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+
+    fn lanes(limit: usize, max_wait: Duration) -> Arc<CommandLanes> {
+        Arc::new(CommandLanes::with_lanes(&[(
+            Lane::ReadHeavy,
+            limit,
+            max_wait,
+        )]))
+    }
+
+    #[test]
+    fn no_more_commands_run_at_once_than_the_lane_allows() {
+        let lanes = lanes(2, Duration::from_secs(10));
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let (lanes, running, most) = (lanes.clone(), running.clone(), most.clone());
+                thread::spawn(move || {
+                    let _scope = lanes.enter(Lane::ReadHeavy, None).unwrap();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(30));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+        let occupancy = &lanes.occupancy()[0];
+        assert_eq!((occupancy.running, occupancy.waiting), (0, 0));
+    }
+
+    #[test]
+    fn a_full_lane_turns_commands_away_as_busy() {
+        let lanes = lanes(1, Duration::from_millis(100));
+        let held = lanes.enter(Lane::ReadHeavy, None).unwrap();
+
+        let err = lanes.enter(Lane::ReadHeavy, None).err().unwrap();
+        assert_eq!(err.kind, CommandErrorKind::Busy);
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "busy",
+                "message": "Too many read commands running; try again",
+            })
+        );
+
+        // The one that gave up doesn't hold up those after it.
+        drop(held);
+        assert!(lanes.enter(Lane::ReadHeavy, None).is_ok());
+    }
+
+    #[test]
+    fn commands_past_their_deadline_time_out() {
+        let lanes = lanes(1, Duration::from_secs(1));
+        let scope = lanes
+            .enter(Lane::ReadHeavy, None)
+            .unwrap()
+            .with_deadline(Duration::from_millis(20));
+        assert!(scope.check().is_ok());
+        thread::sleep(Duration::from_millis(30));
+
+        let err = scope.check().unwrap_err();
+        assert_eq!(serde_json::to_value(&err).unwrap()["kind"], "timeout");
+    }
+
+    #[test]
+    fn cancelling_stops_a_long_command_promptly() {
+        let lanes = lanes(1, Duration::from_secs(10));
+        let worker = {
+            let lanes = lanes.clone();
+            thread::spawn(move || {
+                let scope = lanes.enter(Lane::ReadHeavy, Some("preview-1")).unwrap();
+                let started = Instant::now();
+                // A fake scan of a huge folder, a few milliseconds a file.
+                for _ in 0..10_000 {
+                    if let Err(err) = scope.check() {
+                        return (err.kind, started.elapsed());
+                    }
+                    thread::sleep(Duration::from_millis(2));
+                }
+                panic!("never cancelled");
+            })
+        };
+        while lanes.occupancy()[0].running == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(lanes.cancel("preview-1"));
+        let (kind, took) = worker.join().unwrap();
+        assert_eq!(kind, CommandErrorKind::Cancelled);
+        assert!(took < Duration::from_secs(1));
+        // Finished commands can't be cancelled any more.
+        assert!(!lanes.cancel("preview-1"));
+    }
+
+    #[test]
+    fn cancelling_a_waiting_command_takes_it_out_of_the_queue() {
+        let lanes = lanes(1, Duration::from_secs(10));
+        let held = lanes.enter(Lane::ReadHeavy, None).unwrap();
+        let waiter = {
+            let lanes = lanes.clone();
+            thread::spawn(move || lanes.enter(Lane::ReadHeavy, Some("scan")).err().unwrap())
+        };
+        while lanes.occupancy()[0].waiting == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        lanes.cancel("scan");
+        assert_eq!(waiter.join().unwrap().kind, CommandErrorKind::Cancelled);
+        assert_eq!(lanes.occupancy()[0].waiting, 0);
+        drop(held);
+        assert_eq!(lanes.occupancy()[0].running, 0);
+    }
+}
@@ -0,0 +1,237 @@
+//! Companion files: the sidecars, previews and other files next to a file
+//! that a move, copy or sort brings along. A companion either shares the
+//! file's stem and has one of the listed extensions (`IMG_1.xmp` next to
+//! `IMG_1.cr2`), or carries the file's whole name before its own extension
+//! (`IMG_1.cr2.xmp`), or its name matches the action's pattern.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+
+use crate::models::BringCompanions;
+
+/// The companions next to a file, and what was asked for but isn't there.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Companions {
+    pub found: Vec<PathBuf>,
+    /// The names expected for listed extensions that have no file, and the
+    /// pattern when it matched nothing.
+    pub missing: Vec<String>,
+}
+
+/// Checks the extensions and pattern without looking at any file.
+pub fn validate(spec: &BringCompanions) -> Result<(), String> {
+    let pattern = spec.pattern.as_deref().filter(|p| !p.trim().is_empty());
+    if extensions(spec).is_empty() && pattern.is_none() {
+        return Err("Bringing companions needs an extension or a pattern".to_string());
+    }
+    if let Some(pattern) = pattern {
+        if pattern.contains('/') || pattern.contains('\\') {
+            return Err(format!(
+                "Companion pattern \"{pattern}\" matches file names, not paths"
+            ));
+        }
+        Pattern::new(pattern)
+            .map_err(|err| format!("Invalid companion pattern \"{pattern}\": {err}"))?;
+    }
+    Ok(())
+}
+
+/// The companions of `source` in its folder. `pattern` is the action's
+/// pattern with its tokens resolved. `source` itself is never one, so this
+/// works the same before and after the file has gone.
+pub fn find(source: &Path, spec: &BringCompanions, pattern: Option<&str>) -> Companions {
+    let mut companions = Companions::default();
+    let Some(dir) = source.parent() else {
+        return companions;
+    };
+    let (Some(name), Some(stem)) = (file_name(source), file_stem(source)) else {
+        return companions;
+    };
+    let mut siblings: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path != source)
+        .collect();
+    siblings.sort();
+
+    for ext in extensions(spec) {
+        let matching: Vec<&PathBuf> = siblings
+            .iter()
+            .filter(|path| {
+                let (Some(path_stem), Some(path_ext)) = (file_stem(path), extension(path)) else {
+                    return false;
+                };
+                path_ext.eq_ignore_ascii_case(&ext) && (path_stem == stem || path_stem == name)
+            })
+            .collect();
+        if matching.is_empty() {
+            companions.missing.push(format!("{stem}.{ext}"));
+        }
+        companions.found.extend(matching.into_iter().cloned());
+    }
+
+    if let Some(pattern) = pattern.filter(|p| !p.trim().is_empty()) {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..MatchOptions::new()
+        };
+        let matching: Vec<&PathBuf> = match Pattern::new(pattern) {
+            Ok(glob) => siblings
+                .iter()
+                .filter(|path| {
+                    file_name(path).is_some_and(|name| glob.matches_with(&name, options))
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        if matching.is_empty() {
+            companions.missing.push(pattern.to_string());
+        }
+        companions.found.extend(matching.into_iter().cloned());
+    }
+
+    let mut seen = Vec::new();
+    companions.found.retain(|path| {
+        let new = !seen.contains(path);
+        if new {
+            seen.push(path.clone());
+        }
+        new
+    });
+    companions
+}
+
+/// Where `companion` goes when its file went from `source` to `dest`: into
+/// the same folder, keeping up with a rename of the file. A companion found
+/// only by pattern keeps its own name.
+pub fn destination_for(companion: &Path, source: &Path, dest: &Path) -> PathBuf {
+    let dir = dest.parent().unwrap_or(dest);
+    let companion_name = file_name(companion).unwrap_or_default();
+    let renamed = |from: Option<String>, to: Option<String>| {
+        let (from, to) = (from?, to?);
+        let rest = companion_name.strip_prefix(&from)?;
+        rest.starts_with('.').then(|| format!("{to}{rest}"))
+    };
+    let name = renamed(file_name(source), file_name(dest))
+        .or_else(|| renamed(file_stem(source), file_stem(dest)))
+        .unwrap_or(companion_name);
+    dir.join(name)
+}
+
+/// The listed extensions, trimmed and without a leading dot.
+fn extensions(spec: &BringCompanions) -> Vec<String> {
+    let mut exts: Vec<String> = Vec::new();
+    for ext in &spec.extensions {
+        let ext = ext.trim().trim_start_matches('.').to_lowercase();
+        if !ext.is_empty() && !exts.contains(&ext) {
+            exts.push(ext);
+        }
+    }
+    exts
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+fn file_stem(path: &Path) -> Option<String> {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn spec(extensions: &[&str], pattern: Option<&str>) -> BringCompanions {
+        BringCompanions {
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+            pattern: pattern.map(str::to_string),
+            missing_ok: false,
+            fail_on_error: false,
+            record_matches: true,
+        }
+    }
+
+    fn names(paths: &[PathBuf]) -> Vec<String> {
+        paths.iter().filter_map(|path| file_name(path)).collect()
+    }
+
+    #[test]
+    fn finds_same_stem_and_full_name_sidecars() {
+        let temp = tempdir().unwrap();
+        for name in [
+            "IMG_1.CR2",
+            "IMG_1.XMP",
+            "IMG_1.CR2.xmp",
+            "IMG_1.jpg",
+            "IMG_10.xmp",
+        ] {
+            fs::write(temp.path().join(name), b"x").unwrap();
+        }
+
+        let found = find(
+            &temp.path().join("IMG_1.CR2"),
+            &spec(&[".xmp", "jpg", "wav"], None),
+            None,
+        );
+        assert_eq!(
+            names(&found.found),
+            ["IMG_1.CR2.xmp", "IMG_1.XMP", "IMG_1.jpg"]
+        );
+        assert_eq!(found.missing, ["IMG_1.wav"]);
+    }
+
+    #[test]
+    fn pattern_matches_names_and_overlaps_count_once() {
+        let temp = tempdir().unwrap();
+        for name in ["scan.pdf", "scan.txt", "scan_notes.txt", "other.txt"] {
+            fs::write(temp.path().join(name), b"x").unwrap();
+        }
+
+        let found = find(
+            &temp.path().join("scan.pdf"),
+            &spec(&["txt"], Some("scan*.txt")),
+            Some("scan*.txt"),
+        );
+        assert_eq!(names(&found.found), ["scan.txt", "scan_notes.txt"]);
+        assert!(found.missing.is_empty());
+    }
+
+    #[test]
+    fn destination_follows_a_renamed_file() {
+        let dest = Path::new("/photos/2024/trip (1).cr2");
+        let source = Path::new("/in/trip.cr2");
+        assert_eq!(
+            destination_for(Path::new("/in/trip.xmp"), source, dest),
+            Path::new("/photos/2024/trip (1).xmp")
+        );
+        assert_eq!(
+            destination_for(Path::new("/in/trip.cr2.xmp"), source, dest),
+            Path::new("/photos/2024/trip (1).cr2.xmp")
+        );
+        assert_eq!(
+            destination_for(Path::new("/in/notes.txt"), source, dest),
+            Path::new("/photos/2024/notes.txt")
+        );
+    }
+
+    #[test]
+    fn validate_needs_something_to_look_for() {
+        assert!(validate(&spec(&[" . "], None)).is_err());
+        assert!(validate(&spec(&[], Some("a/*.xmp"))).is_err());
+        assert!(validate(&spec(&[], Some("[x"))).is_err());
+        assert!(validate(&spec(&["xmp"], None)).is_ok());
+    }
+}
@@ -0,0 +1,696 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use filedispatch_core::file_info::FileInfo;
+use filedispatch_core::patterns::COUNTER_CAPTURE;
+
+use crate::core::budget::record_transfers;
+use crate::core::counters::{number_run, settle_counter};
+use crate::core::dispatch::{DispatchTrace, ExecuteFn};
+use crate::core::duplicates::{hash_file, DuplicateDetector};
+use crate::core::engine::{
+    evaluate_conditions, log_outcomes, record_companion_matches, write_processed_markers,
+    EvaluationOptions,
+};
+use crate::core::executor::{
+    ActionErrorKind, ActionOutcome, ActionResultStatus, ActionScope, SettledConflict,
+    CONFLICT_INDEX_KEY,
+};
+use crate::core::match_metadata;
+use crate::core::ocr::OcrManager;
+use crate::models::{
+    Action, ConflictDecision, ConflictError, ConflictErrorKind, ConflictFile, LogEntry, LogStatus,
+    PendingConflict, Rule, Settings,
+};
+use crate::storage::conflict_repo::ConflictRepository;
+use crate::storage::database::Database;
+use crate::storage::log_repo::LogRepository;
+use crate::storage::rule_repo::RuleRepository;
+use crate::storage::write_set::WriteSet;
+
+/// A run of a rule's actions, from its `first_index`th action on.
+pub struct ActionRun<'a> {
+    pub rule: &'a Rule,
+    pub actions: &'a [Action],
+    pub first_index: usize,
+    pub captures: &'a HashMap<String, String>,
+    pub trace_id: Option<&'a str>,
+}
+
+/// Queues the conflict a run stopped on, if it stopped on one. The file then
+/// waits for a decision: no rule runs on it until the conflict is resolved.
+pub fn suspend(
+    db: &Database,
+    run: &ActionRun,
+    info: &FileInfo,
+    outcomes: &[ActionOutcome],
+) -> Result<Option<PendingConflict>> {
+    let Some(outcome) = outcomes.iter().find(|outcome| {
+        matches!(
+            outcome.error_kind,
+            Some(ActionErrorKind::ConflictPending(_))
+        )
+    }) else {
+        return Ok(None);
+    };
+    let Some(details) = &outcome.details else {
+        return Ok(None);
+    };
+    let index = details
+        .metadata
+        .get(CONFLICT_INDEX_KEY)
+        .and_then(|index| index.parse::<usize>().ok())
+        .unwrap_or(run.first_index);
+    let destination = details.destination_path.as_deref().unwrap_or_default();
+    let actions = run
+        .actions
+        .get(index.saturating_sub(run.first_index)..)
+        .unwrap_or_default();
+    let mut conflict = ConflictRepository::new(db.clone()).insert(PendingConflict {
+        id: String::new(),
+        rule_id: run.rule.id.clone(),
+        rule_name: String::new(),
+        trace_id: run.trace_id.map(str::to_string),
+        action_type: outcome.action_type.clone(),
+        action_index: index,
+        actions: actions.to_vec(),
+        captures: run.captures.clone(),
+        source: describe_file(Path::new(&details.source_path), info.size)?,
+        destination: describe_file(Path::new(destination), 0)?,
+        created_at: Utc::now(),
+    })?;
+    conflict.rule_name = run.rule.name.clone();
+    Ok(Some(conflict))
+}
+
+/// Size, modified time and contents hash of one side of a conflict.
+/// Folders, which Replace can overwrite, aren't hashed.
+fn describe_file(path: &Path, fallback_size: u64) -> Result<ConflictFile> {
+    let meta = std::fs::metadata(path)?;
+    Ok(ConflictFile {
+        path: path.to_string_lossy().to_string(),
+        size: if meta.is_file() {
+            meta.len()
+        } else {
+            fallback_size
+        },
+        modified: meta.modified().ok().map(DateTime::<Utc>::from),
+        hash: if meta.is_file() {
+            hash_file(path)?
+        } else {
+            String::new()
+        },
+    })
+}
+
+/// Whether `file` is still there with the contents it had when the
+/// conflict was raised.
+fn unchanged(file: &ConflictFile) -> bool {
+    let path = Path::new(&file.path);
+    match std::fs::metadata(path) {
+        Ok(meta) if meta.is_file() => {
+            meta.len() == file.size && hash_file(path).is_ok_and(|hash| hash == file.hash)
+        }
+        Ok(_) => file.hash.is_empty(),
+        Err(_) => false,
+    }
+}
+
+/// Where `name` puts the file: next to the one it conflicts with. Only a
+/// plain file name that isn't taken there will do.
+fn other_name(conflict: &PendingConflict, name: &str) -> Result<PathBuf, ConflictError> {
+    let name = name.trim();
+    let invalid = |message: String| ConflictError::new(ConflictErrorKind::InvalidName, message);
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(invalid(format!("\"{name}\" isn't a file name")));
+    }
+    let destination = Path::new(&conflict.destination.path).with_file_name(name);
+    if destination.exists() {
+        return Err(invalid(format!(
+            "Something named \"{name}\" is already there"
+        )));
+    }
+    Ok(destination)
+}
+
+pub fn is_expired(conflict: &PendingConflict, now: DateTime<Utc>, expiry_hours: u32) -> bool {
+    expiry_hours > 0 && now - conflict.created_at > Duration::hours(expiry_hours as i64)
+}
+
+/// What resolving a conflict needs to finish the suspended run.
+pub struct ConflictRunner<'a> {
+    pub db: &'a Database,
+    pub settings: &'a Settings,
+    pub ocr: &'a Mutex<OcrManager>,
+    pub duplicates: &'a Arc<DuplicateDetector>,
+    pub execute: &'a ExecuteFn<'a>,
+    /// Told about each conflict raised while finishing a run, whether it's
+    /// the same one again or a later action's.
+    pub on_conflict: &'a dyn Fn(&PendingConflict),
+}
+
+impl ConflictRunner<'_> {
+    /// Runs the suspended action the way the user decided, then the rest of
+    /// the rule. If either file changed since the conflict was raised, the
+    /// rule is run again from the suspended action instead, which may raise
+    /// the conflict anew.
+    pub fn resolve(&self, id: &str, decision: &ConflictDecision) -> Result<(), ConflictError> {
+        let conflicts = ConflictRepository::new(self.db.clone());
+        let conflict = conflicts
+            .get(id)
+            .map_err(|e| ConflictError::failed(e.to_string()))?
+            .ok_or_else(|| ConflictError::new(ConflictErrorKind::NotFound, "Conflict not found"))?;
+        let destination = match decision {
+            ConflictDecision::UseOtherName(name) => other_name(&conflict, name)?,
+            _ => PathBuf::from(&conflict.destination.path),
+        };
+        let drop_with = |kind: ConflictErrorKind, message: &str| {
+            let _ = conflicts.delete(&conflict.id);
+            ConflictError::new(kind, message)
+        };
+
+        let rule = RuleRepository::new(self.db.clone())
+            .get(&conflict.rule_id)
+            .map_err(|e| ConflictError::failed(e.to_string()))?
+            .ok_or_else(|| drop_with(ConflictErrorKind::NotFound, "Rule no longer exists"))?;
+        let info = FileInfo::from_path(Path::new(&conflict.source.path)).map_err(|_| {
+            drop_with(
+                ConflictErrorKind::Missing,
+                "File no longer exists where it was waiting",
+            )
+        })?;
+        let _ = conflicts.delete(&conflict.id);
+
+        let run = ActionRun {
+            rule: &rule,
+            actions: &conflict.actions,
+            first_index: conflict.action_index,
+            captures: &conflict.captures,
+            trace_id: conflict.trace_id.as_deref(),
+        };
+        if !unchanged(&conflict.source) || !unchanged(&conflict.destination) {
+            return self.replan(&run, &info);
+        }
+        let scope = ActionScope::new(&rule.id, &info)
+            .starting_at(conflict.action_index)
+            .settling(SettledConflict {
+                destination,
+                policy: decision.policy(),
+            });
+        let outcomes = (self.execute)(&conflict.actions, &info, &conflict.captures, &scope);
+        self.finish(&run, &info, &outcomes).map(|_| ())
+    }
+
+    /// Runs the rule again from the suspended action on a file that changed,
+    /// or whose destination did, since the conflict was raised.
+    fn replan(&self, run: &ActionRun, info: &FileInfo) -> Result<(), ConflictError> {
+        let mut evaluation = {
+            let mut ocr = self.ocr.lock().unwrap();
+            evaluate_conditions(
+                run.rule,
+                info,
+                self.settings,
+                &mut ocr,
+                &EvaluationOptions::finding_duplicates(self.duplicates, &run.rule.folder_id),
+            )
+            .map_err(|e| ConflictError::failed(e.to_string()))?
+        };
+        if !evaluation.matched {
+            return Err(ConflictError::new(
+                ConflictErrorKind::NoLongerMatches,
+                "File changed and no longer matches the rule",
+            ));
+        }
+        // The suspended run already holds a number; it keeps it.
+        match run.captures.get(COUNTER_CAPTURE) {
+            Some(held) => {
+                evaluation
+                    .captures
+                    .insert(COUNTER_CAPTURE.to_string(), held.clone());
+            }
+            None => number_run(self.db, run.rule, &mut evaluation.captures)
+                .map_err(|e| ConflictError::failed(e.to_string()))?,
+        }
+        let run = ActionRun {
+            captures: &evaluation.captures,
+            ..*run
+        };
+        let scope = ActionScope::new(&run.rule.id, info).starting_at(run.first_index);
+        let outcomes = (self.execute)(run.actions, info, run.captures, &scope);
+        match self.finish(&run, info, &outcomes)? {
+            Some(conflict) if conflict.action_index == run.first_index => Err(ConflictError::new(
+                ConflictErrorKind::Replanned,
+                "The files changed since the conflict was raised; decide again",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Logs a finished run like any other and records the match, unless it
+    /// stopped on another conflict. Returns that conflict, if so.
+    fn finish(
+        &self,
+        run: &ActionRun,
+        info: &FileInfo,
+        outcomes: &[ActionOutcome],
+    ) -> Result<Option<PendingConflict>, ConflictError> {
+        let failed = |e: anyhow::Error| ConflictError::failed(e.to_string());
+        let trace = run.trace_id.map(|trace_id| DispatchTrace {
+            trace_id,
+            folder_id: &run.rule.folder_id,
+        });
+        let recorded = match_metadata::resolve(run.rule, info, run.captures, self.settings);
+        let mut writes = WriteSet::default();
+        log_outcomes(&mut writes, run.rule, info, outcomes, &recorded, trace.as_ref());
+        record_transfers(
+            self.db,
+            run.rule,
+            info.size,
+            outcomes,
+            self.settings,
+            Utc::now(),
+        )
+        .map_err(failed)?;
+        settle_counter(self.db, run.rule, run.captures, outcomes).map_err(failed)?;
+        if let Some(conflict) = suspend(self.db, run, info, outcomes).map_err(failed)? {
+            writes.commit(self.db).map_err(failed)?;
+            (self.on_conflict)(&conflict);
+            return Ok(Some(conflict));
+        }
+
+        let path = info.path.to_string_lossy();
+        writes.record_match(
+            &run.rule.id,
+            &path,
+            Some(&info.hash),
+            &recorded,
+            &self.settings.match_metadata_indexed_keys,
+        );
+        record_companion_matches(&RuleRepository::new(self.db.clone()), &mut writes, outcomes);
+        writes.commit(self.db).map_err(failed)?;
+        write_processed_markers(self.settings, &run.rule.id, &info.path, outcomes);
+        match outcomes
+            .iter()
+            .find(|outcome| outcome.status == ActionResultStatus::Error)
+        {
+            Some(failed) => Err(ConflictError::failed(
+                failed
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| "Action failed".to_string()),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Settles the conflicts nobody decided on within the expiry period with
+    /// the fallback decision, and notes in the log that it was made for them.
+    /// A fallback name that won't do falls back to Skip.
+    pub fn expire(&self, now: DateTime<Utc>) -> Result<usize> {
+        let hours = self.settings.conflict_expiry_hours;
+        let fallback = &self.settings.conflict_fallback;
+        let mut expired = 0;
+        for conflict in ConflictRepository::new(self.db.clone()).list()? {
+            if !is_expired(&conflict, now, hours) {
+                continue;
+            }
+            LogRepository::new(self.db.clone()).insert(LogEntry {
+                id: String::new(),
+                rule_id: Some(conflict.rule_id.clone()),
+                rule_name: Some(conflict.rule_name.clone()),
+                file_path: conflict.source.path.clone(),
+                action_type: "conflict".to_string(),
+                action_detail: None,
+                status: LogStatus::Warning,
+                error_message: Some(format!(
+                    "No decision within {hours} hours; fell back to {}",
+                    fallback.label()
+                )),
+                created_at: now,
+            })?;
+            let result = match self.resolve(&conflict.id, fallback) {
+                Err(err) if err.kind == ConflictErrorKind::InvalidName => {
+                    self.resolve(&conflict.id, &ConflictDecision::Skip)
+                }
+                result => result,
+            };
+            if let Err(err) = result {
+                eprintln!("Expired conflict for {}: {err}", conflict.source.path);
+            }
+            expired += 1;
+        }
+        Ok(expired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::executor::{mark_suspended, transfer_file, Transfer};
+    use crate::models::{
+        ActionType, Condition, ConditionGroup, ConflictResolution, ContentSource,
+        ContentsCondition, MatchType, MoveAction, StringOperator,
+    };
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::match_repo::MatchRepository;
+    use std::fs;
+    use tempfile::{tempdir, TempDir};
+
+    /// Stand-in for the executor that understands Move, with its conflict
+    /// handling and suspension.
+    fn execute(
+        actions: &[Action],
+        info: &FileInfo,
+        _: &HashMap<String, String>,
+        scope: &ActionScope,
+    ) -> Vec<ActionOutcome> {
+        let mut outcomes = Vec::new();
+        for (offset, action) in actions.iter().enumerate() {
+            let Action::Move(action) = action else {
+                continue;
+            };
+            let (dest, policy) = match scope.settled(offset) {
+                Some(settled) => (settled.destination.clone(), settled.policy.clone()),
+                None => (
+                    Path::new(&action.destination).join(&info.full_name),
+                    action.on_conflict.clone(),
+                ),
+            };
+            let mut outcome = transfer_file(
+                ActionType::Move,
+                Transfer::Move,
+                &info.path,
+                dest,
+                policy,
+                false,
+            );
+            let suspended = mark_suspended(&mut outcome, &info.path, scope.index(offset));
+            outcomes.push(outcome);
+            if suspended {
+                break;
+            }
+        }
+        outcomes
+    }
+
+    struct Fixture {
+        _dir: TempDir,
+        db: Database,
+        rule: Rule,
+        file: PathBuf,
+        filed: PathBuf,
+        duplicates: Arc<DuplicateDetector>,
+    }
+
+    impl Fixture {
+        fn existing(&self) -> PathBuf {
+            self.filed.join("report.txt")
+        }
+
+        fn suspend_file(&self) -> PendingConflict {
+            let info = FileInfo::from_path(&self.file).unwrap();
+            let captures = HashMap::new();
+            let scope = ActionScope::new(&self.rule.id, &info);
+            let outcomes = execute(&self.rule.actions, &info, &captures, &scope);
+            let run = ActionRun {
+                rule: &self.rule,
+                actions: &self.rule.actions,
+                first_index: 0,
+                captures: &captures,
+                trace_id: Some("trace"),
+            };
+            suspend(&self.db, &run, &info, &outcomes).unwrap().unwrap()
+        }
+
+        fn matched(&self) -> bool {
+            let info = FileInfo::from_path(&self.file).ok();
+            let hash = info.map(|info| info.hash).unwrap_or_default();
+            let matched = MatchRepository::new(self.db.clone())
+                .get_hash_matched_rules(&[self.rule.id.as_str()], &hash)
+                .unwrap();
+            matched.contains(&self.rule.id)
+        }
+
+        fn pending(&self) -> Vec<PendingConflict> {
+            ConflictRepository::new(self.db.clone()).list().unwrap()
+        }
+    }
+
+    fn fixture() -> Fixture {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Inbox")
+            .unwrap();
+        let filed = dir.path().join("filed");
+        fs::create_dir(&filed).unwrap();
+        fs::write(filed.join("report.txt"), "REPORT v0").unwrap();
+        let rule = RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id,
+                name: "Reports".to_string(),
+                enabled: true,
+                stop_processing: true,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![Condition::Contents(ContentsCondition {
+                        operator: StringOperator::Contains,
+                        value: "REPORT".to_string(),
+                        case_sensitive: true,
+                        source: ContentSource::Text,
+                        min_ocr_confidence: None,
+                    })],
+                },
+                actions: vec![Action::Move(MoveAction {
+                    destination: filed.to_string_lossy().to_string(),
+                    on_conflict: ConflictResolution::Ask,
+                    skip_duplicates: false,
+                    destination_permissions: Default::default(),
+                    quarantine: Default::default(),
+                    bring_companions: None,
+                    auto_partition: None,
+                })],
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap();
+        let file = dir.path().join("report.txt");
+        fs::write(&file, "REPORT v1").unwrap();
+        let duplicates = Arc::new(DuplicateDetector::new(db.clone()));
+        Fixture {
+            _dir: dir,
+            db,
+            rule,
+            file,
+            filed,
+            duplicates,
+        }
+    }
+
+    fn runner<'a>(
+        fx: &'a Fixture,
+        settings: &'a Settings,
+        ocr: &'a Mutex<OcrManager>,
+    ) -> ConflictRunner<'a> {
+        ConflictRunner {
+            db: &fx.db,
+            settings,
+            ocr,
+            duplicates: &fx.duplicates,
+            execute: &execute,
+            on_conflict: &|_| {},
+        }
+    }
+
+    #[test]
+    fn an_existing_destination_suspends_the_move() {
+        let fx = fixture();
+        let conflict = fx.suspend_file();
+
+        assert_eq!(conflict.action_index, 0);
+        assert_eq!(conflict.action_type, ActionType::Move);
+        assert_eq!(conflict.source.path, fx.file.to_string_lossy());
+        assert_eq!(conflict.destination.path, fx.existing().to_string_lossy());
+        assert_eq!(conflict.destination.size, "REPORT v0".len() as u64);
+        assert_ne!(conflict.source.hash, conflict.destination.hash);
+        assert_eq!(fs::read_to_string(&fx.file).unwrap(), "REPORT v1");
+        assert_eq!(fs::read_to_string(fx.existing()).unwrap(), "REPORT v0");
+
+        let repo = ConflictRepository::new(fx.db.clone());
+        assert!(repo.is_pending(&fx.file.to_string_lossy()).unwrap());
+        let listed = repo.get(&conflict.id).unwrap().unwrap();
+        assert_eq!(listed.rule_name, "Reports");
+        assert_eq!(listed.trace_id.as_deref(), Some("trace"));
+        assert_eq!(listed.actions.len(), 1);
+    }
+
+    #[test]
+    fn each_decision_completes_the_suspended_move() {
+        let settings = Settings::default();
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        let decisions = [
+            ConflictDecision::Skip,
+            ConflictDecision::Replace,
+            ConflictDecision::Rename,
+            ConflictDecision::UseOtherName("report-v1.txt".to_string()),
+        ];
+        for decision in decisions {
+            let fx = fixture();
+            let conflict = fx.suspend_file();
+            runner(&fx, &settings, &ocr)
+                .resolve(&conflict.id, &decision)
+                .unwrap();
+
+            assert!(fx.pending().is_empty(), "{decision:?}");
+            let mut filed: Vec<String> = fs::read_dir(&fx.filed)
+                .unwrap()
+                .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+                .collect();
+            filed.sort();
+            match decision {
+                ConflictDecision::Skip => {
+                    assert!(fx.matched());
+                    assert_eq!(filed, ["REPORT v0"]);
+                }
+                ConflictDecision::Replace => {
+                    assert!(!fx.file.exists());
+                    assert_eq!(filed, ["REPORT v1"]);
+                }
+                ConflictDecision::Rename => {
+                    assert!(!fx.file.exists());
+                    assert_eq!(filed, ["REPORT v0", "REPORT v1"]);
+                }
+                ConflictDecision::UseOtherName(_) => {
+                    assert!(!fx.file.exists());
+                    let renamed = fx.filed.join("report-v1.txt");
+                    assert_eq!(fs::read_to_string(renamed).unwrap(), "REPORT v1");
+                }
+            }
+            let logged = LogRepository::new(fx.db.clone()).list(10, 0).unwrap();
+            assert_eq!(logged[0].rule_name.as_deref(), Some("Reports"));
+            let detail = logged[0].action_detail.as_ref().unwrap();
+            assert_eq!(
+                detail.metadata.get("trace_id").map(String::as_str),
+                Some("trace")
+            );
+        }
+    }
+
+    #[test]
+    fn another_name_must_be_a_free_file_name() {
+        let fx = fixture();
+        let conflict = fx.suspend_file();
+        let settings = Settings::default();
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        let runner = runner(&fx, &settings, &ocr);
+
+        for name in ["", "../report.txt", "report.txt"] {
+            let decision = ConflictDecision::UseOtherName(name.to_string());
+            let err = runner.resolve(&conflict.id, &decision).unwrap_err();
+            assert_eq!(err.kind, ConflictErrorKind::InvalidName, "{name}");
+        }
+        assert_eq!(fx.pending().len(), 1);
+        assert!(fx.file.exists());
+    }
+
+    #[test]
+    fn changed_files_are_run_again_instead() {
+        let fx = fixture();
+        let conflict = fx.suspend_file();
+        let settings = Settings::default();
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        let runner = runner(&fx, &settings, &ocr);
+
+        // The file in the way changed: replacing it now could lose that
+        // change, so the move is suspended again with what's there now.
+        fs::write(fx.existing(), "REPORT v0, edited").unwrap();
+        let err = runner
+            .resolve(&conflict.id, &ConflictDecision::Replace)
+            .unwrap_err();
+        assert_eq!(err.kind, ConflictErrorKind::Replanned);
+        assert_eq!(
+            fs::read_to_string(fx.existing()).unwrap(),
+            "REPORT v0, edited"
+        );
+        let pending = fx.pending();
+        assert_eq!(pending.len(), 1);
+        assert_ne!(pending[0].id, conflict.id);
+        assert_eq!(
+            pending[0].destination.size,
+            "REPORT v0, edited".len() as u64
+        );
+
+        // The file itself changed and no longer matches the rule.
+        fs::write(&fx.file, "shopping list").unwrap();
+        let err = runner
+            .resolve(&pending[0].id, &ConflictDecision::Replace)
+            .unwrap_err();
+        assert_eq!(err.kind, ConflictErrorKind::NoLongerMatches);
+        assert!(fx.pending().is_empty());
+        assert!(fx.file.exists());
+        assert!(!fx.matched());
+    }
+
+    #[test]
+    fn the_conflict_goes_away_with_the_file_in_the_way() {
+        let fx = fixture();
+        let conflict = fx.suspend_file();
+        let settings = Settings::default();
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+
+        fs::remove_file(fx.existing()).unwrap();
+        runner(&fx, &settings, &ocr)
+            .resolve(&conflict.id, &ConflictDecision::Skip)
+            .unwrap();
+        assert_eq!(fs::read_to_string(fx.existing()).unwrap(), "REPORT v1");
+        assert!(fx.pending().is_empty());
+    }
+
+    #[test]
+    fn undecided_conflicts_expire_into_the_fallback() {
+        let fx = fixture();
+        let conflict = fx.suspend_file();
+        let settings = Settings {
+            conflict_expiry_hours: 24,
+            conflict_fallback: ConflictDecision::Replace,
+            ..Settings::default()
+        };
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        let runner = runner(&fx, &settings, &ocr);
+
+        assert!(!is_expired(&conflict, Utc::now() + Duration::days(30), 0));
+        assert_eq!(runner.expire(Utc::now() + Duration::hours(1)).unwrap(), 0);
+        assert_eq!(fx.pending().len(), 1);
+
+        assert_eq!(runner.expire(Utc::now() + Duration::hours(25)).unwrap(), 1);
+        assert!(fx.pending().is_empty());
+        assert_eq!(fs::read_to_string(fx.existing()).unwrap(), "REPORT v1");
+        let logged = LogRepository::new(fx.db.clone()).list(10, 0).unwrap();
+        let note = logged
+            .iter()
+            .find(|entry| entry.action_type == "conflict")
+            .unwrap();
+        assert_eq!(note.status, LogStatus::Warning);
+        assert_eq!(
+            note.error_message.as_deref(),
+            Some("No decision within 24 hours; fell back to replace")
+        );
+    }
+}
@@ -2,10 +2,18 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Once;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use filedispatch_core::exif::Exif;
+use filedispatch_core::evaluate::ResolvedContents;
+use filedispatch_core::file_info::FileInfo;
+use filedispatch_core::media::{read_media_info, MediaInfo};
+use filedispatch_core::screenshot::ScreenshotEvidence;
 use flate2::{write::ZlibEncoder, Compression};
+use image::ImageDecoder;
 use lopdf::dictionary;
 use lopdf::content::Operation;
 use lopdf::{Object, ObjectId, Stream};
@@ -16,21 +24,163 @@ use tracing::{info, warn};
 
 use crate::core::ocr_geometry::{PageOcrResult, Rect};
 use crate::core::ocr_grouping::group_words_into_lines;
+use crate::core::faces::count_faces;
 use crate::core::ocr::OcrManager;
 use crate::core::pdf_coords::{ocr_pixel_to_pdf_point, PageGeometry, PdfBox};
 use crate::core::pdf_font::{
     build_tounicode_cmap, load_font_data, subset_font_for_codepoints, SubsetFont, OCR_FONT_NAME,
 };
 use crate::core::pdf_page_geometry::extract_page_geometry;
-use crate::models::{ContentSource, FileKind, Settings};
-use crate::utils::file_info::FileInfo;
+use crate::core::screenshots::gather_evidence;
+use crate::models::{ContentSource, CsvColumnCondition, CsvRowScope, FileKind, Settings};
+use crate::utils::archive::{list_archive_entries, ArchiveListing};
+use crate::utils::csv_table::read_column;
+use crate::utils::tempfiles::app_temp;
+
+/// What Image Dimensions and EXIF Date conditions read from an image.
+struct ImageMetadata {
+    dimensions: (u32, u32),
+    taken: Option<DateTime<Utc>>,
+}
 
 #[derive(Default)]
 pub struct ContentCache {
     text: Option<String>,
-    ocr_text: Option<String>,
+    ocr_text: Option<OcrText>,
     text_attempted: bool,
     ocr_attempted: bool,
+    reduced_fidelity: bool,
+    /// Entry cap the listing was read with, and the listing or failure reason.
+    archive: Option<(usize, std::result::Result<ArchiveListing, String>)>,
+    /// Detected face count, or why it couldn't be counted.
+    faces: Option<std::result::Result<u32, String>>,
+    /// What the image header says, or why it couldn't be read.
+    image: Option<std::result::Result<ImageMetadata, String>>,
+    /// Pages in the PDF, or why they couldn't be counted.
+    pages: Option<std::result::Result<u32, String>>,
+    /// MIME type sniffed from the first bytes, or why it couldn't be told.
+    mime: Option<std::result::Result<String, String>>,
+    /// What the media container's headers say, or why they couldn't be read.
+    media: Option<std::result::Result<MediaInfo, String>>,
+    screenshot: Option<ScreenshotEvidence>,
+    /// Why a CSV Column condition couldn't read the file.
+    csv_error: Option<String>,
+    streamed: bool,
+}
+
+impl ContentCache {
+    /// True when PDF text came from the pure-Rust fallback instead of PDFium.
+    pub fn reduced_fidelity(&self) -> bool {
+        self.reduced_fidelity
+    }
+
+    /// Average confidence of the OCR text, if OCR ran and produced any.
+    pub fn ocr_confidence(&self) -> Option<f32> {
+        self.ocr_text.as_ref().map(|ocr| ocr.average_confidence)
+    }
+
+    /// True when a Contents condition searched the file in chunks instead of
+    /// reading it whole.
+    pub fn streamed(&self) -> bool {
+        self.streamed
+    }
+
+    pub(crate) fn mark_streamed(&mut self) {
+        self.streamed = true;
+    }
+
+    /// Why the archive listing couldn't be read (corrupt, encrypted, ...).
+    pub fn archive_error(&self) -> Option<&str> {
+        match &self.archive {
+            Some((_, Err(reason))) => Some(reason.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Why faces couldn't be counted (detector unavailable, image too large, ...).
+    pub fn face_error(&self) -> Option<&str> {
+        match &self.faces {
+            Some(Err(reason)) => Some(reason.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Why the image's header couldn't be read (too large, unknown format, ...).
+    pub fn image_metadata_error(&self) -> Option<&str> {
+        match &self.image {
+            Some(Err(reason)) => Some(reason.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Why the PDF's pages couldn't be counted (too large, damaged, ...).
+    pub fn page_count_error(&self) -> Option<&str> {
+        match &self.pages {
+            Some(Err(reason)) => Some(reason.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Why the file's MIME type couldn't be sniffed (unknown signature, ...).
+    pub fn mime_error(&self) -> Option<&str> {
+        match &self.mime {
+            Some(Err(reason)) => Some(reason.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Why the media file's container couldn't be read (unknown format,
+    /// damaged headers, ...).
+    pub fn media_error(&self) -> Option<&str> {
+        match &self.media {
+            Some(Err(reason)) => Some(reason.as_str()),
+            _ => None,
+        }
+    }
+
+    /// What an Is Screenshot condition found out about the image, if one ran.
+    pub fn screenshot_evidence(&self) -> Option<&ScreenshotEvidence> {
+        self.screenshot.as_ref()
+    }
+
+    /// Why the file couldn't be read as CSV (binary, ragged rows, no such column, ...).
+    pub fn csv_error(&self) -> Option<&str> {
+        self.csv_error.as_deref()
+    }
+}
+
+/// Text recognized by OCR along with the engine's average confidence (0–1)
+/// across the regions that made it past the recognition threshold.
+#[derive(Debug, Clone)]
+struct OcrText {
+    text: String,
+    average_confidence: f32,
+}
+
+/// PDFium could not be loaded. Carried inside `anyhow::Error` so callers can
+/// `downcast_ref` it and tell a missing library apart from a broken document.
+#[derive(Debug)]
+pub struct PdfiumUnavailable(pub String);
+
+impl std::fmt::Display for PdfiumUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PDFium unavailable: {}", self.0)
+    }
+}
+
+impl std::error::Error for PdfiumUnavailable {}
+
+static PDF_FALLBACK_WARNING: Once = Once::new();
+
+#[cfg(test)]
+thread_local! {
+    static FORCE_PDFIUM_UNAVAILABLE: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Test hook: makes `load_pdfium` fail on the current thread.
+#[cfg(test)]
+pub(crate) fn force_pdfium_unavailable(force: bool) {
+    FORCE_PDFIUM_UNAVAILABLE.with(|flag| flag.set(force));
 }
 
 pub fn resolve_contents(
@@ -41,43 +191,313 @@ pub fn resolve_contents(
     cache: &mut ContentCache,
     request_id: Option<&str>,
 ) -> Result<Option<String>> {
+    Ok(resolve_contents_detailed(info, settings, ocr, source, cache, request_id)?
+        .map(|contents| contents.text))
+}
+
+/// Like `resolve_contents`, but also reports the OCR confidence when the text
+/// came from OCR so callers can gate on it.
+pub fn resolve_contents_detailed(
+    info: &FileInfo,
+    settings: &Settings,
+    ocr: &mut OcrManager,
+    source: &ContentSource,
+    cache: &mut ContentCache,
+    request_id: Option<&str>,
+) -> Result<Option<ResolvedContents>> {
+    if info.is_placeholder {
+        return Err(anyhow!("Contents unavailable: file is an online-only placeholder"));
+    }
     match source {
         ContentSource::Text => {
             if cache.text_attempted {
-                return Ok(cache.text.clone());
+                return Ok(cache.text.clone().map(ResolvedContents::plain));
             }
-            let text = extract_text_content(info, settings)?;
+            let text = extract_text_content(info, settings, cache)?;
             cache.text_attempted = true;
             cache.text = text.clone();
-            Ok(text)
-        }
-        ContentSource::Ocr => {
-            if cache.ocr_attempted {
-                return Ok(cache.ocr_text.clone());
-            }
-            let text = extract_ocr_content(info, settings, ocr, request_id)?;
-            cache.ocr_attempted = true;
-            cache.ocr_text = text.clone();
-            Ok(text)
+            Ok(text.map(ResolvedContents::plain))
         }
+        ContentSource::Ocr => resolve_ocr(info, settings, ocr, cache, request_id),
         ContentSource::Auto => {
             if !cache.text_attempted {
-                let text = extract_text_content(info, settings)?;
+                let text = extract_text_content(info, settings, cache)?;
                 cache.text_attempted = true;
                 cache.text = text.clone();
                 if let Some(text) = text {
                     if !text.trim().is_empty() {
-                        return Ok(Some(text));
+                        return Ok(Some(ResolvedContents::plain(text)));
                     }
                 }
             }
-            if cache.ocr_attempted {
-                return Ok(cache.ocr_text.clone());
+            resolve_ocr(info, settings, ocr, cache, request_id)
+        }
+    }
+}
+
+fn resolve_ocr(
+    info: &FileInfo,
+    settings: &Settings,
+    ocr: &mut OcrManager,
+    cache: &mut ContentCache,
+    request_id: Option<&str>,
+) -> Result<Option<ResolvedContents>> {
+    if !cache.ocr_attempted {
+        let text = extract_ocr_content(info, settings, ocr, request_id)?;
+        cache.ocr_attempted = true;
+        cache.ocr_text = text;
+    }
+    Ok(cache
+        .ocr_text
+        .as_ref()
+        .map(|ocr| ResolvedContents::ocr(ocr.text.clone(), ocr.average_confidence)))
+}
+
+/// Plain-text files above this size are searched in chunks when no text size
+/// limit is configured.
+const STREAM_MIN_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Whether a Contents condition should search the file in chunks rather than
+/// extract its text: plain-text kinds over the text size limit (or
+/// `STREAM_MIN_BYTES` when the limit is off), read via the text source.
+pub fn should_stream_contents(info: &FileInfo, settings: &Settings, source: &ContentSource) -> bool {
+    if info.is_placeholder || matches!(source, ContentSource::Ocr) {
+        return false;
+    }
+    let threshold = match settings.content_max_text_bytes {
+        0 => STREAM_MIN_BYTES,
+        limit => limit,
+    };
+    info.size > threshold && is_plain_text_kind(info)
+}
+
+fn is_plain_text_kind(info: &FileInfo) -> bool {
+    !matches!(
+        info.kind,
+        FileKind::Image | FileKind::Video | FileKind::Audio | FileKind::Archive | FileKind::Folder
+    ) && !matches!(info.extension.to_lowercase().as_str(), "pdf" | "docx")
+}
+
+/// Lists the entries of a zip/tar archive, reusing a cached listing when it
+/// was read with at least `max_entries`. Failures yield `None` and are kept
+/// in the cache for `archive_error`.
+pub fn resolve_archive_listing(
+    info: &FileInfo,
+    max_entries: u32,
+    cache: &mut ContentCache,
+) -> Option<ArchiveListing> {
+    let max_entries = max_entries.max(1) as usize;
+    if let Some((cached_max, cached)) = &cache.archive {
+        match cached {
+            Err(_) => return None,
+            Ok(listing) if *cached_max >= max_entries || !listing.truncated => {
+                let mut listing = listing.clone();
+                if listing.entries.len() > max_entries {
+                    listing.entries.truncate(max_entries);
+                    listing.truncated = true;
+                }
+                return Some(listing);
+            }
+            Ok(_) => {}
+        }
+    }
+
+    let listing = if info.is_placeholder {
+        Err("File is an online-only placeholder".to_string())
+    } else {
+        list_archive_entries(&info.path, max_entries).map_err(|err| err.to_string())
+    };
+    cache.archive = Some((max_entries, listing.clone()));
+    listing.ok()
+}
+
+/// Counts the faces in an image once per evaluation. Failures yield `None`
+/// and are kept in the cache for `face_error`.
+pub fn resolve_face_count(info: &FileInfo, cache: &mut ContentCache) -> Option<u32> {
+    cache
+        .faces
+        .get_or_insert_with(|| count_faces(info))
+        .as_ref()
+        .ok()
+        .copied()
+}
+
+/// An image's width and height from its header, once per evaluation.
+pub fn resolve_image_dimensions(
+    info: &FileInfo,
+    settings: &Settings,
+    cache: &mut ContentCache,
+) -> Option<(u32, u32)> {
+    resolve_image_metadata(info, settings, cache).map(|metadata| metadata.dimensions)
+}
+
+/// When an image's EXIF says it was taken, once per evaluation.
+pub fn resolve_exif_date(
+    info: &FileInfo,
+    settings: &Settings,
+    cache: &mut ContentCache,
+) -> Option<DateTime<Utc>> {
+    resolve_image_metadata(info, settings, cache).and_then(|metadata| metadata.taken)
+}
+
+/// Reads an image's header and EXIF without decoding the pixels, once for
+/// all the conditions that ask. Placeholders and images over
+/// `content_max_image_probe_bytes` aren't opened. Failures yield `None` and
+/// are kept in the cache for `image_metadata_error`.
+fn resolve_image_metadata<'a>(
+    info: &FileInfo,
+    settings: &Settings,
+    cache: &'a mut ContentCache,
+) -> Option<&'a ImageMetadata> {
+    cache
+        .image
+        .get_or_insert_with(|| read_image_metadata(info, settings))
+        .as_ref()
+        .ok()
+}
+
+fn read_image_metadata(
+    info: &FileInfo,
+    settings: &Settings,
+) -> std::result::Result<ImageMetadata, String> {
+    if info.is_placeholder {
+        return Err("File is an online-only placeholder".to_string());
+    }
+    let limit = settings.content_max_image_probe_bytes;
+    if limit > 0 && info.size > limit {
+        return Err(format!("Image is over {} MB", limit / (1024 * 1024)));
+    }
+    let mut decoder = image::ImageReader::open(&info.path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|err| err.to_string())?
+        .into_decoder()
+        .map_err(|err| err.to_string())?;
+    let taken = decoder
+        .exif_metadata()
+        .ok()
+        .flatten()
+        .and_then(|exif| Exif::parse(&exif)?.date_taken());
+    Ok(ImageMetadata {
+        dimensions: decoder.dimensions(),
+        taken,
+    })
+}
+
+/// Counts a PDF's pages once per evaluation, from its page tree rather than
+/// by parsing every object. Placeholders and PDFs over
+/// `content_max_pdf_probe_bytes` aren't opened. Failures yield `None` and are
+/// kept in the cache for `page_count_error`.
+pub fn resolve_page_count(
+    info: &FileInfo,
+    settings: &Settings,
+    cache: &mut ContentCache,
+) -> Option<u32> {
+    cache
+        .pages
+        .get_or_insert_with(|| count_pdf_pages(info, settings))
+        .as_ref()
+        .ok()
+        .copied()
+}
+
+fn count_pdf_pages(info: &FileInfo, settings: &Settings) -> std::result::Result<u32, String> {
+    if info.is_placeholder {
+        return Err("File is an online-only placeholder".to_string());
+    }
+    let limit = settings.content_max_pdf_probe_bytes;
+    if limit > 0 && info.size > limit {
+        return Err(format!("PDF is over {} MB", limit / (1024 * 1024)));
+    }
+    let metadata = lopdf::Document::load_metadata(&info.path).map_err(|err| err.to_string())?;
+    // A page tree that can't be followed reads as no pages.
+    match metadata.page_count {
+        0 => Err("No pages found".to_string()),
+        count => Ok(count),
+    }
+}
+
+/// Sniffs the file's MIME type from its first bytes, once per evaluation.
+/// Placeholders aren't opened. Failures, including bytes no known type
+/// starts with, yield `None` and are kept in the cache for `mime_error`.
+pub fn resolve_mime_type(info: &FileInfo, cache: &mut ContentCache) -> Option<String> {
+    cache
+        .mime
+        .get_or_insert_with(|| sniff_mime_type(info))
+        .as_ref()
+        .ok()
+        .cloned()
+}
+
+fn sniff_mime_type(info: &FileInfo) -> std::result::Result<String, String> {
+    if info.is_placeholder {
+        return Err("File is an online-only placeholder".to_string());
+    }
+    infer::get_from_path(&info.path)
+        .map_err(|err| err.to_string())?
+        .map(|kind| kind.mime_type().to_string())
+        .ok_or_else(|| "No known file signature".to_string())
+}
+
+/// Reads a video or audio file's length and codecs from its container's
+/// headers, once per evaluation. Placeholders aren't opened. Failures are
+/// kept in the cache for `media_error`.
+pub fn resolve_media_info(info: &FileInfo, cache: &mut ContentCache) -> Result<MediaInfo> {
+    cache
+        .media
+        .get_or_insert_with(|| read_media(info))
+        .clone()
+        .map_err(|reason| anyhow!(reason))
+}
+
+fn read_media(info: &FileInfo) -> std::result::Result<MediaInfo, String> {
+    if info.is_placeholder {
+        return Err("File is an online-only placeholder".to_string());
+    }
+    let file = fs::File::open(&info.path).map_err(|err| err.to_string())?;
+    read_media_info(&mut std::io::BufReader::new(file)).map_err(|err| err.to_string())
+}
+
+/// Reads what an Is Screenshot condition weighs beyond the name, once per
+/// evaluation. Placeholders aren't opened, so only their name counts.
+pub fn resolve_screenshot_evidence(
+    info: &FileInfo,
+    cache: &mut ContentCache,
+) -> ScreenshotEvidence {
+    cache
+        .screenshot
+        .get_or_insert_with(|| {
+            if info.is_placeholder {
+                ScreenshotEvidence::default()
+            } else {
+                gather_evidence(&info.path)
             }
-            let text = extract_ocr_content(info, settings, ocr, request_id)?;
-            cache.ocr_attempted = true;
-            cache.ocr_text = text.clone();
-            Ok(text)
+        })
+        .clone()
+}
+
+/// Reads the cells a CSV Column condition looks at: the first data row, or up
+/// to `max_rows` of them. Failures yield `None` and are kept in the cache for
+/// `csv_error`.
+pub fn resolve_csv_column(
+    info: &FileInfo,
+    condition: &CsvColumnCondition,
+    cache: &mut ContentCache,
+) -> Option<Vec<String>> {
+    let max_rows = match condition.row {
+        CsvRowScope::First => 1,
+        CsvRowScope::Any { max_rows } => max_rows.max(1) as usize,
+    };
+    let cells = if info.is_placeholder {
+        Err("File is an online-only placeholder".to_string())
+    } else {
+        read_column(&info.path, &condition.column, &condition.delimiter, max_rows)
+            .map_err(|err| err.to_string())
+    };
+    match cells {
+        Ok(cells) => Some(cells),
+        Err(reason) => {
+            cache.csv_error = Some(reason);
+            None
         }
     }
 }
@@ -112,7 +532,19 @@ pub fn make_pdf_searchable(
         }
     }
 
-    let pdfium = load_pdfium()?;
+    let pdfium = match load_pdfium() {
+        Ok(pdfium) => pdfium,
+        Err(err) => {
+            // Rendering needs PDFium, but an already-searchable PDF can still be skipped.
+            if skip_if_text
+                && extract_pdf_text_fallback(source_path, settings.content_max_ocr_pdf_pages)
+                    .is_some()
+            {
+                return Ok(MakePdfSearchableStatus::SkippedAlreadyText);
+            }
+            return Err(err);
+        }
+    };
     let document = pdfium.load_pdf_from_file(source_path, None)?;
 
     if skip_if_text && pdf_has_text(&document, settings.content_max_ocr_pdf_pages)? {
@@ -138,7 +570,11 @@ pub fn make_pdf_searchable(
     Ok(MakePdfSearchableStatus::Completed)
 }
 
-fn extract_text_content(info: &FileInfo, settings: &Settings) -> Result<Option<String>> {
+fn extract_text_content(
+    info: &FileInfo,
+    settings: &Settings,
+    cache: &mut ContentCache,
+) -> Result<Option<String>> {
     if settings.content_max_text_bytes > 0 && info.size > settings.content_max_text_bytes {
         return Ok(None);
     }
@@ -156,7 +592,7 @@ fn extract_text_content(info: &FileInfo, settings: &Settings) -> Result<Option<S
 
     let ext = info.extension.to_lowercase();
     match ext.as_str() {
-        "pdf" => extract_pdf_text(&info.path, settings),
+        "pdf" => extract_pdf_text(&info.path, settings, cache),
         "docx" => extract_docx_text(&info.path),
         _ => extract_plain_text(&info.path),
     }
@@ -228,8 +664,24 @@ fn is_tag(name: &[u8], tag: &[u8]) -> bool {
     name == tag || name.ends_with(&[b':', tag[0]])
 }
 
-fn extract_pdf_text(path: &Path, settings: &Settings) -> Result<Option<String>> {
-    let pdfium = load_pdfium()?;
+fn extract_pdf_text(
+    path: &Path,
+    settings: &Settings,
+    cache: &mut ContentCache,
+) -> Result<Option<String>> {
+    let pdfium = match load_pdfium() {
+        Ok(pdfium) => pdfium,
+        Err(err) => {
+            PDF_FALLBACK_WARNING.call_once(|| {
+                warn!("{err}. Falling back to built-in PDF text extraction (reduced fidelity).");
+            });
+            cache.reduced_fidelity = true;
+            return Ok(extract_pdf_text_fallback(
+                path,
+                settings.content_max_ocr_pdf_pages,
+            ));
+        }
+    };
     let document = pdfium.load_pdf_from_file(path, None)?;
     let max_pages = settings.content_max_ocr_pdf_pages.max(1) as usize;
     let mut text = String::new();
@@ -249,12 +701,56 @@ fn extract_pdf_text(path: &Path, settings: &Settings) -> Result<Option<String>>
     }
 }
 
+/// The first page of the PDF at `path`, drawn `width` pixels wide. Fails with
+/// `PdfiumUnavailable` when PDFium can't be loaded.
+pub(crate) fn render_first_pdf_page(path: &Path, width: u32) -> Result<image::DynamicImage> {
+    let pdfium = load_pdfium()?;
+    let document = pdfium.load_pdf_from_file(path, None)?;
+    let page = document.pages().first()?;
+    let bitmap = page.render_with_config(&PdfRenderConfig::new().set_target_width(width as i32))?;
+    Ok(bitmap.as_image())
+}
+
+/// Decodes text straight from the content streams with lopdf. Used when PDFium
+/// is missing; ignores layout, so word order can differ from PDFium's output.
+/// Documents that can't be parsed or decrypted yield `None` rather than an error.
+fn extract_pdf_text_fallback(path: &Path, max_pages: u32) -> Option<String> {
+    let document = match lopdf::Document::load(path) {
+        Ok(document) => document,
+        Err(err) => {
+            warn!("Fallback PDF parse failed for {}: {}", path.display(), err);
+            return None;
+        }
+    };
+    if document.is_encrypted() {
+        warn!("Skipping encrypted PDF without PDFium: {}", path.display());
+        return None;
+    }
+
+    let page_numbers: Vec<u32> = document
+        .get_pages()
+        .keys()
+        .take(max_pages.max(1) as usize)
+        .copied()
+        .collect();
+    let text = document
+        .extract_text_chunks(&page_numbers)
+        .into_iter()
+        .filter_map(|chunk| chunk.ok())
+        .collect::<String>();
+    if text.trim().is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
 fn extract_ocr_content(
     info: &FileInfo,
     settings: &Settings,
     ocr: &mut OcrManager,
     request_id: Option<&str>,
-) -> Result<Option<String>> {
+) -> Result<Option<OcrText>> {
     if !settings.content_enable_ocr || !ocr.enabled() {
         return Ok(None);
     }
@@ -266,11 +762,14 @@ fn extract_ocr_content(
             return Ok(None);
         }
         let timeout = Duration::from_millis(settings.content_ocr_timeout_image_ms);
-        let text = ocr.recognize_path(&info.path, timeout)?;
-        if text.trim().is_empty() {
+        let result = ocr.recognize_path(&info.path, timeout)?;
+        if result.text.trim().is_empty() {
             Ok(None)
         } else {
-            Ok(Some(text))
+            Ok(Some(OcrText {
+                text: result.text,
+                average_confidence: result.average_confidence,
+            }))
         }
     } else if info.extension.eq_ignore_ascii_case("pdf") {
         if settings.content_max_ocr_pdf_bytes > 0 {
@@ -291,13 +790,30 @@ fn extract_ocr_content(
         if combined.trim().is_empty() {
             Ok(None)
         } else {
-            Ok(Some(combined))
+            Ok(Some(OcrText {
+                text: combined,
+                average_confidence: average_word_confidence(&pages),
+            }))
         }
     } else {
         Ok(None)
     }
 }
 
+fn average_word_confidence(pages: &[PageOcrResult]) -> f32 {
+    let confidences: Vec<f32> = pages
+        .iter()
+        .flat_map(|page| page.lines.iter())
+        .flat_map(|line| line.words.iter())
+        .map(|word| word.confidence)
+        .collect();
+    if confidences.is_empty() {
+        0.0
+    } else {
+        confidences.iter().sum::<f32>() / confidences.len() as f32
+    }
+}
+
 fn pdf_has_text(document: &PdfDocument<'_>, max_pages: u32) -> Result<bool> {
     let limit = max_pages.max(1) as usize;
     for (index, page) in document.pages().iter().enumerate() {
@@ -411,7 +927,7 @@ fn add_text_layer_to_pdf(
             type1_pages += 1;
             append_stream_to_page(&mut doc, *page_id, stream, "F1", font_id)?;
         }
-        let result = save_pdf(doc, source_path, output_path);
+        let result = save_pdf(doc, output_path);
         info!(
             "OCR overlay complete: pages={}, overlays={}, cid_pages={}, type1_pages={}, bytes={}, elapsed_ms={}",
             page_map.len(),
@@ -497,7 +1013,7 @@ fn add_text_layer_to_pdf(
         }
     }
 
-    let result = save_pdf(doc, source_path, output_path);
+    let result = save_pdf(doc, output_path);
     info!(
         "OCR overlay complete: pages={}, overlays={}, cid_pages={}, type1_pages={}, bytes={}, elapsed_ms={}",
         page_map.len(),
@@ -510,18 +1026,16 @@ fn add_text_layer_to_pdf(
     result
 }
 
-fn save_pdf(mut doc: lopdf::Document, source_path: &Path, output_path: &Path) -> Result<()> {
+/// Writes beside `output_path` and renames into place, so a failed save never
+/// clobbers the source or leaves a truncated PDF behind.
+fn save_pdf(mut doc: lopdf::Document, output_path: &Path) -> Result<()> {
     if let Some(parent) = output_path.parent() {
         fs::create_dir_all(parent)?;
     }
 
-    if output_path == source_path {
-        let tmp_path = source_path.with_extension("pdf.tmp");
-        doc.save(&tmp_path)?;
-        fs::rename(tmp_path, source_path)?;
-    } else {
-        doc.save(output_path)?;
-    }
+    let staged = app_temp().beside(output_path)?;
+    doc.save(staged.path())?;
+    staged.persist(output_path)?;
     Ok(())
 }
 
@@ -1094,14 +1608,25 @@ struct WordLayout {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeSet;
+    use std::collections::{BTreeSet, HashMap};
     use std::fs;
     use std::path::Path;
     use std::process::Command;
 
-    use super::{add_text_layer_to_pdf, build_widths_array, load_pdfium, PdfBox, Settings};
+    use super::{
+        add_text_layer_to_pdf, build_widths_array, force_pdfium_unavailable, load_pdfium,
+        make_pdf_searchable, resolve_contents, resolve_page_count, ContentCache, PdfBox,
+        PdfiumUnavailable, Settings,
+    };
+    use crate::core::ocr::OcrManager;
     use crate::core::ocr_geometry::{PageOcrResult, Rect, TextLine, WordBox};
-    use lopdf::{dictionary, Document, Object};
+    use crate::models::{
+        Condition, ConditionGroup, ContentSource, ContentsCondition, FileKind, MatchType,
+        StringOperator,
+    };
+    use filedispatch_core::file_info::FileInfo;
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Document, Object, Stream};
     use tempfile::TempDir;
 
     #[test]
@@ -1384,6 +1909,214 @@ mod tests {
         }
     }
 
+    #[test]
+    fn contents_condition_matches_pdf_without_pdfium() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("invoice.pdf");
+        make_text_doc("INVOICE 4711").save(&path).unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::Contents(ContentsCondition {
+                operator: StringOperator::Contains,
+                value: "invoice 4711".to_string(),
+                case_sensitive: false,
+                source: ContentSource::Text,
+                min_ocr_confidence: None,
+            })],
+        };
+        let settings = Settings::default();
+        let mut ocr = OcrManager::new_placeholder();
+        let mut cache = ContentCache::default();
+
+        force_pdfium_unavailable(true);
+        let result = crate::core::engine::evaluate_group(
+            &group,
+            &info,
+            &settings,
+            &mut ocr,
+            &mut cache,
+            &crate::core::engine::EvaluationOptions::default(),
+        );
+        force_pdfium_unavailable(false);
+
+        assert!(result.unwrap().matched);
+        assert!(cache.reduced_fidelity());
+    }
+
+    #[test]
+    fn fallback_respects_page_limit() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("two-pages.pdf");
+        let mut doc = make_text_doc("FIRST");
+        append_text_page(&mut doc, "SECOND");
+        doc.save(&path).unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+
+        let mut settings = Settings::default();
+        settings.content_max_ocr_pdf_pages = 1;
+        let mut ocr = OcrManager::new_placeholder();
+        let mut cache = ContentCache::default();
+
+        force_pdfium_unavailable(true);
+        let text =
+            resolve_contents(&info, &settings, &mut ocr, &ContentSource::Text, &mut cache, None);
+        force_pdfium_unavailable(false);
+
+        let text = text.unwrap().unwrap();
+        assert!(text.contains("FIRST"));
+        assert!(!text.contains("SECOND"));
+    }
+
+    #[test]
+    fn pages_are_counted_within_the_probe_limit() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("three-pages.pdf");
+        let mut doc = make_text_doc("ONE");
+        append_text_page(&mut doc, "TWO");
+        append_text_page(&mut doc, "THREE");
+        doc.save(&path).unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+
+        let mut settings = Settings::default();
+        let mut cache = ContentCache::default();
+        assert_eq!(resolve_page_count(&info, &settings, &mut cache), Some(3));
+        assert_eq!(cache.page_count_error(), None);
+
+        settings.content_max_pdf_probe_bytes = 1;
+        let mut cache = ContentCache::default();
+        assert_eq!(resolve_page_count(&info, &settings, &mut cache), None);
+        assert!(cache.page_count_error().is_some());
+    }
+
+    #[test]
+    fn text_is_extracted_from_extensions_overridden_to_documents() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("minutes.fdnotes");
+        fs::write(&path, b"PK\x03\x04 quarterly minutes").unwrap();
+        let settings = Settings::default();
+        let mut ocr = OcrManager::new_placeholder();
+
+        let info = FileInfo::from_path(&path).unwrap();
+        let mut cache = ContentCache::default();
+        let text =
+            resolve_contents(&info, &settings, &mut ocr, &ContentSource::Text, &mut cache, None);
+        assert_eq!(text.unwrap(), None);
+
+        filedispatch_core::kinds::set_overrides(&HashMap::from([(
+            "fdnotes".to_string(),
+            FileKind::Document,
+        )]));
+        let info = FileInfo::from_path(&path).unwrap();
+        filedispatch_core::kinds::set_overrides(&HashMap::new());
+        let mut cache = ContentCache::default();
+        let text =
+            resolve_contents(&info, &settings, &mut ocr, &ContentSource::Text, &mut cache, None);
+        assert!(text.unwrap().unwrap().contains("quarterly minutes"));
+    }
+
+    #[test]
+    fn a_kind_override_decides_whether_text_is_extracted() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("notes.txt");
+        fs::write(&path, b"quarterly minutes").unwrap();
+        let settings = Settings::default();
+        let mut ocr = OcrManager::new_placeholder();
+
+        let mut info = FileInfo::from_path(&path).unwrap();
+        let mut cache = ContentCache::default();
+        let text =
+            resolve_contents(&info, &settings, &mut ocr, &ContentSource::Text, &mut cache, None);
+        assert!(text.unwrap().unwrap().contains("quarterly minutes"));
+
+        let overrides = filedispatch_core::simulate::FileInfoOverrides {
+            kind: Some(FileKind::Video),
+            ..Default::default()
+        };
+        assert_eq!(overrides.apply(&mut info), ["kind"]);
+        let mut cache = ContentCache::default();
+        let text =
+            resolve_contents(&info, &settings, &mut ocr, &ContentSource::Text, &mut cache, None);
+        assert_eq!(text.unwrap(), None);
+    }
+
+    #[test]
+    fn make_searchable_reports_pdfium_unavailable() {
+        let temp = TempDir::new().unwrap();
+        let input_path = temp.path().join("scan.pdf");
+        let output_path = temp.path().join("scan-searchable.pdf");
+        make_doc(612.0, 792.0, None, None, None)
+            .save(&input_path)
+            .unwrap();
+        let settings = Settings::default();
+        let mut ocr = OcrManager::new_placeholder();
+
+        force_pdfium_unavailable(true);
+        let result = make_pdf_searchable(
+            &input_path,
+            &output_path,
+            &settings,
+            &mut ocr,
+            None,
+            None,
+            false,
+        );
+        force_pdfium_unavailable(false);
+
+        let err = result.err().expect("expected PDFium error");
+        assert!(err.downcast_ref::<PdfiumUnavailable>().is_some());
+        assert!(!output_path.exists());
+    }
+
+    fn make_text_doc(text: &str) -> Document {
+        let mut doc = make_doc(612.0, 792.0, None, None, None);
+        let page_id = *doc.get_pages().values().next().unwrap();
+        set_page_text(&mut doc, page_id, text);
+        doc
+    }
+
+    fn append_text_page(doc: &mut Document, text: &str) {
+        let pages_id = doc.catalog().unwrap().get(b"Pages").unwrap().as_reference().unwrap();
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => Object::Reference(pages_id),
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        });
+        let pages = doc.get_object_mut(pages_id).unwrap().as_dict_mut().unwrap();
+        let kids = pages.get_mut(b"Kids").unwrap().as_array_mut().unwrap();
+        kids.push(Object::Reference(page_id));
+        let count = kids.len() as i64;
+        pages.set("Count", count);
+        set_page_text(doc, page_id, text);
+    }
+
+    fn set_page_text(doc: &mut Document, page_id: lopdf::ObjectId, text: &str) {
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![72.into(), 720.into()]),
+                Operation::new("Tj", vec![Object::string_literal(text)]),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page = doc.get_object_mut(page_id).unwrap().as_dict_mut().unwrap();
+        page.set("Contents", Object::Reference(content_id));
+        page.set(
+            "Resources",
+            dictionary! { "Font" => dictionary! { "F1" => Object::Reference(font_id) } },
+        );
+    }
+
     fn make_doc(
         width: f32,
         height: f32,
@@ -1826,6 +2559,11 @@ fn is_cjk(ch: char) -> bool {
 }
 
 fn load_pdfium() -> Result<Pdfium> {
+    #[cfg(test)]
+    if FORCE_PDFIUM_UNAVAILABLE.with(|flag| flag.get()) {
+        return Err(PdfiumUnavailable("disabled for test".to_string()).into());
+    }
+
     let mut candidates: Vec<PathBuf> = Vec::new();
 
     if let Ok(explicit) = std::env::var("FILEDISPATCH_PDFIUM_PATH") {
@@ -1876,9 +2614,9 @@ fn load_pdfium() -> Result<Pdfium> {
     match Pdfium::bind_to_system_library() {
         Ok(bindings) => Ok(Pdfium::new(bindings)),
         Err(err) => {
-            let mut message = format!("Failed to load PDFium: {}", err);
+            let mut message = format!("failed to load library: {}", err);
             if let Some(last_error) = last_error {
-                message = format!("Failed to load PDFium: {}", last_error);
+                message = format!("failed to load library: {}", last_error);
             }
             if !tried_locations.is_empty() {
                 message.push_str(&format!(". Tried: {}", tried_locations.join(", ")));
@@ -1886,7 +2624,7 @@ fn load_pdfium() -> Result<Pdfium> {
             message.push_str(
                 ". Set FILEDISPATCH_PDFIUM_PATH or place pdfium.dll next to the executable.",
             );
-            Err(anyhow!(message))
+            Err(PdfiumUnavailable(message).into())
         }
     }
 }
@@ -0,0 +1,212 @@
+//! Each rule's own `{counter}`, kept in the database so numbering carries on
+//! after a restart. A run reserves the rule's next number up front and is
+//! handed it as a capture, so the engine, scans and the approval and conflict
+//! runners never number two files alike. The number stays taken once a move,
+//! copy or rename succeeds, or while one waits on a conflict. Otherwise it's
+//! handed back, so a skipped or failed file leaves no gap unless another run
+//! reserved a later number in the meantime. A move renamed on a collision is
+//! still one file and takes one number.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use filedispatch_core::patterns::{uses_token, COUNTER_CAPTURE};
+
+use crate::core::executor::{ActionErrorKind, ActionOutcome, ActionResultStatus};
+use crate::models::{Action, ActionType, Rule};
+use crate::storage::counter_repo::CounterRepository;
+use crate::storage::database::Database;
+
+/// Whether the rule's moves, copies or renames name files with `{counter}`.
+/// Other rules' counters only count within a run of the app.
+pub fn numbers_files(rule: &Rule) -> bool {
+    rule.actions.iter().any(|action| {
+        let pattern = match action {
+            Action::Move(action) => &action.destination,
+            Action::Copy(action) => &action.destination,
+            Action::SortIntoSubfolder(action) => &action.destination,
+            Action::Rename(action) => &action.pattern,
+            _ => return false,
+        };
+        uses_token(pattern, COUNTER_CAPTURE)
+    })
+}
+
+/// Reserves the rule's next number for the run, when the rule numbers files.
+pub fn number_run(
+    db: &Database,
+    rule: &Rule,
+    captures: &mut HashMap<String, String>,
+) -> Result<()> {
+    if numbers_files(rule) {
+        let next = CounterRepository::new(db.clone()).reserve(&rule.id)?;
+        captures.insert(COUNTER_CAPTURE.to_string(), next.to_string());
+    }
+    Ok(())
+}
+
+/// Hands back the number the run reserved unless one of its moves, copies
+/// or renames succeeded or is waiting on a conflict.
+pub fn settle_counter(
+    db: &Database,
+    rule: &Rule,
+    captures: &HashMap<String, String>,
+    outcomes: &[ActionOutcome],
+) -> Result<()> {
+    let Some(reserved) = captures
+        .get(COUNTER_CAPTURE)
+        .and_then(|value| value.parse().ok())
+        .filter(|_| numbers_files(rule))
+    else {
+        return Ok(());
+    };
+    let numbered = outcomes.iter().any(|outcome| {
+        matches!(
+            outcome.action_type,
+            ActionType::Move
+                | ActionType::Copy
+                | ActionType::SortIntoSubfolder
+                | ActionType::Rename
+        ) && (outcome.status == ActionResultStatus::Success
+            || matches!(
+                outcome.error_kind,
+                Some(ActionErrorKind::ConflictPending(_))
+            ))
+    });
+    if !numbered {
+        CounterRepository::new(db.clone()).release(&rule.id, reserved)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ConditionGroup, ConflictResolution, MatchType, RenameAction};
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::rule_repo::RuleRepository;
+    use chrono::Utc;
+    use tempfile::tempdir;
+
+    fn add_rule(db: &Database, pattern: &str) -> Rule {
+        let folder = FolderRepository::new(db.clone())
+            .create("/watched/invoices", "Invoices")
+            .unwrap();
+        RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id,
+                name: "Number invoices".to_string(),
+                enabled: true,
+                stop_processing: true,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![],
+                },
+                actions: vec![Action::Rename(RenameAction {
+                    pattern: pattern.to_string(),
+                    on_conflict: ConflictResolution::Rename,
+                })],
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap()
+    }
+
+    fn outcome(action_type: ActionType, status: ActionResultStatus) -> ActionOutcome {
+        ActionOutcome {
+            action_type,
+            status,
+            details: None,
+            error: None,
+            error_kind: None,
+        }
+    }
+
+    #[test]
+    fn only_files_that_were_numbered_take_a_number() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let rule = add_rule(&db, "invoice_{counter:0000}.pdf");
+        let run = || {
+            let mut captures = HashMap::new();
+            number_run(&db, &rule, &mut captures).unwrap();
+            captures
+        };
+
+        let first = run();
+        assert_eq!(first[COUNTER_CAPTURE], "1");
+        let failed = [outcome(ActionType::Rename, ActionResultStatus::Error)];
+        settle_counter(&db, &rule, &first, &failed).unwrap();
+        let again = run();
+        assert_eq!(again[COUNTER_CAPTURE], "1");
+
+        let renamed = [
+            outcome(ActionType::Rename, ActionResultStatus::Success),
+            outcome(ActionType::Notify, ActionResultStatus::Success),
+        ];
+        settle_counter(&db, &rule, &again, &renamed).unwrap();
+        settle_counter(&db, &rule, &again, &renamed).unwrap();
+        assert_eq!(run()[COUNTER_CAPTURE], "2");
+
+        // A number can't be handed back once a later one is taken.
+        let (earlier, later) = (run(), run());
+        assert_eq!(later[COUNTER_CAPTURE], "4");
+        settle_counter(&db, &rule, &earlier, &failed).unwrap();
+        settle_counter(&db, &rule, &later, &failed).unwrap();
+        assert_eq!(run()[COUNTER_CAPTURE], "4");
+
+        CounterRepository::new(db.clone()).reset(&rule.id).unwrap();
+        assert_eq!(run()[COUNTER_CAPTURE], "1");
+    }
+
+    #[test]
+    fn runs_on_other_threads_never_share_a_number() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let rule = add_rule(&db, "invoice_{counter}.pdf");
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let (db, rule) = (db.clone(), rule.clone());
+                std::thread::spawn(move || {
+                    (0..50)
+                        .map(|_| {
+                            let mut captures = HashMap::new();
+                            number_run(&db, &rule, &mut captures).unwrap();
+                            captures[COUNTER_CAPTURE].parse::<u64>().unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let mut numbers: Vec<u64> = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect();
+        numbers.sort_unstable();
+        assert_eq!(numbers, (1..=100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rules_without_the_token_keep_no_counter() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let rule = add_rule(&db, "{name}_{counter_note}.{ext}");
+        let mut captures = HashMap::new();
+        number_run(&db, &rule, &mut captures).unwrap();
+        assert!(captures.is_empty());
+    }
+}
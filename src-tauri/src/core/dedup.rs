@@ -0,0 +1,274 @@
+//! Skipping an action an earlier rule already did for the same event. Two
+//! rules that both copy a file to one folder would otherwise leave
+//! "file.pdf" and "file (1).pdf" there, and two that notify with the same
+//! message would show it twice.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use filedispatch_core::file_info::FileInfo;
+use filedispatch_core::patterns::PatternEngine;
+
+use crate::core::executor::{looks_like_directory, ActionOutcome, ActionResultStatus};
+use crate::models::{Action, ActionDetails, ActionType, DedupeActionsSettings};
+use crate::utils::platform::{canonical_path, expand_tilde};
+
+/// Metadata keys naming the rule and action a skipped duplicate repeats.
+pub(crate) const DUPLICATE_OF_RULE_KEY: &str = "duplicate_of_rule";
+pub(crate) const DUPLICATE_OF_ACTION_KEY: &str = "duplicate_of_action";
+
+/// What an action does, as far as telling repeats apart goes. Destinations
+/// are canonical, so `~/Documents` and `/home/me/./Documents` are one place.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PlannedOperation {
+    Move(PathBuf),
+    Copy(PathBuf),
+    SortIntoSubfolder(PathBuf),
+    Notify(String),
+}
+
+impl PlannedOperation {
+    /// What `action` would do, when repeats of it are skipped.
+    pub fn of(
+        engine: &PatternEngine,
+        action: &Action,
+        info: &FileInfo,
+        captures: &HashMap<String, String>,
+        settings: &DedupeActionsSettings,
+    ) -> Option<Self> {
+        let destination = |template: &str, force_dir: bool| {
+            let mut dest = expand_tilde(&engine.resolve(template, info, captures));
+            if force_dir || dest.is_dir() || looks_like_directory(&dest) {
+                dest = dest.join(&info.os_name);
+            }
+            canonical_path(&dest)
+        };
+        match action {
+            Action::Move(action) if settings.file_operations => {
+                Some(Self::Move(destination(&action.destination, false)))
+            }
+            Action::Copy(action) if settings.file_operations => {
+                Some(Self::Copy(destination(&action.destination, false)))
+            }
+            Action::SortIntoSubfolder(action) if settings.file_operations => Some(
+                Self::SortIntoSubfolder(destination(&action.destination, true)),
+            ),
+            Action::Notify(action) if settings.notify => Some(Self::Notify(engine.resolve(
+                &action.message,
+                info,
+                captures,
+            ))),
+            _ => None,
+        }
+    }
+
+    fn action_type(&self) -> ActionType {
+        match self {
+            Self::Move(_) => ActionType::Move,
+            Self::Copy(_) => ActionType::Copy,
+            Self::SortIntoSubfolder(_) => ActionType::SortIntoSubfolder,
+            Self::Notify(_) => ActionType::Notify,
+        }
+    }
+}
+
+/// The action that did an operation first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Performed {
+    pub rule_name: String,
+    /// Position of the action in its rule.
+    pub action_index: usize,
+    /// Where the file went, for a move or sort.
+    pub destination: Option<PathBuf>,
+}
+
+impl Performed {
+    /// The outcome for a repeat of this operation, which leaves the file
+    /// where the first one put it.
+    pub fn duplicate_outcome(&self, op: &PlannedOperation, source: &Path) -> ActionOutcome {
+        let mut metadata = HashMap::new();
+        metadata.insert(DUPLICATE_OF_RULE_KEY.to_string(), self.rule_name.clone());
+        metadata.insert(
+            DUPLICATE_OF_ACTION_KEY.to_string(),
+            (self.action_index + 1).to_string(),
+        );
+        ActionOutcome {
+            action_type: op.action_type(),
+            status: ActionResultStatus::Skipped,
+            details: Some(ActionDetails {
+                source_path: source.to_string_lossy().to_string(),
+                destination_path: self
+                    .destination
+                    .as_ref()
+                    .map(|dest| dest.to_string_lossy().to_string()),
+                metadata,
+            }),
+            error: Some(format!(
+                "Duplicate of rule \"{}\" action {} in this event",
+                self.rule_name,
+                self.action_index + 1
+            )),
+            error_kind: None,
+        }
+    }
+}
+
+/// The operations done so far for one event, shared by every rule that
+/// runs for it.
+#[derive(Debug, Clone, Default)]
+pub struct EventActions(Arc<Mutex<HashMap<PlannedOperation, Performed>>>);
+
+impl EventActions {
+    pub fn earlier(&self, op: &PlannedOperation) -> Option<Performed> {
+        self.0.lock().ok()?.get(op).cloned()
+    }
+
+    /// Records `op` as done by `performed`, unless something did it first.
+    pub fn record(&self, op: PlannedOperation, performed: Performed) {
+        if let Ok(mut done) = self.0.lock() {
+            done.entry(op).or_insert(performed);
+        }
+    }
+}
+
+// Scopes sharing an event's actions are the same scope.
+impl PartialEq for EventActions {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for EventActions {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ConflictResolution, CopyAction, NotifyAction};
+    use tempfile::tempdir;
+
+    fn copy_to(destination: &str) -> Action {
+        Action::Copy(CopyAction {
+            destination: destination.to_string(),
+            on_conflict: ConflictResolution::Rename,
+            skip_duplicates: false,
+            use_reflink: None,
+            destination_permissions: Default::default(),
+            quarantine: Default::default(),
+            bring_companions: None,
+            auto_partition: None,
+        })
+    }
+
+    fn notify(message: &str) -> Action {
+        Action::Notify(NotifyAction {
+            message: message.to_string(),
+            sound: None,
+            generate_thumbnail: false,
+        })
+    }
+
+    #[test]
+    fn notifications_are_deduped_by_default_and_file_operations_are_not() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        std::fs::write(&path, b"report").unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+        let engine = PatternEngine::new();
+        let settings = DedupeActionsSettings::default();
+        let captures = HashMap::new();
+
+        let planned = PlannedOperation::of(
+            &engine,
+            &notify("{name} arrived"),
+            &info,
+            &captures,
+            &settings,
+        );
+        assert_eq!(
+            planned,
+            Some(PlannedOperation::Notify("report arrived".to_string()))
+        );
+        let out = dir.path().join("out/");
+        let copy = copy_to(&out.to_string_lossy());
+        assert_eq!(
+            PlannedOperation::of(&engine, &copy, &info, &captures, &settings),
+            None
+        );
+
+        let settings = DedupeActionsSettings {
+            notify: false,
+            file_operations: true,
+        };
+        assert_eq!(
+            PlannedOperation::of(&engine, &notify("hi"), &info, &captures, &settings),
+            None
+        );
+        assert!(PlannedOperation::of(&engine, &copy, &info, &captures, &settings).is_some());
+    }
+
+    #[test]
+    fn spellings_of_one_destination_are_one_operation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        std::fs::write(&path, b"report").unwrap();
+        std::fs::create_dir(dir.path().join("out")).unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+        let engine = PatternEngine::new();
+        let settings = DedupeActionsSettings {
+            notify: true,
+            file_operations: true,
+        };
+        let plan = |destination: String| {
+            PlannedOperation::of(
+                &engine,
+                &copy_to(&destination),
+                &info,
+                &HashMap::new(),
+                &settings,
+            )
+            .unwrap()
+        };
+
+        let root = dir.path().to_string_lossy().to_string();
+        let plain = plan(format!("{root}/out"));
+        assert_eq!(plain, plan(format!("{root}/./elsewhere/../out/")));
+        assert_eq!(
+            plain,
+            PlannedOperation::Copy(dir.path().canonicalize().unwrap().join("out/report.pdf"))
+        );
+        assert_ne!(plain, plan(format!("{root}/other/")));
+    }
+
+    #[test]
+    fn the_first_action_to_do_an_operation_is_remembered() {
+        let actions = EventActions::default();
+        let op = PlannedOperation::Notify("done".to_string());
+        assert_eq!(actions.earlier(&op), None);
+
+        let first = Performed {
+            rule_name: "Catch-all".to_string(),
+            action_index: 1,
+            destination: None,
+        };
+        actions.record(op.clone(), first.clone());
+        actions.record(
+            op.clone(),
+            Performed {
+                rule_name: "Invoices".to_string(),
+                action_index: 0,
+                destination: None,
+            },
+        );
+        assert_eq!(actions.earlier(&op), Some(first.clone()));
+        assert!(actions.clone() == actions);
+        assert!(EventActions::default() != actions);
+
+        let outcome = first.duplicate_outcome(&op, Path::new("/inbox/a.pdf"));
+        assert_eq!(outcome.status, ActionResultStatus::Skipped);
+        assert_eq!(
+            outcome.error.as_deref(),
+            Some("Duplicate of rule \"Catch-all\" action 2 in this event")
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crossbeam_channel::Sender;
+
+use crate::core::retry::claim_due_events;
+use crate::core::watcher::FileEvent;
+use crate::models::{EngineStatus, EventSource, OfflineDestination};
+use crate::storage::database::Database;
+use crate::storage::retry_repo::RetryRepository;
+use crate::utils::volumes::is_mounted;
+
+/// How often the monitor looks at the volumes transfers are waiting for.
+const TICK: Duration = Duration::from_secs(15);
+
+/// Destination volumes that are offline with transfers waiting for them.
+pub fn offline_destinations(db: &Database) -> Result<Vec<OfflineDestination>> {
+    Ok(RetryRepository::new(db.clone())
+        .queued_by_volume()?
+        .into_iter()
+        .map(|(volume, queued_files)| OfflineDestination {
+            path: PathBuf::from(volume),
+            queued_files,
+        })
+        .filter(|destination| !is_mounted(&destination.path))
+        .collect())
+}
+
+/// Watches the volumes held-back transfers are waiting for and sends those
+/// transfers out as soon as their volume is mounted again, rather than at
+/// their next scheduled retry.
+pub struct DestinationMonitor {
+    db: Database,
+    events: Sender<FileEvent>,
+    paused: Arc<AtomicBool>,
+    status: Arc<Mutex<EngineStatus>>,
+}
+
+impl DestinationMonitor {
+    pub fn new(
+        db: Database,
+        events: Sender<FileEvent>,
+        paused: Arc<AtomicBool>,
+        status: Arc<Mutex<EngineStatus>>,
+    ) -> Self {
+        Self {
+            db,
+            events,
+            paused,
+            status,
+        }
+    }
+
+    pub fn start(self) {
+        thread::spawn(move || loop {
+            if let Err(err) = self.tick(Utc::now()) {
+                eprintln!("Destination monitor error: {err}");
+            }
+            thread::sleep(TICK);
+        });
+    }
+
+    pub(crate) fn tick(&self, now: DateTime<Utc>) -> Result<()> {
+        let repo = RetryRepository::new(self.db.clone());
+        let mut released = 0;
+        for (volume, _) in repo.queued_by_volume()? {
+            if is_mounted(Path::new(&volume)) {
+                released += repo.release_volume(&volume, now)?;
+            }
+        }
+        // While paused they stay due, and the retry scheduler sends them once
+        // processing resumes.
+        if released > 0 && !self.paused.load(Ordering::SeqCst) {
+            for event in claim_due_events(&self.db, now)? {
+                let _ = self.events.send(FileEvent {
+                    source: EventSource::Sweep,
+                    ..event
+                });
+            }
+        }
+        let offline = offline_destinations(&self.db)?;
+        if let Ok(mut status) = self.status.lock() {
+            status.offline_destinations = offline;
+        }
+        Ok(())
+    }
+}
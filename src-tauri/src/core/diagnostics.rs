@@ -0,0 +1,549 @@
+//! What a service manager or monitoring needs to tell whether File Dispatch
+//! is healthy without reading its logs: an exit code for each way startup can
+//! fail, a status file kept current while it runs, and `--check`, which
+//! validates the settings and database and exits.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use filedispatch_core::kinds;
+use filedispatch_core::timezone::parse_timezone;
+use rusqlite_migration::MigrationDefinitionError;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::core::health::{self, CheckDepth};
+use crate::core::unique_name;
+use crate::models::{EngineError, EngineStatus, HealthSeverity, Settings};
+use crate::storage::database::Database;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Exit code of `--check` when it finds an error-severity problem.
+pub const EXIT_CHECK_FAILED: i32 = 1;
+
+/// How often the status file is rewritten unless `--status-interval` says.
+const DEFAULT_STATUS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A reason File Dispatch can't start, each with its own exit code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartupFailure {
+    /// The command line couldn't be read.
+    Usage(String),
+    /// The database couldn't be opened or migrated.
+    Database(String),
+    /// The database was written by a newer version.
+    SchemaTooNew(String),
+    /// The settings don't load or don't validate.
+    Config(String),
+    /// The system won't let folders be watched.
+    Watch(String),
+}
+
+impl StartupFailure {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Usage(_) => 2,
+            Self::Database(_) => 10,
+            Self::SchemaTooNew(_) => 11,
+            Self::Config(_) => 12,
+            Self::Watch(_) => 13,
+        }
+    }
+
+    /// Tells a database from a newer version apart from one that doesn't open.
+    pub fn database(err: anyhow::Error) -> Self {
+        let too_new = err.chain().any(|cause| {
+            matches!(
+                cause.downcast_ref::<rusqlite_migration::Error>(),
+                Some(rusqlite_migration::Error::MigrationDefinition(
+                    MigrationDefinitionError::DatabaseTooFarAhead
+                ))
+            )
+        });
+        if too_new {
+            Self::SchemaTooNew(format!(
+                "the database was written by a newer version than {VERSION}"
+            ))
+        } else {
+            Self::Database(format!("{err:#}"))
+        }
+    }
+
+    pub fn exit(&self) -> ! {
+        eprintln!("File Dispatch couldn't start: {self}");
+        std::process::exit(self.exit_code())
+    }
+}
+
+impl fmt::Display for StartupFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(message) => write!(f, "{message}"),
+            Self::Database(message) => write!(f, "database: {message}"),
+            Self::SchemaTooNew(message) => write!(f, "{message}"),
+            Self::Config(message) => write!(f, "settings: {message}"),
+            Self::Watch(message) => write!(f, "watching folders: {message}"),
+        }
+    }
+}
+
+/// The command line options File Dispatch understands. Anything else is
+/// left alone, since the system and Tauri pass arguments of their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliOptions {
+    /// Validate settings and database, print the report and exit.
+    pub check: bool,
+    pub status_file: Option<PathBuf>,
+    pub status_interval: Duration,
+    /// The database to use instead of the default one.
+    pub database: Option<PathBuf>,
+    /// The settings file `--check` reads instead of the default one.
+    pub settings: Option<PathBuf>,
+}
+
+impl Default for CliOptions {
+    fn default() -> Self {
+        Self {
+            check: false,
+            status_file: None,
+            status_interval: DEFAULT_STATUS_INTERVAL,
+            database: None,
+            settings: None,
+        }
+    }
+}
+
+impl CliOptions {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, StartupFailure> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| StartupFailure::Usage(format!("{name} needs a value")))
+            };
+            match arg.as_str() {
+                "--check" => options.check = true,
+                "--status-file" => options.status_file = Some(value(&arg)?.into()),
+                "--database" => options.database = Some(value(&arg)?.into()),
+                "--settings" => options.settings = Some(value(&arg)?.into()),
+                "--status-interval" => {
+                    let seconds = value(&arg)?;
+                    options.status_interval = seconds
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|seconds| *seconds > 0)
+                        .map(Duration::from_secs)
+                        .ok_or_else(|| {
+                            StartupFailure::Usage(format!(
+                                "--status-interval takes a number of seconds, not {seconds:?}"
+                            ))
+                        })?;
+                }
+                _ => {}
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub fn open_database(path: Option<&Path>) -> Result<Database, StartupFailure> {
+    match path {
+        Some(path) => Database::new_with_path(path.to_path_buf()),
+        None => Database::new(),
+    }
+    .map_err(StartupFailure::database)
+}
+
+/// Where the settings store lives: the app's data folder, named after its
+/// bundle identifier.
+pub fn default_settings_path() -> Option<PathBuf> {
+    let dirs = directories::BaseDirs::new()?;
+    Some(
+        dirs.data_dir()
+            .join("com.filedispatch")
+            .join("settings.json"),
+    )
+}
+
+/// The settings kept under `settings` in the store, or the defaults when
+/// none were saved yet.
+pub fn settings_from_store(value: Option<&Value>) -> Result<Settings, StartupFailure> {
+    let settings = match value {
+        Some(value) => serde_json::from_value::<Settings>(value.clone())
+            .map_err(|err| StartupFailure::Config(err.to_string()))?,
+        None => Settings::default(),
+    };
+    validate_settings(&settings).map_err(StartupFailure::Config)?;
+    Ok(settings)
+}
+
+/// Reads the settings store at `path`, which may not exist yet.
+pub fn load_settings(path: &Path) -> Result<Settings, StartupFailure> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+        Err(err) => return Err(StartupFailure::Config(format!("{}: {err}", path.display()))),
+    };
+    let store: Value = serde_json::from_str(&content)
+        .map_err(|err| StartupFailure::Config(format!("{}: {err}", path.display())))?;
+    settings_from_store(store.get("settings"))
+}
+
+/// The checks saving settings makes, for settings that didn't come through
+/// the app.
+pub fn validate_settings(settings: &Settings) -> Result<(), String> {
+    if let Some(zone) = settings
+        .default_timezone
+        .as_deref()
+        .filter(|zone| !zone.trim().is_empty())
+    {
+        parse_timezone(zone).map_err(|err| format!("Default time zone: {err}"))?;
+    }
+    kinds::normalize_overrides(&settings.kind_overrides)
+        .map_err(|err| format!("Kind mappings: {err}"))?;
+    unique_name::validate_format(&settings.unique_suffix_format)
+        .map_err(|err| format!("Unique suffix format: {err}"))
+}
+
+/// What `--check` prints and the code it exits with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckOutcome {
+    pub report: Value,
+    pub exit_code: i32,
+}
+
+/// Loads the settings and database the way startup would and runs the full
+/// health check on them. Fails on anything startup would fail on, and on
+/// error-severity findings.
+pub fn check(options: &CliOptions) -> CheckOutcome {
+    let settings = match options.settings.clone().or_else(default_settings_path) {
+        Some(path) => load_settings(&path).map(|_| ()),
+        None => Ok(()),
+    };
+    let report = settings
+        .and_then(|()| open_database(options.database.as_deref()))
+        .and_then(|db| {
+            health::check(&db, CheckDepth::Full)
+                .map(|report| (db, report))
+                .map_err(StartupFailure::database)
+        });
+    let (db, report) = match report {
+        Ok(checked) => checked,
+        Err(failure) => {
+            return CheckOutcome {
+                report: json!({
+                    "ok": false,
+                    "version": VERSION,
+                    "error": failure.to_string(),
+                    "exitCode": failure.exit_code(),
+                }),
+                exit_code: failure.exit_code(),
+            }
+        }
+    };
+    let count = |severity| {
+        report
+            .issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    };
+    let errors = count(HealthSeverity::Error);
+    CheckOutcome {
+        report: json!({
+            "ok": errors == 0,
+            "version": VERSION,
+            "database": db.path(),
+            "errors": errors,
+            "warnings": count(HealthSeverity::Warning),
+            "issues": report.issues,
+            "checkedAt": report.checked_at,
+        }),
+        exit_code: if errors == 0 { 0 } else { EXIT_CHECK_FAILED },
+    }
+}
+
+/// What the status file says.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusSnapshot {
+    pub pid: u32,
+    pub version: &'static str,
+    pub started_at: DateTime<Utc>,
+    pub written_at: DateTime<Utc>,
+    /// Whether this is the last snapshot before the process exits.
+    pub stopped: bool,
+    pub paused: bool,
+    pub queue_depth: usize,
+    pub processed_count: u64,
+    pub health_issues: usize,
+    pub unavailable_folders: usize,
+    pub broken_reference_rules: usize,
+    pub last_error: Option<EngineError>,
+}
+
+/// A small JSON file describing the running engine, replaced as a whole so
+/// a reader never sees half of it.
+pub struct StatusFile {
+    path: PathBuf,
+    started_at: DateTime<Utc>,
+}
+
+impl StatusFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            started_at: Utc::now(),
+        }
+    }
+
+    pub fn snapshot(&self, status: &EngineStatus, stopped: bool) -> StatusSnapshot {
+        StatusSnapshot {
+            pid: std::process::id(),
+            version: VERSION,
+            started_at: self.started_at,
+            written_at: Utc::now(),
+            stopped,
+            paused: status.paused,
+            queue_depth: status.queue_depth,
+            processed_count: status.processed_count,
+            health_issues: status.health_issues.len(),
+            unavailable_folders: status.unavailable_folders.len(),
+            broken_reference_rules: status.broken_reference_rules,
+            last_error: status.last_error.clone(),
+        }
+    }
+
+    pub fn write(&self, status: &EngineStatus, stopped: bool) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(&self.snapshot(status, stopped))?;
+        write_atomically(&self.path, &json)
+    }
+
+    /// Rewrites the file every `interval`. Failing to write is logged and
+    /// tried again next time; it never stops the engine.
+    pub fn start(self: Arc<Self>, status: Arc<Mutex<EngineStatus>>, interval: Duration) {
+        std::thread::spawn(move || loop {
+            self.write_logged(&status, false);
+            std::thread::sleep(interval);
+        });
+    }
+
+    pub fn write_logged(&self, status: &Mutex<EngineStatus>, stopped: bool) {
+        let Ok(status) = status.lock().map(|status| status.clone()) else {
+            return;
+        };
+        if let Err(err) = self.write(&status, stopped) {
+            eprintln!("Status file error ({}): {err}", self.path.display());
+        }
+    }
+}
+
+/// Writes `contents` next to `path` and renames it into place, which
+/// replaces the file in one step.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "status file has no name"))?;
+    let staged = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let result = fs::File::create(&staged).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    match result.and_then(|()| fs::rename(&staged, path)) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            let _ = fs::remove_file(&staged);
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::folder_repo::FolderRepository;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::tempdir;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn options_are_read_and_unknown_arguments_left_alone() {
+        let options = CliOptions::parse(args(&[
+            "-psn_0_1234",
+            "--status-file",
+            "/run/fd/status.json",
+            "--status-interval",
+            "5",
+        ]))
+        .unwrap();
+        assert_eq!(
+            options.status_file,
+            Some(PathBuf::from("/run/fd/status.json"))
+        );
+        assert_eq!(options.status_interval, Duration::from_secs(5));
+        assert!(!options.check);
+
+        let missing = CliOptions::parse(args(&["--status-file"])).unwrap_err();
+        assert_eq!(missing.exit_code(), 2);
+        assert!(CliOptions::parse(args(&["--status-interval", "0"])).is_err());
+    }
+
+    #[test]
+    fn a_database_that_wont_open_and_one_from_a_newer_version_exit_differently() {
+        let dir = tempdir().unwrap();
+        let garbage = dir.path().join("garbage.db");
+        fs::write(&garbage, vec![0x5a; 8192]).unwrap();
+        let unopenable = open_database(Some(&garbage)).err().unwrap();
+        assert!(matches!(unopenable, StartupFailure::Database(_)));
+        assert_eq!(unopenable.exit_code(), 10);
+
+        let path = dir.path().join("newer.db");
+        let db = Database::new_with_path(path.clone()).unwrap();
+        db.with_conn(|conn| {
+            conn.pragma_update(None, "user_version", 9999)?;
+            Ok(())
+        })
+        .unwrap();
+        drop(db);
+        let too_new = open_database(Some(&path)).err().unwrap();
+        assert!(matches!(too_new, StartupFailure::SchemaTooNew(_)));
+        assert_eq!(too_new.exit_code(), 11);
+    }
+
+    #[test]
+    fn invalid_settings_are_a_config_failure() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        assert!(load_settings(&path).is_ok());
+
+        fs::write(&path, r#"{"settings": {"uniqueSuffixFormat": "{millis}"}}"#).unwrap();
+        let failure = load_settings(&path).unwrap_err();
+        assert_eq!(failure.exit_code(), 12);
+        fs::write(&path, "{").unwrap();
+        assert_eq!(load_settings(&path).unwrap_err().exit_code(), 12);
+    }
+
+    #[test]
+    fn check_reports_a_healthy_database_and_fails_a_corrupted_one() {
+        let dir = tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        fs::create_dir(&inbox).unwrap();
+        let options = CliOptions {
+            check: true,
+            database: Some(dir.path().join("fd.db")),
+            settings: Some(dir.path().join("settings.json")),
+            ..CliOptions::default()
+        };
+        let db = open_database(options.database.as_deref()).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&inbox.to_string_lossy(), "Inbox")
+            .unwrap();
+
+        let healthy = check(&options);
+        assert_eq!(healthy.exit_code, 0);
+        assert_eq!(healthy.report["ok"], json!(true));
+        assert_eq!(healthy.report["errors"], json!(0));
+        assert_eq!(healthy.report["issues"], json!([]));
+        assert_eq!(healthy.report["version"], json!(VERSION));
+        assert!(healthy.report["checkedAt"].is_string());
+
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO rules (id, folder_id, name, conditions, actions, position, created_at, updated_at) VALUES ('r1', ?1, 'Torn', '{\"matchType\":', '[]', 0, ?2, ?2)",
+                rusqlite::params![folder.id, Utc::now().to_rfc3339()],
+            )?;
+            Ok(())
+        })
+        .unwrap();
+        let corrupted = check(&options);
+        assert_eq!(corrupted.exit_code, EXIT_CHECK_FAILED);
+        assert_eq!(corrupted.report["ok"], json!(false));
+        assert_eq!(corrupted.report["errors"], json!(1));
+        assert_eq!(
+            corrupted.report["issues"][0]["kind"],
+            json!("unreadableRule")
+        );
+        assert_eq!(corrupted.report["issues"][0]["ruleId"], json!("r1"));
+
+        let garbage = CliOptions {
+            database: Some(dir.path().join("garbage.db")),
+            ..options
+        };
+        fs::write(garbage.database.as_ref().unwrap(), vec![0x5a; 8192]).unwrap();
+        let unopenable = check(&garbage);
+        assert_eq!(unopenable.exit_code, 10);
+        assert_eq!(unopenable.report["ok"], json!(false));
+        assert_eq!(unopenable.report["exitCode"], json!(10));
+        assert!(unopenable.report["error"].is_string());
+    }
+
+    #[test]
+    fn readers_never_see_a_partly_written_status_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("status.json");
+        let file = StatusFile::new(path.clone());
+        let status = EngineStatus {
+            last_error: Some(EngineError {
+                message: "x".repeat(64 * 1024),
+                occurred_at: Utc::now(),
+            }),
+            ..EngineStatus::default()
+        };
+        file.write(&status, false).unwrap();
+
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let (done, path) = (done.clone(), path.clone());
+            std::thread::spawn(move || {
+                let mut reads = 0;
+                loop {
+                    let content = fs::read(&path).unwrap();
+                    let snapshot: Value = serde_json::from_slice(&content).unwrap();
+                    assert_eq!(snapshot["pid"], json!(std::process::id()));
+                    reads += 1;
+                    if done.load(Ordering::SeqCst) {
+                        return reads;
+                    }
+                }
+            })
+        };
+        for processed in 0..200 {
+            let status = EngineStatus {
+                processed_count: processed,
+                ..status.clone()
+            };
+            file.write(&status, processed == 199).unwrap();
+        }
+        done.store(true, Ordering::SeqCst);
+        assert!(reader.join().unwrap() > 0);
+
+        let last: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(last["processedCount"], json!(199));
+        assert_eq!(last["stopped"], json!(true));
+        let leftovers = fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(leftovers, 1);
+    }
+
+    #[test]
+    fn a_status_file_that_cant_be_written_is_an_error_not_a_panic() {
+        let dir = tempdir().unwrap();
+        let file = StatusFile::new(dir.path().join("missing").join("status.json"));
+        let status = Mutex::new(EngineStatus::default());
+        assert!(file.write(&EngineStatus::default(), false).is_err());
+        file.write_logged(&status, true);
+    }
+}
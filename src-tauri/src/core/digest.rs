@@ -0,0 +1,430 @@
+//! The daily digest: one notification at a set local time summing up what the
+//! rules did that day, for people who'd rather not hear about every file.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use filedispatch_core::patterns::format_size;
+use tauri::{AppHandle, Emitter};
+
+use crate::core::error_notify::ErrorNotifier;
+use crate::models::{ActionDetails, DailyDigest, LogEntry, LogStatus, Settings};
+use crate::storage::database::Database;
+use crate::storage::log_repo::LogRepository;
+
+/// Sent with the `DailyDigest` whenever a digest goes out.
+pub const DIGEST_READY_EVENT: &str = "digest://ready";
+
+/// How often the scheduler looks at the clock.
+const TICK: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How late a digest still goes out, e.g. when the machine was asleep at the
+/// digest time. A day missed by more than this is skipped.
+const GRACE_HOURS: i64 = 3;
+
+/// Sends the digest once a day at the time in the settings.
+pub struct DigestScheduler {
+    db: Database,
+    settings: Arc<Mutex<Settings>>,
+    notifier: Arc<ErrorNotifier>,
+    tracker: DigestTracker,
+}
+
+impl DigestScheduler {
+    pub fn new(db: Database, settings: Arc<Mutex<Settings>>, notifier: Arc<ErrorNotifier>) -> Self {
+        // A restart after the digest went out mustn't send it again.
+        let last_day = LogRepository::new(db.clone())
+            .last_digest_day()
+            .unwrap_or_default();
+        Self {
+            db,
+            settings,
+            notifier,
+            tracker: DigestTracker::new(last_day),
+        }
+    }
+
+    pub fn start(mut self, app_handle: AppHandle) {
+        thread::spawn(move || loop {
+            match self.tick(Local::now().naive_local()) {
+                Ok(Some(digest)) => {
+                    let _ = app_handle.emit(DIGEST_READY_EVENT, &digest);
+                }
+                Ok(None) => {}
+                Err(err) => eprintln!("Daily digest error: {err}"),
+            }
+            thread::sleep(TICK);
+        });
+    }
+
+    /// Sends the digest if one is due at `now` (local time) and returns it.
+    fn tick(&mut self, now: NaiveDateTime) -> Result<Option<DailyDigest>> {
+        let settings = self.settings.lock().map(|s| s.clone()).unwrap_or_default();
+        let options = &settings.daily_digest;
+        if !options.enabled {
+            return Ok(None);
+        }
+        let Some(day) = self.tracker.due(options.time, now) else {
+            return Ok(None);
+        };
+        let mut digest = collect(&self.db, day, now, options.include_errors)?;
+        if digest.is_empty() && options.skip_empty_days {
+            return Ok(None);
+        }
+        digest.log_id = Some(write_log(&self.db, &digest, now.date())?);
+        if settings.show_notifications {
+            self.notifier.report_digest(message(&digest, now.date()));
+        }
+        Ok(Some(digest))
+    }
+}
+
+/// Remembers the last day a digest went out.
+#[derive(Debug, Default)]
+pub struct DigestTracker {
+    last_day: Option<NaiveDate>,
+}
+
+impl DigestTracker {
+    pub fn new(last_day: Option<NaiveDate>) -> Self {
+        Self { last_day }
+    }
+
+    /// The day whose digest is due at `now`, which then counts as sent. Only
+    /// the latest digest time is considered, so waking from a long sleep owes
+    /// one digest at most.
+    pub fn due(&mut self, time: NaiveTime, now: NaiveDateTime) -> Option<NaiveDate> {
+        let mut at = now.date().and_time(time);
+        if at > now {
+            at -= Duration::days(1);
+        }
+        if now - at > Duration::hours(GRACE_HOURS) {
+            return None;
+        }
+        let day = at.date();
+        if self.last_day.is_some_and(|last| last >= day) {
+            return None;
+        }
+        self.last_day = Some(day);
+        Some(day)
+    }
+}
+
+/// What happened on `day` between local midnight and `until`, or the end of
+/// the day if that's earlier.
+pub fn collect(
+    db: &Database,
+    day: NaiveDate,
+    until: NaiveDateTime,
+    include_errors: bool,
+) -> Result<DailyDigest> {
+    let start = day.and_time(NaiveTime::MIN);
+    let end = until.min(start + Duration::days(1));
+    let (actions, errors) =
+        LogRepository::new(db.clone()).action_totals(local_to_utc(start), local_to_utc(end))?;
+    Ok(DailyDigest {
+        day,
+        actions,
+        errors: if include_errors { errors } else { 0 },
+        log_id: None,
+    })
+}
+
+/// Local times skipped by a DST change are taken as UTC; the hour they're off
+/// by doesn't matter for a day's totals.
+fn local_to_utc(at: NaiveDateTime) -> DateTime<Utc> {
+    Local
+        .from_local_datetime(&at)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+        .unwrap_or_else(|| at.and_utc())
+}
+
+/// Records the digest in the activity log, with each action type's count and
+/// bytes in the metadata, and returns the entry's id.
+fn write_log(db: &Database, digest: &DailyDigest, today: NaiveDate) -> Result<String> {
+    let mut metadata = HashMap::new();
+    metadata.insert("day".to_string(), digest.day.to_string());
+    metadata.insert("errors".to_string(), digest.errors.to_string());
+    for total in &digest.actions {
+        metadata.insert(
+            format!("{}_count", total.action_type),
+            total.count.to_string(),
+        );
+        metadata.insert(
+            format!("{}_bytes", total.action_type),
+            total.bytes.to_string(),
+        );
+    }
+    let entry = LogRepository::new(db.clone()).insert(LogEntry {
+        id: String::new(),
+        rule_id: None,
+        rule_name: None,
+        file_path: String::new(),
+        action_type: "digest".to_string(),
+        action_detail: Some(ActionDetails {
+            source_path: String::new(),
+            destination_path: None,
+            metadata,
+        }),
+        status: LogStatus::Success,
+        error_message: Some(message(digest, today)),
+        created_at: Utc::now(),
+    })?;
+    Ok(entry.id)
+}
+
+/// "Today File Dispatch moved 42 files (1.2 GB), archived 7, and hit 2
+/// errors." Action types without a verb are counted together at the end.
+pub fn message(digest: &DailyDigest, today: NaiveDate) -> String {
+    let when = if digest.day == today {
+        "Today".to_string()
+    } else if Some(digest.day) == today.pred_opt() {
+        "Yesterday".to_string()
+    } else {
+        format!("On {}", digest.day.format("%Y-%m-%d"))
+    };
+
+    let mut parts = Vec::new();
+    let mut other = 0;
+    for total in &digest.actions {
+        let Some(verb) = verb(&total.action_type) else {
+            other += total.count;
+            continue;
+        };
+        let mut part = if parts.is_empty() {
+            format!("{verb} {}", files(total.count))
+        } else {
+            format!("{verb} {}", total.count)
+        };
+        if total.bytes > 0 {
+            part.push_str(&format!(" ({})", format_size(total.bytes, "")));
+        }
+        parts.push(part);
+    }
+    if other > 0 {
+        let plural = if other == 1 { "" } else { "s" };
+        parts.push(format!("ran {other} other action{plural}"));
+    }
+    if digest.errors > 0 {
+        let plural = if digest.errors == 1 { "" } else { "s" };
+        parts.push(format!("hit {} error{plural}", digest.errors));
+    }
+
+    match parts.len() {
+        0 => format!("{when} File Dispatch had nothing to do."),
+        1 => format!("{when} File Dispatch {}.", parts[0]),
+        _ => {
+            let last = parts.pop().unwrap_or_default();
+            format!("{when} File Dispatch {}, and {last}.", parts.join(", "))
+        }
+    }
+}
+
+fn verb(action_type: &str) -> Option<&'static str> {
+    Some(match action_type {
+        "move" => "moved",
+        "copy" => "copied",
+        "rename" => "renamed",
+        "sortIntoSubfolder" => "sorted",
+        "archive" => "archived",
+        "unarchive" => "extracted",
+        "delete" => "trashed",
+        "deletePermanently" => "deleted",
+        "makePdfSearchable" => "made searchable",
+        _ => return None,
+    })
+}
+
+fn files(count: u64) -> String {
+    format!("{count} file{}", if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::error_notify::{ErrorNotice, Notifier};
+    use crate::models::DigestActionTotal;
+    use tempfile::tempdir;
+
+    struct RecordingNotifier {
+        shown: Arc<Mutex<Vec<ErrorNotice>>>,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn show(&self, notice: &ErrorNotice) -> Result<()> {
+            self.shown.lock().unwrap().push(notice.clone());
+            Ok(())
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn six_pm() -> NaiveTime {
+        NaiveTime::from_hms_opt(18, 0, 0).unwrap()
+    }
+
+    fn log(db: &Database, action_type: &str, status: LogStatus, size: u64) {
+        let mut metadata = HashMap::new();
+        metadata.insert("size_bytes".to_string(), size.to_string());
+        LogRepository::new(db.clone())
+            .insert(LogEntry {
+                id: String::new(),
+                rule_id: None,
+                rule_name: Some("Downloads".to_string()),
+                file_path: "/tmp/report.pdf".to_string(),
+                action_type: action_type.to_string(),
+                action_detail: Some(ActionDetails {
+                    source_path: "/tmp/report.pdf".to_string(),
+                    destination_path: None,
+                    metadata,
+                }),
+                status,
+                error_message: None,
+                created_at: Utc::now(),
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn fires_once_at_the_digest_time() {
+        let mut tracker = DigestTracker::default();
+        assert_eq!(tracker.due(six_pm(), at(3, 17, 59)), None);
+        assert_eq!(
+            tracker.due(six_pm(), at(3, 18, 0)),
+            Some(at(3, 0, 0).date())
+        );
+        assert_eq!(tracker.due(six_pm(), at(3, 18, 1)), None);
+        assert_eq!(tracker.due(six_pm(), at(3, 23, 0)), None);
+        assert_eq!(
+            tracker.due(six_pm(), at(4, 18, 0)),
+            Some(at(4, 0, 0).date())
+        );
+
+        // A digest already logged today isn't sent again after a restart.
+        let mut restarted = DigestTracker::new(Some(at(4, 0, 0).date()));
+        assert_eq!(restarted.due(six_pm(), at(4, 18, 30)), None);
+    }
+
+    #[test]
+    fn catches_up_once_after_sleep() {
+        // Asleep from 17:00 until 19:30: the digest goes out on wake.
+        let mut tracker = DigestTracker::default();
+        assert_eq!(tracker.due(six_pm(), at(3, 17, 0)), None);
+        assert_eq!(
+            tracker.due(six_pm(), at(3, 19, 30)),
+            Some(at(3, 0, 0).date())
+        );
+
+        // A digest at 23:00 caught up just after midnight belongs to the day
+        // before.
+        let late = NaiveTime::from_hms_opt(23, 0, 0).unwrap();
+        assert_eq!(tracker.due(late, at(4, 0, 30)), None);
+        let mut tracker = DigestTracker::default();
+        assert_eq!(tracker.due(late, at(4, 0, 30)), Some(at(3, 0, 0).date()));
+
+        // Asleep for days: past the grace window nothing is owed, and the
+        // next digest is just that day's.
+        let mut tracker = DigestTracker::new(Some(at(3, 0, 0).date()));
+        assert_eq!(tracker.due(six_pm(), at(6, 22, 0)), None);
+        assert_eq!(tracker.due(six_pm(), at(7, 9, 0)), None);
+        assert_eq!(
+            tracker.due(six_pm(), at(7, 18, 0)),
+            Some(at(7, 0, 0).date())
+        );
+    }
+
+    #[test]
+    fn sends_the_days_totals_and_skips_empty_days() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let shown = Arc::new(Mutex::new(Vec::new()));
+        let notifier = Arc::new(ErrorNotifier::new(Box::new(RecordingNotifier {
+            shown: shown.clone(),
+        })));
+        let mut settings = Settings::default();
+        settings.daily_digest.enabled = true;
+        let settings = Arc::new(Mutex::new(settings));
+        let now = Local::now().naive_local();
+        settings.lock().unwrap().daily_digest.time = now.time();
+
+        let mut empty_day = DigestScheduler::new(db.clone(), settings.clone(), notifier.clone());
+        assert_eq!(empty_day.tick(now).unwrap(), None);
+        assert!(shown.lock().unwrap().is_empty());
+        assert!(LogRepository::new(db.clone())
+            .list(10, 0)
+            .unwrap()
+            .is_empty());
+
+        for size in [1_000, 2_000] {
+            log(&db, "move", LogStatus::Success, size);
+        }
+        log(&db, "archive", LogStatus::Success, 500);
+        log(&db, "runScript", LogStatus::Error, 0);
+        let mut scheduler = DigestScheduler::new(db.clone(), settings.clone(), notifier.clone());
+        let later = Local::now().naive_local();
+        let digest = scheduler.tick(later).unwrap().unwrap();
+
+        assert_eq!(digest.day, now.date());
+        assert_eq!(
+            digest.actions,
+            vec![
+                DigestActionTotal {
+                    action_type: "move".to_string(),
+                    count: 2,
+                    bytes: 3_000,
+                },
+                DigestActionTotal {
+                    action_type: "archive".to_string(),
+                    count: 1,
+                    bytes: 0,
+                },
+            ]
+        );
+        assert_eq!(digest.errors, 1);
+        let shown = shown.lock().unwrap();
+        assert_eq!(shown.len(), 1);
+        assert_eq!(
+            shown[0].body(),
+            "Today File Dispatch moved 2 files (2.9 KB), archived 1, and hit 1 error."
+        );
+
+        let logs = LogRepository::new(db.clone()).list(10, 0).unwrap();
+        let entry = logs
+            .iter()
+            .find(|entry| entry.action_type == "digest")
+            .unwrap();
+        assert_eq!(digest.log_id.as_deref(), Some(entry.id.as_str()));
+        let metadata = &entry.action_detail.as_ref().unwrap().metadata;
+        assert_eq!(metadata["move_count"], "2");
+        assert_eq!(metadata["move_bytes"], "3000");
+        assert_eq!(metadata["errors"], "1");
+
+        // Sent once; a restart knows from the log.
+        assert_eq!(scheduler.tick(later).unwrap(), None);
+        let mut restarted = DigestScheduler::new(db.clone(), settings, notifier);
+        assert_eq!(restarted.tick(later).unwrap(), None);
+    }
+
+    #[test]
+    fn names_the_day_of_a_late_digest() {
+        let digest = DailyDigest {
+            day: at(3, 0, 0).date(),
+            actions: Vec::new(),
+            errors: 0,
+            log_id: None,
+        };
+        assert_eq!(
+            message(&digest, at(4, 0, 0).date()),
+            "Yesterday File Dispatch had nothing to do."
+        );
+    }
+}
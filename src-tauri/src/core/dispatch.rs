@@ -0,0 +1,2008 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use filedispatch_core::file_info::FileInfo;
+use uuid::Uuid;
+
+use crate::core::approvals::propose;
+use crate::core::boomerang::{find_reappearance, log_reappearance, Boomerang, Reappearance};
+use crate::core::budget::{check_budget, record_transfers, BudgetDecision};
+use crate::core::conflicts::{suspend, ActionRun};
+use crate::core::content::ContentCache;
+use crate::core::counters::{number_run, settle_counter};
+use crate::core::dedup::EventActions;
+use crate::core::duplicates::DuplicateDetector;
+use crate::core::engine::{
+    evaluate_condition, evaluate_conditions, in_path_scope, log_outcomes, log_unreadable,
+    min_age_eligible_at, picked_up, record_companion_matches,
+    record_make_pdf_searchable_output_match, should_stop_processing, write_processed_markers,
+    DuplicateLookup, EvaluationOptions,
+};
+use crate::core::event_writer::EventWriter;
+use crate::core::executor::{
+    ActionErrorKind, ActionOutcome, ActionResultStatus, ActionScope, DISPATCH_TARGET_KEY,
+};
+use crate::core::match_metadata::{self, MatchMetadata};
+use crate::core::ocr::OcrManager;
+use crate::core::retry::{record_failure, record_offline, RetryDecision};
+use crate::core::thumbnails::{activity_thumbnail, record_thumbnail};
+use crate::core::timings::{
+    diagnose_slow_evaluation, log_slow_evaluation, take_regex_usage, RuleTimings,
+};
+use crate::models::{
+    Action, ActionType, BudgetExhausted, ConflictResolution, DeleteAction, EventSource,
+    MoveAction, OnReappear, PendingApproval, PendingConflict, RetryExhausted, Rule, Settings,
+    TimingPhase,
+};
+use crate::storage::conflict_repo::ConflictRepository;
+use crate::storage::database::Database;
+use crate::storage::folder_repo::FolderRepository;
+use crate::storage::log_repo::LogRepository;
+use crate::storage::match_repo::MatchRepository;
+use crate::storage::retry_repo::RetryRepository;
+use crate::storage::rule_repo::RuleRepository;
+use crate::storage::write_set::WriteSet;
+use crate::utils::markers::marked_rule_ids;
+use crate::utils::platform::normalize_user_path;
+
+/// Most folders a single file may be handed through after the one that saw it.
+pub const MAX_DISPATCH_DEPTH: usize = 4;
+
+/// Folders one engine invocation has run so far, plus the trace id shared by
+/// every log entry it writes, the actions its rules have done and the
+/// records waiting to be committed for it.
+#[derive(Debug, Clone)]
+pub struct DispatchChain {
+    trace_id: String,
+    visited: Vec<String>,
+    actions: EventActions,
+    writes: WriteSet,
+}
+
+impl DispatchChain {
+    pub fn new(folder_id: &str) -> Self {
+        Self {
+            trace_id: Uuid::new_v4().to_string(),
+            visited: vec![folder_id.to_string()],
+            actions: EventActions::default(),
+            writes: WriteSet::default(),
+        }
+    }
+
+    pub fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    /// What the rules run for this event so far have done, so a later rule
+    /// doesn't do it again.
+    pub fn actions(&self) -> &EventActions {
+        &self.actions
+    }
+
+    /// The log entries, undo entries and matches the rules have recorded so
+    /// far, committed together once the event is done.
+    pub fn writes(&mut self) -> &mut WriteSet {
+        &mut self.writes
+    }
+
+    /// Number of hand-offs so far; 0 while running the originating folder.
+    pub fn depth(&self) -> usize {
+        self.visited.len() - 1
+    }
+
+    /// Records a hand-off to `folder_id`, refusing cycles and overly long chains.
+    pub fn enter(&mut self, folder_id: &str) -> Result<(), String> {
+        if self.visited.iter().any(|visited| visited == folder_id) {
+            return Err(format!(
+                "Dispatch cycle: folder {} was already visited ({})",
+                folder_id,
+                self.visited.join(" → ")
+            ));
+        }
+        if self.depth() >= MAX_DISPATCH_DEPTH {
+            return Err(format!(
+                "Dispatch chain is longer than {} folders",
+                MAX_DISPATCH_DEPTH
+            ));
+        }
+        self.visited.push(folder_id.to_string());
+        Ok(())
+    }
+}
+
+/// Identifies which folder and chain a log entry belongs to.
+pub struct DispatchTrace<'a> {
+    pub trace_id: &'a str,
+    pub folder_id: &'a str,
+}
+
+impl DispatchTrace<'_> {
+    pub fn apply(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert("trace_id".to_string(), self.trace_id.to_string());
+        metadata.insert("folder_id".to_string(), self.folder_id.to_string());
+    }
+}
+
+pub type ExecuteFn<'a> = dyn Fn(
+        &[Action],
+        &FileInfo,
+        &HashMap<String, String>,
+        &ActionScope,
+    ) -> Vec<ActionOutcome>
+    + 'a;
+
+pub type FailureFn<'a> =
+    dyn Fn(&Rule, &FileInfo, &HashMap<String, String>, &[ActionOutcome]) + 'a;
+
+/// Runs a folder's rule list against one file and follows `DispatchToFolder`
+/// hand-offs within the same invocation.
+pub struct RuleRunner<'a> {
+    pub db: &'a Database,
+    pub settings: &'a Settings,
+    pub ocr: &'a Mutex<OcrManager>,
+    pub execute: &'a ExecuteFn<'a>,
+    /// Dry-runs actions; used to record what an approval-required rule would do.
+    pub plan: &'a ExecuteFn<'a>,
+    /// Told about each match queued for approval.
+    pub on_pending: &'a dyn Fn(&PendingApproval),
+    /// Told about each action suspended on a destination conflict.
+    pub on_conflict: &'a dyn Fn(&PendingConflict),
+    /// Told about rule runs with a failed action, once they're logged.
+    pub on_failure: &'a FailureFn<'a>,
+    /// Called when an action in the originating folder hit a locked file;
+    /// returns whether a retry was scheduled.
+    pub retry_locked: &'a dyn Fn() -> bool,
+    /// Told about rules skipped because the file is younger than their
+    /// minimum age, with the time it becomes old enough.
+    pub defer: &'a dyn Fn(&Rule, DateTime<Utc>),
+    /// Told when a file has failed too many times to keep retrying.
+    pub on_retry_exhausted: &'a dyn Fn(&RetryExhausted),
+    /// Told the first time in a month a rule skips a file for want of budget.
+    pub on_budget_exhausted: &'a dyn Fn(&BudgetExhausted),
+    /// Where each rule's evaluation and execution times are recorded.
+    pub timings: Option<&'a RuleTimings>,
+    /// Commits each event's records, batching them if the settings say so;
+    /// without one they're committed as soon as the event is done.
+    pub writer: Option<&'a EventWriter>,
+    /// The time file ages are measured against.
+    pub now: DateTime<Utc>,
+    /// What Is Duplicate conditions search with; without it they never match.
+    pub duplicates: Option<&'a Arc<DuplicateDetector>>,
+}
+
+impl RuleRunner<'_> {
+    /// Returns true when processing stopped because a lock retry was scheduled.
+    pub fn run(&self, folder_id: &str, info: FileInfo, chain: &mut DispatchChain) -> Result<bool> {
+        let retried = self.run_rules(folder_id, info, chain, None);
+        // What the rules did before an error is recorded all the same.
+        self.commit(chain)?;
+        retried
+    }
+
+    /// Runs one rule that was deferred by its minimum age or is being
+    /// retried. The file is looked at afresh: one that has gone away is
+    /// quietly dropped, one that can't be read is logged, and one modified
+    /// during the wait is deferred again.
+    pub fn run_deferred(
+        &self,
+        folder_id: &str,
+        rule_id: &str,
+        path: &Path,
+        source: EventSource,
+        chain: &mut DispatchChain,
+    ) -> Result<bool> {
+        let retried = match FileInfo::from_path(path) {
+            Ok(mut info) => {
+                if let Some(folder) = FolderRepository::new(self.db.clone()).get(folder_id)? {
+                    picked_up(&mut info, source, &normalize_user_path(&folder.path));
+                }
+                let retried = self.run_rules(folder_id, info, chain, Some(rule_id));
+                self.commit(chain)?;
+                retried?
+            }
+            Err(err) => {
+                log_unreadable(&LogRepository::new(self.db.clone()), path, &err)?;
+                false
+            }
+        };
+        // A retry that didn't schedule another attempt is done with, whether
+        // the rule ran, no longer matched or the file went away.
+        RetryRepository::new(self.db.clone())
+            .forget_claimed(rule_id, &path.to_string_lossy())?;
+        Ok(retried)
+    }
+
+    /// Hands the records of the event in `chain` to the writer.
+    fn commit(&self, chain: &mut DispatchChain) -> Result<()> {
+        let writes = std::mem::take(&mut chain.writes);
+        match self.writer {
+            Some(writer) => writer.submit(writes, self.settings.db_batch_commit_ms, Instant::now()),
+            None => writes.commit(self.db),
+        }
+    }
+
+    fn run_rules(
+        &self,
+        folder_id: &str,
+        mut info: FileInfo,
+        chain: &mut DispatchChain,
+        only_rule: Option<&str>,
+    ) -> Result<bool> {
+        let rule_repo = RuleRepository::new(self.db.clone());
+        let match_repo = MatchRepository::new(self.db.clone());
+        let log_repo = LogRepository::new(self.db.clone());
+
+        // A file waiting on a conflict belongs to the rule that raised it
+        // until the conflict is resolved.
+        if ConflictRepository::new(self.db.clone()).is_pending(&info.path.to_string_lossy())? {
+            return Ok(false);
+        }
+
+        // Populate last_matched from database, or from this event's records
+        // when a folder earlier in the chain matched the file
+        let path = info.path.to_string_lossy().to_string();
+        if let Some(matched_at) = chain.writes().matched_at(&path) {
+            info.last_matched = Some(matched_at);
+        } else if let Ok(last_matched) = match_repo.get_last_match_time(&path) {
+            info.last_matched = last_matched;
+        }
+
+        let rules = rule_repo.list_by_folder(folder_id)?;
+        let folder = FolderRepository::new(self.db.clone()).get(folder_id)?;
+        // Only scoped rules need the folder's root.
+        let root = folder
+            .as_ref()
+            .filter(|_| rules.iter().any(|rule| rule.path_scope.is_some()))
+            .map(|folder| normalize_user_path(&folder.path));
+        let keeps_thumbnails = folder.is_some_and(|folder| folder.activity_thumbnails);
+        let options = EvaluationOptions {
+            duplicates: self.duplicates.map(|detector| DuplicateLookup {
+                detector: Arc::clone(detector),
+                folder_id: folder_id.to_string(),
+            }),
+            ..EvaluationOptions::default()
+        };
+        // Made the first time a rule matches, before its actions move the file.
+        let mut thumbnail: Option<Option<String>> = None;
+
+        // Pre-fetch all rule IDs that have already matched this file's hash
+        // This avoids N+1 queries in the rule loop
+        let rule_ids: Vec<&str> = rules.iter().map(|r| r.id.as_str()).collect();
+        let mut matched_rule_ids = match_repo.get_hash_matched_rules(&rule_ids, &info.hash)?;
+
+        // Markers left on the file stand in for matches this database never
+        // saw (another machine, a reset); they're copied in so later events
+        // don't have to read them again.
+        for rule_id in marked_rule_ids(&info.path, &info.hash, self.settings.processed_marker_mode)
+        {
+            if rule_ids.contains(&rule_id.as_str()) && !matched_rule_ids.contains(&rule_id) {
+                chain.writes().record_match(
+                    &rule_id,
+                    info.path.to_string_lossy().as_ref(),
+                    Some(&info.hash),
+                    &BTreeMap::new(),
+                    &[],
+                );
+                matched_rule_ids.insert(rule_id);
+            }
+        }
+
+        let trace_id = chain.trace_id().to_string();
+        let trace = DispatchTrace {
+            trace_id: &trace_id,
+            folder_id,
+        };
+
+        for rule in rules {
+            // Maintenance rules only run from the scheduler.
+            if !rule.enabled || rule.schedule.is_some() {
+                continue;
+            }
+            if only_rule.is_some_and(|id| id != rule.id) {
+                continue;
+            }
+            if let Some(root) = &root {
+                if !in_path_scope(&rule, root, &info.path) {
+                    continue;
+                }
+            }
+
+            // Skip if this file (by hash) was already processed by this rule
+            // This prevents re-processing after renames or moves
+            if matched_rule_ids.contains(&rule.id) {
+                match find_reappearance(self.db, &rule.id, &info)? {
+                    Reappearance::NotMoved => {
+                        // An executed approval holds back later rules just as
+                        // the rule would have when it first ran.
+                        if rule.approval_required && rule.stop_processing {
+                            break;
+                        }
+                        continue;
+                    }
+                    // Nothing vouches for the earlier copy, so this one is
+                    // treated as new.
+                    Reappearance::Unverified => {}
+                    Reappearance::Returned(boomerang) => {
+                        let on_reappear =
+                            rule.on_reappear.as_ref().unwrap_or(&self.settings.on_reappear);
+                        if *on_reappear != OnReappear::ProcessAgain {
+                            // Later rules can't run on a file that was taken away.
+                            let taken = self.set_aside(
+                                &rule,
+                                &info,
+                                &boomerang,
+                                on_reappear,
+                                &trace,
+                                chain.writes(),
+                            )?;
+                            if taken {
+                                break;
+                            }
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            // A file that's still being written gets another look later; the
+            // rules after this one don't wait for it.
+            if let Some(eligible_at) = min_age_eligible_at(&rule, &info, self.now) {
+                (self.defer)(&rule, eligible_at);
+                continue;
+            }
+
+            // Acquire OCR lock only when evaluating conditions, release after
+            take_regex_usage();
+            let started = Instant::now();
+            let mut evaluation = {
+                let mut ocr = self.ocr.lock().unwrap();
+                evaluate_conditions(&rule, &info, self.settings, &mut ocr, &options)?
+            };
+            self.record_evaluation(&rule, &info, started.elapsed(), &log_repo)?;
+            if !evaluation.matched {
+                continue;
+            }
+
+            // Queue the match for review and keep going; nothing is recorded
+            // until the approval is executed.
+            if rule.approval_required {
+                let approval = propose(self.db, &rule, &info, &evaluation.captures, self.plan)?;
+                (self.on_pending)(&approval);
+                continue;
+            }
+
+            if let BudgetDecision::Exhausted(notice) =
+                check_budget(self.db, &rule, &info, self.settings, self.now)?
+            {
+                if let Some(notice) = notice {
+                    (self.on_budget_exhausted)(&notice);
+                }
+                continue;
+            }
+
+            if keeps_thumbnails && thumbnail.is_none() {
+                thumbnail = Some(activity_thumbnail(&info, self.settings));
+            }
+            number_run(self.db, &rule, &mut evaluation.captures)?;
+            let scope = ActionScope::new(&rule.id, &info).deduping(chain.actions(), &rule.name);
+            let started = Instant::now();
+            let mut outcomes = (self.execute)(&rule.actions, &info, &evaluation.captures, &scope);
+            if let Some(timings) = self.timings {
+                timings.record(&rule, TimingPhase::Execution, started.elapsed(), Utc::now());
+            }
+            if let Some(Some(path)) = &thumbnail {
+                record_thumbnail(&mut outcomes, path);
+            }
+            let handoff = self.take_dispatch(&mut outcomes, chain);
+
+            let recorded =
+                match_metadata::resolve(&rule, &info, &evaluation.captures, self.settings);
+            log_outcomes(chain.writes(), &rule, &info, &outcomes, &recorded, Some(&trace));
+            record_transfers(self.db, &rule, info.size, &outcomes, self.settings, self.now)?;
+            settle_counter(self.db, &rule, &evaluation.captures, &outcomes)?;
+            let run = ActionRun {
+                rule: &rule,
+                actions: &rule.actions,
+                first_index: 0,
+                captures: &evaluation.captures,
+                trace_id: Some(&trace_id),
+            };
+            // The rest of the run, and every later rule, waits for a decision.
+            if let Some(conflict) = suspend(self.db, &run, &info, &outcomes)? {
+                (self.on_conflict)(&conflict);
+                break;
+            }
+            let locked = outcomes
+                .iter()
+                .any(|outcome| outcome.error_kind == Some(ActionErrorKind::Locked));
+            // Retries requeue the original event, which only makes sense
+            // before the file has been handed on.
+            if locked && chain.depth() == 0 && (self.retry_locked)() {
+                // Leave the rule unmatched so the retried event evaluates it again.
+                return Ok(true);
+            }
+            if outcomes
+                .iter()
+                .any(|outcome| outcome.status == ActionResultStatus::Error)
+            {
+                (self.on_failure)(&rule, &info, &evaluation.captures, &outcomes);
+            }
+            // Likewise, a run that will be retried stays unmatched.
+            if !self.queue_retry(folder_id, &rule, &info, &outcomes)? {
+                chain.writes().record_match(
+                    &rule.id,
+                    info.path.to_string_lossy().as_ref(),
+                    Some(&info.hash),
+                    &recorded,
+                    &self.settings.match_metadata_indexed_keys,
+                );
+                write_processed_markers(self.settings, &rule.id, &info.path, &outcomes);
+            }
+            record_make_pdf_searchable_output_match(chain.writes(), &rule.id, &outcomes);
+            record_companion_matches(&rule_repo, chain.writes(), &outcomes);
+
+            if let Some((target, path)) = handoff {
+                let next = if path == info.path {
+                    info
+                } else {
+                    FileInfo::from_path(&path)?
+                };
+                return self.run_rules(&target, next, chain, None);
+            }
+
+            if should_stop_processing(&rule, &outcomes) {
+                break;
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Records how long `rule` took to evaluate and the regexes it compiled.
+    /// An evaluation over the warning threshold is logged, naming the
+    /// condition that's slowest when each is timed again on its own.
+    fn record_evaluation(
+        &self,
+        rule: &Rule,
+        info: &FileInfo,
+        elapsed: Duration,
+        log_repo: &LogRepository,
+    ) -> Result<()> {
+        let Some(timings) = self.timings else {
+            return Ok(());
+        };
+        let now = Utc::now();
+        timings.record(rule, TimingPhase::Evaluation, elapsed, now);
+        timings.record_regex(rule, take_regex_usage(), now);
+
+        let threshold_ms = self.settings.slow_rule_warning_ms;
+        let slow = diagnose_slow_evaluation(rule, elapsed, threshold_ms, |condition| {
+            let mut ocr = self.ocr.lock().unwrap();
+            let mut cache = ContentCache::default();
+            let options = EvaluationOptions::default();
+            let started = Instant::now();
+            let settings = self.settings;
+            let _ = evaluate_condition(condition, info, settings, &mut ocr, &mut cache, &options);
+            started.elapsed()
+        });
+        // The diagnostic's own lookups aren't the rule's.
+        take_regex_usage();
+        if let Some(slow) = slow {
+            log_slow_evaluation(log_repo, rule, &info.path, &slow, threshold_ms)?;
+        }
+        Ok(())
+    }
+
+    /// Deals with a returned file as `on_reappear` says, other than by
+    /// processing it again. Returns whether the file was taken away.
+    fn set_aside(
+        &self,
+        rule: &Rule,
+        info: &FileInfo,
+        boomerang: &Boomerang,
+        on_reappear: &OnReappear,
+        trace: &DispatchTrace,
+        writes: &mut WriteSet,
+    ) -> Result<bool> {
+        let log_repo = LogRepository::new(self.db.clone());
+        log_reappearance(&log_repo, rule, info, boomerang, on_reappear, trace)?;
+        let action = match on_reappear {
+            OnReappear::ProcessAgain | OnReappear::SkipSilently => return Ok(false),
+            OnReappear::DeleteIncoming => Action::Delete(DeleteAction { permanent: false }),
+            OnReappear::MoveToReview { destination } => Action::Move(MoveAction {
+                destination: destination.clone(),
+                on_conflict: ConflictResolution::Rename,
+                skip_duplicates: false,
+                destination_permissions: Default::default(),
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
+            }),
+        };
+        let scope = ActionScope::new(&rule.id, info);
+        let outcomes = (self.execute)(&[action], info, &HashMap::new(), &scope);
+        log_outcomes(writes, rule, info, &outcomes, &MatchMetadata::new(), Some(trace));
+        Ok(outcomes
+            .iter()
+            .any(|outcome| outcome.status == ActionResultStatus::Success))
+    }
+
+    /// Puts a run that failed with a transient error in the retry ledger, or
+    /// clears the ledger for one that didn't. A run held up by an offline
+    /// volume waits there for the volume. Returns whether another attempt is
+    /// scheduled.
+    fn queue_retry(
+        &self,
+        folder_id: &str,
+        rule: &Rule,
+        info: &FileInfo,
+        outcomes: &[ActionOutcome],
+    ) -> Result<bool> {
+        let path = info.path.to_string_lossy();
+        let failure = outcomes.iter().find(|outcome| match outcome.error_kind {
+            Some(ActionErrorKind::Transient | ActionErrorKind::VolumeOffline(_)) => true,
+            // Only once the short lock retries are used up or turned off.
+            Some(ActionErrorKind::Locked) => self.settings.retry_locked_files,
+            _ => false,
+        });
+        let Some(failure) = failure else {
+            RetryRepository::new(self.db.clone()).forget(&rule.id, &path)?;
+            return Ok(false);
+        };
+
+        let error = failure.error.as_deref().unwrap_or("Unknown error");
+        if let Some(ActionErrorKind::VolumeOffline(volume)) = &failure.error_kind {
+            record_offline(self.db, rule, folder_id, &path, volume, error, self.now)?;
+            eprintln!("Destination volume offline, waiting for it: {}", path);
+            return Ok(true);
+        }
+        match record_failure(self.db, rule, folder_id, &path, error, self.now)? {
+            RetryDecision::Scheduled(next_attempt_at) => {
+                eprintln!(
+                    "Transient failure, retrying at {}: {}",
+                    next_attempt_at.to_rfc3339(),
+                    path
+                );
+                Ok(true)
+            }
+            RetryDecision::GaveUp(exhausted) => {
+                (self.on_retry_exhausted)(&exhausted);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Pulls the hand-off target out of a trailing dispatch outcome. A refused
+    /// hand-off turns that outcome into an error so the log says why.
+    fn take_dispatch(
+        &self,
+        outcomes: &mut [ActionOutcome],
+        chain: &mut DispatchChain,
+    ) -> Option<(String, PathBuf)> {
+        let outcome = outcomes.last_mut()?;
+        if outcome.action_type != ActionType::DispatchToFolder
+            || outcome.status == ActionResultStatus::Error
+        {
+            return None;
+        }
+        let details = outcome.details.as_ref()?;
+        let target = details.metadata.get(DISPATCH_TARGET_KEY)?.clone();
+        let path = PathBuf::from(&details.source_path);
+
+        let refusal = match FolderRepository::new(self.db.clone()).get(&target) {
+            Ok(Some(folder)) if folder.enabled => chain.enter(&target).err(),
+            Ok(Some(folder)) => Some(format!("Folder {} is disabled", folder.name)),
+            Ok(None) => Some(format!("Folder {} no longer exists", target)),
+            Err(err) => Some(err.to_string()),
+        };
+        if let Some(message) = refusal {
+            outcome.status = ActionResultStatus::Error;
+            outcome.error = Some(message);
+            return None;
+        }
+        Some((target, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::arrivals::ArrivalGroups;
+    use crate::core::dedup::PlannedOperation;
+    use crate::core::executor::{dispatch_outcome, mark_suspended, transfer_file, Transfer};
+    use filedispatch_core::patterns::PatternEngine;
+    use crate::core::watcher::{FileEvent, FileEventKind};
+    use crate::core::retry::claim_due_events;
+    use crate::storage::undo_repo::UndoRepository;
+    use crate::models::{
+        ActionDetails, ComparisonOperator, Condition, ConditionGroup, ConflictResolution,
+        CopyAction, DedupeActionsSettings, DispatchToFolderAction, EventSource, Folder,
+        GroupSizeCondition, LogStatus, MatchType, MoveAction, ReflinkMode, RenameAction,
+    };
+    use std::fs;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use tempfile::tempdir;
+
+    /// Stands in for the executor: renames by prefixing, and dispatches.
+    fn execute(
+        actions: &[Action],
+        info: &FileInfo,
+        _: &HashMap<String, String>,
+        _: &ActionScope,
+    ) -> Vec<ActionOutcome> {
+        let mut outcomes = Vec::new();
+        let mut current = info.path.clone();
+        for action in actions {
+            match action {
+                Action::Rename(rename) => {
+                    let name = current.file_name().unwrap().to_string_lossy().to_string();
+                    let dest = current.with_file_name(format!("{}{}", rename.pattern, name));
+                    fs::rename(&current, &dest).unwrap();
+                    outcomes.push(ActionOutcome {
+                        action_type: ActionType::Rename,
+                        status: ActionResultStatus::Success,
+                        details: Some(ActionDetails {
+                            source_path: current.to_string_lossy().to_string(),
+                            destination_path: Some(dest.to_string_lossy().to_string()),
+                            metadata: HashMap::new(),
+                        }),
+                        error: None,
+                        error_kind: None,
+                    });
+                    current = dest;
+                }
+                Action::DispatchToFolder(dispatch) => {
+                    outcomes.push(dispatch_outcome(&dispatch.folder_id, &current));
+                    break;
+                }
+                Action::Move(movement) => {
+                    let dest = Path::new(&movement.destination).join(current.file_name().unwrap());
+                    fs::create_dir_all(&movement.destination).unwrap();
+                    fs::rename(&current, &dest).unwrap();
+                    outcomes.push(ActionOutcome {
+                        action_type: ActionType::Move,
+                        status: ActionResultStatus::Success,
+                        details: Some(ActionDetails {
+                            source_path: current.to_string_lossy().to_string(),
+                            destination_path: Some(dest.to_string_lossy().to_string()),
+                            metadata: HashMap::new(),
+                        }),
+                        error: None,
+                        error_kind: None,
+                    });
+                    current = dest;
+                }
+                Action::Delete(_) => {
+                    fs::remove_file(&current).unwrap();
+                    outcomes.push(ActionOutcome {
+                        action_type: ActionType::Delete,
+                        status: ActionResultStatus::Success,
+                        details: None,
+                        error: None,
+                        error_kind: None,
+                    });
+                    break;
+                }
+                _ => {}
+            }
+        }
+        outcomes
+    }
+
+    fn add_rule(db: &Database, folder: &Folder, name: &str, actions: Vec<Action>) -> Rule {
+        RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id.clone(),
+                name: name.to_string(),
+                enabled: true,
+                stop_processing: true,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![],
+                },
+                actions,
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            })
+            .unwrap()
+    }
+
+    fn rename(prefix: &str) -> Action {
+        Action::Rename(RenameAction {
+            pattern: prefix.to_string(),
+            on_conflict: ConflictResolution::Rename,
+        })
+    }
+
+    fn dispatch(folder: &Folder) -> Action {
+        Action::DispatchToFolder(DispatchToFolderAction {
+            folder_id: folder.id.clone(),
+        })
+    }
+
+    fn run(db: &Database, folder: &Folder, path: &Path) -> DispatchChain {
+        run_with(db, folder, path, &Settings::default())
+    }
+
+    fn run_with(db: &Database, folder: &Folder, path: &Path, settings: &Settings) -> DispatchChain {
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        let runner = RuleRunner {
+            db,
+            settings,
+            ocr: &ocr,
+            execute: &execute,
+            plan: &execute,
+            on_pending: &|_| {},
+            on_conflict: &|_| {},
+            on_failure: &|_, _, _, _| {},
+            retry_locked: &|| false,
+            defer: &|_, _| {},
+            on_retry_exhausted: &|_| {},
+            on_budget_exhausted: &|_| {},
+            timings: None,
+            writer: None,
+            now: Utc::now(),
+            duplicates: None,
+        };
+        let mut chain = DispatchChain::new(&folder.id);
+        let retried = runner
+            .run(&folder.id, FileInfo::from_path(path).unwrap(), &mut chain)
+            .unwrap();
+        assert!(!retried);
+        chain
+    }
+
+    fn marking_settings() -> Settings {
+        Settings {
+            write_processed_markers: true,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn marker_is_honored_after_match_history_is_wiped() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Inbox")
+            .unwrap();
+        let rule = add_rule(&db, &inbox, "Tag", vec![rename("done-")]);
+        let path = dir.path().join("invoice.pdf");
+        fs::write(&path, b"invoice").unwrap();
+        let settings = marking_settings();
+        run_with(&db, &inbox, &path, &settings);
+        let done = dir.path().join("done-invoice.pdf");
+        assert!(done.exists());
+
+        // As if the app data were gone: only the marker remembers the run.
+        let matches = MatchRepository::new(db.clone());
+        matches.clear_rule(&rule.id).unwrap();
+        run_with(&db, &inbox, &done, &settings);
+        assert!(done.exists());
+        assert!(!dir.path().join("done-done-invoice.pdf").exists());
+        let hash = FileInfo::from_path(&done).unwrap().hash;
+        assert!(matches.has_hash_match(&rule.id, &hash).unwrap());
+    }
+
+    #[test]
+    fn verify_mode_reprocesses_changed_content() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Inbox")
+            .unwrap();
+        let rule = add_rule(&db, &inbox, "Tag", vec![rename("done-")]);
+        let path = dir.path().join("invoice.pdf");
+        fs::write(&path, b"invoice").unwrap();
+        let settings = marking_settings();
+        run_with(&db, &inbox, &path, &settings);
+
+        let done = dir.path().join("done-invoice.pdf");
+        MatchRepository::new(db.clone()).clear_rule(&rule.id).unwrap();
+        fs::write(&done, b"invoice, second revision").unwrap();
+        run_with(&db, &inbox, &done, &settings);
+        assert!(dir.path().join("done-done-invoice.pdf").exists());
+    }
+
+    #[test]
+    fn evaluation_and_execution_are_timed_separately() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Inbox")
+            .unwrap();
+        let rule = add_rule(&db, &inbox, "Tag", vec![rename("done-")]);
+        let path = dir.path().join("invoice.pdf");
+        fs::write(&path, b"invoice").unwrap();
+
+        let slow_execute = |actions: &[Action],
+                            info: &FileInfo,
+                            captures: &HashMap<String, String>,
+                            scope: &ActionScope| {
+            std::thread::sleep(Duration::from_millis(30));
+            execute(actions, info, captures, scope)
+        };
+        let settings = Settings::default();
+        let timings = RuleTimings::default();
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        let runner = RuleRunner {
+            db: &db,
+            settings: &settings,
+            ocr: &ocr,
+            execute: &slow_execute,
+            plan: &execute,
+            on_pending: &|_| {},
+            on_conflict: &|_| {},
+            on_failure: &|_, _, _, _| {},
+            retry_locked: &|| false,
+            defer: &|_, _| {},
+            on_retry_exhausted: &|_| {},
+            on_budget_exhausted: &|_| {},
+            timings: Some(&timings),
+            writer: None,
+            now: Utc::now(),
+            duplicates: None,
+        };
+        let mut chain = DispatchChain::new(&inbox.id);
+        runner
+            .run(&inbox.id, FileInfo::from_path(&path).unwrap(), &mut chain)
+            .unwrap();
+
+        let timing = timings.snapshot().remove(0);
+        assert_eq!(timing.rule_id, rule.id);
+        assert_eq!(timing.evaluation.count, 1);
+        assert_eq!(timing.execution.count, 1);
+        assert!(timing.execution.last_ms >= 30.0);
+        assert!(timing.evaluation.last_ms < timing.execution.last_ms);
+        let slowest = timings.slowest_since(Utc::now() - chrono::Duration::hours(1)).unwrap();
+        assert_eq!(slowest.phase, TimingPhase::Execution);
+    }
+
+    /// A Downloads folder with a rule limited to `scope` that prefixes
+    /// whatever it touches with `tg-`.
+    fn scoped_downloads(scope: &str) -> (tempfile::TempDir, Database, Folder) {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let root = dir.path().join("Downloads");
+        let downloads = FolderRepository::new(db.clone())
+            .create(&root.to_string_lossy(), "Downloads")
+            .unwrap();
+        let mut rule = add_rule(&db, &downloads, "Telegram", vec![rename("tg-")]);
+        rule.path_scope = Some(scope.to_string());
+        RuleRepository::new(db.clone()).update(&rule).unwrap();
+        for sub in ["Telegram Desktop/chats", "Other"] {
+            fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        (dir, db, downloads)
+    }
+
+    fn runs_on(db: &Database, folder: &Folder, relative: &str) -> bool {
+        let path = Path::new(&folder.path).join(relative);
+        fs::write(&path, b"file").unwrap();
+        run(db, folder, &path);
+        let renamed = path.with_file_name(format!(
+            "tg-{}",
+            path.file_name().unwrap().to_string_lossy()
+        ));
+        renamed.exists()
+    }
+
+    #[test]
+    fn scoped_rule_skips_sibling_folders_and_the_root() {
+        let (_dir, db, downloads) = scoped_downloads("Telegram Desktop");
+        assert!(runs_on(&db, &downloads, "Telegram Desktop/photo.jpg"));
+        assert!(runs_on(&db, &downloads, "Telegram Desktop/chats/voice.ogg"));
+        assert!(!runs_on(&db, &downloads, "Other/photo.jpg"));
+        assert!(!runs_on(&db, &downloads, "photo.jpg"));
+    }
+
+    #[test]
+    fn glob_scope_matches_folders_below_the_root() {
+        let (_dir, db, downloads) = scoped_downloads("Telegram*/**");
+        assert!(runs_on(&db, &downloads, "Telegram Desktop/photo.jpg"));
+        assert!(runs_on(&db, &downloads, "Telegram Desktop/chats/voice.ogg"));
+        assert!(!runs_on(&db, &downloads, "Other/Telegram.jpg"));
+        assert!(!runs_on(&db, &downloads, "Telegram.jpg"));
+    }
+
+    /// Stands in for the executor: moves into the resolved destination folder,
+    /// failing for files named `broken`.
+    fn move_into(
+        actions: &[Action],
+        info: &FileInfo,
+        captures: &HashMap<String, String>,
+        _: &ActionScope,
+    ) -> Vec<ActionOutcome> {
+        let Some(Action::Move(action)) = actions.first() else {
+            return Vec::new();
+        };
+        if info.name == "broken" {
+            return vec![ActionOutcome {
+                action_type: ActionType::Move,
+                status: ActionResultStatus::Error,
+                details: None,
+                error: Some("disk full".to_string()),
+                error_kind: None,
+            }];
+        }
+        let resolved = PatternEngine::new().resolve(&action.destination, info, captures);
+        let folder = PathBuf::from(resolved);
+        fs::create_dir_all(&folder).unwrap();
+        let dest = folder.join(&info.os_name);
+        fs::rename(&info.path, &dest).unwrap();
+        vec![ActionOutcome {
+            action_type: ActionType::Move,
+            status: ActionResultStatus::Success,
+            details: Some(ActionDetails {
+                source_path: info.path.to_string_lossy().to_string(),
+                destination_path: Some(dest.to_string_lossy().to_string()),
+                metadata: HashMap::new(),
+            }),
+            error: None,
+            error_kind: None,
+        }]
+    }
+
+    #[test]
+    fn an_arrival_group_lands_in_one_folder() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let root = dir.path().join("Mail");
+        fs::create_dir_all(&root).unwrap();
+        let mail = FolderRepository::new(db.clone())
+            .create(&root.to_string_lossy(), "Mail")
+            .unwrap();
+        let inbox = dir.path().join("Inbox");
+        let mut rule = add_rule(
+            &db,
+            &mail,
+            "Attachments",
+            vec![Action::Move(MoveAction {
+                destination: format!("{}/{{group_id}}/", inbox.display()),
+                on_conflict: ConflictResolution::Rename,
+                skip_duplicates: false,
+                destination_permissions: Default::default(),
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
+            })],
+        );
+        rule.conditions.conditions = vec![Condition::GroupSize(GroupSizeCondition {
+            operator: ComparisonOperator::GreaterOrEqual,
+            value: 2,
+        })];
+        RuleRepository::new(db.clone()).update(&rule).unwrap();
+
+        let groups = ArrivalGroups::default();
+        let window = Duration::from_millis(1_000);
+        let start = Instant::now();
+        let arrivals = [
+            (0, "invoice.pdf"),
+            (150, "broken.pdf"),
+            (400, "terms.pdf"),
+            (2_000, "late.pdf"),
+        ];
+        for (offset, name) in arrivals {
+            fs::write(root.join(name), name).unwrap();
+            let event = FileEvent {
+                path: root.join(name),
+                folder_id: mail.id.clone(),
+                kind: FileEventKind::Created,
+                rule_id: None,
+                arrival: None,
+                active_app: None,
+                source: EventSource::Live,
+            };
+            groups.add(event, start + Duration::from_millis(offset), window);
+        }
+
+        let settings = Settings::default();
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        let runner = RuleRunner {
+            db: &db,
+            settings: &settings,
+            ocr: &ocr,
+            execute: &move_into,
+            plan: &move_into,
+            on_pending: &|_| {},
+            on_conflict: &|_| {},
+            on_failure: &|_, _, _, _| {},
+            retry_locked: &|| false,
+            defer: &|_, _| {},
+            on_retry_exhausted: &|_| {},
+            on_budget_exhausted: &|_| {},
+            timings: None,
+            writer: None,
+            now: Utc::now(),
+            duplicates: None,
+        };
+        for event in groups.take_closed(start + Duration::from_millis(3_000)) {
+            let mut info = FileInfo::from_path(&event.path).unwrap();
+            info.arrival = event.arrival.clone();
+            let mut chain = DispatchChain::new(&mail.id);
+            runner.run(&mail.id, info, &mut chain).unwrap();
+        }
+
+        // The two that could move share a folder; the failed one stays put
+        // without holding them up, and the straggler is a group of one.
+        let folders: Vec<PathBuf> = fs::read_dir(&inbox)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(folders.len(), 1);
+        assert!(folders[0].join("invoice.pdf").exists());
+        assert!(folders[0].join("terms.pdf").exists());
+        assert!(root.join("broken.pdf").exists());
+        assert!(root.join("late.pdf").exists());
+    }
+
+    #[test]
+    fn chain_refuses_cycles_and_depth() {
+        let mut chain = DispatchChain::new("a");
+        assert!(chain.enter("b").is_ok());
+        assert!(chain.enter("a").unwrap_err().contains("cycle"));
+        for folder in ["c", "d", "e"] {
+            assert!(chain.enter(folder).is_ok());
+        }
+        assert_eq!(chain.depth(), MAX_DISPATCH_DEPTH);
+        assert!(chain.enter("f").is_err());
+    }
+
+    #[test]
+    fn two_folder_chain_runs_both_rule_sets() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folders = FolderRepository::new(db.clone());
+        let inbox = folders.create(&dir.path().join("inbox").to_string_lossy(), "Inbox").unwrap();
+        let archive = folders
+            .create(&dir.path().join("archive").to_string_lossy(), "Archive")
+            .unwrap();
+        let normalize = add_rule(&db, &inbox, "Normalize", vec![rename("clean-"), dispatch(&archive)]);
+        let place = add_rule(&db, &archive, "Place", vec![rename("final-")]);
+
+        let path = dir.path().join("scan.pdf");
+        fs::write(&path, b"report").unwrap();
+        let chain = run(&db, &inbox, &path);
+
+        // The archive rule saw the renamed file, not the original path.
+        assert!(dir.path().join("final-clean-scan.pdf").exists());
+
+        let logs = LogRepository::new(db.clone()).list(10, 0).unwrap();
+        assert_eq!(logs.len(), 3);
+        for log in &logs {
+            let metadata = &log.action_detail.as_ref().unwrap().metadata;
+            assert_eq!(metadata.get("trace_id").map(String::as_str), Some(chain.trace_id()));
+            let expected_folder = if log.rule_id.as_deref() == Some(normalize.id.as_str()) {
+                &inbox.id
+            } else {
+                assert_eq!(log.rule_id.as_deref(), Some(place.id.as_str()));
+                &archive.id
+            };
+            assert_eq!(metadata.get("folder_id"), Some(expected_folder));
+        }
+
+        // Each folder's rule is recorded against the path it saw, so a later
+        // watcher event for the same content skips both.
+        let matches = MatchRepository::new(db.clone());
+        let hash = FileInfo::from_path(&dir.path().join("final-clean-scan.pdf")).unwrap().hash;
+        let matched = matches
+            .get_hash_matched_rules(&[normalize.id.as_str(), place.id.as_str()], &hash)
+            .unwrap();
+        assert!(matched.contains(&normalize.id) && matched.contains(&place.id));
+        let place_paths = matches.recent_paths_for_folder(&archive.id, 5).unwrap();
+        assert_eq!(
+            place_paths,
+            vec![dir.path().join("clean-scan.pdf").to_string_lossy().to_string()]
+        );
+    }
+
+    #[test]
+    fn dispatch_cycle_is_rejected_at_guard() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folders = FolderRepository::new(db.clone());
+        let a = folders.create(&dir.path().join("a").to_string_lossy(), "A").unwrap();
+        let b = folders.create(&dir.path().join("b").to_string_lossy(), "B").unwrap();
+        let to_b = add_rule(&db, &a, "A to B", vec![dispatch(&b)]);
+        let to_a = add_rule(&db, &b, "B to A", vec![rename("b-"), dispatch(&a)]);
+
+        let path = dir.path().join("loop.txt");
+        fs::write(&path, b"loop").unwrap();
+        run(&db, &a, &path);
+
+        // B ran once; its hand-off back to A was refused instead of looping.
+        assert!(dir.path().join("b-loop.txt").exists());
+        let logs = LogRepository::new(db).list(10, 0).unwrap();
+        let refused: Vec<_> = logs
+            .iter()
+            .filter(|log| log.status == LogStatus::Error)
+            .collect();
+        assert_eq!(refused.len(), 1);
+        assert_eq!(refused[0].rule_id.as_deref(), Some(to_a.id.as_str()));
+        assert_eq!(refused[0].action_type, "dispatchToFolder");
+        assert!(refused[0].error_message.as_deref().unwrap().contains("cycle"));
+        assert!(logs
+            .iter()
+            .any(|log| log.rule_id.as_deref() == Some(to_b.id.as_str())
+                && log.status == LogStatus::Success));
+    }
+
+    /// Runs `f` with a runner whose clock reads `now` and returns the
+    /// deferrals it reported.
+    fn with_clock(
+        db: &Database,
+        now: DateTime<Utc>,
+        f: impl FnOnce(&RuleRunner),
+    ) -> Vec<(String, DateTime<Utc>)> {
+        let settings = Settings::default();
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        let deferred = Mutex::new(Vec::new());
+        let defer = |rule: &Rule, eligible_at: DateTime<Utc>| {
+            deferred.lock().unwrap().push((rule.id.clone(), eligible_at));
+        };
+        let runner = RuleRunner {
+            db,
+            settings: &settings,
+            ocr: &ocr,
+            execute: &execute,
+            plan: &execute,
+            on_pending: &|_| {},
+            on_conflict: &|_| {},
+            on_failure: &|_, _, _, _| {},
+            retry_locked: &|| false,
+            defer: &defer,
+            on_retry_exhausted: &|_| {},
+            on_budget_exhausted: &|_| {},
+            timings: None,
+            writer: None,
+            now,
+            duplicates: None,
+        };
+        f(&runner);
+        deferred.into_inner().unwrap()
+    }
+
+    /// An inbox with a rule that waits 5 s before renaming, followed by one
+    /// that acts straight away, and a fresh file in it.
+    fn min_age_fixture(dir: &Path) -> (Database, Folder, Rule, Rule, PathBuf) {
+        let db = Database::new_with_path(dir.join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.join("inbox").to_string_lossy(), "Inbox")
+            .unwrap();
+        let mut gated = add_rule(&db, &inbox, "Photos", vec![rename("late-")]);
+        gated.min_age_seconds = Some(5);
+        RuleRepository::new(db.clone()).update(&gated).unwrap();
+        let other = add_rule(&db, &inbox, "Catalog", vec![]);
+        let path = dir.join("photo.jpg");
+        fs::write(&path, b"jpeg").unwrap();
+        (db, inbox, gated, other, path)
+    }
+
+    fn born_at(path: &Path) -> DateTime<Utc> {
+        let info = FileInfo::from_path(path).unwrap();
+        info.created.max(info.modified)
+    }
+
+    #[test]
+    fn young_file_defers_its_rule_until_old_enough() {
+        let dir = tempdir().unwrap();
+        let (db, inbox, gated, other, path) = min_age_fixture(dir.path());
+        let born = born_at(&path);
+        let seconds = chrono::Duration::seconds;
+
+        let deferred = with_clock(&db, born + seconds(1), |runner| {
+            let mut chain = DispatchChain::new(&inbox.id);
+            runner
+                .run(&inbox.id, FileInfo::from_path(&path).unwrap(), &mut chain)
+                .unwrap();
+        });
+        assert_eq!(deferred, vec![(gated.id.clone(), born + seconds(5))]);
+        assert!(path.exists());
+        // The next rule didn't wait for the gated one.
+        let hash = FileInfo::from_path(&path).unwrap().hash;
+        let matched = MatchRepository::new(db.clone())
+            .get_hash_matched_rules(&[gated.id.as_str(), other.id.as_str()], &hash)
+            .unwrap();
+        assert!(matched.contains(&other.id) && !matched.contains(&gated.id));
+
+        let deferred = with_clock(&db, born + seconds(6), |runner| {
+            let mut chain = DispatchChain::new(&inbox.id);
+            runner
+                .run_deferred(&inbox.id, &gated.id, &path, EventSource::Live, &mut chain)
+                .unwrap();
+        });
+        assert!(deferred.is_empty());
+        assert!(dir.path().join("late-photo.jpg").exists());
+        let logs = LogRepository::new(db).list(10, 0).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].rule_id.as_deref(), Some(gated.id.as_str()));
+    }
+
+    #[test]
+    fn deferred_rule_drops_a_deleted_file_quietly() {
+        let dir = tempdir().unwrap();
+        let (db, inbox, gated, _, path) = min_age_fixture(dir.path());
+        let born = born_at(&path);
+        fs::remove_file(&path).unwrap();
+
+        let deferred = with_clock(&db, born + chrono::Duration::seconds(6), |runner| {
+            let mut chain = DispatchChain::new(&inbox.id);
+            let retried = runner
+                .run_deferred(&inbox.id, &gated.id, &path, EventSource::Live, &mut chain)
+                .unwrap();
+            assert!(!retried);
+        });
+        assert!(deferred.is_empty());
+        assert!(LogRepository::new(db).list(10, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn modification_during_the_wait_restarts_the_clock() {
+        let dir = tempdir().unwrap();
+        let (db, inbox, gated, _, path) = min_age_fixture(dir.path());
+        let born = born_at(&path);
+        let seconds = chrono::Duration::seconds;
+
+        // The importer touches the file again 4 s in.
+        let touched = born + seconds(4);
+        let mtime =
+            filetime::FileTime::from_unix_time(touched.timestamp(), touched.timestamp_subsec_nanos());
+        filetime::set_file_mtime(&path, mtime).unwrap();
+
+        let deferred = with_clock(&db, born + seconds(6), |runner| {
+            let mut chain = DispatchChain::new(&inbox.id);
+            runner
+                .run_deferred(&inbox.id, &gated.id, &path, EventSource::Live, &mut chain)
+                .unwrap();
+        });
+        assert_eq!(deferred.len(), 1);
+        assert_eq!(deferred[0].0, gated.id);
+        assert!((deferred[0].1 - (touched + seconds(5))).num_milliseconds().abs() < 1000);
+        assert!(path.exists());
+
+        with_clock(&db, touched + seconds(6), |runner| {
+            let mut chain = DispatchChain::new(&inbox.id);
+            runner
+                .run_deferred(&inbox.id, &gated.id, &path, EventSource::Live, &mut chain)
+                .unwrap();
+        });
+        assert!(dir.path().join("late-photo.jpg").exists());
+    }
+
+    /// Runs `f` with a runner that executes through `execute` at `now` and
+    /// collects the retries it gave up on.
+    fn with_executor(
+        db: &Database,
+        now: DateTime<Utc>,
+        execute: &ExecuteFn,
+        f: impl FnOnce(&RuleRunner),
+    ) -> Vec<RetryExhausted> {
+        let settings = Settings::default();
+        let ocr = Mutex::new(OcrManager::new_placeholder());
+        let exhausted = Mutex::new(Vec::new());
+        let on_retry_exhausted = |retry: &RetryExhausted| {
+            exhausted.lock().unwrap().push(retry.clone());
+        };
+        let runner = RuleRunner {
+            db,
+            settings: &settings,
+            ocr: &ocr,
+            execute,
+            plan: execute,
+            on_pending: &|_| {},
+            on_conflict: &|_| {},
+            on_failure: &|_, _, _, _| {},
+            retry_locked: &|| false,
+            defer: &|_, _| {},
+            on_retry_exhausted: &on_retry_exhausted,
+            on_budget_exhausted: &|_| {},
+            timings: None,
+            writer: None,
+            now,
+            duplicates: None,
+        };
+        f(&runner);
+        exhausted.into_inner().unwrap()
+    }
+
+    /// Moves the file into `nas`, or fails the way an unreachable share does
+    /// while `online` is false. Counts the moves that went through.
+    fn flaky_move<'a>(
+        nas: &'a Path,
+        online: &'a AtomicBool,
+        moved: &'a AtomicU32,
+    ) -> impl Fn(&[Action], &FileInfo, &HashMap<String, String>, &ActionScope) -> Vec<ActionOutcome> + 'a
+    {
+        move |_, info, _, _| {
+            if !online.load(Ordering::SeqCst) {
+                return vec![ActionOutcome {
+                    action_type: ActionType::Move,
+                    status: ActionResultStatus::Error,
+                    details: None,
+                    error: Some("Host is down".to_string()),
+                    error_kind: Some(ActionErrorKind::Transient),
+                }];
+            }
+            let dest = nas.join(&info.full_name);
+            fs::rename(&info.path, &dest).unwrap();
+            moved.fetch_add(1, Ordering::SeqCst);
+            vec![ActionOutcome {
+                action_type: ActionType::Move,
+                status: ActionResultStatus::Success,
+                details: Some(ActionDetails {
+                    source_path: info.path.to_string_lossy().to_string(),
+                    destination_path: Some(dest.to_string_lossy().to_string()),
+                    metadata: HashMap::new(),
+                }),
+                error: None,
+                error_kind: None,
+            }]
+        }
+    }
+
+    /// Sends every retry due at `now` through the runner, like the engine does.
+    fn run_due_retries(db: &Database, now: DateTime<Utc>, execute: &ExecuteFn) -> Vec<RetryExhausted> {
+        let events = claim_due_events(db, now).unwrap();
+        with_executor(db, now, execute, |runner| {
+            for event in &events {
+                let rule_id = event.rule_id.as_deref().unwrap();
+                let mut chain = DispatchChain::new(&event.folder_id);
+                runner
+                    .run_deferred(
+                        &event.folder_id,
+                        rule_id,
+                        &event.path,
+                        event.source,
+                        &mut chain,
+                    )
+                    .unwrap();
+            }
+        })
+    }
+
+    #[test]
+    fn transient_failure_is_retried_until_the_destination_is_back() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.path().join("inbox").to_string_lossy(), "Inbox")
+            .unwrap();
+        let rule = add_rule(&db, &inbox, "To NAS", vec![]);
+        let nas = dir.path().join("nas");
+        fs::create_dir_all(&nas).unwrap();
+        let path = dir.path().join("scan.pdf");
+        fs::write(&path, b"scan").unwrap();
+
+        let (online, moved) = (AtomicBool::new(false), AtomicU32::new(0));
+        let execute = flaky_move(&nas, &online, &moved);
+        let t0 = Utc::now();
+        with_executor(&db, t0, &execute, |runner| {
+            let mut chain = DispatchChain::new(&inbox.id);
+            runner
+                .run(&inbox.id, FileInfo::from_path(&path).unwrap(), &mut chain)
+                .unwrap();
+        });
+        // Not recorded as matched, so nothing stops the retry from running it.
+        let hash = FileInfo::from_path(&path).unwrap().hash;
+        let matches = MatchRepository::new(db.clone());
+        assert!(matches.get_hash_matched_rules(&[rule.id.as_str()], &hash).unwrap().is_empty());
+
+        let minutes = chrono::Duration::minutes;
+        assert!(claim_due_events(&db, t0 + chrono::Duration::seconds(30)).unwrap().is_empty());
+        run_due_retries(&db, t0 + minutes(1), &execute);
+        let pending = RetryRepository::new(db.clone()).list().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].attempts, 2);
+        assert_eq!(pending[0].next_attempt_at, t0 + minutes(3));
+
+        online.store(true, Ordering::SeqCst);
+        run_due_retries(&db, t0 + minutes(3), &execute);
+        assert_eq!(moved.load(Ordering::SeqCst), 1);
+        assert!(nas.join("scan.pdf").exists());
+        assert!(RetryRepository::new(db.clone()).list().unwrap().is_empty());
+        assert!(run_due_retries(&db, t0 + chrono::Duration::days(2), &execute).is_empty());
+        assert_eq!(moved.load(Ordering::SeqCst), 1);
+
+        let logs = LogRepository::new(db).list(10, 0).unwrap();
+        let successes = logs.iter().filter(|log| log.status == LogStatus::Success).count();
+        assert_eq!(successes, 1);
+    }
+
+    #[test]
+    fn retries_give_up_after_the_last_attempt() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.path().join("inbox").to_string_lossy(), "Inbox")
+            .unwrap();
+        let rule = add_rule(&db, &inbox, "To NAS", vec![]);
+        let nas = dir.path().join("nas");
+        let path = dir.path().join("scan.pdf");
+        fs::write(&path, b"scan").unwrap();
+
+        let (online, moved) = (AtomicBool::new(false), AtomicU32::new(0));
+        let execute = flaky_move(&nas, &online, &moved);
+        let mut now = Utc::now();
+        let mut exhausted = with_executor(&db, now, &execute, |runner| {
+            let mut chain = DispatchChain::new(&inbox.id);
+            runner
+                .run(&inbox.id, FileInfo::from_path(&path).unwrap(), &mut chain)
+                .unwrap();
+        });
+        while let Some(retry) = RetryRepository::new(db.clone()).list().unwrap().pop() {
+            now = retry.next_attempt_at;
+            exhausted.extend(run_due_retries(&db, now, &execute));
+        }
+
+        assert_eq!(exhausted.len(), 1);
+        assert_eq!(exhausted[0].rule_id, rule.id);
+        assert_eq!(exhausted[0].attempts, crate::core::retry::MAX_ATTEMPTS);
+        assert_eq!(exhausted[0].last_error, "Host is down");
+        let logs = LogRepository::new(db.clone()).list(20, 0).unwrap();
+        assert!(logs.iter().any(|log| log.action_type == "retry"
+            && log.error_message.as_deref().unwrap().starts_with("Gave up")));
+        // Given up means matched: the next event for the file leaves it be.
+        let hash = FileInfo::from_path(&path).unwrap().hash;
+        let matched = MatchRepository::new(db).get_hash_matched_rules(&[rule.id.as_str()], &hash);
+        assert_eq!(matched.unwrap().len(), 1);
+        assert_eq!(moved.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn the_rule_classification_goes_into_its_tokens_logs_and_undo_history() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.path().join("inbox").to_string_lossy(), "Inbox")
+            .unwrap();
+        let mut rule = add_rule(&db, &inbox, "Ledgers", vec![]);
+        rule.classification = Some("FIN-7Y".to_string());
+        RuleRepository::new(db.clone()).update(&rule).unwrap();
+        let nas = dir.path().join("nas");
+        fs::create_dir_all(&nas).unwrap();
+        let path = dir.path().join("ledger.pdf");
+        fs::write(&path, b"ledger").unwrap();
+
+        let (online, moved) = (AtomicBool::new(true), AtomicU32::new(0));
+        let move_to_nas = flaky_move(&nas, &online, &moved);
+        let token = Mutex::new(None);
+        let execute = |actions: &[Action],
+                       info: &FileInfo,
+                       captures: &HashMap<String, String>,
+                       scope: &ActionScope| {
+            *token.lock().unwrap() = captures.get("classification").cloned();
+            move_to_nas(actions, info, captures, scope)
+        };
+        with_executor(&db, Utc::now(), &execute, |runner| {
+            let mut chain = DispatchChain::new(&inbox.id);
+            runner
+                .run(&inbox.id, FileInfo::from_path(&path).unwrap(), &mut chain)
+                .unwrap();
+        });
+
+        assert_eq!(token.into_inner().unwrap().as_deref(), Some("FIN-7Y"));
+        let logs = LogRepository::new(db.clone()).list(10, 0).unwrap();
+        let detail = logs[0].action_detail.as_ref().unwrap();
+        assert_eq!(
+            detail.metadata.get("classification").map(String::as_str),
+            Some("FIN-7Y")
+        );
+        let undo = UndoRepository::new(db.clone()).list(10).unwrap();
+        assert_eq!(undo[0].classification.as_deref(), Some("FIN-7Y"));
+
+        // The code stays in history as written after the rule moves on.
+        rule.classification = Some("FIN-10Y".to_string());
+        RuleRepository::new(db.clone()).update(&rule).unwrap();
+        let logs = LogRepository::new(db.clone()).list(10, 0).unwrap();
+        let detail = logs[0].action_detail.as_ref().unwrap();
+        assert_eq!(detail.metadata["classification"], "FIN-7Y");
+        let undo = UndoRepository::new(db).list(10).unwrap();
+        assert_eq!(undo[0].classification.as_deref(), Some("FIN-7Y"));
+    }
+
+    #[test]
+    fn logs_carry_the_rule_revision_that_ran_across_an_edit() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.path().join("inbox").to_string_lossy(), "Inbox")
+            .unwrap();
+        let mut rule = add_rule(&db, &inbox, "Archive", vec![]);
+        let nas = dir.path().join("nas");
+        fs::create_dir_all(&nas).unwrap();
+        let (online, moved) = (AtomicBool::new(true), AtomicU32::new(0));
+        let move_to_nas = flaky_move(&nas, &online, &moved);
+        let run = |name: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, name).unwrap();
+            with_executor(&db, Utc::now(), &move_to_nas, |runner| {
+                let mut chain = DispatchChain::new(&inbox.id);
+                runner
+                    .run(&inbox.id, FileInfo::from_path(&path).unwrap(), &mut chain)
+                    .unwrap();
+            });
+            let logs = LogRepository::new(db.clone()).list(1, 0).unwrap();
+            logs[0].action_detail.clone().unwrap().metadata
+        };
+
+        let before = run("first.pdf");
+        rule.actions = vec![rename("archived-")];
+        RuleRepository::new(db.clone()).update(&rule).unwrap();
+        let after = run("second.pdf");
+
+        assert_eq!(before["rule_revision"], "1");
+        assert_eq!(after["rule_revision"], "2");
+        assert_ne!(before["rule_content_hash"], after["rule_content_hash"]);
+    }
+
+    #[test]
+    fn logs_say_what_brought_each_file_in() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let root = dir.path().join("inbox");
+        let inbox = FolderRepository::new(db.clone())
+            .create(&root.to_string_lossy(), "Inbox")
+            .unwrap();
+        add_rule(&db, &inbox, "Archive", vec![]);
+        let nas = dir.path().join("nas");
+        fs::create_dir_all(&nas).unwrap();
+        fs::create_dir_all(root.join("2019/trips")).unwrap();
+        let path = root.join("2019/trips/lisbon.jpg");
+        fs::write(&path, b"photo").unwrap();
+
+        let (online, moved) = (AtomicBool::new(false), AtomicU32::new(0));
+        let move_to_nas = flaky_move(&nas, &online, &moved);
+        let seen = Mutex::new(Vec::new());
+        let execute = |actions: &[Action],
+                       info: &FileInfo,
+                       captures: &HashMap<String, String>,
+                       scope: &ActionScope| {
+            seen.lock()
+                .unwrap()
+                .push((info.event_source, info.original_subdir.clone()));
+            move_to_nas(actions, info, captures, scope)
+        };
+        let t0 = Utc::now();
+        with_executor(&db, t0, &execute, |runner| {
+            let mut info = FileInfo::from_path(&path).unwrap();
+            picked_up(&mut info, EventSource::Catchup, &root);
+            let mut chain = DispatchChain::new(&inbox.id);
+            runner.run(&inbox.id, info, &mut chain).unwrap();
+        });
+        online.store(true, Ordering::SeqCst);
+        run_due_retries(&db, t0 + chrono::Duration::hours(1), &execute);
+
+        let seen = seen.into_inner().unwrap();
+        assert_eq!(
+            seen,
+            [
+                (EventSource::Catchup, "2019/trips".to_string()),
+                (EventSource::Retry, "2019/trips".to_string()),
+            ]
+        );
+        let logs = LogRepository::new(db).list(10, 0).unwrap();
+        let sources: Vec<_> = logs
+            .iter()
+            .rev()
+            .filter(|log| log.action_type == "move")
+            .map(|log| log.action_detail.as_ref().unwrap().metadata["event_source"].clone())
+            .collect();
+        assert_eq!(sources, ["catchup", "retry"]);
+    }
+
+    #[test]
+    fn transfers_to_an_offline_volume_wait_for_it_and_go_out_when_it_returns() {
+        use crate::core::destinations::{offline_destinations, DestinationMonitor};
+        use crate::core::executor::offline_refusal;
+        use crate::models::EngineStatus;
+        use crate::utils::volumes::mock_volume;
+        use std::sync::Arc;
+
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.path().join("inbox").to_string_lossy(), "Inbox")
+            .unwrap();
+        add_rule(&db, &inbox, "To NAS", vec![]);
+        let nas = dir.path().join("nas");
+        mock_volume(&nas);
+        let path = dir.path().join("scan.pdf");
+        fs::write(&path, b"scan").unwrap();
+
+        let moved = AtomicU32::new(0);
+        let execute =
+            |_: &[Action], info: &FileInfo, _: &HashMap<String, String>, _: &ActionScope| {
+                let dest = nas.join(&info.full_name);
+                if let Some(refusal) = offline_refusal(ActionType::Move, &dest) {
+                    return vec![refusal];
+                }
+                fs::rename(&info.path, &dest).unwrap();
+                moved.fetch_add(1, Ordering::SeqCst);
+                vec![ActionOutcome {
+                    action_type: ActionType::Move,
+                    status: ActionResultStatus::Success,
+                    details: Some(ActionDetails {
+                        source_path: info.path.to_string_lossy().to_string(),
+                        destination_path: Some(dest.to_string_lossy().to_string()),
+                        metadata: HashMap::new(),
+                    }),
+                    error: None,
+                    error_kind: None,
+                }]
+            };
+        let t0 = Utc::now();
+        with_executor(&db, t0, &execute, |runner| {
+            let mut chain = DispatchChain::new(&inbox.id);
+            let retried = runner
+                .run(&inbox.id, FileInfo::from_path(&path).unwrap(), &mut chain)
+                .unwrap();
+            assert!(retried);
+        });
+
+        let pending = RetryRepository::new(db.clone()).list().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].attempts, 1);
+        assert_eq!(
+            pending[0].volume.as_deref(),
+            Some(nas.to_string_lossy().as_ref())
+        );
+        let offline = offline_destinations(&db).unwrap();
+        assert_eq!(offline.len(), 1);
+        assert_eq!(
+            (offline[0].path.as_path(), offline[0].queued_files),
+            (nas.as_path(), 1)
+        );
+        // Waiting on a volume isn't a failed attempt; it goes on waiting.
+        run_due_retries(&db, t0 + chrono::Duration::hours(1), &execute);
+        let pending = RetryRepository::new(db.clone()).list().unwrap();
+        assert_eq!(pending[0].attempts, 1);
+
+        let (events, received) = crossbeam_channel::unbounded();
+        let status = Arc::new(Mutex::new(EngineStatus::default()));
+        let monitor = DestinationMonitor::new(
+            db.clone(),
+            events,
+            Arc::new(AtomicBool::new(false)),
+            status.clone(),
+        );
+        let t1 = t0 + chrono::Duration::hours(1);
+        monitor.tick(t1).unwrap();
+        assert!(received.try_recv().is_err());
+        assert_eq!(status.lock().unwrap().offline_destinations.len(), 1);
+
+        fs::create_dir(&nas).unwrap();
+        monitor.tick(t1).unwrap();
+        let event = received.try_recv().unwrap();
+        assert!(status.lock().unwrap().offline_destinations.is_empty());
+        with_executor(&db, t1, &execute, |runner| {
+            let rule_id = event.rule_id.as_deref().unwrap();
+            let mut chain = DispatchChain::new(&event.folder_id);
+            runner
+                .run_deferred(
+                    &event.folder_id,
+                    rule_id,
+                    &event.path,
+                    event.source,
+                    &mut chain,
+                )
+                .unwrap();
+        });
+        assert_eq!(moved.load(Ordering::SeqCst), 1);
+        assert!(nas.join("scan.pdf").exists());
+        assert!(RetryRepository::new(db).list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_file_waiting_on_a_conflict_is_left_to_it() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.path().join("inbox").to_string_lossy(), "Inbox")
+            .unwrap();
+        let filed = dir.path().join("filed");
+        fs::create_dir_all(&filed).unwrap();
+        fs::write(filed.join("scan.pdf"), b"older scan").unwrap();
+        let mut asks = add_rule(
+            &db,
+            &inbox,
+            "File scans",
+            vec![Action::Move(MoveAction {
+                destination: filed.to_string_lossy().to_string(),
+                on_conflict: ConflictResolution::Ask,
+                skip_duplicates: false,
+                destination_permissions: Default::default(),
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
+            })],
+        );
+        asks.stop_processing = false;
+        RuleRepository::new(db.clone()).update(&asks).unwrap();
+        let mut later = add_rule(&db, &inbox, "Prefix", vec![rename("late-")]);
+        later.position = 1;
+        RuleRepository::new(db.clone()).update(&later).unwrap();
+        let path = dir.path().join("scan.pdf");
+        fs::write(&path, b"scan").unwrap();
+
+        let moves = AtomicU32::new(0);
+        let execute = |actions: &[Action],
+                       info: &FileInfo,
+                       captures: &HashMap<String, String>,
+                       scope: &ActionScope| {
+            let Some(Action::Move(action)) = actions.first() else {
+                return execute(actions, info, captures, scope);
+            };
+            moves.fetch_add(1, Ordering::SeqCst);
+            let dest = Path::new(&action.destination).join(&info.full_name);
+            let policy = action.on_conflict.clone();
+            let mut outcome =
+                transfer_file(ActionType::Move, Transfer::Move, &info.path, dest, policy, false);
+            mark_suspended(&mut outcome, &info.path, scope.index(0));
+            vec![outcome]
+        };
+        let run = || {
+            with_executor(&db, Utc::now(), &execute, |runner| {
+                let mut chain = DispatchChain::new(&inbox.id);
+                let info = FileInfo::from_path(&path).unwrap();
+                assert!(!runner.run(&inbox.id, info, &mut chain).unwrap());
+            });
+        };
+
+        run();
+        let conflicts = ConflictRepository::new(db.clone());
+        let pending = conflicts.list().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].rule_id, asks.id);
+        // The rule after it didn't get the file either.
+        assert!(path.exists());
+        let hash = FileInfo::from_path(&path).unwrap().hash;
+        let matched = MatchRepository::new(db.clone())
+            .get_hash_matched_rules(&[asks.id.as_str(), later.id.as_str()], &hash)
+            .unwrap();
+        assert!(matched.is_empty());
+
+        // Another event for the same file while the conflict waits.
+        run();
+        assert_eq!(moves.load(Ordering::SeqCst), 1);
+        assert!(path.exists());
+        assert_eq!(conflicts.list().unwrap()[0].id, pending[0].id);
+    }
+
+    /// An inbox whose rule moves files to `Sorted`, with `invoice.pdf`
+    /// already sorted there.
+    fn sorted_invoice() -> (tempfile::TempDir, Database, Folder, Rule) {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox_path = dir.path().join("Inbox");
+        fs::create_dir_all(&inbox_path).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&inbox_path.to_string_lossy(), "Inbox")
+            .unwrap();
+        let sorted = dir.path().join("Sorted");
+        let action = Action::Move(MoveAction {
+            destination: sorted.to_string_lossy().to_string(),
+            on_conflict: ConflictResolution::Rename,
+            skip_duplicates: false,
+            destination_permissions: Default::default(),
+            quarantine: Default::default(),
+            bring_companions: None,
+            auto_partition: None,
+        });
+        let rule = add_rule(&db, &inbox, "Sort", vec![action]);
+        let path = inbox_path.join("invoice.pdf");
+        fs::write(&path, b"invoice").unwrap();
+        run(&db, &inbox, &path);
+        assert!(sorted.join("invoice.pdf").exists());
+        (dir, db, inbox, rule)
+    }
+
+    /// Puts a copy of the sorted invoice back in the inbox, as a sync client
+    /// downloading it again would.
+    fn bring_back(dir: &Path) -> PathBuf {
+        let sorted = dir.join("Sorted/invoice.pdf");
+        let incoming = dir.join("Inbox/invoice.pdf");
+        fs::copy(&sorted, &incoming).unwrap();
+        let mtime = filetime::FileTime::from_last_modification_time(
+            &fs::metadata(&sorted).unwrap(),
+        );
+        filetime::set_file_mtime(&incoming, mtime).unwrap();
+        incoming
+    }
+
+    #[test]
+    fn reappearing_file_is_skipped_with_the_existing_location() {
+        let (dir, db, inbox, rule) = sorted_invoice();
+        let incoming = bring_back(dir.path());
+        run(&db, &inbox, &incoming);
+
+        assert!(incoming.exists());
+        let sorted = dir.path().join("Sorted");
+        assert_eq!(fs::read_dir(&sorted).unwrap().count(), 1);
+        let logs = LogRepository::new(db).list(10, 0).unwrap();
+        let log = logs.iter().find(|log| log.action_type == "reappeared").unwrap();
+        assert_eq!(log.rule_id.as_deref(), Some(rule.id.as_str()));
+        assert_eq!(log.status, LogStatus::Skipped);
+        let existing = sorted.join("invoice.pdf").to_string_lossy().to_string();
+        assert!(log.error_message.as_deref().unwrap().contains(&existing));
+        let detail = log.action_detail.as_ref().unwrap();
+        assert_eq!(detail.metadata["existing_path"], existing);
+        assert_eq!(detail.metadata["first_path"], incoming.to_string_lossy());
+        assert!(detail.metadata.contains_key("first_processed_at"));
+    }
+
+    #[test]
+    fn delete_incoming_removes_only_the_new_copy() {
+        let (dir, db, inbox, mut rule) = sorted_invoice();
+        rule.on_reappear = Some(OnReappear::DeleteIncoming);
+        RuleRepository::new(db.clone()).update(&rule).unwrap();
+        let incoming = bring_back(dir.path());
+        run(&db, &inbox, &incoming);
+
+        assert!(!incoming.exists());
+        assert_eq!(fs::read(dir.path().join("Sorted/invoice.pdf")).unwrap(), b"invoice");
+    }
+
+    #[test]
+    fn missing_historical_destination_processes_the_file_again() {
+        let (dir, db, inbox, _) = sorted_invoice();
+        let incoming = bring_back(dir.path());
+        let sorted = dir.path().join("Sorted/invoice.pdf");
+        fs::remove_file(&sorted).unwrap();
+        run(&db, &inbox, &incoming);
+
+        assert!(!incoming.exists());
+        assert!(sorted.exists());
+        let logs = LogRepository::new(db).list(10, 0).unwrap();
+        assert!(logs.iter().all(|log| log.action_type != "reappeared"));
+    }
+
+    #[test]
+    fn rule_setting_overrides_the_global_one() {
+        let (dir, db, inbox, mut rule) = sorted_invoice();
+        let settings = Settings {
+            on_reappear: OnReappear::DeleteIncoming,
+            ..Settings::default()
+        };
+        rule.on_reappear = Some(OnReappear::SkipSilently);
+        RuleRepository::new(db.clone()).update(&rule).unwrap();
+        let incoming = bring_back(dir.path());
+        run_with(&db, &inbox, &incoming, &settings);
+        assert!(incoming.exists());
+
+        rule.on_reappear = None;
+        RuleRepository::new(db.clone()).update(&rule).unwrap();
+        run_with(&db, &inbox, &incoming, &settings);
+        assert!(!incoming.exists());
+    }
+
+    /// Stands in for the executor's copies, skipping the ones an earlier rule
+    /// already made for the event the way it does.
+    fn deduping_copy(
+        settings: &DedupeActionsSettings,
+    ) -> impl Fn(&[Action], &FileInfo, &HashMap<String, String>, &ActionScope) -> Vec<ActionOutcome> + '_
+    {
+        move |actions, info, captures, scope| {
+            let engine = PatternEngine::new();
+            let mut outcomes = Vec::new();
+            for (offset, action) in actions.iter().enumerate() {
+                let Action::Copy(copy) = action else {
+                    continue;
+                };
+                let planned = PlannedOperation::of(&engine, action, info, captures, settings);
+                if let Some(op) = &planned {
+                    if let Some(earlier) = scope.duplicate_of(op) {
+                        outcomes.push(earlier.duplicate_outcome(op, &info.path));
+                        continue;
+                    }
+                }
+                let outcome = transfer_file(
+                    ActionType::Copy,
+                    Transfer::Copy(ReflinkMode::Never),
+                    &info.path,
+                    Path::new(&copy.destination).join(&info.os_name),
+                    copy.on_conflict.clone(),
+                    false,
+                );
+                if let Some(op) = planned {
+                    scope.performed(op, offset, &outcome);
+                }
+                outcomes.push(outcome);
+            }
+            outcomes
+        }
+    }
+
+    fn copy_to(destination: &Path) -> Action {
+        Action::Copy(CopyAction {
+            destination: format!("{}/", destination.display()),
+            on_conflict: ConflictResolution::Rename,
+            skip_duplicates: false,
+            use_reflink: None,
+            destination_permissions: Default::default(),
+            quarantine: Default::default(),
+            bring_companions: None,
+            auto_partition: None,
+        })
+    }
+
+    /// A catch-all rule that copies to `first` and doesn't stop processing,
+    /// then a specific one that copies to `second`.
+    fn copy_with_two_rules(first: &Path, second: &Path) -> (tempfile::TempDir, Database) {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let inbox = FolderRepository::new(db.clone())
+            .create(&dir.path().join("inbox").to_string_lossy(), "Inbox")
+            .unwrap();
+        let mut catch_all = add_rule(&db, &inbox, "Catch-all", vec![copy_to(first)]);
+        catch_all.stop_processing = false;
+        RuleRepository::new(db.clone()).update(&catch_all).unwrap();
+        add_rule(&db, &inbox, "Invoices", vec![copy_to(second)]);
+
+        let path = dir.path().join("invoice.pdf");
+        fs::write(&path, b"invoice").unwrap();
+        let settings = DedupeActionsSettings {
+            notify: true,
+            file_operations: true,
+        };
+        let execute = deduping_copy(&settings);
+        with_executor(&db, Utc::now(), &execute, |runner| {
+            let mut chain = DispatchChain::new(&inbox.id);
+            runner
+                .run(&inbox.id, FileInfo::from_path(&path).unwrap(), &mut chain)
+                .unwrap();
+        });
+        (dir, db)
+    }
+
+    #[test]
+    fn a_copy_an_earlier_rule_made_for_the_event_is_skipped() {
+        let out = tempdir().unwrap();
+        // The same folder, spelled differently.
+        let spelled = out.path().join("sub/..");
+        let (_dir, db) = copy_with_two_rules(out.path(), &spelled);
+
+        assert!(out.path().join("invoice.pdf").exists());
+        assert!(!out.path().join("invoice (1).pdf").exists());
+        let logs = LogRepository::new(db).list(10, 0).unwrap();
+        let skipped: Vec<_> = logs
+            .iter()
+            .filter(|log| log.status == LogStatus::Skipped)
+            .collect();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].rule_name.as_deref(), Some("Invoices"));
+        assert_eq!(
+            skipped[0].error_message.as_deref(),
+            Some("Duplicate of rule \"Catch-all\" action 1 in this event")
+        );
+    }
+
+    #[test]
+    fn copies_to_different_folders_both_go_through() {
+        let out = tempdir().unwrap();
+        let (_dir, db) = copy_with_two_rules(&out.path().join("all"), &out.path().join("invoices"));
+
+        assert!(out.path().join("all/invoice.pdf").exists());
+        assert!(out.path().join("invoices/invoice.pdf").exists());
+        let logs = LogRepository::new(db).list(10, 0).unwrap();
+        assert!(logs.iter().all(|log| log.status != LogStatus::Skipped));
+    }
+}
@@ -1,22 +1,148 @@
 use std::collections::HashMap;
-use std::fmt::Write as _;
+use std::fmt::{self, Write as _};
 use std::fs::{self, File};
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Mutex;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::Utc;
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-use crate::models::Folder;
+use crate::models::{DuplicateScope, Folder, SampledHashSettings, SampledMatch};
 use crate::storage::database::Database;
+use crate::storage::folder_repo::FolderRepository;
 use crate::utils::platform::normalize_user_path;
+use crate::utils::walk::{walk_files, WalkOptions};
 
 pub struct DuplicateDetector {
     db: Database,
-    cache: Mutex<HashMap<String, HashMap<String, PathBuf>>>,
+    cache: Mutex<HashMap<String, HashMap<Fingerprint, PathBuf>>>,
+}
+
+/// What duplicate checks compare files by. Fingerprints only equal ones of
+/// the same scheme taken with the same parameters, so a sampled one never
+/// matches a full hash, nor one sampled before the settings changed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Fingerprint {
+    pub scheme: HashScheme,
+    /// SHA-256 of the whole file, or of its size and samples.
+    pub digest: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum HashScheme {
+    Full,
+    Sampled(Sampling),
+}
+
+/// The windows a sampled hash reads; see `SampledHashSettings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Sampling {
+    pub head_bytes: u64,
+    pub tail_bytes: u64,
+    pub window_count: u32,
+    pub window_bytes: u64,
+}
+
+impl HashScheme {
+    /// How a file of `size` bytes is hashed under `settings`.
+    pub(crate) fn for_size(size: u64, settings: &SampledHashSettings) -> Self {
+        if !settings.enabled || size < settings.min_size_bytes.max(1) {
+            return HashScheme::Full;
+        }
+        HashScheme::Sampled(Sampling {
+            head_bytes: settings.head_bytes,
+            tail_bytes: settings.tail_bytes,
+            window_count: settings.window_count,
+            window_bytes: settings.window_bytes,
+        })
+    }
+
+    pub(crate) fn fingerprint(self, path: &Path) -> Result<Fingerprint> {
+        let digest = match self {
+            HashScheme::Full => hash_file(path)?,
+            HashScheme::Sampled(sampling) => sampling.hash(path)?,
+        };
+        Ok(Fingerprint {
+            scheme: self,
+            digest,
+        })
+    }
+}
+
+/// Stored next to the digest, e.g. `sampled:4194304+4194304+8x1048576`.
+impl fmt::Display for HashScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashScheme::Full => f.write_str("sha256"),
+            HashScheme::Sampled(s) => write!(
+                f,
+                "sampled:{}+{}+{}x{}",
+                s.head_bytes, s.tail_bytes, s.window_count, s.window_bytes
+            ),
+        }
+    }
+}
+
+impl FromStr for HashScheme {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        if text == "sha256" {
+            return Ok(HashScheme::Full);
+        }
+        let bad = || anyhow!("unknown hash scheme {text}");
+        let sizes = text.strip_prefix("sampled:").ok_or_else(bad)?;
+        let (edges, windows) = sizes.rsplit_once('+').ok_or_else(bad)?;
+        let (head, tail) = edges.split_once('+').ok_or_else(bad)?;
+        let (count, window) = windows.split_once('x').ok_or_else(bad)?;
+        Ok(HashScheme::Sampled(Sampling {
+            head_bytes: head.parse()?,
+            tail_bytes: tail.parse()?,
+            window_count: count.parse()?,
+            window_bytes: window.parse()?,
+        }))
+    }
+}
+
+impl Sampling {
+    /// Where each window starts and how long it is in a file of `size`
+    /// bytes: the head, the evenly spaced windows, then the tail. Windows
+    /// may overlap in small files.
+    fn windows(&self, size: u64) -> Vec<(u64, u64)> {
+        let mut windows = vec![(0, self.head_bytes.min(size))];
+        let window = self.window_bytes.min(size);
+        let count = u64::from(self.window_count);
+        for i in 1..=count {
+            windows.push(((size - window) / (count + 1) * i, window));
+        }
+        let tail = self.tail_bytes.min(size);
+        windows.push((size - tail, tail));
+        windows
+    }
+
+    fn hash(&self, path: &Path) -> Result<String> {
+        let mut file = File::open(path)?;
+        let size = file.metadata()?.len();
+        let mut hasher = Sha256::new();
+        hasher.update(size.to_le_bytes());
+        let mut buffer = [0u8; 8192];
+        for (start, len) in self.windows(size) {
+            file.seek(SeekFrom::Start(start))?;
+            let mut window = (&mut file).take(len);
+            loop {
+                let read = window.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+        }
+        Ok(to_hex(&hasher.finalize()))
+    }
 }
 
 impl DuplicateDetector {
@@ -28,81 +154,140 @@ impl DuplicateDetector {
     }
 
     /// Returns true if the file was removed as a duplicate.
-    pub fn check_and_remove(&self, folder: &Folder, file_path: &Path) -> Result<bool> {
-        if !file_path.is_file() {
+    pub fn check_and_remove(
+        &self,
+        folder: &Folder,
+        file_path: &Path,
+        sampling: &SampledHashSettings,
+    ) -> Result<bool> {
+        let Some((fingerprint, original_path)) =
+            self.locate(std::slice::from_ref(folder), file_path, sampling)?
+        else {
             return Ok(false);
+        };
+        if self.remove_duplicate(folder, file_path, &fingerprint, &original_path) {
+            return Ok(true);
+        }
+        self.store_cache(folder, &fingerprint, file_path);
+        Ok(false)
+    }
+
+    /// Another file with the same contents as `file_path` in `scope`, seen
+    /// from the folder `folder_id` whose rules are running. Nothing is
+    /// removed; a file with no duplicate is remembered as the one later
+    /// copies duplicate.
+    pub fn find_duplicate(
+        &self,
+        folder_id: &str,
+        scope: DuplicateScope,
+        file_path: &Path,
+        sampling: &SampledHashSettings,
+    ) -> Result<Option<PathBuf>> {
+        let folders = FolderRepository::new(self.db.clone());
+        let Some(folder) = folders.get(folder_id)? else {
+            return Ok(None);
+        };
+        let mut scoped = vec![folder];
+        if scope == DuplicateScope::AnyWatchedFolder {
+            scoped.extend(folders.list()?.into_iter().filter(|other| {
+                other.id != folder_id && other.enabled && !other.is_group && !other.path.is_empty()
+            }));
+        }
+        Ok(self
+            .locate(&scoped, file_path, sampling)?
+            .map(|(_, original)| original))
+    }
+
+    /// The first of `folders` is the one `file_path` was found in. Without a
+    /// duplicate in any of them, the file is cached as its folder's copy of
+    /// its contents.
+    fn locate(
+        &self,
+        folders: &[Folder],
+        file_path: &Path,
+        sampling: &SampledHashSettings,
+    ) -> Result<Option<(Fingerprint, PathBuf)>> {
+        let Some(home) = folders.first() else {
+            return Ok(None);
+        };
+        if !file_path.is_file() {
+            return Ok(None);
         }
 
         let file_size = match fs::metadata(file_path) {
             Ok(metadata) => metadata.len(),
             Err(err) => {
                 eprintln!("Duplicate check failed to read metadata: {err}");
-                return Ok(false);
+                return Ok(None);
             }
         };
 
-        let file_hash = match hash_file(file_path) {
-            Ok(hash) => hash,
+        let fingerprint = match HashScheme::for_size(file_size, sampling).fingerprint(file_path) {
+            Ok(fingerprint) => fingerprint,
             Err(err) => {
                 eprintln!(
                     "Duplicate check failed to hash file {}: {err}",
                     file_path.display()
                 );
-                return Ok(false);
+                return Ok(None);
             }
         };
 
-        if let Some(original_path) = self.cached_original(folder, file_path, &file_hash, file_size)
-        {
-            if self.remove_duplicate(folder, file_path, &file_hash, &original_path) {
-                return Ok(true);
+        for folder in folders {
+            if let Some(original_path) = self.cached_original(
+                folder,
+                file_path,
+                &fingerprint,
+                file_size,
+                sampling.on_match,
+            ) {
+                return Ok(Some((fingerprint, original_path)));
             }
         }
 
-        if let Some(original_path) =
-            self.find_existing_original(folder, file_path, file_size, &file_hash)?
-        {
-            if self.remove_duplicate(folder, file_path, &file_hash, &original_path) {
-                self.store_cache(folder, &file_hash, &original_path);
-                return Ok(true);
+        for folder in folders {
+            if let Some(original_path) =
+                self.find_existing_original(folder, file_path, file_size, &fingerprint, sampling)?
+            {
+                self.store_cache(folder, &fingerprint, &original_path);
+                return Ok(Some((fingerprint, original_path)));
             }
         }
 
-        self.store_cache(folder, &file_hash, file_path);
-        Ok(false)
+        self.store_cache(home, &fingerprint, file_path);
+        Ok(None)
     }
 
     fn cached_original(
         &self,
         folder: &Folder,
         file_path: &Path,
-        file_hash: &str,
+        fingerprint: &Fingerprint,
         file_size: u64,
+        on_match: SampledMatch,
     ) -> Option<PathBuf> {
         let mut cache = self.cache.lock().ok()?;
         let folder_cache = cache.entry(folder.id.clone()).or_default();
-        if let Some(original) = folder_cache.get(file_hash).cloned() {
+        if let Some(original) = folder_cache.get(fingerprint).cloned() {
             if original == file_path {
                 return None;
             }
             if let Ok(metadata) = fs::metadata(&original) {
-                if metadata.len() == file_size {
-                    if let Ok(original_hash) = hash_file(&original) {
-                        if original_hash == file_hash {
-                            return Some(original);
-                        }
-                    }
+                if metadata.len() == file_size
+                    && same_content(file_path, &original, fingerprint, on_match)
+                {
+                    return Some(original);
                 }
             }
-            folder_cache.remove(file_hash);
+            folder_cache.remove(fingerprint);
         }
         None
     }
 
-    fn store_cache(&self, folder: &Folder, file_hash: &str, file_path: &Path) {
+    fn store_cache(&self, folder: &Folder, fingerprint: &Fingerprint, file_path: &Path) {
         if let Ok(mut cache) = self.cache.lock() {
             let folder_cache = cache.entry(folder.id.clone()).or_default();
-            folder_cache.insert(file_hash.to_string(), file_path.to_path_buf());
+            folder_cache.insert(fingerprint.clone(), file_path.to_path_buf());
         }
     }
 
@@ -111,39 +296,31 @@ impl DuplicateDetector {
         folder: &Folder,
         file_path: &Path,
         file_size: u64,
-        file_hash: &str,
+        fingerprint: &Fingerprint,
+        sampling: &SampledHashSettings,
     ) -> Result<Option<PathBuf>> {
         let folder_path = normalize_user_path(&folder.path);
         if !folder_path.exists() {
             return Ok(None);
         }
 
-        let max_depth = folder.max_depth().unwrap_or(usize::MAX);
-        for entry in walkdir::WalkDir::new(&folder_path)
-            .max_depth(max_depth)
-            .into_iter()
-            .filter_map(|entry| entry.ok())
-        {
-            if !entry.file_type().is_file() {
-                continue;
-            }
-            let path = entry.path();
+        let options = WalkOptions {
+            max_depth: folder.max_depth().unwrap_or(usize::MAX),
+            ..WalkOptions::default()
+        };
+        for path in walk_files(&folder_path, options, |_| false).flatten() {
             if path == file_path {
                 continue;
             }
-            let metadata = match entry.metadata() {
+            let metadata = match std::fs::metadata(&path) {
                 Ok(metadata) => metadata,
                 Err(_) => continue,
             };
             if metadata.len() != file_size {
                 continue;
             }
-            let candidate_hash = match hash_file(path) {
-                Ok(hash) => hash,
-                Err(_) => continue,
-            };
-            if candidate_hash == file_hash {
-                return Ok(Some(path.to_path_buf()));
+            if same_content(file_path, &path, fingerprint, sampling.on_match) {
+                return Ok(Some(path));
             }
         }
         Ok(None)
@@ -153,7 +330,7 @@ impl DuplicateDetector {
         &self,
         folder: &Folder,
         file_path: &Path,
-        file_hash: &str,
+        fingerprint: &Fingerprint,
         original_path: &Path,
     ) -> bool {
         if let Err(err) = trash::delete(file_path) {
@@ -171,12 +348,13 @@ impl DuplicateDetector {
 
         if let Err(err) = self.db.with_conn(|conn| {
             conn.execute(
-                "INSERT INTO duplicate_removals (id, folder_id, file_path, file_hash, original_path, removed_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                "INSERT INTO duplicate_removals (id, folder_id, file_path, file_hash, hash_scheme, original_path, removed_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
                 rusqlite::params![
                     removal_id,
                     folder.id,
                     file_path_str,
-                    file_hash,
+                    fingerprint.digest,
+                    fingerprint.scheme.to_string(),
                     original_path_str,
                     removed_at,
                 ],
@@ -190,7 +368,52 @@ impl DuplicateDetector {
     }
 }
 
-fn hash_file(path: &Path) -> Result<String> {
+/// Whether `candidate` holds what `file`, with `fingerprint`, does. It's
+/// fingerprinted the same way; when samples agree, `on_match` decides whether
+/// both are read in full to be sure.
+pub(crate) fn same_content(
+    file: &Path,
+    candidate: &Path,
+    fingerprint: &Fingerprint,
+    on_match: SampledMatch,
+) -> bool {
+    let Ok(other) = fingerprint.scheme.fingerprint(candidate) else {
+        return false;
+    };
+    if other != *fingerprint {
+        return false;
+    }
+    match (fingerprint.scheme, on_match) {
+        (HashScheme::Full, _) | (HashScheme::Sampled(_), SampledMatch::Trust) => true,
+        (HashScheme::Sampled(_), SampledMatch::Confirm) => {
+            matches!((hash_file(file), hash_file(candidate)), (Ok(a), Ok(b)) if a == b)
+        }
+    }
+}
+
+/// Undo only needs to notice that a file was edited after an action wrote
+/// it, so files of at least this size are sampled rather than read whole.
+const UNDO_SAMPLED_FROM_BYTES: u64 = 16 * 1024 * 1024;
+
+/// What undo history records of a file an action just wrote.
+pub(crate) fn undo_fingerprint(path: &Path) -> Result<Fingerprint> {
+    let size = fs::metadata(path)?.len();
+    let sampling = SampledHashSettings {
+        enabled: true,
+        min_size_bytes: UNDO_SAMPLED_FROM_BYTES,
+        ..SampledHashSettings::default()
+    };
+    HashScheme::for_size(size, &sampling).fingerprint(path)
+}
+
+/// Whether `path` still holds what was recorded as `digest`, taken the way
+/// `scheme` names; no scheme means a full hash.
+pub(crate) fn matches_recorded(path: &Path, digest: &str, scheme: Option<&str>) -> Result<bool> {
+    let scheme = scheme.map_or(Ok(HashScheme::Full), str::parse)?;
+    Ok(scheme.fingerprint(path)?.digest == digest)
+}
+
+pub(crate) fn hash_file(path: &Path) -> Result<String> {
     let file = File::open(path)?;
     let mut reader = BufReader::new(file);
     let mut hasher = Sha256::new();
@@ -204,10 +427,151 @@ fn hash_file(path: &Path) -> Result<String> {
         hasher.update(&buffer[..read]);
     }
 
-    let digest = hasher.finalize();
+    Ok(to_hex(&hasher.finalize()))
+}
+
+fn to_hex(digest: &[u8]) -> String {
     let mut hex = String::with_capacity(digest.len() * 2);
     for byte in digest {
         let _ = write!(hex, "{:02x}", byte);
     }
-    Ok(hex)
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    const SIZE: u64 = 1024 * 1024;
+
+    fn settings(on_match: SampledMatch) -> SampledHashSettings {
+        SampledHashSettings {
+            enabled: true,
+            min_size_bytes: 64 * 1024,
+            head_bytes: 4096,
+            tail_bytes: 4096,
+            window_count: 2,
+            window_bytes: 4096,
+            on_match,
+        }
+    }
+
+    /// A sparse file of `SIZE` bytes, zero but for `byte` at `offset`.
+    fn sparse(path: &Path, offset: u64, byte: u8) {
+        let mut file = File::create(path).unwrap();
+        file.set_len(SIZE).unwrap();
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(&[byte]).unwrap();
+    }
+
+    fn fingerprint(path: &Path, settings: &SampledHashSettings) -> Fingerprint {
+        HashScheme::for_size(SIZE, settings)
+            .fingerprint(path)
+            .unwrap()
+    }
+
+    #[test]
+    fn files_differing_between_the_samples_are_only_duplicates_when_trusted() {
+        let dir = tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.mkv"), dir.path().join("b.mkv"));
+        // Well clear of the head, tail and the windows at a third and two thirds.
+        sparse(&a, 100_000, 1);
+        sparse(&b, 100_000, 2);
+
+        let confirm = settings(SampledMatch::Confirm);
+        let sampled = fingerprint(&a, &confirm);
+        assert!(matches!(sampled.scheme, HashScheme::Sampled(_)));
+        assert_eq!(sampled, fingerprint(&b, &confirm));
+        assert!(!same_content(&a, &b, &sampled, SampledMatch::Confirm));
+        assert!(same_content(&a, &b, &sampled, SampledMatch::Trust));
+
+        // A difference inside a window shows without reading the rest.
+        let c = dir.path().join("c.mkv");
+        sparse(&c, SIZE - 1, 1);
+        assert!(!same_content(&a, &c, &sampled, SampledMatch::Trust));
+    }
+
+    #[test]
+    fn identical_files_are_duplicates_either_way() {
+        let dir = tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.mkv"), dir.path().join("b.mkv"));
+        sparse(&a, 100_000, 7);
+        sparse(&b, 100_000, 7);
+
+        for on_match in [SampledMatch::Trust, SampledMatch::Confirm] {
+            let sampled = fingerprint(&a, &settings(on_match));
+            assert!(same_content(&a, &b, &sampled, on_match));
+        }
+        let full = HashScheme::Full.fingerprint(&a).unwrap();
+        assert!(same_content(&a, &b, &full, SampledMatch::Trust));
+    }
+
+    #[test]
+    fn find_duplicate_looks_as_far_as_the_scope_and_removes_nothing() {
+        let dir = tempdir().unwrap();
+        let (inbox, archive) = (dir.path().join("inbox"), dir.path().join("archive"));
+        fs::create_dir_all(&inbox).unwrap();
+        fs::create_dir_all(&archive).unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folders = crate::storage::folder_repo::FolderRepository::new(db.clone());
+        let watched = folders.create(&inbox.to_string_lossy(), "Inbox").unwrap();
+        folders
+            .create(&archive.to_string_lossy(), "Archive")
+            .unwrap();
+        let original = archive.join("scan.pdf");
+        let copy = inbox.join("scan (1).pdf");
+        fs::write(&original, b"scanned").unwrap();
+        fs::write(&copy, b"scanned").unwrap();
+
+        let detector = DuplicateDetector::new(db);
+        let sampling = SampledHashSettings::default();
+        let find = |scope| {
+            detector
+                .find_duplicate(&watched.id, scope, &copy, &sampling)
+                .unwrap()
+        };
+        assert_eq!(find(DuplicateScope::SameFolder), None);
+        assert_eq!(find(DuplicateScope::AnyWatchedFolder), Some(original));
+        assert!(copy.exists());
+    }
+
+    #[test]
+    fn fingerprints_taken_another_way_never_match() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.mkv");
+        sparse(&a, 100_000, 7);
+        let before = fingerprint(&a, &settings(SampledMatch::Trust));
+        let after = fingerprint(
+            &a,
+            &SampledHashSettings {
+                window_count: 3,
+                ..settings(SampledMatch::Trust)
+            },
+        );
+        assert_ne!(before, after);
+        assert_ne!(before, HashScheme::Full.fingerprint(&a).unwrap());
+        assert_eq!(before.scheme.to_string(), "sampled:4096+4096+2x4096");
+        let parsed: HashScheme = before.scheme.to_string().parse().unwrap();
+        assert_eq!(parsed, before.scheme);
+        assert!(matches_recorded(&a, &before.digest, Some("sampled:4096+4096+2x4096")).unwrap());
+        assert!(!matches_recorded(&a, &before.digest, None).unwrap());
+        assert!(matches_recorded(&a, &before.digest, Some("sampled:4096")).is_err());
+
+        // A record taken before the change doesn't vouch for files after it.
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let detector = DuplicateDetector::new(db);
+        let folder = crate::storage::folder_repo::FolderRepository::new(detector.db.clone())
+            .create(&dir.path().to_string_lossy(), "Videos")
+            .unwrap();
+        let b = dir.path().join("b.mkv");
+        sparse(&b, 100_000, 7);
+        detector.store_cache(&folder, &before, &a);
+        let cached = |fingerprint| {
+            detector.cached_original(&folder, &b, fingerprint, SIZE, SampledMatch::Trust)
+        };
+        assert_eq!(cached(&after), None);
+        assert_eq!(cached(&before), Some(a));
+    }
 }
@@ -1,50 +1,240 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroUsize;
 use std::process::Command;
 use std::thread;
 
-use anyhow::Result;
-use chrono::{Duration, Utc};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use filedispatch_core::cloud_placeholder::request_hydration;
+use filedispatch_core::evaluate::{self, Collision, ContentProvider, ResolvedContents};
+use filedispatch_core::file_info::{FileInfo, FileInfoError};
+use filedispatch_core::media::MediaInfo;
+use filedispatch_core::screenshot::ScreenshotEvidence;
 use lru::LruCache;
-use regex::{Regex, RegexBuilder};
 
-thread_local! {
-    /// Thread-local cache for compiled regexes to avoid recompilation
-    static REGEX_CACHE: RefCell<LruCache<(String, bool), Regex>> =
-        RefCell::new(LruCache::new(NonZeroUsize::new(100).unwrap()));
-}
-
-use crate::core::content::{resolve_contents, ContentCache};
-use crate::core::duplicates::DuplicateDetector;
-use crate::core::executor::{ActionExecutor, ActionOutcome, ActionResultStatus};
+use crate::core::arrivals::{ArrivalGroups, MAX_GROUP_WINDOW_MS};
+use crate::core::classify;
+use crate::core::content::{
+    resolve_archive_listing, resolve_contents, resolve_contents_detailed, resolve_csv_column,
+    resolve_exif_date, resolve_face_count, resolve_image_dimensions, resolve_media_info,
+    resolve_mime_type, resolve_page_count, resolve_screenshot_evidence, should_stream_contents,
+    ContentCache,
+};
+use crate::core::dispatch::{DispatchChain, DispatchTrace, RuleRunner};
+use crate::core::duplicates::{hash_file, undo_fingerprint, DuplicateDetector, HashScheme};
+use crate::core::error_notify::ErrorNotifier;
+use crate::core::event_journal::{journaled, EventJournal, Settled};
+use crate::core::event_writer::EventWriter;
+use crate::core::folder_lanes::{FolderLanes, Ticket};
+use crate::core::match_metadata::{self, MatchMetadata};
+use crate::core::revisions;
+use crate::core::startup::EventMux;
+use crate::core::executor::{
+    ActionExecutor, ActionOutcome, ActionResultStatus, ActionScope, COMPANION_OF_KEY,
+    COMPANION_RECORD_KEY,
+};
+use crate::core::stream_search::search_file;
+use crate::core::timings::RuleTimings;
 use crate::core::watcher::{FileEvent, FileEventKind};
 use crate::models::{
-    ActionDetails, ActionType, Condition, ConditionGroup, DateOperator, EngineError, EngineEvent,
-    EngineStatus, FileKind, LogEntry, LogStatus, MatchType, Rule, SizeUnit, StringCondition,
-    StringOperator, TimeOperator, TimeUnit,
+    ActionDetails, ActionType, BudgetExhausted, Condition, ConditionGroup, ContentSource,
+    CsvColumnCondition, DuplicateScope, EngineError, EngineEvent, EngineStatus, ErrorNotifyMode,
+    EventDisposition, EventSource, LogEntry, LogStatus, PendingApproval, PendingConflict,
+    PlaceholderMode, RetryExhausted, Rule, StringCondition, UndoStatus,
 };
 use crate::storage::database::Database;
 use crate::storage::folder_repo::FolderRepository;
 use crate::storage::log_repo::LogRepository;
-use crate::storage::match_repo::MatchRepository;
 use crate::storage::rule_repo::RuleRepository;
-use crate::storage::undo_repo::UndoRepository;
-use crate::utils::file_info::FileInfo;
+use crate::storage::write_set::WriteSet;
+use crate::utils::archive::ArchiveListing;
+use crate::utils::file_lock::is_locked;
+use crate::utils::file_tags::{self, TagFormat};
+use crate::utils::gitguard::{GitGuard, GitHold};
+use crate::utils::markers::{self, ProcessedMark};
+use crate::utils::platform::{expand_tilde, normalize_user_path};
+use crate::utils::xattr;
+
+pub(crate) use filedispatch_core::evaluate::EvaluationResult;
 
 /// Maximum entries in the debounce cache before LRU eviction
 const DEBOUNCE_CACHE_CAPACITY: usize = 10_000;
 
+/// How many times a locked file is requeued before the engine gives up on it
+const MAX_LOCK_RETRIES: u32 = 5;
+
+/// How often a hydrating placeholder is re-checked
+const HYDRATE_POLL_INTERVAL_MS: u64 = 15_000;
+
+/// How often an event held for a busy git repository is looked at again.
+const GIT_RECHECK_MS: u64 = 5_000;
+
+/// Whether `path` had an event less than `window_ms` before `now`. Otherwise
+/// `now` is remembered as its latest event.
+pub(crate) fn debounced(
+    last_seen: &std::sync::Mutex<LruCache<std::path::PathBuf, std::time::Instant>>,
+    path: &std::path::Path,
+    now: std::time::Instant,
+    window_ms: u64,
+) -> bool {
+    let Ok(mut last_seen) = last_seen.lock() else {
+        return false;
+    };
+    if let Some(prev) = last_seen.peek(path) {
+        if now.duration_since(*prev).as_millis() < window_ms as u128 {
+            return true;
+        }
+    }
+    last_seen.put(path.to_path_buf(), now);
+    false
+}
+
+/// What the engine does with a file given its folder's placeholder mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PlaceholderGate {
+    /// Evaluate rules normally.
+    Process,
+    /// Evaluate rules; content conditions fail and moves are refused.
+    MetadataOnly,
+    /// Log the file as skipped and stop.
+    Skip,
+    /// Trigger a download and requeue the event.
+    Hydrate,
+}
+
+pub(crate) fn placeholder_gate(mode: PlaceholderMode, is_placeholder: bool) -> PlaceholderGate {
+    if !is_placeholder {
+        return PlaceholderGate::Process;
+    }
+    match mode {
+        PlaceholderMode::Skip => PlaceholderGate::Skip,
+        PlaceholderMode::ProcessMetadataOnly => PlaceholderGate::MetadataOnly,
+        PlaceholderMode::Hydrate => PlaceholderGate::Hydrate,
+    }
+}
+
+/// When a rule with a minimum file age may act on `info`, or `None` if it
+/// may already. The age runs from the later of creation and last modification.
+pub(crate) fn min_age_eligible_at(
+    rule: &Rule,
+    info: &FileInfo,
+    now: chrono::DateTime<Utc>,
+) -> Option<chrono::DateTime<Utc>> {
+    let min_age = rule.min_age_seconds.filter(|secs| *secs > 0)?;
+    let eligible_at = info.created.max(info.modified) + Duration::seconds(min_age as i64);
+    (now < eligible_at).then_some(eligible_at)
+}
+
+/// Whether `path` lies within the rule's `path_scope` under the folder at
+/// `root`. A plain scope names a subfolder; a glob may match the file or any
+/// folder above it. Rules without a scope cover the whole folder.
+pub(crate) fn in_path_scope(rule: &Rule, root: &std::path::Path, path: &std::path::Path) -> bool {
+    let Some(scope) = rule.path_scope.as_deref().map(normalize_scope) else {
+        return true;
+    };
+    if scope.is_empty() {
+        return true;
+    }
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let relative: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+
+    if !is_scope_glob(&scope) {
+        let scope: Vec<&str> = scope.split('/').collect();
+        return relative.len() > scope.len()
+            && relative.iter().zip(&scope).all(|(part, expected)| part == expected);
+    }
+    let Ok(pattern) = glob::Pattern::new(&scope) else {
+        return false;
+    };
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..glob::MatchOptions::new()
+    };
+    (1..=relative.len()).any(|depth| pattern.matches_with(&relative[..depth].join("/"), options))
+}
+
+/// Notes on `info` how it reached the engine and which directory under the
+/// folder at `root` it was in then, for Event Source conditions and the
+/// `{event_source}` and `{original_subdir}` tokens.
+pub(crate) fn picked_up(info: &mut FileInfo, source: EventSource, root: &std::path::Path) {
+    info.event_source = source;
+    info.original_subdir = info
+        .path
+        .parent()
+        .and_then(|dir| dir.strip_prefix(root).ok())
+        .map(|dir| {
+            dir.components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default();
+}
+
+/// Rejects scopes that would reach outside the folder or don't parse.
+pub(crate) fn validate_path_scope(scope: &str) -> Result<(), String> {
+    let trimmed = scope.trim();
+    if trimmed.starts_with(['/', '\\'])
+        || std::path::Path::new(trimmed).is_absolute()
+        || trimmed.as_bytes().get(1) == Some(&b':')
+    {
+        return Err(format!("path scope \"{scope}\" must be relative to the folder"));
+    }
+    let scope = normalize_scope(trimmed);
+    if scope.split('/').any(|part| part == "..") {
+        return Err(format!("path scope \"{scope}\" can't leave the folder"));
+    }
+    if is_scope_glob(&scope) {
+        glob::Pattern::new(&scope)
+            .map_err(|err| format!("invalid path scope \"{scope}\": {err}"))?;
+    }
+    Ok(())
+}
+
+fn normalize_scope(scope: &str) -> String {
+    let scope = scope.trim().replace('\\', "/");
+    scope
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_scope_glob(scope: &str) -> bool {
+    scope.contains(['*', '?', '['])
+}
+
 pub struct RuleEngine {
     event_rx: crossbeam_channel::Receiver<FileEvent>,
+    /// Files that changed while the app was closed, queued by startup.
+    backlog_rx: crossbeam_channel::Receiver<FileEvent>,
     db: Database,
     executor: ActionExecutor,
     _settings: std::sync::Arc<std::sync::Mutex<crate::models::Settings>>,
     ocr: std::sync::Arc<std::sync::Mutex<crate::core::ocr::OcrManager>>,
     last_seen: std::sync::Mutex<LruCache<std::path::PathBuf, std::time::Instant>>,
     paused: std::sync::Arc<std::sync::atomic::AtomicBool>,
-    duplicate_detector: DuplicateDetector,
+    duplicate_detector: std::sync::Arc<DuplicateDetector>,
     status: std::sync::Arc<std::sync::Mutex<EngineStatus>>,
+    retry_tx: Option<crossbeam_channel::Sender<FileEvent>>,
+    lock_retries: std::sync::Mutex<HashMap<std::path::PathBuf, u32>>,
+    hydrations: std::sync::Mutex<HashMap<std::path::PathBuf, std::time::Instant>>,
+    /// Path and rule pairs with a minimum-age retry already scheduled.
+    min_age_waits: std::sync::Mutex<std::collections::HashSet<(std::path::PathBuf, String)>>,
+    error_notifier: Option<std::sync::Arc<ErrorNotifier>>,
+    rule_timings: Option<std::sync::Arc<RuleTimings>>,
+    event_journal: Option<std::sync::Arc<EventJournal>>,
+    folder_lanes: Option<std::sync::Arc<FolderLanes>>,
+    arrival_groups: std::sync::Arc<ArrivalGroups>,
+    /// For folders that respect git.
+    git_guard: GitGuard,
+    /// Commits each event's records, alone or in batches.
+    event_writer: std::sync::Arc<EventWriter>,
 }
 
 impl RuleEngine {
@@ -56,26 +246,129 @@ impl RuleEngine {
         ocr: std::sync::Arc<std::sync::Mutex<crate::core::ocr::OcrManager>>,
         paused: std::sync::Arc<std::sync::atomic::AtomicBool>,
         status: std::sync::Arc<std::sync::Mutex<EngineStatus>>,
+    ) -> Self {
+        let executor = ActionExecutor::new(app_handle, settings.clone(), ocr.clone());
+        Self::with_executor(event_rx, db, executor, settings, ocr, paused, status)
+    }
+
+    /// An engine that runs without the app or a watcher, on events handed to
+    /// `process_all`. Nothing is retried later: files that are locked or
+    /// too young for a rule are left as they are. Used by sandbox runs.
+    pub fn detached(
+        db: Database,
+        settings: std::sync::Arc<std::sync::Mutex<crate::models::Settings>>,
+        ocr: std::sync::Arc<std::sync::Mutex<crate::core::ocr::OcrManager>>,
+    ) -> Self {
+        let executor = ActionExecutor::detached(settings.clone(), ocr.clone());
+        Self::with_executor(
+            crossbeam_channel::never(),
+            db,
+            executor,
+            settings,
+            ocr,
+            std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            std::sync::Arc::new(std::sync::Mutex::new(EngineStatus::default())),
+        )
+    }
+
+    fn with_executor(
+        event_rx: crossbeam_channel::Receiver<FileEvent>,
+        db: Database,
+        executor: ActionExecutor,
+        settings: std::sync::Arc<std::sync::Mutex<crate::models::Settings>>,
+        ocr: std::sync::Arc<std::sync::Mutex<crate::core::ocr::OcrManager>>,
+        paused: std::sync::Arc<std::sync::atomic::AtomicBool>,
+        status: std::sync::Arc<std::sync::Mutex<EngineStatus>>,
     ) -> Self {
         Self {
             event_rx,
+            backlog_rx: crossbeam_channel::never(),
             db: db.clone(),
-            executor: ActionExecutor::new(app_handle, settings.clone(), ocr.clone()),
+            executor,
             _settings: settings,
             ocr,
             last_seen: std::sync::Mutex::new(LruCache::new(
                 NonZeroUsize::new(DEBOUNCE_CACHE_CAPACITY).unwrap(),
             )),
             paused,
-            duplicate_detector: DuplicateDetector::new(db.clone()),
+            duplicate_detector: std::sync::Arc::new(DuplicateDetector::new(db.clone())),
             status,
+            retry_tx: None,
+            lock_retries: std::sync::Mutex::new(HashMap::new()),
+            hydrations: std::sync::Mutex::new(HashMap::new()),
+            min_age_waits: std::sync::Mutex::new(std::collections::HashSet::new()),
+            error_notifier: None,
+            rule_timings: None,
+            event_journal: None,
+            folder_lanes: None,
+            arrival_groups: std::sync::Arc::new(ArrivalGroups::default()),
+            git_guard: GitGuard::default(),
+            event_writer: std::sync::Arc::new(EventWriter::new(db)),
         }
     }
 
+    /// Lets the engine requeue events for files that were locked by another
+    /// process or are still being downloaded by a sync client.
+    pub fn with_retry_sender(mut self, tx: crossbeam_channel::Sender<FileEvent>) -> Self {
+        self.retry_tx = Some(tx);
+        self
+    }
+
+    /// Lets the engine work through the startup backlog between live events.
+    pub fn with_backlog(mut self, rx: crossbeam_channel::Receiver<FileEvent>) -> Self {
+        self.backlog_rx = rx;
+        self
+    }
+
+    /// Lets the engine raise notifications for failed actions.
+    pub fn with_error_notifier(mut self, notifier: std::sync::Arc<ErrorNotifier>) -> Self {
+        self.error_notifier = Some(notifier);
+        self
+    }
+
+    /// Lets the engine time each rule it runs.
+    pub fn with_rule_timings(mut self, timings: std::sync::Arc<RuleTimings>) -> Self {
+        self.rule_timings = Some(timings);
+        self
+    }
+
+    /// Lets the engine journal the events it receives.
+    pub fn with_event_journal(mut self, journal: std::sync::Arc<EventJournal>) -> Self {
+        self.event_journal = Some(journal);
+        self
+    }
+
+    /// Shares the copies Is Duplicate conditions remember with scans and the
+    /// approval and conflict runners.
+    pub fn with_duplicate_detector(mut self, detector: std::sync::Arc<DuplicateDetector>) -> Self {
+        self.duplicate_detector = detector;
+        self
+    }
+
+    /// Lets strict sequential folders take turns with their scans.
+    pub fn with_folder_lanes(mut self, lanes: std::sync::Arc<FolderLanes>) -> Self {
+        self.folder_lanes = Some(lanes);
+        self
+    }
+
     pub fn start(self) {
+        self.start_batch_flusher();
         thread::spawn(move || {
-            for event in self.event_rx.iter() {
-                if let Err(err) = self.process_event(&event) {
+            let mut events = EventMux::new(self.event_rx.clone(), self.backlog_rx.clone());
+            while let Some(event) = events.next_event() {
+                // Only files arriving now are grouped; the startup backlog
+                // didn't arrive together.
+                let live = events.last_was_live();
+                // Waits out a "Run now" or scheduled scan of a strict folder
+                // that's mid-file.
+                let _turn = self
+                    .folder_lanes
+                    .as_ref()
+                    .and_then(|lanes| lanes.enqueue(&event.folder_id))
+                    .map(Ticket::wait);
+                let journal = self.event_journal.as_deref();
+                let processed = journaled(journal, &event, |event| self.process_event(event, live));
+                if let Err(err) = processed {
                     self.record_error(err.to_string());
                     eprintln!("Rule engine error: {err}");
                 }
@@ -83,112 +376,438 @@ impl RuleEngine {
         });
     }
 
-    fn process_event(&self, event: &FileEvent) -> Result<()> {
+    /// Commits batched records once they've waited long enough, including
+    /// while no events arrive to do it.
+    fn start_batch_flusher(&self) {
+        let writer = self.event_writer.clone();
+        let settings = self._settings.clone();
+        thread::spawn(move || loop {
+            let batch_ms = settings.lock().map(|s| s.db_batch_commit_ms).unwrap_or(0);
+            let tick = if batch_ms == 0 { 250 } else { batch_ms.clamp(10, 250) };
+            thread::sleep(std::time::Duration::from_millis(tick));
+            if let Err(err) = writer.flush_if_due(batch_ms, std::time::Instant::now()) {
+                eprintln!("Failed to commit batched records: {err}");
+            }
+        });
+    }
+
+    /// Processes `events` one after another on the calling thread, as the
+    /// backlog is, and returns once the last has settled. Errors are
+    /// returned with the path of their event instead of stopping the rest.
+    pub fn process_all(
+        &self,
+        events: impl IntoIterator<Item = FileEvent>,
+    ) -> Vec<(std::path::PathBuf, String)> {
+        let mut errors = Vec::new();
+        let mut last = None;
+        for event in events {
+            if let Err(err) = self.process_event(&event, false) {
+                errors.push((event.path.clone(), err.to_string()));
+            }
+            last = Some(event.path);
+        }
+        // Batched records count as part of the last event.
+        if let (Err(err), Some(path)) = (self.event_writer.flush(std::time::Instant::now()), last)
+        {
+            errors.push((path, err.to_string()));
+        }
+        errors
+    }
+
+    fn process_event(&self, event: &FileEvent, live: bool) -> Result<Settled> {
         self.record_event(event);
+        // Rules look up what earlier events did with this file.
+        self.event_writer.flush_for(&event.path, std::time::Instant::now())?;
+        // This event ends the wait either way; a file that's still too young
+        // schedules a new one.
+        if let Some(rule_id) = &event.rule_id {
+            if let Ok(mut waits) = self.min_age_waits.lock() {
+                waits.remove(&(event.path.clone(), rule_id.clone()));
+            }
+        }
         if self.paused.load(std::sync::atomic::Ordering::SeqCst) {
-            return Ok(());
+            return Ok(EventDisposition::Paused.into());
         }
+        if let Some(rule_id) = &event.rule_id {
+            self.process_deferred(event, rule_id)?;
+            return Ok(EventDisposition::Processed.into());
+        }
+        // A group's members come back after its window, possibly within the
+        // debounce window of their first event.
         let debounce_ms = self._settings.lock().map(|s| s.debounce_ms).unwrap_or(500);
-        let now = std::time::Instant::now();
-        if let Ok(mut last_seen) = self.last_seen.lock() {
-            if let Some(prev) = last_seen.peek(&event.path) {
-                if now.duration_since(*prev).as_millis() < debounce_ms as u128 {
-                    return Ok(());
-                }
-            }
-            last_seen.put(event.path.clone(), now);
+        if event.arrival.is_none()
+            && debounced(&self.last_seen, &event.path, std::time::Instant::now(), debounce_ms)
+        {
+            return Ok(EventDisposition::Debounced.into());
         }
         let mut info = match FileInfo::from_path(&event.path) {
             Ok(info) => info,
-            Err(_) => return Ok(()),
+            Err(err) => {
+                log_unreadable(&LogRepository::new(self.db.clone()), &event.path, &err)?;
+                return Ok(Settled {
+                    disposition: EventDisposition::Ignored,
+                    detail: Some(err.to_string()),
+                });
+            }
         };
 
         let folder_repo = FolderRepository::new(self.db.clone());
         let folder = match folder_repo.get(&event.folder_id)? {
             Some(folder) => folder,
-            None => return Ok(()),
+            None => return Ok(EventDisposition::Ignored.into()),
         };
 
+        // Clone settings once per event, not per rule
+        let settings = self._settings.lock().map(|s| s.clone()).unwrap_or_default();
+        if let Some(settled) = self.git_gate(event, &folder, &settings) {
+            return Ok(settled);
+        }
+
+        if live && event.arrival.is_none() && self.hold_for_group(event, &folder) {
+            return Ok(EventDisposition::Grouped.into());
+        }
+        info.arrival = event.arrival.clone();
+        info.active_app = event.active_app.clone();
+        picked_up(&mut info, event.source, &normalize_user_path(&folder.path));
+
+        let log_repo = LogRepository::new(self.db.clone());
+
+        match placeholder_gate(folder.placeholder_mode, info.is_placeholder) {
+            PlaceholderGate::Process => {
+                if let Ok(mut hydrations) = self.hydrations.lock() {
+                    hydrations.remove(&event.path);
+                }
+            }
+            PlaceholderGate::MetadataOnly => {}
+            PlaceholderGate::Skip => {
+                log_placeholder_skip(&log_repo, &info, "Skipped online-only placeholder")?;
+                return Ok(EventDisposition::Ignored.into());
+            }
+            PlaceholderGate::Hydrate => {
+                if !self.schedule_hydration(event, &settings) {
+                    log_placeholder_skip(
+                        &log_repo,
+                        &info,
+                        "Placeholder was not downloaded within the maximum wait",
+                    )?;
+                }
+                return Ok(EventDisposition::Ignored.into());
+            }
+        }
+
+        // Hashing a placeholder for duplicate detection would download it.
         if folder.remove_duplicates
+            && !info.is_placeholder
             && matches!(event.kind, FileEventKind::Created | FileEventKind::Renamed)
         {
             if self
                 .duplicate_detector
-                .check_and_remove(&folder, &event.path)?
+                .check_and_remove(&folder, &event.path, &settings.sampled_hashing)?
             {
-                return Ok(());
+                return Ok(EventDisposition::Ignored.into());
             }
         }
 
-        let rule_repo = RuleRepository::new(self.db.clone());
-        let match_repo = MatchRepository::new(self.db.clone());
+        let mut chain = DispatchChain::new(&folder.id);
+        let retry_scheduled =
+            self.with_runner(event, &settings, |runner| runner.run(&folder.id, info, &mut chain))?;
 
-        // Populate last_matched from database
-        if let Ok(last_matched) =
-            match_repo.get_last_match_time(info.path.to_string_lossy().as_ref())
-        {
-            info.last_matched = last_matched;
+        if !retry_scheduled {
+            if let Ok(mut retries) = self.lock_retries.lock() {
+                retries.remove(&event.path);
+            }
         }
-        let log_repo = LogRepository::new(self.db.clone());
-        let undo_repo = UndoRepository::new(self.db.clone());
-
-        let rules = rule_repo.list_by_folder(&event.folder_id)?;
 
-        // Pre-fetch all rule IDs that have already matched this file's hash
-        // This avoids N+1 queries in the rule loop
-        let rule_ids: Vec<&str> = rules.iter().map(|r| r.id.as_str()).collect();
-        let matched_rule_ids = match_repo.get_hash_matched_rules(&rule_ids, &info.hash)?;
+        self.record_processed();
+        Ok(EventDisposition::Processed.into())
+    }
 
-        // Clone settings once per event, not per rule
+    /// Runs the one rule an event was requeued for once the file should have
+    /// reached the rule's minimum age. Debouncing, placeholders and duplicates
+    /// were dealt with when the event first arrived.
+    fn process_deferred(&self, event: &FileEvent, rule_id: &str) -> Result<()> {
+        let Some(folder) = FolderRepository::new(self.db.clone()).get(&event.folder_id)? else {
+            return Ok(());
+        };
         let settings = self._settings.lock().map(|s| s.clone()).unwrap_or_default();
+        if self.git_gate(event, &folder, &settings).is_some() {
+            return Ok(());
+        }
+        let mut chain = DispatchChain::new(&folder.id);
+        let retry_scheduled = self.with_runner(event, &settings, |runner| {
+            runner.run_deferred(&folder.id, rule_id, &event.path, event.source, &mut chain)
+        })?;
+
+        if !retry_scheduled {
+            if let Ok(mut retries) = self.lock_retries.lock() {
+                retries.remove(&event.path);
+            }
+        }
+
+        self.record_processed();
+        Ok(())
+    }
 
-        for rule in rules {
-            if !rule.enabled {
-                continue;
+    /// Builds the rule runner for `event`, wired to this engine's executor,
+    /// notifications and retries, and hands it to `run`.
+    fn with_runner<T>(
+        &self,
+        event: &FileEvent,
+        settings: &crate::models::Settings,
+        run: impl FnOnce(&RuleRunner) -> T,
+    ) -> T {
+        let execute = |actions: &[crate::models::Action],
+                       info: &FileInfo,
+                       captures: &HashMap<String, String>,
+                       scope: &ActionScope| {
+            self.executor.execute_actions(actions, info, captures, scope)
+        };
+        let plan = |actions: &[crate::models::Action],
+                    info: &FileInfo,
+                    captures: &HashMap<String, String>,
+                    _: &ActionScope| {
+            self.executor.simulate_actions(actions, info, captures)
+        };
+        let on_pending = |approval: &PendingApproval| {
+            self.executor.notify_pending_approval(approval);
+        };
+        let on_conflict = |conflict: &PendingConflict| {
+            self.executor.notify_pending_conflict(conflict);
+        };
+        let on_failure = |rule: &Rule,
+                          info: &FileInfo,
+                          captures: &HashMap<String, String>,
+                          outcomes: &[ActionOutcome]| {
+            let Some(notifier) = &self.error_notifier else {
+                return;
+            };
+            let mode = if settings.show_notifications {
+                settings.notify_on_error
+            } else {
+                ErrorNotifyMode::Off
+            };
+            notifier.report(mode, rule, info, captures, outcomes, Utc::now());
+        };
+        let retry_locked = || self.schedule_lock_retry(event, settings);
+        let defer = |rule: &Rule, eligible_at: chrono::DateTime<Utc>| {
+            self.schedule_min_age_retry(event, &rule.id, eligible_at);
+        };
+        let on_retry_exhausted = |exhausted: &RetryExhausted| {
+            self.executor.notify_retry_exhausted(exhausted);
+        };
+        let on_budget_exhausted = |exhausted: &BudgetExhausted| {
+            self.executor.notify_budget_exhausted(exhausted);
+        };
+        let runner = RuleRunner {
+            db: &self.db,
+            settings,
+            ocr: &self.ocr,
+            execute: &execute,
+            plan: &plan,
+            on_pending: &on_pending,
+            on_conflict: &on_conflict,
+            on_failure: &on_failure,
+            retry_locked: &retry_locked,
+            defer: &defer,
+            on_retry_exhausted: &on_retry_exhausted,
+            on_budget_exhausted: &on_budget_exhausted,
+            timings: self.rule_timings.as_deref(),
+            writer: Some(&self.event_writer),
+            now: Utc::now(),
+            duplicates: Some(&self.duplicate_detector),
+        };
+        run(&runner)
+    }
+
+    /// Holds a file that just arrived in a folder with a group window until
+    /// no other file has arrived there for the length of the window. Every
+    /// member of the group is then sent back tagged with it and processed on
+    /// its own, so one failing doesn't stop the rest.
+    fn hold_for_group(&self, event: &FileEvent, folder: &crate::models::Folder) -> bool {
+        if folder.group_window_ms == 0 {
+            return false;
+        }
+        let Some(tx) = self.retry_tx.clone() else {
+            return false;
+        };
+        if !matches!(event.kind, FileEventKind::Created | FileEventKind::Renamed) {
+            // A change to a file that's waiting with its group is picked up
+            // when the group is processed.
+            return self.arrival_groups.is_held(&folder.id, &event.path);
+        }
+        let window =
+            std::time::Duration::from_millis(folder.group_window_ms.min(MAX_GROUP_WINDOW_MS));
+        self.arrival_groups.add(event.clone(), std::time::Instant::now(), window);
+        let groups = self.arrival_groups.clone();
+        thread::spawn(move || {
+            thread::sleep(window);
+            for member in groups.take_closed(std::time::Instant::now()) {
+                let _ = tx.send(member);
             }
+        });
+        true
+    }
 
-            // Skip if this file (by hash) was already processed by this rule
-            // This prevents re-processing after renames or moves
-            // Now uses pre-fetched set instead of per-rule query
-            if matched_rule_ids.contains(&rule.id) {
-                continue;
+    fn schedule_lock_retry(&self, event: &FileEvent, settings: &crate::models::Settings) -> bool {
+        if !settings.retry_locked_files {
+            return false;
+        }
+        let Some(tx) = self.retry_tx.clone() else {
+            return false;
+        };
+        let attempts = match self.lock_retries.lock() {
+            Ok(mut retries) => {
+                let attempts = retries.entry(event.path.clone()).or_insert(0);
+                *attempts += 1;
+                if *attempts > MAX_LOCK_RETRIES {
+                    retries.remove(&event.path);
+                    return false;
+                }
+                *attempts
             }
+            Err(_) => return false,
+        };
 
-            // Acquire OCR lock only when evaluating conditions, release after
-            let evaluation = {
-                let mut ocr = self.ocr.lock().unwrap();
-                evaluate_conditions(&rule, &info, &settings, &mut ocr, &EvaluationOptions::default())?
-            };
-            if !evaluation.matched {
-                continue;
+        // Wait out the debounce window so the retried event isn't dropped.
+        let delay_ms = (settings.locked_retry_delay_seconds * 1000).max(settings.debounce_ms + 100);
+        let event = FileEvent {
+            source: EventSource::Retry,
+            ..event.clone()
+        };
+        eprintln!(
+            "File is locked, retrying in {}ms (attempt {}/{}): {}",
+            delay_ms,
+            attempts,
+            MAX_LOCK_RETRIES,
+            event.path.display()
+        );
+        thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(delay_ms));
+            let _ = tx.send(event);
+        });
+        true
+    }
+
+    /// Requeues `event` for one rule at the time the file reaches the rule's
+    /// minimum age. A path and rule with a retry already pending are left to
+    /// it; that retry re-checks the age.
+    fn schedule_min_age_retry(
+        &self,
+        event: &FileEvent,
+        rule_id: &str,
+        eligible_at: chrono::DateTime<Utc>,
+    ) {
+        let Some(tx) = self.retry_tx.clone() else {
+            return;
+        };
+        match self.min_age_waits.lock() {
+            Ok(mut waits) => {
+                if !waits.insert((event.path.clone(), rule_id.to_string())) {
+                    return;
+                }
             }
+            Err(_) => return,
+        }
 
-            let outcomes =
-                self.executor
-                    .execute_actions(&rule.actions, &info, &evaluation.captures);
+        let delay_ms = (eligible_at - Utc::now()).num_milliseconds().max(0) as u64 + 1;
+        let event = FileEvent {
+            rule_id: Some(rule_id.to_string()),
+            ..event.clone()
+        };
+        thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(delay_ms));
+            let _ = tx.send(event);
+        });
+    }
 
-            log_outcomes(&log_repo, &undo_repo, &rule, &info, &outcomes)?;
-            match_repo.record_match(
-                &rule.id,
-                info.path.to_string_lossy().as_ref(),
-                Some(&info.hash),
-            )?;
-            record_make_pdf_searchable_output_match(&match_repo, &rule.id, &outcomes);
+    /// Keeps events in a folder that respects git out of git's way. While git
+    /// is partway through a checkout, merge or rebase the event is requeued
+    /// until it's done; with `skip_git_tracked`, files git tracks are left
+    /// alone. Returns how the event settled when it goes no further.
+    fn git_gate(
+        &self,
+        event: &FileEvent,
+        folder: &crate::models::Folder,
+        settings: &crate::models::Settings,
+    ) -> Option<Settled> {
+        if !folder.respect_git {
+            return None;
+        }
+        let root = normalize_user_path(&folder.path);
+        match self.git_guard.hold(&event.path, &root) {
+            GitHold::Proceed => {}
+            GitHold::GaveUp => eprintln!(
+                "Git has been busy in {} for too long, processing anyway: {}",
+                root.display(),
+                event.path.display()
+            ),
+            GitHold::Wait => {
+                if let Some(tx) = self.retry_tx.clone() {
+                    // Wait out the debounce window so the requeued event isn't dropped.
+                    let delay_ms = GIT_RECHECK_MS.max(settings.debounce_ms + 100);
+                    let event = event.clone();
+                    thread::spawn(move || {
+                        thread::sleep(std::time::Duration::from_millis(delay_ms));
+                        let _ = tx.send(event);
+                    });
+                    return Some(Settled {
+                        disposition: EventDisposition::Ignored,
+                        detail: Some("Waiting for git to finish".to_string()),
+                    });
+                }
+            }
+        }
+        if folder.skip_git_tracked && self.git_guard.is_tracked(&root, &event.path) == Some(true) {
+            return Some(Settled {
+                disposition: EventDisposition::Ignored,
+                detail: Some("Tracked by git".to_string()),
+            });
+        }
+        None
+    }
 
-            if should_stop_processing(&rule, &outcomes) {
-                break;
+    /// Reads the placeholder so the sync client downloads it, then requeues the
+    /// event. Returns false once the file has been waiting longer than allowed.
+    fn schedule_hydration(&self, event: &FileEvent, settings: &crate::models::Settings) -> bool {
+        let Some(tx) = self.retry_tx.clone() else {
+            return false;
+        };
+        let now = std::time::Instant::now();
+        let max_wait =
+            std::time::Duration::from_secs(settings.placeholder_hydrate_max_wait_seconds);
+        match self.hydrations.lock() {
+            Ok(mut hydrations) => {
+                let started = *hydrations.entry(event.path.clone()).or_insert(now);
+                if now.duration_since(started) > max_wait {
+                    hydrations.remove(&event.path);
+                    return false;
+                }
             }
+            Err(_) => return false,
         }
 
-        self.record_processed();
-        Ok(())
+        let delay_ms = HYDRATE_POLL_INTERVAL_MS.max(settings.debounce_ms + 100);
+        let event = event.clone();
+        eprintln!(
+            "Waiting for placeholder to download, rechecking in {}ms: {}",
+            delay_ms,
+            event.path.display()
+        );
+        thread::spawn(move || {
+            if let Err(err) = request_hydration(&event.path) {
+                eprintln!("Failed to request download of {}: {}", event.path.display(), err);
+            }
+            thread::sleep(std::time::Duration::from_millis(delay_ms));
+            let _ = tx.send(event);
+        });
+        true
     }
 
     fn record_event(&self, event: &FileEvent) {
         let now = Utc::now();
         if let Ok(mut status) = self.status.lock() {
             status.paused = self.paused.load(std::sync::atomic::Ordering::SeqCst);
-            status.queue_depth = self.event_rx.len();
+            status.queue_depth = self.event_rx.len() + self.backlog_rx.len();
             status.last_event = Some(EngineEvent {
                 path: event.path.to_string_lossy().to_string(),
                 folder_id: event.folder_id.clone(),
@@ -201,12 +820,30 @@ impl RuleEngine {
 
     fn record_processed(&self) {
         let now = Utc::now();
+        let db_writes = self.event_writer.stats(std::time::Instant::now());
         if let Ok(mut status) = self.status.lock() {
             status.processed_count = status.processed_count.saturating_add(1);
-            status.queue_depth = self.event_rx.len();
+            status.db_writes = db_writes;
+            status.queue_depth = self.event_rx.len() + self.backlog_rx.len();
             status.paused = self.paused.load(std::sync::atomic::Ordering::SeqCst);
             status.updated_at = now;
         }
+        self.record_timings();
+    }
+
+    /// Shows the slowest rule of the last hour and saves the statistics
+    /// when it's time to.
+    fn record_timings(&self) {
+        let Some(timings) = &self.rule_timings else {
+            return;
+        };
+        let slowest = timings.slowest_since(Utc::now() - Duration::hours(1));
+        if let Ok(mut status) = self.status.lock() {
+            status.slowest_rule = slowest;
+        }
+        if let Err(err) = timings.flush_if_due(&self.db) {
+            eprintln!("Failed to save rule timings: {err}");
+        }
     }
 
     fn record_error(&self, message: String) {
@@ -216,15 +853,15 @@ impl RuleEngine {
                 message,
                 occurred_at: now,
             });
-            status.queue_depth = self.event_rx.len();
+            status.queue_depth = self.event_rx.len() + self.backlog_rx.len();
             status.paused = self.paused.load(std::sync::atomic::Ordering::SeqCst);
             status.updated_at = now;
         }
     }
 }
 
-fn record_make_pdf_searchable_output_match(
-    match_repo: &MatchRepository,
+pub(crate) fn record_make_pdf_searchable_output_match(
+    writes: &mut WriteSet,
     rule_id: &str,
     outcomes: &[ActionOutcome],
 ) {
@@ -245,14 +882,110 @@ fn record_make_pdf_searchable_output_match(
             .unwrap_or(details.source_path.as_str());
         let path = std::path::Path::new(path_str);
         if let Ok(info) = FileInfo::from_path(path) {
-            let _ = match_repo.record_match(rule_id, path_str, Some(&info.hash));
+            writes.record_match(rule_id, path_str, Some(&info.hash), &BTreeMap::new(), &[]);
+        }
+    }
+}
+
+/// Records the companions a run brought along as already handled by every
+/// enabled rule, so they don't set off rules of their own where they landed,
+/// or where a copy left them. Best-effort, like the other match records.
+pub(crate) fn record_companion_matches(
+    rule_repo: &RuleRepository,
+    writes: &mut WriteSet,
+    outcomes: &[ActionOutcome],
+) {
+    let paths: Vec<&str> = outcomes
+        .iter()
+        .filter(|outcome| outcome.status == ActionResultStatus::Success)
+        .filter_map(|outcome| outcome.details.as_ref())
+        .filter(|details| details.metadata.contains_key(COMPANION_RECORD_KEY))
+        .flat_map(|details| {
+            details
+                .destination_path
+                .as_deref()
+                .into_iter()
+                .chain(std::iter::once(details.source_path.as_str()))
+        })
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+    let Ok(rules) = rule_repo.list_enabled() else {
+        return;
+    };
+    for path in paths {
+        // A moved companion's source is gone and needs no record.
+        let Ok(info) = FileInfo::from_path(std::path::Path::new(path)) else {
+            continue;
+        };
+        for rule in &rules {
+            writes.record_match(&rule.id, path, Some(&info.hash), &BTreeMap::new(), &[]);
         }
     }
 }
 
-pub(crate) struct EvaluationResult {
-    pub matched: bool,
-    pub captures: HashMap<String, String>,
+/// The files a run brought along as companions that aren't to be processed
+/// on their own: the ones recorded as handled, and any that moved away.
+pub(crate) fn brought_along(outcomes: &[ActionOutcome]) -> Vec<std::path::PathBuf> {
+    outcomes
+        .iter()
+        .filter(|outcome| outcome.status == ActionResultStatus::Success)
+        .filter_map(|outcome| outcome.details.as_ref())
+        .filter(|details| details.metadata.contains_key(COMPANION_OF_KEY))
+        .filter(|details| {
+            details.metadata.contains_key(COMPANION_RECORD_KEY)
+                || !std::path::Path::new(&details.source_path).exists()
+        })
+        .map(|details| std::path::PathBuf::from(&details.source_path))
+        .collect()
+}
+
+/// Leaves a processed marker for `rule_id` where a successful run left the
+/// file, and drops any index entry at the old location. Best-effort: a
+/// marker that can't be written just means no marker.
+pub(crate) fn write_processed_markers(
+    settings: &crate::models::Settings,
+    rule_id: &str,
+    source: &std::path::Path,
+    outcomes: &[ActionOutcome],
+) {
+    if !settings.write_processed_markers
+        || outcomes
+            .iter()
+            .any(|outcome| outcome.status == ActionResultStatus::Error)
+    {
+        return;
+    }
+    let mut current = source.to_path_buf();
+    for outcome in outcomes {
+        if outcome.status != ActionResultStatus::Success {
+            continue;
+        }
+        match outcome.action_type {
+            ActionType::Delete | ActionType::DeletePermanently => {
+                let _ = markers::remove_marks(source);
+                return;
+            }
+            ActionType::Move | ActionType::Rename | ActionType::SortIntoSubfolder => {
+                if let Some(dest) = outcome
+                    .details
+                    .as_ref()
+                    .and_then(|details| details.destination_path.as_ref())
+                {
+                    current = std::path::PathBuf::from(dest);
+                }
+            }
+            _ => {}
+        }
+    }
+    if current != source {
+        let _ = markers::remove_marks(source);
+    }
+    if let Ok(info) = FileInfo::from_path(&current) {
+        let mark = ProcessedMark::new(rule_id, Utc::now().timestamp(), &info.hash);
+        let _ = markers::write_mark(&current, mark);
+    }
 }
 
 #[derive(Clone, Default)]
@@ -260,6 +993,44 @@ pub(crate) struct EvaluationOptions {
     pub skip_content: bool,
     pub surface_errors: bool,
     pub ocr_request_id: Option<String>,
+    /// Set by previews only; see `evaluate::EvaluationOptions::now`.
+    pub now: Option<chrono::DateTime<Utc>>,
+    /// Where Is Duplicate conditions look for copies. Without it they can't
+    /// be evaluated, so they don't match, negated or not.
+    pub duplicates: Option<DuplicateLookup>,
+}
+
+/// The detector Is Duplicate conditions search with, and the folder whose
+/// rules are being evaluated.
+#[derive(Clone)]
+pub(crate) struct DuplicateLookup {
+    pub detector: std::sync::Arc<DuplicateDetector>,
+    pub folder_id: String,
+}
+
+impl EvaluationOptions {
+    /// The default options, with Is Duplicate conditions looking for copies
+    /// of files in `folder_id` with `detector`.
+    pub fn finding_duplicates(
+        detector: &std::sync::Arc<DuplicateDetector>,
+        folder_id: &str,
+    ) -> Self {
+        Self {
+            duplicates: Some(DuplicateLookup {
+                detector: std::sync::Arc::clone(detector),
+                folder_id: folder_id.to_string(),
+            }),
+            ..Self::default()
+        }
+    }
+
+    fn core(&self) -> evaluate::EvaluationOptions {
+        evaluate::EvaluationOptions {
+            skip_content: self.skip_content,
+            surface_errors: self.surface_errors,
+            now: self.now,
+        }
+    }
 }
 
 pub(crate) fn evaluate_conditions(
@@ -270,7 +1041,9 @@ pub(crate) fn evaluate_conditions(
     options: &EvaluationOptions,
 ) -> Result<EvaluationResult> {
     let mut cache = ContentCache::default();
-    evaluate_group(&rule.conditions, info, settings, ocr, &mut cache, options)
+    let mut result = evaluate_group(&rule.conditions, info, settings, ocr, &mut cache, options)?;
+    evaluate::add_rule_captures(rule, &mut result);
+    Ok(result)
 }
 
 pub(crate) fn evaluate_group(
@@ -281,62 +1054,8 @@ pub(crate) fn evaluate_group(
     cache: &mut ContentCache,
     options: &EvaluationOptions,
 ) -> Result<EvaluationResult> {
-    if options.skip_content
-        && matches!(group.match_type, MatchType::None)
-        && group_has_content_condition(group)
-    {
-        return Ok(EvaluationResult {
-            matched: false,
-            captures: HashMap::new(),
-        });
-    }
-
-    match group.match_type {
-        MatchType::All => {
-            let mut captures = HashMap::new();
-            for condition in &group.conditions {
-                let result = evaluate_condition(condition, info, settings, ocr, cache, options)?;
-                if !result.matched {
-                    return Ok(EvaluationResult {
-                        matched: false,
-                        captures: HashMap::new(),
-                    });
-                }
-                captures.extend(result.captures);
-            }
-            Ok(EvaluationResult {
-                matched: true,
-                captures,
-            })
-        }
-        MatchType::Any => {
-            for condition in &group.conditions {
-                let result = evaluate_condition(condition, info, settings, ocr, cache, options)?;
-                if result.matched {
-                    return Ok(result);
-                }
-            }
-            Ok(EvaluationResult {
-                matched: false,
-                captures: HashMap::new(),
-            })
-        }
-        MatchType::None => {
-            for condition in &group.conditions {
-                let result = evaluate_condition(condition, info, settings, ocr, cache, options)?;
-                if result.matched {
-                    return Ok(EvaluationResult {
-                        matched: false,
-                        captures: HashMap::new(),
-                    });
-                }
-            }
-            Ok(EvaluationResult {
-                matched: true,
-                captures: HashMap::new(),
-            })
-        }
-    }
+    let mut content = AppContent::new(settings, ocr, cache, options);
+    evaluate::evaluate_group(group, info, settings, &mut content, &options.core())
 }
 
 pub(crate) fn evaluate_condition(
@@ -347,277 +1066,181 @@ pub(crate) fn evaluate_condition(
     cache: &mut ContentCache,
     options: &EvaluationOptions,
 ) -> Result<EvaluationResult> {
-    match condition {
-        Condition::Name(cond) => evaluate_string(&info.name, cond),
-        Condition::Extension(cond) => evaluate_string(&info.extension, cond),
-        Condition::FullName(cond) => evaluate_string(&info.full_name, cond),
-        Condition::Contents(cond) => {
-            if options.skip_content {
-                return Ok(EvaluationResult {
-                    matched: false,
-                    captures: HashMap::new(),
-                });
-            }
-            let resolved =
-                resolve_contents(info, settings, ocr, &cond.source, cache, options.ocr_request_id.as_deref());
-            let text = if options.surface_errors {
-                resolved?
-            } else {
-                resolved.unwrap_or(None)
-            }
-            .unwrap_or_default();
-            if text.is_empty() {
-                return Ok(EvaluationResult {
-                    matched: false,
-                    captures: HashMap::new(),
-                });
-            }
-            let string_cond = StringCondition {
-                operator: cond.operator.clone(),
-                value: cond.value.clone(),
-                case_sensitive: cond.case_sensitive,
-            };
-            evaluate_string(&text, &string_cond)
-        }
-        Condition::Size(cond) => Ok(EvaluationResult {
-            matched: evaluate_size(info.size, cond),
-            captures: HashMap::new(),
-        }),
-        Condition::DateCreated(cond) => Ok(EvaluationResult {
-            matched: evaluate_date(info.created, &cond.operator),
-            captures: HashMap::new(),
-        }),
-        Condition::DateModified(cond) => Ok(EvaluationResult {
-            matched: evaluate_date(info.modified, &cond.operator),
-            captures: HashMap::new(),
-        }),
-        Condition::DateAdded(cond) => Ok(EvaluationResult {
-            matched: evaluate_date(info.added, &cond.operator),
-            captures: HashMap::new(),
-        }),
-        Condition::DateLastMatched(cond) => Ok(EvaluationResult {
-            // Use the last_matched field from FileInfo if available
-            // Files that have never been matched will return None, and we'll treat them
-            // as "matched a very long time ago" (so they match "not in the last X")
-            matched: info
-                .last_matched
-                .map(|dt| evaluate_date(dt, &cond.operator))
-                .unwrap_or_else(|| {
-                    // If never matched, only match conditions looking for old/never-matched files
-                    matches!(&cond.operator, DateOperator::NotInTheLast { .. })
-                }),
-            captures: HashMap::new(),
-        }),
-        Condition::CurrentTime(cond) => Ok(EvaluationResult {
-            matched: evaluate_time(&cond.operator),
-            captures: HashMap::new(),
-        }),
-        Condition::Kind(cond) => Ok(EvaluationResult {
-            matched: evaluate_kind(info.kind.clone(), cond.kind.clone(), cond.negate),
-            captures: HashMap::new(),
-        }),
-        Condition::ShellScript(cond) => Ok(EvaluationResult {
-            matched: evaluate_shell(&cond.command, &info.path),
-            captures: HashMap::new(),
-        }),
-        Condition::Nested(group) => evaluate_group(group, info, settings, ocr, cache, options),
-    }
+    let mut content = AppContent::new(settings, ocr, cache, options);
+    evaluate::evaluate_condition(condition, info, settings, &mut content, &options.core())
 }
 
-fn group_has_content_condition(group: &ConditionGroup) -> bool {
-    group.conditions.iter().any(|condition| match condition {
-        Condition::Contents(_) => true,
-        Condition::Nested(nested) => group_has_content_condition(nested),
-        _ => false,
-    })
+/// Answers the conditions `filedispatch_core` can't decide alone from the
+/// file on disk, with OCR, the classifier and the shared `ContentCache`.
+struct AppContent<'a> {
+    settings: &'a crate::models::Settings,
+    ocr: &'a mut crate::core::ocr::OcrManager,
+    cache: &'a mut ContentCache,
+    ocr_request_id: Option<&'a str>,
+    duplicates: Option<&'a DuplicateLookup>,
 }
 
-pub(crate) fn evaluate_string(
-    target: &str,
-    cond: &crate::models::StringCondition,
-) -> Result<EvaluationResult> {
-    let mut captures = HashMap::new();
-
-    let matched = match cond.operator {
-        // Use eq_ignore_ascii_case to avoid allocations for equality checks
-        StringOperator::Is => {
-            if cond.case_sensitive {
-                target == cond.value
-            } else {
-                target.eq_ignore_ascii_case(&cond.value)
-            }
+impl<'a> AppContent<'a> {
+    fn new(
+        settings: &'a crate::models::Settings,
+        ocr: &'a mut crate::core::ocr::OcrManager,
+        cache: &'a mut ContentCache,
+        options: &'a EvaluationOptions,
+    ) -> Self {
+        Self {
+            settings,
+            ocr,
+            cache,
+            ocr_request_id: options.ocr_request_id.as_deref(),
+            duplicates: options.duplicates.as_ref(),
         }
-        StringOperator::IsNot => {
-            if cond.case_sensitive {
-                target != cond.value
-            } else {
-                !target.eq_ignore_ascii_case(&cond.value)
-            }
-        }
-        // For contains/starts_with/ends_with, only allocate when case-insensitive
-        StringOperator::Contains => {
-            if cond.case_sensitive {
-                target.contains(&cond.value)
-            } else {
-                target.to_lowercase().contains(&cond.value.to_lowercase())
-            }
-        }
-        StringOperator::DoesNotContain => {
-            if cond.case_sensitive {
-                !target.contains(&cond.value)
-            } else {
-                !target.to_lowercase().contains(&cond.value.to_lowercase())
-            }
-        }
-        StringOperator::StartsWith => {
-            if cond.case_sensitive {
-                target.starts_with(&cond.value)
-            } else {
-                target.to_lowercase().starts_with(&cond.value.to_lowercase())
-            }
-        }
-        StringOperator::EndsWith => {
-            if cond.case_sensitive {
-                target.ends_with(&cond.value)
-            } else {
-                target.to_lowercase().ends_with(&cond.value.to_lowercase())
-            }
-        }
-        // Regex handles case-insensitivity internally, no pre-allocation needed
-        // Use cached compiled regex to avoid recompilation per file
-        StringOperator::Matches | StringOperator::DoesNotMatch => {
-            let regex = get_or_compile_regex(&cond.value, !cond.case_sensitive)?;
-            let matches = regex.captures(target);
-            if let Some(caps) = matches {
-                for (i, cap) in caps.iter().enumerate().skip(1) {
-                    if let Some(value) = cap {
-                        captures.insert(i.to_string(), value.as_str().to_string());
-                    }
-                }
-                cond.operator == StringOperator::Matches
-            } else {
-                cond.operator == StringOperator::DoesNotMatch
-            }
+    }
+}
+
+impl ContentProvider for AppContent<'_> {
+    fn contents(
+        &mut self,
+        info: &FileInfo,
+        source: &ContentSource,
+    ) -> Result<Option<ResolvedContents>> {
+        resolve_contents_detailed(
+            info,
+            self.settings,
+            self.ocr,
+            source,
+            self.cache,
+            self.ocr_request_id,
+        )
+    }
+
+    fn search(
+        &mut self,
+        info: &FileInfo,
+        source: &ContentSource,
+        cond: &StringCondition,
+    ) -> Option<Result<EvaluationResult>> {
+        if !should_stream_contents(info, self.settings, source) {
+            return None;
         }
-    };
+        self.cache.mark_streamed();
+        Some(search_file(&info.path, cond).map(|found| EvaluationResult {
+            matched: found.matched,
+            captures: found.captures,
+        }))
+    }
 
-    Ok(EvaluationResult { matched, captures })
-}
+    fn classify(&mut self, info: &FileInfo) -> Result<Option<classify::Prediction>> {
+        let model = if self.settings.classifier_enabled {
+            classify::active_model()
+        } else {
+            None
+        };
+        let Some(model) = model else {
+            return Ok(None);
+        };
+        let text = resolve_contents(
+            info,
+            self.settings,
+            self.ocr,
+            &ContentSource::Auto,
+            self.cache,
+            self.ocr_request_id,
+        )?
+        .unwrap_or_default();
+        Ok(model.predict(&text))
+    }
 
-/// Get a compiled regex from cache or compile and cache it
-fn get_or_compile_regex(pattern: &str, case_insensitive: bool) -> Result<Regex> {
-    let key = (pattern.to_string(), case_insensitive);
+    fn archive_listing(&mut self, info: &FileInfo, max_entries: u32) -> Option<ArchiveListing> {
+        resolve_archive_listing(info, max_entries, self.cache)
+    }
 
-    // Try to get from cache first
-    let cached = REGEX_CACHE.with(|cache| {
-        cache.borrow_mut().get(&key).cloned()
-    });
+    fn face_count(&mut self, info: &FileInfo) -> Option<u32> {
+        resolve_face_count(info, self.cache)
+    }
 
-    if let Some(regex) = cached {
-        return Ok(regex);
+    fn image_dimensions(&mut self, info: &FileInfo) -> Option<(u32, u32)> {
+        resolve_image_dimensions(info, self.settings, self.cache)
     }
 
-    // Compile and cache
-    let mut builder = RegexBuilder::new(pattern);
-    builder.case_insensitive(case_insensitive);
-    let regex = builder.build()?;
+    fn exif_date(&mut self, info: &FileInfo) -> Option<DateTime<Utc>> {
+        resolve_exif_date(info, self.settings, self.cache)
+    }
 
-    REGEX_CACHE.with(|cache| {
-        cache.borrow_mut().put(key, regex.clone());
-    });
+    fn page_count(&mut self, info: &FileInfo) -> Option<u32> {
+        resolve_page_count(info, self.settings, self.cache)
+    }
 
-    Ok(regex)
-}
+    fn media_info(&mut self, info: &FileInfo) -> Result<Option<MediaInfo>> {
+        resolve_media_info(info, self.cache).map(Some)
+    }
 
-pub(crate) fn evaluate_size(size: u64, cond: &crate::models::SizeCondition) -> bool {
-    let value = match cond.value {
-        Some(v) => to_bytes(v, &cond.unit),
-        None => 0,
-    };
-    match &cond.operator {
-        crate::models::ComparisonOperator::Equals => size == value,
-        crate::models::ComparisonOperator::NotEquals => size != value,
-        crate::models::ComparisonOperator::GreaterThan => size > value,
-        crate::models::ComparisonOperator::LessThan => size < value,
-        crate::models::ComparisonOperator::GreaterOrEqual => size >= value,
-        crate::models::ComparisonOperator::LessOrEqual => size <= value,
-        crate::models::ComparisonOperator::Between { min, max } => {
-            let min = to_bytes(*min, &cond.unit);
-            let max = to_bytes(*max, &cond.unit);
-            size >= min && size <= max
-        }
+    fn mime_type(&mut self, info: &FileInfo) -> Option<String> {
+        resolve_mime_type(info, self.cache)
     }
-}
 
-fn to_bytes(value: u64, unit: &SizeUnit) -> u64 {
-    match unit {
-        SizeUnit::Bytes => value,
-        SizeUnit::Kilobytes => value * 1024,
-        SizeUnit::Megabytes => value * 1024 * 1024,
-        SizeUnit::Gigabytes => value * 1024 * 1024 * 1024,
+    fn tags(&mut self, info: &FileInfo) -> Result<Option<Vec<String>>> {
+        Ok(file_tags::read(
+            &xattr::System,
+            TagFormat::native(),
+            &info.path,
+        )?)
     }
-}
 
-pub(crate) fn evaluate_date(date: chrono::DateTime<Utc>, operator: &DateOperator) -> bool {
-    let now = Utc::now();
-    let date_only = date.date_naive();
-
-    match operator {
-        DateOperator::Is { date } => date_only == *date,
-        DateOperator::IsBefore { date } => date_only < *date,
-        DateOperator::IsAfter { date } => date_only > *date,
-        DateOperator::Between { start, end } => date_only >= *start && date_only <= *end,
-        DateOperator::InTheLast { amount, unit } => {
-            let delta = to_duration(*amount, unit);
-            date >= now - delta
-        }
-        DateOperator::NotInTheLast { amount, unit } => {
-            let delta = to_duration(*amount, unit);
-            date < now - delta
-        }
+    fn duplicate_of(
+        &mut self,
+        info: &FileInfo,
+        scope: DuplicateScope,
+    ) -> Result<Option<std::path::PathBuf>> {
+        let Some(lookup) = self.duplicates else {
+            return Err(anyhow!("Is Duplicate can't be checked here"));
+        };
+        lookup.detector.find_duplicate(
+            &lookup.folder_id,
+            scope,
+            &info.path,
+            &self.settings.sampled_hashing,
+        )
     }
-}
 
-pub(crate) fn evaluate_time(operator: &TimeOperator) -> bool {
-    let now = chrono::Local::now().time();
-    evaluate_time_with(now, operator)
-}
+    fn screenshot_evidence(&mut self, info: &FileInfo) -> Option<ScreenshotEvidence> {
+        Some(resolve_screenshot_evidence(info, self.cache))
+    }
 
-fn evaluate_time_with(now: chrono::NaiveTime, operator: &TimeOperator) -> bool {
-    match operator {
-        TimeOperator::Is { time } => now == *time,
-        TimeOperator::IsBefore { time } => now < *time,
-        TimeOperator::IsAfter { time } => now > *time,
-        TimeOperator::Between { start, end } => {
-            if start <= end {
-                now >= *start && now <= *end
-            } else {
-                now >= *start || now <= *end
-            }
-        }
+    fn csv_column(&mut self, info: &FileInfo, cond: &CsvColumnCondition) -> Option<Vec<String>> {
+        resolve_csv_column(info, cond, self.cache)
+    }
+
+    /// Contents are only hashed once the names collide and the sizes agree.
+    fn collision(&mut self, folder: &str, info: &FileInfo) -> Option<Collision> {
+        let candidate = expand_tilde(folder).join(&info.os_name);
+        // A file already in the destination doesn't collide with itself.
+        let existing = std::fs::metadata(&candidate)
+            .ok()
+            .filter(|meta| meta.is_file() && candidate != info.path)?;
+        let same_content = existing.len() == info.size
+            && matches!(
+                (hash_file(&candidate), hash_file(&info.path)),
+                (Ok(existing), Ok(incoming)) if existing == incoming
+            );
+        Some(Collision {
+            path: candidate,
+            same_content,
+        })
     }
-}
 
-fn to_duration(amount: u32, unit: &TimeUnit) -> Duration {
-    match unit {
-        TimeUnit::Minutes => Duration::minutes(amount as i64),
-        TimeUnit::Hours => Duration::hours(amount as i64),
-        TimeUnit::Days => Duration::days(amount as i64),
-        TimeUnit::Weeks => Duration::weeks(amount as i64),
-        TimeUnit::Months => Duration::days(30 * amount as i64),
-        TimeUnit::Years => Duration::days(365 * amount as i64),
+    fn is_locked(&mut self, path: &std::path::Path) -> bool {
+        evaluate_locked(path, false)
     }
-}
 
-pub(crate) fn evaluate_kind(actual: FileKind, expected: FileKind, negate: bool) -> bool {
-    let matches = actual == expected;
-    if negate {
-        !matches
-    } else {
-        matches
+    fn run_shell(&mut self, command: &str, path: &std::path::Path) -> bool {
+        evaluate_shell(command, path)
     }
 }
 
+pub(crate) fn evaluate_locked(path: &std::path::Path, negate: bool) -> bool {
+    is_locked(path) != negate
+}
+
 pub(crate) fn evaluate_shell(command: &str, path: &std::path::Path) -> bool {
     let mut cmd = if cfg!(target_os = "windows") {
         let mut c = Command::new("cmd");
@@ -633,17 +1256,22 @@ pub(crate) fn evaluate_shell(command: &str, path: &std::path::Path) -> bool {
     status.map(|s| s.success()).unwrap_or(false)
 }
 
+/// Queues a log entry for each of a rule run's outcomes, with an undo entry
+/// for each file it moved, copied or renamed.
 pub fn log_outcomes(
-    repo: &LogRepository,
-    undo_repo: &UndoRepository,
+    writes: &mut WriteSet,
     rule: &Rule,
     info: &FileInfo,
     outcomes: &[ActionOutcome],
-) -> Result<()> {
+    recorded: &MatchMetadata,
+    trace: Option<&DispatchTrace>,
+) {
     for outcome in outcomes {
         let status = match outcome.status {
             ActionResultStatus::Success => LogStatus::Success,
-            ActionResultStatus::Skipped => LogStatus::Skipped,
+            ActionResultStatus::Skipped | ActionResultStatus::Simulated => {
+                LogStatus::Skipped
+            }
             ActionResultStatus::Error => LogStatus::Error,
         };
         let mut details = outcome.details.clone();
@@ -667,6 +1295,24 @@ pub fn log_outcomes(
                 metadata,
             });
         }
+        if let (Some(trace), Some(details)) = (trace, details.as_mut()) {
+            trace.apply(&mut details.metadata);
+        }
+        if let Some(details) = details.as_mut() {
+            details.metadata.insert(
+                "event_source".to_string(),
+                info.event_source.as_str().to_string(),
+            );
+        }
+        if let (Some(code), Some(details)) = (&rule.classification, details.as_mut()) {
+            details
+                .metadata
+                .insert("classification".to_string(), code.clone());
+        }
+        if let Some(details) = details.as_mut() {
+            match_metadata::echo(recorded, &mut details.metadata);
+            revisions::describe(rule, &mut details.metadata);
+        }
         let entry = LogEntry {
             id: String::new(),
             rule_id: Some(rule.id.clone()),
@@ -678,10 +1324,11 @@ pub fn log_outcomes(
             error_message: outcome.error.clone(),
             created_at: Utc::now(),
         };
-        let inserted = repo.insert(entry)?;
+        let inserted = writes.log(entry);
         if should_track_undo {
             if let Some(detail) = &inserted.action_detail {
                 if let Some(dest) = &detail.destination_path {
+                    let fingerprint = undo_fingerprint(std::path::Path::new(dest)).ok();
                     let undo_entry = crate::models::UndoEntry {
                         id: String::new(),
                         log_id: inserted.id.clone(),
@@ -689,17 +1336,70 @@ pub fn log_outcomes(
                         original_path: detail.source_path.clone(),
                         current_path: dest.clone(),
                         created_at: Utc::now(),
+                        hash: fingerprint.as_ref().map(|taken| taken.digest.clone()),
+                        hash_scheme: fingerprint
+                            .filter(|taken| taken.scheme != HashScheme::Full)
+                            .map(|taken| taken.scheme.to_string()),
+                        classification: rule.classification.clone(),
+                        status: UndoStatus::Available,
+                        status_reason: None,
                     };
-                    let _ = undo_repo.insert(undo_entry);
-                    let _ = undo_repo.cleanup(50);
+                    writes.undo(undo_entry);
                 }
             }
         }
     }
+}
+
+pub(crate) fn log_placeholder_skip(
+    repo: &LogRepository,
+    info: &FileInfo,
+    reason: &str,
+) -> Result<()> {
+    repo.insert(LogEntry {
+        id: String::new(),
+        rule_id: None,
+        rule_name: None,
+        file_path: info.path.to_string_lossy().to_string(),
+        action_type: "placeholder".to_string(),
+        action_detail: None,
+        status: LogStatus::Skipped,
+        error_message: Some(reason.to_string()),
+        created_at: Utc::now(),
+    })?;
+    Ok(())
+}
+
+/// Records why a file couldn't be looked at. Files that are simply gone
+/// again, like a download's temporary file, are everyday churn and only
+/// show in the event journal.
+pub(crate) fn log_unreadable(
+    repo: &LogRepository,
+    path: &std::path::Path,
+    err: &FileInfoError,
+) -> Result<()> {
+    if matches!(err, FileInfoError::NotFound) {
+        return Ok(());
+    }
+    repo.insert(LogEntry {
+        id: String::new(),
+        rule_id: None,
+        rule_name: None,
+        file_path: path.to_string_lossy().to_string(),
+        action_type: "unreadable".to_string(),
+        action_detail: None,
+        status: if err.is_skip() {
+            LogStatus::Skipped
+        } else {
+            LogStatus::Error
+        },
+        error_message: Some(err.to_string()),
+        created_at: Utc::now(),
+    })?;
     Ok(())
 }
 
-fn action_type_to_string(action_type: &ActionType) -> String {
+pub(crate) fn action_type_to_string(action_type: &ActionType) -> String {
     match action_type {
         ActionType::Move => "move",
         ActionType::Copy => "copy",
@@ -718,11 +1418,14 @@ fn action_type_to_string(action_type: &ActionType) -> String {
         ActionType::Continue => "continue",
         ActionType::Ignore => "ignore",
         ActionType::MakePdfSearchable => "makePdfSearchable",
+        ActionType::CreateFolderStructure => "createFolderStructure",
+        ActionType::DispatchToFolder => "dispatchToFolder",
+        ActionType::SetTags => "setTags",
     }
     .to_string()
 }
 
-fn should_stop_processing(rule: &Rule, outcomes: &[ActionOutcome]) -> bool {
+pub(crate) fn should_stop_processing(rule: &Rule, outcomes: &[ActionOutcome]) -> bool {
     if !rule.stop_processing {
         return false;
     }
@@ -734,18 +1437,15 @@ fn should_stop_processing(rule: &Rule, outcomes: &[ActionOutcome]) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::{
-        evaluate_date, evaluate_kind, evaluate_shell, evaluate_size, evaluate_string,
-        evaluate_time_with, EvaluationResult,
-    };
+    use super::{evaluate_locked, evaluate_shell, EvaluationResult};
     use crate::core::executor::{ActionOutcome, ActionResultStatus};
     use crate::models::{
-        ActionType, ComparisonOperator, Condition, ConditionGroup, DateOperator, FileKind,
-        MatchType, Rule, SizeCondition, SizeUnit, StringCondition, StringOperator, TimeOperator,
-        TimeUnit,
+        ActionType, Condition, ConditionGroup, DateCondition, DateOperator, MatchType, Rule,
+        StringCondition, StringOperator, TimeUnit,
     };
-    use crate::utils::file_info::FileInfo;
-    use chrono::{Duration, NaiveTime, Utc};
+    use filedispatch_core::evaluate::evaluate_string;
+    use filedispatch_core::file_info::FileInfo;
+    use std::collections::HashMap;
     use std::fs;
     use tempfile::tempdir;
 
@@ -771,427 +1471,1058 @@ mod tests {
         )
     }
 
-    // ==================== STRING CONDITION TESTS ====================
+    // ==================== TIME ZONE TESTS ====================
 
     #[test]
-    fn string_is_matches_exact() {
-        let cond = StringCondition {
-            operator: StringOperator::Is,
-            value: "report".to_string(),
-            case_sensitive: false,
+    fn date_condition_uses_the_default_zone_and_rejects_unknown_ones() {
+        let info = file_info_for("upload.jpg");
+        let today = |zone: &str| {
+            Condition::DateModified(DateCondition {
+                operator: DateOperator::InTheLast {
+                    amount: 1,
+                    unit: TimeUnit::Hours,
+                },
+                timezone: Some(zone.to_string()),
+            })
         };
-        let result = evaluate_string("report", &cond).unwrap();
-        assert!(result.matched);
-    }
-
-    #[test]
-    fn string_is_case_insensitive() {
-        let cond = StringCondition {
-            operator: StringOperator::Is,
-            value: "REPORT".to_string(),
-            case_sensitive: false,
+        let group = |condition| ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![condition],
         };
-        let result = evaluate_string("report", &cond).unwrap();
-        assert!(result.matched);
+
+        // Without a configured default, "default" behaves like an unset zone.
+        assert!(evaluate_group(&group(today("default")), &info).unwrap().matched);
+        let err = evaluate_group(&group(today("Nowhere/City")), &info).unwrap_err();
+        assert!(err.to_string().contains("unknown time zone"), "{err}");
     }
 
+    // ==================== CLASSIFIER CONDITION TESTS ====================
+
     #[test]
-    fn string_is_case_sensitive_fails() {
-        let cond = StringCondition {
-            operator: StringOperator::Is,
-            value: "REPORT".to_string(),
-            case_sensitive: true,
+    fn classified_as_requires_opt_in() {
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::ClassifiedAs(crate::models::ClassifiedAsCondition {
+                category: String::new(),
+                min_score: 0.0,
+            })],
         };
-        let result = evaluate_string("report", &cond).unwrap();
+        let result = evaluate_group(&group, &file_info_for("invoice.txt")).unwrap();
         assert!(!result.matched);
     }
 
+    // ==================== LOCK CONDITION TESTS ====================
+
     #[test]
-    fn string_is_not() {
-        let cond = StringCondition {
-            operator: StringOperator::IsNot,
-            value: "other".to_string(),
-            case_sensitive: false,
-        };
-        let result = evaluate_string("report", &cond).unwrap();
-        assert!(result.matched);
+    fn locked_condition_detects_held_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("open.docx");
+        std::fs::write(&path, "data").unwrap();
+
+        assert!(!evaluate_locked(&path, false));
+        assert!(evaluate_locked(&path, true));
+
+        let _guard = crate::utils::file_lock::open_exclusive(&path).unwrap();
+        assert!(evaluate_locked(&path, false));
+        assert!(!evaluate_locked(&path, true));
     }
 
+    // ==================== PLACEHOLDER TESTS ====================
+
     #[test]
-    fn string_contains() {
-        let cond = StringCondition {
-            operator: StringOperator::Contains,
-            value: "port".to_string(),
-            case_sensitive: false,
-        };
-        let result = evaluate_string("report", &cond).unwrap();
-        assert!(result.matched);
+    fn placeholder_gate_follows_folder_mode() {
+        use super::{placeholder_gate, PlaceholderGate};
+        use crate::models::PlaceholderMode;
+
+        for mode in [
+            PlaceholderMode::Skip,
+            PlaceholderMode::ProcessMetadataOnly,
+            PlaceholderMode::Hydrate,
+        ] {
+            assert_eq!(placeholder_gate(mode, false), PlaceholderGate::Process);
+        }
+        assert_eq!(placeholder_gate(PlaceholderMode::Skip, true), PlaceholderGate::Skip);
+        assert_eq!(
+            placeholder_gate(PlaceholderMode::ProcessMetadataOnly, true),
+            PlaceholderGate::MetadataOnly
+        );
+        assert_eq!(placeholder_gate(PlaceholderMode::Hydrate, true), PlaceholderGate::Hydrate);
     }
 
     #[test]
-    fn string_does_not_contain() {
-        let cond = StringCondition {
-            operator: StringOperator::DoesNotContain,
-            value: "xyz".to_string(),
-            case_sensitive: false,
+    fn placeholder_metadata_conditions_match_but_contents_fail() {
+        use crate::models::{ContentSource, ContentsCondition};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("invoice.txt");
+        fs::write(&path, "invoice total").unwrap();
+        let mut info = FileInfo::from_path(&path).unwrap();
+        info.is_placeholder = true;
+
+        let name_group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::Name(StringCondition {
+                operator: StringOperator::Is,
+                value: "invoice".to_string(),
+                case_sensitive: false,
+            })],
         };
-        let result = evaluate_string("report", &cond).unwrap();
-        assert!(result.matched);
-    }
+        assert!(evaluate_group(&name_group, &info).unwrap().matched);
 
-    #[test]
-    fn string_starts_with() {
-        let cond = StringCondition {
-            operator: StringOperator::StartsWith,
-            value: "rep".to_string(),
-            case_sensitive: false,
+        let contents_group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::Contents(ContentsCondition {
+                operator: StringOperator::Contains,
+                value: "invoice".to_string(),
+                case_sensitive: false,
+                source: ContentSource::Auto,
+                min_ocr_confidence: None,
+            })],
         };
-        let result = evaluate_string("report", &cond).unwrap();
-        assert!(result.matched);
+        assert!(!evaluate_group(&contents_group, &info).unwrap().matched);
+
+        let settings = crate::models::Settings::default();
+        let mut ocr = crate::core::ocr::OcrManager::new_placeholder();
+        let mut cache = crate::core::content::ContentCache::default();
+        let options = super::EvaluationOptions {
+            surface_errors: true,
+            ..Default::default()
+        };
+        let err = super::evaluate_group(
+            &contents_group,
+            &info,
+            &settings,
+            &mut ocr,
+            &mut cache,
+            &options,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("placeholder"));
+
+        info.is_placeholder = false;
+        assert!(evaluate_group(&contents_group, &info).unwrap().matched);
     }
 
     #[test]
-    fn string_ends_with() {
-        let cond = StringCondition {
-            operator: StringOperator::EndsWith,
-            value: "ort".to_string(),
-            case_sensitive: false,
+    fn contents_over_text_limit_are_streamed() {
+        use crate::models::{ContentSource, ContentsCondition};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        let mut body = String::from("id,customer,invoice_total\n");
+        for row in 0..2_000 {
+            body.push_str(&format!("{},acme,{}.00\n", row, row));
+        }
+        fs::write(&path, body).unwrap();
+
+        let mut settings = crate::models::Settings::default();
+        settings.content_max_text_bytes = 1024;
+        let info = FileInfo::from_path(&path).unwrap();
+        let contents = |operator: StringOperator, value: &str| ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::Contents(ContentsCondition {
+                operator,
+                value: value.to_string(),
+                case_sensitive: false,
+                source: ContentSource::Text,
+                min_ocr_confidence: None,
+            })],
         };
-        let result = evaluate_string("report", &cond).unwrap();
-        assert!(result.matched);
+        let evaluate = |group: &ConditionGroup,
+                        settings: &crate::models::Settings,
+                        cache: &mut crate::core::content::ContentCache| {
+            let mut ocr = crate::core::ocr::OcrManager::new_placeholder();
+            super::evaluate_group(
+                group,
+                &info,
+                settings,
+                &mut ocr,
+                cache,
+                &super::EvaluationOptions::default(),
+            )
+            .unwrap()
+        };
+
+        let mut cache = crate::core::content::ContentCache::default();
+        let group = contents(StringOperator::Contains, "Invoice_Total");
+        assert!(evaluate(&group, &settings, &mut cache).matched);
+        assert!(cache.streamed());
+        let mut cache = crate::core::content::ContentCache::default();
+        let group = contents(StringOperator::DoesNotContain, "refund");
+        assert!(evaluate(&group, &settings, &mut cache).matched);
+
+        // Under the limit the text is extracted as before.
+        settings.content_max_text_bytes = 0;
+        let mut cache = crate::core::content::ContentCache::default();
+        let group = contents(StringOperator::Contains, "invoice_total");
+        assert!(evaluate(&group, &settings, &mut cache).matched);
+        assert!(!cache.streamed());
     }
 
-    #[test]
-    fn string_matches_regex() {
-        let cond = StringCondition {
-            operator: StringOperator::Matches,
-            value: r"report_\d{4}".to_string(),
-            case_sensitive: false,
+    // ==================== OCR CONFIDENCE TESTS ====================
+
+    fn evaluate_with_ocr_confidence(
+        path: &std::path::Path,
+        source: crate::models::ContentSource,
+        confidence: f32,
+        min_ocr_confidence: Option<f32>,
+    ) -> EvaluationResult {
+        use crate::core::ocr::{OcrManager, OcrResult};
+        use crate::models::ContentsCondition;
+
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::Contents(ContentsCondition {
+                operator: StringOperator::Contains,
+                value: "PAID".to_string(),
+                case_sensitive: true,
+                source,
+                min_ocr_confidence,
+            })],
         };
-        let result = evaluate_string("report_2024", &cond).unwrap();
-        assert!(result.matched);
+        let info = FileInfo::from_path(path).unwrap();
+        let settings = crate::models::Settings::default();
+        let mut ocr = OcrManager::mocked(OcrResult {
+            text: "INVOICE 12\nPAID".to_string(),
+            average_confidence: confidence,
+        });
+        let mut cache = crate::core::content::ContentCache::default();
+        super::evaluate_group(
+            &group,
+            &info,
+            &settings,
+            &mut ocr,
+            &mut cache,
+            &super::EvaluationOptions::default(),
+        )
+        .unwrap()
     }
 
     #[test]
-    fn string_matches_regex_captures() {
-        let cond = StringCondition {
-            operator: StringOperator::Matches,
-            value: r"invoice_(\d{4})_(\w+)".to_string(),
-            case_sensitive: false,
-        };
-        let result = evaluate_string("invoice_2024_sales", &cond).unwrap();
-        assert!(result.matched);
-        assert_eq!(result.captures.get("1"), Some(&"2024".to_string()));
-        assert_eq!(result.captures.get("2"), Some(&"sales".to_string()));
+    fn contents_ocr_confidence_gate() {
+        use crate::models::ContentSource;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("fax.png");
+        fs::write(&path, [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]).unwrap();
+
+        let below = evaluate_with_ocr_confidence(&path, ContentSource::Ocr, 0.69, Some(0.7));
+        assert!(!below.matched);
+
+        let at = evaluate_with_ocr_confidence(&path, ContentSource::Ocr, 0.7, Some(0.7));
+        assert!(at.matched);
+
+        let above = evaluate_with_ocr_confidence(&path, ContentSource::Ocr, 0.93, Some(0.7));
+        assert!(above.matched);
+        assert_eq!(above.captures.get("ocr_confidence"), Some(&"0.93".to_string()));
+
+        let ungated = evaluate_with_ocr_confidence(&path, ContentSource::Ocr, 0.2, None);
+        assert!(ungated.matched);
     }
 
     #[test]
-    fn string_does_not_match_regex() {
-        let cond = StringCondition {
-            operator: StringOperator::DoesNotMatch,
-            value: r"^\d+$".to_string(),
-            case_sensitive: false,
-        };
-        let result = evaluate_string("report", &cond).unwrap();
+    fn contents_ocr_confidence_gate_ignores_plain_text() {
+        use crate::models::ContentSource;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("receipt.txt");
+        fs::write(&path, "Status: PAID").unwrap();
+
+        // The mocked OCR would fail the gate, but plain text never reaches it.
+        let result = evaluate_with_ocr_confidence(&path, ContentSource::Auto, 0.1, Some(0.9));
         assert!(result.matched);
+        assert!(!result.captures.contains_key("ocr_confidence"));
     }
 
-    // ==================== SIZE CONDITION TESTS ====================
+    // ==================== ARCHIVE CONTAINS TESTS ====================
 
-    #[test]
-    fn size_equals() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::Equals,
-            value: Some(100),
-            unit: SizeUnit::Bytes,
-        };
-        assert!(evaluate_size(100, &cond));
-        assert!(!evaluate_size(99, &cond));
+    fn archive_condition(
+        operator: StringOperator,
+        value: &str,
+        match_scope: crate::models::ArchiveMatchScope,
+    ) -> crate::models::ArchiveContainsCondition {
+        crate::models::ArchiveContainsCondition {
+            name_condition: StringCondition {
+                operator,
+                value: value.to_string(),
+                case_sensitive: false,
+            },
+            match_scope,
+            max_entries: 100,
+        }
+    }
+
+    fn listing(entries: &[&str], truncated: bool) -> crate::utils::archive::ArchiveListing {
+        crate::utils::archive::ArchiveListing {
+            entries: entries.iter().map(|e| e.to_string()).collect(),
+            truncated,
+        }
     }
 
     #[test]
-    fn size_not_equals() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::NotEquals,
-            value: Some(100),
-            unit: SizeUnit::Bytes,
-        };
-        assert!(evaluate_size(99, &cond));
-        assert!(!evaluate_size(100, &cond));
+    fn archive_contains_any_entry_in_nested_dirs() {
+        use filedispatch_core::evaluate::evaluate_archive_contains;
+        use crate::models::ArchiveMatchScope;
+
+        let entries = listing(&["readme.txt", "drawings/site/plan.DWG", "drawings/b.dwg"], false);
+        let cond = archive_condition(StringOperator::EndsWith, ".dwg", ArchiveMatchScope::AnyEntry);
+        let result = evaluate_archive_contains(&entries, &cond).unwrap();
+
+        assert!(result.matched);
+        assert_eq!(
+            result.captures.get("archive_match"),
+            Some(&"drawings/site/plan.DWG".to_string())
+        );
+        assert_eq!(result.captures.get("archive_entry_count"), Some(&"3".to_string()));
+
+        let cond = archive_condition(StringOperator::EndsWith, ".step", ArchiveMatchScope::AnyEntry);
+        assert!(!evaluate_archive_contains(&entries, &cond).unwrap().matched);
     }
 
     #[test]
-    fn size_greater_than() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::GreaterThan,
-            value: Some(100),
-            unit: SizeUnit::Bytes,
-        };
-        assert!(evaluate_size(101, &cond));
-        assert!(!evaluate_size(100, &cond));
+    fn archive_contains_all_entries_requires_full_listing() {
+        use filedispatch_core::evaluate::evaluate_archive_contains;
+        use crate::models::ArchiveMatchScope;
+
+        let cond = archive_condition(StringOperator::EndsWith, ".dwg", ArchiveMatchScope::AllEntries);
+        assert!(evaluate_archive_contains(&listing(&["a.dwg", "b/c.dwg"], false), &cond)
+            .unwrap()
+            .matched);
+        assert!(!evaluate_archive_contains(&listing(&["a.dwg", "notes.txt"], false), &cond)
+            .unwrap()
+            .matched);
+        // Entries beyond the cap were never seen.
+        assert!(!evaluate_archive_contains(&listing(&["a.dwg", "b.dwg"], true), &cond)
+            .unwrap()
+            .matched);
+        assert!(!evaluate_archive_contains(&listing(&[], false), &cond)
+            .unwrap()
+            .matched);
     }
 
     #[test]
-    fn size_less_than() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::LessThan,
-            value: Some(100),
-            unit: SizeUnit::Bytes,
+    fn archive_contains_skips_password_protected_zip() {
+        use crate::models::ArchiveMatchScope;
+        use std::io::Write;
+        use zip::write::FileOptions;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("secret.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let options =
+            FileOptions::<()>::default().with_aes_encryption(zip::AesMode::Aes256, "hunter2");
+        zip.start_file("plan.dwg", options).unwrap();
+        zip.write_all(b"dwg").unwrap();
+        zip.finish().unwrap();
+
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::ArchiveContains(archive_condition(
+                StringOperator::EndsWith,
+                ".dwg",
+                ArchiveMatchScope::AnyEntry,
+            ))],
         };
-        assert!(evaluate_size(99, &cond));
-        assert!(!evaluate_size(100, &cond));
+        let info = FileInfo::from_path(&path).unwrap();
+        let settings = crate::models::Settings::default();
+        let mut ocr = crate::core::ocr::OcrManager::new_placeholder();
+        let mut cache = crate::core::content::ContentCache::default();
+        let result = super::evaluate_group(
+            &group,
+            &info,
+            &settings,
+            &mut ocr,
+            &mut cache,
+            &super::EvaluationOptions::default(),
+        )
+        .unwrap();
+
+        assert!(!result.matched);
+        assert!(cache.archive_error().unwrap().contains("password"));
+    }
+
+    // ==================== CSV COLUMN TESTS ====================
+
+    fn csv_condition(column: crate::models::CsvColumnRef, value: &str) -> Condition {
+        Condition::CsvColumn(crate::models::CsvColumnCondition {
+            column,
+            row: crate::models::CsvRowScope::Any { max_rows: 10 },
+            condition: StringCondition {
+                operator: StringOperator::StartsWith,
+                value: value.to_string(),
+                case_sensitive: false,
+            },
+            delimiter: crate::models::CsvDelimiter::Auto,
+        })
     }
 
     #[test]
-    fn size_greater_or_equal() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::GreaterOrEqual,
-            value: Some(100),
-            unit: SizeUnit::Bytes,
+    fn csv_column_captures_cell_for_destination() {
+        use filedispatch_core::patterns::PatternEngine;
+        use crate::models::CsvColumnRef;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("statement.csv");
+        fs::write(
+            &path,
+            "Date;Account;Amount
+2025-03-01;CHK-1001;-12,50
+2025-03-02;SAV-2002;100,00
+",
+        )
+        .unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![csv_condition(
+                CsvColumnRef::Header {
+                    name: "account".to_string(),
+                },
+                "sav-",
+            )],
         };
-        assert!(evaluate_size(100, &cond));
-        assert!(evaluate_size(101, &cond));
-        assert!(!evaluate_size(99, &cond));
+
+        let result = evaluate_group(&group, &info).unwrap();
+        assert!(result.matched);
+        let destination =
+            PatternEngine::new().resolve("Finance/{csv:account}/", &info, &result.captures);
+        assert_eq!(destination, "Finance/SAV-2002/");
+
+        let by_index = ConditionGroup {
+            conditions: vec![csv_condition(CsvColumnRef::Index { index: 2 }, "chk-")],
+            ..group
+        };
+        let result = evaluate_group(&by_index, &info).unwrap();
+        assert_eq!(result.captures.get("csv:2"), Some(&"CHK-1001".to_string()));
+    }
+
+    fn exists_at(destination: &str, compare: crate::models::CollisionCompare) -> Condition {
+        Condition::ExistsAtDestination(crate::models::ExistsAtDestinationCondition {
+            destination_pattern: destination.to_string(),
+            compare,
+            negate: false,
+        })
+    }
+
+    fn all_of(conditions: Vec<Condition>) -> ConditionGroup {
+        ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions,
+        }
     }
 
     #[test]
-    fn size_less_or_equal() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::LessOrEqual,
-            value: Some(100),
-            unit: SizeUnit::Bytes,
-        };
-        assert!(evaluate_size(100, &cond));
-        assert!(evaluate_size(99, &cond));
-        assert!(!evaluate_size(101, &cond));
+    fn exists_at_destination_matches_name_collisions() {
+        use crate::models::CollisionCompare;
+
+        let dir = tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        let archive = dir.path().join("archive");
+        fs::create_dir_all(&inbox).unwrap();
+        fs::create_dir_all(&archive).unwrap();
+        let incoming = inbox.join("invoice.pdf");
+        fs::write(&incoming, b"march").unwrap();
+        fs::write(archive.join("invoice.pdf"), b"april").unwrap();
+        let info = FileInfo::from_path(&incoming).unwrap();
+        let destination = archive.to_string_lossy().to_string();
+
+        let by_name = all_of(vec![exists_at(&destination, CollisionCompare::NameOnly)]);
+        let result = evaluate_group(&by_name, &info).unwrap();
+        assert!(result.matched);
+        assert_eq!(
+            result.captures.get("collision_path"),
+            Some(&archive.join("invoice.pdf").to_string_lossy().to_string())
+        );
+        assert_eq!(
+            result.captures.get("collision_same_content"),
+            Some(&"false".to_string())
+        );
+
+        let by_hash = all_of(vec![exists_at(&destination, CollisionCompare::NameAndHash)]);
+        assert!(!evaluate_group(&by_hash, &info).unwrap().matched);
     }
 
     #[test]
-    fn size_between() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::Between { min: 50, max: 150 },
-            value: None,
-            unit: SizeUnit::Bytes,
-        };
-        assert!(evaluate_size(50, &cond));
-        assert!(evaluate_size(100, &cond));
-        assert!(evaluate_size(150, &cond));
-        assert!(!evaluate_size(49, &cond));
-        assert!(!evaluate_size(151, &cond));
+    fn exists_at_destination_compares_contents() {
+        use crate::models::CollisionCompare;
+
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("archive");
+        fs::create_dir_all(&archive).unwrap();
+        let incoming = dir.path().join("scan.png");
+        fs::write(&incoming, b"same bytes").unwrap();
+        fs::write(archive.join("scan.png"), b"same bytes").unwrap();
+        let info = FileInfo::from_path(&incoming).unwrap();
+
+        let group = all_of(vec![exists_at(
+            &archive.to_string_lossy(),
+            CollisionCompare::NameAndHash,
+        )]);
+        let result = evaluate_group(&group, &info).unwrap();
+        assert!(result.matched);
+        assert_eq!(
+            result.captures.get("collision_same_content"),
+            Some(&"true".to_string())
+        );
     }
 
     #[test]
-    fn size_kilobytes_conversion() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::Equals,
-            value: Some(1),
-            unit: SizeUnit::Kilobytes,
-        };
-        assert!(evaluate_size(1024, &cond));
+    fn exists_at_destination_treats_missing_folders_as_no_collision() {
+        use crate::models::{CollisionCompare, ExistsAtDestinationCondition};
+
+        let dir = tempdir().unwrap();
+        let incoming = dir.path().join("notes.txt");
+        fs::write(&incoming, b"notes").unwrap();
+        let info = FileInfo::from_path(&incoming).unwrap();
+        let missing = dir.path().join("nowhere").to_string_lossy().to_string();
+
+        let group = all_of(vec![exists_at(&missing, CollisionCompare::NameOnly)]);
+        assert!(!evaluate_group(&group, &info).unwrap().matched);
+
+        let negated = all_of(vec![Condition::ExistsAtDestination(
+            ExistsAtDestinationCondition {
+                destination_pattern: missing,
+                compare: CollisionCompare::NameOnly,
+                negate: true,
+            },
+        )]);
+        let result = evaluate_group(&negated, &info).unwrap();
+        assert!(result.matched);
+        assert!(result.captures.is_empty());
     }
 
     #[test]
-    fn size_megabytes_conversion() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::GreaterThan,
-            value: Some(1),
-            unit: SizeUnit::Megabytes,
-        };
-        assert!(evaluate_size(1024 * 1024 + 1, &cond));
-        assert!(!evaluate_size(1024 * 1024, &cond));
+    fn exists_at_destination_resolves_earlier_captures() {
+        use crate::models::{CollisionCompare, CsvColumnRef};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("statement.csv");
+        fs::write(&path, "Account,Amount\nSAV-2002,100\n").unwrap();
+        let account_dir = dir.path().join("SAV-2002");
+        fs::create_dir_all(&account_dir).unwrap();
+        fs::write(account_dir.join("statement.csv"), "older").unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+        let destination = format!("{}/{{csv:account}}", dir.path().to_string_lossy());
+
+        let group = all_of(vec![
+            csv_condition(
+                CsvColumnRef::Header {
+                    name: "account".to_string(),
+                },
+                "sav-",
+            ),
+            exists_at(&destination, CollisionCompare::NameOnly),
+        ]);
+        let result = evaluate_group(&group, &info).unwrap();
+        assert!(result.matched);
+        assert_eq!(
+            result.captures.get("collision_path"),
+            Some(&account_dir.join("statement.csv").to_string_lossy().to_string())
+        );
+
+        // Without the capture the folder resolves to the parent, which has
+        // no file of that name besides the incoming one.
+        let alone = all_of(vec![exists_at(&destination, CollisionCompare::NameOnly)]);
+        assert!(!evaluate_group(&alone, &info).unwrap().matched);
     }
 
     #[test]
-    fn size_gigabytes_conversion() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::LessThan,
-            value: Some(1),
-            unit: SizeUnit::Gigabytes,
-        };
-        assert!(evaluate_size(1024 * 1024 * 1024 - 1, &cond));
+    fn csv_column_records_why_file_is_unreadable() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        fs::write(&path, "id,account
+1,ACC-1
+2
+").unwrap();
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![csv_condition(
+                crate::models::CsvColumnRef::Header {
+                    name: "account".to_string(),
+                },
+                "XYZ",
+            )],
+        };
+        let info = FileInfo::from_path(&path).unwrap();
+        let settings = crate::models::Settings::default();
+        let mut ocr = crate::core::ocr::OcrManager::new_placeholder();
+        let mut cache = crate::core::content::ContentCache::default();
+        let result = super::evaluate_group(
+            &group,
+            &info,
+            &settings,
+            &mut ocr,
+            &mut cache,
+            &super::EvaluationOptions::default(),
+        )
+        .unwrap();
+
+        assert!(!result.matched);
+        assert_eq!(cache.csv_error(), Some("line 3 has 1 fields, not the header's 2"));
     }
 
-    // ==================== DATE CONDITION TESTS ====================
+    // ==================== GROUP SIZE TESTS ====================
 
     #[test]
-    fn date_is() {
-        let today = Utc::now();
-        let operator = DateOperator::Is {
-            date: today.date_naive(),
+    fn group_size_gates_on_files_that_arrived_together() {
+        use filedispatch_core::evaluate::evaluate_group_size;
+        use crate::models::{Arrival, ComparisonOperator, GroupSizeCondition};
+
+        let together = GroupSizeCondition {
+            operator: ComparisonOperator::GreaterOrEqual,
+            value: 2,
         };
-        assert!(evaluate_date(today, &operator));
+        let mut info = file_info_for("invoice.pdf");
+        // Without a group the file counts as a group of one.
+        assert!(!evaluate_group_size(&info, &together).matched);
+
+        info.arrival = Some(Arrival {
+            id: "3f9a0c1e".to_string(),
+            size: 3,
+        });
+        assert!(evaluate_group_size(&info, &together).matched);
+
+        info.arrival = Some(Arrival {
+            id: "7b21d4aa".to_string(),
+            size: 1,
+        });
+        assert!(!evaluate_group_size(&info, &together).matched);
     }
 
+    // ==================== ACTIVE APPLICATION TESTS ====================
+
     #[test]
-    fn date_is_before() {
-        let past = Utc::now() - Duration::days(10);
-        let operator = DateOperator::IsBefore {
-            date: Utc::now().date_naive(),
+    fn active_application_matches_the_app_captured_with_the_event() {
+        use filedispatch_core::evaluate::evaluate_active_application;
+        use crate::models::{ActiveApp, StringCondition, StringOperator};
+
+        let freecad = StringCondition {
+            operator: StringOperator::Is,
+            value: "FreeCAD".to_string(),
+            case_sensitive: false,
         };
-        assert!(evaluate_date(past, &operator));
-    }
+        let mut info = file_info_for("bracket.step");
+        // Scanned files carry no app, and the condition doesn't guess.
+        assert!(!evaluate_active_application(&info, &freecad).unwrap().matched);
 
-    #[test]
-    fn date_is_after() {
-        let future = Utc::now();
-        let operator = DateOperator::IsAfter {
-            date: (Utc::now() - Duration::days(10)).date_naive(),
+        info.active_app = Some(ActiveApp::Known {
+            name: "FreeCAD".to_string(),
+            identifier: Some("/usr/bin/freecad".to_string()),
+        });
+        assert!(evaluate_active_application(&info, &freecad).unwrap().matched);
+
+        info.active_app = Some(ActiveApp::Unavailable {
+            reason: "Wayland doesn't let other apps see which window is in front".to_string(),
+        });
+        let not_freecad = StringCondition {
+            operator: StringOperator::IsNot,
+            ..freecad
         };
-        assert!(evaluate_date(future, &operator));
+        assert!(!evaluate_active_application(&info, &not_freecad).unwrap().matched);
     }
 
+    // ==================== FACE COUNT TESTS ====================
+
     #[test]
-    fn date_between() {
-        let now = Utc::now();
-        let operator = DateOperator::Between {
-            start: (now - Duration::days(5)).date_naive(),
-            end: (now + Duration::days(5)).date_naive(),
+    fn face_count_compares_and_captures() {
+        use filedispatch_core::evaluate::evaluate_face_count;
+        use crate::models::{ComparisonOperator, FaceCountCondition};
+
+        let at_least_one = FaceCountCondition {
+            operator: ComparisonOperator::GreaterOrEqual,
+            value: 1,
         };
-        assert!(evaluate_date(now, &operator));
-    }
+        let result = evaluate_face_count(3, &at_least_one);
+        assert!(result.matched);
+        assert_eq!(result.captures.get("face_count"), Some(&"3".to_string()));
+        assert!(!evaluate_face_count(0, &at_least_one).matched);
 
-    #[test]
-    fn date_in_the_last_days() {
-        let recent = Utc::now() - Duration::hours(12);
-        let operator = DateOperator::InTheLast {
-            amount: 1,
-            unit: TimeUnit::Days,
+        let group_photo = FaceCountCondition {
+            operator: ComparisonOperator::Between { min: 3, max: 10 },
+            value: 0,
         };
-        assert!(evaluate_date(recent, &operator));
+        assert!(!evaluate_face_count(2, &group_photo).matched);
+        assert!(evaluate_face_count(10, &group_photo).matched);
     }
 
     #[test]
-    fn date_in_the_last_hours() {
-        let recent = Utc::now() - Duration::minutes(30);
-        let operator = DateOperator::InTheLast {
-            amount: 1,
-            unit: TimeUnit::Hours,
+    fn face_count_without_detector_does_not_match() {
+        use crate::models::{ComparisonOperator, FaceCountCondition};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("holiday.png");
+        image::RgbImage::new(32, 24).save(&path).unwrap();
+        crate::core::faces::set_face_model(None);
+
+        // "No faces" mustn't match just because nothing could be counted.
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::FaceCount(FaceCountCondition {
+                operator: ComparisonOperator::Equals,
+                value: 0,
+            })],
         };
-        assert!(evaluate_date(recent, &operator));
+        let info = FileInfo::from_path(&path).unwrap();
+        let settings = crate::models::Settings::default();
+        let mut ocr = crate::core::ocr::OcrManager::new_placeholder();
+        let mut cache = crate::core::content::ContentCache::default();
+        let result = super::evaluate_group(
+            &group,
+            &info,
+            &settings,
+            &mut ocr,
+            &mut cache,
+            &super::EvaluationOptions::default(),
+        )
+        .unwrap();
+
+        assert!(!result.matched);
+        assert!(cache
+            .face_error()
+            .unwrap()
+            .starts_with(crate::core::faces::DETECTOR_UNAVAILABLE));
     }
 
     #[test]
-    fn date_not_in_the_last() {
-        let old = Utc::now() - Duration::days(10);
-        let operator = DateOperator::NotInTheLast {
-            amount: 5,
-            unit: TimeUnit::Days,
+    fn image_dimensions_come_from_the_header_within_the_probe_limit() {
+        use crate::models::{ComparisonOperator, ImageDimensionsCondition, ImageShape, PixelBound};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("banner.png");
+        image::RgbImage::new(64, 24).save(&path).unwrap();
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::ImageDimensions(ImageDimensionsCondition {
+                width: Some(PixelBound {
+                    operator: ComparisonOperator::GreaterOrEqual,
+                    value: 64,
+                }),
+                height: None,
+                shape: Some(ImageShape::Landscape),
+            })],
         };
-        assert!(evaluate_date(old, &operator));
+        let info = FileInfo::from_path(&path).unwrap();
+        let mut settings = crate::models::Settings::default();
+        let mut ocr = crate::core::ocr::OcrManager::new_placeholder();
+        let mut evaluate = |settings: &crate::models::Settings| {
+            let mut cache = crate::core::content::ContentCache::default();
+            let result = super::evaluate_group(
+                &group,
+                &info,
+                settings,
+                &mut ocr,
+                &mut cache,
+                &super::EvaluationOptions::default(),
+            )
+            .unwrap();
+            (result, cache.image_metadata_error().map(str::to_string))
+        };
+
+        let (result, error) = evaluate(&settings);
+        assert!(result.matched);
+        assert_eq!(result.captures["image_width"], "64");
+        assert_eq!(result.captures["image_height"], "24");
+        assert_eq!(error, None);
+
+        settings.content_max_image_probe_bytes = 1;
+        let (result, error) = evaluate(&settings);
+        assert!(!result.matched);
+        assert!(error.is_some());
     }
 
     #[test]
-    fn date_in_the_last_weeks() {
-        let recent = Utc::now() - Duration::days(3);
-        let operator = DateOperator::InTheLast {
-            amount: 1,
-            unit: TimeUnit::Weeks,
+    fn exif_date_is_read_with_the_dimensions() {
+        use crate::models::{ComparisonOperator, ImageDimensionsCondition, PixelBound};
+
+        let dir = tempdir().unwrap();
+        let photo = dir.path().join("IMG_0001.jpg");
+        image::RgbImage::new(32, 16).save(&photo).unwrap();
+        // IFD0 holding only DateTime, its text just past the IFD.
+        let mut tiff = vec![0x49, 0x49, 42, 0, 8, 0, 0, 0, 1, 0];
+        tiff.extend_from_slice(&0x0132u16.to_le_bytes());
+        tiff.extend_from_slice(&[2, 0, 20, 0, 0, 0, 26, 0, 0, 0, 0, 0, 0, 0]);
+        tiff.extend_from_slice(b"2018:08:04 12:00:00\0");
+        crate::core::screenshots::tests::insert_exif(&photo, &tiff);
+        let pasted = dir.path().join("pasted.png");
+        image::RgbImage::new(32, 16).save(&pasted).unwrap();
+
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![
+                Condition::ExifDate(DateCondition {
+                    operator: DateOperator::IsBefore {
+                        date: chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+                    },
+                    timezone: None,
+                }),
+                Condition::ImageDimensions(ImageDimensionsCondition {
+                    width: Some(PixelBound {
+                        operator: ComparisonOperator::Equals,
+                        value: 32,
+                    }),
+                    height: None,
+                    shape: None,
+                }),
+            ],
+        };
+        let settings = crate::models::Settings::default();
+        let mut ocr = crate::core::ocr::OcrManager::new_placeholder();
+        let mut evaluate = |path: &std::path::Path| {
+            let info = FileInfo::from_path(path).unwrap();
+            let mut cache = crate::core::content::ContentCache::default();
+            super::evaluate_group(
+                &group,
+                &info,
+                &settings,
+                &mut ocr,
+                &mut cache,
+                &super::EvaluationOptions::default(),
+            )
+            .unwrap()
+            .matched
         };
-        assert!(evaluate_date(recent, &operator));
-    }
 
-    #[test]
-    fn date_in_the_last_months() {
-        let recent = Utc::now() - Duration::days(15);
-        let operator = DateOperator::InTheLast {
-            amount: 1,
-            unit: TimeUnit::Months,
-        };
-        assert!(evaluate_date(recent, &operator));
+        assert!(evaluate(&photo));
+        assert!(!evaluate(&pasted));
     }
 
     #[test]
-    fn date_in_the_last_years() {
-        let recent = Utc::now() - Duration::days(180);
-        let operator = DateOperator::InTheLast {
-            amount: 1,
-            unit: TimeUnit::Years,
-        };
-        assert!(evaluate_date(recent, &operator));
-    }
+    fn media_duration_comes_from_the_container_and_bad_media_does_not_match() {
+        use crate::models::{ComparisonOperator, MediaDurationCondition, TimeUnit};
 
-    // ==================== TIME CONDITION TESTS ====================
+        let dir = tempdir().unwrap();
+        // Three seconds of MPEG-1 layer III at 128 kbit/s: 417-byte frames.
+        let clip = dir.path().join("clip.mp3");
+        let mut frame = vec![0xff, 0xfb, 0x90, 0x00];
+        frame.resize(417, 0);
+        fs::write(&clip, frame.repeat(116)).unwrap();
+        let broken = dir.path().join("broken.mp4");
+        fs::write(&broken, b"\0\0\0\x20ftypisom but nothing else").unwrap();
 
-    #[test]
-    fn time_is() {
-        let now = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
-        let operator = TimeOperator::Is {
-            time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::MediaDuration(MediaDurationCondition {
+                operator: ComparisonOperator::LessThan,
+                value: 1,
+                unit: TimeUnit::Minutes,
+            })],
         };
-        assert!(evaluate_time_with(now, &operator));
-    }
+        let settings = crate::models::Settings::default();
+        let mut ocr = crate::core::ocr::OcrManager::new_placeholder();
+        let mut evaluate = |path: &std::path::Path| {
+            let info = FileInfo::from_path(path).unwrap();
+            let mut cache = crate::core::content::ContentCache::default();
+            let result = super::evaluate_group(
+                &group,
+                &info,
+                &settings,
+                &mut ocr,
+                &mut cache,
+                &super::EvaluationOptions::default(),
+            )
+            .unwrap();
+            (result, cache.media_error().map(str::to_string))
+        };
+
+        let (result, error) = evaluate(&clip);
+        assert!(result.matched);
+        assert_eq!(result.captures["media_duration"], "3");
+        assert_eq!(error, None);
 
-    #[test]
-    fn time_between_normal_range() {
-        let now = NaiveTime::from_hms_opt(14, 0, 0).unwrap();
-        let operator = TimeOperator::Between {
-            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
-            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
-        };
-        assert!(evaluate_time_with(now, &operator));
+        let (result, error) = evaluate(&broken);
+        assert!(!result.matched);
+        assert!(error.is_some());
     }
 
     #[test]
-    fn time_between_outside_range() {
-        let now = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
-        let operator = TimeOperator::Between {
-            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
-            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+    fn mime_type_is_sniffed_from_the_bytes_whatever_the_extension() {
+        let dir = tempdir().unwrap();
+        let photo = dir.path().join("photo.txt");
+        image::RgbImage::new(4, 4)
+            .save_with_format(&photo, image::ImageFormat::Png)
+            .unwrap();
+        let notes = dir.path().join("notes.png");
+        fs::write(&notes, "just text").unwrap();
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::MimeType(StringCondition {
+                operator: StringOperator::IsNot,
+                value: "application/pdf".to_string(),
+                case_sensitive: false,
+            })],
         };
-        assert!(!evaluate_time_with(now, &operator));
-    }
-
-    // ==================== KIND CONDITION TESTS ====================
-
-    #[test]
-    fn kind_matches_file() {
-        assert!(evaluate_kind(FileKind::File, FileKind::File, false));
-    }
-
-    #[test]
-    fn kind_matches_folder() {
-        assert!(evaluate_kind(FileKind::Folder, FileKind::Folder, false));
-    }
-
-    #[test]
-    fn kind_matches_image() {
-        assert!(evaluate_kind(FileKind::Image, FileKind::Image, false));
+        let settings = crate::models::Settings::default();
+        let mut ocr = crate::core::ocr::OcrManager::new_placeholder();
+        let mut evaluate = |path: &std::path::Path| {
+            let info = FileInfo::from_path(path).unwrap();
+            let mut cache = crate::core::content::ContentCache::default();
+            let result = super::evaluate_group(
+                &group,
+                &info,
+                &settings,
+                &mut ocr,
+                &mut cache,
+                &super::EvaluationOptions::default(),
+            )
+            .unwrap();
+            (result.matched, cache.mime_error().map(str::to_string))
+        };
+
+        assert_eq!(evaluate(&photo), (true, None));
+        // Text has no signature, so even a negated condition doesn't match.
+        let (matched, error) = evaluate(&notes);
+        assert!(!matched);
+        assert!(error.is_some());
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    #[test]
+    fn tags_never_match_where_files_cant_be_tagged() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("invoice.pdf");
+        fs::write(&path, "pdf").unwrap();
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::Tag(StringCondition {
+                operator: StringOperator::IsNot,
+                value: "Red".to_string(),
+                case_sensitive: false,
+            })],
+        };
+        let info = FileInfo::from_path(&path).unwrap();
+        let mut cache = crate::core::content::ContentCache::default();
+        let result = super::evaluate_group(
+            &group,
+            &info,
+            &crate::models::Settings::default(),
+            &mut crate::core::ocr::OcrManager::new_placeholder(),
+            &mut cache,
+            &super::EvaluationOptions::default(),
+        )
+        .unwrap();
+        assert!(!result.matched);
     }
 
     #[test]
-    fn kind_matches_video() {
-        assert!(evaluate_kind(FileKind::Video, FileKind::Video, false));
-    }
+    fn duplicates_capture_their_original_only_with_a_detector() {
+        use crate::models::{DuplicateScope, IsDuplicateCondition};
+        use crate::storage::folder_repo::FolderRepository;
 
-    #[test]
-    fn kind_matches_audio() {
-        assert!(evaluate_kind(FileKind::Audio, FileKind::Audio, false));
-    }
+        let dir = tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        fs::create_dir_all(&inbox).unwrap();
+        let db =
+            crate::storage::database::Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&inbox.to_string_lossy(), "Inbox")
+            .unwrap();
+        let original = inbox.join("photo.jpg");
+        let copy = inbox.join("photo copy.jpg");
+        fs::write(&original, "jpeg").unwrap();
+        fs::write(&copy, "jpeg").unwrap();
+        let group = ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![Condition::IsDuplicate(IsDuplicateCondition {
+                scope: DuplicateScope::SameFolder,
+                negate: false,
+            })],
+        };
+        let info = FileInfo::from_path(&copy).unwrap();
+        let evaluate = |options: &super::EvaluationOptions| {
+            super::evaluate_group(
+                &group,
+                &info,
+                &crate::models::Settings::default(),
+                &mut crate::core::ocr::OcrManager::new_placeholder(),
+                &mut crate::core::content::ContentCache::default(),
+                options,
+            )
+            .unwrap()
+        };
 
-    #[test]
-    fn kind_matches_document() {
-        assert!(evaluate_kind(FileKind::Document, FileKind::Document, false));
+        assert!(!evaluate(&super::EvaluationOptions::default()).matched);
+        let mut negated = group.clone();
+        negated.conditions = vec![Condition::IsDuplicate(IsDuplicateCondition {
+            scope: DuplicateScope::SameFolder,
+            negate: true,
+        })];
+        let unchecked = super::evaluate_group(
+            &negated,
+            &info,
+            &crate::models::Settings::default(),
+            &mut crate::core::ocr::OcrManager::new_placeholder(),
+            &mut crate::core::content::ContentCache::default(),
+            &super::EvaluationOptions::default(),
+        )
+        .unwrap();
+        assert!(!unchecked.matched);
+        let result = evaluate(&super::EvaluationOptions {
+            duplicates: Some(super::DuplicateLookup {
+                detector: std::sync::Arc::new(super::DuplicateDetector::new(db)),
+                folder_id: folder.id,
+            }),
+            ..Default::default()
+        });
+        assert!(result.matched);
+        assert_eq!(result.captures["duplicate_of"], original.to_string_lossy());
     }
 
     #[test]
-    fn kind_matches_archive() {
-        assert!(evaluate_kind(FileKind::Archive, FileKind::Archive, false));
-    }
+    fn a_camera_jpeg_is_not_a_screenshot() {
+        use crate::core::content::ContentCache;
+        use crate::models::ScreenshotCondition;
 
-    #[test]
-    fn kind_matches_code() {
-        assert!(evaluate_kind(FileKind::Code, FileKind::Code, false));
-    }
+        let dir = tempdir().unwrap();
+        let photo = dir.path().join("IMG_2041.jpg");
+        crate::core::screenshots::tests::write_camera_jpeg(&photo, 1920, 1080);
+        let pasted = dir.path().join("image.png");
+        image::RgbImage::new(1920, 1080).save(&pasted).unwrap();
+        let settings = crate::models::Settings::default();
+        let is_screenshot = |path: &std::path::Path, negate: bool, cache: &mut ContentCache| {
+            let group = ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![Condition::IsScreenshot(ScreenshotCondition { negate })],
+            };
+            super::evaluate_group(
+                &group,
+                &FileInfo::from_path(path).unwrap(),
+                &settings,
+                &mut crate::core::ocr::OcrManager::new_placeholder(),
+                cache,
+                &super::EvaluationOptions::default(),
+            )
+            .unwrap()
+            .matched
+        };
 
-    #[test]
-    fn kind_negate_works() {
-        assert!(evaluate_kind(FileKind::File, FileKind::Folder, true));
-        assert!(!evaluate_kind(FileKind::File, FileKind::File, true));
-    }
+        // A common screen size, but a camera took it.
+        let mut cache = ContentCache::default();
+        assert!(!is_screenshot(&photo, false, &mut cache));
+        assert!(is_screenshot(&photo, true, &mut cache));
+        let evidence = cache.screenshot_evidence().unwrap();
+        let verdict = filedispatch_core::screenshot::judge("IMG_2041.jpg", &[], evidence);
+        assert!(verdict.signals.contains(&"camera EXIF".to_string()));
 
-    #[test]
-    fn kind_mismatch() {
-        assert!(!evaluate_kind(FileKind::File, FileKind::Image, false));
+        // A pasted PNG the size of a screen.
+        assert!(is_screenshot(&pasted, false, &mut ContentCache::default()));
     }
 
     // ==================== SHELL CONDITION TESTS ====================
@@ -1449,29 +2780,6 @@ mod tests {
         assert!(!result.matched);
     }
 
-    #[test]
-    fn current_time_between_handles_wraparound() {
-        let now = NaiveTime::from_hms_opt(1, 30, 0).unwrap();
-        let operator = TimeOperator::Between {
-            start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
-            end: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
-        };
-        assert!(evaluate_time_with(now, &operator));
-    }
-
-    #[test]
-    fn current_time_before_after() {
-        let now = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
-        let before = TimeOperator::IsBefore {
-            time: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
-        };
-        let after = TimeOperator::IsAfter {
-            time: NaiveTime::from_hms_opt(8, 30, 0).unwrap(),
-        };
-        assert!(evaluate_time_with(now, &before));
-        assert!(evaluate_time_with(now, &after));
-    }
-
     #[test]
     fn continue_action_overrides_stop_processing() {
         let rule = Rule {
@@ -1487,6 +2795,17 @@ mod tests {
             },
             actions: vec![],
             position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -1495,6 +2814,7 @@ mod tests {
             status: ActionResultStatus::Success,
             details: None,
             error: None,
+            error_kind: None,
         }];
         assert!(!super::should_stop_processing(&rule, &outcomes));
     }
@@ -1514,6 +2834,17 @@ mod tests {
             },
             actions: vec![],
             position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -1522,257 +2853,13 @@ mod tests {
             status: ActionResultStatus::Success,
             details: None,
             error: None,
+            error_kind: None,
         }];
         assert!(super::should_stop_processing(&rule, &outcomes));
     }
 
     // ==================== EDGE CASE TESTS ====================
 
-    // --- Date/Time Boundary Conditions ---
-
-    #[test]
-    fn date_at_exact_midnight() {
-        // Test date condition at exactly midnight (00:00:00)
-        let midnight = Utc::now()
-            .date_naive()
-            .and_hms_opt(0, 0, 0)
-            .unwrap()
-            .and_utc();
-        let operator = DateOperator::Is {
-            date: midnight.date_naive(),
-        };
-        assert!(evaluate_date(midnight, &operator));
-    }
-
-    #[test]
-    fn date_one_second_before_midnight() {
-        let almost_midnight = Utc::now()
-            .date_naive()
-            .and_hms_opt(23, 59, 59)
-            .unwrap()
-            .and_utc();
-        let operator = DateOperator::Is {
-            date: almost_midnight.date_naive(),
-        };
-        assert!(evaluate_date(almost_midnight, &operator));
-    }
-
-    #[test]
-    fn time_at_exact_midnight() {
-        let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
-        let operator = TimeOperator::Is {
-            time: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-        };
-        assert!(evaluate_time_with(midnight, &operator));
-    }
-
-    #[test]
-    fn time_between_crossing_midnight_at_boundary() {
-        // Test wraparound at exactly the end boundary
-        let end_time = NaiveTime::from_hms_opt(2, 0, 0).unwrap();
-        let operator = TimeOperator::Between {
-            start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
-            end: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
-        };
-        assert!(evaluate_time_with(end_time, &operator));
-    }
-
-    #[test]
-    fn time_between_not_crossing_midnight_at_exact_boundaries() {
-        let start = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
-        let end = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
-        let operator = TimeOperator::Between { start, end };
-        // At start boundary
-        assert!(evaluate_time_with(start, &operator));
-        // At end boundary
-        assert!(evaluate_time_with(end, &operator));
-    }
-
-    #[test]
-    fn date_in_the_last_just_within_boundary() {
-        // Test InTheLast just within the duration
-        let just_within = Utc::now() - Duration::hours(23);
-        let operator = DateOperator::InTheLast {
-            amount: 1,
-            unit: TimeUnit::Days,
-        };
-        // Should match since it's within 1 day
-        assert!(evaluate_date(just_within, &operator));
-    }
-
-    #[test]
-    fn date_not_in_the_last_clearly_outside() {
-        // Test NotInTheLast clearly outside the duration
-        let clearly_outside = Utc::now() - Duration::days(2);
-        let operator = DateOperator::NotInTheLast {
-            amount: 1,
-            unit: TimeUnit::Days,
-        };
-        // Should match since it's more than 1 day ago
-        assert!(evaluate_date(clearly_outside, &operator));
-    }
-
-    #[test]
-    fn date_in_the_last_just_outside_boundary() {
-        // Test InTheLast just outside the duration
-        let just_outside = Utc::now() - Duration::hours(25);
-        let operator = DateOperator::InTheLast {
-            amount: 1,
-            unit: TimeUnit::Days,
-        };
-        // Should NOT match since it's more than 1 day ago
-        assert!(!evaluate_date(just_outside, &operator));
-    }
-
-    // --- Size Boundary Conditions ---
-
-    #[test]
-    fn size_zero_bytes_equals() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::Equals,
-            value: Some(0),
-            unit: SizeUnit::Bytes,
-        };
-        assert!(evaluate_size(0, &cond));
-        assert!(!evaluate_size(1, &cond));
-    }
-
-    #[test]
-    fn size_zero_bytes_greater_than() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::GreaterThan,
-            value: Some(0),
-            unit: SizeUnit::Bytes,
-        };
-        assert!(evaluate_size(1, &cond));
-        assert!(!evaluate_size(0, &cond));
-    }
-
-    #[test]
-    fn size_zero_bytes_less_than() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::LessThan,
-            value: Some(0),
-            unit: SizeUnit::Bytes,
-        };
-        // Nothing is less than 0 for u64
-        assert!(!evaluate_size(0, &cond));
-    }
-
-    #[test]
-    fn size_between_with_equal_min_max() {
-        // Edge case: min equals max
-        let cond = SizeCondition {
-            operator: ComparisonOperator::Between { min: 100, max: 100 },
-            value: None,
-            unit: SizeUnit::Bytes,
-        };
-        assert!(evaluate_size(100, &cond));
-        assert!(!evaluate_size(99, &cond));
-        assert!(!evaluate_size(101, &cond));
-    }
-
-    #[test]
-    fn size_between_with_min_greater_than_max() {
-        // Edge case: min > max (should never match)
-        let cond = SizeCondition {
-            operator: ComparisonOperator::Between { min: 150, max: 50 },
-            value: None,
-            unit: SizeUnit::Bytes,
-        };
-        // This condition can never be satisfied
-        assert!(!evaluate_size(100, &cond));
-        assert!(!evaluate_size(50, &cond));
-        assert!(!evaluate_size(150, &cond));
-    }
-
-    #[test]
-    fn size_max_u64_value() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::Equals,
-            value: Some(u64::MAX),
-            unit: SizeUnit::Bytes,
-        };
-        assert!(evaluate_size(u64::MAX, &cond));
-        assert!(!evaluate_size(u64::MAX - 1, &cond));
-    }
-
-    #[test]
-    fn size_none_value_treated_as_zero() {
-        let cond = SizeCondition {
-            operator: ComparisonOperator::Equals,
-            value: None,
-            unit: SizeUnit::Bytes,
-        };
-        assert!(evaluate_size(0, &cond));
-    }
-
-    // --- Regex Edge Cases ---
-
-    #[test]
-    fn regex_empty_pattern() {
-        let cond = StringCondition {
-            operator: StringOperator::Matches,
-            value: "".to_string(),
-            case_sensitive: false,
-        };
-        // Empty regex matches everything
-        let result = evaluate_string("anything", &cond).unwrap();
-        assert!(result.matched);
-    }
-
-    #[test]
-    fn regex_invalid_pattern_returns_error() {
-        let cond = StringCondition {
-            operator: StringOperator::Matches,
-            value: r"[invalid".to_string(), // Unclosed bracket
-            case_sensitive: false,
-        };
-        let result = evaluate_string("test", &cond);
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn regex_special_characters_in_input() {
-        let cond = StringCondition {
-            operator: StringOperator::Matches,
-            value: r"file\.txt".to_string(),
-            case_sensitive: false,
-        };
-        let result = evaluate_string("file.txt", &cond).unwrap();
-        assert!(result.matched);
-        // Should not match without the dot
-        let result2 = evaluate_string("filetxt", &cond).unwrap();
-        assert!(!result2.matched);
-    }
-
-    #[test]
-    fn regex_unicode_characters() {
-        let cond = StringCondition {
-            operator: StringOperator::Matches,
-            value: r"文档_\d+".to_string(),
-            case_sensitive: false,
-        };
-        let result = evaluate_string("文档_2024", &cond).unwrap();
-        assert!(result.matched);
-    }
-
-    #[test]
-    fn regex_cache_hit() {
-        // Test that regex caching works by using the same pattern twice
-        let cond = StringCondition {
-            operator: StringOperator::Matches,
-            value: r"test_\d+".to_string(),
-            case_sensitive: false,
-        };
-        // First call - compiles and caches
-        let result1 = evaluate_string("test_123", &cond).unwrap();
-        assert!(result1.matched);
-        // Second call - should use cached regex
-        let result2 = evaluate_string("test_456", &cond).unwrap();
-        assert!(result2.matched);
-    }
-
     // --- Empty Condition Groups ---
 
     #[test]
@@ -1893,69 +2980,6 @@ mod tests {
         assert!(result.matched);
     }
 
-    // --- String Operator Edge Cases ---
-
-    #[test]
-    fn string_empty_value_contains() {
-        let cond = StringCondition {
-            operator: StringOperator::Contains,
-            value: "".to_string(),
-            case_sensitive: false,
-        };
-        let result = evaluate_string("anything", &cond).unwrap();
-        assert!(result.matched);
-    }
-
-    #[test]
-    fn string_empty_target_contains() {
-        let cond = StringCondition {
-            operator: StringOperator::Contains,
-            value: "x".to_string(),
-            case_sensitive: false,
-        };
-        let result = evaluate_string("", &cond).unwrap();
-        assert!(!result.matched);
-    }
-
-    #[test]
-    fn string_both_empty() {
-        let cond = StringCondition {
-            operator: StringOperator::Is,
-            value: "".to_string(),
-            case_sensitive: false,
-        };
-        let result = evaluate_string("", &cond).unwrap();
-        assert!(result.matched);
-    }
-
-    #[test]
-    fn string_unicode_case_sensitivity_ascii_folded() {
-        // Test case insensitivity with ASCII only (eq_ignore_ascii_case)
-        let cond = StringCondition {
-            operator: StringOperator::Is,
-            value: "ÑOÑO".to_string(), // Uppercase ASCII O, uppercase Ñ
-            case_sensitive: false,
-        };
-        // eq_ignore_ascii_case folds ASCII O to o, but Ñ stays unchanged
-        // "ÑOÑO" vs "ÑoÑo" - ASCII O/o matches, Ñ matches Ñ
-        let result = evaluate_string("ÑoÑo", &cond).unwrap();
-        assert!(result.matched);
-    }
-
-    #[test]
-    fn string_unicode_case_sensitivity_non_ascii_not_folded() {
-        // Test that non-ASCII case differences are NOT handled
-        let cond = StringCondition {
-            operator: StringOperator::Is,
-            value: "MÜNCHEN".to_string(), // Uppercase Ü
-            case_sensitive: false,
-        };
-        // eq_ignore_ascii_case does NOT fold ü to Ü
-        let result = evaluate_string("münchen", &cond).unwrap();
-        // This won't match because ü ≠ Ü in ASCII comparison
-        assert!(!result.matched);
-    }
-
     // --- Shell Script Edge Cases ---
 
     #[test]
@@ -1983,65 +3007,6 @@ mod tests {
         assert!(result);
     }
 
-    // --- Date Between Edge Cases ---
-
-    #[test]
-    fn date_between_same_start_and_end() {
-        let today = Utc::now();
-        let operator = DateOperator::Between {
-            start: today.date_naive(),
-            end: today.date_naive(),
-        };
-        assert!(evaluate_date(today, &operator));
-    }
-
-    #[test]
-    fn date_between_start_after_end() {
-        let now = Utc::now();
-        let operator = DateOperator::Between {
-            start: (now + Duration::days(5)).date_naive(),
-            end: (now - Duration::days(5)).date_naive(),
-        };
-        // Start > end, should not match anything
-        assert!(!evaluate_date(now, &operator));
-    }
-
-    // --- Captures with Multiple Groups ---
-
-    #[test]
-    fn regex_multiple_captures() {
-        let cond = StringCondition {
-            operator: StringOperator::Matches,
-            value: r"(\d{4})-(\d{2})-(\d{2})_([a-z]+)".to_string(),
-            case_sensitive: false,
-        };
-        let result = evaluate_string("2024-01-15_report", &cond).unwrap();
-        assert!(result.matched);
-        assert_eq!(result.captures.get("1"), Some(&"2024".to_string()));
-        assert_eq!(result.captures.get("2"), Some(&"01".to_string()));
-        assert_eq!(result.captures.get("3"), Some(&"15".to_string()));
-        assert_eq!(result.captures.get("4"), Some(&"report".to_string()));
-    }
-
-    #[test]
-    fn regex_optional_capture_group() {
-        let cond = StringCondition {
-            operator: StringOperator::Matches,
-            value: r"file_(\d+)?\.txt".to_string(),
-            case_sensitive: false,
-        };
-        // With number
-        let result1 = evaluate_string("file_123.txt", &cond).unwrap();
-        assert!(result1.matched);
-        assert_eq!(result1.captures.get("1"), Some(&"123".to_string()));
-
-        // Without number - capture group is None
-        let result2 = evaluate_string("file_.txt", &cond).unwrap();
-        assert!(result2.matched);
-        // Optional group that didn't match won't be in captures
-        assert!(result2.captures.get("1").is_none());
-    }
-
     // --- Integration Test: evaluate_conditions ---
 
     #[test]
@@ -2071,6 +3036,17 @@ mod tests {
             },
             actions: vec![],
             position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -2109,6 +3085,17 @@ mod tests {
             },
             actions: vec![],
             position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -2167,6 +3154,17 @@ mod tests {
             // The executor has its own comprehensive test suite
             actions: vec![],
             position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -2222,6 +3220,17 @@ mod tests {
             },
             actions: vec![],
             position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -2244,6 +3253,17 @@ mod tests {
             },
             actions: vec![],
             position: 1,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -2268,6 +3288,7 @@ mod tests {
             status: crate::core::executor::ActionResultStatus::Success,
             details: None,
             error: None,
+            error_kind: None,
         }];
         assert!(super::should_stop_processing(&rule1, &outcomes));
 
@@ -2307,6 +3328,17 @@ mod tests {
             },
             actions: vec![],
             position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -2354,6 +3386,17 @@ mod tests {
             },
             actions: vec![],
             position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -2401,6 +3444,17 @@ mod tests {
             },
             actions: vec![],
             position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
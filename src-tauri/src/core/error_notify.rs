@@ -0,0 +1,597 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use filedispatch_core::file_info::FileInfo;
+use lru::LruCache;
+use serde::Deserialize;
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+use uuid::Uuid;
+
+use crate::core::dispatch::ExecuteFn;
+use crate::core::engine::log_outcomes;
+use crate::core::executor::{ActionOutcome, ActionResultStatus, ActionScope};
+use crate::core::match_metadata::MatchMetadata;
+use crate::models::{Action, ActionType, ErrorNotifyMode, Rule};
+use crate::storage::database::Database;
+use crate::storage::rule_repo::RuleRepository;
+use crate::storage::write_set::WriteSet;
+
+/// How long `FirstPerRule` stays quiet after notifying about a rule.
+const DEDUP_WINDOW_MINUTES: i64 = 60;
+
+/// Failed actions kept around so a notification button can retry them.
+const FAILURE_CACHE_CAPACITY: usize = 200;
+
+/// Notification action type registered by the frontend on platforms that
+/// support notification buttons.
+pub const ERROR_ACTION_TYPE_ID: &str = "rule-error";
+
+/// What a notification button asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorNotificationAction {
+    Retry,
+    OpenLog,
+    DisableRule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoticeKind {
+    /// An action failed on a file; the notice can retry it.
+    ActionFailed,
+    /// A rule's actions point at something missing, so it will fail once it
+    /// matches; there's no file and nothing to retry.
+    BrokenReference,
+    /// The daily digest; not an error at all, but shown the same way.
+    Digest,
+}
+
+/// One failure, as shown to the user.
+#[derive(Debug, Clone)]
+pub struct ErrorNotice {
+    pub kind: NoticeKind,
+    pub failure_id: String,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub file_name: String,
+    pub message: String,
+    /// Failures of the same rule held back since its previous notification.
+    pub suppressed: u32,
+}
+
+impl ErrorNotice {
+    pub fn title(&self) -> String {
+        match self.kind {
+            NoticeKind::ActionFailed => format!("{} failed", self.rule_name),
+            NoticeKind::BrokenReference => format!("{} can't run", self.rule_name),
+            NoticeKind::Digest => "Daily digest".to_string(),
+        }
+    }
+
+    pub fn body(&self) -> String {
+        if self.kind != NoticeKind::ActionFailed {
+            return self.message.clone();
+        }
+        let mut body = format!("{}: {}", self.file_name, self.message);
+        if self.suppressed > 0 {
+            let plural = if self.suppressed == 1 { "" } else { "s" };
+            body.push_str(&format!(
+                " (+{} more failure{} in the last hour)",
+                self.suppressed, plural
+            ));
+        }
+        body
+    }
+}
+
+/// Shows error notices. Swapped out in tests.
+pub trait Notifier: Send + Sync {
+    fn show(&self, notice: &ErrorNotice) -> Result<()>;
+}
+
+/// Platform notifications through tauri_plugin_notification.
+pub struct TauriNotifier {
+    app_handle: AppHandle,
+}
+
+impl TauriNotifier {
+    pub fn new(app_handle: AppHandle) -> Self {
+        Self { app_handle }
+    }
+}
+
+impl Notifier for TauriNotifier {
+    fn show(&self, notice: &ErrorNotice) -> Result<()> {
+        let builder = self
+            .app_handle
+            .notification()
+            .builder()
+            .title(notice.title())
+            .body(notice.body());
+        if notice.kind != NoticeKind::ActionFailed {
+            builder.show()?;
+            return Ok(());
+        }
+        // Desktop backends ignore the action type, which leaves a plain
+        // notification without buttons.
+        builder
+            .action_type_id(ERROR_ACTION_TYPE_ID)
+            .extra("failureId", &notice.failure_id)
+            .extra("ruleId", &notice.rule_id)
+            .show()?;
+        Ok(())
+    }
+}
+
+/// Decides which failures are worth a notification.
+#[derive(Debug, Default)]
+pub struct ErrorPolicy {
+    windows: HashMap<String, RuleWindow>,
+}
+
+#[derive(Debug)]
+struct RuleWindow {
+    notified_at: DateTime<Utc>,
+    suppressed: u32,
+}
+
+impl ErrorPolicy {
+    /// Returns `Some(suppressed)` when this failure should notify, where
+    /// `suppressed` counts the rule's failures held back since it last did.
+    pub fn admit(
+        &mut self,
+        mode: ErrorNotifyMode,
+        rule_id: &str,
+        now: DateTime<Utc>,
+    ) -> Option<u32> {
+        match mode {
+            ErrorNotifyMode::Off => None,
+            ErrorNotifyMode::All => Some(0),
+            ErrorNotifyMode::FirstPerRule => {
+                let window = Duration::minutes(DEDUP_WINDOW_MINUTES);
+                match self.windows.get_mut(rule_id) {
+                    Some(open) if now - open.notified_at < window => {
+                        open.suppressed += 1;
+                        None
+                    }
+                    Some(expired) => {
+                        let suppressed = expired.suppressed;
+                        *expired = RuleWindow {
+                            notified_at: now,
+                            suppressed: 0,
+                        };
+                        Some(suppressed)
+                    }
+                    None => {
+                        self.windows.insert(
+                            rule_id.to_string(),
+                            RuleWindow {
+                                notified_at: now,
+                                suppressed: 0,
+                            },
+                        );
+                        Some(0)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Everything needed to run a failed action again on its own.
+#[derive(Debug, Clone)]
+pub struct FailedAction {
+    pub rule_id: String,
+    pub action: Action,
+    /// Where the file was when the action ran, after any earlier moves.
+    pub path: PathBuf,
+    pub captures: HashMap<String, String>,
+    /// The original run's scope, so a retry reuses its idempotency key.
+    pub scope: ActionScope,
+}
+
+/// Turns failed outcomes into platform notifications and remembers the failed
+/// actions so they can be retried from one.
+pub struct ErrorNotifier {
+    notifier: Box<dyn Notifier>,
+    policy: Mutex<ErrorPolicy>,
+    failures: Mutex<LruCache<String, FailedAction>>,
+}
+
+impl ErrorNotifier {
+    pub fn new(notifier: Box<dyn Notifier>) -> Self {
+        Self {
+            notifier,
+            policy: Mutex::new(ErrorPolicy::default()),
+            failures: Mutex::new(LruCache::new(
+                NonZeroUsize::new(FAILURE_CACHE_CAPACITY).unwrap(),
+            )),
+        }
+    }
+
+    /// Notifies about the first failed outcome of a rule run, if `mode` lets
+    /// it through. Returns the id a retry would use.
+    pub fn report(
+        &self,
+        mode: ErrorNotifyMode,
+        rule: &Rule,
+        info: &FileInfo,
+        captures: &HashMap<String, String>,
+        outcomes: &[ActionOutcome],
+        now: DateTime<Utc>,
+    ) -> Option<String> {
+        // The executor stops at the first error, so outcomes line up with
+        // the rule's actions.
+        let index = outcomes
+            .iter()
+            .position(|outcome| outcome.status == ActionResultStatus::Error)?;
+        let suppressed = self.policy.lock().ok()?.admit(mode, &rule.id, now)?;
+
+        let failure_id = Uuid::new_v4().to_string();
+        if let Some(action) = rule.actions.get(index) {
+            if let Ok(mut failures) = self.failures.lock() {
+                failures.put(
+                    failure_id.clone(),
+                    FailedAction {
+                        rule_id: rule.id.clone(),
+                        action: action.clone(),
+                        path: path_before(&outcomes[..index], &info.path),
+                        captures: captures.clone(),
+                        scope: ActionScope::new(&rule.id, info).starting_at(index),
+                    },
+                );
+            }
+        }
+        let notice = ErrorNotice {
+            kind: NoticeKind::ActionFailed,
+            failure_id: failure_id.clone(),
+            rule_id: rule.id.clone(),
+            rule_name: rule.name.clone(),
+            file_name: info.full_name.clone(),
+            message: outcomes[index]
+                .error
+                .clone()
+                .unwrap_or_else(|| "Action failed".to_string()),
+            suppressed,
+        };
+        if let Err(err) = self.notifier.show(&notice) {
+            eprintln!("Failed to show error notification: {err}");
+        }
+        Some(failure_id)
+    }
+
+    /// Notifies that `rule` now points at something missing, if `mode` lets
+    /// it through. Shares the rule's window with its failures, since those
+    /// are what a broken reference leads to.
+    pub fn report_broken_references(
+        &self,
+        mode: ErrorNotifyMode,
+        rule: &Rule,
+        warnings: &[String],
+        now: DateTime<Utc>,
+    ) -> bool {
+        let Some(first) = warnings.first() else {
+            return false;
+        };
+        let Some(suppressed) = self
+            .policy
+            .lock()
+            .ok()
+            .and_then(|mut policy| policy.admit(mode, &rule.id, now))
+        else {
+            return false;
+        };
+        let mut message = first.clone();
+        if warnings.len() > 1 {
+            message.push_str(&format!(" (+{} more)", warnings.len() - 1));
+        }
+        let notice = ErrorNotice {
+            kind: NoticeKind::BrokenReference,
+            failure_id: String::new(),
+            rule_id: rule.id.clone(),
+            rule_name: rule.name.clone(),
+            file_name: String::new(),
+            message,
+            suppressed,
+        };
+        if let Err(err) = self.notifier.show(&notice) {
+            eprintln!("Failed to show error notification: {err}");
+        }
+        true
+    }
+
+    /// Shows the daily digest. It isn't held back by the error policy; the
+    /// caller decides whether a day is worth one.
+    pub fn report_digest(&self, message: String) {
+        let notice = ErrorNotice {
+            kind: NoticeKind::Digest,
+            failure_id: String::new(),
+            rule_id: String::new(),
+            rule_name: String::new(),
+            file_name: String::new(),
+            message,
+            suppressed: 0,
+        };
+        if let Err(err) = self.notifier.show(&notice) {
+            eprintln!("Failed to show digest notification: {err}");
+        }
+    }
+
+    pub fn failure(&self, failure_id: &str) -> Option<FailedAction> {
+        self.failures.lock().ok()?.peek(failure_id).cloned()
+    }
+
+    /// Runs a failed action again against the file it failed on and logs the
+    /// result under its rule. A successful retry can't be repeated.
+    pub fn retry(
+        &self,
+        db: &Database,
+        failure_id: &str,
+        execute: &ExecuteFn,
+    ) -> Result<ActionOutcome, String> {
+        let failed = self
+            .failure(failure_id)
+            .ok_or_else(|| "This failure can no longer be retried".to_string())?;
+        let rule = RuleRepository::new(db.clone())
+            .get(&failed.rule_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Rule no longer exists".to_string())?;
+        let info = FileInfo::from_path(&failed.path).map_err(|e| e.to_string())?;
+
+        let outcomes = execute(
+            std::slice::from_ref(&failed.action),
+            &info,
+            &failed.captures,
+            &failed.scope,
+        );
+        let mut writes = WriteSet::default();
+        log_outcomes(&mut writes, &rule, &info, &outcomes, &MatchMetadata::new(), None);
+        writes.commit(db).map_err(|e| e.to_string())?;
+        let outcome = outcomes
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Action produced no result".to_string())?;
+        if outcome.status != ActionResultStatus::Error {
+            if let Ok(mut failures) = self.failures.lock() {
+                failures.pop(failure_id);
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+/// Mirrors how the executor follows a file through earlier actions.
+fn path_before(outcomes: &[ActionOutcome], original: &Path) -> PathBuf {
+    let mut current = original.to_path_buf();
+    for outcome in outcomes {
+        let Some(dest) = outcome
+            .details
+            .as_ref()
+            .and_then(|details| details.destination_path.as_ref())
+        else {
+            continue;
+        };
+        if matches!(
+            outcome.action_type,
+            ActionType::Move | ActionType::Rename | ActionType::SortIntoSubfolder
+        ) {
+            current = PathBuf::from(dest);
+        }
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ConditionGroup, LogStatus, MatchType, ScriptAction};
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::log_repo::LogRepository;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    struct RecordingNotifier {
+        shown: Arc<Mutex<Vec<ErrorNotice>>>,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn show(&self, notice: &ErrorNotice) -> Result<()> {
+            self.shown.lock().unwrap().push(notice.clone());
+            Ok(())
+        }
+    }
+
+    fn outcome(status: ActionResultStatus, error: Option<&str>) -> ActionOutcome {
+        ActionOutcome {
+            action_type: ActionType::RunScript,
+            status,
+            details: None,
+            error: error.map(str::to_string),
+            error_kind: None,
+        }
+    }
+
+    #[test]
+    fn first_per_rule_counts_suppressed_failures() {
+        let mut policy = ErrorPolicy::default();
+        let start = Utc::now();
+        let mode = ErrorNotifyMode::FirstPerRule;
+
+        assert_eq!(policy.admit(mode, "a", start), Some(0));
+        for minute in [1, 20, 59] {
+            assert_eq!(policy.admit(mode, "a", start + Duration::minutes(minute)), None);
+        }
+        // Another rule has its own window.
+        assert_eq!(policy.admit(mode, "b", start + Duration::minutes(5)), Some(0));
+        assert_eq!(policy.admit(mode, "a", start + Duration::minutes(61)), Some(3));
+        assert_eq!(policy.admit(mode, "a", start + Duration::minutes(62)), None);
+
+        assert_eq!(policy.admit(ErrorNotifyMode::Off, "c", start), None);
+        assert_eq!(policy.admit(ErrorNotifyMode::All, "a", start), Some(0));
+        assert_eq!(policy.admit(ErrorNotifyMode::All, "a", start), Some(0));
+    }
+
+    #[test]
+    fn notice_mentions_held_back_failures() {
+        let shown = Arc::new(Mutex::new(Vec::new()));
+        let notifier = ErrorNotifier::new(Box::new(RecordingNotifier {
+            shown: shown.clone(),
+        }));
+        let rule = Rule {
+            id: "rule-1".to_string(),
+            folder_id: "folder".to_string(),
+            name: "Upload".to_string(),
+            enabled: true,
+            stop_processing: false,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![],
+            },
+            actions: vec![],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        fs::write(&path, b"pdf").unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+        let failed = [outcome(ActionResultStatus::Error, Some("Permission denied"))];
+        let start = Utc::now();
+        let mode = ErrorNotifyMode::FirstPerRule;
+
+        assert!(notifier
+            .report(mode, &rule, &info, &HashMap::new(), &[], start)
+            .is_none());
+        let captures = HashMap::new();
+        for minute in [0, 10, 20, 90] {
+            let at = start + Duration::minutes(minute);
+            notifier.report(mode, &rule, &info, &captures, &failed, at);
+        }
+
+        let shown = shown.lock().unwrap();
+        assert_eq!(shown.len(), 2);
+        assert_eq!(shown[0].title(), "Upload failed");
+        assert_eq!(shown[0].body(), "report.pdf: Permission denied");
+        assert_eq!(
+            shown[1].body(),
+            "report.pdf: Permission denied (+2 more failures in the last hour)"
+        );
+    }
+
+    #[test]
+    fn retry_reruns_only_the_failed_action() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Inbox")
+            .unwrap();
+        let script = |command: &str| {
+            Action::RunScript(ScriptAction {
+                command: command.to_string(),
+            })
+        };
+        let rule = RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id,
+                name: "Upload".to_string(),
+                enabled: true,
+                stop_processing: false,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![],
+                },
+                actions: vec![script("prepare"), script("upload")],
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap();
+        let path = dir.path().join("report.pdf");
+        fs::write(&path, b"pdf").unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+
+        // "upload" fails the first time it runs and succeeds afterwards.
+        let uploads = AtomicUsize::new(0);
+        let ran = Mutex::new(Vec::new());
+        let execute = |actions: &[Action],
+                       _: &FileInfo,
+                       _: &HashMap<String, String>,
+                       scope: &ActionScope| {
+            let mut outcomes = Vec::new();
+            for (offset, action) in actions.iter().enumerate() {
+                let Action::RunScript(script) = action else {
+                    continue;
+                };
+                ran.lock()
+                    .unwrap()
+                    .push((script.command.clone(), scope.idempotency_key(offset)));
+                if script.command == "upload" && uploads.fetch_add(1, Ordering::SeqCst) == 0 {
+                    outcomes.push(outcome(ActionResultStatus::Error, Some("Server unavailable")));
+                    break;
+                }
+                outcomes.push(outcome(ActionResultStatus::Success, None));
+            }
+            outcomes
+        };
+
+        let shown = Arc::new(Mutex::new(Vec::new()));
+        let notifier = ErrorNotifier::new(Box::new(RecordingNotifier {
+            shown: shown.clone(),
+        }));
+        let scope = ActionScope::new(&rule.id, &info);
+        let outcomes = execute(&rule.actions, &info, &HashMap::new(), &scope);
+        let failure_id = notifier
+            .report(ErrorNotifyMode::All, &rule, &info, &HashMap::new(), &outcomes, Utc::now())
+            .unwrap();
+        assert_eq!(shown.lock().unwrap()[0].failure_id, failure_id);
+
+        let retried = notifier.retry(&db, &failure_id, &execute).unwrap();
+        assert_eq!(retried.status, ActionResultStatus::Success);
+        let ran = ran.lock().unwrap().clone();
+        let commands: Vec<&str> = ran.iter().map(|(command, _)| command.as_str()).collect();
+        assert_eq!(commands, vec!["prepare", "upload", "upload"]);
+        // The retry is the same logical action, so the receiver can dedupe it.
+        assert_eq!(ran[2].1, ran[1].1);
+        assert_ne!(ran[1].1, ran[0].1);
+        let logs = LogRepository::new(db.clone()).list(10, 0).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].status, LogStatus::Success);
+        assert_eq!(logs[0].rule_id.as_deref(), Some(rule.id.as_str()));
+
+        // Succeeded retries are forgotten.
+        assert!(notifier.retry(&db, &failure_id, &execute).is_err());
+    }
+}
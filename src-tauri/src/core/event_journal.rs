@@ -0,0 +1,396 @@
+//! An optional journal of every file event the engine receives, with what
+//! became of it, for answering "did the watcher even see that file?".
+//! Entries are handed to a writer thread over a bounded channel so the
+//! engine never waits on the database; when the writer falls behind,
+//! entries are dropped and counted instead.
+
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::Result;
+use chrono::Utc;
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+
+use crate::core::watcher::{FileEvent, FileEventKind};
+use crate::models::{EventDisposition, JournalEntry, JournalQuery, Settings};
+use crate::storage::database::Database;
+use crate::storage::journal_repo::{JournalRepository, JournalWrite};
+
+/// Writes waiting for the writer thread before new ones are dropped.
+const QUEUE_CAPACITY: usize = 4096;
+
+/// Writes applied in one transaction at most.
+const MAX_BATCH: usize = 512;
+
+enum Message {
+    Write(JournalWrite),
+    /// Answered once every write queued before it is stored.
+    Flush(Sender<()>),
+}
+
+pub struct EventJournal {
+    tx: Sender<Message>,
+    settings: Arc<Mutex<Settings>>,
+    next_id: AtomicI64,
+    dropped: AtomicU64,
+}
+
+impl EventJournal {
+    /// Starts the writer thread. Ids continue from the stored journal.
+    pub fn new(db: Database, settings: Arc<Mutex<Settings>>) -> Self {
+        let repo = JournalRepository::new(db);
+        let next_id = repo.max_id().unwrap_or(0) + 1;
+        let (tx, rx) = bounded(QUEUE_CAPACITY);
+        let writer_settings = settings.clone();
+        thread::spawn(move || write_loop(repo, rx, writer_settings));
+        Self {
+            tx,
+            settings,
+            next_id: AtomicI64::new(next_id),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Journals `event` if the journal is turned on, returning the id its
+    /// disposition is settled under.
+    pub fn record(&self, event: &FileEvent) -> Option<i64> {
+        let enabled = self
+            .settings
+            .lock()
+            .map(|s| s.event_journal_enabled)
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let entry = JournalEntry {
+            id,
+            received_at: Utc::now(),
+            path: event.path.to_string_lossy().to_string(),
+            kind: kind_name(&event.kind).to_string(),
+            folder_id: event.folder_id.clone(),
+            disposition: None,
+            detail: None,
+            active_app: event.active_app.clone(),
+        };
+        self.send(JournalWrite::Append(entry)).then_some(id)
+    }
+
+    pub fn settle(&self, id: i64, disposition: EventDisposition, detail: Option<String>) {
+        self.send(JournalWrite::Settle {
+            id,
+            disposition,
+            detail,
+        });
+    }
+
+    /// Waits until everything journaled so far is stored.
+    pub fn flush(&self) {
+        let (done_tx, done_rx) = bounded(1);
+        if self.tx.send(Message::Flush(done_tx)).is_ok() {
+            let _ = done_rx.recv();
+        }
+    }
+
+    /// Stored entries matching `query`, newest first, after a flush.
+    pub fn query(&self, db: &Database, query: &JournalQuery) -> Result<Vec<JournalEntry>> {
+        self.flush();
+        JournalRepository::new(db.clone()).query(query)
+    }
+
+    /// Entries not journaled because the writer fell behind.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn send(&self, write: JournalWrite) -> bool {
+        match self.tx.try_send(Message::Write(write)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+}
+
+/// What became of an event, with the reason for one dropped over a problem
+/// with its file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Settled {
+    pub disposition: EventDisposition,
+    pub detail: Option<String>,
+}
+
+impl From<EventDisposition> for Settled {
+    fn from(disposition: EventDisposition) -> Self {
+        Self {
+            disposition,
+            detail: None,
+        }
+    }
+}
+
+/// Journals `event` around `process`, settling it with what `process`
+/// returns, or as an error.
+pub(crate) fn journaled(
+    journal: Option<&EventJournal>,
+    event: &FileEvent,
+    process: impl FnOnce(&FileEvent) -> Result<Settled>,
+) -> Result<Settled> {
+    let id = journal.and_then(|journal| journal.record(event));
+    let result = process(event);
+    if let (Some(journal), Some(id)) = (journal, id) {
+        match &result {
+            Ok(settled) => journal.settle(id, settled.disposition, settled.detail.clone()),
+            Err(err) => journal.settle(id, EventDisposition::Error, Some(err.to_string())),
+        }
+    }
+    result
+}
+
+/// Renders `entries` as one JSON object per line, oldest first, for
+/// attaching to a bug report.
+pub(crate) fn to_jsonl(entries: &[JournalEntry]) -> Result<String> {
+    let mut out = String::new();
+    for entry in entries.iter().rev() {
+        out.push_str(&serde_json::to_string(entry)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn kind_name(kind: &FileEventKind) -> &'static str {
+    match kind {
+        FileEventKind::Created => "created",
+        FileEventKind::Modified => "modified",
+        FileEventKind::Renamed => "renamed",
+        FileEventKind::Deleted => "deleted",
+    }
+}
+
+fn write_loop(repo: JournalRepository, rx: Receiver<Message>, settings: Arc<Mutex<Settings>>) {
+    while let Ok(first) = rx.recv() {
+        let mut writes = Vec::new();
+        let mut flushes = Vec::new();
+        for message in std::iter::once(first).chain(rx.try_iter().take(MAX_BATCH - 1)) {
+            match message {
+                Message::Write(write) => writes.push(write),
+                Message::Flush(done) => flushes.push(done),
+            }
+        }
+        if !writes.is_empty() {
+            let max_entries = settings
+                .lock()
+                .map(|s| s.event_journal_max_entries)
+                .unwrap_or(0);
+            if let Err(err) = repo.apply(&writes, max_entries.max(1)) {
+                eprintln!("Event journal write failed: {err}");
+            }
+        }
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    use chrono::Duration as ChronoDuration;
+    use lru::LruCache;
+    use tempfile::tempdir;
+
+    use crate::core::engine::debounced;
+    use crate::models::{ActiveApp, EventSource};
+
+    fn journal(max_entries: u32) -> (tempfile::TempDir, Database, EventJournal) {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let settings = Settings {
+            event_journal_enabled: true,
+            event_journal_max_entries: max_entries,
+            ..Settings::default()
+        };
+        let journal = EventJournal::new(db.clone(), Arc::new(Mutex::new(settings)));
+        (dir, db, journal)
+    }
+
+    fn event(path: &str) -> FileEvent {
+        FileEvent {
+            path: PathBuf::from(path),
+            folder_id: "folder-1".to_string(),
+            kind: FileEventKind::Created,
+            rule_id: None,
+            arrival: None,
+            active_app: None,
+            source: EventSource::Live,
+        }
+    }
+
+    #[test]
+    fn records_debounced_and_processed_dispositions() {
+        let (_dir, db, journal) = journal(100);
+        let last_seen = Mutex::new(LruCache::new(std::num::NonZeroUsize::new(8).unwrap()));
+        let start = Instant::now();
+        let arrivals = [
+            ("/in/report.pdf", Duration::ZERO),
+            ("/in/report.pdf", Duration::from_millis(100)),
+            ("/in/other.pdf", Duration::from_millis(150)),
+            ("/in/report.pdf", Duration::from_millis(900)),
+        ];
+        for (path, offset) in arrivals {
+            journaled(Some(&journal), &event(path), |event| {
+                let disposition = if debounced(&last_seen, &event.path, start + offset, 500) {
+                    EventDisposition::Debounced
+                } else {
+                    EventDisposition::Processed
+                };
+                Ok(disposition.into())
+            })
+            .unwrap();
+        }
+        journaled(Some(&journal), &event("/in/pipe"), |_| {
+            Ok(Settled {
+                disposition: EventDisposition::Ignored,
+                detail: Some("Not a regular file (FIFO)".to_string()),
+            })
+        })
+        .unwrap();
+        let failed = journaled(Some(&journal), &event("/in/broken.pdf"), |_| {
+            Err(anyhow::anyhow!("disk on fire"))
+        });
+        assert!(failed.is_err());
+
+        let entries = journal.query(&db, &JournalQuery::default()).unwrap();
+        let seen: Vec<_> = entries
+            .iter()
+            .rev()
+            .map(|entry| (entry.path.as_str(), entry.disposition))
+            .collect();
+        assert_eq!(
+            seen,
+            vec![
+                ("/in/report.pdf", Some(EventDisposition::Processed)),
+                ("/in/report.pdf", Some(EventDisposition::Debounced)),
+                ("/in/other.pdf", Some(EventDisposition::Processed)),
+                ("/in/report.pdf", Some(EventDisposition::Processed)),
+                ("/in/pipe", Some(EventDisposition::Ignored)),
+                ("/in/broken.pdf", Some(EventDisposition::Error)),
+            ]
+        );
+        assert_eq!(entries[1].detail.as_deref(), Some("Not a regular file (FIFO)"));
+        assert_eq!(entries[0].detail.as_deref(), Some("disk on fire"));
+        assert_eq!(entries[0].kind, "created");
+    }
+
+    #[test]
+    fn records_the_app_in_front() {
+        let (_dir, db, journal) = journal(100);
+        let freecad = ActiveApp::Known {
+            name: "FreeCAD".to_string(),
+            identifier: Some("/usr/bin/freecad".to_string()),
+        };
+        let wayland = ActiveApp::Unavailable {
+            reason: "Wayland doesn't say".to_string(),
+        };
+        for active_app in [Some(freecad.clone()), Some(wayland.clone()), None] {
+            journal
+                .record(&FileEvent {
+                    active_app,
+                    ..event("/out/bracket.step")
+                })
+                .unwrap();
+        }
+
+        let entries = journal.query(&db, &JournalQuery::default()).unwrap();
+        let apps: Vec<_> = entries.iter().rev().map(|entry| entry.active_app.clone()).collect();
+        assert_eq!(apps, [Some(freecad), Some(wayland), None]);
+    }
+
+    #[test]
+    fn keeps_only_the_newest_entries_at_the_cap() {
+        let (_dir, db, journal) = journal(3);
+        for index in 0..5 {
+            journal
+                .record(&event(&format!("/in/file{index}.txt")))
+                .unwrap();
+        }
+
+        let entries = journal.query(&db, &JournalQuery::default()).unwrap();
+        let paths: Vec<_> = entries.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["/in/file4.txt", "/in/file3.txt", "/in/file2.txt"]
+        );
+        assert!(entries.iter().all(|entry| entry.disposition.is_none()));
+    }
+
+    #[test]
+    fn query_filters_by_path_and_time() {
+        let (_dir, db, journal) = journal(100);
+        let now = Utc::now();
+        let writes: Vec<_> = [
+            (1, "/in/Invoice-march.pdf", now - ChronoDuration::hours(3)),
+            (
+                2,
+                "/in/invoice-april.pdf",
+                now - ChronoDuration::minutes(30),
+            ),
+            (3, "/in/photo.jpg", now - ChronoDuration::minutes(20)),
+            (4, "/in/invoice-may.pdf", now),
+        ]
+        .into_iter()
+        .map(|(id, path, received_at)| {
+            JournalWrite::Append(JournalEntry {
+                id,
+                received_at,
+                path: path.to_string(),
+                kind: "created".to_string(),
+                folder_id: "folder-1".to_string(),
+                disposition: Some(EventDisposition::Processed),
+                detail: None,
+                active_app: None,
+            })
+        })
+        .collect();
+        JournalRepository::new(db.clone())
+            .apply(&writes, 100)
+            .unwrap();
+
+        let query = |path: Option<&str>, since, until| {
+            let query = JournalQuery {
+                path_contains: path.map(str::to_string),
+                since,
+                until,
+                limit: None,
+            };
+            let entries = journal.query(&db, &query).unwrap();
+            entries
+                .into_iter()
+                .map(|entry| entry.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(query(Some("invoice"), None, None), vec![4, 2, 1]);
+        let hour_ago = Some(now - ChronoDuration::hours(1));
+        assert_eq!(query(None, hour_ago, None), vec![4, 3, 2]);
+        let ten_minutes_ago = Some(now - ChronoDuration::minutes(10));
+        assert_eq!(query(Some("INVOICE"), hour_ago, ten_minutes_ago), vec![2]);
+    }
+
+    #[test]
+    fn records_nothing_while_turned_off() {
+        let (_dir, db, journal) = journal(100);
+        journal.settings.lock().unwrap().event_journal_enabled = false;
+
+        assert_eq!(journal.record(&event("/in/file.txt")), None);
+        assert!(journal
+            .query(&db, &JournalQuery::default())
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,349 @@
+//! Where the engine sends each processed event's records. They're committed
+//! as soon as the event is done, or, with `db_batch_commit_ms` set, held
+//! with the records of the events after it and committed together once the
+//! oldest has waited that long. On a spinning disk each commit costs a sync,
+//! so a burst of small files goes much faster in batches.
+//!
+//! Records are handed over only once their event's actions have finished,
+//! and batches are committed in the order the events finished. An event for
+//! a file with records still waiting commits them first, so it sees what
+//! the events before it recorded. A crash loses at most the records waiting
+//! in the batch, never part of one event's.
+
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+
+use crate::models::DbWriteStats;
+use crate::storage::database::Database;
+use crate::storage::write_set::{commit_all, WriteSet};
+
+/// Commits the statistics are taken over.
+const STATS_WINDOW: Duration = Duration::from_secs(60);
+
+pub struct EventWriter {
+    db: Database,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    pending: Vec<WriteSet>,
+    pending_paths: HashSet<String>,
+    /// When the oldest waiting event was handed over.
+    oldest: Option<Instant>,
+    /// When each recent commit happened and how many events it carried.
+    commits: VecDeque<(Instant, usize)>,
+    total_commits: u64,
+}
+
+impl EventWriter {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Takes one event's records: committed now when `batch_ms` is 0,
+    /// otherwise added to the batch, which is committed once its oldest
+    /// event has waited `batch_ms`.
+    pub fn submit(&self, writes: WriteSet, batch_ms: u64, now: Instant) -> Result<()> {
+        if writes.is_empty() {
+            return self.flush_if_due(batch_ms, now);
+        }
+        let mut inner = self.lock()?;
+        inner
+            .pending_paths
+            .extend(writes.paths().map(str::to_string));
+        inner.pending.push(writes);
+        inner.oldest.get_or_insert(now);
+        if is_due(&inner, batch_ms, now) {
+            inner.commit(&self.db, now)?;
+        }
+        Ok(())
+    }
+
+    /// Commits the batch if its oldest event has waited `batch_ms`, or
+    /// straight away once batching is off.
+    pub fn flush_if_due(&self, batch_ms: u64, now: Instant) -> Result<()> {
+        let mut inner = self.lock()?;
+        if is_due(&inner, batch_ms, now) {
+            inner.commit(&self.db, now)?;
+        }
+        Ok(())
+    }
+
+    /// Commits the batch if it holds records about `path`, before an event
+    /// for it is processed.
+    pub fn flush_for(&self, path: &Path, now: Instant) -> Result<()> {
+        let mut inner = self.lock()?;
+        if inner
+            .pending_paths
+            .contains(path.to_string_lossy().as_ref())
+        {
+            inner.commit(&self.db, now)?;
+        }
+        Ok(())
+    }
+
+    /// Commits whatever is waiting.
+    pub fn flush(&self, now: Instant) -> Result<()> {
+        self.lock()?.commit(&self.db, now)
+    }
+
+    /// Commits per second and events per commit over the last minute.
+    pub fn stats(&self, now: Instant) -> DbWriteStats {
+        let Ok(mut inner) = self.inner.lock() else {
+            return DbWriteStats::default();
+        };
+        inner.forget_before(now);
+        let Some((first, _)) = inner.commits.front() else {
+            return DbWriteStats::default();
+        };
+        let commits = inner.commits.len() as f64;
+        let events: usize = inner.commits.iter().map(|(_, events)| events).sum();
+        // A window that started less than a second ago counts as a second.
+        let seconds = now.duration_since(*first).as_secs_f64().max(1.0);
+        DbWriteStats {
+            commits_per_second: commits / seconds,
+            average_batch_size: events as f64 / commits,
+        }
+    }
+
+    /// Commits made since the writer was created.
+    pub fn total_commits(&self) -> u64 {
+        self.inner
+            .lock()
+            .map(|inner| inner.total_commits)
+            .unwrap_or(0)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Inner>> {
+        self.inner
+            .lock()
+            .map_err(|_| anyhow!("Event writer lock poisoned"))
+    }
+}
+
+fn is_due(inner: &Inner, batch_ms: u64, now: Instant) -> bool {
+    inner.oldest.is_some_and(|oldest| {
+        batch_ms == 0 || now.duration_since(oldest) >= Duration::from_millis(batch_ms)
+    })
+}
+
+impl Inner {
+    /// Commits the batch in one transaction. If that fails, each event's
+    /// records are committed on their own so one bad event doesn't cost the
+    /// others theirs; the first error is returned.
+    fn commit(&mut self, db: &Database, now: Instant) -> Result<()> {
+        let pending = std::mem::take(&mut self.pending);
+        self.pending_paths.clear();
+        self.oldest = None;
+        if pending.is_empty() {
+            return Ok(());
+        }
+        if commit_all(db, &pending).is_ok() {
+            self.counted(now, pending.len());
+            return Ok(());
+        }
+        let mut first_error = None;
+        for writes in pending {
+            match writes.commit(db) {
+                Ok(()) => self.counted(now, 1),
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    fn counted(&mut self, now: Instant, events: usize) {
+        self.commits.push_back((now, events));
+        self.total_commits += 1;
+        self.forget_before(now);
+    }
+
+    fn forget_before(&mut self, now: Instant) {
+        while self
+            .commits
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > STATS_WINDOW)
+        {
+            self.commits.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use super::*;
+    use crate::models::{ConditionGroup, LogEntry, LogStatus, MatchType, Rule};
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::log_repo::LogRepository;
+    use crate::storage::match_repo::MatchRepository;
+    use crate::storage::rule_repo::RuleRepository;
+    use tempfile::{tempdir, TempDir};
+
+    fn setup() -> (TempDir, Database, String) {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create("/tmp/inbox", "Inbox")
+            .unwrap();
+        let rule = RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id,
+                name: "Archive".to_string(),
+                enabled: true,
+                stop_processing: false,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![],
+                },
+                actions: vec![],
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            })
+            .unwrap();
+        (dir, db, rule.id)
+    }
+
+    /// What processing `name` leaves: a log entry and a match recording `run`.
+    fn event(rule_id: &str, name: &str, run: &str) -> WriteSet {
+        let path = format!("/tmp/inbox/{name}");
+        let mut writes = WriteSet::default();
+        writes.log(LogEntry {
+            id: String::new(),
+            rule_id: None,
+            rule_name: None,
+            file_path: path.clone(),
+            action_type: "tag".to_string(),
+            action_detail: None,
+            status: LogStatus::Success,
+            error_message: None,
+            created_at: chrono::Utc::now(),
+        });
+        let metadata = BTreeMap::from([("run".to_string(), run.to_string())]);
+        writes.record_match(rule_id, &path, Some(name), &metadata, &[]);
+        writes
+    }
+
+    fn logged(db: &Database) -> usize {
+        LogRepository::new(db.clone())
+            .list(10_000, 0)
+            .unwrap()
+            .len()
+    }
+
+    #[test]
+    fn a_burst_takes_one_commit_per_event_or_far_fewer_in_batches() {
+        let start = Instant::now();
+        let (_dir, db, rule_id) = setup();
+        let writer = EventWriter::new(db.clone());
+        for i in 0..1000 {
+            let writes = event(&rule_id, &format!("{i}.txt"), "1");
+            writer.submit(writes, 0, start).unwrap();
+        }
+        assert_eq!(writer.total_commits(), 1000);
+        assert_eq!(logged(&db), 1000);
+
+        // One event finishes every millisecond; a batch waits 50.
+        let (_dir, db, rule_id) = setup();
+        let writer = EventWriter::new(db.clone());
+        for i in 0..1000u64 {
+            let writes = event(&rule_id, &format!("{i}.txt"), "1");
+            writer
+                .submit(writes, 50, start + Duration::from_millis(i))
+                .unwrap();
+        }
+        assert!(logged(&db) < 1000);
+        writer.flush(start + Duration::from_secs(1)).unwrap();
+        assert_eq!(logged(&db), 1000);
+        assert!(
+            writer.total_commits() <= 21,
+            "{} commits",
+            writer.total_commits()
+        );
+        let stats = writer.stats(start + Duration::from_secs(1));
+        assert!(stats.average_batch_size >= 45.0, "{stats:?}");
+        assert!(stats.commits_per_second > 0.0);
+    }
+
+    #[test]
+    fn batches_keep_their_order_and_are_committed_before_their_file_comes_back() {
+        let now = Instant::now();
+        let (_dir, db, rule_id) = setup();
+        let writer = EventWriter::new(db.clone());
+        let matches = MatchRepository::new(db.clone());
+        writer
+            .submit(event(&rule_id, "a.pdf", "1"), 60_000, now)
+            .unwrap();
+        writer
+            .submit(event(&rule_id, "a.pdf", "2"), 60_000, now)
+            .unwrap();
+        assert!(matches
+            .latest_for_hash(&rule_id, "a.pdf")
+            .unwrap()
+            .is_none());
+
+        writer
+            .flush_for(Path::new("/tmp/inbox/b.pdf"), now)
+            .unwrap();
+        assert_eq!(logged(&db), 0);
+        writer
+            .flush_for(Path::new("/tmp/inbox/a.pdf"), now)
+            .unwrap();
+        let latest = matches.latest_for_hash(&rule_id, "a.pdf").unwrap().unwrap();
+        assert_eq!(latest.metadata["run"], "2");
+        assert_eq!(writer.total_commits(), 1);
+
+        // An event that can't be written doesn't take the others with it.
+        writer
+            .submit(event(&rule_id, "c.pdf", "1"), 60_000, now)
+            .unwrap();
+        writer
+            .submit(event("deleted-rule", "d.pdf", "1"), 60_000, now)
+            .unwrap();
+        writer
+            .submit(event(&rule_id, "e.pdf", "1"), 60_000, now)
+            .unwrap();
+        assert!(writer.flush(now).is_err());
+        assert!(matches
+            .latest_for_hash(&rule_id, "c.pdf")
+            .unwrap()
+            .is_some());
+        assert!(matches
+            .latest_for_hash(&rule_id, "e.pdf")
+            .unwrap()
+            .is_some());
+        assert_eq!(logged(&db), 4);
+
+        // Turning batching off commits what was waiting.
+        writer
+            .submit(event(&rule_id, "f.pdf", "1"), 60_000, now)
+            .unwrap();
+        writer.flush_if_due(0, now).unwrap();
+        assert_eq!(logged(&db), 5);
+    }
+}
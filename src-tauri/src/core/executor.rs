@@ -1,24 +1,222 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::process::Command;
 use std::time::Duration;
 
-use tauri::{AppHandle, Manager};
+use chrono::Utc;
+use filedispatch_core::file_info::FileInfo;
+use filedispatch_core::patterns::PatternEngine;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_notification::NotificationExt;
 use tauri_plugin_opener::open_path;
 
+use crate::core::budget::BUDGET_EXHAUSTED_EVENT;
+use crate::core::companions;
+use crate::core::dedup::{EventActions, Performed, PlannedOperation};
+use crate::core::notify_sound::{focus_active, plan_notification, resolve_sound};
 use crate::core::ocr::OcrManager;
-use crate::core::content::make_pdf_searchable;
-use crate::core::patterns::PatternEngine;
+use crate::core::partition::{self, Partitioned, PARTITION_KEY};
+use crate::core::thumbnails::{ThumbnailCache, THUMBNAIL_KEY};
+use crate::core::unique_name::{self, NameSuffix};
+use crate::core::content::{make_pdf_searchable, PdfiumUnavailable};
 use crate::models::{
-    Action, ActionDetails, ActionType, ArchiveAction, ConflictResolution, DeleteAction,
-    MakePdfSearchableAction, OpenAction, OpenWithAction, PauseAction, Settings,
-    ShowInFileManagerAction, UnarchiveAction,
+    Action, ActionDetails, ActionType, ArchiveAction, ArchiveEncryptionMethod, AutoPartition,
+    BringCompanions,
+    BudgetExhausted,
+    ConflictResolution,
+    CreateFolderStructureAction, DeleteAction, DestinationPermissions, MakePdfSearchableAction,
+    NotifyAction, OpenAction, OpenWithAction, PauseAction, PendingApproval, PendingConflict,
+    QuarantineHandling, ReflinkMode, RetryExhausted, SetTagsAction, Settings,
+    ShowInFileManagerAction, TagMode, UnarchiveAction,
+};
+use crate::utils::archive::{
+    create_archive, ensure_archive_path, extract_archive, ExtractFilter,
+};
+use crate::utils::clone::copy_file;
+use crate::utils::file_mode;
+use crate::utils::file_lock::{is_lock_error, is_locked};
+use crate::utils::file_tags::{self, TagFormat};
+use crate::utils::permissions::{
+    check_access, first_denial, PermissionDenied, PermissionNeeded, ProtectedCategory,
 };
-use crate::utils::archive::{create_archive, ensure_archive_path, extract_archive};
-use crate::utils::file_info::FileInfo;
 use crate::utils::platform::expand_tilde;
+use crate::utils::quarantine;
+use crate::utils::secrets::resolve_password;
+use crate::utils::tempfiles::app_temp;
+use crate::utils::transient::is_transient;
+use crate::utils::volumes::offline_volume;
+use crate::utils::xattr::{self, Xattrs};
+
+/// Metadata key carrying the folder a `DispatchToFolder` outcome hands off to.
+pub(crate) const DISPATCH_TARGET_KEY: &str = "target_folder_id";
+
+/// Metadata key recording whether a copy was made as a copy-on-write clone.
+pub(crate) const REFLINKED_KEY: &str = "reflinked";
+
+/// Metadata key explaining why `destination_permissions` had no effect.
+pub(crate) const PERMISSIONS_NOTE_KEY: &str = "permissions_note";
+
+/// Metadata key on a companion's outcome naming the file it came along with.
+pub(crate) const COMPANION_OF_KEY: &str = "companion_of";
+
+/// Metadata key on a companion's outcome asking for it to be recorded as
+/// handled by every enabled rule.
+pub(crate) const COMPANION_RECORD_KEY: &str = "record_match";
+
+/// Metadata key counting the companions that came along with a file.
+pub(crate) const COMPANIONS_KEY: &str = "companions";
+
+/// Metadata keys counting the file entries an unarchive wrote and left out.
+pub(crate) const EXTRACTED_KEY: &str = "extracted";
+pub(crate) const SKIPPED_KEY: &str = "skipped";
+
+/// Metadata key listing the Finder tags a file has after Set Tags.
+pub(crate) const TAGS_KEY: &str = "tags";
+
+/// Metadata key naming how an archive was encrypted. The password never
+/// goes into an outcome.
+pub(crate) const ENCRYPTION_KEY: &str = "encryption";
+
+/// Metadata key giving the position in its rule of an action suspended on a
+/// conflict.
+pub(crate) const CONFLICT_INDEX_KEY: &str = "conflict_action_index";
+
+/// Metadata key on a simulated outcome naming how a taken destination would
+/// be resolved: `rename`, `replace`, `unique_suffix` or `ask`.
+pub(crate) const DRY_RUN_CONFLICT_KEY: &str = "dry_run_conflict";
+
+/// Metadata key on a simulated outcome giving the path it would remove.
+pub(crate) const DRY_RUN_REMOVES_KEY: &str = "dry_run_removes";
+
+/// Environment variable carrying a script action's idempotency key.
+pub(crate) const IDEMPOTENCY_ENV: &str = "FD_IDEMPOTENCY_KEY";
+
+/// The rule run a batch of actions belongs to. Actions with effects outside
+/// File Dispatch get a key from it that stays the same when the event is
+/// delivered again (a lock retry, a retry from a notification) so the other
+/// side can drop the repeat.
+///
+/// The event is identified by rule, path and the file's modified-time/size
+/// hash rather than the dispatch trace id, which is new on every delivery. A
+/// changed file is a new event and gets new keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionScope {
+    event: String,
+    first_index: usize,
+    settled: Option<SettledConflict>,
+    dedup: Option<Dedup>,
+}
+
+/// The event's operations so far, and the rule this batch belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Dedup {
+    actions: EventActions,
+    rule_name: String,
+}
+
+/// How the user settled the conflict a batch's first action was suspended
+/// on: where the file goes and what happens if something is still there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettledConflict {
+    pub destination: PathBuf,
+    pub policy: ConflictResolution,
+}
+
+impl ActionScope {
+    pub fn new(rule_id: &str, info: &FileInfo) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(rule_id.as_bytes());
+        hasher.update([0]);
+        hasher.update(info.path.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(info.hash.as_bytes());
+        let digest = hasher.finalize();
+        Self {
+            event: digest[..16].iter().map(|b| format!("{b:02x}")).collect(),
+            first_index: 0,
+            settled: None,
+            dedup: None,
+        }
+    }
+
+    /// The same batch, skipping operations earlier rules did for the event
+    /// and recording its own for the rules after it.
+    pub fn deduping(self, actions: &EventActions, rule_name: &str) -> Self {
+        Self {
+            dedup: Some(Dedup {
+                actions: actions.clone(),
+                rule_name: rule_name.to_string(),
+            }),
+            ..self
+        }
+    }
+
+    /// The same event, for a batch that starts at action `index` of the rule.
+    pub fn starting_at(&self, index: usize) -> Self {
+        Self {
+            event: self.event.clone(),
+            first_index: index,
+            settled: None,
+            dedup: None,
+        }
+    }
+
+    /// The same batch, with its first action settling the conflict it was
+    /// suspended on.
+    pub fn settling(self, settled: SettledConflict) -> Self {
+        Self {
+            settled: Some(settled),
+            ..self
+        }
+    }
+
+    /// How the conflict of the action at `offset` was settled, if it was.
+    pub fn settled(&self, offset: usize) -> Option<&SettledConflict> {
+        self.settled.as_ref().filter(|_| offset == 0)
+    }
+
+    /// Position in the rule of the action at `offset` within the batch.
+    pub fn index(&self, offset: usize) -> usize {
+        self.first_index + offset
+    }
+
+    /// Key for the action at `offset` within the batch.
+    pub fn idempotency_key(&self, offset: usize) -> String {
+        format!("{}-{}", self.event, self.first_index + offset)
+    }
+
+    /// What an earlier rule did for the event that `op` would repeat.
+    pub fn duplicate_of(&self, op: &PlannedOperation) -> Option<Performed> {
+        self.dedup.as_ref()?.actions.earlier(op)
+    }
+
+    /// Records `op`, done by the action at `offset`, for the rules after this
+    /// one. Only an operation that went through counts.
+    pub fn performed(&self, op: PlannedOperation, offset: usize, outcome: &ActionOutcome) {
+        let Some(dedup) = &self.dedup else {
+            return;
+        };
+        if outcome.status != ActionResultStatus::Success {
+            return;
+        }
+        let destination = outcome
+            .details
+            .as_ref()
+            .and_then(|details| details.destination_path.as_ref())
+            .map(PathBuf::from);
+        dedup.actions.record(
+            op,
+            Performed {
+                rule_name: dedup.rule_name.clone(),
+                action_index: self.index(offset),
+                destination,
+            },
+        );
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ActionOutcome {
@@ -26,6 +224,30 @@ pub struct ActionOutcome {
     pub status: ActionResultStatus,
     pub details: Option<ActionDetails>,
     pub error: Option<String>,
+    pub error_kind: Option<ActionErrorKind>,
+}
+
+/// Failure categories callers may react to (e.g. by retrying later).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionErrorKind {
+    /// The source file is held open by another process.
+    Locked,
+    /// The action needs PDFium to render pages and the library isn't available.
+    PdfiumUnavailable,
+    /// The source is an online-only cloud placeholder, so moving it would
+    /// relocate an empty stub.
+    Placeholder,
+    /// macOS hasn't granted access to the protected location the action writes to.
+    PermissionDenied(ProtectedCategory),
+    /// A failure that may clear up by itself, such as a network share that
+    /// dropped out or a full disk.
+    Transient,
+    /// The destination is on a removable drive or network share that isn't
+    /// mounted; holds its mount point.
+    VolumeOffline(PathBuf),
+    /// The destination exists and the action asks the user what to do;
+    /// holds the destination.
+    ConflictPending(PathBuf),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -33,11 +255,14 @@ pub enum ActionResultStatus {
     Success,
     Skipped,
     Error,
+    /// Worked out by a dry run; nothing was touched.
+    Simulated,
 }
 
 pub struct ActionExecutor {
     pattern_engine: PatternEngine,
-    app_handle: AppHandle,
+    /// `None` when running without the app, as a sandbox run does.
+    app_handle: Option<AppHandle>,
     settings: std::sync::Arc<std::sync::Mutex<Settings>>,
     ocr: std::sync::Arc<std::sync::Mutex<OcrManager>>,
 }
@@ -50,7 +275,23 @@ impl ActionExecutor {
     ) -> Self {
         Self {
             pattern_engine: PatternEngine::new(),
-            app_handle,
+            app_handle: Some(app_handle),
+            settings,
+            ocr,
+        }
+    }
+
+    /// An executor that runs without the app. Nothing reaches the UI, and the
+    /// actions that reach outside the files it works on are stood in for:
+    /// opening files and notifications are skipped, and deleting to the trash
+    /// removes the file outright.
+    pub fn detached(
+        settings: std::sync::Arc<std::sync::Mutex<Settings>>,
+        ocr: std::sync::Arc<std::sync::Mutex<OcrManager>>,
+    ) -> Self {
+        Self {
+            pattern_engine: PatternEngine::new(),
+            app_handle: None,
             settings,
             ocr,
         }
@@ -61,12 +302,14 @@ impl ActionExecutor {
         actions: &[Action],
         info: &FileInfo,
         captures: &HashMap<String, String>,
+        scope: &ActionScope,
     ) -> Vec<ActionOutcome> {
-        let dry_run = self
+        let (dry_run, default_timezone, dedupe) = self
             .settings
             .lock()
-            .map(|s| s.dry_run)
-            .unwrap_or(false);
+            .map(|s| (s.dry_run, s.default_timezone.clone(), s.dedupe_actions.clone()))
+            .unwrap_or_default();
+        self.pattern_engine.set_default_timezone(default_timezone.as_deref());
         if dry_run {
             return self.simulate_actions(actions, info, captures);
         }
@@ -74,81 +317,171 @@ impl ActionExecutor {
         let mut outcomes = Vec::new();
         let mut current_path = info.path.clone();
 
-        for action in actions {
-            let result = match action {
-                Action::Move(action) => self.execute_move(
-                    ActionType::Move,
-                    action.destination.as_str(),
-                    action.on_conflict.clone(),
-                    action.skip_duplicates,
-                    false,
-                    &current_path,
-                    info,
-                    captures,
-                ),
-                Action::Copy(action) => self.execute_copy(
-                    ActionType::Copy,
-                    action.destination.as_str(),
-                    action.on_conflict.clone(),
-                    action.skip_duplicates,
-                    false,
-                    &current_path,
-                    info,
-                    captures,
-                ),
-                Action::Rename(action) => self.execute_rename(
-                    action.pattern.as_str(),
-                    action.on_conflict.clone(),
-                    &current_path,
-                    info,
-                    captures,
-                ),
-                Action::SortIntoSubfolder(action) => self.execute_move(
-                    ActionType::SortIntoSubfolder,
-                    action.destination.as_str(),
-                    action.on_conflict.clone(),
-                    false,
-                    true,
-                    &current_path,
-                    info,
-                    captures,
-                ),
-                Action::Archive(action) => {
-                    self.execute_archive(action, &current_path, info, captures)
+        for (offset, action) in actions.iter().enumerate() {
+            let settled = scope.settled(offset);
+            let settled_action = settled.map(|settled| with_policy(action, &settled.policy));
+            let action = settled_action.as_ref().unwrap_or(action);
+            let planned = match (settled, &scope.dedup) {
+                (None, Some(_)) => {
+                    PlannedOperation::of(&self.pattern_engine, action, info, captures, &dedupe)
                 }
-                Action::Unarchive(action) => self.execute_unarchive(action, &current_path, info, captures),
-                Action::Delete(action) => {
-                    self.execute_delete(ActionType::Delete, action, &current_path)
+                _ => None,
+            };
+            if let Some((op, earlier)) = planned
+                .as_ref()
+                .and_then(|op| scope.duplicate_of(op).map(|earlier| (op, earlier)))
+            {
+                // The earlier rule already took the file there.
+                let moved = matches!(
+                    op,
+                    PlannedOperation::Move(_) | PlannedOperation::SortIntoSubfolder(_)
+                );
+                if let Some(dest) = earlier.destination.as_ref().filter(|_| moved) {
+                    current_path = dest.clone();
                 }
-                Action::DeletePermanently(action) => self.execute_delete(
-                    ActionType::DeletePermanently,
-                    &DeleteAction {
-                        permanent: true,
-                        ..action.clone()
+                outcomes.push(earlier.duplicate_outcome(op, &current_path));
+                continue;
+            }
+            let mut result = if let Some(settled) = settled {
+                self.execute_settled(action, settled, &current_path)
+            } else if let Some(outcome) = self.detached_refusal(action, &current_path) {
+                outcome
+            } else {
+                match action {
+                    Action::Move(action) => self.with_quarantine(
+                        ActionType::Move,
+                        action.quarantine,
+                        &current_path,
+                        || {
+                            apply_destination_permissions(
+                                self.execute_move(
+                                    ActionType::Move,
+                                    action.destination.as_str(),
+                                    action.on_conflict.clone(),
+                                    action.skip_duplicates,
+                                    false,
+                                    action.auto_partition.as_ref(),
+                                    &current_path,
+                                    info,
+                                    captures,
+                                ),
+                                &action.destination_permissions,
+                            )
+                        },
+                    ),
+                    Action::Copy(action) => self.with_quarantine(
+                        ActionType::Copy,
+                        action.quarantine,
+                        &current_path,
+                        || {
+                            apply_destination_permissions(
+                                self.execute_copy(
+                                    ActionType::Copy,
+                                    action.destination.as_str(),
+                                    action.on_conflict.clone(),
+                                    action.skip_duplicates,
+                                    action.use_reflink,
+                                    false,
+                                    action.auto_partition.as_ref(),
+                                    &current_path,
+                                    info,
+                                    captures,
+                                ),
+                                &action.destination_permissions,
+                            )
+                        },
+                    ),
+                    Action::Rename(action) => self.execute_rename(
+                        action.pattern.as_str(),
+                        action.on_conflict.clone(),
+                        &current_path,
+                        info,
+                        captures,
+                    ),
+                    Action::SortIntoSubfolder(action) => self.with_quarantine(
+                        ActionType::SortIntoSubfolder,
+                        action.quarantine,
+                        &current_path,
+                        || {
+                            apply_destination_permissions(
+                                self.execute_move(
+                                    ActionType::SortIntoSubfolder,
+                                    action.destination.as_str(),
+                                    action.on_conflict.clone(),
+                                    false,
+                                    true,
+                                    action.auto_partition.as_ref(),
+                                    &current_path,
+                                    info,
+                                    captures,
+                                ),
+                                &action.destination_permissions,
+                            )
+                        },
+                    ),
+                    Action::Archive(action) => {
+                        self.execute_archive(action, &current_path, info, captures)
+                    }
+                    Action::Unarchive(action) => {
+                        self.execute_unarchive(action, &current_path, info, captures)
+                    }
+                    Action::Delete(action) => {
+                        self.execute_delete(ActionType::Delete, action, &current_path)
+                    }
+                    Action::DeletePermanently(action) => self.execute_delete(
+                        ActionType::DeletePermanently,
+                        &DeleteAction {
+                            permanent: true,
+                            ..action.clone()
+                        },
+                        &current_path,
+                    ),
+                    Action::RunScript(action) => execute_script(
+                        &action.command,
+                        &current_path,
+                        &scope.idempotency_key(offset),
+                    ),
+                    Action::Notify(action) => self.execute_notify(action, info, captures),
+                    Action::Open(action) => self.execute_open(action, &current_path),
+                    Action::ShowInFileManager(action) => {
+                        self.execute_show_in_file_manager(action, &current_path)
+                    }
+                    Action::OpenWith(action) => self.execute_open_with(action, &current_path),
+                    Action::MakePdfSearchable(action) => {
+                        self.execute_make_pdf_searchable(action, &current_path)
+                    }
+                    Action::CreateFolderStructure(action) => {
+                        self.execute_create_folder_structure(action, &current_path, info, captures)
+                    }
+                    Action::DispatchToFolder(action) => {
+                        dispatch_outcome(&action.folder_id, &current_path)
+                    }
+                    Action::SetTags(action) => {
+                        self.execute_set_tags(action, &current_path, info, captures)
+                    }
+                    Action::Pause(action) => self.execute_pause(action),
+                    Action::Continue => ActionOutcome {
+                        action_type: ActionType::Continue,
+                        status: ActionResultStatus::Success,
+                        details: None,
+                        error: None,
+                        error_kind: None,
+                    },
+                    Action::Ignore => ActionOutcome {
+                        action_type: ActionType::Ignore,
+                        status: ActionResultStatus::Skipped,
+                        details: None,
+                        error: Some("Ignored by rule".to_string()),
+                        error_kind: None,
                     },
-                    &current_path,
-                ),
-                Action::RunScript(action) => self.execute_script(&action.command, &current_path),
-                Action::Notify(action) => self.execute_notify(&action.message, info, captures),
-                Action::Open(action) => self.execute_open(action, &current_path),
-                Action::ShowInFileManager(action) => self.execute_show_in_file_manager(action, &current_path),
-                Action::OpenWith(action) => self.execute_open_with(action, &current_path),
-                Action::MakePdfSearchable(action) => {
-                    self.execute_make_pdf_searchable(action, &current_path)
                 }
-                Action::Pause(action) => self.execute_pause(action),
-                Action::Continue => ActionOutcome {
-                    action_type: ActionType::Continue,
-                    status: ActionResultStatus::Success,
-                    details: None,
-                    error: None,
-                },
-                Action::Ignore => ActionOutcome {
-                    action_type: ActionType::Ignore,
-                    status: ActionResultStatus::Skipped,
-                    details: None,
-                    error: Some("Ignored by rule".to_string()),
-                },
+            };
+            if let Some(op) = planned {
+                scope.performed(op, offset, &result);
+            }
+            let companions = match self.companion_plan(action, info, captures) {
+                Some(plan) => transfer_companions(&mut result, &plan),
+                None => Vec::new(),
             };
 
             if let Some(details) = &result.details {
@@ -162,9 +495,14 @@ impl ActionExecutor {
                 }
             }
 
-            let stop_on_error = result.status == ActionResultStatus::Error;
+            let suspended = mark_suspended(&mut result, &current_path, scope.index(offset));
+            // A dispatch hands the file off, so nothing after it runs here.
+            let stop = result.status == ActionResultStatus::Error
+                || result.action_type == ActionType::DispatchToFolder
+                || suspended;
             outcomes.push(result);
-            if stop_on_error {
+            outcomes.extend(companions);
+            if stop {
                 break;
             }
         }
@@ -172,11 +510,69 @@ impl ActionExecutor {
         outcomes
     }
 
-    fn simulate_actions(
+    /// Lets the UI know a match is waiting in the review queue.
+    pub fn notify_pending_approval(&self, approval: &PendingApproval) {
+        self.emit("approval://pending", approval);
+    }
+
+    /// Lets the UI know an action is waiting for a decision on a conflict.
+    pub fn notify_pending_conflict(&self, conflict: &PendingConflict) {
+        self.emit("conflict://pending", conflict);
+    }
+
+    /// Lets the UI know a file failed too many times to keep retrying.
+    pub fn notify_retry_exhausted(&self, exhausted: &RetryExhausted) {
+        self.emit("engine://retry-exhausted", exhausted);
+    }
+
+    /// Lets the UI know a rule has used up its monthly byte budget.
+    pub fn notify_budget_exhausted(&self, exhausted: &BudgetExhausted) {
+        self.emit(BUDGET_EXHAUSTED_EVENT, exhausted);
+    }
+
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Some(app_handle) = &self.app_handle {
+            let _ = app_handle.emit(event, payload);
+        }
+    }
+
+    /// Skips the actions that need the app, when running without it.
+    fn detached_refusal(&self, action: &Action, source_path: &Path) -> Option<ActionOutcome> {
+        if self.app_handle.is_some() {
+            return None;
+        }
+        let action_type = match action {
+            Action::Notify(_) => ActionType::Notify,
+            Action::Open(_) => ActionType::Open,
+            Action::ShowInFileManager(_) => ActionType::ShowInFileManager,
+            Action::OpenWith(_) => ActionType::OpenWith,
+            _ => return None,
+        };
+        let mut outcome = success_outcome(action_type, source_path, None);
+        outcome.status = ActionResultStatus::Skipped;
+        outcome.error = Some("Not run without the app".to_string());
+        Some(outcome)
+    }
+
+    /// Resolves where each action would put the file without touching it.
+    pub fn simulate_actions(
         &self,
         actions: &[Action],
         info: &FileInfo,
         captures: &HashMap<String, String>,
+    ) -> Vec<ActionOutcome> {
+        self.simulate_actions_claiming(actions, info, captures, &mut HashSet::new())
+    }
+
+    /// `simulate_actions`, with a taken destination being one on disk or one
+    /// `claimed` by a file simulated earlier in the same run. The
+    /// destinations this file would take are added to `claimed`.
+    pub fn simulate_actions_claiming(
+        &self,
+        actions: &[Action],
+        info: &FileInfo,
+        captures: &HashMap<String, String>,
+        claimed: &mut HashSet<PathBuf>,
     ) -> Vec<ActionOutcome> {
         let mut outcomes = Vec::new();
         let mut current_path = info.path.clone();
@@ -190,6 +586,7 @@ impl ActionExecutor {
                         info,
                         captures,
                         false,
+                        action.auto_partition.as_ref(),
                         &current_path,
                     ),
                 ),
@@ -200,6 +597,7 @@ impl ActionExecutor {
                         info,
                         captures,
                         false,
+                        action.auto_partition.as_ref(),
                         &current_path,
                     ),
                 ),
@@ -219,6 +617,7 @@ impl ActionExecutor {
                         info,
                         captures,
                         true,
+                        action.auto_partition.as_ref(),
                         &current_path,
                     ),
                 ),
@@ -241,12 +640,83 @@ impl ActionExecutor {
                     ActionType::MakePdfSearchable,
                     self.resolve_pdf_searchable_destination(action, &current_path),
                 ),
+                Action::CreateFolderStructure(action) => (
+                    ActionType::CreateFolderStructure,
+                    Some(expand_tilde(&self.pattern_engine.resolve(
+                        &action.base_destination,
+                        info,
+                        captures,
+                    ))),
+                ),
+                Action::DispatchToFolder(action) => {
+                    let mut outcome = dry_run_outcome(ActionType::DispatchToFolder, &current_path, None);
+                    if let Some(details) = outcome.details.as_mut() {
+                        details
+                            .metadata
+                            .insert(DISPATCH_TARGET_KEY.to_string(), action.folder_id.clone());
+                    }
+                    outcomes.push(outcome);
+                    break;
+                }
+                Action::SetTags(_) => (ActionType::SetTags, None),
                 Action::Pause(_) => (ActionType::Pause, None),
                 Action::Continue => (ActionType::Continue, None),
                 Action::Ignore => (ActionType::Ignore, None),
             };
 
-            let outcome = dry_run_outcome(action_type, &current_path, dest_path);
+            let mut dest_path = dest_path;
+            let predicted = match (conflict_policy(action), dest_path.as_mut()) {
+                (Some((conflict, skip_duplicates)), Some(dest)) => {
+                    predict_conflict(dest, conflict, skip_duplicates, claimed)
+                }
+                _ => Ok(Predicted::Free),
+            };
+            let predicted = match predicted {
+                Ok(predicted) => predicted,
+                Err(reason) => {
+                    let mut outcome = dry_run_outcome(action_type, &current_path, dest_path);
+                    outcome.status = ActionResultStatus::Skipped;
+                    outcome.error = Some(reason);
+                    outcomes.push(outcome);
+                    continue;
+                }
+            };
+            if let Some(dest) = &dest_path {
+                if conflict_policy(action).is_some() {
+                    claimed.insert(dest.clone());
+                }
+            }
+            let removes = match (action, &predicted) {
+                (_, Predicted::Replaced) => dest_path.clone(),
+                (Action::Delete(_) | Action::DeletePermanently(_), _) => Some(current_path.clone()),
+                (Action::Archive(archive), _) if archive.delete_after => Some(current_path.clone()),
+                _ => None,
+            };
+            let mut outcome = dry_run_outcome(action_type, &current_path, dest_path);
+            if let Some(details) = outcome.details.as_mut() {
+                predicted.record(&mut details.metadata);
+                if let Some(removes) = removes {
+                    details.metadata.insert(
+                        DRY_RUN_REMOVES_KEY.to_string(),
+                        removes.to_string_lossy().to_string(),
+                    );
+                }
+            }
+            if auto_partition(action).is_some() {
+                record_partition(&mut outcome);
+            }
+            let companions = match (self.companion_plan(action, info, captures), &outcome.details) {
+                (Some(plan), Some(details)) => match &details.destination_path {
+                    Some(dest) => simulate_companions(
+                        &plan,
+                        outcome.action_type.clone(),
+                        &current_path,
+                        Path::new(dest),
+                    ),
+                    None => Vec::new(),
+                },
+                _ => Vec::new(),
+            };
             if let Some(details) = &outcome.details {
                 if let Some(dest) = &details.destination_path {
                     if matches!(
@@ -258,23 +728,123 @@ impl ActionExecutor {
                 }
             }
             outcomes.push(outcome);
+            outcomes.extend(companions);
         }
 
         outcomes
     }
 
+    /// How the companions of a file `action` moves, copies or sorts come
+    /// along, when it asks for them.
+    fn companion_plan<'a>(
+        &self,
+        action: &'a Action,
+        info: &FileInfo,
+        captures: &HashMap<String, String>,
+    ) -> Option<CompanionPlan<'a>> {
+        let (spec, transfer, conflict, skip_duplicates, perms, quarantine) = match action {
+            Action::Move(action) => (
+                action.bring_companions.as_ref()?,
+                Transfer::Move,
+                &action.on_conflict,
+                action.skip_duplicates,
+                &action.destination_permissions,
+                action.quarantine,
+            ),
+            Action::Copy(action) => (
+                action.bring_companions.as_ref()?,
+                Transfer::Copy(self.reflink_mode(action.use_reflink)),
+                &action.on_conflict,
+                action.skip_duplicates,
+                &action.destination_permissions,
+                action.quarantine,
+            ),
+            Action::SortIntoSubfolder(action) => (
+                action.bring_companions.as_ref()?,
+                Transfer::Move,
+                &action.on_conflict,
+                false,
+                &action.destination_permissions,
+                action.quarantine,
+            ),
+            _ => return None,
+        };
+        let pattern = spec
+            .pattern
+            .as_deref()
+            .filter(|pattern| !pattern.trim().is_empty())
+            .map(|pattern| self.pattern_engine.resolve(pattern, info, captures));
+        Some(CompanionPlan {
+            spec,
+            pattern,
+            transfer,
+            conflict,
+            skip_duplicates,
+            perms,
+            quarantine,
+        })
+    }
+
+    /// Runs a move or copy of `source` with its quarantine attribute handled
+    /// as `handling` asks, refusing to strip it unless settings allow that.
+    fn with_quarantine(
+        &self,
+        action_type: ActionType,
+        handling: QuarantineHandling,
+        source: &Path,
+        transfer: impl FnOnce() -> ActionOutcome,
+    ) -> ActionOutcome {
+        let allow_strip = self
+            .settings
+            .lock()
+            .map(|s| s.allow_quarantine_strip)
+            .unwrap_or(false);
+        if let Some(outcome) = quarantine_refusal(action_type, handling, allow_strip) {
+            return outcome;
+        }
+        let before = quarantine::capture(&xattr::System, handling, source);
+        apply_quarantine(transfer(), handling, before.as_deref(), &xattr::System)
+    }
+
+    /// The action's reflink mode, or the global setting when it has none.
+    fn reflink_mode(&self, reflink: Option<ReflinkMode>) -> ReflinkMode {
+        reflink.unwrap_or_else(|| {
+            self.settings
+                .lock()
+                .map(|s| s.use_reflink)
+                .unwrap_or(ReflinkMode::Auto)
+        })
+    }
+
+    /// Where `dest` goes once `spec` has picked its partition.
+    fn partition(
+        &self,
+        dest: &Path,
+        spec: &AutoPartition,
+        info: &FileInfo,
+    ) -> Option<Partitioned> {
+        let zone = self.pattern_engine.default_timezone();
+        partition::apply(dest, spec, info, zone, Utc::now())
+    }
+
     fn resolve_destination(
         &self,
         destination: &str,
         info: &FileInfo,
         captures: &HashMap<String, String>,
         force_dir: bool,
+        partition: Option<&AutoPartition>,
         source_path: &Path,
     ) -> Option<PathBuf> {
         let resolved = self.pattern_engine.resolve(destination, info, captures);
         let mut dest_path = expand_tilde(&resolved);
         if force_dir || dest_path.is_dir() || looks_like_directory(&dest_path) {
-            dest_path = dest_path.join(&info.full_name);
+            dest_path = dest_path.join(&info.os_name);
+        }
+        if let Some(partitioned) =
+            partition.and_then(|spec| self.partition(&dest_path, spec, info))
+        {
+            dest_path = partitioned.dest;
         }
         if dest_path == source_path {
             None
@@ -292,8 +862,8 @@ impl ActionExecutor {
     ) -> Option<PathBuf> {
         let resolved = self.pattern_engine.resolve(pattern, info, captures);
         let dest_path = match source_path.parent() {
-            Some(parent) => parent.join(&resolved),
-            None => PathBuf::from(resolved.as_str()),
+            Some(parent) => parent.join(info.restore_name(&resolved)),
+            None => PathBuf::from(info.restore_name(&resolved)),
         };
         if dest_path == source_path {
             None
@@ -346,6 +916,7 @@ impl ActionExecutor {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn execute_move(
         &self,
         action_type: ActionType,
@@ -353,82 +924,179 @@ impl ActionExecutor {
         conflict: ConflictResolution,
         skip_duplicates: bool,
         force_dir: bool,
+        partition: Option<&AutoPartition>,
         source_path: &Path,
         info: &FileInfo,
         captures: &HashMap<String, String>,
     ) -> ActionOutcome {
+        if let Some(outcome) = placeholder_refusal(action_type.clone(), info) {
+            return outcome;
+        }
         let resolved = self.pattern_engine.resolve(destination, info, captures);
         let mut dest_path = expand_tilde(&resolved);
         if force_dir || dest_path.is_dir() || looks_like_directory(&dest_path) {
-            dest_path = dest_path.join(&info.full_name);
+            dest_path = dest_path.join(&info.os_name);
         }
-
-        if let Err(outcome) = prepare_destination(
-            action_type.clone(),
-            &mut dest_path,
-            conflict,
-            skip_duplicates,
-        ) {
+        // Held until the file has landed, so a numbered partition isn't
+        // overfilled by files arriving at the same time.
+        let partitioned = partition.and_then(|spec| self.partition(&dest_path, spec, info));
+        if let Some(partitioned) = &partitioned {
+            dest_path = partitioned.dest.clone();
+        }
+        if let Some(outcome) = offline_refusal(action_type.clone(), &dest_path) {
             return outcome;
         }
-
-        if let Some(parent) = dest_path.parent() {
-            if let Err(err) = fs::create_dir_all(parent) {
-                return error_outcome(action_type.clone(), err.to_string());
-            }
+        if let Some(outcome) =
+            self.permission_refusal(action_type.clone(), dest_path.parent().unwrap_or(&dest_path))
+        {
+            return outcome;
         }
 
-        let result = fs::rename(source_path, &dest_path).or_else(|err| {
-            if is_cross_device_error(&err) {
-                move_fallback(source_path, &dest_path)
-                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
-            } else {
-                Err(err)
-            }
-        });
-
-        match result {
-            Ok(_) => success_outcome(action_type, source_path, Some(dest_path)),
-            Err(err) => error_outcome(action_type, err.to_string()),
+        let mut outcome = transfer_file(
+            action_type,
+            Transfer::Move,
+            source_path,
+            dest_path,
+            conflict,
+            skip_duplicates,
+        );
+        if partitioned.is_some() {
+            record_partition(&mut outcome);
         }
+        outcome
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn execute_copy(
         &self,
         action_type: ActionType,
         destination: &str,
         conflict: ConflictResolution,
         skip_duplicates: bool,
+        reflink: Option<ReflinkMode>,
         force_dir: bool,
+        partition: Option<&AutoPartition>,
         source_path: &Path,
         info: &FileInfo,
         captures: &HashMap<String, String>,
     ) -> ActionOutcome {
+        if let Some(outcome) = placeholder_refusal(action_type.clone(), info) {
+            return outcome;
+        }
         let resolved = self.pattern_engine.resolve(destination, info, captures);
         let mut dest_path = expand_tilde(&resolved);
         if force_dir || dest_path.is_dir() || looks_like_directory(&dest_path) {
-            dest_path = dest_path.join(&info.full_name);
+            dest_path = dest_path.join(&info.os_name);
+        }
+        // Held until the file has landed, so a numbered partition isn't
+        // overfilled by files arriving at the same time.
+        let partitioned = partition.and_then(|spec| self.partition(&dest_path, spec, info));
+        if let Some(partitioned) = &partitioned {
+            dest_path = partitioned.dest.clone();
+        }
+        if let Some(outcome) = offline_refusal(action_type.clone(), &dest_path) {
+            return outcome;
+        }
+        if let Some(outcome) =
+            self.permission_refusal(action_type.clone(), dest_path.parent().unwrap_or(&dest_path))
+        {
+            return outcome;
         }
 
-        if let Err(outcome) = prepare_destination(
-            action_type.clone(),
-            &mut dest_path,
+        let mut outcome = transfer_file(
+            action_type,
+            Transfer::Copy(self.reflink_mode(reflink)),
+            source_path,
+            dest_path,
             conflict,
             skip_duplicates,
-        ) {
-            return outcome;
+        );
+        if partitioned.is_some() {
+            record_partition(&mut outcome);
         }
+        outcome
+    }
 
-        if let Some(parent) = dest_path.parent() {
-            if let Err(err) = fs::create_dir_all(parent) {
-                return error_outcome(action_type.clone(), err.to_string());
+    /// Runs an action that was suspended on a conflict to the destination
+    /// the user settled on, without resolving its pattern again.
+    fn execute_settled(
+        &self,
+        action: &Action,
+        settled: &SettledConflict,
+        source_path: &Path,
+    ) -> ActionOutcome {
+        let (action_type, transfer, perms, handling) = match action {
+            Action::Move(action) => (
+                ActionType::Move,
+                Transfer::Move,
+                Some(&action.destination_permissions),
+                action.quarantine,
+            ),
+            Action::Copy(action) => (
+                ActionType::Copy,
+                Transfer::Copy(self.reflink_mode(action.use_reflink)),
+                Some(&action.destination_permissions),
+                action.quarantine,
+            ),
+            Action::SortIntoSubfolder(action) => (
+                ActionType::SortIntoSubfolder,
+                Transfer::Move,
+                Some(&action.destination_permissions),
+                action.quarantine,
+            ),
+            Action::Rename(_) => (
+                ActionType::Rename,
+                Transfer::Move,
+                None,
+                QuarantineHandling::Keep,
+            ),
+            _ => {
+                return error_outcome(
+                    ActionType::Move,
+                    "Only moves, copies, sorts and renames wait on conflicts".to_string(),
+                )
             }
+        };
+        let dest = &settled.destination;
+        if let Some(outcome) = offline_refusal(action_type.clone(), dest) {
+            return outcome;
         }
-
-        match fs_extra::file::copy(source_path, &dest_path, &fs_extra::file::CopyOptions::new()) {
-            Ok(_) => success_outcome(action_type, source_path, Some(dest_path)),
-            Err(err) => error_outcome(action_type, err.to_string()),
+        if let Some(outcome) =
+            self.permission_refusal(action_type.clone(), dest.parent().unwrap_or(dest))
+        {
+            return outcome;
         }
+        self.with_quarantine(action_type.clone(), handling, source_path, || {
+            let outcome = transfer_file(
+                action_type,
+                transfer,
+                source_path,
+                dest.clone(),
+                settled.policy.clone(),
+                false,
+            );
+            match perms {
+                Some(perms) => apply_destination_permissions(outcome, perms),
+                None => outcome,
+            }
+        })
+    }
+
+    /// Refuses to write into `dir` when macOS hasn't granted access to the
+    /// protected location it's in. The UI hears about each category once per
+    /// session so it can point the user at System Settings.
+    fn permission_refusal(&self, action_type: ActionType, dir: &Path) -> Option<ActionOutcome> {
+        let denied = check_access(dir).err()?;
+        if first_denial(denied.category) {
+            self.emit(
+                "permission://needed",
+                PermissionNeeded {
+                    category: denied.category,
+                    path: denied.path.to_string_lossy().to_string(),
+                },
+            );
+        }
+        Some(permission_outcome(action_type, denied))
     }
 
     fn execute_rename(
@@ -445,11 +1113,11 @@ impl ActionExecutor {
             None => PathBuf::from(resolved.as_str()),
         };
 
-        if let Err(outcome) =
-            prepare_destination(ActionType::Rename, &mut dest_path, conflict, false)
+        let suffix = match prepare_destination(ActionType::Rename, &mut dest_path, conflict, false)
         {
-            return outcome;
-        }
+            Ok(suffix) => suffix,
+            Err(outcome) => return outcome,
+        };
 
         let result = fs::rename(source_path, &dest_path).or_else(|err| {
             if is_windows_case_only_rename(source_path, &dest_path) {
@@ -463,10 +1131,11 @@ impl ActionExecutor {
             }
         });
 
-        match result {
+        let outcome = match result {
             Ok(_) => success_outcome(ActionType::Rename, source_path, Some(dest_path)),
-            Err(err) => error_outcome(ActionType::Rename, err.to_string()),
-        }
+            Err(err) => io_error_outcome(ActionType::Rename, source_path, err),
+        };
+        record_suffix(outcome, suffix.as_ref())
     }
 
     fn execute_delete(
@@ -483,11 +1152,13 @@ impl ActionExecutor {
                         status: ActionResultStatus::Skipped,
                         details: None,
                         error: Some("Permanent deletes are disabled in settings".to_string()),
+                        error_kind: None,
                     };
                 }
             }
         }
-        let result = if action.permanent {
+        // Without the app the trash isn't one the user would look in.
+        let result = if action.permanent || self.app_handle.is_none() {
             if source_path.is_dir() {
                 fs::remove_dir_all(source_path)
             } else {
@@ -513,9 +1184,31 @@ impl ActionExecutor {
     ) -> ActionOutcome {
         let resolved = self.pattern_engine.resolve(&action.destination, info, captures);
         let dest_path = ensure_archive_path(&expand_tilde(&resolved), source_path, &action.format);
+        if let Some(outcome) = offline_refusal(ActionType::Archive, &dest_path) {
+            return outcome;
+        }
+        if let Some(outcome) =
+            self.permission_refusal(ActionType::Archive, dest_path.parent().unwrap_or(&dest_path))
+        {
+            return outcome;
+        }
 
-        let result = create_archive(source_path, &dest_path, &action.format)
-            .map_err(|err| error_outcome(ActionType::Archive, err.to_string()));
+        let password = match action
+            .encryption
+            .as_ref()
+            .map(|encryption| resolve_password(&encryption.password_source))
+            .transpose()
+        {
+            Ok(password) => password,
+            Err(err) => return error_outcome(ActionType::Archive, err.to_string()),
+        };
+        let result = create_archive(
+            source_path,
+            &dest_path,
+            &action.format,
+            password.as_ref().map(|password| password.as_str()),
+        )
+        .map_err(|err| error_outcome(ActionType::Archive, err.to_string()));
 
         let dest_path = match result {
             Ok(path) => path,
@@ -533,7 +1226,16 @@ impl ActionExecutor {
             }
         }
 
-        success_outcome(ActionType::Archive, source_path, Some(dest_path))
+        let mut outcome = success_outcome(ActionType::Archive, source_path, Some(dest_path));
+        if let (Some(encryption), Some(details)) = (&action.encryption, outcome.details.as_mut()) {
+            let method = match encryption.method {
+                ArchiveEncryptionMethod::ZipAes256 => "zipAes256",
+            };
+            details
+                .metadata
+                .insert(ENCRYPTION_KEY.to_string(), method.to_string());
+        }
+        outcome
     }
 
     fn execute_unarchive(
@@ -557,18 +1259,39 @@ impl ActionExecutor {
         } else {
             expand_tilde(&resolved)
         };
-
-        if let Err(err) = extract_archive(source_path, &dest_path) {
-            return error_outcome(ActionType::Unarchive, err.to_string());
+        if let Some(outcome) = self.permission_refusal(ActionType::Unarchive, &dest_path) {
+            return outcome;
         }
 
+        let filter = match ExtractFilter::new(
+            &action.include_globs,
+            &action.exclude_globs,
+            action.skip_junk,
+        ) {
+            Ok(filter) => filter,
+            Err(err) => return error_outcome(ActionType::Unarchive, err.to_string()),
+        };
+        let summary = match extract_archive(source_path, &dest_path, &filter) {
+            Ok(summary) => summary,
+            Err(err) => return error_outcome(ActionType::Unarchive, err.to_string()),
+        };
+
         if action.delete_after {
             if let Err(err) = fs::remove_file(source_path) {
                 return error_outcome(ActionType::Unarchive, err.to_string());
             }
         }
 
-        success_outcome(ActionType::Unarchive, source_path, Some(dest_path))
+        let mut outcome = success_outcome(ActionType::Unarchive, source_path, Some(dest_path));
+        if let Some(details) = outcome.details.as_mut() {
+            details
+                .metadata
+                .insert(EXTRACTED_KEY.to_string(), summary.extracted.to_string());
+            details
+                .metadata
+                .insert(SKIPPED_KEY.to_string(), summary.skipped.to_string());
+        }
+        outcome
     }
 
     fn execute_open(&self, _action: &OpenAction, source_path: &Path) -> ActionOutcome {
@@ -645,7 +1368,10 @@ impl ActionExecutor {
         } else {
             searchable_output_path(source_path)
         };
-        let resource_dir = self.app_handle.path().resource_dir().ok();
+        let resource_dir = self
+            .app_handle
+            .as_ref()
+            .and_then(|app_handle| app_handle.path().resource_dir().ok());
 
         match make_pdf_searchable(
             source_path,
@@ -669,11 +1395,67 @@ impl ActionExecutor {
                 status: ActionResultStatus::Skipped,
                 details: None,
                 error: Some("PDF already has selectable text".to_string()),
+                error_kind: None,
             },
-            Err(err) => error_outcome(ActionType::MakePdfSearchable, err.to_string()),
+            Err(err) => pdf_error_outcome(err),
         }
     }
 
+    fn execute_create_folder_structure(
+        &self,
+        action: &CreateFolderStructureAction,
+        source_path: &Path,
+        info: &FileInfo,
+        captures: &HashMap<String, String>,
+    ) -> ActionOutcome {
+        let base = expand_tilde(
+            &self
+                .pattern_engine
+                .resolve(&action.base_destination, info, captures),
+        );
+        if let Some(outcome) = self.permission_refusal(ActionType::CreateFolderStructure, &base) {
+            return outcome;
+        }
+        let entries: Vec<String> = action
+            .structure
+            .iter()
+            .map(|entry| self.pattern_engine.resolve(entry, info, captures))
+            .collect();
+
+        match create_folder_structure(&base, &entries) {
+            Ok(result) => {
+                let mut outcome =
+                    success_outcome(ActionType::CreateFolderStructure, source_path, Some(base));
+                if let Some(details) = outcome.details.as_mut() {
+                    details.metadata = result.metadata();
+                }
+                outcome
+            }
+            Err(err) => error_outcome(ActionType::CreateFolderStructure, err),
+        }
+    }
+
+    fn execute_set_tags(
+        &self,
+        action: &SetTagsAction,
+        source_path: &Path,
+        info: &FileInfo,
+        captures: &HashMap<String, String>,
+    ) -> ActionOutcome {
+        let tags: Vec<String> = action
+            .tags
+            .iter()
+            .map(|tag| self.pattern_engine.resolve(tag, info, captures))
+            .collect();
+        set_tags(
+            &xattr::System,
+            TagFormat::native(),
+            source_path,
+            &tags,
+            action.mode,
+        )
+    }
+
     fn execute_pause(&self, action: &PauseAction) -> ActionOutcome {
         // Cap pause duration to prevent blocking the engine for too long
         // Maximum 60 seconds to avoid indefinite blocking
@@ -710,63 +1492,14 @@ impl ActionExecutor {
         outcome
     }
 
-    fn execute_script(&self, command: &str, source_path: &Path) -> ActionOutcome {
-        // Try to execute the script, with fallback on Windows
-        let result = if cfg!(target_os = "windows") {
-            // On Windows: Try PowerShell first, fall back to cmd.exe
-            let ps_result = Command::new("powershell")
-                .arg("-NoProfile")
-                .arg("-ExecutionPolicy")
-                .arg("Bypass")
-                .arg("-Command")
-                .arg(command)
-                .env("FILE_PATH", source_path)
-                .status();
-
-            match ps_result {
-                Ok(status) if status.success() => {
-                    return success_outcome(ActionType::RunScript, source_path, None);
-                }
-                Ok(status) => {
-                    // PowerShell ran but script failed - return error
-                    return error_outcome(
-                        ActionType::RunScript,
-                        format!("PowerShell script failed: {status}"),
-                    );
-                }
-                Err(_) => {
-                    // PowerShell not found or failed to start, try cmd.exe
-                    Command::new("cmd")
-                        .arg("/C")
-                        .arg(command)
-                        .env("FILE_PATH", source_path)
-                        .status()
-                }
-            }
-        } else {
-            // Unix: use sh
-            Command::new("sh")
-                .arg("-c")
-                .arg(command)
-                .env("FILE_PATH", source_path)
-                .status()
-        };
-
-        match result {
-            Ok(status) if status.success() => {
-                success_outcome(ActionType::RunScript, source_path, None)
-            }
-            Ok(status) => error_outcome(ActionType::RunScript, format!("Script failed: {status}")),
-            Err(err) => error_outcome(ActionType::RunScript, err.to_string()),
-        }
-    }
-
     fn execute_notify(
         &self,
-        message: &str,
+        action: &NotifyAction,
         info: &FileInfo,
         captures: &HashMap<String, String>,
     ) -> ActionOutcome {
+        let mut respect_dnd = false;
+        let mut thumbnails = None;
         if let Ok(settings) = self.settings.lock() {
             if !settings.show_notifications {
                 return ActionOutcome {
@@ -774,27 +1507,58 @@ impl ActionExecutor {
                     status: ActionResultStatus::Skipped,
                     details: None,
                     error: Some("Notifications disabled".to_string()),
+                    error_kind: None,
                 };
             }
+            respect_dnd = settings.respect_dnd;
+            if action.generate_thumbnail {
+                thumbnails = ThumbnailCache::from_settings(&settings).ok();
+            }
         }
-        let body = self.pattern_engine.resolve(message, info, captures);
-        let notification = self
-            .app_handle
+        let plan = plan_notification(action.sound.as_deref(), respect_dnd, focus_active);
+        let mut outcome = success_outcome(ActionType::Notify, &info.path, None);
+        let thumbnail = thumbnails
+            .and_then(|cache| cache.thumbnail(&info.path, &info.kind, &info.extension));
+        if let (Some(thumbnail), Some(details)) = (&thumbnail, outcome.details.as_mut()) {
+            details.metadata.insert(
+                THUMBNAIL_KEY.to_string(),
+                thumbnail.path.to_string_lossy().to_string(),
+            );
+        }
+        if let (Some(suppressed), Some(details)) = (plan.suppressed, outcome.details.as_mut()) {
+            details
+                .metadata
+                .insert("suppressed".to_string(), suppressed.as_str().to_string());
+            details
+                .metadata
+                .insert("suppressed_reason".to_string(), "do_not_disturb".to_string());
+        }
+        if !plan.show {
+            return outcome;
+        }
+
+        let Some(app_handle) = &self.app_handle else {
+            return outcome;
+        };
+        let body = self.pattern_engine.resolve(&action.message, info, captures);
+        let mut notification = app_handle
             .notification()
             .builder()
             .title("File Dispatch")
             .body(body);
+        if let Some(sound) = &plan.sound {
+            notification = notification.sound(resolve_sound(app_handle, sound));
+        }
+        // Linux and Windows show an image path as the notification's icon;
+        // macOS only ever shows the app's.
+        if let Some(thumbnail) = thumbnail.filter(|_| cfg!(not(target_os = "macos"))) {
+            notification = notification.icon(thumbnail.path.to_string_lossy());
+        }
 
         if let Err(err) = notification.show() {
             return error_outcome(ActionType::Notify, err.to_string());
         }
-
-        ActionOutcome {
-            action_type: ActionType::Notify,
-            status: ActionResultStatus::Success,
-            details: None,
-            error: None,
-        }
+        outcome
     }
 }
 
@@ -814,10 +1578,19 @@ fn is_cross_device_error(err: &std::io::Error) -> bool {
     }
 }
 
+/// Copies then deletes when a rename can't cross devices. The copy is cloned
+/// where the two paths still share a clone-capable filesystem.
 fn move_fallback(source: &Path, dest: &Path) -> Result<(), std::io::Error> {
-    fs_extra::file::move_file(source, dest, &fs_extra::file::CopyOptions::new())
-        .map(|_| ())
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+    if !source.is_file() {
+        return fs_extra::file::move_file(source, dest, &fs_extra::file::CopyOptions::new())
+            .map(|_| ())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
+    }
+    // Copy beside the destination so a failed copy never leaves half a file there.
+    let staged = app_temp().beside(dest)?;
+    copy_file(source, staged.path(), ReflinkMode::Auto)?;
+    staged.persist(dest)?;
+    fs::remove_file(source)
 }
 
 fn is_windows_case_only_rename(source: &Path, dest: &Path) -> bool {
@@ -832,28 +1605,81 @@ fn is_windows_case_only_rename(source: &Path, dest: &Path) -> bool {
     src.eq_ignore_ascii_case(&dst)
 }
 
+/// Renames through a held temp name; if the second step fails (or the app
+/// dies in between) the file is moved back to `source`.
 fn temp_rename(source: &Path, dest: &Path) -> Result<(), std::io::Error> {
-    let parent = source.parent().unwrap_or_else(|| Path::new("."));
-    let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
-    let ext = source.extension().and_then(|s| s.to_str()).unwrap_or("");
-    let temp_name = if ext.is_empty() {
-        format!("{}.rename_tmp", stem)
-    } else {
-        format!("{}.rename_tmp.{}", stem, ext)
-    };
-    let temp_path = parent.join(temp_name);
-    if temp_path.exists() {
-        fs::remove_file(&temp_path)?;
-    }
-    fs::rename(source, &temp_path)?;
-    fs::rename(&temp_path, dest)?;
-    Ok(())
+    let held = app_temp().holding(source)?;
+    fs::rename(source, held.path())?;
+    held.persist(dest)
 }
 
 /// Check if a path looks like a directory (has no file extension or ends with a separator).
 /// Used when the path doesn't exist yet but we need to determine if the user intended it
 /// to be a directory destination.
-fn looks_like_directory(path: &Path) -> bool {
+/// Checks that a folder structure entry is a relative path that stays under
+/// its base folder. Used both when saving a rule and after patterns resolve,
+/// since a capture can smuggle in `..` that the raw pattern didn't have.
+pub(crate) fn validate_structure_entry(entry: &str) -> Result<(), String> {
+    if entry.trim().is_empty() {
+        return Err("Folder structure entries can't be empty".to_string());
+    }
+    let escapes = Path::new(entry).components().any(|component| {
+        !matches!(component, Component::Normal(_) | Component::CurDir)
+    });
+    if escapes {
+        return Err(format!(
+            "Folder structure entry must be a relative path inside the base folder: {}",
+            entry
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct FolderStructureResult {
+    created: Vec<PathBuf>,
+    existing: Vec<PathBuf>,
+}
+
+impl FolderStructureResult {
+    fn metadata(&self) -> HashMap<String, String> {
+        let join = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let mut metadata = HashMap::new();
+        metadata.insert("created".to_string(), join(&self.created));
+        metadata.insert("existing".to_string(), join(&self.existing));
+        metadata
+    }
+}
+
+/// Creates `base` and every (already resolved) entry beneath it. Directories
+/// that already exist are reported rather than treated as errors, so running
+/// the same rule twice is harmless. All entries are validated before anything
+/// is created.
+fn create_folder_structure(base: &Path, entries: &[String]) -> Result<FolderStructureResult, String> {
+    for entry in entries {
+        validate_structure_entry(entry)?;
+    }
+
+    let mut result = FolderStructureResult::default();
+    let targets = std::iter::once(base.to_path_buf()).chain(entries.iter().map(|e| base.join(e)));
+    for target in targets {
+        if target.is_dir() {
+            result.existing.push(target);
+            continue;
+        }
+        fs::create_dir_all(&target).map_err(|err| format!("{}: {}", target.display(), err))?;
+        result.created.push(target);
+    }
+    Ok(result)
+}
+
+pub(crate) fn looks_like_directory(path: &Path) -> bool {
     // If it ends with a path separator, it's definitely meant to be a directory
     let path_str = path.to_string_lossy();
     if path_str.ends_with('/') || path_str.ends_with('\\') {
@@ -864,30 +1690,251 @@ fn looks_like_directory(path: &Path) -> bool {
     path.extension().is_none()
 }
 
+/// How a file and its companions get to their destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transfer {
+    Move,
+    Copy(ReflinkMode),
+}
+
+/// The companions a move, copy or sort brings along and how.
+pub(crate) struct CompanionPlan<'a> {
+    pub spec: &'a BringCompanions,
+    /// The spec's pattern with its tokens resolved.
+    pub pattern: Option<String>,
+    pub transfer: Transfer,
+    pub conflict: &'a ConflictResolution,
+    pub skip_duplicates: bool,
+    pub perms: &'a DestinationPermissions,
+    pub quarantine: QuarantineHandling,
+}
+
+/// Moves or copies `source` to `dest` once a conflict there is settled.
+pub(crate) fn transfer_file(
+    action_type: ActionType,
+    transfer: Transfer,
+    source: &Path,
+    mut dest: PathBuf,
+    conflict: ConflictResolution,
+    skip_duplicates: bool,
+) -> ActionOutcome {
+    let suffix =
+        match prepare_destination(action_type.clone(), &mut dest, conflict, skip_duplicates) {
+            Ok(suffix) => suffix,
+            Err(outcome) => return outcome,
+        };
+
+    if let Some(parent) = dest.parent() {
+        if let Err(err) = fs::create_dir_all(parent) {
+            return io_error_outcome(action_type, source, err);
+        }
+    }
+
+    let outcome = match transfer {
+        Transfer::Move => {
+            let result = fs::rename(source, &dest).or_else(|err| {
+                if is_cross_device_error(&err) {
+                    move_fallback(source, &dest)
+                } else {
+                    Err(err)
+                }
+            });
+            match result {
+                Ok(_) => success_outcome(action_type, source, Some(dest)),
+                Err(err) => io_error_outcome(action_type, source, err),
+            }
+        }
+        Transfer::Copy(mode) => match copy_file(source, &dest, mode) {
+            Ok(copied) => {
+                let mut outcome = success_outcome(action_type, source, Some(dest));
+                if let Some(details) = outcome.details.as_mut() {
+                    details
+                        .metadata
+                        .insert(REFLINKED_KEY.to_string(), copied.reflinked.to_string());
+                }
+                outcome
+            }
+            Err(err) => io_error_outcome(action_type, source, err),
+        },
+    };
+    record_suffix(outcome, suffix.as_ref())
+}
+
+/// Brings the companions of the file `primary` just landed along the same way
+/// and with the same conflict handling. Each companion gets its own outcome,
+/// linked to the file by `COMPANION_OF_KEY` and placed after it. A companion
+/// that didn't make it is reported in its outcome; it only fails `primary` as
+/// well when the spec says so, and the files that did land stay where they
+/// are either way.
+pub(crate) fn transfer_companions(
+    primary: &mut ActionOutcome,
+    plan: &CompanionPlan,
+) -> Vec<ActionOutcome> {
+    if primary.status != ActionResultStatus::Success {
+        return Vec::new();
+    }
+    let Some(details) = primary.details.as_mut() else {
+        return Vec::new();
+    };
+    let Some(dest) = details.destination_path.clone().map(PathBuf::from) else {
+        return Vec::new();
+    };
+    let source = PathBuf::from(&details.source_path);
+    let action_type = primary.action_type.clone();
+    let found = companions::find(&source, plan.spec, plan.pattern.as_deref());
+
+    let mut outcomes = Vec::new();
+    for companion in &found.found {
+        // A companion follows its file's unique name instead of getting one
+        // of its own.
+        let conflict = match plan.conflict {
+            ConflictResolution::UniqueSuffix => ConflictResolution::Rename,
+            conflict => conflict.clone(),
+        };
+        let before = quarantine::capture(&xattr::System, plan.quarantine, companion);
+        let outcome = transfer_file(
+            action_type.clone(),
+            plan.transfer,
+            companion,
+            companions::destination_for(companion, &source, &dest),
+            conflict,
+            plan.skip_duplicates,
+        );
+        let outcome = apply_destination_permissions(outcome, plan.perms);
+        let outcome = apply_quarantine(outcome, plan.quarantine, before.as_deref(), &xattr::System);
+        outcomes.push(link_companion(outcome, companion, &source, plan.spec));
+    }
+    if !plan.spec.missing_ok {
+        for missing in &found.missing {
+            let outcome = error_outcome(
+                action_type.clone(),
+                format!("Companion {missing} not found next to {}", source.display()),
+            );
+            outcomes.push(link_companion(outcome, &source, &source, plan.spec));
+        }
+    }
+
+    let landed = outcomes
+        .iter()
+        .filter(|outcome| outcome.status == ActionResultStatus::Success)
+        .count();
+    details
+        .metadata
+        .insert(COMPANIONS_KEY.to_string(), landed.to_string());
+    if plan.spec.fail_on_error {
+        let failed = outcomes
+            .iter()
+            .find(|outcome| outcome.status == ActionResultStatus::Error);
+        if let Some(failed) = failed {
+            primary.status = ActionResultStatus::Error;
+            primary.error = Some(format!(
+                "A companion couldn't be brought along: {}",
+                failed.error.as_deref().unwrap_or("unknown error")
+            ));
+        }
+    }
+    outcomes
+}
+
+/// Where the companions of the file at `source` would go if it went to `dest`.
+fn simulate_companions(
+    plan: &CompanionPlan,
+    action_type: ActionType,
+    source: &Path,
+    dest: &Path,
+) -> Vec<ActionOutcome> {
+    let found = companions::find(source, plan.spec, plan.pattern.as_deref());
+    found
+        .found
+        .iter()
+        .map(|companion| {
+            let target = companions::destination_for(companion, source, dest);
+            let outcome = dry_run_outcome(action_type.clone(), companion, Some(target));
+            link_companion(outcome, companion, source, plan.spec)
+        })
+        .collect()
+}
+
+/// Ties a companion's outcome to the file it came along with. Its failures
+/// never ask for a retry, since the file itself already landed.
+fn link_companion(
+    mut outcome: ActionOutcome,
+    companion: &Path,
+    source: &Path,
+    spec: &BringCompanions,
+) -> ActionOutcome {
+    outcome.error_kind = None;
+    let details = outcome.details.get_or_insert_with(|| ActionDetails {
+        source_path: companion.to_string_lossy().to_string(),
+        destination_path: None,
+        metadata: HashMap::new(),
+    });
+    details
+        .metadata
+        .insert(COMPANION_OF_KEY.to_string(), source.to_string_lossy().to_string());
+    // The log would otherwise give it the size of the file it came with.
+    let landed = details.destination_path.as_deref().map(Path::new);
+    if let Ok(meta) = fs::metadata(landed.unwrap_or(companion)) {
+        details
+            .metadata
+            .insert("size_bytes".to_string(), meta.len().to_string());
+    }
+    if spec.record_matches {
+        details
+            .metadata
+            .insert(COMPANION_RECORD_KEY.to_string(), "true".to_string());
+    }
+    outcome
+}
+
+/// Settles a conflict at `dest_path` by `conflict`, changing the path when
+/// the file goes elsewhere. Returns the suffix a unique-suffix name got.
 fn prepare_destination(
     action_type: ActionType,
     dest_path: &mut PathBuf,
     conflict: ConflictResolution,
     skip_duplicates: bool,
-) -> Result<(), ActionOutcome> {
-    if dest_path.exists() {
+) -> Result<Option<NameSuffix>, ActionOutcome> {
+    prepare_destination_probed(action_type, dest_path, conflict, skip_duplicates, |path| {
+        path.exists()
+    })
+}
+
+/// `prepare_destination`, asking `exists` whether something is in the way.
+fn prepare_destination_probed(
+    action_type: ActionType,
+    dest_path: &mut PathBuf,
+    conflict: ConflictResolution,
+    skip_duplicates: bool,
+    exists: impl Fn(&Path) -> bool,
+) -> Result<Option<NameSuffix>, ActionOutcome> {
+    let skipped = |action_type| ActionOutcome {
+        action_type,
+        status: ActionResultStatus::Skipped,
+        details: None,
+        error: Some("Destination exists; skipped".to_string()),
+        error_kind: None,
+    };
+
+    if conflict == ConflictResolution::UniqueSuffix {
+        if skip_duplicates && exists(dest_path) {
+            return Err(skipped(action_type));
+        }
+        // Whatever is there, the new name is free, so the destination isn't
+        // asked; on a shared folder the answer could be stale anyway.
+        let (unique, suffix) = unique_name::next(dest_path);
+        *dest_path = unique;
+        return Ok(Some(suffix));
+    }
+
+    if exists(dest_path) {
         if skip_duplicates {
-            return Err(ActionOutcome {
-                action_type,
-                status: ActionResultStatus::Skipped,
-                details: None,
-                error: Some("Destination exists; skipped".to_string()),
-            });
+            return Err(skipped(action_type));
         }
 
         match conflict {
             ConflictResolution::Skip => {
-                return Err(ActionOutcome {
-                    action_type,
-                    status: ActionResultStatus::Skipped,
-                    details: None,
-                    error: Some("Destination exists; skipped".to_string()),
-                });
+                return Err(skipped(action_type));
             }
             ConflictResolution::Replace => {
                 if dest_path.is_dir() {
@@ -901,13 +1948,168 @@ fn prepare_destination(
             ConflictResolution::Rename => {
                 *dest_path = unique_path(dest_path);
             }
+            ConflictResolution::Ask => {
+                return Err(ActionOutcome {
+                    action_type,
+                    status: ActionResultStatus::Skipped,
+                    details: None,
+                    error: Some("Destination exists; waiting for a decision".to_string()),
+                    error_kind: Some(ActionErrorKind::ConflictPending(dest_path.clone())),
+                });
+            }
+            ConflictResolution::UniqueSuffix => unreachable!("named above"),
         }
     }
-    Ok(())
+    Ok(None)
+}
+
+/// Notes the parts of the unique suffix the file's name got, if it got one.
+fn record_suffix(mut outcome: ActionOutcome, suffix: Option<&NameSuffix>) -> ActionOutcome {
+    if let (Some(suffix), Some(details)) = (suffix, outcome.details.as_mut()) {
+        suffix.record(&mut details.metadata);
+    }
+    outcome
+}
+
+/// Notes where a run stopped on a conflict the user is asked about: the file
+/// as it was then and the action's position in the rule, which is where
+/// resolving the conflict picks up. Returns whether it did stop there.
+pub(crate) fn mark_suspended(outcome: &mut ActionOutcome, source: &Path, index: usize) -> bool {
+    let Some(ActionErrorKind::ConflictPending(dest)) = &outcome.error_kind else {
+        return false;
+    };
+    let mut metadata = HashMap::new();
+    metadata.insert(CONFLICT_INDEX_KEY.to_string(), index.to_string());
+    outcome.details = Some(ActionDetails {
+        source_path: source.to_string_lossy().to_string(),
+        destination_path: Some(dest.to_string_lossy().to_string()),
+        metadata,
+    });
+    true
+}
+
+/// How a move, copy or sort splits its destination into subfolders, if it does.
+fn auto_partition(action: &Action) -> Option<&AutoPartition> {
+    match action {
+        Action::Move(action) => action.auto_partition.as_ref(),
+        Action::Copy(action) => action.auto_partition.as_ref(),
+        Action::SortIntoSubfolder(action) => action.auto_partition.as_ref(),
+        _ => None,
+    }
+}
+
+/// Notes the partition a file went into: the folder it landed in, which
+/// conflict handling may have renamed it within but never moved it out of.
+fn record_partition(outcome: &mut ActionOutcome) {
+    let Some(details) = outcome.details.as_mut() else {
+        return;
+    };
+    let subdir = details
+        .destination_path
+        .as_deref()
+        .map(Path::new)
+        .and_then(Path::parent)
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().to_string());
+    if let Some(subdir) = subdir {
+        details.metadata.insert(PARTITION_KEY.to_string(), subdir);
+    }
+}
+
+/// How a move, copy, sort or rename resolves a taken destination, and
+/// whether it skips one that's there already.
+fn conflict_policy(action: &Action) -> Option<(&ConflictResolution, bool)> {
+    match action {
+        Action::Move(action) => Some((&action.on_conflict, action.skip_duplicates)),
+        Action::Copy(action) => Some((&action.on_conflict, action.skip_duplicates)),
+        Action::SortIntoSubfolder(action) => Some((&action.on_conflict, false)),
+        Action::Rename(action) => Some((&action.on_conflict, false)),
+        _ => None,
+    }
 }
 
-fn unique_path(path: &Path) -> PathBuf {
-    if !path.exists() {
+/// What a dry run expects the action's conflict handling to do.
+enum Predicted {
+    Free,
+    Renamed,
+    Replaced,
+    Suffixed(NameSuffix),
+    Asked,
+}
+
+impl Predicted {
+    fn record(&self, metadata: &mut HashMap<String, String>) {
+        let label = match self {
+            Predicted::Free => return,
+            Predicted::Renamed => "rename",
+            Predicted::Replaced => "replace",
+            Predicted::Suffixed(suffix) => {
+                suffix.record(metadata);
+                "unique_suffix"
+            }
+            Predicted::Asked => "ask",
+        };
+        metadata.insert(DRY_RUN_CONFLICT_KEY.to_string(), label.to_string());
+    }
+}
+
+/// `prepare_destination` without touching anything: a destination counts as
+/// taken when it's on disk or `claimed`. Returns why the action would be
+/// skipped instead.
+fn predict_conflict(
+    dest_path: &mut PathBuf,
+    conflict: &ConflictResolution,
+    skip_duplicates: bool,
+    claimed: &HashSet<PathBuf>,
+) -> Result<Predicted, String> {
+    let taken = |path: &Path| path.exists() || claimed.contains(path);
+    let skipped = || Err("Destination exists; would be skipped".to_string());
+    if *conflict == ConflictResolution::UniqueSuffix {
+        if skip_duplicates && taken(dest_path.as_path()) {
+            return skipped();
+        }
+        let (unique, suffix) = unique_name::next(dest_path);
+        *dest_path = unique;
+        return Ok(Predicted::Suffixed(suffix));
+    }
+    if !taken(dest_path.as_path()) {
+        return Ok(Predicted::Free);
+    }
+    if skip_duplicates {
+        return skipped();
+    }
+    match conflict {
+        ConflictResolution::Skip => skipped(),
+        ConflictResolution::Replace => Ok(Predicted::Replaced),
+        ConflictResolution::Rename => {
+            *dest_path = unique_path_probed(dest_path, taken);
+            Ok(Predicted::Renamed)
+        }
+        ConflictResolution::Ask => Ok(Predicted::Asked),
+        ConflictResolution::UniqueSuffix => unreachable!("named above"),
+    }
+}
+
+/// A copy of `action` resolving conflicts with `policy`.
+pub(crate) fn with_policy(action: &Action, policy: &ConflictResolution) -> Action {
+    let mut action = action.clone();
+    match &mut action {
+        Action::Move(action) => action.on_conflict = policy.clone(),
+        Action::Copy(action) => action.on_conflict = policy.clone(),
+        Action::SortIntoSubfolder(action) => action.on_conflict = policy.clone(),
+        Action::Rename(action) => action.on_conflict = policy.clone(),
+        _ => {}
+    }
+    action
+}
+
+pub(crate) fn unique_path(path: &Path) -> PathBuf {
+    unique_path_probed(path, |path| path.exists())
+}
+
+/// `unique_path`, asking `exists` whether a name is taken.
+fn unique_path_probed(path: &Path, exists: impl Fn(&Path) -> bool) -> PathBuf {
+    if !exists(path) {
         return path.to_path_buf();
     }
 
@@ -922,7 +2124,7 @@ fn unique_path(path: &Path) -> PathBuf {
         } else {
             parent.join(format!("{} ({}).{}", stem, i, ext))
         };
-        if !candidate.exists() {
+        if !exists(&candidate) {
             return candidate;
         }
         i += 1;
@@ -940,6 +2142,60 @@ fn searchable_output_path(path: &Path) -> PathBuf {
     unique_path(&candidate)
 }
 
+/// Runs a script action with the file's path in FILE_PATH and its idempotency
+/// key in FD_IDEMPOTENCY_KEY.
+fn execute_script(command: &str, source_path: &Path, idempotency_key: &str) -> ActionOutcome {
+    // Try to execute the script, with fallback on Windows
+    let result = if cfg!(target_os = "windows") {
+        // On Windows: Try PowerShell first, fall back to cmd.exe
+        let ps_result = Command::new("powershell")
+            .arg("-NoProfile")
+            .arg("-ExecutionPolicy")
+            .arg("Bypass")
+            .arg("-Command")
+            .arg(command)
+            .env("FILE_PATH", source_path)
+            .env(IDEMPOTENCY_ENV, idempotency_key)
+            .status();
+
+        match ps_result {
+            Ok(status) if status.success() => {
+                return success_outcome(ActionType::RunScript, source_path, None);
+            }
+            Ok(status) => {
+                // PowerShell ran but script failed - return error
+                return error_outcome(
+                    ActionType::RunScript,
+                    format!("PowerShell script failed: {status}"),
+                );
+            }
+            Err(_) => {
+                // PowerShell not found or failed to start, try cmd.exe
+                Command::new("cmd")
+                    .arg("/C")
+                    .arg(command)
+                    .env("FILE_PATH", source_path)
+                    .env(IDEMPOTENCY_ENV, idempotency_key)
+                    .status()
+            }
+        }
+    } else {
+        // Unix: use sh
+        Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("FILE_PATH", source_path)
+            .env(IDEMPOTENCY_ENV, idempotency_key)
+            .status()
+    };
+
+    match result {
+        Ok(status) if status.success() => success_outcome(ActionType::RunScript, source_path, None),
+        Ok(status) => error_outcome(ActionType::RunScript, format!("Script failed: {status}")),
+        Err(err) => error_outcome(ActionType::RunScript, err.to_string()),
+    }
+}
+
 fn success_outcome(action_type: ActionType, source: &Path, dest: Option<PathBuf>) -> ActionOutcome {
     ActionOutcome {
         action_type,
@@ -950,7 +2206,138 @@ fn success_outcome(action_type: ActionType, source: &Path, dest: Option<PathBuf>
             metadata: HashMap::new(),
         }),
         error: None,
+        error_kind: None,
+    }
+}
+
+/// Gives a file that a move or copy just landed the mode and group `perms`
+/// asks for. A failure turns the outcome into an error, but the details still
+/// point at where the file now is.
+fn apply_destination_permissions(
+    mut outcome: ActionOutcome,
+    perms: &DestinationPermissions,
+) -> ActionOutcome {
+    if outcome.status != ActionResultStatus::Success || *perms == DestinationPermissions::Keep {
+        return outcome;
+    }
+    let Some(details) = outcome.details.as_mut() else {
+        return outcome;
+    };
+    let Some(dest) = details.destination_path.clone() else {
+        return outcome;
+    };
+    match file_mode::apply(Path::new(&dest), perms) {
+        Ok(Some(change)) => change.record(&mut details.metadata),
+        Ok(None) => {
+            details.metadata.insert(
+                PERMISSIONS_NOTE_KEY.to_string(),
+                "File permissions aren't applied on this platform".to_string(),
+            );
+        }
+        Err(message) => {
+            outcome.status = ActionResultStatus::Error;
+            outcome.error = Some(message);
+        }
+    }
+    outcome
+}
+
+/// Skips a move or copy that would strip the quarantine attribute when the
+/// user hasn't acknowledged in settings what that does.
+fn quarantine_refusal(
+    action_type: ActionType,
+    handling: QuarantineHandling,
+    allow_strip: bool,
+) -> Option<ActionOutcome> {
+    let message = quarantine::check_allowed(handling, allow_strip).err()?;
+    Some(ActionOutcome {
+        action_type,
+        status: ActionResultStatus::Skipped,
+        details: None,
+        error: Some(message),
+        error_kind: None,
+    })
+}
+
+/// Gives a file that a move or copy just landed the quarantine attribute
+/// `handling` asks for, recording whether it had one before and after.
+/// `before` is what the source carried. A failure turns the outcome into an
+/// error, but the details still point at where the file now is.
+fn apply_quarantine(
+    mut outcome: ActionOutcome,
+    handling: QuarantineHandling,
+    before: Option<&[u8]>,
+    xattrs: &dyn Xattrs,
+) -> ActionOutcome {
+    if outcome.status != ActionResultStatus::Success || handling == QuarantineHandling::Keep {
+        return outcome;
+    }
+    let Some(details) = outcome.details.as_mut() else {
+        return outcome;
+    };
+    let Some(dest) = details.destination_path.clone() else {
+        return outcome;
+    };
+    match quarantine::settle(xattrs, handling, before, Path::new(&dest)) {
+        Ok(Some(change)) => change.record(&mut details.metadata),
+        Ok(None) => {
+            details.metadata.insert(
+                quarantine::QUARANTINE_NOTE_KEY.to_string(),
+                "Quarantine attributes only exist on macOS".to_string(),
+            );
+        }
+        Err(err) => {
+            outcome.status = ActionResultStatus::Error;
+            outcome.error = Some(format!("Couldn't update the quarantine attribute: {err}"));
+        }
+    }
+    outcome
+}
+
+/// Gives the file at `path` tags in the platform's `format`, recording the
+/// ones it ends up with. Skipped rather than failed where it can't carry tags.
+fn set_tags(
+    xattrs: &dyn Xattrs,
+    format: Option<TagFormat>,
+    path: &Path,
+    tags: &[String],
+    mode: TagMode,
+) -> ActionOutcome {
+    match file_tags::write(xattrs, format, path, tags, mode) {
+        Ok(Some(tags)) => {
+            let mut outcome = success_outcome(ActionType::SetTags, path, None);
+            if let Some(details) = outcome.details.as_mut() {
+                details
+                    .metadata
+                    .insert(TAGS_KEY.to_string(), tags.join(", "));
+            }
+            outcome
+        }
+        Ok(None) => {
+            let reason = if format.is_some() && xattrs.supported() {
+                "The volume can't store tags"
+            } else {
+                "Tags can only be set on macOS and Linux"
+            };
+            let mut outcome = success_outcome(ActionType::SetTags, path, None);
+            outcome.status = ActionResultStatus::Skipped;
+            outcome.error = Some(reason.to_string());
+            outcome
+        }
+        Err(err) => error_outcome(ActionType::SetTags, format!("Couldn't set tags: {err}")),
+    }
+}
+
+/// Marks the hand-off to another folder; the engine reads the target from the
+/// metadata and runs that folder's rules against `source`.
+pub(crate) fn dispatch_outcome(folder_id: &str, source: &Path) -> ActionOutcome {
+    let mut outcome = success_outcome(ActionType::DispatchToFolder, source, None);
+    if let Some(details) = outcome.details.as_mut() {
+        details
+            .metadata
+            .insert(DISPATCH_TARGET_KEY.to_string(), folder_id.to_string());
     }
+    outcome
 }
 
 fn dry_run_outcome(action_type: ActionType, source: &Path, dest: Option<PathBuf>) -> ActionOutcome {
@@ -958,13 +2345,14 @@ fn dry_run_outcome(action_type: ActionType, source: &Path, dest: Option<PathBuf>
     metadata.insert("dry_run".to_string(), "true".to_string());
     ActionOutcome {
         action_type,
-        status: ActionResultStatus::Skipped,
+        status: ActionResultStatus::Simulated,
         details: Some(ActionDetails {
             source_path: source.to_string_lossy().to_string(),
             destination_path: dest.map(|p| p.to_string_lossy().to_string()),
             metadata,
         }),
         error: Some("Dry run".to_string()),
+        error_kind: None,
     }
 }
 
@@ -974,7 +2362,72 @@ fn error_outcome(action_type: ActionType, message: String) -> ActionOutcome {
         status: ActionResultStatus::Error,
         details: None,
         error: Some(message),
+        error_kind: None,
+    }
+}
+
+/// Like `error_outcome`, but tags failures caused by another process holding
+/// the source open, or that may otherwise clear up, so the engine can retry
+/// instead of giving up.
+fn io_error_outcome(action_type: ActionType, source: &Path, err: std::io::Error) -> ActionOutcome {
+    if is_lock_error(&err) || is_locked(source) {
+        return ActionOutcome {
+            action_type,
+            status: ActionResultStatus::Error,
+            details: None,
+            error: Some(format!("File is in use by another process: {}", err)),
+            error_kind: Some(ActionErrorKind::Locked),
+        };
+    }
+    let mut outcome = error_outcome(action_type, err.to_string());
+    if is_transient(&err) {
+        outcome.error_kind = Some(ActionErrorKind::Transient);
+    }
+    outcome
+}
+
+fn placeholder_refusal(action_type: ActionType, info: &FileInfo) -> Option<ActionOutcome> {
+    if !info.is_placeholder {
+        return None;
+    }
+    Some(ActionOutcome {
+        action_type,
+        status: ActionResultStatus::Error,
+        details: None,
+        error: Some("File is an online-only placeholder and hasn't been downloaded".to_string()),
+        error_kind: Some(ActionErrorKind::Placeholder),
+    })
+}
+
+/// Refuses a transfer to a volume that's offline before anything is written,
+/// so the file waits for the volume instead of failing on the first write.
+pub(crate) fn offline_refusal(action_type: ActionType, dest: &Path) -> Option<ActionOutcome> {
+    let root = offline_volume(dest)?;
+    Some(ActionOutcome {
+        action_type,
+        status: ActionResultStatus::Error,
+        details: None,
+        error: Some(format!("Destination volume offline: {}", root.display())),
+        error_kind: Some(ActionErrorKind::VolumeOffline(root)),
+    })
+}
+
+fn permission_outcome(action_type: ActionType, denied: PermissionDenied) -> ActionOutcome {
+    ActionOutcome {
+        action_type,
+        status: ActionResultStatus::Error,
+        details: None,
+        error: Some(denied.to_string()),
+        error_kind: Some(ActionErrorKind::PermissionDenied(denied.category)),
+    }
+}
+
+fn pdf_error_outcome(err: anyhow::Error) -> ActionOutcome {
+    let mut outcome = error_outcome(ActionType::MakePdfSearchable, err.to_string());
+    if err.downcast_ref::<PdfiumUnavailable>().is_some() {
+        outcome.error_kind = Some(ActionErrorKind::PdfiumUnavailable);
     }
+    outcome
 }
 
 #[cfg(test)]
@@ -1129,6 +2582,67 @@ mod tests {
         assert_eq!(dest_path, dir.path().join("existing (1).txt"));
     }
 
+    #[test]
+    fn unique_suffix_never_asks_whether_the_destination_exists() {
+        let dir = tempdir().unwrap();
+        let original = dir.path().join("scan.pdf");
+        fs::write(&original, "already here").unwrap();
+        let probes = std::cell::Cell::new(0);
+        let exists = |path: &Path| {
+            probes.set(probes.get() + 1);
+            path.exists()
+        };
+
+        let mut dest_path = original.clone();
+        let suffix = prepare_destination_probed(
+            ActionType::Move,
+            &mut dest_path,
+            ConflictResolution::UniqueSuffix,
+            false,
+            exists,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(probes.get(), 0);
+        let name = format!("scan.{}.pdf", suffix.render(&unique_name::format()));
+        assert_eq!(dest_path, dir.path().join(name));
+
+        let mut dest_path = original.clone();
+        prepare_destination_probed(
+            ActionType::Move,
+            &mut dest_path,
+            ConflictResolution::Rename,
+            false,
+            exists,
+        )
+        .unwrap();
+        assert_eq!(probes.get(), 1);
+    }
+
+    #[test]
+    fn a_unique_suffix_name_is_where_the_outcome_says_the_file_went() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("scan.pdf");
+        fs::write(&source, "scan").unwrap();
+
+        let outcome = transfer_file(
+            ActionType::Move,
+            Transfer::Move,
+            &source,
+            dir.path().join("out/scan.pdf"),
+            ConflictResolution::UniqueSuffix,
+            false,
+        );
+        assert_eq!(outcome.status, ActionResultStatus::Success);
+        let details = outcome.details.unwrap();
+        let landed = PathBuf::from(details.destination_path.unwrap());
+        assert!(landed.exists());
+        let name = landed.file_name().unwrap().to_string_lossy().to_string();
+        let random = &details.metadata[unique_name::UNIQUE_RANDOM_KEY];
+        assert!(name.starts_with("scan.") && name.ends_with(".pdf"));
+        assert!(name.contains(&format!(".{random}")));
+    }
+
     // ==================== WINDOWS CASE-ONLY RENAME TESTS ====================
 
     #[test]
@@ -1173,6 +2687,280 @@ mod tests {
         assert_eq!(fs::read_to_string(&dest).unwrap(), "content");
     }
 
+    // ==================== DESTINATION PERMISSION TESTS ====================
+
+    #[test]
+    fn destination_permissions_keep_leaves_the_outcome_alone() {
+        let dir = tempdir().unwrap();
+        let dest = dir.path().join("report.pdf");
+        fs::write(&dest, "content").unwrap();
+        let outcome = success_outcome(ActionType::Move, Path::new("/in/report.pdf"), Some(dest));
+
+        let kept = apply_destination_permissions(outcome.clone(), &DestinationPermissions::Keep);
+
+        assert_eq!(kept.status, ActionResultStatus::Success);
+        assert!(kept.details.unwrap().metadata.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn destination_permissions_failure_keeps_the_landed_path() {
+        let dir = tempdir().unwrap();
+        let dest = dir.path().join("report.pdf");
+        fs::write(&dest, "content").unwrap();
+        let outcome =
+            success_outcome(ActionType::Copy, Path::new("/in/report.pdf"), Some(dest.clone()));
+        let perms = DestinationPermissions::Explicit {
+            mode: None,
+            group: Some("no-such-group-for-dispatch".to_string()),
+        };
+
+        let failed = apply_destination_permissions(outcome, &perms);
+
+        assert_eq!(failed.status, ActionResultStatus::Error);
+        assert!(failed.error.unwrap().contains("no-such-group-for-dispatch"));
+        assert_eq!(
+            failed.details.unwrap().destination_path,
+            Some(dest.to_string_lossy().to_string())
+        );
+    }
+
+    // ==================== QUARANTINE TESTS ====================
+
+    const DOWNLOADED: &[u8] = b"0083;66b1c2d3;Safari;";
+
+    #[test]
+    fn quarantine_preserve_survives_the_copy_fallback() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("app.dmg");
+        let dest = dir.path().join("apps").join("app.dmg");
+        fs::write(&source, b"dmg").unwrap();
+        fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        let xattrs = xattr::MemoryXattrs::default();
+        xattrs
+            .set(&source, quarantine::ATTRIBUTE, DOWNLOADED)
+            .unwrap();
+
+        let before = quarantine::capture(&xattrs, QuarantineHandling::Preserve, &source);
+        // The copy a rename across volumes falls back to carries no attributes.
+        move_fallback(&source, &dest).unwrap();
+        assert_eq!(xattrs.get(&dest, quarantine::ATTRIBUTE).unwrap(), None);
+
+        let outcome = apply_quarantine(
+            success_outcome(ActionType::Move, &source, Some(dest.clone())),
+            QuarantineHandling::Preserve,
+            before.as_deref(),
+            &xattrs,
+        );
+        assert_eq!(outcome.status, ActionResultStatus::Success);
+        assert_eq!(
+            xattrs.get(&dest, quarantine::ATTRIBUTE).unwrap().as_deref(),
+            Some(DOWNLOADED)
+        );
+        let metadata = outcome.details.unwrap().metadata;
+        assert_eq!(metadata[quarantine::QUARANTINE_BEFORE_KEY], "present");
+        assert_eq!(metadata[quarantine::QUARANTINE_AFTER_KEY], "present");
+    }
+
+    #[test]
+    fn quarantine_strip_is_refused_until_acknowledged() {
+        let refused = quarantine_refusal(ActionType::Move, QuarantineHandling::Strip, false)
+            .expect("strip refused");
+        assert_eq!(refused.status, ActionResultStatus::Skipped);
+        assert!(refused.error.unwrap().contains("disabled in settings"));
+
+        assert!(quarantine_refusal(ActionType::Move, QuarantineHandling::Strip, true).is_none());
+        assert!(
+            quarantine_refusal(ActionType::Copy, QuarantineHandling::Preserve, false).is_none()
+        );
+    }
+
+    #[test]
+    fn set_tags_records_the_tags_and_skips_where_tags_cant_be_stored() {
+        let xattrs = xattr::MemoryXattrs::default();
+        let path = Path::new("/inbox/invoice.pdf");
+        let format = Some(TagFormat::Xdg);
+        let invoices = ["Invoices".to_string()];
+        let outcome = set_tags(&xattrs, format, path, &invoices, TagMode::Append);
+        assert_eq!(outcome.status, ActionResultStatus::Success);
+        assert_eq!(outcome.details.unwrap().metadata[TAGS_KEY], "Invoices");
+
+        let paid = ["Paid".to_string()];
+        let outcome = set_tags(&xattrs, format, path, &paid, TagMode::Append);
+        assert_eq!(
+            outcome.details.unwrap().metadata[TAGS_KEY],
+            "Invoices, Paid"
+        );
+        let outcome = set_tags(&xattrs, format, path, &paid, TagMode::Replace);
+        assert_eq!(outcome.details.unwrap().metadata[TAGS_KEY], "Paid");
+
+        let outcome = set_tags(&xattrs, None, path, &paid, TagMode::Append);
+        assert_eq!(outcome.status, ActionResultStatus::Skipped);
+        assert_eq!(
+            outcome.error.unwrap(),
+            "Tags can only be set on macOS and Linux"
+        );
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn quarantine_preserve_survives_the_copy_fallback_on_disk() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("app.dmg");
+        let dest = dir.path().join("app-moved.dmg");
+        fs::write(&source, b"dmg").unwrap();
+        xattr::System
+            .set(&source, quarantine::ATTRIBUTE, DOWNLOADED)
+            .unwrap();
+
+        let before = quarantine::capture(&xattr::System, QuarantineHandling::Preserve, &source);
+        move_fallback(&source, &dest).unwrap();
+        let outcome = apply_quarantine(
+            success_outcome(ActionType::Move, &source, Some(dest.clone())),
+            QuarantineHandling::Preserve,
+            before.as_deref(),
+            &xattr::System,
+        );
+        assert_eq!(outcome.status, ActionResultStatus::Success);
+        assert_eq!(
+            xattr::System
+                .get(&dest, quarantine::ATTRIBUTE)
+                .unwrap()
+                .as_deref(),
+            Some(DOWNLOADED)
+        );
+    }
+
+    // ==================== COMPANION TESTS ====================
+
+    fn companions(extensions: &[&str], missing_ok: bool) -> BringCompanions {
+        BringCompanions {
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+            pattern: None,
+            missing_ok,
+            fail_on_error: false,
+            record_matches: true,
+        }
+    }
+
+    /// Moves `name` from `inbox` into `archive` and brings its companions.
+    fn move_with_companions(
+        inbox: &Path,
+        archive: &Path,
+        name: &str,
+        spec: &BringCompanions,
+    ) -> (ActionOutcome, Vec<ActionOutcome>) {
+        let mut primary = transfer_file(
+            ActionType::Move,
+            Transfer::Move,
+            &inbox.join(name),
+            archive.join(name),
+            ConflictResolution::Rename,
+            false,
+        );
+        let plan = CompanionPlan {
+            spec,
+            pattern: None,
+            transfer: Transfer::Move,
+            conflict: &ConflictResolution::Rename,
+            skip_duplicates: false,
+            perms: &DestinationPermissions::Keep,
+            quarantine: QuarantineHandling::Keep,
+        };
+        let outcomes = transfer_companions(&mut primary, &plan);
+        (primary, outcomes)
+    }
+
+    #[test]
+    fn companions_move_along_with_the_file() {
+        let dir = tempdir().unwrap();
+        let (inbox, archive) = (dir.path().join("inbox"), dir.path().join("archive"));
+        fs::create_dir_all(&inbox).unwrap();
+        for name in ["IMG_1.CR2", "IMG_1.xmp", "IMG_1.jpg", "IMG_2.jpg"] {
+            fs::write(inbox.join(name), name).unwrap();
+        }
+
+        let spec = companions(&["xmp", "jpg"], false);
+        let (primary, outcomes) = move_with_companions(&inbox, &archive, "IMG_1.CR2", &spec);
+
+        assert_eq!(primary.status, ActionResultStatus::Success);
+        let metadata = &primary.details.unwrap().metadata;
+        assert_eq!(metadata.get(COMPANIONS_KEY).map(String::as_str), Some("2"));
+        assert_eq!(outcomes.len(), 2);
+        for outcome in &outcomes {
+            assert_eq!(outcome.status, ActionResultStatus::Success);
+            assert_eq!(outcome.action_type, ActionType::Move);
+            let details = outcome.details.as_ref().unwrap();
+            assert_eq!(
+                details.metadata.get(COMPANION_OF_KEY),
+                Some(&inbox.join("IMG_1.CR2").to_string_lossy().to_string())
+            );
+            assert!(details.metadata.contains_key(COMPANION_RECORD_KEY));
+        }
+        for name in ["IMG_1.CR2", "IMG_1.xmp", "IMG_1.jpg"] {
+            assert_eq!(fs::read_to_string(archive.join(name)).unwrap(), name);
+            assert!(!inbox.join(name).exists());
+        }
+        assert!(inbox.join("IMG_2.jpg").exists());
+    }
+
+    #[test]
+    fn a_missing_companion_is_reported_unless_allowed() {
+        let dir = tempdir().unwrap();
+        let (inbox, archive) = (dir.path().join("inbox"), dir.path().join("archive"));
+        fs::create_dir_all(&inbox).unwrap();
+        for name in ["a.CR2", "a.xmp", "b.CR2", "b.xmp", "c.CR2", "c.xmp"] {
+            fs::write(inbox.join(name), name).unwrap();
+        }
+
+        let (primary, outcomes) =
+            move_with_companions(&inbox, &archive, "a.CR2", &companions(&["xmp", "jpg"], true));
+        assert_eq!(primary.status, ActionResultStatus::Success);
+        assert_eq!(outcomes.len(), 1);
+        assert!(archive.join("a.xmp").exists());
+
+        let (primary, outcomes) =
+            move_with_companions(&inbox, &archive, "b.CR2", &companions(&["xmp", "jpg"], false));
+        assert_eq!(primary.status, ActionResultStatus::Success);
+        let missing = outcomes.last().unwrap();
+        assert_eq!(missing.status, ActionResultStatus::Error);
+        assert!(missing.error.as_deref().unwrap().contains("b.jpg"));
+        assert!(archive.join("b.xmp").exists());
+
+        let mut strict = companions(&["xmp", "jpg"], false);
+        strict.fail_on_error = true;
+        let (primary, _) = move_with_companions(&inbox, &archive, "c.CR2", &strict);
+        assert_eq!(primary.status, ActionResultStatus::Error);
+        assert!(primary.error.unwrap().contains("c.jpg"));
+        // What landed stays where it is.
+        assert!(archive.join("c.CR2").exists());
+        assert!(archive.join("c.xmp").exists());
+    }
+
+    #[test]
+    fn a_companion_conflict_is_resolved_like_the_file() {
+        let dir = tempdir().unwrap();
+        let (inbox, archive) = (dir.path().join("inbox"), dir.path().join("archive"));
+        fs::create_dir_all(&inbox).unwrap();
+        fs::create_dir_all(&archive).unwrap();
+        fs::write(inbox.join("IMG_1.CR2"), "raw").unwrap();
+        fs::write(inbox.join("IMG_1.xmp"), "new").unwrap();
+        fs::write(archive.join("IMG_1.xmp"), "old").unwrap();
+
+        let (primary, outcomes) =
+            move_with_companions(&inbox, &archive, "IMG_1.CR2", &companions(&["xmp"], false));
+
+        assert_eq!(primary.status, ActionResultStatus::Success);
+        assert_eq!(outcomes[0].status, ActionResultStatus::Success);
+        let renamed = archive.join("IMG_1 (1).xmp");
+        assert_eq!(
+            outcomes[0].details.as_ref().unwrap().destination_path,
+            Some(renamed.to_string_lossy().to_string())
+        );
+        assert_eq!(fs::read_to_string(&renamed).unwrap(), "new");
+        assert_eq!(fs::read_to_string(archive.join("IMG_1.xmp")).unwrap(), "old");
+    }
+
     // ==================== TEMP RENAME TESTS ====================
 
     #[test]
@@ -1228,6 +3016,87 @@ mod tests {
         assert_eq!(outcome.error, Some("Permission denied".to_string()));
     }
 
+    #[test]
+    fn io_error_outcome_flags_locked_source() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("busy.txt");
+        fs::write(&source, "data").unwrap();
+
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let outcome = io_error_outcome(ActionType::Move, &source, denied);
+        assert_eq!(outcome.error_kind, None);
+
+        let _guard = crate::utils::file_lock::open_exclusive(&source).unwrap();
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let outcome = io_error_outcome(ActionType::Move, &source, denied);
+        assert_eq!(outcome.status, ActionResultStatus::Error);
+        assert_eq!(outcome.error_kind, Some(ActionErrorKind::Locked));
+    }
+
+    #[test]
+    fn io_error_outcome_flags_errors_worth_retrying() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("report.pdf");
+        fs::write(&source, "data").unwrap();
+
+        let timed_out = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        let outcome = io_error_outcome(ActionType::Move, &source, timed_out);
+        assert_eq!(outcome.error_kind, Some(ActionErrorKind::Transient));
+
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let outcome = io_error_outcome(ActionType::Move, &source, missing);
+        assert_eq!(outcome.error_kind, None);
+    }
+
+    #[test]
+    fn transfers_to_an_offline_volume_are_told_apart() {
+        let dir = tempdir().unwrap();
+        let nas = dir.path().join("nas");
+        crate::utils::volumes::mock_volume(&nas);
+        let dest = nas.join("Archive/scan.pdf");
+
+        let outcome = offline_refusal(ActionType::Copy, &dest).unwrap();
+        assert_eq!(outcome.status, ActionResultStatus::Error);
+        assert_eq!(outcome.error_kind, Some(ActionErrorKind::VolumeOffline(nas.clone())));
+        assert!(outcome.error.unwrap().starts_with("Destination volume offline"));
+
+        fs::create_dir(&nas).unwrap();
+        assert!(offline_refusal(ActionType::Copy, &dest).is_none());
+        assert!(offline_refusal(ActionType::Copy, &dir.path().join("local/scan.pdf")).is_none());
+    }
+
+    #[test]
+    fn placeholder_refusal_blocks_only_placeholders() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("cloud.pdf");
+        fs::write(&source, "").unwrap();
+        let mut info = FileInfo::from_path(&source).unwrap();
+
+        assert!(placeholder_refusal(ActionType::Move, &info).is_none());
+
+        info.is_placeholder = true;
+        let outcome = placeholder_refusal(ActionType::Copy, &info).unwrap();
+        assert_eq!(outcome.action_type, ActionType::Copy);
+        assert_eq!(outcome.status, ActionResultStatus::Error);
+        assert_eq!(outcome.error_kind, Some(ActionErrorKind::Placeholder));
+    }
+
+    #[test]
+    fn permission_outcome_names_protected_category() {
+        let denied = PermissionDenied {
+            category: ProtectedCategory::Documents,
+            path: PathBuf::from("/Users/alex/Documents/Invoices"),
+        };
+        let outcome = permission_outcome(ActionType::Move, denied);
+
+        assert_eq!(outcome.status, ActionResultStatus::Error);
+        assert_eq!(
+            outcome.error_kind,
+            Some(ActionErrorKind::PermissionDenied(ProtectedCategory::Documents))
+        );
+        assert!(outcome.error.unwrap().contains("Documents folder"));
+    }
+
     // ==================== ACTION RESULT STATUS TESTS ====================
 
     #[test]
@@ -1283,6 +3152,99 @@ mod tests {
         assert!(dest.exists());
     }
 
+    // ==================== FOLDER STRUCTURE TESTS ====================
+
+    fn project_captures(file_name: &str) -> HashMap<String, String> {
+        let regex = regex::Regex::new(r"^NEWPROJECT-(.+)\.txt$").unwrap();
+        let caps = regex.captures(file_name).unwrap();
+        let mut captures = HashMap::new();
+        captures.insert("1".to_string(), caps[1].to_string());
+        captures
+    }
+
+    fn resolve_entries(info: &FileInfo, captures: &HashMap<String, String>) -> Vec<String> {
+        let engine = PatternEngine::new();
+        ["{1}/incoming", "{1}/working", "{1}/final"]
+            .iter()
+            .map(|entry| engine.resolve(entry, info, captures))
+            .collect()
+    }
+
+    #[test]
+    fn create_folder_structure_from_regex_capture() {
+        let dir = tempdir().unwrap();
+        let trigger = dir.path().join("NEWPROJECT-acme.txt");
+        fs::write(&trigger, "").unwrap();
+        let info = FileInfo::from_path(&trigger).unwrap();
+        let captures = project_captures(&info.full_name);
+        let base = dir.path().join("projects");
+
+        let result = create_folder_structure(&base, &resolve_entries(&info, &captures)).unwrap();
+
+        for sub in ["incoming", "working", "final"] {
+            assert!(base.join("acme").join(sub).is_dir());
+        }
+        assert_eq!(result.created.len(), 4);
+        assert!(result.existing.is_empty());
+    }
+
+    #[test]
+    fn create_folder_structure_is_idempotent() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().join("projects");
+        let entries = vec!["acme/incoming".to_string(), "acme/final".to_string()];
+
+        create_folder_structure(&base, &entries).unwrap();
+        fs::write(base.join("acme/incoming/keep.txt"), "data").unwrap();
+        let rerun = create_folder_structure(&base, &entries).unwrap();
+
+        assert!(rerun.created.is_empty());
+        assert_eq!(rerun.existing.len(), 3);
+        assert!(base.join("acme/incoming/keep.txt").exists());
+        let metadata = rerun.metadata();
+        assert_eq!(metadata.get("created").map(String::as_str), Some(""));
+        assert!(metadata["existing"].contains("incoming"));
+    }
+
+    #[test]
+    fn create_folder_structure_rejects_traversal() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().join("projects");
+        let outside = dir.path().join("outside").to_string_lossy().to_string();
+
+        for entry in ["../escape", "acme/../../escape", outside.as_str(), ""] {
+            assert!(validate_structure_entry(entry).is_err(), "{entry:?} accepted");
+        }
+        let entries = vec!["acme/incoming".to_string(), "../escape".to_string()];
+        assert!(create_folder_structure(&base, &entries).is_err());
+        // Validation happens up front, so nothing is created on rejection.
+        assert!(!base.exists());
+        assert!(!dir.path().join("escape").exists());
+
+        assert!(validate_structure_entry("{1}/incoming").is_ok());
+        assert!(validate_structure_entry("./acme/final").is_ok());
+    }
+
+    #[test]
+    fn create_folder_structure_then_move_trigger_inside() {
+        let dir = tempdir().unwrap();
+        let trigger = dir.path().join("NEWPROJECT-acme.txt");
+        fs::write(&trigger, "brief").unwrap();
+        let info = FileInfo::from_path(&trigger).unwrap();
+        let captures = project_captures(&info.full_name);
+        let base = dir.path().join("projects");
+
+        create_folder_structure(&base, &resolve_entries(&info, &captures)).unwrap();
+        let engine = PatternEngine::new();
+        let dest = base
+            .join(engine.resolve("{1}/incoming", &info, &captures))
+            .join(&info.full_name);
+        move_fallback(&trigger, &dest).unwrap();
+
+        assert!(!trigger.exists());
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "brief");
+    }
+
     // ==================== CROSS-DEVICE ERROR DETECTION ====================
 
     #[test]
@@ -1373,21 +3335,29 @@ mod tests {
     // --- Temp Rename Edge Cases ---
 
     #[test]
-    fn temp_rename_removes_existing_temp() {
+    fn temp_rename_leaves_no_temp_behind() {
         let dir = tempdir().unwrap();
         let source = dir.path().join("File.txt");
         let dest = dir.path().join("file.txt");
-        let temp_path = dir.path().join("File.rename_tmp.txt");
+        fs::write(&source, "content").unwrap();
+
+        temp_rename(&source, &dest).unwrap();
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .flatten()
+            .filter(|entry| crate::utils::tempfiles::is_temp_artifact(&entry.path()))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
 
-        // Create source and a pre-existing temp file
+    #[test]
+    fn temp_rename_puts_the_file_back_when_the_second_step_fails() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("File.txt");
         fs::write(&source, "content").unwrap();
-        fs::write(&temp_path, "old temp").unwrap();
 
-        let result = temp_rename(&source, &dest);
-        // On case-insensitive filesystems, this should work
-        if result.is_ok() {
-            assert!(!temp_path.exists());
-        }
+        assert!(temp_rename(&source, &dir.path().join("missing/file.txt")).is_err());
+        assert_eq!(fs::read_to_string(&source).unwrap(), "content");
     }
 
     #[test]
@@ -1668,13 +3638,14 @@ mod tests {
         fs::write(&source, "content with émojis 🎉").unwrap();
 
         // Test that archive creation handles Unicode paths
-        let result = create_archive(&source_dir, &archive, &ArchiveFormat::Zip);
+        let result = create_archive(&source_dir, &archive, &ArchiveFormat::Zip, None);
         if result.is_ok() {
             assert!(archive.exists());
             // Verify extraction also handles Unicode
             let extract_dir = dir.path().join("extract");
             fs::create_dir(&extract_dir).unwrap();
-            let extract_result = extract_archive(&archive, &extract_dir);
+            let extract_result =
+                extract_archive(&archive, &extract_dir, &ExtractFilter::default());
             assert!(extract_result.is_ok());
         }
     }
@@ -1691,7 +3662,7 @@ mod tests {
 
         fs::write(&source, "content").unwrap();
 
-        let result = create_archive(&source_dir, &archive, &ArchiveFormat::Zip);
+        let result = create_archive(&source_dir, &archive, &ArchiveFormat::Zip, None);
         if result.is_ok() {
             assert!(archive.exists());
         }
@@ -1708,7 +3679,7 @@ mod tests {
         let archive = dir.path().join("empty.zip");
 
         // Archive creation with empty directory should handle gracefully
-        let result = create_archive(&empty_dir, &archive, &ArchiveFormat::Zip);
+        let result = create_archive(&empty_dir, &archive, &ArchiveFormat::Zip, None);
         // Empty archive might fail or succeed depending on implementation
         // We just verify it doesn't panic
         let _ = result;
@@ -1935,4 +3906,139 @@ mod tests {
         // Path should have been modified
         assert_ne!(dest_path, dir.path().join("file.txt"));
     }
+
+    // ==================== IDEMPOTENCY TESTS ====================
+
+    #[test]
+    fn idempotency_keys_survive_redelivery_but_not_a_changed_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        fs::write(&path, "v1").unwrap();
+
+        let first = ActionScope::new("rule-1", &FileInfo::from_path(&path).unwrap());
+        let redelivered = ActionScope::new("rule-1", &FileInfo::from_path(&path).unwrap());
+        assert_eq!(first.idempotency_key(1), redelivered.idempotency_key(1));
+        assert_ne!(first.idempotency_key(0), first.idempotency_key(1));
+        // A retry starting at the second action keys it the same way.
+        assert_eq!(first.starting_at(1).idempotency_key(0), first.idempotency_key(1));
+        let other_rule = ActionScope::new("rule-2", &FileInfo::from_path(&path).unwrap());
+        assert_ne!(first.idempotency_key(0), other_rule.idempotency_key(0));
+
+        // Writing the file again is a separate event.
+        fs::write(&path, "version 2").unwrap();
+        let changed = ActionScope::new("rule-1", &FileInfo::from_path(&path).unwrap());
+        assert_ne!(first.idempotency_key(0), changed.idempotency_key(0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn script_receives_idempotency_key() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        fs::write(&path, "pdf").unwrap();
+        let seen = dir.path().join("seen.txt");
+        let command = format!(
+            "grep -qx \"$FD_IDEMPOTENCY_KEY\" '{0}' 2>/dev/null || echo \"$FD_IDEMPOTENCY_KEY\" >> '{0}'",
+            seen.display()
+        );
+        let key = ActionScope::new("rule-1", &FileInfo::from_path(&path).unwrap()).idempotency_key(0);
+
+        // Delivering the same event twice leaves one record behind.
+        for _ in 0..2 {
+            let outcome = execute_script(&command, &path, &key);
+            assert_eq!(outcome.status, ActionResultStatus::Success);
+        }
+        assert_eq!(fs::read_to_string(&seen).unwrap(), format!("{key}\n"));
+    }
+
+    fn dry_run_executor() -> ActionExecutor {
+        ActionExecutor::detached(
+            std::sync::Arc::new(std::sync::Mutex::new(Settings::default())),
+            std::sync::Arc::new(std::sync::Mutex::new(OcrManager::new_placeholder())),
+        )
+    }
+
+    fn move_to(destination: &Path, on_conflict: ConflictResolution) -> Action {
+        Action::Move(crate::models::MoveAction {
+            destination: format!("{}/", destination.to_string_lossy()),
+            on_conflict,
+            skip_duplicates: false,
+            destination_permissions: Default::default(),
+            quarantine: Default::default(),
+            bring_companions: None,
+            auto_partition: None,
+        })
+    }
+
+    #[test]
+    fn dry_run_predicts_conflicts_against_the_disk_and_earlier_files() {
+        let dir = tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        let archive = dir.path().join("archive");
+        fs::create_dir_all(inbox.join("nested")).unwrap();
+        fs::create_dir_all(&archive).unwrap();
+        fs::write(archive.join("taken.txt"), "there").unwrap();
+        let first = inbox.join("report.txt");
+        let second = inbox.join("nested").join("report.txt");
+        let taken = inbox.join("taken.txt");
+        for path in [&first, &second, &taken] {
+            fs::write(path, "new").unwrap();
+        }
+        let executor = dry_run_executor();
+        let captures = HashMap::new();
+        let mut claimed = HashSet::new();
+        let mut run = |path: &Path, action: Action| {
+            let info = FileInfo::from_path(path).unwrap();
+            executor.simulate_actions_claiming(&[action], &info, &captures, &mut claimed)
+        };
+
+        let outcome = run(&first, move_to(&archive, ConflictResolution::Rename)).remove(0);
+        assert_eq!(outcome.status, ActionResultStatus::Simulated);
+        let details = outcome.details.as_ref().unwrap();
+        let expected = archive.join("report.txt").to_string_lossy().to_string();
+        assert_eq!(details.destination_path.as_deref(), Some(expected.as_str()));
+        assert!(!details.metadata.contains_key(DRY_RUN_CONFLICT_KEY));
+
+        // Only the first file would have got there; nothing was moved.
+        let outcome = run(&second, move_to(&archive, ConflictResolution::Rename)).remove(0);
+        let details = outcome.details.as_ref().unwrap();
+        let renamed = archive.join("report (1).txt").to_string_lossy().to_string();
+        assert_eq!(details.destination_path.as_deref(), Some(renamed.as_str()));
+        assert_eq!(details.metadata[DRY_RUN_CONFLICT_KEY], "rename");
+        assert!(first.exists() && second.exists());
+        assert!(!archive.join("report.txt").exists());
+
+        let outcome = run(&taken, move_to(&archive, ConflictResolution::Skip)).remove(0);
+        assert_eq!(outcome.status, ActionResultStatus::Skipped);
+
+        let outcome = run(&taken, move_to(&archive, ConflictResolution::Replace)).remove(0);
+        let details = outcome.details.as_ref().unwrap();
+        let replaced = archive.join("taken.txt").to_string_lossy().to_string();
+        assert_eq!(details.metadata[DRY_RUN_CONFLICT_KEY], "replace");
+        assert_eq!(details.metadata[DRY_RUN_REMOVES_KEY], replaced);
+        assert_eq!(fs::read_to_string(archive.join("taken.txt")).unwrap(), "there");
+    }
+
+    #[test]
+    fn dry_run_predicts_paths_in_missing_folders_and_reports_removals() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("old.log");
+        fs::write(&source, "log").unwrap();
+        let missing = dir.path().join("not").join("yet");
+        let info = FileInfo::from_path(&source).unwrap();
+        let executor = dry_run_executor();
+        let actions = [
+            move_to(&missing, ConflictResolution::Rename),
+            Action::Delete(DeleteAction { permanent: false }),
+        ];
+
+        let outcomes = executor.simulate_actions(&actions, &info, &HashMap::new());
+        let moved = missing.join("old.log").to_string_lossy().to_string();
+        let details = outcomes[0].details.as_ref().unwrap();
+        assert_eq!(details.destination_path.as_deref(), Some(moved.as_str()));
+        let details = outcomes[1].details.as_ref().unwrap();
+        assert_eq!(details.metadata[DRY_RUN_REMOVES_KEY], moved);
+        assert!(!missing.exists());
+        assert!(source.exists());
+    }
 }
@@ -0,0 +1,271 @@
+//! Counts faces in images with a small local detector. Only the count leaves
+//! this module: no crops, boxes or embeddings are kept or persisted.
+
+use filedispatch_core::file_info::FileInfo;
+use serde::Serialize;
+
+use crate::core::model_manager::ModelManager;
+use crate::models::FileKind;
+
+/// Reason given when face detection can't run at all.
+pub const DETECTOR_UNAVAILABLE: &str = "detector unavailable";
+
+/// Images above this size are skipped rather than decoded.
+const MAX_IMAGE_BYTES: u64 = 25 * 1024 * 1024;
+
+/// How long a single image may take before it's given up on.
+#[cfg(feature = "faces")]
+const DETECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[cfg(test)]
+thread_local! {
+    static FACE_MODEL: std::cell::RefCell<Option<Option<std::path::PathBuf>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Test hook: makes `path` the installed face model on the current thread,
+/// `None` meaning none is installed.
+#[cfg(test)]
+pub(crate) fn set_face_model(path: Option<std::path::PathBuf>) {
+    FACE_MODEL.with(|model| *model.borrow_mut() = Some(path));
+}
+
+fn face_model_path() -> Option<std::path::PathBuf> {
+    #[cfg(test)]
+    if let Some(path) = FACE_MODEL.with(|model| model.borrow().clone()) {
+        return path;
+    }
+    ModelManager::new().ok()?.get_face_model_path()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceModelStatus {
+    /// Whether this build can detect faces at all (the `faces` feature).
+    pub supported: bool,
+    pub installed: bool,
+}
+
+pub fn model_status() -> FaceModelStatus {
+    FaceModelStatus {
+        supported: cfg!(feature = "faces"),
+        installed: face_model_path().is_some(),
+    }
+}
+
+/// Number of faces in the image, or why it couldn't be counted.
+pub fn count_faces(info: &FileInfo) -> Result<u32, String> {
+    if info.kind != FileKind::Image {
+        return Err("not an image".to_string());
+    }
+    if info.is_placeholder {
+        return Err("file is an online-only placeholder".to_string());
+    }
+    if info.size > MAX_IMAGE_BYTES {
+        return Err(format!(
+            "image is larger than {} MB",
+            MAX_IMAGE_BYTES / (1024 * 1024)
+        ));
+    }
+    if !cfg!(feature = "faces") {
+        return Err(format!(
+            "{DETECTOR_UNAVAILABLE}: built without face detection"
+        ));
+    }
+    let Some(model) = face_model_path() else {
+        return Err(format!("{DETECTOR_UNAVAILABLE}: face model not installed"));
+    };
+    detect(model, info.path.clone())
+}
+
+#[cfg(not(feature = "faces"))]
+fn detect(_model: std::path::PathBuf, _image: std::path::PathBuf) -> Result<u32, String> {
+    Err(DETECTOR_UNAVAILABLE.to_string())
+}
+
+/// Runs the detector on its own thread so a pathological image can't hold up
+/// rule evaluation past `DETECT_TIMEOUT`.
+#[cfg(feature = "faces")]
+fn detect(model: std::path::PathBuf, image: std::path::PathBuf) -> Result<u32, String> {
+    let (tx, rx) = crossbeam_channel::bounded(1);
+    std::thread::spawn(move || {
+        let _ = tx.send(detector::count(&model, &image));
+    });
+    rx.recv_timeout(DETECT_TIMEOUT).map_err(|_| {
+        format!(
+            "face detection timed out after {}s",
+            DETECT_TIMEOUT.as_secs()
+        )
+    })?
+}
+
+/// UltraFace-style ONNX detector (RFB-320): a 320x240 RGB input, per-anchor
+/// `[background, face]` scores and normalized corner boxes as outputs.
+#[cfg(feature = "faces")]
+mod detector {
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+
+    use image::imageops::FilterType;
+    use once_cell::sync::Lazy;
+    use ort::session::Session;
+    use ort::value::Tensor;
+
+    const INPUT_WIDTH: u32 = 320;
+    const INPUT_HEIGHT: u32 = 240;
+    const SCORE_THRESHOLD: f32 = 0.7;
+    const IOU_THRESHOLD: f32 = 0.3;
+
+    /// The loaded session and the model file it came from.
+    static SESSION: Lazy<Mutex<Option<(PathBuf, Session)>>> = Lazy::new(|| Mutex::new(None));
+
+    pub(super) fn count(model: &Path, image: &Path) -> Result<u32, String> {
+        let input = preprocess(image)?;
+        let mut session = SESSION
+            .lock()
+            .map_err(|_| "face detector poisoned".to_string())?;
+        if session.as_ref().map_or(true, |(path, _)| path != model) {
+            let loaded = Session::builder()
+                .and_then(|builder| builder.commit_from_file(model))
+                .map_err(|err| format!("failed to load face model: {err}"))?;
+            *session = Some((model.to_path_buf(), loaded));
+        }
+        let (_, session) = session.as_mut().expect("session loaded above");
+        let outputs = session
+            .run(ort::inputs![input])
+            .map_err(|err| format!("face detection failed: {err}"))?;
+        let (_, scores) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(|err| err.to_string())?;
+        let (_, boxes) = outputs[1]
+            .try_extract_tensor::<f32>()
+            .map_err(|err| err.to_string())?;
+        Ok(count_detections(scores, boxes))
+    }
+
+    fn preprocess(image: &Path) -> Result<Tensor<f32>, String> {
+        let decoded = image::open(image).map_err(|err| err.to_string())?.to_rgb8();
+        let resized =
+            image::imageops::resize(&decoded, INPUT_WIDTH, INPUT_HEIGHT, FilterType::Triangle);
+        let plane = (INPUT_WIDTH * INPUT_HEIGHT) as usize;
+        let mut data = vec![0f32; 3 * plane];
+        for (x, y, pixel) in resized.enumerate_pixels() {
+            let offset = (y * INPUT_WIDTH + x) as usize;
+            for channel in 0..3 {
+                data[channel * plane + offset] = (pixel[channel] as f32 - 127.0) / 128.0;
+            }
+        }
+        Tensor::from_array((
+            [1usize, 3, INPUT_HEIGHT as usize, INPUT_WIDTH as usize],
+            data,
+        ))
+        .map_err(|err| err.to_string())
+    }
+
+    /// Faces left after thresholding the scores and suppressing overlapping
+    /// boxes. `scores` holds two values per anchor, `boxes` four.
+    pub(super) fn count_detections(scores: &[f32], boxes: &[f32]) -> u32 {
+        let mut candidates: Vec<(f32, [f32; 4])> = scores
+            .chunks_exact(2)
+            .zip(boxes.chunks_exact(4))
+            .filter(|(score, _)| score[1] > SCORE_THRESHOLD)
+            .map(|(score, bbox)| (score[1], [bbox[0], bbox[1], bbox[2], bbox[3]]))
+            .collect();
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut kept: Vec<[f32; 4]> = Vec::new();
+        for (_, bbox) in candidates {
+            if kept.iter().all(|other| iou(&bbox, other) <= IOU_THRESHOLD) {
+                kept.push(bbox);
+            }
+        }
+        kept.len() as u32
+    }
+
+    fn iou(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+        let width = (a[2].min(b[2]) - a[0].max(b[0])).max(0.0);
+        let height = (a[3].min(b[3]) - a[1].max(b[1])).max(0.0);
+        let overlap = width * height;
+        let area = |r: &[f32; 4]| (r[2] - r[0]).max(0.0) * (r[3] - r[1]).max(0.0);
+        let union = area(a) + area(b) - overlap;
+        if union <= 0.0 {
+            0.0
+        } else {
+            overlap / union
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn image_info(dir: &std::path::Path) -> FileInfo {
+        let path = dir.join("beach.png");
+        image::RgbImage::from_fn(64, 48, |x, y| image::Rgb([x as u8 * 4, y as u8 * 5, 200]))
+            .save(&path)
+            .unwrap();
+        FileInfo::from_path(&path).unwrap()
+    }
+
+    #[test]
+    fn missing_model_reports_detector_unavailable() {
+        let dir = tempdir().unwrap();
+        set_face_model(None);
+        let reason = count_faces(&image_info(dir.path())).unwrap_err();
+        assert!(reason.starts_with(DETECTOR_UNAVAILABLE), "{reason}");
+    }
+
+    #[test]
+    fn only_images_under_the_cap_are_looked_at() {
+        let dir = tempdir().unwrap();
+        let text = dir.path().join("notes.txt");
+        fs::write(&text, "hello").unwrap();
+        assert_eq!(
+            count_faces(&FileInfo::from_path(&text).unwrap()).unwrap_err(),
+            "not an image"
+        );
+
+        let mut huge = image_info(dir.path());
+        huge.size = MAX_IMAGE_BYTES + 1;
+        assert!(count_faces(&huge).unwrap_err().contains("larger than"));
+    }
+
+    #[cfg(feature = "faces")]
+    #[test]
+    fn overlapping_detections_count_once() {
+        let scores = [0.1, 0.9, 0.05, 0.95, 0.2, 0.8, 0.6, 0.4];
+        let boxes = [
+            0.10, 0.10, 0.30, 0.40, // face A
+            0.11, 0.10, 0.31, 0.41, // face A again, slightly shifted
+            0.60, 0.20, 0.80, 0.50, // face B
+            0.00, 0.00, 1.00, 1.00, // below the score threshold
+        ];
+        assert_eq!(detector::count_detections(&scores, &boxes), 2);
+        assert_eq!(detector::count_detections(&[], &[]), 0);
+    }
+
+    #[cfg(feature = "faces")]
+    fn fixture(name: &str) -> FileInfo {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/faces")
+            .join(name);
+        FileInfo::from_path(&path).unwrap()
+    }
+
+    #[cfg(feature = "faces")]
+    #[test]
+    #[ignore = "needs the face model downloaded"]
+    fn scenery_fixture_has_no_faces() {
+        assert_eq!(count_faces(&fixture("no_face.png")), Ok(0));
+    }
+
+    #[cfg(feature = "faces")]
+    #[test]
+    #[ignore = "needs the face model downloaded and a portrait at tests/fixtures/faces/face.jpg"]
+    fn portrait_fixture_has_a_face() {
+        assert!(count_faces(&fixture("face.jpg")).unwrap() >= 1);
+    }
+}
@@ -0,0 +1,604 @@
+//! How files flow between watched folders. A rule that moves, copies or
+//! sorts a file into another watched folder hands it to that folder's rules,
+//! and a dispatch does so directly; followed far enough, those hand-offs can
+//! form chains, or loops that only the self-event suppression keeps from
+//! spinning.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::core::executor::looks_like_directory;
+use crate::models::{
+    Action, ActionType, FlowEdge, FlowFinding, FlowFolder, FlowKind, FolderFlowGraph,
+    HealthSeverity, Rule,
+};
+use crate::storage::database::Database;
+use crate::storage::folder_repo::FolderRepository;
+use crate::storage::rule_repo::RuleRepository;
+use crate::utils::platform::normalize_user_path;
+
+/// Paths through the folders reported at most, in case a large setup has
+/// many branching chains.
+const MAX_PATHS: usize = 100;
+
+/// The flow between the enabled watched folders, from their enabled rules.
+pub fn folder_flow(db: &Database) -> Result<FolderFlowGraph> {
+    let folders = FolderRepository::new(db.clone())
+        .list()?
+        .into_iter()
+        .filter(|folder| folder.enabled && !folder.is_group && !folder.path.is_empty())
+        .map(|folder| FlowFolder {
+            id: folder.id,
+            name: folder.name,
+            path: folder.path,
+            scan_depth: folder.scan_depth,
+        })
+        .collect();
+    let rules = RuleRepository::new(db.clone()).list_enabled()?;
+    Ok(flow_graph(folders, &rules))
+}
+
+/// Builds the graph of hand-offs between `folders` and reports the loops,
+/// chains and single hand-offs in it. Destinations are judged by the text
+/// before their first token, since whatever the tokens resolve to, the file
+/// lands somewhere below it. A rule sending files into a subfolder of its
+/// own folder isn't counted; that's what sorting does.
+pub fn flow_graph<'a>(
+    folders: Vec<FlowFolder>,
+    rules: impl IntoIterator<Item = &'a Rule>,
+) -> FolderFlowGraph {
+    let roots: Vec<PathBuf> = folders
+        .iter()
+        .map(|folder| normalize_user_path(&folder.path))
+        .collect();
+    let index: HashMap<&str, usize> = folders
+        .iter()
+        .enumerate()
+        .map(|(i, folder)| (folder.id.as_str(), i))
+        .collect();
+
+    let mut edges = Vec::new();
+    for rule in rules.into_iter().filter(|rule| rule.enabled) {
+        let Some(&from) = index.get(rule.folder_id.as_str()) else {
+            continue;
+        };
+        for action in &rule.actions {
+            let (action_type, to, destination) = match action {
+                Action::Move(action) => (
+                    ActionType::Move,
+                    landing_folder(&folders, &roots, &action.destination, false),
+                    action.destination.clone(),
+                ),
+                Action::Copy(action) => (
+                    ActionType::Copy,
+                    landing_folder(&folders, &roots, &action.destination, false),
+                    action.destination.clone(),
+                ),
+                Action::SortIntoSubfolder(action) => (
+                    ActionType::SortIntoSubfolder,
+                    landing_folder(&folders, &roots, &action.destination, true),
+                    action.destination.clone(),
+                ),
+                Action::DispatchToFolder(action) => {
+                    let to = index.get(action.folder_id.as_str()).copied();
+                    let name = to.map(|to| folders[to].name.clone()).unwrap_or_default();
+                    (ActionType::DispatchToFolder, to, name)
+                }
+                _ => continue,
+            };
+            let Some(to) = to.filter(|&to| to != from) else {
+                continue;
+            };
+            edges.push((
+                from,
+                to,
+                FlowEdge {
+                    from_folder_id: folders[from].id.clone(),
+                    to_folder_id: folders[to].id.clone(),
+                    rule_id: rule.id.clone(),
+                    rule_name: rule.name.clone(),
+                    action_type,
+                    destination,
+                },
+            ));
+        }
+    }
+
+    let findings = Flow::new(&folders, &edges).findings();
+    FolderFlowGraph {
+        folders,
+        edges: edges.into_iter().map(|(_, _, edge)| edge).collect(),
+        findings,
+    }
+}
+
+/// The watched folder a file sent to `destination` lands in, within that
+/// folder's scan depth. Where folders are nested, the innermost one.
+fn landing_folder(
+    folders: &[FlowFolder],
+    roots: &[PathBuf],
+    destination: &str,
+    force_dir: bool,
+) -> Option<usize> {
+    let dir = landing_dir(destination, force_dir)?;
+    roots
+        .iter()
+        .enumerate()
+        .filter(|(i, root)| {
+            let Ok(relative) = dir.strip_prefix(root) else {
+                return false;
+            };
+            let depth = folders[*i].scan_depth;
+            depth < 0 || relative.components().count() <= depth as usize
+        })
+        .max_by_key(|(_, root)| root.components().count())
+        .map(|(i, _)| i)
+}
+
+/// The directory a file sent to `destination` ends up in or below. Tokens
+/// count as wildcards, so a pattern is cut back to the last separator before
+/// its first token. `None` when that leaves no absolute path.
+fn landing_dir(destination: &str, force_dir: bool) -> Option<PathBuf> {
+    let dir = match destination.find('{') {
+        Some(start) => {
+            let prefix = &destination[..start];
+            let end = prefix.rfind(['/', '\\'])?;
+            normalize_user_path(&prefix[..=end])
+        }
+        None => {
+            let path = normalize_user_path(destination);
+            if force_dir || looks_like_directory(&path) {
+                path
+            } else {
+                path.parent()?.to_path_buf()
+            }
+        }
+    };
+    dir.is_absolute().then_some(dir)
+}
+
+/// The hand-offs between folders, grouped by the pair of folders they join.
+struct Flow<'a> {
+    folders: &'a [FlowFolder],
+    links: BTreeMap<(usize, usize), Vec<&'a FlowEdge>>,
+    /// Each folder's loop, as the lowest index in it; folders in no loop are
+    /// their own.
+    component: Vec<usize>,
+}
+
+impl<'a> Flow<'a> {
+    fn new(folders: &'a [FlowFolder], edges: &'a [(usize, usize, FlowEdge)]) -> Self {
+        let mut links: BTreeMap<(usize, usize), Vec<&FlowEdge>> = BTreeMap::new();
+        for (from, to, edge) in edges {
+            links.entry((*from, *to)).or_default().push(edge);
+        }
+        let mut flow = Self {
+            folders,
+            links,
+            component: Vec::new(),
+        };
+        let reach: Vec<Vec<bool>> = (0..folders.len()).map(|i| flow.reachable(i)).collect();
+        flow.component = (0..folders.len())
+            .map(|i| (0..=i).find(|&j| reach[i][j] && reach[j][i]).unwrap_or(i))
+            .collect();
+        flow
+    }
+
+    fn next(&self, from: usize) -> impl Iterator<Item = usize> + '_ {
+        self.links
+            .range((from, 0)..(from + 1, 0))
+            .map(|(&(_, to), _)| to)
+    }
+
+    /// Which folders files leaving `start` can reach, `start` itself included.
+    fn reachable(&self, start: usize) -> Vec<bool> {
+        let mut seen = vec![false; self.folders.len()];
+        seen[start] = true;
+        let mut stack = vec![start];
+        while let Some(folder) = stack.pop() {
+            for to in self.next(folder) {
+                if !seen[to] {
+                    seen[to] = true;
+                    stack.push(to);
+                }
+            }
+        }
+        seen
+    }
+
+    fn findings(&self) -> Vec<FlowFinding> {
+        let mut findings = Vec::new();
+        let mut looped: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (folder, &component) in self.component.iter().enumerate() {
+            looped.entry(component).or_default().push(folder);
+        }
+        for (start, members) in looped.into_iter().filter(|(_, m)| m.len() > 1) {
+            findings.push(self.cycle(start, &members));
+        }
+        findings.extend(self.paths().into_iter().map(|path| self.chain(&path)));
+        findings
+    }
+
+    /// A loop through the folders in `members`, walked from `start` by the
+    /// shortest way back.
+    fn cycle(&self, start: usize, members: &[usize]) -> FlowFinding {
+        let mut path = self.way_back(start);
+        path.push(start);
+        let edges: Vec<&FlowEdge> = self
+            .links
+            .iter()
+            .filter(|((from, to), _)| members.contains(from) && members.contains(to))
+            .flat_map(|(_, edges)| edges.iter().copied())
+            .collect();
+        let mut message = format!(
+            "Files can loop between folders: {}. {}",
+            self.route(&path),
+            describe(&edges)
+        );
+        if edges
+            .iter()
+            .any(|edge| edge.action_type == ActionType::DispatchToFolder)
+        {
+            message.push_str(" A dispatch to another folder is part of the loop.");
+        }
+        self.finding(
+            FlowKind::Cycle,
+            HealthSeverity::Error,
+            &path,
+            &edges,
+            message,
+        )
+    }
+
+    /// The folders from `start` round to just before it comes back.
+    fn way_back(&self, start: usize) -> Vec<usize> {
+        let mut previous: HashMap<usize, usize> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(folder) = queue.pop_front() {
+            for to in self.next(folder) {
+                if self.component[to] != self.component[start] {
+                    continue;
+                }
+                if to == start {
+                    let mut path = vec![folder];
+                    while let Some(&before) = path.last().and_then(|f| previous.get(f)) {
+                        path.push(before);
+                    }
+                    path.reverse();
+                    return path;
+                }
+                if let Entry::Vacant(entry) = previous.entry(to) {
+                    entry.insert(folder);
+                    queue.push_back(to);
+                }
+            }
+        }
+        vec![start]
+    }
+
+    /// Every longest path over the hand-offs that aren't part of a loop.
+    fn paths(&self) -> Vec<Vec<usize>> {
+        let outside: Vec<(usize, usize)> = self
+            .links
+            .keys()
+            .copied()
+            .filter(|(from, to)| self.component[*from] != self.component[*to])
+            .collect();
+        let mut paths = Vec::new();
+        let starts = outside
+            .iter()
+            .map(|(from, _)| *from)
+            .filter(|folder| !outside.iter().any(|(_, to)| to == folder));
+        for start in starts.collect::<BTreeSet<_>>() {
+            let mut stack = vec![vec![start]];
+            while let Some(path) = stack.pop() {
+                if paths.len() >= MAX_PATHS {
+                    return paths;
+                }
+                let last = *path.last().unwrap_or(&start);
+                let next: Vec<usize> = outside
+                    .iter()
+                    .filter(|(from, _)| *from == last)
+                    .map(|(_, to)| *to)
+                    .collect();
+                if next.is_empty() {
+                    paths.push(path);
+                    continue;
+                }
+                for to in next.into_iter().rev() {
+                    let mut longer = path.clone();
+                    longer.push(to);
+                    stack.push(longer);
+                }
+            }
+        }
+        paths
+    }
+
+    fn chain(&self, path: &[usize]) -> FlowFinding {
+        let edges: Vec<&FlowEdge> = path
+            .windows(2)
+            .filter_map(|pair| self.links.get(&(pair[0], pair[1])))
+            .flat_map(|edges| edges.iter().copied())
+            .collect();
+        if path.len() > 2 {
+            let message = format!(
+                "Files pass through {} folders in turn: {}. {}",
+                path.len(),
+                self.route(path),
+                describe(&edges)
+            );
+            self.finding(
+                FlowKind::Chain,
+                HealthSeverity::Warning,
+                path,
+                &edges,
+                message,
+            )
+        } else {
+            let message = format!(
+                "Files sent from \"{}\" to \"{}\" are processed again there. {}",
+                self.folders[path[0]].name,
+                self.folders[path[1]].name,
+                describe(&edges)
+            );
+            self.finding(
+                FlowKind::Handoff,
+                HealthSeverity::Info,
+                path,
+                &edges,
+                message,
+            )
+        }
+    }
+
+    fn route(&self, path: &[usize]) -> String {
+        path.iter()
+            .map(|&folder| format!("\"{}\"", self.folders[folder].name))
+            .collect::<Vec<_>>()
+            .join(" → ")
+    }
+
+    fn finding(
+        &self,
+        kind: FlowKind,
+        severity: HealthSeverity,
+        path: &[usize],
+        edges: &[&FlowEdge],
+        message: String,
+    ) -> FlowFinding {
+        let mut rule_ids: Vec<String> = Vec::new();
+        for edge in edges {
+            if !rule_ids.contains(&edge.rule_id) {
+                rule_ids.push(edge.rule_id.clone());
+            }
+        }
+        FlowFinding {
+            kind,
+            severity,
+            folder_ids: path
+                .iter()
+                .map(|&folder| self.folders[folder].id.clone())
+                .collect(),
+            rule_ids,
+            message,
+        }
+    }
+}
+
+/// The rules behind `edges`, as `Rule "PDFs" moves to ~/Documents/Inbox/`.
+fn describe(edges: &[&FlowEdge]) -> String {
+    let parts: Vec<String> = edges
+        .iter()
+        .map(|edge| {
+            let verb = match edge.action_type {
+                ActionType::Copy => "copies to",
+                ActionType::SortIntoSubfolder => "sorts into",
+                ActionType::DispatchToFolder => "dispatches to",
+                _ => "moves to",
+            };
+            format!("Rule \"{}\" {verb} {}", edge.rule_name, edge.destination)
+        })
+        .collect();
+    format!("{}.", parts.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        ConditionGroup, ConflictResolution, DispatchToFolderAction, MatchType, MoveAction,
+    };
+    use chrono::Utc;
+
+    /// Whether files sent to `destination` land inside `root`.
+    fn lands_in(destination: &str, root: &str) -> bool {
+        landing_dir(destination, false)
+            .is_some_and(|dir| dir.starts_with(normalize_user_path(root)))
+    }
+
+    fn folder(id: &str, path: &str) -> FlowFolder {
+        FlowFolder {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: path.to_string(),
+            scan_depth: -1,
+        }
+    }
+
+    fn rule(id: &str, folder_id: &str, actions: Vec<Action>) -> Rule {
+        Rule {
+            id: id.to_string(),
+            folder_id: folder_id.to_string(),
+            name: id.to_string(),
+            enabled: true,
+            stop_processing: true,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![],
+            },
+            actions,
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn move_to(destination: &str) -> Action {
+        Action::Move(MoveAction {
+            destination: destination.to_string(),
+            on_conflict: ConflictResolution::Rename,
+            skip_duplicates: false,
+            destination_permissions: Default::default(),
+            quarantine: Default::default(),
+            bring_companions: None,
+            auto_partition: None,
+        })
+    }
+
+    #[test]
+    fn two_folders_sending_files_to_each_other_are_a_cycle() {
+        let folders = vec![
+            folder("downloads", "/home/ana/Downloads"),
+            folder("inbox", "/home/ana/Documents/Inbox"),
+        ];
+        let rules = [
+            rule(
+                "pdfs",
+                "downloads",
+                vec![move_to("/home/ana/Documents/Inbox/")],
+            ),
+            rule(
+                "back",
+                "inbox",
+                vec![move_to("/home/ana/Downloads/Returned/{name}")],
+            ),
+        ];
+        let graph = flow_graph(folders, &rules);
+
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.findings.len(), 1);
+        let cycle = &graph.findings[0];
+        assert_eq!(cycle.kind, FlowKind::Cycle);
+        assert_eq!(cycle.severity, HealthSeverity::Error);
+        assert_eq!(cycle.folder_ids, ["downloads", "inbox", "downloads"]);
+        assert_eq!(cycle.rule_ids, ["pdfs", "back"]);
+        assert!(cycle
+            .message
+            .contains("/home/ana/Downloads/Returned/{name}"));
+    }
+
+    #[test]
+    fn a_one_way_hand_off_is_info_and_a_longer_chain_a_warning() {
+        let folders = vec![
+            folder("downloads", "/home/ana/Downloads"),
+            folder("inbox", "/home/ana/Inbox"),
+            folder("archive", "/home/ana/Archive"),
+            folder("photos", "/home/ana/Photos"),
+        ];
+        let rules = [
+            rule("to-inbox", "downloads", vec![move_to("/home/ana/Inbox/")]),
+            rule(
+                "file",
+                "inbox",
+                vec![Action::DispatchToFolder(DispatchToFolderAction {
+                    folder_id: "archive".to_string(),
+                })],
+            ),
+            rule(
+                "to-photos",
+                "downloads",
+                vec![move_to("/home/ana/Photos/{year}/")],
+            ),
+        ];
+        let graph = flow_graph(folders, &rules);
+
+        let kinds: Vec<(FlowKind, HealthSeverity, Vec<String>)> = graph
+            .findings
+            .iter()
+            .map(|f| (f.kind, f.severity, f.folder_ids.clone()))
+            .collect();
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                (
+                    FlowKind::Chain,
+                    HealthSeverity::Warning,
+                    ids(&["downloads", "inbox", "archive"])
+                ),
+                (
+                    FlowKind::Handoff,
+                    HealthSeverity::Info,
+                    ids(&["downloads", "photos"])
+                ),
+            ]
+        );
+        assert!(graph
+            .findings
+            .iter()
+            .all(|finding| finding.kind != FlowKind::Cycle));
+    }
+
+    #[test]
+    fn tokens_are_wildcards_below_the_text_before_them() {
+        assert!(lands_in("~/Documents/{1}/", "~/Documents"));
+        assert!(lands_in("~/Documents/Inbox{year}/x.pdf", "~/Documents"));
+        assert!(!lands_in("~/Documents/{1}/", "~/Documents/Inbox"));
+        assert!(landing_dir("{1}/sorted", false).is_none());
+
+        let folders = vec![
+            folder("downloads", "~/Downloads"),
+            folder("documents", "~/Documents"),
+            folder("inbox", "~/Documents/Inbox"),
+        ];
+        let rules = [rule(
+            "by-match",
+            "downloads",
+            vec![move_to("~/Documents/{1}/")],
+        )];
+        let graph = flow_graph(folders, &rules);
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].to_folder_id, "documents");
+
+        let mut shallow = folder("documents", "~/Documents");
+        shallow.scan_depth = 0;
+        let graph = flow_graph(
+            vec![folder("downloads", "~/Downloads"), shallow],
+            &[rule(
+                "deep",
+                "downloads",
+                vec![move_to("~/Documents/Old/{1}/")],
+            )],
+        );
+        assert!(graph.edges.is_empty());
+    }
+
+    #[test]
+    fn sorting_into_a_subfolder_of_its_own_folder_is_not_a_hand_off() {
+        let graph = flow_graph(
+            vec![folder("downloads", "/home/ana/Downloads")],
+            &[rule(
+                "sort",
+                "downloads",
+                vec![move_to("/home/ana/Downloads/{extension}/")],
+            )],
+        );
+        assert!(graph.edges.is_empty() && graph.findings.is_empty());
+    }
+}
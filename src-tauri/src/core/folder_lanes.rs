@@ -0,0 +1,243 @@
+//! One-at-a-time processing for folders marked strict sequential. Live events
+//! and "Run now"/scheduled scans of such a folder take a ticket when they
+//! arrive and wait for their turn, so rules that number files by what's
+//! already there never see two files at once. Other folders have no lane.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::models::{Folder, FolderId, SerializedFolder};
+use crate::storage::database::Database;
+use crate::storage::folder_repo::FolderRepository;
+
+#[derive(Default)]
+pub struct FolderLanes {
+    lanes: Mutex<HashMap<FolderId, Arc<Lane>>>,
+}
+
+#[derive(Default)]
+struct Lane {
+    state: Mutex<LaneState>,
+    turn: Condvar,
+}
+
+#[derive(Default)]
+struct LaneState {
+    issued: u64,
+    serving: u64,
+}
+
+/// A place in a folder's lane, handed out in arrival order.
+pub struct Ticket {
+    lane: Arc<Lane>,
+    number: u64,
+    waited: bool,
+}
+
+/// Held while a file of a strict folder is processed; the next ticket is
+/// served when it's dropped.
+pub struct LaneTurn {
+    lane: Arc<Lane>,
+}
+
+impl FolderLanes {
+    pub fn load(db: &Database) -> Self {
+        let lanes = Self::default();
+        lanes.sync(&FolderRepository::new(db.clone()).list().unwrap_or_default());
+        lanes
+    }
+
+    /// Gives every strict folder a lane and drops the lanes of the others.
+    pub fn sync(&self, folders: &[Folder]) {
+        for folder in folders {
+            self.set_strict(&folder.id, folder.strict_sequential);
+        }
+    }
+
+    pub fn set_strict(&self, folder_id: &str, strict: bool) {
+        let Ok(mut lanes) = self.lanes.lock() else {
+            return;
+        };
+        if strict {
+            lanes.entry(folder_id.to_string()).or_default();
+        } else {
+            // Tickets already handed out keep the lane alive until served.
+            lanes.remove(folder_id);
+        }
+    }
+
+    /// A ticket for the folder's next file, or `None` for folders that run
+    /// in parallel.
+    pub fn enqueue(&self, folder_id: &str) -> Option<Ticket> {
+        let lane = self.lanes.lock().ok()?.get(folder_id).cloned()?;
+        let number = {
+            let mut state = lane.state.lock().ok()?;
+            state.issued += 1;
+            state.issued - 1
+        };
+        Some(Ticket {
+            lane,
+            number,
+            waited: false,
+        })
+    }
+
+    /// Strict folders and how many of their files are waiting or running.
+    pub fn snapshot(&self) -> Vec<SerializedFolder> {
+        let Ok(lanes) = self.lanes.lock() else {
+            return Vec::new();
+        };
+        let mut folders: Vec<SerializedFolder> = lanes
+            .iter()
+            .map(|(folder_id, lane)| SerializedFolder {
+                folder_id: folder_id.clone(),
+                backlog: lane
+                    .state
+                    .lock()
+                    .map(|state| (state.issued - state.serving) as usize)
+                    .unwrap_or_default(),
+            })
+            .collect();
+        folders.sort_by(|a, b| a.folder_id.cmp(&b.folder_id));
+        folders
+    }
+}
+
+impl Ticket {
+    /// Blocks until every earlier ticket of the lane was served.
+    pub fn wait(mut self) -> LaneTurn {
+        self.lane.wait_for(self.number);
+        self.waited = true;
+        LaneTurn {
+            lane: self.lane.clone(),
+        }
+    }
+}
+
+impl Drop for Ticket {
+    /// A ticket that's given up still has to pass its turn on, or the files
+    /// after it would wait forever.
+    fn drop(&mut self) {
+        if !self.waited {
+            self.lane.wait_for(self.number);
+            self.lane.advance();
+        }
+    }
+}
+
+impl Drop for LaneTurn {
+    fn drop(&mut self) {
+        self.lane.advance();
+    }
+}
+
+impl Lane {
+    fn wait_for(&self, number: u64) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        while state.serving != number {
+            state = match self.turn.wait(state) {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+        }
+    }
+
+    fn advance(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.serving += 1;
+        }
+        self.turn.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Barrier;
+    use std::thread;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    /// Numbers a scan the way a numbering rule would: one past the files
+    /// already in the folder. `between` holds the worker between counting
+    /// and writing.
+    fn file_next(dir: &Path, between: Option<&Barrier>) -> usize {
+        let number = fs::read_dir(dir).unwrap().count() + 1;
+        match between {
+            Some(barrier) => {
+                barrier.wait();
+            }
+            None => thread::sleep(Duration::from_millis(20)),
+        }
+        fs::write(dir.join(format!("scan-{number:03}.pdf")), b"%PDF").unwrap();
+        number
+    }
+
+    #[test]
+    fn strict_folder_numbers_simultaneous_files_consecutively() {
+        let dir = tempdir().unwrap();
+        let lanes = FolderLanes::default();
+        lanes.set_strict("scans", true);
+
+        // Two workers get a file each at the same moment.
+        let tickets: Vec<Ticket> = (0..2).map(|_| lanes.enqueue("scans").unwrap()).collect();
+        assert_eq!(lanes.snapshot()[0].backlog, 2);
+        let start = Arc::new(Barrier::new(2));
+        let workers: Vec<_> = tickets
+            .into_iter()
+            .map(|ticket| {
+                let start = start.clone();
+                let dir = dir.path().to_path_buf();
+                thread::spawn(move || {
+                    start.wait();
+                    let _turn = ticket.wait();
+                    file_next(&dir, None)
+                })
+            })
+            .collect();
+        let numbers: Vec<usize> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+
+        assert_eq!(numbers, vec![1, 2]);
+        assert_eq!(lanes.snapshot()[0].backlog, 0);
+    }
+
+    #[test]
+    fn other_folders_interleave() {
+        let dir = tempdir().unwrap();
+        let lanes = FolderLanes::default();
+        lanes.set_strict("scans", true);
+        assert!(lanes.enqueue("inbox").is_none());
+
+        let between = Arc::new(Barrier::new(2));
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let between = between.clone();
+                let dir = dir.path().to_path_buf();
+                thread::spawn(move || file_next(&dir, Some(&between)))
+            })
+            .collect();
+        let numbers: Vec<usize> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+
+        // Both counted before either wrote, so they collide.
+        assert_eq!(numbers, vec![1, 1]);
+    }
+
+    #[test]
+    fn abandoned_tickets_pass_their_turn_on() {
+        let lanes = FolderLanes::default();
+        lanes.set_strict("scans", true);
+        let first = lanes.enqueue("scans").unwrap();
+        let second = lanes.enqueue("scans").unwrap();
+        drop(first);
+        drop(second.wait());
+        assert_eq!(lanes.snapshot()[0].backlog, 0);
+
+        lanes.set_strict("scans", false);
+        assert!(lanes.snapshot().is_empty());
+        assert!(lanes.enqueue("scans").is_none());
+    }
+}
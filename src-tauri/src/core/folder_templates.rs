@@ -0,0 +1,377 @@
+//! Folder templates: a watched folder's options and rules saved under a name,
+//! so another folder can be set up the same way. Each template is a JSON file
+//! in a `templates` folder next to the database. Its rules are stored in the
+//! rule export format under the same `schemaVersion`, so a template from an
+//! older version is upgraded when read and one from a newer version refused.
+//!
+//! Destinations inside the saved folder are stored as `${folder}` and point
+//! into the new folder once applied. Any other `${var}` placeholder is a
+//! variable the template asks for.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use filedispatch_core::rule_schema::{
+    export_rule_values, import_rule_values, RULE_SCHEMA_VERSION,
+};
+use filedispatch_core::validate::keeps_literal_password;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::core::relink::{destination_mut, rebase_path};
+use crate::models::{Folder, FolderSettings, FolderTemplateInfo, PresetVariable, Rule};
+use crate::storage::database::Database;
+use crate::utils::platform::normalize_user_path;
+
+/// The variable every template gets: the path of the folder it's applied to.
+pub const FOLDER_VARIABLE: &str = "folder";
+
+const TEMPLATE_EXTENSION: &str = "json";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TemplateFile {
+    schema_version: Value,
+    id: String,
+    name: String,
+    created_at: DateTime<Utc>,
+    settings: FolderSettings,
+    #[serde(default)]
+    variables: Vec<PresetVariable>,
+    rules: Vec<Value>,
+}
+
+/// A `templates` folder next to the database.
+pub fn template_dir(db: &Database) -> PathBuf {
+    db.path()
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("templates")
+}
+
+/// Saves `folder` with `rules` as a new template in `dir`. `variables`
+/// describes placeholders in the rules; ones found but not described are
+/// added with their id as the name.
+pub fn save_template(
+    dir: &Path,
+    folder: &Folder,
+    rules: &[Rule],
+    name: &str,
+    variables: Vec<PresetVariable>,
+) -> Result<FolderTemplateInfo> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("A template needs a name");
+    }
+    if let Some(rule) = rules.iter().find(|rule| keeps_literal_password(rule)) {
+        bail!(
+            "Rule \"{}\" keeps its archive password in the rule, which a template would \
+             store in plain text. Keep the password in the keychain or an environment \
+             variable instead.",
+            rule.name
+        );
+    }
+    let root = normalize_user_path(&folder.path);
+    let placeholder = format!("${{{FOLDER_VARIABLE}}}");
+    let mut rules = rules.to_vec();
+    for rule in rules.iter_mut() {
+        for destination in rule.actions.iter_mut().filter_map(destination_mut) {
+            if let Some(relative) = rebase_path(destination, &root, Path::new(&placeholder)) {
+                *destination = relative;
+            }
+        }
+    }
+    let rules = export_rule_values(&rules)?;
+
+    let mut variables: Vec<PresetVariable> = variables
+        .into_iter()
+        .filter(|var| var.id != FOLDER_VARIABLE)
+        .collect();
+    for id in placeholders(&rules) {
+        if id != FOLDER_VARIABLE && !variables.iter().any(|var| var.id == id) {
+            variables.push(PresetVariable {
+                name: id.clone(),
+                id,
+                var_type: "text".to_string(),
+                default: None,
+            });
+        }
+    }
+
+    let file = TemplateFile {
+        schema_version: Value::String(RULE_SCHEMA_VERSION.to_string()),
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        created_at: Utc::now(),
+        settings: folder.settings(),
+        variables,
+        rules,
+    };
+    fs::create_dir_all(dir)?;
+    let path = template_path(dir, &file.id)?;
+    fs::write(&path, serde_json::to_vec_pretty(&file)?)?;
+    Ok(read_template(&path)?.0)
+}
+
+/// Every readable template in `dir`, by name. Unreadable files are skipped.
+pub fn list_templates(dir: &Path) -> Result<Vec<FolderTemplateInfo>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut templates = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(TEMPLATE_EXTENSION) {
+            continue;
+        }
+        match read_template(&path) {
+            Ok((info, _)) => templates.push(info),
+            Err(err) => eprintln!("Skipping template {}: {err}", path.display()),
+        }
+    }
+    templates.sort_by_key(|template| template.name.to_lowercase());
+    Ok(templates)
+}
+
+/// The template `id` in `dir` and its rules, upgraded to the current schema.
+pub fn load_template(dir: &Path, id: &str) -> Result<(FolderTemplateInfo, Vec<Rule>)> {
+    read_template(&template_path(dir, id)?)
+}
+
+pub fn delete_template(dir: &Path, id: &str) -> Result<()> {
+    fs::remove_file(template_path(dir, id)?)?;
+    Ok(())
+}
+
+/// Copies a template out to `destination`, to share or keep elsewhere.
+pub fn export_template(dir: &Path, id: &str, destination: &Path) -> Result<()> {
+    fs::copy(template_path(dir, id)?, destination)?;
+    Ok(())
+}
+
+/// Adds the template file at `source` to `dir`, under a new id if its own is
+/// already taken. The file is checked the same way as when it's applied.
+pub fn import_template(dir: &Path, source: &Path) -> Result<FolderTemplateInfo> {
+    let content = fs::read_to_string(source)?;
+    let mut file: TemplateFile = serde_json::from_str(&content)?;
+    import_rule_values(&file.schema_version, file.rules.clone())
+        .map_err(|err| anyhow!(err.to_string()))?;
+    fs::create_dir_all(dir)?;
+    let taken = template_path(dir, &file.id).map_or(true, |path| path.exists());
+    if taken {
+        file.id = Uuid::new_v4().to_string();
+    }
+    let path = template_path(dir, &file.id)?;
+    fs::write(&path, serde_json::to_vec_pretty(&file)?)?;
+    Ok(read_template(&path)?.0)
+}
+
+fn template_path(dir: &Path, id: &str) -> Result<PathBuf> {
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!("No template {id}");
+    }
+    Ok(dir.join(format!("{id}.{TEMPLATE_EXTENSION}")))
+}
+
+/// The template at `path` and its rules, upgraded to the current schema.
+fn read_template(path: &Path) -> Result<(FolderTemplateInfo, Vec<Rule>)> {
+    let content = fs::read_to_string(path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => anyhow!("No template at {}", path.display()),
+        _ => err.into(),
+    })?;
+    let file: TemplateFile = serde_json::from_str(&content)?;
+    let rules = import_rule_values(&file.schema_version, file.rules)
+        .map_err(|err| anyhow!(err.to_string()))?;
+    let info = FolderTemplateInfo {
+        id: file.id,
+        name: file.name,
+        created_at: file.created_at,
+        settings: file.settings,
+        variables: file.variables,
+        rule_count: rules.len(),
+        path: path.to_string_lossy().to_string(),
+    };
+    Ok((info, rules))
+}
+
+/// The ids of the `${var}` placeholders anywhere in `rules`.
+fn placeholders(rules: &[Value]) -> Vec<String> {
+    let pattern = Regex::new(r"\$\{([A-Za-z0-9_-]+)\}").expect("valid placeholder regex");
+    let text = serde_json::to_string(rules).unwrap_or_default();
+    let mut ids: Vec<String> = Vec::new();
+    for captures in pattern.captures_iter(&text) {
+        let id = captures[1].to_string();
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        Action, ArchiveAction, ArchiveEncryption, ArchiveEncryptionMethod, ArchiveFormat,
+        Condition, ConditionGroup, ConflictResolution, MatchType, MoveAction, PasswordSource,
+        StringCondition, StringOperator,
+    };
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    fn folder(path: &Path) -> Folder {
+        let now = Utc::now();
+        Folder {
+            id: "source".to_string(),
+            path: path.to_string_lossy().to_string(),
+            name: "Source".to_string(),
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+            rule_count: 0,
+            scan_depth: -1,
+            remove_duplicates: true,
+            trash_incomplete_downloads: false,
+            incomplete_timeout_minutes: 60,
+            parent_id: None,
+            is_group: false,
+            placeholder_mode: Default::default(),
+            strict_sequential: false,
+            group_window_ms: 0,
+            passthrough_patterns: Vec::new(),
+            respect_git: false,
+            skip_git_tracked: false,
+            activity_thumbnails: false,
+            kind: Default::default(),
+            trash_max_mb: 0,
+        }
+    }
+
+    fn move_rule(destination: &str) -> Rule {
+        let now = Utc::now();
+        Rule {
+            id: "rule".to_string(),
+            folder_id: "source".to_string(),
+            name: "PDFs".to_string(),
+            enabled: true,
+            stop_processing: true,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![Condition::Extension(StringCondition {
+                    operator: StringOperator::Is,
+                    value: "pdf".to_string(),
+                    case_sensitive: false,
+                })],
+            },
+            actions: vec![Action::Move(MoveAction {
+                destination: destination.to_string(),
+                on_conflict: ConflictResolution::Rename,
+                skip_duplicates: false,
+                destination_permissions: Default::default(),
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
+            })],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: Value::Null,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn saving_makes_destinations_folder_relative_and_declares_placeholders() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("source");
+        let dir = temp.path().join("templates");
+        let rules = [
+            move_rule(&source.join("PDFs").to_string_lossy()),
+            move_rule("${archive}/PDFs"),
+        ];
+
+        let info = save_template(&dir, &folder(&source), &rules, " Inbox ", vec![]).unwrap();
+        assert_eq!(info.name, "Inbox");
+        assert_eq!(info.settings.scan_depth, -1);
+        let ids: Vec<&str> = info.variables.iter().map(|var| var.id.as_str()).collect();
+        assert_eq!(ids, ["archive"]);
+
+        let (_, loaded) = load_template(&dir, &info.id).unwrap();
+        let Action::Move(action) = &loaded[0].actions[0] else {
+            panic!("expected a move");
+        };
+        assert_eq!(
+            action.destination,
+            Path::new("${folder}").join("PDFs").to_string_lossy()
+        );
+        assert!(loaded.iter().all(|rule| rule.id.is_empty()));
+    }
+
+    #[test]
+    fn archive_passwords_kept_in_rules_are_not_saved() {
+        let temp = tempdir().unwrap();
+        let dir = temp.path().join("templates");
+        let mut rule = move_rule("${archive}/PDFs");
+        rule.actions = vec![Action::Archive(ArchiveAction {
+            destination: "${archive}/{name}".to_string(),
+            format: ArchiveFormat::Zip,
+            delete_after: false,
+            encryption: Some(ArchiveEncryption {
+                method: ArchiveEncryptionMethod::ZipAes256,
+                password_source: PasswordSource::Literal("hunter2".to_string()),
+            }),
+        })];
+
+        let err = save_template(&dir, &folder(temp.path()), &[rule], "HR", vec![]).unwrap_err();
+        assert!(err.to_string().contains("keeps its archive password"));
+        assert!(list_templates(&dir).unwrap().is_empty());
+    }
+
+    #[test]
+    fn export_and_import_keep_the_schema_version() {
+        let temp = tempdir().unwrap();
+        let dir = temp.path().join("templates");
+        let rules = [move_rule("/tmp/PDFs")];
+        let info = save_template(&dir, &folder(temp.path()), &rules, "Shared", vec![]).unwrap();
+        let exported = temp.path().join("shared.json");
+        export_template(&dir, &info.id, &exported).unwrap();
+        let file: Value = serde_json::from_str(&fs::read_to_string(&exported).unwrap()).unwrap();
+        assert_eq!(file["schemaVersion"], RULE_SCHEMA_VERSION.to_string());
+
+        // The original still has the id, so the copy gets a new one.
+        let imported = import_template(&dir, &exported).unwrap();
+        assert_ne!(imported.id, info.id);
+        assert_eq!(list_templates(&dir).unwrap().len(), 2);
+
+        let mut newer = file.clone();
+        newer["schemaVersion"] = Value::String("99.0".to_string());
+        fs::write(&exported, newer.to_string()).unwrap();
+        assert!(import_template(&dir, &exported).is_err());
+        assert_eq!(list_templates(&dir).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn ids_cannot_reach_outside_the_template_folder() {
+        let temp = tempdir().unwrap();
+        assert!(load_template(temp.path(), "../settings").is_err());
+        assert!(delete_template(temp.path(), "").is_err());
+    }
+}
@@ -0,0 +1,1026 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::Result;
+use chrono::Utc;
+use filedispatch_core::validate;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use crate::core::companions;
+use crate::core::engine::validate_path_scope;
+use crate::core::executor::validate_structure_entry;
+use crate::core::flow;
+use crate::core::notify_sound::validate_sound;
+use crate::core::partition;
+use crate::models::{
+    Action, Condition, ConditionGroup, CopyAction, FlowFolder, FolderKind, HealthIssue,
+    HealthIssueKind, HealthRepairOptions, HealthRepairResult, HealthReport, HealthSeverity,
+    MoveAction, Rule, RuleSchedule, SortAction,
+};
+use crate::storage::database::Database;
+use crate::storage::rule_repo::map_rule;
+use crate::utils::archive::ExtractFilter;
+use crate::utils::file_mode;
+use crate::utils::platform::normalize_user_path;
+
+/// Columns the repositories read back without allowing NULL. Most are declared
+/// NOT NULL, but the ones added by later migrations aren't.
+const REQUIRED_COLUMNS: &[(&str, &[&str])] = &[
+    (
+        "folders",
+        &[
+            "path",
+            "name",
+            "enabled",
+            "created_at",
+            "updated_at",
+            "scan_depth",
+            "remove_duplicates",
+            "trash_incomplete_downloads",
+            "incomplete_timeout_minutes",
+            "is_group",
+        ],
+    ),
+    (
+        "rules",
+        &[
+            "folder_id",
+            "name",
+            "enabled",
+            "stop_processing",
+            "conditions",
+            "actions",
+            "position",
+            "created_at",
+            "updated_at",
+            "approval_required",
+        ],
+    ),
+];
+
+/// Rows that reference a rule or folder by `(table, column, parent table)`.
+const REFERENCES: &[(&str, &str, &str)] = &[
+    ("rule_matches", "rule_id", "rules"),
+    ("match_metadata", "rule_id", "rules"),
+    ("pending_approvals", "rule_id", "rules"),
+    ("duplicate_removals", "folder_id", "folders"),
+    ("incomplete_files", "folder_id", "folders"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckDepth {
+    /// Everything but SQLite's page-level integrity check; cheap enough for startup.
+    Quick,
+    Full,
+}
+
+struct FolderRow {
+    id: String,
+    name: String,
+    path: Option<String>,
+    enabled: bool,
+    is_group: bool,
+    scan_depth: i32,
+    /// The system trash, which has no path to check.
+    is_trash: bool,
+}
+
+struct RuleRow {
+    id: String,
+    folder_id: Option<String>,
+    name: String,
+    enabled: bool,
+    position: Option<i64>,
+    conditions: Option<String>,
+    actions: Option<String>,
+    schedule: Option<String>,
+    parsed: Result<Rule, String>,
+}
+
+/// Looks for anything in the database that would make events fail or rules
+/// silently not run.
+pub fn check(db: &Database, depth: CheckDepth) -> Result<HealthReport> {
+    db.with_conn(|conn| {
+        let mut issues = Vec::new();
+        if depth == CheckDepth::Full {
+            check_integrity(conn, &mut issues)?;
+        }
+        check_required_columns(conn, &mut issues)?;
+
+        let folders = load_folders(conn)?;
+        let rules = load_rules(conn)?;
+        let folder_ids: HashSet<&str> = folders.iter().map(|f| f.id.as_str()).collect();
+        check_folders(conn, &folders, &mut issues)?;
+        check_rules(&rules, &folder_ids, &mut issues);
+        check_positions(&rules, &folders, &mut issues);
+        check_references(conn, &mut issues)?;
+        check_flow(&folders, &rules, &mut issues);
+
+        issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+        Ok(HealthReport {
+            issues,
+            checked_at: Utc::now(),
+        })
+    })
+}
+
+/// Applies the fixes `options` asks for to what a full check finds, then
+/// checks again.
+pub fn repair(db: &Database, options: &HealthRepairOptions) -> Result<HealthRepairResult> {
+    let report = check(db, CheckDepth::Full)?;
+    let mut repaired = Vec::new();
+
+    db.with_conn(|conn| {
+        let tx = conn.transaction()?;
+        let now = Utc::now().to_rfc3339();
+        if options.disable_unreadable_rules {
+            for id in subjects(&report, HealthIssueKind::UnreadableRule, |i| &i.rule_id) {
+                let name = name_or_id(&tx, "SELECT name FROM rules WHERE id = ?1", &id)?;
+                let changed = tx.execute(
+                    "UPDATE rules SET enabled = 0, updated_at = ?1 WHERE id = ?2 AND enabled = 1",
+                    params![now, id],
+                )?;
+                if changed > 0 {
+                    repaired.push(format!("Disabled rule \"{name}\""));
+                }
+            }
+        }
+        if options.renumber_positions {
+            let folders = subjects(&report, HealthIssueKind::DuplicatePosition, |i| &i.folder_id);
+            for folder_id in folders {
+                let ids: Vec<String> = tx
+                    .prepare(
+                        "SELECT id FROM rules WHERE folder_id = ?1 ORDER BY position, created_at, rowid",
+                    )?
+                    .query_map(params![folder_id], |row| row.get(0))?
+                    .collect::<rusqlite::Result<_>>()?;
+                for (position, id) in ids.iter().enumerate() {
+                    tx.execute(
+                        "UPDATE rules SET position = ?1, updated_at = ?2 WHERE id = ?3",
+                        params![position as i64, now, id],
+                    )?;
+                }
+                let name = name_or_id(&tx, "SELECT name FROM folders WHERE id = ?1", &folder_id)?;
+                repaired.push(format!("Re-numbered the rules in \"{name}\""));
+            }
+        }
+        if options.disable_missing_folders {
+            for id in subjects(&report, HealthIssueKind::MissingFolderPath, |i| &i.folder_id) {
+                let name = name_or_id(&tx, "SELECT name FROM folders WHERE id = ?1", &id)?;
+                tx.execute(
+                    "UPDATE folders SET enabled = 0, updated_at = ?1 WHERE id = ?2",
+                    params![now, id],
+                )?;
+                repaired.push(format!("Disabled folder \"{name}\""));
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    })?;
+
+    Ok(HealthRepairResult {
+        repaired,
+        report: check(db, CheckDepth::Full)?,
+    })
+}
+
+/// The rule or folder ids of the issues of one kind, each once.
+fn subjects(
+    report: &HealthReport,
+    kind: HealthIssueKind,
+    subject: impl Fn(&HealthIssue) -> &Option<String>,
+) -> Vec<String> {
+    let mut seen = HashSet::new();
+    report
+        .issues
+        .iter()
+        .filter(|issue| issue.kind == kind)
+        .filter_map(|issue| subject(issue).clone())
+        .filter(|id| seen.insert(id.clone()))
+        .collect()
+}
+
+/// The name `sql` reads for `id`, or the id itself when the row is gone or
+/// its name is missing, as in the rows being repaired.
+fn name_or_id(conn: &Connection, sql: &str, id: &str) -> rusqlite::Result<String> {
+    let name: Option<String> = conn
+        .query_row(sql, params![id], |row| row.get(0))
+        .optional()?
+        .flatten();
+    Ok(name.unwrap_or_else(|| id.to_string()))
+}
+
+/// Rejects action settings that can't be executed safely and regexes that
+/// don't compile. Patterns are checked unresolved here; the executor checks
+/// again once captures are filled in. The checks that only need the rule live
+/// in `filedispatch_core::validate`; the ones here look at the system.
+pub(crate) fn validate_rule(rule: &Rule) -> Result<(), String> {
+    validate::validate_rule(rule)?;
+    if let Some(scope) = &rule.path_scope {
+        validate_path_scope(scope).map_err(|err| format!("Rule \"{}\": {}", rule.name, err))?;
+    }
+    for action in &rule.actions {
+        match action {
+            Action::CreateFolderStructure(action) => {
+                for entry in &action.structure {
+                    validate_structure_entry(entry)
+                        .map_err(|err| format!("Rule \"{}\": {}", rule.name, err))?;
+                }
+            }
+            Action::Unarchive(action) => {
+                ExtractFilter::new(&action.include_globs, &action.exclude_globs, action.skip_junk)
+                    .map_err(|err| format!("Rule \"{}\": {}", rule.name, err))?;
+            }
+            Action::Move(MoveAction {
+                destination_permissions,
+                bring_companions,
+                auto_partition,
+                ..
+            })
+            | Action::Copy(CopyAction {
+                destination_permissions,
+                bring_companions,
+                auto_partition,
+                ..
+            })
+            | Action::SortIntoSubfolder(SortAction {
+                destination_permissions,
+                bring_companions,
+                auto_partition,
+                ..
+            }) => {
+                file_mode::validate(destination_permissions)
+                    .map_err(|err| format!("Rule \"{}\": {}", rule.name, err))?;
+                if let Some(spec) = bring_companions {
+                    companions::validate(spec)
+                        .map_err(|err| format!("Rule \"{}\": {}", rule.name, err))?;
+                }
+                if let Some(spec) = auto_partition {
+                    partition::validate(spec)
+                        .map_err(|err| format!("Rule \"{}\": {}", rule.name, err))?;
+                }
+            }
+            Action::Notify(action) => {
+                if let Some(sound) = &action.sound {
+                    validate_sound(sound)
+                        .map_err(|err| format!("Rule \"{}\": {}", rule.name, err))?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn check_integrity(conn: &Connection, issues: &mut Vec<HealthIssue>) -> Result<()> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let rows: Vec<String> = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for message in rows.into_iter().filter(|row| row != "ok") {
+        issues.push(HealthIssue {
+            severity: HealthSeverity::Error,
+            kind: HealthIssueKind::Corruption,
+            rule_id: None,
+            folder_id: None,
+            message,
+        });
+    }
+    Ok(())
+}
+
+fn check_required_columns(conn: &Connection, issues: &mut Vec<HealthIssue>) -> Result<()> {
+    for (table, columns) in REQUIRED_COLUMNS {
+        for column in *columns {
+            let mut stmt = conn.prepare(&format!("SELECT id FROM {table} WHERE {column} IS NULL"))?;
+            let ids: Vec<String> = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            for id in ids {
+                let (rule_id, folder_id) = match *table {
+                    "rules" => (Some(id.clone()), None),
+                    _ => (None, Some(id.clone())),
+                };
+                issues.push(HealthIssue {
+                    severity: HealthSeverity::Error,
+                    kind: HealthIssueKind::MissingValue,
+                    rule_id,
+                    folder_id,
+                    message: format!("{table}.{column} is empty for {id}"),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn load_folders(conn: &Connection) -> Result<Vec<FolderRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, path, enabled, is_group, scan_depth, kind FROM folders",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(FolderRow {
+            id: row.get(0)?,
+            name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            path: row.get(2)?,
+            enabled: row.get::<_, Option<i64>>(3)?.unwrap_or(0) != 0,
+            is_group: row.get::<_, Option<i64>>(4)?.unwrap_or(0) != 0,
+            scan_depth: row.get::<_, Option<i32>>(5)?.unwrap_or(0),
+            is_trash: row.get::<_, Option<String>>(6)?.as_deref()
+                == Some(FolderKind::SystemTrash.as_str()),
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn load_rules(conn: &Connection) -> Result<Vec<RuleRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, folder_id, name, enabled, stop_processing, conditions, actions, position, created_at, updated_at, schedule, approval_required, min_age_seconds, path_scope, extra, skip_reference_check, monthly_byte_budget, classification, on_reappear, record_metadata, revision FROM rules ORDER BY folder_id, position",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(RuleRow {
+            id: row.get(0)?,
+            folder_id: row.get(1)?,
+            name: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            enabled: row.get::<_, Option<i64>>(3)?.unwrap_or(0) != 0,
+            position: row.get(7)?,
+            conditions: row.get(5)?,
+            actions: row.get(6)?,
+            schedule: row.get(10)?,
+            parsed: map_rule(row).map_err(|err| err.to_string()),
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn check_folders(
+    conn: &Connection,
+    folders: &[FolderRow],
+    issues: &mut Vec<HealthIssue>,
+) -> Result<()> {
+    for folder in folders.iter().filter(|f| f.enabled && !f.is_group && !f.is_trash) {
+        let Some(path) = &folder.path else {
+            continue;
+        };
+        let problem = match std::fs::metadata(normalize_user_path(path)) {
+            Ok(meta) if meta.is_dir() => continue,
+            Ok(_) => "isn't a directory",
+            Err(_) => "doesn't exist",
+        };
+        issues.push(HealthIssue {
+            severity: HealthSeverity::Warning,
+            kind: HealthIssueKind::MissingFolderPath,
+            rule_id: None,
+            folder_id: Some(folder.id.clone()),
+            message: format!("Folder \"{}\": {path} {problem}", folder.name),
+        });
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, name FROM folders WHERE parent_id IS NOT NULL AND parent_id NOT IN (SELECT id FROM folders)",
+    )?;
+    let orphans: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (id, name) in orphans {
+        issues.push(HealthIssue {
+            severity: HealthSeverity::Warning,
+            kind: HealthIssueKind::DanglingReference,
+            rule_id: None,
+            folder_id: Some(id),
+            message: format!("Folder \"{name}\" is inside a group that no longer exists"),
+        });
+    }
+    Ok(())
+}
+
+fn check_rules(rules: &[RuleRow], folder_ids: &HashSet<&str>, issues: &mut Vec<HealthIssue>) {
+    for row in rules {
+        let issue = |severity, kind, message| HealthIssue {
+            severity,
+            kind,
+            rule_id: Some(row.id.clone()),
+            folder_id: row.folder_id.clone(),
+            message,
+        };
+        if let Some(folder_id) = &row.folder_id {
+            if !folder_ids.contains(folder_id.as_str()) {
+                issues.push(issue(
+                    HealthSeverity::Warning,
+                    HealthIssueKind::OrphanedRule,
+                    format!("Rule \"{}\" belongs to a folder that no longer exists", row.name),
+                ));
+            }
+        }
+        let rule = match &row.parsed {
+            Ok(rule) => rule,
+            Err(err) => {
+                let detail = locate_parse_error(row).unwrap_or_else(|| err.clone());
+                // Once disabled it no longer breaks its folder, but it's still lost.
+                let (severity, state) = if row.enabled {
+                    (HealthSeverity::Error, "")
+                } else {
+                    (HealthSeverity::Warning, " (disabled)")
+                };
+                issues.push(issue(
+                    severity,
+                    HealthIssueKind::UnreadableRule,
+                    format!("Rule \"{}\"{state} can't be read: {detail}", row.name),
+                ));
+                continue;
+            }
+        };
+        if let Err(err) = validate_rule(rule) {
+            issues.push(issue(HealthSeverity::Error, HealthIssueKind::InvalidPattern, err));
+        }
+        if validate::keeps_literal_password(rule) {
+            issues.push(issue(
+                HealthSeverity::Warning,
+                HealthIssueKind::LiteralPassword,
+                format!(
+                    "Rule \"{}\" keeps its archive password in the rule, so plain exports \
+                     refuse it and unencrypted backups leave the password out",
+                    rule.name
+                ),
+            ));
+        }
+        for action in &rule.actions {
+            if let Action::DispatchToFolder(action) = action {
+                if !folder_ids.contains(action.folder_id.as_str()) {
+                    issues.push(issue(
+                        HealthSeverity::Warning,
+                        HealthIssueKind::DanglingReference,
+                        format!(
+                            "Rule \"{}\" dispatches to a folder that no longer exists",
+                            rule.name
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn check_positions(rules: &[RuleRow], folders: &[FolderRow], issues: &mut Vec<HealthIssue>) {
+    let mut by_folder: BTreeMap<&str, HashMap<i64, Vec<&str>>> = BTreeMap::new();
+    for row in rules {
+        if let (Some(folder_id), Some(position)) = (&row.folder_id, row.position) {
+            by_folder
+                .entry(folder_id)
+                .or_default()
+                .entry(position)
+                .or_default()
+                .push(&row.name);
+        }
+    }
+    for (folder_id, positions) in by_folder {
+        let mut clashes: Vec<(i64, Vec<&str>)> = positions
+            .into_iter()
+            .filter(|(_, names)| names.len() > 1)
+            .collect();
+        if clashes.is_empty() {
+            continue;
+        }
+        clashes.sort();
+        let folder = folders
+            .iter()
+            .find(|f| f.id == folder_id)
+            .map(|f| f.name.as_str())
+            .unwrap_or(folder_id);
+        let detail: Vec<String> = clashes
+            .iter()
+            .map(|(position, names)| format!("{} at {position}", names.join(", ")))
+            .collect();
+        issues.push(HealthIssue {
+            severity: HealthSeverity::Warning,
+            kind: HealthIssueKind::DuplicatePosition,
+            rule_id: None,
+            folder_id: Some(folder_id.to_string()),
+            message: format!("Rules in \"{folder}\" share a position: {}", detail.join("; ")),
+        });
+    }
+}
+
+fn check_references(conn: &Connection, issues: &mut Vec<HealthIssue>) -> Result<()> {
+    for (table, column, parent) in REFERENCES {
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {table} WHERE {column} NOT IN (SELECT id FROM {parent})"),
+            [],
+            |row| row.get(0),
+        )?;
+        if count > 0 {
+            issues.push(HealthIssue {
+                severity: HealthSeverity::Info,
+                kind: HealthIssueKind::DanglingReference,
+                rule_id: None,
+                folder_id: None,
+                message: format!("{count} row(s) in {table} point at missing {parent}"),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Loops and chains the rules form between watched folders.
+fn check_flow(folders: &[FolderRow], rules: &[RuleRow], issues: &mut Vec<HealthIssue>) {
+    let watched = folders
+        .iter()
+        .filter(|folder| folder.enabled && !folder.is_group)
+        .filter_map(|folder| {
+            Some(FlowFolder {
+                id: folder.id.clone(),
+                name: folder.name.clone(),
+                path: folder.path.clone().filter(|path| !path.is_empty())?,
+                scan_depth: folder.scan_depth,
+            })
+        })
+        .collect();
+    let parsed = rules.iter().filter_map(|row| row.parsed.as_ref().ok());
+    let graph = flow::flow_graph(watched, parsed);
+    for finding in graph.findings {
+        issues.push(HealthIssue {
+            severity: finding.severity,
+            kind: HealthIssueKind::FolderFlow,
+            rule_id: finding.rule_ids.into_iter().next(),
+            folder_id: finding.folder_ids.into_iter().next(),
+            message: finding.message,
+        });
+    }
+}
+
+/// Narrows a rule that won't deserialize down to the innermost condition or
+/// action that fails on its own, as `path: error`.
+fn locate_parse_error(row: &RuleRow) -> Option<String> {
+    if let Some(json) = &row.conditions {
+        if let Some(err) = locate_json::<ConditionGroup>(json, "conditions", |value| {
+            locate_group(value, "conditions")
+        }) {
+            return Some(err);
+        }
+    }
+    if let Some(json) = &row.actions {
+        if let Some(err) = locate_json::<Vec<Action>>(json, "actions", |value| {
+            locate_list::<Action>(value, "actions", |_, _| None)
+        }) {
+            return Some(err);
+        }
+    }
+    if let Some(json) = &row.schedule {
+        if let Err(err) = serde_json::from_str::<RuleSchedule>(json) {
+            return Some(format!("schedule: {err}"));
+        }
+    }
+    None
+}
+
+fn locate_json<T: serde::de::DeserializeOwned>(
+    json: &str,
+    path: &str,
+    descend: impl Fn(&Value) -> Option<String>,
+) -> Option<String> {
+    let err = serde_json::from_str::<T>(json).err()?;
+    let Ok(value) = serde_json::from_str::<Value>(json) else {
+        return Some(format!("{path}: {err}"));
+    };
+    Some(descend(&value).unwrap_or_else(|| format!("{path}: {err}")))
+}
+
+/// `path` names the group itself: `conditions` at the root, or e.g.
+/// `conditions[2]` for a nested group.
+fn locate_group(value: &Value, path: &str) -> Option<String> {
+    let err = serde_json::from_value::<ConditionGroup>(value.clone()).err()?;
+    let items = value.get("conditions").unwrap_or(&Value::Null);
+    let items_path = match path {
+        "conditions" => path.to_string(),
+        _ => format!("{path}.conditions"),
+    };
+    locate_list::<Condition>(items, &items_path, |item, item_path| {
+        match item.get("type").and_then(Value::as_str) {
+            Some("nested") => locate_group(item, item_path),
+            _ => None,
+        }
+    })
+    .or_else(|| Some(format!("{path}: {err}")))
+}
+
+fn locate_list<T: serde::de::DeserializeOwned>(
+    value: &Value,
+    path: &str,
+    descend: impl Fn(&Value, &str) -> Option<String>,
+) -> Option<String> {
+    let items = value.as_array()?;
+    items.iter().enumerate().find_map(|(index, item)| {
+        let err = serde_json::from_value::<T>(item.clone()).err()?;
+        let item_path = format!("{path}[{index}]");
+        Some(descend(item, &item_path).unwrap_or_else(|| format!("{item_path}: {err}")))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        CsvColumnCondition, CsvColumnRef, CsvDelimiter, Folder, MatchType, PasswordSource,
+        StringCondition, StringOperator,
+    };
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::rule_repo::RuleRepository;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn seeded(dir: &Path) -> (Database, Folder) {
+        let db = Database::new_with_path(dir.join("test.db")).unwrap();
+        let watched = dir.join("inbox");
+        fs::create_dir_all(&watched).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&watched.to_string_lossy(), "Inbox")
+            .unwrap();
+        (db, folder)
+    }
+
+    fn add_rule(db: &Database, folder: &Folder, name: &str, conditions: Vec<Condition>) -> Rule {
+        RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id.clone(),
+                name: name.to_string(),
+                enabled: true,
+                stop_processing: true,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions,
+                },
+                actions: vec![],
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap()
+    }
+
+    fn name_matches(pattern: &str) -> Condition {
+        Condition::Name(StringCondition {
+            operator: StringOperator::Matches,
+            value: pattern.to_string(),
+            case_sensitive: false,
+        })
+    }
+
+    fn execute(db: &Database, sql: &str, values: &[&str]) {
+        db.with_conn(|conn| {
+            conn.execute(sql, rusqlite::params_from_iter(values))?;
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    fn of_kind(report: &HealthReport, kind: HealthIssueKind) -> Vec<&HealthIssue> {
+        report.issues.iter().filter(|issue| issue.kind == kind).collect()
+    }
+
+    #[test]
+    fn unreadable_rule_is_located_and_disabled_not_deleted() {
+        let dir = tempdir().unwrap();
+        let (db, folder) = seeded(dir.path());
+        let good = add_rule(&db, &folder, "Invoices", vec![name_matches("^inv")]);
+        let broken = add_rule(&db, &folder, "Receipts", vec![]);
+        assert!(check(&db, CheckDepth::Full).unwrap().issues.is_empty());
+
+        // A torn write left an unknown condition type inside a nested group.
+        execute(
+            &db,
+            "UPDATE rules SET conditions = ?1 WHERE id = ?2",
+            &[
+                r#"{"matchType":"all","conditions":[{"type":"name","operator":"is","value":"a","caseSensitive":false},{"type":"nested","matchType":"any","conditions":[{"type":"bogus"}]}]}"#,
+                &broken.id,
+            ],
+        );
+        let repo = RuleRepository::new(db.clone());
+        assert!(repo.list_by_folder(&folder.id).is_err());
+
+        let report = check(&db, CheckDepth::Quick).unwrap();
+        let issues = of_kind(&report, HealthIssueKind::UnreadableRule);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, HealthSeverity::Error);
+        assert_eq!(issues[0].rule_id.as_deref(), Some(broken.id.as_str()));
+        assert!(issues[0].message.contains("conditions[1].conditions[0]: unknown variant `bogus`"));
+
+        // Nothing changes without the flag.
+        let result = repair(&db, &HealthRepairOptions::default()).unwrap();
+        assert!(result.repaired.is_empty());
+        assert!(repo.list_by_folder(&folder.id).is_err());
+
+        let options = HealthRepairOptions {
+            disable_unreadable_rules: true,
+            ..Default::default()
+        };
+        let result = repair(&db, &options).unwrap();
+        assert_eq!(result.repaired, vec!["Disabled rule \"Receipts\"".to_string()]);
+        let listed = repo.list_by_folder(&folder.id).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, good.id);
+        let remaining = of_kind(&result.report, HealthIssueKind::UnreadableRule);
+        assert_eq!(remaining[0].severity, HealthSeverity::Warning);
+        assert!(repair(&db, &options).unwrap().repaired.is_empty());
+    }
+
+    #[test]
+    fn broken_action_is_reported_by_index() {
+        let dir = tempdir().unwrap();
+        let (db, folder) = seeded(dir.path());
+        let rule = add_rule(&db, &folder, "Archive", vec![]);
+        execute(
+            &db,
+            "UPDATE rules SET actions = ?1 WHERE id = ?2",
+            &[r#"[{"type":"continue"},{"type":"move","destination":"~/x"}]"#, &rule.id],
+        );
+
+        let report = check(&db, CheckDepth::Quick).unwrap();
+        let issues = of_kind(&report, HealthIssueKind::UnreadableRule);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.contains("actions[1]: missing field"));
+    }
+
+    #[test]
+    fn duplicate_positions_are_renumbered_in_order() {
+        let dir = tempdir().unwrap();
+        let (db, folder) = seeded(dir.path());
+        let first = add_rule(&db, &folder, "First", vec![]);
+        let second = add_rule(&db, &folder, "Second", vec![]);
+        let third = add_rule(&db, &folder, "Third", vec![]);
+        execute(&db, "UPDATE rules SET position = 0 WHERE id = ?1", &[&second.id]);
+        execute(&db, "UPDATE rules SET position = 5 WHERE id = ?1", &[&third.id]);
+
+        let report = check(&db, CheckDepth::Quick).unwrap();
+        let issues = of_kind(&report, HealthIssueKind::DuplicatePosition);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].folder_id.as_deref(), Some(folder.id.as_str()));
+        assert!(issues[0].message.contains("First") && issues[0].message.contains("Second"));
+
+        let options = HealthRepairOptions {
+            renumber_positions: true,
+            ..Default::default()
+        };
+        let result = repair(&db, &options).unwrap();
+        assert_eq!(result.repaired.len(), 1);
+        assert!(of_kind(&result.report, HealthIssueKind::DuplicatePosition).is_empty());
+        let order: Vec<(String, i32)> = RuleRepository::new(db)
+            .list_by_folder(&folder.id)
+            .unwrap()
+            .into_iter()
+            .map(|rule| (rule.id, rule.position))
+            .collect();
+        assert_eq!(order, vec![(first.id, 0), (second.id, 1), (third.id, 2)]);
+    }
+
+    #[test]
+    fn rules_left_without_their_folder_are_renumbered_under_its_id() {
+        let dir = tempdir().unwrap();
+        let (db, _) = seeded(dir.path());
+        let watched = dir.path().join("other");
+        fs::create_dir_all(&watched).unwrap();
+        let other = FolderRepository::new(db.clone())
+            .create(&watched.to_string_lossy(), "Other")
+            .unwrap();
+        add_rule(&db, &other, "First", vec![]);
+        let second = add_rule(&db, &other, "Second", vec![]);
+        execute(
+            &db,
+            "UPDATE rules SET position = 0 WHERE id = ?1",
+            &[&second.id],
+        );
+        db.with_conn(|conn| {
+            conn.pragma_update(None, "foreign_keys", false)?;
+            conn.execute("DELETE FROM folders WHERE id = ?1", params![other.id])?;
+            conn.pragma_update(None, "foreign_keys", true)?;
+            Ok(())
+        })
+        .unwrap();
+
+        let options = HealthRepairOptions {
+            renumber_positions: true,
+            ..Default::default()
+        };
+        let result = repair(&db, &options).unwrap();
+        assert_eq!(
+            result.repaired,
+            vec![format!("Re-numbered the rules in \"{}\"", other.id)]
+        );
+        assert!(of_kind(&result.report, HealthIssueKind::DuplicatePosition).is_empty());
+    }
+
+    #[test]
+    fn missing_folder_paths_are_disabled() {
+        let dir = tempdir().unwrap();
+        let (db, inbox) = seeded(dir.path());
+        let repo = FolderRepository::new(db.clone());
+        let gone = repo
+            .create(&dir.path().join("unplugged").to_string_lossy(), "External drive")
+            .unwrap();
+        let file = dir.path().join("notes.txt");
+        fs::write(&file, "x").unwrap();
+        let not_dir = repo.create(&file.to_string_lossy(), "Notes").unwrap();
+
+        let report = check(&db, CheckDepth::Quick).unwrap();
+        let mut flagged: Vec<&str> = of_kind(&report, HealthIssueKind::MissingFolderPath)
+            .iter()
+            .filter_map(|issue| issue.folder_id.as_deref())
+            .collect();
+        flagged.sort();
+        let mut expected = vec![gone.id.as_str(), not_dir.id.as_str()];
+        expected.sort();
+        assert_eq!(flagged, expected);
+
+        let options = HealthRepairOptions {
+            disable_missing_folders: true,
+            ..Default::default()
+        };
+        let result = repair(&db, &options).unwrap();
+        assert_eq!(result.repaired.len(), 2);
+        assert!(result.report.issues.is_empty());
+        assert!(!repo.get(&gone.id).unwrap().unwrap().enabled);
+        assert!(!repo.get(&not_dir.id).unwrap().unwrap().enabled);
+        assert!(repo.get(&inbox.id).unwrap().unwrap().enabled);
+    }
+
+    #[test]
+    fn invalid_regex_is_reported_and_rejected_on_save() {
+        let dir = tempdir().unwrap();
+        let (db, folder) = seeded(dir.path());
+        let rule = add_rule(&db, &folder, "Scans", vec![name_matches("scan_(\\d+")]);
+
+        let report = check(&db, CheckDepth::Quick).unwrap();
+        let issues = of_kind(&report, HealthIssueKind::InvalidPattern);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule_id.as_deref(), Some(rule.id.as_str()));
+        assert!(validate_rule(&rule).unwrap_err().contains("invalid regex"));
+    }
+
+    #[test]
+    fn archive_passwords_kept_in_rules_are_flagged() {
+        let dir = tempdir().unwrap();
+        let (db, folder) = seeded(dir.path());
+        let mut rule = add_rule(&db, &folder, "Payslips", vec![]);
+        let archive = |password_source| {
+            Action::Archive(crate::models::ArchiveAction {
+                destination: "~/Archive/{name}".to_string(),
+                format: crate::models::ArchiveFormat::Zip,
+                delete_after: false,
+                encryption: Some(crate::models::ArchiveEncryption {
+                    method: crate::models::ArchiveEncryptionMethod::ZipAes256,
+                    password_source,
+                }),
+            })
+        };
+        let repo = RuleRepository::new(db.clone());
+        rule.actions = vec![archive(PasswordSource::Keyring("payslips".to_string()))];
+        repo.update(&rule).unwrap();
+        let report = check(&db, CheckDepth::Quick).unwrap();
+        assert!(of_kind(&report, HealthIssueKind::LiteralPassword).is_empty());
+
+        rule.actions = vec![archive(PasswordSource::Literal("hunter2".to_string()))];
+        repo.update(&rule).unwrap();
+        let report = check(&db, CheckDepth::Quick).unwrap();
+        let issues = of_kind(&report, HealthIssueKind::LiteralPassword);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule_id.as_deref(), Some(rule.id.as_str()));
+    }
+
+    #[test]
+    fn unknown_time_zones_are_rejected_on_save() {
+        let dir = tempdir().unwrap();
+        let (db, folder) = seeded(dir.path());
+        let uploads = |zone: &str| {
+            Condition::DateCreated(crate::models::DateCondition {
+                operator: crate::models::DateOperator::InTheLast {
+                    amount: 1,
+                    unit: crate::models::TimeUnit::Days,
+                },
+                timezone: Some(zone.to_string()),
+            })
+        };
+        let mut rule = add_rule(&db, &folder, "Camera", vec![uploads("default")]);
+        rule.actions = vec![Action::Rename(crate::models::RenameAction {
+            pattern: "{created:%Y-%m-%d@UTC}-{name}".to_string(),
+            on_conflict: crate::models::ConflictResolution::Rename,
+        })];
+        assert!(validate_rule(&rule).is_ok());
+
+        rule.conditions.conditions = vec![uploads("Europe/Atlantis")];
+        let err = validate_rule(&rule).unwrap_err();
+        assert!(err.contains("unknown time zone \"Europe/Atlantis\""), "{err}");
+
+        rule.conditions.conditions = vec![uploads("UTC")];
+        rule.actions = vec![Action::Rename(crate::models::RenameAction {
+            pattern: "{name}-{date@Moon/Base}".to_string(),
+            on_conflict: crate::models::ConflictResolution::Rename,
+        })];
+        let err = validate_rule(&rule).unwrap_err();
+        assert!(err.contains("actions[0]: unknown time zone \"Moon/Base\""), "{err}");
+    }
+
+    #[test]
+    fn csv_column_settings_are_checked_on_save() {
+        let dir = tempdir().unwrap();
+        let (db, folder) = seeded(dir.path());
+        let account = |column: CsvColumnRef, delimiter: CsvDelimiter| {
+            Condition::CsvColumn(CsvColumnCondition {
+                column,
+                row: Default::default(),
+                condition: crate::models::StringCondition {
+                    operator: StringOperator::Matches,
+                    value: "^DE\\d{2}".to_string(),
+                    case_sensitive: false,
+                },
+                delimiter,
+            })
+        };
+        let header = CsvColumnRef::Header {
+            name: "IBAN".to_string(),
+        };
+        let mut rule = add_rule(
+            &db,
+            &folder,
+            "Statements",
+            vec![account(header.clone(), CsvDelimiter::Char { value: ';' })],
+        );
+        assert!(validate_rule(&rule).is_ok());
+
+        let first = CsvColumnRef::Index { index: 0 };
+        rule.conditions.conditions = vec![account(first, CsvDelimiter::Auto)];
+        assert!(validate_rule(&rule).unwrap_err().contains("start at 1"));
+
+        rule.conditions.conditions = vec![account(header, CsvDelimiter::Char { value: '§' })];
+        assert!(validate_rule(&rule).unwrap_err().contains("ASCII"));
+    }
+
+    #[test]
+    fn explicit_destination_permissions_are_checked_on_save() {
+        let dir = tempdir().unwrap();
+        let (db, folder) = seeded(dir.path());
+        let mut rule = add_rule(&db, &folder, "Shared", vec![]);
+        let sort = |mode: &str| {
+            Action::SortIntoSubfolder(SortAction {
+                destination: "~/Shared/{kind}".to_string(),
+                on_conflict: crate::models::ConflictResolution::Rename,
+                destination_permissions: crate::models::DestinationPermissions::Explicit {
+                    mode: Some(mode.to_string()),
+                    group: None,
+                },
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
+            })
+        };
+        rule.actions = vec![sort("0640")];
+        assert!(validate_rule(&rule).is_ok());
+
+        rule.actions = vec![sort("0999")];
+        let err = validate_rule(&rule).unwrap_err();
+        assert!(err.contains("Invalid file mode \"0999\""), "{err}");
+    }
+
+    #[test]
+    fn orphaned_rows_and_null_columns_are_reported() {
+        let dir = tempdir().unwrap();
+        let (db, folder) = seeded(dir.path());
+        let watched = dir.path().join("other");
+        fs::create_dir_all(&watched).unwrap();
+        let other = FolderRepository::new(db.clone())
+            .create(&watched.to_string_lossy(), "Other")
+            .unwrap();
+        let orphan = add_rule(&db, &other, "Leftover", vec![]);
+        db.with_conn(|conn| {
+            // The pool doesn't enforce foreign keys on every connection.
+            conn.pragma_update(None, "foreign_keys", false)?;
+            conn.execute("DELETE FROM folders WHERE id = ?1", params![other.id])?;
+            conn.execute("UPDATE folders SET is_group = NULL WHERE id = ?1", params![folder.id])?;
+            conn.pragma_update(None, "foreign_keys", true)?;
+            Ok(())
+        })
+        .unwrap();
+
+        let report = check(&db, CheckDepth::Full).unwrap();
+        let orphans = of_kind(&report, HealthIssueKind::OrphanedRule);
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].rule_id.as_deref(), Some(orphan.id.as_str()));
+        let missing = of_kind(&report, HealthIssueKind::MissingValue);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].folder_id.as_deref(), Some(folder.id.as_str()));
+        assert!(missing[0].message.contains("folders.is_group"));
+        assert_eq!(report.issues[0].severity, HealthSeverity::Error);
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::models::{
+    ClassificationCode, ClassificationTotal, DailyInsight, Folder, FolderInsight, InsightsSummary,
+};
+use crate::storage::database::Database;
+use crate::storage::folder_repo::FolderRepository;
+use crate::storage::insights_repo::InsightsRepository;
+use crate::storage::match_repo::MatchRepository;
+
+/// How far back a folder's match percentage looks.
+const MATCH_WINDOW_DAYS: i64 = 30;
+
+/// Totals from the rollups plus the logs still on disk, and each folder's
+/// recent match percentage.
+pub fn summary(
+    db: &Database,
+    seconds_per_file: u32,
+    now: DateTime<Utc>,
+) -> Result<InsightsSummary> {
+    let repo = InsightsRepository::new(db.clone());
+    let daily = repo.daily()?;
+    let reset_at = repo.reset_at()?;
+
+    let files_sorted: u64 = daily.values().map(|day| day.files_sorted).sum();
+    let bytes_moved: u64 = daily.values().map(|day| day.bytes_moved).sum();
+    let first_day = match (daily.keys().next(), reset_at) {
+        (Some(day), Some(reset)) => Some((*day).max(reset.date_naive())),
+        (Some(day), None) => Some(*day),
+        (None, Some(reset)) => Some(reset.date_naive()),
+        (None, None) => None,
+    };
+
+    let mut since = now - Duration::days(MATCH_WINDOW_DAYS);
+    if let Some(reset) = reset_at {
+        since = since.max(reset);
+    }
+    let match_repo = MatchRepository::new(db.clone());
+    let mut folders = Vec::new();
+    for folder in FolderRepository::new(db.clone()).list()? {
+        if folder.is_group {
+            continue;
+        }
+        let matched = match_repo.paths_for_folder_since(&folder.id, since)?;
+        folders.push(folder_insight(&folder, &matched));
+    }
+
+    Ok(InsightsSummary {
+        files_sorted,
+        bytes_moved,
+        time_saved_seconds: files_sorted * seconds_per_file as u64,
+        streak_days: streak_days(&daily, first_day, now.date_naive()),
+        folders,
+    })
+}
+
+pub fn reset(db: &Database, now: DateTime<Utc>) -> Result<()> {
+    InsightsRepository::new(db.clone()).reset(now)
+}
+
+/// Files and bytes per classification code logged on the UTC days `from`
+/// through `to`, labelled from `codes`. Codes are reported as they were
+/// logged, so renaming one in the settings doesn't merge or relabel history.
+pub fn classification_report(
+    db: &Database,
+    codes: &[ClassificationCode],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<ClassificationTotal>> {
+    let until = to.succ_opt().unwrap_or(to);
+    let totals = InsightsRepository::new(db.clone()).classification_totals(from, until)?;
+    Ok(totals
+        .into_iter()
+        .map(|(code, (files, bytes))| ClassificationTotal {
+            label: codes
+                .iter()
+                .find(|known| known.code == code)
+                .map(|known| known.label.clone()),
+            code,
+            files,
+            bytes,
+        })
+        .collect())
+}
+
+/// Days without an intervention counting back from `today`. Quiet days with
+/// no activity don't break the streak; it can't reach back past `first_day`,
+/// the first day anything was tracked.
+fn streak_days(
+    daily: &BTreeMap<NaiveDate, DailyInsight>,
+    first_day: Option<NaiveDate>,
+    today: NaiveDate,
+) -> u32 {
+    let Some(first_day) = first_day else {
+        return 0;
+    };
+    let last_intervention = daily
+        .range(..=today)
+        .rev()
+        .find(|(_, day)| day.interventions > 0)
+        .map(|(day, _)| *day);
+    let start = match last_intervention {
+        Some(day) => (day + Duration::days(1)).max(first_day),
+        None => first_day,
+    };
+    if start > today {
+        return 0;
+    }
+    ((today - start).num_days() + 1) as u32
+}
+
+/// Matched files against everything that passed through the folder: the
+/// matches plus the unmatched files still sitting at its top level.
+fn folder_insight(folder: &Folder, matched: &HashSet<String>) -> FolderInsight {
+    let mut total = matched.len() as u64;
+    if let Ok(entries) = fs::read_dir(Path::new(&folder.path)) {
+        for entry in entries.flatten() {
+            let is_file = entry.file_type().map(|t| t.is_file()).unwrap_or(false);
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            let path = entry.path().to_string_lossy().to_string();
+            if is_file && !hidden && !matched.contains(&path) {
+                total += 1;
+            }
+        }
+    }
+    let matched_files = matched.len() as u64;
+    FolderInsight {
+        folder_id: folder.id.clone(),
+        folder_name: folder.name.clone(),
+        matched_files,
+        total_files: total,
+        match_percent: (total > 0).then(|| matched_files as f64 * 100.0 / total as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::log_repo::LogRepository;
+    use rusqlite::params;
+    use tempfile::tempdir;
+
+    fn day(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn seed_rollup(db: &Database, day: NaiveDate, files: i64, bytes: i64, interventions: i64) {
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO insight_rollups (day, files_sorted, bytes_moved, interventions) VALUES (?1, ?2, ?3, ?4)",
+                params![day.format("%Y-%m-%d").to_string(), files, bytes, interventions],
+            )?;
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    fn seed_log(
+        db: &Database,
+        at: DateTime<Utc>,
+        path: &str,
+        action: &str,
+        status: &str,
+        size: u64,
+    ) {
+        let detail = format!(
+            r#"{{"sourcePath":"{path}","destinationPath":null,"metadata":{{"size_bytes":"{size}"}}}}"#
+        );
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO logs (id, rule_id, rule_name, file_path, action_type, action_detail, status, error_message, created_at) VALUES (?1, NULL, 'Rule', ?2, ?3, ?4, ?5, NULL, ?6)",
+                params![uuid::Uuid::new_v4().to_string(), path, action, detail, status, at.to_rfc3339()],
+            )?;
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    fn seed_classified_log(
+        db: &Database,
+        at: DateTime<Utc>,
+        path: &str,
+        action: &str,
+        code: &str,
+        size: u64,
+    ) {
+        let detail = format!(
+            r#"{{"sourcePath":"{path}","destinationPath":null,"metadata":{{"size_bytes":"{size}","classification":"{code}"}}}}"#
+        );
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO logs (id, rule_id, rule_name, file_path, action_type, action_detail, status, error_message, created_at) VALUES (?1, NULL, 'Rule', ?2, ?3, ?4, 'success', NULL, ?5)",
+                params![uuid::Uuid::new_v4().to_string(), path, action, detail, at.to_rfc3339()],
+            )?;
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn streak_skips_quiet_days_and_stops_at_an_intervention() {
+        let today = day("2026-03-10");
+        let sorted = |files_sorted| DailyInsight {
+            files_sorted,
+            ..Default::default()
+        };
+        let mut daily = BTreeMap::new();
+        daily.insert(
+            day("2026-03-05"),
+            DailyInsight {
+                interventions: 1,
+                ..sorted(2)
+            },
+        );
+        daily.insert(day("2026-03-06"), sorted(3));
+        // 2026-03-07 is a gap day with no activity at all.
+        daily.insert(day("2026-03-08"), sorted(1));
+        daily.insert(day("2026-03-10"), sorted(4));
+
+        assert_eq!(streak_days(&daily, Some(day("2026-03-01")), today), 5);
+
+        daily.insert(
+            day("2026-03-10"),
+            DailyInsight {
+                interventions: 2,
+                ..sorted(4)
+            },
+        );
+        assert_eq!(streak_days(&daily, Some(day("2026-03-01")), today), 0);
+
+        // Without any intervention the streak runs back to the first tracked day.
+        daily.clear();
+        daily.insert(day("2026-03-08"), sorted(1));
+        assert_eq!(streak_days(&daily, Some(day("2026-03-08")), today), 3);
+        assert_eq!(streak_days(&BTreeMap::new(), None, today), 0);
+    }
+
+    #[test]
+    fn summary_combines_rollups_with_recent_logs() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let now = Utc::now();
+        let today = now.date_naive();
+
+        seed_rollup(&db, today - Duration::days(60), 10, 1_000, 1);
+        seed_rollup(&db, today - Duration::days(50), 5, 500, 0);
+        seed_log(&db, now - Duration::days(2), "/in/a.pdf", "move", "success", 100);
+        // A second action on the same file the same day isn't another file.
+        seed_log(&db, now - Duration::days(2), "/in/a.pdf", "rename", "success", 100);
+        seed_log(&db, now - Duration::hours(1), "/in/b.pdf", "copy", "success", 50);
+        seed_log(&db, now - Duration::hours(1), "/in/c.pdf", "notify", "success", 70);
+
+        let summary = summary(&db, 10, now).unwrap();
+        assert_eq!(summary.files_sorted, 17);
+        assert_eq!(summary.bytes_moved, 1_650);
+        assert_eq!(summary.time_saved_seconds, 170);
+        assert_eq!(summary.streak_days, 60);
+    }
+
+    #[test]
+    fn pruning_logs_keeps_historical_totals() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let now = Utc::now();
+
+        seed_rollup(&db, now.date_naive() - Duration::days(90), 3, 300, 0);
+        seed_log(&db, now - Duration::days(45), "/in/old.pdf", "move", "success", 40);
+        seed_log(&db, now - Duration::days(44), "/in/bad.pdf", "move", "error", 10);
+        seed_log(&db, now - Duration::days(1), "/in/new.pdf", "move", "success", 20);
+        let before = summary(&db, 10, now).unwrap();
+        assert_eq!(before.files_sorted, 5);
+        assert_eq!(before.bytes_moved, 360);
+
+        LogRepository::new(db.clone()).cleanup(30).unwrap();
+        let remaining: i64 = db
+            .with_conn(|conn| {
+                Ok(conn.query_row("SELECT COUNT(*) FROM logs", [], |row| row.get(0))?)
+            })
+            .unwrap();
+        assert_eq!(remaining, 1);
+
+        let after = summary(&db, 10, now).unwrap();
+        assert_eq!(after, before);
+        // The failed move 44 days ago still ends the streak after it was pruned.
+        assert_eq!(after.streak_days, 44);
+
+        LogRepository::new(db.clone()).clear().unwrap();
+        assert_eq!(summary(&db, 10, now).unwrap(), before);
+    }
+
+    #[test]
+    fn reset_starts_over() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let now = DateTime::parse_from_rfc3339("2026-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        seed_rollup(&db, now.date_naive() - Duration::days(10), 3, 300, 0);
+        seed_log(&db, now - Duration::hours(2), "/in/a.pdf", "move", "success", 40);
+        reset(&db, now - Duration::hours(1)).unwrap();
+        seed_log(&db, now - Duration::minutes(5), "/in/b.pdf", "move", "success", 25);
+
+        let summary = summary(&db, 10, now).unwrap();
+        assert_eq!(summary.files_sorted, 1);
+        assert_eq!(summary.bytes_moved, 25);
+        assert_eq!(summary.streak_days, 1);
+
+        // Logs from before the reset don't come back through the rollups either.
+        LogRepository::new(db.clone()).clear().unwrap();
+        assert_eq!(super::summary(&db, 10, now).unwrap().files_sorted, 1);
+    }
+
+    #[test]
+    fn classification_report_totals_each_code_across_pruned_and_live_logs() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let now = Utc::now();
+        let today = now.date_naive();
+        let recent = today.and_hms_opt(0, 0, 1).unwrap().and_utc();
+        let codes = [ClassificationCode {
+            code: "FIN-7Y".to_string(),
+            label: "Finance, 7 years".to_string(),
+            description: String::new(),
+        }];
+
+        seed_classified_log(
+            &db,
+            now - Duration::days(5),
+            "/in/a.pdf",
+            "move",
+            "FIN-7Y",
+            100,
+        );
+        seed_classified_log(&db, recent, "/in/b.pdf", "move", "FIN-7Y", 50);
+        seed_classified_log(&db, recent, "/in/b.pdf", "rename", "FIN-7Y", 50);
+        seed_classified_log(&db, recent, "/in/c.pdf", "copy", "HR-3Y", 70);
+        seed_classified_log(&db, recent, "/in/d.pdf", "notify", "HR-3Y", 90);
+        seed_log(&db, recent, "/in/e.pdf", "move", "success", 30);
+        // Pruning rolls the oldest log up; resetting insights leaves it be.
+        LogRepository::new(db.clone()).cleanup(2).unwrap();
+        reset(&db, now).unwrap();
+
+        let report = classification_report(&db, &codes, today - Duration::days(7), today).unwrap();
+        assert_eq!(
+            report,
+            vec![
+                ClassificationTotal {
+                    code: "FIN-7Y".to_string(),
+                    label: Some("Finance, 7 years".to_string()),
+                    files: 2,
+                    bytes: 150,
+                },
+                ClassificationTotal {
+                    code: "HR-3Y".to_string(),
+                    label: None,
+                    files: 1,
+                    bytes: 70,
+                },
+            ]
+        );
+
+        let today_only = classification_report(&db, &codes, today, today).unwrap();
+        assert_eq!(today_only[0].files, 1);
+        assert_eq!(today_only[0].bytes, 50);
+        let earlier = today - Duration::days(30);
+        assert!(classification_report(&db, &codes, earlier, earlier)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn folder_percentage_counts_unmatched_files_left_behind() {
+        let dir = tempdir().unwrap();
+        let watch = dir.path().join("watch");
+        fs::create_dir_all(&watch).unwrap();
+        fs::write(watch.join("left.txt"), "x").unwrap();
+        fs::write(watch.join(".hidden"), "x").unwrap();
+        let kept = watch.join("kept.txt");
+        fs::write(&kept, "x").unwrap();
+
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db)
+            .create(&watch.to_string_lossy(), "Watch")
+            .unwrap();
+        let matched: HashSet<String> = [
+            "/elsewhere/moved.pdf".to_string(),
+            kept.to_string_lossy().to_string(),
+        ]
+        .into_iter()
+        .collect();
+
+        let insight = folder_insight(&folder, &matched);
+        assert_eq!(insight.matched_files, 2);
+        assert_eq!(insight.total_files, 3);
+        assert!((insight.match_percent.unwrap() - 66.666).abs() < 0.01);
+    }
+}
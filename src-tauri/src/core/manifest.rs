@@ -0,0 +1,628 @@
+//! Manifests of the files a rule matched, for handing them on to other tools:
+//! where each file came from, where it is now as far as the logs can tell,
+//! and its hash and size.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::duplicates::hash_file;
+use crate::models::{LogEntry, LogStatus};
+use crate::storage::database::Database;
+use crate::storage::log_repo::LogRepository;
+use crate::storage::match_repo::{MatchRepository, RuleMatch};
+use crate::storage::undo_repo::UndoRepository;
+
+/// Matches read from the database at a time, so a long history is written
+/// out as it's read rather than held in memory.
+const PAGE_SIZE: usize = 500;
+
+/// Largest manifest returned inline; bigger ones must be saved to a file.
+pub const INLINE_LIMIT: usize = 1024 * 1024;
+
+const CSV_HEADER: [&str; 9] = [
+    "matched_at",
+    "original_path",
+    "current_best_known_path",
+    "location",
+    "hash",
+    "size",
+    "classification",
+    "trace_id",
+    "metadata",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ManifestFormat {
+    Csv,
+    Jsonl,
+    /// A playlist of the files' current paths.
+    M3u,
+}
+
+/// Whether a file was found where the logs last saw it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Location {
+    Confirmed,
+    /// Nothing is there any more, or a file of another size is.
+    Unconfirmed,
+}
+
+impl Location {
+    fn as_str(self) -> &'static str {
+        match self {
+            Location::Confirmed => "confirmed",
+            Location::Unconfirmed => "unconfirmed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestRow {
+    pub matched_at: DateTime<Utc>,
+    pub original_path: String,
+    /// The last place a move, rename, sort or undo in the logs took the file.
+    pub current_best_known_path: String,
+    pub location: Location,
+    /// SHA-256 of the file where it is now, or for a file that can't be
+    /// found, as recorded when it was last moved.
+    pub hash: Option<String>,
+    pub size: Option<u64>,
+    pub classification: Option<String>,
+    pub trace_id: Option<String>,
+    /// What the rule's `record_metadata` resolved to when it matched.
+    pub metadata: BTreeMap<String, String>,
+}
+
+pub struct ManifestQuery<'a> {
+    pub rule_id: &'a str,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestExport {
+    pub rows: usize,
+    pub unconfirmed: usize,
+    /// The manifest itself, when it wasn't written to a file.
+    pub content: Option<String>,
+}
+
+/// Writes the manifest to `path`, or returns it when there's none and it
+/// fits within `INLINE_LIMIT`.
+pub fn export(
+    db: &Database,
+    query: &ManifestQuery,
+    format: ManifestFormat,
+    path: Option<&Path>,
+) -> Result<ManifestExport> {
+    match path {
+        Some(path) => {
+            let mut out = BufWriter::new(File::create(path)?);
+            let export = write_manifest(db, query, format, &mut out)?;
+            out.flush()?;
+            Ok(export)
+        }
+        None => export_inline(db, query, format, INLINE_LIMIT),
+    }
+}
+
+fn export_inline(
+    db: &Database,
+    query: &ManifestQuery,
+    format: ManifestFormat,
+    limit: usize,
+) -> Result<ManifestExport> {
+    let mut out = Capped {
+        buf: Vec::new(),
+        limit,
+        exceeded: false,
+    };
+    let written = write_manifest(db, query, format, &mut out);
+    if out.exceeded {
+        bail!(
+            "The manifest is larger than {} KB; save it to a file instead",
+            limit / 1024
+        );
+    }
+    let mut export = written?;
+    export.content = Some(String::from_utf8_lossy(&out.buf).to_string());
+    Ok(export)
+}
+
+/// Writes each match as it's located, page by page.
+pub fn write_manifest(
+    db: &Database,
+    query: &ManifestQuery,
+    format: ManifestFormat,
+    out: impl Write,
+) -> Result<ManifestExport> {
+    let mut export = ManifestExport::default();
+    match format {
+        ManifestFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record(CSV_HEADER)?;
+            for_each_row(db, query, &mut export, |row| {
+                writer.write_record(csv_record(row))?;
+                Ok(())
+            })?;
+            writer.flush()?;
+        }
+        ManifestFormat::Jsonl => {
+            let mut out = out;
+            for_each_row(db, query, &mut export, |row| {
+                serde_json::to_writer(&mut out, row)?;
+                out.write_all(b"\n")?;
+                Ok(())
+            })?;
+        }
+        ManifestFormat::M3u => {
+            let mut out = out;
+            out.write_all(b"#EXTM3U\n")?;
+            for_each_row(db, query, &mut export, |row| {
+                // Players would skip a missing file anyway; a comment keeps
+                // it visible without passing it off as there.
+                if row.location == Location::Unconfirmed {
+                    out.write_all(b"# unconfirmed: ")?;
+                }
+                writeln!(out, "{}", row.current_best_known_path)?;
+                Ok(())
+            })?;
+        }
+    }
+    Ok(export)
+}
+
+fn for_each_row(
+    db: &Database,
+    query: &ManifestQuery,
+    export: &mut ManifestExport,
+    mut write: impl FnMut(&ManifestRow) -> Result<()>,
+) -> Result<()> {
+    let matches = MatchRepository::new(db.clone());
+    let logs = LogRepository::new(db.clone());
+    let undo = UndoRepository::new(db.clone());
+    let mut after: Option<RuleMatch> = None;
+    loop {
+        let page = matches.page_for_rule(
+            query.rule_id,
+            query.since,
+            query.until,
+            after.as_ref(),
+            PAGE_SIZE,
+        )?;
+        for matched in &page {
+            let row = locate(&logs, &undo, query.rule_id, matched)?;
+            export.rows += 1;
+            if row.location == Location::Unconfirmed {
+                export.unconfirmed += 1;
+            }
+            write(&row)?;
+        }
+        if page.len() < PAGE_SIZE {
+            return Ok(());
+        }
+        after = page.into_iter().last();
+    }
+}
+
+/// Where the logs last saw a matched file, and the logs that took it there.
+pub(crate) struct Trail {
+    /// The entries of the run that matched it.
+    pub run: Vec<LogEntry>,
+    pub trace_id: Option<String>,
+    pub current: String,
+    /// The log of the last move, rename, sort or undo along the way.
+    pub last_move: Option<String>,
+}
+
+/// Follows the file from where it matched through the moves of the run that
+/// matched it, then through any later move, rename or undo logged for where
+/// it went, by this rule or another.
+pub(crate) fn follow(logs: &LogRepository, rule_id: &str, matched: &RuleMatch) -> Result<Trail> {
+    let mut run = logs.for_rule_and_path(rule_id, &matched.file_path, matched.matched_at)?;
+    // Runs share a trace id; earlier runs on the same path are left out.
+    let trace_id = run
+        .iter()
+        .rev()
+        .find_map(|entry| metadata(entry, "trace_id"));
+    if let Some(trace_id) = &trace_id {
+        run.retain(|entry| metadata(entry, "trace_id").as_ref() == Some(trace_id));
+    }
+
+    let mut current = matched.file_path.clone();
+    let mut last_move = None;
+    for entry in &run {
+        if let Some(destination) = moved_to(entry, &current) {
+            current = destination;
+            last_move = Some(entry.id.clone());
+        }
+    }
+    let mut after = matched.matched_at;
+    while let Some(entry) = logs.next_move_from(&current, after)? {
+        after = entry.created_at;
+        match moved_to(&entry, &current) {
+            Some(destination) => current = destination,
+            None => break,
+        }
+        last_move = Some(entry.id);
+    }
+    Ok(Trail {
+        run,
+        trace_id,
+        current,
+        last_move,
+    })
+}
+
+fn locate(
+    logs: &LogRepository,
+    undo: &UndoRepository,
+    rule_id: &str,
+    matched: &RuleMatch,
+) -> Result<ManifestRow> {
+    let Trail {
+        run,
+        trace_id,
+        current,
+        last_move,
+    } = follow(logs, rule_id, matched)?;
+
+    let size = run
+        .iter()
+        .find_map(|entry| metadata(entry, "size_bytes"))
+        .and_then(|size| size.parse().ok());
+    let (location, hash) = if is_there(Path::new(&current), size) {
+        (Location::Confirmed, hash_file(Path::new(&current)).ok())
+    } else {
+        // What the file was when it was last moved, if undo history has it.
+        let moved = match &last_move {
+            Some(log_id) => undo.get_by_log(log_id)?,
+            None => None,
+        };
+        // A sampled hash isn't the file's SHA-256, so it isn't passed off as one.
+        let moved = moved.filter(|entry| entry.hash_scheme.is_none());
+        (Location::Unconfirmed, moved.and_then(|entry| entry.hash))
+    };
+    Ok(ManifestRow {
+        matched_at: matched.matched_at,
+        original_path: matched.file_path.clone(),
+        current_best_known_path: current,
+        location,
+        hash,
+        size,
+        classification: run
+            .iter()
+            .find_map(|entry| metadata(entry, "classification")),
+        trace_id,
+        metadata: matched.metadata.clone(),
+    })
+}
+
+fn metadata(entry: &LogEntry, key: &str) -> Option<String> {
+    entry.action_detail.as_ref()?.metadata.get(key).cloned()
+}
+
+/// Where `entry` took the file at `path`, if it moved it.
+fn moved_to(entry: &LogEntry, path: &str) -> Option<String> {
+    let moves = matches!(
+        entry.action_type.as_str(),
+        "move" | "rename" | "sortIntoSubfolder" | "undo"
+    );
+    if entry.status != LogStatus::Success || !moves {
+        return None;
+    }
+    let detail = entry.action_detail.as_ref()?;
+    if detail.source_path != path
+        || detail.metadata.get("undo_action").map(String::as_str) == Some("copy")
+    {
+        return None;
+    }
+    detail.destination_path.clone()
+}
+
+fn is_there(path: &Path, size: Option<u64>) -> bool {
+    match std::fs::metadata(path) {
+        Ok(meta) => meta.is_file() && size.is_none_or(|size| meta.len() == size),
+        Err(_) => false,
+    }
+}
+
+fn csv_record(row: &ManifestRow) -> [String; 9] {
+    [
+        row.matched_at.to_rfc3339(),
+        row.original_path.clone(),
+        row.current_best_known_path.clone(),
+        row.location.as_str().to_string(),
+        row.hash.clone().unwrap_or_default(),
+        row.size.map(|size| size.to_string()).unwrap_or_default(),
+        row.classification.clone().unwrap_or_default(),
+        row.trace_id.clone().unwrap_or_default(),
+        // Blank rather than `{}` when the rule records none.
+        if row.metadata.is_empty() {
+            String::new()
+        } else {
+            serde_json::to_string(&row.metadata).unwrap_or_default()
+        },
+    ]
+}
+
+/// An in-memory sink that refuses to grow past `limit`.
+struct Capped {
+    buf: Vec<u8>,
+    limit: usize,
+    exceeded: bool,
+}
+
+impl Write for Capped {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + data.len() > self.limit {
+            self.exceeded = true;
+            return Err(io::Error::other("manifest over the inline limit"));
+        }
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ActionDetails, ConditionGroup, MatchType, Rule, UndoEntry, UndoStatus};
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::rule_repo::RuleRepository;
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::{tempdir, TempDir};
+
+    struct Fixture {
+        dir: TempDir,
+        db: Database,
+        rule_id: String,
+    }
+
+    fn fixture() -> Fixture {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Recordings")
+            .unwrap();
+        let rule = RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id,
+                name: "Needs transcription".to_string(),
+                enabled: true,
+                stop_processing: true,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: Vec::new(),
+                },
+                actions: Vec::new(),
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: Some("REC-1Y".to_string()),
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap();
+        Fixture {
+            dir,
+            db,
+            rule_id: rule.id,
+        }
+    }
+
+    impl Fixture {
+        fn path(&self, name: &str) -> String {
+            self.dir.path().join(name).to_string_lossy().to_string()
+        }
+
+        /// Logs `action_type` taking `source` to `destination`, as a run of
+        /// the rule on `original` would.
+        fn log(
+            &self,
+            rule_id: Option<&str>,
+            original: &str,
+            action_type: &str,
+            to: [&str; 2],
+        ) -> String {
+            let mut metadata = HashMap::new();
+            metadata.insert("size_bytes".to_string(), "5".to_string());
+            metadata.insert("trace_id".to_string(), "trace-1".to_string());
+            metadata.insert("classification".to_string(), "REC-1Y".to_string());
+            LogRepository::new(self.db.clone())
+                .insert(LogEntry {
+                    id: String::new(),
+                    rule_id: rule_id.map(str::to_string),
+                    rule_name: None,
+                    file_path: original.to_string(),
+                    action_type: action_type.to_string(),
+                    action_detail: Some(ActionDetails {
+                        source_path: to[0].to_string(),
+                        destination_path: Some(to[1].to_string()),
+                        metadata,
+                    }),
+                    status: LogStatus::Success,
+                    error_message: None,
+                    created_at: Utc::now(),
+                })
+                .unwrap()
+                .id
+        }
+
+        fn matched(&self, path: &str) {
+            MatchRepository::new(self.db.clone())
+                .record_match(&self.rule_id, path, Some("1717229400:5"))
+                .unwrap();
+        }
+
+        fn inline(&self, format: ManifestFormat, limit: usize) -> Result<ManifestExport> {
+            let query = ManifestQuery {
+                rule_id: &self.rule_id,
+                since: None,
+                until: None,
+            };
+            export_inline(&self.db, &query, format, limit)
+        }
+    }
+
+    #[test]
+    fn current_path_follows_the_file_after_it_moved() {
+        let fx = fixture();
+        let original = fx.path("memo.wav");
+        let filed = fx.path("filed.wav");
+        let renamed = fx.path("2024-06-01 memo.wav");
+        fx.log(Some(&fx.rule_id), &original, "move", [&original, &filed]);
+        fx.matched(&original);
+        // A later rename by another rule is followed too.
+        fx.log(None, &filed, "rename", [&filed, &renamed]);
+        fs::write(&renamed, "audio").unwrap();
+
+        let export = fx.inline(ManifestFormat::Jsonl, INLINE_LIMIT).unwrap();
+        assert_eq!((export.rows, export.unconfirmed), (1, 0));
+        let row: serde_json::Value = serde_json::from_str(export.content.unwrap().trim()).unwrap();
+        assert_eq!(row["original_path"], original.as_str());
+        assert_eq!(row["current_best_known_path"], renamed.as_str());
+        assert_eq!(row["location"], "confirmed");
+        assert_eq!(
+            row["hash"],
+            "6ed8919ce20490a5e3ad8630a4fab69475297abd07db73918dd5f36fcfaeb11b"
+        );
+        assert_eq!(row["size"], 5);
+        assert_eq!(row["classification"], "REC-1Y");
+        assert_eq!(row["trace_id"], "trace-1");
+    }
+
+    #[test]
+    fn files_that_cant_be_found_are_flagged() {
+        let fx = fixture();
+        let original = fx.path("memo.wav");
+        let filed = fx.path("filed.wav");
+        let moved = fx.log(Some(&fx.rule_id), &original, "move", [&original, &filed]);
+        fx.matched(&original);
+        UndoRepository::new(fx.db.clone())
+            .insert(UndoEntry {
+                id: String::new(),
+                log_id: moved,
+                action_type: "move".to_string(),
+                original_path: original.clone(),
+                current_path: filed.clone(),
+                created_at: Utc::now(),
+                hash: Some("b5bb9d80".to_string()),
+                hash_scheme: None,
+                classification: None,
+                status: UndoStatus::Available,
+                status_reason: None,
+            })
+            .unwrap();
+
+        let export = fx.inline(ManifestFormat::M3u, INLINE_LIMIT).unwrap();
+        assert_eq!((export.rows, export.unconfirmed), (1, 1));
+        assert_eq!(
+            export.content.unwrap(),
+            format!("#EXTM3U\n# unconfirmed: {}\n", filed)
+        );
+
+        // The hash is what undo history recorded as it was moved.
+        let export = fx.inline(ManifestFormat::Jsonl, INLINE_LIMIT).unwrap();
+        let row: serde_json::Value = serde_json::from_str(export.content.unwrap().trim()).unwrap();
+        assert_eq!(row["location"], "unconfirmed");
+        assert_eq!(row["current_best_known_path"], filed.as_str());
+        assert_eq!(row["hash"], "b5bb9d80");
+
+        // A file of another size where it was left isn't taken for it either.
+        fs::write(&filed, "re-recorded").unwrap();
+        let export = fx.inline(ManifestFormat::M3u, INLINE_LIMIT).unwrap();
+        assert_eq!(export.unconfirmed, 1);
+    }
+
+    #[test]
+    fn csv_quotes_paths_with_commas_and_quotes() {
+        let fx = fixture();
+        let original = fx.path("Smith, \"Jr\" interview.wav");
+        fs::write(&original, "audio").unwrap();
+        fx.matched(&original);
+
+        let content = fx
+            .inline(ManifestFormat::Csv, INLINE_LIMIT)
+            .unwrap()
+            .content
+            .unwrap();
+        assert!(content.contains("Smith, \"\"Jr\"\" interview.wav\""));
+        let mut reader = csv::Reader::from_reader(content.as_bytes());
+        assert_eq!(reader.headers().unwrap(), CSV_HEADER.as_slice());
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(&record[1], original);
+        assert_eq!(&record[2], original);
+        assert_eq!(&record[3], "confirmed");
+    }
+
+    #[test]
+    fn recorded_metadata_is_exported_with_each_match() {
+        let fx = fixture();
+        let original = fx.path("acme-0042.wav");
+        fs::write(&original, "audio").unwrap();
+        let metadata = BTreeMap::from([("vendor".to_string(), "acme".to_string())]);
+        MatchRepository::new(fx.db.clone())
+            .record_match_with(&fx.rule_id, &original, None, &metadata, &[])
+            .unwrap();
+
+        let export = fx.inline(ManifestFormat::Jsonl, INLINE_LIMIT).unwrap();
+        let row: serde_json::Value = serde_json::from_str(export.content.unwrap().trim()).unwrap();
+        assert_eq!(row["metadata"]["vendor"], "acme");
+
+        let content = fx
+            .inline(ManifestFormat::Csv, INLINE_LIMIT)
+            .unwrap()
+            .content
+            .unwrap();
+        let mut reader = csv::Reader::from_reader(content.as_bytes());
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(&record[8], r#"{"vendor":"acme"}"#);
+    }
+
+    #[test]
+    fn inline_manifests_are_capped() {
+        let fx = fixture();
+        for n in 0..40 {
+            fx.matched(&fx.path(&format!("memo-{n}.wav")));
+        }
+
+        let err = fx.inline(ManifestFormat::Csv, 2048).unwrap_err();
+        assert!(err.to_string().contains("save it to a file"));
+
+        let path = fx.dir.path().join("manifest.csv");
+        let query = ManifestQuery {
+            rule_id: &fx.rule_id,
+            since: None,
+            until: None,
+        };
+        let export = export(&fx.db, &query, ManifestFormat::Csv, Some(&path)).unwrap();
+        assert_eq!(export.rows, 40);
+        assert_eq!(export.content, None);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 41);
+    }
+}
@@ -0,0 +1,176 @@
+//! Key/value pairs a rule writes into the record of each file it matches
+//! (`Rule::record_metadata`), so other tools can look matches up by what the
+//! rule found, e.g. the vendor its regex picked out, instead of parsing it
+//! back out of where the file went.
+
+use std::collections::{BTreeMap, HashMap};
+
+use filedispatch_core::file_info::FileInfo;
+use filedispatch_core::patterns::PatternEngine;
+
+use crate::models::{Rule, Settings};
+
+/// Resolved pairs by key, as stored with the match.
+pub type MatchMetadata = BTreeMap<String, String>;
+
+/// Longest value kept, in bytes; longer ones are cut short.
+pub const MAX_VALUE_BYTES: usize = 1024;
+
+/// Most one record keeps, keys and values together, in bytes. Pairs that
+/// would take it past this are left out, in key order.
+pub const MAX_RECORD_BYTES: usize = 8 * 1024;
+
+/// Put before each key in the metadata of the run's logs, e.g. `match.vendor`.
+pub const LOG_KEY_PREFIX: &str = "match.";
+
+/// The rule's `record_metadata` patterns resolved for `info`, with the
+/// captures of the conditions that matched it, kept within the limits.
+pub fn resolve(
+    rule: &Rule,
+    info: &FileInfo,
+    captures: &HashMap<String, String>,
+    settings: &Settings,
+) -> MatchMetadata {
+    if rule.record_metadata.is_empty() {
+        return MatchMetadata::new();
+    }
+    let engine = PatternEngine::new();
+    engine.set_default_timezone(settings.default_timezone.as_deref());
+    limit(
+        rule.record_metadata
+            .iter()
+            .map(|(key, pattern)| (key.clone(), engine.resolve(pattern, info, captures))),
+    )
+}
+
+/// Cuts values to `MAX_VALUE_BYTES` and stops adding pairs once the record
+/// would pass `MAX_RECORD_BYTES`. Blank keys and empty values are dropped.
+pub fn limit(pairs: impl IntoIterator<Item = (String, String)>) -> MatchMetadata {
+    let sorted: MatchMetadata = pairs
+        .into_iter()
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        .map(|(key, mut value)| {
+            truncate(&mut value, MAX_VALUE_BYTES);
+            (key, value)
+        })
+        .collect();
+    let mut kept = MatchMetadata::new();
+    let mut size = 0;
+    for (key, value) in sorted {
+        size += key.len() + value.len();
+        if size > MAX_RECORD_BYTES {
+            break;
+        }
+        kept.insert(key, value);
+    }
+    kept
+}
+
+/// Adds the pairs to a log's metadata under `LOG_KEY_PREFIX`.
+pub fn echo(recorded: &MatchMetadata, metadata: &mut HashMap<String, String>) {
+    for (key, value) in recorded {
+        metadata.insert(format!("{LOG_KEY_PREFIX}{key}"), value.clone());
+    }
+}
+
+fn truncate(value: &mut String, max: usize) {
+    if value.len() <= max {
+        return;
+    }
+    let mut end = max;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    value.truncate(end);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ConditionGroup, MatchType};
+    use chrono::Utc;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn rule(record_metadata: &[(&str, &str)]) -> Rule {
+        Rule {
+            id: "rule".to_string(),
+            folder_id: "folder".to_string(),
+            name: "Invoices".to_string(),
+            enabled: true,
+            stop_processing: false,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: Vec::new(),
+            },
+            actions: Vec::new(),
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: record_metadata
+                .iter()
+                .map(|(key, pattern)| (key.to_string(), pattern.to_string()))
+                .collect(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn values_resolve_from_captures_and_tokens() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("acme-2024-06.pdf");
+        fs::write(&path, b"invoice").unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+        // What a regex like `^(\w+)-(\d{4}-\d{2})` captured.
+        let captures = HashMap::from([
+            ("1".to_string(), "acme".to_string()),
+            ("2".to_string(), "2024-06".to_string()),
+        ]);
+        let rule = rule(&[
+            ("vendor", "{1}"),
+            ("period", "{2}"),
+            ("file", "{name}.{ext}"),
+            ("missing", "{3}"),
+        ]);
+
+        let recorded = resolve(&rule, &info, &captures, &Settings::default());
+        assert_eq!(
+            recorded,
+            MatchMetadata::from([
+                ("file".to_string(), "acme-2024-06.pdf".to_string()),
+                ("period".to_string(), "2024-06".to_string()),
+                ("vendor".to_string(), "acme".to_string()),
+            ])
+        );
+
+        let mut logged = HashMap::new();
+        echo(&recorded, &mut logged);
+        assert_eq!(logged["match.vendor"], "acme");
+    }
+
+    #[test]
+    fn long_values_and_records_are_cut_to_the_limits() {
+        // A multi-byte character straddles the value limit.
+        let long = format!("{}é{}", "a".repeat(MAX_VALUE_BYTES - 1), "b".repeat(10));
+        let recorded = limit([("note".to_string(), long)]);
+        assert_eq!(recorded["note"], "a".repeat(MAX_VALUE_BYTES - 1));
+
+        let full = "x".repeat(MAX_VALUE_BYTES - 2);
+        let pairs = (0..10).map(|n| (format!("k{n}"), full.clone()));
+        let recorded = limit(pairs);
+        // Each pair is exactly `MAX_VALUE_BYTES`, so eight fit.
+        assert_eq!(recorded.len(), 8);
+        assert!(recorded.contains_key("k7"));
+        assert!(!recorded.contains_key("k8"));
+    }
+}
@@ -1,3 +1,11 @@
+pub mod analyze;
+pub mod backup;
+pub mod badge;
+pub mod approvals;
+pub mod budget;
+pub mod arrivals;
+pub mod classify;
+pub mod companions;
 pub mod duplicates;
 pub mod model_manager;
 pub mod ocr;
@@ -6,10 +14,47 @@ pub mod ocr_geometry;
 pub mod pdf_font;
 pub mod pdf_coords;
 pub mod pdf_page_geometry;
+pub mod boomerang;
+pub mod bulk_edit;
+pub mod command_lanes;
+pub mod conflicts;
+pub mod counters;
 pub mod content;
+pub mod dedup;
+pub mod digest;
+pub mod destinations;
+pub mod diagnostics;
+pub mod dispatch;
 pub mod engine;
+pub mod error_notify;
+pub mod event_journal;
+pub mod event_writer;
 pub mod executor;
+pub mod faces;
+pub mod flow;
+pub mod folder_lanes;
+pub mod folder_templates;
+pub mod health;
 pub mod incomplete;
-pub mod patterns;
+pub mod insights;
+pub mod manifest;
+pub mod match_metadata;
+pub mod partition;
+pub mod notify_sound;
+pub mod quick_setup;
+pub mod references;
+pub mod relink;
+pub mod sandbox;
+pub mod scan;
+pub mod retry;
+pub mod revisions;
+pub mod scheduler;
+pub mod screenshots;
 pub mod state;
+pub mod startup;
+pub mod stream_search;
+pub mod thumbnails;
+pub mod timings;
+pub mod trash;
+pub mod unique_name;
 pub mod watcher;
@@ -37,7 +37,7 @@ fn validate_language_id(lang_id: &str) -> Result<()> {
     }
 
     // Prevent reserved names
-    let reserved = [".", "..", "detection", "con", "prn", "aux", "nul"];
+    let reserved = [".", "..", "detection", "faces", "con", "prn", "aux", "nul"];
     if reserved.contains(&lang_id.to_lowercase().as_str()) {
         return Err(anyhow!("Invalid language ID '{}': reserved name", lang_id));
     }
@@ -90,6 +90,9 @@ pub struct ModelManifest {
     pub version: u32,
     pub detection: DetectionModel,
     pub languages: Vec<LanguageModel>,
+    /// Face detector for the FaceCount condition; older manifests lack it.
+    #[serde(default)]
+    pub faces: Option<DetectionModel>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -260,6 +263,15 @@ impl ModelManager {
         }
     }
 
+    pub fn get_face_model_path(&self) -> Option<PathBuf> {
+        let face_path = self.models_dir.join("faces").join("face.onnx");
+        if face_path.exists() {
+            Some(face_path)
+        } else {
+            None
+        }
+    }
+
     pub async fn download_language(
         &self,
         app: &AppHandle,
@@ -359,6 +371,50 @@ impl ModelManager {
         Ok(det_path)
     }
 
+    /// Downloads the face detector. Unlike the OCR models it is only accepted
+    /// with a published hash, and a file that doesn't match it is discarded.
+    pub async fn ensure_face_model(&self, app: &AppHandle, manifest: &ModelManifest) -> Result<PathBuf> {
+        let model = manifest
+            .faces
+            .as_ref()
+            .ok_or_else(|| anyhow!("No face detection model is published yet"))?;
+        let sha256 = model
+            .sha256
+            .as_deref()
+            .ok_or_else(|| anyhow!("Face detection model has no SHA256 hash in the manifest"))?;
+
+        let face_dir = self.models_dir.join("faces");
+        let face_path = face_dir.join("face.onnx");
+        if face_path.exists() {
+            return Ok(face_path);
+        }
+
+        fs::create_dir_all(&face_dir)?;
+        // Downloaded beside the final name so a half-written or unverified
+        // file is never picked up by the detector.
+        let partial_path = face_dir.join("face.onnx.part");
+
+        self.emit_progress(app, "faces", 0, model.size_bytes, "downloading");
+        self.download_file(app, &model.url, &partial_path, "faces", 0, model.size_bytes)
+            .await?;
+
+        self.emit_progress(app, "faces", model.size_bytes, model.size_bytes, "verifying");
+        verify_file_sha256(&partial_path, Some(sha256))?;
+        fs::rename(&partial_path, &face_path)?;
+
+        self.emit_progress(app, "faces", model.size_bytes, model.size_bytes, "completed");
+
+        Ok(face_path)
+    }
+
+    pub fn delete_face_model(&self) -> Result<()> {
+        let face_dir = self.models_dir.join("faces");
+        if face_dir.exists() {
+            fs::remove_dir_all(&face_dir)?;
+        }
+        Ok(())
+    }
+
     async fn download_file(
         &self,
         app: &AppHandle,
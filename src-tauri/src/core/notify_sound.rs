@@ -0,0 +1,370 @@
+//! Sounds for Notify actions and the do-not-disturb check that holds them
+//! back. The OS focus state is looked up at most every `FOCUS_TTL`; where it
+//! can't be detected the user counts as not disturbed.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use tauri::{AppHandle, Manager};
+
+/// How long a focus lookup is reused before asking the OS again.
+const FOCUS_TTL: Duration = Duration::from_secs(30);
+
+/// Sound files shipped in `resources/sounds`, picked by file name.
+pub const BUNDLED_SOUNDS: &[&str] = &["chime.wav"];
+
+/// Sound names the platform's notification center plays.
+#[cfg(target_os = "macos")]
+const SYSTEM_SOUNDS: Option<&[&str]> = Some(&[
+    "Basso",
+    "Blow",
+    "Bottle",
+    "Frog",
+    "Funk",
+    "Glass",
+    "Hero",
+    "Morse",
+    "Ping",
+    "Pop",
+    "Purr",
+    "Sosumi",
+    "Submarine",
+    "Tink",
+]);
+#[cfg(windows)]
+const SYSTEM_SOUNDS: Option<&[&str]> = Some(&["Default", "IM", "Mail", "Reminder", "SMS"]);
+/// Sound themes differ between desktops, so any theme-style name is allowed.
+#[cfg(not(any(target_os = "macos", windows)))]
+const SYSTEM_SOUNDS: Option<&[&str]> = None;
+
+static SYSTEM_FOCUS: Lazy<FocusCache> =
+    Lazy::new(|| FocusCache::new(Box::new(SystemFocus), FOCUS_TTL));
+
+/// Checks a Notify action's sound when the rule is saved: a bundled file or a
+/// sound name this platform knows.
+pub fn validate_sound(sound: &str) -> Result<(), String> {
+    let sound = sound.trim();
+    if sound.is_empty() {
+        return Err("Notification sound is empty".to_string());
+    }
+    if BUNDLED_SOUNDS.contains(&sound) {
+        return Ok(());
+    }
+    if sound.contains(['/', '\\', '.']) {
+        return Err(format!(
+            "No bundled sound \"{sound}\"; available: {}",
+            BUNDLED_SOUNDS.join(", ")
+        ));
+    }
+    match SYSTEM_SOUNDS {
+        Some(names) if names.iter().any(|name| name.eq_ignore_ascii_case(sound)) => Ok(()),
+        Some(names) => Err(format!(
+            "Unknown sound \"{sound}\"; use one of {} or {}",
+            names.join(", "),
+            BUNDLED_SOUNDS.join(", ")
+        )),
+        None if sound
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') =>
+        {
+            Ok(())
+        }
+        None => Err(format!(
+            "Unknown sound \"{sound}\"; use a sound theme name such as message-new-instant"
+        )),
+    }
+}
+
+/// What the notification center is given: the path of a bundled file, or the
+/// system sound name as is.
+pub fn resolve_sound(app: &AppHandle, sound: &str) -> String {
+    let sound = sound.trim();
+    if !BUNDLED_SOUNDS.contains(&sound) {
+        return sound.to_string();
+    }
+    let bundled = app
+        .path()
+        .resource_dir()
+        .map(|dir| dir.join("sounds").join(sound))
+        .ok()
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from("src-tauri/resources/sounds").join(sound));
+    bundled.to_string_lossy().to_string()
+}
+
+/// What a Notify action held back, recorded in the outcome metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Suppressed {
+    Sound,
+    Notification,
+}
+
+impl Suppressed {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Suppressed::Sound => "sound",
+            Suppressed::Notification => "notification",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotifyPlan {
+    pub show: bool,
+    pub sound: Option<String>,
+    pub suppressed: Option<Suppressed>,
+}
+
+/// Decides how to notify. While do-not-disturb is on the sound is dropped,
+/// and with `respect_dnd` the whole notification. `focus_active` is only
+/// asked when its answer matters.
+pub fn plan_notification(
+    sound: Option<&str>,
+    respect_dnd: bool,
+    focus_active: impl FnOnce() -> bool,
+) -> NotifyPlan {
+    let sound = sound.map(str::trim).filter(|sound| !sound.is_empty());
+    let quiet = (sound.is_some() || respect_dnd) && focus_active();
+    if !quiet {
+        return NotifyPlan {
+            show: true,
+            sound: sound.map(str::to_string),
+            suppressed: None,
+        };
+    }
+    NotifyPlan {
+        show: !respect_dnd,
+        sound: None,
+        suppressed: Some(if respect_dnd {
+            Suppressed::Notification
+        } else {
+            Suppressed::Sound
+        }),
+    }
+}
+
+/// Whether do-not-disturb or a focus mode is on, cached for a while.
+pub fn focus_active() -> bool {
+    SYSTEM_FOCUS.is_active(Instant::now())
+}
+
+pub trait FocusProbe: Send + Sync {
+    /// `None` when the platform can't tell.
+    fn focus_active(&self) -> Option<bool>;
+}
+
+pub struct FocusCache {
+    probe: Box<dyn FocusProbe>,
+    ttl: Duration,
+    last: Mutex<Option<(Instant, bool)>>,
+}
+
+impl FocusCache {
+    pub fn new(probe: Box<dyn FocusProbe>, ttl: Duration) -> Self {
+        Self {
+            probe,
+            ttl,
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn is_active(&self, now: Instant) -> bool {
+        let Ok(mut last) = self.last.lock() else {
+            return false;
+        };
+        if let Some((checked_at, active)) = *last {
+            if now.saturating_duration_since(checked_at) < self.ttl {
+                return active;
+            }
+        }
+        let active = self.probe.focus_active().unwrap_or(false);
+        *last = Some((now, active));
+        active
+    }
+}
+
+struct SystemFocus;
+
+impl FocusProbe for SystemFocus {
+    /// Focus modes record an assertion while they're on. Reading the file
+    /// can need Full Disk Access; without it the answer is unknown.
+    #[cfg(target_os = "macos")]
+    fn focus_active(&self) -> Option<bool> {
+        let home = directories::UserDirs::new()?.home_dir().to_path_buf();
+        let raw = std::fs::read(home.join("Library/DoNotDisturb/DB/Assertions.json")).ok()?;
+        let json: serde_json::Value = serde_json::from_slice(&raw).ok()?;
+        let records = json
+            .get("data")?
+            .as_array()?
+            .iter()
+            .filter_map(|entry| entry.get("storeAssertionRecords")?.as_array())
+            .map(Vec::len)
+            .sum::<usize>();
+        Some(records > 0)
+    }
+
+    /// Quiet hours, Focus assist, presentation mode and full-screen games
+    /// all show up in the shell's notification state.
+    #[cfg(windows)]
+    fn focus_active(&self) -> Option<bool> {
+        use windows_sys::Win32::UI::Shell::{
+            SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_QUIET_TIME,
+            QUNS_RUNNING_D3D_FULL_SCREEN,
+        };
+        let mut state = 0;
+        // SAFETY: the call only writes the state it's given a pointer to.
+        if unsafe { SHQueryUserNotificationState(&mut state) } != 0 {
+            return None;
+        }
+        Some(matches!(
+            state,
+            QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE | QUNS_QUIET_TIME
+        ))
+    }
+
+    /// Notification daemons that support it expose `Inhibited` on the bus.
+    #[cfg(target_os = "linux")]
+    fn focus_active(&self) -> Option<bool> {
+        let output = std::process::Command::new("gdbus")
+            .args([
+                "call",
+                "--session",
+                "--timeout",
+                "2",
+                "--dest",
+                "org.freedesktop.Notifications",
+                "--object-path",
+                "/org/freedesktop/Notifications",
+                "--method",
+                "org.freedesktop.DBus.Properties.Get",
+                "org.freedesktop.Notifications",
+                "Inhibited",
+            ])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        parse_inhibited(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    #[cfg(not(any(target_os = "macos", windows, target_os = "linux")))]
+    fn focus_active(&self) -> Option<bool> {
+        None
+    }
+}
+
+/// Reads gdbus' reply, such as `(<true>,)`.
+#[cfg(target_os = "linux")]
+fn parse_inhibited(reply: &str) -> Option<bool> {
+    match reply.trim() {
+        "(<true>,)" => Some(true),
+        "(<false>,)" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct FakeProbe {
+        answer: Option<bool>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl FocusProbe for FakeProbe {
+        fn focus_active(&self) -> Option<bool> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.answer
+        }
+    }
+
+    fn cache(answer: Option<bool>) -> (FocusCache, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let probe = FakeProbe {
+            answer,
+            calls: calls.clone(),
+        };
+        (FocusCache::new(Box::new(probe), FOCUS_TTL), calls)
+    }
+
+    #[test]
+    fn focus_is_probed_at_most_every_ttl() {
+        let (cache, calls) = cache(Some(true));
+        let start = Instant::now();
+        assert!(cache.is_active(start));
+        assert!(cache.is_active(start + Duration::from_secs(29)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(cache.is_active(start + Duration::from_secs(31)));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn undetectable_focus_counts_as_not_disturbed() {
+        let (cache, _) = cache(None);
+        assert!(!cache.is_active(Instant::now()));
+    }
+
+    #[test]
+    fn do_not_disturb_mutes_the_sound_or_the_whole_notification() {
+        let (quiet, calls) = cache(Some(true));
+        let now = Instant::now();
+
+        let muted = plan_notification(Some("chime.wav"), false, || quiet.is_active(now));
+        assert!(muted.show);
+        assert_eq!(muted.sound, None);
+        assert_eq!(muted.suppressed, Some(Suppressed::Sound));
+
+        let held = plan_notification(None, true, || quiet.is_active(now));
+        assert!(!held.show);
+        assert_eq!(held.suppressed, Some(Suppressed::Notification));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let (free, _) = cache(Some(false));
+        let plan = plan_notification(Some("chime.wav"), true, || free.is_active(now));
+        assert_eq!(plan.sound.as_deref(), Some("chime.wav"));
+        assert!(plan.show && plan.suppressed.is_none());
+    }
+
+    #[test]
+    fn silent_notifications_skip_the_probe() {
+        let plan = plan_notification(Some("  "), false, || panic!("probed"));
+        assert!(plan.show);
+        assert_eq!(plan.sound, None);
+        assert_eq!(plan.suppressed, None);
+    }
+
+    #[test]
+    fn sounds_are_validated() {
+        assert!(validate_sound("chime.wav").is_ok());
+        assert_eq!(
+            validate_sound(" ").unwrap_err(),
+            "Notification sound is empty"
+        );
+        let err = validate_sound("../secret.wav").unwrap_err();
+        assert_eq!(
+            err,
+            "No bundled sound \"../secret.wav\"; available: chime.wav"
+        );
+        assert!(validate_sound("Nope Sound!").is_err());
+        #[cfg(target_os = "macos")]
+        assert!(validate_sound("glass").is_ok());
+        #[cfg(windows)]
+        assert!(validate_sound("Mail").is_ok());
+        #[cfg(not(any(target_os = "macos", windows)))]
+        assert!(validate_sound("message-new-instant").is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reads_the_inhibited_property() {
+        assert_eq!(parse_inhibited("(<true>,)\n"), Some(true));
+        assert_eq!(parse_inhibited("(<false>,)\n"), Some(false));
+        assert_eq!(parse_inhibited("Error: no such property"), None);
+    }
+}
@@ -53,7 +53,6 @@ impl OcrOptions {
 #[derive(Clone, Debug)]
 pub struct OcrResult {
     pub text: String,
-    #[allow(dead_code)]
     pub average_confidence: f32,
 }
 
@@ -62,6 +61,8 @@ pub struct OcrManager {
     settings: Settings,
     engine: Option<OAROCR>,
     engine_config: Option<ModelConfig>,
+    #[cfg(test)]
+    mock_result: Option<OcrResult>,
 }
 
 static CANCELLED_REQUESTS: Lazy<Mutex<HashSet<String>>> =
@@ -74,9 +75,21 @@ impl OcrManager {
             settings: Settings::default(),
             engine: None,
             engine_config: None,
+            #[cfg(test)]
+            mock_result: None,
         }
     }
 
+    /// Test hook: an enabled manager whose `recognize_path` returns `result`
+    /// without loading any models.
+    #[cfg(test)]
+    pub(crate) fn mocked(result: OcrResult) -> Self {
+        let mut manager = Self::new_placeholder();
+        manager.settings.content_enable_ocr = true;
+        manager.mock_result = Some(result);
+        manager
+    }
+
     pub fn update(&mut self, app_handle: AppHandle, settings: Settings) {
         self.app_handle = Some(app_handle);
         self.settings = settings;
@@ -107,11 +120,14 @@ impl OcrManager {
             .unwrap_or(false)
     }
 
-    pub fn recognize_path(&mut self, path: &Path, timeout: Duration) -> Result<String> {
+    pub fn recognize_path(&mut self, path: &Path, timeout: Duration) -> Result<OcrResult> {
+        #[cfg(test)]
+        if let Some(result) = &self.mock_result {
+            return Ok(result.clone());
+        }
         let image = load_image(path)?;
         let options = OcrOptions::from_settings(&self.settings);
-        let result = self.recognize_image_with_options(image, timeout, &options)?;
-        Ok(result.text)
+        self.recognize_image_with_options(image, timeout, &options)
     }
 
     #[allow(dead_code)]
@@ -0,0 +1,395 @@
+//! Automatic subfolders for destinations that would otherwise hold too many
+//! files: numbered batches filled in turn, or one folder per year, month or
+//! day. The subfolder goes between the resolved destination folder and the
+//! file name, so conflict handling afterwards sees the final path.
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use filedispatch_core::file_info::FileInfo;
+use filedispatch_core::timezone::local_date_in;
+use once_cell::sync::Lazy;
+
+use crate::models::{AutoPartition, DateGranularity, PartitionDateSource};
+
+/// Metadata key naming the subfolder a file was partitioned into.
+pub(crate) const PARTITION_KEY: &str = "partition";
+
+/// Destination folders whose numbered partition is being picked and filled.
+static CLAIMS: Lazy<Claims> = Lazy::new(Claims::default);
+
+#[derive(Default)]
+struct Claims {
+    busy: Mutex<HashSet<PathBuf>>,
+    released: Condvar,
+}
+
+/// Holds a destination folder so no other file picks a numbered partition
+/// there until this one has landed.
+struct Claim {
+    dir: PathBuf,
+}
+
+impl Claim {
+    fn take(dir: &Path) -> Self {
+        let mut busy = CLAIMS.busy.lock().unwrap_or_else(|e| e.into_inner());
+        while busy.contains(dir) {
+            busy = CLAIMS
+                .released
+                .wait(busy)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        busy.insert(dir.to_path_buf());
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        let mut busy = CLAIMS.busy.lock().unwrap_or_else(|e| e.into_inner());
+        busy.remove(&self.dir);
+        CLAIMS.released.notify_all();
+    }
+}
+
+/// A destination with its partition picked. A numbered partition stays
+/// claimed until this is dropped, so keep it until the file has landed.
+pub struct Partitioned {
+    pub dest: PathBuf,
+    /// The subfolder of the destination folder the file goes into.
+    pub subdir: String,
+    _claim: Option<Claim>,
+}
+
+/// Checks the partition settings without looking at any folder.
+pub fn validate(spec: &AutoPartition) -> Result<(), String> {
+    let AutoPartition::ByCount {
+        max_per_dir,
+        subdir_pattern,
+    } = spec
+    else {
+        return Ok(());
+    };
+    if *max_per_dir == 0 {
+        return Err("Partitions need room for at least one file".to_string());
+    }
+    if subdir_pattern.contains('/') || subdir_pattern.contains('\\') {
+        return Err(format!(
+            "Partition pattern \"{subdir_pattern}\" names one folder, not a path"
+        ));
+    }
+    match SubdirPattern::parse(subdir_pattern) {
+        Some(_) => Ok(()),
+        None => Err(format!(
+            "Partition pattern \"{subdir_pattern}\" needs a number, like batch-{{n:4}}"
+        )),
+    }
+}
+
+/// Puts `dest`, a file path, into the partition `spec` picks for it inside
+/// its folder. Dates are taken in `zone`, or the local zone when unset.
+pub fn apply(
+    dest: &Path,
+    spec: &AutoPartition,
+    info: &FileInfo,
+    zone: Option<Tz>,
+    now: DateTime<Utc>,
+) -> Option<Partitioned> {
+    let (dir, name) = (dest.parent()?, dest.file_name()?);
+    let (subdir, claim) = match spec {
+        AutoPartition::ByDate {
+            granularity,
+            date_source,
+        } => {
+            let instant = match date_source {
+                PartitionDateSource::Created => info.created,
+                PartitionDateSource::Modified => info.modified,
+                PartitionDateSource::Now => now,
+            };
+            let date = local_date_in(instant, zone);
+            let format = match granularity {
+                DateGranularity::Year => "%Y",
+                DateGranularity::Month => "%Y-%m",
+                DateGranularity::Day => "%Y-%m-%d",
+            };
+            (date.format(format).to_string(), None)
+        }
+        AutoPartition::ByCount {
+            max_per_dir,
+            subdir_pattern,
+        } => {
+            let pattern = SubdirPattern::parse(subdir_pattern)?;
+            let claim = Claim::take(dir);
+            (numbered(dir, (*max_per_dir).max(1), &pattern), Some(claim))
+        }
+    };
+    Some(Partitioned {
+        dest: dir.join(&subdir).join(name),
+        subdir,
+        _claim: claim,
+    })
+}
+
+/// The highest numbered partition in `dir` while it has room, or the one
+/// after it.
+fn numbered(dir: &Path, max_per_dir: usize, pattern: &SubdirPattern<'_>) -> String {
+    let last = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            pattern.number(&name).map(|number| (number, name))
+        })
+        .max();
+    match last {
+        Some((number, name)) if entry_count(&dir.join(&name)) >= max_per_dir => {
+            pattern.name(number + 1)
+        }
+        Some((_, name)) => name,
+        None => pattern.name(1),
+    }
+}
+
+fn entry_count(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .map(|entries| entries.count())
+        .unwrap_or(0)
+}
+
+/// A partition name around `{n}` or `{n:width}`.
+struct SubdirPattern<'a> {
+    prefix: &'a str,
+    width: usize,
+    suffix: &'a str,
+}
+
+impl<'a> SubdirPattern<'a> {
+    fn parse(pattern: &'a str) -> Option<Self> {
+        let start = pattern.find("{n")?;
+        let rest = &pattern[start + 2..];
+        let end = rest.find('}')?;
+        let width = match &rest[..end] {
+            "" => 0,
+            spec => spec.strip_prefix(':')?.parse().ok()?,
+        };
+        let (prefix, suffix) = (&pattern[..start], &rest[end + 1..]);
+        let name = format!("{prefix}1{suffix}");
+        let plain = Path::new(&name).file_name() == Some(OsStr::new(&name));
+        plain.then_some(Self {
+            prefix,
+            width,
+            suffix,
+        })
+    }
+
+    fn name(&self, number: u64) -> String {
+        format!(
+            "{}{:0width$}{}",
+            self.prefix,
+            number,
+            self.suffix,
+            width = self.width
+        )
+    }
+
+    fn number(&self, name: &str) -> Option<u64> {
+        let digits = name.strip_prefix(self.prefix)?.strip_suffix(self.suffix)?;
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::{Arc, Barrier};
+    use tempfile::tempdir;
+
+    fn by_count(max_per_dir: usize) -> AutoPartition {
+        AutoPartition::ByCount {
+            max_per_dir,
+            subdir_pattern: "batch-{n:4}".to_string(),
+        }
+    }
+
+    fn info_for(path: &Path) -> FileInfo {
+        fs::write(path, b"scan").unwrap();
+        let mut info = FileInfo::from_path(path).unwrap();
+        info.created = Utc.with_ymd_and_hms(2023, 2, 3, 12, 0, 0).unwrap();
+        info.modified = Utc.with_ymd_and_hms(2024, 5, 13, 12, 0, 0).unwrap();
+        info
+    }
+
+    /// Partitions and places `count` files named `scan-<i>.pdf` into `dest`.
+    fn place(dest: &Path, spec: &AutoPartition, inbox: &Path, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| {
+                let source = inbox.join(format!("scan-{i}.pdf"));
+                let info = info_for(&source);
+                let partitioned =
+                    apply(&dest.join(&info.full_name), spec, &info, None, Utc::now()).unwrap();
+                fs::create_dir_all(partitioned.dest.parent().unwrap()).unwrap();
+                fs::rename(&source, &partitioned.dest).unwrap();
+                partitioned.subdir
+            })
+            .collect()
+    }
+
+    #[test]
+    fn numbered_partitions_roll_over_exactly_at_the_limit() {
+        let temp = tempdir().unwrap();
+        let (inbox, dest) = (temp.path().join("inbox"), temp.path().join("scans"));
+        fs::create_dir_all(&inbox).unwrap();
+
+        let subdirs = place(&dest, &by_count(2), &inbox, 5);
+        assert_eq!(
+            subdirs,
+            [
+                "batch-0001",
+                "batch-0001",
+                "batch-0002",
+                "batch-0002",
+                "batch-0003"
+            ]
+        );
+        assert_eq!(entry_count(&dest.join("batch-0002")), 2);
+    }
+
+    #[test]
+    fn numbered_partitions_continue_from_the_highest_existing_one() {
+        let temp = tempdir().unwrap();
+        let (inbox, dest) = (temp.path().join("inbox"), temp.path().join("scans"));
+        fs::create_dir_all(&inbox).unwrap();
+        fs::create_dir_all(dest.join("batch-7")).unwrap();
+        fs::create_dir_all(dest.join("batch-0012")).unwrap();
+        fs::create_dir_all(dest.join("batch-notes")).unwrap();
+
+        assert_eq!(
+            place(&dest, &by_count(1), &inbox, 2),
+            ["batch-0012", "batch-0013"]
+        );
+    }
+
+    #[test]
+    fn date_partitions_follow_granularity_and_source() {
+        let temp = tempdir().unwrap();
+        let source = temp.path().join("scan.pdf");
+        let info = info_for(&source);
+        let dest = temp.path().join("scans").join("scan.pdf");
+        let now = Utc.with_ymd_and_hms(2025, 11, 30, 12, 0, 0).unwrap();
+        let cases = [
+            (DateGranularity::Year, PartitionDateSource::Created, "2023"),
+            (
+                DateGranularity::Month,
+                PartitionDateSource::Created,
+                "2023-02",
+            ),
+            (
+                DateGranularity::Day,
+                PartitionDateSource::Created,
+                "2023-02-03",
+            ),
+            (DateGranularity::Year, PartitionDateSource::Modified, "2024"),
+            (
+                DateGranularity::Month,
+                PartitionDateSource::Modified,
+                "2024-05",
+            ),
+            (
+                DateGranularity::Day,
+                PartitionDateSource::Modified,
+                "2024-05-13",
+            ),
+            (DateGranularity::Year, PartitionDateSource::Now, "2025"),
+            (DateGranularity::Month, PartitionDateSource::Now, "2025-11"),
+            (DateGranularity::Day, PartitionDateSource::Now, "2025-11-30"),
+        ];
+        for (granularity, date_source, expected) in cases {
+            let spec = AutoPartition::ByDate {
+                granularity,
+                date_source,
+            };
+            let partitioned = apply(&dest, &spec, &info, Some(chrono_tz::UTC), now).unwrap();
+            assert_eq!(partitioned.subdir, expected);
+            assert_eq!(
+                partitioned.dest,
+                temp.path().join("scans").join(expected).join("scan.pdf")
+            );
+        }
+    }
+
+    #[test]
+    fn simultaneous_files_never_overfill_a_partition() {
+        let temp = tempdir().unwrap();
+        let dest = Arc::new(temp.path().join("scans"));
+        let start = Arc::new(Barrier::new(8));
+        let workers: Vec<_> = (0..8)
+            .map(|worker| {
+                let (dest, start) = (dest.clone(), start.clone());
+                let inbox = temp.path().join(format!("inbox-{worker}"));
+                fs::create_dir_all(&inbox).unwrap();
+                std::thread::spawn(move || {
+                    start.wait();
+                    place(&dest, &by_count(3), &inbox, 3)
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let mut counts: Vec<(String, usize)> = fs::read_dir(dest.as_path())
+            .unwrap()
+            .flatten()
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                (name, entry_count(&entry.path()))
+            })
+            .collect();
+        counts.sort();
+        assert_eq!(counts.len(), 8);
+        assert!(counts.iter().all(|(_, count)| *count == 3));
+        assert_eq!(counts[7].0, "batch-0008");
+    }
+
+    #[test]
+    fn partitions_apply_at_the_leaf_of_a_nested_destination() {
+        let temp = tempdir().unwrap();
+        let inbox = temp.path().join("inbox");
+        fs::create_dir_all(&inbox).unwrap();
+        // A destination such as `~/Archive/{year}/{kind}/` resolves to nested
+        // folders; only the last one is split up.
+        let dest = temp.path().join("Archive").join("2024").join("Documents");
+
+        let subdirs = place(&dest, &by_count(1), &inbox, 2);
+        assert_eq!(subdirs, ["batch-0001", "batch-0002"]);
+        assert!(dest.join("batch-0002").join("scan-1.pdf").exists());
+        assert_eq!(entry_count(&temp.path().join("Archive")), 1);
+        assert_eq!(entry_count(&temp.path().join("Archive").join("2024")), 1);
+    }
+
+    #[test]
+    fn validation_wants_a_numbered_folder_name() {
+        assert!(validate(&by_count(500)).is_ok());
+        assert!(validate(&by_count(0)).is_err());
+        for pattern in ["batch", "batch-{n:x}", "a/{n}", "{n", ".."] {
+            let spec = AutoPartition::ByCount {
+                max_per_dir: 10,
+                subdir_pattern: pattern.to_string(),
+            };
+            assert!(validate(&spec).is_err(), "{pattern}");
+        }
+    }
+}
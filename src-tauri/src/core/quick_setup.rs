@@ -0,0 +1,280 @@
+//! Suggests the user's standard folders (Downloads, Desktop, ...) to watch on
+//! first run, each with starter presets from the template gallery.
+//!
+//! Locations come from the platform (XDG user dirs, Windows known folders,
+//! the macOS screenshot preference), never from hardcoded names, so
+//! localized folders like `Téléchargements` are found where they are.
+
+use std::path::{Path, PathBuf};
+
+use std::collections::HashMap;
+
+use directories::UserDirs;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::models::Preset;
+
+/// Entries looked at when estimating a folder's size, so a huge folder
+/// doesn't stall the setup screen.
+const MAX_ESTIMATE_ENTRIES: usize = 5_000;
+const MAX_ESTIMATE_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StandardLocation {
+    Downloads,
+    Desktop,
+    Documents,
+    Pictures,
+    Screenshots,
+}
+
+impl StandardLocation {
+    pub const ALL: [StandardLocation; 5] = [
+        StandardLocation::Downloads,
+        StandardLocation::Desktop,
+        StandardLocation::Documents,
+        StandardLocation::Pictures,
+        StandardLocation::Screenshots,
+    ];
+
+    /// Template gallery presets offered for this location.
+    fn preset_ids(self) -> &'static [&'static str] {
+        match self {
+            StandardLocation::Downloads => &["downloads-auto-sort", "downloads-clean-old"],
+            StandardLocation::Desktop => &["general-archive-old"],
+            StandardLocation::Documents => &["finance-invoices"],
+            StandardLocation::Pictures => &["photo-sort-by-date"],
+            StandardLocation::Screenshots => &["general-screenshots"],
+        }
+    }
+}
+
+/// Where the standard locations are on this machine.
+pub trait DirectoryProvider {
+    fn locate(&self, location: StandardLocation) -> Option<PathBuf>;
+}
+
+pub struct SystemDirectories;
+
+impl DirectoryProvider for SystemDirectories {
+    fn locate(&self, location: StandardLocation) -> Option<PathBuf> {
+        let dirs = UserDirs::new()?;
+        match location {
+            StandardLocation::Downloads => dirs.download_dir().map(Path::to_path_buf),
+            StandardLocation::Desktop => dirs.desktop_dir().map(Path::to_path_buf),
+            StandardLocation::Documents => dirs.document_dir().map(Path::to_path_buf),
+            StandardLocation::Pictures => dirs.picture_dir().map(Path::to_path_buf),
+            StandardLocation::Screenshots => screenshots_dir(&dirs),
+        }
+    }
+}
+
+/// The Screenshots known folder (`Pictures\Screenshots`, localized).
+#[cfg(windows)]
+fn screenshots_dir(_dirs: &UserDirs) -> Option<PathBuf> {
+    use std::os::windows::ffi::OsStringExt;
+    use windows_sys::Win32::System::Com::CoTaskMemFree;
+    use windows_sys::Win32::UI::Shell::{FOLDERID_Screenshots, SHGetKnownFolderPath};
+
+    // SAFETY: on success the shell hands back a NUL-terminated string that
+    // the caller frees with CoTaskMemFree, which happens on both paths.
+    unsafe {
+        let mut raw = std::ptr::null_mut();
+        let result = SHGetKnownFolderPath(&FOLDERID_Screenshots, 0, 0, &mut raw);
+        let path = (result == 0).then(|| {
+            let len = (0..).take_while(|&i| *raw.add(i) != 0).count();
+            PathBuf::from(std::ffi::OsString::from_wide(std::slice::from_raw_parts(raw, len)))
+        });
+        CoTaskMemFree(raw as *const std::ffi::c_void);
+        path
+    }
+}
+
+/// The `com.apple.screencapture` location, the Desktop unless changed.
+#[cfg(target_os = "macos")]
+fn screenshots_dir(dirs: &UserDirs) -> Option<PathBuf> {
+    let configured = std::process::Command::new("defaults")
+        .args(["read", "com.apple.screencapture", "location"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|location| !location.is_empty())
+        .map(|location| crate::utils::platform::expand_tilde(&location));
+    configured.or_else(|| dirs.desktop_dir().map(Path::to_path_buf))
+}
+
+/// GNOME and KDE both default to `Screenshots` under the XDG pictures dir.
+/// There's no XDG key for it, so it's only suggested when it exists.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn screenshots_dir(dirs: &UserDirs) -> Option<PathBuf> {
+    Some(dirs.picture_dir()?.join("Screenshots"))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickSetupSuggestion {
+    pub location: StandardLocation,
+    pub path: String,
+    /// The folder's name on disk, localized where the platform localizes it.
+    pub name: String,
+    pub file_count: u64,
+    pub total_bytes: u64,
+    /// The estimate stopped early; the counts are lower bounds.
+    pub estimate_capped: bool,
+    pub preset_ids: Vec<String>,
+}
+
+/// Standard locations that exist and aren't in `watched` yet. A location
+/// shared by two roles (the macOS Desktop also holding screenshots) is
+/// suggested once with both roles' presets.
+pub fn suggestions(
+    provider: &dyn DirectoryProvider,
+    watched: &[PathBuf],
+) -> Vec<QuickSetupSuggestion> {
+    let mut suggestions: Vec<QuickSetupSuggestion> = Vec::new();
+    for location in StandardLocation::ALL {
+        let Some(path) = provider.locate(location) else {
+            continue;
+        };
+        if !path.is_dir() || watched.iter().any(|folder| same_path(folder, &path)) {
+            continue;
+        }
+        let presets = location.preset_ids().iter().map(|id| id.to_string());
+        let path_str = path.to_string_lossy().to_string();
+        let existing = suggestions
+            .iter_mut()
+            .find(|suggestion| same_path(Path::new(&suggestion.path), &path));
+        if let Some(existing) = existing {
+            for preset in presets {
+                if !existing.preset_ids.contains(&preset) {
+                    existing.preset_ids.push(preset);
+                }
+            }
+            continue;
+        }
+        let (file_count, total_bytes, estimate_capped) = estimate(&path);
+        suggestions.push(QuickSetupSuggestion {
+            location,
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path_str.clone()),
+            path: path_str,
+            file_count,
+            total_bytes,
+            estimate_capped,
+            preset_ids: presets.collect(),
+        });
+    }
+    suggestions
+}
+
+/// A suggested (or picked) folder the user chose to set up.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickSetupSelection {
+    pub path: String,
+    pub name: String,
+    #[serde(default)]
+    pub presets: Vec<QuickSetupPreset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickSetupPreset {
+    pub preset: Preset,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+pub(crate) fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Files and bytes under `dir`, looking at no more than
+/// `MAX_ESTIMATE_ENTRIES` entries.
+fn estimate(dir: &Path) -> (u64, u64, bool) {
+    let mut files = 0;
+    let mut bytes = 0;
+    let mut entries = WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(MAX_ESTIMATE_DEPTH)
+        .into_iter()
+        .filter_map(Result::ok);
+    for entry in entries.by_ref().take(MAX_ESTIMATE_ENTRIES) {
+        if entry.file_type().is_file() {
+            files += 1;
+            bytes += entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+        }
+    }
+    (files, bytes, entries.next().is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    struct FakeDirectories(HashMap<StandardLocation, PathBuf>);
+
+    impl DirectoryProvider for FakeDirectories {
+        fn locate(&self, location: StandardLocation) -> Option<PathBuf> {
+            self.0.get(&location).cloned()
+        }
+    }
+
+    #[test]
+    fn suggests_unwatched_locations_with_their_presets() {
+        let home = tempdir().unwrap();
+        let downloads = home.path().join("Téléchargements");
+        let desktop = home.path().join("Bureau");
+        let documents = home.path().join("Documents");
+        for dir in [&downloads, &desktop, &documents] {
+            fs::create_dir(dir).unwrap();
+        }
+        fs::write(downloads.join("a.zip"), vec![0u8; 100]).unwrap();
+        fs::create_dir(downloads.join("nested")).unwrap();
+        fs::write(downloads.join("nested/b.pdf"), vec![0u8; 50]).unwrap();
+
+        let provider = FakeDirectories(HashMap::from([
+            (StandardLocation::Downloads, downloads.clone()),
+            (StandardLocation::Desktop, desktop.clone()),
+            (StandardLocation::Documents, documents.clone()),
+            // Doesn't exist on this machine.
+            (StandardLocation::Pictures, home.path().join("Images")),
+            // Screenshots land on the Desktop, as on macOS.
+            (StandardLocation::Screenshots, desktop.clone()),
+        ]));
+
+        let found = suggestions(&provider, &[documents]);
+        assert_eq!(found.len(), 2);
+
+        assert_eq!(found[0].location, StandardLocation::Downloads);
+        assert_eq!(found[0].name, "Téléchargements");
+        assert_eq!((found[0].file_count, found[0].total_bytes), (2, 150));
+        assert!(!found[0].estimate_capped);
+        assert_eq!(found[0].preset_ids, ["downloads-auto-sort", "downloads-clean-old"]);
+
+        assert_eq!(found[1].location, StandardLocation::Desktop);
+        assert_eq!(found[1].preset_ids, ["general-archive-old", "general-screenshots"]);
+    }
+
+    #[test]
+    fn estimate_stops_at_the_cap() {
+        let dir = tempdir().unwrap();
+        for i in 0..MAX_ESTIMATE_ENTRIES + 1 {
+            fs::write(dir.path().join(format!("{i}.txt")), "x").unwrap();
+        }
+        let (files, bytes, capped) = estimate(dir.path());
+        assert_eq!(files, MAX_ESTIMATE_ENTRIES as u64);
+        assert_eq!(bytes, MAX_ESTIMATE_ENTRIES as u64);
+        assert!(capped);
+    }
+}
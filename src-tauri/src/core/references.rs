@@ -0,0 +1,684 @@
+//! Checks that what rules point at outside File Dispatch is still there: the
+//! apps of Open With actions, scripts run by path, custom OCR model files and
+//! the volumes destinations live on. A rule whose app was uninstalled or
+//! whose backup drive is unplugged otherwise only shows up as failures once
+//! a file matches. Checks only stat paths; nothing is run or opened.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+
+use crate::core::error_notify::ErrorNotifier;
+use crate::models::{
+    Action, Condition, ConditionGroup, ContentSource, EngineStatus, ErrorNotifyMode,
+    OcrModelSource, Rule, RuleId, RuleListEntry, RuleReferenceWarnings, Settings,
+};
+use crate::storage::database::Database;
+use crate::storage::rule_repo::RuleRepository;
+use crate::utils::platform::expand_tilde;
+use crate::utils::volumes::offline_volume;
+
+/// How often every enabled rule is checked again.
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Where macOS looks for apps named without a path.
+const MAC_APP_DIRS: [&str; 3] = ["/Applications", "/System/Applications", "~/Applications"];
+
+/// The latest warnings of every enabled rule that has any.
+#[derive(Default)]
+pub struct ReferenceChecks {
+    state: Mutex<CheckState>,
+}
+
+#[derive(Default)]
+struct CheckState {
+    warnings: HashMap<RuleId, Vec<String>>,
+    /// Set after the first full pass. Rules already broken then don't
+    /// notify: they were never seen healthy.
+    primed: bool,
+}
+
+impl ReferenceChecks {
+    pub fn warnings(&self, rule_id: &str) -> Vec<String> {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|state| state.warnings.get(rule_id).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn broken_count(&self) -> usize {
+        self.state
+            .lock()
+            .map(|state| state.warnings.len())
+            .unwrap_or(0)
+    }
+
+    /// Pairs each rule with its cached warnings, for `rule_list`.
+    pub fn annotate(&self, rules: Vec<Rule>) -> Vec<RuleListEntry> {
+        rules
+            .into_iter()
+            .map(|rule| RuleListEntry {
+                reference_warnings: self.warnings(&rule.id),
+                rule,
+            })
+            .collect()
+    }
+
+    /// Checks `rules` (the enabled ones) and replaces the cached warnings
+    /// with the result. Returns the rules that were healthy before and now
+    /// aren't, with their warnings.
+    pub fn refresh(&self, rules: &[Rule], settings: &Settings) -> Vec<(Rule, Vec<String>)> {
+        let checked: HashMap<RuleId, Vec<String>> = rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .map(|rule| (rule.id.clone(), check_rule(rule, settings)))
+            .filter(|(_, warnings)| !warnings.is_empty())
+            .collect();
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let newly_broken = if state.primed {
+            rules
+                .iter()
+                .filter(|rule| !state.warnings.contains_key(&rule.id))
+                .filter_map(|rule| Some((rule.clone(), checked.get(&rule.id)?.clone())))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        state.warnings = checked;
+        state.primed = true;
+        newly_broken
+    }
+
+    /// Checks one rule again after it was saved, without notifying.
+    pub fn recheck(&self, rule: &Rule, settings: &Settings) {
+        let warnings = if rule.enabled {
+            check_rule(rule, settings)
+        } else {
+            Vec::new()
+        };
+        if let Ok(mut state) = self.state.lock() {
+            if warnings.is_empty() {
+                state.warnings.remove(&rule.id);
+            } else {
+                state.warnings.insert(rule.id.clone(), warnings);
+            }
+        }
+    }
+
+    pub fn forget(&self, rule_id: &str) {
+        if let Ok(mut state) = self.state.lock() {
+            state.warnings.remove(rule_id);
+        }
+    }
+
+    /// Every broken enabled rule, in folder and position order.
+    pub fn report(&self, rules: &[Rule]) -> Vec<RuleReferenceWarnings> {
+        rules
+            .iter()
+            .filter_map(|rule| {
+                let warnings = self.warnings(&rule.id);
+                (!warnings.is_empty()).then(|| RuleReferenceWarnings {
+                    rule_id: rule.id.clone(),
+                    rule_name: rule.name.clone(),
+                    folder_id: rule.folder_id.clone(),
+                    warnings,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Checks every enabled rule, notifies about the ones that just broke and
+/// updates the count in the engine status.
+pub fn check_all(
+    db: &Database,
+    settings: &Settings,
+    checks: &ReferenceChecks,
+    notifier: Option<&ErrorNotifier>,
+    status: &Mutex<EngineStatus>,
+) -> Result<Vec<RuleReferenceWarnings>> {
+    let rules = RuleRepository::new(db.clone()).list_enabled()?;
+    let newly_broken = checks.refresh(&rules, settings);
+    if let Some(notifier) = notifier {
+        let mode = if settings.show_notifications {
+            settings.notify_on_error
+        } else {
+            ErrorNotifyMode::Off
+        };
+        for (rule, warnings) in &newly_broken {
+            notifier.report_broken_references(mode, rule, warnings, Utc::now());
+        }
+    }
+    record_count(checks, status);
+    Ok(checks.report(&rules))
+}
+
+/// Keeps the count in the engine status in step with the cached warnings.
+pub fn record_count(checks: &ReferenceChecks, status: &Mutex<EngineStatus>) {
+    if let Ok(mut status) = status.lock() {
+        status.broken_reference_rules = checks.broken_count();
+    }
+}
+
+/// Runs `check_all` at startup and every `CHECK_INTERVAL` after.
+pub struct ReferenceMonitor {
+    db: Database,
+    settings: Arc<Mutex<Settings>>,
+    checks: Arc<ReferenceChecks>,
+    notifier: Arc<ErrorNotifier>,
+    status: Arc<Mutex<EngineStatus>>,
+}
+
+impl ReferenceMonitor {
+    pub fn new(
+        db: Database,
+        settings: Arc<Mutex<Settings>>,
+        checks: Arc<ReferenceChecks>,
+        notifier: Arc<ErrorNotifier>,
+        status: Arc<Mutex<EngineStatus>>,
+    ) -> Self {
+        Self {
+            db,
+            settings,
+            checks,
+            notifier,
+            status,
+        }
+    }
+
+    pub fn start(self) {
+        thread::spawn(move || loop {
+            let settings = self.settings.lock().map(|s| s.clone()).unwrap_or_default();
+            if let Err(err) = check_all(
+                &self.db,
+                &settings,
+                &self.checks,
+                Some(&self.notifier),
+                &self.status,
+            ) {
+                eprintln!("Rule reference check error: {err}");
+            }
+            thread::sleep(CHECK_INTERVAL);
+        });
+    }
+}
+
+/// What `rule` points at that isn't there, one line each. Rules that opt out
+/// have none.
+pub fn check_rule(rule: &Rule, settings: &Settings) -> Vec<String> {
+    if rule.skip_reference_check {
+        return Vec::new();
+    }
+    let mut warnings = Vec::new();
+    for (index, action) in rule.actions.iter().enumerate() {
+        let number = index + 1;
+        match action {
+            Action::OpenWith(action) if !app_exists(&action.app_path) => {
+                warnings.push(format!(
+                    "Action {number}: app not found: {}",
+                    action.app_path
+                ));
+            }
+            Action::RunScript(action) => {
+                if let Some(problem) = script_problem(&action.command) {
+                    warnings.push(format!("Action {number}: {problem}"));
+                }
+            }
+            _ => {}
+        }
+        for destination in destinations(action) {
+            if let Some(root) = unmounted_volume(destination) {
+                warnings.push(format!(
+                    "Action {number}: volume not mounted: {}",
+                    root.display()
+                ));
+            }
+        }
+    }
+    if settings.ocr_model_source == OcrModelSource::Custom && uses_ocr(rule, settings) {
+        warnings.extend(missing_ocr_models(settings));
+    }
+    let mut seen = HashSet::new();
+    warnings.retain(|warning| seen.insert(warning.clone()));
+    warnings
+}
+
+fn destinations(action: &Action) -> Vec<&str> {
+    match action {
+        Action::Move(action) => vec![action.destination.as_str()],
+        Action::Copy(action) => vec![action.destination.as_str()],
+        Action::SortIntoSubfolder(action) => vec![action.destination.as_str()],
+        Action::Archive(action) => vec![action.destination.as_str()],
+        Action::Unarchive(action) => action.destination.as_deref().into_iter().collect(),
+        Action::CreateFolderStructure(action) => vec![action.base_destination.as_str()],
+        _ => Vec::new(),
+    }
+}
+
+/// Mirrors how `execute_open_with` launches the app: a path is used as is,
+/// a bare name goes through `open -a` on macOS and `PATH` elsewhere.
+fn app_exists(app: &str) -> bool {
+    let app = app.trim();
+    if app.is_empty() {
+        return false;
+    }
+    if app.contains(['/', '\\']) || app.starts_with('~') {
+        return expand_tilde(app).exists();
+    }
+    if cfg!(target_os = "macos") {
+        // Bundle ids can't be resolved without asking Launch Services, so
+        // they're taken on trust.
+        if looks_like_bundle_id(app) {
+            return true;
+        }
+        let bundle = if app.ends_with(".app") {
+            app.to_string()
+        } else {
+            format!("{app}.app")
+        };
+        return MAC_APP_DIRS
+            .iter()
+            .any(|dir| expand_tilde(dir).join(&bundle).exists());
+    }
+    on_path(app)
+}
+
+/// `com.example.App`: dotted, with no spaces and no `.app` suffix.
+fn looks_like_bundle_id(app: &str) -> bool {
+    !app.ends_with(".app")
+        && !app.contains(' ')
+        && app.split('.').filter(|p| !p.is_empty()).count() >= 3
+}
+
+fn on_path(name: &str) -> bool {
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+    let suffixes: &[&str] = if cfg!(windows) {
+        &["", ".exe", ".bat", ".cmd"]
+    } else {
+        &[""]
+    };
+    std::env::split_paths(&paths).any(|dir| {
+        suffixes
+            .iter()
+            .any(|suffix| dir.join(format!("{name}{suffix}")).is_file())
+    })
+}
+
+/// Scripts are only checked when the command starts with a path; a command
+/// like `python3 tidy.py` or `echo` is left to the shell.
+fn script_problem(command: &str) -> Option<String> {
+    let program = first_token(command)?;
+    let looks_like_path = program.starts_with('~') || Path::new(&program).is_absolute();
+    if !looks_like_path || program.contains('{') || program.contains('$') {
+        return None;
+    }
+    let path = expand_tilde(&program);
+    let Ok(metadata) = std::fs::metadata(&path) else {
+        return Some(format!("script not found: {program}"));
+    };
+    if !metadata.is_file() || !is_executable(&metadata) {
+        return Some(format!("script isn't executable: {program}"));
+    }
+    None
+}
+
+/// The command's first word, with surrounding quotes removed.
+fn first_token(command: &str) -> Option<String> {
+    let command = command.trim_start();
+    let mut chars = command.chars();
+    match chars.next()? {
+        quote @ ('"' | '\'') => Some(chars.take_while(|c| *c != quote).collect()),
+        _ => command.split_whitespace().next().map(str::to_string),
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// The volume a destination lives on, when it's one that can be unplugged
+/// and it isn't there. Only the part before the first pattern token counts.
+fn unmounted_volume(destination: &str) -> Option<PathBuf> {
+    let fixed = destination.split(['{', '$']).next().unwrap_or_default();
+    if fixed.trim().is_empty() {
+        return None;
+    }
+    offline_volume(&expand_tilde(fixed))
+}
+
+fn uses_ocr(rule: &Rule, settings: &Settings) -> bool {
+    rule.actions
+        .iter()
+        .any(|action| matches!(action, Action::MakePdfSearchable(_)))
+        || (settings.content_enable_ocr && group_uses_ocr(&rule.conditions))
+}
+
+fn group_uses_ocr(group: &ConditionGroup) -> bool {
+    group.conditions.iter().any(|condition| match condition {
+        Condition::Contents(contents) => {
+            matches!(contents.source, ContentSource::Ocr | ContentSource::Auto)
+        }
+        Condition::Nested(group) => group_uses_ocr(group),
+        _ => false,
+    })
+}
+
+fn missing_ocr_models(settings: &Settings) -> Vec<String> {
+    let models = [
+        ("detection", &settings.ocr_model_det_path),
+        ("recognition", &settings.ocr_model_rec_path),
+        ("dictionary", &settings.ocr_model_dict_path),
+    ];
+    models
+        .into_iter()
+        .filter_map(|(name, path)| {
+            if path.trim().is_empty() {
+                Some(format!("no custom OCR {name} model is set"))
+            } else if !expand_tilde(path).is_file() {
+                Some(format!("custom OCR {name} model not found: {path}"))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Arc;
+
+    use tempfile::tempdir;
+
+    use crate::core::error_notify::{ErrorNotice, Notifier};
+    use crate::models::{
+        ContentsCondition, MakePdfSearchableAction, MatchType, MoveAction, OpenWithAction,
+        ScriptAction, StringOperator,
+    };
+
+    struct RecordingNotifier {
+        shown: Arc<Mutex<Vec<ErrorNotice>>>,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn show(&self, notice: &ErrorNotice) -> Result<()> {
+            self.shown.lock().unwrap().push(notice.clone());
+            Ok(())
+        }
+    }
+
+    fn rule(id: &str, actions: Vec<Action>) -> Rule {
+        Rule {
+            id: id.to_string(),
+            folder_id: "folder-1".to_string(),
+            name: format!("Rule {id}"),
+            enabled: true,
+            stop_processing: false,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![],
+            },
+            actions,
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn open_with(app_path: &Path) -> Action {
+        Action::OpenWith(OpenWithAction {
+            app_path: app_path.to_string_lossy().to_string(),
+        })
+    }
+
+    fn script(command: &str) -> Action {
+        Action::RunScript(ScriptAction {
+            command: command.to_string(),
+        })
+    }
+
+    #[test]
+    fn missing_app_is_reported() {
+        let dir = tempdir().unwrap();
+        let app = dir.path().join("Viewer");
+        fs::write(&app, b"").unwrap();
+        let settings = Settings::default();
+
+        assert!(check_rule(&rule("a", vec![open_with(&app)]), &settings).is_empty());
+        let gone = dir.path().join("Uninstalled");
+        let warnings = check_rule(&rule("a", vec![open_with(&gone)]), &settings);
+        assert_eq!(
+            warnings,
+            vec![format!("Action 1: app not found: {}", gone.display())]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn missing_or_non_executable_script_is_reported() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let runnable = dir.path().join("tidy.sh");
+        fs::write(&runnable, b"#!/bin/sh\n").unwrap();
+        fs::set_permissions(&runnable, fs::Permissions::from_mode(0o755)).unwrap();
+        let plain = dir.path().join("notes.sh");
+        fs::write(&plain, b"#!/bin/sh\n").unwrap();
+        fs::set_permissions(&plain, fs::Permissions::from_mode(0o644)).unwrap();
+        let gone = dir.path().join("gone.sh");
+        let settings = Settings::default();
+        let check = |command: String| check_rule(&rule("a", vec![script(&command)]), &settings);
+
+        assert!(check(format!("{} \"$FILE_PATH\"", runnable.display())).is_empty());
+        assert!(check(format!("'{}' --fast", runnable.display())).is_empty());
+        // Commands that don't start with a path are the shell's business.
+        assert!(check("echo \"$FILE_PATH\"".to_string()).is_empty());
+        assert_eq!(
+            check(format!("{} \"$FILE_PATH\"", gone.display())),
+            vec![format!("Action 1: script not found: {}", gone.display())]
+        );
+        assert_eq!(
+            check(plain.display().to_string()),
+            vec![format!(
+                "Action 1: script isn't executable: {}",
+                plain.display()
+            )]
+        );
+    }
+
+    #[test]
+    fn missing_custom_ocr_models_are_reported_for_rules_using_ocr() {
+        let dir = tempdir().unwrap();
+        let det = dir.path().join("det.onnx");
+        fs::write(&det, b"").unwrap();
+        let settings = Settings {
+            ocr_model_source: OcrModelSource::Custom,
+            ocr_model_det_path: det.to_string_lossy().to_string(),
+            ocr_model_rec_path: dir.path().join("rec.onnx").to_string_lossy().to_string(),
+            ocr_model_dict_path: String::new(),
+            ..Settings::default()
+        };
+        let searchable = Action::MakePdfSearchable(MakePdfSearchableAction {
+            skip_if_text: true,
+            overwrite: false,
+        });
+
+        let warnings = check_rule(&rule("a", vec![searchable]), &settings);
+        assert_eq!(
+            warnings,
+            vec![
+                format!(
+                    "custom OCR recognition model not found: {}",
+                    dir.path().join("rec.onnx").display()
+                ),
+                "no custom OCR dictionary model is set".to_string(),
+            ]
+        );
+
+        let mut reads_text = rule("b", vec![]);
+        reads_text.conditions.conditions = vec![Condition::Nested(ConditionGroup {
+            label: None,
+            match_type: MatchType::Any,
+            conditions: vec![Condition::Contents(ContentsCondition {
+                operator: StringOperator::Contains,
+                value: "invoice".to_string(),
+                case_sensitive: false,
+                source: ContentSource::Auto,
+                min_ocr_confidence: None,
+            })],
+        })];
+        assert_eq!(check_rule(&reads_text, &settings).len(), 2);
+        // Rules that never OCR don't care which models are set.
+        assert!(check_rule(&rule("c", vec![script("echo")]), &settings).is_empty());
+    }
+
+    #[test]
+    fn unmounted_destination_volumes_are_reported() {
+        let move_to = |destination: &str| {
+            Action::Move(MoveAction {
+                destination: destination.to_string(),
+                on_conflict: crate::models::ConflictResolution::Rename,
+                skip_duplicates: false,
+                destination_permissions: Default::default(),
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
+            })
+        };
+        let settings = Settings::default();
+        let volume = "/Volumes/FileDispatch-test-backup-drive";
+
+        let warnings = check_rule(
+            &rule("a", vec![move_to(&format!("{volume}/Photos/{{year}}"))]),
+            &settings,
+        );
+        assert_eq!(
+            warnings,
+            vec![format!("Action 1: volume not mounted: {volume}")]
+        );
+        // Folders on the system disk are created as needed, so they're fine.
+        let dir = tempdir().unwrap();
+        let local = dir.path().join("Sorted").to_string_lossy().to_string();
+        assert!(check_rule(&rule("b", vec![move_to(&local)]), &settings).is_empty());
+    }
+
+    #[test]
+    fn skipped_rules_are_not_checked() {
+        let mut skipped = rule("a", vec![script("/nonexistent/FileDispatch/tidy.sh")]);
+        skipped.skip_reference_check = true;
+
+        assert!(check_rule(&skipped, &Settings::default()).is_empty());
+    }
+
+    #[test]
+    fn notifies_once_when_a_healthy_rule_breaks() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = crate::storage::folder_repo::FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Inbox")
+            .unwrap();
+        let tool = dir.path().join("tool.sh");
+        fs::write(&tool, b"").unwrap();
+        let repo = RuleRepository::new(db.clone());
+        let mut upload = rule("", vec![open_with(&tool)]);
+        upload.folder_id = folder.id.clone();
+        let upload = repo.create(upload).unwrap();
+        let mut broken = rule("", vec![script("/nonexistent/FileDispatch/tidy.sh")]);
+        broken.folder_id = folder.id;
+        repo.create(broken).unwrap();
+
+        let shown = Arc::new(Mutex::new(Vec::new()));
+        let notifier = ErrorNotifier::new(Box::new(RecordingNotifier {
+            shown: shown.clone(),
+        }));
+        let checks = ReferenceChecks::default();
+        let status = Mutex::new(EngineStatus::default());
+        let settings = Settings {
+            notify_on_error: ErrorNotifyMode::All,
+            ..Settings::default()
+        };
+        let run = || check_all(&db, &settings, &checks, Some(&notifier), &status).unwrap();
+
+        // Rules broken from the start are reported but not notified about.
+        assert_eq!(run().len(), 1);
+        assert!(shown.lock().unwrap().is_empty());
+
+        fs::remove_file(&tool).unwrap();
+        let report = run();
+        assert_eq!(report.len(), 2);
+        assert_eq!(status.lock().unwrap().broken_reference_rules, 2);
+        {
+            let shown = shown.lock().unwrap();
+            assert_eq!(shown.len(), 1);
+            assert_eq!(shown[0].rule_id, upload.id);
+            assert_eq!(shown[0].title(), format!("{} can't run", upload.name));
+            assert_eq!(
+                shown[0].body(),
+                format!("Action 1: app not found: {}", tool.display())
+            );
+        }
+
+        // Still broken: no new notification.
+        run();
+        assert_eq!(shown.lock().unwrap().len(), 1);
+
+        // Fixed and broken again notifies again.
+        fs::write(&tool, b"").unwrap();
+        run();
+        assert_eq!(status.lock().unwrap().broken_reference_rules, 1);
+        fs::remove_file(&tool).unwrap();
+        run();
+        assert_eq!(shown.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn annotate_adds_cached_warnings_to_listed_rules() {
+        let checks = ReferenceChecks::default();
+        let healthy = rule("healthy", vec![script("echo")]);
+        let broken = rule("broken", vec![script("/nonexistent/FileDispatch/tidy.sh")]);
+        checks.refresh(&[healthy.clone(), broken.clone()], &Settings::default());
+
+        let entries = checks.annotate(vec![healthy, broken]);
+        assert!(entries[0].reference_warnings.is_empty());
+        assert_eq!(
+            entries[1].reference_warnings,
+            vec!["Action 1: script not found: /nonexistent/FileDispatch/tidy.sh"]
+        );
+        let json = serde_json::to_value(&entries[1]).unwrap();
+        assert_eq!(json["id"], "broken");
+        assert_eq!(
+            json["referenceWarnings"][0],
+            entries[1].reference_warnings[0]
+        );
+    }
+}
@@ -0,0 +1,468 @@
+//! Keeps a watched folder attached to its rules when the folder itself is
+//! renamed or removed: a rename the watcher can follow updates the stored
+//! path, anything else marks the folder unavailable until it's relinked.
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use crossbeam_channel::Receiver;
+use tauri::{AppHandle, Emitter};
+
+use crate::core::health::validate_rule;
+use crate::core::quick_setup::same_path;
+use crate::core::state::AppState;
+use crate::core::watcher::{RootChange, WatcherService};
+use crate::models::{Action, EngineStatus, Folder, FolderPathChanged, UnavailableFolder};
+use crate::storage::database::Database;
+use crate::storage::folder_repo::FolderRepository;
+use crate::storage::rule_repo::RuleRepository;
+use crate::utils::platform::normalize_user_path;
+
+pub const PATH_CHANGED_EVENT: &str = "folder://path-changed";
+
+/// Applies the watcher's root changes as they come in.
+pub struct RootTracker {
+    state: AppState,
+    app_handle: AppHandle,
+}
+
+impl RootTracker {
+    pub fn new(state: AppState, app_handle: AppHandle) -> Self {
+        Self { state, app_handle }
+    }
+
+    pub fn start(self, changes: Receiver<RootChange>) {
+        thread::spawn(move || {
+            for change in changes.iter() {
+                let follow = self
+                    .state
+                    .settings
+                    .lock()
+                    .map(|s| s.follow_folder_renames)
+                    .unwrap_or(true);
+                let result = apply_root_change(
+                    &self.state.db,
+                    &self.state.watcher,
+                    &self.state.engine_status,
+                    follow,
+                    &change,
+                );
+                match result {
+                    Ok(Some(changed)) => {
+                        let _ = self.app_handle.emit(PATH_CHANGED_EVENT, changed);
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        eprintln!("Failed to follow {}: {err}", change.old_path.display())
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Follows a renamed folder to its new path, or marks it unavailable when it
+/// was removed, couldn't be found or `follow_renames` is off. Changes for a
+/// folder that no longer has the old path (one backend event reported twice)
+/// are ignored.
+pub fn apply_root_change(
+    db: &Database,
+    watcher: &Mutex<WatcherService>,
+    status: &Mutex<EngineStatus>,
+    follow_renames: bool,
+    change: &RootChange,
+) -> Result<Option<FolderPathChanged>> {
+    let Some(folder) = FolderRepository::new(db.clone()).get(&change.folder_id)? else {
+        return Ok(None);
+    };
+    if normalize_user_path(&folder.path) != change.old_path {
+        return Ok(None);
+    }
+    if let Ok(mut watcher) = watcher.lock() {
+        watcher.forget_folder(&change.old_path);
+    }
+
+    let new_path = change.new_path.as_deref().filter(|_| follow_renames);
+    let Some(new_path) = new_path else {
+        mark_unavailable(status, &folder.id, &change.old_path);
+        return Ok(None);
+    };
+    match relink_folder(db, &folder.id, new_path) {
+        Ok(changed) => {
+            rewatch(watcher, status, &folder, new_path);
+            Ok(Some(changed))
+        }
+        Err(err) => {
+            mark_unavailable(status, &folder.id, &change.old_path);
+            Err(err)
+        }
+    }
+}
+
+/// Points `folder_id` at `new_path`. Destinations in any rule that pointed
+/// into the old path are moved along and every changed rule is validated
+/// again; nothing is saved unless all of them pass.
+pub fn relink_folder(db: &Database, folder_id: &str, new_path: &Path) -> Result<FolderPathChanged> {
+    let repo = FolderRepository::new(db.clone());
+    let folders = repo.list()?;
+    let folder = folders
+        .iter()
+        .find(|folder| folder.id == folder_id)
+        .ok_or_else(|| anyhow!("Folder not found"))?;
+    if !folder.is_directory() {
+        bail!("Groups and the system trash don't have a path");
+    }
+    if !new_path.is_dir() {
+        bail!("{} isn't a folder", new_path.display());
+    }
+    let taken = folders.iter().any(|other| {
+        other.id != folder.id
+            && !other.is_group
+            && same_path(&normalize_user_path(&other.path), new_path)
+    });
+    if taken {
+        bail!("{} is already being watched", new_path.display());
+    }
+
+    let old_path = normalize_user_path(&folder.path);
+    let rule_repo = RuleRepository::new(db.clone());
+    let mut updated = Vec::new();
+    for other in &folders {
+        for mut rule in rule_repo.list_by_folder(&other.id)? {
+            let mut changed = false;
+            for destination in rule.actions.iter_mut().filter_map(destination_mut) {
+                if let Some(rebased) = rebase_path(destination, &old_path, new_path) {
+                    *destination = rebased;
+                    changed = true;
+                }
+            }
+            if changed {
+                validate_rule(&rule).map_err(|err| anyhow!(err))?;
+                updated.push(rule);
+            }
+        }
+    }
+
+    let new_path_str = new_path.to_string_lossy().to_string();
+    repo.relink(&folder.id, &new_path_str, &updated)?;
+    Ok(FolderPathChanged {
+        folder_id: folder.id.clone(),
+        old_path: folder.path.clone(),
+        new_path: new_path_str,
+        rules_updated: updated.len(),
+    })
+}
+
+/// Moves the watch from the folder's old path to `new_path` and clears it
+/// from the unavailable list.
+pub fn rewatch(
+    watcher: &Mutex<WatcherService>,
+    status: &Mutex<EngineStatus>,
+    folder: &Folder,
+    new_path: &Path,
+) {
+    if let Ok(mut watcher) = watcher.lock() {
+        watcher.forget_folder(&normalize_user_path(&folder.path));
+        if folder.enabled {
+            let _ = watcher.watch_folder(
+                new_path.to_path_buf(),
+                folder.id.clone(),
+                folder.scan_depth,
+                &folder.effective_passthrough_patterns(),
+            );
+        }
+    }
+    if let Ok(mut status) = status.lock() {
+        status
+            .unavailable_folders
+            .retain(|f| f.folder_id != folder.id);
+        status.updated_at = Utc::now();
+    }
+}
+
+pub fn mark_unavailable(status: &Mutex<EngineStatus>, folder_id: &str, path: &Path) {
+    let Ok(mut status) = status.lock() else {
+        return;
+    };
+    if status
+        .unavailable_folders
+        .iter()
+        .any(|f| f.folder_id == folder_id)
+    {
+        return;
+    }
+    let now = Utc::now();
+    status.unavailable_folders.push(UnavailableFolder {
+        folder_id: folder_id.to_string(),
+        path: path.to_string_lossy().to_string(),
+        since: now,
+    });
+    status.updated_at = now;
+}
+
+/// Where the action puts files, for the actions that take a folder path.
+pub(crate) fn destination_mut(action: &mut Action) -> Option<&mut String> {
+    match action {
+        Action::Move(action) => Some(&mut action.destination),
+        Action::Copy(action) => Some(&mut action.destination),
+        Action::SortIntoSubfolder(action) => Some(&mut action.destination),
+        Action::Archive(action) => Some(&mut action.destination),
+        Action::Unarchive(action) => action.destination.as_mut(),
+        Action::CreateFolderStructure(action) => Some(&mut action.base_destination),
+        _ => None,
+    }
+}
+
+/// `value` moved from under `old_root` to under `new_root`, or `None` when
+/// it isn't under `old_root`. Pattern tokens in the rest of the path are
+/// kept as they are.
+pub(crate) fn rebase_path(value: &str, old_root: &Path, new_root: &Path) -> Option<String> {
+    let path = normalize_user_path(value);
+    let rest = path.strip_prefix(old_root).ok()?;
+    let rebased = if rest.as_os_str().is_empty() {
+        new_root.to_path_buf()
+    } else {
+        new_root.join(rest)
+    };
+    Some(rebased.to_string_lossy().to_string())
+}
+
+/// Folders that are already missing when watching starts.
+pub fn mark_missing_folders(status: &Mutex<EngineStatus>, folders: &[Folder]) {
+    for folder in folders.iter().filter(|f| f.enabled && f.is_directory()) {
+        let path = normalize_user_path(&folder.path);
+        if !path.is_dir() {
+            mark_unavailable(status, &folder.id, &path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::watcher::FileEvent;
+    use crate::models::{ConditionGroup, ConflictResolution, MatchType, MoveAction, Rule};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    fn add_rule(db: &Database, folder: &Folder, destination: &str) -> Rule {
+        RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id.clone(),
+                name: format!("To {destination}"),
+                enabled: true,
+                stop_processing: false,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![],
+                },
+                actions: vec![Action::Move(MoveAction {
+                    destination: destination.to_string(),
+                    on_conflict: ConflictResolution::Rename,
+                    skip_duplicates: false,
+                    destination_permissions: Default::default(),
+                    quarantine: Default::default(),
+                    bring_companions: None,
+                    auto_partition: None,
+                })],
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap()
+    }
+
+    fn destination(db: &Database, rule: &Rule) -> String {
+        match &RuleRepository::new(db.clone())
+            .get(&rule.id)
+            .unwrap()
+            .unwrap()
+            .actions[0]
+        {
+            Action::Move(action) => action.destination.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn rebases_only_paths_under_the_old_root() {
+        let old = Path::new("/home/me/Projects/ClientA");
+        let new = Path::new("/home/me/Projects/ClientA-2024");
+        assert_eq!(
+            rebase_path("/home/me/Projects/ClientA/Archive/{year}", old, new).as_deref(),
+            Some("/home/me/Projects/ClientA-2024/Archive/{year}")
+        );
+        assert_eq!(
+            rebase_path("/home/me/Projects/ClientA", old, new).as_deref(),
+            Some("/home/me/Projects/ClientA-2024")
+        );
+        // A sibling that only shares the prefix stays where it is.
+        assert_eq!(rebase_path("/home/me/Projects/ClientAB/x", old, new), None);
+        assert_eq!(rebase_path("/elsewhere", old, new), None);
+    }
+
+    #[test]
+    fn relink_keeps_the_folder_id_and_moves_destinations_along() {
+        let dir = tempdir().unwrap();
+        let old = dir.path().join("ClientA");
+        let new = dir.path().join("ClientA-2024");
+        let inbox = dir.path().join("Inbox");
+        fs::create_dir(&new).unwrap();
+        fs::create_dir(&inbox).unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let repo = FolderRepository::new(db.clone());
+        let client = repo.create(&old.to_string_lossy(), "ClientA").unwrap();
+        let inbox = repo.create(&inbox.to_string_lossy(), "Inbox").unwrap();
+        let into_client = add_rule(&db, &inbox, &format!("{}/Invoices", old.display()));
+        let elsewhere = add_rule(&db, &client, "/srv/archive");
+
+        let changed = relink_folder(&db, &client.id, &new).unwrap();
+        assert_eq!(changed.folder_id, client.id);
+        assert_eq!(changed.rules_updated, 1);
+
+        let relinked = repo.get(&client.id).unwrap().unwrap();
+        assert_eq!(PathBuf::from(&relinked.path), new);
+        assert_eq!(relinked.rule_count, 1);
+        assert_eq!(
+            PathBuf::from(destination(&db, &into_client)),
+            new.join("Invoices")
+        );
+        assert_eq!(destination(&db, &elsewhere), "/srv/archive");
+    }
+
+    #[test]
+    fn relink_refuses_a_folder_that_is_already_watched() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let repo = FolderRepository::new(db.clone());
+        let a = repo
+            .create(&dir.path().join("a").to_string_lossy(), "A")
+            .unwrap();
+        repo.create(&dir.path().to_string_lossy(), "Root").unwrap();
+
+        let err = relink_folder(&db, &a.id, dir.path()).unwrap_err();
+        assert!(err.to_string().contains("already being watched"), "{err}");
+        let missing = relink_folder(&db, &a.id, &dir.path().join("missing")).unwrap_err();
+        assert!(missing.to_string().contains("isn't a folder"), "{missing}");
+        assert_eq!(
+            PathBuf::from(repo.get(&a.id).unwrap().unwrap().path),
+            dir.path().join("a")
+        );
+    }
+
+    fn next_event_under(rx: &Receiver<FileEvent>, root: &Path) -> Option<FileEvent> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            let event = rx.recv_timeout(left).ok()?;
+            if event.path.starts_with(root) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn renamed_folder_keeps_being_processed_under_its_new_path() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        let old = base.join("ClientA");
+        let new = base.join("ClientA-2024");
+        fs::create_dir(&old).unwrap();
+        let db = Database::new_with_path(base.join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&old.to_string_lossy(), "ClientA")
+            .unwrap();
+
+        let (event_tx, event_rx) = crossbeam_channel::unbounded();
+        let (root_tx, root_rx) = crossbeam_channel::unbounded();
+        let mut service = WatcherService::new(event_tx, root_tx, vec![]).unwrap();
+        service
+            .watch_folder(old.clone(), folder.id.clone(), 0, &[])
+            .unwrap();
+        let watcher = Mutex::new(service);
+        let status = Mutex::new(EngineStatus::default());
+
+        fs::rename(&old, &new).unwrap();
+        let change = root_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(change.old_path, old);
+        assert_eq!(change.new_path.as_deref(), Some(new.as_path()));
+
+        let changed = apply_root_change(&db, &watcher, &status, true, &change)
+            .unwrap()
+            .unwrap();
+        assert_eq!(PathBuf::from(&changed.new_path), new);
+        let stored = FolderRepository::new(db.clone())
+            .get(&folder.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(PathBuf::from(stored.path), new);
+        assert!(status.lock().unwrap().unavailable_folders.is_empty());
+        // Reported once by the backend, acted on once.
+        assert_eq!(
+            apply_root_change(&db, &watcher, &status, true, &change).unwrap(),
+            None
+        );
+
+        fs::write(new.join("invoice.pdf"), b"invoice").unwrap();
+        let event = next_event_under(&event_rx, &new).expect("no event under the new path");
+        assert_eq!(event.folder_id, folder.id);
+        assert_eq!(event.path, new.join("invoice.pdf"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn removed_folder_is_marked_unavailable() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        let root = base.join("Scratch");
+        fs::create_dir(&root).unwrap();
+        let db = Database::new_with_path(base.join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&root.to_string_lossy(), "Scratch")
+            .unwrap();
+
+        let (event_tx, _event_rx) = crossbeam_channel::unbounded();
+        let (root_tx, root_rx) = crossbeam_channel::unbounded();
+        let mut service = WatcherService::new(event_tx, root_tx, vec![]).unwrap();
+        service
+            .watch_folder(root.clone(), folder.id.clone(), 0, &[])
+            .unwrap();
+        let watcher = Mutex::new(service);
+        let status = Mutex::new(EngineStatus::default());
+
+        fs::remove_dir(&root).unwrap();
+        let change = root_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(change.new_path, None);
+        assert_eq!(
+            apply_root_change(&db, &watcher, &status, true, &change).unwrap(),
+            None
+        );
+        let unavailable = status.lock().unwrap().unavailable_folders.clone();
+        assert_eq!(unavailable.len(), 1);
+        assert_eq!(unavailable[0].folder_id, folder.id);
+        assert!(watcher.lock().unwrap().list_watched_folders().is_empty());
+        // The folder row is left alone for the user to relink.
+        let stored = FolderRepository::new(db).get(&folder.id).unwrap().unwrap();
+        assert_eq!(PathBuf::from(stored.path), root);
+    }
+}
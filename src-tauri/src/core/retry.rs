@@ -0,0 +1,320 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use crossbeam_channel::Sender;
+
+use crate::core::watcher::{FileEvent, FileEventKind};
+use crate::models::{EventSource, LogEntry, LogStatus, RetryExhausted, Rule};
+use crate::storage::database::Database;
+use crate::storage::log_repo::LogRepository;
+use crate::storage::retry_repo::RetryRepository;
+
+/// Failed attempts, counting the original run, before a file is given up on.
+pub const MAX_ATTEMPTS: u32 = 8;
+
+/// Wait before the first retry; it doubles after every further failure.
+const BASE_DELAY_SECONDS: i64 = 60;
+
+/// Longest wait between two attempts.
+const MAX_DELAY_SECONDS: i64 = 24 * 60 * 60;
+
+/// How long a file waiting on an offline volume waits before it's tried
+/// anyway, in case the volume came back without the monitor noticing.
+const OFFLINE_RECHECK_MINUTES: i64 = 60;
+
+/// How often the scheduler looks for due retries.
+const TICK: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// A claimed retry whose event hasn't been processed within this long, e.g.
+/// because the app quit first, is sent again.
+const CLAIM_LEASE_MINUTES: i64 = 30;
+
+/// What became of a transient failure.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetryDecision {
+    /// Another attempt is due at this time.
+    Scheduled(DateTime<Utc>),
+    GaveUp(RetryExhausted),
+}
+
+/// How long to wait after the `attempts`-th failure: a minute, doubling each
+/// time, capped at a day.
+pub fn backoff(attempts: u32) -> Duration {
+    let doublings = attempts.saturating_sub(1).min(20);
+    Duration::seconds((BASE_DELAY_SECONDS << doublings).min(MAX_DELAY_SECONDS))
+}
+
+/// Records that `rule` failed on `file_path` with a transient error and
+/// schedules the next attempt. After `MAX_ATTEMPTS` failures the retry is
+/// dropped and the give-up is logged instead.
+pub fn record_failure(
+    db: &Database,
+    rule: &Rule,
+    folder_id: &str,
+    file_path: &str,
+    error: &str,
+    now: DateTime<Utc>,
+) -> Result<RetryDecision> {
+    let repo = RetryRepository::new(db.clone());
+    let attempts = repo.attempts(&rule.id, file_path)? + 1;
+    if attempts < MAX_ATTEMPTS {
+        let next_attempt_at = now + backoff(attempts);
+        repo.schedule(&rule.id, folder_id, file_path, attempts, next_attempt_at, error, None)?;
+        return Ok(RetryDecision::Scheduled(next_attempt_at));
+    }
+
+    repo.forget(&rule.id, file_path)?;
+    LogRepository::new(db.clone()).insert(LogEntry {
+        id: String::new(),
+        rule_id: Some(rule.id.clone()),
+        rule_name: Some(rule.name.clone()),
+        file_path: file_path.to_string(),
+        action_type: "retry".to_string(),
+        action_detail: None,
+        status: LogStatus::Error,
+        error_message: Some(format!("Gave up after {} attempts: {}", attempts, error)),
+        created_at: now,
+    })?;
+    Ok(RetryDecision::GaveUp(RetryExhausted {
+        rule_id: rule.id.clone(),
+        rule_name: rule.name.clone(),
+        file_path: file_path.to_string(),
+        attempts,
+        last_error: error.to_string(),
+    }))
+}
+
+/// Records that `rule` couldn't transfer `file_path` because the destination
+/// volume mounted at `volume` is offline. The file waits for the volume
+/// without using up attempts; the destination monitor releases it as soon as
+/// the volume is back. Returns when it's tried anyway.
+pub fn record_offline(
+    db: &Database,
+    rule: &Rule,
+    folder_id: &str,
+    file_path: &str,
+    volume: &Path,
+    error: &str,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>> {
+    let repo = RetryRepository::new(db.clone());
+    let attempts = repo.attempts(&rule.id, file_path)?.max(1);
+    let next_attempt_at = now + Duration::minutes(OFFLINE_RECHECK_MINUTES);
+    let volume = volume.to_string_lossy();
+    repo.schedule(
+        &rule.id,
+        folder_id,
+        file_path,
+        attempts,
+        next_attempt_at,
+        error,
+        Some(&volume),
+    )?;
+    Ok(next_attempt_at)
+}
+
+/// Claims the retries due at `now` and turns each into an event for its one rule.
+pub(crate) fn claim_due_events(db: &Database, now: DateTime<Utc>) -> Result<Vec<FileEvent>> {
+    let stale_before = now - Duration::minutes(CLAIM_LEASE_MINUTES);
+    let due = RetryRepository::new(db.clone()).claim_due(now, stale_before)?;
+    Ok(due
+        .into_iter()
+        .map(|retry| FileEvent {
+            path: PathBuf::from(retry.file_path),
+            folder_id: retry.folder_id,
+            kind: FileEventKind::Modified,
+            rule_id: Some(retry.rule_id),
+            arrival: None,
+            active_app: None,
+            source: EventSource::Retry,
+        })
+        .collect())
+}
+
+/// Periodically requeues rule runs from the retry ledger once they're due.
+pub struct RetryScheduler {
+    db: Database,
+    events: Sender<FileEvent>,
+    paused: Arc<AtomicBool>,
+}
+
+impl RetryScheduler {
+    pub fn new(db: Database, events: Sender<FileEvent>, paused: Arc<AtomicBool>) -> Self {
+        Self { db, events, paused }
+    }
+
+    pub fn start(self) {
+        thread::spawn(move || loop {
+            if let Err(err) = self.tick(Utc::now()) {
+                eprintln!("Retry scheduler error: {err}");
+            }
+            thread::sleep(TICK);
+        });
+    }
+
+    fn tick(&self, now: DateTime<Utc>) -> Result<()> {
+        // Left unclaimed while paused, so they go out as soon as processing resumes.
+        if self.paused.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        for event in claim_due_events(&self.db, now)? {
+            let _ = self.events.send(event);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ConditionGroup, MatchType};
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::rule_repo::RuleRepository;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    fn fixture(dir: &std::path::Path) -> (Database, Rule) {
+        let db = Database::new_with_path(dir.join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.join("inbox").to_string_lossy(), "Inbox")
+            .unwrap();
+        let rule = RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id.clone(),
+                name: "To NAS".to_string(),
+                enabled: true,
+                stop_processing: true,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![],
+                },
+                actions: vec![],
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap();
+        (db, rule)
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_a_day() {
+        assert_eq!(backoff(1), Duration::minutes(1));
+        assert_eq!(backoff(2), Duration::minutes(2));
+        assert_eq!(backoff(5), Duration::minutes(16));
+        assert_eq!(backoff(12), Duration::days(1));
+        assert_eq!(backoff(u32::MAX), Duration::days(1));
+    }
+
+    #[test]
+    fn failures_are_scheduled_with_backoff_from_the_injected_clock() {
+        let dir = tempdir().unwrap();
+        let (db, rule) = fixture(dir.path());
+        let t0 = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+
+        let first = record_failure(&db, &rule, &rule.folder_id, "/nas/a.pdf", "timed out", t0).unwrap();
+        assert_eq!(first, RetryDecision::Scheduled(t0 + Duration::minutes(1)));
+        assert!(claim_due_events(&db, t0 + Duration::seconds(59)).unwrap().is_empty());
+
+        let due = claim_due_events(&db, t0 + Duration::minutes(1)).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].rule_id.as_deref(), Some(rule.id.as_str()));
+        // Claimed: the next pass doesn't send it again while it's in flight.
+        assert!(claim_due_events(&db, t0 + Duration::minutes(2)).unwrap().is_empty());
+
+        let t1 = t0 + Duration::minutes(1);
+        let second = record_failure(&db, &rule, &rule.folder_id, "/nas/a.pdf", "timed out", t1).unwrap();
+        assert_eq!(second, RetryDecision::Scheduled(t1 + Duration::minutes(2)));
+        let listed = RetryRepository::new(db.clone()).list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].attempts, 2);
+        assert_eq!(listed[0].rule_name, "To NAS");
+    }
+
+    #[test]
+    fn stale_claims_are_sent_again() {
+        let dir = tempdir().unwrap();
+        let (db, rule) = fixture(dir.path());
+        let t0 = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        record_failure(&db, &rule, &rule.folder_id, "/nas/a.pdf", "timed out", t0).unwrap();
+
+        let claimed_at = t0 + Duration::minutes(1);
+        assert_eq!(claim_due_events(&db, claimed_at).unwrap().len(), 1);
+        let lease = Duration::minutes(CLAIM_LEASE_MINUTES);
+        assert!(claim_due_events(&db, claimed_at + lease - Duration::seconds(1))
+            .unwrap()
+            .is_empty());
+        assert_eq!(claim_due_events(&db, claimed_at + lease).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn forced_and_cancelled_retries() {
+        let dir = tempdir().unwrap();
+        let (db, rule) = fixture(dir.path());
+        let repo = RetryRepository::new(db.clone());
+        let t0 = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        record_failure(&db, &rule, &rule.folder_id, "/nas/a.pdf", "timed out", t0).unwrap();
+        record_failure(&db, &rule, &rule.folder_id, "/nas/b.pdf", "timed out", t0).unwrap();
+        let listed = repo.list().unwrap();
+
+        assert!(repo.force(&listed[0].id, t0).unwrap());
+        let due = claim_due_events(&db, t0).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].path.to_string_lossy(), listed[0].file_path);
+
+        repo.delete(&listed[1].id).unwrap();
+        assert!(!repo.force(&listed[1].id, t0).unwrap());
+        assert!(claim_due_events(&db, t0 + Duration::days(2)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn gives_up_after_the_last_attempt() {
+        let dir = tempdir().unwrap();
+        let (db, rule) = fixture(dir.path());
+        let mut now = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        for _ in 1..MAX_ATTEMPTS {
+            let decision =
+                record_failure(&db, &rule, &rule.folder_id, "/nas/a.pdf", "unreachable", now).unwrap();
+            let RetryDecision::Scheduled(next) = decision else {
+                panic!("gave up early: {decision:?}");
+            };
+            now = next;
+        }
+
+        let decision = record_failure(&db, &rule, &rule.folder_id, "/nas/a.pdf", "unreachable", now).unwrap();
+        assert_eq!(
+            decision,
+            RetryDecision::GaveUp(RetryExhausted {
+                rule_id: rule.id.clone(),
+                rule_name: rule.name.clone(),
+                file_path: "/nas/a.pdf".to_string(),
+                attempts: MAX_ATTEMPTS,
+                last_error: "unreachable".to_string(),
+            })
+        );
+        assert!(RetryRepository::new(db.clone()).list().unwrap().is_empty());
+        let logs = LogRepository::new(db).list(10, 0).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].action_type, "retry");
+        assert_eq!(logs[0].status, LogStatus::Error);
+        assert!(logs[0].error_message.as_deref().unwrap().starts_with("Gave up after 8 attempts"));
+    }
+}
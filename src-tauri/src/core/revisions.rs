@@ -0,0 +1,139 @@
+//! Which version of a rule did what. Every save bumps the rule's revision;
+//! log entries record the revision that ran with a hash of its conditions and
+//! actions, and previews the revision they were made against, so a preview
+//! can be told apart from a run after the rule was edited in between.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+use crate::models::{Rule, Settings};
+use crate::storage::database::Database;
+use crate::storage::rule_repo::RuleRepository;
+use crate::storage::rule_revision_repo::RuleRevisionRepository;
+
+/// Log metadata keys for the revision that ran and its content hash.
+pub const REVISION_KEY: &str = "rule_revision";
+pub const CONTENT_HASH_KEY: &str = "rule_content_hash";
+
+const CONTENT_HASH_LEN: usize = 12;
+
+/// A short hash of what the rule matches and does; renaming or moving it
+/// leaves the hash alone.
+pub fn content_hash(rule: &Rule) -> String {
+    // Through a `Value`, whose maps are sorted, so the hash doesn't depend on
+    // the order of the rule's own maps.
+    let content = serde_json::to_value((&rule.conditions, &rule.actions)).unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(content.to_string().as_bytes());
+    let mut hash = format!("{:x}", hasher.finalize());
+    hash.truncate(CONTENT_HASH_LEN);
+    hash
+}
+
+/// Adds the revision that ran to a log entry's metadata.
+pub fn describe(rule: &Rule, metadata: &mut HashMap<String, String>) {
+    metadata.insert(REVISION_KEY.to_string(), rule.revision.to_string());
+    metadata.insert(CONTENT_HASH_KEY.to_string(), content_hash(rule));
+}
+
+/// After a save, keeps the rule as saved when the settings ask for it.
+pub fn record(db: &Database, rule_id: &str, settings: &Settings) -> Result<()> {
+    if !settings.keep_rule_revisions {
+        return Ok(());
+    }
+    let Some(rule) = RuleRepository::new(db.clone()).get(rule_id)? else {
+        return Ok(());
+    };
+    RuleRevisionRepository::new(db.clone()).record(&rule, &content_hash(&rule))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        Condition, ConditionGroup, MatchType, Rule, StringCondition, StringOperator,
+    };
+    use crate::storage::folder_repo::FolderRepository;
+    use tempfile::tempdir;
+
+    fn rule(folder_id: &str) -> Rule {
+        Rule {
+            id: String::new(),
+            folder_id: folder_id.to_string(),
+            name: "Invoices".to_string(),
+            enabled: true,
+            stop_processing: false,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![Condition::Extension(StringCondition {
+                    operator: StringOperator::Is,
+                    value: "pdf".to_string(),
+                    case_sensitive: false,
+                })],
+            },
+            actions: vec![],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn the_hash_follows_what_the_rule_does_not_what_its_called() {
+        let original = rule("inbox");
+        let mut renamed = original.clone();
+        renamed.name = "Bills".to_string();
+        renamed.position = 4;
+        assert_eq!(content_hash(&original), content_hash(&renamed));
+        assert_eq!(content_hash(&original).len(), CONTENT_HASH_LEN);
+
+        let mut edited = original.clone();
+        edited.conditions.match_type = MatchType::Any;
+        assert_ne!(content_hash(&original), content_hash(&edited));
+    }
+
+    #[test]
+    fn saved_revisions_are_kept_only_when_asked() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create("/tmp/inbox", "Inbox")
+            .unwrap();
+        let repo = RuleRepository::new(db.clone());
+        let revisions = RuleRevisionRepository::new(db.clone());
+        let mut created = repo.create(rule(&folder.id)).unwrap();
+
+        record(&db, &created.id, &Settings::default()).unwrap();
+        assert!(revisions.get(&created.id, 1).unwrap().is_none());
+
+        let keep = Settings {
+            keep_rule_revisions: true,
+            ..Settings::default()
+        };
+        record(&db, &created.id, &keep).unwrap();
+        created.conditions.match_type = MatchType::Any;
+        repo.update(&created).unwrap();
+        record(&db, &created.id, &keep).unwrap();
+
+        let first = revisions.get(&created.id, 1).unwrap().unwrap();
+        let second = revisions.get(&created.id, 2).unwrap().unwrap();
+        assert_eq!(first.rule["conditions"]["matchType"], "all");
+        assert_eq!(second.rule["conditions"]["matchType"], "any");
+        assert_eq!(second.content_hash, content_hash(&created));
+        assert_ne!(first.content_hash, second.content_hash);
+    }
+}
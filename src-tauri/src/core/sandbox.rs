@@ -0,0 +1,543 @@
+//! Runs a folder's rules against a scenario of made-up files in a throwaway
+//! sandbox, to see what they'd do before they're pointed at real data, or to
+//! replay a bug report. The folder and its rules are copied into a fresh
+//! database with their paths moved into the sandbox, the scenario's files are
+//! written there, and a detached engine processes one synthesized arrival per
+//! file. Nothing outside the sandbox is touched, bar what a rule's scripts do.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, bail, Context, Result};
+use directories::UserDirs;
+
+use crate::core::engine::{action_type_to_string, RuleEngine};
+use crate::core::ocr::OcrManager;
+use crate::core::relink::{destination_mut, rebase_path};
+use crate::core::watcher::{FileEvent, FileEventKind};
+use crate::models::{
+    ActionType, EventSource, Folder, LogEntry, LogStatus, Rule, SandboxEntry, SandboxFileOutcome,
+    SandboxResult, SandboxScenario, ScenarioFile, Settings,
+};
+use crate::storage::database::Database;
+use crate::storage::folder_repo::{FolderRepository, FolderSetup};
+use crate::storage::log_repo::LogRepository;
+use crate::storage::rule_repo::RuleRepository;
+use crate::utils::platform::normalize_user_path;
+use crate::utils::tempfiles::{app_temp, TempArtifact};
+
+/// Where the watched folder, `~` and other absolute paths go in a sandbox.
+pub const FOLDER_DIR: &str = "folder";
+pub const HOME_DIR: &str = "home";
+pub const ROOT_DIR: &str = "root";
+
+const DB_DIR: &str = "db";
+
+/// Largest file a scenario can ask for.
+pub const MAX_FILE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Most log entries a run reports.
+const MAX_LOGS: usize = 100_000;
+
+/// Runs the rules of `folder_id` in `db` against `scenario` and removes the
+/// sandbox again once the result is in.
+pub fn run_scenario(
+    db: &Database,
+    folder_id: &str,
+    scenario: &SandboxScenario,
+    settings: &Settings,
+    ocr: Arc<Mutex<OcrManager>>,
+) -> Result<SandboxResult> {
+    let folder = FolderRepository::new(db.clone())
+        .get(folder_id)?
+        .ok_or_else(|| anyhow!("Folder not found"))?;
+    if folder.is_group {
+        bail!("Groups don't have rules of their own to run");
+    }
+    if !folder.is_directory() {
+        bail!("Rules on the system trash run in the trash sweep, not in a sandbox");
+    }
+    let rules = RuleRepository::new(db.clone()).list_by_folder(folder_id)?;
+    Sandbox::new(&folder, rules)?.run(scenario, settings, ocr)
+}
+
+/// Reads a scenario saved with `save_scenario`.
+pub fn load_scenario(path: &Path) -> Result<SandboxScenario> {
+    let json =
+        fs::read_to_string(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+    serde_json::from_str(&json).with_context(|| format!("{} isn't a scenario", path.display()))
+}
+
+pub fn save_scenario(path: &Path, scenario: &SandboxScenario) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(scenario)?)
+        .with_context(|| format!("Couldn't write {}", path.display()))
+}
+
+/// A copy of one folder and its rules in a temp directory with a database of
+/// its own. Tests build one straight from a folder and rules.
+pub struct Sandbox {
+    // Dropped before the directory it lives in.
+    db: Database,
+    folder: Folder,
+    /// Where the folder is outside the sandbox.
+    original: PathBuf,
+    dir: TempArtifact,
+}
+
+impl Sandbox {
+    /// Copies `folder`'s options and `rules` into a new sandbox, with the
+    /// paths the rules' actions write to moved into it.
+    pub fn new(folder: &Folder, rules: Vec<Rule>) -> Result<Self> {
+        let dir = app_temp().staging_dir_in(&std::env::temp_dir())?;
+        let root = dir.path().to_path_buf();
+        for sub in [FOLDER_DIR, HOME_DIR, ROOT_DIR, DB_DIR] {
+            fs::create_dir_all(root.join(sub))?;
+        }
+        let db = Database::new_with_path(root.join(DB_DIR).join("sandbox.db"))?;
+
+        let original = normalize_user_path(&folder.path);
+        let rules = rules
+            .into_iter()
+            .map(|mut rule| {
+                for destination in rule.actions.iter_mut().filter_map(destination_mut) {
+                    *destination = sandboxed(destination, &original, &root);
+                }
+                rule
+            })
+            .collect();
+        let (folder, _) = FolderRepository::new(db.clone())
+            .create_with_rules(vec![FolderSetup {
+                path: root.join(FOLDER_DIR).to_string_lossy().to_string(),
+                name: folder.name.clone(),
+                settings: Some(folder.settings()),
+                rules,
+            }])?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("The sandbox folder wasn't created"))?;
+        Ok(Self {
+            db,
+            folder,
+            original,
+            dir,
+        })
+    }
+
+    fn root(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Writes the scenario's files, has a detached engine process each one
+    /// that arrives in the folder, in the scenario's order, and reports what
+    /// happened. The sandbox is removed afterwards.
+    pub fn run(
+        self,
+        scenario: &SandboxScenario,
+        settings: &Settings,
+        ocr: Arc<Mutex<OcrManager>>,
+    ) -> Result<SandboxResult> {
+        let mut arrivals = Vec::new();
+        for file in &scenario.files {
+            let (path, arrives) = self.place(&file.path)?;
+            write_file(&path, file)?;
+            if arrives {
+                arrivals.push(path);
+            }
+        }
+
+        let settings = Settings {
+            // The sandbox is there to be changed, and nobody should hear of it.
+            dry_run: false,
+            show_notifications: false,
+            // The log is read back as soon as the run ends.
+            db_batch_commit_ms: 0,
+            ..settings.clone()
+        };
+        let engine = RuleEngine::detached(self.db.clone(), Arc::new(Mutex::new(settings)), ocr);
+        let events = arrivals.iter().map(|path| FileEvent {
+            path: path.clone(),
+            folder_id: self.folder.id.clone(),
+            kind: FileEventKind::Created,
+            rule_id: None,
+            arrival: None,
+            active_app: None,
+            source: EventSource::Live,
+        });
+        let errors = engine
+            .process_all(events)
+            .into_iter()
+            .map(|(path, err)| format!("{}: {err}", self.relative(&path)))
+            .collect();
+        drop(engine);
+
+        let tree = self.tree()?;
+        let mut logs = LogRepository::new(self.db.clone()).list(MAX_LOGS, 0)?;
+        logs.reverse();
+        for entry in &mut logs {
+            self.relativize(entry);
+        }
+        let landed: HashSet<&str> = tree.iter().map(|entry| entry.path.as_str()).collect();
+        let files = arrivals
+            .iter()
+            .map(|path| follow(&self.relative(path), &logs, &landed))
+            .collect();
+        Ok(SandboxResult {
+            tree,
+            logs,
+            files,
+            errors,
+        })
+    }
+
+    /// Where a scenario path goes, and whether it arrives in the folder
+    /// rather than being there already.
+    fn place(&self, path: &str) -> Result<(PathBuf, bool)> {
+        if Path::new(path)
+            .components()
+            .any(|component| component == Component::ParentDir)
+        {
+            bail!("Scenario path \"{path}\" can't leave the sandbox");
+        }
+        if normalize_user_path(path).is_absolute() {
+            let placed = sandboxed(path, &self.original, self.root());
+            return Ok((PathBuf::from(placed), false));
+        }
+        if path.trim().is_empty() {
+            bail!("Scenario files need a path");
+        }
+        Ok((self.root().join(FOLDER_DIR).join(path), true))
+    }
+
+    /// Every file under the folder, home and root, with its size.
+    fn tree(&self) -> Result<Vec<SandboxEntry>> {
+        let mut entries = Vec::new();
+        for sub in [FOLDER_DIR, HOME_DIR, ROOT_DIR] {
+            for entry in walkdir::WalkDir::new(self.root().join(sub)) {
+                let entry = entry?;
+                if entry.file_type().is_file() {
+                    entries.push(SandboxEntry {
+                        path: self.relative(entry.path()),
+                        size: entry.metadata()?.len(),
+                    });
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    /// `path` relative to the sandbox, with `/` between its parts; paths
+    /// outside it are kept as they are.
+    fn relative(&self, path: &Path) -> String {
+        match path.strip_prefix(self.root()) {
+            Ok(rest) => rest
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            Err(_) => path.to_string_lossy().to_string(),
+        }
+    }
+
+    fn relativize(&self, entry: &mut LogEntry) {
+        entry.file_path = self.relative(Path::new(&entry.file_path));
+        if let Some(details) = entry.action_detail.as_mut() {
+            details.source_path = self.relative(Path::new(&details.source_path));
+            if let Some(dest) = details.destination_path.as_mut() {
+                *dest = self.relative(Path::new(dest));
+            }
+        }
+    }
+}
+
+/// Where `value`, a path as a rule or scenario writes it, goes in the sandbox
+/// at `root`: under `folder` when it's in the watched folder `original`, under
+/// `home` when it's elsewhere under `~`, and under `root` for other absolute
+/// paths. Relative paths are left alone.
+fn sandboxed(value: &str, original: &Path, root: &Path) -> String {
+    if let Some(rebased) = rebase_path(value, original, &root.join(FOLDER_DIR)) {
+        return rebased;
+    }
+    if let Some(home) = UserDirs::new().map(|dirs| dirs.home_dir().to_path_buf()) {
+        if let Some(rebased) = rebase_path(value, &home, &root.join(HOME_DIR)) {
+            return rebased;
+        }
+    }
+    let path = normalize_user_path(value);
+    if !path.is_absolute() {
+        return value.to_string();
+    }
+    let rest: PathBuf = path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect();
+    root.join(ROOT_DIR).join(rest).to_string_lossy().to_string()
+}
+
+/// Writes `file` at `path`: its content padded with zeros or cut to its size,
+/// and its timestamps.
+fn write_file(path: &Path, file: &ScenarioFile) -> Result<()> {
+    let mut bytes = file.content.clone().unwrap_or_default().into_bytes();
+    if let Some(size) = file.size {
+        if size > MAX_FILE_BYTES {
+            bail!(
+                "Scenario file \"{}\" is over the {} MB limit",
+                file.path,
+                MAX_FILE_BYTES / (1024 * 1024)
+            );
+        }
+        bytes.resize(size as usize, 0);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, &bytes)?;
+
+    let mut times = fs::FileTimes::new();
+    if let Some(modified) = file.modified {
+        times = times.set_modified(SystemTime::from(modified));
+    }
+    #[cfg(target_os = "macos")]
+    if let Some(created) = file.created {
+        use std::os::macos::fs::FileTimesExt;
+        times = times.set_created(SystemTime::from(created));
+    }
+    #[cfg(windows)]
+    if let Some(created) = file.created {
+        use std::os::windows::fs::FileTimesExt;
+        times = times.set_created(SystemTime::from(created));
+    }
+    fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_times(times)?;
+    Ok(())
+}
+
+/// Follows the file that arrived at `path` through the moves, renames and
+/// sorts in `logs`.
+fn follow(path: &str, logs: &[LogEntry], landed: &HashSet<&str>) -> SandboxFileOutcome {
+    let moves: HashSet<String> = [
+        ActionType::Move,
+        ActionType::Rename,
+        ActionType::SortIntoSubfolder,
+    ]
+    .iter()
+    .map(action_type_to_string)
+    .collect();
+    let mut current = path.to_string();
+    let mut rules: Vec<String> = Vec::new();
+    let mut status: Option<LogStatus> = None;
+    for entry in logs {
+        // A rule's entries are all logged under where the file was when it
+        // started; each action's own source says where it was by then.
+        let source = entry
+            .action_detail
+            .as_ref()
+            .map_or(entry.file_path.as_str(), |details| {
+                details.source_path.as_str()
+            });
+        if entry.file_path != current && source != current {
+            continue;
+        }
+        if let Some(name) = &entry.rule_name {
+            if rules.last() != Some(name) {
+                rules.push(name.clone());
+            }
+        }
+        if status
+            .as_ref()
+            .is_none_or(|worst| severity(&entry.status) > severity(worst))
+        {
+            status = Some(entry.status.clone());
+        }
+        let moved = entry
+            .action_detail
+            .as_ref()
+            .filter(|_| entry.status == LogStatus::Success && moves.contains(&entry.action_type))
+            .and_then(|details| details.destination_path.clone());
+        if let Some(dest) = moved {
+            current = dest;
+        }
+    }
+    SandboxFileOutcome {
+        path: path.to_string(),
+        final_path: landed.contains(current.as_str()).then_some(current),
+        rules,
+        status,
+    }
+}
+
+fn severity(status: &LogStatus) -> u8 {
+    match status {
+        LogStatus::Success => 0,
+        LogStatus::Skipped => 1,
+        LogStatus::Warning => 2,
+        LogStatus::Error => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        Action, Condition, ConditionGroup, ConflictResolution, CopyAction, MatchType, MoveAction,
+        StringCondition, StringOperator,
+    };
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    fn rule(name: &str, conditions: Vec<Condition>, action: Action, stop: bool) -> Rule {
+        Rule {
+            id: String::new(),
+            folder_id: String::new(),
+            name: name.to_string(),
+            enabled: true,
+            stop_processing: stop,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions,
+            },
+            actions: vec![action],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn file(path: &str) -> ScenarioFile {
+        ScenarioFile {
+            path: path.to_string(),
+            size: Some(2048),
+            content: None,
+            modified: None,
+            created: None,
+        }
+    }
+
+    #[test]
+    fn runs_the_rules_in_order_inside_the_sandbox() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let downloads = dir.path().join("Downloads");
+        let folder = FolderRepository::new(db.clone())
+            .create(&downloads.to_string_lossy(), "Downloads")
+            .unwrap();
+        let invoices = rule(
+            "Invoices",
+            vec![Condition::Name(StringCondition {
+                operator: StringOperator::Contains,
+                value: "invoice".to_string(),
+                case_sensitive: false,
+            })],
+            Action::Copy(CopyAction {
+                destination: "~/Archive/Invoices".to_string(),
+                on_conflict: ConflictResolution::Rename,
+                skip_duplicates: false,
+                use_reflink: None,
+                destination_permissions: Default::default(),
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
+            }),
+            true,
+        );
+        let pdfs = rule(
+            "PDFs",
+            vec![Condition::Extension(StringCondition {
+                operator: StringOperator::Is,
+                value: "pdf".to_string(),
+                case_sensitive: false,
+            })],
+            Action::Move(MoveAction {
+                destination: "~/Archive/PDFs".to_string(),
+                on_conflict: ConflictResolution::Rename,
+                skip_duplicates: false,
+                destination_permissions: Default::default(),
+                quarantine: Default::default(),
+                bring_companions: None,
+                auto_partition: None,
+            }),
+            false,
+        );
+        let scenario = SandboxScenario {
+            name: "Invoice already archived".to_string(),
+            files: vec![
+                file("~/Archive/Invoices/invoice.pdf"),
+                file("invoice.pdf"),
+                file("report.pdf"),
+            ],
+        };
+
+        let result = Sandbox::new(&folder, vec![invoices, pdfs])
+            .unwrap()
+            .run(
+                &scenario,
+                &Settings::default(),
+                Arc::new(Mutex::new(OcrManager::new_placeholder())),
+            )
+            .unwrap();
+
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        let tree: Vec<&str> = result
+            .tree
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect();
+        assert_eq!(
+            tree,
+            [
+                "folder/invoice.pdf",
+                "home/Archive/Invoices/invoice (1).pdf",
+                "home/Archive/Invoices/invoice.pdf",
+                "home/Archive/PDFs/report.pdf",
+            ]
+        );
+        // Pre-existing files don't arrive, so only the two in the folder are followed.
+        assert_eq!(result.files.len(), 2);
+        assert_eq!(result.files[0].rules, ["Invoices"]);
+        assert_eq!(
+            result.files[0].final_path.as_deref(),
+            Some("folder/invoice.pdf")
+        );
+        assert_eq!(result.files[1].rules, ["PDFs"]);
+        assert_eq!(
+            result.files[1].final_path.as_deref(),
+            Some("home/Archive/PDFs/report.pdf")
+        );
+        assert_eq!(result.files[1].status, Some(LogStatus::Success));
+        // The real folder was never touched.
+        assert!(!downloads.exists());
+    }
+
+    #[test]
+    fn scenario_paths_cant_leave_the_sandbox() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.path().join("Inbox").to_string_lossy(), "Inbox")
+            .unwrap();
+        let sandbox = Sandbox::new(&folder, vec![]).unwrap();
+        assert!(sandbox.place("../escape.txt").is_err());
+        assert!(sandbox.place("  ").is_err());
+        let (placed, arrives) = sandbox.place("/etc/hosts").unwrap();
+        assert!(!arrives);
+        assert_eq!(placed, sandbox.root().join(ROOT_DIR).join("etc/hosts"));
+    }
+}
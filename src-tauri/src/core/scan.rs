@@ -0,0 +1,452 @@
+use std::cell::Cell;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use filedispatch_core::file_info::FileInfo;
+use glob::Pattern;
+use serde::Serialize;
+
+use crate::core::approvals::propose;
+use crate::core::budget::{check_budget, record_transfers, BudgetDecision};
+use crate::core::conflicts::{suspend, ActionRun};
+use crate::core::counters::{number_run, settle_counter};
+use crate::core::duplicates::DuplicateDetector;
+use crate::core::engine::{
+    brought_along, evaluate_conditions, in_path_scope, log_outcomes, log_placeholder_skip,
+    picked_up, placeholder_gate, record_companion_matches, write_processed_markers,
+    EvaluationOptions, PlaceholderGate,
+};
+use crate::core::executor::{ActionExecutor, ActionResultStatus, ActionScope};
+use crate::core::folder_lanes::{FolderLanes, Ticket};
+use crate::core::match_metadata;
+use crate::core::ocr::OcrManager;
+use crate::core::thumbnails::{activity_thumbnail, record_thumbnail};
+use crate::core::watcher::{compile_patterns, is_passthrough, should_ignore};
+use crate::models::{EventSource, Folder, Rule, Settings};
+use crate::storage::conflict_repo::ConflictRepository;
+use crate::storage::database::Database;
+use crate::storage::log_repo::LogRepository;
+use crate::storage::rule_repo::RuleRepository;
+use crate::storage::scan_cursor_repo::ScanCursorRepository;
+use crate::storage::write_set::WriteSet;
+use crate::utils::gitguard::GitGuard;
+use crate::utils::platform::normalize_user_path;
+use crate::utils::walk::{walk_files, WalkOptions};
+
+/// How often, in files, a scan saves how far it got.
+const CURSOR_EVERY: usize = 256;
+
+#[derive(Clone, Serialize)]
+pub struct RunResult {
+    pub total_files: usize,
+    pub processed: usize,
+    pub matched: usize,
+    pub errors: Vec<String>,
+    /// False when the run stopped at `Settings::scan_max_files_per_run`; the
+    /// next one carries on where it left off.
+    pub complete: bool,
+}
+
+/// Runs `rules` against every file currently in `folder` (respecting its scan
+/// depth). Used by "Run now" and by scheduled maintenance rules.
+///
+/// The folder is read a batch at a time as the scan goes, so the total
+/// `on_progress` gets with `(total, processed, current_file)` is the files
+/// found so far. It's called before each file and once more with an empty
+/// name when the scan finishes. Breaking stops the scan before that file, as
+/// if it reached the per-run cap.
+///
+/// Files of a strict sequential folder take turns with its live events.
+/// `source` says whether "Run now" or a schedule started the scan, and
+/// `duplicates` is where Is Duplicate conditions look for copies.
+#[allow(clippy::too_many_arguments)]
+pub fn scan_folder(
+    db: &Database,
+    executor: &ActionExecutor,
+    folder: &Folder,
+    rules: &[Rule],
+    settings: &Settings,
+    ocr: &std::sync::Mutex<OcrManager>,
+    lanes: &FolderLanes,
+    duplicates: &Arc<DuplicateDetector>,
+    source: EventSource,
+    mut on_progress: impl FnMut(usize, usize, &str) -> ControlFlow<()>,
+) -> Result<RunResult> {
+    let folder_path = normalize_user_path(&folder.path);
+    if !folder_path.exists() {
+        return Err(anyhow!("Folder does not exist: {}", folder_path.display()));
+    }
+
+    let ignore: Vec<Pattern> = settings
+        .ignore_patterns
+        .iter()
+        .filter_map(|p| Pattern::new(p).ok())
+        .collect();
+    let cursor_repo = ScanCursorRepository::new(db.clone());
+    let options = WalkOptions {
+        max_depth: folder.max_depth().unwrap_or(usize::MAX),
+        start_after: cursor_repo.get(&folder.id)?,
+        ..WalkOptions::default()
+    };
+    let git = folder.respect_git.then(GitGuard::default);
+    if git.as_ref().is_some_and(|git| git.busy(&folder_path)) {
+        return Err(anyhow!(
+            "Git is partway through an operation in {}; try again once it's done",
+            folder_path.display()
+        ));
+    }
+    let skip_tracked = folder.skip_git_tracked;
+    let passthrough = compile_patterns(&folder.effective_passthrough_patterns());
+    let root = folder_path.clone();
+    let found = Cell::new(0);
+    let entries = walk_files(&folder_path, options, move |path| {
+        should_ignore(path, &ignore)
+            || is_passthrough(path, &root, &passthrough)
+            || git
+                .as_ref()
+                .filter(|_| skip_tracked)
+                .is_some_and(|git| git.is_tracked(&root, path) == Some(true))
+    })
+    .inspect(|batch| found.set(found.get() + batch.len()))
+    .flatten();
+
+    let cap = match settings.scan_max_files_per_run {
+        0 => usize::MAX,
+        cap => cap as usize,
+    };
+    let pace = Pace::new(settings.scan_files_per_second);
+    let mut processed = 0;
+    let mut matched = 0;
+    let mut errors = Vec::new();
+    let mut complete = true;
+    let mut cursor: Option<PathBuf> = None;
+    // Files an earlier file in the scan brought along as companions or moved
+    // further along the folder.
+    let mut handled = std::collections::HashSet::new();
+
+    let options = EvaluationOptions::finding_duplicates(duplicates, &folder.id);
+    let rule_repo = RuleRepository::new(db.clone());
+    let log_repo = LogRepository::new(db.clone());
+    let conflict_repo = ConflictRepository::new(db.clone());
+
+    for file_path in entries {
+        if processed == cap {
+            complete = false;
+            break;
+        }
+        // Kept as the scan goes, so one cut short by quitting resumes too.
+        if let Some(cursor) = cursor.as_ref().filter(|_| processed % CURSOR_EVERY == 0) {
+            cursor_repo.set(&folder.id, &cursor.to_string_lossy())?;
+        }
+        let file_name = file_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if on_progress(found.get(), processed, &file_name).is_break() {
+            complete = false;
+            break;
+        }
+        cursor = Some(file_path.clone());
+
+        if handled.contains(&file_path) {
+            processed += 1;
+            continue;
+        }
+        pace.wait(processed);
+
+        // The engine takes the lane before OCR too; the other order could
+        // deadlock with it.
+        let _turn = lanes.enqueue(&folder.id).map(Ticket::wait);
+        let mut ocr_guard = ocr.lock().map_err(|_| anyhow!("OCR lock poisoned"))?;
+
+        // Get file info
+        let mut info = match FileInfo::from_path(&file_path) {
+            Ok(info) => info,
+            Err(e) => {
+                errors.push(format!("{}: {}", file_name, e));
+                processed += 1;
+                continue;
+            }
+        };
+        picked_up(&mut info, source, &folder_path);
+
+        // It's the conflict's to finish once someone decides.
+        if conflict_repo.is_pending(&info.path.to_string_lossy()).unwrap_or(false) {
+            processed += 1;
+            continue;
+        }
+
+        // A scan can't wait for downloads, so placeholders are only evaluated
+        // in metadata-only mode.
+        if matches!(
+            placeholder_gate(folder.placeholder_mode, info.is_placeholder),
+            PlaceholderGate::Skip | PlaceholderGate::Hydrate
+        ) {
+            if let Err(e) =
+                log_placeholder_skip(&log_repo, &info, "Skipped online-only placeholder")
+            {
+                errors.push(format!("{}: {}", file_name, e));
+            }
+            processed += 1;
+            continue;
+        }
+
+        // Process against each rule
+        let mut file_matched = false;
+        // Made the first time a rule matches, before its actions move the file.
+        let mut thumbnail: Option<Option<String>> = None;
+        let mut writes = WriteSet::default();
+        for rule in rules {
+            if !rule.enabled || !in_path_scope(rule, &folder_path, &info.path) {
+                continue;
+            }
+
+            // Evaluate conditions
+            let mut evaluation =
+                match evaluate_conditions(rule, &info, settings, &mut ocr_guard, &options) {
+                    Ok(eval) => eval,
+                    Err(e) => {
+                        errors.push(format!("{}: {}", file_name, e));
+                        continue;
+                    }
+                };
+
+            if !evaluation.matched {
+                continue;
+            }
+
+            file_matched = true;
+
+            // Approval rules queue the match and let later rules run.
+            if rule.approval_required {
+                let plan = |actions: &[crate::models::Action],
+                            info: &FileInfo,
+                            captures: &std::collections::HashMap<String, String>,
+                            _: &ActionScope| {
+                    executor.simulate_actions(actions, info, captures)
+                };
+                match propose(db, rule, &info, &evaluation.captures, &plan) {
+                    Ok(approval) => executor.notify_pending_approval(&approval),
+                    Err(e) => errors.push(format!("{}: {}", file_name, e)),
+                }
+                continue;
+            }
+
+            match check_budget(db, rule, &info, settings, Utc::now()) {
+                Ok(BudgetDecision::Allowed) => {}
+                Ok(BudgetDecision::Exhausted(notice)) => {
+                    if let Some(notice) = notice {
+                        executor.notify_budget_exhausted(&notice);
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    errors.push(format!("{}: {}", file_name, e));
+                    continue;
+                }
+            }
+
+            if folder.activity_thumbnails && thumbnail.is_none() {
+                thumbnail = Some(activity_thumbnail(&info, settings));
+            }
+            if let Err(e) = number_run(db, rule, &mut evaluation.captures) {
+                errors.push(format!("{}: {}", file_name, e));
+                continue;
+            }
+            // Execute actions
+            let scope = ActionScope::new(&rule.id, &info);
+            let mut outcomes =
+                executor.execute_actions(&rule.actions, &info, &evaluation.captures, &scope);
+            if let Some(Some(path)) = &thumbnail {
+                record_thumbnail(&mut outcomes, path);
+            }
+
+            // Log outcomes
+            let recorded =
+                match_metadata::resolve(rule, &info, &evaluation.captures, settings);
+            log_outcomes(&mut writes, rule, &info, &outcomes, &recorded, None);
+            if let Err(e) = record_transfers(db, rule, info.size, &outcomes, settings, Utc::now()) {
+                errors.push(format!("{}: {}", file_name, e));
+            }
+            if let Err(e) = settle_counter(db, rule, &evaluation.captures, &outcomes) {
+                errors.push(format!("{}: {}", file_name, e));
+            }
+            let run = ActionRun {
+                rule,
+                actions: &rule.actions,
+                first_index: 0,
+                captures: &evaluation.captures,
+                trace_id: None,
+            };
+            match suspend(db, &run, &info, &outcomes) {
+                Ok(Some(conflict)) => {
+                    executor.notify_pending_conflict(&conflict);
+                    break;
+                }
+                Ok(None) => {}
+                Err(e) => errors.push(format!("{}: {}", file_name, e)),
+            }
+
+            // Record match
+            writes.record_match(
+                &rule.id,
+                info.path.to_string_lossy().as_ref(),
+                Some(&info.hash),
+                &recorded,
+                &settings.match_metadata_indexed_keys,
+            );
+            write_processed_markers(settings, &rule.id, &info.path, &outcomes);
+            record_companion_matches(&rule_repo, &mut writes, &outcomes);
+            handled.extend(brought_along(&outcomes));
+            handled.extend(
+                outcomes
+                    .iter()
+                    .filter(|outcome| outcome.status == ActionResultStatus::Success)
+                    .filter_map(|outcome| outcome.details.as_ref()?.destination_path.as_ref())
+                    .map(PathBuf::from),
+            );
+
+            // Stop processing if rule says so
+            if rule.stop_processing {
+                break;
+            }
+        }
+
+        if let Err(e) = writes.commit(db) {
+            errors.push(format!("{}: {}", file_name, e));
+        }
+        if file_matched {
+            matched += 1;
+        }
+        processed += 1;
+    }
+
+    // A run that got to the end starts from the top next time.
+    match &cursor {
+        Some(cursor) if !complete => cursor_repo.set(&folder.id, &cursor.to_string_lossy())?,
+        _ => cursor_repo.clear(&folder.id)?,
+    }
+
+    let _ = on_progress(found.get(), processed, "");
+
+    Ok(RunResult {
+        total_files: found.get(),
+        processed,
+        matched,
+        errors,
+        complete,
+    })
+}
+
+/// Spaces a scan's files out to `per_second` on average since it started,
+/// so a live event never waits behind more than one of them.
+struct Pace {
+    per_second: u32,
+    started: Instant,
+}
+
+impl Pace {
+    fn new(per_second: u32) -> Self {
+        Self {
+            per_second,
+            started: Instant::now(),
+        }
+    }
+
+    /// Sleeps until file number `done` is due.
+    fn wait(&self, done: usize) {
+        if self.per_second == 0 {
+            return;
+        }
+        let due = self.started + Duration::from_secs_f64(done as f64 / self.per_second as f64);
+        let now = Instant::now();
+        if due > now {
+            std::thread::sleep(due - now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        Condition, ConditionGroup, DuplicateScope, IsDuplicateCondition, MatchType,
+    };
+    use crate::storage::folder_repo::FolderRepository;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    #[test]
+    fn scans_tell_copies_from_originals() {
+        let dir = tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        fs::create_dir_all(&inbox).unwrap();
+        fs::write(inbox.join("a.jpg"), "jpeg").unwrap();
+        fs::write(inbox.join("b.jpg"), "jpeg").unwrap();
+        fs::write(inbox.join("c.jpg"), "other").unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&inbox.to_string_lossy(), "Inbox")
+            .unwrap();
+        let rule = RuleRepository::new(db.clone())
+            .create(Rule {
+                id: String::new(),
+                folder_id: folder.id.clone(),
+                name: "Originals".to_string(),
+                enabled: true,
+                stop_processing: true,
+                conditions: ConditionGroup {
+                    label: None,
+                    match_type: MatchType::All,
+                    conditions: vec![Condition::IsDuplicate(IsDuplicateCondition {
+                        scope: DuplicateScope::SameFolder,
+                        negate: true,
+                    })],
+                },
+                actions: vec![],
+                position: 0,
+                schedule: None,
+                approval_required: false,
+                min_age_seconds: None,
+                path_scope: None,
+                skip_reference_check: false,
+                monthly_byte_budget: None,
+                classification: None,
+                on_reappear: None,
+                record_metadata: HashMap::new(),
+                revision: 0,
+                extra: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+            .unwrap();
+        let settings = Settings::default();
+        let ocr = Arc::new(Mutex::new(OcrManager::new_placeholder()));
+        let executor =
+            ActionExecutor::detached(Arc::new(Mutex::new(settings.clone())), ocr.clone());
+
+        let result = scan_folder(
+            &db,
+            &executor,
+            &folder,
+            &[rule],
+            &settings,
+            &ocr,
+            &FolderLanes::default(),
+            &Arc::new(DuplicateDetector::new(db.clone())),
+            EventSource::Manual,
+            |_, _, _| ControlFlow::Continue(()),
+        )
+        .unwrap();
+        assert_eq!(result.processed, 3);
+        // Only the second of the two identical files is a copy.
+        assert_eq!(result.matched, 2, "{:?}", result.errors);
+    }
+}
@@ -0,0 +1,583 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, Utc};
+use crossbeam_channel::{SendTimeoutError, Sender};
+use filedispatch_core::file_info::FileInfo;
+
+use crate::core::conflicts::ConflictRunner;
+use crate::core::duplicates::DuplicateDetector;
+use crate::core::executor::{ActionExecutor, ActionScope};
+use crate::core::folder_lanes::FolderLanes;
+use crate::core::ocr::OcrManager;
+use crate::core::scan::scan_folder;
+use crate::core::startup::changed_at;
+use crate::core::trash::{self, SystemTrash, TrashSweeper};
+use crate::core::watcher::{
+    compile_patterns, is_passthrough, should_ignore, FileEvent, FileEventKind,
+};
+use crate::models::{
+    Action, EventSource, Folder, FolderKind, PendingConflict, Rule, RuleId, RuleSchedule, Settings,
+};
+use crate::storage::database::Database;
+use crate::storage::folder_repo::FolderRepository;
+use crate::storage::match_repo::MatchRepository;
+use crate::storage::rule_repo::RuleRepository;
+use crate::utils::platform::normalize_user_path;
+use crate::utils::walk::{walk_files, WalkOptions};
+
+/// How often the scheduler checks for due maintenance rules.
+const TICK: Duration = Duration::from_secs(30);
+
+/// How long a folder scan waits on a full backlog before checking whether
+/// it should stop.
+const SEND_POLL: Duration = Duration::from_millis(200);
+
+/// Periodically runs maintenance rules (rules with a `schedule`) against the
+/// current contents of their folder, independent of watcher events, and
+/// sweeps the system trash for the folders mounted on it.
+pub struct MaintenanceScheduler {
+    db: Database,
+    executor: ActionExecutor,
+    settings: Arc<Mutex<Settings>>,
+    ocr: Arc<Mutex<OcrManager>>,
+    paused: Arc<AtomicBool>,
+    folder_lanes: Arc<FolderLanes>,
+    duplicates: Arc<DuplicateDetector>,
+    tracker: ScheduleTracker,
+    last_trash_sweep: Option<Instant>,
+}
+
+impl MaintenanceScheduler {
+    pub fn new(
+        db: Database,
+        app_handle: tauri::AppHandle,
+        settings: Arc<Mutex<Settings>>,
+        ocr: Arc<Mutex<OcrManager>>,
+        paused: Arc<AtomicBool>,
+        folder_lanes: Arc<FolderLanes>,
+        duplicates: Arc<DuplicateDetector>,
+    ) -> Self {
+        Self {
+            db,
+            executor: ActionExecutor::new(app_handle, settings.clone(), ocr.clone()),
+            settings,
+            ocr,
+            paused,
+            folder_lanes,
+            duplicates,
+            tracker: ScheduleTracker::default(),
+            last_trash_sweep: None,
+        }
+    }
+
+    pub fn start(mut self) {
+        thread::spawn(move || loop {
+            if let Err(err) = self.tick(Local::now().naive_local()) {
+                eprintln!("Maintenance scheduler error: {err}");
+            }
+            thread::sleep(TICK);
+        });
+    }
+
+    fn tick(&mut self, now: NaiveDateTime) -> Result<()> {
+        if !self.paused.load(Ordering::SeqCst) {
+            self.expire_conflicts()?;
+            self.sweep_trash()?;
+        }
+        let rules = RuleRepository::new(self.db.clone()).list_scheduled()?;
+        let due = self.tracker.due(&rules, now);
+        // Runs missed while paused are skipped rather than replayed on resume.
+        if due.is_empty() || self.paused.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let mut by_folder: BTreeMap<&str, Vec<Rule>> = BTreeMap::new();
+        for rule in rules.iter().filter(|rule| due.contains(&rule.id)) {
+            by_folder
+                .entry(rule.folder_id.as_str())
+                .or_default()
+                .push(rule.clone());
+        }
+
+        let folder_repo = FolderRepository::new(self.db.clone());
+        let settings = self.settings.lock().map(|s| s.clone()).unwrap_or_default();
+        for (folder_id, folder_rules) in by_folder {
+            let Some(folder) = folder_repo.get(folder_id)? else {
+                continue;
+            };
+            // A folder that's gone shows in the engine status instead of
+            // failing here every tick.
+            if !folder.enabled
+                || !folder.is_directory()
+                || !normalize_user_path(&folder.path).is_dir()
+            {
+                continue;
+            }
+            let result = scan_folder(
+                &self.db,
+                &self.executor,
+                &folder,
+                &folder_rules,
+                &settings,
+                &self.ocr,
+                &self.folder_lanes,
+                &self.duplicates,
+                EventSource::Scan,
+                |_, _, _| ControlFlow::Continue(()),
+            );
+            match result {
+                Ok(result) => {
+                    for error in result.errors {
+                        eprintln!("Maintenance run error in {}: {error}", folder.name);
+                    }
+                }
+                Err(err) => eprintln!("Maintenance run failed for {}: {err}", folder.name),
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the rules of each system trash folder over what's in the trash,
+    /// at most once per `trash::SWEEP_INTERVAL`.
+    fn sweep_trash(&mut self) -> Result<()> {
+        if self
+            .last_trash_sweep
+            .is_some_and(|last| last.elapsed() < trash::SWEEP_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_trash_sweep = Some(Instant::now());
+        let folders: Vec<_> = FolderRepository::new(self.db.clone())
+            .list()?
+            .into_iter()
+            .filter(|folder| folder.enabled && folder.kind == FolderKind::SystemTrash)
+            .collect();
+        if folders.is_empty() {
+            return Ok(());
+        }
+        let Some(system_trash) = SystemTrash::locate() else {
+            return Ok(());
+        };
+        let settings = self.settings.lock().map(|s| s.clone()).unwrap_or_default();
+        let sweeper = TrashSweeper {
+            db: &self.db,
+            executor: &self.executor,
+            settings: &settings,
+            ocr: &self.ocr,
+            duplicates: &self.duplicates,
+        };
+        let rule_repo = RuleRepository::new(self.db.clone());
+        for folder in folders {
+            let rules = rule_repo.list_by_folder(&folder.id)?;
+            match sweeper.sweep(&system_trash, &folder, &rules) {
+                Ok(result) => {
+                    for error in result.errors {
+                        eprintln!("Trash sweep error in {}: {error}", folder.name);
+                    }
+                }
+                Err(err) => eprintln!("Trash sweep failed for {}: {err}", folder.name),
+            }
+        }
+        Ok(())
+    }
+
+    /// Settles conflicts left undecided past the expiry with the fallback.
+    fn expire_conflicts(&self) -> Result<()> {
+        let settings = self.settings.lock().map(|s| s.clone()).unwrap_or_default();
+        let execute = |actions: &[Action],
+                       info: &FileInfo,
+                       captures: &HashMap<String, String>,
+                       scope: &ActionScope| {
+            self.executor.execute_actions(actions, info, captures, scope)
+        };
+        let on_conflict = |conflict: &PendingConflict| {
+            self.executor.notify_pending_conflict(conflict);
+        };
+        ConflictRunner {
+            db: &self.db,
+            settings: &settings,
+            ocr: &self.ocr,
+            duplicates: &self.duplicates,
+            execute: &execute,
+            on_conflict: &on_conflict,
+        }
+        .expire(Utc::now())?;
+        Ok(())
+    }
+}
+
+/// Queues the files of every watched folder for the engine each
+/// `scan_interval_minutes`, so files that were already there when a rule was
+/// added get processed too. Events go through the backlog, taking turns with
+/// live ones, and run the folder's rules like any other event. Files matched
+/// since they last changed are left out; the engine skips the rules that
+/// already matched the rest by hash.
+pub struct FolderScans {
+    db: Database,
+    backlog: Sender<FileEvent>,
+    settings: Arc<Mutex<Settings>>,
+    paused: Arc<AtomicBool>,
+    last_scan: Mutex<Option<Instant>>,
+}
+
+impl FolderScans {
+    pub fn new(
+        db: Database,
+        backlog: Sender<FileEvent>,
+        settings: Arc<Mutex<Settings>>,
+        paused: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            db,
+            backlog,
+            settings,
+            paused,
+            last_scan: Mutex::new(None),
+        }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        thread::spawn(move || loop {
+            if let Err(err) = self.tick() {
+                eprintln!("Folder scan error: {err}");
+            }
+            thread::sleep(TICK);
+        });
+    }
+
+    fn tick(&self) -> Result<()> {
+        let minutes = self
+            .settings
+            .lock()
+            .map(|s| s.scan_interval_minutes)
+            .unwrap_or_default();
+        if minutes == 0 || self.paused.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let interval = Duration::from_secs(u64::from(minutes) * 60);
+        {
+            let mut last_scan = self.last_scan.lock().unwrap_or_else(|e| e.into_inner());
+            match *last_scan {
+                // The first tick only arms the interval; launching already
+                // catches up on what changed while the app was closed.
+                None => {
+                    *last_scan = Some(Instant::now());
+                    return Ok(());
+                }
+                Some(last) if last.elapsed() < interval => return Ok(()),
+                Some(_) => *last_scan = Some(Instant::now()),
+            }
+        }
+
+        let rule_repo = RuleRepository::new(self.db.clone());
+        for folder in FolderRepository::new(self.db.clone()).list()? {
+            if self.paused.load(Ordering::SeqCst) {
+                break;
+            }
+            if !folder.enabled || !folder.is_directory() {
+                continue;
+            }
+            if !rule_repo
+                .list_by_folder(&folder.id)?
+                .iter()
+                .any(|rule| rule.enabled)
+            {
+                continue;
+            }
+            if let Err(err) = self.scan(&folder, || false) {
+                eprintln!("Folder scan failed for {}: {err}", folder.name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues the files of `folder` the rules haven't seen since they last
+    /// changed, stopping early while paused or once `stop` says so. Returns
+    /// how many were queued.
+    pub fn scan(&self, folder: &Folder, stop: impl Fn() -> bool) -> Result<usize> {
+        let root = normalize_user_path(&folder.path);
+        if !root.is_dir() {
+            return Err(anyhow!("Folder is unavailable: {}", root.display()));
+        }
+        let ignore = self
+            .settings
+            .lock()
+            .map(|s| compile_patterns(&s.ignore_patterns))
+            .unwrap_or_default();
+        let passthrough = compile_patterns(&folder.effective_passthrough_patterns());
+        let options = WalkOptions {
+            max_depth: folder.max_depth().unwrap_or(usize::MAX),
+            ..WalkOptions::default()
+        };
+        let walk_root = root.clone();
+        let skip = move |path: &Path| {
+            should_ignore(path, &ignore) || is_passthrough(path, &walk_root, &passthrough)
+        };
+        let stopped = || self.paused.load(Ordering::SeqCst) || stop();
+
+        let match_repo = MatchRepository::new(self.db.clone());
+        let mut queued = 0;
+        for path in walk_files(&root, options, skip).flatten() {
+            if stopped() {
+                break;
+            }
+            let Some(changed) = std::fs::metadata(&path)
+                .ok()
+                .and_then(|meta| changed_at(&meta))
+            else {
+                continue;
+            };
+            let last_match = match_repo.get_last_match_time(&path.to_string_lossy())?;
+            if last_match.is_some_and(|matched| matched >= DateTime::<Utc>::from(changed)) {
+                continue;
+            }
+            let mut event = FileEvent {
+                path,
+                folder_id: folder.id.clone(),
+                kind: FileEventKind::Created,
+                rule_id: None,
+                arrival: None,
+                active_app: None,
+                source: EventSource::Scan,
+            };
+            loop {
+                match self.backlog.send_timeout(event, SEND_POLL) {
+                    Ok(()) => break,
+                    Err(SendTimeoutError::Timeout(_)) if stopped() => return Ok(queued),
+                    Err(SendTimeoutError::Timeout(unsent)) => event = unsent,
+                    Err(SendTimeoutError::Disconnected(_)) => {
+                        return Err(anyhow!("The engine has stopped"));
+                    }
+                }
+            }
+            queued += 1;
+        }
+        Ok(queued)
+    }
+}
+
+/// Tracks the next due time of each scheduled rule. A rule seen for the first
+/// time (or whose schedule changed) is armed for its next occurrence rather
+/// than run immediately, so restarting the app doesn't trigger every rule.
+#[derive(Default)]
+pub struct ScheduleTracker {
+    next_runs: HashMap<RuleId, (RuleSchedule, NaiveDateTime)>,
+}
+
+impl ScheduleTracker {
+    /// Returns the ids of rules due at `now` and re-arms them.
+    pub fn due(&mut self, rules: &[Rule], now: NaiveDateTime) -> Vec<RuleId> {
+        self.next_runs
+            .retain(|id, _| rules.iter().any(|rule| &rule.id == id && rule.schedule.is_some()));
+
+        let mut due = Vec::new();
+        for rule in rules {
+            let Some(schedule) = &rule.schedule else {
+                continue;
+            };
+            match self.next_runs.get(&rule.id) {
+                Some((armed, next)) if armed == schedule => {
+                    if *next <= now {
+                        due.push(rule.id.clone());
+                        self.arm(&rule.id, schedule, now);
+                    }
+                }
+                _ => self.arm(&rule.id, schedule, now),
+            }
+        }
+        due
+    }
+
+    fn arm(&mut self, rule_id: &str, schedule: &RuleSchedule, now: NaiveDateTime) {
+        match next_run(schedule, now) {
+            Some(next) => {
+                self.next_runs
+                    .insert(rule_id.to_string(), (schedule.clone(), next));
+            }
+            None => {
+                self.next_runs.remove(rule_id);
+            }
+        }
+    }
+}
+
+/// The first occurrence of `schedule` strictly after `after`, or `None` for a
+/// schedule that can never fire (zero interval, unparseable time).
+pub fn next_run(schedule: &RuleSchedule, after: NaiveDateTime) -> Option<NaiveDateTime> {
+    match schedule {
+        RuleSchedule::Interval { minutes } => {
+            if *minutes == 0 {
+                return None;
+            }
+            Some(after + chrono::Duration::minutes(i64::from(*minutes)))
+        }
+        RuleSchedule::Daily { time } => {
+            let time = NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()?;
+            let today = after.date().and_time(time);
+            if today > after {
+                Some(today)
+            } else {
+                Some(today + chrono::Duration::days(1))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ConditionGroup, MatchType};
+    use chrono::NaiveDate;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn at(h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, 13)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    fn scheduled_rule(id: &str, schedule: Option<RuleSchedule>) -> Rule {
+        Rule {
+            id: id.to_string(),
+            folder_id: "folder".to_string(),
+            name: id.to_string(),
+            enabled: true,
+            stop_processing: false,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![],
+            },
+            actions: vec![],
+            position: 0,
+            schedule,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn interval_rule_fires_once_per_interval() {
+        let rules = vec![scheduled_rule(
+            "temp",
+            Some(RuleSchedule::Interval { minutes: 60 }),
+        )];
+        let mut tracker = ScheduleTracker::default();
+
+        // First sighting only arms the rule.
+        assert!(tracker.due(&rules, at(9, 0)).is_empty());
+        assert!(tracker.due(&rules, at(9, 59)).is_empty());
+        assert_eq!(tracker.due(&rules, at(10, 0)), vec!["temp".to_string()]);
+        // Re-armed from the run time, so the next tick doesn't fire again.
+        assert!(tracker.due(&rules, at(10, 0)).is_empty());
+        assert!(tracker.due(&rules, at(10, 30)).is_empty());
+        assert_eq!(tracker.due(&rules, at(11, 5)), vec!["temp".to_string()]);
+    }
+
+    #[test]
+    fn changed_schedule_rearms_instead_of_firing() {
+        let mut tracker = ScheduleTracker::default();
+        let hourly = vec![scheduled_rule(
+            "temp",
+            Some(RuleSchedule::Interval { minutes: 60 }),
+        )];
+        assert!(tracker.due(&hourly, at(9, 0)).is_empty());
+
+        let every_five = vec![scheduled_rule(
+            "temp",
+            Some(RuleSchedule::Interval { minutes: 5 }),
+        )];
+        assert!(tracker.due(&every_five, at(10, 0)).is_empty());
+        assert_eq!(tracker.due(&every_five, at(10, 5)), vec!["temp".to_string()]);
+    }
+
+    #[test]
+    fn unscheduled_and_zero_interval_rules_never_fire() {
+        let rules = vec![
+            scheduled_rule("plain", None),
+            scheduled_rule("zero", Some(RuleSchedule::Interval { minutes: 0 })),
+        ];
+        let mut tracker = ScheduleTracker::default();
+        assert!(tracker.due(&rules, at(9, 0)).is_empty());
+        assert!(tracker.due(&rules, at(23, 0)).is_empty());
+    }
+
+    #[test]
+    fn daily_schedule_picks_next_occurrence() {
+        let nightly = RuleSchedule::Daily {
+            time: "02:00".to_string(),
+        };
+        assert_eq!(next_run(&nightly, at(1, 0)), Some(at(2, 0)));
+        assert_eq!(
+            next_run(&nightly, at(2, 0)),
+            Some(at(2, 0) + chrono::Duration::days(1))
+        );
+        assert_eq!(
+            next_run(
+                &RuleSchedule::Daily {
+                    time: "late".to_string()
+                },
+                at(1, 0)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn folder_scan_queues_files_not_matched_since_they_changed() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let root = dir.path().join("inbox");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.txt"), b"a").unwrap();
+        fs::write(root.join("sub/b.txt"), b"b").unwrap();
+        fs::write(root.join("done.txt"), b"done").unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&root.to_string_lossy(), "Inbox")
+            .unwrap();
+        let rule = RuleRepository::new(db.clone())
+            .create(Rule {
+                folder_id: folder.id.clone(),
+                ..scheduled_rule("", None)
+            })
+            .unwrap();
+        let done = root.join("done.txt").to_string_lossy().to_string();
+        MatchRepository::new(db.clone())
+            .record_match(&rule.id, &done, None)
+            .unwrap();
+
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let paused = Arc::new(AtomicBool::new(false));
+        let scans = FolderScans::new(db, tx, Arc::default(), paused.clone());
+        assert_eq!(scans.scan(&folder, || false).unwrap(), 2);
+        let mut queued: Vec<_> = rx
+            .try_iter()
+            .inspect(|event| assert_eq!(event.source, EventSource::Scan))
+            .map(|event| event.path)
+            .collect();
+        queued.sort();
+        assert_eq!(queued, vec![root.join("a.txt"), root.join("sub/b.txt")]);
+
+        paused.store(true, Ordering::SeqCst);
+        assert_eq!(scans.scan(&folder, || false).unwrap(), 0);
+    }
+}
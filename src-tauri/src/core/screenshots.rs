@@ -0,0 +1,165 @@
+//! Gathers what `filedispatch_core::screenshot` weighs beyond a file's name:
+//! the mark macOS puts on screen captures, the image's format, size and
+//! EXIF, and the sizes of the displays. Only headers and metadata are read,
+//! never the pixels.
+
+use std::path::Path;
+use std::sync::RwLock;
+
+use filedispatch_core::screenshot::{has_camera_tags, ScreenshotEvidence};
+use image::{ImageDecoder, ImageFormat, ImageReader};
+
+/// Display sizes in pixels, as the system reported them at startup.
+static DISPLAY_SIZES: RwLock<Vec<(u32, u32)>> = RwLock::new(Vec::new());
+
+pub fn set_display_sizes(sizes: Vec<(u32, u32)>) {
+    if let Ok(mut current) = DISPLAY_SIZES.write() {
+        *current = sizes;
+    }
+}
+
+/// What the system and the image say about whether it's a screenshot. The
+/// system's mark settles it, so the image isn't opened when it's there.
+pub fn gather_evidence(path: &Path) -> ScreenshotEvidence {
+    let mut evidence = ScreenshotEvidence {
+        system_mark: has_screen_capture_mark(path),
+        ..ScreenshotEvidence::default()
+    };
+    if evidence.system_mark {
+        return evidence;
+    }
+    evidence.display_sizes = DISPLAY_SIZES
+        .read()
+        .map(|sizes| sizes.clone())
+        .unwrap_or_default();
+    let Ok(reader) = ImageReader::open(path).and_then(|reader| reader.with_guessed_format()) else {
+        return evidence;
+    };
+    evidence.png = reader.format() == Some(ImageFormat::Png);
+    if let Ok(mut decoder) = reader.into_decoder() {
+        evidence.dimensions = Some(decoder.dimensions());
+        evidence.camera_exif = decoder
+            .exif_metadata()
+            .ok()
+            .flatten()
+            .is_some_and(|exif| has_camera_tags(&exif));
+    }
+    evidence
+}
+
+/// Spotlight's `kMDItemIsScreenCapture`, which macOS sets on the files its
+/// screenshot tool saves. It's kept as a binary plist holding one boolean.
+#[cfg(target_os = "macos")]
+fn has_screen_capture_mark(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    const NAME: &str = "com.apple.metadata:kMDItemIsScreenCapture";
+    let (Ok(path), Ok(name)) = (
+        CString::new(path.as_os_str().as_bytes()),
+        CString::new(NAME),
+    ) else {
+        return false;
+    };
+    let mut buf = [0u8; 64];
+    let len = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+            0,
+            0,
+        )
+    };
+    // "bplist00" followed by the only object: 0x09 is true, 0x08 false.
+    len > 8 && buf.starts_with(b"bplist00") && buf[8] == 0x09
+}
+
+/// Other systems keep no such mark.
+#[cfg(not(target_os = "macos"))]
+fn has_screen_capture_mark(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Saves a JPEG whose EXIF names a camera maker, as a phone's would.
+    pub(crate) fn write_camera_jpeg(path: &Path, width: u32, height: u32) {
+        image::RgbImage::new(width, height).save(path).unwrap();
+        let mut tiff = vec![0x49, 0x49, 42, 0, 8, 0, 0, 0, 1, 0];
+        tiff.extend_from_slice(&0x010fu16.to_le_bytes());
+        tiff.extend_from_slice(&[2, 0, 4, 0, 0, 0, b'A', b'c', b'm', 0, 0, 0, 0, 0]);
+        insert_exif(path, &tiff);
+    }
+
+    /// Puts an EXIF chunk, starting at its TIFF header, in the JPEG at `path`.
+    pub(crate) fn insert_exif(path: &Path, tiff: &[u8]) {
+        let mut app1 = vec![0xff, 0xe1];
+        app1.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        app1.extend_from_slice(b"Exif\0\0");
+        app1.extend_from_slice(tiff);
+        let mut bytes = std::fs::read(path).unwrap();
+        bytes.splice(2..2, app1);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn reads_format_size_and_camera_exif() {
+        let dir = tempdir().unwrap();
+        let pasted = dir.path().join("image.png");
+        image::RgbImage::new(1920, 1080).save(&pasted).unwrap();
+        let evidence = gather_evidence(&pasted);
+        assert!(evidence.png && !evidence.camera_exif && !evidence.system_mark);
+        assert_eq!(evidence.dimensions, Some((1920, 1080)));
+
+        let photo = dir.path().join("IMG_2041.jpg");
+        write_camera_jpeg(&photo, 1920, 1080);
+        let evidence = gather_evidence(&photo);
+        assert!(!evidence.png && evidence.camera_exif);
+        assert_eq!(evidence.dimensions, Some((1920, 1080)));
+
+        let missing = gather_evidence(&dir.path().join("gone.png"));
+        assert_eq!(missing.dimensions, None);
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn the_macos_screen_capture_mark_settles_it() {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("IMG_2041.jpg");
+        write_camera_jpeg(&path, 640, 480);
+        assert!(!gather_evidence(&path).system_mark);
+
+        // A binary plist holding just `true`.
+        let mut plist = b"bplist00\x09\x08".to_vec();
+        plist.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+        plist.extend_from_slice(&1u64.to_be_bytes());
+        plist.extend_from_slice(&0u64.to_be_bytes());
+        plist.extend_from_slice(&9u64.to_be_bytes());
+        let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        let name = CString::new("com.apple.metadata:kMDItemIsScreenCapture").unwrap();
+        let result = unsafe {
+            libc::setxattr(
+                c_path.as_ptr(),
+                name.as_ptr(),
+                plist.as_ptr().cast(),
+                plist.len(),
+                0,
+                0,
+            )
+        };
+        assert_eq!(result, 0);
+
+        let evidence = gather_evidence(&path);
+        assert!(evidence.system_mark);
+        // The image wasn't opened.
+        assert_eq!(evidence.dimensions, None);
+    }
+}
@@ -0,0 +1,660 @@
+//! Work kept out of app setup so the window answers right away. It runs on a
+//! thread of its own in phases, each reported as `startup://progress`, and
+//! stops between items once the app quits. Files that changed while the app
+//! was closed go to the engine as a backlog, which takes turns with live
+//! events rather than holding them up.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crossbeam_channel::{Receiver, SendTimeoutError, Sender};
+use glob::Pattern;
+use tauri::{AppHandle, Emitter};
+
+use crate::core::health::{self, CheckDepth};
+use crate::core::relink::mark_missing_folders;
+use crate::core::state::AppState;
+use crate::core::thumbnails::ThumbnailCache;
+use crate::core::watcher::{
+    compile_patterns, is_passthrough, should_ignore, FileEvent, FileEventKind, WatcherService,
+};
+use crate::models::{EngineStatus, EventSource, Folder, Settings, StartupPhase, StartupProgress};
+use crate::storage::approval_repo::ApprovalRepository;
+use crate::storage::database::Database;
+use crate::storage::folder_repo::FolderRepository;
+use crate::storage::heartbeat_repo::HeartbeatRepository;
+use crate::storage::log_repo::LogRepository;
+use crate::utils::platform::normalize_user_path;
+use crate::utils::tempfiles;
+use crate::utils::walk::{walk_files, WalkOptions};
+
+pub const STARTUP_PROGRESS_EVENT: &str = "startup://progress";
+
+/// Backlog events the engine takes for each live one while both are waiting.
+pub const BACKLOG_PER_LIVE: usize = 4;
+
+/// Progress is reported every this many items within a phase.
+const PROGRESS_EVERY: usize = 250;
+
+/// How often the heartbeat is written once the backlog has drained.
+const HEARTBEAT: Duration = Duration::from_secs(60);
+
+/// How long a full backlog queue is waited on before checking for quit.
+const SEND_POLL: Duration = Duration::from_millis(200);
+
+/// File times can be coarser than the heartbeat's.
+const CATCH_UP_SLACK: Duration = Duration::from_secs(2);
+
+/// Set when the app quits; heavy phases check it between items.
+#[derive(Clone, Default)]
+pub struct StartupCancel(Arc<AtomicBool>);
+
+impl StartupCancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Handed to a running phase to count its items and check for quit.
+pub struct PhaseProgress<'a> {
+    phase: StartupPhase,
+    processed: usize,
+    cancel: &'a StartupCancel,
+    report: &'a mut dyn FnMut(StartupProgress),
+}
+
+impl PhaseProgress<'_> {
+    pub fn advance(&mut self, items: usize) {
+        let before = self.processed / PROGRESS_EVERY;
+        self.processed += items;
+        if self.processed / PROGRESS_EVERY > before {
+            self.report(false);
+        }
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    fn report(&mut self, finished: bool) {
+        (self.report)(StartupProgress {
+            phase: self.phase,
+            processed: self.processed,
+            finished,
+        });
+    }
+}
+
+pub type PhaseFn = Box<dyn FnOnce(&mut PhaseProgress<'_>) -> Result<()> + Send>;
+
+/// Runs `phases` in order and reports `Ready` after the last. A failing
+/// phase is logged and the next one runs anyway. Returns false when the app
+/// quit before they were done.
+pub fn run_phases(
+    phases: Vec<(StartupPhase, PhaseFn)>,
+    cancel: &StartupCancel,
+    report: &mut dyn FnMut(StartupProgress),
+) -> bool {
+    for (phase, run) in phases {
+        if cancel.is_cancelled() {
+            return false;
+        }
+        let mut progress = PhaseProgress {
+            phase,
+            processed: 0,
+            cancel,
+            report: &mut *report,
+        };
+        progress.report(false);
+        if let Err(err) = run(&mut progress) {
+            eprintln!("Startup phase {phase:?} failed: {err}");
+        }
+        progress.report(true);
+    }
+    if cancel.is_cancelled() {
+        return false;
+    }
+    report(StartupProgress {
+        phase: StartupPhase::Ready,
+        processed: 0,
+        finished: true,
+    });
+    true
+}
+
+/// Starts `phases` on their own thread and returns straight away.
+pub fn spawn_phases(
+    phases: Vec<(StartupPhase, PhaseFn)>,
+    cancel: StartupCancel,
+    mut report: impl FnMut(StartupProgress) + Send + 'static,
+) -> JoinHandle<bool> {
+    thread::spawn(move || run_phases(phases, &cancel, &mut report))
+}
+
+/// Everything launch used to do in setup besides opening the database and
+/// loading settings.
+pub struct StartupTasks {
+    db: Database,
+    settings: Arc<Mutex<Settings>>,
+    watcher: Arc<Mutex<WatcherService>>,
+    engine_status: Arc<Mutex<EngineStatus>>,
+    backlog: Sender<FileEvent>,
+    cancel: StartupCancel,
+}
+
+impl StartupTasks {
+    pub fn new(state: &AppState, backlog: Sender<FileEvent>, cancel: StartupCancel) -> Self {
+        Self {
+            db: state.db.clone(),
+            settings: state.settings.clone(),
+            watcher: state.watcher.clone(),
+            engine_status: state.engine_status.clone(),
+            backlog,
+            cancel,
+        }
+    }
+
+    /// Runs the phases in the background, then keeps the heartbeat that the
+    /// next launch catches up from.
+    pub fn start(self, app: AppHandle) {
+        let settings = self.settings.lock().map(|s| s.clone()).unwrap_or_default();
+        let folders = FolderRepository::new(self.db.clone())
+            .list()
+            .unwrap_or_default();
+        let folders = Arc::new(folders);
+        let heartbeat = HeartbeatRepository::new(self.db.clone());
+        let since = heartbeat.last_seen().unwrap_or(None);
+
+        let phases = self.phases(settings, folders, since);
+        let status = self.engine_status.clone();
+        let phases_done = spawn_phases(phases, self.cancel.clone(), move |progress| {
+            if let Ok(mut status) = status.lock() {
+                status.startup = Some(progress.clone());
+                status.updated_at = Utc::now();
+            }
+            let _ = app.emit(STARTUP_PROGRESS_EVENT, progress);
+        });
+
+        let backlog = self.backlog;
+        let cancel = self.cancel;
+        thread::spawn(move || {
+            if !phases_done.join().unwrap_or(false) {
+                return;
+            }
+            // Until the engine has worked through the backlog, a restart
+            // should catch up from the same point.
+            while !cancel.is_cancelled() {
+                if backlog.is_empty() {
+                    if let Err(err) = heartbeat.touch(Utc::now()) {
+                        eprintln!("Heartbeat error: {err}");
+                    }
+                }
+                thread::sleep(HEARTBEAT);
+            }
+        });
+    }
+
+    fn phases(
+        &self,
+        settings: Settings,
+        folders: Arc<Vec<Folder>>,
+        since: Option<DateTime<Utc>>,
+    ) -> Vec<(StartupPhase, PhaseFn)> {
+        let db = self.db.clone();
+        let cleanup_settings = settings.clone();
+        let cleanup: PhaseFn = Box::new(move |progress| {
+            LogRepository::new(db.clone()).cleanup(cleanup_settings.log_retention_days)?;
+            progress.advance(1);
+            ApprovalRepository::new(db).cleanup_expired(cleanup_settings.approval_expiry_hours)?;
+            progress.advance(1);
+            // The limit may have been lowered since thumbnails were last made.
+            if let Ok(thumbnails) = ThumbnailCache::from_settings(&cleanup_settings) {
+                progress.advance(thumbnails.trim(None)?);
+            }
+            Ok(())
+        });
+
+        let status = self.engine_status.clone();
+        let swept = folders.clone();
+        let sweep: PhaseFn = Box::new(move |progress| {
+            mark_missing_folders(&status, &swept);
+            // Clear out temp artifacts a crash left behind before watching starts.
+            let dirs: Vec<_> = swept
+                .iter()
+                .filter(|f| f.is_directory())
+                .map(|f| normalize_user_path(&f.path))
+                .collect();
+            progress.advance(tempfiles::app_temp().sweep(&dirs));
+            Ok(())
+        });
+
+        let db = self.db.clone();
+        let status = self.engine_status.clone();
+        // A broken rule or missing folder shows up in the status instead of
+        // as a stream of per-event errors.
+        let health: PhaseFn = Box::new(move |progress| {
+            let report = health::check(&db, CheckDepth::Quick)?;
+            progress.advance(report.issues.len());
+            if let Ok(mut status) = status.lock() {
+                status.health_issues = report.issues;
+                status.updated_at = report.checked_at;
+            }
+            Ok(())
+        });
+
+        let watcher = self.watcher.clone();
+        let watched = folders.clone();
+        let ignore_patterns = settings.ignore_patterns.clone();
+        let watch: PhaseFn = Box::new(move |progress| {
+            let Ok(mut watcher) = watcher.lock() else {
+                return Ok(());
+            };
+            watcher.set_ignore_patterns(ignore_patterns);
+            for folder in watched.iter().filter(|f| f.enabled && f.is_directory()) {
+                if progress.cancelled() {
+                    break;
+                }
+                let normalized = normalize_user_path(&folder.path);
+                let _ = watcher.watch_folder(
+                    normalized,
+                    folder.id.clone(),
+                    folder.scan_depth,
+                    &folder.effective_passthrough_patterns(),
+                );
+                progress.advance(1);
+            }
+            Ok(())
+        });
+
+        let backlog = self.backlog.clone();
+        let ignore: Vec<Pattern> = settings
+            .ignore_patterns
+            .iter()
+            .filter_map(|p| Pattern::new(p).ok())
+            .collect();
+        let catch_up: PhaseFn = Box::new(move |progress| {
+            // The first launch has nothing to catch up on.
+            let Some(since) = since else {
+                return Ok(());
+            };
+            let cutoff = SystemTime::from(since) - CATCH_UP_SLACK;
+            for folder in folders.iter().filter(|f| f.enabled && f.is_directory()) {
+                let root = normalize_user_path(&folder.path);
+                let depth = folder.max_depth().unwrap_or(usize::MAX);
+                let passthrough = compile_patterns(&folder.effective_passthrough_patterns());
+                let queued = queue_changed_files(
+                    &root,
+                    &folder.id,
+                    depth,
+                    cutoff,
+                    &ignore,
+                    &passthrough,
+                    &backlog,
+                    progress,
+                );
+                if !queued {
+                    break;
+                }
+            }
+            Ok(())
+        });
+
+        vec![
+            (StartupPhase::Cleanup, cleanup),
+            (StartupPhase::Sweep, sweep),
+            (StartupPhase::Health, health),
+            (StartupPhase::Watch, watch),
+            (StartupPhase::CatchUp, catch_up),
+        ]
+    }
+}
+
+/// Queues a `Created` event for each file under `root` changed at or after
+/// `cutoff`, leaving out ignored and passthrough files. Returns false when the
+/// app quit or the engine went away first.
+#[allow(clippy::too_many_arguments)]
+pub fn queue_changed_files(
+    root: &Path,
+    folder_id: &str,
+    max_depth: usize,
+    cutoff: SystemTime,
+    ignore: &[Pattern],
+    passthrough: &[Pattern],
+    backlog: &Sender<FileEvent>,
+    progress: &mut PhaseProgress<'_>,
+) -> bool {
+    let ignore = ignore.to_vec();
+    let passthrough = passthrough.to_vec();
+    let walk_root = root.to_path_buf();
+    let options = WalkOptions {
+        max_depth,
+        ..WalkOptions::default()
+    };
+    let skip = move |path: &Path| {
+        should_ignore(path, &ignore) || is_passthrough(path, &walk_root, &passthrough)
+    };
+    for path in walk_files(root, options, skip).flatten() {
+        if progress.cancelled() {
+            return false;
+        }
+        let Some(changed) = std::fs::metadata(&path)
+            .ok()
+            .and_then(|meta| changed_at(&meta))
+        else {
+            continue;
+        };
+        if changed < cutoff {
+            continue;
+        }
+        let event = FileEvent {
+            path,
+            folder_id: folder_id.to_string(),
+            kind: FileEventKind::Created,
+            rule_id: None,
+            arrival: None,
+            active_app: None,
+            source: EventSource::Catchup,
+        };
+        if !send_backlog(backlog, event, progress.cancel) {
+            return false;
+        }
+        progress.advance(1);
+    }
+    true
+}
+
+/// The latest of a file's times. Moving a file keeps its modified time, so
+/// the inode change time counts too where there is one.
+pub(crate) fn changed_at(meta: &std::fs::Metadata) -> Option<SystemTime> {
+    let mut times = vec![meta.modified().ok(), meta.created().ok()];
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let ctime = u64::try_from(meta.ctime()).ok().map(Duration::from_secs);
+        times.push(ctime.map(|secs| SystemTime::UNIX_EPOCH + secs));
+    }
+    times.into_iter().flatten().max()
+}
+
+/// Blocks while the backlog is full, giving up when the app quits.
+fn send_backlog(backlog: &Sender<FileEvent>, mut event: FileEvent, cancel: &StartupCancel) -> bool {
+    loop {
+        match backlog.send_timeout(event, SEND_POLL) {
+            Ok(()) => return true,
+            Err(SendTimeoutError::Timeout(unsent)) if !cancel.is_cancelled() => event = unsent,
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Merges live watcher events with the startup backlog. While both have
+/// events waiting, each live one is followed by up to `BACKLOG_PER_LIVE`
+/// from the backlog, so new files aren't stuck behind old ones.
+pub struct EventMux {
+    live: Receiver<FileEvent>,
+    backlog: Receiver<FileEvent>,
+    live_open: bool,
+    backlog_open: bool,
+    /// Backlog events taken since the last live one.
+    streak: usize,
+}
+
+impl EventMux {
+    pub fn new(live: Receiver<FileEvent>, backlog: Receiver<FileEvent>) -> Self {
+        Self {
+            live,
+            backlog,
+            live_open: true,
+            backlog_open: true,
+            // Live events go first.
+            streak: BACKLOG_PER_LIVE,
+        }
+    }
+
+    /// The next event, waiting for one if need be. `None` once both queues
+    /// are closed and empty.
+    pub fn next_event(&mut self) -> Option<FileEvent> {
+        loop {
+            let live_first = self.streak >= BACKLOG_PER_LIVE;
+            for live in [live_first, !live_first] {
+                let receiver = if live { &self.live } else { &self.backlog };
+                if let Ok(event) = receiver.try_recv() {
+                    return Some(self.took(live, event));
+                }
+            }
+            if !self.live_open && !self.backlog_open {
+                return None;
+            }
+            let (live, backlog) = (self.live.clone(), self.backlog.clone());
+            crossbeam_channel::select! {
+                recv(live) -> event => match event {
+                    Ok(event) => return Some(self.took(true, event)),
+                    Err(_) => self.close(true),
+                },
+                recv(backlog) -> event => match event {
+                    Ok(event) => return Some(self.took(false, event)),
+                    Err(_) => self.close(false),
+                },
+            }
+        }
+    }
+
+    /// Whether the event `next_event` returned last came from the live queue
+    /// rather than the backlog.
+    pub fn last_was_live(&self) -> bool {
+        self.streak == 0
+    }
+
+    fn took(&mut self, live: bool, event: FileEvent) -> FileEvent {
+        self.streak = if live {
+            0
+        } else {
+            self.streak.saturating_add(1)
+        };
+        event
+    }
+
+    /// A closed queue is swapped for one that never delivers, so `select!`
+    /// stops waking up for it.
+    fn close(&mut self, live: bool) {
+        if live {
+            self.live_open = false;
+            self.live = crossbeam_channel::never();
+        } else {
+            self.backlog_open = false;
+            self.backlog = crossbeam_channel::never();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Instant;
+    use tempfile::tempdir;
+
+    fn event(folder_id: &str, n: usize) -> FileEvent {
+        FileEvent {
+            path: PathBuf::from(format!("/{folder_id}/{n}")),
+            folder_id: folder_id.to_string(),
+            kind: FileEventKind::Created,
+            rule_id: None,
+            arrival: None,
+            active_app: None,
+            source: EventSource::Live,
+        }
+    }
+
+    fn no_op() -> PhaseFn {
+        Box::new(|_| Ok(()))
+    }
+
+    #[test]
+    fn live_events_interleave_with_the_backlog() {
+        let (live_tx, live_rx) = crossbeam_channel::unbounded();
+        let (backlog_tx, backlog_rx) = crossbeam_channel::unbounded();
+        for n in 0..5_000 {
+            backlog_tx.send(event("backlog", n)).unwrap();
+        }
+        for n in 0..100 {
+            live_tx.send(event("live", n)).unwrap();
+        }
+        drop(backlog_tx);
+
+        let mut mux = EventMux::new(live_rx, backlog_rx);
+        let first: Vec<_> = (0..500)
+            .map(|_| mux.next_event().unwrap().folder_id)
+            .collect();
+        for chunk in first.chunks(BACKLOG_PER_LIVE + 1) {
+            assert_eq!(chunk[0], "live");
+            assert!(chunk[1..].iter().all(|source| source == "backlog"));
+        }
+
+        // Once the live queue is empty the backlog flows freely, and a new
+        // live event jumps ahead of what's left of it.
+        for _ in 0..100 {
+            assert_eq!(mux.next_event().unwrap().folder_id, "backlog");
+            assert!(!mux.last_was_live());
+        }
+        live_tx.send(event("live", 100)).unwrap();
+        assert_eq!(mux.next_event().unwrap().folder_id, "live");
+        assert!(mux.last_was_live());
+
+        drop(live_tx);
+        let rest = std::iter::from_fn(|| mux.next_event()).count();
+        assert_eq!(rest, 5_000 - 400 - 100);
+    }
+
+    #[test]
+    fn phases_report_in_order_and_finish_with_ready() {
+        let mut reports = Vec::new();
+        let counting: PhaseFn = Box::new(|progress| {
+            progress.advance(PROGRESS_EVERY + 1);
+            Ok(())
+        });
+        let failing: PhaseFn = Box::new(|_| anyhow::bail!("no database"));
+        let done = run_phases(
+            vec![
+                (StartupPhase::Cleanup, no_op()),
+                (StartupPhase::Health, failing),
+                (StartupPhase::CatchUp, counting),
+            ],
+            &StartupCancel::default(),
+            &mut |progress| reports.push(progress),
+        );
+        assert!(done);
+
+        let seen: Vec<_> = reports
+            .iter()
+            .map(|p| (p.phase, p.processed, p.finished))
+            .collect();
+        assert_eq!(
+            seen,
+            vec![
+                (StartupPhase::Cleanup, 0, false),
+                (StartupPhase::Cleanup, 0, true),
+                (StartupPhase::Health, 0, false),
+                (StartupPhase::Health, 0, true),
+                (StartupPhase::CatchUp, 0, false),
+                (StartupPhase::CatchUp, PROGRESS_EVERY + 1, false),
+                (StartupPhase::CatchUp, PROGRESS_EVERY + 1, true),
+                (StartupPhase::Ready, 0, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn spawning_returns_before_slow_phases_run() {
+        let cancel = StartupCancel::default();
+        let waiting = cancel.clone();
+        let slow: PhaseFn = Box::new(move |progress| {
+            while !progress.cancelled() {
+                thread::sleep(Duration::from_millis(5));
+            }
+            Ok(())
+        });
+
+        let started = Instant::now();
+        let handle = spawn_phases(
+            vec![
+                (StartupPhase::Health, no_op()),
+                (StartupPhase::CatchUp, slow),
+            ],
+            cancel,
+            |_| {},
+        );
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        // Quitting stops the sequence without reporting it ready.
+        waiting.cancel();
+        assert!(!handle.join().unwrap());
+    }
+
+    #[test]
+    fn catch_up_queues_files_changed_since_the_cutoff() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a.txt"), b"a").unwrap();
+        fs::write(dir.path().join("sub/b.txt"), b"b").unwrap();
+        fs::write(dir.path().join("skip.tmp"), b"tmp").unwrap();
+        let ignore = [Pattern::new("*.tmp").unwrap()];
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let cancel = StartupCancel::default();
+        let mut report = |_: StartupProgress| {};
+        let mut progress = PhaseProgress {
+            phase: StartupPhase::CatchUp,
+            processed: 0,
+            cancel: &cancel,
+            report: &mut report,
+        };
+        let queue = |cutoff, progress: &mut PhaseProgress<'_>| {
+            queue_changed_files(
+                dir.path(),
+                "inbox",
+                usize::MAX,
+                cutoff,
+                &ignore,
+                &[],
+                &tx,
+                progress,
+            )
+        };
+
+        // Nothing has changed since a cutoff in the future.
+        assert!(queue(
+            SystemTime::now() + Duration::from_secs(3600),
+            &mut progress
+        ));
+        assert_eq!(rx.try_iter().count(), 0);
+
+        assert!(queue(
+            SystemTime::now() - Duration::from_secs(60),
+            &mut progress
+        ));
+        let mut queued: Vec<_> = rx
+            .try_iter()
+            .inspect(|event| assert_eq!(event.source, EventSource::Catchup))
+            .map(|event| event.path)
+            .collect();
+        queued.sort();
+        assert_eq!(
+            queued,
+            vec![dir.path().join("a.txt"), dir.path().join("sub/b.txt")]
+        );
+        assert_eq!(progress.processed, 2);
+
+        cancel.cancel();
+        assert!(!queue(SystemTime::UNIX_EPOCH, &mut progress));
+    }
+}
@@ -1,8 +1,15 @@
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
+use crate::core::command_lanes::CommandLanes;
+use crate::core::duplicates::DuplicateDetector;
+use crate::core::event_journal::EventJournal;
+use crate::core::folder_lanes::FolderLanes;
 use crate::core::watcher::WatcherService;
 use crate::core::ocr::OcrManager;
+use crate::core::references::ReferenceChecks;
+use crate::core::scheduler::FolderScans;
+use crate::core::timings::RuleTimings;
 use crate::models::{EngineStatus, Settings};
 use crate::storage::database::Database;
 
@@ -14,4 +21,13 @@ pub struct AppState {
     pub ocr: Arc<Mutex<OcrManager>>,
     pub paused: Arc<AtomicBool>,
     pub engine_status: Arc<Mutex<EngineStatus>>,
+    pub rule_timings: Arc<RuleTimings>,
+    pub event_journal: Arc<EventJournal>,
+    pub reference_checks: Arc<ReferenceChecks>,
+    pub folder_lanes: Arc<FolderLanes>,
+    pub command_lanes: Arc<CommandLanes>,
+    pub folder_scans: Arc<FolderScans>,
+    /// Shared by the engine, scans and the approval and conflict runners, so
+    /// Is Duplicate conditions remember the same copies everywhere.
+    pub duplicate_detector: Arc<DuplicateDetector>,
 }
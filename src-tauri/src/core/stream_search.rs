@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use filedispatch_core::evaluate::list_entries;
+use regex::bytes::{Regex, RegexBuilder};
+
+use crate::models::{StringCondition, StringOperator};
+
+/// Bytes read from the file per step.
+pub const STREAM_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Bytes of the previous window kept in front of each chunk when matching a
+/// regex. A regex match longer than this that straddles a chunk boundary is
+/// missed.
+pub const REGEX_WINDOW_OVERLAP: usize = 4 * 1024;
+
+/// Result of searching a file without loading it into memory.
+#[derive(Debug, Default)]
+pub struct StreamMatch {
+    pub matched: bool,
+    /// Regex captures, taken from the window the match was found in.
+    pub captures: HashMap<String, String>,
+    /// How many chunks were read before the search finished.
+    #[allow(dead_code)]
+    pub chunks_read: usize,
+}
+
+/// Runs `cond` against the file at `path` chunk by chunk, stopping at the
+/// first match.
+///
+/// `Contains` carries the last needle-length bytes of each window into the
+/// next one, so needles that straddle a chunk boundary are still found.
+/// `StartsWith` reads only the first chunk and `EndsWith` only the last one.
+/// `Matches` runs the regex over bounded windows (see `REGEX_WINDOW_OVERLAP`),
+/// so `^`/`$` refer to the file start and end but a match must fit within a
+/// window. Case-insensitive matching folds case inside the regex engine rather
+/// than lowercasing the file. Globs have to match the whole file, which is
+/// never held at once, so they're an error here.
+pub fn search_file(path: &Path, cond: &StringCondition) -> Result<StreamMatch> {
+    let case_insensitive = !cond.case_sensitive;
+    let literal = |pattern: String| -> Result<Regex> {
+        Ok(RegexBuilder::new(&pattern)
+            .case_insensitive(case_insensitive)
+            .build()?)
+    };
+    let needle = regex::escape(&cond.value);
+    // Case folding can change a character's UTF-8 length, so leave room for
+    // up to four bytes per needle character.
+    let needle_overlap = if cond.case_sensitive {
+        cond.value.len()
+    } else {
+        cond.value.chars().count() * 4
+    };
+
+    let mut file = File::open(path)?;
+    match cond.operator {
+        StringOperator::Contains | StringOperator::DoesNotContain => {
+            let regex = literal(needle)?;
+            let mut found = scan(&mut file, &regex, needle_overlap)?;
+            found.captures.clear();
+            if cond.operator == StringOperator::DoesNotContain {
+                found.matched = !found.matched;
+            }
+            Ok(found)
+        }
+        StringOperator::Matches | StringOperator::DoesNotMatch => {
+            let regex = RegexBuilder::new(&cond.value)
+                .case_insensitive(case_insensitive)
+                .build()?;
+            let mut found = scan(&mut file, &regex, REGEX_WINDOW_OVERLAP)?;
+            if cond.operator == StringOperator::DoesNotMatch {
+                found.matched = !found.matched;
+                found.captures.clear();
+            }
+            Ok(found)
+        }
+        StringOperator::MatchesGlob | StringOperator::DoesNotMatchGlob => {
+            bail!("Glob operators can't search files too large to read whole")
+        }
+        StringOperator::StartsWith => {
+            let regex = literal(format!(r"\A(?:{})", needle))?;
+            let window = read_window(&mut file, STREAM_CHUNK_BYTES)?;
+            Ok(StreamMatch {
+                matched: regex.is_match(&window),
+                captures: HashMap::new(),
+                chunks_read: 1,
+            })
+        }
+        StringOperator::EndsWith => {
+            let regex = literal(format!(r"(?:{})\z", needle))?;
+            let len = file.metadata()?.len();
+            file.seek(SeekFrom::Start(len.saturating_sub(STREAM_CHUNK_BYTES as u64)))?;
+            let window = read_window(&mut file, STREAM_CHUNK_BYTES)?;
+            Ok(StreamMatch {
+                matched: regex.is_match(&window),
+                captures: HashMap::new(),
+                chunks_read: 1,
+            })
+        }
+        StringOperator::Is | StringOperator::IsNot => {
+            // Only a file no longer than the (case-folded) value can equal it.
+            let len = file.metadata()?.len();
+            let equal = if len > needle_overlap.max(cond.value.len()) as u64 {
+                false
+            } else {
+                let regex = literal(format!(r"\A(?:{})\z", needle))?;
+                regex.is_match(&read_window(&mut file, len as usize)?)
+            };
+            Ok(StreamMatch {
+                matched: equal == (cond.operator == StringOperator::Is),
+                captures: HashMap::new(),
+                chunks_read: 1,
+            })
+        }
+        StringOperator::IsOneOf | StringOperator::IsNotOneOf => {
+            // As with `Is`, only a file no longer than a (case-folded) entry can
+            // equal it.
+            let entries: Vec<&str> = list_entries(&cond.value).collect();
+            let longest = entries
+                .iter()
+                .map(|entry| entry.len().max(entry.chars().count() * 4))
+                .max()
+                .unwrap_or(0);
+            let len = file.metadata()?.len();
+            let mut found = None;
+            if len <= longest as u64 {
+                let window = read_window(&mut file, len as usize)?;
+                for entry in entries {
+                    let regex = literal(format!(r"\A(?:{})\z", regex::escape(entry)))?;
+                    if regex.is_match(&window) {
+                        found = Some(entry);
+                        break;
+                    }
+                }
+            }
+            let mut captures = HashMap::new();
+            if let (Some(entry), StringOperator::IsOneOf) = (found, &cond.operator) {
+                captures.insert("one_of".to_string(), entry.to_string());
+            }
+            Ok(StreamMatch {
+                matched: found.is_some() == (cond.operator == StringOperator::IsOneOf),
+                captures,
+                chunks_read: 1,
+            })
+        }
+    }
+}
+
+/// Slides `regex` over the file in `STREAM_CHUNK_BYTES` steps, keeping
+/// `overlap` bytes of the previous window in front of each new chunk.
+fn scan(file: &mut File, regex: &Regex, overlap: usize) -> Result<StreamMatch> {
+    let mut window: Vec<u8> = Vec::with_capacity(STREAM_CHUNK_BYTES + overlap);
+    let mut chunks_read = 0;
+    let mut at_file_start = true;
+
+    loop {
+        let carried = window.len();
+        window.resize(carried + STREAM_CHUNK_BYTES, 0);
+        let read = fill(file, &mut window[carried..])?;
+        window.truncate(carried + read);
+        chunks_read += 1;
+        let at_eof = read < STREAM_CHUNK_BYTES;
+
+        // Anything starting at the very first carried byte lay entirely in
+        // the previous window and was already tried there. Starting one byte
+        // in also keeps `\A` from matching mid-file.
+        let start = if at_file_start { 0 } else { 1.min(window.len()) };
+        if let Some(caps) = regex.captures_at(&window, start) {
+            let whole = caps.get(0).expect("group 0 is always present");
+            // A match touching the window end may only be a prefix of the real
+            // match (or a false `$`), so let the next window decide.
+            if at_eof || whole.end() < window.len() {
+                let captures = caps
+                    .iter()
+                    .enumerate()
+                    .skip(1)
+                    .filter_map(|(i, cap)| {
+                        cap.map(|m| (i.to_string(), String::from_utf8_lossy(m.as_bytes()).to_string()))
+                    })
+                    .collect();
+                return Ok(StreamMatch {
+                    matched: true,
+                    captures,
+                    chunks_read,
+                });
+            }
+        }
+
+        if at_eof {
+            return Ok(StreamMatch {
+                matched: false,
+                captures: HashMap::new(),
+                chunks_read,
+            });
+        }
+        let keep = overlap.min(window.len());
+        window.drain(..window.len() - keep);
+        at_file_start = false;
+    }
+}
+
+fn read_window(file: &mut File, max: usize) -> Result<Vec<u8>> {
+    let mut buf = vec![0; max];
+    let read = fill(file, &mut buf)?;
+    buf.truncate(read);
+    Ok(buf)
+}
+
+/// Reads until `buf` is full or the file ends; returns the byte count.
+fn fill(file: &mut File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match file.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn cond(operator: StringOperator, value: &str, case_sensitive: bool) -> StringCondition {
+        StringCondition {
+            operator,
+            value: value.to_string(),
+            case_sensitive,
+        }
+    }
+
+    fn csv_file(dir: &Path, header: &str, rows: usize) -> std::path::PathBuf {
+        let path = dir.join("export.csv");
+        let mut file = fs::File::create(&path).unwrap();
+        writeln!(file, "{}", header).unwrap();
+        for row in 0..rows {
+            writeln!(file, "{},widget,{}.00", row, row % 97).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn needle_spanning_chunk_boundary_is_found() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("big.txt");
+        let mut bytes = vec![b'x'; STREAM_CHUNK_BYTES - 3];
+        bytes.extend_from_slice(b"NEEDLE");
+        bytes.extend(vec![b'x'; STREAM_CHUNK_BYTES]);
+        fs::write(&path, &bytes).unwrap();
+
+        let found = search_file(&path, &cond(StringOperator::Contains, "needle", false)).unwrap();
+        assert!(found.matched);
+        assert_eq!(found.chunks_read, 2);
+
+        let regex = search_file(&path, &cond(StringOperator::Matches, "NEE(D+)LE", true)).unwrap();
+        assert!(regex.matched);
+        assert_eq!(regex.captures.get("1").map(String::as_str), Some("D"));
+    }
+
+    #[test]
+    fn header_match_stops_after_first_chunk() {
+        let dir = tempdir().unwrap();
+        let path = csv_file(dir.path(), "id,product,invoice_total", 50_000);
+        assert!(fs::metadata(&path).unwrap().len() > 4 * STREAM_CHUNK_BYTES as u64);
+
+        let found =
+            search_file(&path, &cond(StringOperator::Contains, "INVOICE_TOTAL", false)).unwrap();
+        assert!(found.matched);
+        assert_eq!(found.chunks_read, 1);
+
+        let starts = search_file(&path, &cond(StringOperator::StartsWith, "id,product", true)).unwrap();
+        assert!(starts.matched);
+        assert_eq!(starts.chunks_read, 1);
+    }
+
+    #[test]
+    fn does_not_contain_reads_whole_file() {
+        let dir = tempdir().unwrap();
+        let path = csv_file(dir.path(), "id,product,price", 50_000);
+        let len = fs::metadata(&path).unwrap().len() as usize;
+
+        let absent = search_file(&path, &cond(StringOperator::DoesNotContain, "refund", false)).unwrap();
+        assert!(absent.matched);
+        assert_eq!(absent.chunks_read, len / STREAM_CHUNK_BYTES + 1);
+
+        let present = search_file(&path, &cond(StringOperator::DoesNotContain, "widget", true)).unwrap();
+        assert!(!present.matched);
+        assert_eq!(present.chunks_read, 1);
+    }
+
+    #[test]
+    fn anchors_refer_to_file_edges() {
+        let dir = tempdir().unwrap();
+        let path = csv_file(dir.path(), "id,product,price", 20_000);
+
+        assert!(!search_file(&path, &cond(StringOperator::Matches, r"\Awidget", true)).unwrap().matched);
+        assert!(search_file(&path, &cond(StringOperator::EndsWith, "19999,widget,17.00\n", true))
+            .unwrap()
+            .matched);
+        assert!(!search_file(&path, &cond(StringOperator::Is, "id,product,price", true)).unwrap().matched);
+        assert!(search_file(&path, &cond(StringOperator::MatchesGlob, "id,*", true)).is_err());
+        assert!(search_file(&path, &cond(StringOperator::IsNotOneOf, "id,price", true)).unwrap().matched);
+    }
+}
@@ -0,0 +1,376 @@
+//! Small JPEG thumbnails of matched images and PDFs, for notifications and
+//! the activity log. They're cached under the app data dir by the content
+//! hash of the file they show, so a file that's moved or copied keeps its
+//! thumbnail, and the cache is trimmed to `thumbnail_cache_max_mb`, least
+//! recently used first. Anything that goes wrong making one leaves the file
+//! without a thumbnail; it's never an error.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use directories::ProjectDirs;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageReader};
+
+use filedispatch_core::file_info::FileInfo;
+
+use crate::core::content::render_first_pdf_page;
+use crate::core::duplicates::hash_file;
+use crate::core::executor::ActionOutcome;
+use crate::models::{FileKind, Settings};
+
+/// Metadata key holding the cached thumbnail's path in a log entry.
+pub const THUMBNAIL_KEY: &str = "thumbnail";
+
+/// Longest side of a thumbnail, in pixels.
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// Files larger than this aren't decoded for a thumbnail.
+pub const MAX_SOURCE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// How long decoding or rendering one file may take.
+const RENDER_TIMEOUT: Duration = Duration::from_secs(5);
+
+const JPEG_QUALITY: u8 = 80;
+const EXTENSION: &str = "jpg";
+
+/// `thumbnails` in the app data dir.
+pub fn default_thumbnail_dir() -> Result<PathBuf> {
+    let proj = ProjectDirs::from("", "", "file-dispatch")
+        .ok_or_else(|| anyhow!("Unable to resolve data directory"))?;
+    Ok(proj.data_dir().join("thumbnails"))
+}
+
+/// What the cache handed out: the thumbnail, and whether it was there
+/// already.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub path: PathBuf,
+    pub cached: bool,
+}
+
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ThumbnailCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self { dir, max_bytes }
+    }
+
+    /// The cache in the app data dir, bounded as `settings` say.
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        Ok(Self::new(
+            default_thumbnail_dir()?,
+            settings.thumbnail_cache_max_mb.saturating_mul(1024 * 1024),
+        ))
+    }
+
+    /// A thumbnail of the file at `path`, made now unless one of a file with
+    /// the same contents is cached. `None` for files that aren't images or
+    /// PDFs, are too large, or couldn't be drawn in time.
+    pub fn thumbnail(&self, path: &Path, kind: &FileKind, extension: &str) -> Option<Thumbnail> {
+        let pdf = extension.eq_ignore_ascii_case("pdf");
+        if *kind != FileKind::Image && !pdf {
+            return None;
+        }
+        if fs::metadata(path).ok()?.len() > MAX_SOURCE_BYTES {
+            return None;
+        }
+        let hash = hash_file(path).ok()?;
+        let cached = self.dir.join(format!("{hash}.{EXTENSION}"));
+        if cached.is_file() {
+            // The modification time is what eviction goes by.
+            let _ = touch(&cached);
+            return Some(Thumbnail {
+                path: cached,
+                cached: true,
+            });
+        }
+
+        let image = render_within(path.to_path_buf(), pdf, RENDER_TIMEOUT)?;
+        let bytes = encode(&image).ok()?;
+        fs::create_dir_all(&self.dir).ok()?;
+        let partial = cached.with_extension(format!("{EXTENSION}.part"));
+        fs::write(&partial, bytes).ok()?;
+        if fs::rename(&partial, &cached).is_err() {
+            let _ = fs::remove_file(&partial);
+            return None;
+        }
+        let _ = self.trim(Some(&cached));
+        Some(Thumbnail {
+            path: cached,
+            cached: false,
+        })
+    }
+
+    /// The cached thumbnail at `path` as a `data:` URL the UI can show.
+    /// `None` once it's been evicted, or for paths outside the cache.
+    pub fn data_url(&self, path: &Path) -> Option<String> {
+        if path.parent() != Some(self.dir.as_path())
+            || path.extension().is_none_or(|ext| ext != EXTENSION)
+        {
+            return None;
+        }
+        let bytes = fs::read(path).ok()?;
+        Some(format!("data:image/jpeg;base64,{}", STANDARD.encode(bytes)))
+    }
+
+    /// Removes the least recently used thumbnails until the cache fits in
+    /// its limit, sparing `keep`. Returns how many were removed.
+    pub fn trim(&self, keep: Option<&Path>) -> io::Result<usize> {
+        let mut entries = self.entries()?;
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort_by_key(|(_, _, used)| *used);
+        let mut removed = 0;
+        for (path, size, _) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if Some(path.as_path()) == keep {
+                continue;
+            }
+            fs::remove_file(&path)?;
+            total -= size;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    /// Removes every thumbnail. Returns how many there were.
+    pub fn clear(&self) -> io::Result<usize> {
+        let entries = self.entries()?;
+        for (path, _, _) in &entries {
+            fs::remove_file(path)?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Each thumbnail with its size and when it was last used; an interrupted
+    /// write's leftovers count as used long ago.
+    fn entries(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let read = match fs::read_dir(&self.dir) {
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut entries = Vec::new();
+        for entry in read {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let used = if path.extension().is_some_and(|ext| ext == EXTENSION) {
+                metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)
+            } else {
+                SystemTime::UNIX_EPOCH
+            };
+            entries.push((path, metadata.len(), used));
+        }
+        Ok(entries)
+    }
+}
+
+/// The thumbnail of `info` for the activity log, as the path entries record.
+pub fn activity_thumbnail(info: &FileInfo, settings: &Settings) -> Option<String> {
+    let cache = ThumbnailCache::from_settings(settings).ok()?;
+    let thumbnail = cache.thumbnail(&info.path, &info.kind, &info.extension)?;
+    Some(thumbnail.path.to_string_lossy().to_string())
+}
+
+/// Records `thumbnail` in the details of each outcome that has them, unless
+/// the action recorded one of its own.
+pub fn record_thumbnail(outcomes: &mut [ActionOutcome], thumbnail: &str) {
+    for details in outcomes
+        .iter_mut()
+        .filter_map(|outcome| outcome.details.as_mut())
+    {
+        details
+            .metadata
+            .entry(THUMBNAIL_KEY.to_string())
+            .or_insert_with(|| thumbnail.to_string());
+    }
+}
+
+/// Decodes the image, or draws the PDF's first page, on a thread of its own
+/// and gives up on it after `timeout`.
+fn render_within(path: PathBuf, pdf: bool, timeout: Duration) -> Option<DynamicImage> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let image = if pdf {
+            render_first_pdf_page(&path, THUMBNAIL_SIZE)
+        } else {
+            decode(&path)
+        };
+        let _ = tx.send(image.map(|image| image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)));
+    });
+    rx.recv_timeout(timeout).ok()?.ok()
+}
+
+fn decode(path: &Path) -> Result<DynamicImage> {
+    Ok(ImageReader::open(path)?.with_guessed_format()?.decode()?)
+}
+
+fn encode(image: &DynamicImage) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY).encode_image(&image.to_rgb8())?;
+    Ok(bytes)
+}
+
+fn touch(path: &Path) -> io::Result<()> {
+    fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(SystemTime::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::content::PdfiumUnavailable;
+    use image::{ImageFormat, Rgb, RgbImage};
+    use lopdf::{dictionary, Document, Object};
+    use tempfile::tempdir;
+
+    fn write_png(path: &Path, shade: u8) {
+        RgbImage::from_pixel(640, 480, Rgb([shade, 90, 40]))
+            .save_with_format(path, ImageFormat::Png)
+            .unwrap();
+    }
+
+    fn set_used(path: &Path, secs_ago: u64) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(secs_ago))
+            .unwrap();
+    }
+
+    #[test]
+    fn the_same_contents_share_one_thumbnail() {
+        let dir = tempdir().unwrap();
+        let cache = ThumbnailCache::new(dir.path().join("thumbs"), 1024 * 1024);
+        let photo = dir.path().join("IMG_4001.png");
+        write_png(&photo, 200);
+        let first = cache.thumbnail(&photo, &FileKind::Image, "png").unwrap();
+        assert!(!first.cached);
+        let thumb = image::open(&first.path).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (256, 192));
+
+        // The same photo again, now under another name.
+        let copy = dir.path().join("Pictures-IMG_4001.png");
+        fs::copy(&photo, &copy).unwrap();
+        let second = cache.thumbnail(&copy, &FileKind::Image, "png").unwrap();
+        assert!(second.cached);
+        assert_eq!(second.path, first.path);
+
+        assert!(cache
+            .data_url(&second.path)
+            .unwrap()
+            .starts_with("data:image/jpeg;base64,"));
+        assert_eq!(cache.data_url(&photo), None);
+    }
+
+    #[test]
+    fn least_recently_used_thumbnails_go_first_past_the_cap() {
+        let dir = tempdir().unwrap();
+        let thumbs = dir.path().join("thumbs");
+        let mut made = Vec::new();
+        for shade in [10, 120, 240] {
+            let photo = dir.path().join(format!("{shade}.png"));
+            write_png(&photo, shade);
+            let unbounded = ThumbnailCache::new(thumbs.clone(), u64::MAX);
+            made.push(
+                unbounded
+                    .thumbnail(&photo, &FileKind::Image, "png")
+                    .unwrap()
+                    .path,
+            );
+        }
+        set_used(&made[0], 300);
+        set_used(&made[1], 30);
+        set_used(&made[2], 200);
+
+        let size = |path: &PathBuf| fs::metadata(path).unwrap().len();
+        let cache = ThumbnailCache::new(thumbs, size(&made[1]) + size(&made[2]));
+        assert_eq!(cache.trim(None).unwrap(), 1);
+        assert!(!made[0].exists());
+        assert!(made[1].exists() && made[2].exists());
+
+        assert_eq!(cache.clear().unwrap(), 2);
+        assert!(!made[1].exists());
+    }
+
+    #[test]
+    fn a_corrupt_image_gets_no_thumbnail() {
+        let dir = tempdir().unwrap();
+        let thumbs = dir.path().join("thumbs");
+        let cache = ThumbnailCache::new(thumbs.clone(), 1024 * 1024);
+        let broken = dir.path().join("IMG_4002.jpg");
+        fs::write(&broken, b"\xFF\xD8\xFF\xE0 not really a jpeg").unwrap();
+        assert_eq!(cache.thumbnail(&broken, &FileKind::Image, "jpg"), None);
+        assert!(!thumbs.exists() || fs::read_dir(&thumbs).unwrap().next().is_none());
+
+        let notes = dir.path().join("notes.txt");
+        fs::write(&notes, b"hello").unwrap();
+        assert_eq!(cache.thumbnail(&notes, &FileKind::Document, "txt"), None);
+    }
+
+    #[test]
+    fn pdfs_show_their_first_page() {
+        let dir = tempdir().unwrap();
+        let cache = ThumbnailCache::new(dir.path().join("thumbs"), 1024 * 1024);
+        let pdf = dir.path().join("invoice.pdf");
+        one_page_pdf().save(&pdf).unwrap();
+
+        match render_first_pdf_page(&pdf, THUMBNAIL_SIZE) {
+            // Without PDFium there's nothing to draw the page with.
+            Err(err) if err.downcast_ref::<PdfiumUnavailable>().is_some() => {
+                assert_eq!(cache.thumbnail(&pdf, &FileKind::Document, "pdf"), None);
+            }
+            rendered => {
+                assert_eq!(rendered.unwrap().width(), THUMBNAIL_SIZE);
+                let thumb = cache.thumbnail(&pdf, &FileKind::Document, "pdf").unwrap();
+                let image = image::open(&thumb.path).unwrap();
+                // A US Letter page is taller than it is wide.
+                assert_eq!(image.height(), THUMBNAIL_SIZE);
+                assert!(image.width() < THUMBNAIL_SIZE);
+            }
+        }
+    }
+
+    fn one_page_pdf() -> Document {
+        let mut doc = Document::with_version("1.4");
+        let pages_id = doc.new_object_id();
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => Object::Reference(pages_id),
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![Object::Reference(page_id)],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => Object::Reference(pages_id),
+        });
+        doc.trailer.set("Root", Object::Reference(catalog_id));
+        doc
+    }
+}
@@ -0,0 +1,629 @@
+//! Per-rule timing: how long each rule takes to check its conditions and to
+//! run its actions, kept in memory as rolling statistics and written to the
+//! `rule_timings` table now and then so they survive a restart.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::models::{
+    Condition, DurationStats, LogEntry, LogStatus, RegexCompileStats, Rule, RuleTiming, SlowRule,
+    TimingPhase,
+};
+use crate::storage::database::Database;
+use crate::storage::log_repo::LogRepository;
+use crate::storage::timing_repo::TimingRepository;
+
+pub(crate) use filedispatch_core::evaluate::{take_regex_usage, RegexUsage};
+
+/// Runs per rule and phase that the percentile and the slowest rule are
+/// taken from.
+const RECENT_RUNS: usize = 256;
+
+/// How often changed statistics are written out.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Nearest-rank percentile of `samples`, which get sorted. 0 when empty.
+pub(crate) fn percentile(samples: &mut [f64], percent: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.sort_by(f64::total_cmp);
+    let rank = ((percent / 100.0) * samples.len() as f64).ceil() as usize;
+    samples[rank.clamp(1, samples.len()) - 1]
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[derive(Default)]
+struct Samples {
+    recent: VecDeque<(DateTime<Utc>, Duration)>,
+    count: u64,
+    total: Duration,
+    max: Duration,
+    last: Duration,
+}
+
+impl Samples {
+    fn record(&mut self, at: DateTime<Utc>, duration: Duration) {
+        if self.recent.len() == RECENT_RUNS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((at, duration));
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+        self.last = duration;
+    }
+
+    fn stats(&self) -> DurationStats {
+        if self.count == 0 {
+            return DurationStats::default();
+        }
+        let mut recent: Vec<f64> = self.recent.iter().map(|(_, d)| millis(*d)).collect();
+        DurationStats {
+            count: self.count,
+            mean_ms: millis(self.total) / self.count as f64,
+            p95_ms: percentile(&mut recent, 95.0),
+            max_ms: millis(self.max),
+            last_ms: millis(self.last),
+        }
+    }
+
+    fn slowest_since(&self, since: DateTime<Utc>) -> Option<(DateTime<Utc>, Duration)> {
+        self.recent
+            .iter()
+            .filter(|(at, _)| *at >= since)
+            .max_by_key(|(_, duration)| *duration)
+            .copied()
+    }
+}
+
+struct RuleEntry {
+    name: String,
+    evaluation: Samples,
+    execution: Samples,
+    regex: RegexCompileStats,
+    updated_at: DateTime<Utc>,
+}
+
+impl RuleEntry {
+    fn timing(&self, rule_id: &str) -> RuleTiming {
+        RuleTiming {
+            rule_id: rule_id.to_string(),
+            rule_name: self.name.clone(),
+            evaluation: self.evaluation.stats(),
+            execution: self.execution.stats(),
+            regex: self.regex.clone(),
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+struct Inner {
+    rules: HashMap<String, RuleEntry>,
+    /// Statistics from an earlier run, shown until the rule is timed again.
+    persisted: HashMap<String, RuleTiming>,
+    /// Rules timed since the last flush.
+    dirty: Vec<String>,
+    last_flush: Instant,
+}
+
+/// Timing statistics for every rule, shared by the engine and the commands.
+pub struct RuleTimings {
+    inner: Mutex<Inner>,
+}
+
+impl Default for RuleTimings {
+    fn default() -> Self {
+        Self::with_persisted(Vec::new())
+    }
+}
+
+impl RuleTimings {
+    /// Starts from the statistics saved by an earlier run.
+    pub fn load(db: &Database) -> Self {
+        Self::with_persisted(TimingRepository::new(db.clone()).list().unwrap_or_default())
+    }
+
+    /// Drops everything and starts again from the statistics in `db`, after
+    /// the database was swapped for a backup.
+    pub fn reload(&self, db: &Database) {
+        let fresh = Self::load(db);
+        if let (Ok(mut inner), Ok(fresh)) = (self.inner.lock(), fresh.inner.into_inner()) {
+            *inner = fresh;
+        }
+    }
+
+    fn with_persisted(persisted: Vec<RuleTiming>) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                rules: HashMap::new(),
+                persisted: persisted
+                    .into_iter()
+                    .map(|timing| (timing.rule_id.clone(), timing))
+                    .collect(),
+                dirty: Vec::new(),
+                last_flush: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn record(&self, rule: &Rule, phase: TimingPhase, duration: Duration, at: DateTime<Utc>) {
+        self.update(rule, at, |entry| match phase {
+            TimingPhase::Evaluation => entry.evaluation.record(at, duration),
+            TimingPhase::Execution => entry.execution.record(at, duration),
+        });
+    }
+
+    /// Adds the regex lookups made while evaluating `rule`.
+    pub(crate) fn record_regex(&self, rule: &Rule, usage: RegexUsage, at: DateTime<Utc>) {
+        if usage == RegexUsage::default() {
+            return;
+        }
+        self.update(rule, at, |entry| {
+            entry.regex.cache_hits += usage.hits;
+            entry.regex.cache_misses += usage.misses;
+            entry.regex.compile_ms += millis(usage.compile);
+        });
+    }
+
+    fn update(&self, rule: &Rule, at: DateTime<Utc>, apply: impl FnOnce(&mut RuleEntry)) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        inner.persisted.remove(&rule.id);
+        let entry = inner
+            .rules
+            .entry(rule.id.clone())
+            .or_insert_with(|| RuleEntry {
+                name: rule.name.clone(),
+                evaluation: Samples::default(),
+                execution: Samples::default(),
+                regex: RegexCompileStats::default(),
+                updated_at: at,
+            });
+        entry.name = rule.name.clone();
+        entry.updated_at = at;
+        apply(entry);
+        if !inner.dirty.contains(&rule.id) {
+            inner.dirty.push(rule.id.clone());
+        }
+    }
+
+    /// Every timed rule, slowest (by 95th percentile evaluation) first.
+    pub fn snapshot(&self) -> Vec<RuleTiming> {
+        let Ok(inner) = self.inner.lock() else {
+            return Vec::new();
+        };
+        let mut timings: Vec<RuleTiming> = inner
+            .rules
+            .iter()
+            .map(|(id, entry)| entry.timing(id))
+            .chain(inner.persisted.values().cloned())
+            .collect();
+        timings.sort_by(|a, b| b.evaluation.p95_ms.total_cmp(&a.evaluation.p95_ms));
+        timings
+    }
+
+    /// The single slowest evaluation or execution since `since`.
+    pub fn slowest_since(&self, since: DateTime<Utc>) -> Option<SlowRule> {
+        let inner = self.inner.lock().ok()?;
+        inner
+            .rules
+            .iter()
+            .flat_map(|(id, entry)| {
+                [
+                    (TimingPhase::Evaluation, &entry.evaluation),
+                    (TimingPhase::Execution, &entry.execution),
+                ]
+                .into_iter()
+                .filter_map(move |(phase, samples)| {
+                    let (at, duration) = samples.slowest_since(since)?;
+                    Some((id, entry, phase, at, duration))
+                })
+            })
+            .max_by_key(|(_, _, _, _, duration)| *duration)
+            .map(|(id, entry, phase, at, duration)| SlowRule {
+                rule_id: id.clone(),
+                rule_name: entry.name.clone(),
+                phase,
+                duration_ms: millis(duration),
+                at,
+            })
+    }
+
+    /// Writes out the rules timed since the last flush, at most once per
+    /// `FLUSH_INTERVAL`.
+    pub fn flush_if_due(&self, db: &Database) -> Result<()> {
+        let timings = {
+            let Ok(mut inner) = self.inner.lock() else {
+                return Ok(());
+            };
+            if inner.dirty.is_empty() || inner.last_flush.elapsed() < FLUSH_INTERVAL {
+                return Ok(());
+            }
+            inner.last_flush = Instant::now();
+            let dirty = std::mem::take(&mut inner.dirty);
+            dirty
+                .iter()
+                .filter_map(|id| inner.rules.get(id).map(|entry| entry.timing(id)))
+                .collect::<Vec<_>>()
+        };
+        TimingRepository::new(db.clone()).save(&timings)
+    }
+}
+
+/// Whether evaluating `condition` again has no side effects, the same bar
+/// preview holds conditions to. Shell conditions run the user's command.
+pub(crate) fn is_preview_safe(condition: &Condition) -> bool {
+    match condition {
+        Condition::ShellScript(_) => false,
+        Condition::Nested(group) => group.conditions.iter().all(is_preview_safe),
+        _ => true,
+    }
+}
+
+/// An evaluation that went over `Settings::slow_rule_warning_ms`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SlowEvaluation {
+    pub elapsed: Duration,
+    /// Index of the top-level condition that took longest when timed on its
+    /// own, and how long it took.
+    pub culprit: Option<(usize, Duration)>,
+}
+
+/// Checks `elapsed` against the threshold (0 turns the check off). Over it,
+/// each preview-safe top-level condition is timed again on its own with
+/// `time_condition` to find the likely culprit.
+pub(crate) fn diagnose_slow_evaluation(
+    rule: &Rule,
+    elapsed: Duration,
+    threshold_ms: u64,
+    mut time_condition: impl FnMut(&Condition) -> Duration,
+) -> Option<SlowEvaluation> {
+    if threshold_ms == 0 || elapsed <= Duration::from_millis(threshold_ms) {
+        return None;
+    }
+    let culprit = rule
+        .conditions
+        .conditions
+        .iter()
+        .enumerate()
+        .filter(|(_, condition)| is_preview_safe(condition))
+        .map(|(index, condition)| (index, time_condition(condition)))
+        .max_by_key(|(_, duration)| *duration);
+    Some(SlowEvaluation { elapsed, culprit })
+}
+
+/// Logs a warning naming the slow rule and, when known, its slowest condition.
+pub(crate) fn log_slow_evaluation(
+    repo: &LogRepository,
+    rule: &Rule,
+    path: &Path,
+    slow: &SlowEvaluation,
+    threshold_ms: u64,
+) -> Result<()> {
+    let mut message = format!(
+        "Checking conditions took {:.0} ms (warning threshold {} ms)",
+        millis(slow.elapsed),
+        threshold_ms
+    );
+    if let Some((index, duration)) = slow.culprit {
+        let kind = rule
+            .conditions
+            .conditions
+            .get(index)
+            .and_then(|condition| serde_json::to_value(condition).ok())
+            .and_then(|value| value.get("type")?.as_str().map(str::to_string))
+            .unwrap_or_default();
+        message.push_str(&format!(
+            "; condition {} ({kind}) took {:.0} ms on its own",
+            index + 1,
+            millis(duration)
+        ));
+    }
+    repo.insert(LogEntry {
+        id: String::new(),
+        rule_id: Some(rule.id.clone()),
+        rule_name: Some(rule.name.clone()),
+        file_path: path.to_string_lossy().to_string(),
+        action_type: "slowRule".to_string(),
+        action_detail: None,
+        status: LogStatus::Warning,
+        error_message: Some(message),
+        created_at: Utc::now(),
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::content::ContentCache;
+    use crate::core::engine::{evaluate_condition, EvaluationOptions};
+    use crate::core::ocr::OcrManager;
+    use crate::models::{
+        ConditionGroup, MatchType, ShellCondition, StringCondition, StringOperator,
+    };
+    use crate::storage::folder_repo::FolderRepository;
+    use crate::storage::rule_repo::RuleRepository;
+    use filedispatch_core::file_info::FileInfo;
+    use std::cell::RefCell;
+    use tempfile::tempdir;
+
+    fn name_condition(value: &str) -> Condition {
+        Condition::Name(StringCondition {
+            operator: StringOperator::Matches,
+            value: value.to_string(),
+            case_sensitive: false,
+        })
+    }
+
+    fn rule(id: &str, conditions: Vec<Condition>) -> Rule {
+        Rule {
+            id: id.to_string(),
+            folder_id: "folder".to_string(),
+            name: format!("Rule {id}"),
+            enabled: true,
+            stop_processing: false,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions,
+            },
+            actions: vec![],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let mut samples: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+        assert_eq!(percentile(&mut samples, 95.0), 95.0);
+        assert_eq!(percentile(&mut samples, 50.0), 50.0);
+        assert_eq!(percentile(&mut [7.0], 95.0), 7.0);
+        assert_eq!(percentile(&mut [], 95.0), 0.0);
+        // 95% of 10 samples rounds up to the 10th.
+        let mut ten: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(percentile(&mut ten, 95.0), 10.0);
+    }
+
+    #[test]
+    fn statistics_are_kept_per_rule_and_phase() {
+        let timings = RuleTimings::default();
+        let fast = rule("fast", vec![]);
+        let slow = rule("slow", vec![]);
+        let now = Utc::now();
+        for ms in [10, 20, 30, 40] {
+            timings.record(
+                &fast,
+                TimingPhase::Evaluation,
+                Duration::from_millis(ms),
+                now,
+            );
+        }
+        timings.record(&fast, TimingPhase::Execution, Duration::from_millis(5), now);
+        timings.record(
+            &slow,
+            TimingPhase::Evaluation,
+            Duration::from_millis(900),
+            now,
+        );
+
+        let snapshot = timings.snapshot();
+        assert_eq!(snapshot[0].rule_id, "slow");
+        let fast = &snapshot[1];
+        assert_eq!(fast.evaluation.count, 4);
+        assert_eq!(fast.evaluation.mean_ms, 25.0);
+        assert_eq!(fast.evaluation.p95_ms, 40.0);
+        assert_eq!(fast.evaluation.max_ms, 40.0);
+        assert_eq!(fast.evaluation.last_ms, 40.0);
+        assert_eq!(fast.execution.count, 1);
+        assert_eq!(fast.execution.last_ms, 5.0);
+    }
+
+    #[test]
+    fn slowest_rule_only_looks_at_the_window() {
+        let timings = RuleTimings::default();
+        let now = Utc::now();
+        let old = rule("old", vec![]);
+        let recent = rule("recent", vec![]);
+        let two_hours_ago = now - chrono::Duration::hours(2);
+        timings.record(
+            &old,
+            TimingPhase::Evaluation,
+            Duration::from_secs(5),
+            two_hours_ago,
+        );
+        timings.record(
+            &recent,
+            TimingPhase::Evaluation,
+            Duration::from_millis(80),
+            now,
+        );
+        timings.record(
+            &recent,
+            TimingPhase::Execution,
+            Duration::from_millis(300),
+            now,
+        );
+
+        let slowest = timings
+            .slowest_since(now - chrono::Duration::hours(1))
+            .unwrap();
+        assert_eq!(slowest.rule_id, "recent");
+        assert_eq!(slowest.phase, TimingPhase::Execution);
+        assert_eq!(slowest.duration_ms, 300.0);
+    }
+
+    #[test]
+    fn regex_compilation_is_attributed_to_the_rule_evaluating() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.txt");
+        std::fs::write(&path, "x").unwrap();
+        let info = FileInfo::from_path(&path).unwrap();
+        let settings = crate::models::Settings::default();
+        let mut ocr = OcrManager::new_placeholder();
+        let mut evaluate = |condition: &Condition| {
+            let mut cache = ContentCache::default();
+            let options = EvaluationOptions::default();
+            evaluate_condition(condition, &info, &settings, &mut ocr, &mut cache, &options)
+                .unwrap();
+        };
+
+        let timings = RuleTimings::default();
+        let pattern = name_condition("^rep(or)t$");
+        let first = rule("first", vec![pattern.clone()]);
+        let second = rule("second", vec![pattern.clone()]);
+        take_regex_usage();
+        evaluate(&pattern);
+        timings.record_regex(&first, take_regex_usage(), Utc::now());
+        // The regex cache is per thread, so the second rule reuses it.
+        evaluate(&pattern);
+        timings.record_regex(&second, take_regex_usage(), Utc::now());
+
+        let snapshot = timings.snapshot();
+        let regex = |id: &str| {
+            snapshot
+                .iter()
+                .find(|t| t.rule_id == id)
+                .unwrap()
+                .regex
+                .clone()
+        };
+        assert_eq!(
+            (regex("first").cache_hits, regex("first").cache_misses),
+            (0, 1)
+        );
+        assert!(regex("first").compile_ms > 0.0);
+        assert_eq!(
+            regex("second"),
+            RegexCompileStats {
+                cache_hits: 1,
+                cache_misses: 0,
+                compile_ms: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn over_threshold_evaluation_names_the_slowest_safe_condition() {
+        let shell = Condition::ShellScript(ShellCondition {
+            command: "sleep 10".to_string(),
+        });
+        let slow_rule = rule(
+            "r",
+            vec![
+                name_condition("^a"),
+                name_condition("(x+x+)+y"),
+                shell,
+                name_condition("b$"),
+            ],
+        );
+        // A synthetic evaluator: the backtracking pattern is the slow one,
+        // and the shell condition must never be run again.
+        let timed = RefCell::new(0);
+        let mut time_condition = |condition: &Condition| {
+            *timed.borrow_mut() += 1;
+            match condition {
+                Condition::Name(c) if c.value.contains('+') => Duration::from_millis(700),
+                Condition::Name(_) => Duration::from_millis(2),
+                _ => panic!("shell condition re-run"),
+            }
+        };
+
+        assert_eq!(
+            diagnose_slow_evaluation(
+                &slow_rule,
+                Duration::from_millis(90),
+                100,
+                &mut time_condition
+            ),
+            None
+        );
+        assert_eq!(*timed.borrow(), 0);
+        let slow = diagnose_slow_evaluation(
+            &slow_rule,
+            Duration::from_millis(750),
+            100,
+            &mut time_condition,
+        )
+        .unwrap();
+        assert_eq!(slow.culprit, Some((1, Duration::from_millis(700))));
+        assert_eq!(*timed.borrow(), 3);
+        assert_eq!(
+            diagnose_slow_evaluation(&slow_rule, Duration::from_secs(5), 0, |_| unreachable!()),
+            None
+        );
+
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let log_repo = LogRepository::new(db);
+        log_slow_evaluation(
+            &log_repo,
+            &slow_rule,
+            Path::new("/tmp/file.txt"),
+            &slow,
+            100,
+        )
+        .unwrap();
+        let entries = log_repo.list(10, 0).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].status, LogStatus::Warning);
+        assert_eq!(entries[0].rule_name.as_deref(), Some("Rule r"));
+        let message = entries[0].error_message.as_deref().unwrap();
+        assert!(message.contains("750 ms"), "{message}");
+        assert!(
+            message.contains("condition 2 (name) took 700 ms"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn flushed_statistics_come_back_after_a_restart() {
+        let dir = tempdir().unwrap();
+        let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let folder = FolderRepository::new(db.clone())
+            .create(&dir.path().to_string_lossy(), "Timed")
+            .unwrap();
+        let mut stored = rule("x", vec![]);
+        stored.folder_id = folder.id;
+        let stored = RuleRepository::new(db.clone()).create(stored).unwrap();
+
+        let timings = RuleTimings::default();
+        timings.record(
+            &stored,
+            TimingPhase::Evaluation,
+            Duration::from_millis(12),
+            Utc::now(),
+        );
+        timings.inner.lock().unwrap().last_flush = Instant::now() - FLUSH_INTERVAL;
+        timings.flush_if_due(&db).unwrap();
+        assert!(timings.inner.lock().unwrap().dirty.is_empty());
+
+        let reloaded = RuleTimings::load(&db).snapshot();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].rule_id, stored.id);
+        assert_eq!(reloaded[0].evaluation.last_ms, 12.0);
+    }
+}
@@ -0,0 +1,745 @@
+//! The system trash as a folder of its own. Not every platform's trash can be
+//! watched, so its rules only run in a periodic sweep, and they may only
+//! purge items or notify. Items are matched by what the platform records
+//! about them: on Linux, the `.trashinfo` file beside each item gives its
+//! original path and deletion date; the macOS trash records neither, so an
+//! item's modification time stands in for its deletion date; on Windows the
+//! recycle bin is listed and purged through the shell (`IFileOperation`).
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use directories::BaseDirs;
+use filedispatch_core::file_info::{FileInfo, FileInfoError};
+
+use crate::core::duplicates::DuplicateDetector;
+use crate::core::engine::{
+    action_type_to_string, evaluate_conditions, log_outcomes, EvaluationOptions,
+};
+use crate::core::executor::{ActionExecutor, ActionOutcome, ActionResultStatus, ActionScope};
+use crate::core::match_metadata::MatchMetadata;
+use crate::core::ocr::OcrManager;
+use crate::core::scan::RunResult;
+use crate::models::{
+    Action, ActionDetails, ActionType, EventSource, Folder, FolderKind, LogEntry, LogStatus, Rule,
+    Settings,
+};
+use crate::storage::database::Database;
+use crate::storage::folder_repo::FolderRepository;
+use crate::storage::log_repo::LogRepository;
+use crate::storage::write_set::WriteSet;
+
+/// How often the scheduler sweeps the trash.
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Metadata keys on a purge's log entry: where the item was deleted from,
+/// when, and why it was purged when no rule asked for it.
+pub const ORIGINAL_PATH_KEY: &str = "original_path";
+pub const DELETED_AT_KEY: &str = "deleted_at";
+pub const PURGE_REASON_KEY: &str = "purge_reason";
+
+/// The `DeletionDate` format of a `.trashinfo` file, in local time.
+const DELETION_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Rules on the trash can only purge items or notify about them.
+pub fn validate_trash_rule(rule: &Rule) -> Result<(), String> {
+    let allowed =
+        |action: &Action| matches!(action, Action::DeletePermanently(_) | Action::Notify(_));
+    if rule.actions.iter().all(allowed) {
+        return Ok(());
+    }
+    Err(format!(
+        "Rule \"{}\": rules on the system trash can only delete permanently or notify",
+        rule.name
+    ))
+}
+
+/// Checks `rule` against the trash's action limits when its folder is the
+/// system trash.
+pub fn validate_folder_rule(db: &Database, rule: &Rule) -> Result<(), String> {
+    let folder = FolderRepository::new(db.clone())
+        .get(&rule.folder_id)
+        .map_err(|e| e.to_string())?;
+    if folder.is_some_and(|folder| folder.kind == FolderKind::SystemTrash) {
+        validate_trash_rule(rule)?;
+    }
+    Ok(())
+}
+
+/// Where the current user's trash is and how it's laid out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SystemTrash {
+    /// The freedesktop.org layout: items in `files/`, and a `.trashinfo`
+    /// for each in `info/`.
+    Freedesktop(PathBuf),
+    /// A directory holding the trashed items and nothing else about them.
+    Plain(PathBuf),
+    #[cfg(windows)]
+    RecycleBin,
+}
+
+impl SystemTrash {
+    /// The home trash, `$XDG_DATA_HOME/Trash`.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn locate() -> Option<Self> {
+        BaseDirs::new().map(|dirs| SystemTrash::Freedesktop(dirs.data_dir().join("Trash")))
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn locate() -> Option<Self> {
+        BaseDirs::new().map(|dirs| SystemTrash::Plain(dirs.home_dir().join(".Trash")))
+    }
+
+    #[cfg(windows)]
+    pub fn locate() -> Option<Self> {
+        Some(SystemTrash::RecycleBin)
+    }
+
+    /// What's in the trash, deleted longest ago first. A trash that doesn't
+    /// exist yet is empty.
+    pub fn items(&self) -> Result<Vec<TrashItem>> {
+        let mut items = match self {
+            SystemTrash::Freedesktop(root) => freedesktop_items(root)?,
+            SystemTrash::Plain(root) => plain_items(root)?,
+            #[cfg(windows)]
+            SystemTrash::RecycleBin => recycle_bin_items()?,
+        };
+        items.sort_by_key(|item| item.deleted_at);
+        Ok(items)
+    }
+}
+
+/// One item in the trash.
+#[derive(Debug, Clone)]
+pub struct TrashItem {
+    /// Where the item is now, inside the trash.
+    pub path: PathBuf,
+    /// Where it was deleted from, where the platform records it.
+    pub original_path: Option<PathBuf>,
+    /// When it was deleted, or its modification time where the platform
+    /// doesn't record that.
+    pub deleted_at: DateTime<Utc>,
+    /// In bytes; a directory's is the total of its files.
+    pub size: u64,
+    entry: Entry,
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+    /// The item and its `.trashinfo` file.
+    Freedesktop {
+        info: PathBuf,
+    },
+    Plain,
+    #[cfg(windows)]
+    RecycleBin(trash::TrashItem),
+}
+
+impl TrashItem {
+    /// The item as rules see it: under its original name and parent folder
+    /// where those are known, added when it was deleted.
+    pub fn file_info(&self) -> Result<FileInfo, FileInfoError> {
+        let mut info = FileInfo::from_path(&self.path)?;
+        if let Some(original) = &self.original_path {
+            if let Some(name) = original.file_name() {
+                let original = Path::new(name);
+                info.os_name = name.to_os_string();
+                info.full_name = name.to_string_lossy().to_string();
+                info.name = original
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                info.extension = original
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+            }
+            info.parent = original
+                .parent()
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().to_string());
+        }
+        info.added = self.deleted_at;
+        info.size = self.size;
+        info.event_source = EventSource::Scan;
+        Ok(info)
+    }
+
+    /// Deletes the item for good, along with what the trash kept about it.
+    pub fn purge(&self) -> Result<()> {
+        match &self.entry {
+            Entry::Freedesktop { info } => {
+                remove(&self.path)?;
+                match fs::remove_file(info) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                    _ => {}
+                }
+            }
+            Entry::Plain => remove(&self.path)?,
+            #[cfg(windows)]
+            Entry::RecycleBin(item) => {
+                trash::os_limited::purge_all([item]).map_err(|err| anyhow!(err.to_string()))?
+            }
+        }
+        Ok(())
+    }
+
+    fn describe(&self, metadata: &mut HashMap<String, String>) {
+        if let Some(original) = &self.original_path {
+            metadata.insert(
+                ORIGINAL_PATH_KEY.to_string(),
+                original.to_string_lossy().to_string(),
+            );
+        }
+        metadata.insert(DELETED_AT_KEY.to_string(), self.deleted_at.to_rfc3339());
+        metadata.insert("size_bytes".to_string(), self.size.to_string());
+    }
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+fn freedesktop_items(root: &Path) -> Result<Vec<TrashItem>> {
+    let entries = match fs::read_dir(root.join("info")) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut items = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let info = entry.path();
+        if info.extension() != Some(OsStr::new("trashinfo")) {
+            continue;
+        }
+        let Some(name) = info.file_stem() else {
+            continue;
+        };
+        let path = root.join("files").join(name);
+        // An info file whose item is already gone.
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        let parsed = fs::read_to_string(&info)
+            .map(|text| parse_trashinfo(&text))
+            .unwrap_or_default();
+        items.push(TrashItem {
+            original_path: parsed.path,
+            deleted_at: parsed.deleted_at.unwrap_or_else(|| modified(&metadata)),
+            size: size_of(&path, &metadata),
+            path,
+            entry: Entry::Freedesktop { info },
+        });
+    }
+    Ok(items)
+}
+
+fn plain_items(root: &Path) -> Result<Vec<TrashItem>> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut items = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if entry.file_name() == ".DS_Store" {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        items.push(TrashItem {
+            original_path: None,
+            deleted_at: modified(&metadata),
+            size: size_of(&path, &metadata),
+            path,
+            entry: Entry::Plain,
+        });
+    }
+    Ok(items)
+}
+
+#[cfg(windows)]
+fn recycle_bin_items() -> Result<Vec<TrashItem>> {
+    let listed = trash::os_limited::list().map_err(|err| anyhow!(err.to_string()))?;
+    Ok(listed
+        .into_iter()
+        .map(|item| TrashItem {
+            path: PathBuf::from(&item.id),
+            original_path: Some(item.original_path()),
+            deleted_at: DateTime::from_timestamp(item.time_deleted, 0).unwrap_or_else(Utc::now),
+            size: trash::os_limited::metadata(&item)
+                .ok()
+                .and_then(|metadata| metadata.size.size())
+                .unwrap_or(0),
+            entry: Entry::RecycleBin(item),
+        })
+        .collect())
+}
+
+fn modified(metadata: &fs::Metadata) -> DateTime<Utc> {
+    metadata
+        .modified()
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now())
+}
+
+fn size_of(path: &Path, metadata: &fs::Metadata) -> u64 {
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// What a `.trashinfo` file says about its item.
+#[derive(Debug, Default, PartialEq)]
+struct TrashInfo {
+    path: Option<PathBuf>,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+fn parse_trashinfo(text: &str) -> TrashInfo {
+    let mut parsed = TrashInfo::default();
+    let mut in_section = false;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == "[Trash Info]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_section) else {
+            continue;
+        };
+        match key.trim() {
+            "Path" => parsed.path = Some(percent_decode(value.trim())),
+            "DeletionDate" => {
+                parsed.deleted_at =
+                    NaiveDateTime::parse_from_str(value.trim(), DELETION_DATE_FORMAT)
+                        .ok()
+                        .and_then(|date| Local.from_local_datetime(&date).earliest())
+                        .map(|date| date.with_timezone(&Utc));
+            }
+            _ => {}
+        }
+    }
+    parsed
+}
+
+/// A `Path=` value, which escapes bytes as `%XX`, back as a path. Names that
+/// aren't UTF-8 come back byte for byte where the OS allows.
+fn percent_decode(value: &str) -> PathBuf {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        PathBuf::from(std::ffi::OsString::from_vec(decoded))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+    }
+}
+
+/// Runs a trash folder's rules over what's in the trash, then purges the
+/// items deleted longest ago while the trash is over the folder's size limit.
+pub struct TrashSweeper<'a> {
+    pub db: &'a Database,
+    pub executor: &'a ActionExecutor,
+    pub settings: &'a Settings,
+    pub ocr: &'a Mutex<OcrManager>,
+    pub duplicates: &'a Arc<DuplicateDetector>,
+}
+
+impl TrashSweeper<'_> {
+    pub fn sweep(&self, trash: &SystemTrash, folder: &Folder, rules: &[Rule]) -> Result<RunResult> {
+        let items = trash.items()?;
+        let log_repo = LogRepository::new(self.db.clone());
+        let mut result = RunResult {
+            total_files: items.len(),
+            processed: 0,
+            matched: 0,
+            errors: Vec::new(),
+            complete: true,
+        };
+        let mut kept = Vec::new();
+        for item in items {
+            result.processed += 1;
+            let info = match item.file_info() {
+                Ok(info) => info,
+                Err(FileInfoError::NotFound) => continue,
+                Err(err) => {
+                    result
+                        .errors
+                        .push(format!("{}: {err}", item.path.display()));
+                    kept.push(item);
+                    continue;
+                }
+            };
+            let mut writes = WriteSet::default();
+            let applied = self.apply_rules(&item, &info, rules, &mut writes, &mut result);
+            writes.commit(self.db)?;
+            let (matched, purged) = applied?;
+            if matched {
+                result.matched += 1;
+            }
+            if !purged {
+                kept.push(item);
+            }
+        }
+        if folder.trash_max_mb > 0 {
+            self.enforce_limit(kept, folder.trash_max_mb, &log_repo, &mut result)?;
+        }
+        Ok(result)
+    }
+
+    /// Returns whether any rule matched the item and whether one purged it.
+    fn apply_rules(
+        &self,
+        item: &TrashItem,
+        info: &FileInfo,
+        rules: &[Rule],
+        writes: &mut WriteSet,
+        result: &mut RunResult,
+    ) -> Result<(bool, bool)> {
+        let (mut matched, mut purged) = (false, false);
+        for rule in rules.iter().filter(|rule| rule.enabled) {
+            if let Err(err) = validate_trash_rule(rule) {
+                result.errors.push(err);
+                continue;
+            }
+            let evaluation = {
+                let mut ocr = self.ocr.lock().map_err(|_| anyhow!("OCR lock poisoned"))?;
+                evaluate_conditions(
+                    rule,
+                    info,
+                    self.settings,
+                    &mut ocr,
+                    &EvaluationOptions::finding_duplicates(self.duplicates, &rule.folder_id),
+                )
+            };
+            let evaluation = match evaluation {
+                Ok(evaluation) if evaluation.matched => evaluation,
+                Ok(_) => continue,
+                Err(err) => {
+                    result.errors.push(format!("{}: {err}", info.full_name));
+                    continue;
+                }
+            };
+            matched = true;
+
+            let scope = ActionScope::new(&rule.id, info);
+            let mut outcomes = Vec::new();
+            for action in &rule.actions {
+                if matches!(action, Action::DeletePermanently(_)) {
+                    let outcome = self.purge(item);
+                    purged |= outcome.status == ActionResultStatus::Success;
+                    outcomes.push(outcome);
+                } else {
+                    outcomes.extend(self.executor.execute_actions(
+                        std::slice::from_ref(action),
+                        info,
+                        &evaluation.captures,
+                        &scope,
+                    ));
+                }
+            }
+            for details in outcomes
+                .iter_mut()
+                .filter_map(|outcome| outcome.details.as_mut())
+            {
+                item.describe(&mut details.metadata);
+            }
+            log_outcomes(writes, rule, info, &outcomes, &MatchMetadata::new(), None);
+            if purged || rule.stop_processing {
+                break;
+            }
+        }
+        Ok((matched, purged))
+    }
+
+    fn purge(&self, item: &TrashItem) -> ActionOutcome {
+        let details = ActionDetails {
+            source_path: item.path.to_string_lossy().to_string(),
+            destination_path: None,
+            metadata: HashMap::new(),
+        };
+        let (status, error) = if !self.settings.allow_permanent_delete {
+            (
+                ActionResultStatus::Skipped,
+                Some("Permanent deletes are disabled in settings".to_string()),
+            )
+        } else {
+            match item.purge() {
+                Ok(()) => (ActionResultStatus::Success, None),
+                Err(err) => (ActionResultStatus::Error, Some(err.to_string())),
+            }
+        };
+        ActionOutcome {
+            action_type: ActionType::DeletePermanently,
+            status,
+            details: Some(details),
+            error,
+            error_kind: None,
+        }
+    }
+
+    /// Purges the oldest of `kept` until they add up to no more than
+    /// `max_mb`.
+    fn enforce_limit(
+        &self,
+        kept: Vec<TrashItem>,
+        max_mb: u64,
+        log_repo: &LogRepository,
+        result: &mut RunResult,
+    ) -> Result<()> {
+        let limit = max_mb.saturating_mul(1024 * 1024);
+        let mut total: u64 = kept.iter().map(|item| item.size).sum();
+        for item in kept {
+            if total <= limit {
+                break;
+            }
+            let mut outcome = self.purge(&item);
+            if outcome.status == ActionResultStatus::Success {
+                total = total.saturating_sub(item.size);
+            }
+            if let Some(details) = outcome.details.as_mut() {
+                item.describe(&mut details.metadata);
+                details.metadata.insert(
+                    PURGE_REASON_KEY.to_string(),
+                    format!("The trash is over {max_mb} MB"),
+                );
+            }
+            if let Some(error) = outcome
+                .error
+                .as_ref()
+                .filter(|_| outcome.status == ActionResultStatus::Error)
+            {
+                result
+                    .errors
+                    .push(format!("{}: {error}", item.path.display()));
+            }
+            log_repo.insert(LogEntry {
+                id: String::new(),
+                rule_id: None,
+                rule_name: None,
+                file_path: item.path.to_string_lossy().to_string(),
+                action_type: action_type_to_string(&outcome.action_type),
+                action_detail: outcome.details,
+                status: match outcome.status {
+                    ActionResultStatus::Success => LogStatus::Success,
+                    ActionResultStatus::Skipped | ActionResultStatus::Simulated => {
+                        LogStatus::Skipped
+                    }
+                    ActionResultStatus::Error => LogStatus::Error,
+                },
+                error_message: outcome.error,
+                created_at: Utc::now(),
+            })?;
+            // Nothing more can be freed while permanent deletes are off.
+            if !self.settings.allow_permanent_delete {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        ConditionGroup, ConflictResolution, DeleteAction, MatchType, MoveAction, NotifyAction,
+    };
+    use tempfile::tempdir;
+
+    fn trashed(root: &Path, name: &str, contents: &[u8], info: Option<&str>) {
+        fs::create_dir_all(root.join("files")).unwrap();
+        fs::create_dir_all(root.join("info")).unwrap();
+        fs::write(root.join("files").join(name), contents).unwrap();
+        if let Some(info) = info {
+            fs::write(root.join("info").join(format!("{name}.trashinfo")), info).unwrap();
+        }
+    }
+
+    fn local(date: &str) -> DateTime<Utc> {
+        let naive = NaiveDateTime::parse_from_str(date, DELETION_DATE_FORMAT).unwrap();
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn linux_trash_items_carry_their_original_path_and_deletion_date() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("Trash");
+        trashed(
+            &root,
+            "report.2.pdf",
+            b"%PDF-1.4",
+            Some(
+                "[Trash Info]\nPath=/home/ana/Work%20Docs/report.pdf\nDeletionDate=2024-03-01T09:30:00\n",
+            ),
+        );
+        trashed(
+            &root,
+            "notes.txt",
+            b"hello",
+            Some("[Trash Info]\nPath=/home/ana/notes.txt\nDeletionDate=2023-12-24T18:00:00\n"),
+        );
+        // Info whose item was already purged by something else.
+        fs::write(
+            root.join("info").join("gone.txt.trashinfo"),
+            "[Trash Info]\nPath=/home/ana/gone.txt\nDeletionDate=2024-01-01T00:00:00\n",
+        )
+        .unwrap();
+
+        let items = SystemTrash::Freedesktop(root.clone()).items().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            items[0].original_path,
+            Some(PathBuf::from("/home/ana/notes.txt"))
+        );
+        assert_eq!(items[0].deleted_at, local("2023-12-24T18:00:00"));
+        assert_eq!(
+            items[1].original_path,
+            Some(PathBuf::from("/home/ana/Work Docs/report.pdf"))
+        );
+        assert_eq!(items[1].size, 8);
+
+        let info = items[1].file_info().unwrap();
+        assert_eq!(info.full_name, "report.pdf");
+        assert_eq!(info.name, "report");
+        assert_eq!(info.parent.as_deref(), Some("Work Docs"));
+        assert_eq!(info.added, local("2024-03-01T09:30:00"));
+
+        items[1].purge().unwrap();
+        assert!(!root.join("files/report.2.pdf").exists());
+        assert!(!root.join("info/report.2.pdf.trashinfo").exists());
+        assert_eq!(SystemTrash::Freedesktop(root).items().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn trashinfo_without_a_date_falls_back_to_the_modification_time() {
+        let parsed =
+            parse_trashinfo("[Other]\nPath=/elsewhere\n[Trash Info]\nPath=/a%2Fb/%E2%9C%93%zz\n");
+        assert_eq!(parsed.path, Some(PathBuf::from("/a/b/✓%zz")));
+        assert_eq!(parsed.deleted_at, None);
+
+        let dir = tempdir().unwrap();
+        trashed(
+            dir.path(),
+            "undated.txt",
+            b"x",
+            Some("[Trash Info]\nPath=/tmp/undated.txt\n"),
+        );
+        let items = SystemTrash::Freedesktop(dir.path().to_path_buf())
+            .items()
+            .unwrap();
+        let modified = fs::metadata(dir.path().join("files/undated.txt"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        assert_eq!(items[0].deleted_at, DateTime::<Utc>::from(modified));
+    }
+
+    #[test]
+    fn a_missing_trash_is_empty() {
+        let dir = tempdir().unwrap();
+        let missing = dir.path().join("Trash");
+        assert!(SystemTrash::Freedesktop(missing.clone())
+            .items()
+            .unwrap()
+            .is_empty());
+        assert!(SystemTrash::Plain(missing).items().unwrap().is_empty());
+    }
+
+    #[test]
+    fn trash_rules_can_only_purge_or_notify() {
+        let now = Utc::now();
+        let mut rule = Rule {
+            id: "purge".to_string(),
+            folder_id: "trash".to_string(),
+            name: "Old".to_string(),
+            enabled: true,
+            stop_processing: true,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![],
+            },
+            actions: vec![
+                Action::DeletePermanently(DeleteAction { permanent: true }),
+                Action::Notify(NotifyAction {
+                    message: "Purged {name}".to_string(),
+                    sound: None,
+                    generate_thumbnail: false,
+                }),
+            ],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: now,
+            updated_at: now,
+        };
+        assert!(validate_trash_rule(&rule).is_ok());
+
+        rule.actions.push(Action::Move(MoveAction {
+            destination: "~/Restored".to_string(),
+            on_conflict: ConflictResolution::Rename,
+            skip_duplicates: false,
+            destination_permissions: Default::default(),
+            quarantine: Default::default(),
+            bring_companions: None,
+            auto_partition: None,
+        }));
+        let err = validate_trash_rule(&rule).unwrap_err();
+        assert!(err.contains("only delete permanently or notify"));
+    }
+}
@@ -0,0 +1,226 @@
+//! Names the unique-suffix conflict policy gives files, maildir style:
+//! `scan.pdf` lands as `scan.1717423001123.a3f9.officepc.pdf`. The suffix is
+//! unique without looking at the destination, which matters on a share that
+//! several machines file into, where an existence check can be out of date
+//! by the time the file arrives.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use filedispatch_core::models::DEFAULT_UNIQUE_SUFFIX_FORMAT;
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+/// Metadata keys holding the parts of the suffix a file got.
+pub(crate) const UNIQUE_MILLIS_KEY: &str = "unique_millis";
+pub(crate) const UNIQUE_RANDOM_KEY: &str = "unique_random";
+pub(crate) const UNIQUE_HOST_KEY: &str = "unique_host";
+
+const TOKENS: &[&str] = &["millis", "random", "host"];
+
+/// The format from `Settings::unique_suffix_format`; empty until set.
+static FORMAT: RwLock<String> = RwLock::new(String::new());
+
+/// Starts somewhere random so machines don't step through the same values,
+/// and steps so two files in one millisecond never get the same one here.
+static SEQUENCE: Lazy<AtomicU16> = Lazy::new(|| AtomicU16::new(Uuid::new_v4().as_u128() as u16));
+
+static HOST: Lazy<Option<String>> = Lazy::new(|| hostname().as_deref().and_then(host_label));
+
+/// Follows `Settings::unique_suffix_format`; one that doesn't validate is
+/// treated as the default.
+pub fn set_format(format: &str) {
+    let format = match validate_format(format) {
+        Ok(()) => format.to_string(),
+        Err(_) => DEFAULT_UNIQUE_SUFFIX_FORMAT.to_string(),
+    };
+    if let Ok(mut current) = FORMAT.write() {
+        *current = format;
+    }
+}
+
+pub fn format() -> String {
+    FORMAT
+        .read()
+        .ok()
+        .filter(|format| !format.is_empty())
+        .map(|format| format.to_string())
+        .unwrap_or_else(|| DEFAULT_UNIQUE_SUFFIX_FORMAT.to_string())
+}
+
+/// Checks a suffix format: only known tokens, nothing that would leave the
+/// folder, and a `{random}` to tell apart files named in the same millisecond.
+pub fn validate_format(format: &str) -> Result<(), String> {
+    if format.contains(['/', '\\']) {
+        return Err("the suffix can't contain a path separator".to_string());
+    }
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            return Err("unclosed token".to_string());
+        };
+        let token = &rest[start + 1..start + end];
+        if !TOKENS.contains(&token) {
+            return Err(format!("unknown token {{{token}}}"));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    if !format.contains("{random}") {
+        return Err("the suffix needs {random}".to_string());
+    }
+    Ok(())
+}
+
+/// The parts of one file's suffix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameSuffix {
+    pub millis: i64,
+    /// Four hex digits.
+    pub random: String,
+    /// This machine's name, when the system gives one.
+    pub host: Option<String>,
+}
+
+impl NameSuffix {
+    pub fn generate(now: DateTime<Utc>) -> Self {
+        Self {
+            millis: now.timestamp_millis(),
+            random: format!("{:04x}", SEQUENCE.fetch_add(1, Ordering::Relaxed)),
+            host: HOST.clone(),
+        }
+    }
+
+    /// `format` with the parts filled in. A part that's empty takes the dot
+    /// before it along, so a missing host doesn't leave `..` behind.
+    pub fn render(&self, format: &str) -> String {
+        let rendered = format
+            .replace("{millis}", &self.millis.to_string())
+            .replace("{random}", &self.random)
+            .replace("{host}", self.host.as_deref().unwrap_or_default());
+        rendered
+            .split('.')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// `path` with the suffix between its stem and extension.
+    pub fn apply(&self, path: &Path, format: &str) -> PathBuf {
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "file".to_string());
+        let name = match path.extension() {
+            Some(ext) => format!("{stem}.{}.{}", self.render(format), ext.to_string_lossy()),
+            None => format!("{stem}.{}", self.render(format)),
+        };
+        path.with_file_name(name)
+    }
+
+    /// Notes the parts in an outcome's metadata, so the log shows how the
+    /// name came about.
+    pub fn record(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert(UNIQUE_MILLIS_KEY.to_string(), self.millis.to_string());
+        metadata.insert(UNIQUE_RANDOM_KEY.to_string(), self.random.clone());
+        if let Some(host) = &self.host {
+            metadata.insert(UNIQUE_HOST_KEY.to_string(), host.clone());
+        }
+    }
+}
+
+/// A new unique name for `path` in the current format.
+pub fn next(path: &Path) -> (PathBuf, NameSuffix) {
+    let suffix = NameSuffix::generate(Utc::now());
+    (suffix.apply(path, &format()), suffix)
+}
+
+/// The first label of a host name, lowercased and without anything that
+/// doesn't belong in a file name.
+fn host_label(host: &str) -> Option<String> {
+    let label: String = host
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    (!label.is_empty()).then_some(label)
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer outlives the call and its length is passed along.
+    let result = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if result != 0 {
+        return std::env::var("HOSTNAME").ok();
+    }
+    let end = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..end]).to_string())
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suffix(host: Option<&str>) -> NameSuffix {
+        NameSuffix {
+            millis: 1717423001123,
+            random: "a3f9".to_string(),
+            host: host.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn formats_render_between_stem_and_extension() {
+        let path = Path::new("/inbox/scan.pdf");
+        assert_eq!(
+            suffix(Some("officepc")).apply(path, DEFAULT_UNIQUE_SUFFIX_FORMAT),
+            PathBuf::from("/inbox/scan.1717423001123.a3f9.officepc.pdf")
+        );
+        assert_eq!(
+            suffix(None).apply(path, DEFAULT_UNIQUE_SUFFIX_FORMAT),
+            PathBuf::from("/inbox/scan.1717423001123.a3f9.pdf")
+        );
+        assert_eq!(
+            suffix(Some("officepc")).apply(Path::new("/inbox/README"), "{host}-{random}"),
+            PathBuf::from("/inbox/README.officepc-a3f9")
+        );
+        assert_eq!(
+            host_label("OfficePC.corp.example"),
+            Some("officepc".to_string())
+        );
+    }
+
+    #[test]
+    fn formats_are_checked() {
+        assert!(validate_format(DEFAULT_UNIQUE_SUFFIX_FORMAT).is_ok());
+        assert!(validate_format("{random}").is_ok());
+        assert!(validate_format("{millis}").is_err());
+        assert!(validate_format("{random}.{user}").is_err());
+        assert!(validate_format("{random").is_err());
+        assert!(validate_format("{random}/x").is_err());
+    }
+
+    #[test]
+    fn files_in_the_same_millisecond_get_different_names() {
+        let now = Utc::now();
+        let (first, second) = (NameSuffix::generate(now), NameSuffix::generate(now));
+        assert_eq!(first.millis, second.millis);
+        assert_ne!(first.random, second.random);
+        let path = Path::new("/inbox/scan.pdf");
+        assert_ne!(
+            first.apply(path, DEFAULT_UNIQUE_SUFFIX_FORMAT),
+            second.apply(path, DEFAULT_UNIQUE_SUFFIX_FORMAT)
+        );
+    }
+}
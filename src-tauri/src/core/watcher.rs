@@ -1,13 +1,21 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::Instant;
 
 use anyhow::Result;
 use crossbeam_channel::Sender;
 use glob::Pattern;
-use notify::event::ModifyKind;
+use notify::event::{ModifyKind, RenameMode};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 
+use crate::core::event_journal::EventJournal;
+use crate::models::{ActiveApp, Arrival, EventDisposition, EventSource};
+use crate::utils::foreground::{self, ForegroundCache};
+use crate::utils::markers::is_marker_index;
+use crate::utils::tempfiles::is_temp_artifact;
+
 #[derive(Debug, Clone)]
 pub enum FileEventKind {
     Created,
@@ -21,32 +29,99 @@ pub struct FileEvent {
     pub path: PathBuf,
     pub folder_id: String,
     pub kind: FileEventKind,
+    /// Set when the event is requeued for a single rule that was waiting for
+    /// the file to reach its minimum age; only that rule runs.
+    pub rule_id: Option<String>,
+    /// Set once the event's arrival group has closed and it's sent back to
+    /// be processed with the rest of the group.
+    pub arrival: Option<Arrival>,
+    /// The app in front when the watcher received the event. Deletions and
+    /// events raised other than by the watcher have none.
+    pub active_app: Option<ActiveApp>,
+    /// What raised the event; only the watcher's own are live.
+    pub source: EventSource,
+}
+
+/// A watched folder itself was renamed or removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootChange {
+    pub folder_id: String,
+    pub old_path: PathBuf,
+    /// Where it went, when that could be told; `None` once it's removed or
+    /// moved out of sight.
+    pub new_path: Option<PathBuf>,
+}
+
+/// Identifies a directory across renames on the same volume.
+type FileId = (u64, u64);
+
+/// What the watcher drops before an event reaches the engine.
+#[derive(Default)]
+struct EventFilter {
+    ignore_patterns: RwLock<Vec<Pattern>>,
+    /// Each folder's passthrough patterns, by folder id.
+    passthrough: RwLock<HashMap<String, Vec<Pattern>>>,
+    /// Events dropped by passthrough patterns, by folder id. Kept across
+    /// re-watches so a settings change doesn't reset them.
+    passthrough_drops: RwLock<HashMap<String, AtomicU64>>,
+    /// Where ignore-pattern drops are journaled, once there is a journal.
+    journal: RwLock<Option<Arc<EventJournal>>>,
+}
+
+impl EventFilter {
+    fn count_passthrough(&self, folder_id: &str) {
+        if let Some(drops) = self.passthrough_drops.read().unwrap().get(folder_id) {
+            drops.fetch_add(1, Ordering::Relaxed);
+        }
+    }
 }
 
 pub struct WatcherService {
     watcher: RecommendedWatcher,
     watched_folders: Arc<RwLock<HashMap<PathBuf, String>>>,
     folder_depths: Arc<RwLock<HashMap<String, i32>>>, // folder_id -> scan_depth
-    ignore_patterns: Arc<RwLock<Vec<Pattern>>>,
+    root_ids: Arc<RwLock<HashMap<PathBuf, FileId>>>,
+    filter: Arc<EventFilter>,
 }
 
 impl WatcherService {
-    pub fn new(event_tx: Sender<FileEvent>, ignore_patterns: Vec<String>) -> Result<Self> {
+    /// File events go to `event_tx`; renames and removals of the watched
+    /// folders themselves go to `root_tx`.
+    pub fn new(
+        event_tx: Sender<FileEvent>,
+        root_tx: Sender<RootChange>,
+        ignore_patterns: Vec<String>,
+    ) -> Result<Self> {
         let watched_folders = Arc::new(RwLock::new(HashMap::new()));
         let folder_depths = Arc::new(RwLock::new(HashMap::new()));
-        let ignore_patterns = Arc::new(RwLock::new(
-            ignore_patterns
-                .into_iter()
-                .filter_map(|p| Pattern::new(&p).ok())
-                .collect(),
-        ));
+        let root_ids = Arc::new(RwLock::new(HashMap::new()));
+        let filter = Arc::new(EventFilter {
+            ignore_patterns: RwLock::new(compile_patterns(&ignore_patterns)),
+            ..EventFilter::default()
+        });
 
         let folders_ref = watched_folders.clone();
         let depths_ref = folder_depths.clone();
-        let ignore_ref = ignore_patterns.clone();
-        let mut watcher = notify::recommended_watcher(move |res| {
+        let ids_ref = root_ids.clone();
+        let filter_ref = filter.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
             if let Ok(event) = res {
-                handle_event(event, &folders_ref, &depths_ref, &ignore_ref, &event_tx);
+                let changes = root_changes(
+                    &event,
+                    &folders_ref.read().unwrap(),
+                    &ids_ref.read().unwrap(),
+                );
+                for change in changes {
+                    let _ = root_tx.send(change);
+                }
+                handle_event(
+                    event,
+                    &folders_ref,
+                    &depths_ref,
+                    &filter_ref,
+                    foreground::system(),
+                    &event_tx,
+                );
             }
         })?;
 
@@ -58,17 +133,32 @@ impl WatcherService {
             watcher,
             watched_folders,
             folder_depths,
-            ignore_patterns,
+            root_ids,
+            filter,
         })
     }
 
-    pub fn watch_folder(&mut self, path: PathBuf, folder_id: String, scan_depth: i32) -> Result<()> {
+    /// Journals the events ignore patterns drop from now on.
+    pub fn set_journal(&mut self, journal: Arc<EventJournal>) {
+        *self.filter.journal.write().unwrap() = Some(journal);
+    }
+
+    pub fn watch_folder(
+        &mut self,
+        path: PathBuf,
+        folder_id: String,
+        scan_depth: i32,
+        passthrough_patterns: &[String],
+    ) -> Result<()> {
         // Skip groups (empty path)
         if path.as_os_str().is_empty() {
             return Ok(());
         }
 
         self.watcher.watch(&path, RecursiveMode::Recursive)?;
+        if let Some(id) = file_id(&path) {
+            self.root_ids.write().unwrap().insert(path.clone(), id);
+        }
         self.watched_folders
             .write()
             .unwrap()
@@ -76,24 +166,55 @@ impl WatcherService {
         self.folder_depths
             .write()
             .unwrap()
-            .insert(folder_id, scan_depth);
+            .insert(folder_id.clone(), scan_depth);
+        self.filter
+            .passthrough_drops
+            .write()
+            .unwrap()
+            .entry(folder_id.clone())
+            .or_default();
+        self.filter
+            .passthrough
+            .write()
+            .unwrap()
+            .insert(folder_id, compile_patterns(passthrough_patterns));
         Ok(())
     }
 
     pub fn unwatch_folder(&mut self, path: &Path) -> Result<()> {
         self.watcher.unwatch(path)?;
+        self.forget(path);
+        Ok(())
+    }
+
+    /// Drops a folder that was renamed or removed. The backend has usually
+    /// dropped its watch already, so failing to unwatch it is expected.
+    pub fn forget_folder(&mut self, path: &Path) {
+        let _ = self.watcher.unwatch(path);
+        self.forget(path);
+    }
+
+    fn forget(&mut self, path: &Path) {
+        self.root_ids.write().unwrap().remove(path);
         if let Some(folder_id) = self.watched_folders.write().unwrap().remove(path) {
             self.folder_depths.write().unwrap().remove(&folder_id);
+            self.filter.passthrough.write().unwrap().remove(&folder_id);
         }
-        Ok(())
     }
 
     pub fn set_ignore_patterns(&mut self, patterns: Vec<String>) {
-        let compiled = patterns
-            .into_iter()
-            .filter_map(|p| Pattern::new(&p).ok())
-            .collect();
-        *self.ignore_patterns.write().unwrap() = compiled;
+        *self.filter.ignore_patterns.write().unwrap() = compile_patterns(&patterns);
+    }
+
+    /// Events each folder's passthrough patterns dropped since launch.
+    pub fn passthrough_drops(&self) -> HashMap<String, u64> {
+        self.filter
+            .passthrough_drops
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(folder_id, drops)| (folder_id.clone(), drops.load(Ordering::Relaxed)))
+            .collect()
     }
 
     pub fn list_watched_folders(&self) -> Vec<(PathBuf, String, i32)> {
@@ -113,7 +234,8 @@ fn handle_event(
     event: Event,
     folders: &Arc<RwLock<HashMap<PathBuf, String>>>,
     depths: &Arc<RwLock<HashMap<String, i32>>>,
-    ignore_patterns: &Arc<RwLock<Vec<Pattern>>>,
+    filter: &EventFilter,
+    foreground: &ForegroundCache,
     event_tx: &Sender<FileEvent>,
 ) {
     let kind = match event.kind {
@@ -126,28 +248,136 @@ fn handle_event(
 
     let folders_guard = folders.read().unwrap();
     let depths_guard = depths.read().unwrap();
-    let ignore_guard = ignore_patterns.read().unwrap();
+    let passthrough_guard = filter.passthrough.read().unwrap();
+    let ignore_guard = filter.ignore_patterns.read().unwrap();
+    // Asked for once per event, and only when a path lands in a watched folder.
+    let mut active_app = None;
 
     for path in event.paths {
-        if should_ignore(&path, &ignore_guard) {
+        // The watched folder itself went away; see `root_changes`.
+        if folders_guard.contains_key(&path) && !path.exists() {
+            continue;
+        }
+        // Passthrough files are dropped first, leaving nothing but a count.
+        let Some((root, folder_id)) = resolve_folder(&path, &folders_guard, &depths_guard) else {
+            continue;
+        };
+        if passthrough_guard
+            .get(&folder_id)
+            .is_some_and(|patterns| is_passthrough(&path, root, patterns))
+        {
+            filter.count_passthrough(&folder_id);
+            continue;
+        }
+        if is_temp_artifact(&path) || is_marker_index(&path) {
             continue;
         }
 
-        if let Some(folder_id) = resolve_folder_id(&path, &folders_guard, &depths_guard) {
-            let _ = event_tx.send(FileEvent {
-                path,
-                folder_id,
-                kind: kind.clone(),
-            });
+        // Ignored files are dropped here too, but leave a trace in the journal.
+        if matches_any(&path, &ignore_guard) {
+            journal_ignored(filter, path, folder_id, &kind);
+            continue;
+        }
+
+        if !matches!(kind, FileEventKind::Deleted) && active_app.is_none() {
+            active_app = Some(foreground.active_app(Instant::now()));
         }
+        let _ = event_tx.send(FileEvent {
+            path,
+            folder_id,
+            kind: kind.clone(),
+            rule_id: None,
+            arrival: None,
+            active_app: active_app.clone(),
+            source: EventSource::Live,
+        });
     }
 }
 
-fn resolve_folder_id(
-    path: &Path,
+fn journal_ignored(filter: &EventFilter, path: PathBuf, folder_id: String, kind: &FileEventKind) {
+    let journal = filter.journal.read().unwrap();
+    let Some(journal) = journal.as_deref() else {
+        return;
+    };
+    let event = FileEvent {
+        path,
+        folder_id,
+        kind: kind.clone(),
+        rule_id: None,
+        arrival: None,
+        active_app: None,
+        source: EventSource::Live,
+    };
+    if let Some(id) = journal.record(&event) {
+        let detail = Some("Matches an ignore pattern".to_string());
+        journal.settle(id, EventDisposition::Ignored, detail);
+    }
+}
+
+/// Watched folders that `event` shows were renamed or removed. Most backends
+/// report the old path only, so the new one is looked for among the old
+/// path's siblings by file id, where the platform has one.
+fn root_changes(
+    event: &Event,
     folders: &HashMap<PathBuf, String>,
+    ids: &HashMap<PathBuf, FileId>,
+) -> Vec<RootChange> {
+    let renamed_to = match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => event.paths.get(1),
+        EventKind::Modify(ModifyKind::Name(_)) | EventKind::Remove(_) => None,
+        _ => return Vec::new(),
+    };
+    event
+        .paths
+        .iter()
+        .filter_map(|path| {
+            let folder_id = folders.get(path)?;
+            if path.exists() {
+                return None;
+            }
+            let new_path = renamed_to
+                .filter(|to| *to != path && to.is_dir())
+                .cloned()
+                .or_else(|| find_moved_root(path, *ids.get(path)?));
+            Some(RootChange {
+                folder_id: folder_id.clone(),
+                old_path: path.clone(),
+                new_path,
+            })
+        })
+        .collect()
+}
+
+fn find_moved_root(old_path: &Path, id: FileId) -> Option<PathBuf> {
+    std::fs::read_dir(old_path.parent()?)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|candidate| candidate.is_dir() && file_id(candidate) == Some(id))
+}
+
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.dev(), meta.ino()))
+}
+
+/// Windows has no stable way to read a file id from std; a renamed folder
+/// there is reported with the new path when the backend knows it, and
+/// otherwise marked unavailable.
+#[cfg(not(unix))]
+fn file_id(_path: &Path) -> Option<FileId> {
+    None
+}
+
+/// The watched folder `path` belongs to within its scan depth, as its root
+/// and id.
+fn resolve_folder<'a>(
+    path: &Path,
+    folders: &'a HashMap<PathBuf, String>,
     depths: &HashMap<String, i32>,
-) -> Option<String> {
+) -> Option<(&'a Path, String)> {
     for (folder_path, folder_id) in folders.iter() {
         // Check if path is under this watched folder
         if !path.starts_with(folder_path) {
@@ -171,11 +401,212 @@ fn resolve_folder_id(
             continue;
         }
 
-        return Some(folder_id.clone());
+        return Some((folder_path.as_path(), folder_id.clone()));
     }
     None
 }
 
-fn should_ignore(path: &Path, patterns: &[Pattern]) -> bool {
+pub(crate) fn compile_patterns(patterns: &[String]) -> Vec<Pattern> {
+    patterns
+        .iter()
+        .filter_map(|p| Pattern::new(p).ok())
+        .collect()
+}
+
+pub(crate) fn should_ignore(path: &Path, patterns: &[Pattern]) -> bool {
+    is_temp_artifact(path) || is_marker_index(path) || matches_any(path, patterns)
+}
+
+fn matches_any(path: &Path, patterns: &[Pattern]) -> bool {
     patterns.iter().any(|pattern| pattern.matches_path(path))
 }
+
+/// Whether a passthrough pattern matches `path`'s place inside `root`, or
+/// any file or folder name along the way, so `.stfolder` covers what's in it.
+pub(crate) fn is_passthrough(path: &Path, root: &Path, patterns: &[Pattern]) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    patterns.iter().any(|pattern| {
+        pattern.matches_path(relative)
+            || relative
+                .components()
+                .any(|part| pattern.matches(&part.as_os_str().to_string_lossy()))
+    })
+}
+
+/// Checks a folder's passthrough patterns before they're saved. One that
+/// would swallow every file needs `confirmed`.
+pub(crate) fn check_passthrough_patterns(
+    patterns: &[String],
+    confirmed: bool,
+) -> Result<(), String> {
+    for pattern in patterns {
+        if let Err(err) = Pattern::new(pattern) {
+            return Err(format!("Invalid passthrough pattern \"{pattern}\": {err}"));
+        }
+        let everything = matches!(pattern.trim(), "*" | "**" | "**/*" | "*.*");
+        if everything && !confirmed {
+            return Err(format!(
+                "Passthrough pattern \"{pattern}\" would hide every file in the folder"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::foreground::tests::{known, FixedForeground};
+    use notify::event::{CreateKind, RemoveKind};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    fn watching(root: &Path) -> Arc<RwLock<HashMap<PathBuf, String>>> {
+        Arc::new(RwLock::new(HashMap::from([(
+            root.to_path_buf(),
+            "downloads".to_string(),
+        )])))
+    }
+
+    #[test]
+    fn captures_the_app_in_front_when_the_event_arrives() {
+        let root = std::env::temp_dir().join("filedispatch-watcher-foreground");
+        let folders = watching(&root);
+        let depths = Arc::new(RwLock::new(HashMap::new()));
+        let filter = EventFilter::default();
+        let lookups = Arc::new(AtomicU32::new(0));
+        let foreground = ForegroundCache::new(
+            Box::new(FixedForeground {
+                app: known("FreeCAD"),
+                lookups: lookups.clone(),
+            }),
+            Duration::ZERO,
+        );
+        let (tx, rx) = crossbeam_channel::unbounded();
+
+        let created = Event::new(EventKind::Create(CreateKind::File))
+            .add_path(root.join("bracket.step"))
+            .add_path(root.join("bracket.stl"));
+        handle_event(created, &folders, &depths, &filter, &foreground, &tx);
+        let events: Vec<FileEvent> = rx.try_iter().collect();
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .all(|event| event.active_app == Some(known("FreeCAD"))));
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        let removed =
+            Event::new(EventKind::Remove(RemoveKind::File)).add_path(root.join("bracket.step"));
+        handle_event(removed, &folders, &depths, &filter, &foreground, &tx);
+        let events: Vec<FileEvent> = rx.try_iter().collect();
+        assert_eq!(events[0].active_app, None);
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    }
+
+    fn patterns(globs: &[&str]) -> Vec<Pattern> {
+        compile_patterns(
+            &globs
+                .iter()
+                .map(|glob| glob.to_string())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn passthrough_files_leave_nothing_but_a_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let db =
+            crate::storage::database::Database::new_with_path(dir.path().join("test.db")).unwrap();
+        let settings = crate::models::Settings {
+            event_journal_enabled: true,
+            ..crate::models::Settings::default()
+        };
+        let journal = Arc::new(EventJournal::new(
+            db.clone(),
+            Arc::new(std::sync::Mutex::new(settings)),
+        ));
+        let root = dir.path().join("Sync");
+        let folders = watching(&root);
+        let depths = Arc::new(RwLock::new(HashMap::from([("downloads".to_string(), -1)])));
+        let filter = EventFilter {
+            ignore_patterns: RwLock::new(patterns(&["*.log"])),
+            passthrough: RwLock::new(HashMap::from([(
+                "downloads".to_string(),
+                patterns(&["desktop.ini", ".stfolder", "*.sync-conflict-*"]),
+            )])),
+            passthrough_drops: RwLock::new(HashMap::from([(
+                "downloads".to_string(),
+                AtomicU64::new(0),
+            )])),
+            journal: RwLock::new(Some(journal.clone())),
+        };
+        let lookups = Arc::new(AtomicU32::new(0));
+        let foreground = ForegroundCache::new(
+            Box::new(FixedForeground {
+                app: known("Finder"),
+                lookups: lookups.clone(),
+            }),
+            Duration::ZERO,
+        );
+        let (tx, rx) = crossbeam_channel::unbounded();
+
+        let passthrough = Event::new(EventKind::Create(CreateKind::File))
+            .add_path(root.join("desktop.ini"))
+            .add_path(root.join(".stfolder/index"))
+            .add_path(root.join("notes.sync-conflict-20240101-ABC.md"));
+        handle_event(passthrough, &folders, &depths, &filter, &foreground, &tx);
+        // Nothing reaches the engine, so nothing is debounced or counted there.
+        assert_eq!(rx.try_iter().count(), 0);
+        assert_eq!(lookups.load(Ordering::SeqCst), 0);
+        let drops = filter.passthrough_drops.read().unwrap()["downloads"].load(Ordering::SeqCst);
+        assert_eq!(drops, 3);
+
+        let ignored =
+            Event::new(EventKind::Create(CreateKind::File)).add_path(root.join("sync.log"));
+        handle_event(ignored, &folders, &depths, &filter, &foreground, &tx);
+        assert_eq!(rx.try_iter().count(), 0);
+
+        let entries = journal.query(&db, &Default::default()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].path.ends_with("sync.log"));
+        assert_eq!(entries[0].disposition, Some(EventDisposition::Ignored));
+        assert_eq!(journal.dropped(), 0);
+    }
+
+    #[test]
+    fn patterns_hiding_everything_need_confirming() {
+        let broad = vec!["desktop.ini".to_string(), "**".to_string()];
+        assert!(check_passthrough_patterns(&broad, false).is_err());
+        assert!(check_passthrough_patterns(&broad, true).is_ok());
+        assert!(check_passthrough_patterns(&["[oops".to_string()], true).is_err());
+        assert!(check_passthrough_patterns(&["*.sync-conflict-*".to_string()], false).is_ok());
+    }
+
+    #[test]
+    fn folders_that_respect_git_pass_through_its_directory() {
+        let root = PathBuf::from("/repo");
+        let mut folder: crate::models::Folder = serde_json::from_value(serde_json::json!({
+            "id": "repo",
+            "path": "/repo",
+            "name": "repo",
+            "enabled": true,
+            "createdAt": "2024-01-01T00:00:00Z",
+            "updatedAt": "2024-01-01T00:00:00Z",
+            "passthroughPatterns": ["desktop.ini"]
+        }))
+        .unwrap();
+        let internals = root.join(".git/objects/ab/cdef");
+        let plain = compile_patterns(&folder.effective_passthrough_patterns());
+        assert!(!is_passthrough(&internals, &root, &plain));
+
+        folder.respect_git = true;
+        let git = compile_patterns(&folder.effective_passthrough_patterns());
+        assert!(is_passthrough(&internals, &root, &git));
+        assert!(is_passthrough(&root.join(".git/index.lock"), &root, &git));
+        assert!(is_passthrough(&root.join("desktop.ini"), &root, &git));
+        assert!(!is_passthrough(&root.join("src/.gitignore"), &root, &git));
+        assert_eq!(folder.passthrough_patterns, ["desktop.ini"]);
+    }
+}
@@ -1,43 +1,83 @@
 mod commands;
 mod core;
-mod models;
 mod storage;
 mod utils;
 
-use commands::engine::{engine_pause_set, engine_pause_toggle, engine_status_get};
+use filedispatch_core::models;
+
+use commands::approvals::{approval_dismiss, approval_execute, approvals_list};
+use commands::backup::{backup_list, backup_now, backup_restore};
+use commands::classify::{classifier_status, classifier_train};
+use commands::conflicts::{conflict_resolve, conflicts_list};
+use commands::engine::{
+    command_cancel, command_stats, destinations_status, engine_pause_set, engine_pause_toggle,
+    engine_status_get, event_journal_export, event_journal_query, rule_timings,
+};
+use commands::faces::{faces_delete_model, faces_download_model, faces_model_status};
+use commands::folder_templates::{
+    folder_template_apply, folder_template_delete, folder_template_export, folder_template_import,
+    folder_template_list, folder_template_save,
+};
 use commands::folders::{
-    folder_add, folder_create_group, folder_list, folder_move, folder_remove, folder_rename,
-    folder_toggle, folder_update_settings,
+    folder_add, folder_create_group, folder_list, folder_move, folder_relink, folder_remove,
+    folder_rename, folder_toggle, folder_update_settings,
 };
-use commands::logs::{log_clear, log_list};
+use commands::health::{folder_flow_graph, health_check, health_repair};
+use commands::insights::{classification_report, insights_reset, insights_summary};
+use commands::logs::{
+    errors_acknowledge, log_clear, log_list, logs_mark_reviewed, logs_unreviewed_summary,
+    matches_query, rule_matches_export, thumbnail_cache_clear, thumbnail_read,
+};
+use commands::notifications::error_notification_action;
 use commands::ocr::{
     ocr_cancel_download, ocr_cancel_request, ocr_delete_language, ocr_download_language,
     ocr_fetch_available_languages, ocr_get_installed_languages,
 };
+use commands::permissions::permissions_status;
 use commands::presets::{preset_install, preset_read};
-use commands::preview::{preview_file, preview_rule, preview_rule_draft};
+use commands::profiles::{profile_create, profile_delete, profile_list, profile_switch};
+use commands::quick_setup::{quick_setup_apply, quick_setup_suggestions};
+use commands::retries::{retries_list, retry_cancel, retry_force};
+use commands::preview::{
+    preview_file, preview_rule, preview_rule_draft, rule_dry_run, rule_suggest,
+};
 use commands::rules::{
-    rule_create, rule_delete, rule_duplicate, rule_export, rule_get, rule_import, rule_list,
-    rule_reorder, rule_toggle, rule_update,
+    rule_budget_status, rule_counter_reset, rule_create, rule_delete, rule_duplicate, rule_export,
+    rule_get, rule_import, rule_list, rule_reorder, rule_revision_get, rule_toggle, rule_update,
+    rules_analyze, rules_bulk_edit, rules_check_references,
 };
-use commands::run::folder_run_now;
-use commands::settings::{settings_get, settings_update};
-use commands::undo::{undo_execute, undo_list};
+use commands::run::{folder_run_now, folder_scan_now};
+use commands::sandbox::{sandbox_run, sandbox_scenario_load, sandbox_scenario_save};
+use commands::settings::{kind_mappings_list, settings_get, settings_update};
+use commands::undo::{undo_execute, undo_list, undo_stats};
+use core::backup::BackupScheduler;
+use core::badge::{Badge, BadgeManager, TauriBadgeRenderer, DEBOUNCE, TRAY_ID};
+use core::destinations::DestinationMonitor;
+use core::diagnostics::{self, CliOptions, StartupFailure, StatusFile};
+use core::digest::DigestScheduler;
+use core::duplicates::DuplicateDetector;
 use core::engine::RuleEngine;
+use core::error_notify::{ErrorNotifier, TauriNotifier};
+use core::event_journal::EventJournal;
+use core::folder_lanes::FolderLanes;
 use core::incomplete::IncompleteCleaner;
 use core::ocr::OcrManager;
+use core::references::{ReferenceChecks, ReferenceMonitor};
+use core::relink::RootTracker;
+use core::retry::RetryScheduler;
+use core::scheduler::{FolderScans, MaintenanceScheduler};
+use core::startup::{StartupCancel, StartupTasks};
 use core::state::AppState;
+use core::timings::RuleTimings;
 use core::watcher::WatcherService;
 use models::{EngineStatus, Settings};
 use std::time::Duration;
 use storage::database::Database;
-use storage::folder_repo::FolderRepository;
-use storage::log_repo::LogRepository;
+use storage::undo_repo::UndoRepository;
 use tauri::menu::{Menu, MenuItem};
 use tauri::tray::TrayIconBuilder;
-use tauri::Manager;
+use tauri::{Listener, Manager};
 use tauri_plugin_store::StoreBuilder;
-use utils::platform::normalize_user_path;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -47,17 +87,55 @@ fn greet(name: &str) -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let db = Database::new().expect("failed to initialize database");
+    let options =
+        CliOptions::parse(std::env::args().skip(1)).unwrap_or_else(|failure| failure.exit());
+    if options.check {
+        let outcome = diagnostics::check(&options);
+        println!("{}", serde_json::to_string_pretty(&outcome.report).unwrap_or_default());
+        std::process::exit(outcome.exit_code);
+    }
+    let db = diagnostics::open_database(options.database.as_deref())
+        .unwrap_or_else(|failure| failure.exit());
     let (event_tx, event_rx) = crossbeam_channel::bounded(1000);
-    let watcher = WatcherService::new(event_tx, vec![]).expect("failed to initialize watcher");
+    let retry_tx = event_tx.clone();
+    // Bounded so a huge catch-up waits for the engine instead of piling up.
+    let (backlog_tx, backlog_rx) = crossbeam_channel::bounded(1000);
+    let startup_cancel = StartupCancel::default();
+    let quit_cancel = startup_cancel.clone();
+    let (root_tx, root_rx) = crossbeam_channel::unbounded();
+    let mut watcher = WatcherService::new(event_tx, root_tx, vec![])
+        .unwrap_or_else(|err| StartupFailure::Watch(err.to_string()).exit());
     let engine_status = std::sync::Arc::new(std::sync::Mutex::new(EngineStatus::default()));
+    let status_file = options
+        .status_file
+        .clone()
+        .map(|path| std::sync::Arc::new(StatusFile::new(path)));
+    let exit_status_file = status_file.clone();
+    let exit_engine_status = engine_status.clone();
+    let settings = std::sync::Arc::new(std::sync::Mutex::new(Settings::default()));
+    let event_journal = std::sync::Arc::new(EventJournal::new(db.clone(), settings.clone()));
+    watcher.set_journal(event_journal.clone());
+    let paused = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let folder_scans = std::sync::Arc::new(FolderScans::new(
+        db.clone(),
+        backlog_tx.clone(),
+        settings.clone(),
+        paused.clone(),
+    ));
     let state = AppState {
         db: db.clone(),
         watcher: std::sync::Arc::new(std::sync::Mutex::new(watcher)),
-        settings: std::sync::Arc::new(std::sync::Mutex::new(Settings::default())),
+        settings: settings.clone(),
         ocr: std::sync::Arc::new(std::sync::Mutex::new(OcrManager::new_placeholder())),
-        paused: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+        paused,
         engine_status: engine_status.clone(),
+        rule_timings: std::sync::Arc::new(RuleTimings::load(&db)),
+        event_journal,
+        reference_checks: std::sync::Arc::new(ReferenceChecks::default()),
+        folder_lanes: std::sync::Arc::new(FolderLanes::load(&db)),
+        command_lanes: std::sync::Arc::default(),
+        folder_scans,
+        duplicate_detector: std::sync::Arc::new(DuplicateDetector::new(db.clone())),
     };
 
     tauri::Builder::default()
@@ -91,15 +169,19 @@ pub fn run() {
             let store = StoreBuilder::new(app, "settings.json")
                 .build()
                 .map_err(|e| tauri::Error::Anyhow(e.into()))?;
-            let settings = store
-                .get("settings")
-                .and_then(|value| serde_json::from_value::<Settings>(value.clone()).ok())
-                .unwrap_or_default();
+            let settings = diagnostics::settings_from_store(store.get("settings").as_ref())
+                .unwrap_or_else(|failure| failure.exit());
+            filedispatch_core::kinds::set_overrides(&settings.kind_overrides);
+            crate::core::unique_name::set_format(&settings.unique_suffix_format);
 
             let state = app.state::<AppState>();
             if let Ok(mut ocr) = state.ocr.lock() {
                 ocr.update(app.handle().clone(), settings.clone());
             }
+            let error_notifier = std::sync::Arc::new(ErrorNotifier::new(Box::new(
+                TauriNotifier::new(app.handle().clone()),
+            )));
+            app.manage(error_notifier.clone());
             let engine = RuleEngine::new(
                 event_rx,
                 db.clone(),
@@ -108,12 +190,50 @@ pub fn run() {
                 state.ocr.clone(),
                 state.paused.clone(),
                 state.engine_status.clone(),
-            );
+            )
+            .with_retry_sender(retry_tx.clone())
+            .with_error_notifier(error_notifier.clone())
+            .with_rule_timings(state.rule_timings.clone())
+            .with_event_journal(state.event_journal.clone())
+            .with_folder_lanes(state.folder_lanes.clone())
+            .with_duplicate_detector(state.duplicate_detector.clone())
+            .with_backlog(backlog_rx);
             engine.start();
+            if let Some(status_file) = status_file {
+                status_file.start(state.engine_status.clone(), options.status_interval);
+            }
 
-            let repo = FolderRepository::new(db.clone());
-            let log_repo = LogRepository::new(db.clone());
-            let _ = log_repo.cleanup(settings.log_retention_days);
+            DestinationMonitor::new(
+                db.clone(),
+                retry_tx.clone(),
+                state.paused.clone(),
+                state.engine_status.clone(),
+            )
+            .start();
+            RetryScheduler::new(db.clone(), retry_tx, state.paused.clone()).start();
+            RootTracker::new(state.inner().clone(), app.handle().clone()).start(root_rx);
+
+            MaintenanceScheduler::new(
+                db.clone(),
+                app.handle().clone(),
+                state.settings.clone(),
+                state.ocr.clone(),
+                state.paused.clone(),
+                state.folder_lanes.clone(),
+                state.duplicate_detector.clone(),
+            )
+            .start();
+            state.folder_scans.clone().start();
+
+            // Screenshots are often the size of a connected display.
+            if let Ok(monitors) = app.available_monitors() {
+                crate::core::screenshots::set_display_sizes(
+                    monitors
+                        .iter()
+                        .map(|monitor| (monitor.size().width, monitor.size().height))
+                        .collect(),
+                );
+            }
 
             // Store settings synchronously (fast operation)
             if let Ok(mut stored) = state.settings.lock() {
@@ -123,20 +243,23 @@ pub fn run() {
                 ocr.update(app.handle().clone(), settings.clone());
             }
 
-            // Defer folder watching to a background thread to avoid blocking startup
-            let watcher_state = state.watcher.clone();
-            let ignore_patterns = settings.ignore_patterns.clone();
-            std::thread::spawn(move || {
-                if let Ok(folders) = repo.list() {
-                    if let Ok(mut watcher) = watcher_state.lock() {
-                        watcher.set_ignore_patterns(ignore_patterns);
-                        for folder in folders.into_iter().filter(|f| f.enabled) {
-                            let normalized = normalize_user_path(&folder.path);
-                            let _ = watcher.watch_folder(normalized, folder.id.clone(), folder.scan_depth);
-                        }
-                    }
-                }
-            });
+            ReferenceMonitor::new(
+                db.clone(),
+                state.settings.clone(),
+                state.reference_checks.clone(),
+                error_notifier.clone(),
+                state.engine_status.clone(),
+            )
+            .start();
+
+            DigestScheduler::new(db.clone(), state.settings.clone(), error_notifier)
+                .start(app.handle().clone());
+            BackupScheduler::new(db.clone(), state.settings.clone()).start();
+
+            // Cleanup, health checks, watching and catching up run in the
+            // background so the window answers right away.
+            StartupTasks::new(state.inner(), backlog_tx, startup_cancel)
+                .start(app.handle().clone());
 
             let incomplete_cleaner = IncompleteCleaner::new(db.clone());
             std::thread::spawn(move || loop {
@@ -146,6 +269,23 @@ pub fn run() {
                 std::thread::sleep(Duration::from_secs(300));
             });
 
+            // Undo history is trimmed here rather than on every insert, so a
+            // large batch isn't slowed down by deletes.
+            let undo_repo = UndoRepository::new(db.clone());
+            let undo_settings = state.settings.clone();
+            std::thread::spawn(move || loop {
+                let limits = undo_settings
+                    .lock()
+                    .map(|s| (s.undo_max_entries, s.undo_retention_days));
+                if let Ok((max_entries, retention_days)) = limits {
+                    let now = chrono::Utc::now();
+                    if let Err(err) = undo_repo.prune(max_entries, retention_days, now) {
+                        eprintln!("Undo prune error: {err}");
+                    }
+                }
+                std::thread::sleep(Duration::from_secs(600));
+            });
+
             let show = MenuItem::new(app, "Show", true, None::<&str>)?;
             let hide = MenuItem::new(app, "Hide", true, None::<&str>)?;
             let pause = MenuItem::new(app, "Pause Processing", true, None::<&str>)?;
@@ -159,7 +299,7 @@ pub fn run() {
             let quit_id = quit.id().clone();
 
             let paused_flag = state.paused.clone();
-            TrayIconBuilder::new()
+            TrayIconBuilder::with_id(TRAY_ID)
                 .icon(app.default_window_icon().unwrap().clone())
                 .menu(&tray_menu)
                 .on_menu_event(move |app, event| {
@@ -181,6 +321,20 @@ pub fn run() {
                 })
                 .build(app)?;
 
+            if let Some(renderer) = TauriBadgeRenderer::new(app.handle().clone()) {
+                let badge = Badge::new(db.clone(), Box::new(renderer), DEBOUNCE);
+                let badge = BadgeManager::start(badge);
+                for event in ["approval://pending", "conflict://pending"] {
+                    let handle = app.handle().clone();
+                    app.listen(event, move |_| {
+                        if let Some(badge) = handle.try_state::<BadgeManager>() {
+                            badge.refresh();
+                        }
+                    });
+                }
+                app.manage(badge);
+            }
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -193,29 +347,75 @@ pub fn run() {
             folder_create_group,
             folder_move,
             folder_rename,
+            folder_relink,
             rule_list,
             rule_get,
+            rule_budget_status,
             rule_create,
             rule_update,
+            rule_revision_get,
             rule_delete,
             rule_toggle,
+            rule_counter_reset,
             rule_reorder,
             rule_duplicate,
             rule_export,
             rule_import,
+            rules_analyze,
+            rules_bulk_edit,
+            rules_check_references,
             log_list,
             log_clear,
+            thumbnail_cache_clear,
+            thumbnail_read,
+            errors_acknowledge,
+            logs_mark_reviewed,
+            logs_unreviewed_summary,
+            rule_matches_export,
+            matches_query,
+            error_notification_action,
             preview_rule,
+            rule_dry_run,
             preview_file,
             preview_rule_draft,
+            rule_suggest,
+            sandbox_run,
+            sandbox_scenario_save,
+            sandbox_scenario_load,
             preset_read,
             preset_install,
             settings_get,
             settings_update,
+            kind_mappings_list,
             undo_list,
             undo_execute,
+            undo_stats,
+            backup_now,
+            backup_list,
+            backup_restore,
+            approvals_list,
+            approval_execute,
+            approval_dismiss,
+            conflicts_list,
+            conflict_resolve,
+            retries_list,
+            retry_cancel,
+            retry_force,
+            insights_summary,
+            insights_reset,
+            classification_report,
+            health_check,
+            health_repair,
+            folder_flow_graph,
             folder_run_now,
+            folder_scan_now,
             engine_status_get,
+            destinations_status,
+            rule_timings,
+            command_stats,
+            command_cancel,
+            event_journal_query,
+            event_journal_export,
             engine_pause_set,
             engine_pause_toggle,
             ocr_fetch_available_languages,
@@ -224,7 +424,33 @@ pub fn run() {
             ocr_cancel_download,
             ocr_cancel_request,
             ocr_delete_language,
+            faces_model_status,
+            faces_download_model,
+            faces_delete_model,
+            classifier_train,
+            classifier_status,
+            profile_list,
+            profile_create,
+            profile_delete,
+            profile_switch,
+            permissions_status,
+            quick_setup_suggestions,
+            quick_setup_apply,
+            folder_template_save,
+            folder_template_list,
+            folder_template_apply,
+            folder_template_delete,
+            folder_template_export,
+            folder_template_import,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running File Dispatch");
+        .build(tauri::generate_context!())
+        .expect("error while building File Dispatch")
+        .run(move |_, event| {
+            if let tauri::RunEvent::Exit = event {
+                quit_cancel.cancel();
+                if let Some(status_file) = &exit_status_file {
+                    status_file.write_logged(&exit_engine_status, true);
+                }
+            }
+        });
 }
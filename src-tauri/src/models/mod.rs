@@ -1,22 +0,0 @@
-pub mod action;
-pub mod condition;
-pub mod engine;
-pub mod folder;
-pub mod log_entry;
-pub mod preview;
-pub mod rule;
-pub mod settings;
-pub mod preset;
-pub mod undo;
-pub mod ocr_job;
-
-pub use action::*;
-pub use condition::*;
-pub use engine::*;
-pub use folder::*;
-pub use log_entry::*;
-pub use preview::*;
-pub use rule::*;
-pub use settings::*;
-pub use preset::*;
-pub use undo::*;
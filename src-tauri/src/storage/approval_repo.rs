@@ -0,0 +1,114 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, types::Type, Row};
+use uuid::Uuid;
+
+use crate::models::PendingApproval;
+use crate::storage::database::Database;
+
+pub struct ApprovalRepository {
+    db: Database,
+}
+
+impl ApprovalRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Stores a pending approval. A file already waiting on the same rule keeps
+    /// its id and age; only its hash, captures and plan are refreshed.
+    pub fn upsert(&self, mut approval: PendingApproval) -> Result<PendingApproval> {
+        let captures_json = serde_json::to_string(&approval.captures)?;
+        let planned_json = serde_json::to_string(&approval.planned)?;
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO pending_approvals (id, rule_id, file_path, file_hash, captures, planned, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) ON CONFLICT(rule_id, file_path) DO UPDATE SET file_hash = excluded.file_hash, captures = excluded.captures, planned = excluded.planned",
+                params![
+                    Uuid::new_v4().to_string(),
+                    approval.rule_id,
+                    approval.file_path,
+                    approval.file_hash,
+                    captures_json,
+                    planned_json,
+                    Utc::now().to_rfc3339(),
+                ],
+            )?;
+            let (id, created_at): (String, String) = conn.query_row(
+                "SELECT id, created_at FROM pending_approvals WHERE rule_id = ?1 AND file_path = ?2",
+                params![approval.rule_id, approval.file_path],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            approval.id = id;
+            approval.created_at = DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc);
+            Ok(approval)
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<PendingApproval>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT a.id, a.rule_id, r.name, a.file_path, a.file_hash, a.captures, a.planned, a.created_at FROM pending_approvals a JOIN rules r ON r.id = a.rule_id ORDER BY a.created_at DESC",
+            )?;
+            let rows = stmt.query_map([], map_approval)?;
+            let mut approvals = Vec::new();
+            for approval in rows {
+                approvals.push(approval?);
+            }
+            Ok(approvals)
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<PendingApproval>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT a.id, a.rule_id, r.name, a.file_path, a.file_hash, a.captures, a.planned, a.created_at FROM pending_approvals a JOIN rules r ON r.id = a.rule_id WHERE a.id = ?1",
+            )?;
+            let mut rows = stmt.query_map(params![id], map_approval)?;
+            Ok(rows.next().transpose()?)
+        })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute("DELETE FROM pending_approvals WHERE id = ?1", params![id])?;
+            Ok(())
+        })
+    }
+
+    /// Deletes approvals older than `expiry_hours`. Zero keeps them forever.
+    pub fn cleanup_expired(&self, expiry_hours: u32) -> Result<()> {
+        if expiry_hours == 0 {
+            return Ok(());
+        }
+        let cutoff = Utc::now() - Duration::hours(expiry_hours as i64);
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "DELETE FROM pending_approvals WHERE created_at < ?1",
+                params![cutoff.to_rfc3339()],
+            )?;
+            Ok(())
+        })
+    }
+}
+
+fn map_approval(row: &Row<'_>) -> rusqlite::Result<PendingApproval> {
+    let captures_json: String = row.get(5)?;
+    let planned_json: String = row.get(6)?;
+    let created_at: String = row.get(7)?;
+    let created_at = DateTime::parse_from_rfc3339(&created_at)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(7, Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+    Ok(PendingApproval {
+        id: row.get(0)?,
+        rule_id: row.get(1)?,
+        rule_name: row.get(2)?,
+        file_path: row.get(3)?,
+        file_hash: row.get(4)?,
+        captures: serde_json::from_str(&captures_json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, Type::Text, Box::new(e)))?,
+        planned: serde_json::from_str(&planned_json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, Type::Text, Box::new(e)))?,
+        created_at,
+    })
+}
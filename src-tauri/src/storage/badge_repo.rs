@@ -0,0 +1,44 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+
+use crate::models::BadgeCounts;
+use crate::storage::database::Database;
+use crate::storage::log_repo::unacknowledged_errors;
+
+pub struct BadgeRepository {
+    db: Database,
+}
+
+impl BadgeRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// What's waiting on the user: approvals, conflicts, and errors not yet
+    /// acknowledged or reviewed.
+    pub fn counts(&self) -> Result<BadgeCounts> {
+        self.db.with_conn(|conn| {
+            let count = |sql: &str| -> rusqlite::Result<usize> {
+                conn.query_row(sql, [], |row| row.get::<_, i64>(0))
+                    .map(|count| count.max(0) as usize)
+            };
+            Ok(BadgeCounts {
+                approvals: count("SELECT COUNT(*) FROM pending_approvals")?,
+                conflicts: count("SELECT COUNT(*) FROM pending_conflicts")?,
+                errors: unacknowledged_errors(conn, None, None)? as usize,
+            })
+        })
+    }
+
+    /// Stops counting the errors logged up to `now`.
+    pub fn acknowledge_errors(&self, now: DateTime<Utc>) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO error_acknowledgment (id, acknowledged_at) VALUES (1, ?1) ON CONFLICT(id) DO UPDATE SET acknowledged_at = excluded.acknowledged_at",
+                params![now.to_rfc3339()],
+            )?;
+            Ok(())
+        })
+    }
+}
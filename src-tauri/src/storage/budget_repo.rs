@@ -0,0 +1,69 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use crate::models::ByteUsage;
+use crate::storage::database::Database;
+
+pub struct BudgetRepository {
+    db: Database,
+}
+
+impl BudgetRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Adds one transferred file of `bytes` to the rule's total for `month`.
+    pub fn record(&self, rule_id: &str, month: &str, action_type: &str, bytes: u64) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO byte_usage (rule_id, month, action_type, bytes, count) VALUES (?1, ?2, ?3, ?4, 1) ON CONFLICT(rule_id, month, action_type) DO UPDATE SET bytes = bytes + excluded.bytes, count = count + 1",
+                params![rule_id, month, action_type, bytes as i64],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn usage(&self, rule_id: &str, month: &str) -> Result<Vec<ByteUsage>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT action_type, bytes, count FROM byte_usage WHERE rule_id = ?1 AND month = ?2 ORDER BY action_type",
+            )?;
+            let rows = stmt.query_map(params![rule_id, month], |row| {
+                Ok(ByteUsage {
+                    action_type: row.get(0)?,
+                    bytes: row.get::<_, i64>(1)?.max(0) as u64,
+                    count: row.get::<_, i64>(2)?.max(0) as u64,
+                })
+            })?;
+            let mut usage = Vec::new();
+            for entry in rows {
+                usage.push(entry?);
+            }
+            Ok(usage)
+        })
+    }
+
+    pub fn used_bytes(&self, rule_id: &str, month: &str) -> Result<u64> {
+        self.db.with_conn(|conn| {
+            let bytes: i64 = conn.query_row(
+                "SELECT COALESCE(SUM(bytes), 0) FROM byte_usage WHERE rule_id = ?1 AND month = ?2",
+                params![rule_id, month],
+                |row| row.get(0),
+            )?;
+            Ok(bytes.max(0) as u64)
+        })
+    }
+
+    /// Notes that the rule ran out of budget in `month`. Returns false when
+    /// that was already noted.
+    pub fn note_exhausted(&self, rule_id: &str, month: &str) -> Result<bool> {
+        self.db.with_conn(|conn| {
+            let inserted = conn.execute(
+                "INSERT OR IGNORE INTO budget_notices (rule_id, month) VALUES (?1, ?2)",
+                params![rule_id, month],
+            )?;
+            Ok(inserted > 0)
+        })
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+use uuid::Uuid;
+
+use crate::models::PendingConflict;
+use crate::storage::database::Database;
+
+const COLUMNS: &str = "c.id, c.rule_id, r.name, c.trace_id, c.action_type, c.action_index, c.actions, c.captures, c.source, c.destination, c.created_at";
+
+pub struct ConflictRepository {
+    db: Database,
+}
+
+impl ConflictRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Stores a suspended action under a new id. A conflict already pending
+    /// for the same file is replaced.
+    pub fn insert(&self, mut conflict: PendingConflict) -> Result<PendingConflict> {
+        conflict.id = Uuid::new_v4().to_string();
+        let action_type = serde_json::to_string(&conflict.action_type)?;
+        let actions = serde_json::to_string(&conflict.actions)?;
+        let captures = serde_json::to_string(&conflict.captures)?;
+        let source = serde_json::to_string(&conflict.source)?;
+        let destination = serde_json::to_string(&conflict.destination)?;
+
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO pending_conflicts (id, rule_id, trace_id, action_type, action_index, actions, captures, source_path, source, destination, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    conflict.id,
+                    conflict.rule_id,
+                    conflict.trace_id,
+                    action_type,
+                    conflict.action_index as i64,
+                    actions,
+                    captures,
+                    conflict.source.path,
+                    source,
+                    destination,
+                    conflict.created_at.to_rfc3339(),
+                ],
+            )?;
+            Ok(conflict)
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<PendingConflict>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {COLUMNS} FROM pending_conflicts c JOIN rules r ON r.id = c.rule_id ORDER BY c.created_at DESC"
+            ))?;
+            let rows = stmt.query_map([], map_conflict)?;
+            let mut conflicts = Vec::new();
+            for conflict in rows {
+                conflicts.push(conflict?);
+            }
+            Ok(conflicts)
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<PendingConflict>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {COLUMNS} FROM pending_conflicts c JOIN rules r ON r.id = c.rule_id WHERE c.id = ?1"
+            ))?;
+            let mut rows = stmt.query_map(params![id], map_conflict)?;
+            Ok(rows.next().transpose()?)
+        })
+    }
+
+    /// Whether the file at `path` is waiting on a conflict.
+    pub fn is_pending(&self, path: &str) -> Result<bool> {
+        self.db.with_conn(|conn| {
+            let found = conn
+                .query_row(
+                    "SELECT 1 FROM pending_conflicts WHERE source_path = ?1",
+                    params![path],
+                    |_| Ok(()),
+                )
+                .optional()?;
+            Ok(found.is_some())
+        })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute("DELETE FROM pending_conflicts WHERE id = ?1", params![id])?;
+            Ok(())
+        })
+    }
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row<'_>, index: usize) -> rusqlite::Result<T> {
+    let json: String = row.get(index)?;
+    serde_json::from_str(&json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+fn map_conflict(row: &Row<'_>) -> rusqlite::Result<PendingConflict> {
+    let created_at: String = row.get(10)?;
+    let created_at = DateTime::parse_from_rfc3339(&created_at)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(10, Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+    let action_index: i64 = row.get(5)?;
+    Ok(PendingConflict {
+        id: row.get(0)?,
+        rule_id: row.get(1)?,
+        rule_name: row.get(2)?,
+        trace_id: row.get(3)?,
+        action_type: json_column(row, 4)?,
+        action_index: action_index as usize,
+        actions: json_column(row, 6)?,
+        captures: json_column(row, 7)?,
+        source: json_column(row, 8)?,
+        destination: json_column(row, 9)?,
+        created_at,
+    })
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use crate::storage::database::Database;
+
+pub struct CounterRepository {
+    db: Database,
+}
+
+impl CounterRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Takes the rule's next number, starting at 1. One statement reads and
+    /// advances the counter, so runs on other threads never get the same one.
+    pub fn reserve(&self, rule_id: &str) -> Result<u64> {
+        self.db.with_conn(|conn| {
+            let value: i64 = conn.query_row(
+                "INSERT INTO rule_counters (rule_id, value) VALUES (?1, 1) ON CONFLICT(rule_id) DO UPDATE SET value = MAX(value, 0) + 1 RETURNING value",
+                params![rule_id],
+                |row| row.get(0),
+            )?;
+            Ok(value.max(1) as u64)
+        })
+    }
+
+    /// Hands `value` back for the rule's next file, as long as no number
+    /// after it was taken in the meantime. Returns whether it was.
+    pub fn release(&self, rule_id: &str, value: u64) -> Result<bool> {
+        self.db.with_conn(|conn| {
+            let changed = conn.execute(
+                "UPDATE rule_counters SET value = value - 1 WHERE rule_id = ?1 AND value = ?2",
+                params![rule_id, value as i64],
+            )?;
+            Ok(changed > 0)
+        })
+    }
+
+    /// Starts the rule's numbering over at 1.
+    pub fn reset(&self, rule_id: &str) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "DELETE FROM rule_counters WHERE rule_id = ?1",
+                params![rule_id],
+            )?;
+            Ok(())
+        })
+    }
+}
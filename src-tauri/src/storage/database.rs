@@ -1,5 +1,6 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
 
 use anyhow::{anyhow, Result};
 use directories::ProjectDirs;
@@ -8,9 +9,12 @@ use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::Connection;
 use rusqlite_migration::{Migrations, M};
 
+/// Every clone shares one pool, so `replace_file` reopens the database for
+/// all of them at once.
 #[derive(Clone)]
 pub struct Database {
-    pool: Pool<SqliteConnectionManager>,
+    pool: Arc<RwLock<Pool<SqliteConnectionManager>>>,
+    path: Arc<PathBuf>,
 }
 
 impl Database {
@@ -20,46 +24,164 @@ impl Database {
     }
 
     pub fn new_with_path(path: PathBuf) -> Result<Self> {
-        let manager = SqliteConnectionManager::file(path);
-        let pool = Pool::new(manager)?;
-        let db = Self { pool };
+        let pool = Pool::new(SqliteConnectionManager::file(&path))?;
+        let db = Self {
+            pool: Arc::new(RwLock::new(pool)),
+            path: Arc::new(path),
+        };
         db.migrate()?;
         Ok(db)
     }
 
-    #[allow(dead_code)]
-    pub fn pool(&self) -> &Pool<SqliteConnectionManager> {
-        &self.pool
+    pub fn path(&self) -> &Path {
+        &self.path
     }
 
     #[allow(dead_code)]
     pub fn get_conn(&self) -> Result<PooledConnection<SqliteConnectionManager>> {
-        Ok(self.pool.get()?)
+        Ok(self.read_pool()?.get()?)
     }
 
+    /// Runs `f` on a pooled connection. `replace_file` waits for running
+    /// calls to finish, so `f` must not block on another database call.
     pub fn with_conn<T>(&self, f: impl FnOnce(&mut Connection) -> Result<T>) -> Result<T> {
-        let mut conn = self.pool.get()?;
+        let pool = self.read_pool()?;
+        let mut conn = pool.get()?;
         f(&mut conn)
     }
 
+    /// The schema version of this database, as `PRAGMA user_version`.
+    pub fn schema_version(&self) -> Result<i64> {
+        self.with_conn(|conn| {
+            Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+        })
+    }
+
+    /// Writes a consistent copy of the database to `dest`, which must not
+    /// exist yet. Safe while other connections write.
+    pub fn backup_into(&self, dest: &Path) -> Result<()> {
+        let dest = dest
+            .to_str()
+            .ok_or_else(|| anyhow!("Backup path isn't valid UTF-8: {}", dest.display()))?;
+        self.with_conn(|conn| {
+            conn.execute("VACUUM INTO ?1", [dest])?;
+            Ok(())
+        })
+    }
+
+    /// Swaps the database file for `staged`, keeping the current file at
+    /// `previous`. All connections are closed first and the pool is reopened
+    /// on the new file, migrated to the current schema. If the new file can't
+    /// be opened, the old one is put back.
+    pub fn replace_file(&self, staged: &Path, previous: &Path) -> Result<()> {
+        let mut pool = self
+            .pool
+            .write()
+            .map_err(|_| anyhow!("Database lock poisoned"))?;
+        pool.get()?
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        // Dropping the only handle closes every connection, which also
+        // removes the -wal and -shm files.
+        *pool = Pool::builder()
+            .max_size(1)
+            .build_unchecked(SqliteConnectionManager::memory());
+
+        if previous.exists() {
+            fs::remove_file(previous)?;
+        }
+        fs::rename(self.path.as_path(), previous)?;
+        if let Err(err) = fs::rename(staged, self.path.as_path()) {
+            fs::rename(previous, self.path.as_path())?;
+            *pool = open_migrated(&self.path)?;
+            return Err(err.into());
+        }
+        match open_migrated(&self.path) {
+            Ok(reopened) => {
+                *pool = reopened;
+                Ok(())
+            }
+            Err(err) => {
+                let _ = fs::rename(self.path.as_path(), staged);
+                fs::rename(previous, self.path.as_path())?;
+                *pool = open_migrated(&self.path)?;
+                Err(err)
+            }
+        }
+    }
+
+    fn read_pool(&self) -> Result<RwLockReadGuard<'_, Pool<SqliteConnectionManager>>> {
+        self.pool
+            .read()
+            .map_err(|_| anyhow!("Database lock poisoned"))
+    }
+
     fn migrate(&self) -> Result<()> {
-        let mut conn = self.pool.get()?;
-        conn.pragma_update(None, "foreign_keys", true)?;
-        conn.pragma_update(None, "journal_mode", "WAL")?;
-
-        let migrations = Migrations::new(vec![
-            M::up(include_str!("migrations/001_initial.sql")),
-            M::up(include_str!("migrations/002_undo.sql")),
-            M::up(include_str!("migrations/003_folder_settings.sql")),
-            M::up(include_str!("migrations/004_folder_duplicates.sql")),
-            M::up(include_str!("migrations/005_incomplete_downloads.sql")),
-            M::up(include_str!("migrations/006_folder_groups.sql")),
-        ]);
-        migrations.to_latest(&mut conn)?;
-        Ok(())
+        let mut conn = self.read_pool()?.get()?;
+        prepare(&mut conn)
     }
 }
 
+fn open_migrated(path: &Path) -> Result<Pool<SqliteConnectionManager>> {
+    let pool = Pool::new(SqliteConnectionManager::file(path))?;
+    let mut conn = pool.get()?;
+    prepare(&mut conn)?;
+    Ok(pool)
+}
+
+fn prepare(conn: &mut Connection) -> Result<()> {
+    conn.pragma_update(None, "foreign_keys", true)?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+
+    migrations().to_latest(conn)?;
+    Ok(())
+}
+
+/// Schema migrations, in order. Exposed to tests so they can stop at an older version.
+pub(crate) fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up(include_str!("migrations/001_initial.sql")),
+        M::up(include_str!("migrations/002_undo.sql")),
+        M::up(include_str!("migrations/003_folder_settings.sql")),
+        M::up(include_str!("migrations/004_folder_duplicates.sql")),
+        M::up(include_str!("migrations/005_incomplete_downloads.sql")),
+        M::up(include_str!("migrations/006_folder_groups.sql")),
+        M::up(include_str!("migrations/007_profiles.sql")),
+        M::up(include_str!("migrations/008_rule_schedule.sql")),
+        M::up(include_str!("migrations/009_folder_placeholder_mode.sql")),
+        M::up(include_str!("migrations/010_undo_hash.sql")),
+        M::up(include_str!("migrations/011_approvals.sql")),
+        M::up(include_str!("migrations/012_insights.sql")),
+        M::up(include_str!("migrations/013_rule_min_age.sql")),
+        M::up(include_str!("migrations/014_retries.sql")),
+        M::up(include_str!("migrations/015_rule_path_scope.sql")),
+        M::up(include_str!("migrations/016_rule_extra.sql")),
+        M::up(include_str!("migrations/017_rule_timings.sql")),
+        M::up(include_str!("migrations/018_event_journal.sql")),
+        M::up(include_str!("migrations/019_rule_skip_reference_check.sql")),
+        M::up(include_str!("migrations/020_folder_strict_sequential.sql")),
+        M::up(include_str!("migrations/021_heartbeat.sql")),
+        M::up(include_str!("migrations/022_folder_group_window.sql")),
+        M::up(include_str!("migrations/023_scan_cursors.sql")),
+        M::up(include_str!("migrations/024_rule_byte_budget.sql")),
+        M::up(include_str!("migrations/025_retry_volumes.sql")),
+        M::up(include_str!("migrations/026_classification.sql")),
+        M::up(include_str!("migrations/027_pending_conflicts.sql")),
+        M::up(include_str!("migrations/028_journal_active_app.sql")),
+        M::up(include_str!("migrations/029_duplicate_hash_scheme.sql")),
+        M::up(include_str!("migrations/030_rule_on_reappear.sql")),
+        M::up(include_str!("migrations/031_folder_passthrough.sql")),
+        M::up(include_str!("migrations/032_error_acknowledgment.sql")),
+        M::up(include_str!("migrations/033_log_review.sql")),
+        M::up(include_str!("migrations/034_folder_git.sql")),
+        M::up(include_str!("migrations/035_match_metadata.sql")),
+        M::up(include_str!("migrations/036_folder_thumbnails.sql")),
+        M::up(include_str!("migrations/037_system_trash.sql")),
+        M::up(include_str!("migrations/038_rule_revisions.sql")),
+        M::up(include_str!("migrations/039_rule_counters.sql")),
+        M::up(include_str!("migrations/040_undo_hash_scheme.sql")),
+    ])
+}
+
 pub fn default_db_path() -> Result<PathBuf> {
     let proj = ProjectDirs::from("", "", "file-dispatch")
         .ok_or_else(|| anyhow!("Unable to resolve project directories"))?;
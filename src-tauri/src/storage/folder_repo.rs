@@ -1,15 +1,25 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use rusqlite::{params, types::Type, Row};
+use rusqlite::{params, types::Type, Connection, Row};
 use uuid::Uuid;
 
-use crate::models::Folder;
+use crate::models::{Folder, FolderKind, FolderSettings, PlaceholderMode, Rule};
 use crate::storage::database::Database;
+use crate::storage::rule_repo::{insert_rule, update_rule};
 
 pub struct FolderRepository {
     db: Database,
 }
 
+/// A folder to create along with its rules; see `create_with_rules`.
+pub struct FolderSetup {
+    pub path: String,
+    pub name: String,
+    /// Options other than the defaults, if any.
+    pub settings: Option<FolderSettings>,
+    pub rules: Vec<Rule>,
+}
+
 impl FolderRepository {
     pub fn new(db: Database) -> Self {
         Self { db }
@@ -18,7 +28,7 @@ impl FolderRepository {
     pub fn list(&self) -> Result<Vec<Folder>> {
         self.db.with_conn(|conn| {
             let mut stmt = conn.prepare(
-                "SELECT f.id, f.path, f.name, f.enabled, f.created_at, f.updated_at, f.scan_depth, f.remove_duplicates, f.trash_incomplete_downloads, f.incomplete_timeout_minutes, f.parent_id, f.is_group, COUNT(r.id) as rule_count
+                "SELECT f.id, f.path, f.name, f.enabled, f.created_at, f.updated_at, f.scan_depth, f.remove_duplicates, f.trash_incomplete_downloads, f.incomplete_timeout_minutes, f.parent_id, f.is_group, f.placeholder_mode, f.strict_sequential, f.group_window_ms, f.passthrough_patterns, f.respect_git, f.skip_git_tracked, f.activity_thumbnails, f.kind, f.trash_max_mb, COUNT(r.id) as rule_count
                  FROM folders f
                  LEFT JOIN rules r ON r.folder_id = f.id
                  GROUP BY f.id
@@ -36,7 +46,7 @@ impl FolderRepository {
     pub fn get(&self, id: &str) -> Result<Option<Folder>> {
         self.db.with_conn(|conn| {
             let mut stmt = conn.prepare(
-                "SELECT f.id, f.path, f.name, f.enabled, f.created_at, f.updated_at, f.scan_depth, f.remove_duplicates, f.trash_incomplete_downloads, f.incomplete_timeout_minutes, f.parent_id, f.is_group, COUNT(r.id) as rule_count
+                "SELECT f.id, f.path, f.name, f.enabled, f.created_at, f.updated_at, f.scan_depth, f.remove_duplicates, f.trash_incomplete_downloads, f.incomplete_timeout_minutes, f.parent_id, f.is_group, f.placeholder_mode, f.strict_sequential, f.group_window_ms, f.passthrough_patterns, f.respect_git, f.skip_git_tracked, f.activity_thumbnails, f.kind, f.trash_max_mb, COUNT(r.id) as rule_count
                  FROM folders f
                  LEFT JOIN rules r ON r.folder_id = f.id
                  WHERE f.id = ?1
@@ -47,46 +57,48 @@ impl FolderRepository {
         })
     }
 
-    pub fn create(&self, path: &str, name: &str) -> Result<Folder> {
-        let now = Utc::now();
-        let folder = Folder {
-            id: Uuid::new_v4().to_string(),
-            path: path.to_string(),
-            name: name.to_string(),
-            enabled: true,
-            created_at: now,
-            updated_at: now,
-            rule_count: 0,
-            scan_depth: 0,
-            remove_duplicates: false,
-            trash_incomplete_downloads: false,
-            incomplete_timeout_minutes: 60,
-            parent_id: None,
-            is_group: false,
-        };
+    /// Creates a folder standing for the system trash, which has no path.
+    pub fn create_system_trash(&self, name: &str) -> Result<Folder> {
+        let mut folder = new_folder("", name);
+        folder.kind = FolderKind::SystemTrash;
+        self.db.with_conn(|conn| {
+            insert_folder(conn, &folder)?;
+            Ok(folder)
+        })
+    }
 
+    pub fn create(&self, path: &str, name: &str) -> Result<Folder> {
+        let folder = new_folder(path, name);
         self.db.with_conn(|conn| {
-            conn.execute(
-                "INSERT INTO folders (id, path, name, enabled, created_at, updated_at, scan_depth, remove_duplicates, trash_incomplete_downloads, incomplete_timeout_minutes, parent_id, is_group) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-                params![
-                    folder.id,
-                    folder.path,
-                    folder.name,
-                    bool_to_i64(folder.enabled),
-                    folder.created_at.to_rfc3339(),
-                    folder.updated_at.to_rfc3339(),
-                    folder.scan_depth,
-                    bool_to_i64(folder.remove_duplicates),
-                    bool_to_i64(folder.trash_incomplete_downloads),
-                    folder.incomplete_timeout_minutes as i64,
-                    folder.parent_id,
-                    bool_to_i64(folder.is_group),
-                ],
-            )?;
+            insert_folder(conn, &folder)?;
             Ok(folder)
         })
     }
 
+    /// Creates the folders and their rules in one transaction: either all of
+    /// them exist afterwards or none do.
+    pub fn create_with_rules(&self, setups: Vec<FolderSetup>) -> Result<Vec<(Folder, Vec<Rule>)>> {
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            let mut created = Vec::new();
+            for setup in setups {
+                let mut folder = new_folder(&setup.path, &setup.name);
+                if let Some(settings) = &setup.settings {
+                    folder.apply_settings(settings);
+                }
+                insert_folder(&tx, &folder)?;
+                let mut rules = Vec::new();
+                for mut rule in setup.rules {
+                    rule.folder_id = folder.id.clone();
+                    rules.push(insert_rule(&tx, rule)?);
+                }
+                created.push((folder, rules));
+            }
+            tx.commit()?;
+            Ok(created)
+        })
+    }
+
     pub fn delete(&self, id: &str) -> Result<()> {
         self.db.with_conn(|conn| {
             // Reparent children to the parent of the deleted folder (move them up one level)
@@ -126,22 +138,23 @@ impl FolderRepository {
         })
     }
 
-    pub fn update_settings(
-        &self,
-        id: &str,
-        scan_depth: i32,
-        remove_duplicates: bool,
-        trash_incomplete_downloads: bool,
-        incomplete_timeout_minutes: u32,
-    ) -> Result<()> {
+    pub fn update_settings(&self, id: &str, settings: &FolderSettings) -> Result<()> {
         self.db.with_conn(|conn| {
             conn.execute(
-                "UPDATE folders SET scan_depth = ?1, remove_duplicates = ?2, trash_incomplete_downloads = ?3, incomplete_timeout_minutes = ?4, updated_at = ?5 WHERE id = ?6",
+                "UPDATE folders SET scan_depth = ?1, remove_duplicates = ?2, trash_incomplete_downloads = ?3, incomplete_timeout_minutes = ?4, placeholder_mode = ?5, strict_sequential = ?6, group_window_ms = ?7, passthrough_patterns = ?8, respect_git = ?9, skip_git_tracked = ?10, activity_thumbnails = ?11, trash_max_mb = ?12, updated_at = ?13 WHERE id = ?14",
                 params![
-                    scan_depth,
-                    bool_to_i64(remove_duplicates),
-                    bool_to_i64(trash_incomplete_downloads),
-                    incomplete_timeout_minutes as i64,
+                    settings.scan_depth,
+                    bool_to_i64(settings.remove_duplicates),
+                    bool_to_i64(settings.trash_incomplete_downloads),
+                    settings.incomplete_timeout_minutes as i64,
+                    settings.placeholder_mode.as_str(),
+                    bool_to_i64(settings.strict_sequential),
+                    settings.group_window_ms as i64,
+                    serde_json::to_string(&settings.passthrough_patterns)?,
+                    bool_to_i64(settings.respect_git),
+                    bool_to_i64(settings.skip_git_tracked),
+                    bool_to_i64(settings.activity_thumbnails),
+                    settings.trash_max_mb as i64,
                     Utc::now().to_rfc3339(),
                     id,
                 ],
@@ -149,6 +162,23 @@ impl FolderRepository {
             Ok(())
         })
     }
+    /// Points the folder at `path`, keeping its id and rules, and saves
+    /// `rules` (rewritten for the new path) in the same transaction.
+    pub fn relink(&self, id: &str, path: &str, rules: &[Rule]) -> Result<()> {
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "UPDATE folders SET path = ?1, updated_at = ?2 WHERE id = ?3",
+                params![path, Utc::now().to_rfc3339(), id],
+            )?;
+            for rule in rules {
+                update_rule(&tx, rule)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
     pub fn move_folder(&self, id: &str, parent_id: Option<String>) -> Result<()> {
         self.db.with_conn(|conn| {
             conn.execute(
@@ -175,11 +205,20 @@ impl FolderRepository {
             incomplete_timeout_minutes: 60,
             parent_id,
             is_group: true,
+            placeholder_mode: PlaceholderMode::default(),
+            strict_sequential: false,
+            group_window_ms: 0,
+            passthrough_patterns: Vec::new(),
+            respect_git: false,
+            skip_git_tracked: false,
+            activity_thumbnails: false,
+            kind: FolderKind::Directory,
+            trash_max_mb: 0,
         };
 
         self.db.with_conn(|conn| {
             conn.execute(
-                "INSERT INTO folders (id, path, name, enabled, created_at, updated_at, scan_depth, remove_duplicates, trash_incomplete_downloads, incomplete_timeout_minutes, parent_id, is_group) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                "INSERT INTO folders (id, path, name, enabled, created_at, updated_at, scan_depth, remove_duplicates, trash_incomplete_downloads, incomplete_timeout_minutes, parent_id, is_group, placeholder_mode, strict_sequential, group_window_ms, passthrough_patterns, respect_git, skip_git_tracked, activity_thumbnails, kind, trash_max_mb) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
                 params![
                     folder.id,
                     folder.path,
@@ -193,6 +232,15 @@ impl FolderRepository {
                     folder.incomplete_timeout_minutes as i64,
                     folder.parent_id,
                     bool_to_i64(folder.is_group),
+                    folder.placeholder_mode.as_str(),
+                    bool_to_i64(folder.strict_sequential),
+                    folder.group_window_ms as i64,
+                    serde_json::to_string(&folder.passthrough_patterns)?,
+                    bool_to_i64(folder.respect_git),
+                    bool_to_i64(folder.skip_git_tracked),
+                    bool_to_i64(folder.activity_thumbnails),
+                    folder.kind.as_str(),
+                    folder.trash_max_mb as i64,
                 ],
             )?;
             Ok(folder)
@@ -200,6 +248,64 @@ impl FolderRepository {
     }
 }
 
+fn new_folder(path: &str, name: &str) -> Folder {
+    let now = Utc::now();
+    Folder {
+        id: Uuid::new_v4().to_string(),
+        path: path.to_string(),
+        name: name.to_string(),
+        enabled: true,
+        created_at: now,
+        updated_at: now,
+        rule_count: 0,
+        scan_depth: 0,
+        remove_duplicates: false,
+        trash_incomplete_downloads: false,
+        incomplete_timeout_minutes: 60,
+        parent_id: None,
+        is_group: false,
+        placeholder_mode: PlaceholderMode::default(),
+        strict_sequential: false,
+        group_window_ms: 0,
+        passthrough_patterns: Vec::new(),
+        respect_git: false,
+        skip_git_tracked: false,
+        activity_thumbnails: false,
+        kind: FolderKind::Directory,
+        trash_max_mb: 0,
+    }
+}
+
+fn insert_folder(conn: &Connection, folder: &Folder) -> Result<()> {
+    conn.execute(
+        "INSERT INTO folders (id, path, name, enabled, created_at, updated_at, scan_depth, remove_duplicates, trash_incomplete_downloads, incomplete_timeout_minutes, parent_id, is_group, placeholder_mode, strict_sequential, group_window_ms, passthrough_patterns, respect_git, skip_git_tracked, activity_thumbnails, kind, trash_max_mb) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
+        params![
+            folder.id,
+            folder.path,
+            folder.name,
+            bool_to_i64(folder.enabled),
+            folder.created_at.to_rfc3339(),
+            folder.updated_at.to_rfc3339(),
+            folder.scan_depth,
+            bool_to_i64(folder.remove_duplicates),
+            bool_to_i64(folder.trash_incomplete_downloads),
+            folder.incomplete_timeout_minutes as i64,
+            folder.parent_id,
+            bool_to_i64(folder.is_group),
+            folder.placeholder_mode.as_str(),
+            bool_to_i64(folder.strict_sequential),
+            folder.group_window_ms as i64,
+            serde_json::to_string(&folder.passthrough_patterns)?,
+            bool_to_i64(folder.respect_git),
+            bool_to_i64(folder.skip_git_tracked),
+            bool_to_i64(folder.activity_thumbnails),
+            folder.kind.as_str(),
+            folder.trash_max_mb as i64,
+        ],
+    )?;
+    Ok(())
+}
+
 fn map_folder(row: &Row<'_>) -> rusqlite::Result<Folder> {
     let created_at: String = row.get(4)?;
     let updated_at: String = row.get(5)?;
@@ -209,9 +315,27 @@ fn map_folder(row: &Row<'_>) -> rusqlite::Result<Folder> {
     let incomplete_timeout_minutes: i64 = row.get(9)?;
     let parent_id: Option<String> = row.get(10)?;
     let is_group: bool = i64_to_bool(row.get(11)?);
+    let placeholder_mode: Option<String> = row.get(12)?;
+    let placeholder_mode = placeholder_mode
+        .map(|value| PlaceholderMode::parse(&value))
+        .unwrap_or_default();
+    let strict_sequential = row.get::<_, Option<i64>>(13)?.is_some_and(i64_to_bool);
+    let group_window_ms = row.get::<_, Option<i64>>(14)?.unwrap_or(0).max(0) as u64;
     let incomplete_timeout_minutes = incomplete_timeout_minutes.max(1) as u32;
-    // rule_count is now at index 12 in the query
-    let rule_count: i64 = row.get(12)?;
+    let passthrough_patterns = row
+        .get::<_, Option<String>>(15)?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let respect_git = row.get::<_, Option<i64>>(16)?.is_some_and(i64_to_bool);
+    let skip_git_tracked = row.get::<_, Option<i64>>(17)?.is_some_and(i64_to_bool);
+    let activity_thumbnails = row.get::<_, Option<i64>>(18)?.is_some_and(i64_to_bool);
+    let kind = row
+        .get::<_, Option<String>>(19)?
+        .map(|value| FolderKind::parse(&value))
+        .unwrap_or_default();
+    let trash_max_mb = row.get::<_, Option<i64>>(20)?.unwrap_or(0).max(0) as u64;
+    // rule_count is now at index 21 in the query
+    let rule_count: i64 = row.get(21)?;
     let created_at = DateTime::parse_from_rfc3339(&created_at)
         .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(e)))?
         .with_timezone(&Utc);
@@ -231,6 +355,15 @@ fn map_folder(row: &Row<'_>) -> rusqlite::Result<Folder> {
         incomplete_timeout_minutes,
         parent_id,
         is_group,
+        placeholder_mode,
+        strict_sequential,
+        group_window_ms,
+        passthrough_patterns,
+        respect_git,
+        skip_git_tracked,
+        activity_thumbnails,
+        kind,
+        trash_max_mb,
         rule_count,
     })
 }
@@ -0,0 +1,38 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension};
+
+use crate::storage::database::Database;
+
+pub struct HeartbeatRepository {
+    db: Database,
+}
+
+impl HeartbeatRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// When the app was last known to be caught up; `None` before the first run.
+    pub fn last_seen(&self) -> Result<Option<DateTime<Utc>>> {
+        self.db.with_conn(|conn| {
+            let value: Option<String> = conn
+                .query_row("SELECT seen_at FROM heartbeat WHERE id = 1", [], |row| row.get(0))
+                .optional()?;
+            Ok(match value {
+                Some(value) => Some(DateTime::parse_from_rfc3339(&value)?.with_timezone(&Utc)),
+                None => None,
+            })
+        })
+    }
+
+    pub fn touch(&self, now: DateTime<Utc>) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO heartbeat (id, seen_at) VALUES (1, ?1) ON CONFLICT(id) DO UPDATE SET seen_at = excluded.seen_at",
+                params![now.to_rfc3339()],
+            )?;
+            Ok(())
+        })
+    }
+}
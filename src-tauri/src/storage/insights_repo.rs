@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, types::Type, Connection, OptionalExtension};
+
+use crate::models::DailyInsight;
+use crate::storage::database::Database;
+
+/// Per-day activity from the logs table between `?1` (inclusive) and `?2`
+/// (exclusive, or unbounded when NULL). A file counts once per day however many
+/// organizing actions ran on it; undos and failed actions count as interventions.
+const DAILY_FROM_LOGS: &str = "SELECT substr(created_at, 1, 10) AS day,
+        COUNT(DISTINCT CASE WHEN status = 'success' AND action_type IN ('move', 'copy', 'rename', 'sortIntoSubfolder', 'archive', 'delete', 'deletePermanently') THEN file_path END) AS files_sorted,
+        COALESCE(SUM(CASE WHEN status = 'success' AND action_type IN ('move', 'copy', 'sortIntoSubfolder') THEN CAST(json_extract(action_detail, '$.metadata.size_bytes') AS INTEGER) END), 0) AS bytes_moved,
+        SUM(CASE WHEN action_type = 'undo' OR status = 'error' THEN 1 ELSE 0 END) AS interventions
+    FROM logs
+    WHERE created_at >= ?1 AND (?2 IS NULL OR created_at < ?2)
+    GROUP BY day";
+
+/// Per-day, per-classification activity from the logs table between `?1` and
+/// `?2`, bounded like `DAILY_FROM_LOGS` and counted the same way.
+const CLASSIFIED_FROM_LOGS: &str = "SELECT substr(created_at, 1, 10) AS day,
+        json_extract(action_detail, '$.metadata.classification') AS code,
+        COUNT(DISTINCT file_path) AS files,
+        COALESCE(SUM(CASE WHEN action_type IN ('move', 'copy', 'sortIntoSubfolder') THEN CAST(json_extract(action_detail, '$.metadata.size_bytes') AS INTEGER) END), 0) AS bytes
+    FROM logs
+    WHERE status = 'success' AND action_type IN ('move', 'copy', 'rename', 'sortIntoSubfolder', 'archive', 'delete', 'deletePermanently')
+        AND json_extract(action_detail, '$.metadata.classification') IS NOT NULL
+        AND created_at >= ?1 AND (?2 IS NULL OR created_at < ?2)
+    GROUP BY day, code";
+
+pub struct InsightsRepository {
+    db: Database,
+}
+
+impl InsightsRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Rolled-up days merged with the logs still on disk, keyed by UTC day.
+    pub fn daily(&self) -> Result<BTreeMap<NaiveDate, DailyInsight>> {
+        self.db.with_conn(|conn| {
+            let mut days = BTreeMap::new();
+
+            let mut stmt = conn.prepare(
+                "SELECT day, files_sorted, bytes_moved, interventions FROM insight_rollups",
+            )?;
+            let rows = stmt.query_map([], map_daily)?;
+            for row in rows {
+                let (day, insight) = row?;
+                add_day(&mut days, day, insight);
+            }
+
+            let since = reset_at(conn)?.map(|at| at.to_rfc3339()).unwrap_or_default();
+            let mut stmt = conn.prepare(DAILY_FROM_LOGS)?;
+            let rows = stmt.query_map(params![since, Option::<String>::None], map_daily)?;
+            for row in rows {
+                let (day, insight) = row?;
+                add_day(&mut days, day, insight);
+            }
+            Ok(days)
+        })
+    }
+
+    /// Files and bytes per classification code on the UTC days from `from` up
+    /// to but not including `to`, from the rollups and the logs still on disk.
+    /// Resetting insights doesn't affect these.
+    pub fn classification_totals(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<BTreeMap<String, (u64, u64)>> {
+        let (from, to) = (
+            from.format("%Y-%m-%d").to_string(),
+            to.format("%Y-%m-%d").to_string(),
+        );
+        self.db.with_conn(|conn| {
+            let mut totals = BTreeMap::new();
+            let mut add = |row: rusqlite::Result<(String, i64, i64)>| -> Result<()> {
+                let (code, files, bytes) = row?;
+                let entry: &mut (u64, u64) = totals.entry(code).or_default();
+                entry.0 += files.max(0) as u64;
+                entry.1 += bytes.max(0) as u64;
+                Ok(())
+            };
+
+            let mut stmt = conn.prepare(
+                "SELECT code, files, bytes FROM classification_rollups WHERE day >= ?1 AND day < ?2",
+            )?;
+            let rows = stmt.query_map(params![from, to], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?;
+            for row in rows {
+                add(row)?;
+            }
+
+            let mut stmt = conn.prepare(&format!(
+                "SELECT code, files, bytes FROM ({CLASSIFIED_FROM_LOGS})"
+            ))?;
+            let rows = stmt.query_map(params![from, to], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?;
+            for row in rows {
+                add(row)?;
+            }
+            Ok(totals)
+        })
+    }
+
+    pub fn reset_at(&self) -> Result<Option<DateTime<Utc>>> {
+        self.db.with_conn(|conn| reset_at(conn))
+    }
+
+    /// Drops the rollups and ignores every log written before `now`.
+    pub fn reset(&self, now: DateTime<Utc>) -> Result<()> {
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM insight_rollups", [])?;
+            tx.execute(
+                "INSERT INTO insight_state (id, reset_at) VALUES (1, ?1) ON CONFLICT(id) DO UPDATE SET reset_at = excluded.reset_at",
+                params![now.to_rfc3339()],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+}
+
+/// Folds the logs written before `before` (all of them when `None`) into the
+/// daily and classification rollups. Call it in the same transaction that
+/// deletes those logs.
+pub(crate) fn rollup_logs(conn: &Connection, before: Option<&DateTime<Utc>>) -> Result<()> {
+    let since = reset_at(conn)?.map(|at| at.to_rfc3339()).unwrap_or_default();
+    conn.execute(
+        &format!(
+            "INSERT INTO insight_rollups (day, files_sorted, bytes_moved, interventions)
+             SELECT * FROM ({DAILY_FROM_LOGS}) WHERE true
+             ON CONFLICT(day) DO UPDATE SET
+                files_sorted = files_sorted + excluded.files_sorted,
+                bytes_moved = bytes_moved + excluded.bytes_moved,
+                interventions = interventions + excluded.interventions"
+        ),
+        params![since, before.map(|at| at.to_rfc3339())],
+    )?;
+    conn.execute(
+        &format!(
+            "INSERT INTO classification_rollups (day, code, files, bytes)
+             SELECT * FROM ({CLASSIFIED_FROM_LOGS}) WHERE true
+             ON CONFLICT(day, code) DO UPDATE SET
+                files = files + excluded.files,
+                bytes = bytes + excluded.bytes"
+        ),
+        params!["", before.map(|at| at.to_rfc3339())],
+    )?;
+    Ok(())
+}
+
+fn reset_at(conn: &Connection) -> Result<Option<DateTime<Utc>>> {
+    let value: Option<String> = conn
+        .query_row("SELECT reset_at FROM insight_state WHERE id = 1", [], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    Ok(match value {
+        Some(value) => Some(DateTime::parse_from_rfc3339(&value)?.with_timezone(&Utc)),
+        None => None,
+    })
+}
+
+fn map_daily(row: &rusqlite::Row<'_>) -> rusqlite::Result<(NaiveDate, DailyInsight)> {
+    let day: String = row.get(0)?;
+    let day = NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))?;
+    Ok((
+        day,
+        DailyInsight {
+            files_sorted: row.get::<_, i64>(1)?.max(0) as u64,
+            bytes_moved: row.get::<_, i64>(2)?.max(0) as u64,
+            interventions: row.get::<_, i64>(3)?.max(0) as u64,
+        },
+    ))
+}
+
+fn add_day(days: &mut BTreeMap<NaiveDate, DailyInsight>, day: NaiveDate, insight: DailyInsight) {
+    let entry = days.entry(day).or_default();
+    entry.files_sorted += insight.files_sorted;
+    entry.bytes_moved += insight.bytes_moved;
+    entry.interventions += insight.interventions;
+}
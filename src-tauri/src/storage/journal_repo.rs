@@ -0,0 +1,140 @@
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, types::Type, Row};
+
+use crate::models::{EventDisposition, JournalEntry, JournalQuery};
+use crate::storage::database::Database;
+
+/// Entries returned by a query that doesn't set a limit.
+const DEFAULT_QUERY_LIMIT: u32 = 500;
+
+/// A change to the journal, applied in order with others in one transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JournalWrite {
+    Append(JournalEntry),
+    Settle {
+        id: i64,
+        disposition: EventDisposition,
+        detail: Option<String>,
+    },
+}
+
+pub struct JournalRepository {
+    db: Database,
+}
+
+impl JournalRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Applies `writes`, then drops all but the newest `max_entries` entries.
+    /// Settling an entry that was already dropped does nothing.
+    pub fn apply(&self, writes: &[JournalWrite], max_entries: u32) -> Result<()> {
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            for write in writes {
+                match write {
+                    JournalWrite::Append(entry) => {
+                        let active_app = entry
+                            .active_app
+                            .as_ref()
+                            .map(serde_json::to_string)
+                            .transpose()?;
+                        tx.execute(
+                            "INSERT OR REPLACE INTO event_journal (id, received_at, path, kind, folder_id, disposition, detail, active_app) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                            params![
+                                entry.id,
+                                timestamp(&entry.received_at),
+                                entry.path,
+                                entry.kind,
+                                entry.folder_id,
+                                entry.disposition.map(|d| d.as_str()),
+                                entry.detail,
+                                active_app,
+                            ],
+                        )?;
+                    }
+                    JournalWrite::Settle {
+                        id,
+                        disposition,
+                        detail,
+                    } => {
+                        tx.execute(
+                            "UPDATE event_journal SET disposition = ?2, detail = ?3 WHERE id = ?1",
+                            params![id, disposition.as_str(), detail],
+                        )?;
+                    }
+                }
+            }
+            tx.execute(
+                "DELETE FROM event_journal WHERE id <= (SELECT id FROM event_journal ORDER BY id DESC LIMIT 1 OFFSET ?1)",
+                params![max_entries],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Entries matching `query`, newest first.
+    pub fn query(&self, query: &JournalQuery) -> Result<Vec<JournalEntry>> {
+        let path_contains = query
+            .path_contains
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty());
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, received_at, path, kind, folder_id, disposition, detail, active_app FROM event_journal WHERE (?1 IS NULL OR instr(lower(path), lower(?1)) > 0) AND (?2 IS NULL OR received_at >= ?2) AND (?3 IS NULL OR received_at <= ?3) ORDER BY id DESC LIMIT ?4",
+            )?;
+            let rows = stmt.query_map(
+                params![
+                    path_contains,
+                    query.since.as_ref().map(timestamp),
+                    query.until.as_ref().map(timestamp),
+                    query.limit.unwrap_or(DEFAULT_QUERY_LIMIT),
+                ],
+                map_entry,
+            )?;
+            let mut entries = Vec::new();
+            for entry in rows {
+                entries.push(entry?);
+            }
+            Ok(entries)
+        })
+    }
+
+    /// The highest id handed out so far, so ids keep increasing across restarts.
+    pub fn max_id(&self) -> Result<i64> {
+        self.db.with_conn(|conn| {
+            let id: Option<i64> =
+                conn.query_row("SELECT MAX(id) FROM event_journal", [], |row| row.get(0))?;
+            Ok(id.unwrap_or(0))
+        })
+    }
+}
+
+/// Fixed-width, so stored timestamps compare correctly as text.
+fn timestamp(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn map_entry(row: &Row<'_>) -> rusqlite::Result<JournalEntry> {
+    let received_at: String = row.get(1)?;
+    let received_at = DateTime::parse_from_rfc3339(&received_at)
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(1, Type::Text, Box::new(err)))?
+        .with_timezone(&Utc);
+    let disposition: Option<String> = row.get(5)?;
+    // An app that doesn't read back is left out rather than failing the query.
+    let active_app: Option<String> = row.get(7)?;
+    Ok(JournalEntry {
+        id: row.get(0)?,
+        received_at,
+        path: row.get(2)?,
+        kind: row.get(3)?,
+        folder_id: row.get(4)?,
+        disposition: disposition.as_deref().and_then(EventDisposition::parse),
+        detail: row.get(6)?,
+        active_app: active_app.and_then(|json| serde_json::from_str(&json).ok()),
+    })
+}
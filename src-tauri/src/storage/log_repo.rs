@@ -1,10 +1,18 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
-use rusqlite::{params, types::Type, Row};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row};
 use uuid::Uuid;
 
-use crate::models::{LogEntry, LogStatus};
+use crate::models::{
+    DigestActionTotal, LogEntry, LogStatus, RuleActivity, StatusCounts, UnreviewedSummary,
+};
 use crate::storage::database::Database;
+use crate::storage::insights_repo::rollup_logs;
+
+/// Entries stamped this soon after a review or an errors acknowledgment are
+/// taken as seen by it, in case the clock that stamped them ran a little
+/// ahead.
+const CLOCK_SKEW_SECONDS: i64 = 2;
 
 pub struct LogRepository {
     db: Database,
@@ -19,26 +27,8 @@ impl LogRepository {
         entry.id = Uuid::new_v4().to_string();
         entry.created_at = Utc::now();
 
-        let detail_json = match &entry.action_detail {
-            Some(detail) => Some(serde_json::to_string(detail)?),
-            None => None,
-        };
-
         self.db.with_conn(|conn| {
-            conn.execute(
-                "INSERT INTO logs (id, rule_id, rule_name, file_path, action_type, action_detail, status, error_message, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-                params![
-                    entry.id,
-                    entry.rule_id,
-                    entry.rule_name,
-                    entry.file_path,
-                    entry.action_type,
-                    detail_json,
-                    log_status_to_str(&entry.status),
-                    entry.error_message,
-                    entry.created_at.to_rfc3339(),
-                ],
-            )?;
+            insert_log(conn, &entry)?;
             Ok(entry)
         })
     }
@@ -57,25 +47,302 @@ impl LogRepository {
         })
     }
 
+    /// Successful actions per action type between `from` (inclusive) and `to`
+    /// (exclusive), with the size of the files moved, copied or sorted, and
+    /// the number of failed actions not yet acknowledged. Earlier digests
+    /// aren't counted.
+    pub fn action_totals(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<(Vec<DigestActionTotal>, u64)> {
+        let (from, to) = (from.to_rfc3339(), to.to_rfc3339());
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT action_type, COUNT(*), COALESCE(SUM(CASE WHEN action_type IN ('move', 'copy', 'sortIntoSubfolder') THEN CAST(json_extract(action_detail, '$.metadata.size_bytes') AS INTEGER) END), 0)
+                FROM logs
+                WHERE status = 'success' AND action_type NOT IN ('digest', 'backup', 'restore') AND created_at >= ?1 AND created_at < ?2
+                GROUP BY action_type
+                ORDER BY COUNT(*) DESC, action_type",
+            )?;
+            let rows = stmt.query_map(params![from, to], |row| {
+                Ok(DigestActionTotal {
+                    action_type: row.get(0)?,
+                    count: row.get::<_, i64>(1)? as u64,
+                    bytes: row.get::<_, i64>(2)?.max(0) as u64,
+                })
+            })?;
+            let mut totals = Vec::new();
+            for total in rows {
+                totals.push(total?);
+            }
+            let errors = unacknowledged_errors(conn, Some(&from), Some(&to))?;
+            Ok((totals, errors))
+        })
+    }
+
+    /// What `rule_id` logged for `file_path` up to `until`, oldest first.
+    pub fn for_rule_and_path(
+        &self,
+        rule_id: &str,
+        file_path: &str,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<LogEntry>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, rule_id, rule_name, file_path, action_type, action_detail, status, error_message, created_at FROM logs WHERE rule_id = ?1 AND file_path = ?2 AND created_at <= ?3 ORDER BY created_at",
+            )?;
+            let rows = stmt.query_map(params![rule_id, file_path, until.to_rfc3339()], map_log)?;
+            let mut entries = Vec::new();
+            for entry in rows {
+                entries.push(entry?);
+            }
+            Ok(entries)
+        })
+    }
+
+    /// The first move, rename, sort or undo after `after` that took the file
+    /// at `path` somewhere else.
+    pub fn next_move_from(&self, path: &str, after: DateTime<Utc>) -> Result<Option<LogEntry>> {
+        self.db.with_conn(|conn| {
+            let entry = conn
+                .query_row(
+                    "SELECT id, rule_id, rule_name, file_path, action_type, action_detail, status, error_message, created_at FROM logs
+                    WHERE status = 'success' AND created_at > ?2
+                        AND action_type IN ('move', 'rename', 'sortIntoSubfolder', 'undo')
+                        AND json_extract(action_detail, '$.sourcePath') = ?1
+                        AND json_extract(action_detail, '$.destinationPath') IS NOT NULL
+                        AND COALESCE(json_extract(action_detail, '$.metadata.undo_action'), '') != 'copy'
+                    ORDER BY created_at LIMIT 1",
+                    params![path, after.to_rfc3339()],
+                    map_log,
+                )
+                .optional()?;
+            Ok(entry)
+        })
+    }
+
+    /// The day of the most recent digest still in the log.
+    pub fn last_digest_day(&self) -> Result<Option<NaiveDate>> {
+        self.db.with_conn(|conn| {
+            let day: Option<String> = conn
+                .query_row(
+                    "SELECT json_extract(action_detail, '$.metadata.day') FROM logs WHERE action_type = 'digest' ORDER BY created_at DESC LIMIT 1",
+                    [],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .flatten();
+            Ok(day.and_then(|day| day.parse().ok()))
+        })
+    }
+
+    /// Marks everything logged up to `now` as reviewed, which acknowledges
+    /// its errors too.
+    pub fn mark_reviewed(&self, now: DateTime<Utc>) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO log_review (id, reviewed_at, pruned_unreviewed) VALUES (1, ?1, 0) ON CONFLICT(id) DO UPDATE SET reviewed_at = excluded.reviewed_at, pruned_unreviewed = 0",
+                params![now.to_rfc3339()],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// What was logged since the last review, per status and per rule, with
+    /// the latest `error_limit` new errors.
+    pub fn unreviewed_summary(&self, error_limit: usize) -> Result<UnreviewedSummary> {
+        self.db.with_conn(|conn| {
+            let review: Option<(String, i64)> = conn
+                .query_row(
+                    "SELECT reviewed_at, pruned_unreviewed FROM log_review WHERE id = 1",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            let since = review
+                .as_ref()
+                .and_then(|(reviewed_at, _)| DateTime::parse_from_rfc3339(reviewed_at).ok())
+                .map(|reviewed_at| reviewed_at.with_timezone(&Utc));
+            let from = seen_until(review.as_ref().map(|(reviewed_at, _)| reviewed_at.clone()));
+
+            let mut stmt = conn.prepare(
+                "SELECT rule_id, MAX(rule_name), status, COUNT(*) FROM logs WHERE created_at >= ?1 GROUP BY rule_id, status",
+            )?;
+            let rows = stmt.query_map(params![from], |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    log_status_from_str(row.get::<_, String>(2)?.as_str()),
+                    row.get::<_, i64>(3)?.max(0) as u64,
+                ))
+            })?;
+            let mut counts = StatusCounts::default();
+            let mut by_rule: Vec<RuleActivity> = Vec::new();
+            for row in rows {
+                let (rule_id, rule_name, status, count) = row?;
+                counts.add(&status, count);
+                let position = match by_rule.iter().position(|rule| rule.rule_id == rule_id) {
+                    Some(position) => position,
+                    None => {
+                        by_rule.push(RuleActivity {
+                            rule_id,
+                            rule_name: None,
+                            counts: StatusCounts::default(),
+                        });
+                        by_rule.len() - 1
+                    }
+                };
+                let rule = &mut by_rule[position];
+                rule.rule_name = rule.rule_name.take().or(rule_name);
+                rule.counts.add(&status, count);
+            }
+            by_rule.sort_by(|a, b| {
+                b.counts
+                    .total()
+                    .cmp(&a.counts.total())
+                    .then_with(|| a.rule_name.cmp(&b.rule_name))
+            });
+            let rules_with_errors = by_rule
+                .iter()
+                .filter(|rule| rule.counts.error > 0)
+                .cloned()
+                .collect();
+
+            let mut stmt = conn.prepare(
+                "SELECT id, rule_id, rule_name, file_path, action_type, action_detail, status, error_message, created_at FROM logs WHERE status = 'error' AND created_at >= ?1 ORDER BY created_at DESC LIMIT ?2",
+            )?;
+            let rows = stmt.query_map(params![from, error_limit as i64], map_log)?;
+            let mut recent_errors = Vec::new();
+            for entry in rows {
+                recent_errors.push(entry?);
+            }
+
+            Ok(UnreviewedSummary {
+                since,
+                counts,
+                by_rule,
+                rules_with_errors,
+                recent_errors,
+                pruned_before_review: review.map_or(0, |(_, pruned)| pruned.max(0) as u64),
+            })
+        })
+    }
+
+    /// Deletes every log; their totals are kept in the insight rollups.
     pub fn clear(&self) -> Result<()> {
         self.db.with_conn(|conn| {
-            conn.execute("DELETE FROM logs", [])?;
+            let tx = conn.transaction()?;
+            rollup_logs(&tx, None)?;
+            tx.execute("DELETE FROM logs", [])?;
+            tx.commit()?;
             Ok(())
         })
     }
 
+    /// Deletes logs older than `retention_days`, rolling them up first. The
+    /// cutoff is rounded down to midnight UTC so a day is rolled up in one go.
     pub fn cleanup(&self, retention_days: u32) -> Result<()> {
-        let cutoff = Utc::now() - chrono::Duration::days(retention_days as i64);
+        let cutoff = (Utc::now() - chrono::Duration::days(retention_days as i64))
+            .date_naive()
+            .and_time(NaiveTime::MIN)
+            .and_utc();
         self.db.with_conn(|conn| {
-            conn.execute(
+            let tx = conn.transaction()?;
+            rollup_logs(&tx, Some(&cutoff))?;
+            // Counted so the next review can say what it never got to see.
+            let reviewed_at = tx
+                .query_row("SELECT reviewed_at FROM log_review WHERE id = 1", [], |row| {
+                    row.get::<_, String>(0)
+                })
+                .optional()?;
+            if let Some(reviewed_at) = reviewed_at {
+                tx.execute(
+                    "UPDATE log_review SET pruned_unreviewed = pruned_unreviewed + (SELECT COUNT(*) FROM logs WHERE created_at < ?1 AND created_at >= ?2) WHERE id = 1",
+                    params![cutoff.to_rfc3339(), seen_until(Some(reviewed_at))],
+                )?;
+            }
+            tx.execute(
                 "DELETE FROM logs WHERE created_at < ?1",
                 params![cutoff.to_rfc3339()],
             )?;
+            // Revisions go with the logs that ran them; each rule's current
+            // one stays.
+            tx.execute(
+                "DELETE FROM rule_revisions WHERE created_at < ?1 AND revision < COALESCE((SELECT revision FROM rules WHERE rules.id = rule_revisions.rule_id), revision + 1)",
+                params![cutoff.to_rfc3339()],
+            )?;
+            tx.commit()?;
             Ok(())
         })
     }
 }
 
+/// Writes `entry` with the id and time it already has.
+pub(crate) fn insert_log(conn: &Connection, entry: &LogEntry) -> Result<()> {
+    let detail_json = match &entry.action_detail {
+        Some(detail) => Some(serde_json::to_string(detail)?),
+        None => None,
+    };
+    conn.execute(
+        "INSERT INTO logs (id, rule_id, rule_name, file_path, action_type, action_detail, status, error_message, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            entry.id,
+            entry.rule_id,
+            entry.rule_name,
+            entry.file_path,
+            entry.action_type,
+            detail_json,
+            log_status_to_str(&entry.status),
+            entry.error_message,
+            entry.created_at.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Where entries not yet seen start, given when they were last seen:
+/// `CLOCK_SKEW_SECONDS` after it, or the start of the log if never.
+fn seen_until(seen_at: Option<String>) -> String {
+    seen_at
+        .and_then(|seen_at| DateTime::parse_from_rfc3339(&seen_at).ok())
+        .map(|seen_at| {
+            (seen_at.with_timezone(&Utc) + chrono::Duration::seconds(CLOCK_SKEW_SECONDS))
+                .to_rfc3339()
+        })
+        .unwrap_or_default()
+}
+
+/// Errors logged between `from` (inclusive) and `to` (exclusive), when
+/// given, that haven't been acknowledged. Reviewing the log acknowledges its
+/// errors just as acknowledging them from the tray does, and the badge, the
+/// digest and the review summary all count them here so their numbers agree.
+pub(crate) fn unacknowledged_errors(
+    conn: &Connection,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> rusqlite::Result<u64> {
+    let acknowledged_at: Option<String> = conn
+        .query_row(
+            "SELECT acknowledged_at FROM error_acknowledgment WHERE id = 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let reviewed_at: Option<String> = conn
+        .query_row("SELECT reviewed_at FROM log_review WHERE id = 1", [], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    let since = seen_until(acknowledged_at.max(reviewed_at));
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM logs WHERE status = 'error' AND created_at >= MAX(?1, ?2) AND (?3 IS NULL OR created_at < ?3)",
+        params![since, from.unwrap_or_default(), to],
+        |row| row.get(0),
+    )?;
+    Ok(count.max(0) as u64)
+}
+
 fn map_log(row: &Row<'_>) -> rusqlite::Result<LogEntry> {
     let detail_json: Option<String> = row.get(5)?;
     let created_at: String = row.get(8)?;
@@ -107,6 +374,7 @@ fn log_status_to_str(status: &LogStatus) -> &str {
         LogStatus::Success => "success",
         LogStatus::Error => "error",
         LogStatus::Skipped => "skipped",
+        LogStatus::Warning => "warning",
     }
 }
 
@@ -115,6 +383,7 @@ fn log_status_from_str(value: &str) -> LogStatus {
         "success" => LogStatus::Success,
         "error" => LogStatus::Error,
         "skipped" => LogStatus::Skipped,
+        "warning" => LogStatus::Warning,
         _ => LogStatus::Error,
     }
 }
@@ -1,9 +1,45 @@
+use std::collections::BTreeMap;
+
 use anyhow::Result;
-use chrono::Utc;
-use rusqlite::params;
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
 
 use crate::storage::database::Database;
 
+/// A file a rule matched, as recorded when it ran.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch {
+    pub file_path: String,
+    pub matched_at: DateTime<Utc>,
+    /// What the rule's `record_metadata` resolved to.
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// A match found by `MatchRepository::query`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchRecord {
+    pub rule_id: String,
+    pub file_path: String,
+    pub file_hash: Option<String>,
+    pub matched_at: DateTime<Utc>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+pub struct MatchQuery<'a> {
+    pub rule_id: Option<&'a str>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Pairs each match must have recorded, exactly.
+    pub metadata: &'a BTreeMap<String, String>,
+    /// Keys looked up through `match_metadata`; others are read out of
+    /// each record.
+    pub indexed_keys: &'a [String],
+    pub limit: usize,
+}
+
 pub struct MatchRepository {
     db: Database,
 }
@@ -100,11 +136,32 @@ impl MatchRepository {
         file_path: &str,
         file_hash: Option<&str>,
     ) -> Result<()> {
+        self.record_match_with(rule_id, file_path, file_hash, &BTreeMap::new(), &[])
+    }
+
+    /// Records the match with what the rule's `record_metadata` resolved to,
+    /// indexing the pairs whose keys are in `indexed_keys`.
+    pub fn record_match_with(
+        &self,
+        rule_id: &str,
+        file_path: &str,
+        file_hash: Option<&str>,
+        metadata: &BTreeMap<String, String>,
+        indexed_keys: &[String],
+    ) -> Result<()> {
+        let matched_at = Utc::now();
         self.db.with_conn(|conn| {
-            conn.execute(
-                "INSERT OR REPLACE INTO rule_matches (rule_id, file_path, file_hash, matched_at) VALUES (?1, ?2, ?3, ?4)",
-                params![rule_id, file_path, file_hash, Utc::now().to_rfc3339()],
+            let tx = conn.transaction()?;
+            write_match(
+                &tx,
+                rule_id,
+                file_path,
+                file_hash,
+                matched_at,
+                metadata,
+                indexed_keys,
             )?;
+            tx.commit()?;
             Ok(())
         })
     }
@@ -115,10 +172,191 @@ impl MatchRepository {
                 "DELETE FROM rule_matches WHERE rule_id = ?1",
                 params![rule_id],
             )?;
+            conn.execute(
+                "DELETE FROM match_metadata WHERE rule_id = ?1",
+                params![rule_id],
+            )?;
             Ok(())
         })
     }
 
+    /// Rebuilds `match_metadata` from the recorded metadata after the
+    /// indexed keys changed.
+    pub fn reindex_metadata(&self, indexed_keys: &[String]) -> Result<()> {
+        let keys = serde_json::to_string(indexed_keys)?;
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM match_metadata", [])?;
+            tx.execute(
+                "INSERT INTO match_metadata (rule_id, file_path, key, value) SELECT m.rule_id, m.file_path, pair.key, pair.value FROM rule_matches m, json_each(m.metadata) pair WHERE m.metadata IS NOT NULL AND pair.key IN (SELECT value FROM json_each(?1))",
+                params![keys],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Up to `limit` matches, newest first, recorded by `rule_id` (or any
+    /// rule) between `since` and `until` with all of the given pairs.
+    pub fn query(&self, query: &MatchQuery) -> Result<Vec<MatchRecord>> {
+        let mut sql = String::from(
+            "SELECT m.rule_id, m.file_path, m.file_hash, m.matched_at, m.metadata FROM rule_matches m WHERE (?1 IS NULL OR m.rule_id = ?1) AND (?2 IS NULL OR m.matched_at >= ?2) AND (?3 IS NULL OR m.matched_at <= ?3)",
+        );
+        let mut values = vec![
+            query.rule_id.map_or(Value::Null, |id| Value::Text(id.to_string())),
+            query.since.map_or(Value::Null, |at| Value::Text(at.to_rfc3339())),
+            query.until.map_or(Value::Null, |at| Value::Text(at.to_rfc3339())),
+        ];
+        for (key, value) in query.metadata {
+            let (key_param, value_param) = (values.len() + 1, values.len() + 2);
+            if query.indexed_keys.contains(key) {
+                sql.push_str(&format!(
+                    " AND (m.rule_id, m.file_path) IN (SELECT rule_id, file_path FROM match_metadata WHERE key = ?{key_param} AND value = ?{value_param})"
+                ));
+                values.push(Value::Text(key.clone()));
+            } else {
+                sql.push_str(&format!(
+                    " AND json_extract(m.metadata, ?{key_param}) = ?{value_param}"
+                ));
+                values.push(Value::Text(json_path(key)));
+            }
+            values.push(Value::Text(value.clone()));
+        }
+        sql.push_str(&format!(
+            " ORDER BY m.matched_at DESC, m.file_path LIMIT ?{}",
+            values.len() + 1
+        ));
+        values.push(Value::Integer(query.limit as i64));
+
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?;
+            let mut records = Vec::new();
+            for row in rows {
+                let (rule_id, file_path, file_hash, matched_at, metadata) = row?;
+                let Ok(matched_at) = DateTime::parse_from_rfc3339(&matched_at) else {
+                    continue;
+                };
+                records.push(MatchRecord {
+                    rule_id,
+                    file_path,
+                    file_hash,
+                    matched_at: matched_at.with_timezone(&Utc),
+                    metadata: parse_metadata(metadata.as_deref()),
+                });
+            }
+            Ok(records)
+        })
+    }
+
+    /// Most recently matched file paths across all rules of a folder.
+    pub fn recent_paths_for_folder(&self, folder_id: &str, limit: usize) -> Result<Vec<String>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT m.file_path FROM rule_matches m JOIN rules r ON r.id = m.rule_id WHERE r.folder_id = ?1 GROUP BY m.file_path ORDER BY MAX(m.matched_at) DESC LIMIT ?2",
+            )?;
+            let rows = stmt.query_map(params![folder_id, limit as i64], |row| {
+                row.get::<_, String>(0)
+            })?;
+            let mut paths = Vec::new();
+            for row in rows {
+                paths.push(row?);
+            }
+            Ok(paths)
+        })
+    }
+
+    /// Distinct file paths matched by any rule of a folder since `since`.
+    pub fn paths_for_folder_since(
+        &self,
+        folder_id: &str,
+        since: chrono::DateTime<Utc>,
+    ) -> Result<std::collections::HashSet<String>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT DISTINCT m.file_path FROM rule_matches m JOIN rules r ON r.id = m.rule_id WHERE r.folder_id = ?1 AND m.matched_at >= ?2",
+            )?;
+            let rows = stmt.query_map(params![folder_id, since.to_rfc3339()], |row| {
+                row.get::<_, String>(0)
+            })?;
+            let mut paths = std::collections::HashSet::new();
+            for row in rows {
+                paths.insert(row?);
+            }
+            Ok(paths)
+        })
+    }
+
+    /// Up to `limit` files `rule_id` matched between `since` and `until`,
+    /// oldest first, continuing after `after`, the last of the previous page.
+    pub fn page_for_rule(
+        &self,
+        rule_id: &str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        after: Option<&RuleMatch>,
+        limit: usize,
+    ) -> Result<Vec<RuleMatch>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT file_path, matched_at, metadata FROM rule_matches WHERE rule_id = ?1 AND (?2 IS NULL OR matched_at >= ?2) AND (?3 IS NULL OR matched_at <= ?3) AND (?4 IS NULL OR matched_at > ?4 OR (matched_at = ?4 AND file_path > ?5)) ORDER BY matched_at, file_path LIMIT ?6",
+            )?;
+            let rows = stmt.query_map(
+                params![
+                    rule_id,
+                    since.map(|at| at.to_rfc3339()),
+                    until.map(|at| at.to_rfc3339()),
+                    after.map(|last| last.matched_at.to_rfc3339()),
+                    after.map(|last| last.file_path.as_str()),
+                    limit as i64,
+                ],
+                map_rule_match,
+            )?;
+            let mut matches = Vec::new();
+            for row in rows {
+                let (file_path, matched_at, metadata) = row?;
+                let Ok(matched_at) = DateTime::parse_from_rfc3339(&matched_at) else {
+                    continue;
+                };
+                matches.push(RuleMatch {
+                    file_path,
+                    matched_at: matched_at.with_timezone(&Utc),
+                    metadata: parse_metadata(metadata.as_deref()),
+                });
+            }
+            Ok(matches)
+        })
+    }
+
+    /// The latest file `rule_id` matched with this hash.
+    pub fn latest_for_hash(&self, rule_id: &str, file_hash: &str) -> Result<Option<RuleMatch>> {
+        self.db.with_conn(|conn| {
+            let row = conn
+                .query_row(
+                    "SELECT file_path, matched_at, metadata FROM rule_matches WHERE rule_id = ?1 AND file_hash = ?2 ORDER BY matched_at DESC LIMIT 1",
+                    params![rule_id, file_hash],
+                    map_rule_match,
+                )
+                .optional()?;
+            Ok(row.and_then(|(file_path, matched_at, metadata)| {
+                let matched_at = DateTime::parse_from_rfc3339(&matched_at).ok()?;
+                Some(RuleMatch {
+                    file_path,
+                    matched_at: matched_at.with_timezone(&Utc),
+                    metadata: parse_metadata(metadata.as_deref()),
+                })
+            }))
+        })
+    }
+
     /// Get the last time this file was matched by any rule
     pub fn get_last_match_time(&self, file_path: &str) -> Result<Option<chrono::DateTime<Utc>>> {
         self.db.with_conn(|conn| {
@@ -141,3 +379,55 @@ impl MatchRepository {
         })
     }
 }
+
+/// Writes one match and its indexed metadata; callers hold the transaction.
+pub(crate) fn write_match(
+    conn: &Connection,
+    rule_id: &str,
+    file_path: &str,
+    file_hash: Option<&str>,
+    matched_at: DateTime<Utc>,
+    metadata: &BTreeMap<String, String>,
+    indexed_keys: &[String],
+) -> Result<()> {
+    let metadata_json = if metadata.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(metadata)?)
+    };
+    conn.execute(
+        "INSERT OR REPLACE INTO rule_matches (rule_id, file_path, file_hash, matched_at, metadata) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![rule_id, file_path, file_hash, matched_at.to_rfc3339(), metadata_json],
+    )?;
+    conn.execute(
+        "DELETE FROM match_metadata WHERE rule_id = ?1 AND file_path = ?2",
+        params![rule_id, file_path],
+    )?;
+    for (key, value) in metadata {
+        if indexed_keys.contains(key) {
+            conn.execute(
+                "INSERT INTO match_metadata (rule_id, file_path, key, value) VALUES (?1, ?2, ?3, ?4)",
+                params![rule_id, file_path, key, value],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+type MatchRow = (String, String, Option<String>);
+
+fn map_rule_match(row: &rusqlite::Row<'_>) -> rusqlite::Result<MatchRow> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+}
+
+/// Recorded metadata; a record that doesn't parse reads as having none.
+fn parse_metadata(json: Option<&str>) -> BTreeMap<String, String> {
+    json.and_then(|json| serde_json::from_str(json).ok()).unwrap_or_default()
+}
+
+/// `key` as a JSON path into a record's metadata, quoted so dots and
+/// brackets in it are taken literally. Keys can't contain quotes (see
+/// `validate_rule`).
+fn json_path(key: &str) -> String {
+    format!("$.\"{key}\"")
+}
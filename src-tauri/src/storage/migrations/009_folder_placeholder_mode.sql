@@ -0,0 +1,2 @@
+-- How online-only cloud placeholders are handled per folder
+ALTER TABLE folders ADD COLUMN placeholder_mode TEXT DEFAULT 'skip';
@@ -0,0 +1,2 @@
+-- Hash of the file at current_path when the action ran; NULL for legacy entries
+ALTER TABLE undo_entries ADD COLUMN hash TEXT;
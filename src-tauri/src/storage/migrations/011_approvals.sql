@@ -0,0 +1,15 @@
+ALTER TABLE rules ADD COLUMN approval_required INTEGER NOT NULL DEFAULT 0;
+
+-- Matches of approval-required rules waiting for the user to confirm them
+CREATE TABLE IF NOT EXISTS pending_approvals (
+    id TEXT PRIMARY KEY,
+    rule_id TEXT NOT NULL REFERENCES rules(id) ON DELETE CASCADE,
+    file_path TEXT NOT NULL,
+    file_hash TEXT NOT NULL,
+    captures TEXT NOT NULL,
+    planned TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    UNIQUE (rule_id, file_path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_approvals_created_at ON pending_approvals(created_at);
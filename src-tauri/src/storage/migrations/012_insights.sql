@@ -0,0 +1,14 @@
+-- Per-day totals for logs that have been pruned or cleared, so insights
+-- survive log retention. Days are UTC dates (YYYY-MM-DD).
+CREATE TABLE IF NOT EXISTS insight_rollups (
+    day TEXT PRIMARY KEY,
+    files_sorted INTEGER NOT NULL DEFAULT 0,
+    bytes_moved INTEGER NOT NULL DEFAULT 0,
+    interventions INTEGER NOT NULL DEFAULT 0
+);
+
+-- Single row holding when insights were last reset; logs before it are ignored.
+CREATE TABLE IF NOT EXISTS insight_state (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    reset_at TEXT NOT NULL
+);
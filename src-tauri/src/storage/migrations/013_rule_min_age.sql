@@ -0,0 +1,2 @@
+-- Seconds a file must sit unmodified before the rule acts on it; NULL for no gate
+ALTER TABLE rules ADD COLUMN min_age_seconds INTEGER;
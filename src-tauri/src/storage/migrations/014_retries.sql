@@ -0,0 +1,16 @@
+-- Rule runs that failed with a transient error, waiting to be tried again
+CREATE TABLE IF NOT EXISTS retries (
+    id TEXT PRIMARY KEY,
+    rule_id TEXT NOT NULL REFERENCES rules(id) ON DELETE CASCADE,
+    folder_id TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    next_attempt_at TEXT NOT NULL,
+    last_error TEXT NOT NULL,
+    -- Set while the retry's event is queued, so the next pass doesn't send it twice
+    claimed_at TEXT,
+    created_at TEXT NOT NULL,
+    UNIQUE (rule_id, file_path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_retries_next_attempt_at ON retries(next_attempt_at);
@@ -0,0 +1,2 @@
+-- Subfolder (or glob) under the folder root a rule is limited to; NULL for the whole folder
+ALTER TABLE rules ADD COLUMN path_scope TEXT;
@@ -0,0 +1,2 @@
+-- Rule-level fields from newer versions this one doesn't know, as a JSON object; NULL when none
+ALTER TABLE rules ADD COLUMN extra TEXT;
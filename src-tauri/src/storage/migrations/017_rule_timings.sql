@@ -0,0 +1,6 @@
+-- Last persisted timing statistics per rule, so they survive a restart
+CREATE TABLE IF NOT EXISTS rule_timings (
+    rule_id TEXT PRIMARY KEY REFERENCES rules(id) ON DELETE CASCADE,
+    stats TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
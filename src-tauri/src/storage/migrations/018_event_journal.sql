@@ -0,0 +1,12 @@
+-- Ring buffer of file events as the engine received them, for debugging
+CREATE TABLE IF NOT EXISTS event_journal (
+    id INTEGER PRIMARY KEY,
+    received_at TEXT NOT NULL,
+    path TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    folder_id TEXT NOT NULL,
+    disposition TEXT,
+    detail TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_event_journal_received_at ON event_journal(received_at);
@@ -0,0 +1,2 @@
+-- 1 when the periodic check for missing apps, scripts and volumes should leave the rule alone
+ALTER TABLE rules ADD COLUMN skip_reference_check INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Process a folder's files one at a time, in arrival order
+ALTER TABLE folders ADD COLUMN strict_sequential INTEGER DEFAULT 0;
@@ -0,0 +1,6 @@
+-- Single row holding when the app last had its startup backlog drained; files
+-- changed after it are caught up on the next start.
+CREATE TABLE IF NOT EXISTS heartbeat (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    seen_at TEXT NOT NULL
+);
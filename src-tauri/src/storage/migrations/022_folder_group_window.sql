@@ -0,0 +1,2 @@
+-- Collect files arriving within this many milliseconds into one group; 0 is off
+ALTER TABLE folders ADD COLUMN group_window_ms INTEGER DEFAULT 0;
@@ -0,0 +1,7 @@
+-- Where a folder scan stopped when it hit the per-run file cap; the next run
+-- carries on after this path.
+CREATE TABLE IF NOT EXISTS scan_cursors (
+    folder_id TEXT PRIMARY KEY REFERENCES folders(id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
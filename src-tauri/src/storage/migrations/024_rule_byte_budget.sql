@@ -0,0 +1,19 @@
+-- Most bytes a rule may move, copy or archive in a calendar month; NULL for no limit
+ALTER TABLE rules ADD COLUMN monthly_byte_budget INTEGER;
+
+-- Bytes each rule's successful transfers added up to, per month and action type
+CREATE TABLE IF NOT EXISTS byte_usage (
+    rule_id TEXT NOT NULL REFERENCES rules(id) ON DELETE CASCADE,
+    month TEXT NOT NULL,
+    action_type TEXT NOT NULL,
+    bytes INTEGER NOT NULL DEFAULT 0,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (rule_id, month, action_type)
+);
+
+-- Months in which a rule's budget ran out and the UI was told so
+CREATE TABLE IF NOT EXISTS budget_notices (
+    rule_id TEXT NOT NULL REFERENCES rules(id) ON DELETE CASCADE,
+    month TEXT NOT NULL,
+    PRIMARY KEY (rule_id, month)
+);
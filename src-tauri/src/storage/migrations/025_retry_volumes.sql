@@ -0,0 +1,2 @@
+-- Mount point of the offline destination volume a retry waits for, so it can go as soon as the volume is back
+ALTER TABLE retries ADD COLUMN volume TEXT;
@@ -0,0 +1,17 @@
+-- Retention class a rule's actions are tagged with, e.g. FIN-7Y
+ALTER TABLE rules ADD COLUMN classification TEXT;
+
+-- The rule's classification when the action ran
+ALTER TABLE undo_entries ADD COLUMN classification TEXT;
+
+-- Per-day, per-classification totals for logs that have been pruned or
+-- cleared, so classification reports cover more than log retention. Days are
+-- UTC dates (YYYY-MM-DD). Unlike the insight rollups, resetting insights
+-- leaves these alone.
+CREATE TABLE IF NOT EXISTS classification_rollups (
+    day TEXT NOT NULL,
+    code TEXT NOT NULL,
+    files INTEGER NOT NULL DEFAULT 0,
+    bytes INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, code)
+);
@@ -0,0 +1,17 @@
+-- Moves, copies and renames suspended because their destination exists and
+-- the action asks the user what to do. A file waits on one conflict at a time.
+CREATE TABLE IF NOT EXISTS pending_conflicts (
+    id TEXT PRIMARY KEY,
+    rule_id TEXT NOT NULL REFERENCES rules(id) ON DELETE CASCADE,
+    trace_id TEXT,
+    action_type TEXT NOT NULL,
+    action_index INTEGER NOT NULL,
+    actions TEXT NOT NULL,
+    captures TEXT NOT NULL,
+    source_path TEXT NOT NULL UNIQUE,
+    source TEXT NOT NULL,
+    destination TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_conflicts_created_at ON pending_conflicts(created_at);
@@ -0,0 +1,3 @@
+-- The application in front when the event came in, as JSON; NULL for events
+-- that came in without one, such as retries.
+ALTER TABLE event_journal ADD COLUMN active_app TEXT;
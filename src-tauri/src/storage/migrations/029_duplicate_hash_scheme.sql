@@ -0,0 +1,3 @@
+-- How file_hash was taken: 'sha256' of the whole file, or the sampled
+-- scheme with its parameters, such as 'sampled:4194304+4194304+8x1048576'.
+ALTER TABLE duplicate_removals ADD COLUMN hash_scheme TEXT NOT NULL DEFAULT 'sha256';
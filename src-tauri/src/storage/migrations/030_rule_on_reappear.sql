@@ -0,0 +1,3 @@
+-- What a rule does with a file it moved away that comes back, as JSON.
+-- NULL follows the global setting.
+ALTER TABLE rules ADD COLUMN on_reappear TEXT;
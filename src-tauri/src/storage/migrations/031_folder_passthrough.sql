@@ -0,0 +1,2 @@
+-- Globs for files the watcher drops unseen, as a JSON array
+ALTER TABLE folders ADD COLUMN passthrough_patterns TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,6 @@
+-- Single row holding when the user last acknowledged errors; the tray badge
+-- counts the error logs after it.
+CREATE TABLE IF NOT EXISTS error_acknowledgment (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    acknowledged_at TEXT NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Single row holding when the activity log was last reviewed, and how many
+-- entries logged after that retention deleted before the next review.
+CREATE TABLE IF NOT EXISTS log_review (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    reviewed_at TEXT NOT NULL,
+    pruned_unreviewed INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,3 @@
+-- Git working tree handling, off unless a folder opts in
+ALTER TABLE folders ADD COLUMN respect_git INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE folders ADD COLUMN skip_git_tracked INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,17 @@
+-- Key/value pairs rules write into the record of each file they match.
+-- Patterns on the rule, as a JSON object; NULL when it records none.
+ALTER TABLE rules ADD COLUMN record_metadata TEXT;
+-- What they resolved to for this match, as a JSON object.
+ALTER TABLE rule_matches ADD COLUMN metadata TEXT;
+
+-- The same pairs for the keys in Settings::match_metadata_indexed_keys, so
+-- matches can be looked up by them without reading every record.
+CREATE TABLE IF NOT EXISTS match_metadata (
+    rule_id TEXT NOT NULL REFERENCES rules(id) ON DELETE CASCADE,
+    file_path TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (rule_id, file_path, key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_match_metadata_key_value ON match_metadata(key, value);
@@ -0,0 +1,2 @@
+-- Thumbnails of matched images and PDFs in the activity log, off unless a folder opts in
+ALTER TABLE folders ADD COLUMN activity_thumbnails INTEGER NOT NULL DEFAULT 0;
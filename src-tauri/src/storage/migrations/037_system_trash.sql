@@ -0,0 +1,3 @@
+-- Folders standing for the system trash, and how large the trash may grow
+ALTER TABLE folders ADD COLUMN kind TEXT NOT NULL DEFAULT 'directory';
+ALTER TABLE folders ADD COLUMN trash_max_mb INTEGER NOT NULL DEFAULT 0;
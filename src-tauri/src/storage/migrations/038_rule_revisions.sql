@@ -0,0 +1,15 @@
+-- Counts each rule's saves; log entries record the revision that ran.
+ALTER TABLE rules ADD COLUMN revision INTEGER NOT NULL DEFAULT 1;
+
+-- Each saved revision in full, kept with Settings::keep_rule_revisions and
+-- pruned along with the logs.
+CREATE TABLE IF NOT EXISTS rule_revisions (
+    rule_id TEXT NOT NULL REFERENCES rules(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    content_hash TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (rule_id, revision)
+);
+
+CREATE INDEX IF NOT EXISTS idx_rule_revisions_created_at ON rule_revisions(created_at);
@@ -0,0 +1,5 @@
+-- The last {counter} number each rule moved, copied or renamed a file with
+CREATE TABLE IF NOT EXISTS rule_counters (
+    rule_id TEXT PRIMARY KEY REFERENCES rules(id) ON DELETE CASCADE,
+    value INTEGER NOT NULL
+);
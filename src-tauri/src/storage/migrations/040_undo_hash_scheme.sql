@@ -0,0 +1,3 @@
+-- How hash was taken when it isn't a SHA-256 of the whole file, such as
+-- 'sampled:4194304+4194304+8x1048576' for large files; NULL otherwise.
+ALTER TABLE undo_entries ADD COLUMN hash_scheme TEXT;
@@ -1,9 +1,23 @@
+pub mod approval_repo;
+pub mod badge_repo;
+pub mod budget_repo;
+pub mod conflict_repo;
+pub mod counter_repo;
 pub mod database;
 pub mod folder_repo;
+pub mod heartbeat_repo;
+pub mod insights_repo;
+pub mod journal_repo;
 pub mod log_repo;
 pub mod match_repo;
+pub mod profile_repo;
+pub mod retry_repo;
 pub mod rule_repo;
+pub mod rule_revision_repo;
+pub mod scan_cursor_repo;
+pub mod timing_repo;
 pub mod undo_repo;
+pub mod write_set;
 
 #[cfg(test)]
 mod tests;
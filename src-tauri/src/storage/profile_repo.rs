@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, types::Type, Row};
+use uuid::Uuid;
+
+use crate::models::{Profile, Settings};
+use crate::storage::database::Database;
+
+pub struct ProfileRepository {
+    db: Database,
+}
+
+impl ProfileRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub fn list(&self) -> Result<Vec<Profile>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, name, settings, folder_states, rule_states, is_active, created_at, updated_at FROM profiles ORDER BY name COLLATE NOCASE ASC",
+            )?;
+            let rows = stmt.query_map([], map_profile)?;
+            let mut profiles = Vec::new();
+            for profile in rows {
+                profiles.push(profile?);
+            }
+            Ok(profiles)
+        })
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Result<Option<Profile>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, name, settings, folder_states, rule_states, is_active, created_at, updated_at FROM profiles WHERE name = ?1",
+            )?;
+            let mut rows = stmt.query_map(params![name], map_profile)?;
+            Ok(rows.next().transpose()?)
+        })
+    }
+
+    /// Snapshots `settings` together with the current enabled state of every folder and rule.
+    pub fn create(&self, name: &str, settings: &Settings) -> Result<Profile> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("Profile name cannot be empty"));
+        }
+        if self.get_by_name(name)?.is_some() {
+            return Err(anyhow!("Profile '{}' already exists", name));
+        }
+
+        let (folder_states, rule_states) = self.current_states()?;
+        let now = Utc::now();
+        let profile = Profile {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            settings: settings.clone(),
+            folder_states,
+            rule_states,
+            is_active: false,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let settings_json = serde_json::to_string(&profile.settings)?;
+        let folder_json = serde_json::to_string(&profile.folder_states)?;
+        let rule_json = serde_json::to_string(&profile.rule_states)?;
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO profiles (id, name, settings, folder_states, rule_states, is_active, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?7)",
+                params![
+                    profile.id,
+                    profile.name,
+                    settings_json,
+                    folder_json,
+                    rule_json,
+                    profile.created_at.to_rfc3339(),
+                    profile.updated_at.to_rfc3339(),
+                ],
+            )?;
+            Ok(profile)
+        })
+    }
+
+    pub fn delete_by_name(&self, name: &str) -> Result<()> {
+        self.db.with_conn(|conn| {
+            let removed = conn.execute("DELETE FROM profiles WHERE name = ?1", params![name])?;
+            if removed == 0 {
+                return Err(anyhow!("Profile '{}' not found", name));
+            }
+            Ok(())
+        })
+    }
+
+    /// Applies the profile's folder/rule enabled states and marks it active in a
+    /// single transaction. Ids that no longer exist are ignored.
+    pub fn activate(&self, profile: &Profile) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            for (folder_id, enabled) in &profile.folder_states {
+                tx.execute(
+                    "UPDATE folders SET enabled = ?1, updated_at = ?2 WHERE id = ?3",
+                    params![bool_to_i64(*enabled), now, folder_id],
+                )?;
+            }
+            for (rule_id, enabled) in &profile.rule_states {
+                tx.execute(
+                    "UPDATE rules SET enabled = ?1, updated_at = ?2 WHERE id = ?3",
+                    params![bool_to_i64(*enabled), now, rule_id],
+                )?;
+            }
+            tx.execute(
+                "UPDATE profiles SET is_active = CASE WHEN id = ?1 THEN 1 ELSE 0 END",
+                params![profile.id],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    fn current_states(&self) -> Result<(HashMap<String, bool>, HashMap<String, bool>)> {
+        self.db.with_conn(|conn| {
+            let mut folder_states = HashMap::new();
+            let mut stmt = conn.prepare("SELECT id, enabled FROM folders")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, i64_to_bool(row.get(1)?)))
+            })?;
+            for row in rows {
+                let (id, enabled) = row?;
+                folder_states.insert(id, enabled);
+            }
+
+            let mut rule_states = HashMap::new();
+            let mut stmt = conn.prepare("SELECT id, enabled FROM rules")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, i64_to_bool(row.get(1)?)))
+            })?;
+            for row in rows {
+                let (id, enabled) = row?;
+                rule_states.insert(id, enabled);
+            }
+
+            Ok((folder_states, rule_states))
+        })
+    }
+}
+
+fn map_profile(row: &Row<'_>) -> rusqlite::Result<Profile> {
+    let settings_json: String = row.get(2)?;
+    let folder_json: String = row.get(3)?;
+    let rule_json: String = row.get(4)?;
+    let created_at: String = row.get(6)?;
+    let updated_at: String = row.get(7)?;
+    let created_at = DateTime::parse_from_rfc3339(&created_at)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+    let updated_at = DateTime::parse_from_rfc3339(&updated_at)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(7, Type::Text, Box::new(e)))?
+        .with_timezone(&Utc);
+
+    Ok(Profile {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        settings: serde_json::from_str(&settings_json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(2, Type::Text, Box::new(e))
+        })?,
+        folder_states: serde_json::from_str(&folder_json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(3, Type::Text, Box::new(e))
+        })?,
+        rule_states: serde_json::from_str(&rule_json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(e))
+        })?,
+        is_active: i64_to_bool(row.get(5)?),
+        created_at,
+        updated_at,
+    })
+}
+
+fn bool_to_i64(value: bool) -> i64 {
+    if value {
+        1
+    } else {
+        0
+    }
+}
+
+fn i64_to_bool(value: i64) -> bool {
+    value != 0
+}
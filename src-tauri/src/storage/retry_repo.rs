@@ -0,0 +1,201 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+use uuid::Uuid;
+
+use crate::models::RetryEntry;
+use crate::storage::database::Database;
+
+const SELECT_RETRY: &str = "SELECT t.id, t.rule_id, r.name, t.folder_id, t.file_path, t.attempts, t.next_attempt_at, t.last_error, t.volume, t.created_at FROM retries t JOIN rules r ON r.id = t.rule_id";
+
+pub struct RetryRepository {
+    db: Database,
+}
+
+impl RetryRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Failed attempts recorded for the rule on this file; 0 when none are.
+    pub fn attempts(&self, rule_id: &str, file_path: &str) -> Result<u32> {
+        self.db.with_conn(|conn| {
+            let attempts = conn
+                .query_row(
+                    "SELECT attempts FROM retries WHERE rule_id = ?1 AND file_path = ?2",
+                    params![rule_id, file_path],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(attempts.unwrap_or(0))
+        })
+    }
+
+    /// Records a failed attempt and when to try again, and the offline volume
+    /// it waits for, if any. A file already waiting on the same rule keeps its
+    /// id and age, and is released from any claim.
+    #[allow(clippy::too_many_arguments)]
+    pub fn schedule(
+        &self,
+        rule_id: &str,
+        folder_id: &str,
+        file_path: &str,
+        attempts: u32,
+        next_attempt_at: DateTime<Utc>,
+        last_error: &str,
+        volume: Option<&str>,
+    ) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO retries (id, rule_id, folder_id, file_path, attempts, next_attempt_at, last_error, volume, claimed_at, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, NULL, ?9) ON CONFLICT(rule_id, file_path) DO UPDATE SET folder_id = excluded.folder_id, attempts = excluded.attempts, next_attempt_at = excluded.next_attempt_at, last_error = excluded.last_error, volume = excluded.volume, claimed_at = NULL",
+                params![
+                    Uuid::new_v4().to_string(),
+                    rule_id,
+                    folder_id,
+                    file_path,
+                    attempts,
+                    next_attempt_at.to_rfc3339(),
+                    last_error,
+                    volume,
+                    Utc::now().to_rfc3339(),
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<RetryEntry>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!("{SELECT_RETRY} ORDER BY t.next_attempt_at ASC"))?;
+            let rows = stmt.query_map([], map_retry)?;
+            let mut retries = Vec::new();
+            for retry in rows {
+                retries.push(retry?);
+            }
+            Ok(retries)
+        })
+    }
+
+    /// Claims every retry that is due at `now`, plus any claimed at or before
+    /// `stale_before` whose event never got processed.
+    pub fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        stale_before: DateTime<Utc>,
+    ) -> Result<Vec<RetryEntry>> {
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            let due = {
+                let mut stmt = tx.prepare(&format!(
+                    "{SELECT_RETRY} WHERE (t.claimed_at IS NULL AND t.next_attempt_at <= ?1) OR t.claimed_at <= ?2 ORDER BY t.next_attempt_at ASC"
+                ))?;
+                let rows = stmt.query_map(
+                    params![now.to_rfc3339(), stale_before.to_rfc3339()],
+                    map_retry,
+                )?;
+                let mut due = Vec::new();
+                for retry in rows {
+                    due.push(retry?);
+                }
+                due
+            };
+            for retry in &due {
+                tx.execute(
+                    "UPDATE retries SET claimed_at = ?1 WHERE id = ?2",
+                    params![now.to_rfc3339(), retry.id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(due)
+        })
+    }
+
+    /// Offline volumes with retries waiting for them, and how many each has.
+    pub fn queued_by_volume(&self) -> Result<Vec<(String, usize)>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT volume, COUNT(*) FROM retries WHERE volume IS NOT NULL GROUP BY volume ORDER BY volume",
+            )?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            let mut volumes = Vec::new();
+            for volume in rows {
+                volumes.push(volume?);
+            }
+            Ok(volumes)
+        })
+    }
+
+    /// Makes every retry waiting for `volume` due at `now`. Returns how many.
+    pub fn release_volume(&self, volume: &str, now: DateTime<Utc>) -> Result<usize> {
+        self.db.with_conn(|conn| {
+            let released = conn.execute(
+                "UPDATE retries SET next_attempt_at = ?1, claimed_at = NULL, volume = NULL WHERE volume = ?2",
+                params![now.to_rfc3339(), volume],
+            )?;
+            Ok(released)
+        })
+    }
+
+    /// Makes a retry due at `now`. Returns false if it no longer exists.
+    pub fn force(&self, id: &str, now: DateTime<Utc>) -> Result<bool> {
+        self.db.with_conn(|conn| {
+            let changed = conn.execute(
+                "UPDATE retries SET next_attempt_at = ?1, claimed_at = NULL WHERE id = ?2",
+                params![now.to_rfc3339(), id],
+            )?;
+            Ok(changed > 0)
+        })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute("DELETE FROM retries WHERE id = ?1", params![id])?;
+            Ok(())
+        })
+    }
+
+    /// Drops the retry for the rule on this file, if there is one.
+    pub fn forget(&self, rule_id: &str, file_path: &str) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "DELETE FROM retries WHERE rule_id = ?1 AND file_path = ?2",
+                params![rule_id, file_path],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Drops the retry for the rule on this file if it is still claimed, i.e.
+    /// its run ended without scheduling another attempt.
+    pub fn forget_claimed(&self, rule_id: &str, file_path: &str) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "DELETE FROM retries WHERE rule_id = ?1 AND file_path = ?2 AND claimed_at IS NOT NULL",
+                params![rule_id, file_path],
+            )?;
+            Ok(())
+        })
+    }
+}
+
+fn map_retry(row: &Row<'_>) -> rusqlite::Result<RetryEntry> {
+    Ok(RetryEntry {
+        id: row.get(0)?,
+        rule_id: row.get(1)?,
+        rule_name: row.get(2)?,
+        folder_id: row.get(3)?,
+        file_path: row.get(4)?,
+        attempts: row.get(5)?,
+        next_attempt_at: parse_time(row, 6)?,
+        last_error: row.get(7)?,
+        volume: row.get(8)?,
+        created_at: parse_time(row, 9)?,
+    })
+}
+
+fn parse_time(row: &Row<'_>, index: usize) -> rusqlite::Result<DateTime<Utc>> {
+    let value: String = row.get(index)?;
+    DateTime::parse_from_rfc3339(&value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
@@ -1,9 +1,15 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use rusqlite::{params, types::Type, Row};
+use filedispatch_core::unknown_fields::{merge_unknown, unknown_fields};
+use rusqlite::{params, types::Type, Connection, Row};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
 use uuid::Uuid;
 
-use crate::models::{Rule, RuleId};
+use crate::models::{OnReappear, Rule, RuleId, RuleSchedule};
 use crate::storage::database::Database;
 
 pub struct RuleRepository {
@@ -18,12 +24,20 @@ impl RuleRepository {
     pub fn list_by_folder(&self, folder_id: &str) -> Result<Vec<Rule>> {
         self.db.with_conn(|conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, folder_id, name, enabled, stop_processing, conditions, actions, position, created_at, updated_at FROM rules WHERE folder_id = ?1 ORDER BY position ASC",
+                "SELECT id, folder_id, name, enabled, stop_processing, conditions, actions, position, created_at, updated_at, schedule, approval_required, min_age_seconds, path_scope, extra, skip_reference_check, monthly_byte_budget, classification, on_reappear, record_metadata, revision FROM rules WHERE folder_id = ?1 ORDER BY position ASC",
             )?;
-            let rows = stmt.query_map(params![folder_id], |row| map_rule(row))?;
+            let rows = stmt.query_map(params![folder_id], |row| {
+                // A rule that no longer deserializes stops failing the whole
+                // folder once the health repair has disabled it.
+                let enabled = i64_to_bool(row.get(3)?);
+                match map_rule(row) {
+                    Err(rusqlite::Error::FromSqlConversionFailure(..)) if !enabled => Ok(None),
+                    result => result.map(Some),
+                }
+            })?;
             let mut rules = Vec::new();
             for rule in rows {
-                rules.push(rule?);
+                rules.extend(rule?);
             }
             Ok(rules)
         })
@@ -32,66 +46,59 @@ impl RuleRepository {
     pub fn get(&self, id: &str) -> Result<Option<Rule>> {
         self.db.with_conn(|conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, folder_id, name, enabled, stop_processing, conditions, actions, position, created_at, updated_at FROM rules WHERE id = ?1",
+                "SELECT id, folder_id, name, enabled, stop_processing, conditions, actions, position, created_at, updated_at, schedule, approval_required, min_age_seconds, path_scope, extra, skip_reference_check, monthly_byte_budget, classification, on_reappear, record_metadata, revision FROM rules WHERE id = ?1",
             )?;
             let mut rows = stmt.query_map(params![id], |row| map_rule(row))?;
             Ok(rows.next().transpose()?)
         })
     }
 
-    pub fn create(&self, mut rule: Rule) -> Result<Rule> {
-        let now = Utc::now();
-        rule.id = Uuid::new_v4().to_string();
-        rule.created_at = now;
-        rule.updated_at = now;
-
-        let conditions_json = serde_json::to_string(&rule.conditions)?;
-        let actions_json = serde_json::to_string(&rule.actions)?;
-
+    /// Enabled rules that carry a maintenance schedule, across all folders.
+    pub fn list_scheduled(&self) -> Result<Vec<Rule>> {
         self.db.with_conn(|conn| {
-            let next_position: i32 = conn.query_row(
-                "SELECT COALESCE(MAX(position), -1) + 1 FROM rules WHERE folder_id = ?1",
-                params![rule.folder_id],
-                |row| row.get(0),
+            let mut stmt = conn.prepare(
+                "SELECT id, folder_id, name, enabled, stop_processing, conditions, actions, position, created_at, updated_at, schedule, approval_required, min_age_seconds, path_scope, extra, skip_reference_check, monthly_byte_budget, classification, on_reappear, record_metadata, revision FROM rules WHERE enabled = 1 AND schedule IS NOT NULL ORDER BY folder_id, position ASC",
             )?;
-            rule.position = next_position;
+            let rows = stmt.query_map([], map_rule)?;
+            let mut rules = Vec::new();
+            for rule in rows {
+                rules.push(rule?);
+            }
+            Ok(rules)
+        })
+    }
 
-            conn.execute(
-                "INSERT INTO rules (id, folder_id, name, enabled, stop_processing, conditions, actions, position, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-                params![
-                    rule.id,
-                    rule.folder_id,
-                    rule.name,
-                    bool_to_i64(rule.enabled),
-                    bool_to_i64(rule.stop_processing),
-                    conditions_json,
-                    actions_json,
-                    rule.position,
-                    rule.created_at.to_rfc3339(),
-                    rule.updated_at.to_rfc3339(),
-                ],
+    /// Enabled rules across all folders.
+    pub fn list_enabled(&self) -> Result<Vec<Rule>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, folder_id, name, enabled, stop_processing, conditions, actions, position, created_at, updated_at, schedule, approval_required, min_age_seconds, path_scope, extra, skip_reference_check, monthly_byte_budget, classification, on_reappear, record_metadata, revision FROM rules WHERE enabled = 1 ORDER BY folder_id, position ASC",
             )?;
-            Ok(rule)
+            let rows = stmt.query_map([], map_rule)?;
+            let mut rules = Vec::new();
+            for rule in rows {
+                rules.push(rule?);
+            }
+            Ok(rules)
         })
     }
 
+    pub fn create(&self, rule: Rule) -> Result<Rule> {
+        self.db.with_conn(|conn| insert_rule(conn, rule))
+    }
+
     pub fn update(&self, rule: &Rule) -> Result<()> {
-        let conditions_json = serde_json::to_string(&rule.conditions)?;
-        let actions_json = serde_json::to_string(&rule.actions)?;
+        self.db.with_conn(|conn| update_rule(conn, rule))
+    }
+
+    /// Writes every rule or none of them.
+    pub fn update_many(&self, rules: &[Rule]) -> Result<()> {
         self.db.with_conn(|conn| {
-            conn.execute(
-                "UPDATE rules SET name = ?1, enabled = ?2, stop_processing = ?3, conditions = ?4, actions = ?5, position = ?6, updated_at = ?7 WHERE id = ?8",
-                params![
-                    rule.name,
-                    bool_to_i64(rule.enabled),
-                    bool_to_i64(rule.stop_processing),
-                    conditions_json,
-                    actions_json,
-                    rule.position,
-                    Utc::now().to_rfc3339(),
-                    rule.id,
-                ],
-            )?;
+            let tx = conn.transaction()?;
+            for rule in rules {
+                update_rule(&tx, rule)?;
+            }
+            tx.commit()?;
             Ok(())
         })
     }
@@ -128,11 +135,15 @@ impl RuleRepository {
     }
 }
 
-fn map_rule(row: &Row<'_>) -> rusqlite::Result<Rule> {
+pub(crate) fn map_rule(row: &Row<'_>) -> rusqlite::Result<Rule> {
     let conditions_json: String = row.get(5)?;
     let actions_json: String = row.get(6)?;
     let created_at: String = row.get(8)?;
     let updated_at: String = row.get(9)?;
+    let schedule_json: Option<String> = row.get(10)?;
+    let extra_json: Option<String> = row.get(14)?;
+    let on_reappear_json: Option<String> = row.get(18)?;
+    let record_metadata_json: Option<String> = row.get(19)?;
     let created_at = DateTime::parse_from_rfc3339(&created_at)
         .map_err(|e| rusqlite::Error::FromSqlConversionFailure(8, Type::Text, Box::new(e)))?
         .with_timezone(&Utc);
@@ -140,24 +151,194 @@ fn map_rule(row: &Row<'_>) -> rusqlite::Result<Rule> {
         .map_err(|e| rusqlite::Error::FromSqlConversionFailure(9, Type::Text, Box::new(e)))?
         .with_timezone(&Utc);
 
+    let (conditions, unknown_conditions) = from_json_keeping_unknown(&conditions_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, Type::Text, Box::new(e)))?;
+    let (actions, unknown_actions) = from_json_keeping_unknown(&actions_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, Type::Text, Box::new(e)))?;
+    let mut extra = match extra_json {
+        Some(json) => serde_json::from_str::<Map<String, Value>>(&json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(14, Type::Text, Box::new(e))
+        })?,
+        None => Map::new(),
+    };
+    for (key, unknown) in [("conditions", unknown_conditions), ("actions", unknown_actions)] {
+        if !unknown.is_null() {
+            extra.insert(key.to_string(), unknown);
+        }
+    }
+
     Ok(Rule {
         id: row.get(0)?,
         folder_id: row.get(1)?,
         name: row.get(2)?,
         enabled: i64_to_bool(row.get(3)?),
         stop_processing: i64_to_bool(row.get(4)?),
-        conditions: serde_json::from_str(&conditions_json).map_err(|e| {
-            rusqlite::Error::FromSqlConversionFailure(5, Type::Text, Box::new(e))
-        })?,
-        actions: serde_json::from_str(&actions_json).map_err(|e| {
-            rusqlite::Error::FromSqlConversionFailure(6, Type::Text, Box::new(e))
-        })?,
+        conditions,
+        actions,
         position: row.get(7)?,
+        schedule: schedule_json
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(10, Type::Text, Box::new(e)))?,
+        approval_required: i64_to_bool(row.get(11)?),
+        min_age_seconds: row.get::<_, Option<i64>>(12)?.map(|secs| secs.max(0) as u64),
+        path_scope: row.get(13)?,
+        skip_reference_check: i64_to_bool(row.get(15)?),
+        monthly_byte_budget: row.get::<_, Option<i64>>(16)?.map(|bytes| bytes.max(0) as u64),
+        classification: row.get(17)?,
+        on_reappear: on_reappear_json
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(18, Type::Text, Box::new(e)))?,
+        record_metadata: record_metadata_json
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(19, Type::Text, Box::new(e)))?
+            .unwrap_or_default(),
+        revision: row.get::<_, Option<i64>>(20)?.unwrap_or(1).max(1) as u32,
+        extra: if extra.is_empty() {
+            Value::Null
+        } else {
+            Value::Object(extra)
+        },
         created_at,
         updated_at,
     })
 }
 
+/// Inserts `rule` with a fresh id at the end of its folder.
+pub(crate) fn insert_rule(conn: &Connection, mut rule: Rule) -> Result<Rule> {
+    let now = Utc::now();
+    rule.id = Uuid::new_v4().to_string();
+    rule.created_at = now;
+    rule.updated_at = now;
+    rule.revision = 1;
+
+    let conditions_json = to_json_keeping_unknown(&rule.conditions, &rule.extra, "conditions")?;
+    let actions_json = to_json_keeping_unknown(&rule.actions, &rule.extra, "actions")?;
+    let schedule_json = schedule_to_json(&rule.schedule)?;
+    let extra_json = rule_level_extra(&rule.extra)?;
+    let on_reappear_json = on_reappear_to_json(&rule.on_reappear)?;
+    let record_metadata_json = record_metadata_to_json(&rule.record_metadata)?;
+
+    rule.position = conn.query_row(
+        "SELECT COALESCE(MAX(position), -1) + 1 FROM rules WHERE folder_id = ?1",
+        params![rule.folder_id],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "INSERT INTO rules (id, folder_id, name, enabled, stop_processing, conditions, actions, position, created_at, updated_at, schedule, approval_required, min_age_seconds, path_scope, extra, skip_reference_check, monthly_byte_budget, classification, on_reappear, record_metadata, revision) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
+        params![
+            rule.id,
+            rule.folder_id,
+            rule.name,
+            bool_to_i64(rule.enabled),
+            bool_to_i64(rule.stop_processing),
+            conditions_json,
+            actions_json,
+            rule.position,
+            rule.created_at.to_rfc3339(),
+            rule.updated_at.to_rfc3339(),
+            schedule_json,
+            bool_to_i64(rule.approval_required),
+            rule.min_age_seconds.map(|secs| secs as i64),
+            rule.path_scope,
+            extra_json,
+            bool_to_i64(rule.skip_reference_check),
+            rule.monthly_byte_budget.map(|bytes| bytes as i64),
+            rule.classification,
+            on_reappear_json,
+            record_metadata_json,
+            rule.revision,
+        ],
+    )?;
+    Ok(rule)
+}
+
+pub(crate) fn update_rule(conn: &Connection, rule: &Rule) -> Result<()> {
+    let conditions_json = to_json_keeping_unknown(&rule.conditions, &rule.extra, "conditions")?;
+    let actions_json = to_json_keeping_unknown(&rule.actions, &rule.extra, "actions")?;
+    let schedule_json = schedule_to_json(&rule.schedule)?;
+    let extra_json = rule_level_extra(&rule.extra)?;
+    let on_reappear_json = on_reappear_to_json(&rule.on_reappear)?;
+    let record_metadata_json = record_metadata_to_json(&rule.record_metadata)?;
+    conn.execute(
+        "UPDATE rules SET name = ?1, enabled = ?2, stop_processing = ?3, conditions = ?4, actions = ?5, position = ?6, updated_at = ?7, schedule = ?8, approval_required = ?9, min_age_seconds = ?10, path_scope = ?11, extra = ?12, skip_reference_check = ?13, monthly_byte_budget = ?14, classification = ?15, on_reappear = ?16, record_metadata = ?17, revision = revision + 1 WHERE id = ?18",
+        params![
+            rule.name,
+            bool_to_i64(rule.enabled),
+            bool_to_i64(rule.stop_processing),
+            conditions_json,
+            actions_json,
+            rule.position,
+            Utc::now().to_rfc3339(),
+            schedule_json,
+            bool_to_i64(rule.approval_required),
+            rule.min_age_seconds.map(|secs| secs as i64),
+            rule.path_scope,
+            extra_json,
+            bool_to_i64(rule.skip_reference_check),
+            rule.monthly_byte_budget.map(|bytes| bytes as i64),
+            rule.classification,
+            on_reappear_json,
+            record_metadata_json,
+            rule.id,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Parses a JSON column, also returning the fields `T` doesn't know.
+fn from_json_keeping_unknown<T: DeserializeOwned + Serialize>(
+    json: &str,
+) -> serde_json::Result<(T, Value)> {
+    let raw: Value = serde_json::from_str(json)?;
+    let parsed = T::deserialize(&raw)?;
+    let unknown = unknown_fields(&raw, &serde_json::to_value(&parsed)?);
+    Ok((parsed, unknown))
+}
+
+/// Serializes `value` with the unknown fields kept under `extra[key]`.
+fn to_json_keeping_unknown<T: Serialize>(value: &T, extra: &Value, key: &str) -> Result<String> {
+    let mut json = serde_json::to_value(value)?;
+    if let Some(unknown) = extra.get(key) {
+        merge_unknown(&mut json, unknown);
+    }
+    Ok(serde_json::to_string(&json)?)
+}
+
+/// The unknown fields of the rule itself; those inside conditions and actions
+/// are stored with them.
+fn rule_level_extra(extra: &Value) -> Result<Option<String>> {
+    let Value::Object(extra) = extra else {
+        return Ok(None);
+    };
+    let own: Map<String, Value> = extra
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "conditions" | "actions"))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if own.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::to_string(&own)?))
+}
+
+fn schedule_to_json(schedule: &Option<RuleSchedule>) -> Result<Option<String>> {
+    Ok(schedule.as_ref().map(serde_json::to_string).transpose()?)
+}
+
+fn on_reappear_to_json(on_reappear: &Option<OnReappear>) -> Result<Option<String>> {
+    Ok(on_reappear.as_ref().map(serde_json::to_string).transpose()?)
+}
+
+fn record_metadata_to_json(record_metadata: &HashMap<String, String>) -> Result<Option<String>> {
+    if record_metadata.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::to_string(record_metadata)?))
+}
+
 fn bool_to_i64(value: bool) -> i64 {
     if value {
         1
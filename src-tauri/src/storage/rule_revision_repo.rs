@@ -0,0 +1,56 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension};
+
+use crate::models::{Rule, RuleRevision};
+use crate::storage::database::Database;
+
+pub struct RuleRevisionRepository {
+    db: Database,
+}
+
+impl RuleRevisionRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Keeps `rule` as saved at its current revision.
+    pub fn record(&self, rule: &Rule, content_hash: &str) -> Result<()> {
+        let content = serde_json::to_string(rule)?;
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO rule_revisions (rule_id, revision, content_hash, content, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![rule.id, rule.revision, content_hash, content, Utc::now().to_rfc3339()],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn get(&self, rule_id: &str, revision: u32) -> Result<Option<RuleRevision>> {
+        self.db.with_conn(|conn| {
+            let row = conn
+                .query_row(
+                    "SELECT content_hash, content, created_at FROM rule_revisions WHERE rule_id = ?1 AND revision = ?2",
+                    params![rule_id, revision],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, String>(2)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            let Some((content_hash, content, created_at)) = row else {
+                return Ok(None);
+            };
+            Ok(Some(RuleRevision {
+                rule_id: rule_id.to_string(),
+                revision,
+                content_hash,
+                rule: serde_json::from_str(&content)?,
+                created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+            }))
+        })
+    }
+}
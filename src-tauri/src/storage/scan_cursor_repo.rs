@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+
+use crate::storage::database::Database;
+
+pub struct ScanCursorRepository {
+    db: Database,
+}
+
+impl ScanCursorRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// The last file a capped scan of the folder got to; `None` when the last
+    /// scan finished.
+    pub fn get(&self, folder_id: &str) -> Result<Option<PathBuf>> {
+        self.db.with_conn(|conn| {
+            let path: Option<String> = conn
+                .query_row(
+                    "SELECT path FROM scan_cursors WHERE folder_id = ?1",
+                    params![folder_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(path.map(PathBuf::from))
+        })
+    }
+
+    pub fn set(&self, folder_id: &str, path: &str) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO scan_cursors (folder_id, path, updated_at) VALUES (?1, ?2, ?3) ON CONFLICT(folder_id) DO UPDATE SET path = excluded.path, updated_at = excluded.updated_at",
+                params![folder_id, path, Utc::now().to_rfc3339()],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn clear(&self, folder_id: &str) -> Result<()> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                "DELETE FROM scan_cursors WHERE folder_id = ?1",
+                params![folder_id],
+            )?;
+            Ok(())
+        })
+    }
+}
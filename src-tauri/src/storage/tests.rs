@@ -1,7 +1,21 @@
-use super::database::Database;
+use super::badge_repo::BadgeRepository;
+use super::database::{migrations, Database};
 use super::folder_repo::FolderRepository;
+use super::heartbeat_repo::HeartbeatRepository;
+use super::log_repo::LogRepository;
+use super::match_repo::{MatchQuery, MatchRepository};
+use super::profile_repo::ProfileRepository;
 use super::rule_repo::RuleRepository;
-use crate::models::{ConditionGroup, MatchType, Rule};
+use super::rule_revision_repo::RuleRevisionRepository;
+use super::scan_cursor_repo::ScanCursorRepository;
+use super::undo_repo::UndoRepository;
+use super::write_set::{commit_all, WriteSet};
+use crate::models::{
+    ActionDetails, ConditionGroup, FolderKind, FolderSettings, LogEntry, LogStatus, MatchType,
+    PlaceholderMode, Rule, RuleSchedule, Settings, UndoEntry, UndoStatus,
+};
+use rusqlite::{params, Connection};
+use std::collections::{BTreeMap, HashMap};
 use tempfile::tempdir;
 
 #[test]
@@ -30,6 +44,130 @@ fn folder_repo_crud() {
     assert!(list.is_empty());
 }
 
+fn folder_settings(placeholder_mode: PlaceholderMode, strict_sequential: bool) -> FolderSettings {
+    FolderSettings {
+        scan_depth: 0,
+        remove_duplicates: false,
+        trash_incomplete_downloads: false,
+        incomplete_timeout_minutes: 60,
+        placeholder_mode,
+        strict_sequential,
+        group_window_ms: 0,
+        passthrough_patterns: Vec::new(),
+        respect_git: false,
+        skip_git_tracked: false,
+        activity_thumbnails: false,
+        trash_max_mb: 0,
+    }
+}
+
+#[test]
+fn folder_repo_persists_placeholder_mode() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = FolderRepository::new(db);
+
+    let created = repo.create("/tmp/OneDrive", "OneDrive").unwrap();
+    assert_eq!(created.placeholder_mode, PlaceholderMode::Skip);
+
+    repo.update_settings(&created.id, &folder_settings(PlaceholderMode::Hydrate, false))
+        .unwrap();
+    let updated = repo.get(&created.id).unwrap().unwrap();
+    assert_eq!(updated.placeholder_mode, PlaceholderMode::Hydrate);
+}
+
+#[test]
+fn folder_repo_persists_strict_sequential() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = FolderRepository::new(db);
+
+    let created = repo.create("/tmp/Scans", "Scans").unwrap();
+    assert!(!created.strict_sequential);
+
+    repo.update_settings(&created.id, &folder_settings(PlaceholderMode::Skip, true))
+        .unwrap();
+    assert!(repo.get(&created.id).unwrap().unwrap().strict_sequential);
+    assert!(repo.list().unwrap()[0].strict_sequential);
+}
+
+#[test]
+fn folder_repo_persists_group_window() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = FolderRepository::new(db);
+
+    let created = repo.create("/tmp/Mail", "Mail").unwrap();
+    assert_eq!(created.group_window_ms, 0);
+
+    let settings = FolderSettings {
+        group_window_ms: 1500,
+        ..folder_settings(PlaceholderMode::Skip, false)
+    };
+    repo.update_settings(&created.id, &settings).unwrap();
+    assert_eq!(repo.get(&created.id).unwrap().unwrap().group_window_ms, 1500);
+}
+
+#[test]
+fn folder_repo_persists_git_handling() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = FolderRepository::new(db);
+
+    let created = repo.create("/tmp/notes", "notes").unwrap();
+    assert!(!created.respect_git);
+
+    let settings = FolderSettings {
+        respect_git: true,
+        skip_git_tracked: true,
+        ..folder_settings(PlaceholderMode::Skip, false)
+    };
+    repo.update_settings(&created.id, &settings).unwrap();
+    let folder = repo.get(&created.id).unwrap().unwrap();
+    assert!(folder.respect_git && folder.skip_git_tracked);
+    assert_eq!(repo.list().unwrap()[0].settings(), settings);
+}
+
+#[test]
+fn folder_repo_persists_activity_thumbnails() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = FolderRepository::new(db);
+
+    let created = repo.create("/tmp/photos", "photos").unwrap();
+    assert!(!created.activity_thumbnails);
+
+    let settings = FolderSettings {
+        activity_thumbnails: true,
+        ..folder_settings(PlaceholderMode::Skip, false)
+    };
+    repo.update_settings(&created.id, &settings).unwrap();
+    assert!(repo.get(&created.id).unwrap().unwrap().activity_thumbnails);
+    assert_eq!(repo.list().unwrap()[0].settings(), settings);
+}
+
+#[test]
+fn folder_repo_persists_system_trash_folders() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = FolderRepository::new(db);
+
+    let watched = repo.create("/tmp/downloads", "downloads").unwrap();
+    let trash = repo.create_system_trash("Trash").unwrap();
+    assert_eq!(repo.get(&watched.id).unwrap().unwrap().kind, FolderKind::Directory);
+    assert!(!trash.is_directory());
+
+    let settings = FolderSettings {
+        trash_max_mb: 2048,
+        ..folder_settings(PlaceholderMode::Skip, false)
+    };
+    repo.update_settings(&trash.id, &settings).unwrap();
+    let stored = repo.get(&trash.id).unwrap().unwrap();
+    assert_eq!(stored.kind, FolderKind::SystemTrash);
+    assert_eq!(stored.path, "");
+    assert_eq!(stored.trash_max_mb, 2048);
+}
+
 #[test]
 fn rule_repo_create_list() {
     let dir = tempdir().unwrap();
@@ -55,6 +193,17 @@ fn rule_repo_create_list() {
         },
         actions: vec![],
         position: 0,
+        schedule: None,
+        approval_required: false,
+        min_age_seconds: None,
+        path_scope: None,
+        skip_reference_check: false,
+        monthly_byte_budget: None,
+        classification: None,
+        on_reappear: None,
+        record_metadata: HashMap::new(),
+        revision: 0,
+        extra: serde_json::Value::Null,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
     };
@@ -66,3 +215,909 @@ fn rule_repo_create_list() {
     assert_eq!(list.len(), 1);
     assert_eq!(list[0].name, "Test Rule");
 }
+
+#[test]
+fn rule_repo_persists_schedule() {
+    let dir = tempdir().unwrap();
+    let db_path = dir.path().join("test.db");
+    let db = Database::new_with_path(db_path).unwrap();
+    let folder_repo = FolderRepository::new(db.clone());
+    let rule_repo = RuleRepository::new(db);
+
+    let folder_str = dir.path().join("watch").to_string_lossy().to_string();
+    let folder = folder_repo.create(&folder_str, "Temp").unwrap();
+
+    let make_rule = |name: &str, schedule: Option<RuleSchedule>| Rule {
+        id: "".to_string(),
+        folder_id: folder.id.clone(),
+        name: name.to_string(),
+        enabled: true,
+        stop_processing: false,
+        conditions: ConditionGroup {
+            label: None,
+            match_type: MatchType::All,
+            conditions: vec![],
+        },
+        actions: vec![],
+        position: 0,
+        schedule,
+        approval_required: false,
+        min_age_seconds: None,
+        path_scope: None,
+        skip_reference_check: false,
+        monthly_byte_budget: None,
+        classification: None,
+        on_reappear: None,
+        record_metadata: HashMap::new(),
+        revision: 0,
+        extra: serde_json::Value::Null,
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+
+    rule_repo.create(make_rule("On arrival", None)).unwrap();
+    let mut nightly = rule_repo
+        .create(make_rule(
+            "Nightly",
+            Some(RuleSchedule::Daily {
+                time: "02:00".to_string(),
+            }),
+        ))
+        .unwrap();
+
+    let scheduled = rule_repo.list_scheduled().unwrap();
+    assert_eq!(scheduled.len(), 1);
+    assert_eq!(scheduled[0].id, nightly.id);
+    assert_eq!(
+        scheduled[0].schedule,
+        Some(RuleSchedule::Daily {
+            time: "02:00".to_string()
+        })
+    );
+
+    nightly.schedule = None;
+    rule_repo.update(&nightly).unwrap();
+    assert!(rule_repo.list_scheduled().unwrap().is_empty());
+}
+
+#[test]
+fn rule_revisions_count_saves_and_outlive_the_log_only_for_the_current_one() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let rule_repo = RuleRepository::new(db.clone());
+    let revision_repo = RuleRevisionRepository::new(db.clone());
+
+    let mut rule = invoice_rule(&db, dir.path());
+    assert_eq!(rule.revision, 1);
+    revision_repo.record(&rule, "aaaaaaaaaaaa").unwrap();
+
+    rule.name = "Invoices, renamed".to_string();
+    rule_repo.update(&rule).unwrap();
+    let rule = rule_repo.get(&rule.id).unwrap().unwrap();
+    assert_eq!(rule.revision, 2);
+    revision_repo.record(&rule, "bbbbbbbbbbbb").unwrap();
+
+    let first = revision_repo.get(&rule.id, 1).unwrap().unwrap();
+    assert_eq!(first.content_hash, "aaaaaaaaaaaa");
+    assert_eq!(first.rule["name"], "Invoices");
+    assert!(revision_repo.get(&rule.id, 3).unwrap().is_none());
+
+    db.with_conn(|conn| {
+        conn.execute("UPDATE rule_revisions SET created_at = '2000-01-01T00:00:00Z'", [])?;
+        Ok(())
+    })
+    .unwrap();
+    LogRepository::new(db.clone()).cleanup(30).unwrap();
+    assert!(revision_repo.get(&rule.id, 1).unwrap().is_none());
+    assert!(revision_repo.get(&rule.id, 2).unwrap().is_some());
+
+    rule_repo.delete(&rule.id).unwrap();
+    assert!(revision_repo.get(&rule.id, 2).unwrap().is_none());
+}
+
+#[test]
+fn match_repo_pages_through_a_rules_matches() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let folder_str = dir.path().join("watch").to_string_lossy().to_string();
+    let folder = FolderRepository::new(db.clone()).create(&folder_str, "Temp").unwrap();
+    let rule = RuleRepository::new(db.clone())
+        .create(Rule {
+            id: "".to_string(),
+            folder_id: folder.id,
+            name: "Audio".to_string(),
+            enabled: true,
+            stop_processing: false,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![],
+            },
+            actions: vec![],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::new(),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        })
+        .unwrap();
+    // Two files matched in the same instant are told apart by path.
+    db.with_conn(|conn| {
+        for (path, at) in [
+            ("/in/a.wav", "2024-06-01T09:00:00+00:00"),
+            ("/in/c.wav", "2024-06-02T09:00:00+00:00"),
+            ("/in/b.wav", "2024-06-02T09:00:00+00:00"),
+            ("/in/d.wav", "2024-06-03T09:00:00+00:00"),
+        ] {
+            conn.execute(
+                "INSERT INTO rule_matches (rule_id, file_path, file_hash, matched_at) VALUES (?1, ?2, NULL, ?3)",
+                params![rule.id, path, at],
+            )?;
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    let repo = MatchRepository::new(db);
+    let mut paths = Vec::new();
+    let mut after = None;
+    loop {
+        let page = repo.page_for_rule(&rule.id, None, None, after.as_ref(), 2).unwrap();
+        paths.extend(page.iter().map(|m| m.file_path.clone()));
+        if page.len() < 2 {
+            break;
+        }
+        after = page.into_iter().last();
+    }
+    assert_eq!(paths, ["/in/a.wav", "/in/b.wav", "/in/c.wav", "/in/d.wav"]);
+
+    let since = "2024-06-02T00:00:00Z".parse().unwrap();
+    let until = "2024-06-02T23:59:59Z".parse().unwrap();
+    let day = repo.page_for_rule(&rule.id, Some(since), Some(until), None, 10).unwrap();
+    assert_eq!(day.len(), 2);
+}
+
+fn invoice_rule(db: &Database, folder_path: &std::path::Path) -> Rule {
+    let folder = FolderRepository::new(db.clone())
+        .create(&folder_path.to_string_lossy(), "Invoices")
+        .unwrap();
+    RuleRepository::new(db.clone())
+        .create(Rule {
+            id: "".to_string(),
+            folder_id: folder.id,
+            name: "Invoices".to_string(),
+            enabled: true,
+            stop_processing: false,
+            conditions: ConditionGroup {
+                label: None,
+                match_type: MatchType::All,
+                conditions: vec![],
+            },
+            actions: vec![],
+            position: 0,
+            schedule: None,
+            approval_required: false,
+            min_age_seconds: None,
+            path_scope: None,
+            skip_reference_check: false,
+            monthly_byte_budget: None,
+            classification: None,
+            on_reappear: None,
+            record_metadata: HashMap::from([("vendor".to_string(), "{1}".to_string())]),
+            revision: 0,
+            extra: serde_json::Value::Null,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        })
+        .unwrap()
+}
+
+#[test]
+fn match_repo_finds_matches_by_recorded_metadata() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let rule = invoice_rule(&db, &dir.path().join("watch"));
+    assert_eq!(
+        RuleRepository::new(db.clone()).get(&rule.id).unwrap().unwrap().record_metadata["vendor"],
+        "{1}"
+    );
+
+    // Seed a few thousand matches from a handful of vendors over ten days.
+    const SEEDED: usize = 5000;
+    db.with_conn(|conn| {
+        let tx = conn.transaction()?;
+        for n in 0..SEEDED {
+            let metadata = format!(r#"{{"vendor":"vendor-{}","invoice":"{n}"}}"#, n % 50);
+            tx.execute(
+                "INSERT INTO rule_matches (rule_id, file_path, file_hash, matched_at, metadata) VALUES (?1, ?2, NULL, ?3, ?4)",
+                params![
+                    rule.id,
+                    format!("/in/{n}.pdf"),
+                    format!("2024-06-{:02}T09:00:00+00:00", 1 + n % 10),
+                    metadata,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    })
+    .unwrap();
+    let repo = MatchRepository::new(db.clone());
+    let indexed = vec!["vendor".to_string()];
+    repo.reindex_metadata(&indexed).unwrap();
+
+    let wanted = BTreeMap::from([("vendor".to_string(), "vendor-7".to_string())]);
+    let query = MatchQuery {
+        rule_id: Some(&rule.id),
+        since: None,
+        until: None,
+        metadata: &wanted,
+        indexed_keys: &indexed,
+        limit: SEEDED,
+    };
+    let started = std::time::Instant::now();
+    let found = repo.query(&query).unwrap();
+    let elapsed = started.elapsed();
+    assert_eq!(found.len(), SEEDED / 50);
+    assert!(found.iter().all(|m| m.metadata["vendor"] == "vendor-7"));
+    assert!(elapsed < std::time::Duration::from_millis(250), "{elapsed:?}");
+
+    // Keys that aren't indexed are read out of each record instead.
+    let unindexed = repo.query(&MatchQuery { indexed_keys: &[], ..query }).unwrap();
+    assert_eq!(unindexed, found);
+
+    let wanted = BTreeMap::from([
+        ("vendor".to_string(), "vendor-7".to_string()),
+        ("invoice".to_string(), "57".to_string()),
+    ]);
+    let since = "2024-06-08T00:00:00Z".parse().unwrap();
+    let narrowed = repo
+        .query(&MatchQuery {
+            since: Some(since),
+            metadata: &wanted,
+            ..query
+        })
+        .unwrap();
+    assert_eq!(narrowed.len(), 1);
+    assert_eq!(narrowed[0].file_path, "/in/57.pdf");
+    let recent = repo.query(&MatchQuery { since: Some(since), ..query }).unwrap();
+    assert!(!recent.is_empty());
+    assert!(recent.iter().all(|m| m.matched_at >= since));
+
+    // Matching the file again replaces what it recorded, index included.
+    let metadata = BTreeMap::from([("vendor".to_string(), "vendor-99".to_string())]);
+    repo.record_match_with(&rule.id, "/in/7.pdf", None, &metadata, &indexed)
+        .unwrap();
+    let wanted = BTreeMap::from([("vendor".to_string(), "vendor-99".to_string())]);
+    let moved = repo.query(&MatchQuery { metadata: &wanted, ..query }).unwrap();
+    assert_eq!(moved.len(), 1);
+    assert_eq!(moved[0].file_path, "/in/7.pdf");
+    let remaining = repo.query(&query).unwrap();
+    assert_eq!(remaining.len(), SEEDED / 50 - 1);
+}
+
+/// The records a rule leaves for moving `name` out of the inbox.
+fn moved_writes(rule: &Rule, name: &str) -> WriteSet {
+    let source = format!("/inbox/{name}");
+    let mut writes = WriteSet::default();
+    let logged = writes.log(LogEntry {
+        id: String::new(),
+        rule_id: Some(rule.id.clone()),
+        rule_name: Some(rule.name.clone()),
+        file_path: source.clone(),
+        action_type: "move".to_string(),
+        action_detail: Some(ActionDetails {
+            source_path: source.clone(),
+            destination_path: Some(format!("/done/{name}")),
+            metadata: HashMap::new(),
+        }),
+        status: LogStatus::Success,
+        error_message: None,
+        created_at: chrono::Utc::now(),
+    });
+    writes.undo(UndoEntry {
+        id: String::new(),
+        log_id: logged.id,
+        action_type: "move".to_string(),
+        original_path: source.clone(),
+        current_path: format!("/done/{name}"),
+        created_at: chrono::Utc::now(),
+        hash: None,
+        hash_scheme: None,
+        classification: None,
+        status: UndoStatus::Available,
+        status_reason: None,
+    });
+    writes.record_match(&rule.id, &source, Some(name), &BTreeMap::new(), &[]);
+    writes
+}
+
+#[test]
+fn an_events_records_are_committed_whole_or_not_at_all() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let rule = invoice_rule(&db, &dir.path().join("inbox"));
+    let count = |table: &str| -> i64 {
+        db.with_conn(|conn| {
+            Ok(conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0))?)
+        })
+        .unwrap()
+    };
+
+    moved_writes(&rule, "a.pdf").commit(&db).unwrap();
+    assert_eq!((count("logs"), count("undo_entries"), count("rule_matches")), (1, 1, 1));
+    assert_eq!(
+        moved_writes(&rule, "a.pdf").paths().collect::<Vec<_>>(),
+        ["/inbox/a.pdf", "/done/a.pdf", "/done/a.pdf", "/inbox/a.pdf"]
+    );
+
+    // The match is written last and fails: a rule that's gone can't match.
+    let mut gone = rule.clone();
+    gone.id = "deleted-rule".to_string();
+    let mut failing = moved_writes(&rule, "b.pdf");
+    failing.record_match(&gone.id, "/inbox/b.pdf", None, &BTreeMap::new(), &[]);
+    assert!(failing.commit(&db).is_err());
+    assert_eq!((count("logs"), count("undo_entries"), count("rule_matches")), (1, 1, 1));
+
+    // Several events committed together fail together too.
+    let batch = [moved_writes(&rule, "c.pdf"), moved_writes(&gone, "d.pdf")];
+    assert!(commit_all(&db, &batch).is_err());
+    assert_eq!(count("logs"), 1);
+    commit_all(&db, &batch[..1]).unwrap();
+    assert_eq!((count("logs"), count("undo_entries"), count("rule_matches")), (2, 2, 2));
+}
+
+#[test]
+fn match_metadata_migration_keeps_existing_matches() {
+    let dir = tempdir().unwrap();
+    let db_path = dir.path().join("existing.db");
+
+    // A database from before matches recorded metadata.
+    let mut conn = Connection::open(&db_path).unwrap();
+    migrations().to_version(&mut conn, 34).unwrap();
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO folders (id, path, name, enabled, created_at, updated_at) VALUES ('folder-1', '/in', 'In', 1, ?1, ?1)",
+        params![now],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO rules (id, folder_id, name, enabled, stop_processing, conditions, actions, position, created_at, updated_at) VALUES ('rule-1', 'folder-1', 'Old', 1, 0, '{\"matchType\":\"all\",\"conditions\":[]}', '[]', 0, ?1, ?1)",
+        params![now],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO rule_matches (rule_id, file_path, file_hash, matched_at) VALUES ('rule-1', '/in/a.pdf', NULL, ?1)",
+        params![now],
+    )
+    .unwrap();
+    drop(conn);
+
+    let db = Database::new_with_path(db_path).unwrap();
+    let rule = RuleRepository::new(db.clone()).get("rule-1").unwrap().unwrap();
+    assert!(rule.record_metadata.is_empty());
+    let repo = MatchRepository::new(db);
+    let indexed = vec!["vendor".to_string()];
+    repo.reindex_metadata(&indexed).unwrap();
+    let query = MatchQuery {
+        rule_id: Some("rule-1"),
+        since: None,
+        until: None,
+        metadata: &BTreeMap::new(),
+        indexed_keys: &indexed,
+        limit: 10,
+    };
+    let old = repo.query(&query).unwrap();
+    assert_eq!(old.len(), 1);
+    assert!(old[0].metadata.is_empty());
+
+    let metadata = BTreeMap::from([("vendor".to_string(), "acme".to_string())]);
+    repo.record_match_with("rule-1", "/in/b.pdf", None, &metadata, &indexed)
+        .unwrap();
+    let found = repo.query(&MatchQuery { metadata: &metadata, ..query }).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].file_path, "/in/b.pdf");
+}
+
+#[test]
+fn profile_repo_snapshot_and_activate() {
+    let dir = tempdir().unwrap();
+    let db_path = dir.path().join("test.db");
+    let db = Database::new_with_path(db_path).unwrap();
+    let folder_repo = FolderRepository::new(db.clone());
+    let profile_repo = ProfileRepository::new(db);
+
+    let work = folder_repo
+        .create(&dir.path().join("work").to_string_lossy(), "Work")
+        .unwrap();
+    let home = folder_repo
+        .create(&dir.path().join("home").to_string_lossy(), "Home")
+        .unwrap();
+    folder_repo.set_enabled(&home.id, false).unwrap();
+
+    let settings = Settings {
+        dry_run: true,
+        ..Settings::default()
+    };
+    let work_profile = profile_repo.create("Work", &settings).unwrap();
+    assert_eq!(work_profile.folder_states.get(&work.id), Some(&true));
+    assert_eq!(work_profile.folder_states.get(&home.id), Some(&false));
+    assert!(profile_repo.create("Work", &settings).is_err());
+    assert!(profile_repo.create("  ", &settings).is_err());
+
+    folder_repo.set_enabled(&work.id, false).unwrap();
+    folder_repo.set_enabled(&home.id, true).unwrap();
+    let home_profile = profile_repo.create("Home", &Settings::default()).unwrap();
+
+    profile_repo.activate(&work_profile).unwrap();
+    assert!(folder_repo.get(&work.id).unwrap().unwrap().enabled);
+    assert!(!folder_repo.get(&home.id).unwrap().unwrap().enabled);
+
+    let stored = profile_repo.get_by_name("Work").unwrap().unwrap();
+    assert!(stored.is_active);
+    assert!(stored.settings.dry_run);
+
+    profile_repo.activate(&home_profile).unwrap();
+    assert!(!folder_repo.get(&work.id).unwrap().unwrap().enabled);
+    let profiles = profile_repo.list().unwrap();
+    assert_eq!(profiles.len(), 2);
+    assert_eq!(profiles.iter().filter(|p| p.is_active).count(), 1);
+    assert!(profiles.iter().any(|p| p.name == "Home" && p.is_active));
+
+    profile_repo.delete_by_name("Work").unwrap();
+    assert!(profile_repo.delete_by_name("Work").is_err());
+    assert_eq!(profile_repo.list().unwrap().len(), 1);
+}
+
+#[test]
+fn undo_hash_migration_keeps_legacy_rows() {
+    let dir = tempdir().unwrap();
+    let db_path = dir.path().join("legacy.db");
+
+    // Simulate a database created before undo hashes existed.
+    let mut conn = Connection::open(&db_path).unwrap();
+    migrations().to_version(&mut conn, 9).unwrap();
+    let created_at = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO logs (id, file_path, action_type, status, created_at) VALUES ('log-1', '/a.txt', 'move', 'success', ?1)",
+        params![created_at],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO undo_entries (id, log_id, action_type, original_path, current_path, created_at) VALUES ('undo-1', 'log-1', 'move', '/a.txt', '/b/a.txt', ?1)",
+        params![created_at],
+    )
+    .unwrap();
+    drop(conn);
+
+    let repo = UndoRepository::new(Database::new_with_path(db_path).unwrap());
+    let legacy = repo.get("undo-1").unwrap().unwrap();
+    assert_eq!(legacy.current_path, "/b/a.txt");
+    assert!(legacy.hash.is_none());
+}
+
+#[test]
+fn undo_repo_prune_removes_expired_entries() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = UndoRepository::new(db.clone());
+
+    let old_created = (chrono::Utc::now() - chrono::Duration::days(10)).to_rfc3339();
+    db.with_conn(|conn| {
+        conn.execute(
+            "INSERT INTO logs (id, file_path, action_type, status, created_at) VALUES ('log-1', '/a.txt', 'move', 'success', ?1)",
+            params![old_created],
+        )?;
+        Ok(())
+    })
+    .unwrap();
+    let old = repo
+        .insert(UndoEntry {
+            id: String::new(),
+            log_id: "log-1".to_string(),
+            action_type: "move".to_string(),
+            original_path: "/a.txt".to_string(),
+            current_path: "/b/a.txt".to_string(),
+            created_at: chrono::Utc::now(),
+            hash: Some("abc".to_string()),
+            hash_scheme: None,
+            classification: Some("FIN-7Y".to_string()),
+            status: UndoStatus::Available,
+            status_reason: None,
+        })
+        .unwrap();
+    db.with_conn(|conn| {
+        conn.execute(
+            "UPDATE undo_entries SET created_at = ?1 WHERE id = ?2",
+            params![old_created, old.id],
+        )?;
+        Ok(())
+    })
+    .unwrap();
+    let fresh = repo
+        .insert(UndoEntry {
+            original_path: "/c.txt".to_string(),
+            current_path: "/d/c.txt".to_string(),
+            ..old.clone()
+        })
+        .unwrap();
+
+    let now = chrono::Utc::now();
+    assert_eq!(repo.prune(0, 0, now).unwrap(), 0);
+    assert_eq!(repo.list(10).unwrap().len(), 2);
+
+    assert_eq!(repo.prune(0, 7, now).unwrap(), 1);
+    let remaining = repo.list(10).unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].id, fresh.id);
+    assert_eq!(remaining[0].hash.as_deref(), Some("abc"));
+    assert_eq!(remaining[0].classification.as_deref(), Some("FIN-7Y"));
+}
+
+/// Inserts `count` moves one second apart, the newest at `newest`.
+fn insert_undo_run(
+    db: &Database,
+    prefix: &str,
+    newest: chrono::DateTime<chrono::Utc>,
+    count: usize,
+) {
+    db.with_conn(|conn| {
+        let tx = conn.transaction()?;
+        for i in 0..count {
+            let id = format!("{prefix}-{i}");
+            let created_at = (newest - chrono::Duration::seconds(i as i64)).to_rfc3339();
+            tx.execute(
+                "INSERT INTO logs (id, file_path, action_type, status, created_at) VALUES (?1, '/a.txt', 'move', 'success', ?2)",
+                params![id, created_at],
+            )?;
+            tx.execute(
+                "INSERT INTO undo_entries (id, log_id, action_type, original_path, current_path, created_at) VALUES (?1, ?1, 'move', '/a.txt', '/b/a.txt', ?2)",
+                params![id, created_at],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn undo_repo_prune_keeps_the_latest_run_whole() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = UndoRepository::new(db.clone());
+    let now = chrono::Utc::now();
+    insert_undo_run(&db, "old", now - chrono::Duration::hours(2), 400);
+    insert_undo_run(&db, "run", now, 300);
+
+    assert_eq!(repo.prune(500, 7, now).unwrap(), 200);
+    let kept: Vec<String> = repo.list(1000).unwrap().into_iter().map(|e| e.id).collect();
+    assert_eq!(kept.len(), 500);
+    assert!((0..300).all(|i| kept.contains(&format!("run-{i}"))));
+    assert!(kept.contains(&"old-199".to_string()));
+    assert!(!kept.contains(&"old-200".to_string()));
+
+    // A run bigger than the cap stays whole; only older entries go.
+    assert_eq!(repo.prune(100, 7, now).unwrap(), 200);
+    assert_eq!(repo.list(1000).unwrap().len(), 300);
+    assert_eq!(repo.prune(100, 7, now).unwrap(), 0);
+}
+
+#[test]
+fn undo_repo_stats_count_what_prune_would_delete() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = UndoRepository::new(db.clone());
+    let now = chrono::Utc::now();
+    let empty = repo.stats(10, 7, now).unwrap();
+    assert_eq!((empty.entries, empty.oldest, empty.reclaimable), (0, None, 0));
+
+    let expired = now - chrono::Duration::days(10);
+    insert_undo_run(&db, "expired", expired, 5);
+    insert_undo_run(&db, "older", now - chrono::Duration::days(1), 20);
+    insert_undo_run(&db, "run", now, 8);
+
+    let stats = repo.stats(10, 7, now).unwrap();
+    assert_eq!(stats.entries, 33);
+    let oldest = stats.oldest.unwrap();
+    assert_eq!(oldest.timestamp(), (expired - chrono::Duration::seconds(4)).timestamp());
+    assert_eq!(stats.reclaimable, 23);
+    assert_eq!((stats.max_entries, stats.retention_days), (10, 7));
+    assert_eq!(repo.stats(0, 0, now).unwrap().reclaimable, 0);
+
+    assert_eq!(repo.prune(10, 7, now).unwrap(), 23);
+    let after = repo.stats(10, 7, now).unwrap();
+    assert_eq!((after.entries, after.reclaimable), (10, 0));
+}
+
+#[test]
+fn heartbeat_repo_keeps_the_latest_time() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = HeartbeatRepository::new(db);
+    assert_eq!(repo.last_seen().unwrap(), None);
+
+    let first = chrono::Utc::now() - chrono::Duration::hours(1);
+    repo.touch(first).unwrap();
+    let later = first + chrono::Duration::minutes(1);
+    repo.touch(later).unwrap();
+    assert_eq!(repo.last_seen().unwrap(), Some(later));
+}
+
+#[test]
+fn scan_cursor_is_kept_per_folder_until_cleared() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let folder = FolderRepository::new(db.clone())
+        .create(&dir.path().join("watch").to_string_lossy(), "Watch")
+        .unwrap();
+    let repo = ScanCursorRepository::new(db.clone());
+    assert_eq!(repo.get(&folder.id).unwrap(), None);
+
+    repo.set(&folder.id, "/watch/a.txt").unwrap();
+    repo.set(&folder.id, "/watch/b.txt").unwrap();
+    assert_eq!(repo.get(&folder.id).unwrap(), Some("/watch/b.txt".into()));
+
+    repo.clear(&folder.id).unwrap();
+    assert_eq!(repo.get(&folder.id).unwrap(), None);
+
+    repo.set(&folder.id, "/watch/c.txt").unwrap();
+    FolderRepository::new(db).delete(&folder.id).unwrap();
+    assert_eq!(repo.get(&folder.id).unwrap(), None);
+}
+
+#[test]
+fn badge_repo_counts_what_waits_and_errors_since_acknowledged() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let folder = FolderRepository::new(db.clone())
+        .create(&dir.path().join("watch").to_string_lossy(), "Watch")
+        .unwrap();
+    let repo = BadgeRepository::new(db.clone());
+    assert_eq!(repo.counts().unwrap().total(), 0);
+
+    let now = chrono::Utc::now();
+    let log_error = |conn: &Connection, id: &str, at: chrono::DateTime<chrono::Utc>| {
+        conn.execute(
+            "INSERT INTO logs (id, file_path, action_type, status, created_at) VALUES (?1, '/watch/a.pdf', 'move', 'error', ?2)",
+            params![id, at.to_rfc3339()],
+        )
+    };
+    db.with_conn(|conn| {
+        conn.execute(
+            "INSERT INTO rules (id, folder_id, name, conditions, actions, position, created_at, updated_at) VALUES ('r1', ?1, 'Rule', '{}', '[]', 0, ?2, ?2)",
+            params![folder.id, now.to_rfc3339()],
+        )?;
+        for path in ["/watch/a.pdf", "/watch/b.pdf"] {
+            conn.execute(
+                "INSERT INTO pending_approvals (id, rule_id, file_path, file_hash, captures, planned, created_at) VALUES (?1, 'r1', ?1, '', '{}', '[]', ?2)",
+                params![path, now.to_rfc3339()],
+            )?;
+        }
+        conn.execute(
+            "INSERT INTO pending_conflicts (id, rule_id, action_type, action_index, actions, captures, source_path, source, destination, created_at) VALUES ('c1', 'r1', 'move', 0, '[]', '{}', '/watch/c.pdf', '/watch/c.pdf', '/done/c.pdf', ?1)",
+            params![now.to_rfc3339()],
+        )?;
+        log_error(conn, "e1", now - chrono::Duration::minutes(2))?;
+        log_error(conn, "e2", now - chrono::Duration::minutes(1))?;
+        conn.execute(
+            "INSERT INTO logs (id, file_path, action_type, status, created_at) VALUES ('ok', '/watch/d.pdf', 'move', 'success', ?1)",
+            params![now.to_rfc3339()],
+        )?;
+        Ok(())
+    })
+    .unwrap();
+
+    let counts = repo.counts().unwrap();
+    assert_eq!((counts.approvals, counts.conflicts, counts.errors), (2, 1, 2));
+    assert_eq!(counts.total(), 5);
+
+    // Acknowledging clears the errors so far; only later ones count again.
+    repo.acknowledge_errors(now).unwrap();
+    assert_eq!(repo.counts().unwrap().errors, 0);
+    db.with_conn(|conn| {
+        log_error(conn, "e3", now + chrono::Duration::minutes(1))?;
+        Ok(())
+    })
+    .unwrap();
+    let counts = repo.counts().unwrap();
+    assert_eq!((counts.approvals, counts.errors), (2, 1));
+}
+
+fn insert_log(
+    db: &Database,
+    id: &str,
+    rule: Option<(&str, &str)>,
+    status: &str,
+    at: chrono::DateTime<chrono::Utc>,
+) {
+    db.with_conn(|conn| {
+        conn.execute(
+            "INSERT INTO logs (id, rule_id, rule_name, file_path, action_type, status, created_at) VALUES (?1, ?2, ?3, '/inbox/a.pdf', 'move', ?4, ?5)",
+            params![id, rule.map(|r| r.0), rule.map(|r| r.1), status, at.to_rfc3339()],
+        )?;
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn unreviewed_summary_groups_new_entries_by_status_and_rule() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let folder = FolderRepository::new(db.clone())
+        .create(&dir.path().join("watch").to_string_lossy(), "Watch")
+        .unwrap();
+    let repo = LogRepository::new(db.clone());
+    let now = chrono::Utc::now();
+    db.with_conn(|conn| {
+        for (position, id) in ["r1", "r2"].iter().enumerate() {
+            conn.execute(
+                "INSERT INTO rules (id, folder_id, name, conditions, actions, position, created_at, updated_at) VALUES (?1, ?2, ?1, '{}', '[]', ?3, ?4, ?4)",
+                params![id, folder.id, position as i64, now.to_rfc3339()],
+            )?;
+        }
+        Ok(())
+    })
+    .unwrap();
+    let invoices = Some(("r1", "Invoices"));
+    let photos = Some(("r2", "Photos"));
+    insert_log(
+        &db,
+        "old",
+        invoices,
+        "error",
+        now - chrono::Duration::hours(2),
+    );
+    repo.mark_reviewed(now - chrono::Duration::hours(1))
+        .unwrap();
+    // Stamped by a clock running a second ahead of the review's.
+    insert_log(
+        &db,
+        "skewed",
+        invoices,
+        "success",
+        now - chrono::Duration::minutes(60) + chrono::Duration::seconds(1),
+    );
+    insert_log(
+        &db,
+        "i1",
+        invoices,
+        "success",
+        now - chrono::Duration::minutes(30),
+    );
+    insert_log(
+        &db,
+        "i2",
+        invoices,
+        "error",
+        now - chrono::Duration::minutes(20),
+    );
+    insert_log(
+        &db,
+        "i3",
+        invoices,
+        "error",
+        now - chrono::Duration::minutes(10),
+    );
+    insert_log(
+        &db,
+        "p1",
+        photos,
+        "success",
+        now - chrono::Duration::minutes(5),
+    );
+    insert_log(&db, "b1", None, "skipped", now);
+
+    let summary = repo.unreviewed_summary(1).unwrap();
+    assert_eq!(summary.counts.total(), 5);
+    assert_eq!((summary.counts.success, summary.counts.error), (2, 2));
+    assert_eq!(summary.counts.skipped, 1);
+    let rules: Vec<_> = summary
+        .by_rule
+        .iter()
+        .map(|rule| (rule.rule_name.as_deref(), rule.counts.total()))
+        .collect();
+    assert_eq!(
+        rules,
+        vec![(Some("Invoices"), 3), (None, 1), (Some("Photos"), 1)]
+    );
+    assert_eq!(summary.rules_with_errors.len(), 1);
+    assert_eq!(summary.rules_with_errors[0].rule_id.as_deref(), Some("r1"));
+    let recent: Vec<_> = summary
+        .recent_errors
+        .iter()
+        .map(|e| e.id.as_str())
+        .collect();
+    assert_eq!(recent, vec!["i3"]);
+    assert_eq!(summary.pruned_before_review, 0);
+
+    repo.mark_reviewed(now + chrono::Duration::seconds(1))
+        .unwrap();
+    let summary = repo.unreviewed_summary(5).unwrap();
+    assert_eq!(summary.counts.total(), 0);
+    assert!(summary.by_rule.is_empty() && summary.recent_errors.is_empty());
+}
+
+#[test]
+fn reviewing_acknowledges_errors_for_the_badge_and_the_digest() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = LogRepository::new(db.clone());
+    let badge = BadgeRepository::new(db.clone());
+    let now = chrono::Utc::now();
+    let day = (
+        now - chrono::Duration::hours(3),
+        now + chrono::Duration::minutes(1),
+    );
+    insert_log(&db, "e1", None, "error", now - chrono::Duration::hours(2));
+    insert_log(&db, "e2", None, "error", now - chrono::Duration::hours(1));
+    let errors = || {
+        let digest = repo.action_totals(day.0, day.1).unwrap().1;
+        let badge = badge.counts().unwrap().errors as u64;
+        assert_eq!(digest, badge);
+        badge
+    };
+    assert_eq!(errors(), 2);
+
+    repo.mark_reviewed(now - chrono::Duration::minutes(90))
+        .unwrap();
+    assert_eq!(errors(), 1);
+    // An acknowledgment older than the review doesn't bring errors back.
+    badge
+        .acknowledge_errors(now - chrono::Duration::hours(3))
+        .unwrap();
+    assert_eq!(errors(), 1);
+    badge.acknowledge_errors(now).unwrap();
+    assert_eq!(errors(), 0);
+}
+
+#[test]
+fn entries_pruned_before_review_are_noted_until_the_next_one() {
+    let dir = tempdir().unwrap();
+    let db = Database::new_with_path(dir.path().join("test.db")).unwrap();
+    let repo = LogRepository::new(db.clone());
+    let now = chrono::Utc::now();
+    insert_log(
+        &db,
+        "seen",
+        None,
+        "success",
+        now - chrono::Duration::days(12),
+    );
+    repo.mark_reviewed(now - chrono::Duration::days(10))
+        .unwrap();
+    insert_log(
+        &db,
+        "missed-1",
+        None,
+        "error",
+        now - chrono::Duration::days(9),
+    );
+    insert_log(
+        &db,
+        "missed-2",
+        None,
+        "success",
+        now - chrono::Duration::days(8),
+    );
+    insert_log(&db, "recent", None, "success", now);
+
+    repo.cleanup(2).unwrap();
+    let summary = repo.unreviewed_summary(5).unwrap();
+    assert_eq!(summary.pruned_before_review, 2);
+    assert_eq!(summary.counts.total(), 1);
+
+    repo.mark_reviewed(now + chrono::Duration::seconds(1))
+        .unwrap();
+    assert_eq!(repo.unreviewed_summary(5).unwrap().pruned_before_review, 0);
+}
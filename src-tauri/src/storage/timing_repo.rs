@@ -0,0 +1,57 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use crate::models::RuleTiming;
+use crate::storage::database::Database;
+
+pub struct TimingRepository {
+    db: Database,
+}
+
+impl TimingRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Replaces the stored statistics of each of `timings`' rules.
+    pub fn save(&self, timings: &[RuleTiming]) -> Result<()> {
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            for timing in timings {
+                // Skips rules deleted since they were timed.
+                tx.execute(
+                    "INSERT INTO rule_timings (rule_id, stats, updated_at) SELECT ?1, ?2, ?3 WHERE EXISTS (SELECT 1 FROM rules WHERE id = ?1) ON CONFLICT(rule_id) DO UPDATE SET stats = excluded.stats, updated_at = excluded.updated_at",
+                    params![
+                        timing.rule_id,
+                        serde_json::to_string(timing)?,
+                        timing.updated_at.to_rfc3339(),
+                    ],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Stored statistics under the rules' current names. Rows that no longer
+    /// parse are skipped.
+    pub fn list(&self) -> Result<Vec<RuleTiming>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT t.stats, r.name FROM rule_timings t JOIN rules r ON r.id = t.rule_id",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+            let mut timings = Vec::new();
+            for row in rows {
+                let (stats, name) = row?;
+                if let Ok(mut timing) = serde_json::from_str::<RuleTiming>(&stats) {
+                    timing.rule_name = name;
+                    timings.push(timing);
+                }
+            }
+            Ok(timings)
+        })
+    }
+}
@@ -1,11 +1,15 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
-use rusqlite::{params, types::Type, Row};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, types::Type, Connection, Row};
 use uuid::Uuid;
 
-use crate::models::UndoEntry;
+use crate::models::{UndoEntry, UndoStats, UndoStatus};
 use crate::storage::database::Database;
 
+/// Entries written less than this apart count as one run, such as a scan or
+/// a burst of downloads.
+const RUN_GAP_SECONDS: i64 = 120;
+
 pub struct UndoRepository {
     db: Database,
 }
@@ -20,17 +24,7 @@ impl UndoRepository {
         entry.created_at = Utc::now();
 
         self.db.with_conn(|conn| {
-            conn.execute(
-                "INSERT INTO undo_entries (id, log_id, action_type, original_path, current_path, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                params![
-                    entry.id,
-                    entry.log_id,
-                    entry.action_type,
-                    entry.original_path,
-                    entry.current_path,
-                    entry.created_at.to_rfc3339(),
-                ],
-            )?;
+            insert_undo(conn, &entry)?;
             Ok(entry)
         })
     }
@@ -38,7 +32,7 @@ impl UndoRepository {
     pub fn list(&self, limit: usize) -> Result<Vec<UndoEntry>> {
         self.db.with_conn(|conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, log_id, action_type, original_path, current_path, created_at FROM undo_entries ORDER BY created_at DESC LIMIT ?1",
+                "SELECT id, log_id, action_type, original_path, current_path, created_at, hash, classification, hash_scheme FROM undo_entries ORDER BY created_at DESC LIMIT ?1",
             )?;
             let rows = stmt.query_map(params![limit as i64], |row| map_undo(row))?;
             let mut entries = Vec::new();
@@ -52,13 +46,24 @@ impl UndoRepository {
     pub fn get(&self, id: &str) -> Result<Option<UndoEntry>> {
         self.db.with_conn(|conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, log_id, action_type, original_path, current_path, created_at FROM undo_entries WHERE id = ?1",
+                "SELECT id, log_id, action_type, original_path, current_path, created_at, hash, classification, hash_scheme FROM undo_entries WHERE id = ?1",
             )?;
             let mut rows = stmt.query_map(params![id], |row| map_undo(row))?;
             Ok(rows.next().transpose()?)
         })
     }
 
+    /// The entry recorded for the action logged as `log_id`.
+    pub fn get_by_log(&self, log_id: &str) -> Result<Option<UndoEntry>> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, log_id, action_type, original_path, current_path, created_at, hash, classification, hash_scheme FROM undo_entries WHERE log_id = ?1",
+            )?;
+            let mut rows = stmt.query_map(params![log_id], map_undo)?;
+            Ok(rows.next().transpose()?)
+        })
+    }
+
     #[allow(dead_code)]
     pub fn latest(&self) -> Result<Option<UndoEntry>> {
         let mut items = self.list(1)?;
@@ -72,22 +77,113 @@ impl UndoRepository {
         })
     }
 
-    pub fn cleanup(&self, max_entries: usize) -> Result<()> {
+    /// Deletes entries older than `retention_days` and then the oldest past
+    /// `max_entries`; zero turns either limit off. The latest run is kept
+    /// whole even when it alone is over the cap, so a big batch can always
+    /// be undone in full. Returns how many entries were deleted.
+    pub fn prune(
+        &self,
+        max_entries: u32,
+        retention_days: u32,
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
         self.db.with_conn(|conn| {
-            conn.execute(
-                "DELETE FROM undo_entries WHERE id NOT IN (SELECT id FROM undo_entries ORDER BY created_at DESC LIMIT ?1)",
-                params![max_entries as i64],
-            )?;
-            Ok(())
+            let doomed = prunable(&timeline(conn)?, max_entries, retention_days, now);
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare("DELETE FROM undo_entries WHERE id = ?1")?;
+                for id in &doomed {
+                    stmt.execute(params![id])?;
+                }
+            }
+            tx.commit()?;
+            Ok(doomed.len())
         })
     }
+
+    pub fn stats(
+        &self,
+        max_entries: u32,
+        retention_days: u32,
+        now: DateTime<Utc>,
+    ) -> Result<UndoStats> {
+        self.db.with_conn(|conn| {
+            let entries = timeline(conn)?;
+            Ok(UndoStats {
+                entries: entries.len() as u64,
+                oldest: entries.last().map(|(_, created_at)| *created_at),
+                reclaimable: prunable(&entries, max_entries, retention_days, now).len() as u64,
+                max_entries,
+                retention_days,
+            })
+        })
+    }
+}
+
+/// Writes `entry` with the id and time it already has.
+pub(crate) fn insert_undo(conn: &Connection, entry: &UndoEntry) -> Result<()> {
+    conn.execute(
+        "INSERT INTO undo_entries (id, log_id, action_type, original_path, current_path, created_at, hash, classification, hash_scheme) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            entry.id,
+            entry.log_id,
+            entry.action_type,
+            entry.original_path,
+            entry.current_path,
+            entry.created_at.to_rfc3339(),
+            entry.hash,
+            entry.classification,
+            entry.hash_scheme,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Every entry's id and creation time, newest first.
+fn timeline(conn: &Connection) -> Result<Vec<(String, DateTime<Utc>)>> {
+    let mut stmt =
+        conn.prepare("SELECT id, created_at FROM undo_entries ORDER BY created_at DESC")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, parse_created_at(row, 1)?)))?;
+    let mut entries = Vec::new();
+    for entry in rows {
+        entries.push(entry?);
+    }
+    Ok(entries)
+}
+
+/// The ids `prune` deletes from `entries`, which are newest first.
+fn prunable(
+    entries: &[(String, DateTime<Utc>)],
+    max_entries: u32,
+    retention_days: u32,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let cutoff = (retention_days > 0).then(|| now - Duration::days(retention_days as i64));
+    let live = entries
+        .iter()
+        .position(|(_, created_at)| cutoff.is_some_and(|cutoff| *created_at < cutoff))
+        .unwrap_or(entries.len());
+    let keep = if max_entries == 0 {
+        live
+    } else {
+        let latest_run = entries[..live]
+            .windows(2)
+            .position(|pair| pair[0].1 - pair[1].1 > Duration::seconds(RUN_GAP_SECONDS))
+            .map_or(live, |last| last + 1);
+        live.min((max_entries as usize).max(latest_run))
+    };
+    entries[keep..].iter().map(|(id, _)| id.clone()).collect()
+}
+
+fn parse_created_at(row: &Row<'_>, index: usize) -> rusqlite::Result<DateTime<Utc>> {
+    let created_at: String = row.get(index)?;
+    Ok(DateTime::parse_from_rfc3339(&created_at)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))?
+        .with_timezone(&Utc))
 }
 
 fn map_undo(row: &Row<'_>) -> rusqlite::Result<UndoEntry> {
-    let created_at: String = row.get(5)?;
-    let created_at = DateTime::parse_from_rfc3339(&created_at)
-        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, Type::Text, Box::new(e)))?
-        .with_timezone(&Utc);
+    let created_at = parse_created_at(row, 5)?;
     Ok(UndoEntry {
         id: row.get(0)?,
         log_id: row.get(1)?,
@@ -95,5 +191,10 @@ fn map_undo(row: &Row<'_>) -> rusqlite::Result<UndoEntry> {
         original_path: row.get(3)?,
         current_path: row.get(4)?,
         created_at,
+        hash: row.get(6)?,
+        classification: row.get(7)?,
+        hash_scheme: row.get(8)?,
+        status: UndoStatus::Available,
+        status_reason: None,
     })
 }
@@ -0,0 +1,144 @@
+//! The records one event leaves behind: its log entries, the undo entries
+//! for what it moved, and the matches that keep it from running again.
+//! They're gathered while the event is processed and committed together, so
+//! a burst of files costs one commit per event instead of one per record,
+//! and an event's records are written whole or not at all.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::{LogEntry, UndoEntry};
+use crate::storage::database::Database;
+use crate::storage::log_repo::insert_log;
+use crate::storage::match_repo::write_match;
+use crate::storage::undo_repo::insert_undo;
+
+#[derive(Debug, Clone, Default)]
+pub struct WriteSet {
+    writes: Vec<Write>,
+}
+
+#[derive(Debug, Clone)]
+enum Write {
+    Log(LogEntry),
+    Undo(UndoEntry),
+    Match(MatchWrite),
+}
+
+#[derive(Debug, Clone)]
+struct MatchWrite {
+    rule_id: String,
+    file_path: String,
+    file_hash: Option<String>,
+    matched_at: DateTime<Utc>,
+    metadata: BTreeMap<String, String>,
+    indexed_keys: Vec<String>,
+}
+
+impl WriteSet {
+    /// Queues `entry` with a fresh id and time, and returns it as it will be
+    /// stored.
+    pub fn log(&mut self, mut entry: LogEntry) -> LogEntry {
+        entry.id = Uuid::new_v4().to_string();
+        entry.created_at = Utc::now();
+        self.writes.push(Write::Log(entry.clone()));
+        entry
+    }
+
+    /// Queues `entry` with a fresh id and time; its log entry must be queued
+    /// first.
+    pub fn undo(&mut self, mut entry: UndoEntry) {
+        entry.id = Uuid::new_v4().to_string();
+        entry.created_at = Utc::now();
+        self.writes.push(Write::Undo(entry));
+    }
+
+    /// Queues a match as `MatchRepository::record_match_with` records it.
+    pub fn record_match(
+        &mut self,
+        rule_id: &str,
+        file_path: &str,
+        file_hash: Option<&str>,
+        metadata: &BTreeMap<String, String>,
+        indexed_keys: &[String],
+    ) {
+        self.writes.push(Write::Match(MatchWrite {
+            rule_id: rule_id.to_string(),
+            file_path: file_path.to_string(),
+            file_hash: file_hash.map(str::to_string),
+            matched_at: Utc::now(),
+            metadata: metadata.clone(),
+            indexed_keys: indexed_keys.to_vec(),
+        }));
+    }
+
+    /// When the latest queued match of `file_path` was made.
+    pub fn matched_at(&self, file_path: &str) -> Option<DateTime<Utc>> {
+        self.writes
+            .iter()
+            .filter_map(|write| match write {
+                Write::Match(record) if record.file_path == file_path => Some(record.matched_at),
+                _ => None,
+            })
+            .max()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// The files the queued records are about: the ones logged, where they
+    /// were moved or copied to, and the ones matched.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.writes.iter().flat_map(|write| {
+            let (first, second) = match write {
+                Write::Log(entry) => (
+                    Some(entry.file_path.as_str()),
+                    entry
+                        .action_detail
+                        .as_ref()
+                        .and_then(|detail| detail.destination_path.as_deref()),
+                ),
+                Write::Undo(entry) => (Some(entry.current_path.as_str()), None),
+                Write::Match(record) => (Some(record.file_path.as_str()), None),
+            };
+            first.into_iter().chain(second)
+        })
+    }
+
+    /// Writes the queued records in one transaction.
+    pub fn commit(self, db: &Database) -> Result<()> {
+        commit_all(db, std::slice::from_ref(&self))
+    }
+}
+
+/// Writes `sets` in order in one transaction; if any record fails, none of
+/// them are written.
+pub fn commit_all(db: &Database, sets: &[WriteSet]) -> Result<()> {
+    if sets.iter().all(WriteSet::is_empty) {
+        return Ok(());
+    }
+    db.with_conn(|conn| {
+        let tx = conn.transaction()?;
+        for write in sets.iter().flat_map(|set| &set.writes) {
+            match write {
+                Write::Log(entry) => insert_log(&tx, entry)?,
+                Write::Undo(entry) => insert_undo(&tx, entry)?,
+                Write::Match(record) => write_match(
+                    &tx,
+                    &record.rule_id,
+                    &record.file_path,
+                    record.file_hash.as_deref(),
+                    record.matched_at,
+                    &record.metadata,
+                    &record.indexed_keys,
+                )?,
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    })
+}
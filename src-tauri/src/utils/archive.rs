@@ -6,12 +6,112 @@ use anyhow::{anyhow, Result};
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use glob::{MatchOptions, Pattern};
 use tar::Builder;
 use walkdir::WalkDir;
 use zip::write::FileOptions;
-use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use zip::{AesMode, CompressionMethod, ZipArchive, ZipWriter};
 
 use crate::models::ArchiveFormat;
+use crate::utils::tempfiles::app_temp;
+
+pub use filedispatch_core::evaluate::ArchiveListing;
+
+/// How far into a tar stream a listing walk may read. Entry data is skipped,
+/// not extracted, but gzip still has to be inflated to reach each header, so
+/// very large `.tar.gz` files are cut off here.
+const LISTING_MAX_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Folders and files an extraction leaves out when junk skipping is on.
+const JUNK_DIRS: &[&str] = &["__MACOSX"];
+const JUNK_FILES: &[&str] = &[".DS_Store", "Thumbs.db"];
+
+const FILTER_MATCH: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+/// A zip's entries are encrypted and there's no password to open them with.
+/// Carried inside `anyhow::Error` so callers can `downcast_ref` it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordRequired;
+
+impl std::fmt::Display for PasswordRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Archive is password-protected")
+    }
+}
+
+impl std::error::Error for PasswordRequired {}
+
+/// Which archive entries an extraction keeps. Globs are matched against the
+/// sanitized entry path and each folder above it, ignoring case, so `*.pdf`
+/// picks PDFs at any depth and `*/samples` leaves a whole folder out. An
+/// exclusion wins over an inclusion. The default keeps everything.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    skip_junk: bool,
+}
+
+impl ExtractFilter {
+    pub fn new(include: &[String], exclude: &[String], skip_junk: bool) -> Result<Self> {
+        Ok(Self {
+            include: compile_globs(include)?,
+            exclude: compile_globs(exclude)?,
+            skip_junk,
+        })
+    }
+
+    fn is_active(&self) -> bool {
+        self.skip_junk || !self.include.is_empty() || !self.exclude.is_empty()
+    }
+
+    pub fn allows(&self, entry_path: &Path) -> bool {
+        let parts: Vec<String> = entry_path
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        if self.skip_junk && is_junk(&parts) {
+            return false;
+        }
+        let paths: Vec<String> = (1..=parts.len()).map(|depth| parts[..depth].join("/")).collect();
+        let matches = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .any(|pattern| paths.iter().any(|path| pattern.matches_with(path, FILTER_MATCH)))
+        };
+        !matches(&self.exclude) && (self.include.is_empty() || matches(&self.include))
+    }
+}
+
+fn compile_globs(globs: &[String]) -> Result<Vec<Pattern>> {
+    globs
+        .iter()
+        .map(|glob| glob.trim())
+        .filter(|glob| !glob.is_empty())
+        .map(|glob| {
+            Pattern::new(&glob.replace('\\', "/"))
+                .map_err(|err| anyhow!("invalid glob \"{glob}\": {err}"))
+        })
+        .collect()
+}
+
+fn is_junk(parts: &[String]) -> bool {
+    parts.iter().any(|part| JUNK_DIRS.contains(&part.as_str()))
+        || parts
+            .last()
+            .is_some_and(|name| JUNK_FILES.contains(&name.as_str()))
+}
+
+/// File entries an extraction wrote and left out; folders aren't counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtractSummary {
+    pub extracted: usize,
+    pub skipped: usize,
+}
 
 pub fn detect_archive_format(path: &Path) -> Option<ArchiveFormat> {
     let name = path.file_name()?.to_string_lossy().to_lowercase();
@@ -25,6 +125,76 @@ pub fn detect_archive_format(path: &Path) -> Option<ArchiveFormat> {
     }
 }
 
+/// Reads the entry listing of a zip, tar or tar.gz file without extracting or
+/// decompressing entry contents: the central directory for zip, a header walk
+/// for tar. Stops after `max_entries` files. Password-protected zips are an
+/// error, since their names can't be trusted to describe what's inside.
+pub fn list_archive_entries(path: &Path, max_entries: usize) -> Result<ArchiveListing> {
+    let format = detect_archive_format(path)
+        .or_else(|| sniff_archive_format(path))
+        .ok_or_else(|| anyhow!("Not a zip or tar archive"))?;
+    match format {
+        ArchiveFormat::Zip => list_zip(path, max_entries),
+        ArchiveFormat::Tar => {
+            let mut archive = tar::Archive::new(File::open(path)?);
+            list_tar(archive.entries_with_seek()?, max_entries)
+        }
+        ArchiveFormat::TarGz => {
+            let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+            list_tar(archive.entries()?, max_entries)
+        }
+    }
+}
+
+fn sniff_archive_format(path: &Path) -> Option<ArchiveFormat> {
+    match infer::get_from_path(path).ok().flatten()?.mime_type() {
+        "application/zip" => Some(ArchiveFormat::Zip),
+        "application/x-tar" => Some(ArchiveFormat::Tar),
+        "application/gzip" => Some(ArchiveFormat::TarGz),
+        _ => None,
+    }
+}
+
+fn list_zip(path: &Path, max_entries: usize) -> Result<ArchiveListing> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut listing = ArchiveListing::default();
+    for index in 0..archive.len() {
+        let file = archive.by_index_raw(index)?;
+        if file.encrypted() {
+            return Err(PasswordRequired.into());
+        }
+        if file.is_dir() {
+            continue;
+        }
+        if listing.entries.len() >= max_entries {
+            listing.truncated = true;
+            break;
+        }
+        listing.entries.push(file.name().to_string());
+    }
+    Ok(listing)
+}
+
+fn list_tar<R: io::Read>(entries: tar::Entries<'_, R>, max_entries: usize) -> Result<ArchiveListing> {
+    let mut listing = ArchiveListing::default();
+    for entry in entries {
+        let entry = entry?;
+        if entry.raw_header_position() > LISTING_MAX_BYTES {
+            listing.truncated = true;
+            break;
+        }
+        if entry.header().entry_type().is_dir() {
+            continue;
+        }
+        if listing.entries.len() >= max_entries {
+            listing.truncated = true;
+            break;
+        }
+        listing.entries.push(path_to_string(&entry.path()?));
+    }
+    Ok(listing)
+}
+
 pub fn ensure_archive_path(
     destination: &Path,
     source: &Path,
@@ -50,46 +220,95 @@ pub fn ensure_archive_path(
     PathBuf::from(format!("{}.{}", dest_str, suffix))
 }
 
-pub fn create_archive(source: &Path, destination: &Path, format: &ArchiveFormat) -> Result<PathBuf> {
+/// Archives `source` at `destination`. With a password, the zip's entries
+/// are encrypted with AES-256; tar archives can't be encrypted.
+pub fn create_archive(
+    source: &Path,
+    destination: &Path,
+    format: &ArchiveFormat,
+    password: Option<&str>,
+) -> Result<PathBuf> {
+    if password.is_some() && *format != ArchiveFormat::Zip {
+        return Err(anyhow!("Encryption is not supported for tar archives"));
+    }
     if let Some(parent) = destination.parent() {
         fs::create_dir_all(parent)?;
     }
 
+    // Written beside the destination first, so a failure can't leave a
+    // truncated archive that looks finished.
+    let staged = app_temp().beside(destination)?;
     match format {
-        ArchiveFormat::Zip => create_zip(source, destination)?,
-        ArchiveFormat::Tar => create_tar(source, destination)?,
-        ArchiveFormat::TarGz => create_tar_gz(source, destination)?,
+        ArchiveFormat::Zip => create_zip(source, staged.path(), password)?,
+        ArchiveFormat::Tar => create_tar(source, staged.path())?,
+        ArchiveFormat::TarGz => create_tar_gz(source, staged.path())?,
     }
+    staged.persist(destination)?;
 
     Ok(destination.to_path_buf())
 }
 
-pub fn extract_archive(archive_path: &Path, destination: &Path) -> Result<()> {
+pub fn extract_archive(
+    archive_path: &Path,
+    destination: &Path,
+    filter: &ExtractFilter,
+) -> Result<ExtractSummary> {
     let format = detect_archive_format(archive_path)
         .ok_or_else(|| anyhow!("Unsupported archive format"))?;
 
     fs::create_dir_all(destination)?;
 
-    match format {
-        ArchiveFormat::Zip => extract_zip(archive_path, destination)?,
-        ArchiveFormat::Tar => extract_tar(archive_path, destination)?,
-        ArchiveFormat::TarGz => extract_tar_gz(archive_path, destination)?,
-    }
+    // Extract into a staging directory and only move entries over once the
+    // whole archive came out, so a bad entry halfway leaves nothing behind.
+    let staging = app_temp().staging_dir_in(destination)?;
+    let summary = match format {
+        ArchiveFormat::Zip => extract_zip(archive_path, staging.path(), filter)?,
+        ArchiveFormat::Tar => extract_tar(archive_path, staging.path(), filter)?,
+        ArchiveFormat::TarGz => extract_tar_gz(archive_path, staging.path(), filter)?,
+    };
+    merge_into(staging.path(), destination)?;
+
+    Ok(summary)
+}
 
+/// Moves everything in `from` into `to`, merging into existing directories and
+/// replacing files, as extracting straight into `to` would have.
+fn merge_into(from: &Path, to: &Path) -> Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if !entry.file_type()?.is_dir() {
+            fs::rename(entry.path(), &target)?;
+            continue;
+        }
+        // Not followed: a symlinked directory in the destination could point anywhere.
+        match fs::symlink_metadata(&target) {
+            Ok(meta) if meta.is_dir() => merge_into(&entry.path(), &target)?,
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(anyhow!("Security error: archive entry escapes destination"));
+            }
+            _ => fs::rename(entry.path(), &target)?,
+        }
+    }
     Ok(())
 }
 
-fn create_zip(source: &Path, destination: &Path) -> Result<()> {
+fn create_zip(source: &Path, destination: &Path, password: Option<&str>) -> Result<()> {
     let file = File::create(destination)?;
     let mut zip = ZipWriter::new(file);
     let options = FileOptions::<()>::default()
         .compression_method(CompressionMethod::Deflated)
         .unix_permissions(0o755);
+    // Folder entries hold no data, so only files are encrypted.
+    let file_options = match password {
+        Some(password) => options.with_aes_encryption(AesMode::Aes256, password),
+        None => options,
+    };
 
     let base = source.parent().unwrap_or_else(|| Path::new(""));
     if source.is_file() {
         let name = path_to_string(source.strip_prefix(base).unwrap_or(source));
-        zip.start_file(name, options)?;
+        zip.start_file(name, file_options)?;
         let mut src = File::open(source)?;
         io::copy(&mut src, &mut zip)?;
     } else {
@@ -100,7 +319,7 @@ fn create_zip(source: &Path, destination: &Path) -> Result<()> {
             if entry.file_type().is_dir() {
                 zip.add_directory(name, options)?;
             } else {
-                zip.start_file(name, options)?;
+                zip.start_file(name, file_options)?;
                 let mut src = File::open(path)?;
                 io::copy(&mut src, &mut zip)?;
             }
@@ -143,16 +362,31 @@ fn append_to_tar(builder: &mut Builder<impl Write>, source: &Path) -> Result<()>
     Ok(())
 }
 
-fn extract_zip(archive_path: &Path, destination: &Path) -> Result<()> {
+fn extract_zip(
+    archive_path: &Path,
+    destination: &Path,
+    filter: &ExtractFilter,
+) -> Result<ExtractSummary> {
     let file = File::open(archive_path)?;
     let mut archive = ZipArchive::new(file)?;
+    for i in 0..archive.len() {
+        if archive.by_index_raw(i)?.encrypted() {
+            return Err(PasswordRequired.into());
+        }
+    }
 
     let canonical_dest = destination.canonicalize()
         .unwrap_or_else(|_| destination.to_path_buf());
 
+    let mut summary = ExtractSummary::default();
     for i in 0..archive.len() {
         let mut entry = archive.by_index(i)?;
-        let entry_path = sanitize_entry_path(Path::new(entry.name()))?;
+        let is_dir = entry.name().ends_with('/') || entry.is_dir();
+        let Some(entry_path) =
+            filtered_entry_path(Path::new(entry.name()), is_dir, filter, &mut summary)?
+        else {
+            continue;
+        };
         let outpath = destination.join(&entry_path);
 
         // For existing paths, verify they're within destination (blocks symlink traversal)
@@ -176,7 +410,7 @@ fn extract_zip(archive_path: &Path, destination: &Path) -> Result<()> {
             }
         }
 
-        if entry.name().ends_with('/') || entry.is_dir() {
+        if is_dir {
             fs::create_dir_all(&outpath)?;
         } else {
             if let Some(parent) = outpath.parent() {
@@ -187,34 +421,49 @@ fn extract_zip(archive_path: &Path, destination: &Path) -> Result<()> {
         }
     }
 
-    Ok(())
+    Ok(summary)
 }
 
-fn extract_tar(archive_path: &Path, destination: &Path) -> Result<()> {
+fn extract_tar(
+    archive_path: &Path,
+    destination: &Path,
+    filter: &ExtractFilter,
+) -> Result<ExtractSummary> {
     let file = File::open(archive_path)?;
     let mut archive = tar::Archive::new(file);
-    extract_tar_safely(&mut archive, destination)?;
-    Ok(())
+    extract_tar_safely(&mut archive, destination, filter)
 }
 
-fn extract_tar_gz(archive_path: &Path, destination: &Path) -> Result<()> {
+fn extract_tar_gz(
+    archive_path: &Path,
+    destination: &Path,
+    filter: &ExtractFilter,
+) -> Result<ExtractSummary> {
     let file = File::open(archive_path)?;
     let decoder = GzDecoder::new(file);
     let mut archive = tar::Archive::new(decoder);
-    extract_tar_safely(&mut archive, destination)?;
-    Ok(())
+    extract_tar_safely(&mut archive, destination, filter)
 }
 
 /// Safely extracts a tar archive, preventing path traversal attacks (zip slip).
 /// Each entry's path is validated to ensure it stays within the destination directory.
-fn extract_tar_safely<R: std::io::Read>(archive: &mut tar::Archive<R>, destination: &Path) -> Result<()> {
+fn extract_tar_safely<R: std::io::Read>(
+    archive: &mut tar::Archive<R>,
+    destination: &Path,
+    filter: &ExtractFilter,
+) -> Result<ExtractSummary> {
     let canonical_dest = destination.canonicalize()
         .unwrap_or_else(|_| destination.to_path_buf());
 
+    let mut summary = ExtractSummary::default();
     for entry in archive.entries()? {
         let mut entry = entry?;
+        let is_dir = entry.header().entry_type().is_dir();
         let raw_entry_path = entry.path()?;
-        let entry_path = sanitize_entry_path(&raw_entry_path)?;
+        let Some(entry_path) = filtered_entry_path(&raw_entry_path, is_dir, filter, &mut summary)?
+        else {
+            continue;
+        };
 
         // Construct the full output path
         let outpath = destination.join(&entry_path);
@@ -257,7 +506,7 @@ fn extract_tar_safely<R: std::io::Read>(archive: &mut tar::Archive<R>, destinati
         }
 
         // Extract the entry
-        if entry.header().entry_type().is_dir() {
+        if is_dir {
             fs::create_dir_all(&outpath)?;
         } else {
             if let Some(parent) = outpath.parent() {
@@ -267,7 +516,7 @@ fn extract_tar_safely<R: std::io::Read>(archive: &mut tar::Archive<R>, destinati
         }
     }
 
-    Ok(())
+    Ok(summary)
 }
 
 fn archive_suffix(format: &ArchiveFormat) -> &'static str {
@@ -282,6 +531,29 @@ fn path_to_string(path: &Path) -> String {
     path.to_string_lossy().replace('\\', "/")
 }
 
+/// Sanitizes an entry's path and runs it through `filter`, counting it.
+/// `None` means the entry is left out. While filtering, folder entries are
+/// left out too: the folders kept files need are created for them, and
+/// folders holding only skipped entries shouldn't appear.
+fn filtered_entry_path(
+    raw_entry_path: &Path,
+    is_dir: bool,
+    filter: &ExtractFilter,
+    summary: &mut ExtractSummary,
+) -> Result<Option<PathBuf>> {
+    let entry_path = sanitize_entry_path(raw_entry_path)?;
+    if is_dir {
+        return Ok((!filter.is_active()).then_some(entry_path));
+    }
+    if filter.allows(&entry_path) {
+        summary.extracted += 1;
+        Ok(Some(entry_path))
+    } else {
+        summary.skipped += 1;
+        Ok(None)
+    }
+}
+
 fn sanitize_entry_path(entry_path: &Path) -> Result<PathBuf> {
     let raw = entry_path.to_string_lossy();
     let normalized = raw.replace('\\', "/");
@@ -318,12 +590,16 @@ fn sanitize_entry_path(entry_path: &Path) -> Result<PathBuf> {
 
 #[cfg(test)]
 mod tests {
-    use super::{create_archive, detect_archive_format, ensure_archive_path, extract_archive};
+    use super::{
+        create_archive, detect_archive_format, ensure_archive_path, extract_archive,
+        list_archive_entries, ExtractFilter, ExtractSummary, PasswordRequired,
+    };
     use crate::models::ArchiveFormat;
     use std::fs;
-    use std::io::Write;
+    use std::io::{Read, Write};
     use tempfile::tempdir;
     use zip::write::FileOptions;
+    use zip::ZipArchive;
 
     #[test]
     fn detects_formats() {
@@ -351,10 +627,10 @@ mod tests {
             let archive_dir = dir.path().join("out");
             fs::create_dir_all(&archive_dir).unwrap();
             let archive_path = ensure_archive_path(&archive_dir, &source, &format);
-            create_archive(&source, &archive_path, &format).unwrap();
+            create_archive(&source, &archive_path, &format, None).unwrap();
 
             let extract_dir = dir.path().join(format!("extract_{:?}", format));
-            extract_archive(&archive_path, &extract_dir).unwrap();
+            extract_archive(&archive_path, &extract_dir, &ExtractFilter::default()).unwrap();
 
             let extracted = extract_dir
                 .join("sample.txt")
@@ -378,10 +654,55 @@ mod tests {
 
         let extract_dir = dir.path().join("extract");
         fs::create_dir_all(&extract_dir).unwrap();
-        let result = extract_archive(&archive_path, &extract_dir);
+        let result = extract_archive(&archive_path, &extract_dir, &ExtractFilter::default());
         assert!(result.is_err());
     }
 
+    #[test]
+    fn failed_extraction_leaves_the_destination_untouched() {
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("half-bad.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive_path).unwrap());
+        let options = FileOptions::<()>::default();
+        zip.start_file("good.txt", options).unwrap();
+        zip.write_all(b"fine").unwrap();
+        zip.start_file("../evil.txt", options).unwrap();
+        zip.write_all(b"nope").unwrap();
+        zip.finish().unwrap();
+
+        let extract_dir = dir.path().join("extract");
+        fs::create_dir_all(&extract_dir).unwrap();
+        fs::write(extract_dir.join("existing.txt"), "mine").unwrap();
+        assert!(extract_archive(&archive_path, &extract_dir, &ExtractFilter::default()).is_err());
+
+        let names: Vec<_> = fs::read_dir(&extract_dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name())
+            .collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("existing.txt")]);
+    }
+
+    #[test]
+    fn extraction_merges_into_existing_folders() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("project");
+        fs::create_dir_all(source.join("docs")).unwrap();
+        fs::write(source.join("docs/new.txt"), "new").unwrap();
+        let archive_path = dir.path().join("project.zip");
+        create_archive(&source, &archive_path, &ArchiveFormat::Zip, None).unwrap();
+
+        let extract_dir = dir.path().join("extract");
+        let docs = extract_dir.join("project").join("docs");
+        fs::create_dir_all(&docs).unwrap();
+        fs::write(docs.join("old.txt"), "old").unwrap();
+        extract_archive(&archive_path, &extract_dir, &ExtractFilter::default()).unwrap();
+
+        assert_eq!(fs::read_to_string(docs.join("new.txt")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(docs.join("old.txt")).unwrap(), "old");
+        assert_eq!(fs::read_dir(&extract_dir).unwrap().count(), 1);
+    }
+
     #[test]
     fn rejects_tar_path_traversal() {
         let dir = tempdir().unwrap();
@@ -391,10 +712,262 @@ mod tests {
 
         let extract_dir = dir.path().join("extract");
         fs::create_dir_all(&extract_dir).unwrap();
-        let result = extract_archive(&archive_path, &extract_dir);
+        let result = extract_archive(&archive_path, &extract_dir, &ExtractFilter::default());
         assert!(result.is_err());
     }
 
+    #[test]
+    fn lists_zip_entries_in_nested_dirs() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("vendor");
+        fs::create_dir_all(source.join("drawings/site")).unwrap();
+        fs::write(source.join("readme.txt"), b"hi").unwrap();
+        fs::write(source.join("drawings/site/plan.dwg"), b"dwg").unwrap();
+        let archive_path = dir.path().join("vendor.zip");
+        create_archive(&source, &archive_path, &ArchiveFormat::Zip, None).unwrap();
+
+        let listing = list_archive_entries(&archive_path, 100).unwrap();
+        let mut entries = listing.entries.clone();
+        entries.sort();
+        assert_eq!(entries, vec!["vendor/drawings/site/plan.dwg", "vendor/readme.txt"]);
+        assert!(!listing.truncated);
+    }
+
+    #[test]
+    fn lists_tar_gz_entries() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("bundle");
+        fs::create_dir_all(source.join("cad")).unwrap();
+        fs::write(source.join("cad/part.dwg"), b"dwg").unwrap();
+        fs::write(source.join("notes.md"), b"notes").unwrap();
+        let archive_path = dir.path().join("bundle.tar.gz");
+        create_archive(&source, &archive_path, &ArchiveFormat::TarGz, None).unwrap();
+
+        let listing = list_archive_entries(&archive_path, 100).unwrap();
+        let mut entries = listing.entries.clone();
+        entries.sort();
+        assert_eq!(entries, vec!["bundle/cad/part.dwg", "bundle/notes.md"]);
+    }
+
+    #[test]
+    fn listing_stops_at_entry_cap() {
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("many.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive_path).unwrap());
+        for index in 0..5 {
+            zip.start_file(format!("file{}.txt", index), FileOptions::<()>::default())
+                .unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let listing = list_archive_entries(&archive_path, 3).unwrap();
+        assert_eq!(listing.entries.len(), 3);
+        assert!(listing.truncated);
+    }
+
+    #[test]
+    fn listing_rejects_password_protected_zip() {
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("secret.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive_path).unwrap());
+        let options =
+            FileOptions::<()>::default().with_aes_encryption(zip::AesMode::Aes256, "hunter2");
+        zip.start_file("plan.dwg", options).unwrap();
+        zip.write_all(b"dwg").unwrap();
+        zip.finish().unwrap();
+
+        let err = list_archive_entries(&archive_path, 100).unwrap_err();
+        assert!(err.to_string().contains("password"));
+    }
+
+    #[test]
+    fn archives_zip_with_aes_encryption() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("contract.pdf");
+        fs::write(&source, b"salary: 1").unwrap();
+        let archive_path = dir.path().join("contract.zip");
+        create_archive(&source, &archive_path, &ArchiveFormat::Zip, Some("hunter2")).unwrap();
+
+        // WinZip's AES layout, which other tools read: a 0x9901 extra field of
+        // 7 bytes with the "AE" vendor id.
+        let raw = fs::read(&archive_path).unwrap();
+        assert!(raw
+            .windows(8)
+            .any(|w| w[..4] == [0x01, 0x99, 0x07, 0x00] && &w[6..] == b"AE"));
+        assert!(!raw.windows(9).any(|w| w == b"salary: 1"));
+
+        let mut zip = ZipArchive::new(fs::File::open(&archive_path).unwrap()).unwrap();
+        assert!(zip.by_index_raw(0).unwrap().encrypted());
+        assert!(zip.get_aes_verification_key_and_salt(0).unwrap().is_some());
+        assert!(zip.by_index(0).is_err());
+        assert!(zip.by_index_decrypt(0, b"wrong").is_err());
+        let mut contents = String::new();
+        zip.by_index_decrypt(0, b"hunter2")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "salary: 1");
+    }
+
+    #[test]
+    fn tar_archives_cannot_be_encrypted() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("contract.pdf");
+        fs::write(&source, b"salary: 1").unwrap();
+        for format in [ArchiveFormat::Tar, ArchiveFormat::TarGz] {
+            let archive_path = ensure_archive_path(dir.path(), &source, &format);
+            let err = create_archive(&source, &archive_path, &format, Some("hunter2")).unwrap_err();
+            assert!(err.to_string().contains("not supported"));
+            assert!(!archive_path.exists());
+        }
+    }
+
+    #[test]
+    fn extracting_an_encrypted_zip_asks_for_a_password() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("contract.pdf");
+        fs::write(&source, b"salary: 1").unwrap();
+        let archive_path = dir.path().join("contract.zip");
+        create_archive(&source, &archive_path, &ArchiveFormat::Zip, Some("hunter2")).unwrap();
+
+        let out = dir.path().join("out");
+        let err = extract_archive(&archive_path, &out, &ExtractFilter::default()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PasswordRequired>(),
+            Some(&PasswordRequired)
+        );
+        assert_eq!(fs::read_dir(&out).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn listing_rejects_corrupt_archive() {
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("broken.zip");
+        fs::write(&archive_path, b"PK\x03\x04 definitely not a zip").unwrap();
+
+        assert!(list_archive_entries(&archive_path, 100).is_err());
+    }
+
+    fn write_zip(archive_path: &std::path::Path, names: &[&str]) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(archive_path).unwrap());
+        for name in names {
+            if name.ends_with('/') {
+                zip.add_directory(*name, FileOptions::<()>::default()).unwrap();
+            } else {
+                zip.start_file(*name, FileOptions::<()>::default()).unwrap();
+                zip.write_all(name.as_bytes()).unwrap();
+            }
+        }
+        zip.finish().unwrap();
+    }
+
+    fn extracted_files(root: &std::path::Path) -> Vec<String> {
+        let mut files: Vec<String> = walkdir::WalkDir::new(root)
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                let relative = entry.path().strip_prefix(root).unwrap();
+                relative.to_string_lossy().replace('\\', "/")
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn skips_junk_entries_when_asked() {
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("vendor.zip");
+        write_zip(
+            &archive_path,
+            &[
+                "payload/",
+                "payload/spec.pdf",
+                "payload/.DS_Store",
+                "__MACOSX/",
+                "__MACOSX/payload/._spec.pdf",
+                "Thumbs.db",
+            ],
+        );
+
+        let extract_dir = dir.path().join("extract");
+        let filter = ExtractFilter::new(&[], &[], true).unwrap();
+        let summary = extract_archive(&archive_path, &extract_dir, &filter).unwrap();
+
+        assert_eq!(extracted_files(&extract_dir), vec!["payload/spec.pdf"]);
+        assert_eq!(summary, ExtractSummary { extracted: 1, skipped: 3 });
+        assert!(!extract_dir.join("__MACOSX").exists());
+    }
+
+    #[test]
+    fn default_filter_extracts_everything() {
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("vendor.zip");
+        write_zip(&archive_path, &["empty/", "payload/.DS_Store", "payload/spec.pdf"]);
+
+        let extract_dir = dir.path().join("extract");
+        let summary =
+            extract_archive(&archive_path, &extract_dir, &ExtractFilter::default()).unwrap();
+
+        assert_eq!(
+            extracted_files(&extract_dir),
+            vec!["payload/.DS_Store", "payload/spec.pdf"]
+        );
+        assert!(extract_dir.join("empty").is_dir());
+        assert_eq!(summary, ExtractSummary { extracted: 2, skipped: 0 });
+    }
+
+    #[test]
+    fn include_glob_selects_matching_entries_at_any_depth() {
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("mixed.tar");
+        let source = dir.path().join("mixed");
+        fs::create_dir_all(source.join("docs")).unwrap();
+        fs::create_dir_all(source.join("samples")).unwrap();
+        fs::write(source.join("Manual.PDF"), "pdf").unwrap();
+        fs::write(source.join("docs/spec.pdf"), "pdf").unwrap();
+        fs::write(source.join("docs/notes.txt"), "txt").unwrap();
+        fs::write(source.join("samples/big.bin"), "bin").unwrap();
+        create_archive(&source, &archive_path, &ArchiveFormat::Tar, None).unwrap();
+
+        let extract_dir = dir.path().join("extract");
+        let filter = ExtractFilter::new(&["*.pdf".to_string()], &[], false).unwrap();
+        let summary = extract_archive(&archive_path, &extract_dir, &filter).unwrap();
+
+        assert_eq!(
+            extracted_files(&extract_dir),
+            vec!["mixed/Manual.PDF", "mixed/docs/spec.pdf"]
+        );
+        assert_eq!(summary, ExtractSummary { extracted: 2, skipped: 2 });
+        assert!(!extract_dir.join("mixed/samples").exists());
+    }
+
+    #[test]
+    fn exclusion_wins_over_inclusion() {
+        let dir = tempdir().unwrap();
+        let archive_path = dir.path().join("vendor.zip");
+        write_zip(
+            &archive_path,
+            &["payload/spec.pdf", "payload/samples/sample.pdf", "payload/readme.txt"],
+        );
+
+        let extract_dir = dir.path().join("extract");
+        let filter =
+            ExtractFilter::new(&["*.pdf".to_string()], &["*/samples".to_string()], false)
+                .unwrap();
+        extract_archive(&archive_path, &extract_dir, &filter).unwrap();
+
+        assert_eq!(extracted_files(&extract_dir), vec!["payload/spec.pdf"]);
+    }
+
+    #[test]
+    fn rejects_invalid_globs() {
+        let err = ExtractFilter::new(&[], &["[unclosed".to_string()], false).unwrap_err();
+        assert!(err.to_string().contains("[unclosed"));
+    }
+
     fn write_raw_tar_entry(archive_path: &std::path::Path, name: &str, data: &[u8]) {
         let mut header = [0u8; 512];
         let name_bytes = name.as_bytes();
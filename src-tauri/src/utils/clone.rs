@@ -0,0 +1,346 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::models::ReflinkMode;
+
+/// How a file copy was carried out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopyResult {
+    /// The destination shares the source's blocks instead of holding a copy.
+    pub reflinked: bool,
+}
+
+/// Copies `source` to `dest` (which must not exist), trying a copy-on-write
+/// clone first unless `mode` is `Never`.
+///
+/// The clone is FICLONE on Linux, clonefile on macOS and
+/// FSCTL_DUPLICATE_EXTENTS_TO_FILE on Windows (ReFS). Under `Auto` a
+/// filesystem or volume pair that can't clone falls back to a regular copy;
+/// under `Always` that is an error.
+pub fn copy_file(source: &Path, dest: &Path, mode: ReflinkMode) -> io::Result<CopyResult> {
+    copy_with(
+        mode,
+        || {
+            if !source.is_file() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "only regular files can be cloned",
+                ));
+            }
+            reflink(source, dest)
+        },
+        || {
+            fs_extra::file::copy(source, dest, &fs_extra::file::CopyOptions::new())
+                .map(|_| ())
+                .map_err(io::Error::other)
+        },
+    )
+}
+
+/// The decision between cloning and copying, with both operations injected.
+fn copy_with(
+    mode: ReflinkMode,
+    reflink: impl FnOnce() -> io::Result<()>,
+    copy: impl FnOnce() -> io::Result<()>,
+) -> io::Result<CopyResult> {
+    if mode == ReflinkMode::Never {
+        copy()?;
+        return Ok(CopyResult { reflinked: false });
+    }
+    match reflink() {
+        Ok(()) => Ok(CopyResult { reflinked: true }),
+        Err(err) if is_unsupported(&err) => {
+            if mode == ReflinkMode::Always {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("Copy-on-write clone isn't possible here: {err}"),
+                ));
+            }
+            copy()?;
+            Ok(CopyResult { reflinked: false })
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Errors meaning "this filesystem or volume pair can't clone", as opposed
+/// to a real I/O failure.
+pub fn is_unsupported(err: &io::Error) -> bool {
+    if err.kind() == io::ErrorKind::Unsupported {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        matches!(
+            err.raw_os_error(),
+            Some(code) if code == libc::EOPNOTSUPP
+                || code == libc::ENOTSUP
+                || code == libc::EXDEV
+                || code == libc::ENOTTY
+                || code == libc::EINVAL
+        )
+    }
+    #[cfg(windows)]
+    {
+        // ERROR_INVALID_FUNCTION, ERROR_NOT_SAME_DEVICE, ERROR_NOT_SUPPORTED,
+        // ERROR_INVALID_PARAMETER
+        matches!(err.raw_os_error(), Some(1) | Some(17) | Some(50) | Some(87))
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, dest: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // _IOW(0x94, 9, int)
+    const FICLONE: libc::c_ulong = 0x4004_9409;
+
+    let src = fs::File::open(source)?;
+    let dst = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)?;
+    let result = unsafe { libc::ioctl(dst.as_raw_fd(), FICLONE as _, src.as_raw_fd()) };
+    if result != 0 {
+        let err = io::Error::last_os_error();
+        drop(dst);
+        let _ = fs::remove_file(dest);
+        return Err(err);
+    }
+    dst.set_permissions(src.metadata()?.permissions())?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn reflink(source: &Path, dest: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let src = CString::new(source.as_os_str().as_bytes())?;
+    let dst = CString::new(dest.as_os_str().as_bytes())?;
+    // clonefile creates `dest` itself and copies permissions along with the data.
+    if unsafe { libc::clonefile(src.as_ptr(), dst.as_ptr(), 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn reflink(source: &Path, dest: &Path) -> io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+
+    const FSCTL_DUPLICATE_EXTENTS_TO_FILE: u32 = 0x0009_8344;
+    // ReFS clusters are 4 KiB or 64 KiB; rounding the length up to 64 KiB
+    // satisfies either, and the destination's size caps what's visible.
+    const CLUSTER_ALIGN: u64 = 64 * 1024;
+
+    #[repr(C)]
+    struct DuplicateExtentsData {
+        file_handle: *mut std::ffi::c_void,
+        source_file_offset: i64,
+        target_file_offset: i64,
+        byte_count: i64,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn DeviceIoControl(
+            device: *mut std::ffi::c_void,
+            control_code: u32,
+            in_buffer: *const std::ffi::c_void,
+            in_size: u32,
+            out_buffer: *mut std::ffi::c_void,
+            out_size: u32,
+            bytes_returned: *mut u32,
+            overlapped: *mut std::ffi::c_void,
+        ) -> i32;
+    }
+
+    let src = fs::File::open(source)?;
+    let len = src.metadata()?.len();
+    let dst = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)?;
+    let cloned = (|| {
+        dst.set_len(len)?;
+        if len == 0 {
+            return Ok(());
+        }
+        let data = DuplicateExtentsData {
+            file_handle: src.as_raw_handle() as *mut _,
+            source_file_offset: 0,
+            target_file_offset: 0,
+            byte_count: len.div_ceil(CLUSTER_ALIGN) as i64 * CLUSTER_ALIGN as i64,
+        };
+        let mut returned = 0u32;
+        let ok = unsafe {
+            DeviceIoControl(
+                dst.as_raw_handle() as *mut _,
+                FSCTL_DUPLICATE_EXTENTS_TO_FILE,
+                &data as *const _ as *const _,
+                std::mem::size_of::<DuplicateExtentsData>() as u32,
+                std::ptr::null_mut(),
+                0,
+                &mut returned,
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    })();
+    if cloned.is_err() {
+        drop(dst);
+        let _ = fs::remove_file(dest);
+    }
+    cloned
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn reflink(_source: &Path, _dest: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "copy-on-write clones aren't supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tempfile::tempdir;
+
+    #[cfg(unix)]
+    fn os_error(code: i32) -> io::Error {
+        io::Error::from_raw_os_error(code)
+    }
+
+    #[test]
+    fn never_skips_the_clone_attempt() {
+        let tried = Cell::new(false);
+        let result = copy_with(
+            ReflinkMode::Never,
+            || {
+                tried.set(true);
+                Ok(())
+            },
+            || Ok(()),
+        )
+        .unwrap();
+        assert!(!result.reflinked);
+        assert!(!tried.get());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn auto_falls_back_only_when_cloning_is_unsupported() {
+        for code in [libc::EOPNOTSUPP, libc::EXDEV] {
+            let copied = Cell::new(false);
+            let result = copy_with(
+                ReflinkMode::Auto,
+                || Err(os_error(code)),
+                || {
+                    copied.set(true);
+                    Ok(())
+                },
+            )
+            .unwrap();
+            assert!(!result.reflinked);
+            assert!(copied.get());
+        }
+
+        let cloned = copy_with(ReflinkMode::Auto, || Ok(()), || panic!("copied")).unwrap();
+        assert!(cloned.reflinked);
+
+        // A real failure isn't papered over with a copy.
+        let err = copy_with(
+            ReflinkMode::Auto,
+            || Err(os_error(libc::ENOSPC)),
+            || panic!("copied"),
+        )
+        .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOSPC));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn always_refuses_to_fall_back() {
+        let err = copy_with(
+            ReflinkMode::Always,
+            || Err(os_error(libc::EXDEV)),
+            || panic!("copied"),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(copy_with(ReflinkMode::Always, || Ok(()), || panic!("copied"))
+            .unwrap()
+            .reflinked);
+    }
+
+    /// tmpfs can't clone, so Auto has to end up with an ordinary copy.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn tmpfs_falls_back_to_copy() {
+        const TMPFS_MAGIC: i64 = 0x0102_1994;
+        let shm = Path::new("/dev/shm");
+        let Ok(c_path) = std::ffi::CString::new("/dev/shm") else {
+            return;
+        };
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if !shm.is_dir()
+            || unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0
+            || stat.f_type as i64 != TMPFS_MAGIC
+        {
+            return;
+        }
+
+        let dir = tempfile::tempdir_in(shm).unwrap();
+        let source = dir.path().join("big.bin");
+        fs::write(&source, vec![7u8; 256 * 1024]).unwrap();
+        let dest = dir.path().join("copy.bin");
+
+        let result = copy_file(&source, &dest, ReflinkMode::Auto).unwrap();
+        assert!(!result.reflinked);
+        assert_eq!(fs::read(&dest).unwrap(), fs::read(&source).unwrap());
+
+        let always = copy_file(&source, &dir.path().join("never.bin"), ReflinkMode::Always);
+        assert!(always.is_err());
+        assert!(!dir.path().join("never.bin").exists());
+    }
+
+    /// Set FILE_DISPATCH_REFLINK_DIR to a directory on Btrfs or XFS (e.g. a
+    /// loopback mount in CI) to check that clones really happen there.
+    #[test]
+    fn reflink_capable_filesystem_clones() {
+        let Some(root) = std::env::var_os("FILE_DISPATCH_REFLINK_DIR") else {
+            return;
+        };
+        let dir = tempfile::tempdir_in(root).unwrap();
+        let source = dir.path().join("big.bin");
+        fs::write(&source, vec![3u8; 1024 * 1024]).unwrap();
+        let dest = dir.path().join("clone.bin");
+
+        let result = copy_file(&source, &dest, ReflinkMode::Always).unwrap();
+        assert!(result.reflinked);
+        assert_eq!(fs::read(&dest).unwrap(), fs::read(&source).unwrap());
+    }
+
+    #[test]
+    fn never_copies_normally() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("a.txt");
+        fs::write(&source, "data").unwrap();
+        let dest = dir.path().join("b.txt");
+
+        let result = copy_file(&source, &dest, ReflinkMode::Never).unwrap();
+        assert!(!result.reflinked);
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "data");
+    }
+}
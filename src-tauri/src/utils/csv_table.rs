@@ -0,0 +1,226 @@
+//! Reads one column of a CSV file for the CsvColumn condition. Streams the
+//! file and stops after the rows asked for or `MAX_CSV_BYTES`, so a large
+//! export costs no more than its first rows.
+
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use filedispatch_core::validate::ascii_delimiter;
+
+use crate::models::{CsvColumnRef, CsvDelimiter};
+
+/// Bytes read at most; rows past this are never looked at.
+pub const MAX_CSV_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Bytes checked for binary content and searched for the header line.
+const SNIFF_BYTES: u64 = 64 * 1024;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Delimiters auto-detection chooses between, preferred in this order on a tie.
+const AUTO_DELIMITERS: [u8; 3] = [b',', b';', b'\t'];
+
+/// The cells of `column` in the first `max_rows` data rows, in order. Files
+/// that don't read as CSV (binary, not UTF-8, rows of differing lengths) are
+/// an error saying why.
+pub fn read_column(
+    path: &Path,
+    column: &CsvColumnRef,
+    delimiter: &CsvDelimiter,
+    max_rows: usize,
+) -> Result<Vec<String>> {
+    read_column_from(File::open(path)?, column, delimiter, max_rows)
+}
+
+pub(crate) fn read_column_from(
+    source: impl Read,
+    column: &CsvColumnRef,
+    delimiter: &CsvDelimiter,
+    max_rows: usize,
+) -> Result<Vec<String>> {
+    let mut capped = source.take(MAX_CSV_BYTES);
+    let mut head = Vec::new();
+    (&mut capped).take(SNIFF_BYTES).read_to_end(&mut head)?;
+    if head.contains(&0) {
+        bail!("Binary file");
+    }
+    let bom = if head.starts_with(UTF8_BOM) {
+        UTF8_BOM.len()
+    } else {
+        0
+    };
+    let delimiter = match delimiter {
+        CsvDelimiter::Auto => detect_delimiter(&head[bom..]),
+        CsvDelimiter::Char { value } => ascii_delimiter(*value)?,
+    };
+    let mut head = Cursor::new(head);
+    head.set_position(bom as u64);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(true)
+        .from_reader(head.chain(capped));
+    let headers = reader.headers().map_err(describe)?.clone();
+    if headers.iter().all(|header| header.trim().is_empty()) {
+        bail!("No header line");
+    }
+    let index = column_index(&headers, column)?;
+
+    let mut cells = Vec::new();
+    let mut record = csv::StringRecord::new();
+    while cells.len() < max_rows {
+        match reader.read_record(&mut record) {
+            Ok(true) => cells.push(record.get(index).unwrap_or_default().to_string()),
+            Ok(false) => break,
+            // The cap can cut the last row short; the rows before it stand.
+            Err(_) if reader.get_ref().get_ref().1.limit() == 0 => break,
+            Err(err) => return Err(describe(err)),
+        }
+    }
+    Ok(cells)
+}
+
+fn column_index(headers: &csv::StringRecord, column: &CsvColumnRef) -> Result<usize> {
+    match column {
+        CsvColumnRef::Index { index } => {
+            if *index == 0 || *index > headers.len() {
+                bail!(
+                    "No column {index}; the header has {} column{}",
+                    headers.len(),
+                    if headers.len() == 1 { "" } else { "s" }
+                );
+            }
+            Ok(index - 1)
+        }
+        CsvColumnRef::Header { name } => {
+            let wanted = name.trim().to_lowercase();
+            headers
+                .iter()
+                .position(|header| header.trim().to_lowercase() == wanted)
+                .ok_or_else(|| anyhow!("No \"{}\" column in the header", name.trim()))
+        }
+    }
+}
+
+/// Picks whichever of comma, semicolon and tab appears most often outside
+/// quotes in the first line.
+fn detect_delimiter(head: &[u8]) -> u8 {
+    let line = head.split(|byte| *byte == b'\n').next().unwrap_or_default();
+    let mut counts = [0usize; AUTO_DELIMITERS.len()];
+    let mut quoted = false;
+    for byte in line {
+        if *byte == b'"' {
+            quoted = !quoted;
+        } else if !quoted {
+            if let Some(slot) = AUTO_DELIMITERS.iter().position(|d| d == byte) {
+                counts[slot] += 1;
+            }
+        }
+    }
+    let best = counts.iter().enumerate().fold(
+        0,
+        |best, (slot, count)| if *count > counts[best] { slot } else { best },
+    );
+    AUTO_DELIMITERS[best]
+}
+
+fn describe(err: csv::Error) -> anyhow::Error {
+    match err.kind() {
+        csv::ErrorKind::Utf8 { .. } => anyhow!("Not UTF-8 text"),
+        csv::ErrorKind::UnequalLengths {
+            pos,
+            expected_len,
+            len,
+        } => {
+            let line = pos
+                .as_ref()
+                .map(|pos| format!("line {}", pos.line()))
+                .unwrap_or_else(|| "a row".to_string());
+            anyhow!("{line} has {len} fields, not the header's {expected_len}")
+        }
+        _ => anyhow!(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BANK_EXPORT: &str = "\u{feff}Booking date;Account ;Amount;Memo\n\
+        2025-03-01;DE44 5001 0517 5407 3249 31;-12,50;\"Coffee; beans\"\n\
+        2025-03-02;DE89 3704 0044 0532 0130 00;100,00;Salary\n";
+
+    fn read(source: &str, column: CsvColumnRef, max_rows: usize) -> Result<Vec<String>> {
+        read_column_from(source.as_bytes(), &column, &CsvDelimiter::Auto, max_rows)
+    }
+
+    fn header(name: &str) -> CsvColumnRef {
+        CsvColumnRef::Header {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn reads_semicolon_delimited_exports() {
+        assert_eq!(detect_delimiter(BANK_EXPORT.as_bytes()), b';');
+        assert_eq!(
+            read(BANK_EXPORT, CsvColumnRef::Index { index: 4 }, 10).unwrap(),
+            vec!["Coffee; beans", "Salary"]
+        );
+    }
+
+    #[test]
+    fn header_names_ignore_case_and_surrounding_space() {
+        assert_eq!(
+            read(BANK_EXPORT, header("  ACCOUNT"), 1).unwrap(),
+            vec!["DE44 5001 0517 5407 3249 31"]
+        );
+        let err = read(BANK_EXPORT, header("IBAN"), 1).unwrap_err();
+        assert_eq!(err.to_string(), "No \"IBAN\" column in the header");
+        assert!(read(BANK_EXPORT, CsvColumnRef::Index { index: 0 }, 1).is_err());
+    }
+
+    #[test]
+    fn stops_after_max_rows() {
+        let mut source = "id,account\n".to_string();
+        for row in 1..=50 {
+            source.push_str(&format!("{row},ACC-{row}\n"));
+        }
+        // A bad row past the cap is never read.
+        source.push_str("51\n");
+
+        let cells = read(&source, header("account"), 20).unwrap();
+        assert_eq!(cells.len(), 20);
+        assert_eq!(cells.last().map(String::as_str), Some("ACC-20"));
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_csv() {
+        let mismatched = "id,account\n1,ACC-1\n2\n";
+        let err = read(mismatched, header("account"), 10).unwrap_err();
+        assert_eq!(err.to_string(), "line 3 has 1 fields, not the header's 2");
+
+        let binary = read_column_from(
+            &b"PK\x03\x04\x00\x00binary"[..],
+            &header("account"),
+            &CsvDelimiter::Auto,
+            10,
+        );
+        assert_eq!(binary.unwrap_err().to_string(), "Binary file");
+    }
+
+    #[test]
+    fn explicit_delimiter_overrides_detection() {
+        let source = "a|b\n1|2\n";
+        let cells = read_column_from(
+            source.as_bytes(),
+            &header("b"),
+            &CsvDelimiter::Char { value: '|' },
+            1,
+        )
+        .unwrap();
+        assert_eq!(cells, vec!["2"]);
+    }
+}
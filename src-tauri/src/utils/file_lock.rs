@@ -0,0 +1,116 @@
+use std::fs::OpenOptions;
+use std::io;
+use std::path::Path;
+
+/// Returns true when another process holds the file open in a way that blocks
+/// moving or writing it. The file is opened for write but never modified.
+///
+/// On Windows this requests an exclusive (no-share) handle, which fails with a
+/// sharing violation while any other process has the file open. Elsewhere it
+/// probes for a conflicting `flock` lock, since Unix lets open files be moved.
+pub fn is_locked(path: &Path) -> bool {
+    if !path.is_file() {
+        return false;
+    }
+    match open_exclusive(path) {
+        Ok(_) => false,
+        Err(err) => is_lock_error(&err),
+    }
+}
+
+/// Classifies an I/O error as a lock/sharing conflict rather than a
+/// permission or missing-file problem.
+pub fn is_lock_error(err: &io::Error) -> bool {
+    #[cfg(windows)]
+    {
+        // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+        matches!(err.raw_os_error(), Some(32) | Some(33))
+    }
+    #[cfg(unix)]
+    {
+        matches!(err.raw_os_error(), Some(code) if code == libc::EWOULDBLOCK || code == libc::ETXTBSY)
+    }
+    #[cfg(not(any(windows, unix)))]
+    {
+        let _ = err;
+        false
+    }
+}
+
+/// Opens `path` for read/write with an exclusive lock held until the returned
+/// handle is dropped. Fails with a lock error if another handle conflicts.
+#[cfg(windows)]
+pub fn open_exclusive(path: &Path) -> io::Result<std::fs::File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .share_mode(0)
+        .open(path)
+}
+
+#[cfg(unix)]
+pub fn open_exclusive(path: &Path) -> io::Result<std::fs::File> {
+    use std::os::unix::io::AsRawFd;
+    let file = OpenOptions::new().read(true).write(true).open(path)?;
+    // The lock is released when `file` is dropped.
+    let result = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+#[cfg(not(any(windows, unix)))]
+pub fn open_exclusive(path: &Path) -> io::Result<std::fs::File> {
+    OpenOptions::new().read(true).write(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn unlocked_file_is_not_locked() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("free.txt");
+        fs::write(&path, "data").unwrap();
+
+        assert!(!is_locked(&path));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "data");
+        assert!(!is_locked(dir.path()));
+        assert!(!is_locked(&dir.path().join("missing.txt")));
+    }
+
+    #[test]
+    fn held_exclusive_handle_is_detected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("busy.txt");
+        fs::write(&path, "data").unwrap();
+
+        let guard = open_exclusive(&path).unwrap();
+        assert!(is_locked(&path));
+        drop(guard);
+        assert!(!is_locked(&path));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn rename_of_open_file_reports_lock_error() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("open.txt");
+        fs::write(&path, "data").unwrap();
+
+        let _guard = open_exclusive(&path).unwrap();
+        let err = fs::rename(&path, dir.path().join("moved.txt")).unwrap_err();
+        assert!(is_lock_error(&err));
+    }
+
+    #[test]
+    fn permission_errors_are_not_lock_errors() {
+        let err = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(!is_lock_error(&err));
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::models::DestinationPermissions;
+
+/// Metadata keys recording a file's mode and group before and after a
+/// `destination_permissions` change.
+pub const MODE_BEFORE_KEY: &str = "mode_before";
+pub const MODE_AFTER_KEY: &str = "mode_after";
+pub const GROUP_BEFORE_KEY: &str = "group_before";
+pub const GROUP_AFTER_KEY: &str = "group_after";
+
+const SETGID: u32 = 0o2000;
+
+/// The permission bits and group id of a file or folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ownership {
+    /// Permission bits, including setuid, setgid and sticky.
+    pub mode: u32,
+    pub gid: u32,
+}
+
+/// What applying `destination_permissions` did to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModeChange {
+    pub before: Ownership,
+    pub after: Ownership,
+}
+
+impl ModeChange {
+    pub fn record(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert(
+            MODE_BEFORE_KEY.to_string(),
+            format!("{:04o}", self.before.mode),
+        );
+        metadata.insert(
+            MODE_AFTER_KEY.to_string(),
+            format!("{:04o}", self.after.mode),
+        );
+        metadata.insert(GROUP_BEFORE_KEY.to_string(), self.before.gid.to_string());
+        metadata.insert(GROUP_AFTER_KEY.to_string(), self.after.gid.to_string());
+    }
+}
+
+/// Parses an octal mode such as `0640` or `640`.
+pub fn parse_mode(mode: &str) -> Result<u32, String> {
+    let trimmed = mode.trim();
+    let digits = trimmed.strip_prefix("0o").unwrap_or(trimmed);
+    match u32::from_str_radix(digits, 8) {
+        Ok(bits) if !digits.is_empty() && bits <= 0o7777 => Ok(bits),
+        _ => Err(format!(
+            "Invalid file mode \"{mode}\": expected octal like 0640"
+        )),
+    }
+}
+
+/// Checks an explicit mode and group without touching any file.
+pub fn validate(perms: &DestinationPermissions) -> Result<(), String> {
+    let DestinationPermissions::Explicit { mode, group } = perms else {
+        return Ok(());
+    };
+    let mode = mode.as_deref().filter(|mode| !mode.trim().is_empty());
+    let group = group.as_deref().filter(|group| !group.trim().is_empty());
+    if mode.is_none() && group.is_none() {
+        return Err("Explicit permissions need a mode, a group, or both".to_string());
+    }
+    if let Some(mode) = mode {
+        parse_mode(mode)?;
+    }
+    #[cfg(unix)]
+    if let Some(group) = group {
+        resolve_group(group)?;
+    }
+    Ok(())
+}
+
+/// What a file created in `dir` would get: the folder's read and write bits,
+/// execute bits only where the file already had them, and the folder's group
+/// when the folder is setgid.
+pub fn inherited(file: Ownership, dir: Ownership) -> Ownership {
+    Ownership {
+        mode: (dir.mode & 0o666) | (file.mode & dir.mode & 0o111),
+        gid: if dir.mode & SETGID != 0 {
+            dir.gid
+        } else {
+            file.gid
+        },
+    }
+}
+
+/// Applies `perms` to the file at `path`. `None` means the platform has no
+/// Unix modes and nothing was changed.
+#[cfg(unix)]
+pub fn apply(path: &Path, perms: &DestinationPermissions) -> Result<Option<ModeChange>, String> {
+    use std::fs;
+    use std::os::unix::fs::{chown, PermissionsExt};
+
+    let before = ownership(path)?;
+    let after = match perms {
+        DestinationPermissions::Keep => before,
+        DestinationPermissions::Inherit => {
+            let dir = path.parent().unwrap_or(Path::new("/"));
+            inherited(before, ownership(dir)?)
+        }
+        DestinationPermissions::Explicit { mode, group } => {
+            let mode = match mode.as_deref().filter(|mode| !mode.trim().is_empty()) {
+                Some(mode) => parse_mode(mode)?,
+                None => before.mode,
+            };
+            let gid = match group.as_deref().filter(|group| !group.trim().is_empty()) {
+                Some(group) => resolve_group(group)?,
+                None => before.gid,
+            };
+            Ownership { mode, gid }
+        }
+    };
+
+    if after.gid != before.gid {
+        chown(path, None, Some(after.gid)).map_err(|err| {
+            if err.raw_os_error() == Some(libc::EPERM) {
+                format!(
+                    "Not permitted to change the group of {} to {}: the app must own the \
+                     file and belong to that group",
+                    path.display(),
+                    after.gid
+                )
+            } else {
+                format!("Couldn't change the group of {}: {err}", path.display())
+            }
+        })?;
+    }
+    // A group change by an unprivileged user clears setuid and setgid, so the
+    // mode always goes on after it.
+    if after.mode != before.mode || after.gid != before.gid {
+        fs::set_permissions(path, fs::Permissions::from_mode(after.mode)).map_err(|err| {
+            format!(
+                "Couldn't set the mode of {} to {:04o}: {err}",
+                path.display(),
+                after.mode
+            )
+        })?;
+    }
+    Ok(Some(ModeChange { before, after }))
+}
+
+#[cfg(not(unix))]
+pub fn apply(_path: &Path, _perms: &DestinationPermissions) -> Result<Option<ModeChange>, String> {
+    Ok(None)
+}
+
+#[cfg(unix)]
+fn ownership(path: &Path) -> Result<Ownership, String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let meta = std::fs::metadata(path)
+        .map_err(|err| format!("Couldn't read permissions of {}: {err}", path.display()))?;
+    Ok(Ownership {
+        mode: meta.mode() & 0o7777,
+        gid: meta.gid(),
+    })
+}
+
+/// Resolves a group name or numeric id to a gid.
+#[cfg(unix)]
+fn resolve_group(group: &str) -> Result<u32, String> {
+    let group = group.trim();
+    if let Ok(gid) = group.parse::<u32>() {
+        return Ok(gid);
+    }
+    let unknown = || format!("Unknown group \"{group}\"");
+    let name = std::ffi::CString::new(group).map_err(|_| unknown())?;
+    let mut buf: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        let mut entry: libc::group = unsafe { std::mem::zeroed() };
+        let mut found: *mut libc::group = std::ptr::null_mut();
+        let code = unsafe {
+            libc::getgrnam_r(
+                name.as_ptr(),
+                &mut entry,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut found,
+            )
+        };
+        if code == libc::ERANGE && buf.len() < 1 << 20 {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if code != 0 || found.is_null() {
+            return Err(unknown());
+        }
+        return Ok(entry.gr_gid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use std::fs;
+    #[cfg(unix)]
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    #[cfg(unix)]
+    use tempfile::tempdir;
+
+    #[test]
+    fn parse_mode_accepts_octal_only() {
+        assert_eq!(parse_mode("0600"), Ok(0o600));
+        assert_eq!(parse_mode("2770"), Ok(0o2770));
+        assert_eq!(parse_mode("0o644"), Ok(0o644));
+        assert!(parse_mode("0800").is_err());
+        assert!(parse_mode("17777").is_err());
+        assert!(parse_mode("").is_err());
+    }
+
+    #[test]
+    fn validate_requires_something_explicit() {
+        assert!(validate(&DestinationPermissions::Inherit).is_ok());
+        assert!(validate(&DestinationPermissions::Explicit {
+            mode: None,
+            group: Some(" ".to_string()),
+        })
+        .is_err());
+        assert!(validate(&DestinationPermissions::Explicit {
+            mode: Some("rw-r--r--".to_string()),
+            group: None,
+        })
+        .is_err());
+        assert!(validate(&DestinationPermissions::Explicit {
+            mode: Some("0640".to_string()),
+            group: Some("0".to_string()),
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn inherited_takes_the_group_only_from_setgid_folders() {
+        let file = Ownership {
+            mode: 0o755,
+            gid: 100,
+        };
+        let shared = Ownership {
+            mode: 0o2770,
+            gid: 500,
+        };
+        assert_eq!(
+            inherited(file, shared),
+            Ownership {
+                mode: 0o770,
+                gid: 500
+            }
+        );
+
+        let plain = Ownership {
+            mode: 0o750,
+            gid: 500,
+        };
+        let doc = Ownership {
+            mode: 0o644,
+            gid: 100,
+        };
+        assert_eq!(
+            inherited(doc, plain),
+            Ownership {
+                mode: 0o640,
+                gid: 100
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn inherit_follows_a_setgid_folder() {
+        let temp = tempdir().unwrap();
+        let shared = temp.path().join("shared");
+        fs::create_dir(&shared).unwrap();
+        fs::set_permissions(&shared, fs::Permissions::from_mode(0o2770)).unwrap();
+        let file = shared.join("report.pdf");
+        fs::write(&file, b"x").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let change = apply(&file, &DestinationPermissions::Inherit)
+            .unwrap()
+            .unwrap();
+
+        let meta = fs::metadata(&file).unwrap();
+        assert_eq!(meta.mode() & 0o7777, 0o660);
+        assert_eq!(meta.gid(), fs::metadata(&shared).unwrap().gid());
+        assert_eq!(change.before.mode, 0o644);
+        assert_eq!(change.after.mode, 0o660);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn explicit_mode_is_applied_and_recorded() {
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("secret.txt");
+        fs::write(&file, b"x").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let perms = DestinationPermissions::Explicit {
+            mode: Some("0600".to_string()),
+            group: None,
+        };
+        let change = apply(&file, &perms).unwrap().unwrap();
+
+        assert_eq!(fs::metadata(&file).unwrap().mode() & 0o7777, 0o600);
+        let mut metadata = HashMap::new();
+        change.record(&mut metadata);
+        assert_eq!(
+            metadata.get(MODE_BEFORE_KEY).map(String::as_str),
+            Some("0644")
+        );
+        assert_eq!(
+            metadata.get(MODE_AFTER_KEY).map(String::as_str),
+            Some("0600")
+        );
+        assert_eq!(
+            metadata.get(GROUP_BEFORE_KEY),
+            metadata.get(GROUP_AFTER_KEY)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn changing_to_a_foreign_group_reports_eperm() {
+        // Root, or a member of group 0, is allowed to make this change.
+        let mut groups = vec![0 as libc::gid_t; 256];
+        let count = unsafe { libc::getgroups(groups.len() as i32, groups.as_mut_ptr()) };
+        let in_root_group = count < 0 || groups[..count as usize].contains(&0);
+        if unsafe { libc::geteuid() } == 0 || unsafe { libc::getegid() } == 0 || in_root_group {
+            return;
+        }
+
+        let temp = tempdir().unwrap();
+        let file = temp.path().join("report.pdf");
+        fs::write(&file, b"x").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let perms = DestinationPermissions::Explicit {
+            mode: Some("0640".to_string()),
+            group: Some("0".to_string()),
+        };
+        let err = apply(&file, &perms).unwrap_err();
+
+        assert!(err.starts_with("Not permitted"), "{err}");
+        assert_eq!(fs::metadata(&file).unwrap().mode() & 0o7777, 0o644);
+    }
+}
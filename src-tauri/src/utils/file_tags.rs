@@ -0,0 +1,221 @@
+//! A file's tags, read from and written to the extended attribute the
+//! platform keeps them in: Finder's on macOS, whose property list is handled
+//! by `filedispatch_core::finder_tags`, and `user.xdg.tags` on Linux, a
+//! comma-separated list file managers such as Dolphin show.
+
+use std::io;
+use std::path::Path;
+
+use filedispatch_core::finder_tags::{self, add_tags, tag_name};
+
+use crate::models::TagMode;
+use crate::utils::xattr::{is_unsupported, Xattrs};
+
+/// The attribute Linux desktops keep tags in.
+pub const XDG_ATTRIBUTE: &str = "user.xdg.tags";
+
+/// Where and how tags are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagFormat {
+    Finder,
+    Xdg,
+}
+
+impl TagFormat {
+    /// How this platform keeps tags; `None` where files can't be tagged.
+    pub fn native() -> Option<Self> {
+        if cfg!(target_os = "macos") {
+            Some(Self::Finder)
+        } else if cfg!(target_os = "linux") {
+            Some(Self::Xdg)
+        } else {
+            None
+        }
+    }
+
+    fn attribute(self) -> &'static str {
+        match self {
+            Self::Finder => finder_tags::ATTRIBUTE,
+            Self::Xdg => XDG_ATTRIBUTE,
+        }
+    }
+
+    fn decode(self, value: &[u8]) -> Option<Vec<String>> {
+        match self {
+            Self::Finder => finder_tags::decode(value),
+            Self::Xdg => Some(
+                std::str::from_utf8(value)
+                    .ok()?
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+        }
+    }
+
+    fn encode(self, entries: &[String]) -> Vec<u8> {
+        match self {
+            Self::Finder => finder_tags::encode(entries),
+            Self::Xdg => entries.join(",").into_bytes(),
+        }
+    }
+}
+
+/// The names of the file's tags, without Finder's colors. `None` when files
+/// here can't carry tags.
+pub fn read(
+    xattrs: &dyn Xattrs,
+    format: Option<TagFormat>,
+    path: &Path,
+) -> io::Result<Option<Vec<String>>> {
+    Ok(entries(xattrs, format, path)?.map(|(_, entries)| names(&entries)))
+}
+
+/// Adds `tags` to the file's, or replaces them, and returns the names it has
+/// afterwards. `None`, with the file untouched, where it can't carry tags.
+/// A comma can't be part of a Linux tag, so names there are split at them.
+pub fn write(
+    xattrs: &dyn Xattrs,
+    format: Option<TagFormat>,
+    path: &Path,
+    tags: &[String],
+    mode: TagMode,
+) -> io::Result<Option<Vec<String>>> {
+    let Some((format, existing)) = entries(xattrs, format, path)? else {
+        return Ok(None);
+    };
+    let existing = match mode {
+        TagMode::Append => existing,
+        TagMode::Replace => Vec::new(),
+    };
+    let tags: Vec<String> = match format {
+        TagFormat::Finder => tags.to_vec(),
+        TagFormat::Xdg => tags
+            .iter()
+            .flat_map(|tag| tag.split(','))
+            .map(str::to_string)
+            .collect(),
+    };
+    let entries = add_tags(existing, &tags);
+    match xattrs.set(path, format.attribute(), &format.encode(&entries)) {
+        Ok(()) => Ok(Some(names(&entries))),
+        Err(err) if is_unsupported(&err) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// The raw entries, Finder's colors included; an untagged file has none.
+fn entries(
+    xattrs: &dyn Xattrs,
+    format: Option<TagFormat>,
+    path: &Path,
+) -> io::Result<Option<(TagFormat, Vec<String>)>> {
+    let Some(format) = format.filter(|_| xattrs.supported()) else {
+        return Ok(None);
+    };
+    match xattrs.get(path, format.attribute()) {
+        Ok(None) => Ok(Some((format, Vec::new()))),
+        Ok(Some(value)) => match format.decode(&value) {
+            Some(entries) => Ok(Some((format, entries))),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The tags attribute isn't a list of names",
+            )),
+        },
+        Err(err) if is_unsupported(&err) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn names(entries: &[String]) -> Vec<String> {
+    entries
+        .iter()
+        .map(|entry| tag_name(entry).to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::xattr::MemoryXattrs;
+    use filedispatch_core::finder_tags::{encode, ATTRIBUTE};
+
+    const FINDER: Option<TagFormat> = Some(TagFormat::Finder);
+    const XDG: Option<TagFormat> = Some(TagFormat::Xdg);
+
+    #[test]
+    fn adding_keeps_the_tags_finder_set() {
+        let xattrs = MemoryXattrs::default();
+        let path = Path::new("/inbox/invoice.pdf");
+        assert_eq!(read(&xattrs, FINDER, path).unwrap(), Some(Vec::new()));
+        xattrs
+            .set(path, ATTRIBUTE, &encode(&["Red\n6".to_string()]))
+            .unwrap();
+
+        let tags = write(
+            &xattrs,
+            FINDER,
+            path,
+            &["Invoices".to_string()],
+            TagMode::Append,
+        );
+        assert_eq!(tags.unwrap().unwrap(), ["Red", "Invoices"]);
+        let stored = finder_tags::decode(&xattrs.get(path, ATTRIBUTE).unwrap().unwrap()).unwrap();
+        assert_eq!(stored, ["Red\n6", "Invoices"]);
+
+        let tags = write(
+            &xattrs,
+            FINDER,
+            path,
+            &["Paid".to_string()],
+            TagMode::Replace,
+        );
+        assert_eq!(tags.unwrap().unwrap(), ["Paid"]);
+        assert_eq!(read(&xattrs, FINDER, path).unwrap().unwrap(), ["Paid"]);
+    }
+
+    #[test]
+    fn linux_tags_are_a_comma_separated_list() {
+        let xattrs = MemoryXattrs::default();
+        let path = Path::new("/inbox/invoice.pdf");
+        xattrs.set(path, XDG_ATTRIBUTE, b"work, 2024").unwrap();
+        assert_eq!(read(&xattrs, XDG, path).unwrap().unwrap(), ["work", "2024"]);
+
+        let tags = ["Invoices,Paid".to_string(), "WORK".to_string()];
+        let written = write(&xattrs, XDG, path, &tags, TagMode::Append).unwrap();
+        assert_eq!(written.unwrap(), ["work", "2024", "Invoices", "Paid"]);
+        assert_eq!(
+            xattrs.get(path, XDG_ATTRIBUTE).unwrap().unwrap(),
+            b"work,2024,Invoices,Paid"
+        );
+    }
+
+    #[test]
+    fn a_garbled_attribute_is_left_alone() {
+        let xattrs = MemoryXattrs::default();
+        let path = Path::new("/inbox/invoice.pdf");
+        xattrs.set(path, ATTRIBUTE, b"not a plist").unwrap();
+        assert!(read(&xattrs, FINDER, path).is_err());
+        let paid = ["Paid".to_string()];
+        assert!(write(&xattrs, FINDER, path, &paid, TagMode::Append).is_err());
+        assert_eq!(
+            xattrs.get(path, ATTRIBUTE).unwrap().unwrap(),
+            b"not a plist"
+        );
+    }
+
+    #[test]
+    fn nothing_is_tagged_where_the_platform_has_no_tags() {
+        let xattrs = MemoryXattrs::default();
+        let path = Path::new("C:\\Inbox\\invoice.pdf");
+        assert_eq!(read(&xattrs, None, path).unwrap(), None);
+        let paid = ["Paid".to_string()];
+        assert_eq!(
+            write(&xattrs, None, path, &paid, TagMode::Append).unwrap(),
+            None
+        );
+        assert_eq!(xattrs.get(path, ATTRIBUTE).unwrap(), None);
+    }
+}
@@ -0,0 +1,310 @@
+//! Which application is in front, asked of the OS as file events come in so
+//! rules can tell what saved a file. Windows and macOS always know. On Linux
+//! it's X11's `_NET_ACTIVE_WINDOW`, read through `xprop`; Wayland doesn't let
+//! other apps see it, so there the app is unavailable.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+
+use crate::models::ActiveApp;
+
+/// How long a lookup is reused. One save raises a burst of events, and each
+/// would otherwise ask the OS again.
+const LOOKUP_TTL: Duration = Duration::from_millis(500);
+
+static SYSTEM_FOREGROUND: Lazy<ForegroundCache> =
+    Lazy::new(|| ForegroundCache::new(Box::new(SystemForeground), LOOKUP_TTL));
+
+/// The system's foreground app, cached briefly.
+pub fn system() -> &'static ForegroundCache {
+    &SYSTEM_FOREGROUND
+}
+
+pub trait ForegroundProvider: Send + Sync {
+    fn active_app(&self) -> ActiveApp;
+}
+
+pub struct ForegroundCache {
+    provider: Box<dyn ForegroundProvider>,
+    ttl: Duration,
+    last: Mutex<Option<(Instant, ActiveApp)>>,
+}
+
+impl ForegroundCache {
+    pub fn new(provider: Box<dyn ForegroundProvider>, ttl: Duration) -> Self {
+        Self {
+            provider,
+            ttl,
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn active_app(&self, now: Instant) -> ActiveApp {
+        let Ok(mut last) = self.last.lock() else {
+            return self.provider.active_app();
+        };
+        if let Some((checked_at, app)) = &*last {
+            if now.saturating_duration_since(*checked_at) < self.ttl {
+                return app.clone();
+            }
+        }
+        let app = self.provider.active_app();
+        *last = Some((now, app.clone()));
+        app
+    }
+}
+
+fn unavailable(reason: &str) -> ActiveApp {
+    ActiveApp::Unavailable {
+        reason: reason.to_string(),
+    }
+}
+
+struct SystemForeground;
+
+impl ForegroundProvider for SystemForeground {
+    /// The foreground window's process, named after its executable.
+    #[cfg(windows)]
+    fn active_app(&self) -> ActiveApp {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Threading::{
+            OpenProcess, QueryFullProcessImageNameW, PROCESS_QUERY_LIMITED_INFORMATION,
+        };
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            GetForegroundWindow, GetWindowThreadProcessId,
+        };
+
+        // SAFETY: these calls only read what they're given pointers to write,
+        // within the lengths they're told, and the process handle is closed.
+        let path = unsafe {
+            let window = GetForegroundWindow();
+            if window == 0 {
+                return unavailable("No window is in front");
+            }
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(window, &mut pid);
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if process == 0 {
+                return unavailable("The app in front can't be looked at");
+            }
+            let mut buf = [0u16; 1024];
+            let mut len = buf.len() as u32;
+            let found = QueryFullProcessImageNameW(process, 0, buf.as_mut_ptr(), &mut len);
+            CloseHandle(process);
+            if found == 0 {
+                return unavailable("The app in front can't be looked at");
+            }
+            String::from_utf16_lossy(&buf[..len as usize])
+        };
+        let name = std::path::Path::new(&path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone());
+        ActiveApp::Known {
+            name,
+            identifier: Some(path),
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn active_app(&self) -> ActiveApp {
+        use objc2_app_kit::NSWorkspace;
+
+        let Some(app) = NSWorkspace::sharedWorkspace().frontmostApplication() else {
+            return unavailable("No app is in front");
+        };
+        let identifier = app.bundleIdentifier().map(|id| id.to_string());
+        match app.localizedName().map(|name| name.to_string()) {
+            Some(name) => ActiveApp::Known { name, identifier },
+            None => match identifier {
+                Some(id) => ActiveApp::Known {
+                    name: id.clone(),
+                    identifier: Some(id),
+                },
+                None => unavailable("The app in front has no name"),
+            },
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn active_app(&self) -> ActiveApp {
+        if let Err(reason) = x11_session(|name| std::env::var(name).ok()) {
+            return unavailable(reason);
+        }
+        let xprop = |args: &[&str]| {
+            std::process::Command::new("xprop")
+                .args(args)
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        };
+        let Some(root) = xprop(&["-root", "_NET_ACTIVE_WINDOW"]) else {
+            return unavailable("xprop isn't installed or couldn't reach the X server");
+        };
+        let Some(window) = parse_active_window(&root) else {
+            return unavailable("No window is in front");
+        };
+        let Some(props) = xprop(&["-id", &window, "WM_CLASS", "_NET_WM_PID"]) else {
+            return unavailable("The window in front went away");
+        };
+        let (class, pid) = parse_window_props(&props);
+        let executable = pid
+            .and_then(|pid| std::fs::read_link(format!("/proc/{pid}/exe")).ok())
+            .map(|path| path.to_string_lossy().to_string());
+        match class {
+            Some(name) => ActiveApp::Known {
+                name,
+                identifier: executable,
+            },
+            None => unavailable("The window in front doesn't name its app"),
+        }
+    }
+
+    #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+    fn active_app(&self) -> ActiveApp {
+        unavailable("This platform doesn't say which app is in front")
+    }
+}
+
+/// Whether the session is one X11 can be asked about. Under Wayland, X11
+/// only sees apps running through XWayland, which would be misleading.
+#[cfg(target_os = "linux")]
+fn x11_session(env: impl Fn(&str) -> Option<String>) -> Result<(), &'static str> {
+    let wayland = env("WAYLAND_DISPLAY").is_some_and(|display| !display.is_empty())
+        || env("XDG_SESSION_TYPE").is_some_and(|kind| kind.eq_ignore_ascii_case("wayland"));
+    if wayland {
+        return Err("Wayland doesn't let other apps see which window is in front");
+    }
+    if env("DISPLAY").is_none_or(|display| display.is_empty()) {
+        return Err("There's no X11 display to ask which window is in front");
+    }
+    Ok(())
+}
+
+/// Reads the window id from `_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007`.
+/// An id of 0 means no window has focus.
+#[cfg(target_os = "linux")]
+fn parse_active_window(output: &str) -> Option<String> {
+    let id = output.split('#').nth(1)?.split(',').next()?.trim();
+    let digits = id.strip_prefix("0x")?;
+    (u64::from_str_radix(digits, 16).ok()? != 0).then(|| id.to_string())
+}
+
+/// Reads the class, the second string of `WM_CLASS(STRING) = "navigator",
+/// "Firefox"`, and the process id from `_NET_WM_PID(CARDINAL) = 4242`.
+#[cfg(target_os = "linux")]
+fn parse_window_props(output: &str) -> (Option<String>, Option<u32>) {
+    let mut class = None;
+    let mut pid = None;
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        if key.starts_with("WM_CLASS") {
+            class = value
+                .split(',')
+                .map(|part| part.trim().trim_matches('"'))
+                .rfind(|part| !part.is_empty())
+                .map(str::to_string);
+        } else if key.starts_with("_NET_WM_PID") {
+            pid = value.trim().parse().ok();
+        }
+    }
+    (class, pid)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// Stands in for the OS: reports `app` and counts the lookups.
+    pub(crate) struct FixedForeground {
+        pub app: ActiveApp,
+        pub lookups: Arc<AtomicU32>,
+    }
+
+    impl ForegroundProvider for FixedForeground {
+        fn active_app(&self) -> ActiveApp {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            self.app.clone()
+        }
+    }
+
+    pub(crate) fn known(name: &str) -> ActiveApp {
+        ActiveApp::Known {
+            name: name.to_string(),
+            identifier: None,
+        }
+    }
+
+    #[test]
+    fn a_burst_of_events_asks_the_os_once() {
+        let lookups = Arc::new(AtomicU32::new(0));
+        let cache = ForegroundCache::new(
+            Box::new(FixedForeground {
+                app: known("FreeCAD"),
+                lookups: lookups.clone(),
+            }),
+            LOOKUP_TTL,
+        );
+        let start = Instant::now();
+        for offset in [0, 100, 400] {
+            let app = cache.active_app(start + Duration::from_millis(offset));
+            assert_eq!(app, known("FreeCAD"));
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        cache.active_app(start + Duration::from_millis(600));
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn wayland_sessions_are_unsupported() {
+        let session = |vars: &[(&str, &str)]| {
+            let vars: Vec<(String, String)> = vars
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            x11_session(move |name| {
+                vars.iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.clone())
+            })
+        };
+        assert_eq!(session(&[("DISPLAY", ":0")]), Ok(()));
+        assert!(
+            session(&[("DISPLAY", ":0"), ("WAYLAND_DISPLAY", "wayland-0")])
+                .unwrap_err()
+                .contains("Wayland")
+        );
+        assert!(session(&[("DISPLAY", ":0"), ("XDG_SESSION_TYPE", "wayland")]).is_err());
+        assert!(session(&[]).unwrap_err().contains("X11"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn reads_xprop_output() {
+        assert_eq!(
+            parse_active_window("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007\n"),
+            Some("0x3a00007".to_string())
+        );
+        assert_eq!(
+            parse_active_window("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x0\n"),
+            None
+        );
+
+        let props = "WM_CLASS(STRING) = \"navigator\", \"Firefox\"\n_NET_WM_PID(CARDINAL) = 4242\n";
+        assert_eq!(
+            parse_window_props(props),
+            (Some("Firefox".to_string()), Some(4242))
+        );
+        let bare = "WM_CLASS:  not found.\n_NET_WM_PID:  not found.\n";
+        assert_eq!(parse_window_props(bare), (None, None));
+    }
+}
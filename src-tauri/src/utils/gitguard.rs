@@ -0,0 +1,323 @@
+//! Keeps rules out of git's way in folders that are working trees. Finds the
+//! repository a folder is in, tells when git is partway through a checkout,
+//! merge or rebase, and which files it tracks. Only stats and the `git`
+//! command are used, never libgit2.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// What git leaves in its directory while it's rewriting the working tree.
+const BUSY_MARKERS: &[&str] = &[
+    "index.lock",
+    "MERGE_HEAD",
+    "CHERRY_PICK_HEAD",
+    "REVERT_HEAD",
+    "rebase-merge",
+    "rebase-apply",
+];
+
+/// How long a busy check is taken as still true.
+pub const BUSY_CACHE: Duration = Duration::from_secs(3);
+
+/// How long events wait on a busy repository before they're processed
+/// anyway, in case git crashed and left a lock behind.
+pub const MAX_WAIT: Duration = Duration::from_secs(10 * 60);
+
+/// What to do with an event in a folder that respects git.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitHold {
+    /// Git isn't busy; go ahead.
+    Proceed,
+    /// Git is busy; send the event back later.
+    Wait,
+    /// Git has been busy for longer than `MAX_WAIT`; go ahead regardless.
+    GaveUp,
+}
+
+/// Busy checks and tracked files, cached per repository.
+pub struct GitGuard {
+    cache_for: Duration,
+    busy: Mutex<HashMap<PathBuf, (Instant, bool)>>,
+    /// When each held path first had to wait.
+    waits: Mutex<HashMap<PathBuf, Instant>>,
+    tracked: Mutex<HashMap<PathBuf, TrackedFiles>>,
+}
+
+/// `git ls-files` for one repository, as of one version of its index.
+struct TrackedFiles {
+    index: Option<(SystemTime, u64)>,
+    paths: HashSet<String>,
+}
+
+impl Default for GitGuard {
+    fn default() -> Self {
+        Self::new(BUSY_CACHE)
+    }
+}
+
+impl GitGuard {
+    pub fn new(cache_for: Duration) -> Self {
+        Self {
+            cache_for,
+            busy: Mutex::new(HashMap::new()),
+            waits: Mutex::new(HashMap::new()),
+            tracked: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether git is partway through an operation in the repository
+    /// `folder` is in. False outside a repository.
+    pub fn busy(&self, folder: &Path) -> bool {
+        self.busy_at(folder, Instant::now())
+    }
+
+    fn busy_at(&self, folder: &Path, now: Instant) -> bool {
+        let Ok(mut cache) = self.busy.lock() else {
+            return false;
+        };
+        if let Some((checked, busy)) = cache.get(folder) {
+            if now.duration_since(*checked) < self.cache_for {
+                return *busy;
+            }
+        }
+        let busy = repo_root(folder)
+            .and_then(|root| git_dir(&root))
+            .is_some_and(|dir| is_busy(&dir));
+        cache.insert(folder.to_path_buf(), (now, busy));
+        busy
+    }
+
+    /// Whether the event for `path` in `folder` should wait for git. Paths
+    /// stop being held once git is done or they've waited `MAX_WAIT`.
+    pub fn hold(&self, path: &Path, folder: &Path) -> GitHold {
+        self.hold_at(path, folder, Instant::now())
+    }
+
+    fn hold_at(&self, path: &Path, folder: &Path, now: Instant) -> GitHold {
+        let busy = self.busy_at(folder, now);
+        let Ok(mut waits) = self.waits.lock() else {
+            return GitHold::Proceed;
+        };
+        if !busy {
+            waits.remove(path);
+            return GitHold::Proceed;
+        }
+        let since = *waits.entry(path.to_path_buf()).or_insert(now);
+        if now.duration_since(since) > MAX_WAIT {
+            waits.remove(path);
+            return GitHold::GaveUp;
+        }
+        GitHold::Wait
+    }
+
+    /// Paths waiting on git right now.
+    pub fn waiting(&self) -> usize {
+        self.waits.lock().map(|waits| waits.len()).unwrap_or(0)
+    }
+
+    /// Whether git tracks `path`, which is in `folder`. `None` outside a
+    /// repository or when git couldn't be asked. Files are listed once per
+    /// version of the index, not per question.
+    pub fn is_tracked(&self, folder: &Path, path: &Path) -> Option<bool> {
+        let root = repo_root(folder)?;
+        let relative = relative_path(path, &root)?;
+        let dir = git_dir(&root)?;
+        let index = fs::metadata(dir.join("index"))
+            .ok()
+            .and_then(|meta| Some((meta.modified().ok()?, meta.len())));
+        let mut cache = self.tracked.lock().ok()?;
+        let current = cache
+            .get(&root)
+            .is_some_and(|tracked| tracked.index == index);
+        if !current {
+            let paths = ls_files(&root)?;
+            cache.insert(root.clone(), TrackedFiles { index, paths });
+        }
+        Some(cache.get(&root)?.paths.contains(&relative))
+    }
+}
+
+/// `folder`, or the nearest folder above it, that has a `.git`.
+pub fn repo_root(folder: &Path) -> Option<PathBuf> {
+    folder
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// The git directory of the working tree at `root`. Linked worktrees and
+/// submodules have a `.git` file pointing to it instead.
+pub fn git_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let contents = fs::read_to_string(&dot_git).ok()?;
+    let target = contents.trim().strip_prefix("gitdir:")?.trim();
+    Some(root.join(target))
+}
+
+/// Whether git left signs of an unfinished operation in `git_dir`.
+pub fn is_busy(git_dir: &Path) -> bool {
+    BUSY_MARKERS
+        .iter()
+        .any(|marker| git_dir.join(marker).exists())
+}
+
+/// `path` inside `root` as git writes it, with `/` between parts.
+fn relative_path(path: &Path, root: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Vec<_> = relative
+        .components()
+        .map(|part| match part {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    Some(parts.join("/"))
+}
+
+fn ls_files(root: &Path) -> Option<HashSet<String>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["ls-files", "-z"])
+        .output();
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            eprintln!(
+                "git ls-files failed in {}: {}",
+                root.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return None;
+        }
+        Err(err) => {
+            eprintln!("Couldn't run git in {}: {err}", root.display());
+            return None;
+        }
+    };
+    Some(
+        output
+            .stdout
+            .split(|byte| *byte == 0)
+            .filter(|path| !path.is_empty())
+            .map(|path| String::from_utf8_lossy(path).to_string())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_wait_while_the_index_is_locked_and_go_once_it_is_not() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join(".git/index.lock"), b"").unwrap();
+        let guard = GitGuard::new(Duration::from_secs(2));
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let start = Instant::now();
+        let file = root.join("notes.md");
+
+        // An event arrives mid-rebase and goes back on the retry queue.
+        assert_eq!(guard.hold_at(&file, root, start), GitHold::Wait);
+        tx.send(file.clone()).unwrap();
+        assert_eq!(guard.waiting(), 1);
+
+        fs::remove_file(root.join(".git/index.lock")).unwrap();
+        // The busy check is cached for a moment, so it still waits...
+        let retried = rx.try_recv().unwrap();
+        assert_eq!(
+            guard.hold_at(&retried, root, start + Duration::from_secs(1)),
+            GitHold::Wait
+        );
+        tx.send(retried).unwrap();
+        // ...and is let through once the cache is stale.
+        let retried = rx.try_recv().unwrap();
+        let later = start + Duration::from_secs(3);
+        assert_eq!(guard.hold_at(&retried, root, later), GitHold::Proceed);
+        assert!(rx.try_recv().is_err());
+        assert_eq!(guard.waiting(), 0);
+    }
+
+    #[test]
+    fn a_lock_left_behind_is_waited_out_only_so_long() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git/rebase-merge")).unwrap();
+        let guard = GitGuard::new(Duration::ZERO);
+        let file = root.join("notes.md");
+        let start = Instant::now();
+
+        assert_eq!(guard.hold_at(&file, root, start), GitHold::Wait);
+        let late = start + MAX_WAIT + Duration::from_secs(1);
+        assert_eq!(guard.hold_at(&file, root, late), GitHold::GaveUp);
+    }
+
+    #[test]
+    fn worktrees_point_to_their_git_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("main/.git/worktrees/feature");
+        fs::create_dir_all(&main).unwrap();
+        let tree = dir.path().join("feature");
+        fs::create_dir(&tree).unwrap();
+        fs::write(tree.join(".git"), format!("gitdir: {}\n", main.display())).unwrap();
+
+        assert_eq!(repo_root(&tree.join("docs")), Some(tree.clone()));
+        assert_eq!(git_dir(&tree), Some(main.clone()));
+        fs::write(main.join("MERGE_HEAD"), b"").unwrap();
+        assert!(GitGuard::default().busy(&tree));
+    }
+
+    #[test]
+    fn tracked_files_are_told_apart_from_untracked_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(root)
+                .args(args)
+                .output()
+                .map(|output| output.status.success())
+                .unwrap_or(false)
+        };
+        if !git(&["init", "-q"]) {
+            eprintln!("git isn't available; skipping");
+            return;
+        }
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), b"fn main() {}").unwrap();
+        fs::write(root.join("scratch.txt"), b"notes").unwrap();
+        assert!(git(&["add", "src/main.rs"]));
+
+        let guard = GitGuard::default();
+        assert_eq!(
+            guard.is_tracked(root, &root.join("src/main.rs")),
+            Some(true)
+        );
+        assert_eq!(
+            guard.is_tracked(root, &root.join("scratch.txt")),
+            Some(false)
+        );
+
+        // Adding a file changes the index, so the listing is read again.
+        assert!(git(&["add", "scratch.txt"]));
+        assert_eq!(
+            guard.is_tracked(root, &root.join("scratch.txt")),
+            Some(true)
+        );
+
+        let elsewhere = tempfile::tempdir().unwrap();
+        let outside = elsewhere.path().join("a.txt");
+        assert_eq!(guard.is_tracked(elsewhere.path(), &outside), None);
+    }
+}
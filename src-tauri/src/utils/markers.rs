@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::models::ProcessedMarkerMode;
+use crate::utils::tempfiles::app_temp;
+
+/// Extended attribute holding a file's marks where the filesystem has them.
+pub const MARKER_XATTR: &str = "user.filedispatch.processed";
+
+/// Per-directory fallback for filesystems without user xattrs.
+pub const INDEX_FILE_NAME: &str = ".filedispatch.index";
+
+/// Marks kept per file; the oldest go first.
+const MAX_MARKS: usize = 16;
+
+/// Largest xattr value written, well under every filesystem's limit.
+const MAX_XATTR_BYTES: usize = 1024;
+
+/// An index that has grown past this is left alone rather than rewritten.
+const MAX_INDEX_BYTES: u64 = 1024 * 1024;
+
+/// Characters of the file hash kept in a mark.
+const HASH_PREFIX_LEN: usize = 32;
+
+/// Serializes read-modify-write cycles on index files within this process.
+static INDEX_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Records that a rule processed a file with a given hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessedMark {
+    #[serde(rename = "r")]
+    pub rule_id: String,
+    /// Unix seconds.
+    #[serde(rename = "t")]
+    pub processed_at: i64,
+    #[serde(rename = "h")]
+    pub hash: String,
+}
+
+impl ProcessedMark {
+    pub fn new(rule_id: &str, processed_at: i64, hash: &str) -> Self {
+        Self {
+            rule_id: rule_id.to_string(),
+            processed_at,
+            hash: hash_prefix(hash).to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MarkerIndex {
+    version: u32,
+    /// File name to its marks; each mark carries the hash it was written for.
+    files: BTreeMap<String, Vec<ProcessedMark>>,
+}
+
+#[cfg(test)]
+thread_local! {
+    static FORCE_INDEX: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Test hook: makes the current thread skip xattrs and use the index file.
+#[cfg(test)]
+pub(crate) fn force_index_fallback(force: bool) {
+    FORCE_INDEX.with(|flag| flag.set(force));
+}
+
+fn hash_prefix(hash: &str) -> &str {
+    match hash.char_indices().nth(HASH_PREFIX_LEN) {
+        Some((end, _)) => &hash[..end],
+        None => hash,
+    }
+}
+
+pub fn is_marker_index(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == INDEX_FILE_NAME)
+}
+
+/// Rules whose marks on `path` count as already having processed it: any mark
+/// under `Trust`, only marks for the file's current `hash` under `Verify`.
+pub fn marked_rule_ids(path: &Path, hash: &str, mode: ProcessedMarkerMode) -> Vec<String> {
+    if mode == ProcessedMarkerMode::Ignore {
+        return Vec::new();
+    }
+    let hash = hash_prefix(hash);
+    let mut rule_ids: Vec<String> = read_marks(path)
+        .into_iter()
+        .filter(|mark| mode == ProcessedMarkerMode::Trust || mark.hash == hash)
+        .map(|mark| mark.rule_id)
+        .collect();
+    rule_ids.sort();
+    rule_ids.dedup();
+    rule_ids
+}
+
+/// Every mark on `path`, from its xattr and its directory's index.
+pub fn read_marks(path: &Path) -> Vec<ProcessedMark> {
+    let mut marks = if use_xattr() {
+        xattr::get(path, MARKER_XATTR)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_slice::<Vec<ProcessedMark>>(&value).ok())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    if let Some((index_path, name)) = index_location(path) {
+        if let Some(entry) = read_index(&index_path).files.remove(&name) {
+            marks.extend(entry);
+        }
+    }
+    marks
+}
+
+/// Adds `mark` to the file, replacing an earlier one from the same rule.
+/// Uses the xattr where it can be set, otherwise the directory's index.
+pub fn write_mark(path: &Path, mark: ProcessedMark) -> io::Result<()> {
+    if use_xattr() {
+        let mut marks = xattr::get(path, MARKER_XATTR)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_slice::<Vec<ProcessedMark>>(&value).ok())
+            .unwrap_or_default();
+        merge_mark(&mut marks, mark.clone());
+        let mut value = serde_json::to_vec(&marks)?;
+        while value.len() > MAX_XATTR_BYTES && marks.len() > 1 {
+            marks.remove(0);
+            value = serde_json::to_vec(&marks)?;
+        }
+        if value.len() <= MAX_XATTR_BYTES && xattr::set(path, MARKER_XATTR, &value).is_ok() {
+            return Ok(());
+        }
+    }
+    let (index_path, name) = index_location(path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    update_index(&index_path, |index| {
+        let marks = index.files.entry(name).or_default();
+        merge_mark(marks, mark);
+    })
+}
+
+/// Drops the index entry for `path`, e.g. after the file was moved away or
+/// deleted. An xattr leaves with the file, so there's nothing else to clear.
+pub fn remove_marks(path: &Path) -> io::Result<()> {
+    let Some((index_path, name)) = index_location(path) else {
+        return Ok(());
+    };
+    if !index_path.exists() {
+        return Ok(());
+    }
+    update_index(&index_path, |index| {
+        index.files.remove(&name);
+    })
+}
+
+/// Marks written for other content under the same name no longer apply.
+fn merge_mark(marks: &mut Vec<ProcessedMark>, mark: ProcessedMark) {
+    marks.retain(|existing| existing.rule_id != mark.rule_id && existing.hash == mark.hash);
+    marks.push(mark);
+    if marks.len() > MAX_MARKS {
+        marks.drain(..marks.len() - MAX_MARKS);
+    }
+}
+
+fn index_location(path: &Path) -> Option<(PathBuf, String)> {
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    let dir = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    Some((dir.join(INDEX_FILE_NAME), name))
+}
+
+fn read_index(index_path: &Path) -> MarkerIndex {
+    match fs::metadata(index_path) {
+        Ok(metadata) if metadata.len() <= MAX_INDEX_BYTES => fs::read(index_path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default(),
+        _ => MarkerIndex::default(),
+    }
+}
+
+/// Rewrites the index through a temp file so readers never see half of it.
+/// An empty index is removed instead.
+fn update_index(index_path: &Path, change: impl FnOnce(&mut MarkerIndex)) -> io::Result<()> {
+    let _guard = INDEX_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if fs::metadata(index_path).is_ok_and(|metadata| metadata.len() > MAX_INDEX_BYTES) {
+        return Err(io::Error::other("marker index is over its size limit"));
+    }
+    let mut index = read_index(index_path);
+    change(&mut index);
+    if index.files.is_empty() {
+        return match fs::remove_file(index_path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        };
+    }
+    index.version = 1;
+    let staged = app_temp().beside(index_path)?;
+    fs::write(staged.path(), serde_json::to_vec(&index)?)?;
+    staged.persist(index_path)
+}
+
+#[cfg(test)]
+fn use_xattr() -> bool {
+    !FORCE_INDEX.with(|flag| flag.get()) && cfg!(target_os = "linux")
+}
+
+#[cfg(not(test))]
+fn use_xattr() -> bool {
+    cfg!(target_os = "linux")
+}
+
+#[cfg(target_os = "linux")]
+mod xattr {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use super::MAX_XATTR_BYTES;
+
+    fn c_strings(path: &Path, name: &str) -> io::Result<(CString, CString)> {
+        Ok((
+            CString::new(path.as_os_str().as_bytes())?,
+            CString::new(name)?,
+        ))
+    }
+
+    /// `None` when the attribute isn't set; values over the size cap are
+    /// someone else's and read as an error.
+    pub fn get(path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let (path, name) = c_strings(path, name)?;
+        let mut buf = vec![0u8; MAX_XATTR_BYTES];
+        let len = unsafe {
+            libc::getxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+            )
+        };
+        if len < 0 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::ENODATA) => Ok(None),
+                _ => Err(err),
+            };
+        }
+        buf.truncate(len as usize);
+        Ok(Some(buf))
+    }
+
+    pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        let (path, name) = c_strings(path, name)?;
+        let result = unsafe {
+            libc::setxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod xattr {
+    use std::io;
+    use std::path::Path;
+
+    pub fn get(_path: &Path, _name: &str) -> io::Result<Option<Vec<u8>>> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+
+    pub fn set(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn index_fallback_round_trip() {
+        let dir = tempdir().unwrap();
+        let report = dir.path().join("report.pdf");
+        let notes = dir.path().join("notes.txt");
+        fs::write(&report, "pdf").unwrap();
+        fs::write(&notes, "txt").unwrap();
+        force_index_fallback(true);
+
+        write_mark(&report, ProcessedMark::new("rule-a", 100, "1700000000:3")).unwrap();
+        write_mark(&report, ProcessedMark::new("rule-b", 200, "1700000000:3")).unwrap();
+        write_mark(&notes, ProcessedMark::new("rule-a", 300, "1700000500:3")).unwrap();
+        // Same rule again replaces its earlier mark.
+        write_mark(&report, ProcessedMark::new("rule-a", 400, "1700000000:3")).unwrap();
+
+        let index = dir.path().join(INDEX_FILE_NAME);
+        assert!(index.exists());
+        let mut marks = read_marks(&report);
+        marks.sort_by(|a, b| a.rule_id.cmp(&b.rule_id));
+        assert_eq!(
+            marks,
+            vec![
+                ProcessedMark::new("rule-a", 400, "1700000000:3"),
+                ProcessedMark::new("rule-b", 200, "1700000000:3"),
+            ]
+        );
+        assert_eq!(
+            marked_rule_ids(&notes, "1700000500:3", ProcessedMarkerMode::Verify),
+            vec!["rule-a".to_string()]
+        );
+
+        // New content under the same name starts over.
+        write_mark(&report, ProcessedMark::new("rule-c", 500, "1800000000:9")).unwrap();
+        assert_eq!(
+            read_marks(&report),
+            vec![ProcessedMark::new("rule-c", 500, "1800000000:9")]
+        );
+
+        remove_marks(&report).unwrap();
+        remove_marks(&notes).unwrap();
+        assert!(read_marks(&report).is_empty());
+        assert!(!index.exists(), "an empty index is removed");
+        // Only the index was ever written.
+        let leftovers: Vec<_> = fs::read_dir(dir.path()).unwrap().flatten().collect();
+        assert_eq!(leftovers.len(), 2);
+        force_index_fallback(false);
+    }
+
+    #[test]
+    fn verify_mode_ignores_marks_for_other_content() {
+        let dir = tempdir().unwrap();
+        let photo = dir.path().join("photo.jpg");
+        fs::write(&photo, "jpg").unwrap();
+        force_index_fallback(true);
+        write_mark(&photo, ProcessedMark::new("rule-a", 100, "1700000000:3")).unwrap();
+
+        assert!(marked_rule_ids(&photo, "1700009999:4", ProcessedMarkerMode::Verify).is_empty());
+        assert_eq!(
+            marked_rule_ids(&photo, "1700009999:4", ProcessedMarkerMode::Trust),
+            vec!["rule-a".to_string()]
+        );
+        assert!(marked_rule_ids(&photo, "1700000000:3", ProcessedMarkerMode::Ignore).is_empty());
+        force_index_fallback(false);
+    }
+
+    #[test]
+    fn marks_are_capped() {
+        let mut marks = Vec::new();
+        for i in 0..(MAX_MARKS + 4) {
+            merge_mark(
+                &mut marks,
+                ProcessedMark::new(&format!("rule-{i}"), i as i64, "h"),
+            );
+        }
+        assert_eq!(marks.len(), MAX_MARKS);
+        assert_eq!(marks[0].rule_id, "rule-4");
+        assert_eq!(hash_prefix(&"x".repeat(100)).len(), HASH_PREFIX_LEN);
+        assert!(is_marker_index(Path::new("/inbox/.filedispatch.index")));
+        assert!(!is_marker_index(Path::new("/inbox/report.pdf")));
+    }
+}
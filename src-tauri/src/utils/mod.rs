@@ -1,3 +1,19 @@
 pub mod archive;
-pub mod file_info;
+pub mod clone;
+pub mod csv_table;
+pub mod file_lock;
+pub mod file_mode;
+pub mod file_tags;
+pub mod foreground;
+pub mod gitguard;
+pub mod markers;
+pub mod permissions;
 pub mod platform;
+pub mod quarantine;
+pub mod sealed;
+pub mod secrets;
+pub mod tempfiles;
+pub mod transient;
+pub mod volumes;
+pub mod walk;
+pub mod xattr;
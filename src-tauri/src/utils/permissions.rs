@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use directories::UserDirs;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+/// How long a directory's probe result is reused before touching it again.
+const PROBE_TTL: Duration = Duration::from_secs(180);
+
+/// Locations macOS guards behind a Files and Folders (TCC) grant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProtectedCategory {
+    Desktop,
+    Documents,
+    Downloads,
+    RemovableVolume,
+    NetworkVolume,
+}
+
+impl ProtectedCategory {
+    pub const ALL: [ProtectedCategory; 5] = [
+        ProtectedCategory::Desktop,
+        ProtectedCategory::Documents,
+        ProtectedCategory::Downloads,
+        ProtectedCategory::RemovableVolume,
+        ProtectedCategory::NetworkVolume,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ProtectedCategory::Desktop => "Desktop folder",
+            ProtectedCategory::Documents => "Documents folder",
+            ProtectedCategory::Downloads => "Downloads folder",
+            ProtectedCategory::RemovableVolume => "removable volumes",
+            ProtectedCategory::NetworkVolume => "network volumes",
+        }
+    }
+}
+
+/// The OS refused access to a protected location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionDenied {
+    pub category: ProtectedCategory,
+    pub path: PathBuf,
+}
+
+impl std::fmt::Display for PermissionDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "No access to the {} ({}). Allow File Dispatch under System Settings > Privacy & Security > Files and Folders.",
+            self.category.label(),
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+/// Payload of the `permission://needed` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionNeeded {
+    pub category: ProtectedCategory,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AccessState {
+    Granted,
+    Denied,
+    /// Nothing of this category is mounted right now.
+    NotApplicable,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionStatus {
+    pub category: ProtectedCategory,
+    pub state: AccessState,
+}
+
+/// Remembers recent probe results per directory.
+pub(crate) struct ProbeCache {
+    ttl: Duration,
+    entries: HashMap<PathBuf, (Instant, bool)>,
+}
+
+impl ProbeCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns whether `dir` is accessible, running `probe` only when there is
+    /// no result younger than the TTL.
+    pub(crate) fn check(
+        &mut self,
+        dir: &Path,
+        now: Instant,
+        probe: impl FnOnce(&Path) -> bool,
+    ) -> bool {
+        if let Some((checked_at, granted)) = self.entries.get(dir) {
+            if now.saturating_duration_since(*checked_at) < self.ttl {
+                return *granted;
+            }
+        }
+        let granted = probe(dir);
+        self.entries.insert(dir.to_path_buf(), (now, granted));
+        granted
+    }
+
+    fn forget(&mut self, dir: &Path) {
+        self.entries.remove(dir);
+    }
+}
+
+static PROBES: Lazy<Mutex<ProbeCache>> = Lazy::new(|| Mutex::new(ProbeCache::new(PROBE_TTL)));
+static NOTIFIED: Lazy<Mutex<HashSet<ProtectedCategory>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Which protected category governs `path`, if any. Anything under
+/// `/Volumes/<name>` is a removable or network volume as decided by
+/// `mount_category`, which returns `None` for the boot volume alias.
+pub fn classify_path(
+    path: &Path,
+    home: &Path,
+    mount_category: impl Fn(&Path) -> Option<ProtectedCategory>,
+) -> Option<ProtectedCategory> {
+    for (dir, category) in [
+        ("Desktop", ProtectedCategory::Desktop),
+        ("Documents", ProtectedCategory::Documents),
+        ("Downloads", ProtectedCategory::Downloads),
+    ] {
+        if path.starts_with(home.join(dir)) {
+            return Some(category);
+        }
+    }
+    let volume = path.strip_prefix("/Volumes").ok()?.components().next()?;
+    mount_category(&Path::new("/Volumes").join(volume))
+}
+
+/// Checks that `dir` is reachable when it sits in a protected location.
+/// Always granted outside macOS.
+pub fn check_access(dir: &Path) -> Result<(), PermissionDenied> {
+    if !cfg!(target_os = "macos") {
+        return Ok(());
+    }
+    let Some(home) = home_dir() else {
+        return Ok(());
+    };
+    let Some(category) = classify_path(dir, &home, mount_category) else {
+        return Ok(());
+    };
+    let granted = PROBES
+        .lock()
+        .map(|mut probes| probes.check(dir, Instant::now(), probe_dir))
+        .unwrap_or(true);
+    if granted {
+        Ok(())
+    } else {
+        Err(PermissionDenied {
+            category,
+            path: dir.to_path_buf(),
+        })
+    }
+}
+
+/// True the first time a denial in `category` is reported this session.
+pub fn first_denial(category: ProtectedCategory) -> bool {
+    NOTIFIED
+        .lock()
+        .map(|mut notified| notified.insert(category))
+        .unwrap_or(false)
+}
+
+/// Probes each category afresh so the UI reflects grants made since the
+/// last check.
+pub fn permissions_status() -> Vec<PermissionStatus> {
+    let granted = |category| PermissionStatus {
+        category,
+        state: AccessState::Granted,
+    };
+    if !cfg!(target_os = "macos") {
+        return ProtectedCategory::ALL.into_iter().map(granted).collect();
+    }
+    let Some(home) = home_dir() else {
+        return ProtectedCategory::ALL.into_iter().map(granted).collect();
+    };
+
+    let mut roots: Vec<(ProtectedCategory, PathBuf)> = vec![
+        (ProtectedCategory::Desktop, home.join("Desktop")),
+        (ProtectedCategory::Documents, home.join("Documents")),
+        (ProtectedCategory::Downloads, home.join("Downloads")),
+    ];
+    if let Ok(volumes) = fs::read_dir("/Volumes") {
+        for volume in volumes.filter_map(Result::ok) {
+            let path = volume.path();
+            if let Some(category) = mount_category(&path) {
+                roots.push((category, path));
+            }
+        }
+    }
+
+    ProtectedCategory::ALL
+        .into_iter()
+        .map(|category| {
+            let results: Vec<bool> = roots
+                .iter()
+                .filter(|(root_category, _)| *root_category == category)
+                .map(|(_, root)| fresh_probe(root))
+                .collect();
+            let state = if results.is_empty() {
+                AccessState::NotApplicable
+            } else if results.iter().all(|granted| *granted) {
+                AccessState::Granted
+            } else {
+                AccessState::Denied
+            };
+            PermissionStatus { category, state }
+        })
+        .collect()
+}
+
+fn fresh_probe(dir: &Path) -> bool {
+    PROBES
+        .lock()
+        .map(|mut probes| {
+            probes.forget(dir);
+            probes.check(dir, Instant::now(), probe_dir)
+        })
+        .unwrap_or(true)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    UserDirs::new().map(|dirs| dirs.home_dir().to_path_buf())
+}
+
+/// Lists the nearest existing ancestor of `dir`. Listing is what TCC blocks,
+/// and unlike writing a probe file it can't trip the watcher.
+fn probe_dir(dir: &Path) -> bool {
+    let Some(existing) = dir.ancestors().find(|path| path.exists()) else {
+        return true;
+    };
+    match fs::read_dir(existing) {
+        Ok(_) => true,
+        Err(err) => err.kind() != std::io::ErrorKind::PermissionDenied,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn mount_category(root: &Path) -> Option<ProtectedCategory> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    // MNT_LOCAL from <sys/mount.h>.
+    const MNT_LOCAL: u32 = 0x0000_1000;
+
+    // "/Volumes/Macintosh HD" links back to the boot volume.
+    if root.canonicalize().map(|p| p == Path::new("/")).unwrap_or(false) {
+        return None;
+    }
+    let c_path = CString::new(root.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Some(ProtectedCategory::RemovableVolume);
+    }
+    if stat.f_flags & MNT_LOCAL == 0 {
+        Some(ProtectedCategory::NetworkVolume)
+    } else {
+        Some(ProtectedCategory::RemovableVolume)
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn mount_category(_root: &Path) -> Option<ProtectedCategory> {
+    Some(ProtectedCategory::RemovableVolume)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn by_name(root: &Path) -> Option<ProtectedCategory> {
+        match root.file_name()?.to_str()? {
+            "Macintosh HD" => None,
+            "Share" => Some(ProtectedCategory::NetworkVolume),
+            _ => Some(ProtectedCategory::RemovableVolume),
+        }
+    }
+
+    #[test]
+    fn classifies_protected_locations() {
+        let home = Path::new("/Users/alex");
+        let classify = |path: &str| classify_path(Path::new(path), home, by_name);
+
+        assert_eq!(classify("/Users/alex/Documents/Invoices"), Some(ProtectedCategory::Documents));
+        assert_eq!(classify("/Users/alex/Desktop"), Some(ProtectedCategory::Desktop));
+        assert_eq!(classify("/Users/alex/Downloads/a.zip"), Some(ProtectedCategory::Downloads));
+        assert_eq!(classify("/Volumes/USB/photos"), Some(ProtectedCategory::RemovableVolume));
+        assert_eq!(classify("/Volumes/Share/team"), Some(ProtectedCategory::NetworkVolume));
+        assert_eq!(classify("/Volumes/Macintosh HD/Users"), None);
+        assert_eq!(classify("/Volumes"), None);
+        assert_eq!(classify("/Users/alex/Pictures"), None);
+        // Only whole path components count.
+        assert_eq!(classify("/Users/alex/DocumentsArchive"), None);
+    }
+
+    #[test]
+    fn probe_cache_reuses_results_within_ttl() {
+        let mut cache = ProbeCache::new(Duration::from_secs(60));
+        let dir = Path::new("/Users/alex/Documents");
+        let start = Instant::now();
+        let calls = Cell::new(0);
+        let probe = |granted: bool| {
+            let calls = &calls;
+            move |_: &Path| {
+                calls.set(calls.get() + 1);
+                granted
+            }
+        };
+
+        assert!(!cache.check(dir, start, probe(false)));
+        assert!(!cache.check(dir, start + Duration::from_secs(30), probe(true)));
+        assert_eq!(calls.get(), 1);
+
+        // Once stale, the probe runs again and its answer replaces the old one.
+        assert!(cache.check(dir, start + Duration::from_secs(61), probe(true)));
+        assert_eq!(calls.get(), 2);
+
+        // Other directories are probed independently.
+        assert!(!cache.check(Path::new("/Volumes/USB"), start, probe(false)));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn access_is_granted_off_macos() {
+        if cfg!(target_os = "macos") {
+            return;
+        }
+        assert!(check_access(Path::new("/Volumes/USB")).is_ok());
+        assert!(permissions_status()
+            .iter()
+            .all(|status| status.state == AccessState::Granted));
+    }
+
+    #[test]
+    fn first_denial_reported_once_per_category() {
+        assert!(first_denial(ProtectedCategory::NetworkVolume));
+        assert!(!first_denial(ProtectedCategory::NetworkVolume));
+    }
+}
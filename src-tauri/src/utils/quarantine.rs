@@ -0,0 +1,199 @@
+//! macOS's `com.apple.quarantine` extended attribute, which marks a file as
+//! downloaded so Gatekeeper checks it before it opens. A rename keeps it, but
+//! the copy a move between volumes falls back to may not, so actions that
+//! care say what should happen to it (`QuarantineHandling`).
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use crate::models::QuarantineHandling;
+use crate::utils::xattr::{is_unsupported, Xattrs};
+
+/// The extended attribute Gatekeeper reads.
+pub const ATTRIBUTE: &str = "com.apple.quarantine";
+
+/// Metadata keys recording whether the file carried the attribute before it
+/// was moved or copied and whether it does once it has landed.
+pub const QUARANTINE_BEFORE_KEY: &str = "quarantine_before";
+pub const QUARANTINE_AFTER_KEY: &str = "quarantine_after";
+
+/// Metadata key explaining why the quarantine setting had no effect.
+pub const QUARANTINE_NOTE_KEY: &str = "quarantine_note";
+
+/// Whether a file carried the attribute before and after it was moved or
+/// copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuarantineChange {
+    pub before: bool,
+    pub after: bool,
+}
+
+impl QuarantineChange {
+    pub fn record(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert(QUARANTINE_BEFORE_KEY.to_string(), state(self.before));
+        metadata.insert(QUARANTINE_AFTER_KEY.to_string(), state(self.after));
+    }
+}
+
+fn state(present: bool) -> String {
+    if present { "present" } else { "absent" }.to_string()
+}
+
+/// Refuses `Strip` unless the user has acknowledged in settings that it lets
+/// files skip Gatekeeper.
+pub fn check_allowed(handling: QuarantineHandling, allow_strip: bool) -> Result<(), String> {
+    if handling == QuarantineHandling::Strip && !allow_strip {
+        return Err("Removing the quarantine attribute is disabled in settings".to_string());
+    }
+    Ok(())
+}
+
+/// The attribute `source` carries before it's moved or copied, when the
+/// handling needs to know. A file whose attribute can't be read counts as
+/// having none.
+pub fn capture(
+    xattrs: &dyn Xattrs,
+    handling: QuarantineHandling,
+    source: &Path,
+) -> Option<Vec<u8>> {
+    if handling == QuarantineHandling::Keep || !xattrs.supported() {
+        return None;
+    }
+    xattrs.get(source, ATTRIBUTE).ok().flatten()
+}
+
+/// Gives the file that just landed at `dest` the attribute `handling` asks
+/// for, given the one it had beforehand. `None` when there's nothing to do:
+/// under `Keep`, or where files can't carry the attribute, as on Linux, whose
+/// filesystems refuse a name outside their `user.` namespace.
+pub fn settle(
+    xattrs: &dyn Xattrs,
+    handling: QuarantineHandling,
+    before: Option<&[u8]>,
+    dest: &Path,
+) -> io::Result<Option<QuarantineChange>> {
+    if handling == QuarantineHandling::Keep || !xattrs.supported() {
+        return Ok(None);
+    }
+    match apply(xattrs, handling, before, dest) {
+        Err(err) if is_unsupported(&err) => Ok(None),
+        result => result,
+    }
+}
+
+fn apply(
+    xattrs: &dyn Xattrs,
+    handling: QuarantineHandling,
+    before: Option<&[u8]>,
+    dest: &Path,
+) -> io::Result<Option<QuarantineChange>> {
+    match handling {
+        QuarantineHandling::Preserve => {
+            if let Some(value) = before {
+                if xattrs.get(dest, ATTRIBUTE)?.as_deref() != Some(value) {
+                    xattrs.set(dest, ATTRIBUTE, value)?;
+                }
+            }
+        }
+        QuarantineHandling::Strip => xattrs.remove(dest, ATTRIBUTE)?,
+        QuarantineHandling::Keep => {}
+    }
+    Ok(Some(QuarantineChange {
+        before: before.is_some(),
+        after: xattrs.get(dest, ATTRIBUTE)?.is_some(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::xattr::{MemoryXattrs, System};
+    use tempfile::tempdir;
+
+    const DOWNLOADED: &[u8] = b"0083;66b1c2d3;Safari;";
+
+    #[test]
+    fn strip_removes_the_attribute_and_records_both_states() {
+        let xattrs = MemoryXattrs::default();
+        let dest = Path::new("/inbox/scanner.dmg");
+        xattrs.set(dest, ATTRIBUTE, DOWNLOADED).unwrap();
+
+        let change = settle(&xattrs, QuarantineHandling::Strip, Some(DOWNLOADED), dest)
+            .unwrap()
+            .unwrap();
+        assert_eq!(xattrs.get(dest, ATTRIBUTE).unwrap(), None);
+
+        let mut metadata = HashMap::new();
+        change.record(&mut metadata);
+        assert_eq!(metadata[QUARANTINE_BEFORE_KEY], "present");
+        assert_eq!(metadata[QUARANTINE_AFTER_KEY], "absent");
+    }
+
+    #[test]
+    fn strip_needs_the_setting() {
+        assert!(check_allowed(QuarantineHandling::Strip, false).is_err());
+        assert!(check_allowed(QuarantineHandling::Strip, true).is_ok());
+        assert!(check_allowed(QuarantineHandling::Preserve, false).is_ok());
+    }
+
+    #[test]
+    fn keep_leaves_the_file_alone() {
+        let xattrs = MemoryXattrs::default();
+        let source = Path::new("/downloads/app.dmg");
+        xattrs.set(source, ATTRIBUTE, DOWNLOADED).unwrap();
+        assert_eq!(capture(&xattrs, QuarantineHandling::Keep, source), None);
+        let dest = Path::new("/apps/app.dmg");
+        assert_eq!(
+            settle(&xattrs, QuarantineHandling::Keep, None, dest).unwrap(),
+            None
+        );
+        assert_eq!(xattrs.get(dest, ATTRIBUTE).unwrap(), None);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn other_platforms_have_nothing_to_settle() {
+        let dir = tempdir().unwrap();
+        let dest = dir.path().join("app.dmg");
+        std::fs::write(&dest, b"dmg").unwrap();
+        assert_eq!(capture(&System, QuarantineHandling::Preserve, &dest), None);
+        assert_eq!(
+            settle(&System, QuarantineHandling::Strip, None, &dest).unwrap(),
+            None
+        );
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn strip_and_preserve_on_the_real_attribute() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("app.dmg");
+        let dest = dir.path().join("copy.dmg");
+        std::fs::write(&source, b"dmg").unwrap();
+        std::fs::write(&dest, b"dmg").unwrap();
+        System.set(&source, ATTRIBUTE, DOWNLOADED).unwrap();
+
+        let before = capture(&System, QuarantineHandling::Preserve, &source);
+        assert_eq!(before.as_deref(), Some(DOWNLOADED));
+        let change = settle(
+            &System,
+            QuarantineHandling::Preserve,
+            before.as_deref(),
+            &dest,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(change.after);
+        assert_eq!(
+            System.get(&dest, ATTRIBUTE).unwrap().as_deref(),
+            Some(DOWNLOADED)
+        );
+
+        let change = settle(&System, QuarantineHandling::Strip, before.as_deref(), &dest)
+            .unwrap()
+            .unwrap();
+        assert!(change.before && !change.after);
+        assert_eq!(System.get(&dest, ATTRIBUTE).unwrap(), None);
+    }
+}
@@ -0,0 +1,257 @@
+//! Password encryption for exports and backups. A sealed file is
+//!
+//! ```text
+//! "FDSEAL" | version | kdf | memory KiB (u32 BE) | passes (u32 BE)
+//!          | lanes (u32 BE) | salt (16) | nonce (24)
+//!          | header checksum (32) | password check (32) | ciphertext + tag
+//! ```
+//!
+//! Argon2id turns the password and a fresh salt into an encryption key and a
+//! check key. The header checksum is a plain SHA-256 of the header, so a
+//! damaged header is reported as damage rather than as a wrong password. The
+//! password check is the SHA-256 of the check key and says whether the
+//! password is right before anything is decrypted. The payload is
+//! XChaCha20-Poly1305 with the header as associated data. The primitives are
+//! the RustCrypto crates'; nothing here is homemade crypto.
+//!
+//! Derived keys are wiped once the call is done with them.
+
+use std::fmt;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+const MAGIC: &[u8; 6] = b"FDSEAL";
+/// `MAGIC` in base64; six bytes encode without padding, so sealed text
+/// always starts with it.
+const TEXT_MAGIC: &str = "RkRTRUFM";
+const VERSION: u8 = 2;
+const KDF_ARGON2ID: u8 = 2;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const DIGEST_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 2 + 3 * 4 + SALT_LEN + NONCE_LEN;
+const PREFIX_LEN: usize = HEADER_LEN + 2 * DIGEST_LEN;
+
+/// Memory in KiB, passes and lanes, well above OWASP's minimum for Argon2id;
+/// tests, in debug builds, would take seconds per key with it, and the
+/// format doesn't care.
+const COST: (u32, u32, u32) = if cfg!(test) {
+    (64, 1, 1)
+} else {
+    (65_536, 3, 1)
+};
+/// More than any file this app writes asks for; a file asking for more is
+/// refused rather than left to spin or to exhaust memory.
+const MAX_COST: (u32, u32, u32) = (1 << 20, 64, 16);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SealError {
+    WrongPassword,
+    /// Not sealed, cut short, from a newer version or altered.
+    Corrupt(String),
+}
+
+impl fmt::Display for SealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongPassword => f.write_str("Wrong password"),
+            Self::Corrupt(reason) => write!(f, "Encrypted file is damaged: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SealError {}
+
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn is_sealed_text(text: &str) -> bool {
+    text.trim_start().starts_with(TEXT_MAGIC)
+}
+
+/// Encrypts `plain` with `password`.
+pub fn seal(plain: &[u8], password: &str) -> Vec<u8> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let (memory, passes, lanes) = COST;
+
+    let mut out = Vec::with_capacity(PREFIX_LEN + plain.len() + TAG_LEN);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&[VERSION, KDF_ARGON2ID]);
+    for cost in [memory, passes, lanes] {
+        out.extend_from_slice(&cost.to_be_bytes());
+    }
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+
+    let keys = derive_keys(password, &salt, COST).expect("built-in key costs are valid");
+    let ciphertext = cipher(&keys[..32])
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plain,
+                aad: &out,
+            },
+        )
+        .expect("payload too large to encrypt");
+    let checksum = Sha256::digest(&out);
+    out.extend_from_slice(&checksum);
+    out.extend_from_slice(&Sha256::digest(&keys[32..]));
+    out.extend_from_slice(&ciphertext);
+    out
+}
+
+/// `seal` as base64, for exports that are text.
+pub fn seal_text(plain: &str, password: &str) -> String {
+    STANDARD.encode(seal(plain.as_bytes(), password))
+}
+
+pub fn open(data: &[u8], password: &str) -> Result<Vec<u8>, SealError> {
+    if !is_sealed(data) {
+        return Err(SealError::Corrupt("not an encrypted file".to_string()));
+    }
+    if data.len() < PREFIX_LEN + TAG_LEN {
+        return Err(SealError::Corrupt("too short".to_string()));
+    }
+    let (header, rest) = data.split_at(HEADER_LEN);
+    let (checksum, rest) = rest.split_at(DIGEST_LEN);
+    let (check, ciphertext) = rest.split_at(DIGEST_LEN);
+    if header[6] != VERSION {
+        return Err(SealError::Corrupt(format!("unknown version {}", header[6])));
+    }
+    if Sha256::digest(header).as_slice() != checksum {
+        return Err(SealError::Corrupt(
+            "header doesn't match its checksum".to_string(),
+        ));
+    }
+    if header[7] != KDF_ARGON2ID {
+        return Err(SealError::Corrupt(format!(
+            "unknown key derivation {}",
+            header[7]
+        )));
+    }
+    let cost_at = |at: usize| u32::from_be_bytes(header[at..at + 4].try_into().unwrap());
+    let cost = (cost_at(8), cost_at(12), cost_at(16));
+    if cost.0 > MAX_COST.0 || cost.1 > MAX_COST.1 || cost.2 > MAX_COST.2 {
+        return Err(SealError::Corrupt(format!("key costs too high {cost:?}")));
+    }
+    let salt = &header[20..20 + SALT_LEN];
+    let nonce = XNonce::from_slice(&header[20 + SALT_LEN..]);
+
+    let keys = derive_keys(password, salt, cost)
+        .map_err(|err| SealError::Corrupt(format!("bad key costs {cost:?}: {err}")))?;
+    if Sha256::digest(&keys[32..]).as_slice() != check {
+        return Err(SealError::WrongPassword);
+    }
+
+    cipher(&keys[..32])
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| SealError::Corrupt("contents don't match their checksum".to_string()))
+}
+
+/// `open` for text from `seal_text`.
+pub fn open_text(text: &str, password: &str) -> Result<String, SealError> {
+    let data = STANDARD
+        .decode(text.trim())
+        .map_err(|err| SealError::Corrupt(err.to_string()))?;
+    String::from_utf8(open(&data, password)?).map_err(|err| SealError::Corrupt(err.to_string()))
+}
+
+/// 32 bytes of encryption key, then 32 of check key.
+fn derive_keys(
+    password: &str,
+    salt: &[u8],
+    (memory, passes, lanes): (u32, u32, u32),
+) -> Result<Zeroizing<[u8; 64]>, argon2::Error> {
+    let params = Params::new(memory, passes, lanes, None)?;
+    let mut keys = Zeroizing::new([0u8; 64]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(
+        password.as_bytes(),
+        salt,
+        keys.as_mut(),
+    )?;
+    Ok(keys)
+}
+
+fn cipher(key: &[u8]) -> XChaCha20Poly1305 {
+    XChaCha20Poly1305::new_from_slice(key).expect("key is 32 bytes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_with_the_right_password() {
+        let sealed = seal(b"~/Clients/Acme/invoices", "hunter2");
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.windows(7).any(|window| window == b"Clients"));
+        assert_eq!(
+            open(&sealed, "hunter2").unwrap(),
+            b"~/Clients/Acme/invoices"
+        );
+
+        let text = seal_text("{}", "pw");
+        assert!(is_sealed_text(&text));
+        assert_eq!(open_text(&text, "pw").unwrap(), "{}");
+    }
+
+    #[test]
+    fn a_wrong_password_is_not_corruption() {
+        let sealed = seal(b"secret paths", "right");
+        assert_eq!(open(&sealed, "wrong"), Err(SealError::WrongPassword));
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let sealed = seal(b"secret paths", "pw");
+
+        let mut flipped = sealed.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(matches!(open(&flipped, "pw"), Err(SealError::Corrupt(_))));
+
+        let mut body = sealed.clone();
+        body[PREFIX_LEN] ^= 1;
+        assert!(matches!(open(&body, "pw"), Err(SealError::Corrupt(_))));
+
+        let cut = &sealed[..HEADER_LEN + 4];
+        assert!(matches!(open(cut, "pw"), Err(SealError::Corrupt(_))));
+        assert!(matches!(
+            open(b"plain text", "pw"),
+            Err(SealError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn a_damaged_header_is_not_a_wrong_password() {
+        let sealed = seal(b"secret paths", "pw");
+        // A salt byte, which would otherwise derive a different key.
+        let mut salt = sealed.clone();
+        salt[20] ^= 1;
+        assert!(matches!(open(&salt, "pw"), Err(SealError::Corrupt(_))));
+
+        let mut costs = sealed.clone();
+        costs[11] ^= 1;
+        assert!(matches!(open(&costs, "pw"), Err(SealError::Corrupt(_))));
+
+        let mut checksum = sealed.clone();
+        checksum[HEADER_LEN] ^= 1;
+        assert!(matches!(open(&checksum, "pw"), Err(SealError::Corrupt(_))));
+    }
+}
@@ -0,0 +1,89 @@
+//! Looks up the passwords actions need at the moment they run. Errors name
+//! where the password was expected, never the password itself.
+
+use std::process::Command;
+
+use anyhow::{anyhow, bail, Result};
+use zeroize::Zeroizing;
+
+use crate::models::PasswordSource;
+
+pub fn resolve_password(source: &PasswordSource) -> Result<Zeroizing<String>> {
+    let password = match source {
+        PasswordSource::Literal(password) => Zeroizing::new(password.clone()),
+        PasswordSource::EnvVar(name) => std::env::var(name)
+            .map(Zeroizing::new)
+            .map_err(|_| anyhow!("Environment variable {name} is not set"))?,
+        PasswordSource::Keyring(service) => keyring_password(service)?,
+    };
+    if password.is_empty() {
+        bail!("{} is empty", describe_source(source));
+    }
+    Ok(password)
+}
+
+/// Where a password comes from, for messages; a literal isn't echoed.
+fn describe_source(source: &PasswordSource) -> String {
+    match source {
+        PasswordSource::Literal(_) => "The rule's archive password".to_string(),
+        PasswordSource::EnvVar(name) => format!("Environment variable {name}"),
+        PasswordSource::Keyring(service) => format!("Keychain entry \"{service}\""),
+    }
+}
+
+/// Reads a generic password from the login keychain on macOS, or from the
+/// Secret Service (GNOME Keyring, KWallet) through `secret-tool` on Linux.
+fn keyring_password(service: &str) -> Result<Zeroizing<String>> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args(["find-generic-password", "-w", "-s", service]);
+        command
+    } else if cfg!(target_os = "linux") {
+        let mut command = Command::new("secret-tool");
+        command.args(["lookup", "service", service]);
+        command
+    } else {
+        bail!("Keychain passwords aren't supported on this system");
+    };
+    let output = command
+        .output()
+        .map_err(|err| anyhow!("Couldn't read keychain entry \"{service}\": {err}"))?;
+    let stdout = Zeroizing::new(output.stdout);
+    if !output.status.success() || stdout.is_empty() {
+        bail!("Keychain entry \"{service}\" not found");
+    }
+    let text = std::str::from_utf8(&stdout)
+        .map_err(|_| anyhow!("Keychain entry \"{service}\" is not text"))?;
+    Ok(Zeroizing::new(
+        text.strip_suffix('\n').unwrap_or(text).to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_environment_variables_and_names_missing_ones() {
+        std::env::set_var("FILEDISPATCH_TEST_ARCHIVE_PASSWORD", "hunter2");
+        let source = PasswordSource::EnvVar("FILEDISPATCH_TEST_ARCHIVE_PASSWORD".to_string());
+        assert_eq!(resolve_password(&source).unwrap().as_str(), "hunter2");
+
+        let missing = PasswordSource::EnvVar("FILEDISPATCH_TEST_NO_SUCH_PASSWORD".to_string());
+        let err = resolve_password(&missing).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Environment variable FILEDISPATCH_TEST_NO_SUCH_PASSWORD is not set"
+        );
+    }
+
+    #[test]
+    fn errors_never_show_a_literal_password() {
+        assert_eq!(
+            describe_source(&PasswordSource::Literal("hunter2".to_string())),
+            "The rule's archive password"
+        );
+        let err = resolve_password(&PasswordSource::Literal(String::new())).unwrap_err();
+        assert_eq!(err.to_string(), "The rule's archive password is empty");
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+
+/// Every intermediate file or directory is named with this prefix, so the
+/// watcher can ignore it and the sweeper can recognise it without the manifest.
+pub const TEMP_PREFIX: &str = ".fd-tmp-";
+
+/// Unregistered artifacts younger than this may still belong to a running operation.
+const ORPHAN_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+static APP_TEMP: Lazy<TempRoot> = Lazy::new(|| TempRoot::new(default_temp_root()));
+
+/// The temp root shared by the executor, OCR output and archive code.
+pub fn app_temp() -> &'static TempRoot {
+    &APP_TEMP
+}
+
+fn default_temp_root() -> PathBuf {
+    if cfg!(test) {
+        return std::env::temp_dir().join("file-dispatch-tests");
+    }
+    ProjectDirs::from("", "", "file-dispatch")
+        .map(|proj| proj.data_dir().join("tmp"))
+        .unwrap_or_else(|| std::env::temp_dir().join("file-dispatch"))
+}
+
+pub fn is_temp_artifact(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(TEMP_PREFIX))
+}
+
+/// Hands out temp artifacts and remembers each one in an on-disk manifest
+/// (one small file per artifact under `<root>/manifest`) until it's cleaned up,
+/// so a crash can't leave anything behind for good. Each session holds a lock
+/// on `<root>/sessions/<session>.lock` from its first artifact on, which is how
+/// a sweeper in another process tells a running session from a crashed one.
+pub struct TempRoot {
+    root: PathBuf,
+    /// Prefixes this process's manifest entries; the sweeper leaves them alone.
+    session: String,
+    lock: Mutex<Option<File>>,
+}
+
+impl TempRoot {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            session: Uuid::new_v4().simple().to_string(),
+            lock: Mutex::new(None),
+        }
+    }
+
+    fn manifest_dir(&self) -> PathBuf {
+        self.root.join("manifest")
+    }
+
+    fn lock_path(&self, session: &str) -> PathBuf {
+        self.root.join("sessions").join(format!("{session}.lock"))
+    }
+
+    /// Takes this session's lock before its first manifest entry is written;
+    /// the OS drops it when the process exits, however it exits.
+    fn hold_lock(&self) -> io::Result<()> {
+        let mut lock = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+        if lock.is_none() {
+            let path = self.lock_path(&self.session);
+            fs::create_dir_all(path.parent().unwrap_or(&self.root))?;
+            let file = File::create(&path)?;
+            file.try_lock().map_err(io::Error::from)?;
+            *lock = Some(file);
+        }
+        Ok(())
+    }
+
+    /// `None` while another session still holds its lock; otherwise the lock,
+    /// now ours, so the session can't be mistaken for running while it's swept.
+    /// A session without a lock file predates them or never got to take it.
+    fn claim_ended(&self, session: &str) -> Option<Option<File>> {
+        let Ok(file) = File::options().write(true).open(self.lock_path(session)) else {
+            return Some(None);
+        };
+        match file.try_lock() {
+            Ok(()) => Some(Some(file)),
+            Err(TryLockError::WouldBlock) => None,
+            // A lock that can't be checked is treated as held.
+            Err(TryLockError::Error(_)) => None,
+        }
+    }
+
+    /// A path next to `target`, so renaming it into place stays on one volume
+    /// and is atomic. Nothing is created there yet.
+    pub fn beside(&self, target: &Path) -> io::Result<TempArtifact> {
+        self.register(temp_path_beside(target), None)
+    }
+
+    /// An empty directory inside `dir` to stage output before moving it into place.
+    pub fn staging_dir_in(&self, dir: &Path) -> io::Result<TempArtifact> {
+        let artifact = self.beside(&dir.join("staging"))?;
+        fs::create_dir_all(&artifact.path)?;
+        Ok(artifact)
+    }
+
+    /// A path next to `original` to park the user's file on while it's being
+    /// renamed. Unlike other artifacts it's never deleted: the guard and the
+    /// sweeper move it back to `original` instead.
+    pub fn holding(&self, original: &Path) -> io::Result<TempArtifact> {
+        self.register(temp_path_beside(original), Some(original.to_path_buf()))
+    }
+
+    fn register(&self, path: PathBuf, origin: Option<PathBuf>) -> io::Result<TempArtifact> {
+        self.hold_lock()?;
+        let manifest = self.manifest_dir();
+        fs::create_dir_all(&manifest)?;
+        let entry = manifest.join(format!("{}-{}", self.session, Uuid::new_v4().simple()));
+        let mut listing = path.to_string_lossy().into_owned();
+        if let Some(origin) = &origin {
+            listing.push('\n');
+            listing.push_str(&origin.to_string_lossy());
+        }
+        fs::write(&entry, listing)?;
+        Ok(TempArtifact {
+            path,
+            origin,
+            entry,
+            persisted: false,
+        })
+    }
+
+    /// Cleans up after crashed runs: artifacts still listed in the manifest by
+    /// a session that no longer holds its lock are removed (or moved back, for
+    /// held files), then unlisted `.fd-tmp-*` entries older than a day are
+    /// removed from the root and the top level of `dirs`. Artifacts of a
+    /// session that's still running, here or in another process, are left
+    /// alone however old they are. Nothing without the prefix is ever touched.
+    /// Returns how many artifacts were cleaned up.
+    pub fn sweep(&self, dirs: &[PathBuf]) -> usize {
+        let mut cleaned = 0;
+        let mut active = HashSet::new();
+        // Each other session's lock, taken once it's known to have ended.
+        let mut ended: HashMap<String, Option<Option<File>>> = HashMap::new();
+        let entries = fs::read_dir(self.manifest_dir())
+            .map(|entries| entries.flatten().collect::<Vec<_>>())
+            .unwrap_or_default();
+        for entry in entries {
+            let Ok(listing) = fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            let mut lines = listing.lines();
+            let path = PathBuf::from(lines.next().unwrap_or_default());
+            let origin = lines.next().map(PathBuf::from);
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let session = name.split('-').next().unwrap_or_default().to_string();
+            if session == self.session {
+                active.insert(path);
+                continue;
+            }
+            let running = ended
+                .entry(session.clone())
+                .or_insert_with(|| self.claim_ended(&session))
+                .is_none();
+            if running {
+                active.insert(path);
+                continue;
+            }
+            if !is_temp_artifact(&path) {
+                // Not something we'd have created; drop the entry, keep the path.
+                let _ = fs::remove_file(entry.path());
+                continue;
+            }
+            let result = match &origin {
+                Some(origin) => restore(&path, origin),
+                None => remove_artifact(&path),
+            };
+            match result {
+                Ok(existed) => {
+                    cleaned += usize::from(existed);
+                    let _ = fs::remove_file(entry.path());
+                }
+                // Leave the entry so the next sweep tries again.
+                Err(_) => {
+                    active.insert(path);
+                }
+            }
+        }
+        let manifest = fs::read_dir(self.manifest_dir())
+            .map(|entries| entries.flatten().collect::<Vec<_>>())
+            .unwrap_or_default();
+        for (session, lock) in ended {
+            let Some(Some(lock)) = lock else {
+                continue;
+            };
+            // Kept while entries are left for the next sweep to retry.
+            let done = !manifest.iter().any(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(&format!("{session}-"))
+            });
+            if done {
+                let _ = fs::remove_file(self.lock_path(&session));
+            }
+            drop(lock);
+        }
+
+        let cutoff = SystemTime::now()
+            .checked_sub(ORPHAN_AGE)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        for dir in std::iter::once(&self.root).chain(dirs) {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if !is_temp_artifact(&path) || active.contains(&path) {
+                    continue;
+                }
+                let stale = entry
+                    .metadata()
+                    .and_then(|meta| meta.modified())
+                    .is_ok_and(|modified| modified < cutoff);
+                if stale && remove_artifact(&path).unwrap_or(false) {
+                    cleaned += 1;
+                }
+            }
+        }
+        cleaned
+    }
+}
+
+/// A registered temp file or directory. Dropping it without `persist` removes
+/// it, so an early return or `?` can't leave it behind.
+pub struct TempArtifact {
+    path: PathBuf,
+    origin: Option<PathBuf>,
+    entry: PathBuf,
+    persisted: bool,
+}
+
+impl TempArtifact {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Renames the artifact onto `dest`, after which it's no longer temporary.
+    pub fn persist(mut self, dest: &Path) -> io::Result<()> {
+        fs::rename(&self.path, dest)?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempArtifact {
+    fn drop(&mut self) {
+        let cleaned = if self.persisted {
+            Ok(false)
+        } else if let Some(origin) = &self.origin {
+            restore(&self.path, origin)
+        } else {
+            remove_artifact(&self.path)
+        };
+        // Keep the entry when cleanup failed so the sweeper gets another go.
+        if cleaned.is_ok() {
+            let _ = fs::remove_file(&self.entry);
+        }
+    }
+}
+
+fn temp_path_beside(target: &Path) -> PathBuf {
+    let parent = target
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let hint = target
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("file");
+    parent.join(format!("{TEMP_PREFIX}{}-{hint}", Uuid::new_v4().simple()))
+}
+
+/// Removes a file or directory; `Ok(false)` if it was already gone.
+fn remove_artifact(path: &Path) -> io::Result<bool> {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+    if meta.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(true)
+}
+
+/// Moves a held file back to where it came from, never over something newer.
+fn restore(path: &Path, origin: &Path) -> io::Result<bool> {
+    if fs::symlink_metadata(path).is_err() {
+        return Ok(false);
+    }
+    if fs::symlink_metadata(origin).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} is back in place; keeping {}", origin.display(), path.display()),
+        ));
+    }
+    fs::rename(path, origin)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filetime::FileTime;
+    use tempfile::tempdir;
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn age(path: &Path, days: i64) {
+        let then = FileTime::from_unix_time(chrono::Utc::now().timestamp() - days * 86_400, 0);
+        filetime::set_file_mtime(path, then).unwrap();
+    }
+
+    #[test]
+    fn sweeper_removes_exactly_what_a_crash_left_behind() {
+        let dir = tempdir().unwrap();
+        let watched = dir.path().join("inbox");
+        fs::create_dir_all(&watched).unwrap();
+        fs::write(watched.join("keep.pdf"), "mine").unwrap();
+        fs::write(watched.join("scan.fd-tmp-notes.txt"), "mine").unwrap();
+        fs::write(watched.join("photo.jpg"), "photo").unwrap();
+
+        // A run that crashes part-way: nothing gets to clean up.
+        let crashed = TempRoot::new(dir.path().join("tmp"));
+        let pdf = crashed.beside(&watched.join("keep.pdf")).unwrap();
+        fs::write(pdf.path(), "half a pdf").unwrap();
+        let staging = crashed.staging_dir_in(&watched).unwrap();
+        fs::write(staging.path().join("extracted.txt"), "partial").unwrap();
+        let held = crashed.holding(&watched.join("photo.jpg")).unwrap();
+        fs::rename(watched.join("photo.jpg"), held.path()).unwrap();
+        std::mem::forget(pdf);
+        std::mem::forget(staging);
+        std::mem::forget(held);
+
+        // Unlisted leftovers: only the old one is fair game.
+        fs::write(watched.join(".fd-tmp-old"), "stale").unwrap();
+        age(&watched.join(".fd-tmp-old"), 2);
+        fs::write(watched.join(".fd-tmp-young"), "in use").unwrap();
+        // A tampered entry pointing at a user file is ignored.
+        let tampered = watched.join("keep.pdf").to_string_lossy().into_owned();
+        fs::write(crashed.manifest_dir().join("other-session"), tampered).unwrap();
+        // The crashed process is gone, and its lock with it.
+        drop(crashed);
+
+        let restarted = TempRoot::new(dir.path().join("tmp"));
+        assert_eq!(restarted.sweep(std::slice::from_ref(&watched)), 4);
+        assert_eq!(
+            names(&watched),
+            vec![".fd-tmp-young", "keep.pdf", "photo.jpg", "scan.fd-tmp-notes.txt"]
+        );
+        assert_eq!(fs::read_to_string(watched.join("photo.jpg")).unwrap(), "photo");
+        assert_eq!(fs::read_to_string(watched.join("keep.pdf")).unwrap(), "mine");
+        assert!(names(&restarted.manifest_dir()).is_empty());
+        assert!(names(&dir.path().join("tmp/sessions")).is_empty());
+    }
+
+    #[test]
+    fn sweeper_skips_artifacts_of_sessions_still_running_elsewhere() {
+        let dir = tempdir().unwrap();
+        let running = TempRoot::new(dir.path().join("tmp"));
+        let artifact = running.beside(&dir.path().join("out.zip")).unwrap();
+        fs::write(artifact.path(), "writing").unwrap();
+        age(artifact.path(), 2);
+
+        let started = TempRoot::new(dir.path().join("tmp"));
+        assert_eq!(started.sweep(&[dir.path().to_path_buf()]), 0);
+        assert!(artifact.path().exists());
+        assert_eq!(names(&running.manifest_dir()).len(), 1);
+
+        // Once it's gone its leftovers are fair game.
+        let path = artifact.path().to_path_buf();
+        std::mem::forget(artifact);
+        drop(running);
+        assert_eq!(started.sweep(&[dir.path().to_path_buf()]), 1);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn sweeper_skips_artifacts_of_the_running_session() {
+        let dir = tempdir().unwrap();
+        let temp = TempRoot::new(dir.path().join("tmp"));
+        let artifact = temp.beside(&dir.path().join("out.zip")).unwrap();
+        fs::write(artifact.path(), "writing").unwrap();
+        age(artifact.path(), 2);
+
+        assert_eq!(temp.sweep(&[dir.path().to_path_buf()]), 0);
+        assert!(artifact.path().exists());
+    }
+
+    fn write_then_fail(temp: &TempRoot, target: &Path) -> io::Result<()> {
+        let artifact = temp.beside(target)?;
+        fs::write(artifact.path(), "partial output")?;
+        // The injected failure: a step that needs a file that isn't there.
+        fs::read(target.with_extension("missing"))?;
+        artifact.persist(target)
+    }
+
+    #[test]
+    fn drop_guard_cleans_up_on_error() {
+        let dir = tempdir().unwrap();
+        let temp = TempRoot::new(dir.path().join("tmp"));
+        let out = dir.path().join("out");
+        fs::create_dir_all(&out).unwrap();
+
+        let err = write_then_fail(&temp, &out.join("report.pdf")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(names(&out).is_empty());
+        assert!(names(&temp.manifest_dir()).is_empty());
+    }
+
+    #[test]
+    fn held_file_goes_back_when_the_rename_fails() {
+        let dir = tempdir().unwrap();
+        let temp = TempRoot::new(dir.path().join("tmp"));
+        let original = dir.path().join("Report.txt");
+        fs::write(&original, "content").unwrap();
+
+        let held = temp.holding(&original).unwrap();
+        fs::rename(&original, held.path()).unwrap();
+        assert!(held.persist(&dir.path().join("missing/report.txt")).is_err());
+
+        assert_eq!(fs::read_to_string(&original).unwrap(), "content");
+        assert!(names(&temp.manifest_dir()).is_empty());
+    }
+}
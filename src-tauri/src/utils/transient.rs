@@ -0,0 +1,108 @@
+use std::error::Error;
+use std::io;
+
+use rusqlite::ErrorCode;
+
+use crate::utils::file_lock::is_lock_error;
+
+/// Whether an error is likely to go away on its own: a timeout, an unreachable
+/// network share or unplugged volume, a locked file, a full disk, or a busy
+/// database. Those are worth retrying later; anything else won't change by
+/// waiting. Walks the error's source chain, so wrapped errors classify too.
+pub fn is_transient(err: &(dyn Error + 'static)) -> bool {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(io_err) = err.downcast_ref::<io::Error>() {
+            if is_transient_io(io_err) {
+                return true;
+            }
+            // `io::Error::new(kind, inner)` hides `inner` from `source()`.
+            if let Some(inner) = io_err.get_ref() {
+                if is_transient(inner) {
+                    return true;
+                }
+            }
+        }
+        if let Some(rusqlite::Error::SqliteFailure(failure, _)) = err.downcast_ref() {
+            if matches!(failure.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) {
+                return true;
+            }
+        }
+        current = err.source();
+    }
+    false
+}
+
+fn is_transient_io(err: &io::Error) -> bool {
+    if is_lock_error(err) || matches!(err.kind(), io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) {
+        return true;
+    }
+    let Some(code) = err.raw_os_error() else {
+        return false;
+    };
+    #[cfg(windows)]
+    {
+        // ERROR_HANDLE_DISK_FULL, ERROR_NOT_READY, ERROR_BAD_NETPATH,
+        // ERROR_NETWORK_BUSY, ERROR_DEV_NOT_EXIST, ERROR_UNEXP_NET_ERR,
+        // ERROR_NETNAME_DELETED, ERROR_BAD_NET_NAME, ERROR_DISK_FULL,
+        // ERROR_SEM_TIMEOUT, ERROR_NETWORK_UNREACHABLE
+        matches!(code, 39 | 21 | 53 | 54 | 55 | 59 | 64 | 67 | 112 | 121 | 1231)
+    }
+    #[cfg(unix)]
+    {
+        [
+            libc::ETIMEDOUT,
+            libc::EIO,
+            libc::EBUSY,
+            libc::ENOSPC,
+            libc::EDQUOT,
+            libc::ESTALE,
+            libc::ENOTCONN,
+            libc::ECONNRESET,
+            libc::ECONNABORTED,
+            libc::EHOSTDOWN,
+            libc::EHOSTUNREACH,
+            libc::ENETDOWN,
+            libc::ENETUNREACH,
+            libc::ENODEV,
+            libc::ENXIO,
+        ]
+        .contains(&code)
+    }
+    #[cfg(not(any(windows, unix)))]
+    {
+        let _ = code;
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_and_disk_errors_are_transient() {
+        assert!(is_transient(&io::Error::from(io::ErrorKind::TimedOut)));
+        #[cfg(unix)]
+        for code in [libc::EHOSTDOWN, libc::ENOSPC, libc::ESTALE] {
+            assert!(is_transient(&io::Error::from_raw_os_error(code)), "{code}");
+        }
+        assert!(!is_transient(&io::Error::from(io::ErrorKind::NotFound)));
+        assert!(!is_transient(&io::Error::from(io::ErrorKind::PermissionDenied)));
+    }
+
+    #[test]
+    fn wrapped_errors_are_classified_by_their_cause() {
+        let wrapped = io::Error::new(io::ErrorKind::Other, io::Error::from(io::ErrorKind::TimedOut));
+        assert!(is_transient(&wrapped));
+        let chained = anyhow::Error::new(io::Error::from(io::ErrorKind::TimedOut)).context("moving");
+        assert!(is_transient(chained.as_ref()));
+
+        let busy = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+            None,
+        );
+        assert!(is_transient(&busy));
+        assert!(!is_transient(&rusqlite::Error::QueryReturnedNoRows));
+    }
+}
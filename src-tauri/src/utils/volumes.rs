@@ -0,0 +1,145 @@
+//! Volumes that can go away: removable drives and network shares. Rule
+//! reference checks use this to warn about unplugged drives, and the
+//! executor and destination monitor to hold transfers until a volume is back.
+
+use std::path::{Component, Path, PathBuf};
+
+#[cfg(test)]
+thread_local! {
+    static MOCK_VOLUMES: std::cell::RefCell<Vec<PathBuf>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Test hook: on the current thread, treats `root` as a volume's mount point
+/// that's mounted whenever the folder exists.
+#[cfg(test)]
+pub(crate) fn mock_volume(root: &Path) {
+    MOCK_VOLUMES.with(|roots| roots.borrow_mut().push(root.to_path_buf()));
+}
+
+/// The volume `path` lives on, when it's one that can be unplugged and it
+/// isn't mounted.
+pub fn offline_volume(path: &Path) -> Option<PathBuf> {
+    let root = volume_root(path)?;
+    (!is_mounted(&root)).then_some(root)
+}
+
+/// Whether the volume mounted at `root` is there. A missing mount point, or
+/// the empty folder left behind on the parent's disk, means it isn't; a
+/// symlink is taken at its word.
+pub fn is_mounted(root: &Path) -> bool {
+    #[cfg(test)]
+    if MOCK_VOLUMES.with(|roots| roots.borrow().iter().any(|mock| mock == root)) {
+        return root.is_dir();
+    }
+    match std::fs::symlink_metadata(root) {
+        Ok(metadata) if metadata.file_type().is_symlink() => true,
+        Ok(metadata) if metadata.is_dir() => !left_behind_mount_point(root, &metadata),
+        _ => false,
+    }
+}
+
+/// The mount point of removable and network volumes: `/Volumes/<name>` on
+/// macOS, `/media/<user>/<name>`, `/run/media/<user>/<name>` and
+/// `/mnt/<name>` on Linux, and drive roots other than the system drive on
+/// Windows.
+pub fn volume_root(path: &Path) -> Option<PathBuf> {
+    #[cfg(test)]
+    if let Some(root) = MOCK_VOLUMES.with(|roots| {
+        roots
+            .borrow()
+            .iter()
+            .find(|root| path.starts_with(root))
+            .cloned()
+    }) {
+        return Some(root);
+    }
+    let mut components = path.components();
+    let first = components.next()?;
+    if let Component::Prefix(prefix) = first {
+        let drive = prefix.as_os_str().to_string_lossy().to_uppercase();
+        let system = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+        let is_drive = drive.len() == 2 && drive.ends_with(':');
+        if !is_drive || drive == system.to_uppercase() {
+            return None;
+        }
+        return Some(PathBuf::from(format!("{drive}\\")));
+    }
+    if first != Component::RootDir {
+        return None;
+    }
+    let parts: Vec<&str> = components
+        .filter_map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    let depth = match parts.as_slice() {
+        ["Volumes", _, ..] | ["mnt", _, ..] => 2,
+        ["media", _, _, ..] => 3,
+        ["run", "media", _, _, ..] => 4,
+        _ => return None,
+    };
+    let mut root = PathBuf::from("/");
+    root.extend(&parts[..depth]);
+    Some(root)
+}
+
+/// An empty folder on the same device as its parent is where a volume was
+/// mounted, not the volume: writing there would fill the wrong disk.
+#[cfg(unix)]
+fn left_behind_mount_point(root: &Path, metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let Some(parent) = root
+        .parent()
+        .and_then(|parent| std::fs::metadata(parent).ok())
+    else {
+        return false;
+    };
+    let empty = std::fs::read_dir(root)
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(false);
+    parent.dev() == metadata.dev() && empty
+}
+
+#[cfg(not(unix))]
+fn left_behind_mount_point(_root: &Path, _metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn volume_roots_are_found_from_any_path_on_them() {
+        assert_eq!(
+            volume_root(Path::new("/run/media/ana/Backup/Photos")),
+            Some(PathBuf::from("/run/media/ana/Backup"))
+        );
+        assert_eq!(
+            volume_root(Path::new("/Volumes/NAS/Archive/2024")),
+            Some(PathBuf::from("/Volumes/NAS"))
+        );
+        assert_eq!(volume_root(Path::new("/home/ana/Documents")), None);
+        assert_eq!(
+            offline_volume(Path::new("/Volumes/FileDispatch-test-drive/a.pdf")),
+            Some(PathBuf::from("/Volumes/FileDispatch-test-drive"))
+        );
+    }
+
+    #[test]
+    fn a_mocked_volume_is_offline_while_its_folder_is_gone() {
+        let dir = tempdir().unwrap();
+        let nas = dir.path().join("nas");
+        mock_volume(&nas);
+        let dest = nas.join("Archive/2024/scan.pdf");
+
+        assert_eq!(offline_volume(&dest), Some(nas.clone()));
+        fs::create_dir(&nas).unwrap();
+        assert_eq!(offline_volume(&dest), None);
+        assert!(is_mounted(&nas));
+    }
+}
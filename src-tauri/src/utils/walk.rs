@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crossbeam_channel::{bounded, Receiver};
+
+/// Batches the walker may get ahead of its consumer by.
+const QUEUED_BATCHES: usize = 2;
+
+pub struct WalkOptions {
+    pub max_depth: usize,
+    /// Files per batch.
+    pub batch_size: usize,
+    /// Resume after this path from an earlier walk of the same root; it and
+    /// everything before it are skipped without being read.
+    pub start_after: Option<PathBuf>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            batch_size: 256,
+            start_after: None,
+        }
+    }
+}
+
+/// The files under a folder, in batches, read on a background thread that
+/// stays at most a couple of batches ahead. Dropping it stops the walk.
+pub struct FileBatches {
+    rx: Receiver<Vec<PathBuf>>,
+    buffered: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+}
+
+impl FileBatches {
+    /// The most paths the walker held at once that the consumer hadn't taken.
+    #[allow(dead_code)]
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+}
+
+impl Iterator for FileBatches {
+    type Item = Vec<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = self.rx.recv().ok()?;
+        self.buffered.fetch_sub(batch.len(), Ordering::Relaxed);
+        Some(batch)
+    }
+}
+
+/// Walks `root` in path order, so a walk can resume from the last path an
+/// earlier one handed out. Entries `skip` accepts, files or directories, are
+/// left out along with everything under them. Unreadable entries are skipped.
+///
+/// Only one directory's names are held for sorting at a time, so memory stays
+/// bounded by the widest directory rather than the size of the tree.
+pub fn walk_files(
+    root: &Path,
+    options: WalkOptions,
+    skip: impl Fn(&Path) -> bool + Send + 'static,
+) -> FileBatches {
+    let (tx, rx) = bounded(QUEUED_BATCHES);
+    let buffered = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let batch_size = options.batch_size.max(1);
+    let root = root.to_path_buf();
+    let (held, high) = (buffered.clone(), peak.clone());
+    thread::spawn(move || {
+        let start_after = options.start_after;
+        let entries = walkdir::WalkDir::new(&root)
+            .max_depth(options.max_depth)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                let path = entry.path();
+                // Directories holding the cursor are entered; anything else
+                // at or before it was handed out already.
+                let pending = start_after
+                    .as_deref()
+                    .is_none_or(|after| path > after || (path != after && after.starts_with(path)));
+                pending && (entry.depth() == 0 || !skip(path))
+            })
+            .filter_map(|e| e.ok())
+            .filter(|entry| entry.file_type().is_file());
+        let mut batch = Vec::with_capacity(batch_size);
+        for entry in entries {
+            batch.push(entry.into_path());
+            let now = held.fetch_add(1, Ordering::Relaxed) + 1;
+            high.fetch_max(now, Ordering::Relaxed);
+            if batch.len() == batch_size {
+                let full = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+                if tx.send(full).is_err() {
+                    return;
+                }
+            }
+        }
+        if !batch.is_empty() {
+            let _ = tx.send(batch);
+        }
+    });
+    FileBatches { rx, buffered, peak }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn names(root: &Path, batches: FileBatches) -> Vec<String> {
+        batches
+            .flatten()
+            .map(|path| {
+                path.strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn walks_in_path_order_and_resumes_after_a_cursor() {
+        let dir = tempdir().unwrap();
+        for name in [
+            "b.txt",
+            "a/z.txt",
+            "a/m/1.txt",
+            "a b/c.txt",
+            "skip/x.txt",
+            "c.tmp",
+        ] {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+        let skip = |path: &Path| path.ends_with("skip") || path.ends_with("c.tmp");
+        let options = || WalkOptions {
+            batch_size: 2,
+            ..WalkOptions::default()
+        };
+
+        let all = names(dir.path(), walk_files(dir.path(), options(), skip));
+        assert_eq!(all, ["a/m/1.txt", "a/z.txt", "a b/c.txt", "b.txt"]);
+
+        let resumed = WalkOptions {
+            start_after: Some(dir.path().join("a/z.txt")),
+            ..options()
+        };
+        let rest = names(dir.path(), walk_files(dir.path(), resumed, skip));
+        assert_eq!(rest, ["a b/c.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn a_huge_tree_is_held_a_few_batches_at_a_time() {
+        let dir = tempdir().unwrap();
+        for folder in 0..100 {
+            let folder = dir.path().join(format!("{folder:03}"));
+            fs::create_dir(&folder).unwrap();
+            for file in 0..1000 {
+                fs::File::create(folder.join(format!("{file:04}"))).unwrap();
+            }
+        }
+
+        let options = WalkOptions {
+            batch_size: 100,
+            ..WalkOptions::default()
+        };
+        let mut batches = walk_files(dir.path(), options, |_| false);
+        let mut seen = 0;
+        for batch in batches.by_ref() {
+            seen += batch.len();
+            // A slow consumer lets the walker fill the queue.
+            thread::sleep(std::time::Duration::from_micros(200));
+        }
+        assert_eq!(seen, 100_000);
+        assert!(
+            batches.peak() <= (QUEUED_BATCHES + 1) * 100,
+            "{}",
+            batches.peak()
+        );
+    }
+}
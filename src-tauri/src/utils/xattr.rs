@@ -0,0 +1,243 @@
+//! Extended attributes, which File Dispatch reads and writes on macOS and
+//! Linux: the quarantine flag on macOS (`quarantine`) and tags on both
+//! (`file_tags`). Attributes are never followed through symlinks.
+
+use std::io;
+use std::path::Path;
+
+/// Reads and writes a file's extended attributes by name.
+pub trait Xattrs {
+    /// Whether files here can carry attributes at all.
+    fn supported(&self) -> bool;
+    fn get(&self, path: &Path, name: &str) -> io::Result<Option<Vec<u8>>>;
+    fn set(&self, path: &Path, name: &str, value: &[u8]) -> io::Result<()>;
+    /// Removing an attribute the file doesn't have is not an error.
+    fn remove(&self, path: &Path, name: &str) -> io::Result<()>;
+}
+
+/// The attributes on the real filesystem; only macOS and Linux have them.
+pub struct System;
+
+impl Xattrs for System {
+    fn supported(&self) -> bool {
+        cfg!(any(target_os = "macos", target_os = "linux"))
+    }
+
+    fn get(&self, path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+        sys::get(path, name)
+    }
+
+    fn set(&self, path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        sys::set(path, name, value)
+    }
+
+    fn remove(&self, path: &Path, name: &str) -> io::Result<()> {
+        sys::remove(path, name)
+    }
+}
+
+/// Whether `err` says the file's volume can't store attributes, as FAT and
+/// some network shares can't.
+pub fn is_unsupported(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::Unsupported || err.raw_os_error() == Some(libc::ENOTSUP)
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    fn is_missing(err: &io::Error) -> bool {
+        err.raw_os_error() == Some(libc::ENOATTR)
+    }
+
+    pub fn get(path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let name = CString::new(name)?;
+        let size = unsafe {
+            libc::getxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                std::ptr::null_mut(),
+                0,
+                0,
+                libc::XATTR_NOFOLLOW,
+            )
+        };
+        if size < 0 {
+            let err = io::Error::last_os_error();
+            return if is_missing(&err) { Ok(None) } else { Err(err) };
+        }
+        let mut value = vec![0u8; size as usize];
+        let read = unsafe {
+            libc::getxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_mut_ptr() as *mut libc::c_void,
+                value.len(),
+                0,
+                libc::XATTR_NOFOLLOW,
+            )
+        };
+        if read < 0 {
+            let err = io::Error::last_os_error();
+            return if is_missing(&err) { Ok(None) } else { Err(err) };
+        }
+        value.truncate(read as usize);
+        Ok(Some(value))
+    }
+
+    pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let name = CString::new(name)?;
+        let result = unsafe {
+            libc::setxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+                libc::XATTR_NOFOLLOW,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn remove(path: &Path, name: &str) -> io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let name = CString::new(name)?;
+        if unsafe { libc::removexattr(path.as_ptr(), name.as_ptr(), libc::XATTR_NOFOLLOW) } != 0 {
+            let err = io::Error::last_os_error();
+            if !is_missing(&err) {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    fn is_missing(err: &io::Error) -> bool {
+        err.raw_os_error() == Some(libc::ENODATA)
+    }
+
+    pub fn get(path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let name = CString::new(name)?;
+        let size =
+            unsafe { libc::lgetxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+        if size < 0 {
+            let err = io::Error::last_os_error();
+            return if is_missing(&err) { Ok(None) } else { Err(err) };
+        }
+        let mut value = vec![0u8; size as usize];
+        let read = unsafe {
+            libc::lgetxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_mut_ptr() as *mut libc::c_void,
+                value.len(),
+            )
+        };
+        if read < 0 {
+            let err = io::Error::last_os_error();
+            return if is_missing(&err) { Ok(None) } else { Err(err) };
+        }
+        value.truncate(read as usize);
+        Ok(Some(value))
+    }
+
+    pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let name = CString::new(name)?;
+        let result = unsafe {
+            libc::lsetxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn remove(path: &Path, name: &str) -> io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let name = CString::new(name)?;
+        if unsafe { libc::lremovexattr(path.as_ptr(), name.as_ptr()) } != 0 {
+            let err = io::Error::last_os_error();
+            if !is_missing(&err) {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+mod sys {
+    use std::io;
+    use std::path::Path;
+
+    pub fn get(_path: &Path, _name: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    pub fn set(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "extended attributes are only used on macOS and Linux",
+        ))
+    }
+
+    pub fn remove(_path: &Path, _name: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Attributes kept in memory by path and name, standing in for the
+/// filesystem's.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct MemoryXattrs {
+    values: std::sync::Mutex<std::collections::HashMap<(std::path::PathBuf, String), Vec<u8>>>,
+}
+
+#[cfg(test)]
+impl Xattrs for MemoryXattrs {
+    fn supported(&self) -> bool {
+        true
+    }
+
+    fn get(&self, path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let key = (path.to_path_buf(), name.to_string());
+        Ok(self.values.lock().unwrap().get(&key).cloned())
+    }
+
+    fn set(&self, path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        let key = (path.to_path_buf(), name.to_string());
+        self.values.lock().unwrap().insert(key, value.to_vec());
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path, name: &str) -> io::Result<()> {
+        let key = (path.to_path_buf(), name.to_string());
+        self.values.lock().unwrap().remove(&key);
+        Ok(())
+    }
+}
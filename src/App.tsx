@@ -4,6 +4,7 @@ import {
   FileDown,
   FileUp,
   FileSearch,
+  ListChecks,
   LayoutGrid,
   Settings,
   FolderCog,
@@ -26,6 +27,7 @@ import { FolderOptionsDialog } from "@/components/folders/FolderOptionsDialog";
 import { FolderList } from "@/components/folders/FolderList";
 import { RuleList } from "@/components/rules/RuleList";
 import { RuleEditor } from "@/components/rules/RuleEditor";
+import { BulkEditDialog } from "@/components/rules/BulkEditDialog";
 import { SettingsDialog } from "@/components/settings/SettingsDialog";
 import { StatsModal } from "@/components/ui/StatsModal";
 import { AddFolderIcon, AddGroupIcon } from "@/components/ui/CustomIcons";
@@ -33,10 +35,13 @@ import { ConfirmDialog } from "@/components/ui/ConfirmDialog";
 import { TemplateGallery } from "@/components/templates/TemplateGallery";
 import { PresetImportDialog } from "@/components/presets/PresetImportDialog";
 import { RuleStatusDialog } from "@/components/status/RuleStatusDialog";
+import { StartupIndicator } from "@/components/status/StartupIndicator";
 import { ToastViewport } from "@/components/ui/ToastViewport";
 import { CommandPalette } from "@/components/ui/CommandPalette";
 import { useFolders } from "@/hooks/useFolders";
+import { useDigest } from "@/hooks/useDigest";
 import { useEngineStatus } from "@/hooks/useEngineStatus";
+import { useErrorNotifications } from "@/hooks/useErrorNotifications";
 import { useLogs } from "@/hooks/useLogs";
 import { useRules } from "@/hooks/useRules";
 import { useSettings } from "@/hooks/useSettings";
@@ -45,7 +50,7 @@ import { useFolderStore } from "@/stores/folderStore";
 import { useLogStore } from "@/stores/logStore";
 import { useRuleStore } from "@/stores/ruleStore";
 import { useSettingsStore } from "@/stores/settingsStore";
-import type { Rule } from "@/types";
+import type { Rule, RuleImportError } from "@/types";
 import { normalizeRuleImportPayload } from "@/lib/ruleTransfer";
 import { useEngineStore } from "@/stores/engineStore";
 import { useShallow } from "zustand/shallow";
@@ -55,6 +60,7 @@ function App() {
   useFolders();
   useRules();
   useLogs();
+  useDigest();
   useSettings();
   useEngineStatus();
 
@@ -100,9 +106,11 @@ function App() {
   const [editorMode, setEditorMode] = useState<"empty" | "new" | "edit">("empty");
   const [editingRule, setEditingRule] = useState<Rule | null>(null);
   const [isLogExpanded, setIsLogExpanded] = useState(false);
+  useErrorNotifications(() => setIsLogExpanded(true));
   const [isGalleryOpen, setIsGalleryOpen] = useState(false);
   const [templateMode, setTemplateMode] = useState<"browse" | "create">("browse");
   const [isStatusOpen, setIsStatusOpen] = useState(false);
+  const [isBulkEditOpen, setIsBulkEditOpen] = useState(false);
   const [isSettingsOpen, setIsSettingsOpen] = useState(false);
   const [searchQuery, setSearchQuery] = useState("");
   const [isSearchOpen, setIsSearchOpen] = useState(false);
@@ -294,7 +302,7 @@ function App() {
     setIsRuleExporting(true);
     try {
       const payload = await ruleExport(selectedFolderId);
-      const defaultName = `${activeFolder?.name ?? "rules"}.filedispatch-rules.yaml`;
+      const defaultName = `${activeFolder?.name ?? "rules"}.filedispatch-rules.json`;
       const path = await save({
         defaultPath: defaultName,
         filters: [{ name: "File Dispatch Rules", extensions: ["filedispatch-rules", "yaml", "yml", "json"] }],
@@ -323,7 +331,13 @@ function App() {
       await ruleImport(selectedFolderId, normalizedPayload);
       await loadRules(selectedFolderId);
     } catch (err) {
-      setRuleTransferError(`Import failed: ${String(err)}`);
+      const importError = err as Partial<RuleImportError> | null;
+      const message = importError?.message
+        ? importError.path
+          ? `${importError.path}: ${importError.message}`
+          : importError.message
+        : String(err);
+      setRuleTransferError(`Import failed: ${message}`);
     } finally {
       setIsRuleImporting(false);
     }
@@ -541,6 +555,18 @@ function App() {
             <FileUp className="h-4 w-4" strokeWidth={1.5} />
           </button>
 
+          <button
+            onClick={() => setIsBulkEditOpen(true)}
+            className={`flex items-center justify-center p-1.5 rounded transition-colors ${isLinear
+              ? "text-[var(--fg-secondary)] hover:bg-[var(--bg-subtle)] hover:text-[var(--fg-primary)] disabled:opacity-40 disabled:hover:bg-transparent"
+              : "text-[var(--fg-primary)] disabled:opacity-40"
+              }`}
+            title="Bulk Edit Rules"
+            aria-label="Bulk edit rules"
+          >
+            <ListChecks className="h-4 w-4" strokeWidth={1.5} />
+          </button>
+
           <button
             className={`flex items-center justify-center p-1.5 rounded transition-colors ${isLinear
               ? "text-[var(--fg-secondary)] hover:bg-[var(--bg-subtle)] hover:text-[var(--fg-primary)] disabled:opacity-40 disabled:hover:bg-transparent"
@@ -575,6 +601,7 @@ function App() {
 
         {/* Right: Global Controls (Search, Activity Log, Stats, Settings) */}
         <div className="flex items-center gap-1 px-3">
+          <StartupIndicator />
           {/* Search */}
           {isSearchOpen ? (
             <div className="flex items-center gap-1">
@@ -880,6 +907,10 @@ function App() {
         open={isStatusOpen}
         onClose={() => setIsStatusOpen(false)}
       />
+      <BulkEditDialog
+        open={isBulkEditOpen}
+        onClose={() => setIsBulkEditOpen(false)}
+      />
       <ConfirmDialog
         isOpen={showDiscardConfirm}
         onClose={() => setShowDiscardConfirm(false)}
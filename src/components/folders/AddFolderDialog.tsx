@@ -1,9 +1,16 @@
 import { useRef, useState } from "react";
 import { createPortal } from "react-dom";
-import { open } from "@tauri-apps/plugin-dialog";
+import { open, save } from "@tauri-apps/plugin-dialog";
 import { FolderPlus, X } from "lucide-react";
 import type { ReactNode } from "react";
 
+import type { FolderTemplateInfo, PresetVariable } from "@/types";
+import {
+  folderTemplateApply,
+  folderTemplateExport,
+  folderTemplateImport,
+  folderTemplateList,
+} from "@/lib/tauri";
 import { useFolderStore } from "@/stores/folderStore";
 import { useFocusTrap } from "@/hooks/useFocusTrap";
 
@@ -21,9 +28,16 @@ export function AddFolderDialog({
   icon,
 }: AddFolderDialogProps) {
   const addFolder = useFolderStore((state) => state.addFolder);
+  const loadFolders = useFolderStore((state) => state.loadFolders);
   const [isOpen, setIsOpen] = useState(false);
   const [path, setPath] = useState("");
   const [name, setName] = useState("");
+  const [systemTrash, setSystemTrash] = useState(false);
+  const [templates, setTemplates] = useState<FolderTemplateInfo[]>([]);
+  const [templateId, setTemplateId] = useState("");
+  const [askedVariables, setAskedVariables] = useState<PresetVariable[]>([]);
+  const [variables, setVariables] = useState<Record<string, string>>({});
+  const [error, setError] = useState<string>();
   const dialogRef = useRef<HTMLDivElement>(null);
 
   useFocusTrap(isOpen, dialogRef);
@@ -37,12 +51,87 @@ export function AddFolderDialog({
     }
   };
 
+  const loadTemplates = async () => {
+    try {
+      setTemplates(await folderTemplateList());
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  const handleOpen = () => {
+    setIsOpen(true);
+    void loadTemplates();
+  };
+
+  const handleClose = () => {
+    setTemplateId("");
+    setSystemTrash(false);
+    setAskedVariables([]);
+    setVariables({});
+    setError(undefined);
+    setIsOpen(false);
+  };
+
+  const handleImportTemplate = async () => {
+    const selected = await open({
+      multiple: false,
+      filters: [{ name: "Folder template", extensions: ["json"] }],
+    });
+    if (typeof selected !== "string") return;
+    try {
+      const template = await folderTemplateImport(selected);
+      await loadTemplates();
+      setTemplateId(template.id);
+      setError(undefined);
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  const handleExportTemplate = async () => {
+    const template = templates.find((item) => item.id === templateId);
+    if (!template) return;
+    const destination = await save({
+      defaultPath: `${template.name}.json`,
+      filters: [{ name: "Folder template", extensions: ["json"] }],
+    });
+    if (!destination) return;
+    try {
+      await folderTemplateExport(template.id, destination);
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
   const handleSave = async () => {
+    if (systemTrash) {
+      if (!name) return;
+      await addFolder("", name, "systemTrash");
+      setName("");
+      handleClose();
+      return;
+    }
     if (!path || !name) return;
-    await addFolder(path, name);
+    if (!templateId) {
+      await addFolder(path, name);
+    } else {
+      try {
+        const applied = await folderTemplateApply(templateId, path, name, variables);
+        if (applied.status === "needsVariables") {
+          setAskedVariables(applied.variables);
+          setError(undefined);
+          return;
+        }
+        await loadFolders();
+      } catch (err) {
+        setError(String(err));
+        return;
+      }
+    }
     setPath("");
     setName("");
-    setIsOpen(false);
+    handleClose();
   };
 
   const modal =
@@ -52,7 +141,7 @@ export function AddFolderDialog({
             <button
               type="button"
               className="absolute inset-0 bg-black/40 backdrop-blur-sm"
-              onClick={() => setIsOpen(false)}
+              onClick={handleClose}
               aria-label="Close add folder dialog"
               tabIndex={-1}
             />
@@ -72,7 +161,7 @@ export function AddFolderDialog({
                  </div>
                  <button
                    className="rounded-[var(--radius)] p-1 text-[var(--fg-muted)] transition-colors hover:bg-[var(--bg-subtle)] hover:text-[var(--fg-primary)]"
-                   onClick={() => setIsOpen(false)}
+                   onClick={handleClose}
                    type="button"
                    aria-label="Close add folder dialog"
                  >
@@ -92,16 +181,29 @@ export function AddFolderDialog({
                         placeholder="Select a folder…"
                         value={path}
                         onChange={(e) => setPath(e.target.value)}
+                        disabled={systemTrash}
                         aria-label="Folder path"
                       />
                       <button
-                        className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-[11px] font-semibold text-[var(--fg-primary)] transition-colors hover:bg-[var(--bg-subtle)]"
+                        className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-[11px] font-semibold text-[var(--fg-primary)] transition-colors hover:bg-[var(--bg-subtle)] disabled:opacity-50"
                         onClick={handlePick}
+                        disabled={systemTrash}
                         type="button"
                       >
                         Browse
                       </button>
                     </div>
+                    <label className="mt-2 flex items-center gap-2 text-[11px] text-[var(--fg-secondary)]">
+                      <input
+                        type="checkbox"
+                        checked={systemTrash}
+                        onChange={(e) => {
+                          setSystemTrash(e.target.checked);
+                          if (e.target.checked && !name) setName("Trash");
+                        }}
+                      />
+                      The system trash instead — its rules can only purge or notify, and run every 15 minutes
+                    </label>
                   </div>
                   <div>
                     <label htmlFor="add-folder-name" className="text-[11px] font-semibold text-[var(--fg-secondary)]">
@@ -116,11 +218,69 @@ export function AddFolderDialog({
                       aria-label="Folder display name"
                     />
                   </div>
+                  <div>
+                    <label htmlFor="add-folder-template" className="text-[11px] font-semibold text-[var(--fg-secondary)]">
+                      Template
+                    </label>
+                    <div className="mt-2 flex gap-2">
+                      <select
+                        id="add-folder-template"
+                        className="w-full rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                        value={templateId}
+                        onChange={(e) => {
+                          setTemplateId(e.target.value);
+                          setAskedVariables([]);
+                        }}
+                      >
+                        <option value="">No template</option>
+                        {templates.map((template) => (
+                          <option key={template.id} value={template.id}>
+                            {template.name} ({template.ruleCount} rules)
+                          </option>
+                        ))}
+                      </select>
+                      <button
+                        className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-[11px] font-semibold text-[var(--fg-primary)] transition-colors hover:bg-[var(--bg-subtle)]"
+                        onClick={handleImportTemplate}
+                        type="button"
+                      >
+                        Import
+                      </button>
+                      {templateId ? (
+                        <button
+                          className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-[11px] font-semibold text-[var(--fg-primary)] transition-colors hover:bg-[var(--bg-subtle)]"
+                          onClick={handleExportTemplate}
+                          type="button"
+                        >
+                          Export
+                        </button>
+                      ) : null}
+                    </div>
+                  </div>
+                  {askedVariables.map((variable) => (
+                    <div key={variable.id}>
+                      <label
+                        htmlFor={`add-folder-var-${variable.id}`}
+                        className="text-[11px] font-semibold text-[var(--fg-secondary)]"
+                      >
+                        {variable.name}
+                      </label>
+                      <input
+                        id={`add-folder-var-${variable.id}`}
+                        className="mt-2 w-full rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] outline-none transition-colors placeholder:text-[var(--fg-muted)] focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                        value={variables[variable.id] ?? ""}
+                        onChange={(e) =>
+                          setVariables((current) => ({ ...current, [variable.id]: e.target.value }))
+                        }
+                      />
+                    </div>
+                  ))}
+                  {error ? <p className="text-[11px] text-red-500">{error}</p> : null}
                 </div>
                 <div className="mt-6 flex justify-end gap-3">
                   <button
                     className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-4 py-1.5 text-[11px] font-semibold text-[var(--fg-primary)] transition-colors hover:bg-[var(--bg-subtle)]"
-                    onClick={() => setIsOpen(false)}
+                    onClick={handleClose}
                     type="button"
                   >
                     Cancel
@@ -147,7 +307,7 @@ export function AddFolderDialog({
           className ??
           "flex items-center gap-2 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1.5 text-sm font-semibold text-[var(--fg-primary)] transition-colors hover:bg-[var(--bg-subtle)]"
         }
-        onClick={() => setIsOpen(true)}
+        onClick={handleOpen}
         type="button"
         aria-label={label || "Add folder"}
       >
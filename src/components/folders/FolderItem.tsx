@@ -1,8 +1,8 @@
-import { useState } from "react";
+import { useEffect, useRef, useState } from "react";
 import { Folder, Play, Loader2 } from "lucide-react";
 
 import type { Folder as FolderType } from "@/types";
-import { folderRunNow, type RunResult } from "@/lib/tauri";
+import { CommandFailure, commandCancel, folderRunNow, type RunResult } from "@/lib/tauri";
 import { Switch } from "@/components/ui/Switch";
 import { cn } from "@/lib/utils";
 import { useToastStore } from "@/stores/toastStore";
@@ -27,19 +27,34 @@ export function FolderItem({
   onDragStart,
 }: FolderItemProps) {
   const [running, setRunning] = useState(false);
+  const runTokenRef = useRef<string | null>(null);
   const addToast = useToastStore((state) => state.addToast);
 
+  // A scan still going when the folder leaves the list has no one to report to.
+  useEffect(
+    () => () => {
+      if (runTokenRef.current) {
+        void commandCancel(runTokenRef.current);
+      }
+    },
+    [],
+  );
+
   const handleRunNow = async (e: React.MouseEvent) => {
     e.stopPropagation();
     if (running) return;
 
     setRunning(true);
+    const requestToken = `run-${folder.id}-${Date.now()}`;
+    runTokenRef.current = requestToken;
     try {
-      const result: RunResult = await folderRunNow(folder.id);
+      const result: RunResult = await folderRunNow(folder.id, requestToken);
       addToast(
         {
-          title: "Run complete",
-          message: `Processed ${result.processed} files · Matched ${result.matched}`,
+          title: result.complete ? "Run complete" : "Run paused",
+          message: result.complete
+            ? `Processed ${result.processed} files · Matched ${result.matched}`
+            : `Processed ${result.processed} files · Matched ${result.matched} · The next run carries on`,
           variant: result.errors.length > 0 ? "error" : "success",
         },
         result.errors.length > 0 ? 6000 : 4000,
@@ -55,12 +70,14 @@ export function FolderItem({
         );
       }
     } catch (err) {
+      if (err instanceof CommandFailure && err.kind === "cancelled") return;
       addToast({
-        title: "Run failed",
+        title: err instanceof CommandFailure && err.kind === "busy" ? "Busy" : "Run failed",
         message: String(err),
         variant: "error",
       });
     } finally {
+      runTokenRef.current = null;
       setRunning(false);
     }
   };
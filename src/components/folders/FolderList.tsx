@@ -6,6 +6,7 @@ import { ChevronRight } from "lucide-react";
 import { useFolderStore } from "@/stores/folderStore";
 import { useRuleStore } from "@/stores/ruleStore";
 import { FolderItem } from "@/components/folders/FolderItem";
+import { QuickSetupPanel } from "@/components/folders/QuickSetupPanel";
 import type { Folder } from "@/types";
 import { cn } from "@/lib/utils";
 
@@ -198,9 +199,12 @@ export function FolderList() {
           {/* List */}
           <div className="flex flex-col gap-0.5" >
             {folders.length === 0 ? (
-              <div className="mx-2 mt-1 rounded-[var(--radius)] border border-dashed border-[var(--border-main)] px-4 py-6 text-center text-xs text-[var(--fg-muted)]">
-                No folders yet.
-              </div>
+              <>
+                <div className="mx-2 mt-1 rounded-[var(--radius)] border border-dashed border-[var(--border-main)] px-4 py-6 text-center text-xs text-[var(--fg-muted)]">
+                  No folders yet.
+                </div>
+                <QuickSetupPanel />
+              </>
             ) : (
               tree.map(node => renderNode(node))
             )}
@@ -2,12 +2,17 @@ import React, { useRef, useState } from "react";
 import { createPortal } from "react-dom";
 import { Settings, X, Trash2 } from "lucide-react";
 
-import type { Folder } from "@/types";
+import type { Folder, PlaceholderMode } from "@/types";
+import { folderTemplateSave } from "@/lib/tauri";
 import { useFolderStore } from "@/stores/folderStore";
+import { useToastStore } from "@/stores/toastStore";
 import { ConfirmDialog } from "@/components/ui/ConfirmDialog";
 import { Switch } from "@/components/ui/Switch";
 import { useFocusTrap } from "@/hooks/useFocusTrap";
 
+/** Passthrough patterns that would swallow every file in the folder. */
+const BROAD_PASSTHROUGH = ["*", "**", "**/*", "*.*"];
+
 type FolderOptionsTrigger = React.ReactElement<{ onClick?: React.MouseEventHandler }>;
 
 interface FolderOptionsDialogProps {
@@ -22,7 +27,18 @@ export function FolderOptionsDialog({ folder, trigger }: FolderOptionsDialogProp
   const [removeDuplicates, setRemoveDuplicates] = useState(folder.removeDuplicates);
   const [trashIncompleteDownloads, setTrashIncompleteDownloads] = useState(folder.trashIncompleteDownloads);
   const [incompleteTimeoutMinutes, setIncompleteTimeoutMinutes] = useState(folder.incompleteTimeoutMinutes);
+  const [placeholderMode, setPlaceholderMode] = useState<PlaceholderMode>(folder.placeholderMode);
+  const [strictSequential, setStrictSequential] = useState(folder.strictSequential);
+  const [groupWindowMs, setGroupWindowMs] = useState(folder.groupWindowMs);
+  const [passthrough, setPassthrough] = useState(folder.passthroughPatterns.join("\n"));
+  const [respectGit, setRespectGit] = useState(folder.respectGit);
+  const [skipGitTracked, setSkipGitTracked] = useState(folder.skipGitTracked);
+  const [activityThumbnails, setActivityThumbnails] = useState(folder.activityThumbnails);
+  const [trashMaxMb, setTrashMaxMb] = useState(folder.trashMaxMb);
+  const [showBroadPassthroughConfirm, setShowBroadPassthroughConfirm] = useState(false);
+  const [templateName, setTemplateName] = useState("");
   const [showDeleteConfirm, setShowDeleteConfirm] = useState(false);
+  const addToast = useToastStore((state) => state.addToast);
   const updateSettings = useFolderStore((state) => state.updateFolderSettings);
   const renameFolder = useFolderStore((state) => state.renameFolder);
   const removeFolder = useFolderStore((state) => state.removeFolder);
@@ -36,18 +52,60 @@ export function FolderOptionsDialog({ folder, trigger }: FolderOptionsDialogProp
     setOpen(false);
   };
 
-  const handleSave = async () => {
+  const handleSaveTemplate = async () => {
+    const templateLabel = templateName.trim();
+    if (!templateLabel) return;
+    try {
+      const template = await folderTemplateSave(folder.id, templateLabel);
+      addToast({
+        title: "Template saved",
+        message: `${template.name} · ${template.ruleCount} rules`,
+        variant: "success",
+      });
+      setTemplateName("");
+    } catch (err) {
+      addToast({ title: "Template not saved", message: String(err), variant: "error" });
+    }
+  };
+
+  const passthroughPatterns = passthrough
+    .split("\n")
+    .map((pattern) => pattern.trim())
+    .filter(Boolean);
+
+  const handleSave = async (confirmBroadPassthrough = false) => {
+    if (
+      !folder.isGroup &&
+      !confirmBroadPassthrough &&
+      passthroughPatterns.some((pattern) => BROAD_PASSTHROUGH.includes(pattern))
+    ) {
+      setShowBroadPassthroughConfirm(true);
+      return;
+    }
+
     if (name !== folder.name) {
       await renameFolder(folder.id, name);
     }
 
     if (!folder.isGroup) {
-      await updateSettings(folder.id, {
-        scanDepth,
-        removeDuplicates,
-        trashIncompleteDownloads,
-        incompleteTimeoutMinutes,
-      });
+      await updateSettings(
+        folder.id,
+        {
+          scanDepth,
+          removeDuplicates,
+          trashIncompleteDownloads,
+          incompleteTimeoutMinutes,
+          placeholderMode,
+          strictSequential,
+          groupWindowMs,
+          passthroughPatterns,
+          respectGit,
+          skipGitTracked: respectGit && skipGitTracked,
+          activityThumbnails,
+          trashMaxMb,
+        },
+        confirmBroadPassthrough,
+      );
     }
     setOpen(false);
   };
@@ -58,6 +116,14 @@ export function FolderOptionsDialog({ folder, trigger }: FolderOptionsDialogProp
     setRemoveDuplicates(folder.removeDuplicates);
     setTrashIncompleteDownloads(folder.trashIncompleteDownloads);
     setIncompleteTimeoutMinutes(folder.incompleteTimeoutMinutes);
+    setPlaceholderMode(folder.placeholderMode);
+    setStrictSequential(folder.strictSequential);
+    setGroupWindowMs(folder.groupWindowMs);
+    setPassthrough(folder.passthroughPatterns.join("\n"));
+    setRespectGit(folder.respectGit);
+    setSkipGitTracked(folder.skipGitTracked);
+    setActivityThumbnails(folder.activityThumbnails);
+    setTrashMaxMb(folder.trashMaxMb);
     setOpen(true);
   };
 
@@ -66,6 +132,14 @@ export function FolderOptionsDialog({ folder, trigger }: FolderOptionsDialogProp
     setRemoveDuplicates(folder.removeDuplicates);
     setTrashIncompleteDownloads(folder.trashIncompleteDownloads);
     setIncompleteTimeoutMinutes(folder.incompleteTimeoutMinutes);
+    setPlaceholderMode(folder.placeholderMode);
+    setStrictSequential(folder.strictSequential);
+    setGroupWindowMs(folder.groupWindowMs);
+    setPassthrough(folder.passthroughPatterns.join("\n"));
+    setRespectGit(folder.respectGit);
+    setSkipGitTracked(folder.skipGitTracked);
+    setActivityThumbnails(folder.activityThumbnails);
+    setTrashMaxMb(folder.trashMaxMb);
     setOpen(false);
   };
 
@@ -211,6 +285,198 @@ export function FolderOptionsDialog({ folder, trigger }: FolderOptionsDialogProp
                     />
                   </div>
                 </div>
+
+                {/* Cloud Placeholders */}
+                <div>
+                  <label htmlFor="folder-options-placeholders" className="block text-sm font-medium text-[var(--fg-primary)] mb-2">
+                    Online-only Files
+                  </label>
+                  <p className="text-xs text-[var(--fg-muted)] mb-3">
+                    How to handle cloud placeholders (OneDrive, Dropbox, iCloud) that haven't been downloaded
+                  </p>
+                  <select
+                    id="folder-options-placeholders"
+                    value={placeholderMode}
+                    onChange={(e) => setPlaceholderMode(e.target.value as PlaceholderMode)}
+                    className="w-full rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-2 text-sm text-[var(--fg-primary)] focus:border-[var(--accent)] focus:outline-none focus:ring-1 focus:ring-[var(--accent)]"
+                    disabled={loading}
+                  >
+                    <option value="skip">Skip</option>
+                    <option value="processMetadataOnly">Match by name, size and date only</option>
+                    <option value="hydrate">Download, then process</option>
+                  </select>
+                  <p className="text-xs text-[var(--fg-muted)] mt-2">
+                    {placeholderMode === "skip" && "Placeholders are ignored and logged as skipped"}
+                    {placeholderMode === "processMetadataOnly" &&
+                      "Contents conditions never match and placeholders are never moved or copied"}
+                    {placeholderMode === "hydrate" && "Waits for the sync client to download the file before running rules"}
+                  </p>
+                </div>
+
+                {/* Strict Sequential */}
+                <div className="flex items-center justify-between gap-4 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-3">
+                  <div>
+                    <div className="text-sm font-medium text-[var(--fg-primary)]">
+                      One file at a time
+                    </div>
+                    <p className="text-xs text-[var(--fg-muted)]">
+                      Process files strictly in arrival order, for rules that number files by what's already there
+                    </p>
+                  </div>
+                  <Switch
+                    checked={strictSequential}
+                    onCheckedChange={setStrictSequential}
+                    disabled={loading}
+                    ariaLabel="One file at a time"
+                  />
+                </div>
+
+                {/* Arrival Groups */}
+                <div className="flex items-center justify-between gap-4 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-3">
+                  <div>
+                    <label htmlFor="folder-options-group-window" className="text-sm font-medium text-[var(--fg-primary)]">
+                      Group files arriving together
+                    </label>
+                    <p className="text-xs text-[var(--fg-muted)]">
+                      Milliseconds between files that count as one arrival, like an email's attachments. 0 is off
+                    </p>
+                  </div>
+                  <input
+                    id="folder-options-group-window"
+                    className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)] disabled:opacity-50"
+                    type="number"
+                    min={0}
+                    max={60000}
+                    step={100}
+                    value={groupWindowMs}
+                    onChange={(e) => setGroupWindowMs(Math.max(0, Number(e.target.value)))}
+                    disabled={loading}
+                    aria-label="Group window in milliseconds"
+                  />
+                </div>
+
+                {/* Passthrough */}
+                <div>
+                  <label htmlFor="folder-options-passthrough" className="block text-sm font-medium text-[var(--fg-primary)] mb-2">
+                    Never touch
+                  </label>
+                  <p className="text-xs text-[var(--fg-muted)] mb-3">
+                    One pattern per line, like desktop.ini or *.sync-conflict-*. Unlike ignore patterns, these files
+                    leave no trace in the activity log or event journal
+                  </p>
+                  <textarea
+                    id="folder-options-passthrough"
+                    value={passthrough}
+                    onChange={(e) => setPassthrough(e.target.value)}
+                    rows={3}
+                    className="w-full rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-2 font-mono text-xs text-[var(--fg-primary)] focus:border-[var(--accent)] focus:outline-none focus:ring-1 focus:ring-[var(--accent)]"
+                    placeholder="desktop.ini"
+                    disabled={loading}
+                  />
+                </div>
+
+                {/* Git */}
+                <div className="space-y-3 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-3">
+                  <div className="flex items-center justify-between gap-4">
+                    <div>
+                      <div className="text-sm font-medium text-[var(--fg-primary)]">Git working tree</div>
+                      <p className="text-xs text-[var(--fg-muted)]">
+                        Never touch .git, and hold files while git is in the middle of a checkout, merge or rebase
+                      </p>
+                    </div>
+                    <Switch
+                      checked={respectGit}
+                      onCheckedChange={setRespectGit}
+                      disabled={loading}
+                      ariaLabel="Git working tree"
+                    />
+                  </div>
+                  {respectGit ? (
+                    <div className="flex items-center justify-between gap-4">
+                      <div>
+                        <div className="text-sm font-medium text-[var(--fg-primary)]">Leave tracked files alone</div>
+                        <p className="text-xs text-[var(--fg-muted)]">
+                          Only files git doesn't track are processed
+                        </p>
+                      </div>
+                      <Switch
+                        checked={skipGitTracked}
+                        onCheckedChange={setSkipGitTracked}
+                        disabled={loading}
+                        ariaLabel="Leave tracked files alone"
+                      />
+                    </div>
+                  ) : null}
+                </div>
+
+                {/* Thumbnails */}
+                <div className="flex items-center justify-between gap-4 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-3">
+                  <div>
+                    <div className="text-sm font-medium text-[var(--fg-primary)]">Thumbnails in activity</div>
+                    <p className="text-xs text-[var(--fg-muted)]">
+                      Show a small picture of each image or PDF a rule acts on
+                    </p>
+                  </div>
+                  <Switch
+                    checked={activityThumbnails}
+                    onCheckedChange={setActivityThumbnails}
+                    disabled={loading}
+                    ariaLabel="Thumbnails in activity"
+                  />
+                </div>
+
+                {/* Trash Size Limit */}
+                {folder.kind === "systemTrash" ? (
+                  <div className="flex items-center justify-between gap-4 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-3">
+                    <div>
+                      <label htmlFor="folder-options-trash-max" className="text-sm font-medium text-[var(--fg-primary)]">
+                        Keep the trash under
+                      </label>
+                      <p className="text-xs text-[var(--fg-muted)]">
+                        Megabytes; past this, items deleted longest ago are purged. 0 is no limit
+                      </p>
+                    </div>
+                    <input
+                      id="folder-options-trash-max"
+                      className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)] disabled:opacity-50"
+                      type="number"
+                      min={0}
+                      step={100}
+                      value={trashMaxMb}
+                      onChange={(e) => setTrashMaxMb(Math.max(0, Number(e.target.value)))}
+                      disabled={loading}
+                      aria-label="Trash size limit in megabytes"
+                    />
+                  </div>
+                ) : null}
+
+                {/* Template */}
+                <div className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-3">
+                  <label htmlFor="folder-options-template-name" className="text-sm font-medium text-[var(--fg-primary)]">
+                    Save as template
+                  </label>
+                  <p className="text-xs text-[var(--fg-muted)]">
+                    Keep these options and rules to set up another folder the same way
+                  </p>
+                  <div className="mt-2 flex gap-2">
+                    <input
+                      id="folder-options-template-name"
+                      className="w-full rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors placeholder:text-[var(--fg-muted)] focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)] disabled:opacity-50"
+                      placeholder="Template name"
+                      value={templateName}
+                      onChange={(e) => setTemplateName(e.target.value)}
+                      disabled={loading}
+                    />
+                    <button
+                      onClick={handleSaveTemplate}
+                      className="shrink-0 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-xs font-semibold text-[var(--fg-primary)] transition-colors hover:bg-[var(--bg-subtle)] disabled:opacity-50"
+                      disabled={loading || !templateName.trim()}
+                      type="button"
+                    >
+                      Save template
+                    </button>
+                  </div>
+                </div>
               </>
             )}
 
@@ -246,7 +512,7 @@ export function FolderOptionsDialog({ folder, trigger }: FolderOptionsDialogProp
               Cancel
             </button>
             <button
-              onClick={handleSave}
+              onClick={() => handleSave()}
               className="rounded-[var(--radius)] bg-[var(--accent)] px-4 py-2 text-sm font-semibold text-[var(--accent-contrast)] hover:opacity-90 disabled:opacity-50"
               disabled={loading}
             >
@@ -294,6 +560,16 @@ export function FolderOptionsDialog({ folder, trigger }: FolderOptionsDialogProp
         confirmLabel="Remove"
         variant="danger"
       />
+
+      <ConfirmDialog
+        isOpen={showBroadPassthroughConfirm}
+        onClose={() => setShowBroadPassthroughConfirm(false)}
+        onConfirm={() => handleSave(true)}
+        title="Hide every file?"
+        message={`A "Never touch" pattern matches everything, so no rule will run in "${folder.name}" and nothing will be logged.`}
+        confirmLabel="Save anyway"
+        variant="warning"
+      />
     </>
   );
 }
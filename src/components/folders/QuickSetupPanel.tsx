@@ -0,0 +1,112 @@
+import { useEffect, useState } from "react";
+
+import { BUILTIN_TEMPLATES } from "@/data/templates";
+import { quickSetupApply, quickSetupSuggestions } from "@/lib/tauri";
+import { useFolderStore } from "@/stores/folderStore";
+import type { QuickSetupSuggestion } from "@/types";
+
+function formatBytes(bytes: number) {
+  if (!bytes) return "0 B";
+  const units = ["B", "KB", "MB", "GB", "TB"];
+  let size = bytes;
+  let unitIndex = 0;
+  while (size >= 1024 && unitIndex < units.length - 1) {
+    size /= 1024;
+    unitIndex += 1;
+  }
+  return `${size.toFixed(size >= 10 || unitIndex === 0 ? 0 : 1)} ${units[unitIndex]}`;
+}
+
+function presetDefaults(presetId: string) {
+  const template = BUILTIN_TEMPLATES.find((t) => t.id === presetId);
+  if (!template) return null;
+  const variables: Record<string, string> = {};
+  template.preset.variables.forEach((v) => {
+    if (v.default) variables[v.id] = v.default;
+  });
+  return { preset: template.preset, variables };
+}
+
+/** Offers the user's standard folders, with starter templates, on first run. */
+export function QuickSetupPanel() {
+  const loadFolders = useFolderStore((state) => state.loadFolders);
+  const [suggestions, setSuggestions] = useState<QuickSetupSuggestion[]>([]);
+  const [selected, setSelected] = useState<Record<string, boolean>>({});
+  const [applying, setApplying] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    quickSetupSuggestions()
+      .then((found) => {
+        setSuggestions(found);
+        setSelected(Object.fromEntries(found.map((s) => [s.path, true])));
+      })
+      .catch(() => setSuggestions([]));
+  }, []);
+
+  if (suggestions.length === 0) return null;
+
+  const chosen = suggestions.filter((s) => selected[s.path]);
+
+  const handleApply = async () => {
+    setApplying(true);
+    setError(null);
+    try {
+      await quickSetupApply(
+        chosen.map((s) => ({
+          path: s.path,
+          name: s.name,
+          presets: s.presetIds.flatMap((id) => presetDefaults(id) ?? []),
+        })),
+      );
+      await loadFolders();
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setApplying(false);
+    }
+  };
+
+  return (
+    <div className="mx-2 mt-2 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-3 text-xs">
+      <div className="font-semibold text-[var(--fg-primary)]">Quick setup</div>
+      <p className="mt-1 text-[11px] text-[var(--fg-muted)]">
+        Watch these folders with starter rules. You can change the rules afterwards.
+      </p>
+      <div className="mt-2 flex flex-col gap-1.5">
+        {suggestions.map((s) => (
+          <label key={s.path} className="flex cursor-pointer items-start gap-2">
+            <input
+              type="checkbox"
+              className="mt-0.5"
+              checked={selected[s.path] ?? false}
+              onChange={(e) => setSelected((prev) => ({ ...prev, [s.path]: e.target.checked }))}
+            />
+            <span className="min-w-0">
+              <span className="block truncate text-[var(--fg-primary)]" title={s.path}>
+                {s.name}
+              </span>
+              <span className="block text-[10px] text-[var(--fg-muted)]">
+                {s.fileCount}
+                {s.estimateCapped ? "+" : ""} files · {formatBytes(s.totalBytes)}
+                {s.presetIds.length > 0 &&
+                  ` · ${s.presetIds
+                    .map((id) => BUILTIN_TEMPLATES.find((t) => t.id === id)?.name ?? id)
+                    .join(", ")}`}
+              </span>
+            </span>
+          </label>
+        ))}
+      </div>
+      {error && <div className="mt-2 text-[11px] text-[var(--fg-alert)]">{error}</div>}
+      <button
+        type="button"
+        className="mt-3 w-full rounded-[var(--radius)] bg-[var(--accent)] px-3 py-1 text-[11px] font-semibold text-[var(--accent-contrast)] transition-colors disabled:opacity-50"
+        disabled={applying || chosen.length === 0}
+        onClick={handleApply}
+      >
+        {applying ? "Setting up…" : `Set up ${chosen.length} folder${chosen.length === 1 ? "" : "s"}`}
+      </button>
+    </div>
+  );
+}
@@ -7,13 +7,16 @@ import {
   ArrowRightLeft,
   Ban,
   Bell,
+  Check,
   FileDown,
   ExternalLink,
   FolderOpen,
   RotateCcw,
   Search,
+  Tag,
   Terminal,
   Trash2,
+  X,
 } from "lucide-react";
 import { useVirtualizer } from "@tanstack/react-virtual";
 import { openPath } from "@tauri-apps/plugin-opener";
@@ -23,7 +26,8 @@ import { writeTextFile } from "@tauri-apps/plugin-fs";
 import { useFolderStore } from "@/stores/folderStore";
 import { useLogStore } from "@/stores/logStore";
 import { useRuleStore } from "@/stores/ruleStore";
-import type { LogEntry, LogStatus } from "@/types";
+import { ruleMatchesExport, thumbnailRead } from "@/lib/tauri";
+import type { LogEntry, LogStatus, ManifestFormat } from "@/types";
 import { MagiSelect } from "@/components/ui/MagiSelect";
 
 interface ActivityLogProps {
@@ -35,7 +39,17 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
   const entries = useLogStore((state) => state.entries);
   const undoEntries = useLogStore((state) => state.undoEntries);
   const undoAction = useLogStore((state) => state.undoAction);
+  const approvals = useLogStore((state) => state.approvals);
+  const executeApproval = useLogStore((state) => state.executeApproval);
+  const dismissApproval = useLogStore((state) => state.dismissApproval);
+  const conflicts = useLogStore((state) => state.conflicts);
+  const resolveConflict = useLogStore((state) => state.resolveConflict);
+  const [otherNames, setOtherNames] = useState<Record<string, string>>({});
   const clearLogs = useLogStore((state) => state.clearLogs);
+  const acknowledgeErrors = useLogStore((state) => state.acknowledgeErrors);
+  const unreviewed = useLogStore((state) => state.unreviewed);
+  const loadUnreviewed = useLogStore((state) => state.loadUnreviewed);
+  const markReviewed = useLogStore((state) => state.markReviewed);
   const selectedFolderId = useFolderStore((state) => state.selectedFolderId);
   const rules = useRuleStore((state) => state.rules);
   const [query, setQuery] = useState(() => {
@@ -63,6 +77,31 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
 
   const deferredQuery = useDeferredValue(query);
 
+  // Looking at the errors takes them off the tray badge.
+  const showingErrors = onlyErrors || statusFilter === "error";
+  const errorCount = entries.filter((entry) => entry.status === "error").length;
+  useEffect(() => {
+    if (showingErrors) void acknowledgeErrors();
+  }, [showingErrors, errorCount, acknowledgeErrors]);
+
+  // What came in since the last look is summarized when the view comes into
+  // focus, and counts as seen once it loses focus.
+  useEffect(() => {
+    void loadUnreviewed();
+    const onFocus = () => void loadUnreviewed();
+    const onBlur = () => void markReviewed();
+    window.addEventListener("focus", onFocus);
+    window.addEventListener("blur", onBlur);
+    return () => {
+      window.removeEventListener("focus", onFocus);
+      window.removeEventListener("blur", onBlur);
+      void markReviewed();
+    };
+  }, [loadUnreviewed, markReviewed]);
+  const unreviewedTotal = unreviewed
+    ? unreviewed.counts.success + unreviewed.counts.error + unreviewed.counts.skipped + unreviewed.counts.warning
+    : 0;
+
   useEffect(() => {
     try {
       window.localStorage.setItem("filedispatch.logQuery", query);
@@ -127,6 +166,38 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
     }
   }, [filteredEntries]);
 
+  const manifestRuleId = useMemo(() => {
+    if (ruleFilter === "all") return null;
+    return scopedEntries.find((entry) => entry.ruleName === ruleFilter)?.ruleId ?? null;
+  }, [scopedEntries, ruleFilter]);
+
+  const handleManifestExport = useCallback(async () => {
+    if (!manifestRuleId) return;
+    try {
+      const rangeMs =
+        timeRange === "1h" ? 60 * 60 * 1000 :
+        timeRange === "24h" ? 24 * 60 * 60 * 1000 :
+        timeRange === "7d" ? 7 * 24 * 60 * 60 * 1000 :
+        null;
+      const since = rangeMs === null ? undefined : new Date(Date.now() - rangeMs).toISOString();
+      const path = await save({
+        defaultPath: `${ruleFilter}-matches.csv`,
+        filters: [
+          { name: "CSV", extensions: ["csv"] },
+          { name: "JSON lines", extensions: ["jsonl"] },
+          { name: "Playlist", extensions: ["m3u"] },
+        ],
+      });
+      if (!path) return;
+      const extension = path.split(".").pop()?.toLowerCase();
+      const format: ManifestFormat =
+        extension === "jsonl" ? "jsonl" : extension === "m3u" ? "m3u" : "csv";
+      await ruleMatchesExport(manifestRuleId, format, { since, path });
+    } catch {
+      // ignore export errors for now
+    }
+  }, [manifestRuleId, ruleFilter, timeRange]);
+
   const undoByLog = useMemo(() => {
     return new Map(undoEntries.map((entry) => [entry.logId, entry]));
   }, [undoEntries]);
@@ -140,10 +211,16 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
     overscan: 5, // Render 5 extra items above/below viewport
   });
 
+  const availableUndoCount = useMemo(
+    () => undoEntries.filter((entry) => entry.status === "available").length,
+    [undoEntries],
+  );
+
   const handleUndo = useCallback((entryId: string) => {
     const undoEntry = undoByLog.get(entryId);
     if (undoEntry) {
-      void undoAction(undoEntry.id);
+      // Changed files need an explicit override so newer edits aren't lost silently.
+      void undoAction(undoEntry.id, { force: undoEntry.status === "changed" });
     }
   }, [undoByLog, undoAction]);
 
@@ -197,6 +274,7 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
                   { label: "Status: All", value: "all" },
                   { label: "Success", value: "success" },
                   { label: "Error", value: "error" },
+                  { label: "Warning", value: "warning" },
                   { label: "Skipped", value: "skipped" }
                ]}
                ariaLabel="Filter by status"
@@ -236,6 +314,17 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
             <FileDown className="h-3 w-3" />
             Export
           </button>
+          {manifestRuleId ? (
+            <button
+              type="button"
+              onClick={handleManifestExport}
+              title="Save a manifest of the files this rule matched in the time range, with where each is now"
+              className="inline-flex items-center gap-1 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[10px] font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:bg-[var(--bg-subtle)] hover:text-[var(--fg-primary)]"
+            >
+              <FileDown className="h-3 w-3" />
+              Export matches
+            </button>
+          ) : null}
           {onToggleExpand ? (
             <button
               onClick={onToggleExpand}
@@ -245,14 +334,155 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
               {expanded ? "Minimize" : "Expand"}
             </button>
           ) : null}
-          {undoEntries.length > 0 ? (
+          {availableUndoCount > 0 ? (
             <div className="rounded-full border border-[var(--border-main)] bg-[var(--bg-subtle)] px-2 py-1 text-[10px] font-semibold text-[var(--fg-secondary)]">
-              Undo available: {undoEntries.length}
+              Undo available: {availableUndoCount}
             </div>
           ) : null}
         </div>
       </div>
 
+      {unreviewed && (unreviewedTotal > 0 || unreviewed.prunedBeforeReview > 0) ? (
+        <div className="border-b border-[var(--border-main)] bg-[var(--bg-subtle)] px-4 py-2 text-xs">
+          <div className="mb-1 text-[10px] font-semibold uppercase tracking-wider text-[var(--fg-secondary)]">
+            Since you last looked
+            {unreviewed.since ? ` (${new Date(unreviewed.since).toLocaleString()})` : ""}
+          </div>
+          <div className="text-[var(--fg-primary)]">
+            {unreviewedTotal} new: {unreviewed.counts.success} done, {unreviewed.counts.error} failed,{" "}
+            {unreviewed.counts.skipped} skipped
+            {unreviewed.counts.warning > 0 ? `, ${unreviewed.counts.warning} with warnings` : ""}
+          </div>
+          {unreviewed.rulesWithErrors.length > 0 ? (
+            <div className="text-[var(--fg-secondary)]">
+              Errors in:{" "}
+              {unreviewed.rulesWithErrors
+                .map((rule) => `${rule.ruleName ?? "No rule"} (${rule.counts.error})`)
+                .join(", ")}
+            </div>
+          ) : null}
+          {unreviewed.recentErrors.map((entry) => (
+            <div key={entry.id} className="truncate text-[10px] text-[var(--fg-muted)]" title={entry.filePath}>
+              {entry.filePath.split(/[/\\]/).pop() ?? entry.filePath}: {entry.errorMessage ?? "Failed"}
+            </div>
+          ))}
+          {unreviewed.prunedBeforeReview > 0 ? (
+            <div className="text-[10px] text-[var(--fg-muted)]">
+              {unreviewed.prunedBeforeReview} older entries were cleaned up before you saw them.
+            </div>
+          ) : null}
+        </div>
+      ) : null}
+
+      {approvals.length > 0 ? (
+        <div className="custom-scrollbar max-h-40 overflow-y-auto border-b border-[var(--border-main)] bg-[var(--bg-subtle)] px-4 py-2">
+          <div className="mb-1 text-[10px] font-semibold uppercase tracking-wider text-[var(--fg-secondary)]">
+            Awaiting approval ({approvals.length})
+          </div>
+          {approvals.map((approval) => {
+            const fileName = approval.filePath.split(/[/\\]/).pop() ?? approval.filePath;
+            const plan = approval.planned
+              .map((step) =>
+                step.destinationPath
+                  ? `${humanizeAction(step.actionType)} → ${step.destinationPath}`
+                  : humanizeAction(step.actionType),
+              )
+              .join(", ");
+            return (
+              <div key={approval.id} className="flex items-center gap-3 py-1 text-xs">
+                <div className="min-w-0 flex-1">
+                  <div className="truncate text-[var(--fg-primary)]" title={approval.filePath}>
+                    {fileName}
+                    <span className="ml-2 text-[var(--fg-muted)]">{approval.ruleName}</span>
+                  </div>
+                  <div className="truncate text-[10px] text-[var(--fg-muted)]" title={plan}>
+                    {plan}
+                  </div>
+                </div>
+                <button
+                  type="button"
+                  onClick={() => void executeApproval(approval.id)}
+                  className="inline-flex items-center gap-1 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[10px] font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)]"
+                >
+                  <Check className="h-3 w-3" />
+                  Run
+                </button>
+                <button
+                  type="button"
+                  onClick={() => void dismissApproval(approval.id)}
+                  className="inline-flex items-center gap-1 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[10px] font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)]"
+                >
+                  <X className="h-3 w-3" />
+                  Dismiss
+                </button>
+              </div>
+            );
+          })}
+        </div>
+      ) : null}
+
+      {conflicts.length > 0 ? (
+        <div className="custom-scrollbar max-h-40 overflow-y-auto border-b border-[var(--border-main)] bg-[var(--bg-subtle)] px-4 py-2">
+          <div className="mb-1 text-[10px] font-semibold uppercase tracking-wider text-[var(--fg-secondary)]">
+            Waiting on a conflict ({conflicts.length})
+          </div>
+          {conflicts.map((conflict) => {
+            const fileName = conflict.source.path.split(/[/\\]/).pop() ?? conflict.source.path;
+            const otherName = otherNames[conflict.id] ?? "";
+            const sides = `${formatBytes(conflict.source.size)} → ${conflict.destination.path} (${formatBytes(conflict.destination.size)} already there)`;
+            return (
+              <div key={conflict.id} className="flex items-center gap-2 py-1 text-xs">
+                <div className="min-w-0 flex-1">
+                  <div className="truncate text-[var(--fg-primary)]" title={conflict.source.path}>
+                    {fileName}
+                    <span className="ml-2 text-[var(--fg-muted)]">{conflict.ruleName}</span>
+                  </div>
+                  <div className="truncate text-[10px] text-[var(--fg-muted)]" title={sides}>
+                    {humanizeAction(conflict.actionType)}: {sides}
+                  </div>
+                </div>
+                <button
+                  type="button"
+                  onClick={() => void resolveConflict(conflict.id, { type: "replace" })}
+                  className="inline-flex items-center gap-1 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[10px] font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)]"
+                >
+                  Replace
+                </button>
+                <button
+                  type="button"
+                  onClick={() => void resolveConflict(conflict.id, { type: "rename" })}
+                  className="inline-flex items-center gap-1 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[10px] font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)]"
+                >
+                  Keep both
+                </button>
+                <input
+                  value={otherName}
+                  onChange={(e) => setOtherNames((names) => ({ ...names, [conflict.id]: e.target.value }))}
+                  placeholder="Other name"
+                  className="w-28 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[10px] text-[var(--fg-primary)]"
+                />
+                <button
+                  type="button"
+                  disabled={!otherName.trim()}
+                  onClick={() => void resolveConflict(conflict.id, { type: "useOtherName", value: otherName.trim() })}
+                  className="inline-flex items-center gap-1 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[10px] font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)] disabled:opacity-50"
+                >
+                  Use name
+                </button>
+                <button
+                  type="button"
+                  onClick={() => void resolveConflict(conflict.id, { type: "skip" })}
+                  className="inline-flex items-center gap-1 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[10px] font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)]"
+                >
+                  <X className="h-3 w-3" />
+                  Skip
+                </button>
+              </div>
+            );
+          })}
+        </div>
+      ) : null}
+
       <div className="flex flex-1 flex-col overflow-hidden bg-[var(--bg-panel)]">
         <div className="flex items-center border-b border-[var(--border-main)] bg-[var(--bg-subtle)] px-4 py-2 text-[10px] font-semibold uppercase tracking-wider text-[var(--fg-secondary)]">
           <div className="w-24">Time</div>
@@ -279,6 +509,7 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
                 const entry = filteredEntries[virtualRow.index];
                 const visual = getActionVisual(entry.actionType);
                 const Icon = visual.icon;
+                const undoEntry = undoByLog.get(entry.id);
                 return (
                   <div
                     key={entry.id}
@@ -299,11 +530,13 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
                     </div>
                     <div className="flex-1 pr-4">
                       <div className="flex items-center gap-3">
-                        <span
-                          className={`inline-flex h-6 w-6 items-center justify-center rounded-[var(--radius)] border border-[var(--border-main)] ${visual.className}`}
-                        >
-                          <Icon className="h-3.5 w-3.5" />
-                        </span>
+                        <LogThumbnail path={entry.actionDetail?.metadata?.thumbnail}>
+                          <span
+                            className={`inline-flex h-6 w-6 items-center justify-center rounded-[var(--radius)] border border-[var(--border-main)] ${visual.className}`}
+                          >
+                            <Icon className="h-3.5 w-3.5" />
+                          </span>
+                        </LogThumbnail>
                         <div className="min-w-0 flex flex-col justify-center">
                           <div className="truncate font-semibold">{formatOperation(entry)}</div>
                           <div className="mt-0.5 text-[10px] text-[var(--fg-muted)]">
@@ -336,15 +569,23 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
                       </div>
                     </div>
                     <div className="w-20 text-right">
-                      {undoByLog.has(entry.id) ? (
+                      {undoEntry?.status === "expired" || undoEntry?.status === "missing" ? (
+                        <span
+                          className="text-[10px] font-semibold text-[var(--fg-muted)]"
+                          title={undoEntry.statusReason}
+                        >
+                          {undoEntry.status === "expired" ? "Expired" : "Missing"}
+                        </span>
+                      ) : undoEntry ? (
                         <button
                           className="inline-flex items-center gap-1 rounded-[var(--radius)] border border-[var(--border-main)] px-2 py-0.5 text-[10px] font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:bg-[var(--bg-subtle)] hover:text-[var(--fg-primary)]"
                           onClick={() => handleUndo(entry.id)}
                           type="button"
                           aria-label="Undo action"
+                          title={undoEntry.statusReason}
                         >
                           <RotateCcw className="h-3 w-3" />
-                          Undo
+                          {undoEntry.status === "changed" ? "Force" : "Undo"}
                         </button>
                       ) : (
                         <span className="text-[10px] opacity-30">—</span>
@@ -376,6 +617,40 @@ export function ActivityLog({ onToggleExpand, expanded = false }: ActivityLogPro
   );
 }
 
+/** Thumbnails already read, by cache path; `null` once one is known to be gone. */
+const thumbnailUrls = new Map<string, string | null>();
+
+/** The entry's thumbnail when it has one still in the cache, else `children`. */
+function LogThumbnail({ path, children }: { path?: string; children: React.ReactNode }) {
+  const [url, setUrl] = useState(() => (path ? thumbnailUrls.get(path) : null) ?? null);
+
+  useEffect(() => {
+    if (!path || thumbnailUrls.has(path)) {
+      setUrl(path ? thumbnailUrls.get(path) ?? null : null);
+      return;
+    }
+    let cancelled = false;
+    thumbnailRead(path)
+      .catch(() => null)
+      .then((read) => {
+        thumbnailUrls.set(path, read);
+        if (!cancelled) setUrl(read);
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [path]);
+
+  if (!url) return <>{children}</>;
+  return (
+    <img
+      src={url}
+      alt=""
+      className="h-6 w-6 shrink-0 rounded-[var(--radius)] border border-[var(--border-main)] object-cover"
+    />
+  );
+}
+
 function formatTime(value: string) {
   const date = new Date(value);
   if (Number.isNaN(date.getTime())) return "";
@@ -387,6 +662,12 @@ function formatOperation(entry: LogEntry) {
   const destination = entry.actionDetail?.destinationPath;
   const action = humanizeAction(entry.actionType);
 
+  if (entry.actionType === "digest") {
+    return entry.errorMessage ?? action;
+  }
+  if ((entry.actionType === "unreadable" || entry.actionType === "reappeared") && entry.errorMessage) {
+    return `${action} → ${fileName} (${entry.errorMessage})`;
+  }
   if (destination) {
     return `${action} → ${destination}`;
   }
@@ -401,6 +682,18 @@ function formatRuleLabel(entry: LogEntry) {
   if (entry.actionType === "undo") {
     return "Undo action";
   }
+  if (entry.actionType === "placeholder") {
+    return "Cloud placeholder";
+  }
+  if (entry.actionType === "digest") {
+    return "Daily digest";
+  }
+  if (entry.actionType === "unreadable") {
+    return "File check";
+  }
+  if (entry.actionType === "backup" || entry.actionType === "restore") {
+    return "Backups";
+  }
   return "Manual action";
 }
 
@@ -441,6 +734,13 @@ function StatusPill({ status, label }: { status: LogStatus; label: string }) {
       </span>
     );
   }
+  if (status === "warning") {
+    return (
+      <span className="inline-flex items-center rounded-full border border-[var(--fg-alert)] px-2 py-0.5 text-[10px] font-semibold text-[var(--fg-alert)]">
+        {label}
+      </span>
+    );
+  }
   return (
     <span className="inline-flex items-center rounded-full border border-[var(--border-main)] px-2 py-0.5 text-[10px] font-semibold text-[var(--fg-muted)]">
       {label}
@@ -471,8 +771,18 @@ function getActionVisual(actionType: string): { icon: LucideIcon; className: str
         return { icon: Terminal, className: success };
     case "notify":
         return { icon: Bell, className: success };
+    case "setTags":
+        return { icon: Tag, className: neutral };
+    case "digest":
+        return { icon: Bell, className: neutral };
+    case "backup":
+    case "restore":
+        return { icon: Archive, className: neutral };
     case "pause":
     case "ignore":
+    case "placeholder":
+    case "unreadable":
+    case "reappeared":
         return { icon: Ban, className: dim };
     default:
         return { icon: Activity, className: neutral };
@@ -511,6 +821,26 @@ function humanizeAction(value: string) {
       return "Undo";
     case "ignore":
       return "Ignore";
+    case "placeholder":
+      return "Online-only";
+    case "retry":
+      return "Retry";
+    case "digest":
+      return "Digest";
+    case "unreadable":
+      return "Not processed";
+    case "reappeared":
+      return "Came back";
+    case "backup":
+      return "Back up";
+    case "restore":
+      return "Restore";
+    case "createFolderStructure":
+      return "Create Folders";
+    case "dispatchToFolder":
+      return "Dispatch";
+    case "setTags":
+      return "Tag";
     default:
       return value;
   }
@@ -14,16 +14,28 @@ interface PreviewPanelProps {
   error: string | null;
   ruleName: string;
   conditionLabels?: string[];
+  /** Shown beside the counts, e.g. when the rule changed since the preview ran. */
+  warning?: string | null;
 }
 
-export function PreviewPanel({ open, onClose, results, loading, error, ruleName, conditionLabels = [] }: PreviewPanelProps) {
+export function PreviewPanel({
+  open,
+  onClose,
+  results,
+  loading,
+  error,
+  ruleName,
+  conditionLabels = [],
+  warning,
+}: PreviewPanelProps) {
   const dialogRef = useRef<HTMLDivElement>(null);
   useFocusTrap(open, dialogRef);
 
   if (!open) return null;
 
   const matched = results.filter((item) => item.matched).length;
-  const unmatched = results.length - matched;
+  const outOfScope = results.filter((item) => item.outOfScope).length;
+  const unmatched = results.length - matched - outOfScope;
 
   const modal =
     typeof document !== "undefined"
@@ -90,11 +102,21 @@ export function PreviewPanel({ open, onClose, results, loading, error, ruleName,
                       <span className="rounded-full border border-[var(--border-main)] bg-[var(--bg-subtle)] px-3 py-1 text-[var(--fg-muted)]">
                         ✗ {unmatched} no match
                       </span>
+                      {outOfScope > 0 && (
+                        <span className="rounded-full border border-[var(--border-main)] bg-[var(--bg-subtle)] px-3 py-1 text-[var(--fg-muted)]">
+                          – {outOfScope} out of scope
+                        </span>
+                      )}
                       {results.length >= 50 && (
                         <span className="rounded-full border border-[var(--fg-alert)] bg-[var(--fg-alert)]/10 px-3 py-1 text-[var(--fg-alert)]">
                           ⚠ Limited to 50 files
                         </span>
                       )}
+                      {warning && (
+                        <span className="rounded-full border border-[var(--fg-alert)] bg-[var(--fg-alert)]/10 px-3 py-1 text-[var(--fg-alert)]">
+                          ⚠ {warning}
+                        </span>
+                      )}
                     </div>
                     {results.map((item) => (
                       <GlassCard key={item.filePath} className="p-4">
@@ -126,6 +148,13 @@ export function PreviewPanel({ open, onClose, results, loading, error, ruleName,
                                   ))}
                                 </div>
                               ) : null}
+                              {item.notes && item.notes.length > 0 ? (
+                                <div className="space-y-1 text-[11px] italic text-[var(--fg-muted)]">
+                                  {item.notes.map((note, idx) => (
+                                    <div key={idx}>{note}</div>
+                                  ))}
+                                </div>
+                              ) : null}
                             </div>
                           </div>
                           <span
@@ -135,7 +164,11 @@ export function PreviewPanel({ open, onClose, results, loading, error, ruleName,
                                 : "text-[var(--fg-muted)]"
                             }`}
                           >
-                            {item.matched ? "Matched" : "No match"}
+                            {item.matched
+                              ? "Matched"
+                              : item.outOfScope
+                                ? "Out of scope"
+                                : "No match"}
                           </span>
                         </div>
                       </GlassCard>
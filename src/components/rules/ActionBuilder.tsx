@@ -3,10 +3,23 @@ import { AlertTriangle, FolderOpen, GripVertical, Plus, X } from "lucide-react";
 import { open as openFolderDialog } from "@tauri-apps/plugin-dialog";
 import { MagiSelect } from "@/components/ui/MagiSelect";
 import { ConfirmDialog } from "@/components/ui/ConfirmDialog";
+import { useFolderStore } from "@/stores/folderStore";
 import { useSettingsStore } from "@/stores/settingsStore";
 
 
-import type { Action, ArchiveFormat, ConflictResolution } from "@/types";
+import type {
+  Action,
+  ArchiveFormat,
+  AutoPartition,
+  BringCompanions,
+  ConflictResolution,
+  DateGranularity,
+  DestinationPermissions,
+  PasswordSource,
+  QuarantineHandling,
+  ReflinkMode,
+  TagMode,
+} from "@/types";
 
 interface ActionBuilderProps {
   actions: Action[];
@@ -28,6 +41,9 @@ const actionTypes = [
   { value: "openWith", label: "Open With…" },
   { value: "showInFileManager", label: "Show in File Manager" },
   { value: "makePdfSearchable", label: "Make PDF Searchable (OCR)" },
+  { value: "createFolderStructure", label: "Create Folder Structure" },
+  { value: "dispatchToFolder", label: "Dispatch to Folder" },
+  { value: "setTags", label: "Set Tags" },
   { value: "pause", label: "Pause" },
   { value: "continue", label: "Continue Matching Rules" },
   { value: "ignore", label: "Ignore" },
@@ -37,8 +53,31 @@ const conflictOptions: { value: ConflictResolution; label: string }[] = [
   { value: "rename", label: "Rename" },
   { value: "replace", label: "Replace" },
   { value: "skip", label: "Skip" },
+  { value: "ask", label: "Ask" },
+  { value: "uniqueSuffix", label: "Unique suffix" },
 ];
 
+const permissionOptions: { value: DestinationPermissions["type"]; label: string }[] = [
+  { value: "keep", label: "Permissions: keep" },
+  { value: "inherit", label: "Inherit from folder" },
+  { value: "explicit", label: "Set mode/group" },
+];
+
+const quarantineOptions: { value: QuarantineHandling; label: string }[] = [
+  { value: "keep", label: "Quarantine: as is" },
+  { value: "preserve", label: "Preserve quarantine" },
+  { value: "strip", label: "Strip quarantine" },
+];
+
+const tagModeOptions: { value: TagMode; label: string }[] = [
+  { value: "append", label: "Add to existing" },
+  { value: "replace", label: "Replace existing" },
+];
+
+function splitGlobs(value: string) {
+  return value.split(",").map((glob) => glob.trim());
+}
+
 const fieldClass =
   "rounded-[var(--radius)] bg-[var(--bg-panel)] border border-[var(--border-main)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-none outline-none transition-colors placeholder:text-[var(--fg-muted)] focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]";
 const longFieldClass = `${fieldClass} min-w-[220px]`;
@@ -205,6 +244,7 @@ function createAction(type: string): Action {
         type: "unarchive",
         destination: "",
         deleteAfter: false,
+        skipJunk: true,
       };
     case "delete":
       return { type: "delete", permanent: false };
@@ -220,8 +260,14 @@ function createAction(type: string): Action {
       return { type: "openWith", appPath: "" };
     case "makePdfSearchable":
       return { type: "makePdfSearchable", skipIfText: true, overwrite: true };
+    case "createFolderStructure":
+      return { type: "createFolderStructure", baseDestination: "", structure: [] };
+    case "dispatchToFolder":
+      return { type: "dispatchToFolder", folderId: "" };
     case "showInFileManager":
       return { type: "showInFileManager" };
+    case "setTags":
+      return { type: "setTags", tags: [] };
     case "pause":
       return { type: "pause", durationSeconds: 5 };
     case "continue":
@@ -289,6 +335,34 @@ function FolderInput({
   );
 }
 
+function DispatchFolderField({
+  action,
+  onChange,
+}: {
+  action: Extract<Action, { type: "dispatchToFolder" }>;
+  onChange: (action: Action) => void;
+}) {
+  const folders = useFolderStore((state) => state.folders);
+  const currentFolderId = useFolderStore((state) => state.selectedFolderId);
+  const targets = folders.filter((folder) => !folder.isGroup && folder.id !== currentFolderId);
+
+  return (
+    <>
+      <MagiSelect
+        width="w-48"
+        value={action.folderId}
+        onChange={(val) => onChange({ ...action, folderId: val })}
+        options={targets.map((folder) => ({ label: folder.name, value: folder.id }))}
+        placeholder="Select folder…"
+        ariaLabel="Folder to dispatch to"
+      />
+      <span className="text-[11px] text-[var(--fg-muted)]">
+        Runs that folder's rules now. Later actions in this rule are skipped.
+      </span>
+    </>
+  );
+}
+
 // Sample file info for preview
 const SAMPLE_FILE = {
   name: "document",
@@ -381,6 +455,10 @@ function conflictHint(mode: ConflictResolution) {
       return "If a file exists, overwrite it.";
     case "skip":
       return "If a file exists, skip this action.";
+    case "ask":
+      return "If a file exists, wait for you to decide in the activity log.";
+    case "uniqueSuffix":
+      return "Always add a time, random and computer suffix, without checking the folder first. For inboxes several machines file into.";
     default:
       return "";
   }
@@ -422,6 +500,41 @@ function renderActionFields(action: Action, onChange: (action: Action) => void)
             Skip duplicates
           </label>
         ) : null}
+        {action.type === "copy" ? (
+          <MagiSelect
+            width="w-40"
+            value={action.useReflink ?? "default"}
+            onChange={(val) =>
+              onChange({
+                ...action,
+                useReflink: val === "default" ? undefined : (val as ReflinkMode),
+              })
+            }
+            options={[
+              { label: "Clone: default", value: "default" },
+              { label: "Clone if possible", value: "auto" },
+              { label: "Always clone", value: "always" },
+              { label: "Never clone", value: "never" },
+            ]}
+            ariaLabel="Copy-on-write clone"
+          />
+        ) : null}
+        <PermissionFields
+          value={action.destinationPermissions ?? { type: "keep" }}
+          onChange={(destinationPermissions) => onChange({ ...action, destinationPermissions })}
+        />
+        <QuarantineField
+          value={action.quarantine ?? "keep"}
+          onChange={(quarantine) => onChange({ ...action, quarantine })}
+        />
+        <CompanionFields
+          value={action.bringCompanions}
+          onChange={(bringCompanions) => onChange({ ...action, bringCompanions })}
+        />
+        <PartitionFields
+          value={action.autoPartition}
+          onChange={(autoPartition) => onChange({ ...action, autoPartition })}
+        />
       </>
     );
   }
@@ -453,6 +566,12 @@ function renderActionFields(action: Action, onChange: (action: Action) => void)
   }
 
   if (action.type === "archive") {
+    const passwordSource = action.encryption?.passwordSource;
+    const setPasswordSource = (source: PasswordSource | null) =>
+      onChange({
+        ...action,
+        encryption: source ? { method: "zipAes256", passwordSource: source } : null,
+      });
     return (
       <>
         <span className="text-[13px] text-[var(--fg-muted)]">to:</span>
@@ -468,6 +587,8 @@ function renderActionFields(action: Action, onChange: (action: Action) => void)
             onChange({
               ...action,
               format: val as ArchiveFormat,
+              // Only zips can be encrypted.
+              encryption: val === "zip" ? action.encryption : null,
             })
           }
           options={[
@@ -477,6 +598,42 @@ function renderActionFields(action: Action, onChange: (action: Action) => void)
           ]}
           ariaLabel="Archive format"
         />
+        {action.format === "zip" ? (
+          <MagiSelect
+            width="w-40"
+            value={passwordSource?.type ?? "none"}
+            onChange={(val) =>
+              setPasswordSource(
+                val === "none"
+                  ? null
+                  : { type: val as PasswordSource["type"], value: passwordSource?.value ?? "" },
+              )
+            }
+            options={[
+              { label: "No password", value: "none" },
+              { label: "Password", value: "literal" },
+              { label: "Env variable", value: "envVar" },
+              { label: "Keychain entry", value: "keyring" },
+            ]}
+            ariaLabel="Archive password"
+          />
+        ) : null}
+        {passwordSource ? (
+          <input
+            className={fieldClass}
+            type={passwordSource.type === "literal" ? "password" : "text"}
+            placeholder={
+              passwordSource.type === "literal"
+                ? "Password"
+                : passwordSource.type === "envVar"
+                  ? "Variable name"
+                  : "Service name"
+            }
+            title="Encrypts the zip with AES-256. The password is looked up each time the rule runs"
+            value={passwordSource.value}
+            onChange={(e) => setPasswordSource({ ...passwordSource, value: e.target.value })}
+          />
+        ) : null}
         <label className="flex items-center gap-2 text-[11px] text-[var(--fg-secondary)]">
           <input
             className="accent-[var(--accent)]"
@@ -508,6 +665,38 @@ function renderActionFields(action: Action, onChange: (action: Action) => void)
           />
           Delete archive
         </label>
+        <input
+          className={fieldClass}
+          placeholder="Only: *.pdf, payload"
+          title="Comma-separated globs; an entry is extracted when it or a folder above it matches"
+          value={(action.includeGlobs ?? []).join(", ")}
+          onChange={(e) => onChange({ ...action, includeGlobs: splitGlobs(e.target.value) })}
+          onBlur={() =>
+            onChange({ ...action, includeGlobs: (action.includeGlobs ?? []).filter(Boolean) })
+          }
+        />
+        <input
+          className={fieldClass}
+          placeholder="Skip: */samples"
+          title="Comma-separated globs; these win over the ones to extract"
+          value={(action.excludeGlobs ?? []).join(", ")}
+          onChange={(e) => onChange({ ...action, excludeGlobs: splitGlobs(e.target.value) })}
+          onBlur={() =>
+            onChange({ ...action, excludeGlobs: (action.excludeGlobs ?? []).filter(Boolean) })
+          }
+        />
+        <label
+          className="flex items-center gap-2 text-[11px] text-[var(--fg-secondary)]"
+          title="__MACOSX, .DS_Store and Thumbs.db"
+        >
+          <input
+            className="accent-[var(--accent)]"
+            type="checkbox"
+            checked={action.skipJunk ?? false}
+            onChange={(e) => onChange({ ...action, skipJunk: e.target.checked })}
+          />
+          Skip junk files
+        </label>
       </>
     );
   }
@@ -517,6 +706,7 @@ function renderActionFields(action: Action, onChange: (action: Action) => void)
       <input
         className={longFieldClass}
         placeholder="Command"
+        title="Runs with $FILE_PATH and $FD_IDEMPOTENCY_KEY (stable when the same event is retried) set"
         value={action.command}
         onChange={(e) => onChange({ ...action, command: e.target.value })}
       />
@@ -525,12 +715,33 @@ function renderActionFields(action: Action, onChange: (action: Action) => void)
 
   if (action.type === "notify") {
     return (
-      <input
-        className={longFieldClass}
-        placeholder="Notification message"
-        value={action.message}
-        onChange={(e) => onChange({ ...action, message: e.target.value })}
-      />
+      <>
+        <input
+          className={longFieldClass}
+          placeholder="Notification message"
+          value={action.message}
+          onChange={(e) => onChange({ ...action, message: e.target.value })}
+        />
+        <input
+          className={`${fieldClass} w-32`}
+          placeholder="Sound (silent)"
+          title="chime.wav, or a system sound such as Glass on macOS or Mail on Windows; muted during do-not-disturb"
+          value={action.sound ?? ""}
+          onChange={(e) => onChange({ ...action, sound: e.target.value || undefined })}
+        />
+        <label
+          className="flex items-center gap-2 text-[11px] text-[var(--fg-secondary)]"
+          title="Images and PDFs only; macOS notifications can't show it, but the activity log does"
+        >
+          <input
+            className="accent-[var(--accent)]"
+            type="checkbox"
+            checked={action.generateThumbnail ?? false}
+            onChange={(e) => onChange({ ...action, generateThumbnail: e.target.checked })}
+          />
+          Thumbnail
+        </label>
+      </>
     );
   }
 
@@ -551,6 +762,28 @@ function renderActionFields(action: Action, onChange: (action: Action) => void)
     );
   }
 
+  if (action.type === "setTags") {
+    return (
+      <>
+        <input
+          className={longFieldClass}
+          placeholder="Tags: Invoices, {year}"
+          title="Comma-separated; macOS and Linux, skipped on volumes that can't store tags"
+          value={action.tags.join(", ")}
+          onChange={(e) => onChange({ ...action, tags: splitGlobs(e.target.value) })}
+          onBlur={() => onChange({ ...action, tags: action.tags.filter(Boolean) })}
+        />
+        <MagiSelect
+          width="w-40"
+          value={action.mode ?? "append"}
+          onChange={(val) => onChange({ ...action, mode: val as TagMode })}
+          options={tagModeOptions}
+          ariaLabel="Tag mode"
+        />
+      </>
+    );
+  }
+
   if (action.type === "openWith") {
     return (
       <input
@@ -562,6 +795,39 @@ function renderActionFields(action: Action, onChange: (action: Action) => void)
     );
   }
 
+  if (action.type === "createFolderStructure") {
+    return (
+      <>
+        <span className="text-[13px] text-[var(--fg-muted)]">in:</span>
+        <FolderInput
+          value={action.baseDestination}
+          onChange={(val) => onChange({ ...action, baseDestination: val })}
+          placeholder="Select base folder…"
+        />
+        <textarea
+          className={`${longFieldClass} min-h-[64px] font-mono`}
+          placeholder={"{1}/incoming\n{1}/working\n{1}/final"}
+          value={action.structure.join("\n")}
+          onChange={(e) => onChange({ ...action, structure: e.target.value.split("\n") })}
+          onBlur={() =>
+            onChange({
+              ...action,
+              structure: action.structure.filter((line) => line.trim() !== ""),
+            })
+          }
+          aria-label="Folders to create, one per line"
+        />
+        <span className="text-[11px] text-[var(--fg-muted)]">
+          One relative folder per line. Existing folders are left as they are.
+        </span>
+      </>
+    );
+  }
+
+  if (action.type === "dispatchToFolder") {
+    return <DispatchFolderField action={action} onChange={onChange} />;
+  }
+
   if (action.type === "makePdfSearchable") {
     return (
       <>
@@ -595,3 +861,219 @@ function renderActionFields(action: Action, onChange: (action: Action) => void)
 
   return null;
 }
+
+function CompanionFields({
+  value,
+  onChange,
+}: {
+  value?: BringCompanions;
+  onChange: (value: BringCompanions | undefined) => void;
+}) {
+  const checkbox = (
+    label: string,
+    checked: boolean,
+    update: (checked: boolean) => BringCompanions | undefined,
+  ) => (
+    <label className="flex items-center gap-2 text-[11px] text-[var(--fg-secondary)]">
+      <input
+        className="accent-[var(--accent)]"
+        type="checkbox"
+        checked={checked}
+        onChange={(e) => onChange(update(e.target.checked))}
+      />
+      {label}
+    </label>
+  );
+  return (
+    <div className="flex w-full flex-wrap items-center gap-2">
+      {checkbox("Bring companion files", value !== undefined, (checked) =>
+        checked ? { extensions: ["xmp"] } : undefined,
+      )}
+      {value ? (
+        <>
+          <input
+            className={`${fieldClass} w-36`}
+            placeholder="xmp, jpg"
+            title="Comma-separated extensions of files with the same name"
+            value={value.extensions.join(", ")}
+            onChange={(e) => onChange({ ...value, extensions: splitGlobs(e.target.value) })}
+            onBlur={() => onChange({ ...value, extensions: value.extensions.filter(Boolean) })}
+            aria-label="Companion extensions"
+          />
+          <input
+            className={`${fieldClass} w-40`}
+            placeholder="{name}_*.txt"
+            title="Also brings files next to it whose names match this glob"
+            value={value.pattern ?? ""}
+            onChange={(e) => onChange({ ...value, pattern: e.target.value || undefined })}
+            aria-label="Companion pattern"
+          />
+          {checkbox("Missing is fine", value.missingOk ?? false, (missingOk) => ({
+            ...value,
+            missingOk,
+          }))}
+          {checkbox("Fail if one fails", value.failOnError ?? false, (failOnError) => ({
+            ...value,
+            failOnError,
+          }))}
+          {checkbox("Skip in later rules", value.recordMatches ?? true, (recordMatches) => ({
+            ...value,
+            recordMatches,
+          }))}
+        </>
+      ) : null}
+    </div>
+  );
+}
+
+function PartitionFields({
+  value,
+  onChange,
+}: {
+  value?: AutoPartition;
+  onChange: (value: AutoPartition | undefined) => void;
+}) {
+  return (
+    <div className="flex w-full flex-wrap items-center gap-2">
+      <MagiSelect
+        width="w-44"
+        value={value?.type ?? "none"}
+        onChange={(val) =>
+          onChange(
+            val === "byCount"
+              ? { type: "byCount", maxPerDir: 1000, subdirPattern: "batch-{n:4}" }
+              : val === "byDate"
+                ? { type: "byDate", granularity: "month", dateSource: "modified" }
+                : undefined,
+          )
+        }
+        options={[
+          { label: "No subfolders", value: "none" },
+          { label: "Subfolders by count", value: "byCount" },
+          { label: "Subfolders by date", value: "byDate" },
+        ]}
+        ariaLabel="Automatic subfolders"
+      />
+      {value?.type === "byCount" ? (
+        <>
+          <input
+            className={`${fieldClass} w-20`}
+            type="number"
+            min={1}
+            title="Files per subfolder before the next one is started"
+            value={value.maxPerDir}
+            onChange={(e) => onChange({ ...value, maxPerDir: Math.max(1, Number(e.target.value)) })}
+            aria-label="Files per subfolder"
+          />
+          <input
+            className={`${fieldClass} w-32`}
+            placeholder="batch-{n:4}"
+            title="Subfolder name; {n:4} is its number padded to four digits"
+            value={value.subdirPattern}
+            onChange={(e) => onChange({ ...value, subdirPattern: e.target.value })}
+            aria-label="Subfolder name"
+          />
+        </>
+      ) : null}
+      {value?.type === "byDate" ? (
+        <>
+          <MagiSelect
+            width="w-28"
+            value={value.granularity}
+            onChange={(val) => onChange({ ...value, granularity: val as DateGranularity })}
+            options={[
+              { label: "Year", value: "year" },
+              { label: "Month", value: "month" },
+              { label: "Day", value: "day" },
+            ]}
+            ariaLabel="Subfolder per"
+          />
+          <MagiSelect
+            width="w-32"
+            value={value.dateSource}
+            onChange={(val) =>
+              onChange({ ...value, dateSource: val as "created" | "modified" | "now" })
+            }
+            options={[
+              { label: "Modified", value: "modified" },
+              { label: "Created", value: "created" },
+              { label: "Today", value: "now" },
+            ]}
+            ariaLabel="Date of"
+          />
+        </>
+      ) : null}
+    </div>
+  );
+}
+
+function PermissionFields({
+  value,
+  onChange,
+}: {
+  value: DestinationPermissions;
+  onChange: (value: DestinationPermissions) => void;
+}) {
+  return (
+    <div className="flex flex-wrap items-center gap-2">
+      <MagiSelect
+        width="w-44"
+        value={value.type}
+        onChange={(val) =>
+          onChange(
+            val === "explicit"
+              ? { type: "explicit", mode: "0640" }
+              : { type: val as "keep" | "inherit" },
+          )
+        }
+        options={permissionOptions}
+        ariaLabel="Destination permissions"
+      />
+      {value.type === "explicit" ? (
+        <>
+          <input
+            className={`${fieldClass} w-20`}
+            placeholder="0640"
+            value={value.mode ?? ""}
+            onChange={(e) => onChange({ ...value, mode: e.target.value || undefined })}
+            aria-label="Mode"
+          />
+          <input
+            className={`${fieldClass} w-28`}
+            placeholder="group"
+            value={value.group ?? ""}
+            onChange={(e) => onChange({ ...value, group: e.target.value || undefined })}
+            aria-label="Group"
+          />
+        </>
+      ) : null}
+    </div>
+  );
+}
+
+function QuarantineField({
+  value,
+  onChange,
+}: {
+  value: QuarantineHandling;
+  onChange: (value: QuarantineHandling) => void;
+}) {
+  const allowStrip = useSettingsStore((state) => state.settings.allowQuarantineStrip);
+  return (
+    <div className="flex flex-wrap items-center gap-2">
+      <MagiSelect
+        width="w-44"
+        value={value}
+        onChange={(val) => onChange(val as QuarantineHandling)}
+        options={quarantineOptions}
+        ariaLabel="Quarantine attribute"
+      />
+      {value === "strip" && !allowStrip ? (
+        <span className="flex items-center gap-1 text-xs text-red-500">
+          <AlertTriangle className="h-3.5 w-3.5" />
+          Stripping quarantine is disabled in settings.
+        </span>
+      ) : null}
+    </div>
+  );
+}
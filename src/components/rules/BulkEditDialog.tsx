@@ -0,0 +1,271 @@
+import { useEffect, useRef, useState } from "react";
+import { createPortal } from "react-dom";
+import { AlertTriangle, X } from "lucide-react";
+
+import type { BulkRuleEdit, ConflictResolution, Folder, Rule, RuleEditSummary } from "@/types";
+import { ruleList, rulesBulkEdit } from "@/lib/tauri";
+import { useFolderStore } from "@/stores/folderStore";
+import { useRuleStore } from "@/stores/ruleStore";
+import { useFocusTrap } from "@/hooks/useFocusTrap";
+
+interface BulkEditDialogProps {
+  open: boolean;
+  onClose: () => void;
+}
+
+type EditType = BulkRuleEdit["type"];
+
+const EDIT_LABELS: Record<EditType, string> = {
+  enable: "Enable",
+  disable: "Disable",
+  setStopProcessing: "Set stop processing",
+  replaceInDestinations: "Replace in destinations",
+  setConflictResolution: "Set conflict resolution",
+};
+
+const inputClass =
+  "rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-xs text-[var(--fg-primary)] focus:border-[var(--accent)] focus:outline-none";
+
+export function BulkEditDialog({ open, onClose }: BulkEditDialogProps) {
+  const folders = useFolderStore((state) => state.folders);
+  const selectedFolderId = useFolderStore((state) => state.selectedFolderId);
+  const loadRules = useRuleStore((state) => state.loadRules);
+  const dialogRef = useRef<HTMLDivElement>(null);
+
+  const [rulesByFolder, setRulesByFolder] = useState<{ folder: Folder; rules: Rule[] }[]>([]);
+  const [selected, setSelected] = useState<Set<string>>(new Set());
+  const [editType, setEditType] = useState<EditType>("replaceInDestinations");
+  const [find, setFind] = useState("");
+  const [replace, setReplace] = useState("");
+  const [regex, setRegex] = useState(false);
+  const [stopProcessing, setStopProcessing] = useState(true);
+  const [policy, setPolicy] = useState<ConflictResolution>("rename");
+  const [results, setResults] = useState<RuleEditSummary[] | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  const [busy, setBusy] = useState(false);
+
+  useFocusTrap(open, dialogRef);
+
+  useEffect(() => {
+    if (!open) return;
+    setResults(null);
+    setError(null);
+    const watched = folders.filter((folder) => !folder.isGroup);
+    Promise.all(watched.map((folder) => ruleList(folder.id).then((rules) => ({ folder, rules }))))
+      .then((loaded) => setRulesByFolder(loaded.filter((entry) => entry.rules.length > 0)))
+      .catch((err) => setError(String(err)));
+  }, [open, folders]);
+
+  if (!open) return null;
+
+  const toggle = (ids: string[], checked: boolean) => {
+    setSelected((current) => {
+      const next = new Set(current);
+      for (const id of ids) {
+        if (checked) next.add(id);
+        else next.delete(id);
+      }
+      return next;
+    });
+  };
+
+  const buildEdit = (): BulkRuleEdit => {
+    switch (editType) {
+      case "setStopProcessing":
+        return { type: editType, value: stopProcessing };
+      case "replaceInDestinations":
+        return { type: editType, find, replace, regex };
+      case "setConflictResolution":
+        return { type: editType, policy };
+      default:
+        return { type: editType };
+    }
+  };
+
+  const handleApply = async () => {
+    setBusy(true);
+    setError(null);
+    setResults(null);
+    try {
+      setResults(await rulesBulkEdit([...selected], buildEdit()));
+      if (selectedFolderId) {
+        await loadRules(selectedFolderId);
+      }
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  const modal = (
+    <div className="fixed inset-0 z-[60] flex items-center justify-center p-4">
+      <button
+        type="button"
+        className="absolute inset-0 bg-black/40 backdrop-blur-sm"
+        onClick={onClose}
+        aria-label="Close bulk edit"
+        tabIndex={-1}
+      />
+      <div
+        ref={dialogRef}
+        role="dialog"
+        aria-modal="true"
+        aria-labelledby="bulk-edit-title"
+        className="relative w-full max-w-3xl overflow-hidden rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] shadow-[var(--shadow-md)]"
+      >
+        <div className="flex items-center justify-between border-b border-[var(--border-main)] px-6 py-4">
+          <div>
+            <h2 id="bulk-edit-title" className="text-lg font-semibold text-[var(--fg-primary)]">Bulk edit rules</h2>
+            <p className="text-xs text-[var(--fg-muted)]">Nothing is saved unless every selected rule stays valid</p>
+          </div>
+          <button
+            type="button"
+            onClick={onClose}
+            className="rounded-[var(--radius)] p-2 text-[var(--fg-muted)] transition-colors hover:bg-[var(--bg-subtle)] hover:text-[var(--fg-primary)]"
+            aria-label="Close bulk edit"
+          >
+            <X className="h-4 w-4" />
+          </button>
+        </div>
+
+        <div className="grid gap-4 p-6 md:grid-cols-[1fr_1fr]">
+          <div className="custom-scrollbar max-h-[50vh] space-y-3 overflow-y-auto rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-3 text-xs">
+            {rulesByFolder.length === 0 ? (
+              <div className="text-[var(--fg-muted)]">No rules yet.</div>
+            ) : (
+              rulesByFolder.map(({ folder, rules }) => {
+                const ids = rules.map((rule) => rule.id);
+                const allSelected = ids.every((id) => selected.has(id));
+                return (
+                  <div key={folder.id}>
+                    <label className="flex items-center gap-2 font-semibold text-[var(--fg-primary)]">
+                      <input type="checkbox" checked={allSelected} onChange={(e) => toggle(ids, e.target.checked)} />
+                      {folder.name}
+                    </label>
+                    <div className="mt-1 space-y-1 pl-5">
+                      {rules.map((rule) => (
+                        <label key={rule.id} className="flex items-center gap-2 text-[var(--fg-secondary)]">
+                          <input
+                            type="checkbox"
+                            checked={selected.has(rule.id)}
+                            onChange={(e) => toggle([rule.id], e.target.checked)}
+                          />
+                          <span className="truncate">{rule.name}</span>
+                        </label>
+                      ))}
+                    </div>
+                  </div>
+                );
+              })
+            )}
+          </div>
+
+          <div className="space-y-3 text-xs">
+            <select
+              className={`${inputClass} w-full`}
+              value={editType}
+              aria-label="Edit"
+              onChange={(e) => setEditType(e.target.value as EditType)}
+            >
+              {(Object.keys(EDIT_LABELS) as EditType[]).map((type) => (
+                <option key={type} value={type}>
+                  {EDIT_LABELS[type]}
+                </option>
+              ))}
+            </select>
+
+            {editType === "replaceInDestinations" ? (
+              <div className="space-y-2">
+                <input
+                  className={`${inputClass} w-full`}
+                  placeholder="Find, e.g. ~/Archive"
+                  value={find}
+                  onChange={(e) => setFind(e.target.value)}
+                />
+                <input
+                  className={`${inputClass} w-full`}
+                  placeholder={regex ? "Replace with, e.g. /Volumes/NAS/$1" : "Replace with"}
+                  value={replace}
+                  onChange={(e) => setReplace(e.target.value)}
+                />
+                <label className="flex items-center gap-2 text-[var(--fg-secondary)]">
+                  <input type="checkbox" checked={regex} onChange={(e) => setRegex(e.target.checked)} />
+                  Regular expression
+                </label>
+                <p className="text-[10px] text-[var(--fg-muted)]">
+                  Applies to Move, Copy, Sort into subfolder and Archive destinations and Rename patterns.
+                </p>
+              </div>
+            ) : null}
+            {editType === "setStopProcessing" ? (
+              <label className="flex items-center gap-2 text-[var(--fg-secondary)]">
+                <input
+                  type="checkbox"
+                  checked={stopProcessing}
+                  onChange={(e) => setStopProcessing(e.target.checked)}
+                />
+                Stop processing after a match
+              </label>
+            ) : null}
+            {editType === "setConflictResolution" ? (
+              <select
+                className={`${inputClass} w-full`}
+                value={policy}
+                aria-label="Conflict resolution"
+                onChange={(e) => setPolicy(e.target.value as ConflictResolution)}
+              >
+                <option value="rename">Rename</option>
+                <option value="replace">Replace</option>
+                <option value="skip">Skip</option>
+                <option value="ask">Ask</option>
+                <option value="uniqueSuffix">Unique suffix</option>
+              </select>
+            ) : null}
+
+            <button
+              type="button"
+              onClick={() => void handleApply()}
+              disabled={busy || selected.size === 0}
+              className="rounded-[var(--radius)] bg-[var(--accent)] px-3 py-1.5 text-[11px] font-semibold text-[var(--accent-contrast)] transition-colors hover:opacity-90 disabled:opacity-50"
+            >
+              Apply to {selected.size} {selected.size === 1 ? "rule" : "rules"}
+            </button>
+
+            {error ? (
+              <div className="flex items-start gap-2 rounded-[var(--radius)] border border-[var(--fg-alert)]/30 bg-[var(--fg-alert)]/10 px-3 py-2 text-[var(--fg-alert)]">
+                <AlertTriangle className="mt-0.5 h-4 w-4 shrink-0" />
+                <div>{error}</div>
+              </div>
+            ) : null}
+
+            {results ? (
+              <div className="custom-scrollbar max-h-[30vh] space-y-2 overflow-y-auto">
+                {results.map((result) => (
+                  <div
+                    key={result.ruleId}
+                    className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] px-2 py-1"
+                  >
+                    <div className="font-semibold text-[var(--fg-primary)]">{result.ruleName}</div>
+                    {result.changes.map((change) => (
+                      <div key={change.field} className="mt-1 break-all text-[10px] text-[var(--fg-secondary)]">
+                        {change.field}: {change.before} → {change.after}
+                      </div>
+                    ))}
+                    {result.warnings.map((warning) => (
+                      <div key={warning} className="mt-1 text-[10px] text-[var(--fg-muted)]">
+                        {warning}
+                      </div>
+                    ))}
+                  </div>
+                ))}
+              </div>
+            ) : null}
+          </div>
+        </div>
+      </div>
+    </div>
+  );
+
+  return typeof document !== "undefined" ? createPortal(modal, document.body) : modal;
+}
@@ -4,8 +4,13 @@ import { MagiSelect } from "@/components/ui/MagiSelect";
 
 
 import type {
+  ArchiveMatchScope,
+  CollisionCompare,
   Condition,
   ConditionGroup,
+  CsvDelimiter,
+  DuplicateScope,
+  EventSource,
   MatchType,
   StringOperator,
   ComparisonOperator,
@@ -13,6 +18,8 @@ import type {
   TimeOperator,
   TimeUnit,
   FileKind,
+  ImageShape,
+  PixelBound,
   SizeUnit,
 } from "@/types";
 
@@ -26,6 +33,9 @@ const conditionTypes = [
   { value: "name", label: "Name" },
   { value: "extension", label: "Extension" },
   { value: "fullName", label: "Full Name" },
+  { value: "path", label: "Path" },
+  { value: "parentFolder", label: "Parent Folder" },
+  { value: "relativePath", label: "Relative Path" },
   { value: "contents", label: "Contents" },
   { value: "size", label: "Size" },
   { value: "dateCreated", label: "Date Created" },
@@ -34,7 +44,42 @@ const conditionTypes = [
   { value: "dateLastMatched", label: "Date Last Matched" },
   { value: "currentTime", label: "Current Time" },
   { value: "kind", label: "Kind" },
+  { value: "mimeType", label: "MIME Type" },
+  { value: "tag", label: "Finder Tag" },
   { value: "shellScript", label: "Shell Script" },
+  { value: "isLocked", label: "File In Use" },
+  { value: "classifiedAs", label: "Classified As" },
+  { value: "archiveContains", label: "Archive Contains" },
+  { value: "faceCount", label: "Face Count" },
+  { value: "imageDimensions", label: "Image Dimensions" },
+  { value: "exifDate", label: "EXIF Date" },
+  { value: "pageCount", label: "PDF Page Count" },
+  { value: "mediaDuration", label: "Media Duration" },
+  { value: "mediaCodec", label: "Media Codec" },
+  { value: "isScreenshot", label: "Is Screenshot" },
+  { value: "isDuplicate", label: "Is Duplicate" },
+  { value: "groupSize", label: "Arrived Together" },
+  { value: "activeApplication", label: "Active Application" },
+  { value: "eventSource", label: "Event Source" },
+  { value: "csvColumn", label: "CSV Column" },
+  { value: "existsAtDestination", label: "Exists At Destination" },
+];
+
+const eventSources: { value: EventSource; label: string }[] = [
+  { value: "live", label: "live" },
+  { value: "catchup", label: "catch-up" },
+  { value: "manual", label: "run now" },
+  { value: "scan", label: "schedule" },
+  { value: "retry", label: "retry" },
+  { value: "sweep", label: "volume back" },
+];
+
+const csvDelimiters = [
+  { value: "auto", label: "auto" },
+  { value: ",", label: "comma" },
+  { value: ";", label: "semicolon" },
+  { value: "\t", label: "tab" },
+  { value: "|", label: "pipe" },
 ];
 
 const stringOperators: { value: StringOperator; label: string }[] = [
@@ -46,6 +91,10 @@ const stringOperators: { value: StringOperator; label: string }[] = [
   { value: "endsWith", label: "ends with" },
   { value: "matches", label: "matches regex" },
   { value: "doesNotMatch", label: "does not match" },
+  { value: "matchesGlob", label: "matches glob" },
+  { value: "doesNotMatchGlob", label: "does not match glob" },
+  { value: "isOneOf", label: "is one of" },
+  { value: "isNotOneOf", label: "is not one of" },
 ];
 
 const sizeOperators: { value: ComparisonOperator; label: string }[] = [
@@ -75,6 +124,8 @@ const dateOperators: { value: DateOperator; label: string }[] = [
   { value: { type: "between", start: today, end: today }, label: "between" },
   { value: { type: "inTheLast", amount: 1, unit: "days" }, label: "in the last" },
   { value: { type: "notInTheLast", amount: 1, unit: "days" }, label: "not in the last" },
+  { value: { type: "inTheLastBusinessDays", amount: 5 }, label: "in the last business days" },
+  { value: { type: "notInTheLastBusinessDays", amount: 5 }, label: "not in the last business days" },
 ];
 
 const timeOperators: { value: TimeOperator; label: string }[] = [
@@ -372,12 +423,38 @@ export function ConditionBuilder({ group, onChange, depth = 0 }: ConditionBuilde
   );
 }
 
+function TimezoneField({
+  value,
+  onChange,
+}: {
+  value?: string | null;
+  onChange: (value: string | null) => void;
+}) {
+  return (
+    <input
+      className={`${fieldClass} w-32`}
+      type="text"
+      placeholder="Time zone"
+      title='IANA time zone such as UTC or Europe/Berlin, or "default" for the one in Settings'
+      aria-label="Time zone"
+      value={value ?? ""}
+      onChange={(e) => onChange(e.target.value.trim() ? e.target.value : null)}
+    />
+  );
+}
+
 function createCondition(type: string): Condition {
   switch (type) {
     case "extension":
       return { type: "extension", operator: "is", value: "", caseSensitive: false };
     case "fullName":
       return { type: "fullName", operator: "is", value: "", caseSensitive: false };
+    case "path":
+      return { type: "path", operator: "contains", value: "", caseSensitive: false };
+    case "parentFolder":
+      return { type: "parentFolder", operator: "is", value: "", caseSensitive: false };
+    case "relativePath":
+      return { type: "relativePath", operator: "startsWith", value: "", caseSensitive: false };
     case "contents":
       return { type: "contents", operator: "contains", value: "", caseSensitive: false, source: "auto" };
     case "size":
@@ -390,12 +467,59 @@ function createCondition(type: string): Condition {
       return { type: "dateAdded", operator: { type: "is", date: "" } };
     case "dateLastMatched":
       return { type: "dateLastMatched", operator: { type: "inTheLast", amount: 7, unit: "days" } };
+    case "exifDate":
+      return { type: "exifDate", operator: { type: "is", date: "" } };
     case "currentTime":
       return { type: "currentTime", operator: { type: "is", time: defaultTime } };
     case "kind":
       return { type: "kind", kind: "file", negate: false };
     case "shellScript":
       return { type: "shellScript", command: "" };
+    case "isLocked":
+      return { type: "isLocked", negate: false };
+    case "classifiedAs":
+      return { type: "classifiedAs", category: "", minScore: 0.3 };
+    case "archiveContains":
+      return {
+        type: "archiveContains",
+        nameCondition: { operator: "endsWith", value: "", caseSensitive: false },
+        matchScope: "anyEntry",
+        maxEntries: 10000,
+      };
+    case "faceCount":
+      return { type: "faceCount", operator: { type: "greaterOrEqual" }, value: 1 };
+    case "imageDimensions":
+      return { type: "imageDimensions", width: { operator: { type: "greaterThan" }, value: 1920 } };
+    case "pageCount":
+      return { type: "pageCount", operator: { type: "greaterThan" }, value: 50 };
+    case "mediaDuration":
+      return { type: "mediaDuration", operator: { type: "greaterThan" }, value: 30, unit: "minutes" };
+    case "mediaCodec":
+      return { type: "mediaCodec", operator: "is", value: "", caseSensitive: false };
+    case "isScreenshot":
+      return { type: "isScreenshot", negate: false };
+    case "isDuplicate":
+      return { type: "isDuplicate", scope: "sameFolder", negate: false };
+    case "groupSize":
+      return { type: "groupSize", operator: { type: "greaterOrEqual" }, value: 2 };
+    case "activeApplication":
+      return { type: "activeApplication", operator: "is", value: "", caseSensitive: false };
+    case "mimeType":
+      return { type: "mimeType", operator: "startsWith", value: "image/", caseSensitive: false };
+    case "tag":
+      return { type: "tag", operator: "is", value: "", caseSensitive: false };
+    case "eventSource":
+      return { type: "eventSource", sources: ["scan", "catchup"] };
+    case "csvColumn":
+      return {
+        type: "csvColumn",
+        column: { type: "header", name: "" },
+        row: { type: "first" },
+        condition: { operator: "is", value: "", caseSensitive: false },
+        delimiter: { type: "auto" },
+      };
+    case "existsAtDestination":
+      return { type: "existsAtDestination", destinationPattern: "", compare: "nameOnly", negate: false };
     case "name":
     default:
       return { type: "name", operator: "contains", value: "", caseSensitive: false };
@@ -410,7 +534,14 @@ function renderConditionFields(
     condition.type === "name" ||
     condition.type === "extension" ||
     condition.type === "fullName" ||
-    condition.type === "contents"
+    condition.type === "path" ||
+    condition.type === "parentFolder" ||
+    condition.type === "relativePath" ||
+    condition.type === "contents" ||
+    condition.type === "activeApplication" ||
+    condition.type === "mimeType" ||
+    condition.type === "mediaCodec" ||
+    condition.type === "tag"
   ) {
     return (
       <>
@@ -425,7 +556,23 @@ function renderConditionFields(
         />
         <input
           className={fieldClass}
-          placeholder="Value"
+          placeholder={
+            condition.operator === "isOneOf" || condition.operator === "isNotOneOf"
+              ? "e.g. jpg, jpeg, png"
+              : condition.type === "activeApplication"
+                ? "App name or identifier"
+                : condition.type === "mimeType"
+                  ? "e.g. application/pdf"
+                  : condition.type === "mediaCodec"
+                    ? "e.g. h264, aac"
+                  : condition.type === "tag"
+                    ? "e.g. Red or Invoices"
+                  : condition.type === "path"
+                    ? "e.g. /Downloads/work/"
+                    : condition.type === "relativePath"
+                      ? "e.g. clients/2024/"
+                      : "Value"
+          }
           value={condition.value}
           onChange={(e) => onChange({ ...condition, value: e.target.value })}
         />
@@ -442,6 +589,24 @@ function renderConditionFields(
             ariaLabel="Content source"
           />
         ) : null}
+        {condition.type === "contents" && condition.source !== "text" ? (
+          <input
+            className={smallFieldClass}
+            type="number"
+            min={0}
+            max={1}
+            step={0.05}
+            placeholder="Min OCR conf."
+            title="Don't match OCR text recognized below this confidence (0–1)"
+            value={condition.minOcrConfidence ?? ""}
+            onChange={(e) =>
+              onChange({
+                ...condition,
+                minOcrConfidence: e.target.value === "" ? null : Number(e.target.value),
+              })
+            }
+          />
+        ) : null}
         <label className="flex items-center gap-2 text-[11px] text-[var(--fg-secondary)]">
           <input
             className="accent-[var(--accent)]"
@@ -526,7 +691,8 @@ function renderConditionFields(
     condition.type === "dateCreated" ||
     condition.type === "dateModified" ||
     condition.type === "dateAdded" ||
-    condition.type === "dateLastMatched"
+    condition.type === "dateLastMatched" ||
+    condition.type === "exifDate"
   ) {
     const operator = condition.operator;
     return (
@@ -595,6 +761,23 @@ function renderConditionFields(
               ariaLabel="Date range unit"
             />
           </>
+        ) : operator.type === "inTheLastBusinessDays" ||
+          operator.type === "notInTheLastBusinessDays" ? (
+          <>
+            <input
+              className={smallFieldClass}
+              type="number"
+              min={0}
+              value={operator.amount}
+              onChange={(e) =>
+                onChange({
+                  ...condition,
+                  operator: { ...operator, amount: Number(e.target.value) },
+                })
+              }
+            />
+            <span className="text-xs text-[var(--fg-muted)]">business days</span>
+          </>
         ) : (
           <input
             className={fieldClass}
@@ -608,6 +791,10 @@ function renderConditionFields(
             }
           />
         )}
+        <TimezoneField
+          value={condition.timezone}
+          onChange={(timezone) => onChange({ ...condition, timezone })}
+        />
       </>
     );
   }
@@ -667,6 +854,10 @@ function renderConditionFields(
             }
           />
         )}
+        <TimezoneField
+          value={condition.timezone}
+          onChange={(timezone) => onChange({ ...condition, timezone })}
+        />
       </>
     );
   }
@@ -705,5 +896,448 @@ function renderConditionFields(
     );
   }
 
+  if (condition.type === "isLocked" || condition.type === "isScreenshot") {
+    return (
+      <label className="flex items-center gap-2 text-[11px] text-[var(--fg-muted)]">
+        <input
+          className="accent-[var(--accent)]"
+          type="checkbox"
+          checked={condition.negate}
+          onChange={(e) => onChange({ ...condition, negate: e.target.checked })}
+        />
+        Not
+      </label>
+    );
+  }
+
+  if (condition.type === "isDuplicate") {
+    return (
+      <>
+        <MagiSelect
+          width="w-40"
+          value={condition.scope}
+          onChange={(val) => onChange({ ...condition, scope: val as DuplicateScope })}
+          options={[
+            { label: "in this folder", value: "sameFolder" },
+            { label: "in any watched folder", value: "anyWatchedFolder" },
+          ]}
+          ariaLabel="Duplicate scope"
+        />
+        <label className="flex items-center gap-2 text-[11px] text-[var(--fg-muted)]">
+          <input
+            className="accent-[var(--accent)]"
+            type="checkbox"
+            checked={condition.negate}
+            onChange={(e) => onChange({ ...condition, negate: e.target.checked })}
+          />
+          Not
+        </label>
+      </>
+    );
+  }
+
+  if (condition.type === "eventSource") {
+    return (
+      <div className="flex flex-wrap items-center gap-3">
+        {eventSources.map((source) => (
+          <label
+            key={source.value}
+            className="flex items-center gap-1 text-[11px] text-[var(--fg-muted)]"
+          >
+            <input
+              className="accent-[var(--accent)]"
+              type="checkbox"
+              checked={condition.sources.includes(source.value)}
+              onChange={(e) =>
+                onChange({
+                  ...condition,
+                  sources: e.target.checked
+                    ? [...condition.sources, source.value]
+                    : condition.sources.filter((s) => s !== source.value),
+                })
+              }
+            />
+            {source.label}
+          </label>
+        ))}
+      </div>
+    );
+  }
+
+  if (condition.type === "archiveContains") {
+    const nameCondition = condition.nameCondition;
+    return (
+      <>
+        <MagiSelect
+          width="w-28"
+          value={condition.matchScope}
+          onChange={(val) => onChange({ ...condition, matchScope: val as ArchiveMatchScope })}
+          options={[
+            { label: "any entry", value: "anyEntry" },
+            { label: "all entries", value: "allEntries" },
+          ]}
+          ariaLabel="Archive match scope"
+        />
+        <MagiSelect
+          width="w-36"
+          value={nameCondition.operator}
+          onChange={(val) =>
+            onChange({
+              ...condition,
+              nameCondition: { ...nameCondition, operator: val as StringOperator },
+            })
+          }
+          options={stringOperators}
+          ariaLabel="Entry name operator"
+        />
+        <input
+          className={fieldClass}
+          placeholder="Entry path"
+          value={nameCondition.value}
+          onChange={(e) =>
+            onChange({
+              ...condition,
+              nameCondition: { ...nameCondition, value: e.target.value },
+            })
+          }
+        />
+        <span className="text-[11px] text-[var(--fg-muted)]">max entries</span>
+        <input
+          className={smallFieldClass}
+          type="number"
+          min={1}
+          value={condition.maxEntries}
+          onChange={(e) => onChange({ ...condition, maxEntries: Number(e.target.value) })}
+        />
+      </>
+    );
+  }
+
+  if (condition.type === "existsAtDestination") {
+    return (
+      <>
+        <input
+          className={longFieldClass}
+          placeholder="~/Archive/{year}"
+          title="Folder to look in; tokens and earlier captures are filled in"
+          value={condition.destinationPattern}
+          onChange={(e) => onChange({ ...condition, destinationPattern: e.target.value })}
+        />
+        <MagiSelect
+          width="w-36"
+          value={condition.compare}
+          onChange={(val) => onChange({ ...condition, compare: val as CollisionCompare })}
+          options={[
+            { label: "same name", value: "nameOnly" },
+            { label: "same name and contents", value: "nameAndHash" },
+          ]}
+          ariaLabel="Collision comparison"
+        />
+        <label className="flex items-center gap-2 text-[11px] text-[var(--fg-muted)]">
+          <input
+            className="accent-[var(--accent)]"
+            type="checkbox"
+            checked={condition.negate}
+            onChange={(e) => onChange({ ...condition, negate: e.target.checked })}
+          />
+          Not
+        </label>
+      </>
+    );
+  }
+
+  if (condition.type === "csvColumn") {
+    const { column, row, delimiter } = condition;
+    const cell = condition.condition;
+    return (
+      <>
+        <MagiSelect
+          width="w-28"
+          value={column.type}
+          onChange={(val) =>
+            onChange({
+              ...condition,
+              column: val === "index" ? { type: "index", index: 1 } : { type: "header", name: "" },
+            })
+          }
+          options={[
+            { label: "header", value: "header" },
+            { label: "column #", value: "index" },
+          ]}
+          ariaLabel="CSV column by"
+        />
+        {column.type === "index" ? (
+          <input
+            className={smallFieldClass}
+            type="number"
+            min={1}
+            value={column.index}
+            onChange={(e) =>
+              onChange({ ...condition, column: { type: "index", index: Number(e.target.value) } })
+            }
+          />
+        ) : (
+          <input
+            className={smallFieldClass}
+            placeholder="Account"
+            value={column.name}
+            onChange={(e) =>
+              onChange({ ...condition, column: { type: "header", name: e.target.value } })
+            }
+          />
+        )}
+        <MagiSelect
+          width="w-36"
+          value={cell.operator}
+          onChange={(val) =>
+            onChange({ ...condition, condition: { ...cell, operator: val as StringOperator } })
+          }
+          options={stringOperators}
+          ariaLabel="CSV cell operator"
+        />
+        <input
+          className={fieldClass}
+          placeholder="Value"
+          value={cell.value}
+          onChange={(e) => onChange({ ...condition, condition: { ...cell, value: e.target.value } })}
+        />
+        <MagiSelect
+          width="w-28"
+          value={row.type}
+          onChange={(val) =>
+            onChange({
+              ...condition,
+              row: val === "any" ? { type: "any", maxRows: 100 } : { type: "first" },
+            })
+          }
+          options={[
+            { label: "first row", value: "first" },
+            { label: "any row", value: "any" },
+          ]}
+          ariaLabel="CSV rows"
+        />
+        {row.type === "any" && (
+          <>
+            <span className="text-[11px] text-[var(--fg-muted)]">of first</span>
+            <input
+              className={smallFieldClass}
+              type="number"
+              min={1}
+              value={row.maxRows}
+              onChange={(e) =>
+                onChange({ ...condition, row: { type: "any", maxRows: Number(e.target.value) } })
+              }
+            />
+          </>
+        )}
+        <MagiSelect
+          width="w-28"
+          value={delimiter.type === "auto" ? "auto" : delimiter.value}
+          onChange={(val) =>
+            onChange({
+              ...condition,
+              delimiter: (val === "auto"
+                ? { type: "auto" }
+                : { type: "char", value: val }) as CsvDelimiter,
+            })
+          }
+          options={csvDelimiters}
+          ariaLabel="CSV delimiter"
+        />
+      </>
+    );
+  }
+
+  if (condition.type === "imageDimensions") {
+    const bound = (axis: "width" | "height") => {
+      const current = condition[axis];
+      const operator = current?.operator;
+      const update = (next?: PixelBound) =>
+        onChange(axis === "width" ? { ...condition, width: next } : { ...condition, height: next });
+      let fields = null;
+      if (current && operator?.type === "between") {
+        fields = (
+          <>
+            <input
+              className={smallFieldClass}
+              type="number"
+              min={0}
+              value={operator.min}
+              onChange={(e) =>
+                update({ ...current, operator: { ...operator, min: Number(e.target.value) } })
+              }
+            />
+            <span className="text-[11px] text-[var(--fg-muted)]">and</span>
+            <input
+              className={smallFieldClass}
+              type="number"
+              min={0}
+              value={operator.max}
+              onChange={(e) =>
+                update({ ...current, operator: { ...operator, max: Number(e.target.value) } })
+              }
+            />
+          </>
+        );
+      } else if (current) {
+        fields = (
+          <input
+            className={smallFieldClass}
+            type="number"
+            min={0}
+            value={current.value}
+            onChange={(e) => update({ ...current, value: Number(e.target.value) })}
+          />
+        );
+      }
+      return (
+        <>
+          <MagiSelect
+            width="w-32"
+            value={operator?.type ?? "any"}
+            onChange={(val) => {
+              const selected = sizeOperators.find((op) => op.value.type === val);
+              update(selected ? { operator: selected.value, value: current?.value ?? 0 } : undefined);
+            }}
+            options={[
+              { label: `any ${axis}`, value: "any" },
+              ...sizeOperators.map((op) => ({ label: `${axis} ${op.label}`, value: op.value.type })),
+            ]}
+            ariaLabel={`Image ${axis} operator`}
+          />
+          {fields}
+          {current && <span className="text-[11px] text-[var(--fg-muted)]">px</span>}
+        </>
+      );
+    };
+    return (
+      <>
+        {bound("width")}
+        {bound("height")}
+        <MagiSelect
+          width="w-32"
+          value={condition.shape ?? "any"}
+          onChange={(val) =>
+            onChange({ ...condition, shape: val === "any" ? undefined : (val as ImageShape) })
+          }
+          options={[
+            { label: "any shape", value: "any" },
+            { label: "portrait", value: "portrait" },
+            { label: "landscape", value: "landscape" },
+            { label: "square", value: "square" },
+          ]}
+          ariaLabel="Image shape"
+        />
+      </>
+    );
+  }
+
+  if (
+    condition.type === "faceCount" ||
+    condition.type === "groupSize" ||
+    condition.type === "pageCount" ||
+    condition.type === "mediaDuration"
+  ) {
+    const operator = condition.operator;
+    const noun =
+      condition.type === "faceCount" ? "faces" : condition.type === "pageCount" ? "pages" : "files";
+    return (
+      <>
+        <MagiSelect
+          width="w-32"
+          value={operator.type}
+          onChange={(val) => {
+            const selected = sizeOperators.find((op) => op.value.type === val);
+            onChange({
+              ...condition,
+              operator: selected?.value ?? { type: "greaterOrEqual" },
+            });
+          }}
+          options={sizeOperators.map(op => ({ label: op.label, value: op.value.type }))}
+          ariaLabel={
+            condition.type === "faceCount"
+              ? "Face count operator"
+              : condition.type === "pageCount"
+                ? "Page count operator"
+                : condition.type === "mediaDuration"
+                  ? "Media duration operator"
+                  : "Group size operator"
+          }
+        />
+        {operator.type === "between" ? (
+          <>
+            <input
+              className={smallFieldClass}
+              type="number"
+              min={0}
+              value={operator.min}
+              onChange={(e) =>
+                onChange({
+                  ...condition,
+                  operator: { ...operator, min: Number(e.target.value) },
+                })
+              }
+            />
+            <span className="text-[11px] text-[var(--fg-muted)]">and</span>
+            <input
+              className={smallFieldClass}
+              type="number"
+              min={0}
+              value={operator.max}
+              onChange={(e) =>
+                onChange({
+                  ...condition,
+                  operator: { ...operator, max: Number(e.target.value) },
+                })
+              }
+            />
+          </>
+        ) : (
+          <input
+            className={smallFieldClass}
+            type="number"
+            min={0}
+            value={condition.value}
+            onChange={(e) => onChange({ ...condition, value: Number(e.target.value) })}
+          />
+        )}
+        {condition.type === "mediaDuration" ? (
+          <MagiSelect
+            width="w-28"
+            value={condition.unit}
+            onChange={(val) => onChange({ ...condition, unit: val as TimeUnit })}
+            options={timeUnits.map(unit => ({ label: unit, value: unit }))}
+            ariaLabel="Media duration unit"
+          />
+        ) : (
+          <span className="text-[11px] text-[var(--fg-muted)]">{noun}</span>
+        )}
+      </>
+    );
+  }
+
+  if (condition.type === "classifiedAs") {
+    return (
+      <>
+        <input
+          className={fieldClass}
+          placeholder="any category"
+          value={condition.category}
+          onChange={(e) => onChange({ ...condition, category: e.target.value })}
+        />
+        <span className="text-[11px] text-[var(--fg-muted)]">min score</span>
+        <input
+          className={smallFieldClass}
+          type="number"
+          min={0}
+          max={1}
+          step={0.05}
+          value={condition.minScore}
+          onChange={(e) => onChange({ ...condition, minScore: Number(e.target.value) })}
+        />
+      </>
+    );
+  }
+
   return null;
 }
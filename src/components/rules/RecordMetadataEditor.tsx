@@ -0,0 +1,61 @@
+import { Plus, X } from "lucide-react";
+
+interface RecordMetadataEditorProps {
+  metadata: Record<string, string>;
+  onChange: (metadata: Record<string, string>) => void;
+  inputClass: string;
+}
+
+/** Key/value patterns a rule records with each file it matches, e.g. vendor → {1}. */
+export function RecordMetadataEditor({ metadata, onChange, inputClass }: RecordMetadataEditorProps) {
+  const entries = Object.entries(metadata);
+
+  const update = (index: number, key: string, value: string) => {
+    const next = entries.map((entry, i) => (i === index ? [key, value] : entry));
+    onChange(Object.fromEntries(next));
+  };
+  const remove = (index: number) => onChange(Object.fromEntries(entries.filter((_, i) => i !== index)));
+
+  return (
+    <div className="space-y-2">
+      {entries.map(([key, value], index) => (
+        <div key={index} className="flex items-center gap-2">
+          <input
+            aria-label="Metadata key"
+            className={`${inputClass} w-40`}
+            type="text"
+            placeholder="vendor"
+            value={key}
+            onChange={(e) => update(index, e.target.value, value)}
+          />
+          <input
+            aria-label="Metadata value pattern"
+            className={`${inputClass} flex-1`}
+            type="text"
+            placeholder="{1}"
+            value={value}
+            onChange={(e) => update(index, key, e.target.value)}
+          />
+          <button
+            className="rounded-[var(--radius)] p-1 text-[var(--fg-muted)] transition-colors hover:bg-[var(--fg-alert)]/15 hover:text-[var(--fg-alert)]"
+            onClick={() => remove(index)}
+            type="button"
+            aria-label="Remove metadata"
+          >
+            <X className="h-4 w-4" />
+          </button>
+        </div>
+      ))}
+      <button
+        className="flex items-center gap-2 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1.5 text-xs font-semibold text-[var(--fg-primary)] transition-colors hover:border-[var(--border-strong)] hover:bg-[var(--bg-subtle)] disabled:opacity-50"
+        type="button"
+        // Keys are unique, so only one can be blank at a time.
+        disabled={"" in metadata}
+        onClick={() => onChange({ ...metadata, "": "" })}
+      >
+        <Plus className="h-3 w-3" />
+        Record a value
+      </button>
+    </div>
+  );
+}
@@ -1,19 +1,21 @@
 import { useCallback, useEffect, useMemo, useRef, useState } from "react";
 import { ChevronDown, ChevronRight, Loader2, Plus } from "lucide-react";
 
-import type { Condition, ConditionGroup, Rule } from "@/types";
+import type { Condition, ConditionGroup, OnReappear, Rule } from "@/types";
 import { useRuleStore } from "@/stores/ruleStore";
 import { useSettingsStore } from "@/stores/settingsStore";
 import { useEditorStore } from "@/stores/editorStore";
 import { ActionBuilder } from "@/components/rules/ActionBuilder";
 import { ConditionBuilder } from "@/components/rules/ConditionBuilder";
+import { RecordMetadataEditor } from "@/components/rules/RecordMetadataEditor";
 import { PreviewPanel } from "@/components/preview/PreviewPanel";
 import { TemplateSaveDialog } from "@/components/templates/TemplateSaveDialog";
 import { HelpTooltip } from "@/components/ui/HelpTooltip";
-import { ocrCancelRequest, previewRuleDraft } from "@/lib/tauri";
+import { commandCancel, previewRuleDraft, ruleCounterReset } from "@/lib/tauri";
 import { matchesShortcut } from "@/lib/shortcuts";
 import type { PreviewItem } from "@/types";
 import { describeCondition } from "@/lib/conditionLabels";
+import { previewRevisionWarning } from "@/lib/ruleRevision";
 
 interface RuleEditorProps {
   mode: "empty" | "new" | "edit";
@@ -23,6 +25,8 @@ interface RuleEditorProps {
   onNewRule?: () => void;
 }
 
+const GIB = 1024 ** 3;
+
 const emptyConditions: ConditionGroup = {
   matchType: "all",
   conditions: [],
@@ -48,8 +52,12 @@ export function RuleEditor({ mode, onClose, folderId, rule, onNewRule }: RuleEdi
   const createRule = useRuleStore((state) => state.createRule);
   const updateRule = useRuleStore((state) => state.updateRule);
   const deleteRule = useRuleStore((state) => state.deleteRule);
+  const savedRevision = useRuleStore(
+    (state) => state.rules.find((saved) => saved.id === rule?.id)?.revision,
+  );
   const theme = useSettingsStore((state) => state.settings.theme);
   const previewMaxFiles = useSettingsStore((state) => state.settings.previewMaxFiles);
+  const classificationCodes = useSettingsStore((state) => state.settings.classificationCodes);
   const livePreviewMaxFiles = Math.min(previewMaxFiles, 50);
   const isMagi = theme === "magi";
 
@@ -60,6 +68,7 @@ export function RuleEditor({ mode, onClose, folderId, rule, onNewRule }: RuleEdi
   const [previewError, setPreviewError] = useState<string | null>(null);
   const [saveError, setSaveError] = useState<string | null>(null);
   const [showTemplateSave, setShowTemplateSave] = useState(false);
+  const [counterReset, setCounterReset] = useState(false);
   const setDirty = useEditorStore((state) => state.setDirty);
 
   // Live preview state
@@ -77,6 +86,19 @@ export function RuleEditor({ mode, onClose, folderId, rule, onNewRule }: RuleEdi
 
   const isOpen = mode !== "empty" && Boolean(folderId);
   const isNew = mode === "new";
+  // Only these actions keep a rule's {counter} between runs.
+  const numbersFiles = draft.actions.some((action) => {
+    switch (action.type) {
+      case "move":
+      case "copy":
+      case "sortIntoSubfolder":
+        return action.destination.includes("{counter");
+      case "rename":
+        return action.pattern.includes("{counter");
+      default:
+        return false;
+    }
+  });
   const conditionLabels = useMemo(
     () => draft.conditions.conditions.map((condition) => describeCondition(condition)),
     [draft.conditions],
@@ -112,7 +134,7 @@ export function RuleEditor({ mode, onClose, folderId, rule, onNewRule }: RuleEdi
     // Increment request ID to invalidate any pending requests
     const currentRequestId = ++previewRequestId.current;
     if (previewTokenRef.current) {
-      void ocrCancelRequest(previewTokenRef.current);
+      void commandCancel(previewTokenRef.current);
     }
     const requestToken = `preview-${Date.now()}-${currentRequestId}`;
     previewTokenRef.current = requestToken;
@@ -150,7 +172,7 @@ export function RuleEditor({ mode, onClose, folderId, rule, onNewRule }: RuleEdi
   const handleCancelPreview = useCallback(() => {
     previewRequestId.current += 1;
     if (previewTokenRef.current) {
-      void ocrCancelRequest(previewTokenRef.current);
+      void commandCancel(previewTokenRef.current);
       previewTokenRef.current = null;
     }
     setLoadingPreview(false);
@@ -177,7 +199,7 @@ export function RuleEditor({ mode, onClose, folderId, rule, onNewRule }: RuleEdi
   useEffect(() => {
     if (!isOpen || !livePreviewExpanded || draft.conditions.conditions.length === 0) {
       if (livePreviewTokenRef.current) {
-        void ocrCancelRequest(livePreviewTokenRef.current);
+        void commandCancel(livePreviewTokenRef.current);
         livePreviewTokenRef.current = null;
       }
       if (!isOpen || draft.conditions.conditions.length === 0) {
@@ -195,7 +217,7 @@ export function RuleEditor({ mode, onClose, folderId, rule, onNewRule }: RuleEdi
     // Increment request ID to invalidate any pending requests
     const currentRequestId = ++previewRequestId.current;
     if (livePreviewTokenRef.current) {
-      void ocrCancelRequest(livePreviewTokenRef.current);
+      void commandCancel(livePreviewTokenRef.current);
     }
     const requestToken = `live-${Date.now()}-${currentRequestId}`;
     livePreviewTokenRef.current = requestToken;
@@ -234,7 +256,7 @@ export function RuleEditor({ mode, onClose, folderId, rule, onNewRule }: RuleEdi
         window.clearTimeout(livePreviewTimeout.current);
       }
       if (livePreviewTokenRef.current === requestToken) {
-        void ocrCancelRequest(requestToken);
+        void commandCancel(requestToken);
         livePreviewTokenRef.current = null;
       }
     };
@@ -400,7 +422,243 @@ export function RuleEditor({ mode, onClose, folderId, rule, onNewRule }: RuleEdi
               onChange={(checked) => setDraft({ ...draft, stopProcessing: checked })}
               tooltip="When enabled, files matching this rule won't be checked against any rules below it. Disable to allow multiple rules to process the same file."
             />
+            <TogglePill
+              label={isMagi ? "REQUIRE APPROVAL" : "Require approval"}
+              checked={Boolean(draft.approvalRequired)}
+              onChange={(checked) => setDraft({ ...draft, approvalRequired: checked })}
+              tooltip="Matches wait in the review queue until you run or dismiss them. Other rules still see the file meanwhile; stop processing applies once the approval has run."
+            />
+            <TogglePill
+              label={isMagi ? "SCHEDULED" : "Run on schedule"}
+              checked={Boolean(draft.schedule)}
+              onChange={(checked) =>
+                setDraft({
+                  ...draft,
+                  schedule: checked ? { type: "daily", time: "02:00" } : null,
+                })
+              }
+              tooltip="Scheduled rules ignore new files and instead run against everything already in the folder on a timer, e.g. a nightly cleanup."
+            />
+            <TogglePill
+              label={isMagi ? "SKIP REF CHECK" : "Skip reference check"}
+              checked={Boolean(draft.skipReferenceCheck)}
+              onChange={(checked) => setDraft({ ...draft, skipReferenceCheck: checked })}
+              tooltip="Don't warn when an app, script, OCR model or volume this rule's actions use is missing, e.g. for a drive that's only plugged in now and then."
+            />
+          </div>
+          {!draft.schedule ? (
+            <div className="mt-3 flex items-center gap-3">
+              <label htmlFor="rule-min-age-input" className="text-xs text-[var(--fg-secondary)] shrink-0">
+                {isMagi ? "MIN FILE AGE:" : "Minimum file age:"}
+              </label>
+              <input
+                id="rule-min-age-input"
+                className={`${inputClass} w-24`}
+                type="number"
+                min={0}
+                placeholder="None"
+                title="Wait until the file hasn't been created or modified for this long, e.g. while a download or photo import finishes. Rules below this one don't wait."
+                value={draft.minAgeSeconds ?? ""}
+                onChange={(e) =>
+                  setDraft({
+                    ...draft,
+                    minAgeSeconds: e.target.value === "" ? null : Math.max(0, Number(e.target.value)),
+                  })
+                }
+              />
+              <span className="text-[11px] text-[var(--fg-secondary)]">seconds</span>
+            </div>
+          ) : null}
+          <div className="mt-3 flex items-center gap-3">
+            <label htmlFor="rule-path-scope-input" className="text-xs text-[var(--fg-secondary)] shrink-0">
+              {isMagi ? "ONLY IN:" : "Only in subfolder:"}
+            </label>
+            <input
+              id="rule-path-scope-input"
+              className={`${inputClass} w-64`}
+              type="text"
+              placeholder="Whole folder"
+              title="A folder inside the watched folder, e.g. Telegram Desktop, or a glob such as Telegram*/**. Files elsewhere in the folder skip this rule."
+              value={draft.pathScope ?? ""}
+              onChange={(e) =>
+                setDraft({
+                  ...draft,
+                  pathScope: e.target.value.trim() === "" ? null : e.target.value,
+                })
+              }
+            />
+          </div>
+          <div className="mt-3 flex items-center gap-3">
+            <label htmlFor="rule-byte-budget-input" className="text-xs text-[var(--fg-secondary)] shrink-0">
+              {isMagi ? "MONTHLY BUDGET:" : "Monthly transfer budget:"}
+            </label>
+            <input
+              id="rule-byte-budget-input"
+              className={`${inputClass} w-24`}
+              type="number"
+              min={0}
+              step="any"
+              placeholder="No limit"
+              title="Most data this rule may move, copy or archive in a calendar month. A file that would take it over is skipped until the next month."
+              value={draft.monthlyByteBudget == null ? "" : draft.monthlyByteBudget / GIB}
+              onChange={(e) =>
+                setDraft({
+                  ...draft,
+                  monthlyByteBudget:
+                    e.target.value === "" ? null : Math.round(Math.max(0, Number(e.target.value)) * GIB),
+                })
+              }
+            />
+            <span className="text-[11px] text-[var(--fg-secondary)]">GB</span>
           </div>
+          {!isNew && rule && numbersFiles && (
+            <div className="mt-3 flex items-center gap-3">
+              <span className="text-xs text-[var(--fg-secondary)] shrink-0">
+                {isMagi ? "COUNTER:" : "File counter:"}
+              </span>
+              <button
+                type="button"
+                disabled={counterReset}
+                title="Numbers files from 1 again. The next file this rule moves, copies or renames gets 1."
+                onClick={async () => {
+                  try {
+                    await ruleCounterReset(rule.id);
+                    setCounterReset(true);
+                  } catch (error) {
+                    setSaveError(error instanceof Error ? error.message : String(error));
+                  }
+                }}
+                className="px-3 py-1 text-xs font-medium rounded text-[var(--fg-secondary)] hover:text-[var(--fg-primary)] hover:bg-[var(--bg-subtle)] transition-colors disabled:opacity-60"
+              >
+                {counterReset ? "Starts at 1" : "Reset to 1"}
+              </button>
+            </div>
+          )}
+          <div className="mt-3 flex items-center gap-3">
+            <label htmlFor="rule-on-reappear-input" className="text-xs text-[var(--fg-secondary)] shrink-0">
+              {isMagi ? "ON RETURN:" : "If a moved file comes back:"}
+            </label>
+            <select
+              id="rule-on-reappear-input"
+              className={`${inputClass} w-auto`}
+              title="A file this rule moved away that reappears with the same contents while the moved copy is still there, such as one a sync client downloads again."
+              value={draft.onReappear?.type ?? ""}
+              onChange={(e) => {
+                const type = e.target.value;
+                setDraft({
+                  ...draft,
+                  onReappear:
+                    type === ""
+                      ? null
+                      : type === "moveToReview"
+                        ? { type: "moveToReview", destination: "" }
+                        : ({ type } as OnReappear),
+                });
+              }}
+            >
+              <option value="">Follow setting</option>
+              <option value="skipSilently">Skip it</option>
+              <option value="processAgain">Process it again</option>
+              <option value="deleteIncoming">Move it to the trash</option>
+              <option value="moveToReview">Move it to a folder</option>
+            </select>
+            {draft.onReappear?.type === "moveToReview" ? (
+              <input
+                aria-label="Folder for returning files"
+                className={`${inputClass} flex-1`}
+                type="text"
+                placeholder="~/Review"
+                value={draft.onReappear.destination}
+                onChange={(e) =>
+                  setDraft({ ...draft, onReappear: { type: "moveToReview", destination: e.target.value } })
+                }
+              />
+            ) : null}
+          </div>
+          {classificationCodes.length > 0 || draft.classification ? (
+            <div className="mt-3 flex items-center gap-3">
+              <label htmlFor="rule-classification-input" className="text-xs text-[var(--fg-secondary)] shrink-0">
+                {isMagi ? "CLASSIFICATION:" : "Classification:"}
+              </label>
+              <select
+                id="rule-classification-input"
+                className={`${inputClass} w-auto`}
+                title="Retention code recorded with everything this rule does, and available as {classification} in patterns."
+                value={draft.classification ?? ""}
+                onChange={(e) =>
+                  setDraft({ ...draft, classification: e.target.value === "" ? null : e.target.value })
+                }
+              >
+                <option value="">None</option>
+                {classificationCodes.map((code) => (
+                  <option key={code.code} value={code.code} title={code.description}>
+                    {code.label ? `${code.code} — ${code.label}` : code.code}
+                  </option>
+                ))}
+                {draft.classification &&
+                !classificationCodes.some((code) => code.code === draft.classification) ? (
+                  <option value={draft.classification}>{draft.classification} (not in settings)</option>
+                ) : null}
+              </select>
+            </div>
+          ) : null}
+          <div className="mt-3">
+            <div className="mb-2 flex items-center gap-2">
+              <span className="text-xs text-[var(--fg-secondary)]">
+                {isMagi ? "RECORD WITH EACH MATCH:" : "Record with each match:"}
+              </span>
+              <HelpTooltip content="Values worked out from the conditions' captures and file tokens when the rule matches, e.g. vendor → {1}. They're kept with the match, shown in the activity log and manifests, and can be queried later." />
+            </div>
+            <RecordMetadataEditor
+              metadata={draft.recordMetadata ?? {}}
+              onChange={(recordMetadata) => setDraft({ ...draft, recordMetadata })}
+              inputClass={inputClass}
+            />
+          </div>
+          {draft.schedule ? (
+            <div className="mt-3 flex items-center gap-3">
+              <select
+                className={`${inputClass} w-auto`}
+                value={draft.schedule.type}
+                aria-label="Schedule type"
+                onChange={(e) =>
+                  setDraft({
+                    ...draft,
+                    schedule:
+                      e.target.value === "interval"
+                        ? { type: "interval", minutes: 60 }
+                        : { type: "daily", time: "02:00" },
+                  })
+                }
+              >
+                <option value="daily">Every day at</option>
+                <option value="interval">Every N minutes</option>
+              </select>
+              {draft.schedule.type === "daily" ? (
+                <input
+                  className={`${inputClass} w-auto`}
+                  type="time"
+                  value={draft.schedule.time}
+                  onChange={(e) =>
+                    setDraft({ ...draft, schedule: { type: "daily", time: e.target.value } })
+                  }
+                />
+              ) : (
+                <input
+                  className={`${inputClass} w-28`}
+                  type="number"
+                  min={1}
+                  value={draft.schedule.minutes}
+                  onChange={(e) =>
+                    setDraft({
+                      ...draft,
+                      schedule: { type: "interval", minutes: Number(e.target.value) },
+                    })
+                  }
+                />
+              )}
+            </div>
+          ) : null}
         </div>
 
         {/* Conditions Section */}
@@ -513,6 +771,7 @@ export function RuleEditor({ mode, onClose, folderId, rule, onNewRule }: RuleEdi
         error={previewError}
         ruleName={draft.name}
         conditionLabels={conditionLabels}
+        warning={previewRevisionWarning(previewResults, savedRevision)}
       />
       <TemplateSaveDialog
         open={showTemplateSave}
@@ -540,6 +799,13 @@ function validateCondition(condition: Condition): string | null {
     case "name":
     case "extension":
     case "fullName":
+    case "path":
+    case "parentFolder":
+    case "relativePath":
+    case "activeApplication":
+    case "mimeType":
+    case "mediaCodec":
+    case "tag":
       if (!condition.value.trim()) return "Provide a value for the condition.";
       return null;
     case "size":
@@ -548,6 +814,9 @@ function validateCondition(condition: Condition): string | null {
     case "shellScript":
       if (!condition.command.trim()) return "Provide a shell script command.";
       return null;
+    case "eventSource":
+      if (condition.sources.length === 0) return "Pick at least one event source.";
+      return null;
     case "nested":
       return validateConditionGroup({
         matchType: condition.matchType,
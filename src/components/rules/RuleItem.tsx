@@ -1,8 +1,9 @@
 import { memo, useState } from "react";
-import { Copy, GripVertical, Trash2 } from "lucide-react";
+import { AlertTriangle, Copy, GripVertical, Trash2 } from "lucide-react";
 
 import type { Condition, Rule } from "@/types";
 import { ConfirmDialog } from "@/components/ui/ConfirmDialog";
+import { useFolderStore } from "@/stores/folderStore";
 
 
 interface RuleItemProps {
@@ -45,6 +46,7 @@ export const RuleItem = memo(function RuleItem({
   const [showDeleteConfirm, setShowDeleteConfirm] = useState(false);
   const triggerSummary = summarizeConditions(rule.conditions.conditions);
   const actionSummary = summarizeAction(rule.actions[0]);
+  const referenceWarnings = rule.referenceWarnings ?? [];
   const activityParts: string[] = [];
   if (lastActivityAt) {
     activityParts.push(`Last run ${formatTimestamp(lastActivityAt)}`);
@@ -124,6 +126,18 @@ export const RuleItem = memo(function RuleItem({
             {activityParts.join(" · ")}
           </span>
         ) : null}
+        {referenceWarnings.length > 0 ? (
+          <span
+            className={`flex items-center gap-1 truncate ${metaClass} text-[var(--fg-alert)]`}
+            title={referenceWarnings.join("\n")}
+          >
+            <AlertTriangle className="h-3 w-3 shrink-0" />
+            <span className="truncate">
+              {referenceWarnings[0]}
+              {referenceWarnings.length > 1 ? ` (+${referenceWarnings.length - 1} more)` : ""}
+            </span>
+          </span>
+        ) : null}
       </button>
 
       {selected && (
@@ -170,6 +184,12 @@ function summarizeConditions(conditions: Condition[]) {
       return `*.${first.value || "…"}`;
     case "fullName":
       return `name ${first.operator} ${first.value || "…"}`;
+    case "path":
+      return `path ${first.operator} ${first.value || "…"}`;
+    case "parentFolder":
+      return `in folder ${first.operator} ${first.value || "…"}`;
+    case "relativePath":
+      return `relative path ${first.operator} ${first.value || "…"}`;
     case "contents":
       return `contents ${first.operator} ${first.value || "…"}`;
     case "kind":
@@ -180,10 +200,46 @@ function summarizeConditions(conditions: Condition[]) {
     case "dateModified":
     case "dateAdded":
       return `${first.type.replace("date", "").toLowerCase()} ${first.operator.type}`;
+    case "exifDate":
+      return `taken ${first.operator.type}`;
     case "currentTime":
       return `time ${first.operator.type}`;
     case "shellScript":
       return "shell script";
+    case "archiveContains":
+      return `archive has ${first.nameCondition.operator} ${first.nameCondition.value || "…"}`;
+    case "faceCount":
+      return `faces ${first.operator.type} ${first.value}`;
+    case "pageCount":
+      return `pages ${first.operator.type} ${first.value}`;
+    case "mediaDuration":
+      return `length ${first.operator.type} ${first.value} ${first.unit}`;
+    case "mediaCodec":
+      return `codec ${first.operator} ${first.value || "…"}`;
+    case "imageDimensions":
+      return [
+        first.width && `w ${first.width.operator.type} ${first.width.value}`,
+        first.height && `h ${first.height.operator.type} ${first.height.value}`,
+        first.shape,
+      ].filter(Boolean).join(" ") || "any size";
+    case "isScreenshot":
+      return first.negate ? "not screenshot" : "screenshot";
+    case "isDuplicate":
+      return first.negate ? "not duplicate" : "duplicate";
+    case "groupSize":
+      return `group ${first.operator.type} ${first.value}`;
+    case "activeApplication":
+      return `app ${first.operator} ${first.value || "…"}`;
+    case "mimeType":
+      return `mime ${first.operator} ${first.value || "…"}`;
+    case "tag":
+      return `tag ${first.operator} ${first.value || "…"}`;
+    case "eventSource":
+      return `from ${first.sources.join("/") || "…"}`;
+    case "csvColumn":
+      return `csv ${first.column.type === "index" ? first.column.index : first.column.name} ${first.condition.operator} ${first.condition.value || "…"}`;
+    case "existsAtDestination":
+      return `${first.negate ? "not in" : "exists in"} ${first.destinationPattern || "…"}`;
     case "nested":
       return `${first.matchType} (${first.conditions.length})`;
     default:
@@ -218,6 +274,15 @@ function summarizeAction(action?: Rule["actions"][number]) {
       return "open";
     case "makePdfSearchable":
       return "ocr pdf";
+    case "createFolderStructure":
+      return `mkdir → ${action.baseDestination || "…"}`;
+    case "dispatchToFolder":
+      return `dispatch → ${
+        useFolderStore.getState().folders.find((folder) => folder.id === action.folderId)?.name ??
+        "…"
+      }`;
+    case "setTags":
+      return `${action.mode === "replace" ? "retag" : "tag"} ${action.tags.join(", ") || "…"}`;
     case "pause":
       return `pause ${action.durationSeconds}s`;
     case "continue":
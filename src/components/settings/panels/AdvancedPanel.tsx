@@ -1,9 +1,76 @@
-import { useSettingsStore } from "@/stores/settingsStore";
+import { useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { save } from "@tauri-apps/plugin-dialog";
+import { writeTextFile } from "@tauri-apps/plugin-fs";
+import { MagiSelect } from "@/components/ui/MagiSelect";
+import { eventJournalExport, permissionsStatus, thumbnailCacheClear } from "@/lib/tauri";
+import { ProcessedMarkerMode, useSettingsStore } from "@/stores/settingsStore";
+import type { AccessState, OnReappear, PermissionStatus, ProtectedCategory } from "@/types";
+import { SettingRow, SettingToggle } from "../SettingsShared";
+import { BackupSection } from "./BackupSection";
+import { ClassificationSection } from "./ClassificationSection";
+import { KindMappingsSection } from "./KindMappingsSection";
+
+const categoryLabels: Record<ProtectedCategory, string> = {
+    desktop: "Desktop",
+    documents: "Documents",
+    downloads: "Downloads",
+    removableVolume: "Removable volumes",
+    networkVolume: "Network volumes",
+};
+
+const stateLabels: Record<AccessState, string> = {
+    granted: "Allowed",
+    denied: "Not allowed",
+    notApplicable: "None mounted",
+};
 
 export function AdvancedPanel() {
     const settings = useSettingsStore((state) => state.settings);
     const setSettings = useSettingsStore((state) => state.setSettings);
     const saveSettings = useSettingsStore((state) => state.saveSettings);
+    const [permissions, setPermissions] = useState<PermissionStatus[]>([]);
+    const [exportError, setExportError] = useState<string | null>(null);
+    const [thumbnailsCleared, setThumbnailsCleared] = useState<string | null>(null);
+
+    useEffect(() => {
+        const refresh = () => {
+            permissionsStatus()
+                .then(setPermissions)
+                .catch(() => setPermissions([]));
+        };
+        refresh();
+        const unlisten = listen("permission://needed", refresh);
+        return () => {
+            void unlisten.then((fn) => fn());
+        };
+    }, []);
+
+    const anyDenied = permissions.some((status) => status.state === "denied");
+
+    const handleJournalExport = async () => {
+        setExportError(null);
+        try {
+            const timestamp = new Date().toISOString().replace(/[:.]/g, "-");
+            const path = await save({
+                defaultPath: `filedispatch-events-${timestamp}.jsonl`,
+                filters: [{ name: "Event journal", extensions: ["jsonl"] }],
+            });
+            if (!path) return;
+            await writeTextFile(path, await eventJournalExport());
+        } catch (err) {
+            setExportError(String(err));
+        }
+    };
+
+    const handleThumbnailClear = async () => {
+        try {
+            const removed = await thumbnailCacheClear();
+            setThumbnailsCleared(`Removed ${removed} thumbnail${removed === 1 ? "" : "s"}`);
+        } catch (err) {
+            setThumbnailsCleared(String(err));
+        }
+    };
 
     return (
         <div className="space-y-6">
@@ -25,6 +92,314 @@ export function AdvancedPanel() {
                     placeholder="e.g. node_modules, .git, *.tmp"
                 />
             </section>
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Screenshot names
+                </h3>
+                <p className="mb-2 text-xs text-[var(--fg-muted)]">
+                    Is Screenshot already knows how macOS, Windows, Linux desktops and phones name
+                    screenshots. Add the prefixes other tools use, one per line.
+                </p>
+                <textarea
+                    className="w-full rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] p-3 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                    rows={3}
+                    value={settings.screenshotNamePrefixes.join("\n")}
+                    onChange={(e) => {
+                        setSettings({ screenshotNamePrefixes: e.target.value.split(/\n+/).filter(Boolean) });
+                        void saveSettings();
+                    }}
+                    placeholder="e.g. Shottr, Snagit"
+                />
+            </section>
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Indexed match metadata
+                </h3>
+                <p className="mb-2 text-xs text-[var(--fg-muted)]">
+                    Keys rules record with their matches that are looked up often, one per line.
+                    Queries by these keys stay fast however many matches there are.
+                </p>
+                <textarea
+                    className="w-full rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] p-3 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                    rows={3}
+                    value={settings.matchMetadataIndexedKeys.join("\n")}
+                    onChange={(e) => {
+                        setSettings({ matchMetadataIndexedKeys: e.target.value.split(/\n+/).filter(Boolean) });
+                        void saveSettings();
+                    }}
+                    placeholder="e.g. vendor"
+                />
+            </section>
+            <KindMappingsSection />
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Processed markers
+                </h3>
+                <div className="space-y-3">
+                    <SettingToggle
+                        title="Mark processed files"
+                        description="Tag files after a rule runs so they aren't processed again on another machine or after a reset"
+                        checked={settings.writeProcessedMarkers}
+                        onChange={(checked) => {
+                            setSettings({ writeProcessedMarkers: checked });
+                            void saveSettings();
+                        }}
+                    />
+                    <SettingRow
+                        title="Existing markers"
+                        description="Whether a marker is enough to skip a rule"
+                    >
+                        <MagiSelect
+                            width="w-40"
+                            value={settings.processedMarkerMode}
+                            onChange={(val) => {
+                                setSettings({ processedMarkerMode: val as ProcessedMarkerMode });
+                                void saveSettings();
+                            }}
+                            options={[
+                                { label: "Trust", value: "trust" },
+                                { label: "Verify contents", value: "verify" },
+                                { label: "Ignore", value: "ignore" },
+                            ]}
+                            ariaLabel="Processed marker handling"
+                        />
+                    </SettingRow>
+                    <SettingRow
+                        title="Files that come back"
+                        description="A file a rule moved away that reappears, such as one a sync client downloads again, while the moved copy is still there"
+                    >
+                        <MagiSelect
+                            width="w-40"
+                            value={settings.onReappear.type}
+                            onChange={(val) => {
+                                setSettings({
+                                    onReappear:
+                                        val === "moveToReview"
+                                            ? { type: "moveToReview", destination: "" }
+                                            : ({ type: val } as OnReappear),
+                                });
+                                void saveSettings();
+                            }}
+                            options={[
+                                { label: "Skip", value: "skipSilently" },
+                                { label: "Process again", value: "processAgain" },
+                                { label: "Move to trash", value: "deleteIncoming" },
+                                { label: "Move to a folder", value: "moveToReview" },
+                            ]}
+                            ariaLabel="Returning file handling"
+                        />
+                    </SettingRow>
+                    {settings.onReappear.type === "moveToReview" ? (
+                        <SettingRow title="Review folder" description="Where returning files are moved">
+                            <input
+                                className="w-48 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                                type="text"
+                                placeholder="~/Review"
+                                value={settings.onReappear.destination}
+                                onChange={(e) => {
+                                    setSettings({
+                                        onReappear: { type: "moveToReview", destination: e.target.value },
+                                    });
+                                    void saveSettings();
+                                }}
+                            />
+                        </SettingRow>
+                    ) : null}
+                    <SettingToggle
+                        title="One notification per message"
+                        description="Skip a notification when an earlier rule already showed the same message for the file"
+                        checked={settings.dedupeActions.notify}
+                        onChange={(checked) => {
+                            setSettings({ dedupeActions: { ...settings.dedupeActions, notify: checked } });
+                            void saveSettings();
+                        }}
+                    />
+                    <SettingToggle
+                        title="One move or copy per destination"
+                        description="Skip a move, copy or sort when an earlier rule already put the file in the same place"
+                        checked={settings.dedupeActions.fileOperations}
+                        onChange={(checked) => {
+                            setSettings({
+                                dedupeActions: { ...settings.dedupeActions, fileOperations: checked },
+                            });
+                            void saveSettings();
+                        }}
+                    />
+                    <SettingRow
+                        title="Unique suffix"
+                        description="Added to every name a rule files with the unique-suffix conflict policy, e.g. scan.1717423001123.a3f9.officepc.pdf. Use {millis}, {random} and {host}; {random} is required."
+                    >
+                        <input
+                            className="w-48 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                            type="text"
+                            placeholder="{millis}.{random}.{host}"
+                            value={settings.uniqueSuffixFormat}
+                            onChange={(e) => {
+                                setSettings({ uniqueSuffixFormat: e.target.value });
+                                void saveSettings();
+                            }}
+                        />
+                    </SettingRow>
+                </div>
+            </section>
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Watched folders
+                </h3>
+                <SettingToggle
+                    title="Follow renamed folders"
+                    description="Update a watched folder's path when it's renamed, instead of marking it unavailable"
+                    checked={settings.followFolderRenames}
+                    onChange={(checked) => {
+                        setSettings({ followFolderRenames: checked });
+                        void saveSettings();
+                    }}
+                />
+                <SettingToggle
+                    title="Keep rule revisions"
+                    description="Save a copy of a rule each time it changes, so log entries can show the version that ran"
+                    checked={settings.keepRuleRevisions}
+                    onChange={(checked) => {
+                        setSettings({ keepRuleRevisions: checked });
+                        void saveSettings();
+                    }}
+                />
+            </section>
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Thumbnails
+                </h3>
+                <div className="space-y-3">
+                    <SettingRow
+                        title="Cache size (MB)"
+                        description="Thumbnails for notifications and the activity log; the least recently shown go first"
+                    >
+                        <input
+                            className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                            type="number"
+                            min={1}
+                            value={settings.thumbnailCacheMaxMb}
+                            onChange={(e) => {
+                                setSettings({ thumbnailCacheMaxMb: Number(e.target.value) });
+                                void saveSettings();
+                            }}
+                        />
+                    </SettingRow>
+                    <SettingRow
+                        title="Clear thumbnails"
+                        description={thumbnailsCleared ?? "Log entries keep their text but lose their pictures"}
+                    >
+                        <button
+                            type="button"
+                            className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-xs font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)]"
+                            onClick={() => void handleThumbnailClear()}
+                        >
+                            Clear
+                        </button>
+                    </SettingRow>
+                </div>
+            </section>
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Database
+                </h3>
+                <div className="space-y-3">
+                    <SettingRow
+                        title="Batch commits (ms)"
+                        description="Commit the records of events finished within this window together; faster on spinning disks, but a crash loses the waiting batch. 0 commits each event at once"
+                    >
+                        <input
+                            className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                            type="number"
+                            min={0}
+                            step={50}
+                            value={settings.dbBatchCommitMs}
+                            onChange={(e) => {
+                                setSettings({ dbBatchCommitMs: Number(e.target.value) });
+                                void saveSettings();
+                            }}
+                        />
+                    </SettingRow>
+                </div>
+            </section>
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Event journal
+                </h3>
+                <div className="space-y-3">
+                    <SettingToggle
+                        title="Record file events"
+                        description="Keep a journal of every event the watcher reports and what became of it"
+                        checked={settings.eventJournalEnabled}
+                        onChange={(checked) => {
+                            setSettings({ eventJournalEnabled: checked });
+                            void saveSettings();
+                        }}
+                    />
+                    <SettingRow
+                        title="Events kept"
+                        description="The oldest events are dropped past this many"
+                    >
+                        <input
+                            className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                            type="number"
+                            min={100}
+                            step={1000}
+                            value={settings.eventJournalMaxEntries}
+                            onChange={(e) => {
+                                setSettings({ eventJournalMaxEntries: Number(e.target.value) });
+                                void saveSettings();
+                            }}
+                        />
+                    </SettingRow>
+                    <SettingRow
+                        title="Export journal"
+                        description="Save the recorded events as JSON lines to attach to a bug report"
+                    >
+                        <button
+                            type="button"
+                            className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-xs font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)]"
+                            onClick={() => void handleJournalExport()}
+                        >
+                            Export…
+                        </button>
+                    </SettingRow>
+                    {exportError ? (
+                        <p className="text-xs text-[var(--fg-alert)]">{exportError}</p>
+                    ) : null}
+                </div>
+            </section>
+            <ClassificationSection />
+            <BackupSection />
+            {anyDenied ? (
+                <section>
+                    <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                        Folder access
+                    </h3>
+                    <p className="mb-2 text-xs text-[var(--fg-muted)]">
+                        macOS is blocking access to some locations. Allow File Dispatch under System
+                        Settings → Privacy &amp; Security → Files and Folders, then reopen this panel.
+                    </p>
+                    <ul className="space-y-1 text-xs">
+                        {permissions.map((status) => (
+                            <li key={status.category} className="flex justify-between">
+                                <span className="text-[var(--fg-secondary)]">
+                                    {categoryLabels[status.category]}
+                                </span>
+                                <span
+                                    className={
+                                        status.state === "denied"
+                                            ? "text-[var(--fg-alert)]"
+                                            : "text-[var(--fg-muted)]"
+                                    }
+                                >
+                                    {stateLabels[status.state]}
+                                </span>
+                            </li>
+                        ))}
+                    </ul>
+                </section>
+            ) : null}
         </div>
     );
 }
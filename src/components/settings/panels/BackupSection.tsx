@@ -0,0 +1,202 @@
+import { useEffect, useState } from "react";
+import { ask, open } from "@tauri-apps/plugin-dialog";
+
+import { backupList, backupNow, backupRestore } from "@/lib/tauri";
+import { type AutoBackupSettings, useSettingsStore } from "@/stores/settingsStore";
+import type { BackupError, BackupInfo } from "@/types";
+import { SettingRow, SettingToggle } from "../SettingsShared";
+
+const numberFieldClass =
+    "w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]";
+
+const buttonClass =
+    "rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-xs font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)] disabled:opacity-50";
+
+function formatBytes(bytes: number): string {
+    if (bytes < 1024) return `${bytes} B`;
+    if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+    return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
+
+function describeError(err: unknown): string {
+    if (err && typeof err === "object" && "message" in err) {
+        return (err as BackupError).message;
+    }
+    return String(err);
+}
+
+export function BackupSection() {
+    const settings = useSettingsStore((state) => state.settings);
+    const setSettings = useSettingsStore((state) => state.setSettings);
+    const saveSettings = useSettingsStore((state) => state.saveSettings);
+    const options = settings.autoBackup;
+    const [backups, setBackups] = useState<BackupInfo[]>([]);
+    const [busy, setBusy] = useState(false);
+    const [error, setError] = useState<string | null>(null);
+    // Only kept while the page is open; never saved.
+    const [password, setPassword] = useState("");
+
+    const refresh = () =>
+        backupList()
+            .then(setBackups)
+            .catch((err) => setError(describeError(err)));
+
+    useEffect(() => {
+        void refresh();
+    }, []);
+
+    const setOptions = (partial: Partial<AutoBackupSettings>) => {
+        setSettings({ autoBackup: { ...options, ...partial } });
+        void saveSettings().then(refresh);
+    };
+
+    const handleChooseFolder = async () => {
+        const selected = await open({ directory: true, multiple: false });
+        if (typeof selected === "string") {
+            setOptions({ destinationDir: selected });
+        }
+    };
+
+    const handleBackupNow = async () => {
+        setBusy(true);
+        setError(null);
+        try {
+            await backupNow(password || undefined);
+            await refresh();
+        } catch (err) {
+            setError(describeError(err));
+        } finally {
+            setBusy(false);
+        }
+    };
+
+    const handleRestore = async (backup: BackupInfo) => {
+        const confirmed = await ask(
+            `Replace your rules, folders and settings with the backup from ${new Date(backup.createdAt).toLocaleString()}? The current database is kept as file-dispatch.db.pre-restore.`,
+            { title: "Restore backup", kind: "warning" },
+        );
+        if (!confirmed) return;
+        setBusy(true);
+        setError(null);
+        try {
+            await backupRestore(backup.id, password || undefined);
+            // Every store holds data from the old database; start over.
+            window.location.reload();
+        } catch (err) {
+            setError(describeError(err));
+            setBusy(false);
+        }
+    };
+
+    return (
+        <section>
+            <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">Backups</h3>
+            <div className="space-y-3">
+                <SettingToggle
+                    title="Back up automatically"
+                    description="Save a copy of the database and settings on a schedule"
+                    checked={options.enabled}
+                    onChange={(checked) => setOptions({ enabled: checked })}
+                />
+                <SettingRow title="Every (days)" description="Days between automatic backups">
+                    <input
+                        className={numberFieldClass}
+                        type="number"
+                        min={1}
+                        value={options.intervalDays}
+                        onChange={(e) => setOptions({ intervalDays: Number(e.target.value) })}
+                    />
+                </SettingRow>
+                <SettingRow title="Backups kept" description="Older backups are deleted (0 = keep all)">
+                    <input
+                        className={numberFieldClass}
+                        type="number"
+                        min={0}
+                        value={options.keepCount}
+                        onChange={(e) => setOptions({ keepCount: Number(e.target.value) })}
+                    />
+                </SettingRow>
+                <SettingRow
+                    title="Backup folder"
+                    description={
+                        options.destinationDir ??
+                        "Next to the database. Pick a folder on another drive to survive a disk failure"
+                    }
+                >
+                    <div className="flex gap-2">
+                        {options.destinationDir ? (
+                            <button
+                                type="button"
+                                className={buttonClass}
+                                onClick={() => setOptions({ destinationDir: null })}
+                            >
+                                Default
+                            </button>
+                        ) : null}
+                        <button
+                            type="button"
+                            className={buttonClass}
+                            onClick={() => void handleChooseFolder()}
+                        >
+                            Choose…
+                        </button>
+                    </div>
+                </SettingRow>
+                <SettingRow
+                    title="Backup password"
+                    description="Encrypts backups made with Back up now, and opens encrypted ones to restore"
+                >
+                    <input
+                        className={numberFieldClass}
+                        type="password"
+                        autoComplete="new-password"
+                        value={password}
+                        onChange={(e) => setPassword(e.target.value)}
+                    />
+                </SettingRow>
+                <SettingRow title="Back up now" description="Make a backup right away">
+                    <button
+                        type="button"
+                        className={buttonClass}
+                        disabled={busy}
+                        onClick={() => void handleBackupNow()}
+                    >
+                        Back up
+                    </button>
+                </SettingRow>
+                {error ? <p className="text-xs text-[var(--fg-alert)]">{error}</p> : null}
+                {backups.length > 0 ? (
+                    <ul className="space-y-1 text-xs">
+                        {backups.map((backup) => (
+                            <li
+                                key={backup.id}
+                                className="flex items-center justify-between gap-4 rounded-[var(--radius)] border border-[var(--border-main)] px-3 py-2"
+                            >
+                                <span className="text-[var(--fg-secondary)]">
+                                    {new Date(backup.createdAt).toLocaleString()} ·{" "}
+                                    {formatBytes(backup.sizeBytes)}
+                                </span>
+                                {backup.status === "valid" || backup.status === "encrypted" ? (
+                                    <button
+                                        type="button"
+                                        className={buttonClass}
+                                        disabled={busy}
+                                        onClick={() => void handleRestore(backup)}
+                                    >
+                                        {backup.status === "encrypted" ? "Restore (encrypted)…" : "Restore…"}
+                                    </button>
+                                ) : (
+                                    <span className="text-[var(--fg-alert)]">
+                                        {backup.status === "newerSchema"
+                                            ? "Made by a newer version"
+                                            : (backup.problem ?? "Damaged")}
+                                    </span>
+                                )}
+                            </li>
+                        ))}
+                    </ul>
+                ) : null}
+            </div>
+        </section>
+    );
+}
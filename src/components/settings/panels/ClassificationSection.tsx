@@ -0,0 +1,152 @@
+import { useEffect, useState } from "react";
+
+import { classificationReport } from "@/lib/tauri";
+import { type ClassificationCode, useSettingsStore } from "@/stores/settingsStore";
+import type { ClassificationTotal } from "@/types";
+import { SettingToggle } from "../SettingsShared";
+
+const fieldClass =
+    "rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]";
+
+const buttonClass =
+    "rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-xs font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)] disabled:opacity-50";
+
+function formatBytes(bytes: number): string {
+    if (bytes < 1024) return `${bytes} B`;
+    if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+    if (bytes < 1024 * 1024 * 1024) return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+    return `${(bytes / (1024 * 1024 * 1024)).toFixed(1)} GB`;
+}
+
+function isoDay(date: Date): string {
+    const local = new Date(date.getTime() - date.getTimezoneOffset() * 60000);
+    return local.toISOString().slice(0, 10);
+}
+
+export function ClassificationSection() {
+    const settings = useSettingsStore((state) => state.settings);
+    const setSettings = useSettingsStore((state) => state.setSettings);
+    const saveSettings = useSettingsStore((state) => state.saveSettings);
+    const codes = settings.classificationCodes;
+    const [from, setFrom] = useState(() => isoDay(new Date(Date.now() - 30 * 86400000)));
+    const [to, setTo] = useState(() => isoDay(new Date()));
+    const [report, setReport] = useState<ClassificationTotal[]>([]);
+    const [error, setError] = useState<string | null>(null);
+
+    useEffect(() => {
+        if (!from || !to) return;
+        classificationReport(from, to)
+            .then((totals) => {
+                setReport(totals);
+                setError(null);
+            })
+            .catch((err) => setError(String(err)));
+    }, [from, to]);
+
+    // Codes are only saved once they have a name; rules refer to them by it.
+    const setCodes = (next: ClassificationCode[]) => {
+        setSettings({ classificationCodes: next });
+        if (next.every((code) => code.code.trim() !== "")) {
+            void saveSettings();
+        }
+    };
+
+    const updateCode = (index: number, partial: Partial<ClassificationCode>) =>
+        setCodes(codes.map((code, i) => (i === index ? { ...code, ...partial } : code)));
+
+    return (
+        <section>
+            <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">Classifications</h3>
+            <p className="mb-2 text-xs text-[var(--fg-muted)]">
+                Retention codes rules can be tagged with. A rule's code is recorded in the log and undo
+                history of everything it does, and is available as {"{classification}"} in patterns.
+                Editing a code here doesn't change what was already recorded.
+            </p>
+            <div className="space-y-3">
+                {codes.map((code, index) => (
+                    <div key={index} className="flex items-center gap-2">
+                        <input
+                            className={`${fieldClass} w-28`}
+                            placeholder="Code"
+                            aria-label="Code"
+                            value={code.code}
+                            onChange={(e) => updateCode(index, { code: e.target.value.trim() })}
+                        />
+                        <input
+                            className={`${fieldClass} w-40`}
+                            placeholder="Label"
+                            aria-label="Label"
+                            value={code.label}
+                            onChange={(e) => updateCode(index, { label: e.target.value })}
+                        />
+                        <input
+                            className={`${fieldClass} flex-1`}
+                            placeholder="Description"
+                            aria-label="Description"
+                            value={code.description}
+                            onChange={(e) => updateCode(index, { description: e.target.value })}
+                        />
+                        <button
+                            type="button"
+                            className={buttonClass}
+                            onClick={() => setCodes(codes.filter((_, i) => i !== index))}
+                        >
+                            Remove
+                        </button>
+                    </div>
+                ))}
+                <button
+                    type="button"
+                    className={buttonClass}
+                    onClick={() => setCodes([...codes, { code: "", label: "", description: "" }])}
+                >
+                    Add code
+                </button>
+                <SettingToggle
+                    title="Require a classification"
+                    description="Rules without one can't be saved or imported"
+                    checked={settings.requireClassification}
+                    onChange={(checked) => {
+                        setSettings({ requireClassification: checked });
+                        void saveSettings();
+                    }}
+                />
+                <div className="flex items-center gap-2 text-xs text-[var(--fg-secondary)]">
+                    <span>Report from</span>
+                    <input
+                        className={fieldClass}
+                        type="date"
+                        value={from}
+                        onChange={(e) => setFrom(e.target.value)}
+                    />
+                    <span>to</span>
+                    <input
+                        className={fieldClass}
+                        type="date"
+                        value={to}
+                        onChange={(e) => setTo(e.target.value)}
+                    />
+                </div>
+                {error ? <p className="text-xs text-[var(--fg-alert)]">{error}</p> : null}
+                {report.length === 0 ? (
+                    <p className="text-xs text-[var(--fg-muted)]">Nothing classified in this range.</p>
+                ) : (
+                    <table className="w-full text-xs text-[var(--fg-secondary)]">
+                        <tbody>
+                            {report.map((total) => (
+                                <tr key={total.code}>
+                                    <td className="py-1 font-semibold text-[var(--fg-primary)]">
+                                        {total.code}
+                                    </td>
+                                    <td className="py-1">{total.label ?? "Removed"}</td>
+                                    <td className="py-1 text-right">{total.files} files</td>
+                                    <td className="py-1 text-right">{formatBytes(total.bytes)}</td>
+                                </tr>
+                            ))}
+                        </tbody>
+                    </table>
+                )}
+            </div>
+        </section>
+    );
+}
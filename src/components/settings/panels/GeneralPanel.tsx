@@ -1,8 +1,10 @@
-import { useEffect } from "react";
+import { useEffect, useState } from "react";
 import { MagiSelect } from "@/components/ui/MagiSelect";
 import { enable, disable } from "@tauri-apps/plugin-autostart";
 
+import { undoStats } from "@/lib/tauri";
 import { ThemeMode, useSettingsStore } from "@/stores/settingsStore";
+import type { UndoStats } from "@/types";
 import { SettingRow, SettingToggle } from "../SettingsShared";
 
 export function GeneralPanel() {
@@ -10,6 +12,17 @@ export function GeneralPanel() {
     const setSettings = useSettingsStore((state) => state.setSettings);
     const saveSettings = useSettingsStore((state) => state.saveSettings);
 
+    const [undoHistory, setUndoHistory] = useState<UndoStats | null>(null);
+
+    const refreshUndoHistory = () =>
+        undoStats()
+            .then(setUndoHistory)
+            .catch(() => setUndoHistory(null));
+
+    useEffect(() => {
+        void refreshUndoHistory();
+    }, []);
+
     useEffect(() => {
         if (settings.startAtLogin) {
             void enable();
@@ -127,6 +140,16 @@ export function GeneralPanel() {
                         }}
                         highlight={settings.allowPermanentDelete}
                     />
+                    <SettingToggle
+                        title="Allow stripping quarantine"
+                        description="Let actions remove macOS's download quarantine, so files open without Gatekeeper's check"
+                        checked={settings.allowQuarantineStrip}
+                        onChange={(checked) => {
+                            setSettings({ allowQuarantineStrip: checked });
+                            void saveSettings();
+                        }}
+                        highlight={settings.allowQuarantineStrip}
+                    />
                 </div>
             </section>
 
@@ -146,6 +169,112 @@ export function GeneralPanel() {
                         }}
                     />
                 </SettingRow>
+                <SettingRow title="Undo window (days)" description="How long actions can be undone (0 = no limit)">
+                    <input
+                        className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                        type="number"
+                        min={0}
+                        value={settings.undoRetentionDays}
+                        onChange={(e) => {
+                            setSettings({ undoRetentionDays: Number(e.target.value) });
+                            void saveSettings().then(refreshUndoHistory);
+                        }}
+                    />
+                </SettingRow>
+                <SettingRow
+                    title="Undo history limit"
+                    description={
+                        "Most actions kept for undo; the latest run is always kept whole (0 = no limit)" +
+                        (undoHistory
+                            ? `. ${undoHistory.entries.toLocaleString()} stored` +
+                              (undoHistory.reclaimable > 0
+                                  ? `, ${undoHistory.reclaimable.toLocaleString()} to be cleared`
+                                  : "")
+                            : "")
+                    }
+                >
+                    <input
+                        className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                        type="number"
+                        min={0}
+                        value={settings.undoMaxEntries}
+                        onChange={(e) => {
+                            setSettings({ undoMaxEntries: Number(e.target.value) });
+                            void saveSettings().then(refreshUndoHistory);
+                        }}
+                    />
+                </SettingRow>
+                <SettingRow title="Approval expiry (hours)" description="How long matches wait for review (0 = no limit)">
+                    <input
+                        className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                        type="number"
+                        min={0}
+                        value={settings.approvalExpiryHours}
+                        onChange={(e) => {
+                            setSettings({ approvalExpiryHours: Number(e.target.value) });
+                            void saveSettings();
+                        }}
+                    />
+                </SettingRow>
+                <SettingRow title="Conflict expiry (hours)" description="How long a conflict waits for your decision (0 = no limit)">
+                    <input
+                        className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                        type="number"
+                        min={0}
+                        value={settings.conflictExpiryHours}
+                        onChange={(e) => {
+                            setSettings({ conflictExpiryHours: Number(e.target.value) });
+                            void saveSettings();
+                        }}
+                    />
+                </SettingRow>
+                <SettingRow title="Undecided conflicts" description="What happens to a conflict nobody decided on in time">
+                    <MagiSelect
+                        width="w-32"
+                        value={settings.conflictFallback.type}
+                        onChange={(val) => {
+                            setSettings({ conflictFallback: { type: val as "skip" | "replace" | "rename" } });
+                            void saveSettings();
+                        }}
+                        options={[
+                            { label: "Skip", value: "skip" },
+                            { label: "Replace", value: "replace" },
+                            { label: "Keep both", value: "rename" },
+                        ]}
+                        ariaLabel="Undecided conflicts"
+                    />
+                </SettingRow>
+                <SettingRow title="Seconds saved per file" description="Manual sorting time assumed for each organized file in Analytics">
+                    <input
+                        className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                        type="number"
+                        min={0}
+                        value={settings.insightsSecondsPerFile}
+                        onChange={(e) => {
+                            setSettings({ insightsSecondsPerFile: Number(e.target.value) });
+                            void saveSettings();
+                        }}
+                    />
+                </SettingRow>
+            </section>
+
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Time zone
+                </h3>
+                <SettingRow
+                    title="Default time zone"
+                    description='Used by date conditions and tokens set to "default", e.g. UTC or Europe/Berlin'
+                >
+                    <input
+                        className="w-40 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                        type="text"
+                        placeholder="System"
+                        value={settings.defaultTimezone ?? ""}
+                        onChange={(e) => setSettings({ defaultTimezone: e.target.value || null })}
+                        onBlur={() => void saveSettings()}
+                    />
+                </SettingRow>
             </section>
         </div>
     );
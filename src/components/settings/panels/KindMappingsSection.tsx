@@ -0,0 +1,134 @@
+import { useEffect, useState } from "react";
+
+import { MagiSelect } from "@/components/ui/MagiSelect";
+import { kindMappingsList } from "@/lib/tauri";
+import { useSettingsStore } from "@/stores/settingsStore";
+import type { FileKind, KindMapping } from "@/types";
+
+const fieldClass =
+    "rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]";
+
+const buttonClass =
+    "rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-xs font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:text-[var(--fg-primary)] disabled:opacity-50";
+
+// Folder isn't an option: folders are told apart without their extension.
+const kindOptions: { label: string; value: FileKind }[] = [
+    { label: "Image", value: "image" },
+    { label: "Video", value: "video" },
+    { label: "Audio", value: "audio" },
+    { label: "Document", value: "document" },
+    { label: "Archive", value: "archive" },
+    { label: "Code", value: "code" },
+    { label: "File", value: "file" },
+    { label: "Other", value: "other" },
+];
+
+interface OverrideRow {
+    extension: string;
+    kind: FileKind;
+}
+
+export function KindMappingsSection() {
+    const settings = useSettingsStore((state) => state.settings);
+    const setSettings = useSettingsStore((state) => state.setSettings);
+    const saveSettings = useSettingsStore((state) => state.saveSettings);
+    const saveError = useSettingsStore((state) => state.saveError);
+    const [rows, setRows] = useState<OverrideRow[]>(() =>
+        Object.entries(settings.kindOverrides).map(([extension, kind]) => ({ extension, kind })),
+    );
+    const [mappings, setMappings] = useState<KindMapping[]>([]);
+    const [showTable, setShowTable] = useState(false);
+
+    const refresh = () => {
+        kindMappingsList()
+            .then(setMappings)
+            .catch(() => setMappings([]));
+    };
+
+    useEffect(refresh, []);
+
+    // Rows are only saved once every one names an extension.
+    const update = (next: OverrideRow[]) => {
+        setRows(next);
+        if (next.some((row) => row.extension.trim() === "")) return;
+        setSettings({
+            kindOverrides: Object.fromEntries(next.map((row) => [row.extension, row.kind])),
+        });
+        void saveSettings().then(refresh);
+    };
+
+    const updateRow = (index: number, partial: Partial<OverrideRow>) =>
+        update(rows.map((row, i) => (i === index ? { ...row, ...partial } : row)));
+
+    return (
+        <section>
+            <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">File kinds</h3>
+            <p className="mb-2 text-xs text-[var(--fg-muted)]">
+                Kind conditions and content matching go by what a file's contents say it is, then by
+                its extension. Map an extension to a kind here to decide it yourself, e.g. to treat
+                Pages or Keynote files as documents rather than archives.
+            </p>
+            <div className="space-y-3">
+                {rows.map((row, index) => (
+                    <div key={index} className="flex items-center gap-2">
+                        <input
+                            className={`${fieldClass} w-32`}
+                            placeholder="Extension"
+                            aria-label="Extension"
+                            value={row.extension}
+                            onChange={(e) => updateRow(index, { extension: e.target.value.trim() })}
+                        />
+                        <MagiSelect
+                            width="w-36"
+                            value={row.kind}
+                            onChange={(val) => updateRow(index, { kind: val as FileKind })}
+                            options={kindOptions}
+                            ariaLabel="Kind"
+                        />
+                        <button
+                            type="button"
+                            className={buttonClass}
+                            onClick={() => update(rows.filter((_, i) => i !== index))}
+                        >
+                            Remove
+                        </button>
+                    </div>
+                ))}
+                <div className="flex items-center gap-2">
+                    <button
+                        type="button"
+                        className={buttonClass}
+                        onClick={() => setRows([...rows, { extension: "", kind: "document" }])}
+                    >
+                        Add mapping
+                    </button>
+                    <button
+                        type="button"
+                        className={buttonClass}
+                        onClick={() => setShowTable(!showTable)}
+                    >
+                        {showTable ? "Hide table" : "Show all mappings"}
+                    </button>
+                </div>
+                {saveError ? <p className="text-xs text-[var(--fg-alert)]">{saveError}</p> : null}
+                {showTable ? (
+                    <table className="w-full text-xs text-[var(--fg-secondary)]">
+                        <tbody>
+                            {mappings.map((mapping) => (
+                                <tr key={mapping.extension}>
+                                    <td className="py-0.5 font-semibold text-[var(--fg-primary)]">
+                                        .{mapping.extension}
+                                    </td>
+                                    <td className="py-0.5">{mapping.kind}</td>
+                                    <td className="py-0.5 text-right text-[var(--fg-muted)]">
+                                        {mapping.source === "override" ? "Yours" : "Built in"}
+                                    </td>
+                                </tr>
+                            ))}
+                        </tbody>
+                    </table>
+                ) : null}
+            </div>
+        </section>
+    );
+}
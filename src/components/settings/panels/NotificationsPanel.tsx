@@ -1,10 +1,17 @@
-import { useSettingsStore } from "@/stores/settingsStore";
-import { SettingToggle } from "../SettingsShared";
+import { MagiSelect } from "@/components/ui/MagiSelect";
+import { useSettingsStore, type DailyDigestSettings } from "@/stores/settingsStore";
+import type { ErrorNotifyMode } from "@/types";
+import { SettingRow, SettingToggle } from "../SettingsShared";
 
 export function NotificationsPanel() {
     const settings = useSettingsStore((state) => state.settings);
     const setSettings = useSettingsStore((state) => state.setSettings);
     const saveSettings = useSettingsStore((state) => state.saveSettings);
+    const digest = settings.dailyDigest;
+    const setDigest = (partial: Partial<DailyDigestSettings>) => {
+        setSettings({ dailyDigest: { ...digest, ...partial } });
+        void saveSettings();
+    };
 
     return (
         <div className="space-y-6">
@@ -22,6 +29,79 @@ export function NotificationsPanel() {
                             void saveSettings();
                         }}
                     />
+                    <SettingToggle
+                        title="Respect do-not-disturb"
+                        description="Hold back Notify actions while do-not-disturb or a focus mode is on. Their sounds are muted then either way"
+                        checked={settings.respectDnd}
+                        onChange={(checked) => {
+                            setSettings({ respectDnd: checked });
+                            void saveSettings();
+                        }}
+                    />
+                    <SettingRow
+                        title="Failed actions"
+                        description="Notify when a rule's action fails, or a rule starts pointing at a missing app, script or volume"
+                    >
+                        <MagiSelect
+                            width="w-40"
+                            value={settings.notifyOnError}
+                            onChange={(val) => {
+                                setSettings({ notifyOnError: val as ErrorNotifyMode });
+                                void saveSettings();
+                            }}
+                            options={[
+                                { label: "Off", value: "off" },
+                                { label: "First per rule/hour", value: "firstPerRule" },
+                                { label: "Every failure", value: "all" },
+                            ]}
+                            ariaLabel="Failed action notifications"
+                        />
+                    </SettingRow>
+                </div>
+            </section>
+
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Daily digest
+                </h3>
+                <div className="space-y-3">
+                    <SettingToggle
+                        title="Send a daily digest"
+                        description="One notification a day summing up what your rules did"
+                        checked={digest.enabled}
+                        onChange={(checked) => setDigest({ enabled: checked })}
+                    />
+                    <SettingRow
+                        title="Time"
+                        description="If your computer is asleep then, the digest is sent when it wakes, up to three hours late"
+                    >
+                        <input
+                            type="time"
+                            className="w-28 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)] disabled:opacity-50"
+                            value={digest.time.slice(0, 5)}
+                            disabled={!digest.enabled}
+                            onChange={(e) => {
+                                if (e.target.value) {
+                                    setDigest({ time: `${e.target.value.slice(0, 5)}:00` });
+                                }
+                            }}
+                            aria-label="Digest time"
+                        />
+                    </SettingRow>
+                    <SettingToggle
+                        title="Include failed actions"
+                        description="Count the actions that failed during the day"
+                        checked={digest.includeErrors}
+                        disabled={!digest.enabled}
+                        onChange={(checked) => setDigest({ includeErrors: checked })}
+                    />
+                    <SettingToggle
+                        title="Skip quiet days"
+                        description="Send nothing on days your rules didn't do anything"
+                        checked={digest.skipEmptyDays}
+                        disabled={!digest.enabled}
+                        onChange={(checked) => setDigest({ skipEmptyDays: checked })}
+                    />
                 </div>
             </section>
         </div>
@@ -7,7 +7,14 @@ import { Slider } from "@/components/ui/Slider";
 import { useSettingsStore } from "@/stores/settingsStore";
 import { SettingRow, SettingToggle } from "../SettingsShared";
 import { LanguageManager } from "./LanguageManager";
-import { ocrGetInstalledLanguages, type InstalledLanguage } from "@/lib/tauri";
+import {
+    facesDeleteModel,
+    facesDownloadModel,
+    facesModelStatus,
+    ocrGetInstalledLanguages,
+    type FaceModelStatus,
+    type InstalledLanguage,
+} from "@/lib/tauri";
 
 export function OCRPanel() {
     const settings = useSettingsStore((state) => state.settings);
@@ -16,13 +23,36 @@ export function OCRPanel() {
     const mb = 1024 * 1024;
 
     const [installedLanguages, setInstalledLanguages] = useState<InstalledLanguage[]>([]);
+    const [faceModel, setFaceModel] = useState<FaceModelStatus | null>(null);
+    const [faceModelBusy, setFaceModelBusy] = useState(false);
+    const [faceModelError, setFaceModelError] = useState<string | null>(null);
 
     useEffect(() => {
         ocrGetInstalledLanguages()
             .then(setInstalledLanguages)
             .catch(() => setInstalledLanguages([]));
+        facesModelStatus()
+            .then(setFaceModel)
+            .catch(() => setFaceModel(null));
     }, []);
 
+    const handleFaceModel = async () => {
+        setFaceModelBusy(true);
+        setFaceModelError(null);
+        try {
+            if (faceModel?.installed) {
+                await facesDeleteModel();
+            } else {
+                await facesDownloadModel();
+            }
+            setFaceModel(await facesModelStatus());
+        } catch (err) {
+            setFaceModelError(String(err));
+        } finally {
+            setFaceModelBusy(false);
+        }
+    };
+
     const toMb = (bytes: number) => Math.max(1, Math.round(bytes / mb));
     const fromMb = (value: number) => Math.max(1, value) * mb;
     const toSeconds = (ms: number) => Math.max(1, Math.round(ms / 1000));
@@ -79,6 +109,36 @@ export function OCRPanel() {
                             }}
                         />
                     </SettingRow>
+                    <SettingRow
+                        title="Max image metadata probe size (MB)"
+                        description="Image Dimensions and EXIF Date conditions don't match larger images"
+                    >
+                        <input
+                            className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                            type="number"
+                            min={0}
+                            value={toMb(settings.contentMaxImageProbeBytes)}
+                            onChange={(e) => {
+                                setSettings({ contentMaxImageProbeBytes: fromMb(Number(e.target.value)) });
+                                void saveSettings();
+                            }}
+                        />
+                    </SettingRow>
+                    <SettingRow
+                        title="Max PDF page count size (MB)"
+                        description="PDF Page Count conditions don't match larger PDFs"
+                    >
+                        <input
+                            className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                            type="number"
+                            min={0}
+                            value={toMb(settings.contentMaxPdfProbeBytes)}
+                            onChange={(e) => {
+                                setSettings({ contentMaxPdfProbeBytes: fromMb(Number(e.target.value)) });
+                                void saveSettings();
+                            }}
+                        />
+                    </SettingRow>
                     <SettingRow
                         title="Max OCR image size (MB)"
                         description="Skip OCR for images larger than this size"
@@ -394,6 +454,36 @@ export function OCRPanel() {
                     )}
                 </div>
             </section>
+
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Face Detection
+                </h3>
+                <div className="space-y-3">
+                    <SettingRow
+                        title="Face detection model"
+                        description={
+                            !faceModel?.supported
+                                ? "Not available in this build"
+                                : faceModel.installed
+                                  ? "Installed. Face Count conditions run locally; only the count is kept"
+                                  : "Download to use Face Count conditions. Images never leave this device"
+                        }
+                    >
+                        <button
+                            className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-1 text-xs font-semibold text-[var(--fg-primary)] transition-colors hover:border-[var(--border-strong)] hover:bg-[var(--bg-subtle)]"
+                            onClick={() => void handleFaceModel()}
+                            type="button"
+                            disabled={!faceModel?.supported || faceModelBusy}
+                        >
+                            {faceModelBusy ? "Working…" : faceModel?.installed ? "Remove" : "Download"}
+                        </button>
+                    </SettingRow>
+                    {faceModelError && (
+                        <div className="text-xs text-[var(--fg-alert)]">{faceModelError}</div>
+                    )}
+                </div>
+            </section>
         </div>
     );
 }
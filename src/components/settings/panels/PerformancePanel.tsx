@@ -1,10 +1,36 @@
-import { useSettingsStore } from "@/stores/settingsStore";
+import { MagiSelect } from "@/components/ui/MagiSelect";
+import { type SampledHashSettings, type SampledMatch, useSettingsStore } from "@/stores/settingsStore";
+import type { ReflinkMode } from "@/types";
 import { SettingRow, SettingToggle } from "../SettingsShared";
 
+const reflinkOptions = [
+    { label: "When possible", value: "auto" },
+    { label: "Always", value: "always" },
+    { label: "Never", value: "never" },
+];
+
+const MIB = 1024 * 1024;
+
+const numberInputClass =
+    "w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]";
+
+/** The sampled hashing sizes, edited in MiB. */
+const sampledSizes: { key: keyof SampledHashSettings; title: string; description: string }[] = [
+    { key: "minSizeBytes", title: "Sample files from (MiB)", description: "Smaller files are always hashed in full" },
+    { key: "headBytes", title: "Start sample (MiB)", description: "Read from the beginning of the file" },
+    { key: "tailBytes", title: "End sample (MiB)", description: "Read from the end of the file" },
+    { key: "windowBytes", title: "Window size (MiB)", description: "Each window read in between" },
+];
+
 export function PerformancePanel() {
     const settings = useSettingsStore((state) => state.settings);
     const setSettings = useSettingsStore((state) => state.setSettings);
     const saveSettings = useSettingsStore((state) => state.saveSettings);
+    const sampling = settings.sampledHashing;
+    const setSampling = (partial: Partial<SampledHashSettings>) => {
+        setSettings({ sampledHashing: { ...sampling, ...partial } });
+        void saveSettings();
+    };
 
     return (
         <div className="space-y-6">
@@ -38,6 +64,81 @@ export function PerformancePanel() {
                             }}
                         />
                     </SettingRow>
+                    <SettingRow
+                        title="Slow rule warning (ms)"
+                        description="Log a warning when checking one rule takes longer; 0 turns it off"
+                    >
+                        <input
+                            className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                            type="number"
+                            min={0}
+                            value={settings.slowRuleWarningMs}
+                            onChange={(e) => {
+                                setSettings({ slowRuleWarningMs: Number(e.target.value) });
+                                void saveSettings();
+                            }}
+                        />
+                    </SettingRow>
+                    <SettingRow
+                        title="Scan files per run"
+                        description="Run now and scheduled runs stop after this many files and carry on next time; 0 scans the whole folder"
+                    >
+                        <input
+                            className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                            type="number"
+                            min={0}
+                            value={settings.scanMaxFilesPerRun}
+                            onChange={(e) => {
+                                setSettings({ scanMaxFilesPerRun: Number(e.target.value) });
+                                void saveSettings();
+                            }}
+                        />
+                    </SettingRow>
+                    <SettingRow
+                        title="Scan files per second"
+                        description="Pace runs so new files aren't kept waiting; 0 doesn't limit"
+                    >
+                        <input
+                            className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                            type="number"
+                            min={0}
+                            value={settings.scanFilesPerSecond}
+                            onChange={(e) => {
+                                setSettings({ scanFilesPerSecond: Number(e.target.value) });
+                                void saveSettings();
+                            }}
+                        />
+                    </SettingRow>
+                    <SettingRow
+                        title="Rescan folders (minutes)"
+                        description="Queue files already in watched folders for their rules; 0 only reacts to changes"
+                    >
+                        <input
+                            className="w-24 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-sm text-[var(--fg-primary)] shadow-[var(--shadow-sm)] outline-none transition-colors focus:border-[var(--accent)] focus:shadow-[0_0_0_1px_var(--accent)]"
+                            type="number"
+                            min={0}
+                            value={settings.scanIntervalMinutes}
+                            onChange={(e) => {
+                                setSettings({ scanIntervalMinutes: Number(e.target.value) });
+                                void saveSettings();
+                            }}
+                        />
+                    </SettingRow>
+                    <SettingRow
+                        title="Copy-on-write copies"
+                        description="Clone files instead of duplicating data on Btrfs, XFS, APFS and ReFS"
+                    >
+                        <MagiSelect
+                            width="w-32"
+                            value={settings.useReflink}
+                            onChange={(val) => {
+                                setSettings({ useReflink: val as ReflinkMode });
+                                void saveSettings();
+                            }}
+                            options={reflinkOptions}
+                            ariaLabel="Copy-on-write copies"
+                        />
+                    </SettingRow>
                     <SettingToggle
                         title="Polling fallback"
                         description="Use polling when native file events fail"
@@ -49,6 +150,56 @@ export function PerformancePanel() {
                     />
                 </div>
             </section>
+            <section>
+                <h3 className="mb-4 text-sm font-semibold text-[var(--fg-primary)]">
+                    Duplicates in Huge Files
+                </h3>
+                <div className="space-y-3">
+                    <SettingToggle
+                        title="Sample huge files"
+                        description="Compare large files by their size and a few windows instead of reading them whole"
+                        checked={sampling.enabled}
+                        onChange={(checked) => setSampling({ enabled: checked })}
+                    />
+                    {sampledSizes.map(({ key, title, description }) => (
+                        <SettingRow key={key} title={title} description={description}>
+                            <input
+                                className={numberInputClass}
+                                type="number"
+                                min={key === "minSizeBytes" ? 1 : 0}
+                                disabled={!sampling.enabled}
+                                value={Math.round((sampling[key] as number) / MIB)}
+                                onChange={(e) => setSampling({ [key]: Number(e.target.value) * MIB })}
+                            />
+                        </SettingRow>
+                    ))}
+                    <SettingRow title="Windows" description="How many windows are read between the start and end">
+                        <input
+                            className={numberInputClass}
+                            type="number"
+                            min={0}
+                            disabled={!sampling.enabled}
+                            value={sampling.windowCount}
+                            onChange={(e) => setSampling({ windowCount: Number(e.target.value) })}
+                        />
+                    </SettingRow>
+                    <SettingRow
+                        title="Matching samples"
+                        description="Confirming reads both files in full before removing one"
+                    >
+                        <MagiSelect
+                            width="w-40"
+                            value={sampling.onMatch}
+                            onChange={(val) => setSampling({ onMatch: val as SampledMatch })}
+                            options={[
+                                { label: "Confirm", value: "confirm" },
+                                { label: "Trust", value: "trust" },
+                            ]}
+                            ariaLabel="Matching samples"
+                        />
+                    </SettingRow>
+                </div>
+            </section>
         </div>
     );
 }
@@ -0,0 +1,118 @@
+import { useState } from "react";
+import { AlertTriangle, Info, ShieldCheck, Wrench } from "lucide-react";
+
+import type { HealthIssue, HealthIssueKind, HealthRepairOptions } from "@/types";
+import { healthCheck, healthRepair } from "@/lib/tauri";
+import { useEngineStore } from "@/stores/engineStore";
+import { useFolderStore } from "@/stores/folderStore";
+import { useRuleStore } from "@/stores/ruleStore";
+
+const REPAIRS: { kind: HealthIssueKind; label: string; option: keyof HealthRepairOptions }[] = [
+  { kind: "unreadableRule", label: "Disable unreadable rules", option: "disableUnreadableRules" },
+  { kind: "duplicatePosition", label: "Re-number rule order", option: "renumberPositions" },
+  { kind: "missingFolderPath", label: "Disable missing folders", option: "disableMissingFolders" },
+];
+
+const buttonClass =
+  "inline-flex items-center gap-1.5 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[10px] font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:bg-[var(--bg-subtle)] hover:text-[var(--fg-primary)] disabled:opacity-50";
+
+export function HealthCard() {
+  const status = useEngineStore((state) => state.status);
+  const loadStatus = useEngineStore((state) => state.loadStatus);
+  const loadFolders = useFolderStore((state) => state.loadFolders);
+  const selectedFolderId = useFolderStore((state) => state.selectedFolderId);
+  const loadRules = useRuleStore((state) => state.loadRules);
+
+  const [busy, setBusy] = useState(false);
+  const [repaired, setRepaired] = useState<string[]>([]);
+  const [error, setError] = useState<string | null>(null);
+
+  const issues: HealthIssue[] = status?.status.healthIssues ?? [];
+  const repairs = REPAIRS.filter((repair) =>
+    issues.some((issue) => issue.kind === repair.kind && issue.severity !== "info"),
+  );
+
+  const run = async (task: () => Promise<void>) => {
+    setBusy(true);
+    setError(null);
+    try {
+      await task();
+      await loadStatus();
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  const handleCheck = () =>
+    run(async () => {
+      setRepaired([]);
+      await healthCheck();
+    });
+
+  const handleRepair = (option: keyof HealthRepairOptions) =>
+    run(async () => {
+      const result = await healthRepair({ [option]: true });
+      setRepaired(result.repaired);
+      await loadFolders();
+      if (selectedFolderId) {
+        await loadRules(selectedFolderId);
+      }
+    });
+
+  return (
+    <div className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-4">
+      <div className="flex items-center justify-between">
+        <div className="text-xs font-semibold uppercase tracking-wide text-[var(--fg-muted)]">Health</div>
+        <button type="button" className={buttonClass} onClick={() => void handleCheck()} disabled={busy}>
+          <ShieldCheck className="h-3 w-3" />
+          Full check
+        </button>
+      </div>
+      <div className="mt-3 space-y-2 text-xs text-[var(--fg-secondary)]">
+        {issues.length > 0 ? (
+          issues.map((issue, index) => (
+            <div
+              key={`${issue.kind}-${issue.ruleId ?? issue.folderId ?? index}`}
+              className={`flex items-start gap-2 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 ${
+                issue.severity === "error" ? "text-[var(--fg-alert)]" : ""
+              }`}
+            >
+              {issue.severity === "info" ? (
+                <Info className="mt-0.5 h-3.5 w-3.5 shrink-0" />
+              ) : (
+                <AlertTriangle className="mt-0.5 h-3.5 w-3.5 shrink-0" />
+              )}
+              <div className="min-w-0 break-words">{issue.message}</div>
+            </div>
+          ))
+        ) : (
+          <div className="text-[var(--fg-muted)]">No problems found.</div>
+        )}
+        {repairs.length > 0 ? (
+          <div className="flex flex-wrap gap-2 pt-1">
+            {repairs.map((repair) => (
+              <button
+                key={repair.option}
+                type="button"
+                className={buttonClass}
+                onClick={() => void handleRepair(repair.option)}
+                disabled={busy}
+              >
+                <Wrench className="h-3 w-3" />
+                {repair.label}
+              </button>
+            ))}
+          </div>
+        ) : null}
+        {repaired.map((line) => (
+          <div key={line} className="text-[10px] text-[var(--fg-muted)]">
+            {line}
+          </div>
+        ))}
+        {error ? <div className="text-[var(--fg-alert)]">{error}</div> : null}
+      </div>
+    </div>
+  );
+}
@@ -4,13 +4,15 @@ import { AlertTriangle, Copy, FileSearch, Pause, Play, RefreshCw, X } from "luci
 import { open as openDialog } from "@tauri-apps/plugin-dialog";
 
 import type { Rule } from "@/types";
-import { previewFile } from "@/lib/tauri";
+import { previewFile, rulesCheckReferences } from "@/lib/tauri";
 import { useEngineStore } from "@/stores/engineStore";
 import { useFolderStore } from "@/stores/folderStore";
 import { useLogStore } from "@/stores/logStore";
 import { useRuleStore } from "@/stores/ruleStore";
 import { useSettingsStore } from "@/stores/settingsStore";
 import { Switch } from "@/components/ui/Switch";
+import { HealthCard } from "@/components/status/HealthCard";
+import { UnavailableFoldersCard } from "@/components/status/UnavailableFoldersCard";
 import { useFocusTrap } from "@/hooks/useFocusTrap";
 
 interface RuleStatusDialogProps {
@@ -22,6 +24,7 @@ interface TestResult {
   rule: Rule;
   matched: boolean;
   actions: string[];
+  overridden: string[];
 }
 
 export function RuleStatusDialog({ open, onClose }: RuleStatusDialogProps) {
@@ -32,7 +35,9 @@ export function RuleStatusDialog({ open, onClose }: RuleStatusDialogProps) {
   const togglePaused = useEngineStore((state) => state.togglePaused);
 
   const selectedFolderId = useFolderStore((state) => state.selectedFolderId);
+  const folders = useFolderStore((state) => state.folders);
   const rules = useRuleStore((state) => state.rules);
+  const loadRules = useRuleStore((state) => state.loadRules);
   const logs = useLogStore((state) => state.entries);
 
   const settings = useSettingsStore((state) => state.settings);
@@ -43,6 +48,9 @@ export function RuleStatusDialog({ open, onClose }: RuleStatusDialogProps) {
   const [testFilePath, setTestFilePath] = useState<string | null>(null);
   const [testLoading, setTestLoading] = useState(false);
   const [testError, setTestError] = useState<string | null>(null);
+  const [pretendAgeDays, setPretendAgeDays] = useState("");
+  const [pretendNow, setPretendNow] = useState("");
+  const [checkingReferences, setCheckingReferences] = useState(false);
   const dialogRef = useRef<HTMLDivElement>(null);
 
   const activeRules = useMemo(
@@ -50,6 +58,17 @@ export function RuleStatusDialog({ open, onClose }: RuleStatusDialogProps) {
     [rules],
   );
 
+  const handleCheckReferences = async () => {
+    setCheckingReferences(true);
+    try {
+      await rulesCheckReferences();
+      await loadStatus();
+      if (selectedFolderId) await loadRules(selectedFolderId);
+    } finally {
+      setCheckingReferences(false);
+    }
+  };
+
   const recentErrors = useMemo(() => {
     return logs
       .filter((entry) => entry.status === "error")
@@ -89,29 +108,27 @@ export function RuleStatusDialog({ open, onClose }: RuleStatusDialogProps) {
 
   useFocusTrap(open, dialogRef);
 
-  const handlePickFile = async () => {
-    setTestError(null);
-    setTestResults([]);
-    const selected = await openDialog({
-      multiple: false,
-      title: "Select a file to test",
-    });
-    if (!selected || Array.isArray(selected)) return;
-
-    if (!selectedFolderId) {
-      setTestError("Select a folder first");
-      return;
-    }
-
-    setTestFilePath(String(selected));
+  const runTest = async (path: string) => {
     setTestLoading(true);
+    setTestError(null);
+    const ageDays = Number(pretendAgeDays);
+    const now = pretendNow ? new Date(pretendNow) : new Date();
+    const aged =
+      pretendAgeDays && Number.isFinite(ageDays)
+        ? new Date(now.getTime() - ageDays * 86_400_000).toISOString()
+        : undefined;
+    const options = {
+      overrides: aged ? { created: aged, modified: aged, added: aged } : undefined,
+      now: pretendNow ? now.toISOString() : undefined,
+    };
     try {
       const previews = await Promise.all(
         activeRules.map((rule) =>
-          previewFile(rule.id, String(selected)).then((result) => ({
+          previewFile(rule.id, path, options).then((result) => ({
             rule,
             matched: result.matched,
             actions: result.actions,
+            overridden: result.overridden ?? [],
           })),
         ),
       );
@@ -124,6 +141,24 @@ export function RuleStatusDialog({ open, onClose }: RuleStatusDialogProps) {
     }
   };
 
+  const handlePickFile = async () => {
+    setTestError(null);
+    setTestResults([]);
+    const selected = await openDialog({
+      multiple: false,
+      title: "Select a file to test",
+    });
+    if (!selected || Array.isArray(selected)) return;
+
+    if (!selectedFolderId) {
+      setTestError("Select a folder first");
+      return;
+    }
+
+    setTestFilePath(String(selected));
+    await runTest(String(selected));
+  };
+
   if (!open) return null;
 
   const modal = (
@@ -171,6 +206,47 @@ export function RuleStatusDialog({ open, onClose }: RuleStatusDialogProps) {
                   <div className="mt-1 text-xs text-[var(--fg-muted)]">
                     Queue depth: {status?.status.queueDepth ?? 0} · Processed: {status?.status.processedCount ?? 0}
                   </div>
+                  {status?.status.slowestRule ? (
+                    <div className="mt-1 text-xs text-[var(--fg-muted)]">
+                      Slowest in the last hour: {status.status.slowestRule.ruleName} (
+                      {Math.round(status.status.slowestRule.durationMs)} ms{" "}
+                      {status.status.slowestRule.phase === "evaluation" ? "checking" : "running"})
+                    </div>
+                  ) : null}
+                  <div className="mt-1 flex items-center gap-2 text-xs text-[var(--fg-muted)]">
+                    <span className={status?.status.brokenReferenceRules ? "text-[var(--fg-alert)]" : ""}>
+                      {status?.status.brokenReferenceRules
+                        ? `${status.status.brokenReferenceRules} rule${
+                            status.status.brokenReferenceRules === 1 ? "" : "s"
+                          } missing an app, script or volume`
+                        : "No rules missing an app, script or volume"}
+                    </span>
+                    <button
+                      type="button"
+                      onClick={() => void handleCheckReferences()}
+                      disabled={checkingReferences}
+                      className="underline decoration-dotted hover:text-[var(--fg-primary)] disabled:opacity-50"
+                    >
+                      {checkingReferences ? "Checking…" : "Check now"}
+                    </button>
+                  </div>
+                  {status?.status.offlineDestinations?.map((destination) => (
+                    <div key={destination.path} className="mt-1 text-xs text-[var(--fg-alert)]">
+                      {destination.path} is offline: {destination.queuedFiles} file
+                      {destination.queuedFiles === 1 ? "" : "s"} waiting
+                    </div>
+                  ))}
+                  {status?.status.serializedFolders?.length ? (
+                    <div className="mt-1 text-xs text-[var(--fg-muted)]">
+                      One file at a time:{" "}
+                      {status.status.serializedFolders
+                        .map((lane) => {
+                          const name = folders.find((f) => f.id === lane.folderId)?.name ?? lane.folderId;
+                          return lane.backlog ? `${name} (${lane.backlog} queued)` : name;
+                        })
+                        .join(", ")}
+                    </div>
+                  ) : null}
                 </div>
                 <button
                   type="button"
@@ -235,6 +311,10 @@ export function RuleStatusDialog({ open, onClose }: RuleStatusDialogProps) {
               ) : null}
             </div>
 
+            <HealthCard />
+
+            <UnavailableFoldersCard />
+
             <div className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-4">
               <div className="text-xs font-semibold uppercase tracking-wide text-[var(--fg-muted)]">
                 Watched folders
@@ -312,9 +392,42 @@ export function RuleStatusDialog({ open, onClose }: RuleStatusDialogProps) {
               </button>
             </div>
 
+            <div className="mt-3 grid grid-cols-2 gap-2 text-xs text-[var(--fg-muted)]">
+              <label className="space-y-1">
+                <span>As if this many days old</span>
+                <input
+                  type="number"
+                  min={0}
+                  value={pretendAgeDays}
+                  onChange={(event) => setPretendAgeDays(event.target.value)}
+                  placeholder="Its real age"
+                  className="w-full rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[var(--fg-primary)]"
+                />
+              </label>
+              <label className="space-y-1">
+                <span>As if it were</span>
+                <input
+                  type="datetime-local"
+                  value={pretendNow}
+                  onChange={(event) => setPretendNow(event.target.value)}
+                  className="w-full rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[var(--fg-primary)]"
+                />
+              </label>
+            </div>
+
             {testFilePath ? (
-              <div className="mt-3 truncate rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-2 text-xs text-[var(--fg-muted)]">
-                {testFilePath}
+              <div className="mt-3 flex items-center gap-2">
+                <div className="min-w-0 flex-1 truncate rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-3 py-2 text-xs text-[var(--fg-muted)]">
+                  {testFilePath}
+                </div>
+                <button
+                  type="button"
+                  onClick={() => void runTest(testFilePath)}
+                  disabled={testLoading}
+                  className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1.5 text-xs text-[var(--fg-secondary)] hover:text-[var(--fg-primary)] disabled:opacity-50"
+                >
+                  Test again
+                </button>
               </div>
             ) : null}
 
@@ -342,6 +455,11 @@ export function RuleStatusDialog({ open, onClose }: RuleStatusDialogProps) {
                         {result.matched ? "Matched" : "No match"}
                       </span>
                     </div>
+                    {result.overridden.length > 0 ? (
+                      <div className="mt-1 text-[10px] font-semibold text-[var(--fg-alert)]">
+                        Pretended, not the file's own: {result.overridden.join(", ")}
+                      </div>
+                    ) : null}
                     {result.actions.length > 0 ? (
                       <ul className="mt-2 space-y-1 text-[10px] text-[var(--fg-muted)]">
                         {result.actions.map((action, index) => (
@@ -0,0 +1,44 @@
+import { useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { Loader2 } from "lucide-react";
+
+import type { StartupPhase, StartupProgress } from "@/types";
+import { useEngineStore } from "@/stores/engineStore";
+
+const PHASE_LABELS: Record<StartupPhase, string> = {
+  cleanup: "Cleaning up",
+  sweep: "Checking folders",
+  health: "Checking rules",
+  watch: "Watching folders",
+  catchUp: "Catching up",
+  ready: "Ready",
+};
+
+/** Shows what launch is still doing in the background, then goes away. */
+export function StartupIndicator() {
+  const initial = useEngineStore((state) => state.status?.status.startup ?? null);
+  const [progress, setProgress] = useState<StartupProgress | null>(null);
+
+  useEffect(() => {
+    const unlisten = listen<StartupProgress>("startup://progress", (event) => {
+      setProgress(event.payload);
+    });
+    return () => {
+      void unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  const current = progress ?? initial;
+  if (!current || current.phase === "ready") return null;
+
+  return (
+    <div
+      className="flex items-center gap-1.5 px-2 text-[11px] text-[var(--fg-muted)]"
+      title="Finishing launch in the background"
+    >
+      <Loader2 className="h-3 w-3 animate-spin" />
+      {PHASE_LABELS[current.phase]}
+      {current.processed > 0 ? ` · ${current.processed.toLocaleString()}` : null}
+    </div>
+  );
+}
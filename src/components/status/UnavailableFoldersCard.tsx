@@ -0,0 +1,79 @@
+import { useState } from "react";
+import { FolderX } from "lucide-react";
+import { open as openDialog } from "@tauri-apps/plugin-dialog";
+
+import type { UnavailableFolder } from "@/types";
+import { folderRelink } from "@/lib/tauri";
+import { useEngineStore } from "@/stores/engineStore";
+import { useFolderStore } from "@/stores/folderStore";
+
+const buttonClass =
+  "inline-flex items-center gap-1.5 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1 text-[10px] font-semibold text-[var(--fg-secondary)] transition-colors hover:border-[var(--border-strong)] hover:bg-[var(--bg-subtle)] hover:text-[var(--fg-primary)] disabled:opacity-50";
+
+/** Watched folders that went missing, each with a way to point it elsewhere. */
+export function UnavailableFoldersCard() {
+  const status = useEngineStore((state) => state.status);
+  const loadStatus = useEngineStore((state) => state.loadStatus);
+  const folders = useFolderStore((state) => state.folders);
+  const loadFolders = useFolderStore((state) => state.loadFolders);
+
+  const [busyId, setBusyId] = useState<string | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  const unavailable: UnavailableFolder[] = status?.status.unavailableFolders ?? [];
+  if (unavailable.length === 0) return null;
+
+  const handleRelink = async (folder: UnavailableFolder) => {
+    const selected = await openDialog({
+      directory: true,
+      multiple: false,
+      title: "Where is this folder now?",
+    });
+    if (typeof selected !== "string") return;
+    setBusyId(folder.folderId);
+    setError(null);
+    try {
+      await folderRelink(folder.folderId, selected);
+      await Promise.all([loadFolders(), loadStatus()]);
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setBusyId(null);
+    }
+  };
+
+  return (
+    <div className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-4">
+      <div className="text-xs font-semibold uppercase tracking-wide text-[var(--fg-muted)]">
+        Unavailable folders
+      </div>
+      <div className="mt-3 space-y-2 text-xs text-[var(--fg-secondary)]">
+        {unavailable.map((folder) => (
+          <div
+            key={folder.folderId}
+            className="flex items-center gap-2 rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-panel)] px-2 py-1"
+          >
+            <FolderX className="h-3.5 w-3.5 shrink-0 text-[var(--fg-alert)]" />
+            <div className="min-w-0 flex-1">
+              <div className="truncate text-[var(--fg-primary)]">
+                {folders.find((f) => f.id === folder.folderId)?.name ?? folder.path}
+              </div>
+              <div className="truncate text-[10px] text-[var(--fg-muted)]" title={folder.path}>
+                {folder.path}
+              </div>
+            </div>
+            <button
+              type="button"
+              className={buttonClass}
+              disabled={busyId !== null}
+              onClick={() => void handleRelink(folder)}
+            >
+              {busyId === folder.folderId ? "Relinking…" : "Relink…"}
+            </button>
+          </div>
+        ))}
+      </div>
+      {error ? <div className="mt-2 text-[11px] text-[var(--fg-alert)]">{error}</div> : null}
+    </div>
+  );
+}
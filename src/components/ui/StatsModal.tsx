@@ -1,9 +1,11 @@
-import React, { useMemo, useRef, useState } from "react";
+import React, { useCallback, useEffect, useMemo, useRef, useState } from "react";
 import { createPortal } from "react-dom";
 import { BarChart3 } from "lucide-react";
 
-import type { LogEntry } from "@/types";
+import type { InsightsSummary, LogEntry } from "@/types";
+import { ConfirmDialog } from "@/components/ui/ConfirmDialog";
 import { useFocusTrap } from "@/hooks/useFocusTrap";
+import { insightsReset, insightsSummary } from "@/lib/tauri";
 
 const WINDOW_HOURS = 10;
 const BUCKET_MS = 60 * 60 * 1000;
@@ -30,6 +32,29 @@ export function StatsModal({
   const [open, setOpen] = useState(false);
   const dialogRef = useRef<HTMLDivElement>(null);
   useFocusTrap(open, dialogRef);
+  const [insights, setInsights] = useState<InsightsSummary | null>(null);
+  const [confirmReset, setConfirmReset] = useState(false);
+
+  const loadInsights = useCallback(async () => {
+    try {
+      setInsights(await insightsSummary());
+    } catch {
+      setInsights(null);
+    }
+  }, []);
+
+  useEffect(() => {
+    if (open) void loadInsights();
+  }, [open, loadInsights]);
+
+  const resetInsights = async () => {
+    setConfirmReset(false);
+    try {
+      await insightsReset();
+    } finally {
+      void loadInsights();
+    }
+  };
 
   // Combined single-pass calculation for status counts and throughput
   const { statusCounts, throughput } = useMemo(() => {
@@ -171,6 +196,56 @@ export function StatsModal({
                 </div>
               </div>
             </div>
+
+            {insights ? (
+              <div className="border-t border-[var(--border-main)] px-5 py-4">
+                <div className="flex items-center justify-between">
+                  <h3 className="text-sm font-semibold text-[var(--fg-primary)]">All time</h3>
+                  <button
+                    onClick={() => setConfirmReset(true)}
+                    className="rounded-[var(--radius)] border border-[var(--border-main)] px-2 py-0.5 text-[11px] font-semibold text-[var(--fg-muted)] transition-colors hover:bg-[var(--bg-subtle)] hover:text-[var(--fg-primary)]"
+                    type="button"
+                  >
+                    Reset
+                  </button>
+                </div>
+                <div className="mt-3 grid gap-4 md:grid-cols-4">
+                  <InsightTile label="Files organized" value={insights.filesSorted.toLocaleString()} />
+                  <InsightTile label="Data moved" value={formatBytes(insights.bytesMoved)} />
+                  <InsightTile label="Time saved" value={formatDuration(insights.timeSavedSeconds)} />
+                  <InsightTile
+                    label="Hands-off streak"
+                    value={`${insights.streakDays} ${insights.streakDays === 1 ? "day" : "days"}`}
+                    hint="Days without an undo or a failed action"
+                  />
+                </div>
+                {insights.folders.length > 0 ? (
+                  <div className="mt-4 space-y-2">
+                    <div className="text-[11px] font-semibold text-[var(--fg-secondary)]">
+                      Files handled by rules (last 30 days)
+                    </div>
+                    {insights.folders.map((folder) => {
+                      const percent = folder.matchPercent ?? 0;
+                      return (
+                        <div key={folder.folderId} className="flex items-center gap-3 text-xs">
+                          <span className="w-40 truncate text-[var(--fg-primary)]" title={folder.folderName}>
+                            {folder.folderName}
+                          </span>
+                          <div className="h-2 flex-1 overflow-hidden rounded-full bg-[var(--border-main)]">
+                            <div className="h-full bg-[var(--accent)]" style={{ width: `${percent}%` }} />
+                          </div>
+                          <span className="w-28 text-right text-[var(--fg-muted)]">
+                            {folder.matchPercent == null
+                              ? "No files"
+                              : `${percent.toFixed(0)}% · ${folder.matchedFiles}/${folder.totalFiles}`}
+                          </span>
+                        </div>
+                      );
+                    })}
+                  </div>
+                ) : null}
+              </div>
+            ) : null}
           </div>
         </div>,
         document.body,
@@ -192,10 +267,39 @@ export function StatsModal({
         </button>
       )}
       {modal}
+      <ConfirmDialog
+        isOpen={confirmReset}
+        onClose={() => setConfirmReset(false)}
+        onConfirm={() => void resetInsights()}
+        title="Reset insights"
+        message="Clear the all-time totals and start the streak over? The activity log is not affected."
+        confirmLabel="Reset"
+        variant="warning"
+      />
     </>
   );
 }
 
+function InsightTile({ label, value, hint }: { label: string; value: string; hint?: string }) {
+  return (
+    <div
+      className="rounded-[var(--radius)] border border-[var(--border-main)] bg-[var(--bg-subtle)] p-3"
+      title={hint}
+    >
+      <div className="text-[11px] font-semibold text-[var(--fg-secondary)]">{label}</div>
+      <div className="mt-1 text-xl font-semibold text-[var(--fg-primary)]">{value}</div>
+    </div>
+  );
+}
+
+function formatDuration(seconds: number) {
+  if (seconds < 60) return `${seconds}s`;
+  const minutes = Math.round(seconds / 60);
+  if (minutes < 60) return `${minutes} min`;
+  const hours = seconds / 3600;
+  return `${hours.toFixed(hours >= 10 ? 0 : 1)} h`;
+}
+
 function formatBytes(bytes: number) {
   if (!bytes) return "0 B";
   const units = ["B", "KB", "MB", "GB", "TB"];
@@ -0,0 +1,60 @@
+import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
+
+import { useToastStore } from "@/stores/toastStore";
+import type { DailyDigest } from "@/types";
+
+// Long enough to read when the window is open at digest time.
+const DIGEST_TOAST_MS = 15_000;
+
+const labels: Record<string, string> = {
+  move: "moved",
+  copy: "copied",
+  rename: "renamed",
+  sortIntoSubfolder: "sorted",
+  archive: "archived",
+  unarchive: "extracted",
+  delete: "trashed",
+  deletePermanently: "deleted",
+};
+
+/** Shows the daily digest as a card when the window is open. */
+export function useDigest() {
+  const addToast = useToastStore((state) => state.addToast);
+
+  useEffect(() => {
+    const unlisten = listen<DailyDigest>("digest://ready", (event) => {
+      addToast(
+        { title: "Daily digest", message: summarizeDigest(event.payload), variant: "info" },
+        DIGEST_TOAST_MS,
+      );
+    });
+    return () => {
+      void unlisten.then((fn) => fn());
+    };
+  }, [addToast]);
+}
+
+export function summarizeDigest(digest: DailyDigest) {
+  const parts = digest.actions
+    .filter((total) => labels[total.actionType])
+    .map((total) => {
+      const size = total.bytes > 0 ? ` (${formatBytes(total.bytes)})` : "";
+      return `${total.count} ${labels[total.actionType]}${size}`;
+    });
+  if (digest.errors > 0) {
+    parts.push(`${digest.errors} ${digest.errors === 1 ? "error" : "errors"}`);
+  }
+  return parts.length > 0 ? parts.join(" · ") : "Nothing to report";
+}
+
+function formatBytes(bytes: number) {
+  const units = ["B", "KB", "MB", "GB", "TB"];
+  let size = bytes;
+  let unitIndex = 0;
+  while (size >= 1024 && unitIndex < units.length - 1) {
+    size /= 1024;
+    unitIndex += 1;
+  }
+  return `${size.toFixed(unitIndex === 0 ? 0 : 1)} ${units[unitIndex]}`;
+}
@@ -0,0 +1,65 @@
+import { useEffect, useRef } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { onAction, registerActionTypes } from "@tauri-apps/plugin-notification";
+
+import { errorNotificationAction } from "@/lib/tauri";
+import type { ErrorNotificationAction } from "@/types";
+
+// Must match ERROR_ACTION_TYPE_ID in core/error_notify.rs.
+const ERROR_ACTION_TYPE_ID = "rule-error";
+
+const actionIds: Record<string, ErrorNotificationAction> = {
+  retry: "retry",
+  "open-log": "openLog",
+  "disable-rule": "disableRule",
+};
+
+interface ActionPerformed {
+  actionId?: string;
+  notification?: { extra?: Record<string, unknown> };
+}
+
+/**
+ * Wires the buttons on error notifications (where the platform shows them)
+ * and opens the activity log when one asks for it.
+ */
+export function useErrorNotifications(onOpenLog: (ruleId: string) => void) {
+  const openLogRef = useRef(onOpenLog);
+  openLogRef.current = onOpenLog;
+
+  useEffect(() => {
+    // Desktop notification backends don't support actions; plain
+    // notifications are shown there instead.
+    void registerActionTypes([
+      {
+        id: ERROR_ACTION_TYPE_ID,
+        actions: [
+          { id: "retry", title: "Retry" },
+          { id: "open-log", title: "Open log", foreground: true },
+          { id: "disable-rule", title: "Disable rule", destructive: true },
+        ],
+      },
+    ]).catch(() => undefined);
+
+    const actionListener = onAction((payload) => {
+      const performed = payload as unknown as ActionPerformed;
+      const action = performed.actionId ? actionIds[performed.actionId] : undefined;
+      const extra = performed.notification?.extra ?? {};
+      if (!action) return;
+      void errorNotificationAction(
+        String(extra.failureId ?? ""),
+        String(extra.ruleId ?? ""),
+        action,
+      ).catch(() => undefined);
+    }).catch(() => undefined);
+
+    const unlisten = listen<{ ruleId: string }>("navigate://log", (event) => {
+      openLogRef.current(event.payload.ruleId);
+    });
+
+    return () => {
+      void actionListener.then((listener) => listener?.unregister());
+      void unlisten.then((fn) => fn());
+    };
+  }, []);
+}
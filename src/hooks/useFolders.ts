@@ -1,11 +1,22 @@
 import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
 
+import { useEngineStore } from "@/stores/engineStore";
 import { useFolderStore } from "@/stores/folderStore";
 
 export function useFolders() {
   const { loadFolders } = useFolderStore();
+  const loadStatus = useEngineStore((state) => state.loadStatus);
 
   useEffect(() => {
     void loadFolders();
-  }, [loadFolders]);
+    // A watched folder was renamed on disk and followed to its new path.
+    const unlisten = listen("folder://path-changed", () => {
+      void loadFolders();
+      void loadStatus();
+    });
+    return () => {
+      void unlisten.then((fn) => fn());
+    };
+  }, [loadFolders, loadStatus]);
 }
@@ -1,18 +1,44 @@
 import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
 
 import { useLogStore } from "@/stores/logStore";
 
 export function useLogs(limit = 100, pollMs = 4000) {
   const loadLogs = useLogStore((state) => state.loadLogs);
   const loadUndoEntries = useLogStore((state) => state.loadUndoEntries);
+  const loadApprovals = useLogStore((state) => state.loadApprovals);
+  const loadConflicts = useLogStore((state) => state.loadConflicts);
 
   useEffect(() => {
     void loadLogs(limit, 0);
     void loadUndoEntries();
+    void loadApprovals();
+    void loadConflicts();
     const id = window.setInterval(() => {
       void loadLogs(limit, 0);
       void loadUndoEntries();
+      // Expired conflicts are settled in the background.
+      void loadConflicts();
     }, pollMs);
-    return () => window.clearInterval(id);
-  }, [limit, loadLogs, loadUndoEntries, pollMs]);
+    const unlisten = listen("approval://pending", () => {
+      void loadApprovals();
+    });
+    const unlistenConflict = listen("conflict://pending", () => {
+      void loadConflicts();
+    });
+    // The give-up is logged; show it without waiting for the next poll.
+    const unlistenRetry = listen("engine://retry-exhausted", () => {
+      void loadLogs(limit, 0);
+    });
+    const unlistenBudget = listen("rule://budget-exhausted", () => {
+      void loadLogs(limit, 0);
+    });
+    return () => {
+      window.clearInterval(id);
+      void unlisten.then((fn) => fn());
+      void unlistenConflict.then((fn) => fn());
+      void unlistenRetry.then((fn) => fn());
+      void unlistenBudget.then((fn) => fn());
+    };
+  }, [limit, loadLogs, loadUndoEntries, loadApprovals, loadConflicts, pollMs]);
 }
@@ -1,4 +1,4 @@
-import type { Condition } from "@/types";
+import type { Condition, ImageDimensionsCondition, PixelBound } from "@/types";
 
 export function describeCondition(condition: Condition): string {
   switch (condition.type) {
@@ -8,6 +8,12 @@ export function describeCondition(condition: Condition): string {
       return `Extension ${formatStringOperator(condition.operator)} ${formatValue(condition.value)}`;
     case "fullName":
       return `Full name ${formatStringOperator(condition.operator)} ${formatValue(condition.value)}`;
+    case "path":
+      return `Path ${formatStringOperator(condition.operator)} ${formatValue(condition.value)}`;
+    case "parentFolder":
+      return `Parent folder ${formatStringOperator(condition.operator)} ${formatValue(condition.value)}`;
+    case "relativePath":
+      return `Relative path ${formatStringOperator(condition.operator)} ${formatValue(condition.value)}`;
     case "contents":
       return `Contents ${formatStringOperator(condition.operator)} ${formatValue(condition.value)}`;
     case "size":
@@ -20,12 +26,56 @@ export function describeCondition(condition: Condition): string {
       return `Date added ${formatDateOperator(condition.operator)}`;
     case "dateLastMatched":
       return `Date last matched ${formatDateOperator(condition.operator)}`;
+    case "exifDate":
+      return `Taken ${formatDateOperator(condition.operator)}`;
     case "currentTime":
       return `Current time ${formatTimeOperator(condition.operator)}`;
     case "kind":
       return `${condition.negate ? "Not " : ""}${condition.kind}`;
     case "shellScript":
       return "Shell script";
+    case "isLocked":
+      return condition.negate ? "File is not in use" : "File is in use";
+    case "classifiedAs":
+      return condition.category.trim()
+        ? `Classified as ${formatValue(condition.category)}`
+        : "Classified as any category";
+    case "faceCount":
+      return condition.operator.type === "between"
+        ? `Face count ${formatComparisonOperator(condition.operator)}`
+        : `Face count ${formatComparisonOperator(condition.operator)} ${condition.value}`;
+    case "pageCount":
+      return condition.operator.type === "between"
+        ? `PDF pages ${formatComparisonOperator(condition.operator)}`
+        : `PDF pages ${formatComparisonOperator(condition.operator)} ${condition.value}`;
+    case "mediaDuration":
+      return condition.operator.type === "between"
+        ? `Media duration ${formatComparisonOperator(condition.operator)} ${condition.unit}`
+        : `Media duration ${formatComparisonOperator(condition.operator)} ${condition.value} ${condition.unit}`;
+    case "mediaCodec":
+      return `Media codec ${formatStringOperator(condition.operator)} ${formatValue(condition.value)}`;
+    case "imageDimensions":
+      return formatImageDimensions(condition);
+    case "isScreenshot":
+      return condition.negate ? "Not a screenshot" : "Is a screenshot";
+    case "isDuplicate":
+      return `${condition.negate ? "Not a duplicate" : "Is a duplicate"} in ${condition.scope === "anyWatchedFolder" ? "any watched folder" : "this folder"}`;
+    case "groupSize":
+      return condition.operator.type === "between"
+        ? `Arrived in a group of ${formatComparisonOperator(condition.operator)}`
+        : `Arrived in a group of ${formatComparisonOperator(condition.operator)} ${condition.value}`;
+    case "activeApplication":
+      return `Active application ${formatStringOperator(condition.operator)} ${formatValue(condition.value)}`;
+    case "mimeType":
+      return `MIME type ${formatStringOperator(condition.operator)} ${formatValue(condition.value)}`;
+    case "tag":
+      return `Tagged ${formatStringOperator(condition.operator)} ${formatValue(condition.value)}`;
+    case "eventSource":
+      return `Brought in by ${condition.sources.join(" or ") || "nothing"}`;
+    case "csvColumn":
+      return `CSV ${formatCsvColumn(condition.column)} ${formatStringOperator(condition.condition.operator)} ${formatValue(condition.condition.value)}`;
+    case "existsAtDestination":
+      return `${condition.negate ? "Not already" : "Already"} in ${formatValue(condition.destinationPattern)}${condition.compare === "nameAndHash" ? " with same contents" : ""}`;
     case "nested":
       return condition.label?.trim()
         ? `Group "${condition.label.trim()}" (${condition.matchType.toUpperCase()}, ${condition.conditions.length})`
@@ -53,6 +103,14 @@ function formatStringOperator(operator: string) {
       return "matches";
     case "doesNotMatch":
       return "does not match";
+    case "matchesGlob":
+      return "matches glob";
+    case "doesNotMatchGlob":
+      return "does not match glob";
+    case "isOneOf":
+      return "is one of";
+    case "isNotOneOf":
+      return "is not one of";
     default:
       return operator;
   }
@@ -91,6 +149,10 @@ function formatDateOperator(operator: { type: string; date?: string; start?: str
       return `in the last ${operator.amount ?? "…"} ${operator.unit ?? ""}`;
     case "notInTheLast":
       return `not in the last ${operator.amount ?? "…"} ${operator.unit ?? ""}`;
+    case "inTheLastBusinessDays":
+      return `in the last ${operator.amount ?? "…"} business days`;
+    case "notInTheLastBusinessDays":
+      return `not in the last ${operator.amount ?? "…"} business days`;
     case "between":
       return `between ${operator.start ?? "…"} and ${operator.end ?? "…"}`;
     default:
@@ -113,6 +175,21 @@ function formatTimeOperator(operator: { type: string; time?: string; start?: str
   }
 }
 
+function formatImageDimensions(condition: ImageDimensionsCondition) {
+  const bound = (axis: string, bound?: PixelBound) =>
+    bound &&
+    (bound.operator.type === "between"
+      ? `${axis} ${formatComparisonOperator(bound.operator)} px`
+      : `${axis} ${formatComparisonOperator(bound.operator)} ${bound.value} px`);
+  const parts = [bound("width", condition.width), bound("height", condition.height), condition.shape];
+  const described = parts.filter(Boolean).join(", ");
+  return described ? `Image ${described}` : "Image of any size";
+}
+
+function formatCsvColumn(column: { type: string; index?: number; name?: string }) {
+  return column.type === "index" ? `column ${column.index ?? "…"}` : formatValue(column.name ?? "");
+}
+
 function formatValue(value: string) {
   return value ? `"${value}"` : "…";
 }
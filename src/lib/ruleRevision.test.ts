@@ -0,0 +1,30 @@
+import { describe, expect, test } from "bun:test";
+
+import type { PreviewItem } from "@/types";
+import { previewRevisionWarning } from "./ruleRevision";
+
+const item = (ruleRevision?: number): PreviewItem => ({
+  filePath: "/inbox/invoice.pdf",
+  matched: true,
+  conditionResults: [true],
+  actions: [],
+  ruleRevision,
+});
+
+describe("previewRevisionWarning", () => {
+  test("warns when the rule was saved again after the preview", () => {
+    expect(previewRevisionWarning([item(7)], 9)).toBe(
+      "Preview was computed against revision 7, current is 9",
+    );
+  });
+
+  test("stays quiet for a preview of the current revision", () => {
+    expect(previewRevisionWarning([item(9)], 9)).toBeNull();
+  });
+
+  test("stays quiet for unsaved drafts and empty previews", () => {
+    expect(previewRevisionWarning([item(0)], 3)).toBeNull();
+    expect(previewRevisionWarning([item(2)], undefined)).toBeNull();
+    expect(previewRevisionWarning([], 3)).toBeNull();
+  });
+});
@@ -0,0 +1,16 @@
+import type { PreviewItem } from "@/types";
+
+/**
+ * Says when a preview was made against an older revision of the rule than
+ * the one saved now, so its results aren't taken for what the rule does.
+ */
+export function previewRevisionWarning(
+  results: PreviewItem[],
+  currentRevision: number | undefined,
+): string | null {
+  const previewed = results[0]?.ruleRevision;
+  if (!previewed || !currentRevision || previewed === currentRevision) {
+    return null;
+  }
+  return `Preview was computed against revision ${previewed}, current is ${currentRevision}`;
+}
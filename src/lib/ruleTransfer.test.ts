@@ -15,6 +15,16 @@ describe("normalizeRuleImportPayload", () => {
     expect(normalized).toBe(JSON.stringify([{ id: "rule-1" }, { id: "rule-2" }]));
   });
 
+  test("passes versioned rule files through unchanged", () => {
+    const payload = JSON.stringify({ schemaVersion: "2.0", rules: [{ name: "Rule One" }] }, null, 2);
+    expect(normalizeRuleImportPayload(payload)).toBe(payload);
+  });
+
+  test("leaves YAML exports for the backend to read", () => {
+    const payload = "- name: Rule One\n  enabled: true\n";
+    expect(normalizeRuleImportPayload(payload)).toBe(payload);
+  });
+
   test("rejects non-object payloads", () => {
     const payload = JSON.stringify(12);
     expect(() => normalizeRuleImportPayload(payload)).toThrow(
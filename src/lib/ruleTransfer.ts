@@ -1,13 +1,19 @@
 /**
  * Normalizes a rule import payload by ensuring it's always an array of rules.
- * @param payload - JSON string containing either a single rule object or an array of rules
- * @returns JSON string of an array of rules
+ * Versioned rule files (with a `schemaVersion`) and YAML exports from older
+ * versions are passed through for the backend to check and upgrade.
+ * @param payload - JSON string containing a rule file, a single rule object or an array of rules
+ * @returns JSON string of a rule file or an array of rules, or the YAML payload unchanged
  * @throws Error if payload is empty, invalid JSON, or not a rule/array of rules
  */
 export function normalizeRuleImportPayload(payload: string): string {
-  if (!payload.trim()) {
+  const trimmed = payload.trim();
+  if (!trimmed) {
     throw new Error("Rule import file is empty.");
   }
+  if (!trimmed.startsWith("{") && !trimmed.startsWith("[")) {
+    return payload;
+  }
 
   let parsed: unknown;
   try {
@@ -25,6 +31,10 @@ export function normalizeRuleImportPayload(payload: string): string {
     return JSON.stringify(parsed);
   }
 
+  if (typeof parsed === "object" && parsed !== null && "schemaVersion" in parsed) {
+    return payload;
+  }
+
   // If it's a single object, wrap it in an array
   if (typeof parsed === "object" && parsed !== null) {
     return JSON.stringify([parsed]);
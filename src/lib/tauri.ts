@@ -1,11 +1,108 @@
-import { invoke } from "@tauri-apps/api/core";
+import { invoke, type InvokeArgs } from "@tauri-apps/api/core";
 
-import type { EngineStatusSnapshot, Folder, LogEntry, Preset, PreviewItem, Rule, UndoEntry } from "@/types";
+import type {
+  BackupInfo,
+  BudgetStatus,
+  BulkRuleEdit,
+  ClassificationTotal,
+  CommandError,
+  CommandErrorKind,
+  ConflictDecision,
+  ConflictResolution,
+  DryRunItem,
+  EngineStatusSnapshot,
+  ErrorNotificationAction,
+  FileInfoOverrides,
+  Folder,
+  FolderFlowGraph,
+  FolderKind,
+  FolderTemplateApplied,
+  FolderTemplateInfo,
+  HealthRepairOptions,
+  HealthRepairResult,
+  HealthReport,
+  InsightsSummary,
+  JournalPage,
+  JournalQuery,
+  KindMapping,
+  LaneOccupancy,
+  ManifestExport,
+  MatchQuery,
+  MatchRecord,
+  ManifestFormat,
+  LogEntry,
+  OfflineDestination,
+  PendingApproval,
+  PendingConflict,
+  PermissionStatus,
+  PlaceholderMode,
+  Preset,
+  PresetVariable,
+  PreviewItem,
+  Profile,
+  QuickSetupSelection,
+  QuickSetupSuggestion,
+  RetryEntry,
+  Rule,
+  RuleAnalysis,
+  RuleEditSummary,
+  RuleReferenceWarnings,
+  RuleRevision,
+  RuleSuggestion,
+  RuleTiming,
+  SandboxResult,
+  SandboxScenario,
+  UndoEntry,
+  UndoStats,
+  UnreviewedSummary,
+} from "@/types";
 import type { AppSettings } from "@/stores/settingsStore";
 
+/** A failed command that queues in a lane. `String(err)` is its message. */
+export class CommandFailure extends Error {
+  constructor(
+    readonly kind: CommandErrorKind,
+    message: string,
+  ) {
+    super(message);
+    this.name = "CommandFailure";
+  }
+
+  toString() {
+    return this.message;
+  }
+}
+
+/** The backend already waits for a place before answering busy. */
+const BUSY_RETRIES = 1;
+const BUSY_BACKOFF_MS = 500;
+
+function asCommandFailure(err: unknown): CommandFailure {
+  if (err && typeof err === "object" && "kind" in err && "message" in err) {
+    const { kind, message } = err as CommandError;
+    return new CommandFailure(kind, message);
+  }
+  return new CommandFailure("failed", String(err));
+}
+
+/** Invokes a command that queues in a lane, trying again while it's busy. */
+async function invokeQueued<T>(command: string, args?: InvokeArgs): Promise<T> {
+  for (let attempt = 0; ; attempt++) {
+    try {
+      return await invoke<T>(command, args);
+    } catch (err) {
+      const failure = asCommandFailure(err);
+      if (failure.kind !== "busy" || attempt >= BUSY_RETRIES) {
+        throw failure;
+      }
+      await new Promise((resolve) => setTimeout(resolve, BUSY_BACKOFF_MS * (attempt + 1)));
+    }
+  }
+}
+
 export const folderList = () => invoke<Folder[]>("folder_list");
-export const folderAdd = (path: string, name: string) =>
-  invoke<Folder>("folder_add", { path, name });
+export const folderAdd = (path: string, name: string, kind?: FolderKind) =>
+  invoke<Folder>("folder_add", { path, name, kind });
 export const folderRemove = (id: string) => invoke<void>("folder_remove", { id });
 export const folderToggle = (id: string, enabled: boolean) =>
   invoke<void>("folder_toggle", { id, enabled });
@@ -14,6 +111,14 @@ export interface FolderSettingsUpdate {
   removeDuplicates: boolean;
   trashIncompleteDownloads: boolean;
   incompleteTimeoutMinutes: number;
+  placeholderMode: PlaceholderMode;
+  strictSequential: boolean;
+  groupWindowMs: number;
+  passthroughPatterns: string[];
+  respectGit: boolean;
+  skipGitTracked: boolean;
+  activityThumbnails: boolean;
+  trashMaxMb: number;
 }
 
 export const folderCreateGroup = (name: string, parentId?: string) =>
@@ -22,63 +127,188 @@ export const folderMove = (id: string, parentId?: string) =>
   invoke<void>("folder_move", { id, parentId });
 export const folderRename = (id: string, name: string) =>
   invoke<void>("folder_rename", { id, name });
+export const folderRelink = (id: string, path: string) =>
+  invoke<Folder>("folder_relink", { id, path });
+
+export const folderUpdateSettings = (
+  id: string,
+  settings: FolderSettingsUpdate,
+  confirmBroadPassthrough?: boolean,
+) => invokeQueued<void>("folder_update_settings", { id, settings, confirmBroadPassthrough });
+
+export const folderTemplateSave = (folderId: string, name: string, variables?: PresetVariable[]) =>
+  invoke<FolderTemplateInfo>("folder_template_save", { folderId, name, variables });
+export const folderTemplateList = () => invoke<FolderTemplateInfo[]>("folder_template_list");
+export const folderTemplateApply = (
+  template: string,
+  path: string,
+  name?: string,
+  variables?: Record<string, string>,
+) =>
+  invoke<FolderTemplateApplied>("folder_template_apply", { template, path, name, variables });
+export const folderTemplateDelete = (id: string) => invoke<void>("folder_template_delete", { id });
+export const folderTemplateExport = (id: string, path: string) =>
+  invoke<void>("folder_template_export", { id, path });
+export const folderTemplateImport = (path: string) =>
+  invoke<FolderTemplateInfo>("folder_template_import", { path });
 
-export const folderUpdateSettings = (id: string, settings: FolderSettingsUpdate) =>
-  invoke<void>("folder_update_settings", { id, ...settings });
+export const quickSetupSuggestions = () =>
+  invoke<QuickSetupSuggestion[]>("quick_setup_suggestions");
+export const quickSetupApply = (selections: QuickSetupSelection[]) =>
+  invoke<Folder[]>("quick_setup_apply", { selections });
 
 export interface RunResult {
   total_files: number;
   processed: number;
   matched: number;
   errors: string[];
+  complete: boolean;
 }
-export const folderRunNow = (folderId: string) =>
-  invoke<RunResult>("folder_run_now", { folderId });
+export const folderRunNow = (folderId: string, requestId?: string) =>
+  invokeQueued<RunResult>("folder_run_now", { folderId, requestId });
+export const folderScanNow = (folderId: string, requestId?: string) =>
+  invokeQueued<number>("folder_scan_now", { folderId, requestId });
 
 export const ruleList = (folderId: string) =>
   invoke<Rule[]>("rule_list", { folderId });
 export const ruleGet = (id: string) => invoke<Rule | null>("rule_get", { id });
-export const ruleCreate = (rule: Rule) => invoke<Rule>("rule_create", { rule });
-export const ruleUpdate = (rule: Rule) => invoke<void>("rule_update", { rule });
-export const ruleDelete = (id: string) => invoke<void>("rule_delete", { id });
+export const ruleRevisionGet = (ruleId: string, revision: number) =>
+  invoke<RuleRevision | null>("rule_revision_get", { ruleId, revision });
+export const ruleBudgetStatus = (id: string) =>
+  invoke<BudgetStatus>("rule_budget_status", { id });
+export const ruleCreate = (rule: Rule) => invokeQueued<Rule>("rule_create", { rule });
+export const ruleUpdate = (rule: Rule) => invokeQueued<void>("rule_update", { rule });
+export const ruleDelete = (id: string) => invokeQueued<void>("rule_delete", { id });
+export const ruleCounterReset = (id: string) =>
+  invokeQueued<void>("rule_counter_reset", { id });
 export const ruleToggle = (id: string, enabled: boolean) =>
-  invoke<void>("rule_toggle", { id, enabled });
+  invokeQueued<void>("rule_toggle", { id, enabled });
 export const ruleReorder = (folderId: string, orderedIds: string[]) =>
-  invoke<void>("rule_reorder", { folderId, orderedIds });
+  invokeQueued<void>("rule_reorder", { folderId, orderedIds });
 export const ruleDuplicate = (id: string) => invoke<Rule>("rule_duplicate", { id });
-export const ruleExport = (folderId: string) =>
-  invoke<string>("rule_export", { folderId });
-export const ruleImport = (folderId: string, payload: string) =>
-  invoke<Rule[]>("rule_import", { folderId, payload });
+export const ruleExport = (folderId: string, password?: string) =>
+  invoke<string>("rule_export", { folderId, password });
+export const ruleImport = (folderId: string, payload: string, password?: string) =>
+  invoke<Rule[]>("rule_import", { folderId, payload, password });
+export const rulesBulkEdit = (ruleIds: string[], edit: BulkRuleEdit) =>
+  invokeQueued<RuleEditSummary[]>("rules_bulk_edit", { ruleIds, edit });
+export const rulesCheckReferences = () =>
+  invoke<RuleReferenceWarnings[]>("rules_check_references");
+export const rulesAnalyze = (folderId: string, empirical?: boolean, sampleSize?: number) =>
+  invokeQueued<RuleAnalysis>("rules_analyze", { folderId, empirical, sampleSize });
 
 export const logList = (limit?: number, offset?: number) =>
-  invoke<LogEntry[]>("log_list", { limit, offset });
-export const logClear = () => invoke<void>("log_clear");
+  invokeQueued<LogEntry[]>("log_list", { limit, offset });
+export const logClear = () => invokeQueued<void>("log_clear");
+export const thumbnailCacheClear = () => invokeQueued<number>("thumbnail_cache_clear");
+export const thumbnailRead = (path: string) => invokeQueued<string | null>("thumbnail_read", { path });
+export const errorsAcknowledge = () => invokeQueued<void>("errors_acknowledge");
+export const logsMarkReviewed = () => invokeQueued<void>("logs_mark_reviewed");
+export const logsUnreviewedSummary = (errorLimit?: number) =>
+  invokeQueued<UnreviewedSummary>("logs_unreviewed_summary", { errorLimit });
 export const undoList = (limit?: number) =>
-  invoke<UndoEntry[]>("undo_list", { limit });
-export const undoExecute = (undoId: string) =>
-  invoke<void>("undo_execute", { undoId });
+  invokeQueued<UndoEntry[]>("undo_list", { limit });
+export const undoExecute = (undoId: string, force?: boolean, onConflict?: ConflictResolution) =>
+  invoke<void>("undo_execute", { undoId, force, onConflict });
+export const undoStats = () => invokeQueued<UndoStats>("undo_stats");
+export const backupNow = (password?: string) => invoke<BackupInfo>("backup_now", { password });
+export const backupList = () => invoke<BackupInfo[]>("backup_list");
+export const backupRestore = (backupId: string, password?: string) =>
+  invoke<void>("backup_restore", { backupId, password });
+export const errorNotificationAction = (
+  failureId: string,
+  ruleId: string,
+  action: ErrorNotificationAction,
+) => invoke<void>("error_notification_action", { failureId, ruleId, action });
+export const approvalsList = () => invoke<PendingApproval[]>("approvals_list");
+export const approvalExecute = (approvalId: string) =>
+  invoke<void>("approval_execute", { approvalId });
+export const approvalDismiss = (approvalId: string) =>
+  invoke<void>("approval_dismiss", { approvalId });
+export const conflictsList = () => invoke<PendingConflict[]>("conflicts_list");
+export const conflictResolve = (id: string, decision: ConflictDecision) =>
+  invoke<void>("conflict_resolve", { id, decision });
+export const retriesList = () => invoke<RetryEntry[]>("retries_list");
+export const retryCancel = (retryId: string) => invoke<void>("retry_cancel", { retryId });
+export const retryForce = (retryId: string) => invoke<void>("retry_force", { retryId });
+export const insightsSummary = () => invokeQueued<InsightsSummary>("insights_summary");
+export const insightsReset = () => invokeQueued<void>("insights_reset");
+export const classificationReport = (from: string, to: string) =>
+  invokeQueued<ClassificationTotal[]>("classification_report", { from, to });
+export const healthCheck = () => invokeQueued<HealthReport>("health_check");
+export const healthRepair = (options: HealthRepairOptions) =>
+  invoke<HealthRepairResult>("health_repair", { options });
+export const folderFlowGraph = () => invokeQueued<FolderFlowGraph>("folder_flow_graph");
+
+export const permissionsStatus = () => invoke<PermissionStatus[]>("permissions_status");
 
 export const settingsGet = () => invoke<AppSettings>("settings_get");
 export const settingsUpdate = (settings: AppSettings) =>
-  invoke<void>("settings_update", { settings });
+  invokeQueued<void>("settings_update", { settings });
+export const kindMappingsList = () => invoke<KindMapping[]>("kind_mappings_list");
+
+export const profileList = () => invoke<Profile[]>("profile_list");
+export const profileCreate = (name: string) => invoke<Profile>("profile_create", { name });
+export const profileDelete = (name: string) => invoke<void>("profile_delete", { name });
+export const profileSwitch = (name: string) => invoke<Profile>("profile_switch", { name });
 
 export const engineStatusGet = () => invoke<EngineStatusSnapshot>("engine_status_get");
 export const enginePauseSet = (paused: boolean) =>
   invoke<boolean>("engine_pause_set", { paused });
 export const enginePauseToggle = () => invoke<boolean>("engine_pause_toggle");
+export const destinationsStatus = () =>
+  invoke<OfflineDestination[]>("destinations_status");
+export const ruleTimings = () => invoke<RuleTiming[]>("rule_timings");
+export const eventJournalQuery = (query: JournalQuery) =>
+  invokeQueued<JournalPage>("event_journal_query", { query });
+export const ruleMatchesExport = (
+  ruleId: string,
+  format: ManifestFormat,
+  options: { since?: string; until?: string; path?: string } = {},
+) => invokeQueued<ManifestExport>("rule_matches_export", { ruleId, format, ...options });
+
+export const matchesQuery = (query: MatchQuery = {}) =>
+  invokeQueued<MatchRecord[]>("matches_query", { ...query });
+
+export const eventJournalExport = (since?: string, until?: string, password?: string) =>
+  invokeQueued<string>("event_journal_export", { since, until, password });
 
 export const previewRule = (ruleId: string, requestId?: string) =>
-  invoke<PreviewItem[]>("preview_rule", { ruleId, requestId });
-export const previewFile = (ruleId: string, filePath: string, requestId?: string) =>
-  invoke<PreviewItem>("preview_file", { ruleId, filePath, requestId });
+  invokeQueued<PreviewItem[]>("preview_rule", { ruleId, requestId });
+export const ruleDryRun = (ruleId: string, requestId?: string) =>
+  invokeQueued<DryRunItem[]>("rule_dry_run", { ruleId, requestId });
+export const previewFile = (
+  ruleId: string,
+  filePath: string,
+  options: { overrides?: FileInfoOverrides; now?: string; requestId?: string } = {},
+) => invokeQueued<PreviewItem>("preview_file", { ruleId, filePath, ...options });
 
 export const previewRuleDraft = (
   rule: Rule,
   maxFiles?: number,
   skipContent?: boolean,
   requestId?: string,
-) => invoke<PreviewItem[]>("preview_rule_draft", { rule, maxFiles, skipContent, requestId });
+) => invokeQueued<PreviewItem[]>("preview_rule_draft", { rule, maxFiles, skipContent, requestId });
+
+export const ruleSuggest = (
+  examples: string[],
+  counterExamples: string[],
+  useContents?: boolean,
+  requestId?: string,
+) =>
+  invokeQueued<RuleSuggestion>("rule_suggest", {
+    examples,
+    counterExamples,
+    useContents,
+    requestId,
+  });
+
+export const sandboxRun = (folderId: string, scenario: SandboxScenario) =>
+  invokeQueued<SandboxResult>("sandbox_run", { folderId, scenario });
+export const sandboxScenarioSave = (path: string, scenario: SandboxScenario) =>
+  invoke<void>("sandbox_scenario_save", { path, scenario });
+export const sandboxScenarioLoad = (path: string) =>
+  invoke<SandboxScenario>("sandbox_scenario_load", { path });
 
 export const presetRead = (path: string) => invoke<Preset>("preset_read", { path });
 export const presetInstall = (
@@ -110,7 +340,30 @@ export const ocrDownloadLanguage = (languageId: string) =>
   invoke<void>("ocr_download_language", { languageId });
 export const ocrCancelDownload = (languageId: string) =>
   invoke<void>("ocr_cancel_download", { languageId });
+/** Cancels a preview or scan started with `requestId`, and OCR it's doing. */
+export const commandCancel = (requestId: string) =>
+  invoke<boolean>("command_cancel", { requestId });
+export const commandStats = () => invoke<LaneOccupancy[]>("command_stats");
 export const ocrCancelRequest = (requestId: string) =>
   invoke<void>("ocr_cancel_request", { requestId });
 export const ocrDeleteLanguage = (languageId: string) =>
   invoke<void>("ocr_delete_language", { languageId });
+
+export interface FaceModelStatus {
+  supported: boolean;
+  installed: boolean;
+}
+
+export const facesModelStatus = () => invoke<FaceModelStatus>("faces_model_status");
+export const facesDownloadModel = () => invoke<void>("faces_download_model");
+export const facesDeleteModel = () => invoke<void>("faces_delete_model");
+
+export interface ClassifierSummary {
+  version: number;
+  trainedAt: string;
+  documentCount: number;
+  categories: { name: string; documentCount: number }[];
+}
+
+export const classifierTrain = () => invoke<ClassifierSummary>("classifier_train");
+export const classifierStatus = () => invoke<ClassifierSummary | null>("classifier_status");
@@ -26,6 +26,15 @@ const createMockFolder = (overrides: Partial<Folder> = {}): Folder => ({
   trashIncompleteDownloads: false,
   incompleteTimeoutMinutes: 5,
   isGroup: false,
+  placeholderMode: "skip",
+  strictSequential: false,
+  groupWindowMs: 0,
+  passthroughPatterns: [],
+  respectGit: false,
+  skipGitTracked: false,
+  activityThumbnails: false,
+  kind: "directory",
+  trashMaxMb: 0,
   ...overrides,
 });
 
@@ -203,6 +212,14 @@ describe("useFolderStore", () => {
         removeDuplicates: true,
         trashIncompleteDownloads: false,
         incompleteTimeoutMinutes: 10,
+        placeholderMode: "hydrate" as const,
+        strictSequential: true,
+        groupWindowMs: 1500,
+        passthroughPatterns: ["desktop.ini"],
+        respectGit: true,
+        skipGitTracked: false,
+        activityThumbnails: false,
+        trashMaxMb: 0,
       };
       mockInvoke
         .mockResolvedValueOnce(undefined)
@@ -210,7 +227,7 @@ describe("useFolderStore", () => {
 
       await useFolderStore.getState().updateFolderSettings("folder-1", settings);
 
-      expect(mockInvoke).toHaveBeenCalledWith("folder_update_settings", { id: "folder-1", ...settings });
+      expect(mockInvoke).toHaveBeenCalledWith("folder_update_settings", { id: "folder-1", settings });
       expect(mockInvoke).toHaveBeenCalledWith("folder_list");
     });
 
@@ -222,6 +239,14 @@ describe("useFolderStore", () => {
         removeDuplicates: false,
         trashIncompleteDownloads: false,
         incompleteTimeoutMinutes: 5,
+        placeholderMode: "skip",
+        strictSequential: false,
+        groupWindowMs: 0,
+        passthroughPatterns: [],
+        respectGit: false,
+        skipGitTracked: false,
+        activityThumbnails: false,
+        trashMaxMb: 0,
       });
 
       expect(useFolderStore.getState().error).toContain("Update failed");
@@ -1,6 +1,6 @@
 import { create } from "zustand";
 
-import type { Folder } from "@/types";
+import type { Folder, FolderKind } from "@/types";
 import { folderAdd, folderList, folderRemove, folderToggle, folderUpdateSettings, folderCreateGroup, folderMove, folderRename } from "@/lib/tauri";
 
 interface FolderState {
@@ -9,12 +9,27 @@ interface FolderState {
   loading: boolean;
   error?: string;
   loadFolders: () => Promise<void>;
-  addFolder: (path: string, name: string) => Promise<void>;
+  addFolder: (path: string, name: string, kind?: FolderKind) => Promise<void>;
   removeFolder: (id: string) => Promise<void>;
   toggleFolder: (id: string, enabled: boolean) => Promise<void>;
   updateFolderSettings: (
     id: string,
-    settings: Pick<Folder, "scanDepth" | "removeDuplicates" | "trashIncompleteDownloads" | "incompleteTimeoutMinutes">,
+    settings: Pick<
+      Folder,
+      | "scanDepth"
+      | "removeDuplicates"
+      | "trashIncompleteDownloads"
+      | "incompleteTimeoutMinutes"
+      | "placeholderMode"
+      | "strictSequential"
+      | "groupWindowMs"
+      | "passthroughPatterns"
+      | "respectGit"
+      | "skipGitTracked"
+      | "activityThumbnails"
+      | "trashMaxMb"
+    >,
+    confirmBroadPassthrough?: boolean,
   ) => Promise<void>;
   createGroup: (name: string, parentId?: string) => Promise<void>;
   moveFolder: (id: string, parentId?: string) => Promise<void>;
@@ -41,10 +56,10 @@ export const useFolderStore = create<FolderState>((set, get) => ({
       set({ error: String(err), loading: false });
     }
   },
-  addFolder: async (path, name) => {
+  addFolder: async (path, name, kind) => {
     set({ loading: true, error: undefined });
     try {
-      await folderAdd(path, name);
+      await folderAdd(path, name, kind);
       await get().loadFolders();
     } catch (err) {
       set({ error: String(err), loading: false });
@@ -100,10 +115,10 @@ export const useFolderStore = create<FolderState>((set, get) => ({
       set({ error: String(err), loading: false });
     }
   },
-  updateFolderSettings: async (id, settings) => {
+  updateFolderSettings: async (id, settings, confirmBroadPassthrough) => {
     set({ loading: true, error: undefined });
     try {
-      await folderUpdateSettings(id, settings);
+      await folderUpdateSettings(id, settings, confirmBroadPassthrough);
       await get().loadFolders();
     } catch (err) {
       set({ error: String(err), loading: false });
@@ -1,23 +1,57 @@
 import { create } from "zustand";
 
-import type { LogEntry, UndoEntry } from "@/types";
-import { logClear, logList, undoExecute, undoList } from "@/lib/tauri";
+import type {
+  ConflictDecision,
+  ConflictResolution,
+  LogEntry,
+  PendingApproval,
+  PendingConflict,
+  UndoEntry,
+  UnreviewedSummary,
+} from "@/types";
+import {
+  approvalDismiss,
+  approvalExecute,
+  approvalsList,
+  conflictResolve,
+  conflictsList,
+  errorsAcknowledge,
+  logClear,
+  logList,
+  logsMarkReviewed,
+  logsUnreviewedSummary,
+  undoExecute,
+  undoList,
+} from "@/lib/tauri";
 
 interface LogState {
   entries: LogEntry[];
   undoEntries: UndoEntry[];
+  approvals: PendingApproval[];
+  conflicts: PendingConflict[];
   ruleStats: Record<string, { lastActivityAt?: string; recentErrors: number; recentEvents: number }>;
+  unreviewed?: UnreviewedSummary;
   loading: boolean;
   error?: string;
   loadLogs: (limit?: number, offset?: number) => Promise<void>;
   loadUndoEntries: (limit?: number) => Promise<void>;
-  undoAction: (undoId: string) => Promise<void>;
+  undoAction: (undoId: string, options?: { force?: boolean; onConflict?: ConflictResolution }) => Promise<void>;
+  loadApprovals: () => Promise<void>;
+  executeApproval: (approvalId: string) => Promise<void>;
+  dismissApproval: (approvalId: string) => Promise<void>;
+  loadConflicts: () => Promise<void>;
+  resolveConflict: (id: string, decision: ConflictDecision) => Promise<void>;
   clearLogs: () => Promise<void>;
+  acknowledgeErrors: () => Promise<void>;
+  loadUnreviewed: () => Promise<void>;
+  markReviewed: () => Promise<void>;
 }
 
 export const useLogStore = create<LogState>((set) => ({
   entries: [],
   undoEntries: [],
+  approvals: [],
+  conflicts: [],
   ruleStats: {},
   loading: false,
   error: undefined,
@@ -38,14 +72,85 @@ export const useLogStore = create<LogState>((set) => ({
       set({ error: String(err) });
     }
   },
-  undoAction: async (undoId) => {
+  undoAction: async (undoId, options) => {
     set({ loading: true, error: undefined });
     try {
-      await undoExecute(undoId);
+      await undoExecute(undoId, options?.force, options?.onConflict);
       const [entries, undoEntries] = await Promise.all([logList(100, 0), undoList(50)]);
       set({ entries, undoEntries, ruleStats: computeRuleStats(entries), loading: false });
     } catch (err) {
-      set({ error: String(err), loading: false });
+      // Undo failures arrive as { kind, message }.
+      const message =
+        err && typeof err === "object" && "message" in err ? String(err.message) : String(err);
+      set({ error: message, loading: false });
+    }
+  },
+  loadApprovals: async () => {
+    try {
+      const approvals = await approvalsList();
+      set({ approvals });
+    } catch (err) {
+      set({ error: String(err) });
+    }
+  },
+  executeApproval: async (approvalId) => {
+    set({ loading: true, error: undefined });
+    let error: string | undefined;
+    try {
+      await approvalExecute(approvalId);
+    } catch (err) {
+      // Approval failures arrive as { kind, message }; a changed file comes
+      // back re-planned and stays in the queue.
+      error = err && typeof err === "object" && "message" in err ? String(err.message) : String(err);
+    }
+    try {
+      const [entries, undoEntries, approvals] = await Promise.all([
+        logList(100, 0),
+        undoList(50),
+        approvalsList(),
+      ]);
+      set({ entries, undoEntries, approvals, ruleStats: computeRuleStats(entries), loading: false, error });
+    } catch (err) {
+      set({ error: error ?? String(err), loading: false });
+    }
+  },
+  dismissApproval: async (approvalId) => {
+    try {
+      await approvalDismiss(approvalId);
+      set((state) => ({ approvals: state.approvals.filter((approval) => approval.id !== approvalId) }));
+    } catch (err) {
+      const message =
+        err && typeof err === "object" && "message" in err ? String(err.message) : String(err);
+      set({ error: message });
+    }
+  },
+  loadConflicts: async () => {
+    try {
+      const conflicts = await conflictsList();
+      set({ conflicts });
+    } catch (err) {
+      set({ error: String(err) });
+    }
+  },
+  resolveConflict: async (id, decision) => {
+    set({ loading: true, error: undefined });
+    let error: string | undefined;
+    try {
+      await conflictResolve(id, decision);
+    } catch (err) {
+      // Conflict failures arrive as { kind, message }; a file that changed
+      // meanwhile may come back as a new conflict.
+      error = err && typeof err === "object" && "message" in err ? String(err.message) : String(err);
+    }
+    try {
+      const [entries, undoEntries, conflicts] = await Promise.all([
+        logList(100, 0),
+        undoList(50),
+        conflictsList(),
+      ]);
+      set({ entries, undoEntries, conflicts, ruleStats: computeRuleStats(entries), loading: false, error });
+    } catch (err) {
+      set({ error: error ?? String(err), loading: false });
     }
   },
   clearLogs: async () => {
@@ -57,6 +162,28 @@ export const useLogStore = create<LogState>((set) => ({
       set({ error: String(err), loading: false });
     }
   },
+  acknowledgeErrors: async () => {
+    try {
+      await errorsAcknowledge();
+    } catch (err) {
+      set({ error: String(err) });
+    }
+  },
+  loadUnreviewed: async () => {
+    try {
+      const unreviewed = await logsUnreviewedSummary();
+      set({ unreviewed });
+    } catch (err) {
+      set({ error: String(err) });
+    }
+  },
+  markReviewed: async () => {
+    try {
+      await logsMarkReviewed();
+    } catch (err) {
+      set({ error: String(err) });
+    }
+  },
 }));
 
 function computeRuleStats(entries: LogEntry[]) {
@@ -1,18 +1,72 @@
 import { create } from "zustand";
 
 import { settingsGet, settingsUpdate } from "@/lib/tauri";
+import type { ConflictDecision, ErrorNotifyMode, FileKind, OnReappear, ReflinkMode } from "@/types";
 
 export type ThemeMode = "light" | "dark" | "system" | "magi";
+export type ProcessedMarkerMode = "trust" | "verify" | "ignore";
+
+export interface DailyDigestSettings {
+  enabled: boolean;
+  /** Local time, "HH:MM:SS". */
+  time: string;
+  includeErrors: boolean;
+  skipEmptyDays: boolean;
+}
+
+export interface AutoBackupSettings {
+  enabled: boolean;
+  intervalDays: number;
+  /** 0 keeps every backup. */
+  keepCount: number;
+  /** null puts backups in a "backups" folder next to the database. */
+  destinationDir: string | null;
+}
+
+export type SampledMatch = "trust" | "confirm";
+
+export interface DedupeActionsSettings {
+  /** Notifications with the same message. */
+  notify: boolean;
+  /** Moves, copies and sorts to the same destination. */
+  fileOperations: boolean;
+}
+
+export interface SampledHashSettings {
+  enabled: boolean;
+  /** Smaller files are always hashed in full. */
+  minSizeBytes: number;
+  headBytes: number;
+  tailBytes: number;
+  windowCount: number;
+  windowBytes: number;
+  /** Whether files with the same samples are hashed in full before counting as duplicates. */
+  onMatch: SampledMatch;
+}
+
+export interface ClassificationCode {
+  code: string;
+  label: string;
+  description: string;
+}
 
 export interface AppSettings {
   startAtLogin: boolean;
   showNotifications: boolean;
+  respectDnd: boolean;
+  notifyOnError: ErrorNotifyMode;
   minimizeToTray: boolean;
   debounceMs: number;
   maxConcurrentRules: number;
   pollingFallback: boolean;
   ignorePatterns: string[];
   logRetentionDays: number;
+  undoRetentionDays: number;
+  undoMaxEntries: number;
+  approvalExpiryHours: number;
+  conflictExpiryHours: number;
+  conflictFallback: ConflictDecision;
+  insightsSecondsPerFile: number;
   theme: ThemeMode;
   dateFormat: string;
   timeFormat: string;
@@ -21,9 +75,14 @@ export interface AppSettings {
   compactMode: boolean;
   dryRun: boolean;
   allowPermanentDelete: boolean;
+  allowQuarantineStrip: boolean;
+  useReflink: ReflinkMode;
   contentEnableOcr: boolean;
   contentMaxTextBytes: number;
   contentMaxOcrImageBytes: number;
+  /** Images over this size aren't probed for their dimensions; 0 probes every one. */
+  contentMaxImageProbeBytes: number;
+  contentMaxPdfProbeBytes: number;
   contentMaxOcrPdfBytes: number;
   contentMaxOcrPdfPages: number;
   contentOcrTimeoutImageMs: number;
@@ -41,6 +100,49 @@ export interface AppSettings {
   ocrEnableDeskew: boolean;
   ocrEnableBinarization: boolean;
   previewMaxFiles: number;
+  retryLockedFiles: boolean;
+  lockedRetryDelaySeconds: number;
+  placeholderHydrateMaxWaitSeconds: number;
+  writeProcessedMarkers: boolean;
+  processedMarkerMode: ProcessedMarkerMode;
+  holidays: string[];
+  defaultTimezone: string | null;
+  classifierEnabled: boolean;
+  classifierRoot: string;
+  classifierMaxFilesPerCategory: number;
+  slowRuleWarningMs: number;
+  scanMaxFilesPerRun: number;
+  scanFilesPerSecond: number;
+  scanIntervalMinutes: number;
+  followFolderRenames: boolean;
+  eventJournalEnabled: boolean;
+  eventJournalMaxEntries: number;
+  dailyDigest: DailyDigestSettings;
+  autoBackup: AutoBackupSettings;
+  /** How duplicate checks compare huge files. */
+  sampledHashing: SampledHashSettings;
+  /** What rules without their own setting do with a file they moved away that comes back. */
+  onReappear: OnReappear;
+  /** Actions a later rule skips when an earlier rule already did them for the same file event. */
+  dedupeActions: DedupeActionsSettings;
+  /** What the unique-suffix conflict policy adds to a name, from {millis}, {random} and {host}. */
+  uniqueSuffixFormat: string;
+  /** Recorded metadata keys that match queries look up through an index. */
+  matchMetadataIndexedKeys: string[];
+  /** Size the thumbnail cache is trimmed to, least recently used first. */
+  thumbnailCacheMaxMb: number;
+  /** Keep a copy of each saved revision of a rule, pruned with the activity log. */
+  keepRuleRevisions: boolean;
+  /** How long processed events' records may wait to be committed together; 0 commits each at once. */
+  dbBatchCommitMs: number;
+  /** The codes rules may be classified with. */
+  classificationCodes: ClassificationCode[];
+  /** Rules without a classification fail validation. */
+  requireClassification: boolean;
+  /** Name prefixes of screenshot tools to recognize beyond the built-in ones. */
+  screenshotNamePrefixes: string[];
+  /** Extensions mapped to a kind, on top of the built-in table. */
+  kindOverrides: Record<string, FileKind>;
 }
 
 interface SettingsState {
@@ -55,12 +157,20 @@ interface SettingsState {
 export const defaultSettings: AppSettings = {
   startAtLogin: true,
   showNotifications: true,
+  respectDnd: false,
+  notifyOnError: "firstPerRule",
   minimizeToTray: true,
   debounceMs: 500,
   maxConcurrentRules: 4,
   pollingFallback: false,
   ignorePatterns: [".DS_Store", "Thumbs.db", ".git", "node_modules", "*.tmp", "*.part"],
   logRetentionDays: 30,
+  undoRetentionDays: 7,
+  undoMaxEntries: 1000,
+  approvalExpiryHours: 72,
+  conflictExpiryHours: 24,
+  conflictFallback: { type: "skip" },
+  insightsSecondsPerFile: 10,
   theme: "system",
   dateFormat: "%Y-%m-%d",
   timeFormat: "%H-%M-%S",
@@ -69,9 +179,13 @@ export const defaultSettings: AppSettings = {
   compactMode: false,
   dryRun: false,
   allowPermanentDelete: false,
+  allowQuarantineStrip: false,
+  useReflink: "auto",
   contentEnableOcr: true,
   contentMaxTextBytes: 10 * 1024 * 1024,
   contentMaxOcrImageBytes: 15 * 1024 * 1024,
+  contentMaxImageProbeBytes: 512 * 1024 * 1024,
+  contentMaxPdfProbeBytes: 512 * 1024 * 1024,
   contentMaxOcrPdfBytes: 30 * 1024 * 1024,
   contentMaxOcrPdfPages: 25,
   contentOcrTimeoutImageMs: 15_000,
@@ -89,6 +203,55 @@ export const defaultSettings: AppSettings = {
   ocrEnableDeskew: false,
   ocrEnableBinarization: false,
   previewMaxFiles: 100,
+  retryLockedFiles: true,
+  lockedRetryDelaySeconds: 30,
+  placeholderHydrateMaxWaitSeconds: 600,
+  writeProcessedMarkers: false,
+  processedMarkerMode: "verify",
+  holidays: [],
+  defaultTimezone: null,
+  classifierEnabled: false,
+  classifierRoot: "",
+  classifierMaxFilesPerCategory: 200,
+  slowRuleWarningMs: 250,
+  scanMaxFilesPerRun: 0,
+  scanFilesPerSecond: 0,
+  scanIntervalMinutes: 0,
+  followFolderRenames: true,
+  eventJournalEnabled: false,
+  eventJournalMaxEntries: 10000,
+  dailyDigest: {
+    enabled: false,
+    time: "18:00:00",
+    includeErrors: true,
+    skipEmptyDays: true,
+  },
+  autoBackup: {
+    enabled: true,
+    intervalDays: 1,
+    keepCount: 7,
+    destinationDir: null,
+  },
+  sampledHashing: {
+    enabled: true,
+    minSizeBytes: 1024 * 1024 * 1024,
+    headBytes: 4 * 1024 * 1024,
+    tailBytes: 4 * 1024 * 1024,
+    windowCount: 8,
+    windowBytes: 1024 * 1024,
+    onMatch: "confirm",
+  },
+  onReappear: { type: "skipSilently" },
+  dedupeActions: { notify: true, fileOperations: false },
+  uniqueSuffixFormat: "{millis}.{random}.{host}",
+  matchMetadataIndexedKeys: [],
+  thumbnailCacheMaxMb: 64,
+  keepRuleRevisions: false,
+  dbBatchCommitMs: 0,
+  classificationCodes: [],
+  requireClassification: false,
+  screenshotNamePrefixes: [],
+  kindOverrides: {},
 };
 
 export const useSettingsStore = create<SettingsState>((set, get) => ({
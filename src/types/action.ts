@@ -1,15 +1,66 @@
-export type ConflictResolution = "rename" | "replace" | "skip";
+export type ConflictResolution = "rename" | "replace" | "skip" | "ask" | "uniqueSuffix";
+
+/** Mode and group a moved or copied file gets once it lands. Unix only. */
+export type DestinationPermissions =
+  | { type: "keep" }
+  | { type: "inherit" }
+  | { type: "explicit"; mode?: string; group?: string };
+
+/** What happens to macOS's quarantine attribute when a file is moved or copied. */
+export type QuarantineHandling = "keep" | "preserve" | "strip";
+
+/** Files next to a moved, copied or sorted file that go along with it. */
+export interface BringCompanions {
+  /** Extensions of files sharing the file's stem, such as `xmp`. */
+  extensions: string[];
+  /** Glob matched against the names of files next to it, tokens resolved first. */
+  pattern?: string;
+  /** A listed extension with no file isn't an error. */
+  missingOk?: boolean;
+  /** A companion that can't be brought along fails the whole action. */
+  failOnError?: boolean;
+  /** Records companions as handled so later rules skip them. Defaults to true. */
+  recordMatches?: boolean;
+}
+
+export type DateGranularity = "year" | "month" | "day";
+
+/** Spreads files over subfolders of the destination so none grows too big. */
+export type AutoPartition =
+  | {
+      type: "byCount";
+      maxPerDir: number;
+      /** Subfolder name with `{n}` or padded `{n:4}` for its number. */
+      subdirPattern: string;
+    }
+  | {
+      type: "byDate";
+      granularity: DateGranularity;
+      dateSource: "created" | "modified" | "now";
+    };
 
 export interface MoveAction {
   destination: string;
   onConflict: ConflictResolution;
   skipDuplicates: boolean;
+  destinationPermissions?: DestinationPermissions;
+  quarantine?: QuarantineHandling;
+  bringCompanions?: BringCompanions;
+  autoPartition?: AutoPartition;
 }
 
+export type ReflinkMode = "auto" | "always" | "never";
+
 export interface CopyAction {
   destination: string;
   onConflict: ConflictResolution;
   skipDuplicates: boolean;
+  /** Overrides the global copy-on-write setting; unset follows it. */
+  useReflink?: ReflinkMode;
+  destinationPermissions?: DestinationPermissions;
+  quarantine?: QuarantineHandling;
+  bringCompanions?: BringCompanions;
+  autoPartition?: AutoPartition;
 }
 
 export interface RenameAction {
@@ -20,19 +71,42 @@ export interface RenameAction {
 export interface SortAction {
   destination: string;
   onConflict: ConflictResolution;
+  destinationPermissions?: DestinationPermissions;
+  quarantine?: QuarantineHandling;
+  bringCompanions?: BringCompanions;
+  autoPartition?: AutoPartition;
 }
 
 export type ArchiveFormat = "zip" | "tar" | "tarGz";
 
+/** Where an archive password comes from; looked up each time the action runs. */
+export type PasswordSource =
+  | { type: "literal"; value: string }
+  | { type: "envVar"; value: string }
+  | { type: "keyring"; value: string };
+
+export interface ArchiveEncryption {
+  method: "zipAes256";
+  passwordSource: PasswordSource;
+}
+
 export interface ArchiveAction {
   destination: string;
   format: ArchiveFormat;
   deleteAfter: boolean;
+  /** Zip only. */
+  encryption?: ArchiveEncryption | null;
 }
 
 export interface UnarchiveAction {
   destination?: string;
   deleteAfter: boolean;
+  /** Only entries matching one of these are extracted; empty extracts all. */
+  includeGlobs?: string[];
+  /** Entries matching any of these are left out, even when included. */
+  excludeGlobs?: string[];
+  /** Leaves out __MACOSX, .DS_Store and Thumbs.db entries. */
+  skipJunk?: boolean;
 }
 
 export interface DeleteAction {
@@ -45,6 +119,10 @@ export interface ScriptAction {
 
 export interface NotifyAction {
   message: string;
+  /** A bundled sound file such as "chime.wav" or a system sound name. */
+  sound?: string;
+  /** Shows a thumbnail of a matched image or PDF where the platform can. */
+  generateThumbnail?: boolean;
 }
 
 export type OpenAction = object;
@@ -64,6 +142,24 @@ export interface MakePdfSearchableAction {
   overwrite: boolean;
 }
 
+export interface CreateFolderStructureAction {
+  baseDestination: string;
+  structure: string[];
+}
+
+export interface DispatchToFolderAction {
+  folderId: string;
+}
+
+export type TagMode = "append" | "replace";
+
+export interface SetTagsAction {
+  /** Tag names; patterns are resolved. */
+  tags: string[];
+  /** Whether the tags are added to the file's or replace them; appends by default. */
+  mode?: TagMode;
+}
+
 export type Action =
   | { type: "move" } & MoveAction
   | { type: "copy" } & CopyAction
@@ -79,6 +175,9 @@ export type Action =
   | { type: "showInFileManager" } & ShowInFileManagerAction
   | { type: "openWith" } & OpenWithAction
   | { type: "makePdfSearchable" } & MakePdfSearchableAction
+  | { type: "createFolderStructure" } & CreateFolderStructureAction
+  | { type: "dispatchToFolder" } & DispatchToFolderAction
+  | { type: "setTags" } & SetTagsAction
   | { type: "pause" } & PauseAction
   | { type: "continue" }
   | { type: "ignore" };
@@ -104,6 +203,9 @@ export type ActionType =
   | "showInFileManager"
   | "openWith"
   | "makePdfSearchable"
+  | "createFolderStructure"
+  | "dispatchToFolder"
+  | "setTags"
   | "pause"
   | "continue"
   | "ignore";
@@ -0,0 +1,53 @@
+import type { ActionType } from "./action";
+import type { HealthSeverity } from "./health";
+
+export type FindingKind = "shadowed" | "likelyShadowed" | "overlap" | "unsatisfiable";
+
+export interface RuleFinding {
+  kind: FindingKind;
+  ruleId: string;
+  ruleName: string;
+  relatedRuleId?: string;
+  message: string;
+}
+
+export interface RuleAnalysis {
+  folderId: string;
+  findings: RuleFinding[];
+  sampledFiles: number;
+}
+
+export interface FlowFolder {
+  id: string;
+  name: string;
+  path: string;
+  scanDepth: number;
+}
+
+/** One rule action that hands files from one watched folder to another. */
+export interface FlowEdge {
+  fromFolderId: string;
+  toFolderId: string;
+  ruleId: string;
+  ruleName: string;
+  actionType: ActionType;
+  /** The destination pattern, or the target folder's name for a dispatch. */
+  destination: string;
+}
+
+export type FlowKind = "handoff" | "chain" | "cycle";
+
+export interface FlowFinding {
+  kind: FlowKind;
+  severity: HealthSeverity;
+  /** In the order files pass through; a cycle ends where it started. */
+  folderIds: string[];
+  ruleIds: string[];
+  message: string;
+}
+
+export interface FolderFlowGraph {
+  folders: FlowFolder[];
+  edges: FlowEdge[];
+  findings: FlowFinding[];
+}
@@ -0,0 +1,31 @@
+import type { ActionType } from "./action";
+
+export interface PlannedAction {
+  actionType: ActionType;
+  sourcePath: string;
+  destinationPath?: string | null;
+}
+
+export interface PendingApproval {
+  id: string;
+  ruleId: string;
+  ruleName: string;
+  filePath: string;
+  fileHash: string;
+  captures: Record<string, string>;
+  planned: PlannedAction[];
+  createdAt: string;
+}
+
+export type ApprovalErrorKind =
+  | "notFound"
+  | "expired"
+  | "missing"
+  | "replanned"
+  | "noLongerMatches"
+  | "failed";
+
+export interface ApprovalError {
+  kind: ApprovalErrorKind;
+  message: string;
+}
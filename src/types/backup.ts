@@ -0,0 +1,24 @@
+export type BackupStatus = "valid" | "newerSchema" | "invalid" | "encrypted";
+
+export interface BackupInfo {
+  id: string;
+  path: string;
+  createdAt: string;
+  sizeBytes: number;
+  schemaVersion: number | null;
+  status: BackupStatus;
+  problem?: string;
+}
+
+export type BackupErrorKind =
+  | "notFound"
+  | "invalid"
+  | "newerSchema"
+  | "passwordRequired"
+  | "wrongPassword"
+  | "failed";
+
+export interface BackupError {
+  kind: BackupErrorKind;
+  message: string;
+}
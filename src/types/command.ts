@@ -0,0 +1,19 @@
+export type Lane = "readLight" | "readHeavy" | "write" | "ocr";
+
+export interface LaneOccupancy {
+  lane: Lane;
+  running: number;
+  waiting: number;
+  limit: number;
+}
+
+/**
+ * `busy`: the command's lane stayed full and nothing ran, so it's safe to
+ * retry. `timeout`: it ran past its time limit and stopped.
+ */
+export type CommandErrorKind = "busy" | "timeout" | "cancelled" | "failed";
+
+export interface CommandError {
+  kind: CommandErrorKind;
+  message: string;
+}
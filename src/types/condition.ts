@@ -8,7 +8,11 @@ export type StringOperator =
   | "startsWith"
   | "endsWith"
   | "matches"
-  | "doesNotMatch";
+  | "doesNotMatch"
+  | "matchesGlob"
+  | "doesNotMatchGlob"
+  | "isOneOf"
+  | "isNotOneOf";
 
 export interface StringCondition {
   operator: StringOperator;
@@ -23,6 +27,7 @@ export interface ContentCondition {
   value: string;
   caseSensitive: boolean;
   source: ContentSource;
+  minOcrConfidence?: number | null;
 }
 
 export type ComparisonOperator =
@@ -50,10 +55,14 @@ export type DateOperator =
   | { type: "isAfter"; date: string }
   | { type: "inTheLast"; amount: number; unit: TimeUnit }
   | { type: "notInTheLast"; amount: number; unit: TimeUnit }
+  | { type: "inTheLastBusinessDays"; amount: number }
+  | { type: "notInTheLastBusinessDays"; amount: number }
   | { type: "between"; start: string; end: string };
 
 export interface DateCondition {
   operator: DateOperator;
+  /** IANA zone or "default"; unset keeps the built-in behavior. */
+  timezone?: string | null;
 }
 
 export type TimeOperator =
@@ -64,6 +73,8 @@ export type TimeOperator =
 
 export interface TimeCondition {
   operator: TimeOperator;
+  /** IANA zone or "default"; unset uses local time. */
+  timezone?: string | null;
 }
 
 export type FileKind =
@@ -82,23 +93,141 @@ export interface KindCondition {
   negate: boolean;
 }
 
+export type KindSource = "default" | "override";
+
+/** An entry of the extension table kinds are told by. */
+export interface KindMapping {
+  extension: string;
+  kind: FileKind;
+  source: KindSource;
+}
+
 export interface ShellCondition {
   command: string;
 }
 
+export interface LockCondition {
+  negate: boolean;
+}
+
+export interface ClassifiedAsCondition {
+  category: string;
+  minScore: number;
+}
+
+export type ArchiveMatchScope = "anyEntry" | "allEntries";
+
+export interface ArchiveContainsCondition {
+  nameCondition: StringCondition;
+  matchScope: ArchiveMatchScope;
+  maxEntries: number;
+}
+
+export interface FaceCountCondition {
+  operator: ComparisonOperator;
+  value: number;
+}
+
+/** A PDF's number of pages; never matches other files. */
+export interface PageCountCondition {
+  operator: ComparisonOperator;
+  value: number;
+}
+
+/** How long a video or audio file plays, compared in whole seconds; never matches other files. */
+export interface MediaDurationCondition {
+  operator: ComparisonOperator;
+  value: number;
+  unit: TimeUnit;
+}
+
+/** An image's size in pixels from its header; every part given has to hold. */
+export interface ImageDimensionsCondition {
+  width?: PixelBound;
+  height?: PixelBound;
+  shape?: ImageShape;
+}
+
+export interface PixelBound {
+  operator: ComparisonOperator;
+  value: number;
+}
+
+export type ImageShape = "portrait" | "landscape" | "square";
+
+/** How many files arrived together with this one, counting itself. */
+export interface GroupSizeCondition {
+  operator: ComparisonOperator;
+  value: number;
+}
+
+/** What brought a file to the engine: the watcher, or a pass over files already there. */
+export type EventSource = "live" | "scan" | "catchup" | "manual" | "retry" | "sweep";
+
+export type CsvColumnRef = { type: "index"; index: number } | { type: "header"; name: string };
+
+export type CsvRowScope = { type: "first" } | { type: "any"; maxRows: number };
+
+export type CsvDelimiter = { type: "auto" } | { type: "char"; value: string };
+
+export interface CsvColumnCondition {
+  column: CsvColumnRef;
+  row: CsvRowScope;
+  condition: StringCondition;
+  delimiter: CsvDelimiter;
+}
+
+export type CollisionCompare = "nameOnly" | "nameAndHash";
+
+export interface ExistsAtDestinationCondition {
+  destinationPattern: string;
+  compare: CollisionCompare;
+  negate: boolean;
+}
+
+export type DuplicateScope = "sameFolder" | "anyWatchedFolder";
+
 export type Condition =
   | { type: "name"; operator: StringOperator; value: string; caseSensitive: boolean }
   | { type: "extension"; operator: StringOperator; value: string; caseSensitive: boolean }
   | { type: "fullName"; operator: StringOperator; value: string; caseSensitive: boolean }
-  | { type: "contents"; operator: StringOperator; value: string; caseSensitive: boolean; source: ContentSource }
+  | { type: "path"; operator: StringOperator; value: string; caseSensitive: boolean }
+  | { type: "parentFolder"; operator: StringOperator; value: string; caseSensitive: boolean }
+  | { type: "relativePath"; operator: StringOperator; value: string; caseSensitive: boolean }
+  | {
+      type: "contents";
+      operator: StringOperator;
+      value: string;
+      caseSensitive: boolean;
+      source: ContentSource;
+      minOcrConfidence?: number | null;
+    }
   | { type: "size"; operator: ComparisonOperator; value?: number; unit: SizeUnit }
   | { type: "dateCreated"; operator: DateOperator }
   | { type: "dateModified"; operator: DateOperator }
   | { type: "dateAdded"; operator: DateOperator }
   | { type: "dateLastMatched"; operator: DateOperator }
+  | { type: "exifDate"; operator: DateOperator }
   | { type: "currentTime"; operator: TimeOperator }
   | { type: "kind"; kind: FileKind; negate: boolean }
   | { type: "shellScript"; command: string }
+  | { type: "isLocked"; negate: boolean }
+  | { type: "classifiedAs"; category: string; minScore: number }
+  | { type: "archiveContains" } & ArchiveContainsCondition
+  | { type: "faceCount" } & FaceCountCondition
+  | { type: "imageDimensions" } & ImageDimensionsCondition
+  | { type: "pageCount" } & PageCountCondition
+  | { type: "mediaDuration" } & MediaDurationCondition
+  | { type: "mediaCodec"; operator: StringOperator; value: string; caseSensitive: boolean }
+  | { type: "isScreenshot"; negate: boolean }
+  | { type: "isDuplicate"; scope: DuplicateScope; negate: boolean }
+  | { type: "groupSize" } & GroupSizeCondition
+  | { type: "activeApplication"; operator: StringOperator; value: string; caseSensitive: boolean }
+  | { type: "mimeType"; operator: StringOperator; value: string; caseSensitive: boolean }
+  | { type: "tag"; operator: StringOperator; value: string; caseSensitive: boolean }
+  | { type: "eventSource"; sources: EventSource[] }
+  | { type: "csvColumn" } & CsvColumnCondition
+  | { type: "existsAtDestination" } & ExistsAtDestinationCondition
   | { type: "nested"; label?: string; matchType: MatchType; conditions: Condition[] };
 
 export interface ConditionGroup {
@@ -0,0 +1,41 @@
+import type { Action, ActionType } from "./action";
+
+export interface ConflictFile {
+  path: string;
+  size: number;
+  modified?: string | null;
+  hash: string;
+}
+
+export interface PendingConflict {
+  id: string;
+  ruleId: string;
+  ruleName: string;
+  traceId?: string | null;
+  actionType: ActionType;
+  actionIndex: number;
+  actions: Action[];
+  captures: Record<string, string>;
+  source: ConflictFile;
+  destination: ConflictFile;
+  createdAt: string;
+}
+
+export type ConflictDecision =
+  | { type: "skip" }
+  | { type: "replace" }
+  | { type: "rename" }
+  | { type: "useOtherName"; value: string };
+
+export type ConflictErrorKind =
+  | "notFound"
+  | "missing"
+  | "invalidName"
+  | "replanned"
+  | "noLongerMatches"
+  | "failed";
+
+export interface ConflictError {
+  kind: ConflictErrorKind;
+  message: string;
+}
@@ -1,3 +1,6 @@
+import type { UnavailableFolder } from "./folder";
+import type { HealthIssue } from "./health";
+
 export interface EngineEvent {
   path: string;
   folderId: string;
@@ -16,13 +19,126 @@ export interface EngineStatus {
   processedCount: number;
   lastEvent: EngineEvent | null;
   lastError: EngineError | null;
+  /** Problems the startup health check found, until a repair clears them. */
+  healthIssues: HealthIssue[];
+  /** The slowest rule run of the last hour. */
+  slowestRule: SlowRule | null;
+  /** Watched folders that were removed or moved out of sight. */
+  unavailableFolders: UnavailableFolder[];
+  /** Enabled rules pointing at a missing app, script, model or volume. */
+  brokenReferenceRules: number;
+  /** Strict sequential folders and how many of their files are waiting or running. */
+  serializedFolders: SerializedFolder[];
+  /** Destination volumes that are offline with transfers waiting for them. */
+  offlineDestinations: OfflineDestination[];
+  /** Where the work deferred from app launch has got to; `ready` once done. */
+  startup: StartupProgress | null;
+  /** Database commits over the last minute. */
+  dbWrites: DbWriteStats;
   updatedAt: string;
 }
 
+export interface DbWriteStats {
+  commitsPerSecond: number;
+  /** Events whose records each commit carried. */
+  averageBatchSize: number;
+}
+
+export type StartupPhase = "cleanup" | "sweep" | "health" | "watch" | "catchUp" | "ready";
+
+/** Payload of `startup://progress`. */
+export interface StartupProgress {
+  phase: StartupPhase;
+  processed: number;
+  finished: boolean;
+}
+
+export interface OfflineDestination {
+  path: string;
+  queuedFiles: number;
+}
+
+export interface SerializedFolder {
+  folderId: string;
+  backlog: number;
+}
+
+export type TimingPhase = "evaluation" | "execution";
+
+export interface SlowRule {
+  ruleId: string;
+  ruleName: string;
+  phase: TimingPhase;
+  durationMs: number;
+  at: string;
+}
+
+export interface DurationStats {
+  count: number;
+  meanMs: number;
+  p95Ms: number;
+  maxMs: number;
+  lastMs: number;
+}
+
+export interface RegexCompileStats {
+  cacheHits: number;
+  cacheMisses: number;
+  compileMs: number;
+}
+
+export interface RuleTiming {
+  ruleId: string;
+  ruleName: string;
+  evaluation: DurationStats;
+  execution: DurationStats;
+  regex: RegexCompileStats;
+  updatedAt: string;
+}
+
+export type EventDisposition =
+  | "processed"
+  | "debounced"
+  | "paused"
+  | "ignored"
+  | "grouped"
+  | "error";
+
+/** The application in front when a file's event came in. */
+export type ActiveApp =
+  | { status: "known"; name: string; identifier: string | null }
+  | { status: "unavailable"; reason: string };
+
+export interface JournalEntry {
+  id: number;
+  receivedAt: string;
+  path: string;
+  kind: "created" | "modified" | "renamed" | "deleted";
+  folderId: string;
+  disposition: EventDisposition | null;
+  detail: string | null;
+  activeApp: ActiveApp | null;
+}
+
+export interface JournalQuery {
+  pathContains?: string;
+  since?: string;
+  until?: string;
+  limit?: number;
+}
+
+export interface JournalPage {
+  entries: JournalEntry[];
+  /** Events not journaled since startup because the writer fell behind. */
+  dropped: number;
+}
+
 export interface WatchedFolder {
   folderId: string;
   path: string;
   scanDepth: number;
+  /** Events the folder's passthrough patterns dropped since launch. */
+  passthroughDrops: number;
 }
 
 export interface EngineStatusSnapshot {
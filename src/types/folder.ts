@@ -1,3 +1,10 @@
+import type { Preset } from "./preset";
+
+export type PlaceholderMode = "skip" | "processMetadataOnly" | "hydrate";
+
+/** `systemTrash` stands for the trash or recycle bin: no path, not watched, rules run in a periodic sweep. */
+export type FolderKind = "directory" | "systemTrash";
+
 export interface Folder {
   id: string;
   path: string;
@@ -12,4 +19,57 @@ export interface Folder {
   incompleteTimeoutMinutes: number;
   parentId?: string;
   isGroup: boolean;
+  placeholderMode: PlaceholderMode;
+  /** Process files one at a time, in the order they arrive. */
+  strictSequential: boolean;
+  /** Files arriving within this many milliseconds form one group; 0 is off. */
+  groupWindowMs: number;
+  /** Globs for files dropped before any rule, log or journal sees them. */
+  passthroughPatterns: string[];
+  /** A git working tree: `.git` is passed through and events wait while git is mid-operation. */
+  respectGit: boolean;
+  /** With `respectGit`, files git tracks are left alone. */
+  skipGitTracked: boolean;
+  /** Images and PDFs matched here get a thumbnail in the activity log. */
+  activityThumbnails: boolean;
+  kind: FolderKind;
+  /** For the system trash: items deleted longest ago are purged while it holds more than this; 0 is no limit. */
+  trashMaxMb: number;
+}
+
+export type StandardLocation = "downloads" | "desktop" | "documents" | "pictures" | "screenshots";
+
+/** A standard folder `quick_setup_suggestions` found that isn't watched yet. */
+export interface QuickSetupSuggestion {
+  location: StandardLocation;
+  path: string;
+  name: string;
+  fileCount: number;
+  totalBytes: number;
+  /** The scan stopped early; the counts are lower bounds. */
+  estimateCapped: boolean;
+  /** Template gallery ids offered for this folder. */
+  presetIds: string[];
+}
+
+export interface QuickSetupSelection {
+  path: string;
+  name: string;
+  presets: { preset: Preset; variables: Record<string, string> }[];
+}
+
+/** Payload of `folder://path-changed`: a watched folder was renamed or relinked. */
+export interface FolderPathChanged {
+  folderId: string;
+  oldPath: string;
+  newPath: string;
+  /** Rules whose destinations pointed into the old path and were updated. */
+  rulesUpdated: number;
+}
+
+/** A watched folder that's gone from disk, until it's found or relinked. */
+export interface UnavailableFolder {
+  folderId: string;
+  path: string;
+  since: string;
 }
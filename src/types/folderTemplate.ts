@@ -0,0 +1,29 @@
+import type { Folder, PresetVariable, Rule } from "./";
+
+export interface FolderTemplateInfo {
+  id: string;
+  name: string;
+  createdAt: string;
+  settings: Pick<
+    Folder,
+    | "scanDepth"
+    | "removeDuplicates"
+    | "trashIncompleteDownloads"
+    | "incompleteTimeoutMinutes"
+    | "placeholderMode"
+    | "strictSequential"
+    | "groupWindowMs"
+    | "passthroughPatterns"
+    | "respectGit"
+    | "skipGitTracked"
+    | "activityThumbnails"
+    | "trashMaxMb"
+  >;
+  variables: PresetVariable[];
+  ruleCount: number;
+  path: string;
+}
+
+export type FolderTemplateApplied =
+  | { status: "created"; folder: Folder; rules: Rule[] }
+  | { status: "needsVariables"; variables: PresetVariable[] };
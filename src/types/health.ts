@@ -0,0 +1,38 @@
+export type HealthSeverity = "info" | "warning" | "error";
+
+export type HealthIssueKind =
+  | "unreadableRule"
+  | "orphanedRule"
+  | "danglingReference"
+  | "invalidPattern"
+  | "missingFolderPath"
+  | "duplicatePosition"
+  | "missingValue"
+  | "corruption"
+  | "folderFlow"
+  | "literalPassword";
+
+export interface HealthIssue {
+  severity: HealthSeverity;
+  kind: HealthIssueKind;
+  ruleId: string | null;
+  folderId: string | null;
+  message: string;
+}
+
+export interface HealthReport {
+  issues: HealthIssue[];
+  checkedAt: string;
+}
+
+/** Each safe fix only runs when its flag is set. */
+export interface HealthRepairOptions {
+  disableUnreadableRules?: boolean;
+  renumberPositions?: boolean;
+  disableMissingFolders?: boolean;
+}
+
+export interface HealthRepairResult {
+  repaired: string[];
+  report: HealthReport;
+}
@@ -1,9 +1,22 @@
 export * from "./action";
+export * from "./analysis";
+export * from "./approval";
+export * from "./backup";
+export * from "./command";
 export * from "./condition";
+export * from "./conflict";
 export * from "./engine";
 export * from "./folder";
+export * from "./folderTemplate";
+export * from "./health";
+export * from "./insights";
 export * from "./log";
+export * from "./notification";
+export * from "./permissions";
 export * from "./preset";
 export * from "./preview";
+export * from "./profile";
+export * from "./retry";
 export * from "./rule";
+export * from "./sandbox";
 export * from "./undo";
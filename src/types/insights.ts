@@ -0,0 +1,38 @@
+export interface FolderInsight {
+  folderId: string;
+  folderName: string;
+  matchedFiles: number;
+  totalFiles: number;
+  matchPercent?: number | null;
+}
+
+export interface InsightsSummary {
+  filesSorted: number;
+  bytesMoved: number;
+  timeSavedSeconds: number;
+  streakDays: number;
+  folders: FolderInsight[];
+}
+
+export interface DigestActionTotal {
+  actionType: string;
+  count: number;
+  bytes: number;
+}
+
+/** Payload of `digest://ready`. */
+export interface DailyDigest {
+  day: string;
+  actions: DigestActionTotal[];
+  errors: number;
+  logId?: string | null;
+}
+
+/** Files and bytes organized under one classification code. */
+export interface ClassificationTotal {
+  code: string;
+  /** Current label of the code; null when it was removed from the settings. */
+  label?: string | null;
+  files: number;
+  bytes: number;
+}
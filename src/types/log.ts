@@ -1,6 +1,6 @@
 import type { ActionDetails } from "./action";
 
-export type LogStatus = "success" | "error" | "skipped";
+export type LogStatus = "success" | "error" | "skipped" | "warning";
 
 export interface LogEntry {
   id: string;
@@ -13,3 +13,56 @@ export interface LogEntry {
   errorMessage?: string;
   createdAt: string;
 }
+
+export type ManifestFormat = "csv" | "jsonl" | "m3u";
+
+export interface ManifestExport {
+  rows: number;
+  /** Files not found where the logs last saw them. */
+  unconfirmed: number;
+  /** The manifest, when it wasn't saved to a file. */
+  content: string | null;
+}
+
+/** A recorded match, as returned by `matches_query`. */
+export interface MatchRecord {
+  ruleId: string;
+  filePath: string;
+  fileHash: string | null;
+  matchedAt: string;
+  /** What the rule's `recordMetadata` resolved to. */
+  metadata: Record<string, string>;
+}
+
+export interface MatchQuery {
+  ruleId?: string;
+  since?: string;
+  until?: string;
+  /** Pairs every match must have recorded. */
+  metadata?: Record<string, string>;
+  limit?: number;
+}
+
+export interface StatusCounts {
+  success: number;
+  error: number;
+  skipped: number;
+  warning: number;
+}
+
+export interface RuleActivity {
+  ruleId?: string;
+  ruleName?: string;
+  counts: StatusCounts;
+}
+
+/** What the log gained since the activity view was last looked at. */
+export interface UnreviewedSummary {
+  since?: string;
+  counts: StatusCounts;
+  byRule: RuleActivity[];
+  rulesWithErrors: RuleActivity[];
+  recentErrors: LogEntry[];
+  /** Entries cleaned up by retention before anyone saw them. */
+  prunedBeforeReview: number;
+}
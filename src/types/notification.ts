@@ -0,0 +1,3 @@
+export type ErrorNotifyMode = "off" | "firstPerRule" | "all";
+
+export type ErrorNotificationAction = "retry" | "openLog" | "disableRule";
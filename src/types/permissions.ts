@@ -0,0 +1,19 @@
+export type ProtectedCategory =
+  | "desktop"
+  | "documents"
+  | "downloads"
+  | "removableVolume"
+  | "networkVolume";
+
+export type AccessState = "granted" | "denied" | "notApplicable";
+
+export interface PermissionStatus {
+  category: ProtectedCategory;
+  state: AccessState;
+}
+
+/** Payload of the `permission://needed` event. */
+export interface PermissionNeeded {
+  category: ProtectedCategory;
+  path: string;
+}
@@ -1,6 +1,74 @@
+import type { ActionType } from "./action";
+import type { FileKind } from "./condition";
+import type { Rule } from "./rule";
+
 export interface PreviewItem {
   filePath: string;
   matched: boolean;
+  /** The file is outside the rule's path scope; its conditions weren't checked. */
+  outOfScope?: boolean;
   conditionResults: boolean[];
   actions: string[];
+  notes?: string[];
+  /** Fields replaced for this preview (and `now` for the clock); not the file's own. */
+  overridden?: string[];
+  /** The saved revision of the rule the preview ran against; 0 for an unsaved draft. */
+  ruleRevision?: number;
+}
+
+/** What a rule would do to one file in its folder, from `rule_dry_run`. */
+export interface DryRunItem {
+  filePath: string;
+  matched: boolean;
+  /** One per action, and per companion that would come along. */
+  outcomes: DryRunOutcome[];
+  /** Why the file wasn't evaluated, when it wasn't. */
+  notes?: string[];
+}
+
+export type DryRunConflict = "rename" | "replace" | "uniqueSuffix" | "ask";
+
+export interface DryRunOutcome {
+  actionType: ActionType;
+  /** False when the action would be skipped; `note` says why. */
+  wouldRun: boolean;
+  sourcePath: string;
+  destinationPath: string | null;
+  /** How a taken destination would be resolved. */
+  conflict: DryRunConflict | null;
+  /** A deleted file, one archived and deleted, or the file a replace overwrites. */
+  removes: string | null;
+  note: string | null;
+}
+
+/** Parts of a file's info to pretend for `preview_file`; dates are RFC 3339. */
+export interface FileInfoOverrides {
+  created?: string;
+  modified?: string;
+  added?: string;
+  size?: number;
+  kind?: FileKind;
+  extension?: string;
+  lastMatched?: string;
+}
+
+/** Whether a suggested rule matches one of the files it was drafted from. */
+export interface ExampleCheck {
+  path: string;
+  matched: boolean;
+  captures: Record<string, string>;
+}
+
+/** A rule drafted from example files by `rule_suggest`. Nothing is saved. */
+export interface RuleSuggestion {
+  /** Without a folder, and with a Move whose destination is left blank. */
+  rule: Rule;
+  /** Why each condition of the rule is there, in order. */
+  explanations: string[];
+  examples: ExampleCheck[];
+  counterExamples: ExampleCheck[];
+  /** Conditions left out or narrowed to get the examples right. */
+  notes: string[];
+  /** What the draft gets wrong; empty when it's right about every file. */
+  unsatisfied: string[];
 }
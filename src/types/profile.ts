@@ -0,0 +1,12 @@
+import type { AppSettings } from "@/stores/settingsStore";
+
+export interface Profile {
+  id: string;
+  name: string;
+  settings: AppSettings;
+  folderStates: Record<string, boolean>;
+  ruleStates: Record<string, boolean>;
+  isActive: boolean;
+  createdAt: string;
+  updatedAt: string;
+}
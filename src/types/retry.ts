@@ -0,0 +1,22 @@
+export interface RetryEntry {
+  id: string;
+  ruleId: string;
+  ruleName: string;
+  folderId: string;
+  filePath: string;
+  attempts: number;
+  nextAttemptAt: string;
+  lastError: string;
+  /** The offline destination volume the file is waiting for, if any. */
+  volume: string | null;
+  createdAt: string;
+}
+
+/** Payload of `engine://retry-exhausted`. */
+export interface RetryExhausted {
+  ruleId: string;
+  ruleName: string;
+  filePath: string;
+  attempts: number;
+  lastError: string;
+}
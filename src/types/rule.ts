@@ -1,4 +1,4 @@
-import type { Action } from "./action";
+import type { Action, ConflictResolution } from "./action";
 import type { ConditionGroup } from "./condition";
 
 export interface Rule {
@@ -10,6 +10,118 @@ export interface Rule {
   conditions: ConditionGroup;
   actions: Action[];
   position: number;
+  schedule?: RuleSchedule | null;
+  /** Matches wait in the review queue instead of running. */
+  approvalRequired?: boolean;
+  /** Seconds a file must sit unchanged before this rule looks at it. */
+  minAgeSeconds?: number | null;
+  /** Subfolder or glob under the folder root the rule is limited to. */
+  pathScope?: string | null;
+  /** Leaves the rule out of the check for missing apps, scripts and volumes. */
+  skipReferenceCheck?: boolean;
+  /** Bytes the rule may move, copy or archive per calendar month. */
+  monthlyByteBudget?: number | null;
+  /** Retention classification code from the settings' allow-list. */
+  classification?: string | null;
+  /** What to do with a file this rule moved away that comes back; `null` follows the setting. */
+  onReappear?: OnReappear | null;
+  /** Key/value patterns resolved when the rule matches a file and recorded with the match. */
+  recordMetadata?: Record<string, string>;
+  /** Bumped each time the rule is saved; runs and previews record the one they used. */
+  revision?: number;
+  /** What the latest reference check found missing; set by `rule_list`. */
+  referenceWarnings?: string[];
+  /** Fields from a newer rule format, kept so they survive a save. */
+  extra?: unknown;
   createdAt: string;
   updatedAt: string;
 }
+
+/** What happens to a file that reappears with the same hash after a rule moved it away. */
+export type OnReappear =
+  | { type: "processAgain" }
+  | { type: "skipSilently" }
+  | { type: "deleteIncoming" }
+  | { type: "moveToReview"; destination: string };
+
+/** Bytes one kind of action transferred for a rule this month. */
+export interface ByteUsage {
+  actionType: string;
+  bytes: number;
+  count: number;
+}
+
+/** What `rule_budget_status` reports. `month` is `YYYY-MM` in the default time zone. */
+export interface BudgetStatus {
+  ruleId: string;
+  month: string;
+  budgetBytes: number | null;
+  usedBytes: number;
+  usage: ByteUsage[];
+}
+
+/** Payload of `rule://budget-exhausted`, sent once per rule and month. */
+export interface BudgetExhausted {
+  ruleId: string;
+  ruleName: string;
+  month: string;
+  budgetBytes: number;
+  usedBytes: number;
+}
+
+/** An enabled rule whose actions point at something that isn't there. */
+export interface RuleReferenceWarnings {
+  ruleId: string;
+  ruleName: string;
+  folderId: string;
+  warnings: string[];
+}
+
+export type RuleImportErrorKind =
+  | "invalid"
+  | "unsupportedVersion"
+  | "passwordRequired"
+  | "wrongPassword"
+  | "failed";
+
+/** Why `rule_import` rejected a file. `path` points into the file. */
+export interface RuleImportError {
+  kind: RuleImportErrorKind;
+  message: string;
+  path?: string | null;
+}
+
+export type RuleSchedule =
+  | { type: "interval"; minutes: number }
+  | { type: "daily"; time: string };
+
+/** A change `rules_bulk_edit` applies to every selected rule. */
+export type BulkRuleEdit =
+  | { type: "enable" }
+  | { type: "disable" }
+  | { type: "setStopProcessing"; value: boolean }
+  | { type: "replaceInDestinations"; find: string; replace: string; regex?: boolean }
+  | { type: "setConflictResolution"; policy: ConflictResolution };
+
+export interface RuleFieldChange {
+  /** Path of the field in the rule, e.g. `actions[1].destination`. */
+  field: string;
+  before: string;
+  after: string;
+}
+
+export interface RuleEditSummary {
+  ruleId: string;
+  ruleName: string;
+  changes: RuleFieldChange[];
+  warnings: string[];
+}
+
+/** A saved copy of a rule as it was at one revision; kept when the setting asks for it. */
+export interface RuleRevision {
+  ruleId: string;
+  revision: number;
+  contentHash: string;
+  rule: Rule;
+  createdAt: string;
+}
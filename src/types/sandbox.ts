@@ -0,0 +1,38 @@
+import type { LogEntry, LogStatus } from "./log";
+
+/** Files to run a folder's rules against in a sandbox. */
+export interface SandboxScenario {
+  name: string;
+  files: ScenarioFile[];
+}
+
+export interface ScenarioFile {
+  /** Relative to the folder, where it arrives; `~/…` or absolute is already there. */
+  path: string;
+  size?: number;
+  content?: string;
+  /** RFC 3339. */
+  modified?: string;
+  /** RFC 3339; only applied on macOS and Windows. */
+  created?: string;
+}
+
+/** Paths are relative to the sandbox: `folder/…`, `home/…` or `root/…`. */
+export interface SandboxResult {
+  tree: SandboxEntry[];
+  logs: LogEntry[];
+  files: SandboxFileOutcome[];
+  errors: string[];
+}
+
+export interface SandboxEntry {
+  path: string;
+  size: number;
+}
+
+export interface SandboxFileOutcome {
+  path: string;
+  finalPath: string | null;
+  rules: string[];
+  status: LogStatus | null;
+}
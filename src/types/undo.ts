@@ -1,3 +1,5 @@
+export type UndoStatus = "available" | "expired" | "changed" | "missing";
+
 export interface UndoEntry {
   id: string;
   logId: string;
@@ -5,4 +7,26 @@ export interface UndoEntry {
   originalPath: string;
   currentPath: string;
   createdAt: string;
+  hash?: string | null;
+  /** How `hash` was taken when it isn't a SHA-256 of the whole file. */
+  hashScheme?: string | null;
+  /** The rule's classification when the action ran. */
+  classification?: string | null;
+  status: UndoStatus;
+  statusReason?: string;
+}
+
+export interface UndoStats {
+  entries: number;
+  oldest: string | null;
+  reclaimable: number;
+  maxEntries: number;
+  retentionDays: number;
+}
+
+export type UndoErrorKind = "notFound" | "expired" | "fileChanged" | "missing" | "originalExists" | "failed";
+
+export interface UndoError {
+  kind: UndoErrorKind;
+  message: string;
 }